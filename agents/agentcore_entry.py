@@ -11,8 +11,10 @@ from typing import Any
 from src.router import create_router_agent
 from src.ingestion import create_ingestion_agent
 from src.query import create_query_agent
+from src.shared import internal_auth
 from src.shared.database import reset_knowledge_base, run_async, execute_query
 from src.shared.tools.database import fact_update, fact_delete, fact_search
+from src.shared.usage import combine_usage
 
 
 async def _lookup_family_ids(user_id: str) -> list[str]:
@@ -101,8 +103,10 @@ def handle_request(event: dict[str, Any]) -> dict[str, Any]:
                 "family_ids": list[str],  # User's family memberships
                 "device_id": str,         # Device making the request
                 "conversation_id": str,   # Conversation context ID
+                "conversation_history": list[dict],  # Earlier turns (optional)
                 "intent": str,            # Pre-classified intent (optional)
-                "source": str,            # Source platform (discord, alexa, api)
+                "source": str,            # Source platform (discord, alexa, api, document, feed)
+                "metadata": dict,         # Where the message came from (optional)
                 "action": str,            # Special action (optional: reset_knowledge)
             }
 
@@ -144,8 +148,20 @@ def handle_request(event: dict[str, Any]) -> dict[str, Any]:
     user_id = event.get("user_id", "")
     family_ids = event.get("family_ids", [])
     conversation_id = event.get("conversation_id")
+    # Earlier messages in the conversation, oldest first ({"role", "content"})
+    conversation_history = event.get("conversation_history") or []
     intent = event.get("intent")
     source = event.get("source", "api")
+    # Transcribed audio (e.g. Discord /transcribe) is stored as a voice fact
+    is_voice = source == "alexa" or event.get("modality") == "voice"
+    # Text extracted from uploaded documents, captured web pages and feed
+    # items is stored as an imported fact
+    if is_voice:
+        source_type = "voice"
+    elif source in ("document", "capture", "feed"):
+        source_type = "import"
+    else:
+        source_type = "text"
 
     # If family_ids not provided, look them up from database
     if not family_ids and user_id:
@@ -163,13 +179,16 @@ def handle_request(event: dict[str, Any]) -> dict[str, Any]:
             "message": "No user_id provided",
         }
 
+    # Routing costs tokens too, so its usage is added to the answering agent's
+    routing_result = None
+
     # If intent is pre-classified, route directly
     if intent == "ingest":
         agent = get_ingestion_agent()
         result = agent.process(
             message=message,
             user_id=user_id,
-            source_type="voice" if source == "alexa" else "text",
+            source_type=source_type,
         )
     elif intent == "query":
         agent = get_query_agent()
@@ -177,6 +196,7 @@ def handle_request(event: dict[str, Any]) -> dict[str, Any]:
             query=message,
             user_id=user_id,
             family_ids=family_ids,
+            conversation_history=conversation_history,
         )
     else:
         # Use Router Agent to classify and route
@@ -185,6 +205,7 @@ def handle_request(event: dict[str, Any]) -> dict[str, Any]:
             message=message,
             user_id=user_id,
             family_ids=family_ids,
+            conversation_history=conversation_history,
         )
 
         # Parse the routing decision and call appropriate agent
@@ -196,7 +217,7 @@ def handle_request(event: dict[str, Any]) -> dict[str, Any]:
             result = agent.process(
                 message=message,
                 user_id=user_id,
-                source_type="text" if source != "alexa" else "voice",
+                source_type=source_type,
             )
         elif "query" in response_text or "search" in response_text or "find" in response_text:
             # Route to query agent
@@ -205,6 +226,7 @@ def handle_request(event: dict[str, Any]) -> dict[str, Any]:
                 query=message,
                 user_id=user_id,
                 family_ids=family_ids,
+                conversation_history=conversation_history,
             )
         else:
             # Default to query agent for unknown intents
@@ -213,17 +235,33 @@ def handle_request(event: dict[str, Any]) -> dict[str, Any]:
                 query=message,
                 user_id=user_id,
                 family_ids=family_ids,
+                conversation_history=conversation_history,
             )
 
+    metadata = {
+        "source": source,
+        "model_id": DEFAULT_MODEL_ID,
+    }
+    # Facts stored by the ingestion pipeline, so callers can follow up on them
+    if result.get("fact_ids"):
+        metadata["fact_ids"] = result["fact_ids"]
+    # Facts retrieved while answering, stored with the query as its sources
+    if result.get("source_fact_ids"):
+        metadata["source_fact_ids"] = result["source_fact_ids"]
+    # Token usage, so callers can track what each query costs
+    metadata.update(
+        combine_usage(
+            routing_result.get("usage") if routing_result else None,
+            result.get("usage"),
+        )
+    )
+
     return {
         "status": "success",
         "response": result.get("response", ""),
         "user_id": user_id,
         "conversation_id": conversation_id,
-        "metadata": {
-            "source": source,
-            "model_id": DEFAULT_MODEL_ID,
-        },
+        "metadata": metadata,
     }
 
 
@@ -233,7 +271,8 @@ def lambda_handler(event: dict[str, Any], context: Any) -> dict[str, Any]:
 
     This can be used for direct Lambda invocation outside of AgentCore.
     It detects whether the call is from API Gateway (wraps response)
-    or direct Lambda invocation (returns raw response).
+    or direct Lambda invocation (returns raw response). Either way the
+    payload must be signed by the calling Lambda (see internal_auth).
 
     Args:
         event: Lambda event payload.
@@ -255,6 +294,9 @@ def lambda_handler(event: dict[str, Any], context: Any) -> dict[str, Any]:
         else:
             body = event
 
+        # Only act as the payload's user if another of our Lambdas sent it
+        internal_auth.verify(body, "POST", internal_auth.AGENT_INVOKE_PATH)
+
         result = handle_request(body)
 
         # If called from API Gateway, wrap in API Gateway format
@@ -377,13 +377,14 @@ class IngestionAgent:
             result = self.graph_pipeline.process(
                 message=message,
                 user_id=user_id,
-                source=source_type if source_type != "voice" else "text",
+                source=source_type,
             )
             return {
                 "response": result.get("response", "Done."),
                 "user_id": user_id,
                 "original_message": message,
                 "mode": "graph",
+                "fact_ids": result.get("fact_ids", []),
                 "execution_time_ms": result.get("execution_time_ms"),
             }
 
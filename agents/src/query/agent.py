@@ -16,6 +16,8 @@ from ..shared.tools import (
     proximity_search,
     semantic_search,
 )
+from ..shared.sources import reset_sources, retrieved_sources
+from ..shared.usage import token_usage
 from .prompts import QUERY_SYSTEM_PROMPT
 
 
@@ -309,12 +311,15 @@ DO NOT skip the search step - always search first before responding.
 Always respect the user's access permissions - only show information they have access to.
 """
 
+        reset_sources()
         response = self.agent(prompt)
 
         return {
             "response": str(response),
             "user_id": user_id,
             "original_query": query,
+            "usage": token_usage(response),
+            "source_fact_ids": retrieved_sources(),
         }
 
 
@@ -5,6 +5,7 @@ from uuid import UUID
 
 from strands import Agent, tool
 
+from ..shared.usage import token_usage
 from .prompts import ROUTER_SYSTEM_PROMPT
 
 # Model IDs for cost optimization
@@ -250,6 +251,7 @@ Analyze this message and route it to the appropriate agent using the available t
             "response": str(response),
             "user_id": user_id,
             "original_message": message,
+            "usage": token_usage(response),
         }
 
 
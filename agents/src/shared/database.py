@@ -1,6 +1,7 @@
 """Database connection management for Second Brain agents."""
 
 import asyncio
+import uuid
 from contextlib import asynccontextmanager
 from typing import Any, AsyncGenerator, Coroutine, TypeVar
 
@@ -80,13 +81,22 @@ async def execute_command(
         return await conn.execute(query, *args)
 
 
+def _is_uuid(value: str) -> bool:
+    try:
+        uuid.UUID(value)
+    except ValueError:
+        return False
+    return True
+
+
 async def resolve_user_id(external_id: str) -> tuple[str | None, str | None]:
     """Resolve a user's database ID from various external identifiers.
 
     Tries to find the user by:
-    1. cognito_sub
-    2. discord_id
-    3. alexa_user_id
+    1. users.id (callers that already resolved the user, e.g. linked Discord accounts)
+    2. cognito_sub
+    3. discord_id
+    4. alexa_user_id
 
     Args:
         external_id: The external identifier (Cognito sub, Discord ID, etc.)
@@ -94,7 +104,16 @@ async def resolve_user_id(external_id: str) -> tuple[str | None, str | None]:
     Returns:
         Tuple of (database_user_id, cognito_sub) or (None, None) if not found.
     """
-    # Try cognito_sub first
+    # Try database ID
+    if _is_uuid(external_id):
+        user = await execute_one(
+            "SELECT id, cognito_sub FROM users WHERE id = $1::uuid",
+            external_id,
+        )
+        if user:
+            return str(user["id"]), user["cognito_sub"]
+
+    # Try cognito_sub
     user = await execute_one(
         "SELECT id, cognito_sub FROM users WHERE cognito_sub = $1::varchar",
         external_id,
@@ -125,12 +144,12 @@ async def get_or_create_user(external_id: str, source: str = "api") -> tuple[str
     """Get or create a user by external identifier.
 
     First tries to resolve the user by various external IDs.
-    If not found and source is 'discord' or 'alexa', returns an error.
+    If not found and source is 'discord', 'alexa', 'sms' or 'email', returns an error.
     Otherwise, creates a new user with the external_id as cognito_sub.
 
     Args:
         external_id: The external identifier.
-        source: The source of the request ('api', 'discord', 'alexa').
+        source: The source of the request ('api', 'discord', 'alexa', 'sms', 'email').
 
     Returns:
         Tuple of (database_user_id, cognito_sub).
@@ -143,8 +162,8 @@ async def get_or_create_user(external_id: str, source: str = "api") -> tuple[str
     if db_id:
         return db_id, cognito_sub
 
-    # For Discord/Alexa, require pre-linked accounts
-    if source in ("discord", "alexa"):
+    # For Discord/Alexa/SMS/email, require pre-linked accounts
+    if source in ("discord", "alexa", "sms", "email"):
         raise ValueError(
             f"No account linked for {source} user {external_id}. "
             "Please link your account first."
@@ -0,0 +1,139 @@
+"""Verification of signed invocations from the Rust Lambdas.
+
+Callers add an ``internal_auth`` field to their payload (see
+``shared::auth::sign_internal`` in the Lambdas): the calling function, the
+Unix time it signed at and a hex HMAC-SHA256 over
+``"{caller}\\n{issued_at}\\n{method}\\n{path}\\n{body_hash}\\n{user_id}\\n{family_ids joined by ','}"``,
+where ``body_hash`` is the SHA-256 of the rest of the payload as compact JSON
+with sorted keys. The key is ``current`` in the secret named by
+INTERNAL_AUTH_SECRET_ARN, or ``previous`` for payloads signed just before a
+rotation. Nothing is accepted when the secret isn't configured.
+"""
+
+import hashlib
+import hmac
+import json
+import os
+import time
+from typing import Any
+
+import boto3
+
+# Operation the Lambdas sign agent invocations for (AGENT_INVOKE_PATH)
+AGENT_INVOKE_PATH = "/agents/invoke"
+
+# Oldest signature accepted (matches INTERNAL_AUTH_MAX_AGE_SECS)
+MAX_AGE_SECONDS = 900
+
+# How long keys are cached in a warm container
+CACHE_TTL_SECONDS = 300
+
+# Youngest cached keys reloaded for a signature that doesn't match them, so
+# bad signatures can't make every invocation call Secrets Manager
+MIN_REFRESH_SECONDS = 60
+
+_cached_keys: list[str] = []
+_cached_at = 0.0
+
+
+class InternalAuthError(Exception):
+    """The payload's signature is missing or invalid."""
+
+
+def signing_input(
+    caller: str,
+    issued_at: int,
+    method: str,
+    path: str,
+    body_hash: str,
+    user_id: str,
+    family_ids: list[str],
+) -> str:
+    """What a signature covers, one field per line."""
+    return "\n".join(
+        [caller, str(issued_at), method, path, body_hash, user_id, ",".join(family_ids)]
+    )
+
+
+def body_hash(event: dict[str, Any]) -> str:
+    """Hex SHA-256 of the payload without its signature, as compact JSON
+    with sorted keys (matches ``shared::auth::internal_body_hash``)."""
+    body = {k: v for k, v in event.items() if k != "internal_auth"}
+    canonical = json.dumps(
+        body, sort_keys=True, separators=(",", ":"), ensure_ascii=False
+    )
+    return hashlib.sha256(canonical.encode()).hexdigest()
+
+
+def _load_keys(secret_arn: str, refresh: bool = False) -> list[str]:
+    """Current and previous keys, cached for CACHE_TTL_SECONDS."""
+    global _cached_keys, _cached_at
+    if not refresh and _cached_keys and time.time() - _cached_at < CACHE_TTL_SECONDS:
+        return _cached_keys
+
+    client = boto3.client("secretsmanager")
+    secret = json.loads(client.get_secret_value(SecretId=secret_arn)["SecretString"])
+    _cached_keys = [k for k in (secret.get("current"), secret.get("previous")) if k]
+    _cached_at = time.time()
+    return _cached_keys
+
+
+def _matches(keys: list[str], message: str, signature: str) -> bool:
+    return any(
+        hmac.compare_digest(
+            hmac.new(key.encode(), message.encode(), hashlib.sha256).hexdigest(),
+            signature,
+        )
+        for key in keys
+    )
+
+
+def verify(event: dict[str, Any], method: str, path: str) -> None:
+    """Check an invocation's signature before performing an operation as its
+    user_id.
+
+    Args:
+        event: The invocation payload.
+        method: Operation the caller must have signed for, e.g. ``POST``.
+        path: Its path, e.g. ``/agents/invoke``.
+
+    Raises:
+        InternalAuthError: If INTERNAL_AUTH_SECRET_ARN is unset, or the
+            signature is missing, stale or doesn't match the operation, the
+            payload or its user_id and family_ids.
+    """
+    secret_arn = os.environ.get("INTERNAL_AUTH_SECRET_ARN")
+    if not secret_arn:
+        raise InternalAuthError("Internal auth is not configured")
+
+    auth = event.get("internal_auth")
+    if not isinstance(auth, dict):
+        raise InternalAuthError("Missing internal signature")
+
+    try:
+        caller = str(auth["caller"])
+        issued_at = int(auth["issued_at"])
+        signature = str(auth["signature"])
+    except (KeyError, TypeError, ValueError):
+        raise InternalAuthError("Malformed internal signature")
+
+    if abs(time.time() - issued_at) > MAX_AGE_SECONDS:
+        raise InternalAuthError("Internal signature has expired")
+
+    message = signing_input(
+        caller,
+        issued_at,
+        method,
+        path,
+        body_hash(event),
+        str(event.get("user_id", "")),
+        [str(f) for f in event.get("family_ids") or []],
+    )
+    if _matches(_load_keys(secret_arn), message, signature):
+        return
+    # The key may have been rotated since it was cached
+    if time.time() - _cached_at >= MIN_REFRESH_SECONDS and _matches(
+        _load_keys(secret_arn, refresh=True), message, signature
+    ):
+        return
+    raise InternalAuthError("Invalid internal signature")
@@ -0,0 +1,30 @@
+"""Facts retrieved while answering a query, reported as the answer's sources.
+
+Search tools note the facts they return; the query agent clears the list
+before each question and reports it afterwards as ``source_fact_ids``, which
+the API stores with the query so a past answer can be shown with citations.
+A Lambda handles one invocation at a time, so a module-level list is enough.
+"""
+
+from typing import Any
+
+_retrieved: list[str] = []
+
+
+def reset_sources() -> None:
+    """Forget the facts noted for the previous question."""
+    _retrieved.clear()
+
+
+def note_sources(facts: list[dict[str, Any]]) -> None:
+    """Note facts a search returned (dicts with an ``id``), keeping the order
+    they were first seen in."""
+    for fact in facts:
+        fact_id = fact.get("id")
+        if fact_id and fact_id not in _retrieved:
+            _retrieved.append(fact_id)
+
+
+def retrieved_sources() -> list[str]:
+    """IDs of the facts noted since the last reset."""
+    return list(_retrieved)
@@ -8,6 +8,7 @@ from strands import tool
 
 from ..database import execute_command, execute_one, execute_query, get_or_create_user, resolve_user_id, run_async
 from ..models import Fact, FactCreate
+from ..sources import note_sources
 
 
 @tool
@@ -200,10 +201,12 @@ def fact_search(
                     -- Facts from related users (with permission check)
                     OR (f.owner_type = 'user' AND uac.access_tier IS NOT NULL AND uac.access_tier <= f.visibility_tier)
                     -- Family-owned facts (if user is in that family)
-                    OR (f.owner_type = 'family' AND f.owner_id = ANY($2::uuid[]))
+                    OR (f.owner_type = 'family' AND f.owner_id = ANY($2::uuid[])
+                        AND (f.space_id IS NULL OR EXISTS (SELECT 1 FROM family_space_members sm WHERE sm.space_id = f.space_id AND sm.user_id = $1)))
                     -- Facts from family members with visibility_tier >= 2 (close family or above)
                     OR (f.owner_type = 'user' AND f.owner_id IN (SELECT user_id FROM same_family_users) AND f.visibility_tier >= 2)
                 )
+                AND f.deleted_at IS NULL
             """
             params.append(db_user_id)
             params.append(family_uuid_list)
@@ -287,6 +290,7 @@ def fact_search(
                 }
                 for row in results
             ]
+            note_sources(facts)
 
             return {
                 "status": "success",
@@ -512,7 +516,8 @@ def fact_delete(
     """Delete a fact from the knowledge base.
 
     Use this tool to remove incorrect or unwanted facts.
-    Only the fact owner can delete it.
+    Only the fact owner can delete it. The fact goes to the trash, where the
+    user can restore it for 30 days before it is purged.
 
     Args:
         fact_id: UUID of the fact to delete.
@@ -533,6 +538,7 @@ def fact_delete(
                 """
                 SELECT id, content FROM facts
                 WHERE id = $1 AND owner_type = 'user' AND owner_id = $2
+                AND deleted_at IS NULL
                 """,
                 UUID(fact_id),
                 UUID(db_user_id),
@@ -544,25 +550,16 @@ def fact_delete(
                     "message": "Fact not found or you don't have permission to delete it",
                 }
 
-            # Delete related records first (foreign keys)
+            # Move to the trash; tags and mentions are kept so a restore is complete
             await execute_command(
-                "DELETE FROM fact_tags WHERE fact_id = $1",
-                UUID(fact_id),
-            )
-            await execute_command(
-                "DELETE FROM entity_mentions WHERE fact_id = $1",
-                UUID(fact_id),
-            )
-
-            # Delete the fact
-            await execute_command(
-                "DELETE FROM facts WHERE id = $1",
+                "UPDATE facts SET deleted_at = NOW(), deleted_by = $2 WHERE id = $1",
                 UUID(fact_id),
+                UUID(db_user_id),
             )
 
             return {
                 "status": "success",
-                "message": f"Deleted fact: {fact['content'][:50]}...",
+                "message": f"Moved fact to trash: {fact['content'][:50]}...",
                 "deleted_fact_id": fact_id,
             }
         except Exception as e:
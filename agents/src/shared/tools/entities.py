@@ -55,11 +55,13 @@ def entity_search(
                        e.metadata, e.created_at,
                        COUNT(f.id) as fact_count
                 FROM entities e
-                LEFT JOIN facts f ON f.about_entity_id = e.id
+                LEFT JOIN facts f ON f.about_entity_id = e.id AND f.deleted_at IS NULL
                 WHERE (
                     (e.owner_type = 'user' AND e.owner_id = $1)
-                    OR e.owner_type = 'family'
+                    OR (e.owner_type = 'family'
+                        AND (e.space_id IS NULL OR EXISTS (SELECT 1 FROM family_space_members sm WHERE sm.space_id = e.space_id AND sm.user_id = $1)))
                 )
+                AND e.deleted_at IS NULL
             """
 
             if query:
@@ -277,7 +279,8 @@ def entity_get_details(
                 WHERE e.id = $1
                 AND (
                     (e.owner_type = 'user' AND e.owner_id = $2)
-                    OR (e.owner_type = 'family' AND e.owner_id = ANY($3::uuid[]))
+                    OR (e.owner_type = 'family' AND e.owner_id = ANY($3::uuid[])
+                        AND (e.space_id IS NULL OR EXISTS (SELECT 1 FROM family_space_members sm WHERE sm.space_id = e.space_id AND sm.user_id = $2)))
                 )
                 """,
                 UUID(entity_id),
@@ -335,6 +338,7 @@ def entity_get_details(
                 SELECT f.id, f.content, f.importance, f.recorded_at, f.valid_from, f.valid_to
                 FROM facts f
                 WHERE f.about_entity_id = $1
+                AND f.deleted_at IS NULL
                 AND (f.valid_to IS NULL OR f.valid_to > CURRENT_DATE)
                 ORDER BY f.importance DESC, f.recorded_at DESC
                 LIMIT 10
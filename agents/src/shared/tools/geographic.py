@@ -56,8 +56,11 @@ def geocode_address(address: str) -> dict[str, Any]:
             "latitude": geometry[1],
             "longitude": geometry[0],
             "confidence": result.get("Relevance", 1.0),
+            # Only geocoders that identify places (HERE, Grab) return one
+            "place_id": result.get("PlaceId"),
             "address_components": {
                 "label": place.get("Label"),
+                "address_number": place.get("AddressNumber"),
                 "street": place.get("Street"),
                 "municipality": place.get("Municipality"),
                 "region": place.get("Region"),
@@ -133,7 +136,8 @@ def proximity_search(
             AND (el.valid_to IS NULL OR el.valid_to > CURRENT_DATE)
             AND (
                 (e.owner_type = 'user' AND e.owner_id = $4)
-                OR e.owner_type = 'family'
+                OR (e.owner_type = 'family'
+                    AND (e.space_id IS NULL OR EXISTS (SELECT 1 FROM family_space_members sm WHERE sm.space_id = e.space_id AND sm.user_id = $4)))
             )
             {type_filter}
             ORDER BY distance_meters ASC
@@ -192,8 +196,10 @@ def store_entity_location(
     """Store a location for an entity.
 
     Use this tool to add or update a location (home, work, school, etc.)
-    for a person, organization, or place. Will geocode the address if
-    coordinates are not provided.
+    for a person, organization, or place. The address is geocoded to
+    normalize it, and for coordinates if they are not provided. If the
+    entity already has the same address under another label, that location
+    is kept instead of storing the address twice.
 
     Args:
         entity_id: UUID of the entity.
@@ -211,20 +217,28 @@ def store_entity_location(
         lon = longitude
         geocode_source = "manual"
         geocode_confidence = 1.0
+        normalized = None
+        place_id = None
 
-        # Geocode if coordinates not provided
-        if (lat is None or lon is None) and geocode_if_missing:
+        # Geocode to normalize the address, and for coordinates if not provided
+        if (lat is None or lon is None) and not geocode_if_missing:
+            geocode_result = {"status": "skipped"}
+        else:
             geocode_result = geocode_address(address)
-            if geocode_result["status"] == "success":
+
+        if geocode_result["status"] == "success":
+            normalized = geocode_result["address_components"]
+            place_id = geocode_result.get("place_id")
+            if lat is None or lon is None:
                 lat = geocode_result["latitude"]
                 lon = geocode_result["longitude"]
                 geocode_source = "aws_location"
                 geocode_confidence = geocode_result.get("confidence", 1.0)
-            else:
-                return {
-                    "status": "error",
-                    "message": f"Could not geocode address: {geocode_result.get('message')}",
-                }
+        elif (lat is None or lon is None) and geocode_if_missing:
+            return {
+                "status": "error",
+                "message": f"Could not geocode address: {geocode_result.get('message')}",
+            }
 
         if lat is None or lon is None:
             return {
@@ -232,27 +246,65 @@ def store_entity_location(
                 "message": "Coordinates required but geocoding was disabled",
             }
 
-        # Upsert the location
+        # The same place under another label (same place_id, or within 25m;
+        # see shared::geo::find_duplicate_location in the Rust lambdas)
+        place_address = (normalized or {}).get("label") or address
+        duplicate = await execute_one(
+            """
+            SELECT id, label
+            FROM entity_locations
+            WHERE entity_id = $1
+              AND valid_to IS NULL
+              AND label <> $2
+              AND (place_id = COALESCE($3, 'addr:' || normalize_address($4))
+                   OR ST_DWithin(location, ST_SetSRID(ST_MakePoint($5, $6), 4326)::geography, 25))
+            ORDER BY updated_at DESC
+            LIMIT 1
+            """,
+            UUID(entity_id),
+            label,
+            place_id,
+            place_address,
+            lon,
+            lat,
+        )
+        if duplicate:
+            return {
+                "status": "success",
+                "entity_id": entity_id,
+                "label": duplicate["label"],
+                "address": address,
+                "deduplicated": True,
+                "message": f"The entity already has this address as '{duplicate['label']}'",
+            }
+
+        # Upsert the location (place_id is derived by a trigger when not given)
         await execute_command(
             """
             INSERT INTO entity_locations (
-                entity_id, label, address_raw, location,
-                geocode_source, geocode_confidence
+                entity_id, label, address_raw, address_normalized, place_id,
+                location, geocode_source, geocode_confidence, geocoded_at
             )
             VALUES (
-                $1, $2, $3,
-                ST_SetSRID(ST_MakePoint($4, $5), 4326)::geography,
-                $6, $7
+                $1, $2, $3, $4::jsonb, $5,
+                ST_SetSRID(ST_MakePoint($6, $7), 4326)::geography,
+                $8, $9, NOW()
             )
-            ON CONFLICT (entity_id, label) DO UPDATE SET
+            ON CONFLICT (entity_id, label) WHERE valid_to IS NULL DO UPDATE SET
                 address_raw = EXCLUDED.address_raw,
+                address_normalized = EXCLUDED.address_normalized,
+                place_id = EXCLUDED.place_id,
                 location = EXCLUDED.location,
                 geocode_source = EXCLUDED.geocode_source,
-                geocode_confidence = EXCLUDED.geocode_confidence
+                geocode_confidence = EXCLUDED.geocode_confidence,
+                geocoded_at = EXCLUDED.geocoded_at,
+                updated_at = NOW()
             """,
             UUID(entity_id),
             label,
             address,
+            json.dumps(normalized) if normalized else None,
+            place_id,
             lon,
             lat,
             geocode_source,
@@ -267,6 +319,7 @@ def store_entity_location(
             "latitude": lat,
             "longitude": lon,
             "geocode_source": geocode_source,
+            "address_normalized": normalized,
         }
 
     return run_async(_store())
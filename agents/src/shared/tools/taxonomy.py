@@ -45,7 +45,8 @@ def tag_cooccurrence_analysis(
                 WHERE (
                     (f.owner_type = 'user' AND f.owner_id = $1)
                     OR (f.owner_type = 'user' AND uac.access_tier IS NOT NULL)
-                    OR (f.owner_type = 'family' AND f.owner_id = ANY($2::uuid[]))
+                    OR (f.owner_type = 'family' AND f.owner_id = ANY($2::uuid[])
+                        AND (f.space_id IS NULL OR EXISTS (SELECT 1 FROM family_space_members sm WHERE sm.space_id = f.space_id AND sm.user_id = $1)))
                 )
             ),
             tag_pairs AS (
@@ -143,7 +144,8 @@ def untagged_facts_analysis(
             WHERE (
                 (f.owner_type = 'user' AND f.owner_id = $1)
                 OR (f.owner_type = 'user' AND uac.access_tier IS NOT NULL)
-                OR (f.owner_type = 'family' AND f.owner_id = ANY($2::uuid[]))
+                OR (f.owner_type = 'family' AND f.owner_id = ANY($2::uuid[])
+                    AND (f.space_id IS NULL OR EXISTS (SELECT 1 FROM family_space_members sm WHERE sm.space_id = f.space_id AND sm.user_id = $1)))
             )
             AND NOT EXISTS (
                 SELECT 1 FROM fact_tags ft WHERE ft.fact_id = f.id
@@ -169,7 +171,8 @@ def untagged_facts_analysis(
                 WHERE (
                     (f.owner_type = 'user' AND f.owner_id = $1)
                     OR (f.owner_type = 'user' AND uac.access_tier IS NOT NULL)
-                    OR (f.owner_type = 'family' AND f.owner_id = ANY($2::uuid[]))
+                    OR (f.owner_type = 'family' AND f.owner_id = ANY($2::uuid[])
+                        AND (f.space_id IS NULL OR EXISTS (SELECT 1 FROM family_space_members sm WHERE sm.space_id = f.space_id AND sm.user_id = $1)))
                 )
             )
             SELECT
@@ -245,7 +248,8 @@ def tag_hierarchy_analysis(
                 WHERE (
                     (f.owner_type = 'user' AND f.owner_id = $1)
                     OR (f.owner_type = 'user' AND uac.access_tier IS NOT NULL)
-                    OR (f.owner_type = 'family' AND f.owner_id = ANY($2::uuid[]))
+                    OR (f.owner_type = 'family' AND f.owner_id = ANY($2::uuid[])
+                        AND (f.space_id IS NULL OR EXISTS (SELECT 1 FROM family_space_members sm WHERE sm.space_id = f.space_id AND sm.user_id = $1)))
                 )
             ),
             tag_stats AS (
@@ -9,6 +9,7 @@ from strands import tool
 
 from ..config import get_settings
 from ..database import execute_one, execute_query, resolve_user_id, run_async
+from ..sources import note_sources
 
 
 def _get_bedrock_client():
@@ -142,11 +143,13 @@ def semantic_search(
                     -- Facts from related users via user_access_cache (with permission check)
                     OR (f.owner_type = 'user' AND uac.access_tier IS NOT NULL AND uac.access_tier <= f.visibility_tier)
                     -- Family-owned facts (if user is in that family)
-                    OR (f.owner_type = 'family' AND f.owner_id = ANY($3::uuid[]))
+                    OR (f.owner_type = 'family' AND f.owner_id = ANY($3::uuid[])
+                        AND (f.space_id IS NULL OR EXISTS (SELECT 1 FROM family_space_members sm WHERE sm.space_id = f.space_id AND sm.user_id = $2)))
                     -- Facts from family members with visibility_tier >= 2 (close family or above)
                     OR (f.owner_type = 'user' AND f.owner_id IN (SELECT user_id FROM same_family_users) AND f.visibility_tier >= 2)
                 )
                 AND (f.valid_to IS NULL OR f.valid_to > CURRENT_DATE)
+                AND f.deleted_at IS NULL
                 AND 1 - (fe.embedding <=> qe.vec) >= $4
                 ORDER BY similarity DESC
                 LIMIT $5
@@ -177,6 +180,7 @@ def semantic_search(
                 }
                 for row in results
             ]
+            note_sources(facts)
 
             return {
                 "status": "success",
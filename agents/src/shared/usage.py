@@ -0,0 +1,29 @@
+"""Token usage reporting for agent runs."""
+
+from typing import Any
+
+
+def token_usage(result: Any) -> dict[str, int]:
+    """Tokens a Strands agent run used, as reported by its metrics.
+
+    Args:
+        result: The AgentResult returned by calling an agent.
+
+    Returns:
+        Dictionary with input_tokens and output_tokens (0 when not reported).
+    """
+    metrics = getattr(result, "metrics", None)
+    usage = getattr(metrics, "accumulated_usage", None) or {}
+    return {
+        "input_tokens": int(usage.get("inputTokens", 0) or 0),
+        "output_tokens": int(usage.get("outputTokens", 0) or 0),
+    }
+
+
+def combine_usage(*usages: dict[str, int] | None) -> dict[str, int]:
+    """Add up token usage from several agent runs."""
+    total = {"input_tokens": 0, "output_tokens": 0}
+    for usage in usages:
+        for key in total:
+            total[key] += (usage or {}).get(key, 0)
+    return total
@@ -36,7 +36,7 @@ database = DatabaseStack(
 )
 database.add_dependency(network)
 
-# Migrations Stack - Database migration runner for CI/CD
+# Migrations Stack - Applies database migrations on deploy
 migrations = MigrationsStack(
     app,
     "SecondBrainMigrations",
@@ -75,12 +75,18 @@ api = ApiStack(
     agent_function_arn=agents.agent_function.function_arn,
     db_secret_arn=database.db_secret.secret_arn,
     db_host=database.db_instance.db_instance_endpoint_address,
+    internal_auth_secret_arn=agents.internal_auth_secret.secret_arn,
+    sms_origination_number=os.environ.get("SMS_ORIGINATION_NUMBER"),  # Optional: two-way SMS number
+    cors_allowed_origins=os.environ.get("CORS_ALLOWED_ORIGINS"),  # Optional: defaults to any origin
+    place_index_name=agents.place_index.index_name,
     env=env,
 )
 api.add_dependency(network)
 api.add_dependency(auth)
 api.add_dependency(agents)
 api.add_dependency(database)
+# Schema changes land before the code that uses them
+api.add_dependency(migrations)
 
 # Integrations Stack - Discord, Alexa, etc.
 integrations = IntegrationsStack(
@@ -89,10 +95,16 @@ integrations = IntegrationsStack(
     vpc=network.vpc,
     security_group=network.lambda_security_group,
     agent_function_arn=agents.agent_function.function_arn,
+    database_secret=database.db_secret,
+    database_host=database.db_instance.db_instance_endpoint_address,
+    alexa_skill_id=os.environ.get("ALEXA_SKILL_ID"),  # Optional: restricts the skill Lambda to this skill
+    internal_auth_secret=agents.internal_auth_secret,
     env=env,
 )
 integrations.add_dependency(network)
 integrations.add_dependency(agents)
+integrations.add_dependency(database)
+integrations.add_dependency(migrations)
 
 # Scheduling Stack - EventBridge rules and scheduled triggers
 scheduling = SchedulingStack(
@@ -103,11 +115,22 @@ scheduling = SchedulingStack(
     database_secret=database.db_secret,
     database_host=database.db_instance.db_instance_endpoint_address,
     agent_function_arn=agents.agent_function.function_arn,
+    inbound_email_address=os.environ.get("INBOUND_EMAIL_ADDRESS"),  # Optional: enables email ingestion
+    push_secret_arn=os.environ.get("PUSH_SECRET_ARN"),  # Optional: existing FCM/APNs credentials
+    alexa_secret_arn=os.environ.get("ALEXA_SECRET_ARN"),  # Optional: enables Alexa notifications
+    entity_photos_bucket=api.entity_photos_bucket,
+    realtime_table=api.realtime_table,
+    websocket_stage=api.websocket_stage,
+    place_index_name=agents.place_index.index_name,
+    route_calculator_name=agents.route_calculator.calculator_name,
+    internal_auth_secret=agents.internal_auth_secret,
     env=env,
 )
 scheduling.add_dependency(network)
 scheduling.add_dependency(database)
+scheduling.add_dependency(migrations)
 scheduling.add_dependency(agents)
+scheduling.add_dependency(api)
 
 # Monitoring Stack - CloudWatch dashboards and alarms
 monitoring = MonitoringStack(
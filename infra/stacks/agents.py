@@ -16,6 +16,52 @@ from aws_cdk import (
 )
 from constructs import Construct
 
+# Rotates the internal auth secret: a new `current` key, with the old one kept
+# as `previous` so payloads signed just before still verify. Nothing outside
+# the secret holds the keys, so setSecret and testSecret have nothing to do.
+ROTATE_INTERNAL_AUTH_SECRET = """
+import json
+
+import boto3
+
+client = boto3.client("secretsmanager")
+
+
+def handler(event, context):
+    arn = event["SecretId"]
+    token = event["ClientRequestToken"]
+    step = event["Step"]
+
+    if step == "createSecret":
+        try:
+            client.get_secret_value(SecretId=arn, VersionId=token, VersionStage="AWSPENDING")
+            return
+        except client.exceptions.ResourceNotFoundException:
+            pass
+        current = json.loads(
+            client.get_secret_value(SecretId=arn, VersionStage="AWSCURRENT")["SecretString"]
+        )
+        key = client.get_random_password(PasswordLength=64, ExcludePunctuation=True)
+        client.put_secret_value(
+            SecretId=arn,
+            ClientRequestToken=token,
+            SecretString=json.dumps(
+                {"current": key["RandomPassword"], "previous": current.get("current", "")}
+            ),
+            VersionStages=["AWSPENDING"],
+        )
+    elif step == "finishSecret":
+        versions = client.describe_secret(SecretId=arn)["VersionIdsToStages"]
+        for version, stages in versions.items():
+            if "AWSCURRENT" in stages and version != token:
+                client.update_secret_version_stage(
+                    SecretId=arn,
+                    VersionStage="AWSCURRENT",
+                    MoveToVersionId=token,
+                    RemoveFromVersionId=version,
+                )
+"""
+
 
 class AgentsStack(Stack):
     """Stack containing Python agent Lambda and supporting resources."""
@@ -53,6 +99,16 @@ class AgentsStack(Stack):
             description="Place index for geocoding addresses",
         )
 
+        # AWS Location Service route calculator for travel-time reminders
+        self.route_calculator = location.CfnRouteCalculator(
+            self,
+            "RouteCalculator",
+            calculator_name="second-brain-route-calculator",
+            data_source="Esri",
+            pricing_plan="RequestBasedUsage",
+            description="Route calculator for reminders to leave in time",
+        )
+
         # IAM Role for Agent Lambda
         agent_role = iam.Role(
             self,
@@ -103,8 +159,56 @@ class AgentsStack(Stack):
             )
         )
 
+        # Key the Rust Lambdas sign agent and follow-up invocations with, so
+        # the receiver can trust the user_id in them (shared::auth::sign_internal)
+        self.internal_auth_secret = secretsmanager.Secret(
+            self,
+            "InternalAuthSecret",
+            secret_name="second-brain/internal-auth",
+            description="HMAC keys for signed Lambda-to-Lambda invocations",
+            generate_secret_string=secretsmanager.SecretStringGenerator(
+                secret_string_template='{"previous":""}',
+                generate_string_key="current",
+                exclude_punctuation=True,
+                password_length=64,
+            ),
+        )
+
+        rotate_internal_auth = lambda_.Function(
+            self,
+            "InternalAuthRotation",
+            function_name="second-brain-internal-auth-rotation",
+            runtime=lambda_.Runtime.PYTHON_3_12,
+            handler="index.handler",
+            code=lambda_.Code.from_inline(ROTATE_INTERNAL_AUTH_SECRET),
+            timeout=Duration.seconds(30),
+        )
+        self.internal_auth_secret.grant_read(rotate_internal_auth)
+        rotate_internal_auth.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=[
+                    "secretsmanager:DescribeSecret",
+                    "secretsmanager:PutSecretValue",
+                    "secretsmanager:UpdateSecretVersionStage",
+                ],
+                resources=[self.internal_auth_secret.secret_arn],
+            )
+        )
+        rotate_internal_auth.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=["secretsmanager:GetRandomPassword"],
+                resources=["*"],
+            )
+        )
+        self.internal_auth_secret.add_rotation_schedule(
+            "InternalAuthRotationSchedule",
+            rotation_lambda=rotate_internal_auth,
+            automatically_after=Duration.days(30),
+        )
+
         # Secrets Manager permissions
         database_secret.grant_read(agent_role)
+        self.internal_auth_secret.grant_read(agent_role)
 
         # Log group for agent function
         agent_log_group = logs.LogGroup(
@@ -156,6 +260,7 @@ class AgentsStack(Stack):
                 "DB_PORT": "5432",
                 "DB_NAME": "second_brain",
                 "DB_SECRET_ARN": database_secret.secret_arn,
+                "INTERNAL_AUTH_SECRET_ARN": self.internal_auth_secret.secret_arn,
                 "BEDROCK_MODEL_ID": "us.anthropic.claude-3-haiku-20240307-v1:0",
                 "EMBEDDING_MODEL_ID": "amazon.titan-embed-text-v2:0",
                 "LOCATION_PLACE_INDEX": self.place_index.index_name,
@@ -5,11 +5,17 @@ from aws_cdk import (
     Duration,
     Stack,
     aws_apigateway as apigw,
+    aws_apigatewayv2 as apigwv2,
+    aws_apigatewayv2_integrations as apigwv2_integrations,
     aws_cognito as cognito,
+    aws_dynamodb as dynamodb,
     aws_ec2 as ec2,
+    aws_events as events,
+    aws_events_targets as targets,
     aws_iam as iam,
     aws_lambda as lambda_,
     aws_logs as logs,
+    aws_s3 as s3,
 )
 from constructs import Construct
 
@@ -52,6 +58,10 @@ class ApiStack(Stack):
         agent_function_arn: str,
         db_secret_arn: str,
         db_host: str,
+        internal_auth_secret_arn: str,
+        sms_origination_number: str | None = None,
+        cors_allowed_origins: str | None = None,
+        place_index_name: str | None = None,
         **kwargs,
     ) -> None:
         """Initialize the API Stack.
@@ -65,6 +75,12 @@ class ApiStack(Stack):
             agent_function_arn: ARN of the agent Lambda function.
             db_secret_arn: ARN of the database credentials secret.
             db_host: Database host address.
+            internal_auth_secret_arn: ARN of the key agent invocations are signed with.
+            sms_origination_number: Two-way SMS number verification codes are sent from.
+            cors_allowed_origins: Comma-separated origins browsers may call the
+                API from. All origins when unset.
+            place_index_name: Amazon Location place index for geocoding entity
+                addresses. Addresses are stored as entered when unset.
             **kwargs: Additional stack properties.
         """
         super().__init__(scope, id, **kwargs)
@@ -83,6 +99,36 @@ class ApiStack(Stack):
             "LOG_LEVEL": "INFO",
         }
 
+        # Browser origins allowed by the Lambdas' CORS handling and by
+        # API Gateway's own preflight responses
+        allowed_origins = apigw.Cors.ALL_ORIGINS
+        if cors_allowed_origins:
+            common_env["CORS_ALLOWED_ORIGINS"] = cors_allowed_origins
+            db_env["CORS_ALLOWED_ORIGINS"] = cors_allowed_origins
+            allowed_origins = [
+                origin.strip()
+                for origin in cors_allowed_origins.split(",")
+                if origin.strip()
+            ]
+
+        # Domain event bus (created by the scheduling stack, which deploys
+        # after this one, so it is referenced by name; see
+        # docs/design/domain-events.md)
+        event_bus_name = "second-brain-events"
+        event_env = {"EVENT_BUS_NAME": event_bus_name}
+        event_bus_arn = (
+            f"arn:aws:events:{Stack.of(self).region}:{Stack.of(self).account}"
+            f":event-bus/{event_bus_name}"
+        )
+
+        def grant_put_events(fn: lambda_.Function) -> None:
+            fn.add_to_role_policy(
+                iam.PolicyStatement(
+                    actions=["events:PutEvents"],
+                    resources=[event_bus_arn],
+                )
+            )
+
         # Helper to create Rust Lambda functions
         def create_rust_lambda(
             construct_id: str,
@@ -121,7 +167,8 @@ class ApiStack(Stack):
                 tracing=lambda_.Tracing.ACTIVE,
             )
 
-            # Grant invoke permission on agent function
+            # Grant invoke permission on agent function, and the key the
+            # invocations are signed with (shared::auth::sign_internal)
             if needs_agent_invoke:
                 fn.add_to_role_policy(
                     iam.PolicyStatement(
@@ -129,6 +176,13 @@ class ApiStack(Stack):
                         resources=[agent_function_arn],
                     )
                 )
+                fn.add_environment("INTERNAL_AUTH_SECRET_ARN", internal_auth_secret_arn)
+                fn.add_to_role_policy(
+                    iam.PolicyStatement(
+                        actions=["secretsmanager:GetSecretValue"],
+                        resources=[internal_auth_secret_arn],
+                    )
+                )
 
             # Grant secrets manager access
             if needs_secrets:
@@ -146,25 +200,121 @@ class ApiStack(Stack):
             "QueryLambda",
             "query",
             "Handles /query requests",
+            # Database access for opt-in diagnostics capture
+            env={**common_env, **db_env},
+            needs_secrets=True,
+        )
+
+        # Answers simple queries from the database when the agents are down
+        # (shared::agents::DirectFallback)
+        query_lambda.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=["bedrock:InvokeModel"],
+                resources=[
+                    f"arn:aws:bedrock:{Stack.of(self).region}::foundation-model/amazon.titan-embed-text-v2:0",
+                    "arn:aws:bedrock:*::foundation-model/anthropic.claude-3-haiku-20240307-v1:0",
+                    f"arn:aws:bedrock:{Stack.of(self).region}:{Stack.of(self).account}"
+                    ":inference-profile/us.anthropic.claude-3-haiku-20240307-v1:0",
+                ],
+            )
+        )
+
+        # Ingest Batch Lambda (large POST /ingest/batch imports, run in the
+        # background; up to 500 items a few at a time)
+        ingest_batch_lambda = create_rust_lambda(
+            "IngestBatchLambda",
+            "ingest_batch",
+            "Processes large batch ingests",
+            timeout_seconds=900,
+            env={**common_env, **db_env, **event_env},
+            needs_secrets=True,
         )
 
         ingest_lambda = create_rust_lambda(
             "IngestLambda",
             "ingest",
             "Handles /ingest requests",
+            env={
+                **common_env,
+                **db_env,
+                **event_env,
+                "INGEST_BATCH_FUNCTION_NAME": ingest_batch_lambda.function_name,
+            },
+            needs_secrets=True,
+        )
+        ingest_batch_lambda.grant_invoke(ingest_lambda)
+        # Batches running short of time hand themselves on to a fresh invocation
+        ingest_batch_lambda.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=["lambda:InvokeFunction"],
+                resources=[
+                    f"arn:aws:lambda:{Stack.of(self).region}:{Stack.of(self).account}:function:second-brain-ingest_batch",
+                ],
+            )
+        )
+        # Resume batch jobs that died partway (their lease ran out)
+        ingest_batch_rule = events.Rule(
+            self,
+            "IngestBatchSweepSchedule",
+            rule_name="second-brain-ingest-batch-sweep",
+            description="Resumes batch ingests that stopped partway",
+            schedule=events.Schedule.rate(Duration.minutes(5)),
+        )
+        ingest_batch_rule.add_target(targets.LambdaFunction(ingest_batch_lambda))
+        # Embeds new facts to find repeats and older facts they supersede
+        for fn in (ingest_lambda, ingest_batch_lambda):
+            fn.add_to_role_policy(
+                iam.PolicyStatement(
+                    actions=["bedrock:InvokeModel"],
+                    resources=[
+                        f"arn:aws:bedrock:{Stack.of(self).region}::foundation-model/amazon.titan-embed-text-v2:0",
+                    ],
+                )
+            )
+            grant_put_events(fn)
+
+        # Briefings read aloud (?audio=true), played from presigned URLs. The
+        # audio is reused for TTS_CACHE_TTL_SECONDS (7 days), so it is kept a
+        # day longer than that
+        speech_audio_bucket = s3.Bucket(
+            self,
+            "SpeechAudioBucket",
+            block_public_access=s3.BlockPublicAccess.BLOCK_ALL,
+            encryption=s3.BucketEncryption.S3_MANAGED,
+            enforce_ssl=True,
+            lifecycle_rules=[
+                s3.LifecycleRule(prefix="tts/", expiration=Duration.days(8)),
+            ],
         )
 
+        # Briefing Lambda (serves stored briefings, generates live as a fallback)
         briefing_lambda = create_rust_lambda(
             "BriefingLambda",
             "briefing",
-            "Handles /briefing requests",
+            "Handles /briefing and /briefings/today requests",
             timeout_seconds=60,
+            env={
+                **common_env,
+                **db_env,
+                "TTS_AUDIO_BUCKET": speech_audio_bucket.bucket_name,
+            },
+            needs_secrets=True,
+        )
+        speech_audio_bucket.grant_read_write(briefing_lambda)
+        briefing_lambda.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=["polly:SynthesizeSpeech"],
+                resources=["*"],
+            )
         )
 
         calendar_lambda = create_rust_lambda(
             "CalendarLambda",
             "calendar",
-            "Handles /calendar requests",
+            "Handles /calendar and /calendar/subscriptions requests",
+            env=db_env,
+            needs_agent_invoke=False,
+            needs_secrets=True,
         )
 
         # Calendar OAuth Lambda (needs secrets access for Google OAuth)
@@ -196,6 +346,32 @@ class ApiStack(Stack):
             )
         )
 
+        # Contacts OAuth Lambda (Google Contacts connection; needs the database
+        # to record connections and Secrets Manager for per-user tokens)
+        # Note: OAUTH_REDIRECT_URI will be set after API creation via CfnOutput
+        contacts_oauth_lambda = create_rust_lambda(
+            "ContactsOAuthLambda",
+            "contacts_oauth",
+            "Handles Google Contacts OAuth flow and connection management",
+            env={**db_env, "GOOGLE_OAUTH_SECRET_ARN": "second-brain/google-oauth"},
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+        contacts_oauth_lambda.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=[
+                    "secretsmanager:GetSecretValue",
+                    "secretsmanager:CreateSecret",
+                    "secretsmanager:PutSecretValue",
+                    "secretsmanager:DeleteSecret",
+                ],
+                resources=[
+                    f"arn:aws:secretsmanager:us-east-1:{Stack.of(self).account}:secret:second-brain/google-oauth*",
+                    f"arn:aws:secretsmanager:us-east-1:{Stack.of(self).account}:secret:second-brain/contacts/*",
+                ],
+            )
+        )
+
         # Families Lambda (database access)
         families_lambda = create_rust_lambda(
             "FamiliesLambda",
@@ -216,25 +392,90 @@ class ApiStack(Stack):
             needs_secrets=True,
         )
 
+        # Entity photos; clients upload and download directly with presigned URLs
+        entity_photos_bucket = s3.Bucket(
+            self,
+            "EntityPhotosBucket",
+            block_public_access=s3.BlockPublicAccess.BLOCK_ALL,
+            encryption=s3.BucketEncryption.S3_MANAGED,
+            enforce_ssl=True,
+            cors=[
+                s3.CorsRule(
+                    allowed_methods=[s3.HttpMethods.PUT, s3.HttpMethods.GET],
+                    allowed_origins=["*"],
+                    allowed_headers=["*"],
+                    max_age=3000,
+                ),
+            ],
+        )
+
         # Entities Lambda (database access)
         entities_lambda = create_rust_lambda(
             "EntitiesLambda",
             "entities",
             "Handles /entities requests",
-            env=db_env,
+            env={
+                **db_env,
+                **event_env,
+                "ENTITY_PHOTOS_BUCKET": entity_photos_bucket.bucket_name,
+            },
             needs_agent_invoke=False,
             needs_secrets=True,
         )
+        grant_put_events(entities_lambda)
+        entity_photos_bucket.grant_read_write(entities_lambda)
+
+        # Fact attachments (receipts, documents, voice memos); same direct
+        # presigned upload/download flow as entity photos
+        fact_attachments_bucket = s3.Bucket(
+            self,
+            "FactAttachmentsBucket",
+            block_public_access=s3.BlockPublicAccess.BLOCK_ALL,
+            encryption=s3.BucketEncryption.S3_MANAGED,
+            enforce_ssl=True,
+            cors=[
+                s3.CorsRule(
+                    allowed_methods=[s3.HttpMethods.PUT, s3.HttpMethods.GET],
+                    allowed_origins=["*"],
+                    allowed_headers=["*"],
+                    max_age=3000,
+                ),
+            ],
+        )
 
         # Locations Lambda (database access with PostGIS)
         locations_lambda = create_rust_lambda(
             "LocationsLambda",
             "locations",
             "Handles /locations and temporal queries",
-            env=db_env,
+            env={
+                **db_env,
+                **event_env,
+                "FACT_ATTACHMENTS_BUCKET": fact_attachments_bucket.bucket_name,
+            },
             needs_agent_invoke=False,
             needs_secrets=True,
         )
+        grant_put_events(locations_lambda)
+        fact_attachments_bucket.grant_read_write(locations_lambda)
+        # Static map images for entity locations, presigned with this role
+        locations_lambda.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=["geo-maps:GetStaticMap"],
+                resources=[f"arn:aws:geo-maps:{self.region}::provider/default"],
+            )
+        )
+        if place_index_name:
+            # Geocoding normalizes addresses so duplicates can be recognized
+            locations_lambda.add_environment("PLACE_INDEX_NAME", place_index_name)
+            locations_lambda.add_to_role_policy(
+                iam.PolicyStatement(
+                    actions=["geo:SearchPlaceIndexForText"],
+                    resources=[
+                        f"arn:aws:geo:{self.region}:{self.account}:place-index/{place_index_name}"
+                    ],
+                )
+            )
 
         # Tags Lambda (database access)
         tags_lambda = create_rust_lambda(
@@ -245,6 +486,15 @@ class ApiStack(Stack):
             needs_agent_invoke=False,
             needs_secrets=True,
         )
+        # Embedding-based suggestions embed content that isn't indexed yet
+        tags_lambda.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=["bedrock:InvokeModel"],
+                resources=[
+                    f"arn:aws:bedrock:{Stack.of(self).region}::foundation-model/amazon.titan-embed-text-v2:0",
+                ],
+            )
+        )
 
         # Feedback Lambda (database access)
         feedback_lambda = create_rust_lambda(
@@ -266,6 +516,436 @@ class ApiStack(Stack):
             needs_secrets=True,
         )
 
+        # Handoffs Lambda (database access, publishes notifications)
+        handoffs_lambda = create_rust_lambda(
+            "HandoffsLambda",
+            "handoffs",
+            "Handles /handoffs briefings between family members",
+            env={
+                **db_env,
+                # Topic lives in the scheduling stack; referenced by name to avoid a cycle
+                "NOTIFICATION_TOPIC_ARN": f"arn:aws:sns:{Stack.of(self).region}:{Stack.of(self).account}:second-brain-notifications",
+            },
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+        handoffs_lambda.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=["sns:Publish"],
+                resources=[
+                    f"arn:aws:sns:{Stack.of(self).region}:{Stack.of(self).account}:second-brain-notifications",
+                ],
+            )
+        )
+
+        # Diagnostics Lambda (debug mode opt-in and admin sample access)
+        diagnostics_lambda = create_rust_lambda(
+            "DiagnosticsLambda",
+            "diagnostics",
+            "Handles /diagnostics debug mode and /admin/diagnostics requests",
+            env=db_env,
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+
+        # Discord Link Lambda (verification codes for linking Discord accounts)
+        discord_link_lambda = create_rust_lambda(
+            "DiscordLinkLambda",
+            "discord_link",
+            "Handles /discord/link requests",
+            env=db_env,
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+
+        # SMS Phone Lambda (phone number verification for SMS ingestion and query)
+        sms_phone_env = dict(db_env)
+        if sms_origination_number:
+            sms_phone_env["SMS_ORIGINATION_NUMBER"] = sms_origination_number
+
+        sms_phone_lambda = create_rust_lambda(
+            "SmsPhoneLambda",
+            "sms_phone",
+            "Handles /sms/phone requests",
+            env=sms_phone_env,
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+
+        # Publishing to a phone number has no resource ARN to scope to
+        sms_phone_lambda.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=["sns:Publish"],
+                resources=["*"],
+            )
+        )
+
+        # Push Devices Lambda (FCM/APNs token registration)
+        push_devices_lambda = create_rust_lambda(
+            "PushDevicesLambda",
+            "push_devices",
+            "Handles /devices/push requests",
+            env=db_env,
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+
+        # Occasions Lambda (upcoming birthdays and anniversaries)
+        occasions_lambda = create_rust_lambda(
+            "OccasionsLambda",
+            "occasions",
+            "Handles /occasions requests",
+            env=db_env,
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+
+        # Full data export bundles, downloaded through presigned URLs and
+        # deleted after a week
+        exports_bucket = s3.Bucket(
+            self,
+            "ExportsBucket",
+            block_public_access=s3.BlockPublicAccess.BLOCK_ALL,
+            encryption=s3.BucketEncryption.S3_MANAGED,
+            enforce_ssl=True,
+            lifecycle_rules=[
+                s3.LifecycleRule(prefix="exports/", expiration=Duration.days(7)),
+            ],
+        )
+
+        # Data Export Lambda (builds a user's export bundle; invoked
+        # asynchronously by the export Lambda)
+        data_export_lambda = create_rust_lambda(
+            "DataExportLambda",
+            "data_export",
+            "Builds full data export bundles",
+            timeout_seconds=300,
+            memory_mb=1024,
+            env={**db_env, "EXPORTS_BUCKET": exports_bucket.bucket_name},
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+        exports_bucket.grant_put(data_export_lambda)
+
+        # Export Lambda (graph downloads; large accounts take a while to render)
+        export_lambda = create_rust_lambda(
+            "ExportLambda",
+            "export",
+            "Handles /export requests",
+            timeout_seconds=60,
+            memory_mb=512,
+            env={
+                **db_env,
+                "EXPORTS_BUCKET": exports_bucket.bucket_name,
+                "DATA_EXPORT_FUNCTION_NAME": data_export_lambda.function_name,
+            },
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+        exports_bucket.grant_read(export_lambda)
+        data_export_lambda.grant_invoke(export_lambda)
+
+        # Trash Lambda (deleted facts, entities and tags)
+        trash_lambda = create_rust_lambda(
+            "TrashLambda",
+            "trash",
+            "Handles /trash listing and restores",
+            env=db_env,
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+
+        # Capture Lambda (browser extension; fetches pages, agent summarizes them)
+        capture_lambda = create_rust_lambda(
+            "CaptureLambda",
+            "capture",
+            "Handles /capture requests",
+            timeout_seconds=60,
+            env={**common_env, **db_env, **event_env},
+            needs_secrets=True,
+        )
+        grant_put_events(capture_lambda)
+
+        # Feeds Lambda (RSS/Atom subscriptions; items are polled by feed_poller)
+        feeds_lambda = create_rust_lambda(
+            "FeedsLambda",
+            "feeds",
+            "Handles /feeds requests",
+            env=db_env,
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+
+        # Account Deletion Lambda (works through scheduled deletions step by
+        # step; invoked asynchronously by the account Lambda and retried by
+        # the schedule below)
+        account_deletion_lambda = create_rust_lambda(
+            "AccountDeletionLambda",
+            "account_deletion",
+            "Carries out scheduled account deletions",
+            timeout_seconds=300,
+            env={**db_env, "USER_POOL_ID": user_pool.user_pool_id},
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+        account_deletion_lambda.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=[
+                    "cognito-idp:AdminUserGlobalSignOut",
+                    "cognito-idp:AdminDisableUser",
+                    "cognito-idp:AdminDeleteUser",
+                ],
+                resources=[user_pool.user_pool_arn],
+            )
+        )
+        account_deletion_lambda.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=["secretsmanager:DeleteSecret"],
+                resources=[
+                    f"arn:aws:secretsmanager:us-east-1:{Stack.of(self).account}:secret:second-brain/calendar/*",
+                    f"arn:aws:secretsmanager:us-east-1:{Stack.of(self).account}:secret:second-brain/contacts/*",
+                ],
+            )
+        )
+
+        # Retry unfinished account deletions every 15 minutes
+        account_deletion_rule = events.Rule(
+            self,
+            "AccountDeletionSchedule",
+            rule_name="second-brain-account-deletion",
+            description="Retries unfinished account deletions",
+            schedule=events.Schedule.rate(Duration.minutes(15)),
+        )
+        account_deletion_rule.add_target(
+            targets.LambdaFunction(account_deletion_lambda)
+        )
+
+        # Account Lambda (DELETE /account schedules the deletion)
+        account_lambda = create_rust_lambda(
+            "AccountLambda",
+            "account",
+            "Handles /account requests",
+            env={
+                **db_env,
+                "ACCOUNT_DELETION_FUNCTION_NAME": account_deletion_lambda.function_name,
+            },
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+        account_deletion_lambda.grant_invoke(account_lambda)
+
+        # Audit Lambda (GET /audit; entries are written by the mutating Lambdas)
+        audit_lambda = create_rust_lambda(
+            "AuditLambda",
+            "audit",
+            "Handles /audit requests",
+            env=db_env,
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+
+        # Spaces Lambda (family spaces and the facts/entities in them)
+        spaces_lambda = create_rust_lambda(
+            "SpacesLambda",
+            "spaces",
+            "Handles /spaces and /families/{familyId}/spaces requests",
+            env=db_env,
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+
+        # Sharing Lambda (what relationships let each person see)
+        sharing_lambda = create_rust_lambda(
+            "SharingLambda",
+            "sharing",
+            "Handles /shared-with-me and /shared-by-me requests",
+            env=db_env,
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+
+        # Access Grants Lambda (time-boxed access to facts and entities)
+        access_grants_lambda = create_rust_lambda(
+            "AccessGrantsLambda",
+            "access_grants",
+            "Handles /access-grants requests",
+            env=db_env,
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+
+        # Preferences Lambda (notification channels, quiet hours, briefings)
+        preferences_lambda = create_rust_lambda(
+            "PreferencesLambda",
+            "preferences",
+            "Handles /preferences requests",
+            env=db_env,
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+
+        # API Keys Lambda (personal keys for scripts and home automation)
+        api_keys_lambda = create_rust_lambda(
+            "ApiKeysLambda",
+            "api_keys",
+            "Handles /api-keys requests",
+            env=db_env,
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+
+        # Auth Lambda (device authorization for CLI and TV clients)
+        auth_lambda = create_rust_lambda(
+            "AuthLambda",
+            "auth",
+            "Handles /auth requests",
+            env=db_env,
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+
+        # Location History Lambda (pings from the mobile app and visits)
+        location_history_lambda = create_rust_lambda(
+            "LocationHistoryLambda",
+            "location_history",
+            "Handles /location-pings and /location-history requests",
+            env=db_env,
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+
+        # Internal Facts Lambda (structured /edit and /forget for the chat
+        # platforms, which invoke it directly rather than through the API)
+        internal_facts_lambda = create_rust_lambda(
+            "InternalFactsLambda",
+            "internal_facts",
+            "Resolves and edits or deletes facts for chat commands",
+            env={
+                **db_env,
+                **event_env,
+                "INTERNAL_AUTH_SECRET_ARN": internal_auth_secret_arn,
+            },
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+        grant_put_events(internal_facts_lambda)
+        internal_facts_lambda.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=["secretsmanager:GetSecretValue"],
+                resources=[internal_auth_secret_arn],
+            )
+        )
+
+        # Conversations Lambda (written by /query and the chat platforms)
+        conversations_lambda = create_rust_lambda(
+            "ConversationsLambda",
+            "conversations",
+            "Handles /conversations requests",
+            env=db_env,
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+
+        # Usage Lambda (token usage, spend and past answers logged by /query)
+        usage_lambda = create_rust_lambda(
+            "UsageLambda",
+            "usage",
+            "Handles /usage and /v1/queries requests",
+            env=db_env,
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+
+        # OpenAPI Lambda (serves the generated API document)
+        openapi_lambda = create_rust_lambda(
+            "OpenApiLambda",
+            "openapi",
+            "Serves /openapi.json",
+            env=common_env,
+            needs_agent_invoke=False,
+            needs_secrets=False,
+        )
+
+        # Real-time updates: tickets and connections (see shared::realtime)
+        realtime_table = dynamodb.Table(
+            self,
+            "RealtimeConnections",
+            table_name="second-brain-realtime-connections",
+            partition_key=dynamodb.Attribute(
+                name="pk", type=dynamodb.AttributeType.STRING
+            ),
+            billing_mode=dynamodb.BillingMode.PAY_PER_REQUEST,
+            time_to_live_attribute="expires_at",
+        )
+        realtime_table.add_global_secondary_index(
+            index_name="user-connections",
+            partition_key=dynamodb.Attribute(
+                name="connected_user_id", type=dynamodb.AttributeType.STRING
+            ),
+            projection_type=dynamodb.ProjectionType.KEYS_ONLY,
+        )
+
+        # WebSocket Lambda ($connect redeems a ticket; queries answer in chunks)
+        websocket_lambda = create_rust_lambda(
+            "WebsocketLambda",
+            "websocket",
+            "Handles WebSocket connections and queries",
+            timeout_seconds=120,
+            env={
+                **common_env,
+                "CONNECTIONS_TABLE_NAME": realtime_table.table_name,
+            },
+        )
+        realtime_table.grant_read_write_data(websocket_lambda)
+
+        self.websocket_api = apigwv2.WebSocketApi(
+            self,
+            "SecondBrainWebSocketApi",
+            api_name="second-brain-realtime",
+            description="Second Brain real-time updates",
+            connect_route_options=apigwv2.WebSocketRouteOptions(
+                integration=apigwv2_integrations.WebSocketLambdaIntegration(
+                    "WebsocketConnect", websocket_lambda
+                )
+            ),
+            disconnect_route_options=apigwv2.WebSocketRouteOptions(
+                integration=apigwv2_integrations.WebSocketLambdaIntegration(
+                    "WebsocketDisconnect", websocket_lambda
+                )
+            ),
+            default_route_options=apigwv2.WebSocketRouteOptions(
+                integration=apigwv2_integrations.WebSocketLambdaIntegration(
+                    "WebsocketDefault", websocket_lambda
+                )
+            ),
+        )
+        websocket_stage = apigwv2.WebSocketStage(
+            self,
+            "SecondBrainWebSocketStage",
+            web_socket_api=self.websocket_api,
+            stage_name="realtime",
+            auto_deploy=True,
+        )
+        websocket_stage.grant_management_api_access(websocket_lambda)
+        websocket_lambda.add_environment(
+            "WEBSOCKET_CALLBACK_URL", websocket_stage.callback_url
+        )
+
+        # Realtime Lambda (POST /realtime/ticket)
+        realtime_lambda = create_rust_lambda(
+            "RealtimeLambda",
+            "realtime",
+            "Handles /realtime requests",
+            env={
+                **db_env,
+                "CONNECTIONS_TABLE_NAME": realtime_table.table_name,
+                "WEBSOCKET_URL": websocket_stage.url,
+            },
+            needs_agent_invoke=False,
+            needs_secrets=True,
+        )
+        realtime_table.grant_write_data(realtime_lambda)
+
         # REST API Gateway
         self.api = apigw.RestApi(
             self,
@@ -280,8 +960,11 @@ class ApiStack(Stack):
                 throttling_rate_limit=100,
                 throttling_burst_limit=200,
             ),
+            # Lambdas return compressed bodies base64-encoded; API Gateway
+            # only decodes them for media types listed here
+            binary_media_types=["*/*"],
             default_cors_preflight_options=apigw.CorsOptions(
-                allow_origins=apigw.Cors.ALL_ORIGINS,
+                allow_origins=allowed_origins,
                 allow_methods=apigw.Cors.ALL_METHODS,
                 allow_headers=[
                     "Content-Type",
@@ -289,6 +972,8 @@ class ApiStack(Stack):
                     "X-Amz-Date",
                     "X-Api-Key",
                     "X-Amz-Security-Token",
+                    "If-Match",
+                    "If-None-Match",
                 ],
             ),
         )
@@ -322,16 +1007,42 @@ class ApiStack(Stack):
             authorization_type=apigw.AuthorizationType.COGNITO,
         )
 
-        # /briefing endpoint
-        briefing_resource = root.add_resource("briefing")
-        briefing_resource.add_method(
+        # /ingest/batch endpoints (imports; large batches run as a job)
+        ingest_batch_resource = ingest_resource.add_resource("batch")
+        ingest_batch_resource.add_method(
+            "POST",
+            apigw.LambdaIntegration(ingest_lambda),
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+        ingest_batch_resource.add_resource("{id}").add_method(
             "GET",
-            apigw.LambdaIntegration(briefing_lambda),
+            apigw.LambdaIntegration(ingest_lambda),
             authorizer=authorizer,
             authorization_type=apigw.AuthorizationType.COGNITO,
         )
 
-        # /calendar endpoint
+        # /briefing endpoint
+        briefing_integration = apigw.LambdaIntegration(briefing_lambda)
+        briefing_resource = root.add_resource("briefing")
+        briefing_resource.add_method(
+            "GET",
+            briefing_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # GET /briefings/today - Today's pre-generated briefing
+        briefings_resource = root.add_resource("briefings")
+        briefings_today_resource = briefings_resource.add_resource("today")
+        briefings_today_resource.add_method(
+            "GET",
+            briefing_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /calendar endpoint
         calendar_resource = root.add_resource("calendar")
         calendar_resource.add_method(
             "GET",
@@ -340,6 +1051,50 @@ class ApiStack(Stack):
             authorization_type=apigw.AuthorizationType.COGNITO,
         )
 
+        # /calendar/subscriptions - iCal feed subscriptions
+        calendar_integration = apigw.LambdaIntegration(calendar_lambda)
+        calendar_subscriptions_resource = calendar_resource.add_resource("subscriptions")
+
+        # GET /calendar/subscriptions - List subscribed feeds
+        calendar_subscriptions_resource.add_method(
+            "GET",
+            calendar_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # POST /calendar/subscriptions - Subscribe to an iCal feed URL
+        calendar_subscriptions_resource.add_method(
+            "POST",
+            calendar_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # DELETE /calendar/subscriptions/{subscriptionId} - Unsubscribe
+        calendar_subscription_resource = calendar_subscriptions_resource.add_resource("{subscriptionId}")
+        calendar_subscription_resource.add_method(
+            "DELETE",
+            calendar_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /calendar/extraction - Attendee and meeting fact extraction preferences
+        calendar_extraction_resource = calendar_resource.add_resource("extraction")
+        calendar_extraction_resource.add_method(
+            "GET",
+            calendar_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+        calendar_extraction_resource.add_method(
+            "PUT",
+            calendar_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
         # /calendar/oauth - OAuth flow endpoints (public, no auth required)
         calendar_oauth_resource = calendar_resource.add_resource("oauth")
         calendar_oauth_integration = apigw.LambdaIntegration(calendar_oauth_lambda)
@@ -360,6 +1115,47 @@ class ApiStack(Stack):
             authorization_type=apigw.AuthorizationType.NONE,
         )
 
+        # /contacts endpoints (Google Contacts connection)
+        contacts_resource = root.add_resource("contacts")
+        contacts_oauth_integration = apigw.LambdaIntegration(contacts_oauth_lambda)
+        contacts_oauth_resource = contacts_resource.add_resource("oauth")
+
+        # GET /contacts/oauth/start - Consent URL for the signed-in user
+        contacts_oauth_start_resource = contacts_oauth_resource.add_resource("start")
+        contacts_oauth_start_resource.add_method(
+            "GET",
+            contacts_oauth_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # GET /contacts/oauth/callback - Google OAuth callback (public)
+        contacts_oauth_callback_resource = contacts_oauth_resource.add_resource("callback")
+        contacts_oauth_callback_resource.add_method(
+            "GET",
+            contacts_oauth_integration,
+            authorization_type=apigw.AuthorizationType.NONE,
+        )
+
+        # /contacts/connection
+        contacts_connection_resource = contacts_resource.add_resource("connection")
+
+        # GET /contacts/connection - Connection status and last sync
+        contacts_connection_resource.add_method(
+            "GET",
+            contacts_oauth_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # DELETE /contacts/connection - Disconnect Google Contacts
+        contacts_connection_resource.add_method(
+            "DELETE",
+            contacts_oauth_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
         # /families endpoints
         families_resource = root.add_resource("families")
         families_integration = apigw.LambdaIntegration(families_lambda)
@@ -413,6 +1209,105 @@ class ApiStack(Stack):
             authorization_type=apigw.AuthorizationType.COGNITO,
         )
 
+        # POST /families/{familyId}/join-codes - Issue a join code
+        family_resource.add_resource("join-codes").add_method(
+            "POST",
+            families_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # POST /families/join - Join a family with a code
+        families_resource.add_resource("join").add_method(
+            "POST",
+            families_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /families/{familyId}/spaces and /spaces - Family spaces
+        spaces_integration = apigw.LambdaIntegration(spaces_lambda)
+        family_spaces_resource = family_resource.add_resource("spaces")
+
+        # GET /families/{familyId}/spaces - List spaces
+        family_spaces_resource.add_method(
+            "GET",
+            spaces_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # POST /families/{familyId}/spaces - Create a space
+        family_spaces_resource.add_method(
+            "POST",
+            spaces_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /spaces/{spaceId}
+        space_resource = root.add_resource("spaces").add_resource("{spaceId}")
+
+        # GET /spaces/{spaceId} - Space details and members
+        space_resource.add_method(
+            "GET",
+            spaces_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # PUT /spaces/{spaceId} - Rename or describe a space
+        space_resource.add_method(
+            "PUT",
+            spaces_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # DELETE /spaces/{spaceId} - Delete an empty space
+        space_resource.add_method(
+            "DELETE",
+            spaces_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # POST /spaces/{spaceId}/members - Add a member
+        space_members_resource = space_resource.add_resource("members")
+        space_members_resource.add_method(
+            "POST",
+            spaces_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # DELETE /spaces/{spaceId}/members/{userId} - Remove a member
+        space_members_resource.add_resource("{userId}").add_method(
+            "DELETE",
+            spaces_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /spaces/{spaceId}/items
+        space_items_resource = space_resource.add_resource("items")
+
+        # POST /spaces/{spaceId}/items - Move facts and entities into the space
+        space_items_resource.add_method(
+            "POST",
+            spaces_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # DELETE /spaces/{spaceId}/items - Move them back to the whole family
+        space_items_resource.add_method(
+            "DELETE",
+            spaces_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
         # /relationships endpoints
         relationships_resource = root.add_resource("relationships")
         relationships_integration = apigw.LambdaIntegration(relationships_lambda)
@@ -452,6 +1347,166 @@ class ApiStack(Stack):
             authorization_type=apigw.AuthorizationType.COGNITO,
         )
 
+        # /relationships/requests - Relationship requests awaiting consent
+        relationship_requests_resource = relationships_resource.add_resource("requests")
+
+        # GET /relationships/requests - List pending requests
+        relationship_requests_resource.add_method(
+            "GET",
+            relationships_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /relationships/requests/{requestId}
+        relationship_request_resource = relationship_requests_resource.add_resource("{requestId}")
+
+        # DELETE /relationships/requests/{requestId} - Withdraw a request
+        relationship_request_resource.add_method(
+            "DELETE",
+            relationships_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # POST /relationships/requests/{requestId}/accept - Accept a request
+        relationship_request_accept_resource = relationship_request_resource.add_resource("accept")
+        relationship_request_accept_resource.add_method(
+            "POST",
+            relationships_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # POST /relationships/requests/{requestId}/decline - Decline a request
+        relationship_request_decline_resource = relationship_request_resource.add_resource("decline")
+        relationship_request_decline_resource.add_method(
+            "POST",
+            relationships_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /shared-with-me and /shared-by-me endpoints
+        sharing_integration = apigw.LambdaIntegration(sharing_lambda)
+
+        # GET /shared-with-me - What you can see of related people
+        shared_with_me_resource = root.add_resource("shared-with-me")
+        shared_with_me_resource.add_method(
+            "GET",
+            sharing_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # GET /shared-by-me - What related people can see of you
+        shared_by_me_resource = root.add_resource("shared-by-me")
+        shared_by_me_resource.add_method(
+            "GET",
+            sharing_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /access-grants endpoints
+        access_grants_resource = root.add_resource("access-grants")
+        access_grants_integration = apigw.LambdaIntegration(access_grants_lambda)
+
+        # GET /access-grants - List grants given and received
+        access_grants_resource.add_method(
+            "GET",
+            access_grants_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # POST /access-grants - Grant time-boxed access
+        access_grants_resource.add_method(
+            "POST",
+            access_grants_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # DELETE /access-grants/{grantId} - Revoke a grant
+        access_grant_resource = access_grants_resource.add_resource("{grantId}")
+        access_grant_resource.add_method(
+            "DELETE",
+            access_grants_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /preferences endpoints
+        preferences_resource = root.add_resource("preferences")
+        preferences_integration = apigw.LambdaIntegration(preferences_lambda)
+        notification_preferences_resource = preferences_resource.add_resource("notifications")
+
+        # GET /preferences/notifications - Get notification preferences
+        notification_preferences_resource.add_method(
+            "GET",
+            preferences_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # PUT /preferences/notifications - Update notification preferences
+        notification_preferences_resource.add_method(
+            "PUT",
+            preferences_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /location-pings and /location-history endpoints
+        location_history_integration = apigw.LambdaIntegration(location_history_lambda)
+        location_pings_resource = root.add_resource("location-pings")
+        location_pings_batch_resource = location_pings_resource.add_resource("batch")
+
+        # POST /location-pings/batch - Upload location pings
+        location_pings_batch_resource.add_method(
+            "POST",
+            location_history_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        location_history_resource = root.add_resource("location-history")
+
+        # DELETE /location-history - Delete pings and visits
+        location_history_resource.add_method(
+            "DELETE",
+            location_history_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        location_history_settings_resource = location_history_resource.add_resource("settings")
+
+        # GET /location-history/settings - Get location history settings
+        location_history_settings_resource.add_method(
+            "GET",
+            location_history_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # PUT /location-history/settings - Update location history settings
+        location_history_settings_resource.add_method(
+            "PUT",
+            location_history_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # GET /location-history/visits - List detected visits
+        location_history_resource.add_resource("visits").add_method(
+            "GET",
+            location_history_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
         # /entities endpoints
         entities_resource = root.add_resource("entities")
         entities_integration = apigw.LambdaIntegration(entities_lambda)
@@ -527,6 +1582,33 @@ class ApiStack(Stack):
             authorization_type=apigw.AuthorizationType.COGNITO,
         )
 
+        # POST /entities/{entityId}/merge - Merge a duplicate entity into this one
+        entity_merge_resource = entity_resource.add_resource("merge")
+        entity_merge_resource.add_method(
+            "POST",
+            entities_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # POST /entities/{entityId}/photo - Presigned upload URL for the entity's photo
+        entity_photo_resource = entity_resource.add_resource("photo")
+        entity_photo_resource.add_method(
+            "POST",
+            entities_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # GET /entities/{entityId}/relationship-health - Interaction history summary
+        entity_health_resource = entity_resource.add_resource("relationship-health")
+        entity_health_resource.add_method(
+            "GET",
+            entities_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
         # Entity locations (handled by locations lambda)
         entity_locations_resource = entity_resource.add_resource("locations")
         locations_integration = apigw.LambdaIntegration(locations_lambda)
@@ -547,6 +1629,15 @@ class ApiStack(Stack):
             authorization_type=apigw.AuthorizationType.COGNITO,
         )
 
+        # GET /entities/{entityId}/locations/map - Static map image URL
+        entity_locations_map_resource = entity_locations_resource.add_resource("map")
+        entity_locations_map_resource.add_method(
+            "GET",
+            locations_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
         # /locations endpoints
         locations_resource = root.add_resource("locations")
 
@@ -578,6 +1669,33 @@ class ApiStack(Stack):
             authorization_type=apigw.AuthorizationType.COGNITO,
         )
 
+        # /facts/nearby - Facts about places near a point
+        facts_nearby_resource = facts_resource.add_resource("nearby")
+        facts_nearby_resource.add_method(
+            "GET",
+            locations_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /facts/search - Full-text search
+        facts_search_resource = facts_resource.add_resource("search")
+        facts_search_resource.add_method(
+            "GET",
+            locations_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /facts/review - Facts due for review
+        facts_review_resource = facts_resource.add_resource("review")
+        facts_review_resource.add_method(
+            "GET",
+            locations_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
         # /tags endpoints
         tags_resource = root.add_resource("tags")
         tags_integration = apigw.LambdaIntegration(tags_lambda)
@@ -616,8 +1734,36 @@ class ApiStack(Stack):
             authorization_type=apigw.AuthorizationType.COGNITO,
         )
 
-        # /tags/{tagId}
-        tag_resource = tags_resource.add_resource("{tagId}")
+        # /tags/rules - Auto-tagging rules
+        tag_rules_resource = tags_resource.add_resource("rules")
+
+        # GET /tags/rules - List rules
+        tag_rules_resource.add_method(
+            "GET",
+            tags_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # POST /tags/rules - Create rule
+        tag_rules_resource.add_method(
+            "POST",
+            tags_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # DELETE /tags/rules/{ruleId} - Delete rule
+        tag_rule_resource = tag_rules_resource.add_resource("{ruleId}")
+        tag_rule_resource.add_method(
+            "DELETE",
+            tags_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /tags/{tagId}
+        tag_resource = tags_resource.add_resource("{tagId}")
 
         # PUT /tags/{tagId} - Update tag
         tag_resource.add_method(
@@ -644,6 +1790,15 @@ class ApiStack(Stack):
             authorization_type=apigw.AuthorizationType.COGNITO,
         )
 
+        # POST /tags/{tagId}/move - Move/rename tag with its descendants
+        tag_move_resource = tag_resource.add_resource("move")
+        tag_move_resource.add_method(
+            "POST",
+            tags_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
         # /facts/{factId}/tags - Fact tagging
         fact_resource = facts_resource.add_resource("{factId}")
         fact_tags_resource = fact_resource.add_resource("tags")
@@ -675,6 +1830,45 @@ class ApiStack(Stack):
             authorization_type=apigw.AuthorizationType.COGNITO,
         )
 
+        # POST /facts/{factId}/review - Confirm, update or archive a fact
+        fact_review_resource = fact_resource.add_resource("review")
+        fact_review_resource.add_method(
+            "POST",
+            locations_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /facts/{factId}/attachments - Files attached to a fact
+        fact_attachments_resource = fact_resource.add_resource("attachments")
+
+        # GET /facts/{factId}/attachments - List attachments
+        fact_attachments_resource.add_method(
+            "GET",
+            locations_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # POST /facts/{factId}/attachments - Presigned upload URL
+        fact_attachments_resource.add_method(
+            "POST",
+            locations_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # DELETE /facts/{factId}/attachments/{attachmentId} - Remove attachment
+        fact_attachment_resource = fact_attachments_resource.add_resource(
+            "{attachmentId}"
+        )
+        fact_attachment_resource.add_method(
+            "DELETE",
+            locations_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
         # /feedback endpoints
         feedback_resource = root.add_resource("feedback")
         feedback_integration = apigw.LambdaIntegration(feedback_lambda)
@@ -716,6 +1910,16 @@ class ApiStack(Stack):
             authorization_type=apigw.AuthorizationType.COGNITO,
         )
 
+        # POST /v1/events - Log a batch of interactions as implicit feedback
+        v1_resource = root.add_resource("v1")
+        events_resource = v1_resource.add_resource("events")
+        events_resource.add_method(
+            "POST",
+            feedback_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
         # /reminders endpoints
         reminders_resource = root.add_resource("reminders")
         reminders_integration = apigw.LambdaIntegration(reminders_lambda)
@@ -772,5 +1976,513 @@ class ApiStack(Stack):
             authorization_type=apigw.AuthorizationType.COGNITO,
         )
 
+        # POST /reminders/{reminderId}/complete - Mark reminder done
+        reminder_complete_resource = reminder_resource.add_resource("complete")
+        reminder_complete_resource.add_method(
+            "POST",
+            reminders_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # GET /reminders/history - Completed and missed occurrences
+        reminders_history_resource = reminders_resource.add_resource("history")
+        reminders_history_resource.add_method(
+            "GET",
+            reminders_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /handoffs endpoints
+        handoffs_resource = root.add_resource("handoffs")
+        handoffs_integration = apigw.LambdaIntegration(handoffs_lambda)
+
+        # POST /handoffs - Compile and send a handoff
+        handoffs_resource.add_method(
+            "POST",
+            handoffs_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # GET /handoffs - List sent and received handoffs
+        handoffs_resource.add_method(
+            "GET",
+            handoffs_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # DELETE /handoffs/{handoffId} - Revoke share link
+        handoff_resource = handoffs_resource.add_resource("{handoffId}")
+        handoff_resource.add_method(
+            "DELETE",
+            handoffs_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # GET /handoffs/shared/{token} - Shared view (public, token is the credential)
+        handoffs_shared_resource = handoffs_resource.add_resource("shared")
+        handoffs_shared_token_resource = handoffs_shared_resource.add_resource("{token}")
+        handoffs_shared_token_resource.add_method(
+            "GET",
+            handoffs_integration,
+            authorization_type=apigw.AuthorizationType.NONE,
+        )
+
+        # /diagnostics/debug-mode endpoints
+        diagnostics_integration = apigw.LambdaIntegration(diagnostics_lambda)
+        diagnostics_resource = root.add_resource("diagnostics")
+        debug_mode_resource = diagnostics_resource.add_resource("debug-mode")
+
+        # GET /diagnostics/debug-mode - Current debug session
+        debug_mode_resource.add_method(
+            "GET",
+            diagnostics_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # PUT /diagnostics/debug-mode - Opt in for a limited window
+        debug_mode_resource.add_method(
+            "PUT",
+            diagnostics_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # DELETE /diagnostics/debug-mode - Opt out
+        debug_mode_resource.add_method(
+            "DELETE",
+            diagnostics_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # GET /admin/diagnostics - Captured samples (admin group checked in the Lambda)
+        admin_resource = root.add_resource("admin")
+        admin_diagnostics_resource = admin_resource.add_resource("diagnostics")
+        admin_diagnostics_resource.add_method(
+            "GET",
+            diagnostics_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /discord/link endpoints
+        discord_link_integration = apigw.LambdaIntegration(discord_link_lambda)
+        discord_resource = root.add_resource("discord")
+        discord_link_resource = discord_resource.add_resource("link")
+
+        # GET /discord/link - Link status
+        discord_link_resource.add_method(
+            "GET",
+            discord_link_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # DELETE /discord/link - Unlink
+        discord_link_resource.add_method(
+            "DELETE",
+            discord_link_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # POST /discord/link/code - Issue a code to redeem with /link
+        discord_link_code_resource = discord_link_resource.add_resource("code")
+        discord_link_code_resource.add_method(
+            "POST",
+            discord_link_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /sms/phone endpoints
+        sms_phone_integration = apigw.LambdaIntegration(sms_phone_lambda)
+        sms_resource = root.add_resource("sms")
+        sms_phone_resource = sms_resource.add_resource("phone")
+
+        # GET /sms/phone - Registered number and verification status
+        sms_phone_resource.add_method(
+            "GET",
+            sms_phone_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # POST /sms/phone - Text a verification code to a number
+        sms_phone_resource.add_method(
+            "POST",
+            sms_phone_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # DELETE /sms/phone - Remove the number
+        sms_phone_resource.add_method(
+            "DELETE",
+            sms_phone_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # POST /sms/phone/verify - Confirm the number with the code
+        sms_phone_verify_resource = sms_phone_resource.add_resource("verify")
+        sms_phone_verify_resource.add_method(
+            "POST",
+            sms_phone_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /devices/push endpoints
+        push_devices_integration = apigw.LambdaIntegration(push_devices_lambda)
+        devices_resource = root.add_resource("devices")
+        push_devices_resource = devices_resource.add_resource("push")
+
+        # GET /devices/push - List registered devices
+        push_devices_resource.add_method(
+            "GET",
+            push_devices_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # POST /devices/push - Register a device token
+        push_devices_resource.add_method(
+            "POST",
+            push_devices_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # DELETE /devices/push/{deviceId} - Unregister a device
+        push_device_resource = push_devices_resource.add_resource("{deviceId}")
+        push_device_resource.add_method(
+            "DELETE",
+            push_devices_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /occasions endpoints
+        occasions_integration = apigw.LambdaIntegration(occasions_lambda)
+        occasions_resource = root.add_resource("occasions")
+
+        # GET /occasions/upcoming - Upcoming birthdays and anniversaries
+        occasions_upcoming_resource = occasions_resource.add_resource("upcoming")
+        occasions_upcoming_resource.add_method(
+            "GET",
+            occasions_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /export endpoints
+        export_resource = root.add_resource("export")
+        export_integration = apigw.LambdaIntegration(export_lambda)
+
+        # GET /export/graph - Download the entity graph or facts
+        export_graph_resource = export_resource.add_resource("graph")
+        export_graph_resource.add_method(
+            "GET",
+            export_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # POST /export - Start a full data export
+        export_resource.add_method(
+            "POST",
+            export_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # GET /export/{exportId} - Data export status and download link
+        export_job_resource = export_resource.add_resource("{exportId}")
+        export_job_resource.add_method(
+            "GET",
+            export_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /trash endpoints
+        trash_resource = root.add_resource("trash")
+        trash_integration = apigw.LambdaIntegration(trash_lambda)
+
+        # GET /trash - Deleted items awaiting purge
+        trash_resource.add_method(
+            "GET",
+            trash_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # POST /trash/{id}/restore - Restore a deleted item
+        trash_item_resource = trash_resource.add_resource("{id}")
+        trash_restore_resource = trash_item_resource.add_resource("restore")
+        trash_restore_resource.add_method(
+            "POST",
+            trash_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /capture - Save a web page from the browser extension
+        capture_resource = root.add_resource("capture")
+        capture_resource.add_method(
+            "POST",
+            apigw.LambdaIntegration(capture_lambda),
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /feeds - RSS/Atom feed subscriptions
+        feeds_integration = apigw.LambdaIntegration(feeds_lambda)
+        feeds_resource = root.add_resource("feeds")
+
+        # GET /feeds - List subscribed feeds
+        feeds_resource.add_method(
+            "GET",
+            feeds_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # POST /feeds - Subscribe to a feed URL
+        feeds_resource.add_method(
+            "POST",
+            feeds_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # DELETE /feeds/{feedId} - Unsubscribe
+        feed_resource = feeds_resource.add_resource("{feedId}")
+        feed_resource.add_method(
+            "DELETE",
+            feeds_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # POST /feeds/{feedId}/enable - Resume polling
+        feed_enable_resource = feed_resource.add_resource("enable")
+        feed_enable_resource.add_method(
+            "POST",
+            feeds_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # POST /feeds/{feedId}/disable - Pause polling
+        feed_disable_resource = feed_resource.add_resource("disable")
+        feed_disable_resource.add_method(
+            "POST",
+            feeds_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # DELETE /account - Schedule deletion of the caller's account
+        account_resource = root.add_resource("account")
+        account_resource.add_method(
+            "DELETE",
+            apigw.LambdaIntegration(account_lambda),
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # GET /audit - Changes to the caller's (or a family's) data
+        audit_resource = root.add_resource("audit")
+        audit_resource.add_method(
+            "GET",
+            apigw.LambdaIntegration(audit_lambda),
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # GET /conversations - The caller's conversations
+        conversations_integration = apigw.LambdaIntegration(conversations_lambda)
+        conversations_resource = root.add_resource("conversations")
+        conversations_resource.add_method(
+            "GET",
+            conversations_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # GET /conversations/{id}/messages - A conversation's questions and answers
+        conversation_resource = conversations_resource.add_resource("{id}")
+        conversation_messages_resource = conversation_resource.add_resource("messages")
+        conversation_messages_resource.add_method(
+            "GET",
+            conversations_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # GET /usage - The caller's token usage and estimated spend for a month
+        usage_integration = apigw.LambdaIntegration(usage_lambda)
+        usage_resource = root.add_resource("usage")
+        usage_resource.add_method(
+            "GET",
+            usage_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # GET /v1/queries/{id} - A past answer with the facts it cited
+        v1_queries_resource = v1_resource.add_resource("queries")
+        v1_query_resource = v1_queries_resource.add_resource("{id}")
+        v1_query_resource.add_method(
+            "GET",
+            usage_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /api-keys endpoints
+        api_keys_resource = root.add_resource("api-keys")
+        api_keys_integration = apigw.LambdaIntegration(api_keys_lambda)
+
+        # GET /api-keys - List the caller's API keys
+        api_keys_resource.add_method(
+            "GET",
+            api_keys_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # POST /api-keys - Issue an API key
+        api_keys_resource.add_method(
+            "POST",
+            api_keys_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # DELETE /api-keys/{keyId} - Revoke an API key
+        api_key_resource = api_keys_resource.add_resource("{keyId}")
+        api_key_resource.add_method(
+            "DELETE",
+            api_keys_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # /auth endpoints (device authorization)
+        auth_resource = root.add_resource("auth")
+        auth_integration = apigw.LambdaIntegration(auth_lambda)
+        auth_device_resource = auth_resource.add_resource("device")
+
+        # POST /auth/device/code - Start signing a device in (public)
+        auth_device_code_resource = auth_device_resource.add_resource("code")
+        auth_device_code_resource.add_method(
+            "POST",
+            auth_integration,
+            authorization_type=apigw.AuthorizationType.NONE,
+        )
+
+        # POST /auth/token - Poll for or refresh device tokens (public)
+        auth_token_resource = auth_resource.add_resource("token")
+        auth_token_resource.add_method(
+            "POST",
+            auth_integration,
+            authorization_type=apigw.AuthorizationType.NONE,
+        )
+
+        # POST /auth/device/approve - Approve or deny a device's code
+        auth_device_approve_resource = auth_device_resource.add_resource("approve")
+        auth_device_approve_resource.add_method(
+            "POST",
+            auth_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # GET /auth/devices - Signed-in devices
+        auth_devices_resource = auth_resource.add_resource("devices")
+        auth_devices_resource.add_method(
+            "GET",
+            auth_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # DELETE /auth/devices/{deviceId} - Sign a device out
+        auth_device_session_resource = auth_devices_resource.add_resource("{deviceId}")
+        auth_device_session_resource.add_method(
+            "DELETE",
+            auth_integration,
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
+        # Routes for API keys and device tokens. A method takes a single
+        # authorizer, so these skip Cognito and the Lambdas check the
+        # X-Api-Key header or device bearer token themselves.
+
+        # POST /v1/ingest - Store a fact (write keys and devices)
+        v1_ingest_resource = v1_resource.add_resource("ingest")
+        v1_ingest_resource.add_method(
+            "POST",
+            apigw.LambdaIntegration(ingest_lambda),
+            authorization_type=apigw.AuthorizationType.NONE,
+        )
+
+        # POST /v1/ingest/batch, GET /v1/ingest/batch/{id} - Import notes
+        # (write keys and devices)
+        v1_ingest_batch_resource = v1_ingest_resource.add_resource("batch")
+        v1_ingest_batch_resource.add_method(
+            "POST",
+            apigw.LambdaIntegration(ingest_lambda),
+            authorization_type=apigw.AuthorizationType.NONE,
+        )
+        v1_ingest_batch_resource.add_resource("{id}").add_method(
+            "GET",
+            apigw.LambdaIntegration(ingest_lambda),
+            authorization_type=apigw.AuthorizationType.NONE,
+        )
+
+        # GET /v1/facts/search - Full-text search (any key or device)
+        v1_facts_resource = v1_resource.add_resource("facts")
+        v1_facts_search_resource = v1_facts_resource.add_resource("search")
+        v1_facts_search_resource.add_method(
+            "GET",
+            locations_integration,
+            authorization_type=apigw.AuthorizationType.NONE,
+        )
+
+        # GET /openapi.json - The API's OpenAPI document (public, like the docs)
+        openapi_resource = root.add_resource("openapi.json")
+        openapi_resource.add_method(
+            "GET",
+            apigw.LambdaIntegration(openapi_lambda),
+        )
+
+        # POST /realtime/ticket - One-time ticket for the WebSocket API
+        realtime_resource = root.add_resource("realtime")
+        realtime_ticket_resource = realtime_resource.add_resource("ticket")
+        realtime_ticket_resource.add_method(
+            "POST",
+            apigw.LambdaIntegration(realtime_lambda),
+            authorizer=authorizer,
+            authorization_type=apigw.AuthorizationType.COGNITO,
+        )
+
         # Export API URL
         self.api_url = self.api.url
+
+        # Entity photos are indexed for face matching by the photo ingest Lambda
+        self.entity_photos_bucket = entity_photos_bucket
+
+        # Domain events are pushed to WebSocket clients by the scheduling stack
+        self.realtime_table = realtime_table
+        self.websocket_stage = websocket_stage
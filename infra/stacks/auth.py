@@ -138,6 +138,15 @@ class AuthStack(Stack):
             ),
         )
 
+        # Admin group (grants access to /admin endpoints such as diagnostics)
+        cognito.CfnUserPoolGroup(
+            self,
+            "AdminGroup",
+            user_pool_id=self.user_pool.user_pool_id,
+            group_name="admin",
+            description="Operators allowed to read diagnostics samples",
+        )
+
         # Cognito Domain
         self.domain = self.user_pool.add_domain(
             "CognitoDomain",
@@ -158,6 +158,8 @@ class DatabaseStack(Stack):
             multi_az=False,  # Cost optimization
             cloudwatch_logs_exports=["postgresql"],
             enable_performance_insights=False,  # Not available on t4g.micro
+            # Lambdas can use IAM auth tokens instead of the secret (DB_AUTH_MODE=iam)
+            iam_authentication=True,
         )
 
         # Outputs
@@ -179,3 +181,11 @@ class DatabaseStack(Stack):
             value=self.db_instance.instance_identifier,
             export_name="SecondBrainDbInstanceId",
         )
+
+    def grant_iam_connect(self, grantee: iam.IGrantable, db_user: str = "sb_lambda") -> None:
+        """Allow a Lambda to connect as `db_user` with an IAM auth token.
+
+        Pair with `DB_AUTH_MODE=iam` (and `DB_IAM_USER` for another user) in
+        the function's environment; the secret is then no longer needed.
+        """
+        self.db_instance.grant_connect(grantee, db_user)
@@ -1,4 +1,4 @@
-"""Integrations Stack for Discord, Alexa, and other external platforms."""
+"""Integrations Stack for Discord, Slack, SMS, Alexa, and other external platforms."""
 
 import os
 from aws_cdk import (
@@ -8,8 +8,12 @@ from aws_cdk import (
     aws_ec2 as ec2,
     aws_iam as iam,
     aws_lambda as lambda_,
+    aws_lambda_event_sources as lambda_event_sources,
     aws_logs as logs,
+    aws_s3 as s3,
     aws_secretsmanager as secretsmanager,
+    aws_sns as sns,
+    aws_sqs as sqs,
 )
 from constructs import Construct
 
@@ -49,6 +53,11 @@ class IntegrationsStack(Stack):
         security_group: ec2.ISecurityGroup,
         agent_function_arn: str,
         discord_secret_arn: str | None = None,
+        slack_secret_arn: str | None = None,
+        database_secret: secretsmanager.ISecret | None = None,
+        database_host: str | None = None,
+        alexa_skill_id: str | None = None,
+        internal_auth_secret: secretsmanager.ISecret | None = None,
         **kwargs,
     ) -> None:
         """Initialize the Integrations Stack.
@@ -60,10 +69,35 @@ class IntegrationsStack(Stack):
             security_group: Security group for Lambda functions.
             agent_function_arn: ARN of the agent Lambda function.
             discord_secret_arn: ARN of secret containing Discord credentials.
+            slack_secret_arn: ARN of secret containing Slack app credentials.
+            database_secret: Secret containing database credentials (enables /list).
+            database_host: Database hostname.
+            alexa_skill_id: Alexa skill ID allowed to invoke the skill Lambda.
+            internal_auth_secret: Key agent and follow-up invocations are signed with.
             **kwargs: Additional stack properties.
         """
         super().__init__(scope, id, **kwargs)
 
+        # Embedding and answer models for chat fallbacks when the agents are
+        # unavailable (shared::agents::DirectFallback)
+        fallback_model_policy = iam.PolicyStatement(
+            actions=["bedrock:InvokeModel"],
+            resources=[
+                f"arn:aws:bedrock:{self.region}::foundation-model/amazon.titan-embed-text-v2:0",
+                "arn:aws:bedrock:*::foundation-model/anthropic.claude-3-haiku-20240307-v1:0",
+                f"arn:aws:bedrock:{self.region}:{self.account}"
+                ":inference-profile/us.anthropic.claude-3-haiku-20240307-v1:0",
+            ],
+        )
+
+        def grant_internal_auth(fn: lambda_.Function) -> None:
+            """Let fn sign the invocations it makes (shared::auth::sign_internal)."""
+            if internal_auth_secret:
+                fn.add_environment(
+                    "INTERNAL_AUTH_SECRET_ARN", internal_auth_secret.secret_arn
+                )
+                internal_auth_secret.grant_read(fn)
+
         # Discord Secret (if not provided, create one)
         if discord_secret_arn:
             discord_secret = secretsmanager.Secret.from_secret_complete_arn(
@@ -89,6 +123,63 @@ class IntegrationsStack(Stack):
             retention=logs.RetentionDays.TWO_WEEKS,
         )
 
+        # Follow-up queue: deferred responses are processed from here so a failed
+        # follow-up is retried and, once exhausted, dead-lettered. maxReceiveCount
+        # must match FOLLOW_UP_MAX_RECEIVES in the Lambda.
+        follow_up_dlq = sqs.Queue(
+            self,
+            "DiscordFollowUpDLQ",
+            queue_name="second-brain-discord-follow-ups-dlq",
+            retention_period=Duration.days(14),
+        )
+
+        follow_up_queue = sqs.Queue(
+            self,
+            "DiscordFollowUpQueue",
+            queue_name="second-brain-discord-follow-ups",
+            visibility_timeout=Duration.minutes(3),
+            dead_letter_queue=sqs.DeadLetterQueue(
+                max_receive_count=3,
+                queue=follow_up_dlq,
+            ),
+        )
+
+        # Voice notes from /transcribe are staged here for Transcribe, which also
+        # writes its output here; neither is kept
+        voice_note_bucket = s3.Bucket(
+            self,
+            "DiscordVoiceNoteBucket",
+            block_public_access=s3.BlockPublicAccess.BLOCK_ALL,
+            encryption=s3.BucketEncryption.S3_MANAGED,
+            enforce_ssl=True,
+            lifecycle_rules=[
+                s3.LifecycleRule(prefix="voice-notes/", expiration=Duration.days(1)),
+            ],
+        )
+
+        # /edit and /forget go to the API stack's internal facts Lambda
+        fact_actions_function_name = "second-brain-internal_facts"
+
+        discord_env = {
+            "AGENT_FUNCTION_NAME": agent_function_arn,
+            "FACT_ACTIONS_FUNCTION_NAME": fact_actions_function_name,
+            "DISCORD_SECRET_ARN": discord_secret.secret_arn,
+            "FOLLOW_UP_QUEUE_URL": follow_up_queue.queue_url,
+            "VOICE_NOTE_BUCKET": voice_note_bucket.bucket_name,
+            "LOG_LEVEL": "INFO",
+            # Public key fetched from secret at runtime
+            "DISCORD_PUBLIC_KEY": "PLACEHOLDER_REPLACED_AT_RUNTIME",
+        }
+
+        # Direct database access for /list
+        if database_secret and database_host:
+            discord_env.update({
+                "DB_HOST": database_host,
+                "DB_PORT": "5432",
+                "DB_NAME": "second_brain",
+                "DB_SECRET_ARN": database_secret.secret_arn,
+            })
+
         # Discord Webhook Lambda
         discord_lambda = lambda_.Function(
             self,
@@ -103,14 +194,9 @@ class IntegrationsStack(Stack):
                 subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
             ),
             security_groups=[security_group],
-            environment={
-                "AGENT_FUNCTION_NAME": agent_function_arn,
-                "DISCORD_SECRET_ARN": discord_secret.secret_arn,
-                "LOG_LEVEL": "INFO",
-                # Public key fetched from secret at runtime
-                "DISCORD_PUBLIC_KEY": "PLACEHOLDER_REPLACED_AT_RUNTIME",
-            },
-            timeout=Duration.seconds(30),
+            environment=discord_env,
+            # Follow-ups wait on /transcribe jobs; interactions still answer in 3s
+            timeout=Duration.minutes(2),
             memory_size=256,
             architecture=lambda_.Architecture.ARM_64,
             log_group=discord_log_group,
@@ -125,10 +211,27 @@ class IntegrationsStack(Stack):
                     agent_function_arn,
                     # Allow Lambda to invoke itself for async follow-up processing
                     f"arn:aws:lambda:{self.region}:{self.account}:function:second-brain-discord-webhook",
+                    f"arn:aws:lambda:{self.region}:{self.account}:function:{fact_actions_function_name}",
                 ],
             )
         )
+        grant_internal_auth(discord_lambda)
+        follow_up_queue.grant_send_messages(discord_lambda)
+        # Transcribe reads the audio and writes job output with the caller's permissions
+        voice_note_bucket.grant_read_write(discord_lambda)
+        discord_lambda.add_event_source(
+            lambda_event_sources.SqsEventSource(
+                follow_up_queue,
+                batch_size=1,
+                report_batch_item_failures=True,
+            )
+        )
         discord_secret.grant_read(discord_lambda)
+        if database_secret:
+            database_secret.grant_read(discord_lambda)
+            # Answers simple questions from the database when the agents are
+            # down (shared::agents::DirectFallback)
+            discord_lambda.add_to_role_policy(fallback_model_policy)
 
         # Polly permissions for text-to-speech
         discord_lambda.add_to_role_policy(
@@ -141,7 +244,7 @@ class IntegrationsStack(Stack):
             )
         )
 
-        # Transcribe permissions for speech-to-text (future use)
+        # Transcribe permissions for /transcribe voice notes
         discord_lambda.add_to_role_policy(
             iam.PolicyStatement(
                 actions=[
@@ -153,6 +256,30 @@ class IntegrationsStack(Stack):
             )
         )
 
+        # Attach to whatever runs the Discord gateway relay (the discord_relay
+        # binary) so it can forward direct messages (MESSAGE_CREATE) to the
+        # webhook Lambda, signed with the internal auth secret
+        relay_statements = [
+            iam.PolicyStatement(
+                actions=["lambda:InvokeFunction"],
+                resources=[discord_lambda.function_arn],
+            )
+        ]
+        if internal_auth_secret:
+            relay_statements.append(
+                iam.PolicyStatement(
+                    actions=["secretsmanager:GetSecretValue"],
+                    resources=[internal_auth_secret.secret_arn],
+                )
+            )
+        self.discord_relay_policy = iam.ManagedPolicy(
+            self,
+            "DiscordRelayPolicy",
+            managed_policy_name="second-brain-discord-relay",
+            description="Forward Discord gateway events to the webhook Lambda",
+            statements=relay_statements,
+        )
+
         # API Gateway for Discord webhook
         self.discord_api = apigw.RestApi(
             self,
@@ -178,3 +305,336 @@ class IntegrationsStack(Stack):
 
         # Store the Lambda function for reference
         self.discord_lambda = discord_lambda
+
+        # Slack Secret (if not provided, create one)
+        if slack_secret_arn:
+            slack_secret = secretsmanager.Secret.from_secret_complete_arn(
+                self, "SlackSecret", slack_secret_arn
+            )
+        else:
+            slack_secret = secretsmanager.Secret(
+                self,
+                "SlackSecret",
+                secret_name="second-brain/slack",
+                description="Slack app credentials",
+                generate_secret_string=secretsmanager.SecretStringGenerator(
+                    secret_string_template='{"signing_secret":"","bot_token":""}',
+                    generate_string_key="placeholder",
+                ),
+            )
+
+        # Slack Webhook Lambda Log Group
+        slack_log_group = logs.LogGroup(
+            self,
+            "SlackWebhookLogs",
+            log_group_name="/aws/lambda/second-brain-slack-webhook",
+            retention=logs.RetentionDays.TWO_WEEKS,
+        )
+
+        slack_env = {
+            "AGENT_FUNCTION_NAME": agent_function_arn,
+            "SLACK_SECRET_ARN": slack_secret.secret_arn,
+            "LOG_LEVEL": "INFO",
+        }
+
+        # Slack users are matched to accounts in the database
+        if database_secret and database_host:
+            slack_env.update({
+                "DB_HOST": database_host,
+                "DB_PORT": "5432",
+                "DB_NAME": "second_brain",
+                "DB_SECRET_ARN": database_secret.secret_arn,
+            })
+
+        # Slack Webhook Lambda (slash commands, events and interactivity)
+        slack_lambda = lambda_.Function(
+            self,
+            "SlackWebhookLambda",
+            function_name="second-brain-slack-webhook",
+            runtime=lambda_.Runtime.PROVIDED_AL2023,
+            handler="bootstrap",
+            code=lambda_.Code.from_asset(_get_lambda_asset_path("slack_webhook")),
+            description="Handles Slack app requests",
+            vpc=vpc,
+            vpc_subnets=ec2.SubnetSelection(
+                subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
+            ),
+            security_groups=[security_group],
+            environment=slack_env,
+            timeout=Duration.seconds(30),
+            memory_size=256,
+            architecture=lambda_.Architecture.ARM_64,
+            log_group=slack_log_group,
+            tracing=lambda_.Tracing.ACTIVE,
+        )
+
+        slack_lambda.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=["lambda:InvokeFunction"],
+                resources=[
+                    agent_function_arn,
+                    # Allow Lambda to invoke itself for async follow-up processing
+                    f"arn:aws:lambda:{self.region}:{self.account}:function:second-brain-slack-webhook",
+                ],
+            )
+        )
+        grant_internal_auth(slack_lambda)
+        slack_secret.grant_read(slack_lambda)
+        if database_secret:
+            database_secret.grant_read(slack_lambda)
+            slack_lambda.add_to_role_policy(fallback_model_policy)
+
+        # API Gateway for Slack; use the one URL for slash commands, event
+        # subscriptions and interactivity
+        self.slack_api = apigw.RestApi(
+            self,
+            "SlackWebhookApi",
+            rest_api_name="second-brain-slack-webhook",
+            description="Slack app request endpoint",
+            deploy_options=apigw.StageOptions(
+                stage_name="prod",
+                throttling_rate_limit=50,
+                throttling_burst_limit=100,
+            ),
+        )
+
+        slack_resource = self.slack_api.root.add_resource("slack")
+        slack_resource.add_method(
+            "POST",
+            apigw.LambdaIntegration(slack_lambda, proxy=True),
+        )
+
+        # Export Slack request URL
+        self.slack_webhook_url = f"{self.slack_api.url}slack"
+
+        self.slack_lambda = slack_lambda
+
+        # Inbound SMS (two-way SMS on the origination number publishes texts to
+        # this topic); replies are sent from the number each text arrived on
+        if database_secret and database_host:
+            self.inbound_sms_topic = sns.Topic(
+                self,
+                "InboundSmsTopic",
+                topic_name="second-brain-inbound-sms",
+                display_name="Second Brain Inbound SMS",
+            )
+            self.inbound_sms_topic.grant_publish(
+                iam.ServicePrincipal("sms-voice.amazonaws.com")
+            )
+
+            sms_log_group = logs.LogGroup(
+                self,
+                "SmsInboundLogs",
+                log_group_name="/aws/lambda/second-brain-sms-inbound",
+                retention=logs.RetentionDays.TWO_WEEKS,
+            )
+
+            sms_lambda = lambda_.Function(
+                self,
+                "SmsInboundLambda",
+                function_name="second-brain-sms-inbound",
+                runtime=lambda_.Runtime.PROVIDED_AL2023,
+                handler="bootstrap",
+                code=lambda_.Code.from_asset(_get_lambda_asset_path("sms_inbound")),
+                description="Answers and saves texts sent to the two-way SMS number",
+                vpc=vpc,
+                vpc_subnets=ec2.SubnetSelection(
+                    subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
+                ),
+                security_groups=[security_group],
+                environment={
+                    "AGENT_FUNCTION_NAME": agent_function_arn,
+                    "DB_HOST": database_host,
+                    "DB_PORT": "5432",
+                    "DB_NAME": "second_brain",
+                    "DB_SECRET_ARN": database_secret.secret_arn,
+                    "LOG_LEVEL": "INFO",
+                },
+                timeout=Duration.minutes(1),
+                memory_size=256,
+                architecture=lambda_.Architecture.ARM_64,
+                log_group=sms_log_group,
+                tracing=lambda_.Tracing.ACTIVE,
+            )
+
+            sms_lambda.add_to_role_policy(
+                iam.PolicyStatement(
+                    actions=["lambda:InvokeFunction"],
+                    resources=[agent_function_arn],
+                )
+            )
+            grant_internal_auth(sms_lambda)
+
+            # Publishing to a phone number has no resource ARN to scope to
+            sms_lambda.add_to_role_policy(
+                iam.PolicyStatement(
+                    actions=["sns:Publish"],
+                    resources=["*"],
+                )
+            )
+            database_secret.grant_read(sms_lambda)
+
+            sms_lambda.add_event_source(
+                lambda_event_sources.SnsEventSource(self.inbound_sms_topic)
+            )
+
+            self.sms_lambda = sms_lambda
+
+        # Alexa skill: reads briefings aloud with Polly and plays the audio
+        # from presigned URLs. The audio is reused for TTS_CACHE_TTL_SECONDS
+        # (7 days), so it is kept a day longer than that
+        if database_secret and database_host:
+            alexa_audio_bucket = s3.Bucket(
+                self,
+                "AlexaAudioBucket",
+                block_public_access=s3.BlockPublicAccess.BLOCK_ALL,
+                encryption=s3.BucketEncryption.S3_MANAGED,
+                enforce_ssl=True,
+                lifecycle_rules=[
+                    s3.LifecycleRule(prefix="tts/", expiration=Duration.days(8)),
+                ],
+            )
+
+            alexa_log_group = logs.LogGroup(
+                self,
+                "AlexaSkillLogs",
+                log_group_name="/aws/lambda/second-brain-alexa-skill",
+                retention=logs.RetentionDays.TWO_WEEKS,
+            )
+
+            alexa_env = {
+                "AGENT_FUNCTION_NAME": agent_function_arn,
+                "DB_HOST": database_host,
+                "DB_PORT": "5432",
+                "DB_NAME": "second_brain",
+                "DB_SECRET_ARN": database_secret.secret_arn,
+                "TTS_AUDIO_BUCKET": alexa_audio_bucket.bucket_name,
+                "LOG_LEVEL": "INFO",
+            }
+            if alexa_skill_id:
+                alexa_env["ALEXA_SKILL_ID"] = alexa_skill_id
+
+            alexa_lambda = lambda_.Function(
+                self,
+                "AlexaSkillLambda",
+                function_name="second-brain-alexa-skill",
+                runtime=lambda_.Runtime.PROVIDED_AL2023,
+                handler="bootstrap",
+                code=lambda_.Code.from_asset(_get_lambda_asset_path("alexa_skill")),
+                description="Handles Alexa skill requests",
+                vpc=vpc,
+                vpc_subnets=ec2.SubnetSelection(
+                    subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
+                ),
+                security_groups=[security_group],
+                environment=alexa_env,
+                # Alexa waits at most 8 seconds for a response
+                timeout=Duration.seconds(8),
+                memory_size=256,
+                architecture=lambda_.Architecture.ARM_64,
+                log_group=alexa_log_group,
+                tracing=lambda_.Tracing.ACTIVE,
+            )
+
+            alexa_lambda.add_to_role_policy(
+                iam.PolicyStatement(
+                    actions=["lambda:InvokeFunction"],
+                    resources=[agent_function_arn],
+                )
+            )
+            grant_internal_auth(alexa_lambda)
+            alexa_lambda.add_to_role_policy(
+                iam.PolicyStatement(
+                    actions=["polly:SynthesizeSpeech"],
+                    resources=["*"],
+                )
+            )
+            database_secret.grant_read(alexa_lambda)
+            alexa_audio_bucket.grant_read_write(alexa_lambda)
+
+            # Only the skill may invoke the function when its ID is known
+            alexa_lambda.add_permission(
+                "AlexaSkillInvoke",
+                principal=iam.ServicePrincipal("alexa-appkit.amazon.com"),
+                action="lambda:InvokeFunction",
+                event_source_token=alexa_skill_id,
+            )
+
+            self.alexa_lambda = alexa_lambda
+
+            # Voice webhook for Google Assistant and other assistants. Tokens are
+            # checked with Cognito GetUser, which needs no IAM permission
+            voice_log_group = logs.LogGroup(
+                self,
+                "VoiceWebhookLogs",
+                log_group_name="/aws/lambda/second-brain-voice-webhook",
+                retention=logs.RetentionDays.TWO_WEEKS,
+            )
+
+            voice_lambda = lambda_.Function(
+                self,
+                "VoiceWebhookLambda",
+                function_name="second-brain-voice-webhook",
+                runtime=lambda_.Runtime.PROVIDED_AL2023,
+                handler="bootstrap",
+                code=lambda_.Code.from_asset(_get_lambda_asset_path("voice_webhook")),
+                description="Handles Google Assistant and other voice assistant webhooks",
+                vpc=vpc,
+                vpc_subnets=ec2.SubnetSelection(
+                    subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
+                ),
+                security_groups=[security_group],
+                environment={
+                    "AGENT_FUNCTION_NAME": agent_function_arn,
+                    "DB_HOST": database_host,
+                    "DB_PORT": "5432",
+                    "DB_NAME": "second_brain",
+                    "DB_SECRET_ARN": database_secret.secret_arn,
+                    "TTS_AUDIO_BUCKET": alexa_audio_bucket.bucket_name,
+                    "LOG_LEVEL": "INFO",
+                },
+                # Google Assistant waits at most 10 seconds for a webhook
+                timeout=Duration.seconds(10),
+                memory_size=256,
+                architecture=lambda_.Architecture.ARM_64,
+                log_group=voice_log_group,
+                tracing=lambda_.Tracing.ACTIVE,
+            )
+
+            voice_lambda.add_to_role_policy(
+                iam.PolicyStatement(
+                    actions=["lambda:InvokeFunction"],
+                    resources=[agent_function_arn],
+                )
+            )
+            grant_internal_auth(voice_lambda)
+            voice_lambda.add_to_role_policy(
+                iam.PolicyStatement(
+                    actions=["polly:SynthesizeSpeech"],
+                    resources=["*"],
+                )
+            )
+            voice_lambda.add_to_role_policy(fallback_model_policy)
+            database_secret.grant_read(voice_lambda)
+            alexa_audio_bucket.grant_read_write(voice_lambda)
+
+            self.voice_api = apigw.RestApi(
+                self,
+                "VoiceWebhookApi",
+                rest_api_name="second-brain-voice-webhook",
+                description="Voice assistant webhook endpoint",
+                deploy_options=apigw.StageOptions(
+                    stage_name="prod",
+                    throttling_rate_limit=20,
+                    throttling_burst_limit=40,
+                ),
+            )
+            voice_resource = self.voice_api.root.add_resource("voice")
+            voice_resource.add_method(
+                "POST",
+                apigw.LambdaIntegration(voice_lambda, proxy=True),
+            )
+
+            # Webhook URL for the Google Actions console
+            self.voice_webhook_url = f"{self.voice_api.url}voice"
+            self.voice_lambda = voice_lambda
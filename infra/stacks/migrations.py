@@ -1,66 +1,59 @@
-"""Migration Stack - Lambda for running database migrations from CI/CD."""
+"""Migration Stack - Lambda that applies database migrations on every deploy."""
 
-from pathlib import Path
-import shutil
+import os
 
 from aws_cdk import (
     Stack,
     Duration,
     CfnOutput,
-    ILocalBundling,
-    BundlingOptions,
-    DockerImage,
     aws_ec2 as ec2,
     aws_lambda as lambda_,
     aws_logs as logs,
     aws_secretsmanager as secretsmanager,
+    triggers,
 )
 from constructs import Construct
-from jsii import implements, member
 
 
-@implements(ILocalBundling)
-class LocalPythonBundling:
-    """Local bundling for Python Lambda without Docker (code only, no deps)."""
+def _get_lambda_asset_path(binary_name: str) -> str:
+    """Get the path to a Rust Lambda asset.
 
-    def __init__(self, project_root: Path):
-        self.project_root = project_root
-
-    @member(jsii_name="tryBundle")
-    def try_bundle(self, output_dir: str, options) -> bool:
-        """Bundle the Lambda code locally (without psycopg2 - comes from layer)."""
-        output_path = Path(output_dir)
-
-        # Copy handler
-        shutil.copy(
-            self.project_root / "lambdas" / "db-migrator" / "handler.py",
-            output_path / "handler.py",
-        )
+    Creates a placeholder if the built binary doesn't exist.
+    """
+    project_root = os.path.dirname(
+        os.path.dirname(os.path.dirname(os.path.abspath(__file__)))
+    )
+    target_path = os.path.join(
+        project_root, "lambdas", "target", "lambda", binary_name
+    )
 
-        # Copy migrations
-        migrations_out = output_path / "migrations"
-        migrations_out.mkdir(exist_ok=True)
-        for sql_file in (self.project_root / "migrations").glob("*.sql"):
-            shutil.copy(sql_file, migrations_out / sql_file.name)
+    # Create placeholder if needed (for synth without build)
+    if not os.path.exists(target_path):
+        os.makedirs(target_path, exist_ok=True)
+        bootstrap_path = os.path.join(target_path, "bootstrap")
+        if not os.path.exists(bootstrap_path):
+            with open(bootstrap_path, "w") as f:
+                f.write("#!/bin/bash\necho 'Placeholder - run cargo lambda build'\n")
+            os.chmod(bootstrap_path, 0o755)
 
-        return True
+    return target_path
 
 
 class MigrationsStack(Stack):
-    """Lambda for running database migrations from CI/CD pipelines.
+    """Rust Lambda that applies the migrations embedded in its binary.
+
+    The migrations are compiled into the `migrate` binary, so a deploy that
+    changes them changes the function, and the trigger below runs it before
+    the deploy completes. A failed migration fails the deploy.
 
-    Usage from CI/CD:
+    Manual usage:
         # Check migration status
-        aws lambda invoke --function-name second-brain-db-migrator \\
+        aws lambda invoke --function-name second-brain-migrate \\
             --payload '{"action": "status"}' response.json
 
         # Run all pending migrations
-        aws lambda invoke --function-name second-brain-db-migrator \\
+        aws lambda invoke --function-name second-brain-migrate \\
             --payload '{"action": "migrate"}' response.json
-
-        # Run specific migration
-        aws lambda invoke --function-name second-brain-db-migrator \\
-            --payload '{"action": "migrate", "version": "001"}' response.json
     """
 
     def __init__(
@@ -75,40 +68,21 @@ class MigrationsStack(Stack):
     ) -> None:
         super().__init__(scope, id, **kwargs)
 
-        # Project paths
-        project_root = Path(__file__).parent.parent.parent
-
-        # Lambda layer with psycopg2 for Amazon Linux 2
-        # Using Klayers - community-maintained Lambda layers
-        # https://github.com/keithrozario/Klayers
-        psycopg2_layer = lambda_.LayerVersion.from_layer_version_arn(
+        log_group = logs.LogGroup(
             self,
-            "Psycopg2Layer",
-            # Klayers psycopg2-binary for Python 3.12 in us-east-1
-            layer_version_arn="arn:aws:lambda:us-east-1:770693421928:layer:Klayers-p312-psycopg2-binary:1",
+            "MigrateLogs",
+            log_group_name="/aws/lambda/second-brain-migrate",
+            retention=logs.RetentionDays.ONE_WEEK,
         )
 
-        # Create Lambda with bundled migrations
         self.migration_function = lambda_.Function(
             self,
             "MigrationRunner",
-            function_name="second-brain-db-migrator",
-            runtime=lambda_.Runtime.PYTHON_3_12,
-            handler="handler.handler",
-            code=lambda_.Code.from_asset(
-                str(project_root),
-                bundling=BundlingOptions(
-                    image=DockerImage.from_registry("public.ecr.aws/sam/build-python3.12:latest"),
-                    local=LocalPythonBundling(project_root),
-                    command=[
-                        "bash", "-c",
-                        "cp lambdas/db-migrator/handler.py /asset-output/ && "
-                        "mkdir -p /asset-output/migrations && "
-                        "cp migrations/*.sql /asset-output/migrations/"
-                    ],
-                ),
-            ),
-            layers=[psycopg2_layer],
+            function_name="second-brain-migrate",
+            runtime=lambda_.Runtime.PROVIDED_AL2023,
+            handler="bootstrap",
+            code=lambda_.Code.from_asset(_get_lambda_asset_path("migrate")),
+            description="Applies pending database migrations",
             vpc=vpc,
             vpc_subnets=ec2.SubnetSelection(
                 subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS,
@@ -116,16 +90,29 @@ class MigrationsStack(Stack):
             security_groups=[security_group],
             timeout=Duration.minutes(5),
             memory_size=256,
+            architecture=lambda_.Architecture.ARM_64,
             environment={
                 "DB_HOST": database_host,
+                "DB_NAME": "second_brain",
                 "DB_SECRET_ARN": database_secret.secret_arn,
+                "LOG_LEVEL": "INFO",
             },
-            log_retention=logs.RetentionDays.ONE_WEEK,
+            log_group=log_group,
         )
 
         # Grant access to secrets
         database_secret.grant_read(self.migration_function)
 
+        # Run the migrations whenever the function (and so its embedded
+        # migrations) changes
+        triggers.Trigger(
+            self,
+            "RunMigrations",
+            handler=self.migration_function,
+            execute_on_handler_change=True,
+            timeout=Duration.minutes(5),
+        )
+
         # Outputs
         CfnOutput(
             self,
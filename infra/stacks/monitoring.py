@@ -10,6 +10,9 @@ from aws_cdk import (
 )
 from constructs import Construct
 
+# Namespace of the EMF metrics written by the Rust Lambdas (shared::metrics)
+APP_METRICS_NAMESPACE = "SecondBrain"
+
 
 class MonitoringStack(Stack):
     """Stack containing CloudWatch dashboards, metrics, and alarms."""
@@ -346,6 +349,105 @@ class MonitoringStack(Stack):
             )
             fn_alarm.add_alarm_action(cw_actions.SnsAction(self.alarm_topic))
 
+        # =====================================================
+        # Application Metrics (EMF records written by the Lambdas)
+        # =====================================================
+        def app_metric_search(metric_name: str, statistic: str, by_operation: bool = False):
+            dimensions = "Service,Operation" if by_operation else "Service"
+            return cloudwatch.MathExpression(
+                expression=(
+                    f"SEARCH('{{{APP_METRICS_NAMESPACE},{dimensions}}} "
+                    f"MetricName=\"{metric_name}\"', '{statistic}', 300)"
+                ),
+                label=metric_name,
+                period=Duration.minutes(5),
+            )
+
+        self.dashboard.add_widgets(
+            cloudwatch.TextWidget(
+                markdown="## Application Metrics\n*Per Lambda (`Service`) and route or intent (`Operation`)*",
+                width=24,
+                height=1,
+            ),
+            cloudwatch.GraphWidget(
+                title="Requests by Service",
+                left=[app_metric_search("Requests", "Sum")],
+                width=8,
+                height=6,
+            ),
+            cloudwatch.GraphWidget(
+                title="Latency by Service (p90 ms)",
+                left=[app_metric_search("Latency", "p90")],
+                width=8,
+                height=6,
+            ),
+            cloudwatch.GraphWidget(
+                title="Errors by Service",
+                left=[app_metric_search("Errors", "Sum")],
+                width=8,
+                height=6,
+            ),
+            cloudwatch.GraphWidget(
+                title="DB Query Time (avg ms)",
+                left=[app_metric_search("DbQueryTime", "Average", by_operation=True)],
+                width=8,
+                height=6,
+            ),
+            cloudwatch.GraphWidget(
+                title="Agent Latency by Intent (p90 ms)",
+                left=[app_metric_search("AgentLatency", "p90", by_operation=True)],
+                width=8,
+                height=6,
+            ),
+            cloudwatch.GraphWidget(
+                title="Notification Deliveries by Channel",
+                left=[app_metric_search("NotificationsDelivered", "Sum", by_operation=True)],
+                right=[app_metric_search("NotificationsFailed", "Sum", by_operation=True)],
+                width=8,
+                height=6,
+            ),
+        )
+
+        # Agent failures seen by the query Lambda
+        agent_error_alarm = cloudwatch.Alarm(
+            self,
+            "AgentErrorAlarm",
+            alarm_name="SecondBrain-Agents-Errors",
+            metric=cloudwatch.Metric(
+                namespace=APP_METRICS_NAMESPACE,
+                metric_name="AgentErrors",
+                dimensions_map={"Service": "second-brain-query"},
+                statistic="Sum",
+                period=Duration.minutes(5),
+            ),
+            threshold=5,
+            evaluation_periods=2,
+            comparison_operator=cloudwatch.ComparisonOperator.GREATER_THAN_THRESHOLD,
+            treat_missing_data=cloudwatch.TreatMissingData.NOT_BREACHING,
+            alarm_description="Queries are failing to get answers from the agents",
+        )
+        agent_error_alarm.add_alarm_action(cw_actions.SnsAction(self.alarm_topic))
+
+        # Notification delivery failures across all channels
+        delivery_alarm = cloudwatch.Alarm(
+            self,
+            "NotificationFailureAlarm",
+            alarm_name="SecondBrain-Notifications-DeliveryFailures",
+            metric=cloudwatch.Metric(
+                namespace=APP_METRICS_NAMESPACE,
+                metric_name="NotificationsFailed",
+                dimensions_map={"Service": "second-brain-notification-sender"},
+                statistic="Sum",
+                period=Duration.minutes(5),
+            ),
+            threshold=10,
+            evaluation_periods=2,
+            comparison_operator=cloudwatch.ComparisonOperator.GREATER_THAN_THRESHOLD,
+            treat_missing_data=cloudwatch.TreatMissingData.NOT_BREACHING,
+            alarm_description="More than 10 notifications failed to deliver in 5 minutes",
+        )
+        delivery_alarm.add_alarm_action(cw_actions.SnsAction(self.alarm_topic))
+
         # =====================================================
         # Custom Metrics Widget for Business KPIs
         # =====================================================
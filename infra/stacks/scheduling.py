@@ -4,6 +4,8 @@ import os
 from aws_cdk import (
     Duration,
     Stack,
+    aws_apigatewayv2 as apigwv2,
+    aws_dynamodb as dynamodb,
     aws_ec2 as ec2,
     aws_events as events,
     aws_events_targets as targets,
@@ -11,8 +13,15 @@ from aws_cdk import (
     aws_lambda as lambda_,
     aws_lambda_event_sources as lambda_event_sources,
     aws_logs as logs,
+    aws_rekognition as rekognition,
+    aws_s3 as s3,
+    aws_s3_notifications as s3n,
     aws_secretsmanager as secretsmanager,
+    aws_ses as ses,
+    aws_ses_actions as ses_actions,
     aws_sns as sns,
+    aws_sns_subscriptions as sns_subs,
+    aws_sqs as sqs,
 )
 from constructs import Construct
 
@@ -56,6 +65,16 @@ class SchedulingStack(Stack):
         google_oauth_secret_arn: str | None = None,
         discord_webhook_secret_arn: str | None = None,
         from_email: str = "noreply@secondbrain.app",
+        app_url: str = "https://secondbrain.app",
+        inbound_email_address: str | None = None,
+        push_secret_arn: str | None = None,
+        alexa_secret_arn: str | None = None,
+        entity_photos_bucket: s3.IBucket | None = None,
+        place_index_name: str | None = None,
+        route_calculator_name: str | None = None,
+        realtime_table: dynamodb.ITable | None = None,
+        websocket_stage: apigwv2.WebSocketStage | None = None,
+        internal_auth_secret: secretsmanager.ISecret | None = None,
         **kwargs,
     ) -> None:
         """Initialize the Scheduling Stack.
@@ -71,10 +90,28 @@ class SchedulingStack(Stack):
             google_oauth_secret_arn: ARN of Google OAuth credentials secret.
             discord_webhook_secret_arn: ARN of Discord webhook secret.
             from_email: Email address for sending notifications.
+            app_url: Web app URL that emails link back to.
+            inbound_email_address: Address users email facts to (enables email ingestion).
+            push_secret_arn: ARN of FCM/APNs push credentials secret.
+            alexa_secret_arn: ARN of Alexa skill client credentials (enables Alexa notifications).
+            entity_photos_bucket: Entity photos bucket (enables face matching on photos).
+            place_index_name: Amazon Location place index for reverse geocoding photos.
+            route_calculator_name: Amazon Location route calculator for reminders to leave in time.
+            realtime_table: WebSocket connections table (enables real-time updates).
+            websocket_stage: WebSocket API stage events are pushed through.
+            internal_auth_secret: Key agent invocations are signed with.
             **kwargs: Additional stack properties.
         """
         super().__init__(scope, id, **kwargs)
 
+        def grant_internal_auth(fn: lambda_.Function) -> None:
+            """Let fn sign the invocations it makes (shared::auth::sign_internal)."""
+            if internal_auth_secret:
+                fn.add_environment(
+                    "INTERNAL_AUTH_SECRET_ARN", internal_auth_secret.secret_arn
+                )
+                internal_auth_secret.grant_read(fn)
+
         # SNS Topic for notifications
         self.notification_topic = sns.Topic(
             self,
@@ -83,6 +120,13 @@ class SchedulingStack(Stack):
             display_name="Second Brain Notifications",
         )
 
+        # Custom event bus for domain events (see docs/design/domain-events.md)
+        self.event_bus = events.EventBus(
+            self,
+            "DomainEventBus",
+            event_bus_name="second-brain-events",
+        )
+
         # Google OAuth secret
         if google_oauth_secret_arn:
             google_secret = secretsmanager.Secret.from_secret_complete_arn(
@@ -115,7 +159,7 @@ class SchedulingStack(Stack):
             runtime=lambda_.Runtime.PROVIDED_AL2023,
             handler="bootstrap",
             code=lambda_.Code.from_asset(_get_lambda_asset_path("calendar_sync")),
-            description="Syncs external calendars (Google, Outlook)",
+            description="Syncs external calendars (Google, Outlook, iCal feeds)",
             vpc=vpc,
             vpc_subnets=ec2.SubnetSelection(
                 subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
@@ -218,6 +262,7 @@ class SchedulingStack(Stack):
                     resources=[agent_function_arn],
                 )
             )
+            grant_internal_auth(briefing_dispatcher_lambda)
 
         # EventBridge rule for morning briefings (6 AM ET daily)
         briefing_rule = events.Rule(
@@ -239,6 +284,19 @@ class SchedulingStack(Stack):
         )
 
         # Reminder Evaluator Lambda
+        reminder_evaluator_env = {
+            "DB_HOST": database_host,
+            "DB_PORT": "5432",
+            "DB_NAME": "second_brain",
+            "DB_SECRET_ARN": database_secret.secret_arn,
+            "NOTIFICATION_TOPIC_ARN": self.notification_topic.topic_arn,
+            "EVENT_BUS_NAME": self.event_bus.event_bus_name,
+            "LOG_LEVEL": "INFO",
+        }
+
+        if route_calculator_name:
+            reminder_evaluator_env["ROUTE_CALCULATOR_NAME"] = route_calculator_name
+
         reminder_evaluator_log_group = logs.LogGroup(
             self,
             "ReminderEvaluatorLogs",
@@ -259,14 +317,7 @@ class SchedulingStack(Stack):
                 subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
             ),
             security_groups=[security_group],
-            environment={
-                "DB_HOST": database_host,
-                "DB_PORT": "5432",
-                "DB_NAME": "second_brain",
-                "DB_SECRET_ARN": database_secret.secret_arn,
-                "NOTIFICATION_TOPIC_ARN": self.notification_topic.topic_arn,
-                "LOG_LEVEL": "INFO",
-            },
+            environment=reminder_evaluator_env,
             timeout=Duration.minutes(2),
             memory_size=256,
             architecture=lambda_.Architecture.ARM_64,
@@ -275,8 +326,19 @@ class SchedulingStack(Stack):
 
         database_secret.grant_read(reminder_evaluator_lambda)
 
-        # Grant permission to publish to notification topic
+        # Grant permission to publish to notification topic and event bus
         self.notification_topic.grant_publish(reminder_evaluator_lambda)
+        self.event_bus.grant_put_events_to(reminder_evaluator_lambda)
+
+        if route_calculator_name:
+            reminder_evaluator_lambda.add_to_role_policy(
+                iam.PolicyStatement(
+                    actions=["geo:CalculateRoute"],
+                    resources=[
+                        f"arn:aws:geo:{self.region}:{self.account}:route-calculator/{route_calculator_name}"
+                    ],
+                )
+            )
 
         # EventBridge rule for reminder evaluation (every 5 minutes)
         reminder_rule = events.Rule(
@@ -305,6 +367,7 @@ class SchedulingStack(Stack):
             "DB_NAME": "second_brain",
             "DB_SECRET_ARN": database_secret.secret_arn,
             "FROM_EMAIL": from_email,
+            "EVENT_BUS_NAME": self.event_bus.event_bus_name,
             "LOG_LEVEL": "INFO",
         }
 
@@ -314,6 +377,34 @@ class SchedulingStack(Stack):
                 self, "DiscordWebhookSecret", discord_webhook_secret_arn
             )
 
+        # Push credentials: {"fcm": <service account key>, "apns": {"key_id", "team_id",
+        # "private_key", "bundle_id", "sandbox"}}; either may be null
+        if push_secret_arn:
+            push_secret = secretsmanager.Secret.from_secret_complete_arn(
+                self, "PushSecret", push_secret_arn
+            )
+        else:
+            push_secret = secretsmanager.Secret(
+                self,
+                "PushSecret",
+                secret_name="second-brain/push",
+                description="FCM and APNs push notification credentials",
+                generate_secret_string=secretsmanager.SecretStringGenerator(
+                    secret_string_template='{"fcm":null,"apns":null}',
+                    generate_string_key="placeholder",
+                ),
+            )
+        notification_sender_env["PUSH_SECRET_ARN"] = push_secret.secret_arn
+
+        # Alexa skill credentials for proactive events: {"client_id", "client_secret",
+        # "development"}; from the skill's Permissions page in the developer console
+        alexa_secret = None
+        if alexa_secret_arn:
+            alexa_secret = secretsmanager.Secret.from_secret_complete_arn(
+                self, "AlexaSecret", alexa_secret_arn
+            )
+            notification_sender_env["ALEXA_SECRET_ARN"] = alexa_secret.secret_arn
+
         notification_sender_lambda = lambda_.Function(
             self,
             "NotificationSenderLambda",
@@ -321,7 +412,7 @@ class SchedulingStack(Stack):
             runtime=lambda_.Runtime.PROVIDED_AL2023,
             handler="bootstrap",
             code=lambda_.Code.from_asset(_get_lambda_asset_path("notification_sender")),
-            description="Sends notifications via email, push, or Discord",
+            description="Sends notifications via email, push, Discord, or Alexa",
             vpc=vpc,
             vpc_subnets=ec2.SubnetSelection(
                 subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
@@ -335,6 +426,10 @@ class SchedulingStack(Stack):
         )
 
         database_secret.grant_read(notification_sender_lambda)
+        push_secret.grant_read(notification_sender_lambda)
+        if alexa_secret:
+            alexa_secret.grant_read(notification_sender_lambda)
+        self.event_bus.grant_put_events_to(notification_sender_lambda)
 
         # SES permissions for sending emails
         notification_sender_lambda.add_to_role_policy(
@@ -349,8 +444,1222 @@ class SchedulingStack(Stack):
             lambda_event_sources.SnsEventSource(self.notification_topic)
         )
 
+        # Digest Builder Lambda
+        # Low-priority notifications held by the sender are batched into
+        # morning/evening digests at each user's briefing times.
+        digest_builder_log_group = logs.LogGroup(
+            self,
+            "DigestBuilderLogs",
+            log_group_name="/aws/lambda/second-brain-digest-builder",
+            retention=logs.RetentionDays.ONE_WEEK,
+        )
+
+        digest_builder_lambda = lambda_.Function(
+            self,
+            "DigestBuilderLambda",
+            function_name="second-brain-digest-builder",
+            runtime=lambda_.Runtime.PROVIDED_AL2023,
+            handler="bootstrap",
+            code=lambda_.Code.from_asset(_get_lambda_asset_path("digest_builder")),
+            description="Batches low-priority notifications into digests",
+            vpc=vpc,
+            vpc_subnets=ec2.SubnetSelection(
+                subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
+            ),
+            security_groups=[security_group],
+            environment={
+                "DB_HOST": database_host,
+                "DB_PORT": "5432",
+                "DB_NAME": "second_brain",
+                "DB_SECRET_ARN": database_secret.secret_arn,
+                "NOTIFICATION_TOPIC_ARN": self.notification_topic.topic_arn,
+                "LOG_LEVEL": "INFO",
+            },
+            timeout=Duration.minutes(2),
+            memory_size=256,
+            architecture=lambda_.Architecture.ARM_64,
+            log_group=digest_builder_log_group,
+        )
+
+        database_secret.grant_read(digest_builder_lambda)
+        self.notification_topic.grant_publish(digest_builder_lambda)
+
+        # EventBridge rule for digests (hourly, matched to each user's local time)
+        digest_rule = events.Rule(
+            self,
+            "DigestBuilderSchedule",
+            rule_name="second-brain-digest-builder",
+            description="Builds notification digests every hour",
+            schedule=events.Schedule.cron(minute="0"),
+        )
+
+        digest_rule.add_target(
+            targets.LambdaFunction(digest_builder_lambda)
+        )
+
+        # Weekly Review Lambda
+        # Emails each user a summary of their week (facts, entities, completed
+        # reminders, with an agent-written narrative) on their review day.
+        weekly_review_log_group = logs.LogGroup(
+            self,
+            "WeeklyReviewLogs",
+            log_group_name="/aws/lambda/second-brain-weekly-review",
+            retention=logs.RetentionDays.ONE_WEEK,
+        )
+
+        weekly_review_env = {
+            "DB_HOST": database_host,
+            "DB_PORT": "5432",
+            "DB_NAME": "second_brain",
+            "DB_SECRET_ARN": database_secret.secret_arn,
+            "FROM_EMAIL": from_email,
+            "APP_URL": app_url,
+            "LOG_LEVEL": "INFO",
+        }
+
+        if agent_function_arn:
+            weekly_review_env["AGENT_FUNCTION_NAME"] = agent_function_arn
+
+        weekly_review_lambda = lambda_.Function(
+            self,
+            "WeeklyReviewLambda",
+            function_name="second-brain-weekly-review",
+            runtime=lambda_.Runtime.PROVIDED_AL2023,
+            handler="bootstrap",
+            code=lambda_.Code.from_asset(_get_lambda_asset_path("weekly_review")),
+            description="Emails weekly review summaries to users",
+            vpc=vpc,
+            vpc_subnets=ec2.SubnetSelection(
+                subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
+            ),
+            security_groups=[security_group],
+            environment=weekly_review_env,
+            timeout=Duration.minutes(5),
+            memory_size=256,
+            architecture=lambda_.Architecture.ARM_64,
+            log_group=weekly_review_log_group,
+        )
+
+        database_secret.grant_read(weekly_review_lambda)
+
+        weekly_review_lambda.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=["ses:SendEmail", "ses:SendRawEmail"],
+                resources=["*"],
+            )
+        )
+
+        if agent_function_arn:
+            weekly_review_lambda.add_to_role_policy(
+                iam.PolicyStatement(
+                    actions=["lambda:InvokeFunction"],
+                    resources=[agent_function_arn],
+                )
+            )
+            grant_internal_auth(weekly_review_lambda)
+
+        # EventBridge rule for weekly reviews (hourly, matched to each user's
+        # review day and local time)
+        weekly_review_rule = events.Rule(
+            self,
+            "WeeklyReviewSchedule",
+            rule_name="second-brain-weekly-review",
+            description="Sends due weekly reviews every hour",
+            schedule=events.Schedule.cron(minute="0"),
+        )
+
+        weekly_review_rule.add_target(
+            targets.LambdaFunction(weekly_review_lambda)
+        )
+
+        # Occasion Scanner Lambda
+        # Birthday/anniversary entity attributes become yearly reminders a few
+        # days ahead of each date.
+        occasion_scanner_log_group = logs.LogGroup(
+            self,
+            "OccasionScannerLogs",
+            log_group_name="/aws/lambda/second-brain-occasion-scanner",
+            retention=logs.RetentionDays.ONE_WEEK,
+        )
+
+        occasion_scanner_lambda = lambda_.Function(
+            self,
+            "OccasionScannerLambda",
+            function_name="second-brain-occasion-scanner",
+            runtime=lambda_.Runtime.PROVIDED_AL2023,
+            handler="bootstrap",
+            code=lambda_.Code.from_asset(_get_lambda_asset_path("occasion_scanner")),
+            description="Creates reminders for birthdays and anniversaries",
+            vpc=vpc,
+            vpc_subnets=ec2.SubnetSelection(
+                subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
+            ),
+            security_groups=[security_group],
+            environment={
+                "DB_HOST": database_host,
+                "DB_PORT": "5432",
+                "DB_NAME": "second_brain",
+                "DB_SECRET_ARN": database_secret.secret_arn,
+                "LOG_LEVEL": "INFO",
+            },
+            timeout=Duration.minutes(5),
+            memory_size=256,
+            architecture=lambda_.Architecture.ARM_64,
+            log_group=occasion_scanner_log_group,
+        )
+
+        database_secret.grant_read(occasion_scanner_lambda)
+
+        # EventBridge rule for the occasion scan (daily)
+        occasion_scanner_rule = events.Rule(
+            self,
+            "OccasionScannerSchedule",
+            rule_name="second-brain-occasion-scanner",
+            description="Scans entities for birthdays and anniversaries daily",
+            schedule=events.Schedule.cron(minute="0", hour="6"),
+        )
+
+        occasion_scanner_rule.add_target(
+            targets.LambdaFunction(occasion_scanner_lambda)
+        )
+
+        # Contacts Sync Lambda
+        contacts_sync_log_group = logs.LogGroup(
+            self,
+            "ContactsSyncLogs",
+            log_group_name="/aws/lambda/second-brain-contacts-sync",
+            retention=logs.RetentionDays.ONE_WEEK,
+        )
+
+        contacts_sync_lambda = lambda_.Function(
+            self,
+            "ContactsSyncLambda",
+            function_name="second-brain-contacts-sync",
+            runtime=lambda_.Runtime.PROVIDED_AL2023,
+            handler="bootstrap",
+            code=lambda_.Code.from_asset(_get_lambda_asset_path("contacts_sync")),
+            description="Syncs Google Contacts into person entities",
+            vpc=vpc,
+            vpc_subnets=ec2.SubnetSelection(
+                subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
+            ),
+            security_groups=[security_group],
+            environment={
+                "DB_HOST": database_host,
+                "DB_PORT": "5432",
+                "DB_NAME": "second_brain",
+                "DB_SECRET_ARN": database_secret.secret_arn,
+                "GOOGLE_OAUTH_SECRET_ARN": google_secret.secret_arn,
+                "LOG_LEVEL": "INFO",
+            },
+            timeout=Duration.minutes(5),
+            memory_size=512,
+            architecture=lambda_.Architecture.ARM_64,
+            log_group=contacts_sync_log_group,
+        )
+
+        database_secret.grant_read(contacts_sync_lambda)
+        google_secret.grant_read(contacts_sync_lambda)
+
+        # Permission to read user contacts secrets
+        contacts_sync_lambda.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=["secretsmanager:GetSecretValue"],
+                resources=[
+                    f"arn:aws:secretsmanager:{Stack.of(self).region}:{Stack.of(self).account}:secret:second-brain/contacts/*",
+                ],
+            )
+        )
+
+        # EventBridge rule for contacts sync (hourly)
+        contacts_sync_rule = events.Rule(
+            self,
+            "ContactsSyncSchedule",
+            rule_name="second-brain-contacts-sync",
+            description="Triggers Google Contacts sync every hour",
+            schedule=events.Schedule.rate(Duration.hours(1)),
+        )
+
+        contacts_sync_rule.add_target(
+            targets.LambdaFunction(contacts_sync_lambda)
+        )
+
+        # Tag Rule Backfill Lambda
+        # Applies auto-tagging rules to facts created since each rule last ran;
+        # a new rule's first run covers the user's whole history.
+        tag_rule_backfill_log_group = logs.LogGroup(
+            self,
+            "TagRuleBackfillLogs",
+            log_group_name="/aws/lambda/second-brain-tag-rule-backfill",
+            retention=logs.RetentionDays.ONE_WEEK,
+        )
+
+        tag_rule_backfill_lambda = lambda_.Function(
+            self,
+            "TagRuleBackfillLambda",
+            function_name="second-brain-tag-rule-backfill",
+            runtime=lambda_.Runtime.PROVIDED_AL2023,
+            handler="bootstrap",
+            code=lambda_.Code.from_asset(_get_lambda_asset_path("tag_rule_backfill")),
+            description="Applies auto-tagging rules to existing facts",
+            vpc=vpc,
+            vpc_subnets=ec2.SubnetSelection(
+                subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
+            ),
+            security_groups=[security_group],
+            environment={
+                "DB_HOST": database_host,
+                "DB_PORT": "5432",
+                "DB_NAME": "second_brain",
+                "DB_SECRET_ARN": database_secret.secret_arn,
+                "LOG_LEVEL": "INFO",
+            },
+            timeout=Duration.minutes(5),
+            memory_size=256,
+            architecture=lambda_.Architecture.ARM_64,
+            log_group=tag_rule_backfill_log_group,
+        )
+
+        database_secret.grant_read(tag_rule_backfill_lambda)
+
+        # EventBridge rule for the tag rule backfill (every 15 minutes)
+        tag_rule_backfill_rule = events.Rule(
+            self,
+            "TagRuleBackfillSchedule",
+            rule_name="second-brain-tag-rule-backfill",
+            description="Applies auto-tagging rules to new and historical facts",
+            schedule=events.Schedule.rate(Duration.minutes(15)),
+        )
+
+        tag_rule_backfill_rule.add_target(
+            targets.LambdaFunction(tag_rule_backfill_lambda)
+        )
+
+        # Embedding Indexer Lambda
+        # FactCreated and FactUpdated events queue the fact for embedding; the
+        # sweep indexes anything the queue missed (facts changed by the agents,
+        # which don't publish events) and retries facts that failed before.
+        embedding_dlq = sqs.Queue(
+            self,
+            "EmbeddingIndexerDLQ",
+            queue_name="second-brain-embedding-indexer-dlq",
+            retention_period=Duration.days(14),
+        )
+
+        embedding_queue = sqs.Queue(
+            self,
+            "EmbeddingIndexerQueue",
+            queue_name="second-brain-embedding-indexer",
+            visibility_timeout=Duration.minutes(6),
+            dead_letter_queue=sqs.DeadLetterQueue(
+                max_receive_count=5,
+                queue=embedding_dlq,
+            ),
+        )
+
+        embedding_indexer_log_group = logs.LogGroup(
+            self,
+            "EmbeddingIndexerLogs",
+            log_group_name="/aws/lambda/second-brain-embedding-indexer",
+            retention=logs.RetentionDays.ONE_WEEK,
+        )
+
+        embedding_indexer_lambda = lambda_.Function(
+            self,
+            "EmbeddingIndexerLambda",
+            function_name="second-brain-embedding-indexer",
+            runtime=lambda_.Runtime.PROVIDED_AL2023,
+            handler="bootstrap",
+            code=lambda_.Code.from_asset(_get_lambda_asset_path("embedding_indexer")),
+            description="Generates vector embeddings for new and edited facts",
+            vpc=vpc,
+            vpc_subnets=ec2.SubnetSelection(
+                subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
+            ),
+            security_groups=[security_group],
+            environment={
+                "DB_HOST": database_host,
+                "DB_PORT": "5432",
+                "DB_NAME": "second_brain",
+                "DB_SECRET_ARN": database_secret.secret_arn,
+                "EMBEDDING_MODEL_ID": "amazon.titan-embed-text-v2:0",
+                "LOG_LEVEL": "INFO",
+            },
+            timeout=Duration.minutes(5),
+            memory_size=256,
+            architecture=lambda_.Architecture.ARM_64,
+            log_group=embedding_indexer_log_group,
+        )
+
+        database_secret.grant_read(embedding_indexer_lambda)
+
+        embedding_indexer_lambda.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=["bedrock:InvokeModel"],
+                resources=[
+                    f"arn:aws:bedrock:{Stack.of(self).region}::foundation-model/amazon.titan-embed-text-v2:0",
+                ],
+            )
+        )
+
+        embedding_indexer_lambda.add_event_source(
+            lambda_event_sources.SqsEventSource(
+                embedding_queue,
+                batch_size=10,
+                report_batch_item_failures=True,
+            )
+        )
+
+        embedding_events_rule = events.Rule(
+            self,
+            "EmbeddingIndexerEvents",
+            rule_name="second-brain-embedding-indexer-events",
+            description="Queues new and edited facts for embedding",
+            event_bus=self.event_bus,
+            event_pattern=events.EventPattern(
+                source=["second-brain"],
+                detail_type=["FactCreated", "FactUpdated"],
+                detail={"schema_version": [1]},
+            ),
+        )
+
+        embedding_events_rule.add_target(
+            targets.SqsQueue(
+                embedding_queue,
+                message=events.RuleTargetInput.from_object(
+                    {"fact_id": events.EventField.from_path("$.detail.data.fact_id")}
+                ),
+            )
+        )
+
+        # EventBridge rule for the embedding sweep (every 15 minutes)
+        embedding_sweep_rule = events.Rule(
+            self,
+            "EmbeddingIndexerSchedule",
+            rule_name="second-brain-embedding-indexer-sweep",
+            description="Indexes facts with missing or stale embeddings",
+            schedule=events.Schedule.rate(Duration.minutes(15)),
+        )
+
+        embedding_sweep_rule.add_target(
+            targets.LambdaFunction(embedding_indexer_lambda)
+        )
+
+        # Trash Purge Lambda
+        # Permanently removes facts, entities and tags deleted over 30 days ago.
+        trash_purge_log_group = logs.LogGroup(
+            self,
+            "TrashPurgeLogs",
+            log_group_name="/aws/lambda/second-brain-trash-purge",
+            retention=logs.RetentionDays.ONE_WEEK,
+        )
+
+        trash_purge_lambda = lambda_.Function(
+            self,
+            "TrashPurgeLambda",
+            function_name="second-brain-trash-purge",
+            runtime=lambda_.Runtime.PROVIDED_AL2023,
+            handler="bootstrap",
+            code=lambda_.Code.from_asset(_get_lambda_asset_path("trash_purge")),
+            description="Purges trashed facts, entities and tags after 30 days",
+            vpc=vpc,
+            vpc_subnets=ec2.SubnetSelection(
+                subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
+            ),
+            security_groups=[security_group],
+            environment={
+                "DB_HOST": database_host,
+                "DB_PORT": "5432",
+                "DB_NAME": "second_brain",
+                "DB_SECRET_ARN": database_secret.secret_arn,
+                "LOG_LEVEL": "INFO",
+            },
+            timeout=Duration.minutes(5),
+            memory_size=256,
+            architecture=lambda_.Architecture.ARM_64,
+            log_group=trash_purge_log_group,
+        )
+
+        database_secret.grant_read(trash_purge_lambda)
+
+        # EventBridge rule for the trash purge (daily at 8 AM UTC)
+        trash_purge_rule = events.Rule(
+            self,
+            "TrashPurgeSchedule",
+            rule_name="second-brain-trash-purge",
+            description="Purges trash items past their 30-day retention",
+            schedule=events.Schedule.cron(minute="0", hour="8"),
+        )
+
+        trash_purge_rule.add_target(
+            targets.LambdaFunction(trash_purge_lambda)
+        )
+
+        # Importance Decay Lambda
+        # Lowers the importance of facts nobody has reviewed in six months.
+        importance_decay_log_group = logs.LogGroup(
+            self,
+            "ImportanceDecayLogs",
+            log_group_name="/aws/lambda/second-brain-importance-decay",
+            retention=logs.RetentionDays.ONE_WEEK,
+        )
+
+        importance_decay_lambda = lambda_.Function(
+            self,
+            "ImportanceDecayLambda",
+            function_name="second-brain-importance-decay",
+            runtime=lambda_.Runtime.PROVIDED_AL2023,
+            handler="bootstrap",
+            code=lambda_.Code.from_asset(_get_lambda_asset_path("importance_decay")),
+            description="Decays the importance of stale facts",
+            vpc=vpc,
+            vpc_subnets=ec2.SubnetSelection(
+                subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
+            ),
+            security_groups=[security_group],
+            environment={
+                "DB_HOST": database_host,
+                "DB_PORT": "5432",
+                "DB_NAME": "second_brain",
+                "DB_SECRET_ARN": database_secret.secret_arn,
+                "LOG_LEVEL": "INFO",
+            },
+            timeout=Duration.minutes(5),
+            memory_size=256,
+            architecture=lambda_.Architecture.ARM_64,
+            log_group=importance_decay_log_group,
+        )
+
+        database_secret.grant_read(importance_decay_lambda)
+
+        # EventBridge rule for importance decay (Sundays at 9 AM UTC)
+        importance_decay_rule = events.Rule(
+            self,
+            "ImportanceDecaySchedule",
+            rule_name="second-brain-importance-decay",
+            description="Weekly importance decay for stale facts",
+            schedule=events.Schedule.cron(minute="0", hour="9", week_day="SUN"),
+        )
+
+        importance_decay_rule.add_target(
+            targets.LambdaFunction(importance_decay_lambda)
+        )
+
+        # Access Grant Sweeper Lambda
+        # Deletes access grants 30 days after they expire or are revoked.
+        access_grant_sweeper_log_group = logs.LogGroup(
+            self,
+            "AccessGrantSweeperLogs",
+            log_group_name="/aws/lambda/second-brain-access-grant-sweeper",
+            retention=logs.RetentionDays.ONE_WEEK,
+        )
+
+        access_grant_sweeper_lambda = lambda_.Function(
+            self,
+            "AccessGrantSweeperLambda",
+            function_name="second-brain-access-grant-sweeper",
+            runtime=lambda_.Runtime.PROVIDED_AL2023,
+            handler="bootstrap",
+            code=lambda_.Code.from_asset(_get_lambda_asset_path("access_grant_sweeper")),
+            description="Deletes access grants that ended over 30 days ago",
+            vpc=vpc,
+            vpc_subnets=ec2.SubnetSelection(
+                subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
+            ),
+            security_groups=[security_group],
+            environment={
+                "DB_HOST": database_host,
+                "DB_PORT": "5432",
+                "DB_NAME": "second_brain",
+                "DB_SECRET_ARN": database_secret.secret_arn,
+                "LOG_LEVEL": "INFO",
+            },
+            timeout=Duration.minutes(5),
+            memory_size=256,
+            architecture=lambda_.Architecture.ARM_64,
+            log_group=access_grant_sweeper_log_group,
+        )
+
+        database_secret.grant_read(access_grant_sweeper_lambda)
+
+        # EventBridge rule for the access grant sweep (daily at 8:30 AM UTC)
+        access_grant_sweeper_rule = events.Rule(
+            self,
+            "AccessGrantSweeperSchedule",
+            rule_name="second-brain-access-grant-sweeper",
+            description="Deletes access grants past their 30-day history",
+            schedule=events.Schedule.cron(minute="30", hour="8"),
+        )
+
+        access_grant_sweeper_rule.add_target(
+            targets.LambdaFunction(access_grant_sweeper_lambda)
+        )
+
+        # Visit Detector Lambda
+        # Clusters location pings into visits, records visit facts and deletes
+        # location history past each user's retention period.
+        visit_detector_log_group = logs.LogGroup(
+            self,
+            "VisitDetectorLogs",
+            log_group_name="/aws/lambda/second-brain-visit-detector",
+            retention=logs.RetentionDays.ONE_WEEK,
+        )
+
+        visit_detector_lambda = lambda_.Function(
+            self,
+            "VisitDetectorLambda",
+            function_name="second-brain-visit-detector",
+            runtime=lambda_.Runtime.PROVIDED_AL2023,
+            handler="bootstrap",
+            code=lambda_.Code.from_asset(_get_lambda_asset_path("visit_detector")),
+            description="Detects visits in location pings and applies location history retention",
+            vpc=vpc,
+            vpc_subnets=ec2.SubnetSelection(
+                subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
+            ),
+            security_groups=[security_group],
+            environment={
+                "DB_HOST": database_host,
+                "DB_PORT": "5432",
+                "DB_NAME": "second_brain",
+                "DB_SECRET_ARN": database_secret.secret_arn,
+                "LOG_LEVEL": "INFO",
+            },
+            timeout=Duration.minutes(5),
+            memory_size=256,
+            architecture=lambda_.Architecture.ARM_64,
+            log_group=visit_detector_log_group,
+        )
+
+        database_secret.grant_read(visit_detector_lambda)
+
+        # EventBridge rule for visit detection (every 15 minutes)
+        visit_detector_rule = events.Rule(
+            self,
+            "VisitDetectorSchedule",
+            rule_name="second-brain-visit-detector",
+            description="Detects visits in location pings",
+            schedule=events.Schedule.rate(Duration.minutes(15)),
+        )
+
+        visit_detector_rule.add_target(
+            targets.LambdaFunction(visit_detector_lambda)
+        )
+
+        # Feed Poller Lambda
+        # Polls enabled RSS/Atom feeds and has the agent summarize new items
+        # as low-importance facts tagged reading/feeds.
+        feed_poller_log_group = logs.LogGroup(
+            self,
+            "FeedPollerLogs",
+            log_group_name="/aws/lambda/second-brain-feed-poller",
+            retention=logs.RetentionDays.ONE_WEEK,
+        )
+
+        feed_poller_env = {
+            "DB_HOST": database_host,
+            "DB_PORT": "5432",
+            "DB_NAME": "second_brain",
+            "DB_SECRET_ARN": database_secret.secret_arn,
+            "LOG_LEVEL": "INFO",
+        }
+
+        if agent_function_arn:
+            feed_poller_env["AGENT_FUNCTION_NAME"] = agent_function_arn
+
+        feed_poller_lambda = lambda_.Function(
+            self,
+            "FeedPollerLambda",
+            function_name="second-brain-feed-poller",
+            runtime=lambda_.Runtime.PROVIDED_AL2023,
+            handler="bootstrap",
+            code=lambda_.Code.from_asset(_get_lambda_asset_path("feed_poller")),
+            description="Polls RSS/Atom feeds and summarizes new items",
+            vpc=vpc,
+            vpc_subnets=ec2.SubnetSelection(
+                subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
+            ),
+            security_groups=[security_group],
+            environment=feed_poller_env,
+            timeout=Duration.minutes(10),
+            memory_size=256,
+            architecture=lambda_.Architecture.ARM_64,
+            log_group=feed_poller_log_group,
+        )
+
+        database_secret.grant_read(feed_poller_lambda)
+
+        if agent_function_arn:
+            feed_poller_lambda.add_to_role_policy(
+                iam.PolicyStatement(
+                    actions=["lambda:InvokeFunction"],
+                    resources=[agent_function_arn],
+                )
+            )
+            grant_internal_auth(feed_poller_lambda)
+
+        # EventBridge rule for feed polling (hourly)
+        feed_poller_rule = events.Rule(
+            self,
+            "FeedPollerSchedule",
+            rule_name="second-brain-feed-poller",
+            description="Polls RSS/Atom feeds every hour",
+            schedule=events.Schedule.cron(minute="30"),
+        )
+
+        feed_poller_rule.add_target(
+            targets.LambdaFunction(feed_poller_lambda)
+        )
+
+        # Drop Folder Ingest Lambda
+        # Files dropped into drop/{cognito_sub}/ are ingested by type; Transcribe
+        # writes audio transcripts back under transcripts/ for a second pass.
+        self.drop_folder_bucket = s3.Bucket(
+            self,
+            "DropFolderBucket",
+            block_public_access=s3.BlockPublicAccess.BLOCK_ALL,
+            encryption=s3.BucketEncryption.S3_MANAGED,
+            enforce_ssl=True,
+            lifecycle_rules=[
+                s3.LifecycleRule(prefix="transcripts/", expiration=Duration.days(30)),
+            ],
+        )
+
+        drop_folder_dlq = sqs.Queue(
+            self,
+            "DropFolderDLQ",
+            queue_name="second-brain-drop-folder-dlq",
+            retention_period=Duration.days(14),
+        )
+
+        drop_folder_queue = sqs.Queue(
+            self,
+            "DropFolderQueue",
+            queue_name="second-brain-drop-folder",
+            visibility_timeout=Duration.minutes(6),
+            dead_letter_queue=sqs.DeadLetterQueue(
+                max_receive_count=5,
+                queue=drop_folder_dlq,
+            ),
+        )
+
+        for prefix in ("drop/", "transcripts/"):
+            self.drop_folder_bucket.add_event_notification(
+                s3.EventType.OBJECT_CREATED,
+                s3n.SqsDestination(drop_folder_queue),
+                s3.NotificationKeyFilter(prefix=prefix),
+            )
+
+        drop_folder_log_group = logs.LogGroup(
+            self,
+            "DropFolderIngestLogs",
+            log_group_name="/aws/lambda/second-brain-drop-folder-ingest",
+            retention=logs.RetentionDays.ONE_WEEK,
+        )
+
+        drop_folder_env = {
+            "DB_HOST": database_host,
+            "DB_PORT": "5432",
+            "DB_NAME": "second_brain",
+            "DB_SECRET_ARN": database_secret.secret_arn,
+            "NOTIFICATION_TOPIC_ARN": self.notification_topic.topic_arn,
+            "LOG_LEVEL": "INFO",
+        }
+
+        if agent_function_arn:
+            drop_folder_env["AGENT_FUNCTION_NAME"] = agent_function_arn
+
+        drop_folder_lambda = lambda_.Function(
+            self,
+            "DropFolderIngestLambda",
+            function_name="second-brain-drop-folder-ingest",
+            runtime=lambda_.Runtime.PROVIDED_AL2023,
+            handler="bootstrap",
+            code=lambda_.Code.from_asset(_get_lambda_asset_path("drop_folder_ingest")),
+            description="Ingests files dropped into the per-user S3 drop folder",
+            vpc=vpc,
+            vpc_subnets=ec2.SubnetSelection(
+                subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
+            ),
+            security_groups=[security_group],
+            environment=drop_folder_env,
+            timeout=Duration.minutes(1),
+            memory_size=256,
+            architecture=lambda_.Architecture.ARM_64,
+            log_group=drop_folder_log_group,
+        )
+
+        database_secret.grant_read(drop_folder_lambda)
+        self.notification_topic.grant_publish(drop_folder_lambda)
+
+        # Transcribe writes job output with the caller's permissions
+        self.drop_folder_bucket.grant_read_write(drop_folder_lambda)
+
+        drop_folder_lambda.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=[
+                    "textract:DetectDocumentText",
+                    "transcribe:StartTranscriptionJob",
+                    "transcribe:GetTranscriptionJob",
+                ],
+                resources=["*"],
+            )
+        )
+
+        if agent_function_arn:
+            drop_folder_lambda.add_to_role_policy(
+                iam.PolicyStatement(
+                    actions=["lambda:InvokeFunction"],
+                    resources=[agent_function_arn],
+                )
+            )
+            grant_internal_auth(drop_folder_lambda)
+
+        drop_folder_lambda.add_event_source(
+            lambda_event_sources.SqsEventSource(
+                drop_folder_queue,
+                batch_size=5,
+                report_batch_item_failures=True,
+            )
+        )
+
+        # Failed transcription jobs never write output; catch them here
+        transcribe_failed_rule = events.Rule(
+            self,
+            "DropFolderTranscribeFailed",
+            rule_name="second-brain-drop-folder-transcribe-failed",
+            description="Marks drop folder audio files failed when transcription fails",
+            event_pattern=events.EventPattern(
+                source=["aws.transcribe"],
+                detail_type=["Transcribe Job State Change"],
+                detail={"TranscriptionJobStatus": ["FAILED"]},
+            ),
+        )
+
+        transcribe_failed_rule.add_target(
+            targets.LambdaFunction(drop_folder_lambda)
+        )
+
+        # Document Ingest Lambda
+        # PDFs and scans uploaded to documents/{cognito_sub}/ in the drop folder
+        # bucket start an async Textract job; Textract reports completion via SNS
+        # to the same queue, and the text is ingested chunk by chunk.
+        document_ingest_dlq = sqs.Queue(
+            self,
+            "DocumentIngestDLQ",
+            queue_name="second-brain-document-ingest-dlq",
+            retention_period=Duration.days(14),
+        )
+
+        document_ingest_queue = sqs.Queue(
+            self,
+            "DocumentIngestQueue",
+            queue_name="second-brain-document-ingest",
+            visibility_timeout=Duration.minutes(16),
+            dead_letter_queue=sqs.DeadLetterQueue(
+                max_receive_count=5,
+                queue=document_ingest_dlq,
+            ),
+        )
+
+        self.drop_folder_bucket.add_event_notification(
+            s3.EventType.OBJECT_CREATED,
+            s3n.SqsDestination(document_ingest_queue),
+            s3.NotificationKeyFilter(prefix="documents/"),
+        )
+
+        textract_topic = sns.Topic(
+            self,
+            "TextractJobTopic",
+            topic_name="second-brain-textract-jobs",
+            display_name="Second Brain Textract job completions",
+        )
+        textract_topic.add_subscription(
+            sns_subs.SqsSubscription(document_ingest_queue, raw_message_delivery=True)
+        )
+
+        # Textract publishes job completions with this role
+        textract_role = iam.Role(
+            self,
+            "TextractPublishRole",
+            assumed_by=iam.ServicePrincipal("textract.amazonaws.com"),
+        )
+        textract_topic.grant_publish(textract_role)
+
+        document_ingest_log_group = logs.LogGroup(
+            self,
+            "DocumentIngestLogs",
+            log_group_name="/aws/lambda/second-brain-document-ingest",
+            retention=logs.RetentionDays.ONE_WEEK,
+        )
+
+        document_ingest_env = {
+            "DB_HOST": database_host,
+            "DB_PORT": "5432",
+            "DB_NAME": "second_brain",
+            "DB_SECRET_ARN": database_secret.secret_arn,
+            "NOTIFICATION_TOPIC_ARN": self.notification_topic.topic_arn,
+            "TEXTRACT_TOPIC_ARN": textract_topic.topic_arn,
+            "TEXTRACT_ROLE_ARN": textract_role.role_arn,
+            "LOG_LEVEL": "INFO",
+        }
+
+        if agent_function_arn:
+            document_ingest_env["AGENT_FUNCTION_NAME"] = agent_function_arn
+
+        document_ingest_lambda = lambda_.Function(
+            self,
+            "DocumentIngestLambda",
+            function_name="second-brain-document-ingest",
+            runtime=lambda_.Runtime.PROVIDED_AL2023,
+            handler="bootstrap",
+            code=lambda_.Code.from_asset(_get_lambda_asset_path("document_ingest")),
+            description="Ingests PDFs and scanned documents with Textract",
+            vpc=vpc,
+            vpc_subnets=ec2.SubnetSelection(
+                subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
+            ),
+            security_groups=[security_group],
+            environment=document_ingest_env,
+            # Each chunk is an agent invocation; long documents take a while
+            timeout=Duration.minutes(15),
+            memory_size=256,
+            architecture=lambda_.Architecture.ARM_64,
+            log_group=document_ingest_log_group,
+        )
+
+        database_secret.grant_read(document_ingest_lambda)
+        self.notification_topic.grant_publish(document_ingest_lambda)
+
+        # Textract reads the document with the caller's permissions
+        self.drop_folder_bucket.grant_read(document_ingest_lambda)
+
+        document_ingest_lambda.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=[
+                    "textract:StartDocumentTextDetection",
+                    "textract:GetDocumentTextDetection",
+                ],
+                resources=["*"],
+            )
+        )
+        textract_role.grant_pass_role(document_ingest_lambda.role)
+
+        if agent_function_arn:
+            document_ingest_lambda.add_to_role_policy(
+                iam.PolicyStatement(
+                    actions=["lambda:InvokeFunction"],
+                    resources=[agent_function_arn],
+                )
+            )
+            grant_internal_auth(document_ingest_lambda)
+
+        document_ingest_lambda.add_event_source(
+            lambda_event_sources.SqsEventSource(
+                document_ingest_queue,
+                batch_size=1,
+                report_batch_item_failures=True,
+            )
+        )
+
+        # Photo Ingest Lambda
+        # Photos uploaded under photos/ are labeled with Rekognition, matched
+        # against person entities' faces and recorded as facts; thumbnails are
+        # written back under thumbnails/.
+        photo_ingest_dlq = sqs.Queue(
+            self,
+            "PhotoIngestDLQ",
+            queue_name="second-brain-photo-ingest-dlq",
+            retention_period=Duration.days(14),
+        )
+
+        photo_ingest_queue = sqs.Queue(
+            self,
+            "PhotoIngestQueue",
+            queue_name="second-brain-photo-ingest",
+            visibility_timeout=Duration.minutes(3),
+            dead_letter_queue=sqs.DeadLetterQueue(
+                max_receive_count=5,
+                queue=photo_ingest_dlq,
+            ),
+        )
+
+        self.drop_folder_bucket.add_event_notification(
+            s3.EventType.OBJECT_CREATED,
+            s3n.SqsDestination(photo_ingest_queue),
+            s3.NotificationKeyFilter(prefix="photos/"),
+        )
+
+        face_collection = rekognition.CfnCollection(
+            self,
+            "FaceCollection",
+            collection_id="second-brain-faces",
+        )
+
+        photo_ingest_log_group = logs.LogGroup(
+            self,
+            "PhotoIngestLogs",
+            log_group_name="/aws/lambda/second-brain-photo-ingest",
+            retention=logs.RetentionDays.ONE_WEEK,
+        )
+
+        photo_ingest_env = {
+            "DB_HOST": database_host,
+            "DB_PORT": "5432",
+            "DB_NAME": "second_brain",
+            "DB_SECRET_ARN": database_secret.secret_arn,
+            "NOTIFICATION_TOPIC_ARN": self.notification_topic.topic_arn,
+            "FACE_COLLECTION_ID": face_collection.collection_id,
+            "LOG_LEVEL": "INFO",
+        }
+
+        if entity_photos_bucket:
+            photo_ingest_env["ENTITY_PHOTOS_BUCKET"] = entity_photos_bucket.bucket_name
+        if place_index_name:
+            photo_ingest_env["PLACE_INDEX_NAME"] = place_index_name
+
+        photo_ingest_lambda = lambda_.Function(
+            self,
+            "PhotoIngestLambda",
+            function_name="second-brain-photo-ingest",
+            runtime=lambda_.Runtime.PROVIDED_AL2023,
+            handler="bootstrap",
+            code=lambda_.Code.from_asset(_get_lambda_asset_path("photo_ingest")),
+            description="Labels uploaded photos and records them as facts",
+            vpc=vpc,
+            vpc_subnets=ec2.SubnetSelection(
+                subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
+            ),
+            security_groups=[security_group],
+            environment=photo_ingest_env,
+            timeout=Duration.minutes(2),
+            # Full-size photos are decoded in memory for thumbnails and face crops
+            memory_size=1024,
+            architecture=lambda_.Architecture.ARM_64,
+            log_group=photo_ingest_log_group,
+        )
+
+        database_secret.grant_read(photo_ingest_lambda)
+        self.notification_topic.grant_publish(photo_ingest_lambda)
+
+        # Rekognition reads photos with the caller's permissions
+        self.drop_folder_bucket.grant_read_write(photo_ingest_lambda)
+        if entity_photos_bucket:
+            entity_photos_bucket.grant_read(photo_ingest_lambda)
+
+        photo_ingest_lambda.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=["rekognition:DetectLabels", "rekognition:DetectFaces"],
+                resources=["*"],
+            )
+        )
+        photo_ingest_lambda.add_to_role_policy(
+            iam.PolicyStatement(
+                actions=[
+                    "rekognition:IndexFaces",
+                    "rekognition:SearchFacesByImage",
+                    "rekognition:DeleteFaces",
+                ],
+                resources=[face_collection.attr_arn],
+            )
+        )
+
+        if place_index_name:
+            photo_ingest_lambda.add_to_role_policy(
+                iam.PolicyStatement(
+                    actions=["geo:SearchPlaceIndexForPosition"],
+                    resources=[
+                        f"arn:aws:geo:{self.region}:{self.account}:place-index/{place_index_name}"
+                    ],
+                )
+            )
+
+        photo_ingest_lambda.add_event_source(
+            lambda_event_sources.SqsEventSource(
+                photo_ingest_queue,
+                batch_size=1,
+                report_batch_item_failures=True,
+            )
+        )
+
+        # Realtime Dispatcher Lambda
+        # Pushes new facts and fired reminders from the domain event bus to
+        # the user's open WebSocket connections.
+        if realtime_table and websocket_stage:
+            realtime_dispatcher_log_group = logs.LogGroup(
+                self,
+                "RealtimeDispatcherLogs",
+                log_group_name="/aws/lambda/second-brain-realtime-dispatcher",
+                retention=logs.RetentionDays.ONE_WEEK,
+            )
+
+            realtime_dispatcher_lambda = lambda_.Function(
+                self,
+                "RealtimeDispatcherLambda",
+                function_name="second-brain-realtime-dispatcher",
+                runtime=lambda_.Runtime.PROVIDED_AL2023,
+                handler="bootstrap",
+                code=lambda_.Code.from_asset(
+                    _get_lambda_asset_path("realtime_dispatcher")
+                ),
+                description="Pushes domain events to WebSocket clients",
+                vpc=vpc,
+                vpc_subnets=ec2.SubnetSelection(
+                    subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
+                ),
+                security_groups=[security_group],
+                environment={
+                    "CONNECTIONS_TABLE_NAME": realtime_table.table_name,
+                    "WEBSOCKET_CALLBACK_URL": websocket_stage.callback_url,
+                    "LOG_LEVEL": "INFO",
+                },
+                timeout=Duration.seconds(30),
+                memory_size=256,
+                architecture=lambda_.Architecture.ARM_64,
+                log_group=realtime_dispatcher_log_group,
+            )
+
+            # Gone connections are removed as they are found
+            realtime_table.grant_read_write_data(realtime_dispatcher_lambda)
+            websocket_stage.grant_management_api_access(realtime_dispatcher_lambda)
+
+            realtime_dispatcher_rule = events.Rule(
+                self,
+                "RealtimeDispatcherRule",
+                rule_name="second-brain-realtime-dispatcher",
+                description="Forwards domain events to WebSocket clients",
+                event_bus=self.event_bus,
+                event_pattern=events.EventPattern(
+                    source=["second-brain"],
+                    detail_type=["FactCreated", "ReminderTriggered"],
+                    detail={"schema_version": [1]},
+                ),
+            )
+
+            realtime_dispatcher_rule.add_target(
+                targets.LambdaFunction(realtime_dispatcher_lambda)
+            )
+
+            self.realtime_dispatcher_lambda = realtime_dispatcher_lambda
+
+        # Email Ingest Lambda
+        # SES stores mail sent to the inbound address under inbound/ and invokes
+        # the Lambda; attachments are handed to the drop folder. The receipt rule
+        # set must be made active in SES (one active rule set per account).
+        if inbound_email_address:
+            inbound_email_bucket = s3.Bucket(
+                self,
+                "InboundEmailBucket",
+                block_public_access=s3.BlockPublicAccess.BLOCK_ALL,
+                encryption=s3.BucketEncryption.S3_MANAGED,
+                enforce_ssl=True,
+                lifecycle_rules=[
+                    s3.LifecycleRule(prefix="inbound/", expiration=Duration.days(7)),
+                ],
+            )
+
+            email_ingest_log_group = logs.LogGroup(
+                self,
+                "EmailIngestLogs",
+                log_group_name="/aws/lambda/second-brain-email-ingest",
+                retention=logs.RetentionDays.ONE_WEEK,
+            )
+
+            email_ingest_env = {
+                "DB_HOST": database_host,
+                "DB_PORT": "5432",
+                "DB_NAME": "second_brain",
+                "DB_SECRET_ARN": database_secret.secret_arn,
+                "INBOUND_EMAIL_BUCKET": inbound_email_bucket.bucket_name,
+                "DROP_FOLDER_BUCKET": self.drop_folder_bucket.bucket_name,
+                # Reply from the inbound address so answers can be replied to
+                "FROM_EMAIL": inbound_email_address,
+                "LOG_LEVEL": "INFO",
+            }
+
+            if agent_function_arn:
+                email_ingest_env["AGENT_FUNCTION_NAME"] = agent_function_arn
+
+            email_ingest_lambda = lambda_.Function(
+                self,
+                "EmailIngestLambda",
+                function_name="second-brain-email-ingest",
+                runtime=lambda_.Runtime.PROVIDED_AL2023,
+                handler="bootstrap",
+                code=lambda_.Code.from_asset(_get_lambda_asset_path("email_ingest")),
+                description="Ingests emails sent to the inbound address",
+                vpc=vpc,
+                vpc_subnets=ec2.SubnetSelection(
+                    subnet_type=ec2.SubnetType.PRIVATE_WITH_EGRESS
+                ),
+                security_groups=[security_group],
+                environment=email_ingest_env,
+                timeout=Duration.minutes(1),
+                memory_size=256,
+                architecture=lambda_.Architecture.ARM_64,
+                log_group=email_ingest_log_group,
+            )
+
+            database_secret.grant_read(email_ingest_lambda)
+            inbound_email_bucket.grant_read(email_ingest_lambda)
+            self.drop_folder_bucket.grant_put(email_ingest_lambda)
+
+            email_ingest_lambda.add_to_role_policy(
+                iam.PolicyStatement(
+                    actions=["ses:SendEmail", "ses:SendRawEmail"],
+                    resources=["*"],
+                )
+            )
+
+            if agent_function_arn:
+                email_ingest_lambda.add_to_role_policy(
+                    iam.PolicyStatement(
+                        actions=["lambda:InvokeFunction"],
+                        resources=[agent_function_arn],
+                    )
+                )
+                grant_internal_auth(email_ingest_lambda)
+
+            ses.ReceiptRuleSet(
+                self,
+                "InboundEmailRuleSet",
+                receipt_rule_set_name="second-brain-inbound-email",
+                rules=[
+                    ses.ReceiptRuleOptions(
+                        recipients=[inbound_email_address],
+                        scan_enabled=True,
+                        actions=[
+                            ses_actions.S3(
+                                bucket=inbound_email_bucket,
+                                object_key_prefix="inbound/",
+                            ),
+                            ses_actions.Lambda(
+                                function=email_ingest_lambda,
+                                invocation_type=ses_actions.LambdaInvocationType.EVENT,
+                            ),
+                        ],
+                    )
+                ],
+            )
+
+            self.email_ingest_lambda = email_ingest_lambda
+
         # Export Lambda functions
         self.calendar_sync_lambda = calendar_sync_lambda
         self.briefing_dispatcher_lambda = briefing_dispatcher_lambda
         self.reminder_evaluator_lambda = reminder_evaluator_lambda
         self.notification_sender_lambda = notification_sender_lambda
+        self.digest_builder_lambda = digest_builder_lambda
+        self.weekly_review_lambda = weekly_review_lambda
+        self.occasion_scanner_lambda = occasion_scanner_lambda
+        self.contacts_sync_lambda = contacts_sync_lambda
+        self.tag_rule_backfill_lambda = tag_rule_backfill_lambda
+        self.embedding_indexer_lambda = embedding_indexer_lambda
+        self.trash_purge_lambda = trash_purge_lambda
+        self.importance_decay_lambda = importance_decay_lambda
+        self.access_grant_sweeper_lambda = access_grant_sweeper_lambda
+        self.visit_detector_lambda = visit_detector_lambda
+        self.feed_poller_lambda = feed_poller_lambda
+        self.drop_folder_lambda = drop_folder_lambda
+        self.document_ingest_lambda = document_ingest_lambda
+        self.photo_ingest_lambda = photo_ingest_lambda
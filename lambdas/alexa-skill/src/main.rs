@@ -1,10 +1,48 @@
 //! Alexa Skill Lambda - Handles Alexa voice interactions.
+//!
+//! Users link their Second Brain account in the Alexa app (Cognito), so each
+//! request carries the user's access token. Supported requests:
+//! - LaunchRequest and `BriefingIntent` (optional `briefingType` slot):
+//!   today's briefing, read by Polly in the configured voice and played with
+//!   an `<audio>` tag, or read by Alexa when the audio can't be produced
+//! - `NotificationsIntent`: reads the notifications Alexa announced, then
+//!   marks them read
+//! - `AMAZON.HelpIntent`, `AMAZON.StopIntent` and `AMAZON.CancelIntent`
+//!
+//! A routine can open the skill (its LaunchRequest plays the briefing), e.g.
+//! "Alexa, good morning" in the Alexa app.
+//!
+//! The skill also keeps `alexa_links` current for Alexa notifications (see
+//! `shared::alexa`): linked users are recorded whenever they use the skill,
+//! skill events track the notifications permission and proactive event
+//! subscription, and disabling the skill removes the link.
+//!
+//! Requests for another skill (`ALEXA_SKILL_ID`) are rejected.
 
 use lambda_runtime::{run, service_fn, Error, LambdaEvent};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use shared::alexa::{
+    grants_notifications, subscribes_to_messages, DEFAULT_API_ENDPOINT, EVENT_EXPIRY_HOURS,
+};
+use shared::briefings::{
+    briefing_audio, briefing_speech, generate_briefing, parse_briefing_type, todays_briefing,
+    StoredBriefing,
+};
+use shared::metrics;
+use shared::tts::{escape_ssml, SpeechInput, TtsService, VoiceOptions};
+use shared::{validate_token, AgentClient, AuthorizedUser};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
 
+/// Alexa speaks at most 8000 characters of SSML per response.
+const MAX_SPEECH_CHARS: usize = 8000;
+
+/// Notifications read per `NotificationsIntent`
+const MAX_MESSAGES: i64 = 5;
+
 #[derive(Debug, Deserialize)]
 struct AlexaRequest {
     request: Value,
@@ -12,6 +50,70 @@ struct AlexaRequest {
     context: Option<Value>,
 }
 
+impl AlexaRequest {
+    fn request_type(&self) -> &str {
+        self.request["type"].as_str().unwrap_or_default()
+    }
+
+    fn intent_name(&self) -> Option<&str> {
+        self.request["intent"]["name"].as_str()
+    }
+
+    fn slot(&self, name: &str) -> Option<&str> {
+        self.request["intent"]["slots"][name]["value"].as_str()
+    }
+
+    /// Request locale, e.g. `en-US`
+    fn locale(&self) -> Option<&str> {
+        self.request["locale"].as_str()
+    }
+
+    fn system(&self, key: &str) -> Option<&Value> {
+        self.context.as_ref().map(|context| &context["System"][key])
+    }
+
+    /// Skill-scoped Alexa user ID
+    fn alexa_user_id(&self) -> Option<&str> {
+        self.system("user")
+            .and_then(|user| user["userId"].as_str())
+            .or_else(|| {
+                self.session
+                    .as_ref()
+                    .and_then(|session| session["user"]["userId"].as_str())
+            })
+    }
+
+    /// Regional Alexa API endpoint for this user
+    fn api_endpoint(&self) -> &str {
+        self.system("apiEndpoint")
+            .and_then(Value::as_str)
+            .unwrap_or(DEFAULT_API_ENDPOINT)
+    }
+
+    /// The account linking token (a Cognito access token)
+    fn access_token(&self) -> Option<&str> {
+        self.system("user")
+            .and_then(|user| user["accessToken"].as_str())
+            .or_else(|| {
+                self.session
+                    .as_ref()
+                    .and_then(|session| session["user"]["accessToken"].as_str())
+            })
+            // AlexaSkillEvent.SkillAccountLinked
+            .or_else(|| self.request["body"]["accessToken"].as_str())
+    }
+
+    fn application_id(&self) -> Option<&str> {
+        self.system("application")
+            .and_then(|application| application["applicationId"].as_str())
+            .or_else(|| {
+                self.session
+                    .as_ref()
+                    .and_then(|session| session["application"]["applicationId"].as_str())
+            })
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct AlexaResponse {
     version: String,
@@ -21,34 +123,395 @@ struct AlexaResponse {
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct AlexaResponseBody {
-    output_speech: OutputSpeech,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_speech: Option<OutputSpeech>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    card: Option<Card>,
     should_end_session: bool,
 }
 
 #[derive(Debug, Serialize)]
-struct OutputSpeech {
-    #[serde(rename = "type")]
-    speech_type: String,
-    text: String,
-}
-
-async fn handler(_event: LambdaEvent<AlexaRequest>) -> Result<AlexaResponse, Error> {
-    // TODO: Implement Alexa skill handling
-    // - Parse intent
-    // - Handle account linking token
-    // - Invoke AgentCore
-    // - Format Alexa response
-
-    Ok(AlexaResponse {
-        version: "1.0".to_string(),
-        response: AlexaResponseBody {
-            output_speech: OutputSpeech {
-                speech_type: "PlainText".to_string(),
-                text: "Second Brain is not yet fully implemented.".to_string(),
+#[serde(tag = "type")]
+enum OutputSpeech {
+    PlainText {
+        text: String,
+    },
+    #[serde(rename = "SSML")]
+    Ssml {
+        ssml: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type")]
+enum Card {
+    /// Prompts the user to link their account in the Alexa app
+    LinkAccount,
+}
+
+impl AlexaResponse {
+    fn speech(speech: OutputSpeech, should_end_session: bool) -> Self {
+        Self {
+            version: "1.0".to_string(),
+            response: AlexaResponseBody {
+                output_speech: Some(speech),
+                card: None,
+                should_end_session,
+            },
+        }
+    }
+
+    /// Response to requests Alexa doesn't speak, such as skill events
+    fn empty() -> Self {
+        Self {
+            version: "1.0".to_string(),
+            response: AlexaResponseBody {
+                output_speech: None,
+                card: None,
+                should_end_session: true,
+            },
+        }
+    }
+
+    fn say(text: &str) -> Self {
+        Self::speech(
+            OutputSpeech::PlainText {
+                text: text.to_string(),
+            },
+            true,
+        )
+    }
+
+    fn link_account() -> Self {
+        let mut response = Self::say(
+            "Please link your Second Brain account in the Alexa app to hear your briefing.",
+        );
+        response.response.card = Some(Card::LinkAccount);
+        response
+    }
+}
+
+struct AppState {
+    db_pool: PgPool,
+    agent_client: AgentClient,
+    tts: TtsService,
+    skill_id: Option<String>,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        let lambda_client = aws_sdk_lambda::Client::new(&config);
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        let agent_function = std::env::var("AGENT_FUNCTION_NAME")
+            .unwrap_or_else(|_| "second-brain-agents".to_string());
+
+        Ok(Self {
+            db_pool,
+            agent_client: AgentClient::new(lambda_client, agent_function),
+            tts: TtsService::from_env(&config),
+            skill_id: std::env::var("ALEXA_SKILL_ID")
+                .ok()
+                .filter(|id| !id.is_empty()),
+        })
+    }
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    event: LambdaEvent<AlexaRequest>,
+) -> Result<AlexaResponse, Error> {
+    let request = event.payload;
+
+    if let Some(skill_id) = &state.skill_id {
+        if request.application_id() != Some(skill_id.as_str()) {
+            return Err("Request is for a different skill".into());
+        }
+    }
+
+    match (request.request_type(), request.intent_name()) {
+        ("LaunchRequest", _) | ("IntentRequest", Some("BriefingIntent")) => {
+            briefing(&state, &request).await
+        }
+        ("IntentRequest", Some("NotificationsIntent")) => notifications(&state, &request).await,
+        ("IntentRequest", Some("AMAZON.HelpIntent")) => Ok(AlexaResponse::speech(
+            OutputSpeech::PlainText {
+                text: "Ask for your morning or evening briefing, or for your messages.".to_string(),
             },
-            should_end_session: true,
+            false,
+        )),
+        ("IntentRequest", Some("AMAZON.StopIntent" | "AMAZON.CancelIntent")) => {
+            Ok(AlexaResponse::say("Goodbye."))
+        }
+        ("SessionEndedRequest", _) => Ok(AlexaResponse::empty()),
+        (
+            "AlexaSkillEvent.SkillPermissionAccepted" | "AlexaSkillEvent.SkillPermissionChanged",
+            _,
+        ) => {
+            let enabled = grants_notifications(&request.request["body"]);
+            skill_event(&state, &request, Some(enabled)).await
+        }
+        ("AlexaSkillEvent.ProactiveSubscriptionChanged", _) => {
+            let enabled = subscribes_to_messages(&request.request["body"]);
+            skill_event(&state, &request, Some(enabled)).await
+        }
+        ("AlexaSkillEvent.SkillAccountLinked", _) => skill_event(&state, &request, None).await,
+        ("AlexaSkillEvent.SkillDisabled", _) => {
+            if let Some(alexa_user_id) = request.alexa_user_id() {
+                // Alexa notifications stay on only if another linked account gets them
+                sqlx::query(
+                    r#"
+                    WITH link AS (
+                        DELETE FROM alexa_links WHERE alexa_user_id = $1 RETURNING user_id
+                    )
+                    UPDATE user_notification_preferences p
+                    SET alexa_enabled = false, updated_at = NOW()
+                    FROM link
+                    WHERE p.user_id = link.user_id
+                      AND NOT EXISTS (
+                          SELECT 1 FROM alexa_links a
+                          WHERE a.user_id = link.user_id
+                            AND a.alexa_user_id <> $1
+                            AND a.notifications_enabled
+                      )
+                    "#,
+                )
+                .bind(alexa_user_id)
+                .execute(&state.db_pool)
+                .await
+                .map_err(|e| format!("Failed to remove Alexa link: {}", e))?;
+                info!("Removed Alexa link for disabled skill");
+            }
+            Ok(AlexaResponse::empty())
+        }
+        (request_type, intent) => {
+            info!(request_type, ?intent, "Unhandled Alexa request");
+            Ok(AlexaResponse::say("Sorry, I can't help with that yet."))
+        }
+    }
+}
+
+/// The Second Brain user the request's account linking token belongs to,
+/// or `None` if the account isn't linked.
+async fn linked_user(
+    state: &AppState,
+    request: &AlexaRequest,
+) -> Result<Option<AuthorizedUser>, Error> {
+    let Some(token) = request.access_token() else {
+        return Ok(None);
+    };
+    // Alexa obtained the token through account linking; the skill only
+    // needs its subject
+    let user = match validate_token(token, "") {
+        Ok(user) => AuthorizedUser::resolve(user, &state.db_pool).await,
+        Err(e) => Err(e),
+    };
+    match user {
+        Ok(user) => {
+            if let Some(alexa_user_id) = request.alexa_user_id() {
+                // Bookkeeping for notifications; the request goes on regardless
+                if let Err(e) =
+                    record_link(state, alexa_user_id, &user, request.api_endpoint()).await
+                {
+                    warn!("Failed to record Alexa link: {}", e);
+                }
+            }
+            Ok(Some(user))
+        }
+        Err(shared::Error::Auth(e)) => {
+            info!("Alexa user not linked: {}", e);
+            Ok(None)
+        }
+        Err(e) => Err(format!("Failed to lookup user: {}", e).into()),
+    }
+}
+
+/// Record (or refresh) the Alexa account a linked user talks to the skill from.
+async fn record_link(
+    state: &AppState,
+    alexa_user_id: &str,
+    user: &AuthorizedUser,
+    api_endpoint: &str,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO alexa_links (alexa_user_id, user_id, api_endpoint)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (alexa_user_id) DO UPDATE
+        SET user_id = EXCLUDED.user_id,
+            api_endpoint = EXCLUDED.api_endpoint,
+            last_seen_at = NOW(),
+            updated_at = NOW()
+        "#,
+    )
+    .bind(alexa_user_id)
+    .bind(user.user_id)
+    .bind(api_endpoint)
+    .execute(&state.db_pool)
+    .await
+    .map_err(|e| format!("Failed to record Alexa link: {}", e))?;
+
+    Ok(())
+}
+
+/// Handle a skill event: record the link and, for permission and subscription
+/// changes, whether the user gets Alexa notifications.
+async fn skill_event(
+    state: &AppState,
+    request: &AlexaRequest,
+    notifications: Option<bool>,
+) -> Result<AlexaResponse, Error> {
+    // Skill events carry the linking token when the account is linked
+    linked_user(state, request).await?;
+
+    if let (Some(alexa_user_id), Some(enabled)) = (request.alexa_user_id(), notifications) {
+        // The user's notification preference follows their latest choice
+        sqlx::query(
+            r#"
+            WITH link AS (
+                UPDATE alexa_links
+                SET notifications_enabled = $2, updated_at = NOW()
+                WHERE alexa_user_id = $1
+                RETURNING user_id
+            )
+            INSERT INTO user_notification_preferences (user_id, alexa_enabled)
+            SELECT user_id, $2 FROM link
+            ON CONFLICT (user_id) DO UPDATE
+            SET alexa_enabled = EXCLUDED.alexa_enabled, updated_at = NOW()
+            "#,
+        )
+        .bind(alexa_user_id)
+        .bind(enabled)
+        .execute(&state.db_pool)
+        .await
+        .map_err(|e| format!("Failed to update Alexa notifications: {}", e))?;
+
+        info!(enabled, "Alexa notifications changed");
+    }
+
+    Ok(AlexaResponse::empty())
+}
+
+/// Read the most recent unread notifications announced on Alexa.
+async fn notifications(state: &AppState, request: &AlexaRequest) -> Result<AlexaResponse, Error> {
+    let Some(user) = linked_user(state, request).await? else {
+        return Ok(AlexaResponse::link_account());
+    };
+
+    // Alexa drops announcements it couldn't deliver after EVENT_EXPIRY_HOURS
+    let messages: Vec<(String, String)> = sqlx::query_as(
+        r#"
+        UPDATE notifications
+        SET read_at = NOW(), updated_at = NOW()
+        WHERE id IN (
+            SELECT id FROM notifications
+            WHERE user_id = $1
+              AND channel = 'alexa'
+              AND status = 'sent'
+              AND read_at IS NULL
+              AND sent_at > NOW() - make_interval(hours => $2)
+            ORDER BY sent_at DESC
+            LIMIT $3
+        )
+        RETURNING title, body
+        "#,
+    )
+    .bind(user.user_id)
+    .bind(EVENT_EXPIRY_HOURS as i32)
+    .bind(MAX_MESSAGES)
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(|e| format!("Failed to fetch notifications: {}", e))?;
+
+    if messages.is_empty() {
+        return Ok(AlexaResponse::say("You have no new messages."));
+    }
+
+    let speech: Vec<String> = messages
+        .iter()
+        .map(|(title, body)| format!("<s>{}</s><s>{}</s>", escape_ssml(title), escape_ssml(body)))
+        .collect();
+    Ok(AlexaResponse::speech(
+        OutputSpeech::Ssml {
+            ssml: format!("<speak>{}</speak>", speech.join("<break time=\"500ms\"/>")),
         },
-    })
+        true,
+    ))
+}
+
+/// Read today's briefing, generating it if the dispatcher hasn't yet.
+async fn briefing(state: &AppState, request: &AlexaRequest) -> Result<AlexaResponse, Error> {
+    let Some(user) = linked_user(state, request).await? else {
+        return Ok(AlexaResponse::link_account());
+    };
+
+    let briefing_type = match parse_briefing_type(request.slot("briefingType")) {
+        Ok(briefing_type) => briefing_type,
+        Err(_) => {
+            return Ok(AlexaResponse::say(
+                "I can read your morning or evening briefing.",
+            ))
+        }
+    };
+
+    let briefing = match todays_briefing(&state.db_pool, user.user_id, briefing_type).await? {
+        Some(briefing) => briefing,
+        None => generate_briefing(
+            &state.db_pool,
+            &state.agent_client,
+            user.user_id,
+            &user.family_ids,
+            briefing_type,
+            "alexa",
+        )
+        .await
+        .map_err(|e| format!("Failed to generate briefing: {}", e))?,
+    };
+
+    let mut voice = VoiceOptions::from_env();
+    if let (None, Some(locale)) = (&voice.language, request.locale()) {
+        voice = voice.with_language(locale);
+    }
+    Ok(AlexaResponse::speech(
+        briefing_output(state, &briefing, &voice).await,
+        true,
+    ))
+}
+
+/// The briefing as Polly audio, or as SSML for Alexa's own voice.
+async fn briefing_output(
+    state: &AppState,
+    briefing: &StoredBriefing,
+    voice: &VoiceOptions,
+) -> OutputSpeech {
+    match briefing_audio(&state.tts, briefing, voice).await {
+        Ok(speech) => {
+            if let Some(url) = speech.url {
+                return OutputSpeech::Ssml {
+                    ssml: format!(r#"<speak><audio src="{}"/></speak>"#, escape_ssml(&url)),
+                };
+            }
+            warn!("No audio bucket configured; Alexa will read the briefing");
+        }
+        Err(e) => warn!("Failed to synthesize briefing: {}", e),
+    }
+
+    let speech = match briefing_speech(&briefing.content) {
+        SpeechInput::Text(text) => escape_ssml(&text),
+        SpeechInput::Ssml(ssml) => ssml,
+    };
+    let speech = if speech.chars().count() > MAX_SPEECH_CHARS {
+        "Your briefing is too long to read here. Open the Second Brain app to see it.".to_string()
+    } else {
+        speech
+    };
+    OutputSpeech::Ssml {
+        ssml: format!("<speak>{}</speak>", speech),
+    }
 }
 
 #[tokio::main]
@@ -58,5 +521,11 @@ async fn main() -> Result<(), Error> {
         .json()
         .init();
 
-    run(service_fn(handler)).await
+    let state = Arc::new(AppState::new().await?);
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        async move { metrics::track_invocation("skill_request", handler(state, event)).await }
+    }))
+    .await
 }
@@ -0,0 +1,325 @@
+//! Access Grants Lambda - Time-boxed access to facts and entities.
+//!
+//! Endpoints:
+//! - GET /access-grants - Grants you gave or received, and those of families
+//!   you admin
+//! - POST /access-grants - Give someone an access tier to your data (or, as a
+//!   family admin, the family's with `familyId`) until `expiresAt`, optionally
+//!   only one entity's facts (`entityId`) or one tag's (`tagId`)
+//! - DELETE /access-grants/{id} - Revoke a grant, or give up one you received
+//!
+//! Grants are checked on every read (see `shared::access::grant_clause`), so
+//! they stop working as soon as they expire; the `access_grant_sweeper`
+//! Lambda deletes them later. See `shared::access_grants`.
+
+use chrono::{DateTime, Utc};
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::{Deserialize, Serialize};
+use shared::access_grants::{self, AccessGrant, GrantOwner, NewGrant};
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::ApiError;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Create grant request
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct CreateGrantRequest {
+    grantee_user_id: Uuid,
+    access_tier: i16,
+    /// Grant the family's facts and entities instead of your own
+    family_id: Option<Uuid>,
+    reason: Option<String>,
+    /// Defaults to now
+    starts_at: Option<DateTime<Utc>>,
+    expires_at: DateTime<Utc>,
+    /// Share only this entity and the facts about or mentioning it
+    entity_id: Option<Uuid>,
+    /// Share only facts with this tag
+    tag_id: Option<Uuid>,
+}
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+/// GET /access-grants
+#[utoipa::path(
+    get,
+    path = "/access-grants",
+    tag = "access",
+    responses(
+        (status = 200, description = "Grants you gave or received, and those of families you admin", body = ApiResponse<Vec<AccessGrant>>),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_grants(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let grants: Vec<AccessGrant> = access_grants::list_grants(&state.db_pool, user.user_id).await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(grants),
+            error: None,
+        },
+    )
+}
+
+/// POST /access-grants
+#[utoipa::path(
+    post,
+    path = "/access-grants",
+    tag = "access",
+    request_body = CreateGrantRequest,
+    responses(
+        (status = 201, description = "The grant", body = ApiResponse<AccessGrant>),
+        (status = 400, description = "Invalid tier or dates, or a grant to yourself", body = ApiError, content_type = "application/problem+json"),
+        (status = 403, description = "Not an admin of the family", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Grantee, entity or tag not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn create_grant(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let request: CreateGrantRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    if request.grantee_user_id == user.user_id {
+        return error_response(400, "Cannot grant access to yourself");
+    }
+
+    let grantee_exists: bool =
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1)")
+            .bind(request.grantee_user_id)
+            .fetch_one(&state.db_pool)
+            .await?;
+
+    if !grantee_exists {
+        return error_response(404, "Grantee not found");
+    }
+
+    // Only family admins share the family's data
+    let owner = match request.family_id {
+        Some(family_id) => {
+            let is_admin: bool = sqlx::query_scalar(
+                "SELECT EXISTS(SELECT 1 FROM family_members WHERE family_id = $1 AND user_id = $2 AND role = 'admin')",
+            )
+            .bind(family_id)
+            .bind(user.user_id)
+            .fetch_one(&state.db_pool)
+            .await?;
+
+            if !is_admin {
+                return error_response(403, "Only family admins can grant access to family data");
+            }
+            GrantOwner::Family(family_id)
+        }
+        None => GrantOwner::User(user.user_id),
+    };
+
+    let grant = match NewGrant::new(
+        request.grantee_user_id,
+        owner,
+        request.access_tier,
+        request.reason,
+        request.starts_at,
+        request.expires_at,
+        Utc::now(),
+    ) {
+        Ok(grant) => grant,
+        Err(e) => return error_response(400, e.to_string()),
+    };
+
+    // A scope must be the owner's own entity or tag (or a system-wide tag);
+    // entities in a space are never granted
+    if let Some(entity_id) = request.entity_id {
+        let entity_exists: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM entities
+                WHERE id = $1 AND owner_type = $2 AND owner_id = $3
+                  AND space_id IS NULL AND deleted_at IS NULL
+            )
+            "#,
+        )
+        .bind(entity_id)
+        .bind(owner.owner_type())
+        .bind(owner.owner_id())
+        .fetch_one(&state.db_pool)
+        .await?;
+
+        if !entity_exists {
+            return error_response(404, "Entity not found");
+        }
+    }
+
+    if let Some(tag_id) = request.tag_id {
+        let tag_exists: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM tags
+                WHERE id = $1
+                  AND (owner_type IS NULL OR (owner_type = $2 AND owner_id = $3))
+            )
+            "#,
+        )
+        .bind(tag_id)
+        .bind(owner.owner_type())
+        .bind(owner.owner_id())
+        .fetch_one(&state.db_pool)
+        .await?;
+
+        if !tag_exists {
+            return error_response(404, "Tag not found");
+        }
+    }
+
+    let grant = grant.scoped(request.entity_id, request.tag_id);
+    let grant = access_grants::create_grant(&state.db_pool, user.user_id, &grant).await?;
+
+    info!(
+        grant_id = %grant.id,
+        grantee = %grant.grantee_user_id,
+        owner_type = %grant.owner_type,
+        access_tier = grant.access_tier,
+        scoped = grant.entity_id.is_some() || grant.tag_id.is_some(),
+        expires_at = %grant.expires_at,
+        "Created access grant"
+    );
+
+    json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(grant),
+            error: None,
+        },
+    )
+}
+
+/// DELETE /access-grants/{id}
+#[utoipa::path(
+    delete,
+    path = "/access-grants/{id}",
+    tag = "access",
+    params(
+        ("id" = Uuid, Path, description = "Grant ID"),
+    ),
+    responses(
+        (status = 200, description = "The grant was revoked", body = ApiResponse<serde_json::Value>),
+        (status = 404, description = "Not a grant you gave or received", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn revoke_grant(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let grant_id = match params.get::<Uuid>("id") {
+        Ok(id) => id,
+        Err(_) => return error_response(400, "Invalid grant ID"),
+    };
+
+    let revoked = access_grants::revoke_grant(&state.db_pool, grant_id, user.user_id).await?;
+
+    if !revoked {
+        return error_response(404, "Access grant not found");
+    }
+
+    info!(grant_id = %grant_id, "Revoked access grant");
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({ "message": "Access grant revoked" })),
+            error: None,
+        },
+    )
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .get("/access-grants", list_grants)
+        .post("/access-grants", create_grant)
+        .delete("/access-grants/{id}", revoke_grant)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
@@ -0,0 +1,265 @@
+//! Account Lambda - Delete the caller's account.
+//!
+//! Endpoints:
+//! - DELETE /account - Schedule deletion of the account and everything in it
+//!
+//! The request is recorded in `account_deletions` and handed to the
+//! `account_deletion` Lambda, which revokes the Cognito login, purges OAuth
+//! tokens, applies the family data policy, deletes the user's data and
+//! finally the user (see `shared::account_deletion`). The row is kept as the
+//! audit record after the user is gone.
+
+use aws_sdk_lambda::primitives::Blob;
+use chrono::{DateTime, Utc};
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::{Deserialize, Serialize};
+use shared::account_deletion::FamilyDataPolicy;
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::ApiError;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Request body for DELETE /account
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct DeleteAccountRequest {
+    /// The account's email address, as confirmation
+    confirm_email: String,
+    /// `keep` (default) or `delete` family-owned facts and entities the user
+    /// created
+    family_data_policy: Option<String>,
+}
+
+/// Account deletion row from database
+#[derive(Debug, sqlx::FromRow)]
+struct AccountDeletionRow {
+    id: Uuid,
+    status: String,
+    family_data_policy: String,
+    requested_at: DateTime<Utc>,
+}
+
+/// Account deletion API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct AccountDeletionResponse {
+    id: String,
+    status: String,
+    family_data_policy: String,
+    requested_at: String,
+}
+
+impl From<AccountDeletionRow> for AccountDeletionResponse {
+    fn from(row: AccountDeletionRow) -> Self {
+        Self {
+            id: row.id.to_string(),
+            status: row.status,
+            family_data_policy: row.family_data_policy,
+            requested_at: row.requested_at.to_rfc3339(),
+        }
+    }
+}
+
+const DELETION_COLUMNS: &str = "id, status::text AS status, family_data_policy, requested_at";
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+    lambda_client: aws_sdk_lambda::Client,
+    /// Lambda that carries out account deletions
+    account_deletion_function: String,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        let account_deletion_function = std::env::var("ACCOUNT_DELETION_FUNCTION_NAME")
+            .map_err(|_| "ACCOUNT_DELETION_FUNCTION_NAME not set")?;
+
+        Ok(Self {
+            db_pool,
+            lambda_client: aws_sdk_lambda::Client::new(&config),
+            account_deletion_function,
+        })
+    }
+}
+
+/// DELETE /account
+///
+/// Schedules the deletion and returns 202. The account is marked inactive
+/// straight away; asking again while a deletion is scheduled returns it.
+#[utoipa::path(
+    delete,
+    path = "/account",
+    tag = "account",
+    request_body = DeleteAccountRequest,
+    responses(
+        (status = 202, description = "The deletion is scheduled (or already was)", body = ApiResponse<AccountDeletionResponse>),
+        (status = 400, description = "`confirmEmail` doesn't match, or an unknown `familyDataPolicy`", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn delete_account(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let request: DeleteAccountRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let policy = match request.family_data_policy.as_deref() {
+        None => FamilyDataPolicy::default(),
+        Some(value) => match FamilyDataPolicy::parse(value) {
+            Some(policy) => policy,
+            None => return error_response(400, "familyDataPolicy must be keep or delete"),
+        },
+    };
+
+    let email: String = sqlx::query_scalar("SELECT email FROM users WHERE id = $1")
+        .bind(user.user_id)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+    if !request.confirm_email.trim().eq_ignore_ascii_case(&email) {
+        return error_response(400, "confirmEmail doesn't match the account's email");
+    }
+
+    let mut tx = state.db_pool.begin().await?;
+
+    let created: Option<AccountDeletionRow> = sqlx::query_as(&format!(
+        r#"
+        INSERT INTO account_deletions (user_id, cognito_sub, family_data_policy)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id) WHERE status = 'scheduled' DO NOTHING
+        RETURNING {}
+        "#,
+        DELETION_COLUMNS
+    ))
+    .bind(user.user_id)
+    .bind(&user.cognito_sub)
+    .bind(policy.as_str())
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(deletion) = created else {
+        drop(tx);
+
+        let scheduled: AccountDeletionRow = sqlx::query_as(&format!(
+            "SELECT {} FROM account_deletions WHERE user_id = $1 AND status = 'scheduled'",
+            DELETION_COLUMNS
+        ))
+        .bind(user.user_id)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+        return json_response(
+            202,
+            &ApiResponse {
+                success: true,
+                data: Some(AccountDeletionResponse::from(scheduled)),
+                error: None,
+            },
+        );
+    };
+
+    sqlx::query("UPDATE users SET status = 'inactive', updated_at = NOW() WHERE id = $1")
+        .bind(user.user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    // The scheduled sweep picks the deletion up if this invoke fails
+    let payload = serde_json::to_vec(&serde_json::json!({ "deletion_id": deletion.id }))?;
+    if let Err(e) = state
+        .lambda_client
+        .invoke()
+        .function_name(&state.account_deletion_function)
+        .invocation_type(aws_sdk_lambda::types::InvocationType::Event)
+        .payload(Blob::new(payload))
+        .send()
+        .await
+    {
+        warn!(deletion_id = %deletion.id, "Failed to start account deletion: {}", e);
+    }
+
+    info!(
+        user_id = %user.user_id,
+        deletion_id = %deletion.id,
+        family_data_policy = policy.as_str(),
+        "Scheduled account deletion"
+    );
+
+    json_response(
+        202,
+        &ApiResponse {
+            success: true,
+            data: Some(AccountDeletionResponse::from(deletion)),
+            error: None,
+        },
+    )
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .delete("/account", delete_account)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
@@ -0,0 +1,236 @@
+//! API Keys Lambda - Personal API keys for scripts and home automation.
+//!
+//! Endpoints:
+//! - GET /api-keys - Your keys, including revoked and expired ones
+//! - POST /api-keys - Issue a key (`name`, `scope` of `read` or `write`,
+//!   optional `expiresAt`); the key is only returned now
+//! - DELETE /api-keys/{id} - Revoke a key
+//!
+//! Keys are sent in the `X-Api-Key` header and accepted wherever Cognito
+//! tokens are (see `shared::api_keys`). Keys and device tokens can't be used
+//! to manage keys.
+
+use chrono::{DateTime, Utc};
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::{Deserialize, Serialize};
+use shared::api_keys::{self, ApiKey, Scope};
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::RateLimit;
+use shared::router::{
+    require_interactive_user, Cors, PathParams, RequestLogger, RequireAuth, Router,
+};
+use shared::shaping::ResponseShaping;
+use shared::ApiError;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Create key request
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct CreateKeyRequest {
+    name: String,
+    scope: Scope,
+    /// Never expires if left out
+    expires_at: Option<DateTime<Utc>>,
+}
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+/// GET /api-keys
+#[utoipa::path(
+    get,
+    path = "/api-keys",
+    tag = "api-keys",
+    responses(
+        (status = 200, description = "Your keys, including revoked and expired ones", body = ApiResponse<Vec<ApiKey>>),
+        (status = 403, description = "Made with an API key or device token", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_keys(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_interactive_user(&event, &state.db_pool).await?;
+
+    let keys: Vec<ApiKey> = api_keys::list_keys(&state.db_pool, user.user_id).await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(keys),
+            error: None,
+        },
+    )
+}
+
+/// POST /api-keys
+#[utoipa::path(
+    post,
+    path = "/api-keys",
+    tag = "api-keys",
+    request_body = CreateKeyRequest,
+    responses(
+        (status = 201, description = "The key; only returned now", body = ApiResponse<api_keys::IssuedKey>),
+        (status = 400, description = "Invalid name or expiry", body = ApiError, content_type = "application/problem+json"),
+        (status = 403, description = "Made with an API key or device token", body = ApiError, content_type = "application/problem+json"),
+        (status = 409, description = "A key with that name already exists", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn create_key(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_interactive_user(&event, &state.db_pool).await?;
+
+    let request: CreateKeyRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let issued = match api_keys::create_key(
+        &state.db_pool,
+        user.user_id,
+        &request.name,
+        request.scope,
+        request.expires_at,
+    )
+    .await
+    {
+        Ok(issued) => issued,
+        Err(shared::Error::Validation(e)) => return error_response(400, e),
+        Err(shared::Error::Conflict(e)) => return error_response(409, e),
+        Err(e) => return Err(e.into()),
+    };
+
+    info!(
+        key_id = %issued.api_key.id,
+        scope = %issued.api_key.scope,
+        "Created API key"
+    );
+
+    json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(issued),
+            error: None,
+        },
+    )
+}
+
+/// DELETE /api-keys/{id}
+#[utoipa::path(
+    delete,
+    path = "/api-keys/{id}",
+    tag = "api-keys",
+    params(
+        ("id" = Uuid, Path, description = "API key ID"),
+    ),
+    responses(
+        (status = 200, description = "The key was revoked", body = ApiResponse<serde_json::Value>),
+        (status = 404, description = "Not one of your keys", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn revoke_key(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_interactive_user(&event, &state.db_pool).await?;
+    let key_id = match params.get::<Uuid>("id") {
+        Ok(id) => id,
+        Err(_) => return error_response(400, "Invalid API key ID"),
+    };
+
+    let revoked = api_keys::revoke_key(&state.db_pool, key_id, user.user_id).await?;
+
+    if !revoked {
+        return error_response(404, "API key not found");
+    }
+
+    info!(key_id = %key_id, "Revoked API key");
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({ "message": "API key revoked" })),
+            error: None,
+        },
+    )
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .get("/api-keys", list_keys)
+        .post("/api-keys", create_key)
+        .delete("/api-keys/{id}", revoke_key)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
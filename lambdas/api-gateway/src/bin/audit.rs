@@ -0,0 +1,268 @@
+//! Audit Lambda - Who changed what.
+//!
+//! Endpoints:
+//! - GET /audit - Changes to the caller's data and changes the caller made,
+//!   most recent first (`?resourceType=&resourceId=&limit=&cursor=`)
+//! - GET /audit?familyId= - Changes to a family's data, for the family's
+//!   admins and creator
+//!
+//! Entries are written by the mutating endpoints through `shared::audit`.
+
+use chrono::{DateTime, Utc};
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::Serialize;
+use shared::audit::AuditResource;
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::models::{keyset_predicate, Cursor, CursorParams, Page, SortDirection};
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, Query, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::ApiError;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Entries listed when `limit` isn't given
+const DEFAULT_LIMIT: i64 = 50;
+
+/// Audit entry row from database
+#[derive(Debug, sqlx::FromRow)]
+struct AuditRow {
+    id: Uuid,
+    actor_id: Uuid,
+    action: String,
+    resource_type: String,
+    resource_id: Uuid,
+    family_id: Option<Uuid>,
+    before: Option<serde_json::Value>,
+    after: Option<serde_json::Value>,
+    created_at: DateTime<Utc>,
+}
+
+/// Audit entry API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct AuditEntryResponse {
+    id: String,
+    actor_id: String,
+    action: String,
+    resource_type: String,
+    resource_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    family_id: Option<String>,
+    before: Option<serde_json::Value>,
+    after: Option<serde_json::Value>,
+    created_at: String,
+}
+
+impl From<AuditRow> for AuditEntryResponse {
+    fn from(row: AuditRow) -> Self {
+        Self {
+            id: row.id.to_string(),
+            actor_id: row.actor_id.to_string(),
+            action: row.action,
+            resource_type: row.resource_type,
+            resource_id: row.resource_id.to_string(),
+            family_id: row.family_id.map(|id| id.to_string()),
+            before: row.before,
+            after: row.after,
+            created_at: row.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+/// GET /audit
+#[utoipa::path(
+    get,
+    path = "/audit",
+    tag = "audit",
+    params(
+        ("familyId" = Option<Uuid>, Query, description = "A family's log instead of your own, for its admins"),
+        ("resourceType" = Option<String>, Query, description = "Only changes to this kind of resource"),
+        ("resourceId" = Option<Uuid>, Query, description = "Only changes to this resource"),
+        ("limit" = Option<i64>, Query, description = "Most entries to return"),
+        ("cursor" = Option<String>, Query, description = "Cursor returned with the previous page"),
+    ),
+    responses(
+        (status = 200, description = "Changes, most recent first", body = ApiResponse<Page<AuditEntryResponse>>),
+        (status = 400, description = "Invalid filter or cursor", body = ApiError, content_type = "application/problem+json"),
+        (status = 403, description = "Not an admin of the family", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_audit(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let query = Query::from_request(&event);
+
+    let family_id = match query.get::<Uuid>("familyId") {
+        Ok(family_id) => family_id,
+        Err(e) => return error_response(400, e.to_string()),
+    };
+    let resource_type = match query.first("resourceType") {
+        Some(value) => match AuditResource::parse(value) {
+            Some(resource) => Some(resource.as_str()),
+            None => {
+                let valid: Vec<&str> = AuditResource::ALL.iter().map(|r| r.as_str()).collect();
+                return error_response(
+                    400,
+                    format!("resourceType must be one of: {}", valid.join(", ")),
+                );
+            }
+        },
+        None => None,
+    };
+    let resource_id = match query.get::<Uuid>("resourceId") {
+        Ok(resource_id) => resource_id,
+        Err(e) => return error_response(400, e.to_string()),
+    };
+    let page_params = match CursorParams::from_query(
+        query.first("limit"),
+        query.first("cursor"),
+        DEFAULT_LIMIT,
+    ) {
+        Ok(p) => p,
+        Err(e) => return error_response(400, e.to_string()),
+    };
+
+    // A family's log is for the people who run it
+    if let Some(family_id) = family_id {
+        let can_read: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM families WHERE id = $1 AND created_by = $2
+            ) OR EXISTS (
+                SELECT 1 FROM family_members
+                WHERE family_id = $1 AND user_id = $2 AND role = 'admin'
+            )
+            "#,
+        )
+        .bind(family_id)
+        .bind(user.user_id)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+        if !can_read {
+            return error_response(403, "Only family admins can view the family's audit log");
+        }
+    }
+
+    let rows: Vec<AuditRow> = sqlx::query_as(&format!(
+        r#"
+        SELECT id, actor_id, action, resource_type, resource_id, family_id,
+               before, after, created_at
+        FROM audit_log
+        WHERE CASE WHEN $1::uuid IS NULL
+                   THEN owner_user_id = $2 OR actor_id = $2
+                   -- Changes to a space's rows stay with its members
+                   ELSE family_id = $1 AND (
+                       COALESCE(after, before)->>'space_id' IS NULL
+                       OR EXISTS (
+                           SELECT 1 FROM family_space_members sm
+                           WHERE sm.space_id = (COALESCE(after, before)->>'space_id')::uuid
+                             AND sm.user_id = $2
+                       )
+                   )
+              END
+          AND ($3::text IS NULL OR resource_type = $3)
+          AND ($4::uuid IS NULL OR resource_id = $4)
+          AND {}
+        ORDER BY created_at DESC, id DESC
+        LIMIT $7
+        "#,
+        keyset_predicate("created_at", "timestamptz", "id", 5, SortDirection::Desc)
+    ))
+    .bind(family_id)
+    .bind(user.user_id)
+    .bind(resource_type)
+    .bind(resource_id)
+    .bind(page_params.after_key())
+    .bind(page_params.after_id())
+    .bind(page_params.fetch_limit())
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let page = Page::from_rows(rows, &page_params, |row| {
+        Cursor::new(row.created_at.to_rfc3339(), row.id)
+    })
+    .map(AuditEntryResponse::from);
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(page),
+            error: None,
+        },
+    )
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .get("/audit", list_audit)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
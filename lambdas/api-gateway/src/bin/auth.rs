@@ -0,0 +1,421 @@
+//! Auth Lambda - OAuth device authorization for CLI and TV clients.
+//!
+//! Endpoints:
+//! - POST /auth/device/code - Start signing a device in (public; `client_name`)
+//! - POST /auth/token - Poll for tokens with `grant_type` of
+//!   `urn:ietf:params:oauth:grant-type:device_code` and `device_code`, or
+//!   refresh them with `refresh_token` (public)
+//! - POST /auth/device/approve - Approve or deny a user code (`userCode`,
+//!   `approve`)
+//! - GET /auth/devices - Devices you have signed in
+//! - DELETE /auth/devices/{id} - Sign a device out
+//!
+//! The public endpoints follow RFC 8628 and RFC 6749: they take JSON or form
+//! bodies and answer token errors as `{"error": "authorization_pending", ...}`
+//! so off-the-shelf OAuth clients work. See `shared::device_auth`.
+
+use lambda_http::http::header::CONTENT_TYPE;
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use shared::device_auth::{
+    self, DeviceSession, TokenError, DEVICE_CODE_GRANT, REFRESH_TOKEN_GRANT,
+};
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::RateLimit;
+use shared::router::{
+    require_interactive_user, require_user, Cors, PathParams, RequestLogger, Router,
+};
+use shared::shaping::ResponseShaping;
+use shared::ApiError;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Device code request
+#[derive(Debug, Deserialize, ToSchema)]
+struct DeviceCodeRequest {
+    #[serde(alias = "client_id")]
+    client_name: String,
+}
+
+/// Token request
+#[derive(Debug, Deserialize, ToSchema)]
+struct TokenRequest {
+    grant_type: String,
+    device_code: Option<String>,
+    refresh_token: Option<String>,
+}
+
+/// Approve request
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct ApproveRequest {
+    user_code: String,
+    /// `false` denies the code
+    #[serde(default = "approve_by_default")]
+    approve: bool,
+}
+
+fn approve_by_default() -> bool {
+    true
+}
+
+/// Approve response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct ApproveResponse {
+    client_name: String,
+    /// `approved` or `denied`
+    status: &'static str,
+}
+
+/// OAuth error response (RFC 6749 section 5.2)
+#[derive(Debug, Serialize, ToSchema)]
+struct OAuthError {
+    error: &'static str,
+    error_description: &'static str,
+}
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+    /// Web page where users enter codes
+    verification_uri: String,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        let app_url =
+            std::env::var("APP_URL").unwrap_or_else(|_| "https://secondbrain.app".to_string());
+
+        Ok(Self {
+            db_pool,
+            verification_uri: format!("{}/device", app_url.trim_end_matches('/')),
+        })
+    }
+}
+
+/// Parse a form-encoded or JSON body, as OAuth clients send either.
+fn parse_oauth_body<T: DeserializeOwned>(event: &Request) -> Result<T, String> {
+    let is_form = event
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/x-www-form-urlencoded"));
+
+    if is_form {
+        serde_urlencoded::from_bytes(event.body().as_ref()).map_err(|e| e.to_string())
+    } else {
+        serde_json::from_slice(event.body().as_ref()).map_err(|e| e.to_string())
+    }
+}
+
+/// POST /auth/device/code
+#[utoipa::path(
+    post,
+    path = "/auth/device/code",
+    tag = "auth",
+    request_body(content = DeviceCodeRequest, description = "JSON or form-encoded"),
+    responses(
+        (status = 200, description = "The codes to show the user and poll with", body = device_auth::DeviceCode),
+        (status = 400, description = "Invalid client name", body = ApiError, content_type = "application/problem+json"),
+    )
+)]
+async fn device_code(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let request: DeviceCodeRequest = match parse_oauth_body(&event) {
+        Ok(r) => r,
+        Err(e) => return error_response(400, format!("Invalid request: {}", e)),
+    };
+
+    let code = match device_auth::start(
+        &state.db_pool,
+        &request.client_name,
+        &state.verification_uri,
+    )
+    .await
+    {
+        Ok(code) => code,
+        Err(shared::Error::Validation(e)) => return error_response(400, e),
+        Err(e) => return Err(e.into()),
+    };
+
+    info!(client_name = %request.client_name.trim(), "Started device authorization");
+
+    json_response(200, &code)
+}
+
+/// POST /auth/token
+#[utoipa::path(
+    post,
+    path = "/auth/token",
+    tag = "auth",
+    request_body(content = TokenRequest, description = "JSON or form-encoded"),
+    responses(
+        (status = 200, description = "Tokens for the device", body = device_auth::Tokens),
+        (status = 400, description = "Not issued, e.g. `authorization_pending` while the user hasn't approved the code yet", body = OAuthError),
+    )
+)]
+async fn token(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let request: TokenRequest = match parse_oauth_body(&event) {
+        Ok(r) => r,
+        Err(e) => return error_response(400, format!("Invalid request: {}", e)),
+    };
+
+    let issued = match (
+        request.grant_type.as_str(),
+        request.device_code,
+        request.refresh_token,
+    ) {
+        (DEVICE_CODE_GRANT, Some(device_code), _) => {
+            device_auth::poll(&state.db_pool, &device_code).await
+        }
+        (REFRESH_TOKEN_GRANT, _, Some(refresh_token)) => {
+            device_auth::refresh(&state.db_pool, &refresh_token).await
+        }
+        (DEVICE_CODE_GRANT, None, _) | (REFRESH_TOKEN_GRANT, _, None) => {
+            return json_response(
+                400,
+                &OAuthError {
+                    error: "invalid_request",
+                    error_description: "device_code or refresh_token is required",
+                },
+            )
+        }
+        _ => {
+            return json_response(
+                400,
+                &OAuthError {
+                    error: "unsupported_grant_type",
+                    error_description: "Use the device_code or refresh_token grant",
+                },
+            )
+        }
+    }?;
+
+    match issued {
+        Ok(tokens) => json_response(200, &tokens),
+        Err(e) => {
+            if e != TokenError::AuthorizationPending {
+                info!(error = e.as_str(), "Refused token request");
+            }
+            json_response(
+                400,
+                &OAuthError {
+                    error: e.as_str(),
+                    error_description: e.description(),
+                },
+            )
+        }
+    }
+}
+
+/// POST /auth/device/approve
+#[utoipa::path(
+    post,
+    path = "/auth/device/approve",
+    tag = "auth",
+    request_body = ApproveRequest,
+    responses(
+        (status = 200, description = "The code was approved or denied", body = ApiResponse<ApproveResponse>),
+        (status = 403, description = "Made with an API key or device token", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Code not found or expired", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn approve_device(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    // A device session approved by a key or another device would outlive
+    // their revocation
+    let user = require_interactive_user(&event, &state.db_pool).await?;
+
+    let request: ApproveRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let client_name = device_auth::approve(
+        &state.db_pool,
+        &request.user_code,
+        user.user_id,
+        request.approve,
+    )
+    .await?;
+
+    let Some(client_name) = client_name else {
+        return error_response(404, "Code not found or expired");
+    };
+
+    let status = if request.approve {
+        "approved"
+    } else {
+        "denied"
+    };
+    info!(client_name = %client_name, status, "Answered device authorization");
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(ApproveResponse {
+                client_name,
+                status,
+            }),
+            error: None,
+        },
+    )
+}
+
+/// GET /auth/devices
+#[utoipa::path(
+    get,
+    path = "/auth/devices",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Devices you have signed in", body = ApiResponse<Vec<DeviceSession>>),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_devices(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let sessions: Vec<DeviceSession> =
+        device_auth::list_sessions(&state.db_pool, user.user_id).await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(sessions),
+            error: None,
+        },
+    )
+}
+
+/// DELETE /auth/devices/{id}
+#[utoipa::path(
+    delete,
+    path = "/auth/devices/{id}",
+    tag = "auth",
+    params(
+        ("id" = Uuid, Path, description = "Device ID"),
+    ),
+    responses(
+        (status = 200, description = "The device was signed out", body = ApiResponse<serde_json::Value>),
+        (status = 404, description = "Not one of your devices", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn sign_out_device(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let session_id = match params.get::<Uuid>("id") {
+        Ok(id) => id,
+        Err(_) => return error_response(400, "Invalid device ID"),
+    };
+
+    let revoked = device_auth::revoke_session(&state.db_pool, session_id, user.user_id).await?;
+
+    if !revoked {
+        return error_response(404, "Device not found");
+    }
+
+    info!(session_id = %session_id, "Signed out device");
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({ "message": "Device signed out" })),
+            error: None,
+        },
+    )
+}
+
+/// Route table.
+///
+/// No `RequireAuth` layer: devices call the code and token endpoints before
+/// they have credentials, and the other handlers resolve the user
+/// themselves.
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(
+            RateLimit::default()
+                .public("POST", "/auth/device/code")
+                .public("POST", "/auth/token"),
+        )
+        .post("/auth/device/code", device_code)
+        .post("/auth/token", token)
+        .post("/auth/device/approve", approve_device)
+        .get("/auth/devices", list_devices)
+        .delete("/auth/devices/{id}", sign_out_device)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
@@ -1,17 +1,220 @@
-//! Briefing Lambda - Handles /v1/briefing endpoint.
+//! Briefing Lambda - Serves stored briefings.
+//!
+//! Endpoints:
+//! - GET /briefings/today - Today's briefing (`?type=morning|evening&regenerate=true`;
+//!   `audio=true` adds a URL to the briefing read aloud, `rate=slow|fast|...` sets its pace)
+//! - GET /briefing - Alias for `/briefings/today`
+//!
+//! Briefings are pre-generated by the scheduled dispatcher and returned straight
+//! from `briefing_history`. Live generation through the agent (several seconds)
+//! only happens when today's briefing is missing or `regenerate=true`.
 
 use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::Serialize;
+use shared::briefings::{
+    briefing_audio, generate_briefing, parse_briefing_type, todays_briefing, StoredBriefing,
+};
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, Query, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::tts::{SpeechRate, TtsService, VoiceOptions};
+use shared::{AgentClient, ApiError, AuthorizedUser};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{error, info, warn};
 use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
 
-async fn handler(_event: Request) -> Result<Response<Body>, Error> {
-    // TODO: Implement morning briefing generation
-    let response = Response::builder()
-        .status(200)
-        .header("content-type", "application/json")
-        .body(Body::from(r#"{"message": "Briefing endpoint - not yet implemented"}"#))
-        .map_err(Box::new)?;
+/// Briefing API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct BriefingResponse {
+    #[serde(flatten)]
+    briefing: StoredBriefing,
+    /// True when served from the pre-generated copy
+    cached: bool,
+    /// Presigned URL of the briefing read aloud, when `audio=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    audio_url: Option<String>,
+}
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+    agent_client: AgentClient,
+    tts: TtsService,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        let lambda_client = aws_sdk_lambda::Client::new(&config);
 
-    Ok(response)
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        let agent_function = std::env::var("AGENT_FUNCTION_NAME")
+            .unwrap_or_else(|_| "second-brain-agents".to_string());
+
+        Ok(Self {
+            db_pool,
+            agent_client: AgentClient::new(lambda_client, agent_function),
+            tts: TtsService::from_env(&config),
+        })
+    }
+}
+
+/// GET /briefings/today
+#[utoipa::path(
+    get,
+    path = "/briefings/today",
+    tag = "briefings",
+    params(
+        ("type" = Option<String>, Query, description = "`morning` (default) or `evening`"),
+        ("regenerate" = Option<bool>, Query, description = "Generate it again instead of serving the stored copy"),
+        ("audio" = Option<bool>, Query, description = "Add a URL to the briefing read aloud"),
+        ("rate" = Option<String>, Query, description = "Pace of the audio: `x-slow`, `slow`, `medium`, `fast` or `x-fast`"),
+    ),
+    responses(
+        (status = 200, description = "Today's briefing", body = ApiResponse<BriefingResponse>),
+        (status = 400, description = "Invalid type or rate", body = ApiError, content_type = "application/problem+json"),
+        (status = 502, description = "The briefing couldn't be generated or read aloud", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn get_todays_briefing(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let query = Query::from_request(&event);
+    let briefing_type = match parse_briefing_type(query.first("type")) {
+        Ok(t) => t,
+        Err(e) => return error_response(400, e.to_string()),
+    };
+    let regenerate = match query.get::<bool>("regenerate") {
+        Ok(regenerate) => regenerate.unwrap_or(false),
+        Err(e) => return error_response(400, e.to_string()),
+    };
+    let audio = match query.get::<bool>("audio") {
+        Ok(audio) => audio.unwrap_or(false),
+        Err(e) => return error_response(400, e.to_string()),
+    };
+    let mut voice = VoiceOptions::from_env();
+    if let Some(rate) = query.first("rate") {
+        match SpeechRate::parse(rate) {
+            Some(rate) => voice = voice.with_rate(rate),
+            None => {
+                return error_response(
+                    400,
+                    "Invalid rate. Must be one of: x-slow, slow, medium, fast, x-fast",
+                )
+            }
+        }
+    }
+
+    let stored = if regenerate {
+        None
+    } else {
+        todays_briefing(&state.db_pool, user.user_id, briefing_type).await?
+    };
+    let cached = stored.is_some();
+
+    let briefing = match stored {
+        Some(briefing) => briefing,
+        None => match generate_live(&state, &user, briefing_type, regenerate).await? {
+            Ok(briefing) => briefing,
+            Err(response) => return Ok(response),
+        },
+    };
+
+    let audio_url = if audio {
+        match briefing_audio(&state.tts, &briefing, &voice).await {
+            Ok(speech) => speech.url,
+            Err(e) => {
+                warn!("Failed to synthesize briefing audio: {}", e);
+                return error_response(502, "Failed to synthesize briefing audio");
+            }
+        }
+    } else {
+        None
+    };
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(BriefingResponse {
+                briefing,
+                cached,
+                audio_url,
+            }),
+            error: None,
+        },
+    )
+}
+
+/// Generate today's briefing through the agent, or the error response to send.
+async fn generate_live(
+    state: &AppState,
+    user: &AuthorizedUser,
+    briefing_type: &str,
+    regenerate: bool,
+) -> Result<Result<StoredBriefing, Response<Body>>, Error> {
+    info!(user_id = %user.user_id, briefing_type, regenerate, "Generating briefing live");
+
+    match generate_briefing(
+        &state.db_pool,
+        &state.agent_client,
+        user.user_id,
+        &user.family_ids,
+        briefing_type,
+        "api",
+    )
+    .await
+    {
+        Ok(briefing) => Ok(Ok(briefing)),
+        Err(shared::Error::Database(e)) => Err(format!("Failed to store briefing: {}", e).into()),
+        Err(e) => {
+            error!("Briefing generation failed: {}", e);
+            Ok(Err(error_response(502, "Failed to generate briefing")?))
+        }
+    }
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .get("/briefings/today", get_todays_briefing)
+        .get("/briefing", get_todays_briefing)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
 }
 
 #[tokio::main]
@@ -21,5 +224,13 @@ async fn main() -> Result<(), Error> {
         .json()
         .init();
 
-    run(service_fn(handler)).await
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
 }
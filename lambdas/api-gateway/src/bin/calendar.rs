@@ -1,19 +1,473 @@
-//! Calendar Lambda - Handles /v1/calendar endpoint.
+//! Calendar Lambda - Handles /v1/calendar endpoints.
+//!
+//! Endpoints:
+//! - GET /calendar - Calendar operations (not yet implemented)
+//! - GET /calendar/subscriptions - List the user's iCal feed subscriptions
+//! - POST /calendar/subscriptions - Subscribe to an iCal feed URL
+//! - DELETE /calendar/subscriptions/{id} - Unsubscribe and remove imported events
+//! - GET /calendar/extraction - Get attendee/fact extraction preferences
+//! - PUT /calendar/extraction - Update extraction preferences
+//!
+//! Subscribed feeds are imported, and extraction preferences applied, by
+//! `calendar_sync` on its next run.
 
+use chrono::{DateTime, Utc};
 use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::{Deserialize, Serialize};
+use shared::calendar_extraction::ExtractionPreferences;
+use shared::http::error_response;
+use shared::ical::{display_feed_url, normalize_feed_url};
+use shared::metrics::RequestMetrics;
+use shared::public_http;
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::ApiError;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
 use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+use uuid::Uuid;
 
-async fn handler(_event: Request) -> Result<Response<Body>, Error> {
+/// Longest subscription name accepted
+const MAX_NAME_LENGTH: usize = 255;
+
+/// Calendar subscription row from database
+#[derive(Debug, sqlx::FromRow)]
+struct SubscriptionRow {
+    id: Uuid,
+    feed_url: String,
+    name: Option<String>,
+    last_synced_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+/// Subscribe request
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct SubscribeRequest {
+    /// Public or private iCal URL (`webcal://` is accepted)
+    url: String,
+    name: Option<String>,
+}
+
+/// Calendar subscription API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct SubscriptionResponse {
+    id: String,
+    /// Scheme and host only; private feed URLs embed a secret
+    url: String,
+    name: Option<String>,
+    last_synced_at: Option<String>,
+    last_error: Option<String>,
+    created_at: String,
+}
+
+impl From<SubscriptionRow> for SubscriptionResponse {
+    fn from(row: SubscriptionRow) -> Self {
+        Self {
+            id: row.id.to_string(),
+            url: display_feed_url(&row.feed_url),
+            name: row.name,
+            last_synced_at: row.last_synced_at.map(|t| t.to_rfc3339()),
+            last_error: row.last_error,
+            created_at: row.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Update extraction preferences request (omitted fields are unchanged)
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct ExtractionPreferencesRequest {
+    enabled: Option<bool>,
+    create_entities: Option<bool>,
+    record_facts: Option<bool>,
+}
+
+/// Extraction preferences API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct ExtractionPreferencesResponse {
+    enabled: bool,
+    create_entities: bool,
+    record_facts: bool,
+}
+
+impl From<ExtractionPreferences> for ExtractionPreferencesResponse {
+    fn from(prefs: ExtractionPreferences) -> Self {
+        Self {
+            enabled: prefs.enabled,
+            create_entities: prefs.create_entities,
+            record_facts: prefs.record_facts,
+        }
+    }
+}
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+/// GET /calendar
+async fn calendar(
+    _state: Arc<AppState>,
+    _event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
     // TODO: Implement calendar operations
     let response = Response::builder()
         .status(200)
         .header("content-type", "application/json")
-        .body(Body::from(r#"{"message": "Calendar endpoint - not yet implemented"}"#))
+        .body(Body::from(
+            r#"{"message": "Calendar endpoint - not yet implemented"}"#,
+        ))
         .map_err(Box::new)?;
 
     Ok(response)
 }
 
+/// GET /calendar/subscriptions
+#[utoipa::path(
+    get,
+    path = "/calendar/subscriptions",
+    tag = "calendar",
+    responses(
+        (status = 200, description = "Your iCal feed subscriptions", body = ApiResponse<Vec<SubscriptionResponse>>),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_subscriptions(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let subscriptions: Vec<SubscriptionRow> = sqlx::query_as(
+        r#"
+        SELECT id, feed_url, name, last_synced_at, last_error, created_at
+        FROM calendar_subscriptions
+        WHERE user_id = $1
+        ORDER BY created_at
+        "#,
+    )
+    .bind(user.user_id)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(
+                subscriptions
+                    .into_iter()
+                    .map(SubscriptionResponse::from)
+                    .collect::<Vec<_>>(),
+            ),
+            error: None,
+        },
+    )
+}
+
+/// POST /calendar/subscriptions
+#[utoipa::path(
+    post,
+    path = "/calendar/subscriptions",
+    tag = "calendar",
+    request_body = SubscribeRequest,
+    responses(
+        (status = 201, description = "The subscription; the feed is imported on the next sync", body = ApiResponse<SubscriptionResponse>),
+        (status = 400, description = "Invalid feed URL or name", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn subscribe(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let request: SubscribeRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let feed_url = match normalize_feed_url(&request.url) {
+        Ok(url) => url,
+        Err(e) => return error_response(400, e.to_string()),
+    };
+    // The host name must not resolve to a private address either
+    public_http::ensure_public(&url::Url::parse(&feed_url)?).await?;
+
+    let name = request
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty());
+    if name.is_some_and(|n| n.chars().count() > MAX_NAME_LENGTH) {
+        return error_response(400, "name is too long");
+    }
+
+    // Re-subscribing to the same feed renames it and retries it from scratch
+    let subscription: SubscriptionRow = sqlx::query_as(
+        r#"
+        INSERT INTO calendar_subscriptions (user_id, feed_url, name)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, feed_url) DO UPDATE
+        SET name = COALESCE(EXCLUDED.name, calendar_subscriptions.name),
+            etag = NULL,
+            last_modified = NULL,
+            last_error = NULL,
+            updated_at = NOW()
+        RETURNING id, feed_url, name, last_synced_at, last_error, created_at
+        "#,
+    )
+    .bind(user.user_id)
+    .bind(&feed_url)
+    .bind(name)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    info!(user_id = %user.user_id, subscription_id = %subscription.id, "Subscribed to calendar feed");
+
+    json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(SubscriptionResponse::from(subscription)),
+            error: None,
+        },
+    )
+}
+
+/// DELETE /calendar/subscriptions/{id}
+#[utoipa::path(
+    delete,
+    path = "/calendar/subscriptions/{id}",
+    tag = "calendar",
+    params(
+        ("id" = Uuid, Path, description = "Subscription ID"),
+    ),
+    responses(
+        (status = 200, description = "Unsubscribed, and the feed's events removed"),
+        (status = 404, description = "Not one of your subscriptions", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn unsubscribe(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let subscription_id = match params.get::<Uuid>("id") {
+        Ok(id) => id,
+        Err(_) => return error_response(400, "Invalid subscription ID"),
+    };
+
+    let mut tx = state.db_pool.begin().await?;
+
+    let result = sqlx::query("DELETE FROM calendar_subscriptions WHERE id = $1 AND user_id = $2")
+        .bind(subscription_id)
+        .bind(user.user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return error_response(404, "Subscription not found");
+    }
+
+    let removed = sqlx::query(
+        r#"
+        DELETE FROM calendar_events
+        WHERE user_id = $1 AND external_provider = 'ics' AND external_calendar_id = $2
+        "#,
+    )
+    .bind(user.user_id)
+    .bind(subscription_id.to_string())
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    info!(
+        user_id = %user.user_id,
+        subscription_id = %subscription_id,
+        events_removed = removed.rows_affected(),
+        "Unsubscribed from calendar feed"
+    );
+
+    json_response(
+        200,
+        &ApiResponse::<()> {
+            success: true,
+            data: None,
+            error: None,
+        },
+    )
+}
+
+/// The user's extraction preferences (defaults if never set)
+async fn get_extraction_preferences(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<ExtractionPreferences, Error> {
+    let prefs: Option<ExtractionPreferences> = sqlx::query_as(
+        r#"
+        SELECT enabled, create_entities, record_facts
+        FROM calendar_extraction_preferences
+        WHERE user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(prefs.unwrap_or_default())
+}
+
+/// GET /calendar/extraction
+#[utoipa::path(
+    get,
+    path = "/calendar/extraction",
+    tag = "calendar",
+    responses(
+        (status = 200, description = "Your attendee and fact extraction preferences", body = ApiResponse<ExtractionPreferencesResponse>),
+    ),
+    security(("cognito" = []))
+)]
+async fn extraction_preferences(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let prefs = get_extraction_preferences(&state.db_pool, user.user_id).await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(ExtractionPreferencesResponse::from(prefs)),
+            error: None,
+        },
+    )
+}
+
+/// PUT /calendar/extraction
+#[utoipa::path(
+    put,
+    path = "/calendar/extraction",
+    tag = "calendar",
+    request_body = ExtractionPreferencesRequest,
+    responses(
+        (status = 200, description = "The updated preferences", body = ApiResponse<ExtractionPreferencesResponse>),
+    ),
+    security(("cognito" = []))
+)]
+async fn update_extraction_preferences(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let request: ExtractionPreferencesRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let current = get_extraction_preferences(&state.db_pool, user.user_id).await?;
+    let prefs = ExtractionPreferences {
+        enabled: request.enabled.unwrap_or(current.enabled),
+        create_entities: request.create_entities.unwrap_or(current.create_entities),
+        record_facts: request.record_facts.unwrap_or(current.record_facts),
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO calendar_extraction_preferences (user_id, enabled, create_entities, record_facts)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id) DO UPDATE
+        SET enabled = EXCLUDED.enabled,
+            create_entities = EXCLUDED.create_entities,
+            record_facts = EXCLUDED.record_facts,
+            updated_at = NOW()
+        "#,
+    )
+    .bind(user.user_id)
+    .bind(prefs.enabled)
+    .bind(prefs.create_entities)
+    .bind(prefs.record_facts)
+    .execute(&state.db_pool)
+    .await?;
+
+    info!(user_id = %user.user_id, ?prefs, "Updated calendar extraction preferences");
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(ExtractionPreferencesResponse::from(prefs)),
+            error: None,
+        },
+    )
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .get("/calendar", calendar)
+        .get("/calendar/subscriptions", list_subscriptions)
+        .post("/calendar/subscriptions", subscribe)
+        .delete("/calendar/subscriptions/{id}", unsubscribe)
+        .get("/calendar/extraction", extraction_preferences)
+        .put("/calendar/extraction", update_extraction_preferences)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     tracing_subscriber::fmt()
@@ -21,5 +475,13 @@ async fn main() -> Result<(), Error> {
         .json()
         .init();
 
-    run(service_fn(handler)).await
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
 }
@@ -5,7 +5,11 @@
 
 use lambda_http::{run, service_fn, Body, Error, Request, RequestExt, Response};
 use serde::{Deserialize, Serialize};
-use shared::ApiResponse;
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::RateLimit;
+use shared::router::{Cors, PathParams, RequestLogger, Router};
+use shared::shaping::ResponseShaping;
+use shared::{error_response, ApiError, ApiResponse};
 use std::sync::Arc;
 use tracing::{error, info};
 use tracing_subscriber::EnvFilter;
@@ -46,18 +50,11 @@ impl AppState {
         let secret_arn = std::env::var("GOOGLE_OAUTH_SECRET_ARN")
             .unwrap_or_else(|_| "second-brain/google-oauth".to_string());
 
-        let secret_value = secrets_client
-            .get_secret_value()
-            .secret_id(&secret_arn)
-            .send()
+        let secret_string = shared::secrets::get_secret(&secrets_client, &secret_arn)
             .await
             .map_err(|e| format!("Failed to get Google OAuth secret: {}", e))?;
 
-        let secret_string = secret_value
-            .secret_string()
-            .ok_or("Secret string is empty")?;
-
-        let credentials: serde_json::Value = serde_json::from_str(secret_string)
+        let credentials: serde_json::Value = serde_json::from_str(&secret_string)
             .map_err(|e| format!("Failed to parse credentials: {}", e))?;
 
         let client_id = credentials["client_id"]
@@ -196,84 +193,100 @@ fn build_auth_url(state: &AppState, user_id: &str) -> String {
     )
 }
 
-async fn handler(state: Arc<AppState>, event: Request) -> Result<Response<Body>, Error> {
-    let raw_path = event.uri().path();
-    // Strip /api stage prefix if present (API Gateway REST API includes stage in path)
-    let path = raw_path.strip_prefix("/api").unwrap_or(raw_path);
-    let method = event.method().as_str();
-
-    match (method, path) {
-        // Initiate OAuth flow - returns URL to redirect user to
-        ("GET", "/calendar/oauth/start") => {
-            // Get user_id from query params or auth context
-            let params = event.query_string_parameters();
-            let user_id = params.first("user_id").unwrap_or("unknown").to_string();
-
-            let auth_url = build_auth_url(&state, &user_id);
-
-            let response = ApiResponse {
-                success: true,
-                data: Some(serde_json::json!({
-                    "auth_url": auth_url,
-                    "message": "Redirect user to auth_url to connect Google Calendar"
-                })),
-                error: None,
-            };
-
-            Ok(Response::builder()
-                .status(200)
-                .header("content-type", "application/json")
-                .body(Body::from(serde_json::to_string(&response)?))
-                .expect("Failed to build response"))
-        }
+/// GET /calendar/oauth/start - the Google consent URL to redirect the user to
+#[utoipa::path(
+    get,
+    path = "/calendar/oauth/start",
+    tag = "calendar",
+    params(
+        ("user_id" = String, Query, description = "The user connecting their calendar"),
+    ),
+    responses(
+        (status = 200, description = "The Google consent URL (`auth_url`) to send the user to", body = ApiResponse<serde_json::Value>),
+    )
+)]
+async fn start(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    // Get user_id from query params or auth context
+    let params = event.query_string_parameters();
+    let user_id = params.first("user_id").unwrap_or("unknown").to_string();
+
+    let auth_url = build_auth_url(&state, &user_id);
+
+    let response = ApiResponse {
+        success: true,
+        data: Some(serde_json::json!({
+            "auth_url": auth_url,
+            "message": "Redirect user to auth_url to connect Google Calendar"
+        })),
+        error: None,
+    };
+
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .body(Body::from(serde_json::to_string(&response)?))?)
+}
 
-        // OAuth callback from Google
-        ("GET", "/calendar/oauth/callback") => {
-            let params = event.query_string_parameters();
-
-            // Check for error from Google
-            if let Some(error) = params.first("error") {
-                error!("OAuth error from Google: {}", error);
-                return Ok(Response::builder()
-                    .status(400)
-                    .header("content-type", "application/json")
-                    .body(Body::from(
-                        serde_json::to_string(&ApiResponse::<()> {
-                            success: false,
-                            data: None,
-                            error: Some(format!("OAuth error: {}", error)),
-                        })?,
-                    ))
-                    .expect("Failed to build response"));
-            }
+/// GET /calendar/oauth/callback - Google redirects here with the authorization code
+#[utoipa::path(
+    get,
+    path = "/calendar/oauth/callback",
+    tag = "calendar",
+    params(
+        ("code" = String, Query, description = "Authorization code from Google"),
+        ("state" = String, Query, description = "The `state` of the consent URL"),
+    ),
+    responses(
+        (status = 200, description = "Page telling the user their calendar is connected", body = String, content_type = "text/html"),
+        (status = 400, description = "Google reported an error, or the code or state is missing", body = ApiError, content_type = "application/problem+json"),
+    )
+)]
+async fn callback(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let params = event.query_string_parameters();
+
+    // Check for error from Google
+    if let Some(error) = params.first("error") {
+        error!("OAuth error from Google: {}", error);
+        return error_response(400, format!("OAuth error: {}", error));
+    }
 
-            // Get authorization code
-            let code = params
-                .first("code")
-                .ok_or("Missing authorization code")?;
+    // Get authorization code
+    let code = params
+        .first("code")
+        .ok_or_else(|| ApiError::validation("Missing authorization code"))?;
 
-            // Get user_id from state parameter
-            let state_param = params.first("state").ok_or("Missing state parameter")?;
+    // Get user_id from state parameter
+    let state_param = params
+        .first("state")
+        .ok_or_else(|| ApiError::validation("Missing state parameter"))?;
 
-            let user_id_bytes = base64::Engine::decode(
-                &base64::engine::general_purpose::URL_SAFE_NO_PAD,
-                state_param,
-            )
-            .map_err(|e| format!("Invalid state parameter: {}", e))?;
+    let user_id_bytes = base64::Engine::decode(
+        &base64::engine::general_purpose::URL_SAFE_NO_PAD,
+        state_param,
+    )
+    .map_err(|e| ApiError::validation(format!("Invalid state parameter: {}", e)))?;
 
-            let user_id = String::from_utf8(user_id_bytes)
-                .map_err(|e| format!("Invalid user_id in state: {}", e))?;
+    let user_id = String::from_utf8(user_id_bytes)
+        .map_err(|e| ApiError::validation(format!("Invalid user_id in state: {}", e)))?;
 
-            info!("Processing OAuth callback for user {}", user_id);
+    info!("Processing OAuth callback for user {}", user_id);
 
-            // Exchange code for tokens
-            let tokens = state.exchange_code(code).await?;
+    // Exchange code for tokens
+    let tokens = state.exchange_code(code).await?;
 
-            // Store tokens
-            state.store_user_tokens(&user_id, &tokens).await?;
+    // Store tokens
+    state.store_user_tokens(&user_id, &tokens).await?;
 
-            // Return success page (or redirect to app)
-            let html = r#"
+    // Return success page (or redirect to app)
+    let html = r#"
 <!DOCTYPE html>
 <html>
 <head><title>Calendar Connected</title></head>
@@ -285,18 +298,26 @@ async fn handler(state: Arc<AppState>, event: Request) -> Result<Response<Body>,
 </html>
 "#;
 
-            Ok(Response::builder()
-                .status(200)
-                .header("content-type", "text/html")
-                .body(Body::from(html))
-                .expect("Failed to build response"))
-        }
+    Ok(Response::builder()
+        .status(200)
+        .header("content-type", "text/html")
+        .body(Body::from(html))?)
+}
 
-        _ => Ok(Response::builder()
-            .status(404)
-            .body(Body::from("Not found"))
-            .expect("Failed to build response")),
-    }
+fn router() -> Router<AppState> {
+    // Public: the callback is a browser redirect from Google
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(
+            RateLimit::default()
+                .public("GET", "/calendar/oauth/start")
+                .public("GET", "/calendar/oauth/callback"),
+        )
+        .get("/calendar/oauth/start", start)
+        .get("/calendar/oauth/callback", callback)
 }
 
 #[tokio::main]
@@ -307,10 +328,12 @@ async fn main() -> Result<(), Error> {
         .init();
 
     let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
 
     run(service_fn(move |event| {
         let state = Arc::clone(&state);
-        async move { handler(state, event).await }
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
     }))
     .await
 }
@@ -0,0 +1,429 @@
+//! Capture Lambda - Saves web pages from the browser extension.
+//!
+//! Endpoints:
+//! - POST /capture - Save the page the user is on
+//!
+//! The page is fetched and its readable text extracted server-side (see
+//! `shared::capture`). It is stored as a `bookmark` entity (captured again,
+//! the same bookmark is updated) and handed to the ingestion agent, which
+//! summarizes it into facts; the facts mention the bookmark. A page that
+//! can't be fetched is still saved from what the extension sent.
+
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::{Deserialize, Serialize};
+use shared::audit::{self, AuditAction, AuditResource};
+use shared::capture::{
+    bookmark_name, capture_message, extract_article, parse_capture_url, Article, MAX_PAGE_BYTES,
+    MAX_SELECTION_CHARS,
+};
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::public_http;
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::{AgentClient, ApiError, AuthorizedUser, EventPublisher};
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
+use url::Url;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// How long to wait for a page
+const FETCH_TIMEOUT_SECS: u64 = 10;
+
+/// Longest screenshot reference accepted
+const MAX_SCREENSHOT_REF_CHARS: usize = 1024;
+
+/// Source reported to the agents
+const SOURCE: &str = "capture";
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Page sent by the extension
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct CaptureRequest {
+    url: String,
+    title: Option<String>,
+    selected_text: Option<String>,
+    /// Where the extension stored a screenshot of the page
+    screenshot_ref: Option<String>,
+}
+
+/// What was created, for the extension to show
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct CaptureResponse {
+    capture_id: Uuid,
+    bookmark_id: Uuid,
+    title: String,
+    fact_ids: Vec<Uuid>,
+    page_fetched: bool,
+    summarized: bool,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+    agent_client: AgentClient,
+    http_client: reqwest::Client,
+    /// Set when `EVENT_BUS_NAME` is configured
+    event_publisher: Option<EventPublisher>,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        let agent_function_name = std::env::var("AGENT_FUNCTION_NAME")
+            .unwrap_or_else(|_| "second-brain-agents".to_string());
+
+        // Only connects to public addresses, redirects included
+        let http_client = public_http::client(Duration::from_secs(FETCH_TIMEOUT_SECS))?;
+
+        Ok(Self {
+            db_pool,
+            agent_client: AgentClient::new(
+                aws_sdk_lambda::Client::new(&config),
+                agent_function_name,
+            ),
+            http_client,
+            event_publisher: EventPublisher::from_env(&config),
+        })
+    }
+}
+
+/// Fetch a page and extract its article, or the reason it couldn't be read.
+async fn fetch_article(client: &reqwest::Client, url: &Url) -> Result<Article, String> {
+    let mut response = client
+        .get(url.clone())
+        .header("Accept", "text/html,application/xhtml+xml")
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+    let is_html = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("html"));
+    if !is_html {
+        return Err("not an HTML page".to_string());
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        let remaining = MAX_PAGE_BYTES - body.len();
+        body.extend_from_slice(&chunk[..chunk.len().min(remaining)]);
+        if body.len() >= MAX_PAGE_BYTES {
+            break;
+        }
+    }
+
+    Ok(extract_article(&String::from_utf8_lossy(&body)))
+}
+
+/// Create the user's bookmark for a URL, or update it if the page was
+/// captured before.
+async fn upsert_bookmark(
+    pool: &PgPool,
+    user: &AuthorizedUser,
+    url: &Url,
+    name: &str,
+    article: Option<&Article>,
+    screenshot_ref: Option<&str>,
+) -> Result<Uuid, sqlx::Error> {
+    let description = article.and_then(|a| a.description.as_deref());
+    let metadata = serde_json::json!({
+        "url": url.as_str(),
+        "site_name": article.and_then(|a| a.site_name.as_deref()),
+        "screenshot_ref": screenshot_ref,
+    });
+
+    let existing: Option<Uuid> = sqlx::query_scalar(
+        r#"
+        SELECT id FROM entities
+        WHERE owner_type = 'user' AND owner_id = $1
+          AND metadata ? 'url' AND metadata->>'url' = $2
+          AND entity_type = 'bookmark'
+          AND deleted_at IS NULL
+        ORDER BY created_at
+        LIMIT 1
+        "#,
+    )
+    .bind(user.user_id)
+    .bind(url.as_str())
+    .fetch_optional(pool)
+    .await?;
+
+    match existing {
+        Some(id) => {
+            let before = audit::snapshot(pool, AuditResource::Entity, id).await;
+            sqlx::query(
+                r#"
+                UPDATE entities
+                SET name = $2,
+                    description = COALESCE($3, description),
+                    metadata = metadata || jsonb_strip_nulls($4),
+                    updated_at = NOW()
+                WHERE id = $1
+                "#,
+            )
+            .bind(id)
+            .bind(name)
+            .bind(description)
+            .bind(&metadata)
+            .execute(pool)
+            .await?;
+            audit::record_change(
+                pool,
+                user.user_id,
+                AuditAction::Update,
+                AuditResource::Entity,
+                id,
+                before,
+            )
+            .await;
+            Ok(id)
+        }
+        None => {
+            let id = sqlx::query_scalar(
+                r#"
+                INSERT INTO entities (owner_type, owner_id, created_by, entity_type, name, description, metadata)
+                VALUES ('user', $1, $1, 'bookmark', $2, $3, jsonb_strip_nulls($4))
+                RETURNING id
+                "#,
+            )
+            .bind(user.user_id)
+            .bind(name)
+            .bind(description)
+            .bind(&metadata)
+            .fetch_one(pool)
+            .await?;
+            audit::record_change(
+                pool,
+                user.user_id,
+                AuditAction::Create,
+                AuditResource::Entity,
+                id,
+                None,
+            )
+            .await;
+            Ok(id)
+        }
+    }
+}
+
+/// POST /capture
+#[utoipa::path(
+    post,
+    path = "/capture",
+    tag = "capture",
+    request_body = CaptureRequest,
+    responses(
+        (status = 201, description = "The page was saved as a bookmark and summarized into facts", body = ApiResponse<CaptureResponse>),
+        (status = 400, description = "Invalid URL or screenshot reference", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn capture(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let request: CaptureRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let url = match parse_capture_url(&request.url) {
+        Ok(url) => url,
+        Err(e) => return error_response(400, e),
+    };
+    let screenshot_ref = request
+        .screenshot_ref
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty());
+    if screenshot_ref.is_some_and(|s| s.chars().count() > MAX_SCREENSHOT_REF_CHARS) {
+        return error_response(400, "screenshotRef is too long");
+    }
+    let selected_text: Option<String> = request
+        .selected_text
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.chars().take(MAX_SELECTION_CHARS).collect());
+
+    let article = match fetch_article(&state.http_client, &url).await {
+        Ok(article) => Some(article),
+        Err(e) => {
+            warn!(url = %url, error = %e, "Couldn't fetch captured page");
+            None
+        }
+    };
+
+    let name = bookmark_name(article.as_ref(), request.title.as_deref(), &url);
+    let bookmark_id = upsert_bookmark(
+        &state.db_pool,
+        &user,
+        &url,
+        &name,
+        article.as_ref(),
+        screenshot_ref,
+    )
+    .await?;
+
+    let message = capture_message(&name, &url, selected_text.as_deref(), article.as_ref());
+    let family_ids = user.family_ids.iter().map(|id| id.to_string()).collect();
+    let source = serde_json::json!({
+        "bookmark_id": bookmark_id,
+        "url": url.as_str(),
+    });
+
+    let (fact_ids, summarized): (Vec<Uuid>, bool) = match state
+        .agent_client
+        .ingest_with_metadata(
+            &message,
+            &user.cognito_sub,
+            family_ids,
+            SOURCE,
+            Some(source),
+        )
+        .await
+    {
+        Ok(response) => {
+            let fact_ids = response
+                .metadata
+                .and_then(|m| m.fact_ids)
+                .unwrap_or_default();
+            (fact_ids, true)
+        }
+        Err(e) => {
+            warn!(url = %url, error = %e, "Failed to summarize captured page");
+            (Vec::new(), false)
+        }
+    };
+
+    if let Some(publisher) = &state.event_publisher {
+        publisher
+            .publish_facts_created(&state.db_pool, &fact_ids, SOURCE)
+            .await;
+    }
+
+    // Facts the agent stored point back at the bookmark
+    sqlx::query(
+        r#"
+        INSERT INTO entity_mentions (fact_id, entity_id, role)
+        SELECT f.id, $2, 'reference' FROM facts f WHERE f.id = ANY($1)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(&fact_ids)
+    .bind(bookmark_id)
+    .execute(&state.db_pool)
+    .await?;
+
+    let capture_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO web_captures (
+            user_id, url, title, selected_text, screenshot_ref,
+            page_fetched, bookmark_entity_id, fact_ids
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id
+        "#,
+    )
+    .bind(user.user_id)
+    .bind(url.as_str())
+    .bind(request.title.as_deref())
+    .bind(selected_text.as_deref())
+    .bind(screenshot_ref)
+    .bind(article.is_some())
+    .bind(bookmark_id)
+    .bind(&fact_ids)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    info!(
+        user_id = %user.user_id,
+        %capture_id,
+        %bookmark_id,
+        facts = fact_ids.len(),
+        "Captured web page"
+    );
+
+    json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(CaptureResponse {
+                capture_id,
+                bookmark_id,
+                title: name,
+                fact_ids,
+                page_fetched: article.is_some(),
+                summarized,
+            }),
+            error: None,
+        },
+    )
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .post("/capture", capture)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
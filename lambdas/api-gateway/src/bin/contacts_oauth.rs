@@ -0,0 +1,529 @@
+//! Contacts OAuth Lambda - Connect and disconnect Google Contacts.
+//!
+//! Endpoints:
+//! - GET /contacts/oauth/start - Google consent URL for the signed-in user
+//! - GET /contacts/oauth/callback - Google OAuth callback (public)
+//! - GET /contacts/connection - Connection status and last sync
+//! - DELETE /contacts/connection - Revoke access and stop syncing
+//!
+//! Tokens are stored in Secrets Manager (`second-brain/contacts/{user_id}`)
+//! like calendar tokens, and the connection is recorded in
+//! `contact_connections` for `contacts_sync` to pick up.
+
+use chrono::{DateTime, Utc};
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::{Deserialize, Serialize};
+use shared::contacts::{token_secret_name, GOOGLE_CONTACTS_SCOPE, GOOGLE_PROVIDER};
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, Query, RequestLogger, Router};
+use shared::shaping::ResponseShaping;
+use shared::ApiError;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Google OAuth token response
+#[derive(Debug, Deserialize)]
+struct GoogleTokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: i64,
+    token_type: String,
+    scope: String,
+}
+
+/// Connection status API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct ConnectionResponse {
+    provider: String,
+    connected: bool,
+    connected_at: Option<DateTime<Utc>>,
+    last_synced_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    contacts_linked: i64,
+}
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+    http_client: reqwest::Client,
+    secrets_client: aws_sdk_secretsmanager::Client,
+    google_client_id: String,
+    google_client_secret: String,
+    redirect_uri: String,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        let secrets_client = aws_sdk_secretsmanager::Client::new(&config);
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        // Get Google OAuth credentials from Secrets Manager
+        let secret_arn = std::env::var("GOOGLE_OAUTH_SECRET_ARN")
+            .unwrap_or_else(|_| "second-brain/google-oauth".to_string());
+
+        let secret_string = shared::secrets::get_secret(&secrets_client, &secret_arn)
+            .await
+            .map_err(|e| format!("Failed to get Google OAuth secret: {}", e))?;
+
+        let credentials: serde_json::Value = serde_json::from_str(&secret_string)
+            .map_err(|e| format!("Failed to parse credentials: {}", e))?;
+
+        let redirect_uri = std::env::var("OAUTH_REDIRECT_URI")
+            .unwrap_or_else(|_| "https://api.example.com/contacts/oauth/callback".to_string());
+
+        Ok(Self {
+            db_pool,
+            http_client: reqwest::Client::new(),
+            secrets_client,
+            google_client_id: credentials["client_id"]
+                .as_str()
+                .ok_or("Missing client_id")?
+                .to_string(),
+            google_client_secret: credentials["client_secret"]
+                .as_str()
+                .ok_or("Missing client_secret")?
+                .to_string(),
+            redirect_uri,
+        })
+    }
+
+    /// Exchange authorization code for tokens
+    async fn exchange_code(&self, code: &str) -> Result<GoogleTokenResponse, Error> {
+        let params = [
+            ("code", code),
+            ("client_id", &self.google_client_id),
+            ("client_secret", &self.google_client_secret),
+            ("redirect_uri", &self.redirect_uri),
+            ("grant_type", "authorization_code"),
+        ];
+
+        let response = self
+            .http_client
+            .post("https://oauth2.googleapis.com/token")
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| format!("Token exchange request failed: {}", e))?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(format!("Token exchange failed: {}", error_text).into());
+        }
+
+        Ok(response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse token response: {}", e))?)
+    }
+
+    /// Store the user's contacts tokens in Secrets Manager
+    async fn store_user_tokens(
+        &self,
+        user_id: Uuid,
+        tokens: &GoogleTokenResponse,
+    ) -> Result<(), Error> {
+        let secret_name = token_secret_name(user_id);
+
+        let token_data = serde_json::json!({
+            "provider": GOOGLE_PROVIDER,
+            "access_token": tokens.access_token,
+            "refresh_token": tokens.refresh_token,
+            "expires_in": tokens.expires_in,
+            "token_type": tokens.token_type,
+            "scope": tokens.scope,
+            "updated_at": Utc::now().to_rfc3339(),
+        });
+
+        // Try to update existing secret, or create new one
+        let result = self
+            .secrets_client
+            .put_secret_value()
+            .secret_id(&secret_name)
+            .secret_string(token_data.to_string())
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if e.to_string().contains("ResourceNotFoundException") => {
+                self.secrets_client
+                    .create_secret()
+                    .name(&secret_name)
+                    .secret_string(token_data.to_string())
+                    .send()
+                    .await
+                    .map_err(|e| format!("Failed to create secret: {}", e))?;
+                Ok(())
+            }
+            Err(e) => Err(format!("Failed to store tokens: {}", e).into()),
+        }
+    }
+
+    /// Revoke the stored token at Google and delete the secret.
+    ///
+    /// Revocation is best effort; the secret is deleted either way.
+    async fn remove_user_tokens(&self, user_id: Uuid) -> Result<(), Error> {
+        let secret_name = token_secret_name(user_id);
+
+        let secret = match self
+            .secrets_client
+            .get_secret_value()
+            .secret_id(&secret_name)
+            .send()
+            .await
+        {
+            Ok(secret) => secret,
+            Err(e) if e.to_string().contains("ResourceNotFoundException") => return Ok(()),
+            Err(e) => return Err(format!("Failed to get contacts tokens: {}", e).into()),
+        };
+
+        let tokens: serde_json::Value =
+            serde_json::from_str(secret.secret_string().unwrap_or("{}")).unwrap_or_default();
+        let token = tokens["refresh_token"]
+            .as_str()
+            .or_else(|| tokens["access_token"].as_str());
+
+        if let Some(token) = token {
+            let revoked = self
+                .http_client
+                .post("https://oauth2.googleapis.com/revoke")
+                .form(&[("token", token)])
+                .send()
+                .await;
+            match revoked {
+                Ok(response) if response.status().is_success() => {}
+                Ok(response) => warn!(
+                    "Google token revocation for user {} returned {}",
+                    user_id,
+                    response.status()
+                ),
+                Err(e) => warn!("Google token revocation for user {} failed: {}", user_id, e),
+            }
+        }
+
+        self.secrets_client
+            .delete_secret()
+            .secret_id(&secret_name)
+            .force_delete_without_recovery(true)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to delete contacts tokens: {}", e))?;
+
+        Ok(())
+    }
+}
+
+/// Generate OAuth authorization URL
+fn build_auth_url(state: &AppState, user_id: Uuid) -> String {
+    // State parameter carries the user_id to the callback
+    let state_param = base64::Engine::encode(
+        &base64::engine::general_purpose::URL_SAFE_NO_PAD,
+        user_id.to_string().as_bytes(),
+    );
+
+    format!(
+        "https://accounts.google.com/o/oauth2/v2/auth?\
+        client_id={}&\
+        redirect_uri={}&\
+        response_type=code&\
+        scope={}&\
+        access_type=offline&\
+        prompt=consent&\
+        state={}",
+        urlencoding::encode(&state.google_client_id),
+        urlencoding::encode(&state.redirect_uri),
+        urlencoding::encode(GOOGLE_CONTACTS_SCOPE),
+        state_param
+    )
+}
+
+/// GET /contacts/oauth/start
+#[utoipa::path(
+    get,
+    path = "/contacts/oauth/start",
+    tag = "contacts",
+    responses(
+        (status = 200, description = "The Google consent URL (`auth_url`) to send the user to", body = ApiResponse<serde_json::Value>),
+    ),
+    security(("cognito" = []))
+)]
+async fn start_oauth(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "auth_url": build_auth_url(&state, user.user_id),
+                "message": "Redirect user to auth_url to connect Google Contacts"
+            })),
+            error: None,
+        },
+    )
+}
+
+/// GET /contacts/oauth/callback
+#[utoipa::path(
+    get,
+    path = "/contacts/oauth/callback",
+    tag = "contacts",
+    params(
+        ("code" = String, Query, description = "Authorization code from Google"),
+        ("state" = String, Query, description = "The `state` of the consent URL"),
+    ),
+    responses(
+        (status = 200, description = "Page telling the user their contacts are connected", body = String, content_type = "text/html"),
+        (status = 400, description = "Google reported an error, or the code or state is missing", body = ApiError, content_type = "application/problem+json"),
+    )
+)]
+async fn oauth_callback(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let query = Query::from_request(&event);
+
+    if let Some(error) = query.first("error") {
+        error!("OAuth error from Google: {}", error);
+        return error_response(400, format!("OAuth error: {}", error));
+    }
+
+    let code = match query.first("code") {
+        Some(code) => code,
+        None => return error_response(400, "Missing authorization code"),
+    };
+
+    let user_id = query
+        .first("state")
+        .and_then(|s| {
+            base64::Engine::decode(&base64::engine::general_purpose::URL_SAFE_NO_PAD, s).ok()
+        })
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .and_then(|s| Uuid::parse_str(&s).ok());
+    let user_id = match user_id {
+        Some(user_id) => user_id,
+        None => return error_response(400, "Invalid state parameter"),
+    };
+
+    info!("Processing contacts OAuth callback for user {}", user_id);
+
+    let tokens = state.exchange_code(code).await?;
+    state.store_user_tokens(user_id, &tokens).await?;
+
+    // A reconnect starts over with a full sync
+    sqlx::query(
+        r#"
+        INSERT INTO contact_connections (user_id, provider)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id, provider) DO UPDATE SET
+            sync_token = NULL,
+            last_error = NULL,
+            connected_at = NOW(),
+            updated_at = NOW()
+        "#,
+    )
+    .bind(user_id)
+    .bind(GOOGLE_PROVIDER)
+    .execute(&state.db_pool)
+    .await?;
+
+    info!("Connected Google Contacts for user {}", user_id);
+
+    let html = r#"
+<!DOCTYPE html>
+<html>
+<head><title>Contacts Connected</title></head>
+<body>
+    <h1>Google Contacts Connected!</h1>
+    <p>Your contacts will be synced to Second Brain shortly.</p>
+    <p>You can close this window.</p>
+</body>
+</html>
+"#;
+
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", "text/html")
+        .body(Body::from(html))?)
+}
+
+/// GET /contacts/connection
+#[utoipa::path(
+    get,
+    path = "/contacts/connection",
+    tag = "contacts",
+    responses(
+        (status = 200, description = "Connection status and last sync", body = ApiResponse<ConnectionResponse>),
+    ),
+    security(("cognito" = []))
+)]
+async fn get_connection(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let connection: Option<(DateTime<Utc>, Option<DateTime<Utc>>, Option<String>, i64)> =
+        sqlx::query_as(
+            r#"
+        SELECT c.connected_at, c.last_synced_at, c.last_error,
+               (SELECT COUNT(*) FROM contact_links l
+                WHERE l.user_id = c.user_id AND l.provider = c.provider
+                AND l.entity_id IS NOT NULL) AS contacts_linked
+        FROM contact_connections c
+        WHERE c.user_id = $1 AND c.provider = $2
+        "#,
+        )
+        .bind(user.user_id)
+        .bind(GOOGLE_PROVIDER)
+        .fetch_optional(&state.db_pool)
+        .await?;
+
+    let response = match connection {
+        Some((connected_at, last_synced_at, last_error, contacts_linked)) => ConnectionResponse {
+            provider: GOOGLE_PROVIDER.to_string(),
+            connected: true,
+            connected_at: Some(connected_at),
+            last_synced_at,
+            last_error,
+            contacts_linked,
+        },
+        None => ConnectionResponse {
+            provider: GOOGLE_PROVIDER.to_string(),
+            connected: false,
+            connected_at: None,
+            last_synced_at: None,
+            last_error: None,
+            contacts_linked: 0,
+        },
+    };
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(response),
+            error: None,
+        },
+    )
+}
+
+/// DELETE /contacts/connection
+///
+/// Synced entities are kept; only the connection and contact links go.
+#[utoipa::path(
+    delete,
+    path = "/contacts/connection",
+    tag = "contacts",
+    responses(
+        (status = 200, description = "Access was revoked", body = ApiResponse<serde_json::Value>),
+        (status = 404, description = "Google Contacts is not connected", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn delete_connection(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    state.remove_user_tokens(user.user_id).await?;
+
+    let mut tx = state.db_pool.begin().await?;
+    let removed =
+        sqlx::query("DELETE FROM contact_connections WHERE user_id = $1 AND provider = $2")
+            .bind(user.user_id)
+            .bind(GOOGLE_PROVIDER)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+    sqlx::query("DELETE FROM contact_links WHERE user_id = $1 AND provider = $2")
+        .bind(user.user_id)
+        .bind(GOOGLE_PROVIDER)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    if removed == 0 {
+        return error_response(404, "Google Contacts is not connected");
+    }
+
+    info!("Disconnected Google Contacts for user {}", user.user_id);
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({"message": "Google Contacts disconnected"})),
+            error: None,
+        },
+    )
+}
+
+fn router() -> Router<AppState> {
+    // No RequireAuth layer: Google calls the callback without a token
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RateLimit::default().public("GET", "/contacts/oauth/callback"))
+        .get("/contacts/oauth/start", start_oauth)
+        .get("/contacts/oauth/callback", oauth_callback)
+        .get("/contacts/connection", get_connection)
+        .delete("/contacts/connection", delete_connection)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
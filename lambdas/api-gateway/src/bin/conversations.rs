@@ -0,0 +1,312 @@
+//! Conversations Lambda - Earlier questions and answers.
+//!
+//! Endpoints:
+//! - GET /conversations - The caller's conversations, most recent first
+//!   (`?limit=&cursor=`)
+//! - GET /conversations/{id}/messages - A conversation's questions and
+//!   answers, oldest first (`?limit=&cursor=`)
+//!
+//! Conversations are written by `POST /query` and the chat platforms through
+//! `shared::conversations`.
+
+use chrono::{DateTime, Utc};
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::Serialize;
+use shared::conversations;
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::models::{keyset_predicate, Cursor, CursorParams, Page, SortDirection};
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, Query, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::ApiError;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Conversations listed when `limit` isn't given
+const DEFAULT_LIMIT: i64 = 20;
+
+/// Messages listed when `limit` isn't given
+const DEFAULT_MESSAGE_LIMIT: i64 = 50;
+
+/// Conversation row from database
+#[derive(Debug, sqlx::FromRow)]
+struct ConversationRow {
+    id: Uuid,
+    source: String,
+    title: Option<String>,
+    started_at: DateTime<Utc>,
+    last_message_at: DateTime<Utc>,
+    message_count: i64,
+}
+
+/// Conversation API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct ConversationResponse {
+    id: String,
+    source: String,
+    title: Option<String>,
+    started_at: String,
+    last_message_at: String,
+    message_count: i64,
+}
+
+impl From<ConversationRow> for ConversationResponse {
+    fn from(row: ConversationRow) -> Self {
+        Self {
+            id: row.id.to_string(),
+            source: row.source,
+            title: row.title,
+            started_at: row.started_at.to_rfc3339(),
+            last_message_at: row.last_message_at.to_rfc3339(),
+            message_count: row.message_count,
+        }
+    }
+}
+
+/// Message row from database
+#[derive(Debug, sqlx::FromRow)]
+struct MessageRow {
+    id: Uuid,
+    role: String,
+    content: String,
+    created_at: DateTime<Utc>,
+}
+
+/// Message API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct MessageResponse {
+    id: String,
+    role: String,
+    content: String,
+    created_at: String,
+}
+
+impl From<MessageRow> for MessageResponse {
+    fn from(row: MessageRow) -> Self {
+        Self {
+            id: row.id.to_string(),
+            role: row.role,
+            content: row.content,
+            created_at: row.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+/// GET /conversations
+#[utoipa::path(
+    get,
+    path = "/conversations",
+    tag = "knowledge",
+    params(
+        ("limit" = Option<i64>, Query, description = "Most conversations to return"),
+        ("cursor" = Option<String>, Query, description = "Cursor returned with the previous page"),
+    ),
+    responses(
+        (status = 200, description = "Your conversations, most recent first", body = ApiResponse<Page<ConversationResponse>>),
+        (status = 400, description = "Invalid cursor", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_conversations(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let query = Query::from_request(&event);
+
+    let page_params = match CursorParams::from_query(
+        query.first("limit"),
+        query.first("cursor"),
+        DEFAULT_LIMIT,
+    ) {
+        Ok(p) => p,
+        Err(e) => return error_response(400, e.to_string()),
+    };
+
+    let rows: Vec<ConversationRow> = sqlx::query_as(&format!(
+        r#"
+        SELECT c.id, c.source, c.title, c.started_at, c.last_message_at,
+               (SELECT COUNT(*) FROM messages m WHERE m.conversation_id = c.id) AS message_count
+        FROM conversations c
+        WHERE c.user_id = $1
+          AND {}
+        ORDER BY c.last_message_at DESC, c.id DESC
+        LIMIT $4
+        "#,
+        keyset_predicate(
+            "c.last_message_at",
+            "timestamptz",
+            "c.id",
+            2,
+            SortDirection::Desc
+        )
+    ))
+    .bind(user.user_id)
+    .bind(page_params.after_key())
+    .bind(page_params.after_id())
+    .bind(page_params.fetch_limit())
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let page = Page::from_rows(rows, &page_params, |row| {
+        Cursor::new(row.last_message_at.to_rfc3339(), row.id)
+    })
+    .map(ConversationResponse::from);
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(page),
+            error: None,
+        },
+    )
+}
+
+/// GET /conversations/{id}/messages
+#[utoipa::path(
+    get,
+    path = "/conversations/{id}/messages",
+    tag = "knowledge",
+    params(
+        ("id" = Uuid, Path, description = "Conversation ID"),
+        ("limit" = Option<i64>, Query, description = "Most messages to return"),
+        ("cursor" = Option<String>, Query, description = "Cursor returned with the previous page"),
+    ),
+    responses(
+        (status = 200, description = "The conversation's questions and answers, oldest first", body = ApiResponse<Page<MessageResponse>>),
+        (status = 400, description = "Invalid cursor", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Not one of your conversations", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_messages(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let query = Query::from_request(&event);
+
+    let id: Uuid = match params.get("id") {
+        Ok(id) => id,
+        Err(e) => return error_response(400, e.to_string()),
+    };
+    let page_params = match CursorParams::from_query(
+        query.first("limit"),
+        query.first("cursor"),
+        DEFAULT_MESSAGE_LIMIT,
+    ) {
+        Ok(p) => p,
+        Err(e) => return error_response(400, e.to_string()),
+    };
+
+    let owned = conversations::belongs_to(&state.db_pool, id, user.user_id).await?;
+    if !owned {
+        return error_response(404, "Conversation not found");
+    }
+
+    let rows: Vec<MessageRow> = sqlx::query_as(&format!(
+        r#"
+        SELECT id, role::text AS role, content, created_at
+        FROM messages
+        WHERE conversation_id = $1
+          AND {}
+        ORDER BY created_at, id
+        LIMIT $4
+        "#,
+        keyset_predicate("created_at", "timestamptz", "id", 2, SortDirection::Asc)
+    ))
+    .bind(id)
+    .bind(page_params.after_key())
+    .bind(page_params.after_id())
+    .bind(page_params.fetch_limit())
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let page = Page::from_rows(rows, &page_params, |row| {
+        Cursor::new(row.created_at.to_rfc3339(), row.id)
+    })
+    .map(MessageResponse::from);
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(page),
+            error: None,
+        },
+    )
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .get("/conversations", list_conversations)
+        .get("/conversations/{id}/messages", list_messages)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
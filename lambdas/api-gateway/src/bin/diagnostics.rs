@@ -0,0 +1,418 @@
+//! Diagnostics API Lambda - Opt-in debug mode and admin access to captured samples.
+//!
+//! While a user's debug session is active, the query/ingest Lambdas and the
+//! Discord bot record PII-scrubbed request/response samples with the agent's
+//! retrieval trace (see `shared::diagnostics`).
+//!
+//! Endpoints:
+//! - GET /diagnostics/debug-mode - Current debug session, if any
+//! - PUT /diagnostics/debug-mode - Turn on debug mode for a limited window
+//! - DELETE /diagnostics/debug-mode - Turn debug mode off
+//! - GET /admin/diagnostics - Captured samples (`?userId=&limit=`, admin group only)
+
+use chrono::{DateTime, Utc};
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::{Deserialize, Serialize};
+use shared::auth::{request_has_group, ADMIN_GROUP};
+use shared::diagnostics::{purge_expired, MAX_SESSION_MINUTES};
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::RateLimit;
+use shared::router::{
+    json_body, require_user, Cors, PathParams, Query, RequestLogger, RequireAuth, Router,
+};
+use shared::shaping::ResponseShaping;
+use shared::ApiError;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Default debug session length
+const DEFAULT_SESSION_MINUTES: i64 = 60;
+
+/// Enable debug mode request
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct EnableDebugModeRequest {
+    duration_minutes: Option<i64>,
+    reason: Option<String>,
+}
+
+/// Debug session row from database
+#[derive(Debug, sqlx::FromRow)]
+struct DebugSessionRow {
+    id: Uuid,
+    reason: Option<String>,
+    started_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+/// Debug session API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct DebugSessionResponse {
+    id: String,
+    reason: Option<String>,
+    started_at: String,
+    expires_at: String,
+}
+
+impl From<DebugSessionRow> for DebugSessionResponse {
+    fn from(row: DebugSessionRow) -> Self {
+        Self {
+            id: row.id.to_string(),
+            reason: row.reason,
+            started_at: row.started_at.to_rfc3339(),
+            expires_at: row.expires_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Diagnostic sample row from database
+#[derive(Debug, sqlx::FromRow)]
+struct SampleRow {
+    id: Uuid,
+    user_id: Uuid,
+    session_id: Uuid,
+    source: String,
+    operation: String,
+    request_text: String,
+    response_text: Option<String>,
+    error_message: Option<String>,
+    retrieval_trace: serde_json::Value,
+    latency_ms: Option<i32>,
+    created_at: DateTime<Utc>,
+}
+
+/// Diagnostic sample API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct SampleResponse {
+    id: String,
+    user_id: String,
+    session_id: String,
+    source: String,
+    operation: String,
+    request: String,
+    response: Option<String>,
+    error: Option<String>,
+    retrieval_trace: serde_json::Value,
+    latency_ms: Option<i32>,
+    created_at: String,
+}
+
+impl From<SampleRow> for SampleResponse {
+    fn from(row: SampleRow) -> Self {
+        Self {
+            id: row.id.to_string(),
+            user_id: row.user_id.to_string(),
+            session_id: row.session_id.to_string(),
+            source: row.source,
+            operation: row.operation,
+            request: row.request_text,
+            response: row.response_text,
+            error: row.error_message,
+            retrieval_trace: row.retrieval_trace,
+            latency_ms: row.latency_ms,
+            created_at: row.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+async fn active_session(pool: &PgPool, user_id: Uuid) -> Result<Option<DebugSessionRow>, Error> {
+    let session = sqlx::query_as(
+        r#"
+        SELECT id, reason, started_at, expires_at
+        FROM debug_sessions
+        WHERE user_id = $1 AND expires_at > NOW()
+        ORDER BY started_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(session)
+}
+
+/// GET /diagnostics/debug-mode
+#[utoipa::path(
+    get,
+    path = "/diagnostics/debug-mode",
+    tag = "diagnostics",
+    responses(
+        (status = 200, description = "Whether debug mode is on (`enabled`) and its `session`", body = ApiResponse<serde_json::Value>),
+    ),
+    security(("cognito" = []))
+)]
+async fn get_debug_mode(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let session = active_session(&state.db_pool, user.user_id).await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "enabled": session.is_some(),
+                "session": session.map(DebugSessionResponse::from),
+            })),
+            error: None,
+        },
+    )
+}
+
+/// PUT /diagnostics/debug-mode
+#[utoipa::path(
+    put,
+    path = "/diagnostics/debug-mode",
+    tag = "diagnostics",
+    request_body(content = EnableDebugModeRequest, description = "Optional; an empty body turns debug mode on with the defaults"),
+    responses(
+        (status = 200, description = "The debug session", body = ApiResponse<DebugSessionResponse>),
+        (status = 400, description = "Invalid body or too long a session", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn enable_debug_mode(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    // An empty body turns debug mode on with the defaults
+    let request: EnableDebugModeRequest = match event.body() {
+        Body::Empty => EnableDebugModeRequest::default(),
+        _ => match json_body(&event) {
+            Ok(r) => r,
+            Err(e) => return error_response(400, e.to_string()),
+        },
+    };
+
+    let minutes = request.duration_minutes.unwrap_or(DEFAULT_SESSION_MINUTES);
+    if !(1..=MAX_SESSION_MINUTES).contains(&minutes) {
+        return error_response(
+            400,
+            format!("durationMinutes must be between 1 and {}", MAX_SESSION_MINUTES),
+        );
+    }
+
+    let mut tx = state.db_pool.begin().await?;
+
+    // Replace any active session so there is only ever one window
+    sqlx::query(
+        "UPDATE debug_sessions SET expires_at = NOW() WHERE user_id = $1 AND expires_at > NOW()",
+    )
+    .bind(user.user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    let session: DebugSessionRow = sqlx::query_as(
+        r#"
+        INSERT INTO debug_sessions (user_id, reason, expires_at)
+        VALUES ($1, $2, NOW() + make_interval(mins => $3))
+        RETURNING id, reason, started_at, expires_at
+        "#,
+    )
+    .bind(user.user_id)
+    .bind(request.reason.filter(|r| !r.trim().is_empty()))
+    .bind(minutes as i32)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    info!(user_id = %user.user_id, minutes, "Debug mode enabled");
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(DebugSessionResponse::from(session)),
+            error: None,
+        },
+    )
+}
+
+/// DELETE /diagnostics/debug-mode
+#[utoipa::path(
+    delete,
+    path = "/diagnostics/debug-mode",
+    tag = "diagnostics",
+    responses(
+        (status = 200, description = "Debug mode is off"),
+    ),
+    security(("cognito" = []))
+)]
+async fn disable_debug_mode(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    sqlx::query(
+        "UPDATE debug_sessions SET expires_at = NOW() WHERE user_id = $1 AND expires_at > NOW()",
+    )
+    .bind(user.user_id)
+    .execute(&state.db_pool)
+    .await?;
+
+    info!(user_id = %user.user_id, "Debug mode disabled");
+
+    json_response(
+        200,
+        &ApiResponse::<()> {
+            success: true,
+            data: None,
+            error: None,
+        },
+    )
+}
+
+/// GET /admin/diagnostics
+#[utoipa::path(
+    get,
+    path = "/admin/diagnostics",
+    tag = "diagnostics",
+    params(
+        ("userId" = Option<Uuid>, Query, description = "Only this user's samples"),
+        ("limit" = Option<i64>, Query, description = "Most samples to return"),
+    ),
+    responses(
+        (status = 200, description = "Captured samples, most recent first", body = ApiResponse<Vec<SampleResponse>>),
+        (status = 400, description = "Invalid filter", body = ApiError, content_type = "application/problem+json"),
+        (status = 403, description = "Not in the admin group", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_samples(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    if !request_has_group(&event, ADMIN_GROUP) {
+        return error_response(403, "Admin access required");
+    }
+
+    let query = Query::from_request(&event);
+    let user_id: Option<Uuid> = match query.get("userId") {
+        Ok(user_id) => user_id,
+        Err(e) => return error_response(400, e.to_string()),
+    };
+    let limit: i64 = match query.get("limit") {
+        Ok(limit) => limit.unwrap_or(50).clamp(1, 200),
+        Err(e) => return error_response(400, e.to_string()),
+    };
+
+    // Cheap with the expiry index; keeps retention honest without a separate job
+    if let Err(e) = purge_expired(&state.db_pool).await {
+        warn!("Failed to purge expired samples: {}", e);
+    }
+
+    let samples: Vec<SampleRow> = sqlx::query_as(
+        r#"
+        SELECT
+            id, user_id, session_id, source, operation,
+            request_text, response_text, error_message,
+            retrieval_trace, latency_ms, created_at
+        FROM diagnostic_samples
+        WHERE ($1::uuid IS NULL OR user_id = $1)
+        AND expires_at > NOW()
+        ORDER BY created_at DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(limit)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let samples: Vec<SampleResponse> = samples.into_iter().map(SampleResponse::from).collect();
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(samples),
+            error: None,
+        },
+    )
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .get("/diagnostics/debug-mode", get_debug_mode)
+        .put("/diagnostics/debug-mode", enable_debug_mode)
+        .delete("/diagnostics/debug-mode", disable_debug_mode)
+        .get("/admin/diagnostics", list_samples)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
@@ -0,0 +1,224 @@
+//! Discord Link Lambda - Links Discord accounts to registered users.
+//!
+//! Endpoints:
+//! - GET /discord/link - Link status
+//! - POST /discord/link/code - Issue a code to redeem with `/link` in Discord
+//! - DELETE /discord/link - Unlink the Discord account
+
+use chrono::{DateTime, Utc};
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::Serialize;
+use shared::discord_links::{create_link_code, unlink, LINK_CODE_TTL_MINUTES};
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::ApiError;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+
+/// Link status row from database
+#[derive(Debug, sqlx::FromRow)]
+struct LinkRow {
+    discord_id: Option<String>,
+    discord_linked_at: Option<DateTime<Utc>>,
+}
+
+/// Link status API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct LinkStatusResponse {
+    linked: bool,
+    discord_id: Option<String>,
+    linked_at: Option<String>,
+}
+
+/// Link code API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct LinkCodeResponse {
+    code: String,
+    expires_at: String,
+    instructions: String,
+}
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+/// GET /discord/link
+#[utoipa::path(
+    get,
+    path = "/discord/link",
+    tag = "integrations",
+    responses(
+        (status = 200, description = "Whether a Discord account is linked", body = ApiResponse<LinkStatusResponse>),
+    ),
+    security(("cognito" = []))
+)]
+async fn get_link(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let link: LinkRow =
+        sqlx::query_as("SELECT discord_id, discord_linked_at FROM users WHERE id = $1")
+            .bind(user.user_id)
+            .fetch_one(&state.db_pool)
+            .await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(LinkStatusResponse {
+                linked: link.discord_id.is_some(),
+                discord_id: link.discord_id,
+                linked_at: link.discord_linked_at.map(|t| t.to_rfc3339()),
+            }),
+            error: None,
+        },
+    )
+}
+
+/// POST /discord/link/code
+#[utoipa::path(
+    post,
+    path = "/discord/link/code",
+    tag = "integrations",
+    responses(
+        (status = 201, description = "A code to redeem with `/link` in Discord", body = ApiResponse<LinkCodeResponse>),
+    ),
+    security(("cognito" = []))
+)]
+async fn issue_code(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let (code, expires_at) = create_link_code(&state.db_pool, user.user_id).await?;
+
+    info!(user_id = %user.user_id, "Issued Discord link code");
+
+    json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(LinkCodeResponse {
+                instructions: format!(
+                    "Run /link code:{} in Discord within {} minutes",
+                    code, LINK_CODE_TTL_MINUTES
+                ),
+                code,
+                expires_at: expires_at.to_rfc3339(),
+            }),
+            error: None,
+        },
+    )
+}
+
+/// DELETE /discord/link
+#[utoipa::path(
+    delete,
+    path = "/discord/link",
+    tag = "integrations",
+    responses(
+        (status = 200, description = "The Discord account was unlinked"),
+        (status = 404, description = "No Discord account linked", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn delete_link(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let unlinked = unlink(&state.db_pool, user.user_id).await?;
+
+    if !unlinked {
+        return error_response(404, "No Discord account linked");
+    }
+
+    info!(user_id = %user.user_id, "Unlinked Discord account");
+
+    json_response(
+        200,
+        &ApiResponse::<()> {
+            success: true,
+            data: None,
+            error: None,
+        },
+    )
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .get("/discord/link", get_link)
+        .delete("/discord/link", delete_link)
+        .post("/discord/link/code", issue_code)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
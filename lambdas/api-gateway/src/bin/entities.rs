@@ -2,30 +2,51 @@
 //!
 //! Endpoints:
 //! - POST /entities - Create entity
-//! - GET /entities - Search/list entities
-//! - GET /entities/{id} - Get entity details with timeline
-//! - PUT /entities/{id} - Update entity
+//! - GET /entities - Search/list entities (`?limit=&cursor=`)
+//! - GET /entities/{id} - Get entity details with timeline (with an `ETag`; honors
+//!   `If-None-Match`)
+//! - PUT /entities/{id} - Update entity (honors `If-Match`)
 //! - DELETE /entities/{id} - Delete entity
 //! - POST /entities/{id}/relationships - Create entity relationship
 //! - GET /entities/{id}/relationships - List entity relationships
-//! - GET /entities/{id}/facts - Get facts about entity (timeline)
+//! - GET /entities/{id}/facts - Get facts about entity (timeline, `?limit=&cursor=`)
+//! - POST /entities/{id}/merge - Merge another entity into this one
+//! - POST /entities/{id}/photo - Get a presigned URL to upload the entity's photo
+//! - GET /entities/{id}/relationship-health - Interaction history summary (`?stale_days=`)
 
 use lambda_http::{run, service_fn, Body, Error, Request, RequestExt, Response};
 use serde::{Deserialize, Serialize};
-use sqlx::postgres::PgPoolOptions;
+use shared::access::{space_clause, visibility_clause};
+use shared::audit::{self, AuditAction, AuditResource};
+use shared::conditional::{self, IfMatch};
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::models::{keyset_predicate, Cursor, CursorParams, Page, SortDirection};
+use shared::relationship_health::{entity_health, DEFAULT_STALE_DAYS};
+use shared::entity_merge::merge_entities;
+use shared::entity_photos::{
+    accepted_types, photo_key, photo_type, DOWNLOAD_URL_TTL_SECS, MAX_PHOTO_BYTES,
+    UPLOAD_URL_TTL_SECS,
+};
+use shared::events::EntityMerged;
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::{ApiError, AuthorizedUser, EventPublisher};
 use sqlx::PgPool;
 use std::sync::Arc;
 use tracing::info;
 use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 /// Entity types
-const ENTITY_TYPES: [&str; 7] = [
-    "person", "organization", "place", "project", "event", "product", "custom"
+const ENTITY_TYPES: [&str; 8] = [
+    "person", "organization", "place", "project", "event", "product", "custom", "bookmark"
 ];
 
 /// Create entity request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct CreateEntityRequest {
     name: String,
     entity_type: String,
@@ -36,7 +57,7 @@ struct CreateEntityRequest {
 }
 
 /// Update entity request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct UpdateEntityRequest {
     name: Option<String>,
     description: Option<String>,
@@ -46,15 +67,40 @@ struct UpdateEntityRequest {
 }
 
 /// Create entity relationship request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct CreateRelationshipRequest {
     target_entity_id: String,
     relationship_type: String,
     metadata: Option<serde_json::Value>,
 }
 
+/// Merge entity request (the path entity is the one kept)
+#[derive(Debug, Deserialize, ToSchema)]
+struct MergeEntityRequest {
+    source_entity_id: String,
+    /// Keep the source's name as an alias of the target (default true)
+    keep_alias: Option<bool>,
+}
+
+/// Photo upload request
+#[derive(Debug, Deserialize, ToSchema)]
+struct PhotoUploadRequest {
+    content_type: String,
+    /// Size of the file in bytes; the upload URL only accepts exactly this size
+    content_length: i64,
+}
+
+/// Photo upload response
+#[derive(Debug, Serialize, ToSchema)]
+struct PhotoUploadResponse {
+    /// PUT the file here with the same Content-Type and Content-Length
+    upload_url: String,
+    photo_key: String,
+    expires_in: u64,
+}
+
 /// Entity response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct EntityResponse {
     id: String,
     entity_type: String,
@@ -67,7 +113,7 @@ struct EntityResponse {
 }
 
 /// Entity detail response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct EntityDetailResponse {
     id: String,
     entity_type: String,
@@ -79,12 +125,14 @@ struct EntityDetailResponse {
     linked_user_id: Option<String>,
     created_at: String,
     updated_at: String,
+    /// Presigned download URL for the entity's photo, valid for an hour
+    photo_url: Option<String>,
     attributes: Vec<EntityAttribute>,
     locations: Vec<EntityLocation>,
     relationships: Vec<EntityRelationship>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct EntityAttribute {
     name: String,
     value: String,
@@ -92,7 +140,7 @@ struct EntityAttribute {
     valid_to: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct EntityLocation {
     label: String,
     address: Option<String>,
@@ -100,7 +148,7 @@ struct EntityLocation {
     longitude: Option<f64>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct EntityRelationship {
     id: String,
     related_entity_id: String,
@@ -121,7 +169,7 @@ struct FactTimelineEntry {
 }
 
 /// API response wrapper
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct ApiResponse<T> {
     success: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -133,661 +181,1165 @@ struct ApiResponse<T> {
 /// Application state
 struct AppState {
     db_pool: PgPool,
+    s3_client: aws_sdk_s3::Client,
+    /// Bucket for entity photos; photo uploads are unavailable when unset
+    photos_bucket: Option<String>,
+    /// Set when `EVENT_BUS_NAME` is configured
+    event_publisher: Option<EventPublisher>,
 }
 
 impl AppState {
     async fn new() -> Result<Self, Error> {
         let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
-        let secrets_client = aws_sdk_secretsmanager::Client::new(&config);
-
-        let db_secret_arn =
-            std::env::var("DB_SECRET_ARN").map_err(|_| "DB_SECRET_ARN not set")?;
 
-        let db_secret = secrets_client
-            .get_secret_value()
-            .secret_id(&db_secret_arn)
-            .send()
+        let db_pool = shared::db::connect(&config)
             .await
-            .map_err(|e| format!("Failed to get DB secret: {}", e))?;
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
 
-        let db_creds: serde_json::Value =
-            serde_json::from_str(db_secret.secret_string().unwrap_or("{}"))?;
+        let s3_client = aws_sdk_s3::Client::new(&config);
+        let photos_bucket = std::env::var("ENTITY_PHOTOS_BUCKET").ok();
 
-        let db_host = std::env::var("DB_HOST").map_err(|_| "DB_HOST not set")?;
-        let db_name = std::env::var("DB_NAME").unwrap_or_else(|_| "second_brain".to_string());
-        let db_user = db_creds["username"].as_str().unwrap_or("sbadmin");
-        let db_pass = db_creds["password"].as_str().unwrap_or("");
+        Ok(Self {
+            db_pool,
+            s3_client,
+            photos_bucket,
+            event_publisher: EventPublisher::from_env(&config),
+        })
+    }
+}
 
-        let database_url = format!(
-            "postgres://{}:{}@{}:5432/{}",
-            db_user, db_pass, db_host, db_name
+/// Presigned GET URL for a stored photo, or `None` if it can't be signed.
+async fn photo_url(state: &AppState, key: &str) -> Option<String> {
+    let bucket = state.photos_bucket.as_deref()?;
+    let config = aws_sdk_s3::presigning::PresigningConfig::expires_in(
+        std::time::Duration::from_secs(DOWNLOAD_URL_TTL_SECS),
+    )
+    .ok()?;
+
+    match state
+        .s3_client
+        .get_object()
+        .bucket(bucket)
+        .key(key)
+        .presigned(config)
+        .await
+    {
+        Ok(request) => Some(request.uri().to_string()),
+        Err(e) => {
+            tracing::warn!("Failed to presign photo {}: {}", key, e);
+            None
+        }
+    }
+}
+
+/// Whether the user can see the entity: relationships may read it per its
+/// visibility tier, only the owner or family may change it.
+async fn has_access(
+    pool: &PgPool,
+    user: &AuthorizedUser,
+    entity_id: Uuid,
+    read: bool,
+) -> Result<bool, Error> {
+    let access = if read {
+        visibility_clause("e", 2)
+    } else {
+        format!(
+            "((e.owner_type = 'user' AND e.owner_id = $2) OR (e.owner_type = 'family' AND e.owner_id = ANY($3) AND {}))",
+            space_clause("e", 2)
+        )
+    };
+    let has_access: bool = sqlx::query_scalar(&format!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM entities e
+            WHERE e.id = $1
+            AND e.deleted_at IS NULL
+            AND {}
+        )
+        "#,
+        access
+    ))
+    .bind(entity_id)
+    .bind(user.user_id)
+    .bind(&user.family_ids)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(has_access)
+}
+
+
+/// POST /entities
+#[utoipa::path(
+    post,
+    path = "/entities",
+    tag = "entities",
+    request_body = CreateEntityRequest,
+    responses(
+        (status = 201, description = "The new entity's `entity_id`, `name` and `entity_type`", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Invalid entity type", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn create_entity(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let request: CreateEntityRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    // Validate entity type
+    if !ENTITY_TYPES.contains(&request.entity_type.as_str()) {
+        return error_response(
+            400,
+            format!(
+                "Invalid entity type. Must be one of: {:?}",
+                ENTITY_TYPES
+            ),
         );
+    }
 
-        let db_pool = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(&database_url)
-            .await
-            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+    let entity_id = Uuid::new_v4();
+    let visibility = request.visibility_tier.unwrap_or(3);
+    let aliases: Vec<String> = request.aliases.unwrap_or_default();
+    let metadata = request.metadata.unwrap_or(serde_json::json!({}));
+
+    sqlx::query(
+        r#"
+        INSERT INTO entities (id, entity_type, name, description, aliases, metadata,
+                              owner_type, owner_id, created_by, visibility_tier)
+        VALUES ($1, $2::entity_type, $3, $4, $5, $6, 'user', $7, $7, $8)
+        "#,
+    )
+    .bind(entity_id)
+    .bind(&request.entity_type)
+    .bind(&request.name)
+    .bind(&request.description)
+    .bind(&aliases)
+    .bind(&metadata)
+    .bind(user_id)
+    .bind(visibility)
+    .execute(&state.db_pool)
+    .await?;
+
+    audit::record_change(&state.db_pool, user_id, AuditAction::Create, AuditResource::Entity, entity_id, None).await;
+
+    info!("Created entity {} ({})", entity_id, request.name);
+
+    Ok(json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "entity_id": entity_id.to_string(),
+                "name": request.name,
+                "entity_type": request.entity_type,
+            })),
+            error: None,
+        },
+    )?)
+}
+
+/// GET /entities
+#[utoipa::path(
+    get,
+    path = "/entities",
+    tag = "entities",
+    params(
+        ("q" = Option<String>, Query, description = "Search names and aliases"),
+        ("type" = Option<String>, Query, description = "Only entities of this type"),
+        ("limit" = Option<i64>, Query, description = "Most entities to return"),
+        ("cursor" = Option<String>, Query, description = "Cursor returned with the previous page"),
+    ),
+    responses(
+        (status = 200, description = "Entities you can see", body = ApiResponse<Page<EntityResponse>>),
+        (status = 400, description = "Invalid cursor", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_entities(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let params = event.query_string_parameters();
+    let query = params.first("q");
+    let entity_type = params.first("type");
+    let page_params = match CursorParams::from_query(params.first("limit"), params.first("cursor"), 20) {
+        Ok(p) => p,
+        Err(e) => {
+            return error_response(400, e.to_string());
+        }
+    };
 
-        Ok(Self { db_pool })
+    let family_ids = &user.family_ids;
+
+    let rows = if let Some(q) = query {
+        // Search with fuzzy matching
+        sqlx::query_as::<_, (Uuid, String, String, Option<String>, Vec<String>, i16, chrono::DateTime<chrono::Utc>, i64)>(
+            &format!(r#"
+            SELECT e.id, e.entity_type::text, e.name, e.description, e.aliases,
+                   e.visibility_tier, e.created_at, COALESCE(COUNT(f.id), 0) as fact_count
+            FROM entities e
+            LEFT JOIN facts f ON f.about_entity_id = e.id AND f.deleted_at IS NULL
+            WHERE {}
+            AND e.deleted_at IS NULL
+            AND (
+                e.name ILIKE $3
+                OR e.normalized_name ILIKE $3
+                OR $4 = ANY(e.aliases)
+            )
+            AND {}
+            GROUP BY e.id
+            ORDER BY e.name, e.id
+            LIMIT $5
+            "#, visibility_clause("e", 1), keyset_predicate("e.name", "text", "e.id", 6, SortDirection::Asc)),
+        )
+        .bind(user_id)
+        .bind(family_ids)
+        .bind(format!("%{}%", q))
+        .bind(q.to_lowercase())
+        .bind(page_params.fetch_limit())
+        .bind(page_params.after_key())
+        .bind(page_params.after_id())
+        .fetch_all(&state.db_pool)
+        .await
+    } else if let Some(etype) = entity_type {
+        // Filter by type
+        sqlx::query_as::<_, (Uuid, String, String, Option<String>, Vec<String>, i16, chrono::DateTime<chrono::Utc>, i64)>(
+            &format!(r#"
+            SELECT e.id, e.entity_type::text, e.name, e.description, e.aliases,
+                   e.visibility_tier, e.created_at, COALESCE(COUNT(f.id), 0) as fact_count
+            FROM entities e
+            LEFT JOIN facts f ON f.about_entity_id = e.id AND f.deleted_at IS NULL
+            WHERE {}
+            AND e.deleted_at IS NULL
+            AND e.entity_type = $3::entity_type
+            AND {}
+            GROUP BY e.id
+            ORDER BY e.name, e.id
+            LIMIT $4
+            "#, visibility_clause("e", 1), keyset_predicate("e.name", "text", "e.id", 5, SortDirection::Asc)),
+        )
+        .bind(user_id)
+        .bind(family_ids)
+        .bind(etype)
+        .bind(page_params.fetch_limit())
+        .bind(page_params.after_key())
+        .bind(page_params.after_id())
+        .fetch_all(&state.db_pool)
+        .await
+    } else {
+        // List all (alphabetical so pages are stable)
+        sqlx::query_as::<_, (Uuid, String, String, Option<String>, Vec<String>, i16, chrono::DateTime<chrono::Utc>, i64)>(
+            &format!(r#"
+            SELECT e.id, e.entity_type::text, e.name, e.description, e.aliases,
+                   e.visibility_tier, e.created_at, COALESCE(COUNT(f.id), 0) as fact_count
+            FROM entities e
+            LEFT JOIN facts f ON f.about_entity_id = e.id AND f.deleted_at IS NULL
+            WHERE {}
+            AND e.deleted_at IS NULL
+            AND {}
+            GROUP BY e.id
+            ORDER BY e.name, e.id
+            LIMIT $3
+            "#, visibility_clause("e", 1), keyset_predicate("e.name", "text", "e.id", 4, SortDirection::Asc)),
+        )
+        .bind(user_id)
+        .bind(family_ids)
+        .bind(page_params.fetch_limit())
+        .bind(page_params.after_key())
+        .bind(page_params.after_id())
+        .fetch_all(&state.db_pool)
+        .await
+    }?;
+
+    let page = Page::from_rows(rows, &page_params, |row| Cursor::new(row.2.clone(), row.0))
+        .map(|(id, entity_type, name, description, aliases, visibility_tier, created_at, fact_count)| {
+            EntityResponse {
+                id: id.to_string(),
+                entity_type,
+                name,
+                description,
+                aliases,
+                visibility_tier,
+                created_at: created_at.to_rfc3339(),
+                fact_count,
+            }
+        });
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(page),
+            error: None,
+        },
+    )?)
+}
+
+/// GET /entities/{id}
+#[utoipa::path(
+    get,
+    path = "/entities/{id}",
+    tag = "entities",
+    params(
+        ("id" = Uuid, Path, description = "Entity ID"),
+        ("If-None-Match" = Option<String>, Header, description = "`ETag` the client has; answered with 304 if it's current"),
+    ),
+    responses(
+        (status = 200, description = "The entity with its attributes, locations and relationships", body = ApiResponse<EntityDetailResponse>, headers(("ETag" = String, description = "Version of the resource, for `If-None-Match` and `If-Match`"))),
+        (status = 304, description = "Not modified since the `If-None-Match` ETag"),
+        (status = 404, description = "Entity not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn get_entity(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let entity_id = params.id("id", "Invalid entity ID")?;
+    if !has_access(&state.db_pool, &user, entity_id, true).await? {
+        return error_response(404, "Entity not found");
+    }
+
+    let entity = sqlx::query_as::<_, (Uuid, String, String, Option<String>, Vec<String>, serde_json::Value, i16, Option<Uuid>, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>, Option<String>)>(
+        r#"
+        SELECT id, entity_type::text, name, description, aliases, metadata,
+               visibility_tier, linked_user_id, created_at, updated_at, photo_key
+        FROM entities WHERE id = $1
+        "#
+    )
+    .bind(entity_id)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    let etag = conditional::etag(entity.9);
+    if conditional::is_not_modified(&event, &etag) {
+        return Ok(conditional::not_modified(&etag));
+    }
+
+    // The related rows are independent, so they're fetched
+    // concurrently rather than one after another
+
+    // Current attributes
+    let attributes = async {
+        sqlx::query_as::<_, (String, String, Option<chrono::NaiveDate>, Option<chrono::NaiveDate>)>(
+            r#"
+            SELECT attribute_name, attribute_value, valid_from, valid_to
+            FROM entity_attributes
+            WHERE entity_id = $1
+            AND (valid_to IS NULL OR valid_to > CURRENT_DATE)
+            ORDER BY attribute_name
+            "#
+        )
+        .bind(entity_id)
+        .fetch_all(&state.db_pool)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, value, valid_from, valid_to)| EntityAttribute {
+            name,
+            value,
+            valid_from: valid_from.map(|d| d.to_string()),
+            valid_to: valid_to.map(|d| d.to_string()),
+        })
+        .collect::<Vec<EntityAttribute>>()
+    };
+
+    // Current locations
+    let locations = async {
+        sqlx::query_as::<_, (String, Option<String>, Option<f64>, Option<f64>)>(
+            r#"
+            SELECT label, address_raw,
+                   ST_Y(location::geometry) as lat, ST_X(location::geometry) as lng
+            FROM entity_locations
+            WHERE entity_id = $1
+            AND (valid_to IS NULL OR valid_to > CURRENT_DATE)
+            ORDER BY label
+            "#
+        )
+        .bind(entity_id)
+        .fetch_all(&state.db_pool)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(label, address, latitude, longitude)| EntityLocation {
+            label,
+            address,
+            latitude,
+            longitude,
+        })
+        .collect::<Vec<EntityLocation>>()
+    };
+
+    // Relationships in either direction
+    let relationships = async {
+        sqlx::query_as::<_, (Uuid, Uuid, String, String, String, String)>(
+            r#"
+            SELECT er.id,
+                   CASE WHEN er.source_entity_id = $1 THEN er.target_entity_id ELSE er.source_entity_id END as related_id,
+                   e.name, e.entity_type::text, er.relationship_type,
+                   CASE WHEN er.source_entity_id = $1 THEN 'outgoing' ELSE 'incoming' END as direction
+            FROM entity_relationships er
+            JOIN entities e ON e.id = CASE WHEN er.source_entity_id = $1 THEN er.target_entity_id ELSE er.source_entity_id END
+            WHERE (er.source_entity_id = $1 OR er.target_entity_id = $1)
+            AND e.deleted_at IS NULL
+            ORDER BY e.name
+            "#
+        )
+        .bind(entity_id)
+        .fetch_all(&state.db_pool)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(id, related_id, name, entity_type, relationship_type, direction)| EntityRelationship {
+            id: id.to_string(),
+            related_entity_id: related_id.to_string(),
+            related_entity_name: name,
+            related_entity_type: entity_type,
+            relationship_type,
+            direction,
+        })
+        .collect::<Vec<EntityRelationship>>()
+    };
+
+    let photo = async {
+        match entity.10.as_deref() {
+            Some(key) => photo_url(&state, key).await,
+            None => None,
+        }
+    };
+
+    let (attributes, locations, relationships, photo_url) =
+        tokio::join!(attributes, locations, relationships, photo);
+
+    let response = EntityDetailResponse {
+        id: entity.0.to_string(),
+        entity_type: entity.1,
+        name: entity.2,
+        description: entity.3,
+        aliases: entity.4,
+        metadata: entity.5,
+        visibility_tier: entity.6,
+        linked_user_id: entity.7.map(|u| u.to_string()),
+        created_at: entity.8.to_rfc3339(),
+        updated_at: entity.9.to_rfc3339(),
+        photo_url,
+        attributes,
+        locations,
+        relationships,
+    };
+
+    Ok(conditional::with_etag(
+        json_response(200, &ApiResponse {
+            success: true,
+            data: Some(response),
+            error: None,
+        })?,
+        &etag,
+    ))
+}
+
+/// PUT /entities/{id}
+#[utoipa::path(
+    put,
+    path = "/entities/{id}",
+    tag = "entities",
+    request_body = UpdateEntityRequest,
+    params(
+        ("id" = Uuid, Path, description = "Entity ID"),
+        ("If-Match" = Option<String>, Header, description = "`ETag` the change is based on; answered with 412 if it's stale"),
+    ),
+    responses(
+        (status = 200, description = "The entity was updated", body = ApiResponse<serde_json::Value>, headers(("ETag" = String, description = "Version of the resource, for `If-None-Match` and `If-Match`"))),
+        (status = 404, description = "Entity not found", body = ApiError, content_type = "application/problem+json"),
+        (status = 412, description = "The entity changed since the `If-Match` ETag", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn update_entity(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let entity_id = params.id("id", "Invalid entity ID")?;
+    if !has_access(&state.db_pool, &user, entity_id, false).await? {
+        return error_response(404, "Entity not found");
     }
+
+    let request: UpdateEntityRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let if_match = IfMatch::from_request(&event);
+    let before = audit::snapshot(&state.db_pool, AuditResource::Entity, entity_id).await;
+
+    // The row is locked until commit, so a concurrent If-Match
+    // update sees this one's version and fails. Returns the
+    // current version instead if the If-Match doesn't match.
+    let updated = shared::db::with_tx(&state.db_pool, async |tx| {
+        let current: chrono::DateTime<chrono::Utc> = sqlx::query_scalar(
+            "SELECT updated_at FROM entities WHERE id = $1 FOR UPDATE"
+        )
+        .bind(entity_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if !if_match.matches(current) {
+            return Ok(Err(current));
+        }
+
+        // Update each field individually for simplicity
+        let updated_at: chrono::DateTime<chrono::Utc> = sqlx::query_scalar(
+            "UPDATE entities SET updated_at = NOW() WHERE id = $1 RETURNING updated_at"
+        )
+        .bind(entity_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if let Some(name) = &request.name {
+            sqlx::query("UPDATE entities SET name = $2 WHERE id = $1")
+                .bind(entity_id)
+                .bind(name)
+                .execute(&mut *tx)
+                .await?;
+        }
+        if let Some(desc) = &request.description {
+            sqlx::query("UPDATE entities SET description = $2 WHERE id = $1")
+                .bind(entity_id)
+                .bind(desc)
+                .execute(&mut *tx)
+                .await?;
+        }
+        if let Some(aliases) = &request.aliases {
+            sqlx::query("UPDATE entities SET aliases = $2 WHERE id = $1")
+                .bind(entity_id)
+                .bind(aliases)
+                .execute(&mut *tx)
+                .await?;
+        }
+        if let Some(metadata) = &request.metadata {
+            sqlx::query("UPDATE entities SET metadata = $2 WHERE id = $1")
+                .bind(entity_id)
+                .bind(metadata)
+                .execute(&mut *tx)
+                .await?;
+        }
+        if let Some(visibility) = request.visibility_tier {
+            sqlx::query("UPDATE entities SET visibility_tier = $2 WHERE id = $1")
+                .bind(entity_id)
+                .bind(visibility)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        Ok::<_, Error>(Ok(updated_at))
+    })
+    .await?;
+
+    let updated_at = match updated {
+        Ok(updated_at) => updated_at,
+        Err(current) => {
+            return Ok(conditional::with_etag(
+                error_response(
+                    412,
+                    "Entity was changed by someone else; reload it and try again",
+                )?,
+                &conditional::etag(current),
+            ));
+        }
+    };
+
+    audit::record_change(&state.db_pool, user_id, AuditAction::Update, AuditResource::Entity, entity_id, before).await;
+
+    info!("Updated entity {}", entity_id);
+
+    Ok(conditional::with_etag(
+        json_response(200, &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({"message": "Entity updated"})),
+            error: None,
+        })?,
+        &conditional::etag(updated_at),
+    ))
 }
 
-/// Extract user_id from Cognito claims
-fn extract_user_id(event: &Request) -> Result<Uuid, Error> {
-    let context = event
-        .request_context_ref()
-        .ok_or("Missing request context")?;
+/// DELETE /entities/{id}
+#[utoipa::path(
+    delete,
+    path = "/entities/{id}",
+    tag = "entities",
+    params(
+        ("id" = Uuid, Path, description = "Entity ID"),
+    ),
+    responses(
+        (status = 200, description = "The entity was moved to the trash", body = ApiResponse<serde_json::Value>),
+        (status = 404, description = "Entity not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn delete_entity(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let entity_id = params.id("id", "Invalid entity ID")?;
+    if !has_access(&state.db_pool, &user, entity_id, false).await? {
+        return error_response(404, "Entity not found");
+    }
+
+    let before = audit::snapshot(&state.db_pool, AuditResource::Entity, entity_id).await;
 
-    let claims = context
-        .authorizer()
-        .and_then(|a| a.fields.get("claims"))
-        .ok_or("Missing claims in authorizer context")?;
+    let deleted = shared::trash::delete_entity(&state.db_pool, entity_id, user_id).await?;
 
-    let sub = claims
-        .get("sub")
-        .and_then(|v| v.as_str())
-        .ok_or("Missing sub claim")?;
+    if deleted {
+        audit::record_change(&state.db_pool, user_id, AuditAction::Delete, AuditResource::Entity, entity_id, before).await;
+    }
+
+    info!("Moved entity {} to trash", entity_id);
 
-    Uuid::parse_str(sub).map_err(|e| format!("Invalid user_id: {}", e).into())
+    Ok(json_response(200, &ApiResponse {
+        success: true,
+        data: Some(serde_json::json!({
+            "message": "Entity moved to trash",
+            "restore_within_days": shared::trash::RETENTION_DAYS,
+        })),
+        error: None,
+    })?)
 }
 
-async fn handler(state: Arc<AppState>, event: Request) -> Result<Response<Body>, Error> {
-    let raw_path = event.uri().path();
-    // Strip /api stage prefix if present (API Gateway REST API includes stage in path)
-    let path = raw_path.strip_prefix("/api").unwrap_or(raw_path);
-    let method = event.method().as_str();
+/// GET /entities/{id}/facts
+#[utoipa::path(
+    get,
+    path = "/entities/{id}/facts",
+    tag = "entities",
+    params(
+        ("id" = Uuid, Path, description = "Entity ID"),
+        ("limit" = Option<i64>, Query, description = "Most facts to return"),
+        ("cursor" = Option<String>, Query, description = "Cursor returned with the previous page"),
+    ),
+    responses(
+        (status = 200, description = "Facts about the entity, most recent first, as `facts` with `next_cursor` and `has_more`", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Invalid cursor", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Entity not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn get_entity_facts(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let entity_id = params.id("id", "Invalid entity ID")?;
+    if !has_access(&state.db_pool, &user, entity_id, true).await? {
+        return error_response(404, "Entity not found");
+    }
 
-    let cognito_sub = match extract_user_id(&event) {
-        Ok(id) => id,
+    let params = event.query_string_parameters();
+    let page_params = match CursorParams::from_query(params.first("limit"), params.first("cursor"), 50) {
+        Ok(p) => p,
         Err(e) => {
-            return Ok(json_response(
-                401,
-                &ApiResponse::<()> {
-                    success: false,
-                    data: None,
-                    error: Some(format!("Authentication required: {}", e)),
-                },
-            )?);
+            return error_response(400, e.to_string());
         }
     };
 
-    // Look up database user_id from Cognito sub
-    let user_id: Uuid = match sqlx::query_scalar::<_, Uuid>(
-        "SELECT id FROM users WHERE cognito_sub = $1::text"
+    let rows = sqlx::query_as::<_, (Uuid, String, i16, chrono::DateTime<chrono::Utc>, Option<chrono::NaiveDate>, Option<chrono::NaiveDate>)>(
+        &format!(r#"
+        SELECT f.id, f.content, f.importance, f.recorded_at, f.valid_from, f.valid_to
+        FROM facts f
+        WHERE f.about_entity_id = $1
+        AND f.deleted_at IS NULL
+        AND {}
+        AND {}
+        ORDER BY COALESCE(f.valid_from, f.recorded_at::date) DESC, f.id DESC
+        LIMIT $2
+        "#, visibility_clause("f", 5), keyset_predicate("COALESCE(f.valid_from, f.recorded_at::date)", "date", "f.id", 3, SortDirection::Desc))
     )
-    .bind(cognito_sub)
-    .fetch_optional(&state.db_pool)
-    .await
-    .map_err(|e| format!("Failed to lookup user: {}", e))? {
-        Some(id) => id,
-        None => {
-            return Ok(json_response(
-                401,
-                &ApiResponse::<()> {
-                    success: false,
-                    data: None,
-                    error: Some("User not registered. Please use the main app to register first.".to_string()),
-                },
-            )?);
-        }
+    .bind(entity_id)
+    .bind(page_params.fetch_limit())
+    .bind(page_params.after_key())
+    .bind(page_params.after_id())
+    .bind(user_id)
+    .bind(&user.family_ids)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let page = Page::from_rows(rows, &page_params, |row| {
+        let timeline_date = row.4.unwrap_or_else(|| row.3.date_naive());
+        Cursor::new(timeline_date.to_string(), row.0)
+    })
+    .map(|(id, content, importance, recorded_at, valid_from, valid_to)| FactTimelineEntry {
+        id: id.to_string(),
+        content,
+        importance,
+        recorded_at: recorded_at.to_rfc3339(),
+        valid_from: valid_from.map(|d| d.to_string()),
+        valid_to: valid_to.map(|d| d.to_string()),
+    });
+
+    Ok(json_response(200, &ApiResponse {
+        success: true,
+        data: Some(serde_json::json!({
+            "entity_id": entity_id.to_string(),
+            "count": page.items.len(),
+            "facts": page.items,
+            "next_cursor": page.next_cursor,
+            "has_more": page.has_more,
+        })),
+        error: None,
+    })?)
+}
+
+/// POST /entities/{id}/relationships
+#[utoipa::path(
+    post,
+    path = "/entities/{id}/relationships",
+    tag = "entities",
+    request_body = CreateRelationshipRequest,
+    params(
+        ("id" = Uuid, Path, description = "Entity ID"),
+    ),
+    responses(
+        (status = 201, description = "The new relationship", body = ApiResponse<serde_json::Value>),
+        (status = 404, description = "Entity not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn create_relationship(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let entity_id = params.id("id", "Invalid entity ID")?;
+    if !has_access(&state.db_pool, &user, entity_id, false).await? {
+        return error_response(404, "Entity not found");
+    }
+
+    let request: CreateRelationshipRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
     };
 
-    match (method, path) {
-        // Create entity
-        ("POST", "/entities") => {
-            let request: CreateEntityRequest = match shared::parse_json_body(event.body())? {
-                Ok(r) => r,
-                Err(response) => return Ok(response),
-            };
-
-            // Validate entity type
-            if !ENTITY_TYPES.contains(&request.entity_type.as_str()) {
-                return Ok(json_response(
-                    400,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some(format!(
-                            "Invalid entity type. Must be one of: {:?}",
-                            ENTITY_TYPES
-                        )),
-                    },
-                )?);
-            }
+    let target_id = Uuid::parse_str(&request.target_entity_id)
+        .map_err(|_| "Invalid target_entity_id")?;
+
+    let rel_id = Uuid::new_v4();
+    let metadata = request.metadata.unwrap_or(serde_json::json!({}));
 
-            let entity_id = Uuid::new_v4();
-            let visibility = request.visibility_tier.unwrap_or(3);
-            let aliases: Vec<String> = request.aliases.unwrap_or_default();
-            let metadata = request.metadata.unwrap_or(serde_json::json!({}));
-
-            sqlx::query(
-                r#"
-                INSERT INTO entities (id, entity_type, name, description, aliases, metadata,
-                                      owner_type, owner_id, created_by, visibility_tier)
-                VALUES ($1, $2::entity_type, $3, $4, $5, $6, 'user', $7, $7, $8)
-                "#,
+    sqlx::query(
+        r#"
+        INSERT INTO entity_relationships (id, source_entity_id, target_entity_id, relationship_type, metadata, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#
+    )
+    .bind(rel_id)
+    .bind(entity_id)
+    .bind(target_id)
+    .bind(&request.relationship_type)
+    .bind(&metadata)
+    .bind(user_id)
+    .execute(&state.db_pool)
+    .await?;
+
+    audit::record_change(&state.db_pool, user_id, AuditAction::Create, AuditResource::EntityRelationship, rel_id, None).await;
+
+    info!("Created entity relationship {} -> {}", entity_id, target_id);
+
+    Ok(json_response(201, &ApiResponse {
+        success: true,
+        data: Some(serde_json::json!({
+            "relationship_id": rel_id.to_string(),
+            "source_entity_id": entity_id.to_string(),
+            "target_entity_id": target_id.to_string(),
+            "relationship_type": request.relationship_type,
+        })),
+        error: None,
+    })?)
+}
+
+/// GET /entities/{id}/relationships
+#[utoipa::path(
+    get,
+    path = "/entities/{id}/relationships",
+    tag = "entities",
+    params(
+        ("id" = Uuid, Path, description = "Entity ID"),
+    ),
+    responses(
+        (status = 200, description = "The entity's relationships in both directions", body = ApiResponse<Vec<EntityRelationship>>),
+        (status = 404, description = "Entity not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_relationships(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let entity_id = params.id("id", "Invalid entity ID")?;
+    if !has_access(&state.db_pool, &user, entity_id, true).await? {
+        return error_response(404, "Entity not found");
+    }
+
+    let relationships: Vec<EntityRelationship> = sqlx::query_as::<_, (Uuid, Uuid, String, String, String, String)>(
+        r#"
+        SELECT er.id,
+               CASE WHEN er.source_entity_id = $1 THEN er.target_entity_id ELSE er.source_entity_id END as related_id,
+               e.name, e.entity_type::text, er.relationship_type,
+               CASE WHEN er.source_entity_id = $1 THEN 'outgoing' ELSE 'incoming' END as direction
+        FROM entity_relationships er
+        JOIN entities e ON e.id = CASE WHEN er.source_entity_id = $1 THEN er.target_entity_id ELSE er.source_entity_id END
+        WHERE (er.source_entity_id = $1 OR er.target_entity_id = $1)
+        AND e.deleted_at IS NULL
+        ORDER BY e.name
+        "#
+    )
+    .bind(entity_id)
+    .fetch_all(&state.db_pool)
+    .await?
+    .into_iter()
+    .map(|(id, related_id, name, entity_type, relationship_type, direction)| EntityRelationship {
+        id: id.to_string(),
+        related_entity_id: related_id.to_string(),
+        related_entity_name: name,
+        related_entity_type: entity_type,
+        relationship_type,
+        direction,
+    })
+    .collect();
+
+    Ok(json_response(200, &ApiResponse {
+        success: true,
+        data: Some(relationships),
+        error: None,
+    })?)
+}
+
+/// POST /entities/{id}/merge
+#[utoipa::path(
+    post,
+    path = "/entities/{id}/merge",
+    tag = "entities",
+    request_body = MergeEntityRequest,
+    params(
+        ("id" = Uuid, Path, description = "Entity ID"),
+    ),
+    responses(
+        (status = 200, description = "What the merge moved onto this entity", body = ApiResponse<MergeSummary>),
+        (status = 400, description = "Merging an entity into itself, or an invalid source", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Entity not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn merge_entity(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let entity_id = params.id("id", "Invalid entity ID")?;
+    if !has_access(&state.db_pool, &user, entity_id, false).await? {
+        return error_response(404, "Entity not found");
+    }
+
+    let request: MergeEntityRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let source_id = match Uuid::parse_str(&request.source_entity_id) {
+        Ok(id) if id != entity_id => id,
+        Ok(_) => {
+            return error_response(400, "Cannot merge an entity into itself");
+        }
+        Err(_) => {
+            return error_response(400, "Invalid source_entity_id");
+        }
+    };
+
+    let source_access: bool = sqlx::query_scalar(&format!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM entities e
+            LEFT JOIN family_members fm ON e.owner_type = 'family' AND e.owner_id = fm.family_id AND fm.user_id = $2
+            WHERE e.id = $1
+            AND e.deleted_at IS NULL
+            AND (
+                (e.owner_type = 'user' AND e.owner_id = $2)
+                OR (e.owner_type = 'family' AND fm.user_id IS NOT NULL AND {})
             )
-            .bind(entity_id)
-            .bind(&request.entity_type)
-            .bind(&request.name)
-            .bind(&request.description)
-            .bind(&aliases)
-            .bind(&metadata)
-            .bind(user_id)
-            .bind(visibility)
-            .execute(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to create entity: {}", e))?;
-
-            info!("Created entity {} ({})", entity_id, request.name);
-
-            Ok(json_response(
-                201,
-                &ApiResponse {
-                    success: true,
-                    data: Some(serde_json::json!({
-                        "entity_id": entity_id.to_string(),
-                        "name": request.name,
-                        "entity_type": request.entity_type,
-                    })),
-                    error: None,
-                },
-            )?)
+        )
+        "#,
+        space_clause("e", 2)
+    ))
+    .bind(source_id)
+    .bind(user_id)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    if !source_access {
+        return error_response(404, "Source entity not found");
+    }
+
+    let target_before = audit::snapshot(&state.db_pool, AuditResource::Entity, entity_id).await;
+    let source_before = audit::snapshot(&state.db_pool, AuditResource::Entity, source_id).await;
+
+    let summary = match merge_entities(
+        &state.db_pool,
+        entity_id,
+        source_id,
+        request.keep_alias.unwrap_or(true),
+        user_id,
+    )
+    .await
+    {
+        Ok(summary) => summary,
+        // The source was merged or deleted by a concurrent request
+        Err(shared::Error::NotFound(e)) => {
+            return error_response(404, e);
         }
+        Err(e) => return Err(e.into()),
+    };
 
-        // Search/list entities
-        ("GET", "/entities") => {
-            let params = event.query_string_parameters();
-            let query = params.first("q");
-            let entity_type = params.first("type");
-            let limit: i64 = params.first("limit").and_then(|l| l.parse().ok()).unwrap_or(20);
+    audit::record_change(&state.db_pool, user_id, AuditAction::Merge, AuditResource::Entity, entity_id, target_before).await;
+    audit::record_change(&state.db_pool, user_id, AuditAction::Merge, AuditResource::Entity, source_id, source_before).await;
+
+    if let Some(publisher) = &state.event_publisher {
+        let event = EntityMerged {
+            target_entity_id: entity_id,
+            merged_entity_ids: vec![source_id],
+            merged_by: user_id,
+            facts_reparented: summary.facts_reparented,
+        };
+        if let Err(e) = publisher.publish(&event).await {
+            tracing::warn!(entity_id = %entity_id, error = %e, "Failed to publish EntityMerged");
+        }
+    }
 
-            // Get user's family IDs for permission check
-            let family_ids: Vec<Uuid> = sqlx::query_scalar(
-                "SELECT family_id FROM family_members WHERE user_id = $1"
-            )
-            .bind(user_id)
-            .fetch_all(&state.db_pool)
-            .await
-            .unwrap_or_default();
-
-            let entities: Vec<EntityResponse> = if let Some(q) = query {
-                // Search with fuzzy matching
-                sqlx::query_as::<_, (Uuid, String, String, Option<String>, Vec<String>, i16, chrono::DateTime<chrono::Utc>, i64)>(
-                    r#"
-                    SELECT e.id, e.entity_type::text, e.name, e.description, e.aliases,
-                           e.visibility_tier, e.created_at, COALESCE(COUNT(f.id), 0) as fact_count
-                    FROM entities e
-                    LEFT JOIN facts f ON f.about_entity_id = e.id
-                    WHERE (
-                        (e.owner_type = 'user' AND e.owner_id = $1)
-                        OR (e.owner_type = 'family' AND e.owner_id = ANY($2))
-                    )
-                    AND (
-                        e.name ILIKE $3
-                        OR e.normalized_name ILIKE $3
-                        OR $4 = ANY(e.aliases)
-                    )
-                    GROUP BY e.id
-                    ORDER BY e.name
-                    LIMIT $5
-                    "#,
-                )
-                .bind(user_id)
-                .bind(&family_ids)
-                .bind(format!("%{}%", q))
-                .bind(q.to_lowercase())
-                .bind(limit)
-                .fetch_all(&state.db_pool)
-                .await
-            } else if let Some(etype) = entity_type {
-                // Filter by type
-                sqlx::query_as::<_, (Uuid, String, String, Option<String>, Vec<String>, i16, chrono::DateTime<chrono::Utc>, i64)>(
-                    r#"
-                    SELECT e.id, e.entity_type::text, e.name, e.description, e.aliases,
-                           e.visibility_tier, e.created_at, COALESCE(COUNT(f.id), 0) as fact_count
-                    FROM entities e
-                    LEFT JOIN facts f ON f.about_entity_id = e.id
-                    WHERE (
-                        (e.owner_type = 'user' AND e.owner_id = $1)
-                        OR (e.owner_type = 'family' AND e.owner_id = ANY($2))
-                    )
-                    AND e.entity_type = $3::entity_type
-                    GROUP BY e.id
-                    ORDER BY e.name
-                    LIMIT $4
-                    "#,
-                )
-                .bind(user_id)
-                .bind(&family_ids)
-                .bind(etype)
-                .bind(limit)
-                .fetch_all(&state.db_pool)
-                .await
-            } else {
-                // List all
-                sqlx::query_as::<_, (Uuid, String, String, Option<String>, Vec<String>, i16, chrono::DateTime<chrono::Utc>, i64)>(
-                    r#"
-                    SELECT e.id, e.entity_type::text, e.name, e.description, e.aliases,
-                           e.visibility_tier, e.created_at, COALESCE(COUNT(f.id), 0) as fact_count
-                    FROM entities e
-                    LEFT JOIN facts f ON f.about_entity_id = e.id
-                    WHERE (
-                        (e.owner_type = 'user' AND e.owner_id = $1)
-                        OR (e.owner_type = 'family' AND e.owner_id = ANY($2))
-                    )
-                    GROUP BY e.id
-                    ORDER BY fact_count DESC, e.name
-                    LIMIT $3
-                    "#,
-                )
-                .bind(user_id)
-                .bind(&family_ids)
-                .bind(limit)
-                .fetch_all(&state.db_pool)
-                .await
-            }
-            .map_err(|e| format!("Failed to fetch entities: {}", e))?
-            .into_iter()
-            .map(|(id, entity_type, name, description, aliases, visibility_tier, created_at, fact_count)| {
-                EntityResponse {
-                    id: id.to_string(),
-                    entity_type,
-                    name,
-                    description,
-                    aliases,
-                    visibility_tier,
-                    created_at: created_at.to_rfc3339(),
-                    fact_count,
-                }
-            })
-            .collect();
-
-            Ok(json_response(
-                200,
-                &ApiResponse {
-                    success: true,
-                    data: Some(entities),
-                    error: None,
-                },
-            )?)
+    info!(
+        "Merged entity {} into {} ({} facts re-parented)",
+        source_id, entity_id, summary.facts_reparented
+    );
+
+    Ok(json_response(200, &ApiResponse {
+        success: true,
+        data: Some(summary),
+        error: None,
+    })?)
+}
+
+/// POST /entities/{id}/photo
+#[utoipa::path(
+    post,
+    path = "/entities/{id}/photo",
+    tag = "entities",
+    request_body = PhotoUploadRequest,
+    params(
+        ("id" = Uuid, Path, description = "Entity ID"),
+    ),
+    responses(
+        (status = 200, description = "A presigned URL to PUT the photo to", body = ApiResponse<PhotoUploadResponse>),
+        (status = 400, description = "Unsupported type or size", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Entity not found", body = ApiError, content_type = "application/problem+json"),
+        (status = 503, description = "Photo uploads are not configured", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn upload_photo(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let entity_id = params.id("id", "Invalid entity ID")?;
+    if !has_access(&state.db_pool, &user, entity_id, false).await? {
+        return error_response(404, "Entity not found");
+    }
+
+    let bucket = match state.photos_bucket.as_deref() {
+        Some(bucket) => bucket,
+        None => {
+            return error_response(503, "Photo uploads are not configured");
         }
+    };
 
-        // Entity-specific routes
-        _ if path.starts_with("/entities/") => {
-            let path_parts: Vec<&str> = path.trim_start_matches("/entities/").split('/').collect();
-            let entity_id = Uuid::parse_str(path_parts[0])
-                .map_err(|_| "Invalid entity ID")?;
-
-            // Verify access to entity
-            let has_access: bool = sqlx::query_scalar(
-                r#"
-                SELECT EXISTS(
-                    SELECT 1 FROM entities e
-                    LEFT JOIN family_members fm ON e.owner_type = 'family' AND e.owner_id = fm.family_id AND fm.user_id = $2
-                    WHERE e.id = $1
-                    AND (
-                        (e.owner_type = 'user' AND e.owner_id = $2)
-                        OR (e.owner_type = 'family' AND fm.user_id IS NOT NULL)
-                    )
-                )
-                "#
-            )
-            .bind(entity_id)
-            .bind(user_id)
-            .fetch_one(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to verify access: {}", e))?;
-
-            if !has_access {
-                return Ok(json_response(
-                    404,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Entity not found".to_string()),
-                    },
-                )?);
-            }
+    let request: PhotoUploadRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
 
-            match (method, path_parts.get(1)) {
-                // Get entity details
-                ("GET", None) => {
-                    let entity = sqlx::query_as::<_, (Uuid, String, String, Option<String>, Vec<String>, serde_json::Value, i16, Option<Uuid>, chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)>(
-                        r#"
-                        SELECT id, entity_type::text, name, description, aliases, metadata,
-                               visibility_tier, linked_user_id, created_at, updated_at
-                        FROM entities WHERE id = $1
-                        "#
-                    )
-                    .bind(entity_id)
-                    .fetch_one(&state.db_pool)
-                    .await
-                    .map_err(|e| format!("Failed to fetch entity: {}", e))?;
-
-                    // Get attributes
-                    let attributes: Vec<EntityAttribute> = sqlx::query_as::<_, (String, String, Option<chrono::NaiveDate>, Option<chrono::NaiveDate>)>(
-                        r#"
-                        SELECT attribute_name, attribute_value, valid_from, valid_to
-                        FROM entity_attributes
-                        WHERE entity_id = $1
-                        AND (valid_to IS NULL OR valid_to > CURRENT_DATE)
-                        ORDER BY attribute_name
-                        "#
-                    )
-                    .bind(entity_id)
-                    .fetch_all(&state.db_pool)
-                    .await
-                    .unwrap_or_default()
-                    .into_iter()
-                    .map(|(name, value, valid_from, valid_to)| EntityAttribute {
-                        name,
-                        value,
-                        valid_from: valid_from.map(|d| d.to_string()),
-                        valid_to: valid_to.map(|d| d.to_string()),
-                    })
-                    .collect();
-
-                    // Get locations
-                    let locations: Vec<EntityLocation> = sqlx::query_as::<_, (String, Option<String>, Option<f64>, Option<f64>)>(
-                        r#"
-                        SELECT label, address_raw,
-                               ST_Y(location::geometry) as lat, ST_X(location::geometry) as lng
-                        FROM entity_locations
-                        WHERE entity_id = $1
-                        AND (valid_to IS NULL OR valid_to > CURRENT_DATE)
-                        ORDER BY label
-                        "#
-                    )
-                    .bind(entity_id)
-                    .fetch_all(&state.db_pool)
-                    .await
-                    .unwrap_or_default()
-                    .into_iter()
-                    .map(|(label, address, latitude, longitude)| EntityLocation {
-                        label,
-                        address,
-                        latitude,
-                        longitude,
-                    })
-                    .collect();
-
-                    // Get relationships
-                    let relationships: Vec<EntityRelationship> = sqlx::query_as::<_, (Uuid, Uuid, String, String, String, String)>(
-                        r#"
-                        SELECT er.id,
-                               CASE WHEN er.source_entity_id = $1 THEN er.target_entity_id ELSE er.source_entity_id END as related_id,
-                               e.name, e.entity_type::text, er.relationship_type,
-                               CASE WHEN er.source_entity_id = $1 THEN 'outgoing' ELSE 'incoming' END as direction
-                        FROM entity_relationships er
-                        JOIN entities e ON e.id = CASE WHEN er.source_entity_id = $1 THEN er.target_entity_id ELSE er.source_entity_id END
-                        WHERE er.source_entity_id = $1 OR er.target_entity_id = $1
-                        ORDER BY e.name
-                        "#
-                    )
-                    .bind(entity_id)
-                    .fetch_all(&state.db_pool)
-                    .await
-                    .unwrap_or_default()
-                    .into_iter()
-                    .map(|(id, related_id, name, entity_type, relationship_type, direction)| EntityRelationship {
-                        id: id.to_string(),
-                        related_entity_id: related_id.to_string(),
-                        related_entity_name: name,
-                        related_entity_type: entity_type,
-                        relationship_type,
-                        direction,
-                    })
-                    .collect();
-
-                    let response = EntityDetailResponse {
-                        id: entity.0.to_string(),
-                        entity_type: entity.1,
-                        name: entity.2,
-                        description: entity.3,
-                        aliases: entity.4,
-                        metadata: entity.5,
-                        visibility_tier: entity.6,
-                        linked_user_id: entity.7.map(|u| u.to_string()),
-                        created_at: entity.8.to_rfc3339(),
-                        updated_at: entity.9.to_rfc3339(),
-                        attributes,
-                        locations,
-                        relationships,
-                    };
-
-                    Ok(json_response(200, &ApiResponse {
-                        success: true,
-                        data: Some(response),
-                        error: None,
-                    })?)
-                }
-
-                // Update entity
-                ("PUT", None) => {
-                    let request: UpdateEntityRequest = match shared::parse_json_body(event.body())? {
-                        Ok(r) => r,
-                        Err(response) => return Ok(response),
-                    };
-
-                    // Update each field individually for simplicity
-                    sqlx::query("UPDATE entities SET updated_at = NOW() WHERE id = $1")
-                        .bind(entity_id)
-                        .execute(&state.db_pool)
-                        .await
-                        .map_err(|e| format!("Failed to update entity: {}", e))?;
-
-                    if let Some(name) = &request.name {
-                        sqlx::query("UPDATE entities SET name = $2 WHERE id = $1")
-                            .bind(entity_id)
-                            .bind(name)
-                            .execute(&state.db_pool)
-                            .await?;
-                    }
-                    if let Some(desc) = &request.description {
-                        sqlx::query("UPDATE entities SET description = $2 WHERE id = $1")
-                            .bind(entity_id)
-                            .bind(desc)
-                            .execute(&state.db_pool)
-                            .await?;
-                    }
-                    if let Some(aliases) = &request.aliases {
-                        sqlx::query("UPDATE entities SET aliases = $2 WHERE id = $1")
-                            .bind(entity_id)
-                            .bind(aliases)
-                            .execute(&state.db_pool)
-                            .await?;
-                    }
-                    if let Some(metadata) = &request.metadata {
-                        sqlx::query("UPDATE entities SET metadata = $2 WHERE id = $1")
-                            .bind(entity_id)
-                            .bind(metadata)
-                            .execute(&state.db_pool)
-                            .await?;
-                    }
-                    if let Some(visibility) = request.visibility_tier {
-                        sqlx::query("UPDATE entities SET visibility_tier = $2 WHERE id = $1")
-                            .bind(entity_id)
-                            .bind(visibility)
-                            .execute(&state.db_pool)
-                            .await?;
-                    }
-
-                    info!("Updated entity {}", entity_id);
-
-                    Ok(json_response(200, &ApiResponse {
-                        success: true,
-                        data: Some(serde_json::json!({"message": "Entity updated"})),
-                        error: None,
-                    })?)
-                }
-
-                // Delete entity
-                ("DELETE", None) => {
-                    sqlx::query("DELETE FROM entities WHERE id = $1")
-                        .bind(entity_id)
-                        .execute(&state.db_pool)
-                        .await
-                        .map_err(|e| format!("Failed to delete entity: {}", e))?;
-
-                    info!("Deleted entity {}", entity_id);
-
-                    Ok(json_response(200, &ApiResponse {
-                        success: true,
-                        data: Some(serde_json::json!({"message": "Entity deleted"})),
-                        error: None,
-                    })?)
-                }
-
-                // Get entity facts (timeline)
-                ("GET", Some(&"facts")) => {
-                    let params = event.query_string_parameters();
-                    let limit: i64 = params.first("limit").and_then(|l| l.parse().ok()).unwrap_or(50);
-
-                    let facts: Vec<FactTimelineEntry> = sqlx::query_as::<_, (Uuid, String, i16, chrono::DateTime<chrono::Utc>, Option<chrono::NaiveDate>, Option<chrono::NaiveDate>)>(
-                        r#"
-                        SELECT f.id, f.content, f.importance, f.recorded_at, f.valid_from, f.valid_to
-                        FROM facts f
-                        WHERE f.about_entity_id = $1
-                        ORDER BY COALESCE(f.valid_from, f.recorded_at::date) DESC, f.importance DESC
-                        LIMIT $2
-                        "#
-                    )
-                    .bind(entity_id)
-                    .bind(limit)
-                    .fetch_all(&state.db_pool)
-                    .await
-                    .map_err(|e| format!("Failed to fetch facts: {}", e))?
-                    .into_iter()
-                    .map(|(id, content, importance, recorded_at, valid_from, valid_to)| FactTimelineEntry {
-                        id: id.to_string(),
-                        content,
-                        importance,
-                        recorded_at: recorded_at.to_rfc3339(),
-                        valid_from: valid_from.map(|d| d.to_string()),
-                        valid_to: valid_to.map(|d| d.to_string()),
-                    })
-                    .collect();
-
-                    Ok(json_response(200, &ApiResponse {
-                        success: true,
-                        data: Some(serde_json::json!({
-                            "entity_id": entity_id.to_string(),
-                            "facts": facts,
-                            "count": facts.len(),
-                        })),
-                        error: None,
-                    })?)
-                }
-
-                // Create entity relationship
-                ("POST", Some(&"relationships")) => {
-                    let request: CreateRelationshipRequest = match shared::parse_json_body(event.body())? {
-                        Ok(r) => r,
-                        Err(response) => return Ok(response),
-                    };
-
-                    let target_id = Uuid::parse_str(&request.target_entity_id)
-                        .map_err(|_| "Invalid target_entity_id")?;
-
-                    let rel_id = Uuid::new_v4();
-                    let metadata = request.metadata.unwrap_or(serde_json::json!({}));
-
-                    sqlx::query(
-                        r#"
-                        INSERT INTO entity_relationships (id, source_entity_id, target_entity_id, relationship_type, metadata, created_by)
-                        VALUES ($1, $2, $3, $4, $5, $6)
-                        "#
-                    )
-                    .bind(rel_id)
-                    .bind(entity_id)
-                    .bind(target_id)
-                    .bind(&request.relationship_type)
-                    .bind(&metadata)
-                    .bind(user_id)
-                    .execute(&state.db_pool)
-                    .await
-                    .map_err(|e| format!("Failed to create relationship: {}", e))?;
-
-                    info!("Created entity relationship {} -> {}", entity_id, target_id);
-
-                    Ok(json_response(201, &ApiResponse {
-                        success: true,
-                        data: Some(serde_json::json!({
-                            "relationship_id": rel_id.to_string(),
-                            "source_entity_id": entity_id.to_string(),
-                            "target_entity_id": target_id.to_string(),
-                            "relationship_type": request.relationship_type,
-                        })),
-                        error: None,
-                    })?)
-                }
-
-                // Get entity relationships
-                ("GET", Some(&"relationships")) => {
-                    let relationships: Vec<EntityRelationship> = sqlx::query_as::<_, (Uuid, Uuid, String, String, String, String)>(
-                        r#"
-                        SELECT er.id,
-                               CASE WHEN er.source_entity_id = $1 THEN er.target_entity_id ELSE er.source_entity_id END as related_id,
-                               e.name, e.entity_type::text, er.relationship_type,
-                               CASE WHEN er.source_entity_id = $1 THEN 'outgoing' ELSE 'incoming' END as direction
-                        FROM entity_relationships er
-                        JOIN entities e ON e.id = CASE WHEN er.source_entity_id = $1 THEN er.target_entity_id ELSE er.source_entity_id END
-                        WHERE er.source_entity_id = $1 OR er.target_entity_id = $1
-                        ORDER BY e.name
-                        "#
-                    )
-                    .bind(entity_id)
-                    .fetch_all(&state.db_pool)
-                    .await
-                    .map_err(|e| format!("Failed to fetch relationships: {}", e))?
-                    .into_iter()
-                    .map(|(id, related_id, name, entity_type, relationship_type, direction)| EntityRelationship {
-                        id: id.to_string(),
-                        related_entity_id: related_id.to_string(),
-                        related_entity_name: name,
-                        related_entity_type: entity_type,
-                        relationship_type,
-                        direction,
-                    })
-                    .collect();
-
-                    Ok(json_response(200, &ApiResponse {
-                        success: true,
-                        data: Some(relationships),
-                        error: None,
-                    })?)
-                }
-
-                _ => Ok(json_response(
-                    405,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Method not allowed".to_string()),
-                    },
-                )?),
-            }
+    let (content_type, extension) = match photo_type(&request.content_type) {
+        Some(t) => t,
+        None => {
+            return error_response(
+                400,
+                format!(
+                    "content_type must be one of: {}",
+                    accepted_types()
+                ),
+            );
         }
+    };
+
+    if request.content_length <= 0 || request.content_length > MAX_PHOTO_BYTES {
+        return error_response(
+            400,
+            format!(
+                "content_length must be between 1 and {} bytes",
+                MAX_PHOTO_BYTES
+            ),
+        );
+    }
+
+    let key = photo_key(entity_id, Uuid::new_v4(), extension);
+    let presigning = aws_sdk_s3::presigning::PresigningConfig::expires_in(
+        std::time::Duration::from_secs(UPLOAD_URL_TTL_SECS),
+    )
+    .map_err(|e| format!("Invalid presigning config: {}", e))?;
+
+    let upload = state
+        .s3_client
+        .put_object()
+        .bucket(bucket)
+        .key(&key)
+        .content_type(content_type)
+        .content_length(request.content_length)
+        .presigned(presigning)
+        .await
+        .map_err(|e| format!("Failed to presign upload: {}", e))?;
+
+    let before = audit::snapshot(&state.db_pool, AuditResource::Entity, entity_id).await;
+
+    sqlx::query(
+        "UPDATE entities SET photo_key = $2, photo_updated_at = NOW(), updated_at = NOW() WHERE id = $1",
+    )
+    .bind(entity_id)
+    .bind(&key)
+    .execute(&state.db_pool)
+    .await?;
+
+    audit::record_change(&state.db_pool, user_id, AuditAction::Update, AuditResource::Entity, entity_id, before).await;
+
+    info!("Issued photo upload {} for entity {}", key, entity_id);
+
+    Ok(json_response(200, &ApiResponse {
+        success: true,
+        data: Some(PhotoUploadResponse {
+            upload_url: upload.uri().to_string(),
+            photo_key: key,
+            expires_in: UPLOAD_URL_TTL_SECS,
+        }),
+        error: None,
+    })?)
+}
 
-        _ => Ok(json_response(
-            404,
-            &ApiResponse::<()> {
-                success: false,
-                data: None,
-                error: Some("Not found".to_string()),
-            },
-        )?),
+/// GET /entities/{id}/relationship-health
+#[utoipa::path(
+    get,
+    path = "/entities/{id}/relationship-health",
+    tag = "entities",
+    params(
+        ("id" = Uuid, Path, description = "Entity ID"),
+        ("stale_days" = Option<i64>, Query, description = "Days without an interaction before the relationship is stale"),
+    ),
+    responses(
+        (status = 200, description = "Interaction history summary", body = ApiResponse<RelationshipHealth>),
+        (status = 400, description = "Invalid stale_days", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Entity not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn get_relationship_health(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let entity_id = params.id("id", "Invalid entity ID")?;
+    if !has_access(&state.db_pool, &user, entity_id, true).await? {
+        return error_response(404, "Entity not found");
     }
+
+    let params = event.query_string_parameters();
+    let stale_days = match params.first("stale_days").map(str::parse::<i64>) {
+        None => DEFAULT_STALE_DAYS,
+        Some(Ok(days)) if (1..=3650).contains(&days) => days,
+        Some(_) => {
+            return error_response(
+                400,
+                "stale_days must be between 1 and 3650",
+            );
+        }
+    };
+
+    let health = entity_health(&state.db_pool, entity_id, stale_days).await?;
+
+    Ok(json_response(200, &ApiResponse {
+        success: true,
+        data: Some(health),
+        error: None,
+    })?)
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .post("/entities", create_entity)
+        .get("/entities", list_entities)
+        .get("/entities/{id}", get_entity)
+        .put("/entities/{id}", update_entity)
+        .delete("/entities/{id}", delete_entity)
+        .get("/entities/{id}/facts", get_entity_facts)
+        .post("/entities/{id}/relationships", create_relationship)
+        .get("/entities/{id}/relationships", list_relationships)
+        .post("/entities/{id}/merge", merge_entity)
+        .post("/entities/{id}/photo", upload_photo)
+        .get("/entities/{id}/relationship-health", get_relationship_health)
 }
 
 fn json_response<T: Serialize>(status: u16, data: &T) -> Result<Response<Body>, Error> {
     Ok(Response::builder()
         .status(status)
         .header("content-type", "application/json")
-        .body(Body::from(serde_json::to_string(data)?))
-        .expect("Failed to build response"))
+        .body(Body::from(serde_json::to_string(data)?))?)
 }
 
 #[tokio::main]
@@ -798,10 +1350,12 @@ async fn main() -> Result<(), Error> {
         .init();
 
     let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
 
     run(service_fn(move |event| {
         let state = Arc::clone(&state);
-        async move { handler(state, event).await }
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
     }))
     .await
 }
@@ -0,0 +1,423 @@
+//! Export Lambda - Download the knowledge graph, or all of a user's data.
+//!
+//! Endpoints:
+//! - GET /export/graph - Entity/relationship graph or facts as a file
+//!   (`?format=graphml|cypher|neo4j-nodes|neo4j-relationships|jsonld`,
+//!   default `graphml`)
+//! - POST /export - Start a full data export
+//! - GET /export/{id} - Data export status, with a download link once complete
+//!
+//! Graph exports cover the user's own and their families' entities and facts;
+//! the formats are rendered by `shared::graph_export`.
+//!
+//! Full data exports run asynchronously: the job is recorded in `data_exports`
+//! and handed to the `data_export` Lambda, which writes a zip bundle (see
+//! `shared::data_export`) to the exports bucket.
+
+use aws_sdk_lambda::primitives::Blob;
+use chrono::{DateTime, Utc};
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::Serialize;
+use shared::data_export::{export_file_name, DOWNLOAD_URL_TTL_SECS};
+use shared::graph_export::{
+    fetch_entities, fetch_facts, fetch_relationships, render_cypher, render_graphml, render_jsonld,
+    render_neo4j_nodes, render_neo4j_relationships, ExportFormat,
+};
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::{Limit, RateLimit};
+use shared::router::{require_user, Cors, PathParams, Query, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::ApiError;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Exports a user can start a minute (each one builds a full bundle)
+const EXPORTS_PER_MINUTE: u32 = 2;
+
+/// Data export row from database
+#[derive(Debug, sqlx::FromRow)]
+struct DataExportRow {
+    id: Uuid,
+    status: String,
+    object_key: Option<String>,
+    size_bytes: Option<i64>,
+    record_counts: serde_json::Value,
+    error_message: Option<String>,
+    created_at: DateTime<Utc>,
+    completed_at: Option<DateTime<Utc>>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+/// Data export API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct DataExportResponse {
+    id: String,
+    /// `pending`, `running`, `completed`, `failed` or `expired`
+    status: String,
+    /// Rows exported per dataset
+    record_counts: serde_json::Value,
+    size_bytes: Option<i64>,
+    /// Presigned link to the zip bundle, valid for an hour
+    download_url: Option<String>,
+    error: Option<String>,
+    created_at: String,
+    completed_at: Option<String>,
+    /// When the bundle is deleted
+    expires_at: Option<String>,
+}
+
+const EXPORT_COLUMNS: &str = r#"
+    id, status::text AS status, object_key, size_bytes, record_counts, error_message,
+    created_at, completed_at, expires_at
+"#;
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+    s3_client: aws_sdk_s3::Client,
+    lambda_client: aws_sdk_lambda::Client,
+    exports_bucket: String,
+    /// Lambda that builds data export bundles
+    data_export_function: String,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        let exports_bucket =
+            std::env::var("EXPORTS_BUCKET").map_err(|_| "EXPORTS_BUCKET not set")?;
+        let data_export_function = std::env::var("DATA_EXPORT_FUNCTION_NAME")
+            .map_err(|_| "DATA_EXPORT_FUNCTION_NAME not set")?;
+
+        Ok(Self {
+            db_pool,
+            s3_client: aws_sdk_s3::Client::new(&config),
+            lambda_client: aws_sdk_lambda::Client::new(&config),
+            exports_bucket,
+            data_export_function,
+        })
+    }
+}
+
+/// GET /export/graph
+///
+/// Returns the export as a file download rather than the usual JSON wrapper.
+#[utoipa::path(
+    get,
+    path = "/export/graph",
+    tag = "export",
+    params(
+        ("format" = Option<String>, Query, description = "`graphml` (default), `cypher`, `neo4j-nodes`, `neo4j-relationships` or `jsonld` (facts)"),
+    ),
+    responses(
+        (status = 200, description = "The export as a file download", content((String = "application/graphml+xml"), (String = "text/plain"), (String = "text/csv"), (String = "application/ld+json"))),
+        (status = 400, description = "Unknown format", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn export_graph(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let format = match Query::from_request(&event).get::<String>("format") {
+        Ok(None) => ExportFormat::GraphMl,
+        Ok(Some(value)) => match ExportFormat::parse(&value) {
+            Some(format) => format,
+            None => {
+                return error_response(
+                    400,
+                    format!("format must be one of: {}", ExportFormat::names()),
+                )
+            }
+        },
+        Err(e) => return error_response(400, e.to_string()),
+    };
+
+    let entities = fetch_entities(&state.db_pool, user.user_id, &user.family_ids).await?;
+
+    let body = if format.includes_facts() {
+        let facts = fetch_facts(&state.db_pool, user.user_id, &user.family_ids).await?;
+        serde_json::to_string_pretty(&render_jsonld(&entities, &facts))?
+    } else {
+        let relationships =
+            fetch_relationships(&state.db_pool, user.user_id, &user.family_ids).await?;
+        match format {
+            ExportFormat::Cypher => render_cypher(&entities, &relationships),
+            ExportFormat::Neo4jNodes => render_neo4j_nodes(&entities),
+            ExportFormat::Neo4jRelationships => render_neo4j_relationships(&relationships),
+            _ => render_graphml(&entities, &relationships),
+        }
+    };
+
+    info!(
+        "Exported {} entities as {:?} for user {}",
+        entities.len(),
+        format,
+        user.user_id
+    );
+
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", format.content_type())
+        .header(
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", format.file_name()),
+        )
+        .body(Body::from(body))?)
+}
+
+/// Response for a data export, with a fresh download link if its bundle is
+/// still available.
+async fn export_response(state: &AppState, row: DataExportRow) -> DataExportResponse {
+    let now = Utc::now();
+    let expired = row.status == "completed" && row.expires_at.is_some_and(|at| at <= now);
+
+    let download_url = match (&row.object_key, row.status.as_str()) {
+        (Some(key), "completed") if !expired => {
+            let file_name = export_file_name(row.completed_at.unwrap_or(row.created_at));
+            download_url(state, key, &file_name).await
+        }
+        _ => None,
+    };
+
+    DataExportResponse {
+        id: row.id.to_string(),
+        status: if expired {
+            "expired".to_string()
+        } else {
+            row.status
+        },
+        record_counts: row.record_counts,
+        size_bytes: row.size_bytes,
+        download_url,
+        error: row.error_message,
+        created_at: row.created_at.to_rfc3339(),
+        completed_at: row.completed_at.map(|t| t.to_rfc3339()),
+        expires_at: row.expires_at.map(|t| t.to_rfc3339()),
+    }
+}
+
+/// Presigned GET URL for an export bundle, or `None` if it can't be signed.
+async fn download_url(state: &AppState, key: &str, file_name: &str) -> Option<String> {
+    let config = aws_sdk_s3::presigning::PresigningConfig::expires_in(
+        std::time::Duration::from_secs(DOWNLOAD_URL_TTL_SECS),
+    )
+    .ok()?;
+
+    match state
+        .s3_client
+        .get_object()
+        .bucket(&state.exports_bucket)
+        .key(key)
+        .response_content_disposition(format!("attachment; filename=\"{}\"", file_name))
+        .presigned(config)
+        .await
+    {
+        Ok(request) => Some(request.uri().to_string()),
+        Err(e) => {
+            tracing::warn!("Failed to presign export {}: {}", key, e);
+            None
+        }
+    }
+}
+
+/// POST /export
+///
+/// Starts a full data export. A user has at most one export in progress;
+/// asking again returns that one.
+#[utoipa::path(
+    post,
+    path = "/export",
+    tag = "export",
+    responses(
+        (status = 202, description = "The export, or the one already in progress", body = ApiResponse<DataExportResponse>),
+        (status = 429, description = "Too many exports started; see `Retry-After`", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn start_export(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let created: Option<DataExportRow> = sqlx::query_as(&format!(
+        r#"
+        INSERT INTO data_exports (user_id)
+        VALUES ($1)
+        ON CONFLICT (user_id) WHERE status IN ('pending', 'running') DO NOTHING
+        RETURNING {}
+        "#,
+        EXPORT_COLUMNS
+    ))
+    .bind(user.user_id)
+    .fetch_optional(&state.db_pool)
+    .await?;
+
+    let Some(export) = created else {
+        let in_progress: DataExportRow = sqlx::query_as(&format!(
+            r#"
+            SELECT {} FROM data_exports
+            WHERE user_id = $1 AND status IN ('pending', 'running')
+            "#,
+            EXPORT_COLUMNS
+        ))
+        .bind(user.user_id)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+        return json_response(
+            202,
+            &ApiResponse {
+                success: true,
+                data: Some(export_response(&state, in_progress).await),
+                error: None,
+            },
+        );
+    };
+
+    let payload = serde_json::to_vec(&serde_json::json!({ "export_id": export.id }))?;
+    let invoked = state
+        .lambda_client
+        .invoke()
+        .function_name(&state.data_export_function)
+        .invocation_type(aws_sdk_lambda::types::InvocationType::Event)
+        .payload(Blob::new(payload))
+        .send()
+        .await;
+
+    if let Err(e) = invoked {
+        let message = format!("Failed to start export: {}", e);
+        sqlx::query("UPDATE data_exports SET status = 'failed', error_message = $2 WHERE id = $1")
+            .bind(export.id)
+            .bind(&message)
+            .execute(&state.db_pool)
+            .await?;
+
+        return Err(message.into());
+    }
+
+    info!(user_id = %user.user_id, export_id = %export.id, "Started data export");
+
+    json_response(
+        202,
+        &ApiResponse {
+            success: true,
+            data: Some(export_response(&state, export).await),
+            error: None,
+        },
+    )
+}
+
+/// GET /export/{id}
+#[utoipa::path(
+    get,
+    path = "/export/{id}",
+    tag = "export",
+    params(
+        ("id" = Uuid, Path, description = "Export ID"),
+    ),
+    responses(
+        (status = 200, description = "The export's status, with a download link once complete", body = ApiResponse<DataExportResponse>),
+        (status = 404, description = "Not one of your exports", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn get_export(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let export_id = match params.get::<Uuid>("id") {
+        Ok(id) => id,
+        Err(_) => return error_response(400, "Invalid export ID"),
+    };
+
+    let export: Option<DataExportRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM data_exports WHERE id = $1 AND user_id = $2",
+        EXPORT_COLUMNS
+    ))
+    .bind(export_id)
+    .bind(user.user_id)
+    .fetch_optional(&state.db_pool)
+    .await?;
+
+    let Some(export) = export else {
+        return error_response(404, "Export not found");
+    };
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(export_response(&state, export).await),
+            error: None,
+        },
+    )
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default().limit("POST", "/export", Limit::per_minute(EXPORTS_PER_MINUTE)))
+        .get("/export/graph", export_graph)
+        .post("/export", start_export)
+        .get("/export/{id}", get_export)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
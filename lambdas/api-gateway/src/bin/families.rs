@@ -5,33 +5,57 @@
 //! - GET /families - List user's families
 //! - GET /families/{id} - Get family details
 //! - POST /families/{id}/members - Invite member
-//! - DELETE /families/{id}/members/{user_id} - Remove member
+//! - DELETE /families/{id}/members/{userId} - Remove member
+//! - POST /families/{id}/join-codes - Issue a join code (admins)
+//! - POST /families/join - Join a family with a code
 
-use lambda_http::{run, service_fn, Body, Error, Request, RequestExt, Response};
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
 use serde::{Deserialize, Serialize};
-use sqlx::postgres::PgPoolOptions;
+use shared::audit::{self, AuditAction, AuditResource};
+use shared::family_join_codes::{self, JoinCodeOptions, JoinOutcome};
+use shared::{ApiError, AuthorizedUser};
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
 use sqlx::PgPool;
 use std::sync::Arc;
 use tracing::{error, info};
 use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 /// Create family request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct CreateFamilyRequest {
     name: String,
     description: Option<String>,
 }
 
 /// Invite member request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct InviteMemberRequest {
     email: String,
     role: Option<String>, // "admin" or "member"
 }
 
+/// Create join code request
+#[derive(Debug, Default, Deserialize, ToSchema)]
+struct CreateJoinCodeRequest {
+    expires_in_minutes: Option<i32>,
+    max_uses: Option<i32>,
+    role: Option<String>,
+}
+
+/// Join family request
+#[derive(Debug, Deserialize, ToSchema)]
+struct JoinFamilyRequest {
+    code: String,
+}
+
 /// Family response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct FamilyResponse {
     id: String,
     name: String,
@@ -52,7 +76,7 @@ struct FamilyMemberResponse {
 }
 
 /// API response wrapper
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct ApiResponse<T> {
     success: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -69,35 +93,9 @@ struct AppState {
 impl AppState {
     async fn new() -> Result<Self, Error> {
         let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
-        let secrets_client = aws_sdk_secretsmanager::Client::new(&config);
 
         // Get database credentials
-        let db_secret_arn =
-            std::env::var("DB_SECRET_ARN").map_err(|_| "DB_SECRET_ARN not set")?;
-
-        let db_secret = secrets_client
-            .get_secret_value()
-            .secret_id(&db_secret_arn)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to get DB secret: {}", e))?;
-
-        let db_creds: serde_json::Value =
-            serde_json::from_str(db_secret.secret_string().unwrap_or("{}"))?;
-
-        let db_host = std::env::var("DB_HOST").map_err(|_| "DB_HOST not set")?;
-        let db_name = std::env::var("DB_NAME").unwrap_or_else(|_| "second_brain".to_string());
-        let db_user = db_creds["username"].as_str().unwrap_or("sbadmin");
-        let db_pass = db_creds["password"].as_str().unwrap_or("");
-
-        let database_url = format!(
-            "postgres://{}:{}@{}:5432/{}",
-            db_user, db_pass, db_host, db_name
-        );
-
-        let db_pool = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(&database_url)
+        let db_pool = shared::db::connect(&config)
             .await
             .map_err(|e| format!("Failed to connect to database: {}", e))?;
 
@@ -105,416 +103,557 @@ impl AppState {
     }
 }
 
-/// Extract user_id from Cognito claims in request context
-fn extract_user_id(event: &Request) -> Result<Uuid, Error> {
-    let context = event
-        .request_context_ref()
-        .ok_or("Missing request context")?;
+/// Whether the user is a member of the family.
+async fn is_member(pool: &PgPool, family_id: Uuid, user_id: Uuid) -> Result<bool, Error> {
+    let is_member: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM family_members WHERE family_id = $1 AND user_id = $2)",
+    )
+    .bind(family_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
 
-    let claims = context
-        .authorizer()
-        .and_then(|a| a.fields.get("claims"))
-        .ok_or("Missing claims in authorizer context")?;
+    Ok(is_member)
+}
 
-    let sub = claims
-        .get("sub")
-        .and_then(|v| v.as_str())
-        .ok_or("Missing sub claim")?;
 
-    Uuid::parse_str(sub).map_err(|e| format!("Invalid user_id: {}", e).into())
+/// POST /families
+#[utoipa::path(
+    post,
+    path = "/families",
+    tag = "families",
+    request_body = CreateFamilyRequest,
+    responses(
+        (status = 201, description = "The new family's `family_id` and `name`; you are its admin", body = ApiResponse<serde_json::Value>),
+    ),
+    security(("cognito" = []))
+)]
+async fn create_family(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let request: CreateFamilyRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let family_id = Uuid::new_v4();
+
+    // Create family (note: description and created_by columns may not exist in older schemas)
+    sqlx::query(
+        r#"
+        INSERT INTO families (id, name)
+        VALUES ($1, $2)
+        "#,
+    )
+    .bind(family_id)
+    .bind(&request.name)
+    .execute(&state.db_pool)
+    .await?;
+
+    // Add creator as admin member
+    let member_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO family_members (family_id, user_id, role)
+        VALUES ($1, $2, 'admin')
+        RETURNING id
+        "#,
+    )
+    .bind(family_id)
+    .bind(user_id)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    audit::record_change(&state.db_pool, user_id, AuditAction::Create, AuditResource::FamilyMember, member_id, None).await;
+
+    AuthorizedUser::invalidate(&user.cognito_sub);
+    info!("Created family {} by user {}", family_id, user_id);
+
+    Ok(json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "family_id": family_id.to_string(),
+                "name": request.name,
+            })),
+            error: None,
+        },
+    )?)
 }
 
-async fn handler(state: Arc<AppState>, event: Request) -> Result<Response<Body>, Error> {
-    let raw_path = event.uri().path();
-    // Strip /api stage prefix if present (API Gateway REST API includes stage in path)
-    let path = raw_path.strip_prefix("/api").unwrap_or(raw_path);
-    let method = event.method().as_str();
-
-    // Extract user_id from auth context
-    let cognito_sub = match extract_user_id(&event) {
-        Ok(id) => id,
-        Err(e) => {
-            return Ok(json_response(
-                401,
-                &ApiResponse::<()> {
-                    success: false,
-                    data: None,
-                    error: Some(format!("Authentication required: {}", e)),
-                },
-            )?);
-        }
+/// GET /families
+#[utoipa::path(
+    get,
+    path = "/families",
+    tag = "families",
+    responses(
+        (status = 200, description = "Families you belong to", body = ApiResponse<Vec<FamilyResponse>>),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_families(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let families: Vec<FamilyResponse> = sqlx::query_as::<_, (Uuid, String, chrono::DateTime<chrono::Utc>, i64)>(
+        r#"
+        SELECT f.id, f.name, f.created_at,
+               (SELECT COUNT(*) FROM family_members fm WHERE fm.family_id = f.id) as member_count
+        FROM families f
+        JOIN family_members fm ON fm.family_id = f.id
+        WHERE fm.user_id = $1
+        ORDER BY f.name
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&state.db_pool)
+    .await?
+    .into_iter()
+    .map(|(id, name, created_at, member_count)| FamilyResponse {
+        id: id.to_string(),
+        name,
+        description: None,
+        created_by: String::new(),
+        created_at: created_at.to_rfc3339(),
+        member_count,
+    })
+    .collect();
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(families),
+            error: None,
+        },
+    )?)
+}
+
+/// POST /families/join
+#[utoipa::path(
+    post,
+    path = "/families/join",
+    tag = "families",
+    request_body = JoinFamilyRequest,
+    responses(
+        (status = 200, description = "You joined the family", body = ApiResponse<serde_json::Value>),
+        (status = 404, description = "Join code is invalid or has expired", body = ApiError, content_type = "application/problem+json"),
+        (status = 429, description = "Too many incorrect codes", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn join_family(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let request: JoinFamilyRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
     };
 
-    // Look up database user_id from Cognito sub
-    let user_id: Uuid = match sqlx::query_scalar::<_, Uuid>(
-        "SELECT id FROM users WHERE cognito_sub = $1::text"
-    )
-    .bind(cognito_sub)
-    .fetch_optional(&state.db_pool)
-    .await
-    .map_err(|e| format!("Failed to lookup user: {}", e))? {
-        Some(id) => id,
-        None => {
-            return Ok(json_response(
-                401,
-                &ApiResponse::<()> {
-                    success: false,
-                    data: None,
-                    error: Some("User not registered".to_string()),
-                },
-            )?);
+    let outcome =
+        family_join_codes::redeem_join_code(&state.db_pool, user_id, &request.code).await?;
+
+    let (family_id, role, joined) = match outcome {
+        JoinOutcome::Joined { family_id, member_id, role } => {
+            audit::record_change(&state.db_pool, user_id, AuditAction::Create, AuditResource::FamilyMember, member_id, None).await;
+
+            AuthorizedUser::invalidate(&user.cognito_sub);
+            info!("User {} joined family {} with a code as {}", user_id, family_id, role);
+            (family_id, Some(role), true)
+        }
+        JoinOutcome::AlreadyMember { family_id } => (family_id, None, false),
+        JoinOutcome::Invalid => {
+            return error_response(404, "Join code is invalid or has expired");
+        }
+        JoinOutcome::TooManyAttempts => {
+            return error_response(429, "Too many incorrect codes, try again later");
         }
     };
 
-    match (method, path) {
-        // Create family
-        ("POST", "/families") => {
-            let request: CreateFamilyRequest = match shared::parse_json_body(event.body())? {
-                Ok(r) => r,
-                Err(response) => return Ok(response),
-            };
-
-            let family_id = Uuid::new_v4();
+    let name: String = sqlx::query_scalar("SELECT name FROM families WHERE id = $1")
+        .bind(family_id)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+    Ok(json_response(
+        if joined { 201 } else { 200 },
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "family_id": family_id.to_string(),
+                "name": name,
+                "role": role,
+                "already_member": !joined,
+            })),
+            error: None,
+        },
+    )?)
+}
 
-            // Create family (note: description and created_by columns may not exist in older schemas)
-            sqlx::query(
-                r#"
-                INSERT INTO families (id, name)
-                VALUES ($1, $2)
-                "#,
-            )
-            .bind(family_id)
-            .bind(&request.name)
-            .execute(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to create family: {}", e))?;
+/// GET /families/{id}
+#[utoipa::path(
+    get,
+    path = "/families/{id}",
+    tag = "families",
+    params(
+        ("id" = Uuid, Path, description = "Family ID"),
+    ),
+    responses(
+        (status = 200, description = "The family and its `members`", body = ApiResponse<serde_json::Value>),
+        (status = 403, description = "Not a member of the family", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Family not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn get_family(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let family_id = params.id("id", "Invalid family ID")?;
+    if !is_member(&state.db_pool, family_id, user_id).await? {
+        return error_response(403, "Not a member of this family");
+    }
 
-            // Add creator as admin member
-            sqlx::query(
+    let family: Option<(Uuid, String, chrono::DateTime<chrono::Utc>)> =
+        sqlx::query_as(
+            "SELECT id, name, created_at FROM families WHERE id = $1"
+        )
+        .bind(family_id)
+        .fetch_optional(&state.db_pool)
+        .await?;
+
+    match family {
+        Some((id, name, created_at)) => {
+            // Get members
+            let members: Vec<FamilyMemberResponse> = sqlx::query_as::<_, (Uuid, String, Option<String>, String, chrono::DateTime<chrono::Utc>)>(
                 r#"
-                INSERT INTO family_members (family_id, user_id, role)
-                VALUES ($1, $2, 'admin')
+                SELECT u.id, u.email, u.display_name, fm.role::text, fm.joined_at
+                FROM family_members fm
+                JOIN users u ON u.id = fm.user_id
+                WHERE fm.family_id = $1
+                ORDER BY fm.role DESC, fm.joined_at
                 "#,
             )
             .bind(family_id)
-            .bind(user_id)
-            .execute(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to add creator as member: {}", e))?;
-
-            info!("Created family {} by user {}", family_id, user_id);
+            .fetch_all(&state.db_pool)
+            .await?
+            .into_iter()
+            .map(|(user_id, email, display_name, role, joined_at)| FamilyMemberResponse {
+                user_id: user_id.to_string(),
+                email,
+                display_name,
+                role,
+                joined_at: joined_at.to_rfc3339(),
+            })
+            .collect();
 
             Ok(json_response(
-                201,
+                200,
                 &ApiResponse {
                     success: true,
                     data: Some(serde_json::json!({
-                        "family_id": family_id.to_string(),
-                        "name": request.name,
+                        "id": id.to_string(),
+                        "name": name,
+                        "created_at": created_at.to_rfc3339(),
+                        "members": members,
                     })),
                     error: None,
                 },
             )?)
         }
+        None => error_response(404, "Family not found"),
+    }
+}
+
+/// POST /families/{id}/members
+#[utoipa::path(
+    post,
+    path = "/families/{id}/members",
+    tag = "families",
+    request_body = InviteMemberRequest,
+    params(
+        ("id" = Uuid, Path, description = "Family ID"),
+    ),
+    responses(
+        (status = 200, description = "The user was added to the family", body = ApiResponse<serde_json::Value>),
+        (status = 403, description = "Not an admin of the family", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "No user with that email", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn invite_member(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let family_id = params.id("id", "Invalid family ID")?;
+    if !is_member(&state.db_pool, family_id, user_id).await? {
+        return error_response(403, "Not a member of this family");
+    }
+
+    // Check if user is admin
+    let is_admin: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM family_members WHERE family_id = $1 AND user_id = $2 AND role = 'admin')"
+    )
+    .bind(family_id)
+    .bind(user_id)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    if !is_admin {
+        return error_response(403, "Only admins can invite members");
+    }
+
+    let request: InviteMemberRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    // Find user by email
+    let invitee: Option<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM users WHERE email = $1"
+    )
+    .bind(&request.email)
+    .fetch_optional(&state.db_pool)
+    .await?;
+
+    match invitee {
+        Some(invitee_id) => {
+            // Add member
+            let role = request.role.unwrap_or_else(|| "member".to_string());
 
-        // List user's families
-        ("GET", "/families") => {
-            let families: Vec<FamilyResponse> = sqlx::query_as::<_, (Uuid, String, chrono::DateTime<chrono::Utc>, i64)>(
+            let member_id: Option<Uuid> = sqlx::query_scalar(
                 r#"
-                SELECT f.id, f.name, f.created_at,
-                       (SELECT COUNT(*) FROM family_members fm WHERE fm.family_id = f.id) as member_count
-                FROM families f
-                JOIN family_members fm ON fm.family_id = f.id
-                WHERE fm.user_id = $1
-                ORDER BY f.name
+                INSERT INTO family_members (family_id, user_id, role, invited_by)
+                VALUES ($1, $2, $3::family_role, $4)
+                ON CONFLICT (family_id, user_id) DO NOTHING
+                RETURNING id
                 "#,
             )
+            .bind(family_id)
+            .bind(invitee_id)
+            .bind(&role)
             .bind(user_id)
-            .fetch_all(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to fetch families: {}", e))?
-            .into_iter()
-            .map(|(id, name, created_at, member_count)| FamilyResponse {
-                id: id.to_string(),
-                name,
-                description: None,
-                created_by: String::new(),
-                created_at: created_at.to_rfc3339(),
-                member_count,
-            })
-            .collect();
+            .fetch_optional(&state.db_pool)
+            .await?;
+
+            if let Some(member_id) = member_id {
+                audit::record_change(&state.db_pool, user_id, AuditAction::Create, AuditResource::FamilyMember, member_id, None).await;
+            }
+
+            info!("Added user {} to family {} with role {}", invitee_id, family_id, role);
 
             Ok(json_response(
                 200,
                 &ApiResponse {
                     success: true,
-                    data: Some(families),
+                    data: Some(serde_json::json!({
+                        "message": "Member added successfully",
+                        "user_id": invitee_id.to_string(),
+                        "role": role,
+                    })),
                     error: None,
                 },
             )?)
         }
+        None => error_response(404, "User not found with that email"),
+    }
+}
 
-        // Get family details or members
-        _ if path.starts_with("/families/") => {
-            let path_parts: Vec<&str> = path.trim_start_matches("/families/").split('/').collect();
+/// POST /families/{id}/join-codes
+#[utoipa::path(
+    post,
+    path = "/families/{id}/join-codes",
+    tag = "families",
+    request_body(content = CreateJoinCodeRequest, description = "Optional; an empty body takes the defaults"),
+    params(
+        ("id" = Uuid, Path, description = "Family ID"),
+    ),
+    responses(
+        (status = 201, description = "A code others can join the family with", body = ApiResponse<family_join_codes::JoinCode>),
+        (status = 400, description = "Invalid expiry, uses or role", body = ApiError, content_type = "application/problem+json"),
+        (status = 403, description = "Not an admin of the family", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn create_join_code(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let family_id = params.id("id", "Invalid family ID")?;
+    if !is_member(&state.db_pool, family_id, user_id).await? {
+        return error_response(403, "Not a member of this family");
+    }
 
-            let family_id = Uuid::parse_str(path_parts[0])
-                .map_err(|_| "Invalid family ID")?;
+    let is_admin: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM family_members WHERE family_id = $1 AND user_id = $2 AND role = 'admin')"
+    )
+    .bind(family_id)
+    .bind(user_id)
+    .fetch_one(&state.db_pool)
+    .await?;
 
-            // Verify user is a member of this family
-            let is_member: bool = sqlx::query_scalar(
-                "SELECT EXISTS(SELECT 1 FROM family_members WHERE family_id = $1 AND user_id = $2)"
-            )
-            .bind(family_id)
-            .bind(user_id)
-            .fetch_one(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to check membership: {}", e))?;
-
-            if !is_member {
-                return Ok(json_response(
-                    403,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Not a member of this family".to_string()),
-                    },
-                )?);
-            }
+    if !is_admin {
+        return error_response(403, "Only admins can create join codes");
+    }
 
-            match (method, path_parts.len()) {
-                // GET /families/{id} - Get family details
-                ("GET", 1) => {
-                    let family: Option<(Uuid, String, chrono::DateTime<chrono::Utc>)> =
-                        sqlx::query_as(
-                            "SELECT id, name, created_at FROM families WHERE id = $1"
-                        )
-                        .bind(family_id)
-                        .fetch_optional(&state.db_pool)
-                        .await
-                        .map_err(|e| format!("Failed to fetch family: {}", e))?;
-
-                    match family {
-                        Some((id, name, created_at)) => {
-                            // Get members
-                            let members: Vec<FamilyMemberResponse> = sqlx::query_as::<_, (Uuid, String, Option<String>, String, chrono::DateTime<chrono::Utc>)>(
-                                r#"
-                                SELECT u.id, u.email, u.display_name, fm.role::text, fm.joined_at
-                                FROM family_members fm
-                                JOIN users u ON u.id = fm.user_id
-                                WHERE fm.family_id = $1
-                                ORDER BY fm.role DESC, fm.joined_at
-                                "#,
-                            )
-                            .bind(family_id)
-                            .fetch_all(&state.db_pool)
-                            .await
-                            .map_err(|e| format!("Failed to fetch members: {}", e))?
-                            .into_iter()
-                            .map(|(user_id, email, display_name, role, joined_at)| FamilyMemberResponse {
-                                user_id: user_id.to_string(),
-                                email,
-                                display_name,
-                                role,
-                                joined_at: joined_at.to_rfc3339(),
-                            })
-                            .collect();
-
-                            Ok(json_response(
-                                200,
-                                &ApiResponse {
-                                    success: true,
-                                    data: Some(serde_json::json!({
-                                        "id": id.to_string(),
-                                        "name": name,
-                                        "created_at": created_at.to_rfc3339(),
-                                        "members": members,
-                                    })),
-                                    error: None,
-                                },
-                            )?)
-                        }
-                        None => Ok(json_response(
-                            404,
-                            &ApiResponse::<()> {
-                                success: false,
-                                data: None,
-                                error: Some("Family not found".to_string()),
-                            },
-                        )?),
-                    }
-                }
-
-                // POST /families/{id}/members - Invite member
-                ("POST", 2) if path_parts[1] == "members" => {
-                    // Check if user is admin
-                    let is_admin: bool = sqlx::query_scalar(
-                        "SELECT EXISTS(SELECT 1 FROM family_members WHERE family_id = $1 AND user_id = $2 AND role = 'admin')"
-                    )
-                    .bind(family_id)
-                    .bind(user_id)
-                    .fetch_one(&state.db_pool)
-                    .await
-                    .map_err(|e| format!("Failed to check admin status: {}", e))?;
-
-                    if !is_admin {
-                        return Ok(json_response(
-                            403,
-                            &ApiResponse::<()> {
-                                success: false,
-                                data: None,
-                                error: Some("Only admins can invite members".to_string()),
-                            },
-                        )?);
-                    }
-
-                    let request: InviteMemberRequest = match shared::parse_json_body(event.body())? {
-                        Ok(r) => r,
-                        Err(response) => return Ok(response),
-                    };
-
-                    // Find user by email
-                    let invitee: Option<Uuid> = sqlx::query_scalar(
-                        "SELECT id FROM users WHERE email = $1"
-                    )
-                    .bind(&request.email)
-                    .fetch_optional(&state.db_pool)
-                    .await
-                    .map_err(|e| format!("Failed to find user: {}", e))?;
-
-                    match invitee {
-                        Some(invitee_id) => {
-                            // Add member
-                            let role = request.role.unwrap_or_else(|| "member".to_string());
-
-                            sqlx::query(
-                                r#"
-                                INSERT INTO family_members (family_id, user_id, role)
-                                VALUES ($1, $2, $3::family_role)
-                                ON CONFLICT (family_id, user_id) DO NOTHING
-                                "#,
-                            )
-                            .bind(family_id)
-                            .bind(invitee_id)
-                            .bind(&role)
-                            .execute(&state.db_pool)
-                            .await
-                            .map_err(|e| format!("Failed to add member: {}", e))?;
-
-                            info!("Added user {} to family {} with role {}", invitee_id, family_id, role);
-
-                            Ok(json_response(
-                                200,
-                                &ApiResponse {
-                                    success: true,
-                                    data: Some(serde_json::json!({
-                                        "message": "Member added successfully",
-                                        "user_id": invitee_id.to_string(),
-                                        "role": role,
-                                    })),
-                                    error: None,
-                                },
-                            )?)
-                        }
-                        None => Ok(json_response(
-                            404,
-                            &ApiResponse::<()> {
-                                success: false,
-                                data: None,
-                                error: Some("User not found with that email".to_string()),
-                            },
-                        )?),
-                    }
-                }
-
-                // DELETE /families/{id}/members/{user_id} - Remove member
-                ("DELETE", 3) if path_parts[1] == "members" => {
-                    let target_user_id = Uuid::parse_str(path_parts[2])
-                        .map_err(|_| "Invalid user ID")?;
-
-                    // Check if requester is admin or removing themselves
-                    let is_admin: bool = sqlx::query_scalar(
-                        "SELECT EXISTS(SELECT 1 FROM family_members WHERE family_id = $1 AND user_id = $2 AND role = 'admin')"
-                    )
-                    .bind(family_id)
-                    .bind(user_id)
-                    .fetch_one(&state.db_pool)
-                    .await
-                    .map_err(|e| format!("Failed to check admin status: {}", e))?;
-
-                    if !is_admin && user_id != target_user_id {
-                        return Ok(json_response(
-                            403,
-                            &ApiResponse::<()> {
-                                success: false,
-                                data: None,
-                                error: Some("Only admins can remove other members".to_string()),
-                            },
-                        )?);
-                    }
-
-                    // Remove member
-                    let result = sqlx::query(
-                        "DELETE FROM family_members WHERE family_id = $1 AND user_id = $2"
-                    )
-                    .bind(family_id)
-                    .bind(target_user_id)
-                    .execute(&state.db_pool)
-                    .await
-                    .map_err(|e| format!("Failed to remove member: {}", e))?;
-
-                    if result.rows_affected() > 0 {
-                        info!("Removed user {} from family {}", target_user_id, family_id);
-                        Ok(json_response(
-                            200,
-                            &ApiResponse {
-                                success: true,
-                                data: Some(serde_json::json!({
-                                    "message": "Member removed successfully"
-                                })),
-                                error: None,
-                            },
-                        )?)
-                    } else {
-                        Ok(json_response(
-                            404,
-                            &ApiResponse::<()> {
-                                success: false,
-                                data: None,
-                                error: Some("Member not found".to_string()),
-                            },
-                        )?)
-                    }
-                }
-
-                _ => Ok(json_response(
-                    404,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Not found".to_string()),
-                    },
-                )?),
-            }
-        }
+    // Every field is optional, so an empty body takes the defaults
+    let request: CreateJoinCodeRequest = match event.body() {
+        Body::Empty => CreateJoinCodeRequest::default(),
+        body => match shared::parse_json_body(body)? {
+            Ok(r) => r,
+            Err(response) => return Ok(response),
+        },
+    };
+
+    let options = match JoinCodeOptions::new(
+        request.expires_in_minutes,
+        request.max_uses,
+        request.role.as_deref(),
+    ) {
+        Ok(options) => options,
+        Err(shared::Error::Validation(e)) => return error_response(400, e),
+        Err(e) => return Err(e.to_string().into()),
+    };
+
+    let join_code =
+        family_join_codes::create_join_code(&state.db_pool, family_id, user_id, &options).await?;
+
+    info!("User {} created a join code for family {}", user_id, family_id);
+
+    Ok(json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(join_code),
+            error: None,
+        },
+    )?)
+}
+
+/// DELETE /families/{id}/members/{userId}
+#[utoipa::path(
+    delete,
+    path = "/families/{id}/members/{userId}",
+    tag = "families",
+    params(
+        ("id" = Uuid, Path, description = "Family ID"),
+        ("userId" = Uuid, Path, description = "The member's user ID"),
+    ),
+    responses(
+        (status = 200, description = "The member was removed", body = ApiResponse<serde_json::Value>),
+        (status = 403, description = "Not an admin of the family, or not a member", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Member not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn remove_member(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let family_id = params.id("id", "Invalid family ID")?;
+    if !is_member(&state.db_pool, family_id, user_id).await? {
+        return error_response(403, "Not a member of this family");
+    }
+
+    let target_user_id = params.id("userId", "Invalid user ID")?;
+
+    // Check if requester is admin or removing themselves
+    let is_admin: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM family_members WHERE family_id = $1 AND user_id = $2 AND role = 'admin')"
+    )
+    .bind(family_id)
+    .bind(user_id)
+    .fetch_one(&state.db_pool)
+    .await?;
 
-        _ => Ok(json_response(
-            404,
-            &ApiResponse::<()> {
-                success: false,
-                data: None,
-                error: Some("Not found".to_string()),
+    if !is_admin && user_id != target_user_id {
+        return error_response(403, "Only admins can remove other members");
+    }
+
+    // Remove member
+    let removed: Option<(Uuid, serde_json::Value)> = sqlx::query_as(
+        "DELETE FROM family_members fm WHERE fm.family_id = $1 AND fm.user_id = $2 RETURNING fm.id, to_jsonb(fm)"
+    )
+    .bind(family_id)
+    .bind(target_user_id)
+    .fetch_optional(&state.db_pool)
+    .await?;
+
+    if let Some((member_id, before)) = removed {
+        audit::record(&state.db_pool, user_id, AuditAction::Delete, AuditResource::FamilyMember, member_id, Some(before), None).await;
+
+        // Other members' containers pick up the change once USER_CACHE_TTL lapses
+        if target_user_id == user_id {
+            AuthorizedUser::invalidate(&user.cognito_sub);
+        }
+        info!("Removed user {} from family {}", target_user_id, family_id);
+        Ok(json_response(
+            200,
+            &ApiResponse {
+                success: true,
+                data: Some(serde_json::json!({
+                    "message": "Member removed successfully"
+                })),
+                error: None,
             },
-        )?),
+        )?)
+    } else {
+        error_response(404, "Member not found")
     }
 }
 
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .post("/families", create_family)
+        .get("/families", list_families)
+        .post("/families/join", join_family)
+        .get("/families/{id}", get_family)
+        .post("/families/{id}/members", invite_member)
+        .post("/families/{id}/join-codes", create_join_code)
+        .delete("/families/{id}/members/{userId}", remove_member)
+}
+
 fn json_response<T: Serialize>(status: u16, data: &T) -> Result<Response<Body>, Error> {
     Ok(Response::builder()
         .status(status)
         .header("content-type", "application/json")
-        .body(Body::from(serde_json::to_string(data)?))
-        .expect("Failed to build response"))
+        .body(Body::from(serde_json::to_string(data)?))?)
 }
 
 #[tokio::main]
@@ -525,10 +664,12 @@ async fn main() -> Result<(), Error> {
         .init();
 
     let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
 
     run(service_fn(move |event| {
         let state = Arc::clone(&state);
-        async move { handler(state, event).await }
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
     }))
     .await
 }
@@ -4,18 +4,29 @@
 //! - POST /feedback - Record feedback
 //! - GET /feedback/stats - Get user's feedback stats
 //! - POST /queries/{id}/feedback - Rate a query response
+//! - GET /feedback/history - Page through recent feedback (`?limit=&cursor=`)
+//! - POST /v1/events - Log a batch of interactions (facts clicked or expanded,
+//!   notifications opened) as implicit feedback; see `shared::interactions`
 
 use lambda_http::{run, service_fn, Body, Error, Request, RequestExt, Response};
 use serde::{Deserialize, Serialize};
-use sqlx::postgres::PgPoolOptions;
+use shared::http::error_response;
+use shared::interactions::{self, InteractionEvent};
+use shared::metrics::RequestMetrics;
+use shared::models::{keyset_predicate, Cursor, CursorParams, Page, SortDirection};
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::ApiError;
 use sqlx::PgPool;
 use std::sync::Arc;
 use tracing::info;
 use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 /// Record feedback request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 struct RecordFeedbackRequest {
     feedback_type: String,
@@ -27,15 +38,24 @@ struct RecordFeedbackRequest {
 }
 
 /// Query feedback request (simplified)
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 struct QueryFeedbackRequest {
     action: String,  // 'thumbs_up' or 'thumbs_down'
     comment: Option<String>,
 }
 
+/// Interaction batch request
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct RecordEventsRequest {
+    events: Vec<InteractionEvent>,
+    /// Client sending the batch ('web', 'ios', ...)
+    source: Option<String>,
+}
+
 /// Feedback stats response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 struct FeedbackStatsResponse {
     total_queries: i32,
@@ -50,7 +70,7 @@ struct FeedbackStatsResponse {
 }
 
 /// API response wrapper
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct ApiResponse<T> {
     success: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -67,34 +87,8 @@ struct AppState {
 impl AppState {
     async fn new() -> Result<Self, Error> {
         let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
-        let secrets_client = aws_sdk_secretsmanager::Client::new(&config);
-
-        let db_secret_arn =
-            std::env::var("DB_SECRET_ARN").map_err(|_| "DB_SECRET_ARN not set")?;
-
-        let db_secret = secrets_client
-            .get_secret_value()
-            .secret_id(&db_secret_arn)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to get DB secret: {}", e))?;
 
-        let db_creds: serde_json::Value =
-            serde_json::from_str(db_secret.secret_string().unwrap_or("{}"))?;
-
-        let db_host = std::env::var("DB_HOST").map_err(|_| "DB_HOST not set")?;
-        let db_name = std::env::var("DB_NAME").unwrap_or_else(|_| "second_brain".to_string());
-        let db_user = db_creds["username"].as_str().unwrap_or("sbadmin");
-        let db_pass = db_creds["password"].as_str().unwrap_or("");
-
-        let database_url = format!(
-            "postgres://{}:{}@{}:5432/{}",
-            db_user, db_pass, db_host, db_name
-        );
-
-        let db_pool = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(&database_url)
+        let db_pool = shared::db::connect(&config)
             .await
             .map_err(|e| format!("Failed to connect to database: {}", e))?;
 
@@ -102,342 +96,373 @@ impl AppState {
     }
 }
 
-/// Extract user_id from Cognito claims
-fn extract_user_id(event: &Request) -> Result<Uuid, Error> {
-    let context = event
-        .request_context_ref()
-        .ok_or("Missing request context")?;
+/// POST /feedback
+#[utoipa::path(
+    post,
+    path = "/feedback",
+    tag = "feedback",
+    request_body = RecordFeedbackRequest,
+    responses(
+        (status = 201, description = "The feedback was recorded", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Invalid feedback type or action", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn record_feedback(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let body = event.body();
+    let body_str = std::str::from_utf8(body.as_ref()).unwrap_or("{}");
+    let request: RecordFeedbackRequest = serde_json::from_str(body_str)
+        .map_err(|_| "Invalid request body")?;
+
+    // Validate feedback_type
+    let valid_types = ["query_satisfaction", "tag_acceptance", "notification_action", "suggestion_action"];
+    if !valid_types.contains(&request.feedback_type.as_str()) {
+        return error_response(400, "Invalid feedback_type");
+    }
+
+    // Validate action
+    let valid_actions = ["accepted", "rejected", "dismissed", "thumbs_up", "thumbs_down", "modified"];
+    if !valid_actions.contains(&request.action.as_str()) {
+        return error_response(400, "Invalid action");
+    }
+
+    let context_id = request.context_id
+        .as_ref()
+        .and_then(|id| Uuid::parse_str(id).ok());
+
+    let metadata = request.metadata.unwrap_or(serde_json::json!({}));
 
-    let claims = context
-        .authorizer()
-        .and_then(|a| a.fields.get("claims"))
-        .ok_or("Missing claims")?;
+    let feedback_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO user_feedback (user_id, feedback_type, context_type, context_id, action, rating, metadata)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id
+        "#
+    )
+    .bind(user_id)
+    .bind(&request.feedback_type)
+    .bind(&request.context_type)
+    .bind(context_id)
+    .bind(&request.action)
+    .bind(request.rating)
+    .bind(&metadata)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    Ok(json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "feedbackId": feedback_id.to_string(),
+            })),
+            error: None,
+        },
+    )?)
+}
 
-    let sub = claims
-        .as_object()
-        .and_then(|c| c.get("sub"))
-        .and_then(|s| s.as_str())
-        .ok_or("Missing sub claim")?;
+/// GET /feedback/stats
+#[utoipa::path(
+    get,
+    path = "/feedback/stats",
+    tag = "feedback",
+    responses(
+        (status = 200, description = "How often answers, tag suggestions and notifications were accepted", body = ApiResponse<FeedbackStatsResponse>),
+    ),
+    security(("cognito" = []))
+)]
+async fn get_stats(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let stats: Option<(i32, i32, f64, i32, i32, f64, i32, i32, f64)> = sqlx::query_as(
+        r#"
+        SELECT
+            total_queries,
+            satisfied_queries,
+            COALESCE(query_satisfaction_rate, 0)::float8,
+            total_tag_suggestions,
+            accepted_tag_suggestions,
+            COALESCE(tag_acceptance_rate, 0)::float8,
+            total_notifications,
+            acted_notifications,
+            COALESCE(notification_action_rate, 0)::float8
+        FROM user_feedback_stats
+        WHERE user_id = $1
+        "#
+    )
+    .bind(user_id)
+    .fetch_optional(&state.db_pool)
+    .await?;
+
+    let response = if let Some((tq, sq, qsr, tts, ats, tar, tn, an, nar)) = stats {
+        FeedbackStatsResponse {
+            total_queries: tq,
+            satisfied_queries: sq,
+            query_satisfaction_rate: qsr,
+            total_tag_suggestions: tts,
+            accepted_tag_suggestions: ats,
+            tag_acceptance_rate: tar,
+            total_notifications: tn,
+            acted_notifications: an,
+            notification_action_rate: nar,
+        }
+    } else {
+        // No stats yet, return zeros
+        FeedbackStatsResponse {
+            total_queries: 0,
+            satisfied_queries: 0,
+            query_satisfaction_rate: 0.0,
+            total_tag_suggestions: 0,
+            accepted_tag_suggestions: 0,
+            tag_acceptance_rate: 0.0,
+            total_notifications: 0,
+            acted_notifications: 0,
+            notification_action_rate: 0.0,
+        }
+    };
 
-    Uuid::parse_str(sub).map_err(|_| "Invalid user ID".into())
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(response),
+            error: None,
+        },
+    )?)
 }
 
-async fn handler(state: Arc<AppState>, event: Request) -> Result<Response<Body>, Error> {
-    let method = event.method().as_str();
-    let raw_path = event.uri().path();
-    // Strip /api stage prefix if present (API Gateway REST API includes stage in path)
-    let path = raw_path.strip_prefix("/api").unwrap_or(raw_path);
+/// POST /queries/{id}/feedback
+#[utoipa::path(
+    post,
+    path = "/queries/{id}/feedback",
+    tag = "feedback",
+    request_body = QueryFeedbackRequest,
+    params(
+        ("id" = Uuid, Path, description = "Query ID"),
+    ),
+    responses(
+        (status = 201, description = "The rating was recorded", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Action must be `thumbs_up` or `thumbs_down`", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn rate_query(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let query_uuid = params.id("id", "Invalid query ID")?;
+
+    let body = event.body();
+    let body_str = std::str::from_utf8(body.as_ref()).unwrap_or("{}");
+    let request: QueryFeedbackRequest = serde_json::from_str(body_str)
+        .map_err(|_| "Invalid request body")?;
+
+    // Validate action
+    if request.action != "thumbs_up" && request.action != "thumbs_down" {
+        return error_response(400, "Action must be 'thumbs_up' or 'thumbs_down'");
+    }
 
-    info!("Feedback request: {} {}", method, path);
+    // Record the feedback
+    let mut metadata = serde_json::json!({});
+    if let Some(comment) = &request.comment {
+        metadata["comment"] = serde_json::Value::String(comment.clone());
+    }
 
-    // Extract user
-    let cognito_sub = match extract_user_id(&event) {
-        Ok(id) => id,
+    let feedback_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO user_feedback (user_id, feedback_type, context_type, context_id, action, metadata)
+        VALUES ($1, 'query_satisfaction', 'query', $2, $3, $4)
+        RETURNING id
+        "#
+    )
+    .bind(user_id)
+    .bind(query_uuid)
+    .bind(&request.action)
+    .bind(&metadata)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    // Update query session with feedback link
+    sqlx::query(
+        "UPDATE query_sessions SET feedback_id = $1 WHERE id = $2 AND user_id = $3"
+    )
+    .bind(feedback_id)
+    .bind(query_uuid)
+    .bind(user_id)
+    .execute(&state.db_pool)
+    .await
+    .ok(); // Ignore errors (query might not be tracked)
+
+    Ok(json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "feedbackId": feedback_id.to_string(),
+                "queryId": query_uuid.to_string(),
+            })),
+            error: None,
+        },
+    )?)
+}
+
+/// GET /feedback/history
+#[utoipa::path(
+    get,
+    path = "/feedback/history",
+    tag = "feedback",
+    params(
+        ("limit" = Option<i64>, Query, description = "Most entries to return"),
+        ("cursor" = Option<String>, Query, description = "Cursor returned with the previous page"),
+    ),
+    responses(
+        (status = 200, description = "Your feedback, most recent first, as `feedback` with `nextCursor` and `hasMore`", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Invalid cursor", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn get_history(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let params = event.query_string_parameters();
+    let page_params = match CursorParams::from_query(params.first("limit"), params.first("cursor"), 20) {
+        Ok(p) => p,
         Err(e) => {
-            return Ok(json_response(
-                401,
-                &ApiResponse::<()> {
-                    success: false,
-                    data: None,
-                    error: Some(e.to_string()),
-                },
-            )?)
+            return error_response(400, e.to_string())
         }
     };
 
-    // Look up database user_id from Cognito sub
-    let user_id: Uuid = match sqlx::query_scalar::<_, Uuid>(
-        "SELECT id FROM users WHERE cognito_sub = $1::text"
+    let feedback: Vec<(Uuid, String, String, Option<Uuid>, String, Option<i16>, chrono::DateTime<chrono::Utc>)> = sqlx::query_as(
+        &format!(
+            r#"
+            SELECT id, feedback_type, context_type, context_id, action, rating, created_at
+            FROM user_feedback
+            WHERE user_id = $1
+            AND {}
+            ORDER BY created_at DESC, id DESC
+            LIMIT $2
+            "#,
+            keyset_predicate("created_at", "timestamptz", "id", 3, SortDirection::Desc)
+        )
     )
-    .bind(cognito_sub)
-    .fetch_optional(&state.db_pool)
-    .await
-    .map_err(|e| format!("Failed to lookup user: {}", e))? {
-        Some(id) => id,
-        None => {
-            return Ok(json_response(
-                401,
-                &ApiResponse::<()> {
-                    success: false,
-                    data: None,
-                    error: Some("User not registered".to_string()),
-                },
-            )?)
-        }
+    .bind(user_id)
+    .bind(page_params.fetch_limit())
+    .bind(page_params.after_key())
+    .bind(page_params.after_id())
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let page = Page::from_rows(feedback, &page_params, |row| Cursor::new(row.6.to_rfc3339(), row.0))
+        .map(|(id, ft, ct, cid, action, rating, created)| {
+            serde_json::json!({
+                "id": id.to_string(),
+                "feedbackType": ft,
+                "contextType": ct,
+                "contextId": cid.map(|u| u.to_string()),
+                "action": action,
+                "rating": rating,
+                "createdAt": created.to_rfc3339(),
+            })
+        });
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "count": page.items.len(),
+                "feedback": page.items,
+                "nextCursor": page.next_cursor,
+                "hasMore": page.has_more,
+            })),
+            error: None,
+        },
+    )?)
+}
+
+/// POST /v1/events
+#[utoipa::path(
+    post,
+    path = "/v1/events",
+    tag = "feedback",
+    request_body = RecordEventsRequest,
+    responses(
+        (status = 202, description = "The events and the implicit feedback they imply were recorded", body = ApiResponse<interactions::Recorded>),
+        (status = 400, description = "Invalid events or source", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn record_events(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let request: RecordEventsRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
     };
 
-    match (method, path) {
-        // Record generic feedback
-        ("POST", "/feedback") => {
-            let body = event.body();
-            let body_str = std::str::from_utf8(body.as_ref()).unwrap_or("{}");
-            let request: RecordFeedbackRequest = serde_json::from_str(body_str)
-                .map_err(|_| "Invalid request body")?;
-
-            // Validate feedback_type
-            let valid_types = ["query_satisfaction", "tag_acceptance", "notification_action", "suggestion_action"];
-            if !valid_types.contains(&request.feedback_type.as_str()) {
-                return Ok(json_response(
-                    400,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Invalid feedback_type".to_string()),
-                    },
-                )?);
-            }
-
-            // Validate action
-            let valid_actions = ["accepted", "rejected", "dismissed", "thumbs_up", "thumbs_down", "modified"];
-            if !valid_actions.contains(&request.action.as_str()) {
-                return Ok(json_response(
-                    400,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Invalid action".to_string()),
-                    },
-                )?);
-            }
-
-            let context_id = request.context_id
-                .as_ref()
-                .and_then(|id| Uuid::parse_str(id).ok());
-
-            let metadata = request.metadata.unwrap_or(serde_json::json!({}));
-
-            let feedback_id: Uuid = sqlx::query_scalar(
-                r#"
-                INSERT INTO user_feedback (user_id, feedback_type, context_type, context_id, action, rating, metadata)
-                VALUES ($1, $2, $3, $4, $5, $6, $7)
-                RETURNING id
-                "#
-            )
-            .bind(user_id)
-            .bind(&request.feedback_type)
-            .bind(&request.context_type)
-            .bind(context_id)
-            .bind(&request.action)
-            .bind(request.rating)
-            .bind(&metadata)
-            .fetch_one(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to record feedback: {}", e))?;
-
-            Ok(json_response(
-                201,
-                &ApiResponse {
-                    success: true,
-                    data: Some(serde_json::json!({
-                        "feedbackId": feedback_id.to_string(),
-                    })),
-                    error: None,
-                },
-            )?)
-        }
+    if let Err(e) = interactions::validate_batch(&request.events) {
+        return error_response(400, e.to_string());
+    }
 
-        // Get user's feedback stats
-        ("GET", "/feedback/stats") => {
-            let stats: Option<(i32, i32, f64, i32, i32, f64, i32, i32, f64)> = sqlx::query_as(
-                r#"
-                SELECT
-                    total_queries,
-                    satisfied_queries,
-                    COALESCE(query_satisfaction_rate, 0)::float8,
-                    total_tag_suggestions,
-                    accepted_tag_suggestions,
-                    COALESCE(tag_acceptance_rate, 0)::float8,
-                    total_notifications,
-                    acted_notifications,
-                    COALESCE(notification_action_rate, 0)::float8
-                FROM user_feedback_stats
-                WHERE user_id = $1
-                "#
-            )
-            .bind(user_id)
-            .fetch_optional(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to fetch stats: {}", e))?;
-
-            let response = if let Some((tq, sq, qsr, tts, ats, tar, tn, an, nar)) = stats {
-                FeedbackStatsResponse {
-                    total_queries: tq,
-                    satisfied_queries: sq,
-                    query_satisfaction_rate: qsr,
-                    total_tag_suggestions: tts,
-                    accepted_tag_suggestions: ats,
-                    tag_acceptance_rate: tar,
-                    total_notifications: tn,
-                    acted_notifications: an,
-                    notification_action_rate: nar,
-                }
-            } else {
-                // No stats yet, return zeros
-                FeedbackStatsResponse {
-                    total_queries: 0,
-                    satisfied_queries: 0,
-                    query_satisfaction_rate: 0.0,
-                    total_tag_suggestions: 0,
-                    accepted_tag_suggestions: 0,
-                    tag_acceptance_rate: 0.0,
-                    total_notifications: 0,
-                    acted_notifications: 0,
-                    notification_action_rate: 0.0,
-                }
-            };
-
-            Ok(json_response(
-                200,
-                &ApiResponse {
-                    success: true,
-                    data: Some(response),
-                    error: None,
-                },
-            )?)
-        }
+    let source = request.source.as_deref().map(str::trim).unwrap_or("api");
+    if source.is_empty() || source.len() > 50 {
+        return error_response(400, "source must be 1-50 characters");
+    }
 
-        // Rate a specific query response
-        _ if path.starts_with("/queries/") && path.ends_with("/feedback") => {
-            if method != "POST" {
-                return Ok(json_response(
-                    405,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Method not allowed".to_string()),
-                    },
-                )?);
-            }
-
-            let query_id = path
-                .trim_start_matches("/queries/")
-                .trim_end_matches("/feedback");
-
-            let query_uuid = Uuid::parse_str(query_id)
-                .map_err(|_| "Invalid query ID")?;
-
-            let body = event.body();
-            let body_str = std::str::from_utf8(body.as_ref()).unwrap_or("{}");
-            let request: QueryFeedbackRequest = serde_json::from_str(body_str)
-                .map_err(|_| "Invalid request body")?;
-
-            // Validate action
-            if request.action != "thumbs_up" && request.action != "thumbs_down" {
-                return Ok(json_response(
-                    400,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Action must be 'thumbs_up' or 'thumbs_down'".to_string()),
-                    },
-                )?);
-            }
-
-            // Record the feedback
-            let mut metadata = serde_json::json!({});
-            if let Some(comment) = &request.comment {
-                metadata["comment"] = serde_json::Value::String(comment.clone());
-            }
-
-            let feedback_id: Uuid = sqlx::query_scalar(
-                r#"
-                INSERT INTO user_feedback (user_id, feedback_type, context_type, context_id, action, metadata)
-                VALUES ($1, 'query_satisfaction', 'query', $2, $3, $4)
-                RETURNING id
-                "#
-            )
-            .bind(user_id)
-            .bind(query_uuid)
-            .bind(&request.action)
-            .bind(&metadata)
-            .fetch_one(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to record feedback: {}", e))?;
-
-            // Update query session with feedback link
-            sqlx::query(
-                "UPDATE query_sessions SET feedback_id = $1 WHERE id = $2 AND user_id = $3"
-            )
-            .bind(feedback_id)
-            .bind(query_uuid)
-            .bind(user_id)
-            .execute(&state.db_pool)
-            .await
-            .ok(); // Ignore errors (query might not be tracked)
-
-            Ok(json_response(
-                201,
-                &ApiResponse {
-                    success: true,
-                    data: Some(serde_json::json!({
-                        "feedbackId": feedback_id.to_string(),
-                        "queryId": query_id,
-                    })),
-                    error: None,
-                },
-            )?)
-        }
+    let recorded =
+        interactions::record_events(&state.db_pool, user_id, source, &request.events).await?;
 
-        // Get recent feedback history
-        ("GET", "/feedback/history") => {
-            let params = event.query_string_parameters();
-            let limit: i32 = params
-                .first("limit")
-                .and_then(|l| l.parse().ok())
-                .unwrap_or(20);
-
-            let feedback: Vec<(Uuid, String, String, Option<Uuid>, String, Option<i16>, chrono::DateTime<chrono::Utc>)> = sqlx::query_as(
-                r#"
-                SELECT id, feedback_type, context_type, context_id, action, rating, created_at
-                FROM user_feedback
-                WHERE user_id = $1
-                ORDER BY created_at DESC
-                LIMIT $2
-                "#
-            )
-            .bind(user_id)
-            .bind(limit)
-            .fetch_all(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to fetch history: {}", e))?;
-
-            let history: Vec<serde_json::Value> = feedback
-                .into_iter()
-                .map(|(id, ft, ct, cid, action, rating, created)| {
-                    serde_json::json!({
-                        "id": id.to_string(),
-                        "feedbackType": ft,
-                        "contextType": ct,
-                        "contextId": cid.map(|u| u.to_string()),
-                        "action": action,
-                        "rating": rating,
-                        "createdAt": created.to_rfc3339(),
-                    })
-                })
-                .collect();
-
-            Ok(json_response(
-                200,
-                &ApiResponse {
-                    success: true,
-                    data: Some(serde_json::json!({
-                        "count": history.len(),
-                        "feedback": history,
-                    })),
-                    error: None,
-                },
-            )?)
-        }
+    info!(events = recorded.events, feedback = recorded.feedback, "Recorded interactions");
 
-        _ => Ok(json_response(
-            404,
-            &ApiResponse::<()> {
-                success: false,
-                data: None,
-                error: Some("Not found".to_string()),
-            },
-        )?),
-    }
+    Ok(json_response(
+        202,
+        &ApiResponse {
+            success: true,
+            data: Some(recorded),
+            error: None,
+        },
+    )?)
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .post("/feedback", record_feedback)
+        .get("/feedback/stats", get_stats)
+        .get("/feedback/history", get_history)
+        .post("/queries/{id}/feedback", rate_query)
+        .post("/v1/events", record_events)
 }
 
 fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
@@ -445,7 +470,6 @@ fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>,
     Ok(Response::builder()
         .status(status)
         .header("Content-Type", "application/json")
-        .header("Access-Control-Allow-Origin", "*")
         .body(Body::from(json))?)
 }
 
@@ -457,11 +481,12 @@ async fn main() -> Result<(), Error> {
         .init();
 
     let state = Arc::new(AppState::new().await?);
-    let state_clone = state.clone();
+    let router = Arc::new(router());
 
     run(service_fn(move |event| {
-        let state = state_clone.clone();
-        async move { handler(state, event).await }
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
     }))
     .await
 }
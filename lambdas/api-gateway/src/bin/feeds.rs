@@ -0,0 +1,417 @@
+//! Feeds Lambda - Handles /v1/feeds endpoints.
+//!
+//! Endpoints:
+//! - GET /feeds - List the user's RSS/Atom feed subscriptions
+//! - POST /feeds - Subscribe to a feed URL
+//! - DELETE /feeds/{id} - Unsubscribe (facts already created are kept)
+//! - POST /feeds/{id}/enable - Resume polling a feed
+//! - POST /feeds/{id}/disable - Stop polling a feed without losing its history
+//!
+//! Enabled feeds are polled, and their new items summarized, by `feed_poller`.
+
+use chrono::{DateTime, Utc};
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::{Deserialize, Serialize};
+use shared::http::error_response;
+use shared::ical::{display_feed_url, normalize_feed_url};
+use shared::metrics::RequestMetrics;
+use shared::public_http;
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::ApiError;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Longest feed name accepted
+const MAX_NAME_LENGTH: usize = 255;
+
+/// Columns selected into `FeedRow` (from `rss_feeds f`)
+const FEED_COLUMNS: &str = r#"
+    f.id, f.feed_url, f.name, f.enabled, f.last_polled_at, f.last_error, f.created_at,
+    (SELECT COUNT(*) FROM rss_feed_items i WHERE i.feed_id = f.id) AS item_count
+"#;
+
+/// Feed row from database
+#[derive(Debug, sqlx::FromRow)]
+struct FeedRow {
+    id: Uuid,
+    feed_url: String,
+    name: Option<String>,
+    enabled: bool,
+    last_polled_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    created_at: DateTime<Utc>,
+    item_count: i64,
+}
+
+/// Subscribe request
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct SubscribeRequest {
+    /// RSS or Atom feed URL
+    url: String,
+    name: Option<String>,
+}
+
+/// Feed API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct FeedResponse {
+    id: String,
+    /// Scheme and host only; private feed URLs embed a secret
+    url: String,
+    name: Option<String>,
+    enabled: bool,
+    /// Items seen so far
+    item_count: i64,
+    last_polled_at: Option<String>,
+    last_error: Option<String>,
+    created_at: String,
+}
+
+impl From<FeedRow> for FeedResponse {
+    fn from(row: FeedRow) -> Self {
+        Self {
+            id: row.id.to_string(),
+            url: display_feed_url(&row.feed_url),
+            name: row.name,
+            enabled: row.enabled,
+            item_count: row.item_count,
+            last_polled_at: row.last_polled_at.map(|t| t.to_rfc3339()),
+            last_error: row.last_error,
+            created_at: row.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+/// GET /feeds
+#[utoipa::path(
+    get,
+    path = "/feeds",
+    tag = "feeds",
+    responses(
+        (status = 200, description = "Your RSS and Atom feed subscriptions", body = ApiResponse<Vec<FeedResponse>>),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_feeds(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let feeds: Vec<FeedRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM rss_feeds f WHERE f.user_id = $1 ORDER BY f.created_at",
+        FEED_COLUMNS
+    ))
+    .bind(user.user_id)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(
+                feeds
+                    .into_iter()
+                    .map(FeedResponse::from)
+                    .collect::<Vec<_>>(),
+            ),
+            error: None,
+        },
+    )
+}
+
+/// POST /feeds
+#[utoipa::path(
+    post,
+    path = "/feeds",
+    tag = "feeds",
+    request_body = SubscribeRequest,
+    responses(
+        (status = 201, description = "The subscription; the feed is polled on the next run", body = ApiResponse<FeedResponse>),
+        (status = 400, description = "Invalid feed URL or name", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn subscribe(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let request: SubscribeRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let feed_url = match normalize_feed_url(&request.url) {
+        Ok(url) => url,
+        Err(e) => return error_response(400, e.to_string()),
+    };
+    // The host name must not resolve to a private address either
+    public_http::ensure_public(&url::Url::parse(&feed_url)?).await?;
+
+    let name = request
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty());
+    if name.is_some_and(|n| n.chars().count() > MAX_NAME_LENGTH) {
+        return error_response(400, "name is too long");
+    }
+
+    // Re-subscribing to the same feed renames and re-enables it; items
+    // already seen stay seen
+    let feed: FeedRow = sqlx::query_as(&format!(
+        r#"
+        WITH f AS (
+            INSERT INTO rss_feeds (user_id, feed_url, name)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, feed_url) DO UPDATE
+            SET name = COALESCE(EXCLUDED.name, rss_feeds.name),
+                enabled = true,
+                last_error = NULL,
+                updated_at = NOW()
+            RETURNING *
+        )
+        SELECT {} FROM f
+        "#,
+        FEED_COLUMNS
+    ))
+    .bind(user.user_id)
+    .bind(&feed_url)
+    .bind(name)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    info!(user_id = %user.user_id, feed_id = %feed.id, "Subscribed to feed");
+
+    json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(FeedResponse::from(feed)),
+            error: None,
+        },
+    )
+}
+
+/// DELETE /feeds/{id}
+#[utoipa::path(
+    delete,
+    path = "/feeds/{id}",
+    tag = "feeds",
+    params(
+        ("id" = Uuid, Path, description = "Feed ID"),
+    ),
+    responses(
+        (status = 200, description = "Unsubscribed; facts already created are kept"),
+        (status = 404, description = "Not one of your feeds", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn unsubscribe(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let feed_id = match params.get::<Uuid>("id") {
+        Ok(id) => id,
+        Err(_) => return error_response(400, "Invalid feed ID"),
+    };
+
+    let result = sqlx::query("DELETE FROM rss_feeds WHERE id = $1 AND user_id = $2")
+        .bind(feed_id)
+        .bind(user.user_id)
+        .execute(&state.db_pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return error_response(404, "Feed not found");
+    }
+
+    info!(user_id = %user.user_id, feed_id = %feed_id, "Unsubscribed from feed");
+
+    json_response(
+        200,
+        &ApiResponse::<()> {
+            success: true,
+            data: None,
+            error: None,
+        },
+    )
+}
+
+/// Enable or disable a feed, returning it.
+async fn set_enabled(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+    enabled: bool,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let feed_id = match params.get::<Uuid>("id") {
+        Ok(id) => id,
+        Err(_) => return error_response(400, "Invalid feed ID"),
+    };
+
+    // Re-enabling clears the last error so the next poll starts clean
+    let feed: Option<FeedRow> = sqlx::query_as(&format!(
+        r#"
+        WITH f AS (
+            UPDATE rss_feeds
+            SET enabled = $3,
+                last_error = CASE WHEN $3 THEN NULL ELSE last_error END,
+                updated_at = NOW()
+            WHERE id = $1 AND user_id = $2
+            RETURNING *
+        )
+        SELECT {} FROM f
+        "#,
+        FEED_COLUMNS
+    ))
+    .bind(feed_id)
+    .bind(user.user_id)
+    .bind(enabled)
+    .fetch_optional(&state.db_pool)
+    .await?;
+
+    let Some(feed) = feed else {
+        return error_response(404, "Feed not found");
+    };
+
+    info!(user_id = %user.user_id, feed_id = %feed_id, enabled, "Updated feed");
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(FeedResponse::from(feed)),
+            error: None,
+        },
+    )
+}
+
+/// POST /feeds/{id}/enable
+#[utoipa::path(
+    post,
+    path = "/feeds/{id}/enable",
+    tag = "feeds",
+    params(
+        ("id" = Uuid, Path, description = "Feed ID"),
+    ),
+    responses(
+        (status = 200, description = "The feed is polled again", body = ApiResponse<FeedResponse>),
+        (status = 404, description = "Not one of your feeds", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn enable_feed(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    set_enabled(state, event, params, true).await
+}
+
+/// POST /feeds/{id}/disable
+#[utoipa::path(
+    post,
+    path = "/feeds/{id}/disable",
+    tag = "feeds",
+    params(
+        ("id" = Uuid, Path, description = "Feed ID"),
+    ),
+    responses(
+        (status = 200, description = "The feed is no longer polled", body = ApiResponse<FeedResponse>),
+        (status = 404, description = "Not one of your feeds", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn disable_feed(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    set_enabled(state, event, params, false).await
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .get("/feeds", list_feeds)
+        .post("/feeds", subscribe)
+        .delete("/feeds/{id}", unsubscribe)
+        .post("/feeds/{id}/enable", enable_feed)
+        .post("/feeds/{id}/disable", disable_feed)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
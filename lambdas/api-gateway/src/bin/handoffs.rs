@@ -0,0 +1,861 @@
+//! Handoffs API Lambda - Household handoff briefings between family members.
+//!
+//! A handoff compiles selected facts plus the sender's active reminders and
+//! calendar events for a time window (e.g. a babysitter shift or a trip), stores
+//! a snapshot, and delivers it to the recipient through their preferred channel
+//! with an expiring share link.
+//!
+//! Endpoints:
+//! - POST /handoffs - Compile and send a handoff
+//! - GET /handoffs - List handoffs sent or received (`?limit=`)
+//! - DELETE /handoffs/{id} - Revoke a handoff's share link
+//! - GET /handoffs/shared/{token} - View a shared handoff (public, token is the credential)
+
+use chrono::{DateTime, Duration, Utc};
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::{Deserialize, Serialize};
+use shared::access::space_clause;
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::{Limit, RateLimit};
+use shared::reminders::{preferred_channel, NotificationPreferences};
+use shared::router::{json_body, require_user, Cors, PathParams, Query, RequestLogger, Router};
+use shared::shaping::ResponseShaping;
+use shared::{ApiError, AuthorizedUser};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Default share link lifetime
+const DEFAULT_EXPIRY_HOURS: i64 = 72;
+/// Maximum share link lifetime
+const MAX_EXPIRY_HOURS: i64 = 24 * 14;
+/// Maximum time window a handoff can cover
+const MAX_WINDOW_DAYS: i64 = 31;
+/// Maximum number of hand-picked facts
+const MAX_FACTS: usize = 50;
+/// Maximum reminders/events included from the window
+const MAX_WINDOW_ITEMS: i64 = 100;
+/// Views of shared handoff links per source IP a minute (the links are public)
+const SHARED_VIEWS_PER_MINUTE: u32 = 30;
+
+/// Create handoff request
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct CreateHandoffRequest {
+    recipient_user_id: String,
+    window_start: String, // ISO 8601 datetime
+    window_end: String,   // ISO 8601 datetime
+    title: Option<String>,
+    note: Option<String>,
+    #[serde(default)]
+    fact_ids: Vec<String>,
+    include_reminders: Option<bool>,
+    include_schedule: Option<bool>,
+    expires_in_hours: Option<i64>,
+}
+
+/// Fact included in a handoff
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct HandoffFact {
+    id: Uuid,
+    content: String,
+    recorded_at: DateTime<Utc>,
+}
+
+/// Reminder included in a handoff
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct HandoffReminder {
+    id: Uuid,
+    title: String,
+    description: Option<String>,
+    next_trigger_at: Option<DateTime<Utc>>,
+}
+
+/// Calendar event included in a handoff
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct HandoffEvent {
+    id: Uuid,
+    title: String,
+    location: Option<String>,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    all_day: bool,
+}
+
+/// Compiled handoff snapshot (stored in `handoffs.content`)
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct HandoffContent {
+    facts: Vec<HandoffFact>,
+    reminders: Vec<HandoffReminder>,
+    events: Vec<HandoffEvent>,
+}
+
+/// Handoff row from database
+#[derive(Debug, sqlx::FromRow)]
+struct HandoffRow {
+    id: Uuid,
+    created_by: Uuid,
+    recipient_id: Uuid,
+    family_id: Uuid,
+    window_start: DateTime<Utc>,
+    window_end: DateTime<Utc>,
+    title: String,
+    note: Option<String>,
+    expires_at: DateTime<Utc>,
+    revoked_at: Option<DateTime<Utc>>,
+    view_count: i32,
+    created_at: DateTime<Utc>,
+}
+
+/// Shared handoff row (public view)
+#[derive(Debug, sqlx::FromRow)]
+struct SharedHandoffRow {
+    title: String,
+    sender: String,
+    note: Option<String>,
+    window_start: DateTime<Utc>,
+    window_end: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    content: serde_json::Value,
+}
+
+/// Handoff API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct HandoffResponse {
+    id: String,
+    direction: &'static str, // sent, received
+    created_by: String,
+    recipient_user_id: String,
+    family_id: String,
+    window_start: String,
+    window_end: String,
+    title: String,
+    note: Option<String>,
+    expires_at: String,
+    revoked: bool,
+    view_count: i32,
+    created_at: String,
+}
+
+impl HandoffResponse {
+    fn from_row(row: HandoffRow, user_id: Uuid) -> Self {
+        Self {
+            id: row.id.to_string(),
+            direction: if row.created_by == user_id { "sent" } else { "received" },
+            created_by: row.created_by.to_string(),
+            recipient_user_id: row.recipient_id.to_string(),
+            family_id: row.family_id.to_string(),
+            window_start: row.window_start.to_rfc3339(),
+            window_end: row.window_end.to_rfc3339(),
+            title: row.title,
+            note: row.note,
+            expires_at: row.expires_at.to_rfc3339(),
+            revoked: row.revoked_at.is_some(),
+            view_count: row.view_count,
+            created_at: row.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Create handoff API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct CreateHandoffResponse {
+    #[serde(flatten)]
+    handoff: HandoffResponse,
+    /// Only returned once; the token itself is never stored
+    share_url: String,
+    channel: &'static str,
+    fact_count: usize,
+    reminder_count: usize,
+    event_count: usize,
+}
+
+/// Public view of a shared handoff
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct SharedHandoffResponse {
+    title: String,
+    from: String,
+    note: Option<String>,
+    window_start: String,
+    window_end: String,
+    expires_at: String,
+    #[serde(flatten)]
+    content: HandoffContent,
+}
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+    sns_client: aws_sdk_sns::Client,
+    notification_topic_arn: Option<String>,
+    /// Base URL for share links; derived from the request host when unset
+    share_base_url: Option<String>,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self {
+            db_pool,
+            sns_client: aws_sdk_sns::Client::new(&config),
+            notification_topic_arn: std::env::var("NOTIFICATION_TOPIC_ARN").ok(),
+            share_base_url: std::env::var("SHARE_BASE_URL").ok(),
+        })
+    }
+}
+
+fn not_found() -> Result<Response<Body>, Error> {
+    error_response(404, "Handoff not found")
+}
+
+fn bad_request(message: impl Into<String>) -> Result<Response<Body>, Error> {
+    error_response(400, message.into())
+}
+
+fn parse_datetime(value: &str, field: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|_| format!("Invalid {}. Use ISO 8601 format", field))
+}
+
+/// Random share token (two v4 UUIDs, 244 bits of randomness).
+fn generate_share_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// Base URL for share links, e.g. `https://abc.execute-api.../api`.
+fn share_base_url(state: &AppState, event: &Request) -> String {
+    if let Some(base) = &state.share_base_url {
+        return base.trim_end_matches('/').to_string();
+    }
+
+    let host = event
+        .headers()
+        .get("host")
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("localhost");
+    format!("https://{}/api", host)
+}
+
+/// Plain-text summary used as the notification body.
+fn summarize(
+    sender: &str,
+    content: &HandoffContent,
+    note: Option<&str>,
+    window_start: DateTime<Utc>,
+    window_end: DateTime<Utc>,
+    share_url: &str,
+    expires_at: DateTime<Utc>,
+) -> String {
+    let mut body = format!(
+        "{} shared a handoff for {} to {}: {} facts, {} reminders, {} events.",
+        sender,
+        window_start.format("%a %b %-d %H:%M UTC"),
+        window_end.format("%a %b %-d %H:%M UTC"),
+        content.facts.len(),
+        content.reminders.len(),
+        content.events.len(),
+    );
+
+    if let Some(note) = note.filter(|n| !n.trim().is_empty()) {
+        body.push_str("\n\n");
+        body.push_str(note.trim());
+    }
+
+    body.push_str(&format!(
+        "\n\nView it here (link expires {}): {}",
+        expires_at.format("%a %b %-d %H:%M UTC"),
+        share_url
+    ));
+    body
+}
+
+/// Compile the handoff snapshot from the sender's data.
+async fn compile_content(
+    pool: &PgPool,
+    user: &AuthorizedUser,
+    fact_ids: &[Uuid],
+    window_start: DateTime<Utc>,
+    window_end: DateTime<Utc>,
+    include_reminders: bool,
+    include_schedule: bool,
+) -> Result<HandoffContent, Error> {
+    let mut content = HandoffContent::default();
+
+    if !fact_ids.is_empty() {
+        // Only current facts the sender can see
+        content.facts = sqlx::query_as(&format!(
+            r#"
+            SELECT id, content, recorded_at
+            FROM facts
+            WHERE id = ANY($1)
+            AND superseded_by IS NULL
+            AND (
+                (owner_type = 'user' AND owner_id = $2)
+                OR (owner_type = 'family' AND owner_id = ANY($3) AND {})
+            )
+            ORDER BY recorded_at DESC
+            "#,
+            space_clause("facts", 2)
+        ))
+        .bind(fact_ids)
+        .bind(user.user_id)
+        .bind(&user.family_ids)
+        .fetch_all(pool)
+        .await?;
+    }
+
+    if include_reminders {
+        content.reminders = sqlx::query_as(
+            r#"
+            SELECT id, title, description, next_trigger_at
+            FROM reminders
+            WHERE user_id = $1
+            AND status = 'active'
+            AND next_trigger_at >= $2
+            AND next_trigger_at < $3
+            ORDER BY next_trigger_at ASC
+            LIMIT $4
+            "#,
+        )
+        .bind(user.user_id)
+        .bind(window_start)
+        .bind(window_end)
+        .bind(MAX_WINDOW_ITEMS)
+        .fetch_all(pool)
+        .await?;
+    }
+
+    if include_schedule {
+        content.events = sqlx::query_as(
+            r#"
+            SELECT id, title, location, start_time, end_time, all_day
+            FROM calendar_events
+            WHERE user_id = $1
+            AND start_time < $3
+            AND end_time > $2
+            ORDER BY start_time ASC
+            LIMIT $4
+            "#,
+        )
+        .bind(user.user_id)
+        .bind(window_start)
+        .bind(window_end)
+        .bind(MAX_WINDOW_ITEMS)
+        .fetch_all(pool)
+        .await?;
+    }
+
+    Ok(content)
+}
+
+/// Queue the handoff notification on the recipient's preferred channel.
+async fn notify_recipient(
+    state: &AppState,
+    recipient_id: Uuid,
+    handoff_id: Uuid,
+    title: &str,
+    body: &str,
+) -> Result<(Uuid, &'static str), Error> {
+    let prefs: NotificationPreferences = sqlx::query_as(
+        r#"
+        SELECT
+            push_enabled,
+            email_enabled,
+            discord_enabled,
+            quiet_hours_enabled,
+            quiet_hours_start,
+            quiet_hours_end,
+            timezone
+        FROM user_notification_preferences
+        WHERE user_id = $1
+        "#,
+    )
+    .bind(recipient_id)
+    .fetch_optional(&state.db_pool)
+    .await?
+    .unwrap_or_default();
+
+    let channel = preferred_channel(&prefs);
+
+    let notification_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO notifications (
+            user_id, notification_type, title, body, channel,
+            source_entity_id, source_entity_type
+        ) VALUES ($1, 'handoff', $2, $3, $4::notification_channel, $5, 'handoff')
+        RETURNING id
+        "#,
+    )
+    .bind(recipient_id)
+    .bind(title)
+    .bind(body)
+    .bind(channel)
+    .bind(handoff_id)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    if let Some(topic_arn) = &state.notification_topic_arn {
+        let message = serde_json::json!({
+            "notification_id": notification_id.to_string(),
+            "type": "handoff",
+            "title": title,
+        });
+
+        // The notification row stays pending if publishing fails
+        if let Err(e) = state
+            .sns_client
+            .publish()
+            .topic_arn(topic_arn)
+            .message(message.to_string())
+            .send()
+            .await
+        {
+            warn!(notification_id = %notification_id, error = %e, "Failed to publish handoff notification");
+        }
+    }
+
+    Ok((notification_id, channel))
+}
+
+/// POST /handoffs
+#[utoipa::path(
+    post,
+    path = "/handoffs",
+    tag = "handoffs",
+    request_body = CreateHandoffRequest,
+    responses(
+        (status = 201, description = "The handoff was compiled and sent; `shareUrl` is only returned now", body = ApiResponse<CreateHandoffResponse>),
+        (status = 400, description = "Invalid recipient, window or facts", body = ApiError, content_type = "application/problem+json"),
+        (status = 403, description = "Recipient is not a member of your family", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn create_handoff(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let request: CreateHandoffRequest = match json_body(&event) {
+        Ok(r) => r,
+        Err(e) => return bad_request(e.to_string()),
+    };
+
+    let recipient_id = match Uuid::parse_str(&request.recipient_user_id) {
+        Ok(id) => id,
+        Err(_) => return bad_request("Invalid recipientUserId"),
+    };
+    if recipient_id == user.user_id {
+        return bad_request("Cannot send a handoff to yourself");
+    }
+
+    let window_start = match parse_datetime(&request.window_start, "windowStart") {
+        Ok(dt) => dt,
+        Err(e) => return bad_request(e),
+    };
+    let window_end = match parse_datetime(&request.window_end, "windowEnd") {
+        Ok(dt) => dt,
+        Err(e) => return bad_request(e),
+    };
+    if window_end <= window_start {
+        return bad_request("windowEnd must be after windowStart");
+    }
+    if window_end - window_start > Duration::days(MAX_WINDOW_DAYS) {
+        return bad_request(format!("Window cannot exceed {} days", MAX_WINDOW_DAYS));
+    }
+
+    let expires_in_hours = request.expires_in_hours.unwrap_or(DEFAULT_EXPIRY_HOURS);
+    if !(1..=MAX_EXPIRY_HOURS).contains(&expires_in_hours) {
+        return bad_request(format!(
+            "expiresInHours must be between 1 and {}",
+            MAX_EXPIRY_HOURS
+        ));
+    }
+
+    if request.fact_ids.len() > MAX_FACTS {
+        return bad_request(format!("At most {} facts can be included", MAX_FACTS));
+    }
+    let fact_ids: Vec<Uuid> = match request
+        .fact_ids
+        .iter()
+        .map(|id| Uuid::parse_str(id))
+        .collect()
+    {
+        Ok(ids) => ids,
+        Err(_) => return bad_request("Invalid fact ID"),
+    };
+
+    // Handoffs are only between members of a shared family
+    let family_id: Option<Uuid> = sqlx::query_scalar(
+        r#"
+        SELECT family_id
+        FROM family_members
+        WHERE user_id = $1 AND family_id = ANY($2)
+        ORDER BY joined_at ASC
+        LIMIT 1
+        "#,
+    )
+    .bind(recipient_id)
+    .bind(&user.family_ids)
+    .fetch_optional(&state.db_pool)
+    .await?;
+
+    let family_id = match family_id {
+        Some(id) => id,
+        None => {
+            return error_response(403, "Recipient is not a member of your family")
+        }
+    };
+
+    let content = compile_content(
+        &state.db_pool,
+        &user,
+        &fact_ids,
+        window_start,
+        window_end,
+        request.include_reminders.unwrap_or(true),
+        request.include_schedule.unwrap_or(true),
+    )
+    .await?;
+
+    if content.facts.len() < fact_ids.len() {
+        return bad_request("One or more facts were not found");
+    }
+
+    let sender: String = sqlx::query_scalar("SELECT display_name FROM users WHERE id = $1")
+        .bind(user.user_id)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+    let title = request
+        .title
+        .filter(|t| !t.trim().is_empty())
+        .unwrap_or_else(|| format!("Handoff from {}", sender));
+    let note = request.note.filter(|n| !n.trim().is_empty());
+    let expires_at = Utc::now() + Duration::hours(expires_in_hours);
+    let share_token = generate_share_token();
+
+    let row: HandoffRow = sqlx::query_as(
+        r#"
+        INSERT INTO handoffs (
+            created_by, recipient_id, family_id,
+            window_start, window_end, title, note, content,
+            share_token_hash, expires_at
+        ) VALUES (
+            $1, $2, $3,
+            $4, $5, $6, $7, $8,
+            sha256(convert_to($9, 'UTF8')), $10
+        )
+        RETURNING
+            id, created_by, recipient_id, family_id,
+            window_start, window_end, title, note,
+            expires_at, revoked_at, view_count, created_at
+        "#,
+    )
+    .bind(user.user_id)
+    .bind(recipient_id)
+    .bind(family_id)
+    .bind(window_start)
+    .bind(window_end)
+    .bind(&title)
+    .bind(&note)
+    .bind(serde_json::to_value(&content)?)
+    .bind(&share_token)
+    .bind(expires_at)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    let share_url = format!(
+        "{}/handoffs/shared/{}",
+        share_base_url(&state, &event),
+        share_token
+    );
+    let body = summarize(
+        &sender,
+        &content,
+        note.as_deref(),
+        window_start,
+        window_end,
+        &share_url,
+        expires_at,
+    );
+
+    let (notification_id, channel) =
+        notify_recipient(&state, recipient_id, row.id, &title, &body).await?;
+
+    sqlx::query("UPDATE handoffs SET notification_id = $2 WHERE id = $1")
+        .bind(row.id)
+        .bind(notification_id)
+        .execute(&state.db_pool)
+        .await?;
+
+    info!(handoff_id = %row.id, recipient_id = %recipient_id, channel, "Handoff sent");
+
+    json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(CreateHandoffResponse {
+                handoff: HandoffResponse::from_row(row, user.user_id),
+                share_url,
+                channel,
+                fact_count: content.facts.len(),
+                reminder_count: content.reminders.len(),
+                event_count: content.events.len(),
+            }),
+            error: None,
+        },
+    )
+}
+
+/// GET /handoffs
+#[utoipa::path(
+    get,
+    path = "/handoffs",
+    tag = "handoffs",
+    params(
+        ("limit" = Option<i64>, Query, description = "Most handoffs to return"),
+    ),
+    responses(
+        (status = 200, description = "Handoffs you sent or received, most recent first", body = ApiResponse<Vec<HandoffResponse>>),
+        (status = 400, description = "Invalid limit", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_handoffs(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let limit: i64 = match Query::from_request(&event).get("limit") {
+        Ok(limit) => limit.unwrap_or(20).clamp(1, 100),
+        Err(e) => return bad_request(e.to_string()),
+    };
+
+    let rows: Vec<HandoffRow> = sqlx::query_as(
+        r#"
+        SELECT
+            id, created_by, recipient_id, family_id,
+            window_start, window_end, title, note,
+            expires_at, revoked_at, view_count, created_at
+        FROM handoffs
+        WHERE created_by = $1 OR recipient_id = $1
+        ORDER BY created_at DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(user.user_id)
+    .bind(limit)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let handoffs: Vec<HandoffResponse> = rows
+        .into_iter()
+        .map(|row| HandoffResponse::from_row(row, user.user_id))
+        .collect();
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(handoffs),
+            error: None,
+        },
+    )
+}
+
+/// DELETE /handoffs/{id}
+#[utoipa::path(
+    delete,
+    path = "/handoffs/{id}",
+    tag = "handoffs",
+    params(
+        ("id" = Uuid, Path, description = "Handoff ID"),
+    ),
+    responses(
+        (status = 200, description = "The share link no longer works"),
+        (status = 404, description = "Handoff not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn revoke_handoff(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let handoff_id = match params.get::<Uuid>("id") {
+        Ok(id) => id,
+        Err(_) => return bad_request("Invalid handoff ID"),
+    };
+
+    let result = sqlx::query(
+        r#"
+        UPDATE handoffs
+        SET revoked_at = COALESCE(revoked_at, NOW())
+        WHERE id = $1 AND created_by = $2
+        "#,
+    )
+    .bind(handoff_id)
+    .bind(user.user_id)
+    .execute(&state.db_pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return not_found();
+    }
+
+    json_response(
+        200,
+        &ApiResponse::<()> {
+            success: true,
+            data: None,
+            error: None,
+        },
+    )
+}
+
+/// GET /handoffs/shared/{token}
+#[utoipa::path(
+    get,
+    path = "/handoffs/shared/{token}",
+    tag = "handoffs",
+    params(
+        ("token" = String, Path, description = "Token from the share link"),
+    ),
+    responses(
+        (status = 200, description = "The handoff as it was sent", body = ApiResponse<SharedHandoffResponse>),
+        (status = 404, description = "Unknown, expired or revoked link", body = ApiError, content_type = "application/problem+json"),
+        (status = 429, description = "Too many views from this address; see `Retry-After`", body = ApiError, content_type = "application/problem+json"),
+    )
+)]
+async fn view_shared_handoff(
+    state: Arc<AppState>,
+    _event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let token = params.raw("token").unwrap_or_default();
+
+    // Expired and revoked links look the same as unknown ones
+    let row: Option<SharedHandoffRow> = sqlx::query_as(
+        r#"
+        UPDATE handoffs h
+        SET view_count = h.view_count + 1, last_viewed_at = NOW()
+        FROM users u
+        WHERE h.share_token_hash = sha256(convert_to($1, 'UTF8'))
+        AND h.revoked_at IS NULL
+        AND h.expires_at > NOW()
+        AND u.id = h.created_by
+        RETURNING
+            h.title, u.display_name AS sender, h.note,
+            h.window_start, h.window_end, h.expires_at, h.content
+        "#,
+    )
+    .bind(token)
+    .fetch_optional(&state.db_pool)
+    .await?;
+
+    let row = match row {
+        Some(row) => row,
+        None => return not_found(),
+    };
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(SharedHandoffResponse {
+                title: row.title,
+                from: row.sender,
+                note: row.note,
+                window_start: row.window_start.to_rfc3339(),
+                window_end: row.window_end.to_rfc3339(),
+                expires_at: row.expires_at.to_rfc3339(),
+                content: serde_json::from_value(row.content)?,
+            }),
+            error: None,
+        },
+    )
+}
+
+/// Route table.
+///
+/// No `RequireAuth` layer: the shared view is public (the token is the
+/// credential) and the other handlers resolve the user themselves.
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(
+            RateLimit::default()
+                .limit(
+                    "GET",
+                    "/handoffs/shared/{token}",
+                    Limit::per_minute(SHARED_VIEWS_PER_MINUTE),
+                )
+                .public("GET", "/handoffs/shared/{token}"),
+        )
+        .post("/handoffs", create_handoff)
+        .get("/handoffs", list_handoffs)
+        .delete("/handoffs/{id}", revoke_handoff)
+        .get("/handoffs/shared/{token}", view_shared_handoff)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
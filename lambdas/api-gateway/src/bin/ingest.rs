@@ -1,124 +1,407 @@
 //! Ingest Lambda - Handles /v1/ingest endpoint.
 //!
 //! This Lambda processes fact ingestion requests from API Gateway, validates the user's
-//! JWT token, and invokes the Python agent system to store the fact.
+//! JWT token (or the API key or device token scripts and devices send), and invokes the
+//! Python agent system to store the fact.
+//! Stored facts that repeat recent ones are merged or flagged, and older facts the rest
+//! replace are then closed off (see `shared::ingest`).
+//!
+//! Endpoints (each also under `/v1` for API keys and device tokens):
+//! - POST /ingest - Store a fact
+//! - POST /ingest/batch - Store up to `MAX_BATCH_ITEMS` items, e.g. imported notes
+//! - GET /ingest/batch/{id} - Progress and per-item results of a batch job
+//!
+//! Batches of up to `SYNC_BATCH_ITEMS` are ingested while the caller waits; larger
+//! ones are recorded in `ingest_batches` and handed to the `ingest_batch` Lambda.
 
-use lambda_http::{run, service_fn, Body, Error, Request, RequestExt, RequestPayloadExt, Response};
+use aws_sdk_lambda::primitives::Blob;
+use lambda_http::{run, service_fn, Body, Error, Request, RequestPayloadExt, Response};
+use shared::diagnostics::{agent_trace, record_sample, Sample};
+use shared::ingest::{
+    ingest_items, BatchIngestRequest, BatchIngestResponse, BatchItemResult, IngestPipeline,
+    SYNC_BATCH_ITEMS,
+};
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::{self, RateLimit};
+use shared::router::{Cors, PathParams, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
 use shared::{
-    extract_user_from_context, AgentClient, ApiResponse, IngestRequest, IngestResponse,
+    error_response, json_response, AgentResponse, ApiError, ApiResponse, AuthenticatedUser,
+    AuthorizedUser, IngestRequest, IngestResponse,
 };
+use sqlx::PgPool;
 use std::sync::Arc;
-use tracing::{error, info};
+use std::time::Instant;
+use tracing::{error, info, warn};
 use tracing_subscriber::EnvFilter;
 use uuid::Uuid;
 
 /// Application state shared across requests.
 struct AppState {
-    agent_client: AgentClient,
+    /// Agent, database and event clients; the database is only set when `DB_HOST`
+    /// is configured, and is used to capture debug-mode samples, detect duplicate
+    /// and superseded facts, and record batch jobs
+    pipeline: Arc<IngestPipeline>,
+    lambda_client: aws_sdk_lambda::Client,
+    /// Lambda that processes large batches (`INGEST_BATCH_FUNCTION_NAME`)
+    ingest_batch_function: Option<String>,
 }
 
 impl AppState {
     async fn new() -> Result<Self, Error> {
         let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
-        let lambda_client = aws_sdk_lambda::Client::new(&config);
 
-        let agent_function = std::env::var("AGENT_FUNCTION_NAME")
-            .unwrap_or_else(|_| "second-brain-agents".to_string());
+        let db_pool = if std::env::var("DB_HOST").is_ok() {
+            let pool = shared::db::connect(&config)
+                .await
+                .map_err(|e| format!("Failed to connect to database: {}", e))?;
+            Some(pool)
+        } else {
+            None
+        };
 
         Ok(Self {
-            agent_client: AgentClient::new(lambda_client, agent_function),
+            pipeline: Arc::new(IngestPipeline::from_env(&config, db_pool)),
+            lambda_client: aws_sdk_lambda::Client::new(&config),
+            ingest_batch_function: std::env::var("INGEST_BATCH_FUNCTION_NAME").ok(),
         })
     }
+
+    fn db_pool(&self) -> Option<&PgPool> {
+        self.pipeline.db_pool.as_ref()
+    }
+}
+
+/// Ingest batch row from database
+#[derive(Debug, sqlx::FromRow)]
+struct IngestBatchRow {
+    id: Uuid,
+    status: String,
+    total_items: i32,
+    stored_items: i32,
+    failed_items: i32,
+    results: sqlx::types::Json<Vec<BatchItemResult>>,
+    error_message: Option<String>,
+}
+
+impl From<IngestBatchRow> for BatchIngestResponse {
+    fn from(row: IngestBatchRow) -> Self {
+        // Recorded as items finish
+        let mut results = row.results.0;
+        results.sort_by_key(|r| r.index);
+
+        Self {
+            batch_id: Some(row.id),
+            status: row.status,
+            total_items: row.total_items as usize,
+            stored_items: row.stored_items as usize,
+            failed_items: row.failed_items as usize,
+            results,
+            error: row.error_message,
+        }
+    }
+}
+
+const BATCH_COLUMNS: &str = r#"
+    id, status, total_items, stored_items, failed_items, results, error_message
+"#;
+
+/// The caller, from the Cognito claims or the API key or device token scripts
+/// and devices send instead. Fails with `Error::Auth` (a 401) if there's none
+/// and `Error::RateLimited` (a 429) if they're over the rate limit.
+async fn caller(state: &AppState, event: &Request) -> shared::Result<AuthenticatedUser> {
+    let user = match (AuthenticatedUser::from_request(event), state.db_pool()) {
+        (Err(_), Some(pool)) => AuthorizedUser::from_request(event, pool)
+            .await
+            .map(AuthenticatedUser::from),
+        (user, _) => {
+            let sub = user.as_ref().ok().map(|u| u.user_id.as_str());
+            ratelimit::check_caller(event, sub).and(user)
+        }
+    };
+    user.map_err(|e| match e {
+        shared::Error::RateLimited(_) => e,
+        e => {
+            error!("Failed to extract user: {}", e);
+            shared::Error::Auth("Authentication required".to_string())
+        }
+    })
 }
 
-async fn handler(state: Arc<AppState>, event: Request) -> Result<Response<Body>, Error> {
-    // Extract user from request context (set by Cognito authorizer)
-    let user = match event.request_context_ref() {
-        Some(ctx) => {
-            if let Some(authorizer) = ctx.authorizer() {
-                let claims = authorizer.fields.get("claims");
-                if let Some(claims) = claims {
-                    match extract_user_from_context(claims) {
-                        Ok(user) => user,
-                        Err(e) => {
-                            error!("Failed to extract user: {}", e);
-                            return Ok(error_response(401, "Authentication required"));
-                        }
-                    }
-                } else {
-                    return Ok(error_response(401, "Authentication required"));
-                }
-            } else {
-                return Ok(error_response(401, "Authentication required"));
-            }
+/// Record a diagnostics sample if the user has debug mode on (best effort).
+async fn capture_sample(
+    pool: &PgPool,
+    user: &AuthenticatedUser,
+    operation: &str,
+    request: &str,
+    result: &shared::Result<AgentResponse>,
+    started: Instant,
+) {
+    let user = match AuthorizedUser::resolve(user.clone(), pool).await {
+        Ok(user) => user,
+        Err(e) => {
+            warn!("Skipping diagnostics capture: {}", e);
+            return;
         }
-        None => return Ok(error_response(401, "Authentication required")),
     };
 
+    let error = result.as_ref().err().map(|e| e.to_string());
+    let sample = Sample {
+        user_id: user.user_id,
+        source: "api",
+        operation,
+        request,
+        response: result.as_ref().ok().map(|r| r.response.as_str()),
+        error: error.as_deref(),
+        trace: result
+            .as_ref()
+            .map(agent_trace)
+            .unwrap_or_else(|_| serde_json::json!({})),
+        latency_ms: Some(started.elapsed().as_millis().min(i32::MAX as u128) as i32),
+    };
+
+    if let Err(e) = record_sample(pool, sample).await {
+        warn!("Failed to record diagnostics sample: {}", e);
+    }
+}
+
+/// POST /ingest
+#[utoipa::path(
+    post,
+    path = "/ingest",
+    tag = "knowledge",
+    request_body = IngestRequest,
+    responses(
+        (status = 200, description = "The fact was stored", body = ApiResponse<IngestResponse>),
+        (status = 400, description = "Invalid request", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn ingest(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = caller(&state, &event).await?;
     info!("Processing ingestion for user: {}", user.user_id);
 
     // Parse request body
     let request: IngestRequest = match event.payload() {
         Ok(Some(req)) => req,
-        Ok(None) => return Ok(error_response(400, "Missing request body")),
-        Err(e) => return Ok(error_response(400, &format!("Invalid request: {}", e))),
+        Ok(None) => return error_response(400, "Missing request body"),
+        Err(e) => return error_response(400, format!("Invalid request: {}", e)),
     };
 
-    // Validate content
-    if request.content.trim().is_empty() {
-        return Ok(error_response(400, "Content cannot be empty"));
+    if let Err(shared::Error::Validation(message)) = request.validate() {
+        return error_response(400, message);
     }
 
-    // Build the message for the agent
-    // Include visibility tier if specified
-    let message = if let Some(tier) = request.visibility_tier {
-        format!(
-            "Remember this (visibility tier {}): {}",
-            tier, request.content
-        )
-    } else {
-        format!("Remember this: {}", request.content)
-    };
+    // Build the message for the agent (content plus any structured hints)
+    let message = request.agent_message();
 
     // Invoke agent system for ingestion
-    let agent_response = match state
+    let started = Instant::now();
+    let result = state
+        .pipeline
         .agent_client
         .ingest(&message, &user.user_id, user.family_ids.clone(), "api")
-        .await
-    {
+        .await;
+
+    if let Some(pool) = state.db_pool() {
+        capture_sample(pool, &user, "ingest", &message, &result, started).await;
+    }
+
+    let agent_response = match result {
         Ok(resp) => resp,
         Err(e) => {
             error!("Agent invocation failed: {}", e);
-            return Ok(error_response(500, "Failed to store fact"));
+            return error_response(500, "Failed to store fact");
         }
     };
 
     // Build response
-    // In a real implementation, we'd parse the agent response to extract fact_id and entities
-    let response_body = ApiResponse::success(IngestResponse {
-        fact_id: Uuid::new_v4(), // TODO: Extract from agent response
-        message: agent_response.response,
-        entities_created: vec![], // TODO: Extract from agent response
-    });
+    let response_body = ApiResponse::success(state.pipeline.settle(agent_response, "api").await);
 
     let body = serde_json::to_string(&response_body)?;
 
     Ok(Response::builder()
         .status(201)
         .header("content-type", "application/json")
-        .body(Body::from(body))
-        .expect("Failed to build response"))
+        .body(Body::from(body))?)
 }
 
-fn error_response(status: u16, message: &str) -> Response<Body> {
-    let body = serde_json::to_string(&ApiResponse::<()>::error(message))
-        .unwrap_or_else(|_| r#"{"success":false,"error":"Internal error"}"#.to_string());
+/// POST /ingest/batch
+///
+/// Small batches are ingested at once and answered with every item's result;
+/// larger ones are started as a job and answered with 202 and its `batch_id`.
+#[utoipa::path(
+    post,
+    path = "/ingest/batch",
+    tag = "knowledge",
+    request_body = BatchIngestRequest,
+    responses(
+        (status = 200, description = "Every item was ingested; one result per item", body = ApiResponse<BatchIngestResponse>),
+        (status = 202, description = "Too many items to ingest at once; poll `GET /ingest/batch/{id}` with the `batch_id`", body = ApiResponse<BatchIngestResponse>),
+        (status = 400, description = "No items, or too many", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn ingest_batch(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = caller(&state, &event).await?;
 
-    Response::builder()
-        .status(status)
-        .header("content-type", "application/json")
-        .body(Body::from(body))
-        .expect("Failed to build error response")
+    let request: BatchIngestRequest = match event.payload() {
+        Ok(Some(req)) => req,
+        Ok(None) => return error_response(400, "Missing request body"),
+        Err(e) => return error_response(400, format!("Invalid request: {}", e)),
+    };
+
+    if let Err(shared::Error::Validation(message)) = request.validate() {
+        return error_response(400, message);
+    }
+
+    if !request.needs_job() {
+        info!(
+            "Ingesting batch of {} items for user: {}",
+            request.items.len(),
+            user.user_id
+        );
+        let results = ingest_items(
+            Arc::clone(&state.pipeline),
+            request.items,
+            0,
+            &user.user_id,
+            &user.family_ids,
+            "api",
+        )
+        .await;
+
+        return json_response(
+            200,
+            &ApiResponse::success(BatchIngestResponse::completed(results)),
+        );
+    }
+
+    let (Some(pool), Some(function)) = (state.db_pool(), &state.ingest_batch_function) else {
+        return error_response(
+            503,
+            format!(
+                "Batches of more than {} items aren't available",
+                SYNC_BATCH_ITEMS
+            ),
+        );
+    };
+    let user = AuthorizedUser::resolve(user, pool).await?;
+
+    let batch: IngestBatchRow = sqlx::query_as(&format!(
+        r#"
+        INSERT INTO ingest_batches (user_id, items, total_items)
+        VALUES ($1, $2, $3)
+        RETURNING {}
+        "#,
+        BATCH_COLUMNS
+    ))
+    .bind(user.user_id)
+    .bind(sqlx::types::Json(&request.items))
+    .bind(request.items.len() as i32)
+    .fetch_one(pool)
+    .await?;
+
+    let payload = serde_json::to_vec(&serde_json::json!({ "batch_id": batch.id }))?;
+    let invoked = state
+        .lambda_client
+        .invoke()
+        .function_name(function)
+        .invocation_type(aws_sdk_lambda::types::InvocationType::Event)
+        .payload(Blob::new(payload))
+        .send()
+        .await;
+
+    if let Err(e) = invoked {
+        let message = format!("Failed to start batch: {}", e);
+        sqlx::query(
+            "UPDATE ingest_batches SET status = 'failed', error_message = $2 WHERE id = $1",
+        )
+        .bind(batch.id)
+        .bind(&message)
+        .execute(pool)
+        .await?;
+
+        return Err(message.into());
+    }
+
+    info!(
+        user_id = %user.user_id,
+        batch_id = %batch.id,
+        items = request.items.len(),
+        "Started ingest batch"
+    );
+
+    json_response(202, &ApiResponse::success(BatchIngestResponse::from(batch)))
+}
+
+/// GET /ingest/batch/{id}
+#[utoipa::path(
+    get,
+    path = "/ingest/batch/{id}",
+    tag = "knowledge",
+    params(
+        ("id" = Uuid, Path, description = "The `batch_id` the batch was started with"),
+    ),
+    responses(
+        (status = 200, description = "Progress and the results of the items processed so far", body = ApiResponse<BatchIngestResponse>),
+        (status = 404, description = "Not one of the caller's batches", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn get_batch(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = caller(&state, &event).await?;
+
+    let Some(pool) = state.db_pool() else {
+        return error_response(404, "Batch not found");
+    };
+    let Ok(batch_id) = params.get::<Uuid>("id") else {
+        return error_response(400, "Invalid batch ID");
+    };
+    let user = AuthorizedUser::resolve(user, pool).await?;
+
+    let batch: Option<IngestBatchRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM ingest_batches WHERE id = $1 AND user_id = $2",
+        BATCH_COLUMNS
+    ))
+    .bind(batch_id)
+    .bind(user.user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(batch) = batch else {
+        return error_response(404, "Batch not found");
+    };
+
+    json_response(200, &ApiResponse::success(BatchIngestResponse::from(batch)))
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .post("/ingest", ingest)
+        .post("/v1/ingest", ingest)
+        .post("/ingest/batch", ingest_batch)
+        .post("/v1/ingest/batch", ingest_batch)
+        .get("/ingest/batch/{id}", get_batch)
+        .get("/v1/ingest/batch/{id}", get_batch)
 }
 
 #[tokio::main]
@@ -129,10 +412,12 @@ async fn main() -> Result<(), Error> {
         .init();
 
     let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
 
     run(service_fn(move |event| {
         let state = Arc::clone(&state);
-        async move { handler(state, event).await }
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
     }))
     .await
 }
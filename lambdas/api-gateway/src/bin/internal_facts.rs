@@ -0,0 +1,252 @@
+//! Internal Facts Lambda - Structured edits and deletions for chat commands.
+//!
+//! Not behind API Gateway: the Discord webhook and the agents invoke it
+//! directly with a signed `shared::fact_actions::FactActionRequest`, whose
+//! `path` picks the operation:
+//!
+//! - `/internal/facts/resolve-and-edit` - replace the content of a fact
+//! - `/internal/facts/resolve-and-delete` - move a fact to the trash
+//! - `/internal/facts/undo` - revert either by its `undo_token` (the user's
+//!   latest change without one)
+//!
+//! The fact is given by `fact_id`, or described by a `query` searched among
+//! the facts the user can change. A description that matches several facts
+//! changes nothing and returns them as `ambiguous` candidates; the caller
+//! repeats the request with the chosen `fact_id`.
+//!
+//! Edits and deletions return an undo token valid for
+//! `shared::fact_actions::UNDO_WINDOW_SECS`. Edits, and undoing them, publish
+//! `FactUpdated` so the fact is re-embedded.
+
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde_json::Value;
+use shared::audit::{self, AuditAction, AuditResource};
+use shared::auth::verify_internal;
+use shared::fact_actions::{
+    edit_fact, issue_undo, resolve_fact, undo, FactActionRequest, FactActionResponse, Resolution,
+    DELETE_PATH, EDIT_PATH, UNDO_PATH,
+};
+use shared::metrics;
+use shared::trash::{self, RETENTION_DAYS};
+use shared::EventPublisher;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+struct AppState {
+    db_pool: PgPool,
+    event_publisher: Option<EventPublisher>,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self {
+            db_pool,
+            event_publisher: EventPublisher::from_env(&config),
+        })
+    }
+}
+
+/// Resolve the request's fact and apply its operation.
+async fn apply(
+    state: &AppState,
+    request: &FactActionRequest,
+) -> shared::Result<FactActionResponse> {
+    let pool = &state.db_pool;
+
+    // Check the operation before searching, so a bad request changes nothing
+    let content = match request.path.as_str() {
+        EDIT_PATH => Some(request.content.as_deref().ok_or_else(|| {
+            shared::Error::Validation("content is required to edit a fact".to_string())
+        })?),
+        DELETE_PATH => None,
+        UNDO_PATH => return apply_undo(state, request).await,
+        other => {
+            return Err(shared::Error::NotFound(format!("Unknown path: {}", other)));
+        }
+    };
+
+    let fact =
+        match resolve_fact(pool, request.user_id, &request.family_ids, &request.target).await? {
+            Resolution::Found(fact) => fact,
+            Resolution::Ambiguous(candidates) => {
+                return Ok(FactActionResponse::Ambiguous { candidates })
+            }
+            Resolution::NotFound => return Ok(FactActionResponse::NotFound),
+        };
+
+    let before = audit::snapshot(pool, AuditResource::Fact, fact.id).await;
+
+    match content {
+        Some(content) => {
+            let Some(edited) =
+                edit_fact(pool, request.user_id, &request.family_ids, fact.id, content).await?
+            else {
+                return Ok(FactActionResponse::NotFound);
+            };
+
+            audit::record_change(
+                pool,
+                request.user_id,
+                AuditAction::Update,
+                AuditResource::Fact,
+                fact.id,
+                before,
+            )
+            .await;
+            info!(fact_id = %fact.id, "Edited fact");
+
+            if let Some(publisher) = &state.event_publisher {
+                publisher
+                    .publish_fact_updated(pool, fact.id, request.user_id)
+                    .await;
+            }
+
+            let undo = issue_undo(
+                pool,
+                request.user_id,
+                fact.id,
+                Some((&fact.content, &edited.content)),
+            )
+            .await?;
+
+            Ok(FactActionResponse::Edited {
+                fact: edited,
+                previous_content: fact.content,
+                undo,
+            })
+        }
+        None => {
+            if !trash::delete_fact(pool, fact.id, request.user_id).await? {
+                return Ok(FactActionResponse::NotFound);
+            }
+
+            audit::record_change(
+                pool,
+                request.user_id,
+                AuditAction::Delete,
+                AuditResource::Fact,
+                fact.id,
+                before,
+            )
+            .await;
+            info!(fact_id = %fact.id, "Moved fact to trash");
+
+            let undo = issue_undo(pool, request.user_id, fact.id, None).await?;
+
+            Ok(FactActionResponse::Deleted {
+                fact,
+                restore_within_days: RETENTION_DAYS,
+                undo,
+            })
+        }
+    }
+}
+
+/// Revert an edit or deletion by its undo token, or the latest one.
+async fn apply_undo(
+    state: &AppState,
+    request: &FactActionRequest,
+) -> shared::Result<FactActionResponse> {
+    let pool = &state.db_pool;
+    let Some(undone) = undo(pool, request.user_id, request.undo_token).await? else {
+        return Ok(FactActionResponse::NotFound);
+    };
+
+    let action = if undone.restored {
+        AuditAction::Restore
+    } else {
+        AuditAction::Update
+    };
+    audit::record_change(
+        pool,
+        request.user_id,
+        action,
+        AuditResource::Fact,
+        undone.fact.id,
+        None,
+    )
+    .await;
+    info!(fact_id = %undone.fact.id, restored = undone.restored, "Undid fact change");
+
+    // A fact restored from the trash has the content it had
+    if !undone.restored {
+        if let Some(publisher) = &state.event_publisher {
+            publisher
+                .publish_fact_updated(pool, undone.fact.id, request.user_id)
+                .await;
+        }
+    }
+
+    Ok(FactActionResponse::Undone { fact: undone.fact })
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    event: LambdaEvent<Value>,
+) -> Result<FactActionResponse, Error> {
+    let payload = event.payload;
+    let request: FactActionRequest = match serde_json::from_value(payload.clone()) {
+        Ok(request) => request,
+        Err(e) => {
+            return Ok(FactActionResponse::Error {
+                message: format!("Invalid request: {}", e),
+            });
+        }
+    };
+
+    let family_ids: Vec<String> = request.family_ids.iter().map(Uuid::to_string).collect();
+    if let Err(e) = verify_internal(
+        &payload,
+        "POST",
+        &request.path,
+        &request.user_id.to_string(),
+        &family_ids,
+    )
+    .await
+    {
+        warn!(user_id = %request.user_id, "Rejected fact action: {}", e);
+        return Ok(FactActionResponse::Error {
+            message: e.to_string(),
+        });
+    }
+
+    match apply(&state, &request).await {
+        Ok(response) => Ok(response),
+        Err(
+            shared::Error::Validation(message)
+            | shared::Error::NotFound(message)
+            | shared::Error::Conflict(message),
+        ) => Ok(FactActionResponse::Error { message }),
+        Err(e) => {
+            error!(path = %request.path, "Fact action failed: {}", e);
+            Ok(FactActionResponse::Error {
+                message: "Failed to change the fact".to_string(),
+            })
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+
+    run(service_fn(move |event| {
+        let state = state.clone();
+        async move { metrics::track_invocation("internal_facts", handler(state, event)).await }
+    }))
+    .await
+}
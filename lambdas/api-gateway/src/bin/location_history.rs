@@ -0,0 +1,359 @@
+//! Location History Lambda - Location pings from the mobile app and visits.
+//!
+//! Endpoints:
+//! - POST /location-pings/batch - Upload pings (`pings`: `latitude`,
+//!   `longitude`, `accuracy`, `recordedAt`); refused unless location history
+//!   is turned on
+//! - GET /location-history/settings - Your location history settings
+//! - PUT /location-history/settings - Change them (`enabled`,
+//!   `retentionDays`, `recordVisitFacts`); fields left out are kept
+//! - GET /location-history/visits - Visits found in your pings (`since`,
+//!   `limit`)
+//! - DELETE /location-history - Delete your pings and visits; `facts=true`
+//!   also moves visit facts to the trash
+//!
+//! Pings are clustered into visits by the `visit_detector` Lambda; see
+//! `shared::location_history`.
+
+use chrono::{DateTime, Utc};
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::{Deserialize, Serialize};
+use shared::http::error_response;
+use shared::location_history::{self, PingInput, Settings, SettingsUpdate, Visit};
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, Query, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::ApiError;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+
+/// Visits listed when no `limit` is given
+const DEFAULT_VISIT_LIMIT: i64 = 50;
+
+/// Most visits listed at once
+const MAX_VISIT_LIMIT: i64 = 500;
+
+/// Ping batch request
+#[derive(Debug, Deserialize, ToSchema)]
+struct PingBatchRequest {
+    pings: Vec<PingInput>,
+}
+
+/// Ping batch response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct PingBatchResponse {
+    /// Pings stored
+    accepted: u64,
+    /// Pings already uploaded
+    duplicates: u64,
+    /// Pings dropped as inaccurate or past the retention period
+    skipped: u64,
+}
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+/// POST /location-pings/batch
+#[utoipa::path(
+    post,
+    path = "/location-pings/batch",
+    tag = "location-history",
+    request_body = PingBatchRequest,
+    responses(
+        (status = 200, description = "How many pings were stored", body = ApiResponse<PingBatchResponse>),
+        (status = 400, description = "A malformed ping", body = ApiError, content_type = "application/problem+json"),
+        (status = 403, description = "Location history is turned off", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn upload_pings(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let request: PingBatchRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let settings = location_history::load_settings(&state.db_pool, user.user_id).await?;
+    if !settings.enabled {
+        return error_response(403, "Location history is turned off");
+    }
+
+    let received = request.pings.len() as u64;
+    let pings =
+        match location_history::prepare_batch(request.pings, settings.retention_days, Utc::now()) {
+            Ok(pings) => pings,
+            Err(shared::Error::Validation(e)) => return error_response(400, e),
+            Err(e) => return Err(e.to_string().into()),
+        };
+    let kept = pings.len() as u64;
+
+    let accepted = if pings.is_empty() {
+        0
+    } else {
+        location_history::insert_pings(&state.db_pool, user.user_id, &pings).await?
+    };
+
+    info!(user_id = %user.user_id, received, accepted, "Stored location pings");
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(PingBatchResponse {
+                accepted,
+                duplicates: kept - accepted,
+                skipped: received - kept,
+            }),
+            error: None,
+        },
+    )
+}
+
+/// GET /location-history/settings
+#[utoipa::path(
+    get,
+    path = "/location-history/settings",
+    tag = "location-history",
+    responses(
+        (status = 200, description = "Your settings, or the defaults if you haven't saved any", body = ApiResponse<Settings>),
+    ),
+    security(("cognito" = []))
+)]
+async fn get_settings(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let settings: Settings = location_history::load_settings(&state.db_pool, user.user_id).await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(settings),
+            error: None,
+        },
+    )
+}
+
+/// PUT /location-history/settings
+#[utoipa::path(
+    put,
+    path = "/location-history/settings",
+    tag = "location-history",
+    request_body = SettingsUpdate,
+    responses(
+        (status = 200, description = "The saved settings", body = ApiResponse<Settings>),
+        (status = 400, description = "Invalid retention period", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn update_settings(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let update: SettingsUpdate = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let current = location_history::load_settings(&state.db_pool, user.user_id).await?;
+
+    let settings = match update.apply(current) {
+        Ok(settings) => settings,
+        Err(shared::Error::Validation(e)) => return error_response(400, e),
+        Err(e) => return Err(e.to_string().into()),
+    };
+
+    let settings = location_history::save_settings(&state.db_pool, user.user_id, &settings).await?;
+
+    info!(
+        user_id = %user.user_id,
+        enabled = settings.enabled,
+        retention_days = settings.retention_days,
+        "Updated location history settings"
+    );
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(settings),
+            error: None,
+        },
+    )
+}
+
+/// GET /location-history/visits
+#[utoipa::path(
+    get,
+    path = "/location-history/visits",
+    tag = "location-history",
+    params(
+        ("since" = Option<DateTime<Utc>>, Query, description = "Only visits that ended after this time"),
+        ("limit" = Option<i64>, Query, description = "Most visits to return"),
+    ),
+    responses(
+        (status = 200, description = "Visits found in your pings, most recent first", body = ApiResponse<Vec<Visit>>),
+        (status = 400, description = "Invalid `since` or `limit`", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_visits(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let query = Query::from_request(&event);
+    let since = match query.get::<DateTime<Utc>>("since") {
+        Ok(since) => since,
+        Err(e) => return error_response(400, e.to_string()),
+    };
+    let limit = match query.get::<i64>("limit") {
+        Ok(limit) => limit
+            .unwrap_or(DEFAULT_VISIT_LIMIT)
+            .clamp(1, MAX_VISIT_LIMIT),
+        Err(e) => return error_response(400, e.to_string()),
+    };
+
+    let visits: Vec<Visit> =
+        location_history::list_visits(&state.db_pool, user.user_id, since, limit).await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(visits),
+            error: None,
+        },
+    )
+}
+
+/// DELETE /location-history
+#[utoipa::path(
+    delete,
+    path = "/location-history",
+    tag = "location-history",
+    params(
+        ("facts" = Option<bool>, Query, description = "Also move visit facts to the trash"),
+    ),
+    responses(
+        (status = 200, description = "What was deleted", body = ApiResponse<location_history::DeletedHistory>),
+        (status = 400, description = "Invalid `facts`", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn delete_history(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let include_facts = match Query::from_request(&event).get::<bool>("facts") {
+        Ok(facts) => facts.unwrap_or(false),
+        Err(e) => return error_response(400, e.to_string()),
+    };
+
+    let deleted =
+        location_history::delete_history(&state.db_pool, user.user_id, include_facts).await?;
+
+    info!(
+        user_id = %user.user_id,
+        pings = deleted.pings,
+        visits = deleted.visits,
+        facts = deleted.facts,
+        "Deleted location history"
+    );
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(deleted),
+            error: None,
+        },
+    )
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .post("/location-pings/batch", upload_pings)
+        .get("/location-history/settings", get_settings)
+        .put("/location-history/settings", update_settings)
+        .get("/location-history/visits", list_visits)
+        .delete("/location-history", delete_history)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
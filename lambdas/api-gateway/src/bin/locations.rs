@@ -2,18 +2,51 @@
 //!
 //! Endpoints:
 //! - POST /locations/geocode - Geocode an address
-//! - GET /locations/nearby - Find entities near a point
-//! - POST /entities/{id}/locations - Add location to entity
+//! - GET /locations/nearby - Find entities near a point, nearest first (`?lat=&lon=&radius=&type=&limit=&cursor=`);
+//!   `cluster=true` groups them into grid cells with counts and centroids instead (`&zoom=`)
+//! - POST /entities/{id}/locations - Add location to entity; the address is geocoded, and
+//!   one the entity already has under another label updates that location instead
 //! - GET /entities/{id}/locations - Get entity locations
+//! - GET /entities/{id}/locations/map - Presigned static map image of an entity's current locations (`?width=&height=`)
 //! - GET /facts/timeline - Get facts with temporal filtering
+//! - GET /facts/nearby - Facts about places near a point (`?lat=&lon=&radius=&limit=`)
+//! - GET /facts/search - Full-text fact search (`?q=&tags=&entity_ids=&from=&to=&limit=&offset=&search_id=`)
+//! - GET /v1/facts/search - The same, for API keys
+//! - GET /facts/review - Facts due for review (`?limit=`)
+//! - POST /facts/{id}/review - Confirm, update or archive a fact under review
+//! - GET /facts/{id}/attachments - List a fact's attachments
+//! - POST /facts/{id}/attachments - Get a presigned URL to upload an attachment
+//! - DELETE /facts/{id}/attachments/{attachmentId} - Remove an attachment
 
 use lambda_http::{run, service_fn, Body, Error, Request, RequestExt, Response};
 use serde::{Deserialize, Serialize};
-use sqlx::postgres::PgPoolOptions;
+use shared::access::{space_clause, visibility_clause};
+use shared::audit::{self, AuditAction, AuditResource};
+use shared::fact_attachments::{
+    accepted_types, attachment_type, clean_file_name, count_attachments, delete_attachment,
+    insert_attachment, list_attachments, Attachment, DOWNLOAD_URL_TTL_SECS,
+    MAX_ATTACHMENTS_PER_FACT, MAX_ATTACHMENT_BYTES, UPLOAD_URL_TTL_SECS,
+};
+use shared::fact_review::{review_fact, review_queue, ReviewAction, ReviewOutcome};
+use shared::fact_search::{search_facts, SearchFilters, DEFAULT_LIMIT, MAX_LIMIT};
+use shared::geo::{
+    self, find_duplicate_location, place_address, MapOptions, MapPoint, NormalizedAddress,
+    StaticMap,
+};
+use shared::http::error_response;
+use shared::interactions::Interactions;
+use shared::metrics::RequestMetrics;
+use shared::models::{keyset_predicate, Cursor, CursorParams, Page, SortDirection};
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::{ApiError, EventPublisher};
 use sqlx::PgPool;
+use std::collections::HashMap;
 use std::sync::Arc;
-use tracing::info;
+use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 /// Geocode request
@@ -23,7 +56,7 @@ struct GeocodeRequest {
 }
 
 /// Store location request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct StoreLocationRequest {
     label: String,
     address: String,
@@ -34,8 +67,45 @@ struct StoreLocationRequest {
     visibility_tier: Option<i16>,
 }
 
+/// Fact review request
+#[derive(Debug, Deserialize, ToSchema)]
+struct FactReviewRequest {
+    action: String,
+    content: Option<String>,
+    valid_from: Option<String>,
+    valid_to: Option<String>,
+}
+
+/// Attachment upload request
+#[derive(Debug, Deserialize, ToSchema)]
+struct AttachmentUploadRequest {
+    file_name: Option<String>,
+    content_type: String,
+    /// Size of the file in bytes; the upload URL only accepts exactly this size
+    content_length: i64,
+}
+
+/// Attachment upload response
+#[derive(Debug, Serialize, ToSchema)]
+struct AttachmentUploadResponse {
+    attachment: Attachment,
+    /// PUT the file here with the same Content-Type and Content-Length
+    upload_url: String,
+    expires_in: u64,
+}
+
+/// Geocoder result for an address
+struct Geocoded {
+    latitude: f64,
+    longitude: f64,
+    confidence: Option<f64>,
+    /// Set by geocoders that identify places (HERE, Grab)
+    place_id: Option<String>,
+    address: NormalizedAddress,
+}
+
 /// Location response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct LocationResponse {
     id: String,
     label: String,
@@ -60,6 +130,64 @@ struct NearbyEntityResponse {
     distance_display: String,
 }
 
+/// Nearby cluster response
+#[derive(Debug, Serialize)]
+struct NearbyClusterResponse {
+    /// Entities with a location in the cluster
+    count: i64,
+    /// Centroid of the cluster's locations
+    latitude: f64,
+    longitude: f64,
+    /// Distance to the cluster's nearest location
+    distance_meters: f64,
+    /// The entity, for clusters of one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entity_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+}
+
+/// Nearby search row: location id, entity id, entity type, name, location
+/// label, address, latitude, longitude and distance
+type NearbyRow = (
+    Uuid,
+    Uuid,
+    String,
+    String,
+    String,
+    Option<String>,
+    f64,
+    f64,
+    f64,
+);
+
+/// Most clusters returned for nearby search
+const NEARBY_MAX_CLUSTERS: i64 = 500;
+
+/// Largest radius for nearby facts, in meters
+const NEARBY_FACTS_MAX_RADIUS: f64 = 50_000.0;
+
+/// Most nearby facts returned
+const NEARBY_FACTS_MAX_LIMIT: i64 = 100;
+
+/// Nearby fact response
+#[derive(Debug, Serialize)]
+struct NearbyFactResponse {
+    id: String,
+    content: String,
+    importance: i16,
+    recorded_at: String,
+    valid_from: Option<String>,
+    valid_to: Option<String>,
+    /// Nearest located entity the fact is about or mentions
+    entity_id: String,
+    entity_name: String,
+    location_label: String,
+    distance_meters: f64,
+    distance_display: String,
+    attachments: Vec<Attachment>,
+}
+
 /// Timeline fact response
 #[derive(Debug, Serialize)]
 struct TimelineFactResponse {
@@ -71,10 +199,11 @@ struct TimelineFactResponse {
     valid_to: Option<String>,
     entity_name: Option<String>,
     is_current: bool,
+    attachments: Vec<Attachment>,
 }
 
 /// API response wrapper
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct ApiResponse<T> {
     success: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -86,63 +215,126 @@ struct ApiResponse<T> {
 /// Application state
 struct AppState {
     db_pool: PgPool,
+    /// For presigning map URLs
+    aws_config: aws_config::SdkConfig,
+    s3_client: aws_sdk_s3::Client,
+    location_client: aws_sdk_location::Client,
+    /// Place index for geocoding addresses; stored as entered when unset
+    place_index_name: Option<String>,
+    /// Bucket for fact attachments; uploads are unavailable when unset
+    attachments_bucket: Option<String>,
+    /// Set when `EVENT_BUS_NAME` is configured
+    event_publisher: Option<EventPublisher>,
+    map_options: MapOptions,
 }
 
 impl AppState {
     async fn new() -> Result<Self, Error> {
         let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
-        let secrets_client = aws_sdk_secretsmanager::Client::new(&config);
 
-        let db_secret_arn =
-            std::env::var("DB_SECRET_ARN").map_err(|_| "DB_SECRET_ARN not set")?;
-
-        let db_secret = secrets_client
-            .get_secret_value()
-            .secret_id(&db_secret_arn)
-            .send()
+        let db_pool = shared::db::connect(&config)
             .await
-            .map_err(|e| format!("Failed to get DB secret: {}", e))?;
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
 
-        let db_creds: serde_json::Value =
-            serde_json::from_str(db_secret.secret_string().unwrap_or("{}"))?;
+        let s3_client = aws_sdk_s3::Client::new(&config);
+        let attachments_bucket = std::env::var("FACT_ATTACHMENTS_BUCKET").ok();
 
-        let db_host = std::env::var("DB_HOST").map_err(|_| "DB_HOST not set")?;
-        let db_name = std::env::var("DB_NAME").unwrap_or_else(|_| "second_brain".to_string());
-        let db_user = db_creds["username"].as_str().unwrap_or("sbadmin");
-        let db_pass = db_creds["password"].as_str().unwrap_or("");
+        Ok(Self {
+            db_pool,
+            s3_client,
+            location_client: aws_sdk_location::Client::new(&config),
+            place_index_name: std::env::var("PLACE_INDEX_NAME").ok(),
+            attachments_bucket,
+            event_publisher: EventPublisher::from_env(&config),
+            map_options: MapOptions::from_env(),
+            aws_config: config,
+        })
+    }
+}
 
-        let database_url = format!(
-            "postgres://{}:{}@{}:5432/{}",
-            db_user, db_pass, db_host, db_name
-        );
+/// Presigned GET URL for a stored attachment, or `None` if it can't be signed.
+async fn attachment_url(state: &AppState, key: &str) -> Option<String> {
+    let bucket = state.attachments_bucket.as_deref()?;
+    let config = aws_sdk_s3::presigning::PresigningConfig::expires_in(
+        std::time::Duration::from_secs(DOWNLOAD_URL_TTL_SECS),
+    )
+    .ok()?;
 
-        let db_pool = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(&database_url)
-            .await
-            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+    match state
+        .s3_client
+        .get_object()
+        .bucket(bucket)
+        .key(key)
+        .presigned(config)
+        .await
+    {
+        Ok(request) => Some(request.uri().to_string()),
+        Err(e) => {
+            warn!("Failed to presign attachment {}: {}", key, e);
+            None
+        }
+    }
+}
+
+/// Attachments on the given facts, with download URLs, grouped by fact.
+async fn attachments_by_fact(
+    state: &AppState,
+    fact_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<Attachment>>, Error> {
+    let attachments = list_attachments(&state.db_pool, fact_ids).await?;
 
-        Ok(Self { db_pool })
+    let mut by_fact: HashMap<Uuid, Vec<Attachment>> = HashMap::new();
+    for mut attachment in attachments {
+        attachment.url = attachment_url(state, &attachment.object_key).await;
+        by_fact.entry(attachment.fact_id).or_default().push(attachment);
     }
+
+    Ok(by_fact)
 }
 
-/// Extract user_id from Cognito claims
-fn extract_user_id(event: &Request) -> Result<Uuid, Error> {
-    let context = event
-        .request_context_ref()
-        .ok_or("Missing request context")?;
+/// Geocode an address with the place index, or `None` if it isn't configured
+/// or finds nothing.
+async fn geocode(state: &AppState, address: &str) -> Option<Geocoded> {
+    let index_name = state.place_index_name.as_ref()?;
 
-    let claims = context
-        .authorizer()
-        .and_then(|a| a.fields.get("claims"))
-        .ok_or("Missing claims in authorizer context")?;
+    let output = match state
+        .location_client
+        .search_place_index_for_text()
+        .index_name(index_name)
+        .text(address)
+        .max_results(1)
+        .send()
+        .await
+    {
+        Ok(output) => output,
+        Err(e) => {
+            warn!("Geocoding failed: {}", e);
+            return None;
+        }
+    };
 
-    let sub = claims
-        .get("sub")
-        .and_then(|v| v.as_str())
-        .ok_or("Missing sub claim")?;
+    let result = output.results().first()?;
+    let place = result.place()?;
+    let (longitude, latitude) = match place.geometry()?.point() {
+        [longitude, latitude, ..] => (*longitude, *latitude),
+        _ => return None,
+    };
 
-    Uuid::parse_str(sub).map_err(|e| format!("Invalid user_id: {}", e).into())
+    Some(Geocoded {
+        latitude,
+        longitude,
+        confidence: result.relevance(),
+        place_id: result.place_id().map(str::to_string),
+        address: NormalizedAddress {
+            label: place.label().map(str::to_string),
+            address_number: place.address_number().map(str::to_string),
+            street: place.street().map(str::to_string),
+            municipality: place.municipality().map(str::to_string),
+            region: place.region().map(str::to_string),
+            postal_code: place.postal_code().map(str::to_string),
+            country: place.country().map(str::to_string),
+        },
+    })
 }
 
 /// Format distance for display
@@ -159,620 +351,1485 @@ fn format_distance(meters: f64) -> String {
     }
 }
 
-async fn handler(state: Arc<AppState>, event: Request) -> Result<Response<Body>, Error> {
-    let raw_path = event.uri().path();
-    // Strip /api stage prefix if present (API Gateway REST API includes stage in path)
-    let path = raw_path.strip_prefix("/api").unwrap_or(raw_path);
-    let method = event.method().as_str();
-
-    let cognito_sub = match extract_user_id(&event) {
-        Ok(id) => id,
-        Err(e) => {
-            return Ok(json_response(
-                401,
-                &ApiResponse::<()> {
-                    success: false,
-                    data: None,
-                    error: Some(format!("Authentication required: {}", e)),
-                },
-            )?);
-        }
-    };
+/// Whether the user can see the fact.
+async fn can_access_fact(
+    pool: &PgPool,
+    fact_id: Uuid,
+    user_id: Uuid,
+    family_ids: &[Uuid],
+) -> Result<bool, Error> {
+    let has_access: bool = sqlx::query_scalar(&format!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM facts f
+            WHERE f.id = $1
+            AND f.deleted_at IS NULL
+            AND (
+                (f.owner_type = 'user' AND f.owner_id = $2)
+                OR (f.owner_type = 'family' AND f.owner_id = ANY($3) AND {})
+            )
+        )
+        "#,
+        space_clause("f", 2)
+    ))
+    .bind(fact_id)
+    .bind(user_id)
+    .bind(family_ids)
+    .fetch_one(pool)
+    .await?;
 
-    // Look up database user_id from Cognito sub
-    let user_id: Uuid = match sqlx::query_scalar::<_, Uuid>(
-        "SELECT id FROM users WHERE cognito_sub = $1::text"
-    )
-    .bind(cognito_sub)
-    .fetch_optional(&state.db_pool)
-    .await
-    .map_err(|e| format!("Failed to lookup user: {}", e))? {
-        Some(id) => id,
-        None => {
-            return Ok(json_response(
-                401,
-                &ApiResponse::<()> {
-                    success: false,
-                    data: None,
-                    error: Some("User not registered".to_string()),
-                },
-            )?);
-        }
-    };
+    Ok(has_access)
+}
 
-    // Get user's family IDs for permission checks
-    let family_ids: Vec<Uuid> = sqlx::query_scalar(
-        "SELECT family_id FROM family_members WHERE user_id = $1"
-    )
+/// Whether the user can see the entity.
+async fn can_access_entity(pool: &PgPool, entity_id: Uuid, user_id: Uuid) -> Result<bool, Error> {
+    let has_access: bool = sqlx::query_scalar(&format!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM entities e
+            LEFT JOIN family_members fm ON e.owner_type = 'family' AND e.owner_id = fm.family_id AND fm.user_id = $2
+            WHERE e.id = $1
+            AND e.deleted_at IS NULL
+            AND (
+                (e.owner_type = 'user' AND e.owner_id = $2)
+                OR (e.owner_type = 'family' AND fm.user_id IS NOT NULL AND {})
+            )
+        )
+        "#,
+        space_clause("e", 2)
+    ))
+    .bind(entity_id)
     .bind(user_id)
-    .fetch_all(&state.db_pool)
-    .await
-    .unwrap_or_default();
-
-    match (method, path) {
-        // Nearby search
-        ("GET", "/locations/nearby") => {
-            let params = event.query_string_parameters();
-
-            let lat: f64 = params.first("lat")
-                .and_then(|l| l.parse().ok())
-                .ok_or("lat parameter required")?;
-            // Support both 'lon' and 'lng' parameter names
-            let lon: f64 = params.first("lon")
-                .or_else(|| params.first("lng"))
-                .and_then(|l| l.parse().ok())
-                .ok_or("lon or lng parameter required")?;
-            // Support radius in meters or radius_km in kilometers
-            let radius: f64 = params.first("radius")
-                .and_then(|r| r.parse().ok())
-                .or_else(|| params.first("radius_km")
-                    .and_then(|r| r.parse::<f64>().ok())
-                    .map(|km| km * 1000.0))
-                .unwrap_or(1000.0);
-            let entity_type = params.first("type");
-            let limit: i64 = params.first("limit")
-                .and_then(|l| l.parse().ok())
-                .unwrap_or(20);
-
-            let results: Vec<NearbyEntityResponse> = if let Some(etype) = entity_type {
-                sqlx::query_as::<_, (Uuid, String, String, String, Option<String>, f64, f64, f64)>(
-                    r#"
-                    SELECT
-                        e.id,
-                        e.entity_type::text,
-                        e.name,
-                        el.label,
-                        el.address_raw,
-                        ST_Y(el.location::geometry) as lat,
-                        ST_X(el.location::geometry) as lon,
-                        ST_Distance(
-                            el.location,
-                            ST_SetSRID(ST_MakePoint($1, $2), 4326)::geography
-                        ) as distance_meters
-                    FROM entities e
-                    JOIN entity_locations el ON el.entity_id = e.id
-                    WHERE ST_DWithin(
-                        el.location,
-                        ST_SetSRID(ST_MakePoint($1, $2), 4326)::geography,
-                        $3
-                    )
-                    AND (el.valid_to IS NULL OR el.valid_to > CURRENT_DATE)
-                    AND (
-                        (e.owner_type = 'user' AND e.owner_id = $4)
-                        OR (e.owner_type = 'family' AND e.owner_id = ANY($5))
-                    )
-                    AND e.entity_type = $6::entity_type
-                    ORDER BY distance_meters ASC
-                    LIMIT $7
-                    "#
-                )
-                .bind(lon)
-                .bind(lat)
-                .bind(radius)
-                .bind(user_id)
-                .bind(&family_ids)
-                .bind(etype)
-                .bind(limit)
-                .fetch_all(&state.db_pool)
-                .await
-            } else {
-                sqlx::query_as::<_, (Uuid, String, String, String, Option<String>, f64, f64, f64)>(
-                    r#"
-                    SELECT
-                        e.id,
-                        e.entity_type::text,
-                        e.name,
-                        el.label,
-                        el.address_raw,
-                        ST_Y(el.location::geometry) as lat,
-                        ST_X(el.location::geometry) as lon,
-                        ST_Distance(
-                            el.location,
-                            ST_SetSRID(ST_MakePoint($1, $2), 4326)::geography
-                        ) as distance_meters
-                    FROM entities e
-                    JOIN entity_locations el ON el.entity_id = e.id
-                    WHERE ST_DWithin(
-                        el.location,
-                        ST_SetSRID(ST_MakePoint($1, $2), 4326)::geography,
-                        $3
-                    )
-                    AND (el.valid_to IS NULL OR el.valid_to > CURRENT_DATE)
-                    AND (
-                        (e.owner_type = 'user' AND e.owner_id = $4)
-                        OR (e.owner_type = 'family' AND e.owner_id = ANY($5))
-                    )
-                    ORDER BY distance_meters ASC
-                    LIMIT $6
-                    "#
-                )
-                .bind(lon)
-                .bind(lat)
-                .bind(radius)
-                .bind(user_id)
-                .bind(&family_ids)
-                .bind(limit)
-                .fetch_all(&state.db_pool)
-                .await
-            }
-            .map_err(|e| format!("Failed to search nearby: {}", e))?
+    .fetch_one(pool)
+    .await?;
+
+    Ok(has_access)
+}
+
+/// GET /locations/nearby
+#[utoipa::path(
+    get,
+    path = "/locations/nearby",
+    tag = "locations",
+    params(
+        ("lat" = f64, Query, description = "Latitude of the center"),
+        ("lon" = f64, Query, description = "Longitude of the center (`lng` also works)"),
+        ("radius" = Option<f64>, Query, description = "Search radius in meters (default 1000; `radius_km` also works)"),
+        ("type" = Option<String>, Query, description = "Only entities of this type"),
+        ("cluster" = Option<bool>, Query, description = "Group results into grid cells"),
+        ("zoom" = Option<f64>, Query, description = "Map zoom the clusters are sized for"),
+        ("limit" = Option<i64>, Query, description = "Most entities to return"),
+        ("cursor" = Option<String>, Query, description = "Cursor returned with the previous page"),
+    ),
+    responses(
+        (status = 200, description = "Entities near the point, nearest first (`center`, `radius_meters`, `results`, `next_cursor`, `has_more`), or `clusters` with counts and centroids when `cluster=true`", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Missing `lat` or `lon`, or an invalid cursor", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn nearby_entities(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+    let family_ids = user.family_ids;
+
+    let params = event.query_string_parameters();
+
+    let lat: f64 = params.first("lat")
+        .and_then(|l| l.parse().ok())
+        .ok_or_else(|| ApiError::validation("lat parameter required"))?;
+    // Support both 'lon' and 'lng' parameter names
+    let lon: f64 = params.first("lon")
+        .or_else(|| params.first("lng"))
+        .and_then(|l| l.parse().ok())
+        .ok_or_else(|| ApiError::validation("lon or lng parameter required"))?;
+    // Support radius in meters or radius_km in kilometers
+    let radius: f64 = params.first("radius")
+        .and_then(|r| r.parse().ok())
+        .or_else(|| params.first("radius_km")
+            .and_then(|r| r.parse::<f64>().ok())
+            .map(|km| km * 1000.0))
+        .unwrap_or(1000.0);
+    let entity_type = params.first("type");
+    let cluster = params
+        .first("cluster")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+
+    let center = "ST_SetSRID(ST_MakePoint($1, $2), 4326)::geography";
+    let filters = format!(
+        r#"
+        WHERE ST_DWithin(el.location, {center}, $3)
+        AND (el.valid_to IS NULL OR el.valid_to > CURRENT_DATE)
+        AND e.deleted_at IS NULL
+        AND {}
+        AND ($6::text IS NULL OR e.entity_type = $6::entity_type)
+        "#,
+        visibility_clause("e", 4),
+        center = center,
+    );
+
+    if cluster {
+        let zoom: Option<f64> = params.first("zoom").and_then(|z| z.parse().ok());
+        let cell_degrees = geo::cluster_cell_degrees(zoom, radius);
+
+        let clusters: Vec<NearbyClusterResponse> =
+            sqlx::query_as::<_, (i64, f64, f64, f64, Uuid, String)>(&format!(
+                r#"
+                SELECT
+                    COUNT(DISTINCT e.id),
+                    ST_Y(ST_Centroid(ST_Collect(el.location::geometry))),
+                    ST_X(ST_Centroid(ST_Collect(el.location::geometry))),
+                    MIN(ST_Distance(el.location, {center})) AS distance_meters,
+                    (array_agg(e.id ORDER BY e.name))[1],
+                    (array_agg(e.name ORDER BY e.name))[1]
+                FROM entities e
+                JOIN entity_locations el ON el.entity_id = e.id
+                {filters}
+                GROUP BY floor(ST_X(el.location::geometry) / $7),
+                         floor(ST_Y(el.location::geometry) / $7)
+                ORDER BY distance_meters ASC
+                LIMIT $8
+                "#,
+                center = center,
+                filters = filters,
+            ))
+            .bind(lon)
+            .bind(lat)
+            .bind(radius)
+            .bind(user_id)
+            .bind(&family_ids)
+            .bind(entity_type)
+            .bind(cell_degrees)
+            .bind(NEARBY_MAX_CLUSTERS)
+            .fetch_all(&state.db_pool)
+            .await?
             .into_iter()
-            .map(|(id, entity_type, name, label, address, latitude, longitude, distance)| {
-                NearbyEntityResponse {
-                    entity_id: id.to_string(),
-                    entity_type,
-                    name,
-                    location_label: label,
-                    address,
+            .map(|(count, latitude, longitude, distance, entity_id, name)| {
+                let single = count == 1;
+                NearbyClusterResponse {
+                    count,
                     latitude,
                     longitude,
                     distance_meters: distance,
-                    distance_display: format_distance(distance),
+                    entity_id: single.then(|| entity_id.to_string()),
+                    name: single.then_some(name),
                 }
             })
             .collect();
 
-            Ok(json_response(
-                200,
-                &ApiResponse {
-                    success: true,
-                    data: Some(serde_json::json!({
-                        "center": {"latitude": lat, "longitude": lon},
-                        "radius_meters": radius,
-                        "count": results.len(),
-                        "results": results,
-                    })),
-                    error: None,
-                },
-            )?)
-        }
+        return Ok(json_response(
+            200,
+            &ApiResponse {
+                success: true,
+                data: Some(serde_json::json!({
+                    "center": {"latitude": lat, "longitude": lon},
+                    "radius_meters": radius,
+                    "cell_degrees": cell_degrees,
+                    "count": clusters.iter().map(|c| c.count).sum::<i64>(),
+                    "clusters": clusters,
+                })),
+                error: None,
+            },
+        )?);
+    }
 
-        // Calculate distance between two points
-        ("GET", "/locations/distance") => {
-            let params = event.query_string_parameters();
-
-            let from_lat: f64 = params.first("from_lat")
-                .and_then(|l| l.parse().ok())
-                .ok_or("from_lat parameter required")?;
-            let from_lon: f64 = params.first("from_lon")
-                .and_then(|l| l.parse().ok())
-                .ok_or("from_lon parameter required")?;
-            let to_lat: f64 = params.first("to_lat")
-                .and_then(|l| l.parse().ok())
-                .ok_or("to_lat parameter required")?;
-            let to_lon: f64 = params.first("to_lon")
-                .and_then(|l| l.parse().ok())
-                .ok_or("to_lon parameter required")?;
-
-            let distance: f64 = sqlx::query_scalar(
-                r#"
-                SELECT ST_Distance(
-                    ST_SetSRID(ST_MakePoint($1, $2), 4326)::geography,
-                    ST_SetSRID(ST_MakePoint($3, $4), 4326)::geography
-                )
-                "#
+    let page_params =
+        match CursorParams::from_query(params.first("limit"), params.first("cursor"), 20) {
+            Ok(p) => p,
+            Err(e) => return error_response(400, e.to_string()),
+        };
+
+    // Nearest first, by location so an entity's other locations aren't skipped
+    let query = format!(
+        r#"
+            SELECT
+                el.id,
+                e.id,
+                e.entity_type::text,
+                e.name,
+                el.label,
+                el.address_raw,
+                ST_Y(el.location::geometry) as lat,
+                ST_X(el.location::geometry) as lon,
+                ST_Distance(el.location, {center}) as distance_meters
+            FROM entities e
+            JOIN entity_locations el ON el.entity_id = e.id
+            {filters}
+            AND {keyset}
+            ORDER BY distance_meters ASC, el.id ASC
+            LIMIT $9
+            "#,
+        center = center,
+        filters = filters,
+        keyset = keyset_predicate(
+            &format!("ST_Distance(el.location, {})", center),
+            "float8",
+            "el.id",
+            7,
+            SortDirection::Asc,
+        ),
+    );
+    let rows: Vec<NearbyRow> = sqlx::query_as(&query)
+        .bind(lon)
+        .bind(lat)
+        .bind(radius)
+        .bind(user_id)
+        .bind(&family_ids)
+        .bind(entity_type)
+        .bind(page_params.after_key())
+        .bind(page_params.after_id())
+        .bind(page_params.fetch_limit())
+        .fetch_all(&state.db_pool)
+        .await?;
+
+    let page = Page::from_rows(rows, &page_params, |row| {
+        Cursor::new(row.8.to_string(), row.0)
+    })
+    .map(
+        |(_, id, entity_type, name, label, address, latitude, longitude, distance)| {
+            NearbyEntityResponse {
+                entity_id: id.to_string(),
+                entity_type,
+                name,
+                location_label: label,
+                address,
+                latitude,
+                longitude,
+                distance_meters: distance,
+                distance_display: format_distance(distance),
+            }
+        },
+    );
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "center": {"latitude": lat, "longitude": lon},
+                "radius_meters": radius,
+                "count": page.items.len(),
+                "results": page.items,
+                "next_cursor": page.next_cursor,
+                "has_more": page.has_more,
+            })),
+            error: None,
+        },
+    )?)
+}
+
+/// GET /locations/distance
+#[utoipa::path(
+    get,
+    path = "/locations/distance",
+    tag = "locations",
+    params(
+        ("from_lat" = f64, Query, description = "Latitude of the first point"),
+        ("from_lon" = f64, Query, description = "Longitude of the first point"),
+        ("to_lat" = f64, Query, description = "Latitude of the second point"),
+        ("to_lon" = f64, Query, description = "Longitude of the second point"),
+    ),
+    responses(
+        (status = 200, description = "Distance between the points in meters, kilometers and miles", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "A missing coordinate", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn distance(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let _user = require_user(&event, &state.db_pool).await?;
+
+    let params = event.query_string_parameters();
+
+    let from_lat: f64 = params.first("from_lat")
+        .and_then(|l| l.parse().ok())
+        .ok_or_else(|| ApiError::validation("from_lat parameter required"))?;
+    let from_lon: f64 = params.first("from_lon")
+        .and_then(|l| l.parse().ok())
+        .ok_or_else(|| ApiError::validation("from_lon parameter required"))?;
+    let to_lat: f64 = params.first("to_lat")
+        .and_then(|l| l.parse().ok())
+        .ok_or_else(|| ApiError::validation("to_lat parameter required"))?;
+    let to_lon: f64 = params.first("to_lon")
+        .and_then(|l| l.parse().ok())
+        .ok_or_else(|| ApiError::validation("to_lon parameter required"))?;
+
+    let distance: f64 = sqlx::query_scalar(
+        r#"
+        SELECT ST_Distance(
+            ST_SetSRID(ST_MakePoint($1, $2), 4326)::geography,
+            ST_SetSRID(ST_MakePoint($3, $4), 4326)::geography
+        )
+        "#
+    )
+    .bind(from_lon)
+    .bind(from_lat)
+    .bind(to_lon)
+    .bind(to_lat)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "distance_meters": distance,
+                "distance_km": distance / 1000.0,
+                "distance_miles": distance / 1609.34,
+                "display": format_distance(distance),
+            })),
+            error: None,
+        },
+    )?)
+}
+
+/// GET /facts/timeline
+#[utoipa::path(
+    get,
+    path = "/facts/timeline",
+    tag = "facts",
+    params(
+        ("as_of" = Option<String>, Query, description = "What was true on this date (`YYYY-MM-DD`)"),
+        ("from" = Option<String>, Query, description = "Facts valid from this date"),
+        ("to" = Option<String>, Query, description = "Facts valid until this date"),
+        ("entity_id" = Option<Uuid>, Query, description = "Only facts about this entity"),
+        ("include_expired" = Option<bool>, Query, description = "Include facts no longer true"),
+        ("limit" = Option<i64>, Query, description = "Most facts to return"),
+    ),
+    responses(
+        (status = 200, description = "Facts in the period, or true on `as_of`, with their attachments", body = ApiResponse<serde_json::Value>),
+    ),
+    security(("cognito" = []))
+)]
+async fn facts_timeline(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+    let family_ids = user.family_ids;
+
+    let params = event.query_string_parameters();
+
+    // Parse optional date filters
+    let as_of = params.first("as_of"); // Point-in-time query
+    let from_date = params.first("from");
+    let to_date = params.first("to");
+    let entity_id = params.first("entity_id");
+    let include_expired = params.first("include_expired")
+        .map(|v| v == "true")
+        .unwrap_or(false);
+    let limit: i64 = params.first("limit")
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(50);
+
+    let mut results: Vec<TimelineFactResponse> = if let Some(point_in_time) = as_of {
+        // Point-in-time query: what was true on this date?
+        let date = chrono::NaiveDate::parse_from_str(point_in_time, "%Y-%m-%d")
+            .map_err(|_| "Invalid as_of date format (use YYYY-MM-DD)")?;
+
+        if let Some(eid) = entity_id {
+            let eid = Uuid::parse_str(eid).map_err(|_| "Invalid entity_id")?;
+            sqlx::query_as::<_, (Uuid, String, i16, chrono::DateTime<chrono::Utc>, Option<chrono::NaiveDate>, Option<chrono::NaiveDate>, Option<String>)>(
+                &format!(r#"
+                SELECT f.id, f.content, f.importance, f.recorded_at, f.valid_from, f.valid_to, e.name
+                FROM facts f
+                LEFT JOIN entities e ON e.id = f.about_entity_id
+                WHERE f.about_entity_id = $1
+                AND f.deleted_at IS NULL
+                AND (f.valid_from IS NULL OR f.valid_from <= $2)
+                AND (f.valid_to IS NULL OR f.valid_to > $2)
+                AND {}
+                ORDER BY f.importance DESC, f.recorded_at DESC
+                LIMIT $5
+                "#, visibility_clause("f", 3))
+            )
+            .bind(eid)
+            .bind(date)
+            .bind(user_id)
+            .bind(&family_ids)
+            .bind(limit)
+            .fetch_all(&state.db_pool)
+            .await
+        } else {
+            sqlx::query_as::<_, (Uuid, String, i16, chrono::DateTime<chrono::Utc>, Option<chrono::NaiveDate>, Option<chrono::NaiveDate>, Option<String>)>(
+                &format!(r#"
+                SELECT f.id, f.content, f.importance, f.recorded_at, f.valid_from, f.valid_to, e.name
+                FROM facts f
+                LEFT JOIN entities e ON e.id = f.about_entity_id
+                WHERE f.deleted_at IS NULL
+                AND (f.valid_from IS NULL OR f.valid_from <= $1)
+                AND (f.valid_to IS NULL OR f.valid_to > $1)
+                AND {}
+                ORDER BY f.importance DESC, f.recorded_at DESC
+                LIMIT $4
+                "#, visibility_clause("f", 2))
             )
-            .bind(from_lon)
-            .bind(from_lat)
-            .bind(to_lon)
-            .bind(to_lat)
-            .fetch_one(&state.db_pool)
+            .bind(date)
+            .bind(user_id)
+            .bind(&family_ids)
+            .bind(limit)
+            .fetch_all(&state.db_pool)
             .await
-            .map_err(|e| format!("Failed to calculate distance: {}", e))?;
+        }
+    } else if from_date.is_some() || to_date.is_some() {
+        // Date range query
+        let from = from_date
+            .map(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d"))
+            .transpose()
+            .map_err(|_| "Invalid from date format")?;
+        let to = to_date
+            .map(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d"))
+            .transpose()
+            .map_err(|_| "Invalid to date format")?;
 
-            Ok(json_response(
-                200,
-                &ApiResponse {
-                    success: true,
-                    data: Some(serde_json::json!({
-                        "distance_meters": distance,
-                        "distance_km": distance / 1000.0,
-                        "distance_miles": distance / 1609.34,
-                        "display": format_distance(distance),
-                    })),
-                    error: None,
-                },
-            )?)
+        sqlx::query_as::<_, (Uuid, String, i16, chrono::DateTime<chrono::Utc>, Option<chrono::NaiveDate>, Option<chrono::NaiveDate>, Option<String>)>(
+            &format!(r#"
+            SELECT f.id, f.content, f.importance, f.recorded_at, f.valid_from, f.valid_to, e.name
+            FROM facts f
+            LEFT JOIN entities e ON e.id = f.about_entity_id
+            WHERE f.deleted_at IS NULL
+            AND {}
+            AND ($3::date IS NULL OR f.valid_from >= $3 OR f.recorded_at::date >= $3)
+            AND ($4::date IS NULL OR f.valid_from <= $4 OR f.recorded_at::date <= $4)
+            ORDER BY COALESCE(f.valid_from, f.recorded_at::date) DESC, f.importance DESC
+            LIMIT $5
+            "#, visibility_clause("f", 1))
+        )
+        .bind(user_id)
+        .bind(&family_ids)
+        .bind(from)
+        .bind(to)
+        .bind(limit)
+        .fetch_all(&state.db_pool)
+        .await
+    } else {
+        // Default: recent facts timeline
+        let validity_filter = if include_expired {
+            "1=1"
+        } else {
+            "(f.valid_to IS NULL OR f.valid_to > CURRENT_DATE)"
+        };
+
+        sqlx::query_as::<_, (Uuid, String, i16, chrono::DateTime<chrono::Utc>, Option<chrono::NaiveDate>, Option<chrono::NaiveDate>, Option<String>)>(
+            &format!(r#"
+            SELECT f.id, f.content, f.importance, f.recorded_at, f.valid_from, f.valid_to, e.name
+            FROM facts f
+            LEFT JOIN entities e ON e.id = f.about_entity_id
+            WHERE f.deleted_at IS NULL
+            AND {}
+            AND {}
+            ORDER BY f.recorded_at DESC, f.importance DESC
+            LIMIT $3
+            "#, visibility_clause("f", 1), validity_filter)
+        )
+        .bind(user_id)
+        .bind(&family_ids)
+        .bind(limit)
+        .fetch_all(&state.db_pool)
+        .await
+    }?
+    .into_iter()
+    .map(|(id, content, importance, recorded_at, valid_from, valid_to, entity_name)| {
+        let today = chrono::Utc::now().date_naive();
+        let is_current = valid_to.map(|d| d > today).unwrap_or(true);
+
+        TimelineFactResponse {
+            id: id.to_string(),
+            content,
+            importance,
+            recorded_at: recorded_at.to_rfc3339(),
+            valid_from: valid_from.map(|d| d.to_string()),
+            valid_to: valid_to.map(|d| d.to_string()),
+            entity_name,
+            is_current,
+            attachments: Vec::new(),
         }
+    })
+    .collect();
 
-        // Temporal timeline query
-        ("GET", "/facts/timeline") => {
-            let params = event.query_string_parameters();
-
-            // Parse optional date filters
-            let as_of = params.first("as_of"); // Point-in-time query
-            let from_date = params.first("from");
-            let to_date = params.first("to");
-            let entity_id = params.first("entity_id");
-            let include_expired = params.first("include_expired")
-                .map(|v| v == "true")
-                .unwrap_or(false);
-            let limit: i64 = params.first("limit")
-                .and_then(|l| l.parse().ok())
-                .unwrap_or(50);
-
-            let results: Vec<TimelineFactResponse> = if let Some(point_in_time) = as_of {
-                // Point-in-time query: what was true on this date?
-                let date = chrono::NaiveDate::parse_from_str(point_in_time, "%Y-%m-%d")
-                    .map_err(|_| "Invalid as_of date format (use YYYY-MM-DD)")?;
-
-                if let Some(eid) = entity_id {
-                    let eid = Uuid::parse_str(eid).map_err(|_| "Invalid entity_id")?;
-                    sqlx::query_as::<_, (Uuid, String, i16, chrono::DateTime<chrono::Utc>, Option<chrono::NaiveDate>, Option<chrono::NaiveDate>, Option<String>)>(
-                        r#"
-                        SELECT f.id, f.content, f.importance, f.recorded_at, f.valid_from, f.valid_to, e.name
-                        FROM facts f
-                        LEFT JOIN entities e ON e.id = f.about_entity_id
-                        WHERE f.about_entity_id = $1
-                        AND (f.valid_from IS NULL OR f.valid_from <= $2)
-                        AND (f.valid_to IS NULL OR f.valid_to > $2)
-                        AND (
-                            (f.owner_type = 'user' AND f.owner_id = $3)
-                            OR (f.owner_type = 'family' AND f.owner_id = ANY($4))
-                        )
-                        ORDER BY f.importance DESC, f.recorded_at DESC
-                        LIMIT $5
-                        "#
-                    )
-                    .bind(eid)
-                    .bind(date)
-                    .bind(user_id)
-                    .bind(&family_ids)
-                    .bind(limit)
-                    .fetch_all(&state.db_pool)
-                    .await
-                } else {
-                    sqlx::query_as::<_, (Uuid, String, i16, chrono::DateTime<chrono::Utc>, Option<chrono::NaiveDate>, Option<chrono::NaiveDate>, Option<String>)>(
-                        r#"
-                        SELECT f.id, f.content, f.importance, f.recorded_at, f.valid_from, f.valid_to, e.name
-                        FROM facts f
-                        LEFT JOIN entities e ON e.id = f.about_entity_id
-                        WHERE (f.valid_from IS NULL OR f.valid_from <= $1)
-                        AND (f.valid_to IS NULL OR f.valid_to > $1)
-                        AND (
-                            (f.owner_type = 'user' AND f.owner_id = $2)
-                            OR (f.owner_type = 'family' AND f.owner_id = ANY($3))
-                        )
-                        ORDER BY f.importance DESC, f.recorded_at DESC
-                        LIMIT $4
-                        "#
-                    )
-                    .bind(date)
-                    .bind(user_id)
-                    .bind(&family_ids)
-                    .bind(limit)
-                    .fetch_all(&state.db_pool)
-                    .await
-                }
-            } else if from_date.is_some() || to_date.is_some() {
-                // Date range query
-                let from = from_date
-                    .map(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d"))
-                    .transpose()
-                    .map_err(|_| "Invalid from date format")?;
-                let to = to_date
-                    .map(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d"))
-                    .transpose()
-                    .map_err(|_| "Invalid to date format")?;
-
-                sqlx::query_as::<_, (Uuid, String, i16, chrono::DateTime<chrono::Utc>, Option<chrono::NaiveDate>, Option<chrono::NaiveDate>, Option<String>)>(
-                    r#"
-                    SELECT f.id, f.content, f.importance, f.recorded_at, f.valid_from, f.valid_to, e.name
-                    FROM facts f
-                    LEFT JOIN entities e ON e.id = f.about_entity_id
-                    WHERE (
-                        (f.owner_type = 'user' AND f.owner_id = $1)
-                        OR (f.owner_type = 'family' AND f.owner_id = ANY($2))
-                    )
-                    AND ($3::date IS NULL OR f.valid_from >= $3 OR f.recorded_at::date >= $3)
-                    AND ($4::date IS NULL OR f.valid_from <= $4 OR f.recorded_at::date <= $4)
-                    ORDER BY COALESCE(f.valid_from, f.recorded_at::date) DESC, f.importance DESC
-                    LIMIT $5
-                    "#
-                )
-                .bind(user_id)
-                .bind(&family_ids)
-                .bind(from)
-                .bind(to)
-                .bind(limit)
-                .fetch_all(&state.db_pool)
-                .await
-            } else {
-                // Default: recent facts timeline
-                let validity_filter = if include_expired {
-                    "1=1"
-                } else {
-                    "(f.valid_to IS NULL OR f.valid_to > CURRENT_DATE)"
-                };
-
-                sqlx::query_as::<_, (Uuid, String, i16, chrono::DateTime<chrono::Utc>, Option<chrono::NaiveDate>, Option<chrono::NaiveDate>, Option<String>)>(
-                    &format!(r#"
-                    SELECT f.id, f.content, f.importance, f.recorded_at, f.valid_from, f.valid_to, e.name
-                    FROM facts f
-                    LEFT JOIN entities e ON e.id = f.about_entity_id
-                    WHERE (
-                        (f.owner_type = 'user' AND f.owner_id = $1)
-                        OR (f.owner_type = 'family' AND f.owner_id = ANY($2))
-                    )
-                    AND {}
-                    ORDER BY f.recorded_at DESC, f.importance DESC
-                    LIMIT $3
-                    "#, validity_filter)
+    let fact_ids: Vec<Uuid> = results.iter().filter_map(|f| f.id.parse().ok()).collect();
+    let mut attachments = attachments_by_fact(&state, &fact_ids).await?;
+    for fact in &mut results {
+        if let Some(files) = fact.id.parse::<Uuid>().ok().and_then(|id| attachments.remove(&id)) {
+            fact.attachments = files;
+        }
+    }
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "count": results.len(),
+                "as_of": as_of,
+                "include_expired": include_expired,
+                "facts": results,
+            })),
+            error: None,
+        },
+    )?)
+}
+
+/// GET /facts/nearby
+#[utoipa::path(
+    get,
+    path = "/facts/nearby",
+    tag = "facts",
+    params(
+        ("lat" = f64, Query, description = "Latitude of the center"),
+        ("lon" = f64, Query, description = "Longitude of the center (`lng` also works)"),
+        ("radius" = Option<f64>, Query, description = "Search radius in meters (default 1000)"),
+        ("limit" = Option<i64>, Query, description = "Most facts to return"),
+    ),
+    responses(
+        (status = 200, description = "Facts about places near the point, nearest first", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Missing coordinates or a radius out of range", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn nearby_facts(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+    let family_ids = user.family_ids;
+
+    let params = event.query_string_parameters();
+
+    let lat = params.first("lat").and_then(|l| l.parse::<f64>().ok());
+    let lon = params.first("lon")
+        .or_else(|| params.first("lng"))
+        .and_then(|l| l.parse::<f64>().ok());
+    let (lat, lon) = match (lat, lon) {
+        (Some(lat), Some(lon)) if (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon) => {
+            (lat, lon)
+        }
+        _ => return error_response(400, "lat and lon are required"),
+    };
+    let radius: f64 = params.first("radius")
+        .and_then(|r| r.parse().ok())
+        .unwrap_or(1000.0);
+    if !(radius > 0.0 && radius <= NEARBY_FACTS_MAX_RADIUS) {
+        return error_response(
+            400,
+            format!("radius must be between 0 and {} meters", NEARBY_FACTS_MAX_RADIUS),
+        );
+    }
+    let limit: i64 = params.first("limit")
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(20)
+        .clamp(1, NEARBY_FACTS_MAX_LIMIT);
+
+    // Each fact is listed once, at its nearest entity
+    let mut results: Vec<NearbyFactResponse> = sqlx::query_as::<_, (Uuid, String, i16, chrono::DateTime<chrono::Utc>, Option<chrono::NaiveDate>, Option<chrono::NaiveDate>, Uuid, String, String, f64)>(
+        &format!(r#"
+        WITH places AS (
+            SELECT e.id AS entity_id, e.name, el.label,
+                   ST_Distance(
+                       el.location,
+                       ST_SetSRID(ST_MakePoint($1, $2), 4326)::geography
+                   ) AS distance_meters
+            FROM entities e
+            JOIN entity_locations el ON el.entity_id = e.id
+            WHERE ST_DWithin(
+                el.location,
+                ST_SetSRID(ST_MakePoint($1, $2), 4326)::geography,
+                $3
+            )
+            AND (el.valid_to IS NULL OR el.valid_to > CURRENT_DATE)
+            AND e.deleted_at IS NULL
+            AND {}
+        ),
+        nearest AS (
+            SELECT DISTINCT ON (f.id)
+                f.id, f.content, f.importance, f.recorded_at, f.valid_from, f.valid_to,
+                p.entity_id, p.name, p.label, p.distance_meters
+            FROM places p
+            JOIN facts f ON f.about_entity_id = p.entity_id
+                OR EXISTS (
+                    SELECT 1 FROM entity_mentions em
+                    WHERE em.fact_id = f.id AND em.entity_id = p.entity_id
                 )
-                .bind(user_id)
-                .bind(&family_ids)
-                .bind(limit)
-                .fetch_all(&state.db_pool)
-                .await
-            }
-            .map_err(|e| format!("Failed to fetch timeline: {}", e))?
-            .into_iter()
-            .map(|(id, content, importance, recorded_at, valid_from, valid_to, entity_name)| {
-                let today = chrono::Utc::now().date_naive();
-                let is_current = valid_to.map(|d| d > today).unwrap_or(true);
-
-                TimelineFactResponse {
-                    id: id.to_string(),
-                    content,
-                    importance,
-                    recorded_at: recorded_at.to_rfc3339(),
-                    valid_from: valid_from.map(|d| d.to_string()),
-                    valid_to: valid_to.map(|d| d.to_string()),
-                    entity_name,
-                    is_current,
+            WHERE f.deleted_at IS NULL
+            AND {}
+            ORDER BY f.id, p.distance_meters
+        )
+        SELECT * FROM nearest
+        ORDER BY distance_meters ASC, recorded_at DESC
+        LIMIT $6
+        "#, visibility_clause("e", 4), visibility_clause("f", 4))
+    )
+    .bind(lon)
+    .bind(lat)
+    .bind(radius)
+    .bind(user_id)
+    .bind(&family_ids)
+    .bind(limit)
+    .fetch_all(&state.db_pool)
+    .await?
+    .into_iter()
+    .map(|(id, content, importance, recorded_at, valid_from, valid_to, entity_id, entity_name, label, distance)| {
+        NearbyFactResponse {
+            id: id.to_string(),
+            content,
+            importance,
+            recorded_at: recorded_at.to_rfc3339(),
+            valid_from: valid_from.map(|d| d.to_string()),
+            valid_to: valid_to.map(|d| d.to_string()),
+            entity_id: entity_id.to_string(),
+            entity_name,
+            location_label: label,
+            distance_meters: distance,
+            distance_display: format_distance(distance),
+            attachments: Vec::new(),
+        }
+    })
+    .collect();
+
+    let fact_ids: Vec<Uuid> = results.iter().filter_map(|f| f.id.parse().ok()).collect();
+    let mut attachments = attachments_by_fact(&state, &fact_ids).await?;
+    for fact in &mut results {
+        if let Some(files) = fact.id.parse::<Uuid>().ok().and_then(|id| attachments.remove(&id)) {
+            fact.attachments = files;
+        }
+    }
+
+    info!("Nearby facts returned {} results", results.len());
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "center": {"latitude": lat, "longitude": lon},
+                "radius_meters": radius,
+                "count": results.len(),
+                "facts": results,
+            })),
+            error: None,
+        },
+    )?)
+}
+
+/// GET /facts/search, GET /v1/facts/search
+#[utoipa::path(
+    get,
+    path = "/facts/search",
+    tag = "facts",
+    params(
+        ("q" = String, Query, description = "Search text"),
+        ("tags" = Option<String>, Query, description = "Comma-separated tags the facts must have"),
+        ("entity_ids" = Option<String>, Query, description = "Comma-separated entities the facts must be about"),
+        ("from" = Option<String>, Query, description = "Facts recorded on or after this date"),
+        ("to" = Option<String>, Query, description = "Facts recorded on or before this date"),
+        ("limit" = Option<i64>, Query, description = "Most facts to return"),
+        ("offset" = Option<i64>, Query, description = "Facts to skip"),
+        ("search_id" = Option<Uuid>, Query, description = "`search_id` of the first page"),
+    ),
+    responses(
+        (status = 200, description = "Matching facts, best first, with the `search_id` to page with", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Missing `q` or invalid filters", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn search(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+    let family_ids = user.family_ids;
+
+    let params = event.query_string_parameters();
+
+    let query = params.first("q").map(str::trim).unwrap_or_default();
+    if query.is_empty() {
+        return error_response(400, "q is required");
+    }
+
+    let filters = match SearchFilters::parse(
+        params.first("tags"),
+        params.first("entity_ids"),
+        params.first("from"),
+        params.first("to"),
+    ) {
+        Ok(filters) => filters,
+        Err(e) => {
+            return error_response(400, e.to_string());
+        }
+    };
+
+    let limit: i64 = params.first("limit")
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(DEFAULT_LIMIT)
+        .clamp(1, MAX_LIMIT);
+    let offset: i64 = params.first("offset")
+        .and_then(|o| o.parse().ok())
+        .unwrap_or(0)
+        .max(0);
+
+    let (mut hits, has_more) = search_facts(
+        &state.db_pool,
+        query,
+        user_id,
+        &family_ids,
+        &filters,
+        limit,
+        offset,
+    )
+    .await?;
+
+    let fact_ids: Vec<Uuid> = hits.iter().map(|h| h.id).collect();
+    let mut attachments = attachments_by_fact(&state, &fact_ids).await?;
+    for hit in &mut hits {
+        hit.attachments = attachments.remove(&hit.id).unwrap_or_default();
+    }
+
+    // Log what was returned so clicks on the results can be tied back
+    // to this search; clients page with the same search_id
+    let search_id = params.first("search_id")
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .unwrap_or_else(Uuid::new_v4);
+    let mut returned = Interactions::new(user_id, "api");
+    returned.returned(search_id, &fact_ids, offset as i32);
+    returned.record(&state.db_pool).await;
+
+    info!("Fact search returned {} results", hits.len());
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "query": query,
+                "search_id": search_id,
+                "count": hits.len(),
+                "has_more": has_more,
+                "next_offset": if has_more { Some(offset + hits.len() as i64) } else { None },
+                "facts": hits,
+            })),
+            error: None,
+        },
+    )?)
+}
+
+/// GET /facts/review
+#[utoipa::path(
+    get,
+    path = "/facts/review",
+    tag = "facts",
+    params(
+        ("limit" = Option<i64>, Query, description = "Most facts to return"),
+    ),
+    responses(
+        (status = 200, description = "Facts due for review, most overdue first", body = ApiResponse<serde_json::Value>),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_review_queue(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+    let family_ids = user.family_ids;
+
+    let limit: i64 = event.query_string_parameters().first("limit")
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(shared::fact_review::DEFAULT_LIMIT)
+        .clamp(1, shared::fact_review::MAX_LIMIT);
+
+    let facts = review_queue(&state.db_pool, user_id, &family_ids, limit).await?;
+
+    info!("Review queue has {} facts due", facts.len());
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "count": facts.len(),
+                "facts": facts,
+            })),
+            error: None,
+        },
+    )?)
+}
+
+/// POST /facts/{id}/review
+#[utoipa::path(
+    post,
+    path = "/facts/{id}/review",
+    tag = "facts",
+    request_body = FactReviewRequest,
+    params(
+        ("id" = Uuid, Path, description = "Fact ID"),
+    ),
+    responses(
+        (status = 200, description = "What the review did", body = ApiResponse<ReviewOutcome>),
+        (status = 400, description = "Unknown action or invalid dates", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Fact not found or no longer current", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn review(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+    let family_ids = user.family_ids;
+
+    let fact_id = params.id("id", "Invalid fact ID")?;
+
+    let request: FactReviewRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let before = audit::snapshot(&state.db_pool, AuditResource::Fact, fact_id).await;
+
+    let reviewed = match ReviewAction::parse(
+        &request.action,
+        request.content.as_deref(),
+        request.valid_from.as_deref(),
+        request.valid_to.as_deref(),
+    ) {
+        Ok(action) => review_fact(&state.db_pool, user_id, &family_ids, fact_id, &action).await,
+        Err(e) => Err(e),
+    };
+
+    match reviewed {
+        Ok(Some(outcome)) => {
+            audit::record_change(&state.db_pool, user_id, AuditAction::Update, AuditResource::Fact, fact_id, before).await;
+            if let Some(replacement_id) = outcome.replaced_by {
+                audit::record_change(&state.db_pool, user_id, AuditAction::Create, AuditResource::Fact, replacement_id, None).await;
+                if let Some(publisher) = &state.event_publisher {
+                    publisher.publish_facts_created(&state.db_pool, &[replacement_id], "review").await;
                 }
-            })
-            .collect();
+            }
+
+            info!("Reviewed fact {}: {}", fact_id, outcome.action);
 
             Ok(json_response(
                 200,
                 &ApiResponse {
                     success: true,
-                    data: Some(serde_json::json!({
-                        "count": results.len(),
-                        "as_of": as_of,
-                        "include_expired": include_expired,
-                        "facts": results,
-                    })),
+                    data: Some(outcome),
                     error: None,
                 },
             )?)
         }
+        Ok(None) => error_response(404, "Fact not found or no longer current"),
+        Err(shared::Error::Validation(e)) => error_response(400, e),
+        Err(e) => Err(e.into()),
+    }
+}
 
-        // Entity location routes
-        _ if path.starts_with("/entities/") && path.contains("/locations") => {
-            let path_parts: Vec<&str> = path
-                .trim_start_matches("/entities/")
-                .split('/')
-                .collect();
+/// GET /facts/{id}/attachments
+#[utoipa::path(
+    get,
+    path = "/facts/{id}/attachments",
+    tag = "facts",
+    params(
+        ("id" = Uuid, Path, description = "Fact ID"),
+    ),
+    responses(
+        (status = 200, description = "The fact's attachments with download URLs", body = ApiResponse<serde_json::Value>),
+        (status = 404, description = "Fact not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_fact_attachments(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+    let family_ids = user.family_ids;
 
-            let entity_id = Uuid::parse_str(path_parts[0])
-                .map_err(|_| "Invalid entity ID")?;
+    let fact_id = params.id("id", "Invalid fact ID")?;
+    if !can_access_fact(&state.db_pool, fact_id, user_id, &family_ids).await? {
+        return error_response(404, "Fact not found");
+    }
 
-            // Verify access to entity
-            let has_access: bool = sqlx::query_scalar(
-                r#"
-                SELECT EXISTS(
-                    SELECT 1 FROM entities e
-                    LEFT JOIN family_members fm ON e.owner_type = 'family' AND e.owner_id = fm.family_id AND fm.user_id = $2
-                    WHERE e.id = $1
-                    AND (
-                        (e.owner_type = 'user' AND e.owner_id = $2)
-                        OR (e.owner_type = 'family' AND fm.user_id IS NOT NULL)
-                    )
-                )
-                "#
-            )
-            .bind(entity_id)
-            .bind(user_id)
-            .fetch_one(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to verify access: {}", e))?;
-
-            if !has_access {
-                return Ok(json_response(
-                    404,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Entity not found".to_string()),
-                    },
-                )?);
-            }
+    let attachments = attachments_by_fact(&state, &[fact_id])
+        .await?
+        .remove(&fact_id)
+        .unwrap_or_default();
 
-            match method {
-                // Get entity locations
-                "GET" => {
-                    let locations: Vec<LocationResponse> = sqlx::query_as::<_, (Uuid, String, Option<String>, Option<f64>, Option<f64>, Option<chrono::NaiveDate>, Option<chrono::NaiveDate>)>(
-                        r#"
-                        SELECT id, label, address_raw,
-                               ST_Y(location::geometry), ST_X(location::geometry),
-                               valid_from, valid_to
-                        FROM entity_locations
-                        WHERE entity_id = $1
-                        ORDER BY label
-                        "#
-                    )
-                    .bind(entity_id)
-                    .fetch_all(&state.db_pool)
-                    .await
-                    .map_err(|e| format!("Failed to fetch locations: {}", e))?
-                    .into_iter()
-                    .map(|(id, label, address, lat, lon, valid_from, valid_to)| {
-                        LocationResponse {
-                            id: id.to_string(),
-                            label,
-                            address,
-                            latitude: lat,
-                            longitude: lon,
-                            valid_from: valid_from.map(|d| d.to_string()),
-                            valid_to: valid_to.map(|d| d.to_string()),
-                        }
-                    })
-                    .collect();
-
-                    Ok(json_response(
-                        200,
-                        &ApiResponse {
-                            success: true,
-                            data: Some(locations),
-                            error: None,
-                        },
-                    )?)
-                }
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "count": attachments.len(),
+                "attachments": attachments,
+            })),
+            error: None,
+        },
+    )?)
+}
 
-                // Add location to entity
-                "POST" => {
-                    let request: StoreLocationRequest = match shared::parse_json_body(event.body())? {
-                        Ok(r) => r,
-                        Err(response) => return Ok(response),
-                    };
-
-                    let location_id = Uuid::new_v4();
-                    let visibility = request.visibility_tier.unwrap_or(3);
-
-                    let valid_from = request.valid_from
-                        .map(|d| chrono::NaiveDate::parse_from_str(&d, "%Y-%m-%d"))
-                        .transpose()
-                        .map_err(|_| "Invalid valid_from date")?;
-                    let valid_to = request.valid_to
-                        .map(|d| chrono::NaiveDate::parse_from_str(&d, "%Y-%m-%d"))
-                        .transpose()
-                        .map_err(|_| "Invalid valid_to date")?;
-
-                    if let (Some(lat), Some(lon)) = (request.latitude, request.longitude) {
-                        sqlx::query(
-                            r#"
-                            INSERT INTO entity_locations (id, entity_id, label, address_raw, location,
-                                                         valid_from, valid_to, visibility_tier)
-                            VALUES ($1, $2, $3, $4, ST_SetSRID(ST_MakePoint($5, $6), 4326)::geography,
-                                    $7, $8, $9)
-                            ON CONFLICT (entity_id, label) WHERE valid_to IS NULL DO UPDATE SET
-                                address_raw = EXCLUDED.address_raw,
-                                location = EXCLUDED.location,
-                                valid_from = EXCLUDED.valid_from,
-                                valid_to = EXCLUDED.valid_to,
-                                updated_at = NOW()
-                            "#
-                        )
-                        .bind(location_id)
-                        .bind(entity_id)
-                        .bind(&request.label)
-                        .bind(&request.address)
-                        .bind(lon)
-                        .bind(lat)
-                        .bind(valid_from)
-                        .bind(valid_to)
-                        .bind(visibility)
-                        .execute(&state.db_pool)
-                        .await
-                        .map_err(|e| format!("Failed to store location: {}", e))?;
-
-                        info!("Stored location {} for entity {}", request.label, entity_id);
-
-                        Ok(json_response(
-                            201,
-                            &ApiResponse {
-                                success: true,
-                                data: Some(serde_json::json!({
-                                    "location_id": location_id.to_string(),
-                                    "entity_id": entity_id.to_string(),
-                                    "label": request.label,
-                                    "latitude": lat,
-                                    "longitude": lon,
-                                })),
-                                error: None,
-                            },
-                        )?)
-                    } else {
-                        // Store without coordinates (address only)
-                        sqlx::query(
-                            r#"
-                            INSERT INTO entity_locations (id, entity_id, label, address_raw,
-                                                         valid_from, valid_to, visibility_tier)
-                            VALUES ($1, $2, $3, $4, $5, $6, $7)
-                            ON CONFLICT (entity_id, label) WHERE valid_to IS NULL DO UPDATE SET
-                                address_raw = EXCLUDED.address_raw,
-                                valid_from = EXCLUDED.valid_from,
-                                valid_to = EXCLUDED.valid_to,
-                                updated_at = NOW()
-                            "#
-                        )
-                        .bind(location_id)
-                        .bind(entity_id)
-                        .bind(&request.label)
-                        .bind(&request.address)
-                        .bind(valid_from)
-                        .bind(valid_to)
-                        .bind(visibility)
-                        .execute(&state.db_pool)
-                        .await
-                        .map_err(|e| format!("Failed to store location: {}", e))?;
-
-                        Ok(json_response(
-                            201,
-                            &ApiResponse {
-                                success: true,
-                                data: Some(serde_json::json!({
-                                    "location_id": location_id.to_string(),
-                                    "entity_id": entity_id.to_string(),
-                                    "label": request.label,
-                                    "note": "Location stored without coordinates. Use geocoding to add coordinates."
-                                })),
-                                error: None,
-                            },
-                        )?)
-                    }
-                }
+/// POST /facts/{id}/attachments
+#[utoipa::path(
+    post,
+    path = "/facts/{id}/attachments",
+    tag = "facts",
+    request_body = AttachmentUploadRequest,
+    params(
+        ("id" = Uuid, Path, description = "Fact ID"),
+    ),
+    responses(
+        (status = 201, description = "The attachment and a presigned URL to PUT the file to", body = ApiResponse<AttachmentUploadResponse>),
+        (status = 400, description = "Unsupported content type or size", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Fact not found", body = ApiError, content_type = "application/problem+json"),
+        (status = 409, description = "The fact has the most attachments allowed", body = ApiError, content_type = "application/problem+json"),
+        (status = 503, description = "Attachment uploads are not configured", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn upload_attachment(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+    let family_ids = user.family_ids;
 
-                _ => Ok(json_response(
-                    405,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Method not allowed".to_string()),
-                    },
-                )?),
-            }
+    let fact_id = params.id("id", "Invalid fact ID")?;
+    if !can_access_fact(&state.db_pool, fact_id, user_id, &family_ids).await? {
+        return error_response(404, "Fact not found");
+    }
+
+    let bucket = match state.attachments_bucket.as_deref() {
+        Some(bucket) => bucket,
+        None => {
+            return error_response(
+                503,
+                "Attachment uploads are not configured",
+            );
         }
+    };
 
-        _ => Ok(json_response(
-            404,
-            &ApiResponse::<()> {
-                success: false,
-                data: None,
-                error: Some("Not found".to_string()),
+    let request: AttachmentUploadRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let file_type = match attachment_type(&request.content_type) {
+        Some(t) => t,
+        None => {
+            return error_response(
+                400,
+                format!(
+                    "content_type must be one of: {}",
+                    accepted_types()
+                ),
+            );
+        }
+    };
+
+    if request.content_length <= 0 || request.content_length > MAX_ATTACHMENT_BYTES {
+        return error_response(
+            400,
+            format!(
+                "content_length must be between 1 and {} bytes",
+                MAX_ATTACHMENT_BYTES
+            ),
+        );
+    }
+
+    let existing = count_attachments(&state.db_pool, fact_id).await?;
+    if existing >= MAX_ATTACHMENTS_PER_FACT {
+        return error_response(
+            409,
+            format!(
+                "A fact can have at most {} attachments",
+                MAX_ATTACHMENTS_PER_FACT
+            ),
+        );
+    }
+
+    let file_name = clean_file_name(request.file_name.as_deref(), file_type.extension);
+    let attachment = insert_attachment(
+        &state.db_pool,
+        fact_id,
+        user_id,
+        &file_name,
+        file_type,
+        request.content_length,
+    )
+    .await?;
+
+    let presigning = aws_sdk_s3::presigning::PresigningConfig::expires_in(
+        std::time::Duration::from_secs(UPLOAD_URL_TTL_SECS),
+    )
+    .map_err(|e| format!("Invalid presigning config: {}", e))?;
+
+    let upload = state
+        .s3_client
+        .put_object()
+        .bucket(bucket)
+        .key(&attachment.object_key)
+        .content_type(file_type.content_type)
+        .content_length(request.content_length)
+        .presigned(presigning)
+        .await
+        .map_err(|e| format!("Failed to presign upload: {}", e))?;
+
+    info!("Issued attachment upload {} for fact {}", attachment.object_key, fact_id);
+
+    Ok(json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(AttachmentUploadResponse {
+                attachment,
+                upload_url: upload.uri().to_string(),
+                expires_in: UPLOAD_URL_TTL_SECS,
+            }),
+            error: None,
+        },
+    )?)
+}
+
+/// DELETE /facts/{id}/attachments/{attachmentId}
+#[utoipa::path(
+    delete,
+    path = "/facts/{id}/attachments/{attachmentId}",
+    tag = "facts",
+    params(
+        ("id" = Uuid, Path, description = "Fact ID"),
+        ("attachmentId" = Uuid, Path, description = "Attachment ID"),
+    ),
+    responses(
+        (status = 200, description = "Attachment deleted", body = ApiResponse<serde_json::Value>),
+        (status = 404, description = "Fact or attachment not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn remove_attachment(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+    let family_ids = user.family_ids;
+
+    let fact_id = params.id("id", "Invalid fact ID")?;
+    if !can_access_fact(&state.db_pool, fact_id, user_id, &family_ids).await? {
+        return error_response(404, "Fact not found");
+    }
+
+    let attachment_id = params.id("attachmentId", "Invalid attachment ID")?;
+
+    let key = delete_attachment(&state.db_pool, fact_id, attachment_id).await?;
+
+    let Some(key) = key else {
+        return error_response(404, "Attachment not found");
+    };
+
+    // The row is gone either way; a leftover object is only storage
+    if let Some(bucket) = state.attachments_bucket.as_deref() {
+        if let Err(e) = state.s3_client.delete_object().bucket(bucket).key(&key).send().await {
+            warn!("Failed to delete attachment object {}: {}", key, e);
+        }
+    }
+
+    info!("Deleted attachment {} from fact {}", attachment_id, fact_id);
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "message": "Attachment deleted",
+                "attachment_id": attachment_id.to_string(),
+            })),
+            error: None,
+        },
+    )?)
+}
+
+/// GET /entities/{id}/locations/map
+#[utoipa::path(
+    get,
+    path = "/entities/{id}/locations/map",
+    tag = "locations",
+    params(
+        ("id" = Uuid, Path, description = "Entity ID"),
+        ("width" = Option<u32>, Query, description = "Image width in pixels"),
+        ("height" = Option<u32>, Query, description = "Image height in pixels"),
+    ),
+    responses(
+        (status = 200, description = "A presigned map image of the entity's current locations", body = ApiResponse<StaticMap>),
+        (status = 400, description = "Map size out of range", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Entity not found or has no locations with coordinates", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn location_map(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let entity_id = params.id("id", "Invalid entity ID")?;
+    if !can_access_entity(&state.db_pool, entity_id, user_id).await? {
+        return error_response(404, "Entity not found");
+    }
+
+    let params = event.query_string_parameters();
+    let options = state.map_options.with_size(
+        params.first("width").and_then(|w| w.parse().ok()),
+        params.first("height").and_then(|h| h.parse().ok()),
+    );
+
+    let points: Vec<MapPoint> = sqlx::query_as::<_, (String, f64, f64)>(
+        r#"
+        SELECT label, ST_Y(location::geometry), ST_X(location::geometry)
+        FROM entity_locations
+        WHERE entity_id = $1
+          AND location IS NOT NULL
+          AND (valid_to IS NULL OR valid_to > CURRENT_DATE)
+        ORDER BY label
+        LIMIT $2
+        "#,
+    )
+    .bind(entity_id)
+    .bind(geo::MAX_MAP_POINTS as i64)
+    .fetch_all(&state.db_pool)
+    .await?
+    .into_iter()
+    .map(|(label, latitude, longitude)| MapPoint {
+        latitude,
+        longitude,
+        label: Some(label),
+    })
+    .collect();
+
+    if points.is_empty() {
+        return error_response(404, "Entity has no locations with coordinates");
+    }
+
+    let map = match geo::static_map(&state.aws_config, &points, &options).await {
+        Ok(map) => map,
+        Err(shared::Error::Validation(e)) => return error_response(400, e),
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(map),
+            error: None,
+        },
+    )?)
+}
+
+/// GET /entities/{id}/locations
+#[utoipa::path(
+    get,
+    path = "/entities/{id}/locations",
+    tag = "locations",
+    params(
+        ("id" = Uuid, Path, description = "Entity ID"),
+    ),
+    responses(
+        (status = 200, description = "The entity's locations by label", body = ApiResponse<Vec<LocationResponse>>),
+        (status = 404, description = "Entity not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_locations(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let entity_id = params.id("id", "Invalid entity ID")?;
+    if !can_access_entity(&state.db_pool, entity_id, user_id).await? {
+        return error_response(404, "Entity not found");
+    }
+
+    let locations: Vec<LocationResponse> = sqlx::query_as::<_, (Uuid, String, Option<String>, Option<f64>, Option<f64>, Option<chrono::NaiveDate>, Option<chrono::NaiveDate>)>(
+        r#"
+        SELECT id, label, address_raw,
+               ST_Y(location::geometry), ST_X(location::geometry),
+               valid_from, valid_to
+        FROM entity_locations
+        WHERE entity_id = $1
+        ORDER BY label
+        "#
+    )
+    .bind(entity_id)
+    .fetch_all(&state.db_pool)
+    .await?
+    .into_iter()
+    .map(|(id, label, address, lat, lon, valid_from, valid_to)| {
+        LocationResponse {
+            id: id.to_string(),
+            label,
+            address,
+            latitude: lat,
+            longitude: lon,
+            valid_from: valid_from.map(|d| d.to_string()),
+            valid_to: valid_to.map(|d| d.to_string()),
+        }
+    })
+    .collect();
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(locations),
+            error: None,
+        },
+    )?)
+}
+
+/// POST /entities/{id}/locations
+#[utoipa::path(
+    post,
+    path = "/entities/{id}/locations",
+    tag = "locations",
+    request_body = StoreLocationRequest,
+    params(
+        ("id" = Uuid, Path, description = "Entity ID"),
+    ),
+    responses(
+        (status = 201, description = "The stored location, geocoded when possible", body = ApiResponse<serde_json::Value>),
+        (status = 200, description = "The entity already has this address under another label; that location is returned", body = ApiResponse<serde_json::Value>),
+        (status = 404, description = "Entity not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn add_location(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let entity_id = params.id("id", "Invalid entity ID")?;
+    if !can_access_entity(&state.db_pool, entity_id, user_id).await? {
+        return error_response(404, "Entity not found");
+    }
+
+    let request: StoreLocationRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let location_id = Uuid::new_v4();
+    let visibility = request.visibility_tier.unwrap_or(3);
+
+    let valid_from = request.valid_from
+        .map(|d| chrono::NaiveDate::parse_from_str(&d, "%Y-%m-%d"))
+        .transpose()
+        .map_err(|_| "Invalid valid_from date")?;
+    let valid_to = request.valid_to
+        .map(|d| chrono::NaiveDate::parse_from_str(&d, "%Y-%m-%d"))
+        .transpose()
+        .map_err(|_| "Invalid valid_to date")?;
+
+    // Geocoding normalizes the address, and places it when the
+    // request has no coordinates
+    let geocoded = geocode(&state, &request.address).await;
+    let (coordinates, geocode_source, geocode_confidence) =
+        match (request.latitude.zip(request.longitude), &geocoded) {
+            (Some(coordinates), _) => {
+                (Some(coordinates), Some("manual"), Some(1.0))
+            }
+            (None, Some(g)) => (
+                Some((g.latitude, g.longitude)),
+                Some("aws_location"),
+                g.confidence,
+            ),
+            (None, None) => (None, None, None),
+        };
+    let normalized = geocoded.as_ref().map(|g| &g.address);
+    let place_id = geocoded.as_ref().and_then(|g| g.place_id.as_deref());
+    let address_normalized = normalized.map(serde_json::to_value).transpose()?;
+    let (lat, lon) = coordinates.unzip();
+
+    // Already stored under another label: fill in what that location
+    // lacks rather than list the place twice
+    let duplicate = find_duplicate_location(
+        &state.db_pool,
+        entity_id,
+        &request.label,
+        place_id,
+        place_address(normalized, &request.address),
+        coordinates,
+    )
+    .await?;
+
+    if let Some(duplicate) = duplicate {
+        sqlx::query(
+            r#"
+            UPDATE entity_locations SET
+                address_normalized = COALESCE(address_normalized, $2),
+                location = COALESCE(location, ST_SetSRID(ST_MakePoint($3, $4), 4326)::geography),
+                geocode_source = COALESCE(geocode_source, $5),
+                geocode_confidence = COALESCE(geocode_confidence, $6),
+                geocoded_at = COALESCE(geocoded_at, CASE WHEN $5::text IS NULL THEN NULL ELSE NOW() END),
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(duplicate.id)
+        .bind(&address_normalized)
+        .bind(lon)
+        .bind(lat)
+        .bind(geocode_source)
+        .bind(geocode_confidence)
+        .execute(&state.db_pool)
+        .await?;
+
+        info!(
+            "Location {} for entity {} is already stored as {}",
+            request.label, entity_id, duplicate.label
+        );
+
+        return Ok(json_response(
+            200,
+            &ApiResponse {
+                success: true,
+                data: Some(serde_json::json!({
+                    "location_id": duplicate.id.to_string(),
+                    "entity_id": entity_id.to_string(),
+                    "label": duplicate.label,
+                    "deduplicated": true,
+                    "note": format!("The entity already has this address as '{}'", duplicate.label),
+                })),
+                error: None,
             },
-        )?),
+        )?);
     }
+
+    let location_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO entity_locations (id, entity_id, label, address_raw, address_normalized,
+                                     place_id, location, geocode_source, geocode_confidence,
+                                     geocoded_at, valid_from, valid_to, visibility_tier)
+        VALUES ($1, $2, $3, $4, $5, $6, ST_SetSRID(ST_MakePoint($7, $8), 4326)::geography,
+                $9, $10, CASE WHEN $9::text IS NULL THEN NULL ELSE NOW() END, $11, $12, $13)
+        ON CONFLICT (entity_id, label) WHERE valid_to IS NULL DO UPDATE SET
+            address_raw = EXCLUDED.address_raw,
+            address_normalized = EXCLUDED.address_normalized,
+            place_id = EXCLUDED.place_id,
+            location = COALESCE(EXCLUDED.location, entity_locations.location),
+            geocode_source = COALESCE(EXCLUDED.geocode_source, entity_locations.geocode_source),
+            geocode_confidence = COALESCE(EXCLUDED.geocode_confidence, entity_locations.geocode_confidence),
+            geocoded_at = COALESCE(EXCLUDED.geocoded_at, entity_locations.geocoded_at),
+            valid_from = EXCLUDED.valid_from,
+            valid_to = EXCLUDED.valid_to,
+            updated_at = NOW()
+        RETURNING id
+        "#,
+    )
+    .bind(location_id)
+    .bind(entity_id)
+    .bind(&request.label)
+    .bind(&request.address)
+    .bind(&address_normalized)
+    .bind(place_id)
+    .bind(lon)
+    .bind(lat)
+    .bind(geocode_source)
+    .bind(geocode_confidence)
+    .bind(valid_from)
+    .bind(valid_to)
+    .bind(visibility)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    info!("Stored location {} for entity {}", request.label, entity_id);
+
+    let data = match coordinates {
+        Some((lat, lon)) => serde_json::json!({
+            "location_id": location_id.to_string(),
+            "entity_id": entity_id.to_string(),
+            "label": request.label,
+            "latitude": lat,
+            "longitude": lon,
+            "address_normalized": address_normalized,
+        }),
+        // Store without coordinates (address only)
+        None => serde_json::json!({
+            "location_id": location_id.to_string(),
+            "entity_id": entity_id.to_string(),
+            "label": request.label,
+            "note": "Location stored without coordinates. Use geocoding to add coordinates."
+        }),
+    };
+
+    Ok(json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(data),
+            error: None,
+        },
+    )?)
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .get("/locations/nearby", nearby_entities)
+        .get("/locations/distance", distance)
+        .get("/facts/timeline", facts_timeline)
+        .get("/facts/nearby", nearby_facts)
+        .get("/facts/search", search)
+        .get("/v1/facts/search", search)
+        .get("/facts/review", list_review_queue)
+        .post("/facts/{id}/review", review)
+        .get("/facts/{id}/attachments", list_fact_attachments)
+        .post("/facts/{id}/attachments", upload_attachment)
+        .delete("/facts/{id}/attachments/{attachmentId}", remove_attachment)
+        .get("/entities/{id}/locations/map", location_map)
+        .get("/entities/{id}/locations", list_locations)
+        .post("/entities/{id}/locations", add_location)
 }
 
 fn json_response<T: Serialize>(status: u16, data: &T) -> Result<Response<Body>, Error> {
     Ok(Response::builder()
         .status(status)
         .header("content-type", "application/json")
-        .body(Body::from(serde_json::to_string(data)?))
-        .expect("Failed to build response"))
+        .body(Body::from(serde_json::to_string(data)?))?)
 }
 
 #[tokio::main]
@@ -783,10 +1840,12 @@ async fn main() -> Result<(), Error> {
         .init();
 
     let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
 
     run(service_fn(move |event| {
         let state = Arc::clone(&state);
-        async move { handler(state, event).await }
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
     }))
     .await
 }
@@ -0,0 +1,178 @@
+//! Occasions Lambda - Upcoming birthdays and anniversaries.
+//!
+//! Endpoints:
+//! - GET /occasions/upcoming - Next occasions on the user's and their families'
+//!   entities (`?days=30`, up to a year ahead)
+//!
+//! Occasions come from birthday/anniversary entity attributes (see
+//! `shared::occasions`); `occasion_scanner` creates the matching reminders.
+
+use chrono::Utc;
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::Serialize;
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::occasions::{fetch_occasion_attributes, upcoming, UpcomingOccasion, MAX_UPCOMING_DAYS};
+use shared::ratelimit::RateLimit;
+use shared::recurrence::user_timezone;
+use shared::router::{require_user, Cors, PathParams, Query, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::ApiError;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+
+/// Days ahead listed when `days` isn't given
+const DEFAULT_UPCOMING_DAYS: i64 = 30;
+
+/// Upcoming occasion API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct OccasionResponse {
+    entity_id: String,
+    entity_name: String,
+    entity_type: String,
+    /// `birthday` or `anniversary`
+    occasion_type: String,
+    title: String,
+    /// Next occurrence (YYYY-MM-DD)
+    date: String,
+    days_until: i64,
+    /// Age turned or anniversary number, when the year is known
+    years: Option<i32>,
+}
+
+impl From<UpcomingOccasion> for OccasionResponse {
+    fn from(occasion: UpcomingOccasion) -> Self {
+        Self {
+            entity_id: occasion.entity_id.to_string(),
+            entity_name: occasion.entity_name,
+            entity_type: occasion.entity_type,
+            occasion_type: occasion.occasion_type.as_str().to_string(),
+            title: occasion.title,
+            date: occasion.date.format("%Y-%m-%d").to_string(),
+            days_until: occasion.days_until,
+            years: occasion.years,
+        }
+    }
+}
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+/// GET /occasions/upcoming
+///
+/// Occasions from today (in the user's timezone) through `days` days ahead,
+/// soonest first.
+#[utoipa::path(
+    get,
+    path = "/occasions/upcoming",
+    tag = "occasions",
+    params(
+        ("days" = Option<i64>, Query, description = "Days ahead to look (default 30, up to a year)"),
+    ),
+    responses(
+        (status = 200, description = "Upcoming birthdays and anniversaries, soonest first", body = ApiResponse<Vec<OccasionResponse>>),
+        (status = 400, description = "`days` out of range", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn upcoming_occasions(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let days = match Query::from_request(&event).get::<i64>("days") {
+        Ok(days) => days.unwrap_or(DEFAULT_UPCOMING_DAYS),
+        Err(e) => return error_response(400, e.to_string()),
+    };
+    if !(0..=MAX_UPCOMING_DAYS).contains(&days) {
+        return error_response(
+            400,
+            format!("days must be between 0 and {}", MAX_UPCOMING_DAYS),
+        );
+    }
+
+    let timezone = user_timezone(&state.db_pool, user.user_id).await?;
+    let today = Utc::now().with_timezone(&timezone).date_naive();
+
+    let attributes = fetch_occasion_attributes(&state.db_pool, user.user_id).await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(
+                upcoming(&attributes, today, days)
+                    .into_iter()
+                    .map(OccasionResponse::from)
+                    .collect::<Vec<_>>(),
+            ),
+            error: None,
+        },
+    )
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .get("/occasions/upcoming", upcoming_occasions)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
@@ -0,0 +1,393 @@
+//! OpenAPI Lambda - The REST API's OpenAPI 3 document.
+//!
+//! Endpoints:
+//! - GET /openapi.json - The document (no authentication needed)
+//!
+//! The document is generated from the API's Rust types (`shared::openapi`) and
+//! the `#[utoipa::path]` annotations on every REST handler, whose Lambdas are
+//! compiled in here as modules for them (`websocket`, `user_signup` and
+//! `internal_facts` aren't behind the REST API). Run with `--print` to write
+//! it to stdout instead, e.g. to generate clients:
+//!
+//! ```text
+//! cargo run --bin openapi -- --print > openapi.json
+//! ```
+
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use shared::metrics::RequestMetrics;
+use shared::openapi::ApiDoc;
+use shared::ratelimit::RateLimit;
+use shared::router::{Cors, PathParams, RequestLogger, Router};
+use shared::shaping::ResponseShaping;
+use std::sync::Arc;
+use tracing_subscriber::EnvFilter;
+use utoipa::OpenApi;
+
+// Only their handlers' path docs are used here
+#[allow(dead_code)]
+#[path = "access_grants.rs"]
+mod access_grants;
+#[allow(dead_code)]
+#[path = "account.rs"]
+mod account;
+#[allow(dead_code)]
+#[path = "api_keys.rs"]
+mod api_keys;
+#[allow(dead_code)]
+#[path = "audit.rs"]
+mod audit;
+#[allow(dead_code)]
+#[path = "auth.rs"]
+mod auth;
+#[allow(dead_code)]
+#[path = "briefing.rs"]
+mod briefing;
+#[allow(dead_code)]
+#[path = "calendar.rs"]
+mod calendar;
+#[allow(dead_code)]
+#[path = "calendar_oauth.rs"]
+mod calendar_oauth;
+#[allow(dead_code)]
+#[path = "capture.rs"]
+mod capture;
+#[allow(dead_code)]
+#[path = "contacts_oauth.rs"]
+mod contacts_oauth;
+#[allow(dead_code)]
+#[path = "conversations.rs"]
+mod conversations;
+#[allow(dead_code)]
+#[path = "diagnostics.rs"]
+mod diagnostics;
+#[allow(dead_code)]
+#[path = "discord_link.rs"]
+mod discord_link;
+#[allow(dead_code)]
+#[path = "entities.rs"]
+mod entities;
+#[allow(dead_code)]
+#[path = "export.rs"]
+mod export;
+#[allow(dead_code)]
+#[path = "families.rs"]
+mod families;
+#[allow(dead_code)]
+#[path = "feedback.rs"]
+mod feedback;
+#[allow(dead_code)]
+#[path = "feeds.rs"]
+mod feeds;
+#[allow(dead_code)]
+#[path = "handoffs.rs"]
+mod handoffs;
+#[allow(dead_code)]
+#[path = "ingest.rs"]
+mod ingest;
+#[allow(dead_code)]
+#[path = "location_history.rs"]
+mod location_history;
+#[allow(dead_code)]
+#[path = "locations.rs"]
+mod locations;
+#[allow(dead_code)]
+#[path = "occasions.rs"]
+mod occasions;
+#[allow(dead_code)]
+#[path = "preferences.rs"]
+mod preferences;
+#[allow(dead_code)]
+#[path = "push_devices.rs"]
+mod push_devices;
+#[allow(dead_code)]
+#[path = "query.rs"]
+mod query;
+#[allow(dead_code)]
+#[path = "realtime.rs"]
+mod realtime;
+#[allow(dead_code)]
+#[path = "relationships.rs"]
+mod relationships;
+#[allow(dead_code)]
+#[path = "reminders.rs"]
+mod reminders;
+#[allow(dead_code)]
+#[path = "sharing.rs"]
+mod sharing;
+#[allow(dead_code)]
+#[path = "sms_phone.rs"]
+mod sms_phone;
+#[allow(dead_code)]
+#[path = "spaces.rs"]
+mod spaces;
+#[allow(dead_code)]
+#[path = "tags.rs"]
+mod tags;
+#[allow(dead_code)]
+#[path = "trash.rs"]
+mod trash;
+#[allow(dead_code)]
+#[path = "usage.rs"]
+mod usage;
+
+/// The documented endpoints.
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_document,
+    access_grants::list_grants,
+    access_grants::create_grant,
+    access_grants::revoke_grant,
+    account::delete_account,
+    api_keys::list_keys,
+    api_keys::create_key,
+    api_keys::revoke_key,
+    audit::list_audit,
+    auth::device_code,
+    auth::token,
+    auth::approve_device,
+    auth::list_devices,
+    auth::sign_out_device,
+    briefing::get_todays_briefing,
+    calendar::list_subscriptions,
+    calendar::subscribe,
+    calendar::unsubscribe,
+    calendar::extraction_preferences,
+    calendar::update_extraction_preferences,
+    calendar_oauth::start,
+    calendar_oauth::callback,
+    capture::capture,
+    contacts_oauth::start_oauth,
+    contacts_oauth::oauth_callback,
+    contacts_oauth::get_connection,
+    contacts_oauth::delete_connection,
+    conversations::list_conversations,
+    conversations::list_messages,
+    diagnostics::get_debug_mode,
+    diagnostics::enable_debug_mode,
+    diagnostics::disable_debug_mode,
+    diagnostics::list_samples,
+    discord_link::get_link,
+    discord_link::issue_code,
+    discord_link::delete_link,
+    entities::create_entity,
+    entities::list_entities,
+    entities::get_entity,
+    entities::update_entity,
+    entities::delete_entity,
+    entities::get_entity_facts,
+    entities::create_relationship,
+    entities::list_relationships,
+    entities::merge_entity,
+    entities::upload_photo,
+    entities::get_relationship_health,
+    export::export_graph,
+    export::start_export,
+    export::get_export,
+    families::create_family,
+    families::list_families,
+    families::join_family,
+    families::get_family,
+    families::invite_member,
+    families::create_join_code,
+    families::remove_member,
+    feedback::record_feedback,
+    feedback::get_stats,
+    feedback::rate_query,
+    feedback::get_history,
+    feedback::record_events,
+    feeds::list_feeds,
+    feeds::subscribe,
+    feeds::unsubscribe,
+    feeds::enable_feed,
+    feeds::disable_feed,
+    handoffs::create_handoff,
+    handoffs::list_handoffs,
+    handoffs::revoke_handoff,
+    handoffs::view_shared_handoff,
+    ingest::ingest,
+    ingest::ingest_batch,
+    ingest::get_batch,
+    location_history::upload_pings,
+    location_history::get_settings,
+    location_history::update_settings,
+    location_history::list_visits,
+    location_history::delete_history,
+    locations::nearby_entities,
+    locations::distance,
+    locations::facts_timeline,
+    locations::nearby_facts,
+    locations::search,
+    locations::list_review_queue,
+    locations::review,
+    locations::list_fact_attachments,
+    locations::upload_attachment,
+    locations::remove_attachment,
+    locations::location_map,
+    locations::list_locations,
+    locations::add_location,
+    occasions::upcoming_occasions,
+    preferences::get_notifications,
+    preferences::update_notifications,
+    push_devices::list_devices,
+    push_devices::register_device,
+    push_devices::delete_device,
+    query::answer,
+    realtime::create_ticket,
+    relationships::create_relationship,
+    relationships::list_relationships,
+    relationships::list_requests,
+    relationships::accept_request,
+    relationships::decline_request,
+    relationships::withdraw_request,
+    relationships::update_relationship,
+    relationships::delete_relationship,
+    reminders::create_reminder,
+    reminders::list_reminders,
+    reminders::get_reminder,
+    reminders::update_reminder,
+    reminders::simulate,
+    reminders::snooze_reminder,
+    reminders::complete_reminder,
+    reminders::reminder_history,
+    reminders::delete_reminder,
+    sharing::shared_with_me,
+    sharing::shared_by_me,
+    sms_phone::get_phone,
+    sms_phone::register_phone,
+    sms_phone::verify_phone,
+    sms_phone::delete_phone,
+    spaces::list_spaces,
+    spaces::create_space,
+    spaces::get_space,
+    spaces::update_space,
+    spaces::delete_space,
+    spaces::add_member,
+    spaces::remove_member,
+    spaces::add_items,
+    spaces::remove_items,
+    tags::create_tag,
+    tags::list_tags,
+    tags::tag_stats,
+    tags::suggest_tags,
+    tags::create_rule,
+    tags::list_rules,
+    tags::delete_rule,
+    tags::list_fact_tags,
+    tags::apply_tags,
+    tags::remove_fact_tag,
+    tags::get_tag,
+    tags::update_tag,
+    tags::delete_tag,
+    tags::move_tag,
+    tags::list_tag_facts,
+    trash::list_trash,
+    trash::restore_item,
+    usage::get_usage,
+    usage::get_query
+))]
+struct Endpoints;
+
+/// The OpenAPI document as pretty-printed JSON.
+fn document_json() -> String {
+    ApiDoc::openapi()
+        .merge_from(Endpoints::openapi())
+        .to_pretty_json()
+        .unwrap_or_else(|_| "{}".to_string())
+}
+
+/// GET /openapi.json
+#[utoipa::path(
+    get,
+    path = "/openapi.json",
+    tag = "docs",
+    responses(
+        (status = 200, description = "This document", body = serde_json::Value),
+    )
+)]
+async fn get_document(
+    _state: Arc<()>,
+    _event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    Ok(Response::builder()
+        .status(200)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "public, max-age=300")
+        .body(Body::from(document_json()))?)
+}
+
+fn router() -> Router<()> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RateLimit::default().public("GET", "/openapi.json"))
+        .get("/openapi.json", get_document)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    if std::env::args().any(|arg| arg == "--print") {
+        println!("{}", document_json());
+        return Ok(());
+    }
+
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(());
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn documents_each_endpoint() {
+        let doc: serde_json::Value = serde_json::from_str(&document_json()).unwrap();
+        for path in [
+            "/query",
+            "/ingest",
+            "/ingest/batch",
+            "/ingest/batch/{id}",
+            "/usage",
+            "/v1/queries/{id}",
+            "/entities/{id}",
+            "/facts/search",
+            "/tags/{id}/move",
+            "/reminders/{id}/snooze",
+            "/families/{familyId}/spaces",
+            "/openapi.json",
+        ] {
+            assert!(doc["paths"][path].is_object(), "missing {}", path);
+        }
+
+        // Errors are problem details
+        assert!(
+            doc["paths"]["/ingest"]["post"]["responses"]["400"]["content"]
+                ["application/problem+json"]
+                .is_object()
+        );
+        assert!(doc["components"]["schemas"]["BatchIngestResponse"].is_object());
+
+        // Conditional requests carry their ETag
+        let get_reminder = &doc["paths"]["/reminders/{id}"]["get"];
+        assert!(get_reminder["responses"]["200"]["headers"]["ETag"].is_object());
+        assert!(get_reminder["responses"]["304"].is_object());
+
+        // Signing in needs no token; everything after it does
+        assert!(doc["paths"]["/auth/device/code"]["post"]["security"].is_null());
+        assert!(doc["paths"]["/auth/devices"]["get"]["security"].is_array());
+    }
+}
@@ -0,0 +1,165 @@
+//! Preferences Lambda - Notification preferences.
+//!
+//! Endpoints:
+//! - GET /preferences/notifications - Your notification preferences (the
+//!   defaults until you save some)
+//! - PUT /preferences/notifications - Change them; fields left out are kept
+//!
+//! Covers channels, quiet hours, briefing and digest times and overrides by
+//! notification type. The reminder evaluator and briefing generators read
+//! these; see `shared::notification_preferences` for what is validated.
+
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::Serialize;
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::notification_preferences::{self, Preferences, PreferencesUpdate};
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::ApiError;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+/// GET /preferences/notifications
+#[utoipa::path(
+    get,
+    path = "/preferences/notifications",
+    tag = "preferences",
+    responses(
+        (status = 200, description = "Your notification preferences, or the defaults until you save some", body = ApiResponse<Preferences>),
+    ),
+    security(("cognito" = []))
+)]
+async fn get_notifications(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let prefs: Preferences = notification_preferences::load(&state.db_pool, user.user_id).await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(prefs),
+            error: None,
+        },
+    )
+}
+
+/// PUT /preferences/notifications
+#[utoipa::path(
+    put,
+    path = "/preferences/notifications",
+    tag = "preferences",
+    request_body = PreferencesUpdate,
+    responses(
+        (status = 200, description = "The saved preferences", body = ApiResponse<Preferences>),
+        (status = 400, description = "An invalid time, timezone, channel or limit", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn update_notifications(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let update: PreferencesUpdate = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let current = notification_preferences::load(&state.db_pool, user.user_id).await?;
+
+    let prefs = match update.apply(current) {
+        Ok(prefs) => prefs,
+        Err(shared::Error::Validation(e)) => return error_response(400, e),
+        Err(e) => return Err(e.to_string().into()),
+    };
+
+    let prefs = notification_preferences::save(&state.db_pool, user.user_id, &prefs).await?;
+
+    info!(user_id = %user.user_id, "Updated notification preferences");
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(prefs),
+            error: None,
+        },
+    )
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .get("/preferences/notifications", get_notifications)
+        .put("/preferences/notifications", update_notifications)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
@@ -0,0 +1,298 @@
+//! Push Devices Lambda - Registers mobile devices for push notifications.
+//!
+//! Endpoints:
+//! - GET /devices/push - List the user's active devices
+//! - POST /devices/push - Register (or refresh) a device token
+//! - DELETE /devices/push/{id} - Unregister a device
+//!
+//! A token belongs to one app install, so registering a token already held by
+//! another user (e.g. after signing out and in as someone else) moves it.
+
+use chrono::{DateTime, Utc};
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::{Deserialize, Serialize};
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::push::PushPlatform;
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::ApiError;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Longest token accepted (FCM tokens are ~160 chars, APNs tokens 64 hex)
+const MAX_TOKEN_LENGTH: usize = 4096;
+
+/// Push device row from database
+#[derive(Debug, sqlx::FromRow)]
+struct PushDeviceRow {
+    id: Uuid,
+    device_platform: String,
+    device_name: Option<String>,
+    last_delivered_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+}
+
+/// Register device request
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct RegisterDeviceRequest {
+    /// `fcm` or `apns` (`android`/`ios` are accepted)
+    platform: String,
+    token: String,
+    device_name: Option<String>,
+}
+
+/// Push device API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct PushDeviceResponse {
+    id: String,
+    platform: String,
+    device_name: Option<String>,
+    last_delivered_at: Option<String>,
+    created_at: String,
+}
+
+impl From<PushDeviceRow> for PushDeviceResponse {
+    fn from(row: PushDeviceRow) -> Self {
+        Self {
+            id: row.id.to_string(),
+            platform: row.device_platform,
+            device_name: row.device_name,
+            last_delivered_at: row.last_delivered_at.map(|t| t.to_rfc3339()),
+            created_at: row.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+/// GET /devices/push
+#[utoipa::path(
+    get,
+    path = "/devices/push",
+    tag = "devices",
+    responses(
+        (status = 200, description = "Your active push devices", body = ApiResponse<Vec<PushDeviceResponse>>),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_devices(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let devices: Vec<PushDeviceRow> = sqlx::query_as(
+        r#"
+        SELECT id, device_platform, device_name, last_delivered_at, created_at
+        FROM push_devices
+        WHERE user_id = $1 AND is_active
+        ORDER BY created_at
+        "#,
+    )
+    .bind(user.user_id)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(
+                devices
+                    .into_iter()
+                    .map(PushDeviceResponse::from)
+                    .collect::<Vec<_>>(),
+            ),
+            error: None,
+        },
+    )
+}
+
+/// POST /devices/push
+#[utoipa::path(
+    post,
+    path = "/devices/push",
+    tag = "devices",
+    request_body = RegisterDeviceRequest,
+    responses(
+        (status = 201, description = "The registered device; a token held by another user moves to you", body = ApiResponse<PushDeviceResponse>),
+        (status = 400, description = "Unknown platform or missing token", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn register_device(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let request: RegisterDeviceRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let platform = match PushPlatform::parse(&request.platform) {
+        Some(platform) => platform,
+        None => return error_response(400, "platform must be fcm or apns"),
+    };
+
+    let token = request.token.trim();
+    if token.is_empty() || token.len() > MAX_TOKEN_LENGTH {
+        return error_response(400, "token is required");
+    }
+
+    // Re-registering reactivates the token and moves it to the current user
+    let device: PushDeviceRow = sqlx::query_as(
+        r#"
+        INSERT INTO push_devices (user_id, device_platform, push_token, device_name)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (device_platform, push_token) DO UPDATE
+        SET user_id = EXCLUDED.user_id,
+            device_name = COALESCE(EXCLUDED.device_name, push_devices.device_name),
+            is_active = true,
+            invalidated_at = NULL,
+            last_error = NULL,
+            updated_at = NOW()
+        RETURNING id, device_platform, device_name, last_delivered_at, created_at
+        "#,
+    )
+    .bind(user.user_id)
+    .bind(platform.as_str())
+    .bind(token)
+    .bind(request.device_name.as_deref().map(str::trim))
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    info!(user_id = %user.user_id, device_id = %device.id, platform = platform.as_str(), "Registered push device");
+
+    json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(PushDeviceResponse::from(device)),
+            error: None,
+        },
+    )
+}
+
+/// DELETE /devices/push/{id}
+#[utoipa::path(
+    delete,
+    path = "/devices/push/{id}",
+    tag = "devices",
+    params(
+        ("id" = Uuid, Path, description = "Device ID"),
+    ),
+    responses(
+        (status = 200, description = "Device unregistered"),
+        (status = 404, description = "Device not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn delete_device(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let device_id = match params.get::<Uuid>("id") {
+        Ok(id) => id,
+        Err(_) => return error_response(400, "Invalid device ID"),
+    };
+
+    let result = sqlx::query("DELETE FROM push_devices WHERE id = $1 AND user_id = $2")
+        .bind(device_id)
+        .bind(user.user_id)
+        .execute(&state.db_pool)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return error_response(404, "Device not found");
+    }
+
+    info!(user_id = %user.user_id, device_id = %device_id, "Removed push device");
+
+    json_response(
+        200,
+        &ApiResponse::<()> {
+            success: true,
+            data: None,
+            error: None,
+        },
+    )
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .get("/devices/push", list_devices)
+        .post("/devices/push", register_device)
+        .delete("/devices/push/{id}", delete_device)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
@@ -2,18 +2,37 @@
 //!
 //! This Lambda processes query requests from API Gateway, validates the user's
 //! JWT token, and invokes the Python agent system to answer the question.
+//! Questions and answers are stored as a conversation (`shared::conversations`)
+//! whose ID is returned, so follow-ups sent with it see the earlier turns.
+//! Each answer's token usage, estimated cost and source facts are logged
+//! (`shared::usage`) for `GET /usage` and `GET /v1/queries/{id}`.
 
-use lambda_http::{run, service_fn, Body, Error, Request, RequestExt, RequestPayloadExt, Response};
+use lambda_http::{run, service_fn, Body, Error, Request, RequestPayloadExt, Response};
+use shared::agents::DirectFallback;
+use shared::conversations;
+use shared::diagnostics::{agent_trace, record_sample, Sample};
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::{self, Limit, RateLimit, QUERIES_PER_MINUTE};
+use shared::router::{Cors, PathParams, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::usage::{self, QueryUsage};
 use shared::{
-    extract_user_from_context, AgentClient, ApiResponse, QueryRequest, QueryResponse,
+    error_response, AgentClient, AgentResponse, ApiError, ApiResponse, AuthenticatedUser,
+    AuthorizedUser, QueryRequest, QueryResponse,
 };
+use sqlx::PgPool;
 use std::sync::Arc;
-use tracing::{error, info};
+use std::time::Instant;
+use tracing::{error, info, warn};
 use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
 
 /// Application state shared across requests.
 struct AppState {
     agent_client: AgentClient,
+    /// Only set when `DB_HOST` is configured; used for conversations, the
+    /// usage log and to capture debug-mode samples
+    db_pool: Option<PgPool>,
 }
 
 impl AppState {
@@ -24,34 +43,123 @@ impl AppState {
         let agent_function = std::env::var("AGENT_FUNCTION_NAME")
             .unwrap_or_else(|_| "second-brain-agents".to_string());
 
+        let db_pool = if std::env::var("DB_HOST").is_ok() {
+            let pool = shared::db::connect(&config)
+                .await
+                .map_err(|e| format!("Failed to connect to database: {}", e))?;
+            Some(pool)
+        } else {
+            None
+        };
+
+        // Simple questions are answered from the database while the agents are down
+        let mut agent_client = AgentClient::new(lambda_client, agent_function);
+        if let Some(pool) = &db_pool {
+            agent_client =
+                agent_client.with_fallback(DirectFallback::from_env(&config, pool.clone()));
+        }
+
         Ok(Self {
-            agent_client: AgentClient::new(lambda_client, agent_function),
+            agent_client,
+            db_pool,
         })
     }
 }
 
-async fn handler(state: Arc<AppState>, event: Request) -> Result<Response<Body>, Error> {
-    // Extract user from request context (set by Cognito authorizer)
-    let user = match event.request_context_ref() {
-        Some(ctx) => {
-            if let Some(authorizer) = ctx.authorizer() {
-                let claims = authorizer.fields.get("claims");
-                if let Some(claims) = claims {
-                    match extract_user_from_context(claims) {
-                        Ok(user) => user,
-                        Err(e) => {
-                            error!("Failed to extract user: {}", e);
-                            return Ok(error_response(401, "Authentication required"));
-                        }
-                    }
-                } else {
-                    return Ok(error_response(401, "Authentication required"));
-                }
+/// Record a diagnostics sample if the user has debug mode on (best effort).
+async fn capture_sample(
+    pool: &PgPool,
+    user: &AuthorizedUser,
+    operation: &str,
+    request: &str,
+    result: &shared::Result<AgentResponse>,
+    started: Instant,
+) {
+    let error = result.as_ref().err().map(|e| e.to_string());
+    let sample = Sample {
+        user_id: user.user_id,
+        source: "api",
+        operation,
+        request,
+        response: result.as_ref().ok().map(|r| r.response.as_str()),
+        error: error.as_deref(),
+        trace: result
+            .as_ref()
+            .map(agent_trace)
+            .unwrap_or_else(|_| serde_json::json!({})),
+        latency_ms: Some(started.elapsed().as_millis().min(i32::MAX as u128) as i32),
+    };
+
+    if let Err(e) = record_sample(pool, sample).await {
+        warn!("Failed to record diagnostics sample: {}", e);
+    }
+}
+
+/// What a query continues.
+enum Conversation {
+    /// One of the user's conversations
+    Continue(Uuid),
+    /// Nothing yet: a conversation is started once the question is answered
+    Start,
+    /// The requested `conversation_id` isn't the user's
+    NotFound,
+}
+
+/// Find the conversation a query continues.
+///
+/// Older clients send the previous response's `session_id` instead; it is
+/// used when it names one of the user's conversations.
+async fn find_conversation(
+    pool: &PgPool,
+    user: &AuthorizedUser,
+    request: &QueryRequest,
+) -> shared::Result<Conversation> {
+    if let Some(id) = request.conversation_id {
+        return Ok(
+            if conversations::belongs_to(pool, id, user.user_id).await? {
+                Conversation::Continue(id)
             } else {
-                return Ok(error_response(401, "Authentication required"));
-            }
+                Conversation::NotFound
+            },
+        );
+    }
+
+    if let Some(id) = request.session_id.as_deref().and_then(|s| s.parse().ok()) {
+        if conversations::belongs_to(pool, id, user.user_id).await? {
+            return Ok(Conversation::Continue(id));
+        }
+    }
+
+    Ok(Conversation::Start)
+}
+
+/// POST /query
+#[utoipa::path(
+    post,
+    path = "/query",
+    tag = "knowledge",
+    request_body = QueryRequest,
+    responses(
+        (status = 200, description = "The answer", body = ApiResponse<QueryResponse>),
+        (status = 404, description = "`conversation_id` isn't one of the caller's", body = ApiError, content_type = "application/problem+json"),
+        (status = 429, description = "Too many questions; see `Retry-After`", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn answer(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    // Extract user from request context (set by Cognito authorizer)
+    let user = AuthenticatedUser::from_request(&event);
+    ratelimit::check_caller(&event, user.as_ref().ok().map(|u| u.user_id.as_str()))?;
+    let user = match user {
+        Ok(user) => user,
+        Err(e) => {
+            error!("Failed to extract user: {}", e);
+            return error_response(401, "Authentication required");
         }
-        None => return Ok(error_response(401, "Authentication required")),
     };
 
     info!("Processing query for user: {}", user.user_id);
@@ -59,39 +167,176 @@ async fn handler(state: Arc<AppState>, event: Request) -> Result<Response<Body>,
     // Parse request body
     let request: QueryRequest = match event.payload() {
         Ok(Some(req)) => req,
-        Ok(None) => return Ok(error_response(400, "Missing request body")),
-        Err(e) => return Ok(error_response(400, &format!("Invalid request: {}", e))),
+        Ok(None) => return error_response(400, "Missing request body"),
+        Err(e) => return error_response(400, format!("Invalid request: {}", e)),
+    };
+
+    // Conversations and diagnostics need the user's account; without it the
+    // question is answered on its own
+    let account = match &state.db_pool {
+        Some(pool) => match AuthorizedUser::resolve(user.clone(), pool).await {
+            Ok(account) => Some((pool, account)),
+            Err(e) => {
+                warn!("Answering without conversation context: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    let conversation = match &account {
+        Some((pool, account)) => match find_conversation(pool, account, &request).await {
+            Ok(Conversation::NotFound) => return error_response(404, "Conversation not found"),
+            Ok(conversation) => Some(conversation),
+            Err(e) => {
+                error!("Failed to load conversation: {}", e);
+                return error_response(500, "Failed to process query");
+            }
+        },
+        None => None,
+    };
+
+    let history = match (&account, &conversation) {
+        (Some((pool, _)), Some(Conversation::Continue(id))) => {
+            conversations::history(pool, *id).await.unwrap_or_else(|e| {
+                warn!("Failed to load conversation history: {}", e);
+                Vec::new()
+            })
+        }
+        _ => Vec::new(),
     };
 
     // Invoke agent system
-    let agent_response = match state
+    let started = Instant::now();
+    let session_id = match &conversation {
+        Some(Conversation::Continue(id)) => Some(id.to_string()),
+        _ => request.session_id.clone(),
+    };
+    let result = state
         .agent_client
-        .query(
+        .query_with_history(
             &request.query,
             &user.user_id,
             user.family_ids.clone(),
-            request.session_id.clone(),
+            session_id,
+            history,
             "api",
         )
-        .await
-    {
+        .await;
+
+    if let Some((pool, account)) = &account {
+        capture_sample(pool, account, "query", &request.query, &result, started).await;
+    }
+
+    let agent_response = match result {
         Ok(resp) => resp,
         Err(e) => {
             error!("Agent invocation failed: {}", e);
-            return Ok(error_response(500, "Failed to process query"));
+            return error_response(500, "Failed to process query");
+        }
+    };
+
+    let metadata = agent_response.metadata;
+    let latency_ms = started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+
+    // Store the exchange (best effort: the answer is returned either way)
+    let conversation_id = match (&account, conversation) {
+        (Some((pool, account)), Some(conversation)) => {
+            let stored = async {
+                let id = match conversation {
+                    Conversation::Continue(id) => id,
+                    _ => conversations::start(pool, account.user_id, "api", &request.query).await?,
+                };
+                conversations::record_exchange(
+                    pool,
+                    id,
+                    &request.query,
+                    &agent_response.response,
+                    metadata.as_ref().and_then(|m| m.model_id.as_deref()),
+                    Some(latency_ms),
+                )
+                .await?;
+                Ok::<_, shared::Error>(id)
+            }
+            .await;
+
+            match stored {
+                Ok(id) => Some(id),
+                Err(e) => {
+                    warn!("Failed to record conversation: {}", e);
+                    None
+                }
+            }
         }
+        _ => None,
+    };
+
+    let agents_used = metadata
+        .as_ref()
+        .and_then(|m| m.agents_used.clone())
+        .unwrap_or_default();
+    let model_id = metadata.as_ref().and_then(|m| m.model_id.as_deref());
+    let input_tokens = metadata.as_ref().and_then(|m| m.input_tokens).unwrap_or(0);
+    let output_tokens = metadata.as_ref().and_then(|m| m.output_tokens).unwrap_or(0);
+    let source_fact_ids = metadata
+        .as_ref()
+        .and_then(|m| m.source_fact_ids.clone())
+        .unwrap_or_default();
+
+    // Logged for the operator's spend dashboards even without a database
+    info!(
+        model_id = model_id.unwrap_or("unknown"),
+        input_tokens,
+        output_tokens,
+        estimated_cost_usd = model_id
+            .map(|model| usage::estimate_cost(model, input_tokens, output_tokens))
+            .unwrap_or(0.0),
+        latency_ms,
+        "Query usage"
+    );
+
+    // Log usage (best effort)
+    let query_id = match &account {
+        Some((pool, account)) => {
+            let recorded = usage::record_query(
+                pool,
+                QueryUsage {
+                    user_id: account.user_id,
+                    source: "api",
+                    query: &request.query,
+                    response: &agent_response.response,
+                    agents_used: &agents_used,
+                    model_id,
+                    conversation_id,
+                    input_tokens,
+                    output_tokens,
+                    duration_ms: latency_ms,
+                    source_fact_ids: &source_fact_ids,
+                },
+            )
+            .await;
+
+            match recorded {
+                Ok(id) => Some(id),
+                Err(e) => {
+                    warn!("Failed to record query usage: {}", e);
+                    None
+                }
+            }
+        }
+        None => None,
     };
 
     // Build response
     let response_body = ApiResponse::success(QueryResponse {
         response: agent_response.response,
-        session_id: agent_response
-            .conversation_id
-            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
-        agents_used: agent_response
-            .metadata
-            .and_then(|m| m.agents_used)
-            .unwrap_or_default(),
+        session_id: conversation_id
+            .map(|id| id.to_string())
+            .or(agent_response.conversation_id)
+            .unwrap_or_else(|| Uuid::new_v4().to_string()),
+        agents_used,
+        conversation_id,
+        query_id,
     });
 
     let body = serde_json::to_string(&response_body)?;
@@ -99,19 +344,18 @@ async fn handler(state: Arc<AppState>, event: Request) -> Result<Response<Body>,
     Ok(Response::builder()
         .status(200)
         .header("content-type", "application/json")
-        .body(Body::from(body))
-        .expect("Failed to build response"))
+        .body(Body::from(body))?)
 }
 
-fn error_response(status: u16, message: &str) -> Response<Body> {
-    let body = serde_json::to_string(&ApiResponse::<()>::error(message))
-        .unwrap_or_else(|_| r#"{"success":false,"error":"Internal error"}"#.to_string());
-
-    Response::builder()
-        .status(status)
-        .header("content-type", "application/json")
-        .body(Body::from(body))
-        .expect("Failed to build error response")
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default().limit("POST", "/query", Limit::per_minute(QUERIES_PER_MINUTE)))
+        .post("/query", answer)
 }
 
 #[tokio::main]
@@ -122,10 +366,12 @@ async fn main() -> Result<(), Error> {
         .init();
 
     let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
 
     run(service_fn(move |event| {
         let state = Arc::clone(&state);
-        async move { handler(state, event).await }
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
     }))
     .await
 }
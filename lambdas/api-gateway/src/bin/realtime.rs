@@ -0,0 +1,146 @@
+//! Realtime Lambda - Tickets for the WebSocket API.
+//!
+//! Endpoints:
+//! - POST /realtime/ticket - One-time ticket to open a WebSocket connection
+//!
+//! The WebSocket handshake can't carry the Cognito token in a header, so the
+//! client exchanges it here for a ticket valid for
+//! `shared::realtime::TICKET_TTL_SECS` and connects with
+//! `{websocketUrl}?ticket={ticket}` (see the `websocket` Lambda).
+
+use chrono::{Duration, Utc};
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::Serialize;
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::RateLimit;
+use shared::realtime::{ConnectionStore, ConnectionUser, TICKET_TTL_SECS};
+use shared::router::{require_user, Cors, PathParams, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+
+/// Ticket API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct TicketResponse {
+    ticket: String,
+    websocket_url: String,
+    expires_at: String,
+}
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+    connections: ConnectionStore,
+    /// `wss://` URL of the WebSocket API
+    websocket_url: String,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        let table_name = std::env::var("CONNECTIONS_TABLE_NAME")
+            .map_err(|_| "CONNECTIONS_TABLE_NAME not set")?;
+        let websocket_url = std::env::var("WEBSOCKET_URL").map_err(|_| "WEBSOCKET_URL not set")?;
+
+        Ok(Self {
+            db_pool,
+            connections: ConnectionStore::new(aws_sdk_dynamodb::Client::new(&config), table_name),
+            websocket_url,
+        })
+    }
+}
+
+/// POST /realtime/ticket
+#[utoipa::path(
+    post,
+    path = "/realtime/ticket",
+    tag = "realtime",
+    responses(
+        (status = 201, description = "A one-time ticket; connect with `{websocketUrl}?ticket={ticket}` before it expires", body = ApiResponse<TicketResponse>),
+    ),
+    security(("cognito" = []))
+)]
+async fn create_ticket(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let ticket = state
+        .connections
+        .create_ticket(&ConnectionUser {
+            user_id: user.user_id,
+            cognito_sub: user.cognito_sub.clone(),
+            family_ids: user.family_ids.clone(),
+        })
+        .await?;
+
+    json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(TicketResponse {
+                ticket,
+                websocket_url: state.websocket_url.clone(),
+                expires_at: (Utc::now() + Duration::seconds(TICKET_TTL_SECS)).to_rfc3339(),
+            }),
+            error: None,
+        },
+    )
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .post("/realtime/ticket", create_ticket)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
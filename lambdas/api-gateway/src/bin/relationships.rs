@@ -1,27 +1,40 @@
 //! Relationship Management Lambda - Handles user relationships and access tiers.
 //!
+//! A relationship lets its source see the target's data, so creating one only
+//! asks: the target accepts (choosing the access tier) or declines, and nothing
+//! is granted until they accept.
+//!
 //! Endpoints:
-//! - POST /relationships - Create a relationship
+//! - POST /relationships - Request a relationship
 //! - GET /relationships - List user's relationships
-//! - PUT /relationships/{id} - Update access tier
+//! - PUT /relationships/{id} - Lower access tier
 //! - DELETE /relationships/{id} - Remove relationship
+//! - GET /relationships/requests - List pending requests to and from the user
+//! - POST /relationships/requests/{id}/accept - Accept a request
+//! - POST /relationships/requests/{id}/decline - Decline a request
+//! - DELETE /relationships/requests/{id} - Withdraw a request
+//!
+//! The consent flow itself lives in `shared::relationships`.
 
-use lambda_http::{run, service_fn, Body, Error, Request, RequestExt, Response};
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
 use serde::{Deserialize, Serialize};
-use sqlx::postgres::PgPoolOptions;
+use shared::audit::{self, AuditAction, AuditResource};
+use shared::ApiError;
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::RateLimit;
+use shared::relationships::{self, default_access_tier};
+use shared::router::{require_user, Cors, PathParams, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
 use sqlx::PgPool;
 use std::sync::Arc;
 use tracing::{error, info};
 use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-/// Relationship types
-const RELATIONSHIP_TYPES: [&str; 8] = [
-    "spouse", "parent", "child", "sibling", "grandparent", "grandchild", "friend", "other"
-];
-
 /// Create relationship request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct CreateRelationshipRequest {
     target_user_id: String,
     relationship_type: String,
@@ -30,13 +43,19 @@ struct CreateRelationshipRequest {
 }
 
 /// Update relationship request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct UpdateRelationshipRequest {
     access_tier: i16,
 }
 
+/// Accept relationship request
+#[derive(Debug, Default, Deserialize, ToSchema)]
+struct AcceptRelationshipRequest {
+    access_tier: Option<i16>, // 1-4, defaults to the tier requested
+}
+
 /// Relationship response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct RelationshipResponse {
     id: String,
     source_user_id: String,
@@ -48,8 +67,30 @@ struct RelationshipResponse {
     target_user_email: Option<String>,
 }
 
+/// Pending relationship request response
+#[derive(Debug, Serialize, ToSchema)]
+struct RelationshipRequestResponse {
+    id: String,
+    requester_user_id: String,
+    target_user_id: String,
+    relationship_type: String,
+    access_tier: i16,
+    bidirectional: bool,
+    created_at: String,
+    /// The other person: the requester for incoming requests, the target for outgoing
+    user_name: Option<String>,
+    user_email: Option<String>,
+}
+
+/// Pending relationship requests, split by direction
+#[derive(Debug, Serialize, ToSchema)]
+struct RelationshipRequestsResponse {
+    incoming: Vec<RelationshipRequestResponse>,
+    outgoing: Vec<RelationshipRequestResponse>,
+}
+
 /// API response wrapper
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct ApiResponse<T> {
     success: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -66,441 +107,583 @@ struct AppState {
 impl AppState {
     async fn new() -> Result<Self, Error> {
         let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
-        let secrets_client = aws_sdk_secretsmanager::Client::new(&config);
-
-        let db_secret_arn =
-            std::env::var("DB_SECRET_ARN").map_err(|_| "DB_SECRET_ARN not set")?;
 
-        let db_secret = secrets_client
-            .get_secret_value()
-            .secret_id(&db_secret_arn)
-            .send()
+        let db_pool = shared::db::connect(&config)
             .await
-            .map_err(|e| format!("Failed to get DB secret: {}", e))?;
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
 
-        let db_creds: serde_json::Value =
-            serde_json::from_str(db_secret.secret_string().unwrap_or("{}"))?;
+/// Whether the relationship exists with the user as its source.
+async fn owns_relationship(
+    pool: &PgPool,
+    relationship_id: Uuid,
+    user_id: Uuid,
+) -> Result<bool, Error> {
+    let owns_relationship: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM relationships WHERE id = $1 AND source_user_id = $2)",
+    )
+    .bind(relationship_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
 
-        let db_host = std::env::var("DB_HOST").map_err(|_| "DB_HOST not set")?;
-        let db_name = std::env::var("DB_NAME").unwrap_or_else(|_| "second_brain".to_string());
-        let db_user = db_creds["username"].as_str().unwrap_or("sbadmin");
-        let db_pass = db_creds["password"].as_str().unwrap_or("");
+    Ok(owns_relationship)
+}
 
-        let database_url = format!(
-            "postgres://{}:{}@{}:5432/{}",
-            db_user, db_pass, db_host, db_name
-        );
+/// POST /relationships
+#[utoipa::path(
+    post,
+    path = "/relationships",
+    tag = "relationships",
+    request_body = CreateRelationshipRequest,
+    responses(
+        (status = 202, description = "The pending request (`request_id`, `status`); nothing is granted until the target accepts", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Unknown relationship type, yourself as target, or access tier not between 1 and 4", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Target user not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn create_relationship(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let request: CreateRelationshipRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
 
-        let db_pool = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(&database_url)
-            .await
-            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+    let target_user_id = Uuid::parse_str(&request.target_user_id)
+        .map_err(|_| "Invalid target_user_id")?;
 
-        Ok(Self { db_pool })
+    // Validate target user exists
+    let target_exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM users WHERE id = $1)"
+    )
+    .bind(target_user_id)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    if !target_exists {
+        return error_response(404, "Target user not found");
     }
+
+    let access_tier = request.access_tier
+        .unwrap_or_else(|| default_access_tier(&request.relationship_type));
+
+    // Nothing is granted until the target accepts; asking again
+    // replaces an open request
+    let request_id = relationships::request(
+        &state.db_pool,
+        user_id,
+        target_user_id,
+        &request.relationship_type,
+        access_tier,
+        request.bidirectional.unwrap_or(false),
+    )
+    .await?;
+
+    info!(
+        "Requested relationship {} -> {} ({})",
+        user_id, target_user_id, request.relationship_type
+    );
+
+    Ok(json_response(
+        202,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "request_id": request_id.to_string(),
+                "relationship_type": request.relationship_type,
+                "access_tier": access_tier,
+                "status": "pending",
+            })),
+            error: None,
+        },
+    )?)
+}
+
+/// GET /relationships
+#[utoipa::path(
+    get,
+    path = "/relationships",
+    tag = "relationships",
+    responses(
+        (status = 200, description = "Relationships giving you access to others' data", body = ApiResponse<Vec<RelationshipResponse>>),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_relationships(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let relationships: Vec<RelationshipResponse> = sqlx::query_as::<_, (Uuid, Uuid, Uuid, String, i16, chrono::DateTime<chrono::Utc>, Option<String>, Option<String>)>(
+        r#"
+        SELECT r.id, r.source_user_id, r.target_user_id, r.relationship_type,
+               r.access_tier, r.created_at, u.display_name, u.email
+        FROM relationships r
+        JOIN users u ON u.id = r.target_user_id
+        WHERE r.source_user_id = $1
+        ORDER BY r.access_tier, r.relationship_type
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&state.db_pool)
+    .await?
+    .into_iter()
+    .map(|(id, source_user_id, target_user_id, relationship_type, access_tier, created_at, target_user_name, target_user_email)| {
+        RelationshipResponse {
+            id: id.to_string(),
+            source_user_id: source_user_id.to_string(),
+            target_user_id: target_user_id.to_string(),
+            relationship_type,
+            access_tier,
+            created_at: created_at.to_rfc3339(),
+            target_user_name,
+            target_user_email,
+        }
+    })
+    .collect();
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(relationships),
+            error: None,
+        },
+    )?)
 }
 
-/// Get default access tier based on relationship type
-fn default_access_tier(relationship_type: &str) -> i16 {
-    match relationship_type {
-        "spouse" | "parent" => 1, // Full access
-        "child" => 2,              // High access (parent sees child's data)
-        "sibling" => 3,            // Medium access
-        "grandparent" | "grandchild" => 3, // Medium access
-        "friend" => 4,             // Low access
-        _ => 4,                    // Default to low
+/// GET /relationships/requests
+#[utoipa::path(
+    get,
+    path = "/relationships/requests",
+    tag = "relationships",
+    responses(
+        (status = 200, description = "Pending requests to and from you", body = ApiResponse<RelationshipRequestsResponse>),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_requests(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let rows = sqlx::query_as::<_, (Uuid, Uuid, Uuid, String, i16, bool, chrono::DateTime<chrono::Utc>, Option<String>, Option<String>)>(
+        r#"
+        SELECT rr.id, rr.requester_user_id, rr.target_user_id, rr.relationship_type,
+               rr.requested_access_tier, rr.bidirectional, rr.created_at,
+               u.display_name, u.email
+        FROM relationship_requests rr
+        JOIN users u ON u.id = CASE
+            WHEN rr.target_user_id = $1 THEN rr.requester_user_id
+            ELSE rr.target_user_id
+        END
+        WHERE rr.status = 'pending'
+        AND (rr.target_user_id = $1 OR rr.requester_user_id = $1)
+        ORDER BY rr.created_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let mut requests = RelationshipRequestsResponse {
+        incoming: Vec::new(),
+        outgoing: Vec::new(),
+    };
+    for (id, requester_user_id, target_user_id, relationship_type, access_tier, bidirectional, created_at, user_name, user_email) in rows {
+        let request = RelationshipRequestResponse {
+            id: id.to_string(),
+            requester_user_id: requester_user_id.to_string(),
+            target_user_id: target_user_id.to_string(),
+            relationship_type,
+            access_tier,
+            bidirectional,
+            created_at: created_at.to_rfc3339(),
+            user_name,
+            user_email,
+        };
+        if target_user_id == user_id {
+            requests.incoming.push(request);
+        } else {
+            requests.outgoing.push(request);
+        }
     }
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(requests),
+            error: None,
+        },
+    )?)
 }
 
-/// Extract user_id from Cognito claims
-fn extract_user_id(event: &Request) -> Result<Uuid, Error> {
-    let context = event
-        .request_context_ref()
-        .ok_or("Missing request context")?;
+/// POST /relationships/requests/{id}/accept (only the target can accept)
+#[utoipa::path(
+    post,
+    path = "/relationships/requests/{id}/accept",
+    tag = "relationships",
+    request_body(content = AcceptRelationshipRequest, description = "Optional; an empty body grants the tier requested"),
+    params(
+        ("id" = Uuid, Path, description = "Relationship request ID"),
+    ),
+    responses(
+        (status = 201, description = "The relationship created (`relationship_id`, `relationship_type`, `access_tier`)", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Access tier not between 1 and 4", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Relationship request not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn accept_request(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let request_id = params.id("id", "Invalid request ID")?;
+
+    accept_pending(&state, event.body(), user_id, request_id).await
+}
 
-    let claims = context
-        .authorizer()
-        .and_then(|a| a.fields.get("claims"))
-        .ok_or("Missing claims in authorizer context")?;
+/// POST /relationships/requests/{id}/decline (only the target can decline)
+#[utoipa::path(
+    post,
+    path = "/relationships/requests/{id}/decline",
+    tag = "relationships",
+    params(
+        ("id" = Uuid, Path, description = "Relationship request ID"),
+    ),
+    responses(
+        (status = 200, description = "Request declined", body = ApiResponse<serde_json::Value>),
+        (status = 404, description = "Relationship request not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn decline_request(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let request_id = params.id("id", "Invalid request ID")?;
+
+    if !relationships::decline(&state.db_pool, request_id, user_id).await? {
+        return error_response(404, "Relationship request not found");
+    }
+
+    info!("Declined relationship request {}", request_id);
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "message": "Relationship request declined"
+            })),
+            error: None,
+        },
+    )?)
+}
 
-    let sub = claims
-        .get("sub")
-        .and_then(|v| v.as_str())
-        .ok_or("Missing sub claim")?;
+/// DELETE /relationships/requests/{id} (only the requester can withdraw)
+#[utoipa::path(
+    delete,
+    path = "/relationships/requests/{id}",
+    tag = "relationships",
+    params(
+        ("id" = Uuid, Path, description = "Relationship request ID"),
+    ),
+    responses(
+        (status = 200, description = "Request withdrawn", body = ApiResponse<serde_json::Value>),
+        (status = 404, description = "Relationship request not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn withdraw_request(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let request_id = params.id("id", "Invalid request ID")?;
+
+    if !relationships::withdraw(&state.db_pool, request_id, user_id).await? {
+        return error_response(404, "Relationship request not found");
+    }
 
-    Uuid::parse_str(sub).map_err(|e| format!("Invalid user_id: {}", e).into())
+    info!("Withdrew relationship request {}", request_id);
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "message": "Relationship request withdrawn"
+            })),
+            error: None,
+        },
+    )?)
 }
 
-async fn handler(state: Arc<AppState>, event: Request) -> Result<Response<Body>, Error> {
-    let raw_path = event.uri().path();
-    // Strip /api stage prefix if present (API Gateway REST API includes stage in path)
-    let path = raw_path.strip_prefix("/api").unwrap_or(raw_path);
-    let method = event.method().as_str();
-
-    let cognito_sub = match extract_user_id(&event) {
-        Ok(id) => id,
-        Err(e) => {
-            return Ok(json_response(
-                401,
-                &ApiResponse::<()> {
-                    success: false,
-                    data: None,
-                    error: Some(format!("Authentication required: {}", e)),
-                },
-            )?);
-        }
+/// PUT /relationships/{id}
+#[utoipa::path(
+    put,
+    path = "/relationships/{id}",
+    tag = "relationships",
+    request_body = UpdateRelationshipRequest,
+    params(
+        ("id" = Uuid, Path, description = "Relationship ID"),
+    ),
+    responses(
+        (status = 200, description = "The new access tier", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Access tier not between 1 and 4", body = ApiError, content_type = "application/problem+json"),
+        (status = 403, description = "Raising an access tier needs a new relationship request", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Relationship not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn update_relationship(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let relationship_id = params.id("id", "Invalid relationship ID")?;
+
+    let request: UpdateRelationshipRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
     };
 
-    // Look up database user_id from Cognito sub
-    let user_id: Uuid = match sqlx::query_scalar::<_, Uuid>(
-        "SELECT id FROM users WHERE cognito_sub = $1::text"
+    let before = audit::snapshot(&state.db_pool, AuditResource::Relationship, relationship_id).await;
+
+    // Raising access needs the target's consent, so it takes a
+    // new request; lowering it doesn't
+    let changed = relationships::change_access_tier(
+        &state.db_pool,
+        relationship_id,
+        user_id,
+        request.access_tier,
     )
-    .bind(cognito_sub)
-    .fetch_optional(&state.db_pool)
-    .await
-    .map_err(|e| format!("Failed to lookup user: {}", e))? {
-        Some(id) => id,
-        None => {
-            return Ok(json_response(
-                401,
-                &ApiResponse::<()> {
-                    success: false,
-                    data: None,
-                    error: Some("User not registered".to_string()),
-                },
-            )?);
-        }
-    };
+    .await?;
 
-    match (method, path) {
-        // Create relationship
-        ("POST", "/relationships") => {
-            let request: CreateRelationshipRequest = match shared::parse_json_body(event.body())? {
-                Ok(r) => r,
-                Err(response) => return Ok(response),
-            };
-
-            // Validate relationship type
-            if !RELATIONSHIP_TYPES.contains(&request.relationship_type.as_str()) {
-                return Ok(json_response(
-                    400,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some(format!(
-                            "Invalid relationship type. Must be one of: {:?}",
-                            RELATIONSHIP_TYPES
-                        )),
-                    },
-                )?);
-            }
-
-            let target_user_id = Uuid::parse_str(&request.target_user_id)
-                .map_err(|_| "Invalid target_user_id")?;
-
-            // Validate target user exists
-            let target_exists: bool = sqlx::query_scalar(
-                "SELECT EXISTS(SELECT 1 FROM users WHERE id = $1)"
-            )
-            .bind(target_user_id)
-            .fetch_one(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to verify target user: {}", e))?;
-
-            if !target_exists {
-                return Ok(json_response(
-                    404,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Target user not found".to_string()),
-                    },
-                )?);
-            }
-
-            // Validate access tier
-            let access_tier = request.access_tier
-                .unwrap_or_else(|| default_access_tier(&request.relationship_type));
-
-            if !(1..=4).contains(&access_tier) {
-                return Ok(json_response(
-                    400,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Access tier must be between 1 and 4".to_string()),
-                    },
-                )?);
-            }
-
-            let relationship_id = Uuid::new_v4();
-
-            // Create relationship
-            sqlx::query(
-                r#"
-                INSERT INTO relationships (id, source_user_id, target_user_id, relationship_type, access_tier)
-                VALUES ($1, $2, $3, $4, $5)
-                ON CONFLICT (source_user_id, target_user_id) DO UPDATE SET
-                    relationship_type = EXCLUDED.relationship_type,
-                    access_tier = EXCLUDED.access_tier
-                "#,
-            )
-            .bind(relationship_id)
-            .bind(user_id)
-            .bind(target_user_id)
-            .bind(&request.relationship_type)
-            .bind(access_tier)
-            .execute(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to create relationship: {}", e))?;
-
-            // Create bidirectional relationship if requested
-            if request.bidirectional.unwrap_or(false) {
-                let reverse_type = get_reverse_relationship_type(&request.relationship_type);
-                let reverse_tier = default_access_tier(&reverse_type);
-
-                sqlx::query(
-                    r#"
-                    INSERT INTO relationships (id, source_user_id, target_user_id, relationship_type, access_tier)
-                    VALUES ($1, $2, $3, $4, $5)
-                    ON CONFLICT (source_user_id, target_user_id) DO UPDATE SET
-                        relationship_type = EXCLUDED.relationship_type,
-                        access_tier = EXCLUDED.access_tier
-                    "#,
-                )
-                .bind(Uuid::new_v4())
-                .bind(target_user_id)
-                .bind(user_id)
-                .bind(&reverse_type)
-                .bind(reverse_tier)
-                .execute(&state.db_pool)
-                .await
-                .map_err(|e| format!("Failed to create reverse relationship: {}", e))?;
-            }
-
-            // Refresh access cache
-            refresh_access_cache(&state.db_pool, user_id).await?;
-            if request.bidirectional.unwrap_or(false) {
-                refresh_access_cache(&state.db_pool, target_user_id).await?;
-            }
-
-            info!(
-                "Created relationship {} -> {} ({})",
-                user_id, target_user_id, request.relationship_type
-            );
-
-            Ok(json_response(
-                201,
-                &ApiResponse {
-                    success: true,
-                    data: Some(serde_json::json!({
-                        "relationship_id": relationship_id.to_string(),
-                        "relationship_type": request.relationship_type,
-                        "access_tier": access_tier,
-                    })),
-                    error: None,
-                },
-            )?)
-        }
+    if !changed {
+        return error_response(404, "Relationship not found");
+    }
 
-        // List relationships
-        ("GET", "/relationships") => {
-            let relationships: Vec<RelationshipResponse> = sqlx::query_as::<_, (Uuid, Uuid, Uuid, String, i16, chrono::DateTime<chrono::Utc>, Option<String>, Option<String>)>(
-                r#"
-                SELECT r.id, r.source_user_id, r.target_user_id, r.relationship_type,
-                       r.access_tier, r.created_at, u.display_name, u.email
-                FROM relationships r
-                JOIN users u ON u.id = r.target_user_id
-                WHERE r.source_user_id = $1
-                ORDER BY r.access_tier, r.relationship_type
-                "#,
-            )
-            .bind(user_id)
-            .fetch_all(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to fetch relationships: {}", e))?
-            .into_iter()
-            .map(|(id, source_user_id, target_user_id, relationship_type, access_tier, created_at, target_user_name, target_user_email)| {
-                RelationshipResponse {
-                    id: id.to_string(),
-                    source_user_id: source_user_id.to_string(),
-                    target_user_id: target_user_id.to_string(),
-                    relationship_type,
-                    access_tier,
-                    created_at: created_at.to_rfc3339(),
-                    target_user_name,
-                    target_user_email,
-                }
-            })
-            .collect();
-
-            Ok(json_response(
-                200,
-                &ApiResponse {
-                    success: true,
-                    data: Some(relationships),
-                    error: None,
-                },
-            )?)
-        }
+    audit::record_change(&state.db_pool, user_id, AuditAction::Update, AuditResource::Relationship, relationship_id, before).await;
+
+    info!("Updated relationship {} to tier {}", relationship_id, request.access_tier);
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "message": "Relationship updated",
+                "access_tier": request.access_tier,
+            })),
+            error: None,
+        },
+    )?)
+}
 
-        // Update or delete specific relationship
-        _ if path.starts_with("/relationships/") => {
-            let relationship_id = path
-                .trim_start_matches("/relationships/")
-                .split('/')
-                .next()
-                .ok_or("Missing relationship ID")?;
-
-            let relationship_id = Uuid::parse_str(relationship_id)
-                .map_err(|_| "Invalid relationship ID")?;
-
-            // Verify ownership
-            let owns_relationship: bool = sqlx::query_scalar(
-                "SELECT EXISTS(SELECT 1 FROM relationships WHERE id = $1 AND source_user_id = $2)"
-            )
+/// DELETE /relationships/{id}
+#[utoipa::path(
+    delete,
+    path = "/relationships/{id}",
+    tag = "relationships",
+    params(
+        ("id" = Uuid, Path, description = "Relationship ID"),
+    ),
+    responses(
+        (status = 200, description = "Relationship deleted", body = ApiResponse<serde_json::Value>),
+        (status = 404, description = "Relationship not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn delete_relationship(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let relationship_id = params.id("id", "Invalid relationship ID")?;
+    if !owns_relationship(&state.db_pool, relationship_id, user_id).await? {
+        return error_response(404, "Relationship not found");
+    }
+
+    let before = audit::snapshot(&state.db_pool, AuditResource::Relationship, relationship_id).await;
+
+    shared::db::with_tx(&state.db_pool, async |tx| {
+        sqlx::query("DELETE FROM relationships WHERE id = $1")
             .bind(relationship_id)
-            .bind(user_id)
-            .fetch_one(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to verify ownership: {}", e))?;
-
-            if !owns_relationship {
-                return Ok(json_response(
-                    404,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Relationship not found".to_string()),
-                    },
-                )?);
-            }
-
-            match method {
-                // Update access tier
-                "PUT" => {
-                    let request: UpdateRelationshipRequest = match shared::parse_json_body(event.body())? {
-                        Ok(r) => r,
-                        Err(response) => return Ok(response),
-                    };
-
-                    if !(1..=4).contains(&request.access_tier) {
-                        return Ok(json_response(
-                            400,
-                            &ApiResponse::<()> {
-                                success: false,
-                                data: None,
-                                error: Some("Access tier must be between 1 and 4".to_string()),
-                            },
-                        )?);
-                    }
-
-                    sqlx::query(
-                        "UPDATE relationships SET access_tier = $1 WHERE id = $2"
-                    )
-                    .bind(request.access_tier)
-                    .bind(relationship_id)
-                    .execute(&state.db_pool)
-                    .await
-                    .map_err(|e| format!("Failed to update relationship: {}", e))?;
-
-                    // Refresh access cache
-                    refresh_access_cache(&state.db_pool, user_id).await?;
-
-                    info!("Updated relationship {} to tier {}", relationship_id, request.access_tier);
-
-                    Ok(json_response(
-                        200,
-                        &ApiResponse {
-                            success: true,
-                            data: Some(serde_json::json!({
-                                "message": "Relationship updated",
-                                "access_tier": request.access_tier,
-                            })),
-                            error: None,
-                        },
-                    )?)
-                }
-
-                // Delete relationship
-                "DELETE" => {
-                    sqlx::query("DELETE FROM relationships WHERE id = $1")
-                        .bind(relationship_id)
-                        .execute(&state.db_pool)
-                        .await
-                        .map_err(|e| format!("Failed to delete relationship: {}", e))?;
-
-                    // Refresh access cache
-                    refresh_access_cache(&state.db_pool, user_id).await?;
-
-                    info!("Deleted relationship {}", relationship_id);
-
-                    Ok(json_response(
-                        200,
-                        &ApiResponse {
-                            success: true,
-                            data: Some(serde_json::json!({
-                                "message": "Relationship deleted"
-                            })),
-                            error: None,
-                        },
-                    )?)
-                }
-
-                _ => Ok(json_response(
-                    405,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Method not allowed".to_string()),
-                    },
-                )?),
-            }
-        }
+            .execute(&mut *tx)
+            .await?;
+
+        relationships::refresh_access_cache(tx, user_id).await
+    })
+    .await?;
+
+    audit::record(&state.db_pool, user_id, AuditAction::Delete, AuditResource::Relationship, relationship_id, before, None).await;
+
+    info!("Deleted relationship {}", relationship_id);
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "message": "Relationship deleted"
+            })),
+            error: None,
+        },
+    )?)
+}
+
+/// Accept a pending request sent to `user_id`: create the relationship at the
+/// tier they choose (and the reverse one, if requested) and grant its access.
+async fn accept_pending(
+    state: &AppState,
+    body: &Body,
+    user_id: Uuid,
+    request_id: Uuid,
+) -> Result<Response<Body>, Error> {
+    // Every field is optional, so an empty body accepts the requested tier
+    let accept: AcceptRelationshipRequest = match body {
+        Body::Empty => AcceptRelationshipRequest::default(),
+        body => match shared::parse_json_body(body)? {
+            Ok(r) => r,
+            Err(response) => return Ok(response),
+        },
+    };
+
+    let Some(pending) = relationships::pending_request(&state.db_pool, request_id, user_id).await? else {
+        return error_response(404, "Relationship request not found");
+    };
+    let requester_id = pending.requester_user_id;
+    let access_tier = accept.access_tier.unwrap_or(pending.requested_access_tier);
+
+    // Any relationships being replaced, for the audit log
+    let before = relationship_snapshot(&state.db_pool, requester_id, user_id).await;
+    let reverse_before = match pending.reverse() {
+        Some(_) => relationship_snapshot(&state.db_pool, user_id, requester_id).await,
+        None => None,
+    };
 
-        _ => Ok(json_response(
-            404,
-            &ApiResponse::<()> {
-                success: false,
-                data: None,
-                error: Some("Not found".to_string()),
-            },
-        )?),
+    // Accepted or declined concurrently
+    let Some(accepted) = relationships::accept(&state.db_pool, &pending, access_tier).await? else {
+        return error_response(404, "Relationship request not found");
+    };
+
+    record_upsert(&state.db_pool, user_id, accepted.relationship_id, before).await;
+    if let Some(reverse_id) = accepted.reverse_id {
+        record_upsert(&state.db_pool, user_id, reverse_id, reverse_before).await;
     }
+
+    info!(
+        "Accepted relationship request {}: {} -> {} ({}, tier {})",
+        request_id, requester_id, user_id, pending.relationship_type, access_tier
+    );
+
+    Ok(json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "relationship_id": accepted.relationship_id.to_string(),
+                "relationship_type": pending.relationship_type,
+                "access_tier": access_tier,
+            })),
+            error: None,
+        },
+    )?)
 }
 
-/// Get the reverse relationship type for bidirectional relationships
-fn get_reverse_relationship_type(relationship_type: &str) -> String {
-    match relationship_type {
-        "parent" => "child".to_string(),
-        "child" => "parent".to_string(),
-        "grandparent" => "grandchild".to_string(),
-        "grandchild" => "grandparent".to_string(),
-        // Symmetric relationships
-        "spouse" | "sibling" | "friend" | "other" => relationship_type.to_string(),
-        _ => "other".to_string(),
+/// Current state of the relationship between two users, if there is one.
+async fn relationship_snapshot(
+    pool: &PgPool,
+    source_user_id: Uuid,
+    target_user_id: Uuid,
+) -> Option<serde_json::Value> {
+    let existing: Option<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM relationships WHERE source_user_id = $1 AND target_user_id = $2"
+    )
+    .bind(source_user_id)
+    .bind(target_user_id)
+    .fetch_optional(pool)
+    .await
+    .ok()
+    .flatten();
+
+    match existing {
+        Some(id) => audit::snapshot(pool, AuditResource::Relationship, id).await,
+        None => None,
     }
 }
 
-/// Refresh the user_access_cache for a user using the database function
-async fn refresh_access_cache(pool: &PgPool, user_id: Uuid) -> Result<(), Error> {
-    // Use the database function to properly refresh the cache
-    sqlx::query("SELECT refresh_user_access_cache($1)")
-        .bind(user_id)
-        .execute(pool)
-        .await
-        .map_err(|e| format!("Failed to refresh access cache: {}", e))?;
+/// Record a relationship saved by `upsert_relationship`, as an update if it
+/// existed before (`before` is its earlier snapshot) and a create otherwise.
+async fn record_upsert(
+    pool: &PgPool,
+    actor_id: Uuid,
+    relationship_id: Uuid,
+    before: Option<serde_json::Value>,
+) {
+    let action = if before.is_some() { AuditAction::Update } else { AuditAction::Create };
+    audit::record_change(pool, actor_id, action, AuditResource::Relationship, relationship_id, before).await;
+}
 
-    Ok(())
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .post("/relationships", create_relationship)
+        .get("/relationships", list_relationships)
+        .get("/relationships/requests", list_requests)
+        .post("/relationships/requests/{id}/accept", accept_request)
+        .post("/relationships/requests/{id}/decline", decline_request)
+        .delete("/relationships/requests/{id}", withdraw_request)
+        .put("/relationships/{id}", update_relationship)
+        .delete("/relationships/{id}", delete_relationship)
 }
 
 fn json_response<T: Serialize>(status: u16, data: &T) -> Result<Response<Body>, Error> {
     Ok(Response::builder()
         .status(status)
         .header("content-type", "application/json")
-        .body(Body::from(serde_json::to_string(data)?))
-        .expect("Failed to build response"))
+        .body(Body::from(serde_json::to_string(data)?))?)
 }
 
 #[tokio::main]
@@ -511,10 +694,12 @@ async fn main() -> Result<(), Error> {
         .init();
 
     let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
 
     run(service_fn(move |event| {
         let state = Arc::clone(&state);
-        async move { handler(state, event).await }
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
     }))
     .await
 }
@@ -2,24 +2,48 @@
 //!
 //! Endpoints:
 //! - POST /reminders - Create a reminder
-//! - GET /reminders - List reminders
-//! - GET /reminders/{id} - Get a single reminder
-//! - PUT /reminders/{id} - Update a reminder
-//! - POST /reminders/{id}/snooze - Snooze a reminder
+//! - GET /reminders - List reminders (`?limit=&cursor=`)
+//! - GET /reminders/{id} - Get a single reminder (with an `ETag`; honors `If-None-Match`)
+//! - PUT /reminders/{id} - Update a reminder (honors `If-Match`)
+//! - POST /reminders/{id}/snooze - Snooze a reminder (`snoozeUntil`, or a `preset` such as
+//!   `"10m"` or `"tomorrow_morning"`); reminders snoozed past `maxSnoozes` escalate
+//! - POST /reminders/{id}/complete - Mark a reminder (or this occurrence) done
+//! - GET /reminders/history - Completed and missed occurrences with weekly stats
+//! - POST /reminders/{id}/simulate - Dry-run the evaluator against a supplied time
 //! - DELETE /reminders/{id} - Delete a reminder
+//!
+//! A `time` reminder with `"inTimeToLeave": true` in its trigger config (and an
+//! optional `travelMode` and `bufferMinutes`) fires when it's time to leave for
+//! its related entity; the reminder evaluator estimates the travel time.
 
-use chrono::{DateTime, NaiveTime, Utc};
-use lambda_http::{run, service_fn, Body, Error, Request, RequestExt, Response};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
 use serde::{Deserialize, Serialize};
-use sqlx::postgres::PgPoolOptions;
+use shared::conditional::{self, IfMatch};
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::models::{keyset_predicate, Cursor, CursorParams, Page, SortDirection};
+use shared::ratelimit::RateLimit;
+use shared::recurrence::{user_timezone, Schedule};
+use shared::reminders::{
+    check_due, escalated_delivery, is_in_quiet_hours, preferred_channel, quiet_hours_end,
+    resolve_snooze_preset, scheduled_at, snooze_decision, DueCheck, LeaveInTime,
+    NotificationPreferences, SnoozeDecision, SnoozeEscalation,
+};
+use shared::router::{
+    json_body, require_user, Cors, PathParams, Query, RequestLogger, RequireAuth, Router,
+};
+use shared::shaping::ResponseShaping;
+use shared::ApiError;
 use sqlx::PgPool;
 use std::sync::Arc;
-use tracing::info;
 use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 /// Create reminder request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 struct CreateReminderRequest {
     title: String,
@@ -29,10 +53,12 @@ struct CreateReminderRequest {
     priority: Option<i16>,
     related_entity_id: Option<String>,
     related_fact_id: Option<String>,
+    max_snoozes: Option<i16>,         // 0 or absent for no limit
+    snooze_escalation: Option<String>, // priority, channel, block
 }
 
 /// Update reminder request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 struct UpdateReminderRequest {
     title: Option<String>,
@@ -40,13 +66,49 @@ struct UpdateReminderRequest {
     trigger_config: Option<serde_json::Value>,
     priority: Option<i16>,
     status: Option<String>,
+    max_snoozes: Option<i16>, // 0 removes the limit
+    snooze_escalation: Option<String>,
 }
 
-/// Snooze reminder request
-#[derive(Debug, Deserialize)]
+/// Snooze reminder request: an exact time or a preset
+#[derive(Debug, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 struct SnoozeReminderRequest {
-    snooze_until: String, // ISO 8601 datetime
+    snooze_until: Option<String>, // ISO 8601 datetime
+    preset: Option<String>,       // "10m", "1h", "tomorrow_morning", ...
+}
+
+/// Simulate reminder request
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct SimulateReminderRequest {
+    now: Option<String>, // ISO 8601 datetime, defaults to the current time
+}
+
+/// One step of the evaluator's decision trace
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct SimulationStep {
+    step: &'static str,
+    passed: bool,
+    detail: String,
+}
+
+/// Result of simulating the reminder evaluator
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct SimulationResponse {
+    reminder_id: String,
+    now: String,
+    due_check: DueCheck,
+    in_quiet_hours: bool,
+    /// When a notification queued now would be released (quiet hours end)
+    deferred_until: Option<String>,
+    channel: &'static str,
+    would_notify: bool,
+    resulting_status: String,
+    next_trigger_at: Option<String>,
+    trace: Vec<SimulationStep>,
 }
 
 /// Reminder response from database
@@ -63,6 +125,10 @@ struct ReminderRow {
     next_trigger_at: Option<DateTime<Utc>>,
     last_triggered_at: Option<DateTime<Utc>>,
     snooze_until: Option<DateTime<Utc>>,
+    snooze_count: i32,
+    max_snoozes: Option<i16>,
+    snooze_escalation: String,
+    escalated: bool,
     related_entity_id: Option<Uuid>,
     related_fact_id: Option<Uuid>,
     created_at: DateTime<Utc>,
@@ -70,7 +136,7 @@ struct ReminderRow {
 }
 
 /// Reminder API response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 struct ReminderResponse {
     id: String,
@@ -83,6 +149,10 @@ struct ReminderResponse {
     next_trigger_at: Option<String>,
     last_triggered_at: Option<String>,
     snooze_until: Option<String>,
+    snooze_count: i32,
+    max_snoozes: Option<i16>,
+    snooze_escalation: String,
+    escalated: bool,
     related_entity_id: Option<String>,
     related_fact_id: Option<String>,
     created_at: String,
@@ -102,6 +172,10 @@ impl From<ReminderRow> for ReminderResponse {
             next_trigger_at: row.next_trigger_at.map(|dt| dt.to_rfc3339()),
             last_triggered_at: row.last_triggered_at.map(|dt| dt.to_rfc3339()),
             snooze_until: row.snooze_until.map(|dt| dt.to_rfc3339()),
+            snooze_count: row.snooze_count,
+            max_snoozes: row.max_snoozes,
+            snooze_escalation: row.snooze_escalation,
+            escalated: row.escalated,
             related_entity_id: row.related_entity_id.map(|u| u.to_string()),
             related_fact_id: row.related_fact_id.map(|u| u.to_string()),
             created_at: row.created_at.to_rfc3339(),
@@ -110,8 +184,62 @@ impl From<ReminderRow> for ReminderResponse {
     }
 }
 
+/// Pending occurrences older than this count as missed
+const MISSED_AFTER_HOURS: i64 = 24;
+
+/// Weeks of stats returned by the history endpoint when `from` is not given
+const HISTORY_STATS_WEEKS: i64 = 12;
+
+/// Reminder occurrence from database
+#[derive(Debug, sqlx::FromRow)]
+struct OccurrenceRow {
+    id: Uuid,
+    reminder_id: Uuid,
+    title: String,
+    trigger_type: String,
+    due_at: DateTime<Utc>,
+    outcome: String,
+    completed_at: Option<DateTime<Utc>>,
+}
+
+/// Reminder occurrence API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct OccurrenceResponse {
+    id: String,
+    reminder_id: String,
+    title: String,
+    trigger_type: String,
+    due_at: String,
+    outcome: String,
+    completed_at: Option<String>,
+}
+
+impl From<OccurrenceRow> for OccurrenceResponse {
+    fn from(row: OccurrenceRow) -> Self {
+        Self {
+            id: row.id.to_string(),
+            reminder_id: row.reminder_id.to_string(),
+            title: row.title,
+            trigger_type: row.trigger_type,
+            due_at: row.due_at.to_rfc3339(),
+            outcome: row.outcome,
+            completed_at: row.completed_at.map(|dt| dt.to_rfc3339()),
+        }
+    }
+}
+
+/// Completed and missed occurrences in one week
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct WeekStats {
+    week_start: NaiveDate,
+    completed: i64,
+    missed: i64,
+}
+
 /// API response wrapper
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct ApiResponse<T> {
     success: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -128,34 +256,8 @@ struct AppState {
 impl AppState {
     async fn new() -> Result<Self, Error> {
         let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
-        let secrets_client = aws_sdk_secretsmanager::Client::new(&config);
-
-        let db_secret_arn =
-            std::env::var("DB_SECRET_ARN").map_err(|_| "DB_SECRET_ARN not set")?;
-
-        let db_secret = secrets_client
-            .get_secret_value()
-            .secret_id(&db_secret_arn)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to get DB secret: {}", e))?;
 
-        let db_creds: serde_json::Value =
-            serde_json::from_str(db_secret.secret_string().unwrap_or("{}"))?;
-
-        let db_host = std::env::var("DB_HOST").map_err(|_| "DB_HOST not set")?;
-        let db_name = std::env::var("DB_NAME").unwrap_or_else(|_| "second_brain".to_string());
-        let db_user = db_creds["username"].as_str().unwrap_or("sbadmin");
-        let db_pass = db_creds["password"].as_str().unwrap_or("");
-
-        let database_url = format!(
-            "postgres://{}:{}@{}:5432/{}",
-            db_user, db_pass, db_host, db_name
-        );
-
-        let db_pool = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(&database_url)
+        let db_pool = shared::db::connect(&config)
             .await
             .map_err(|e| format!("Failed to connect to database: {}", e))?;
 
@@ -163,55 +265,27 @@ impl AppState {
     }
 }
 
-/// Extract user_id from Cognito claims
-fn extract_user_id(event: &Request) -> Result<Uuid, Error> {
-    let context = event
-        .request_context_ref()
-        .ok_or("Missing request context")?;
-
-    let claims = context
-        .authorizer()
-        .and_then(|a| a.fields.get("claims"))
-        .ok_or("Missing claims")?;
-
-    let sub = claims
-        .as_object()
-        .and_then(|c| c.get("sub"))
-        .and_then(|s| s.as_str())
-        .ok_or("Missing sub claim")?;
-
-    Uuid::parse_str(sub).map_err(|_| "Invalid user ID".into())
-}
-
-/// Calculate next trigger time based on trigger type and config
+/// Calculate next trigger time based on trigger type and config.
+///
+/// Recurring schedules are evaluated in `timezone` unless the config names one;
+/// an invalid schedule or `inTimeToLeave` option is a validation error.
 fn calculate_next_trigger(
     trigger_type: &str,
     trigger_config: &serde_json::Value,
-) -> Option<DateTime<Utc>> {
-    match trigger_type {
+    timezone: Tz,
+    now: DateTime<Utc>,
+) -> shared::Result<Option<DateTime<Utc>>> {
+    let next = match trigger_type {
         "time" => {
-            // Direct datetime trigger - support multiple field names for compatibility
-            trigger_config
-                .get("scheduledAt")
-                .or_else(|| trigger_config.get("triggerAt"))
-                .or_else(|| trigger_config.get("at"))
-                .and_then(|v| v.as_str())
-                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-                .map(|dt| dt.with_timezone(&Utc))
-        }
-        "recurring" => {
-            // For recurring, calculate next occurrence based on pattern
-            // This is a simplified version - the DB function handles full logic
-            let time_str = trigger_config
-                .get("time")
-                .and_then(|v| v.as_str())
-                .unwrap_or("09:00");
-
-            let time = NaiveTime::parse_from_str(time_str, "%H:%M").ok()?;
-            let today = Utc::now().date_naive();
-            let datetime = today.and_time(time);
-            Some(DateTime::from_naive_utc_and_offset(datetime, Utc))
+            // Reminders to leave in time start with just the buffer; the
+            // evaluator moves them earlier once it has a travel estimate
+            let at = scheduled_at(trigger_config);
+            match LeaveInTime::from_config(trigger_config)? {
+                Some(leave) => at.map(|at| leave.leave_at(at, None)),
+                None => at,
+            }
         }
+        "recurring" => Schedule::from_config(trigger_config, timezone)?.next_after(now),
         "event" => {
             // Event-based triggers are evaluated by the reminder evaluator
             // Set a far-future placeholder
@@ -227,517 +301,1239 @@ fn calculate_next_trigger(
             None
         }
         _ => None,
-    }
+    };
+
+    Ok(next)
+}
+
+/// Replay the reminder evaluator's decision logic against `now` without side effects.
+async fn simulate_reminder(
+    pool: &PgPool,
+    reminder: &ReminderRow,
+    now: DateTime<Utc>,
+) -> Result<SimulationResponse, Error> {
+    let mut trace = Vec::new();
+
+    let due_check = check_due(
+        &reminder.status,
+        reminder.next_trigger_at,
+        reminder.snooze_until,
+        now,
+    );
+    trace.push(SimulationStep {
+        step: "due_check",
+        passed: due_check == DueCheck::Due,
+        detail: format!(
+            "status={}, next_trigger_at={}, snooze_until={} -> {:?}",
+            reminder.status,
+            reminder.next_trigger_at.map(|dt| dt.to_rfc3339()).unwrap_or_else(|| "none".to_string()),
+            reminder.snooze_until.map(|dt| dt.to_rfc3339()).unwrap_or_else(|| "none".to_string()),
+            due_check,
+        ),
+    });
+
+    let stored_prefs: Option<NotificationPreferences> = sqlx::query_as(
+        r#"
+        SELECT
+            push_enabled,
+            email_enabled,
+            discord_enabled,
+            alexa_enabled,
+            quiet_hours_enabled,
+            quiet_hours_start,
+            quiet_hours_end,
+            timezone
+        FROM user_notification_preferences
+        WHERE user_id = $1
+        "#,
+    )
+    .bind(reminder.user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let using_defaults = stored_prefs.is_none();
+    let prefs = stored_prefs.unwrap_or_default();
+
+    let in_quiet_hours = is_in_quiet_hours(&prefs, now);
+    let deferred_until = quiet_hours_end(&prefs, now);
+    trace.push(SimulationStep {
+        step: "quiet_hours",
+        passed: !in_quiet_hours,
+        detail: if !prefs.quiet_hours_enabled {
+            "quiet hours disabled".to_string()
+        } else {
+            format!(
+                "window {}-{} {} ({}), now {}{}",
+                prefs.quiet_hours_start.map(|t| t.to_string()).unwrap_or_default(),
+                prefs.quiet_hours_end.map(|t| t.to_string()).unwrap_or_default(),
+                prefs.timezone,
+                if in_quiet_hours { "inside" } else { "outside" },
+                now.with_timezone(&prefs.tz()).time().format("%H:%M:%S"),
+                deferred_until
+                    .map(|dt| format!(" -> deferred until {}", dt.to_rfc3339()))
+                    .unwrap_or_default(),
+            )
+        },
+    });
+
+    // Snoozed past its limit, the evaluator escalates the notification
+    let escalation = SnoozeEscalation::parse(&reminder.snooze_escalation).filter(|_| reminder.escalated);
+    let channel = match escalation {
+        Some(escalation) => escalated_delivery(escalation, &prefs, reminder.priority).0,
+        None => preferred_channel(&prefs),
+    };
+    trace.push(SimulationStep {
+        step: "channel_selection",
+        passed: true,
+        detail: format!(
+            "discord={}, push={}, email={}, alexa={}{}{} -> {}",
+            prefs.discord_enabled,
+            prefs.push_enabled,
+            prefs.email_enabled,
+            prefs.alexa_enabled,
+            if using_defaults { " (default preferences)" } else { "" },
+            if escalation.is_some() {
+                format!(", escalated ({})", reminder.snooze_escalation)
+            } else {
+                String::new()
+            },
+            channel,
+        ),
+    });
+
+    // Quiet hours defer the notification rather than skipping the reminder
+    let would_notify = due_check == DueCheck::Due;
+
+    let (resulting_status, next_trigger_at) = if !would_notify {
+        (reminder.status.clone(), reminder.next_trigger_at)
+    } else if let Some(next) = (reminder.trigger_type == "recurring")
+        .then(|| Schedule::from_config(&reminder.trigger_config, prefs.tz()).ok())
+        .flatten()
+        .and_then(|schedule| schedule.next_after(now))
+    {
+        // Same schedule the evaluator uses to reschedule; an invalid one ends it
+        (reminder.status.clone(), Some(next))
+    } else {
+        ("triggered".to_string(), reminder.next_trigger_at)
+    };
+
+    trace.push(SimulationStep {
+        step: "recurrence_advance",
+        passed: would_notify,
+        detail: if !would_notify {
+            "no notification, reminder unchanged".to_string()
+        } else if resulting_status != "triggered" {
+            format!(
+                "recurring -> next_trigger_at={}",
+                next_trigger_at.map(|dt| dt.to_rfc3339()).unwrap_or_else(|| "none".to_string())
+            )
+        } else {
+            format!("{} -> status=triggered", reminder.trigger_type)
+        },
+    });
+
+    Ok(SimulationResponse {
+        reminder_id: reminder.id.to_string(),
+        now: now.to_rfc3339(),
+        due_check,
+        in_quiet_hours,
+        deferred_until: deferred_until.map(|dt| dt.to_rfc3339()),
+        channel,
+        would_notify,
+        resulting_status,
+        next_trigger_at: next_trigger_at.map(|dt| dt.to_rfc3339()),
+        trace,
+    })
 }
 
-async fn handler(state: Arc<AppState>, event: Request) -> Result<Response<Body>, Error> {
-    let method = event.method().as_str();
-    let raw_path = event.uri().path();
-    // Strip /api stage prefix if present (API Gateway REST API includes stage in path)
-    let path = raw_path.strip_prefix("/api").unwrap_or(raw_path);
+fn not_found() -> Result<Response<Body>, Error> {
+    error_response(404, "Reminder not found")
+}
 
-    info!("Reminders request: {} {}", method, path);
+fn bad_request(message: impl Into<String>) -> Result<Response<Body>, Error> {
+    error_response(400, message.into())
+}
 
-    // Extract user
-    let cognito_sub = match extract_user_id(&event) {
-        Ok(id) => id,
-        Err(e) => {
-            return Ok(json_response(
-                401,
-                &ApiResponse::<()> {
-                    success: false,
-                    data: None,
-                    error: Some(e.to_string()),
-                },
-            )?)
+/// Validate the max-snooze policy fields; `0` max snoozes means no limit.
+fn snooze_policy(
+    max_snoozes: Option<i16>,
+    escalation: Option<&str>,
+) -> Result<(Option<i16>, Option<&str>), String> {
+    if max_snoozes.is_some_and(|max| max < 0) {
+        return Err("maxSnoozes must not be negative".to_string());
+    }
+    if let Some(escalation) = escalation {
+        if SnoozeEscalation::parse(escalation).is_none() {
+            return Err("Invalid snoozeEscalation. Must be one of: priority, channel, block".to_string());
+        }
+    }
+    Ok((max_snoozes.filter(|max| *max > 0), escalation))
+}
+
+/// Parse the `{id}` path parameter, returning early with 400 if it is not a UUID.
+macro_rules! reminder_id {
+    ($params:expr) => {
+        match $params.get::<Uuid>("id") {
+            Ok(id) => id,
+            Err(_) => return bad_request("Invalid reminder ID"),
         }
     };
+}
 
-    // Look up database user_id from Cognito sub
-    let user_id: Uuid = match sqlx::query_scalar::<_, Uuid>(
-        "SELECT id FROM users WHERE cognito_sub = $1::text"
+async fn fetch_reminder(
+    pool: &PgPool,
+    reminder_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<ReminderRow>, Error> {
+    let reminder = sqlx::query_as(
+        r#"
+        SELECT
+            id, user_id, title, description,
+            trigger_type::text, trigger_config, priority,
+            status::text, next_trigger_at, last_triggered_at,
+            snooze_until, snooze_count, max_snoozes, snooze_escalation, escalated,
+            related_entity_id, related_fact_id,
+            created_at, updated_at
+        FROM reminders
+        WHERE id = $1 AND user_id = $2
+        "#,
     )
-    .bind(cognito_sub)
-    .fetch_optional(&state.db_pool)
-    .await
-    .map_err(|e| format!("Failed to lookup user: {}", e))? {
-        Some(id) => id,
-        None => {
-            return Ok(json_response(
-                401,
-                &ApiResponse::<()> {
-                    success: false,
-                    data: None,
-                    error: Some("User not registered".to_string()),
-                },
-            )?)
-        }
+    .bind(reminder_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(reminder)
+}
+
+/// POST /reminders
+#[utoipa::path(
+    post,
+    path = "/reminders",
+    tag = "reminders",
+    request_body = CreateReminderRequest,
+    responses(
+        (status = 201, description = "The reminder, scheduled", body = ApiResponse<ReminderResponse>),
+        (status = 400, description = "Invalid trigger type, trigger config or snooze policy", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn create_reminder(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user_id = require_user(&event, &state.db_pool).await?.user_id;
+
+    let request: CreateReminderRequest = match json_body(&event) {
+        Ok(r) => r,
+        Err(e) => return bad_request(e.to_string()),
     };
 
-    match (method, path) {
-        // Create reminder
-        ("POST", "/reminders") => {
-            let body = event.body();
-            let body_str = std::str::from_utf8(body.as_ref()).unwrap_or("{}");
-            let request: CreateReminderRequest =
-                serde_json::from_str(body_str).map_err(|e| format!("Invalid request: {}", e))?;
-
-            // Validate trigger type
-            let valid_types = ["time", "location", "event", "recurring"];
-            if !valid_types.contains(&request.trigger_type.as_str()) {
-                return Ok(json_response(
-                    400,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some(format!(
-                            "Invalid trigger_type. Must be one of: {}",
-                            valid_types.join(", ")
-                        )),
-                    },
-                )?);
-            }
+    // Validate trigger type
+    let valid_types = ["time", "location", "event", "recurring"];
+    if !valid_types.contains(&request.trigger_type.as_str()) {
+        return bad_request(format!(
+            "Invalid trigger_type. Must be one of: {}",
+            valid_types.join(", ")
+        ));
+    }
 
-            let related_entity_id = request
-                .related_entity_id
-                .as_ref()
-                .and_then(|id| Uuid::parse_str(id).ok());
-
-            let related_fact_id = request
-                .related_fact_id
-                .as_ref()
-                .and_then(|id| Uuid::parse_str(id).ok());
-
-            let next_trigger_at = calculate_next_trigger(&request.trigger_type, &request.trigger_config);
-            let priority = request.priority.unwrap_or(2); // Default medium priority
-
-            let reminder: ReminderRow = sqlx::query_as(
-                r#"
-                INSERT INTO reminders (
-                    user_id, title, description, trigger_type,
-                    trigger_config, priority, next_trigger_at,
-                    related_entity_id, related_fact_id
-                ) VALUES (
-                    $1, $2, $3, $4::reminder_trigger_type,
-                    $5, $6, $7,
-                    $8, $9
-                )
-                RETURNING
-                    id, user_id, title, description,
-                    trigger_type::text, trigger_config, priority,
-                    status::text, next_trigger_at, last_triggered_at,
-                    snooze_until, related_entity_id, related_fact_id,
-                    created_at, updated_at
-                "#,
-            )
-            .bind(user_id)
-            .bind(&request.title)
-            .bind(&request.description)
-            .bind(&request.trigger_type)
-            .bind(&request.trigger_config)
-            .bind(priority)
-            .bind(next_trigger_at)
-            .bind(related_entity_id)
-            .bind(related_fact_id)
-            .fetch_one(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to create reminder: {}", e))?;
-
-            Ok(json_response(
-                201,
-                &ApiResponse {
-                    success: true,
-                    data: Some(ReminderResponse::from(reminder)),
-                    error: None,
-                },
-            )?)
-        }
+    let related_entity_id = request
+        .related_entity_id
+        .as_ref()
+        .and_then(|id| Uuid::parse_str(id).ok());
+
+    let related_fact_id = request
+        .related_fact_id
+        .as_ref()
+        .and_then(|id| Uuid::parse_str(id).ok());
+
+    let timezone = user_timezone(&state.db_pool, user_id).await?;
+    let next_trigger_at = match calculate_next_trigger(
+        &request.trigger_type,
+        &request.trigger_config,
+        timezone,
+        Utc::now(),
+    ) {
+        Ok(next) => next,
+        Err(e) => return bad_request(e.to_string()),
+    };
+    if request.trigger_type == "time"
+        && matches!(
+            LeaveInTime::from_config(&request.trigger_config),
+            Ok(Some(_))
+        )
+        && related_entity_id.is_none()
+    {
+        return bad_request("inTimeToLeave needs a relatedEntityId to travel to");
+    }
+    let priority = request.priority.unwrap_or(2); // Default medium priority
+
+    let (max_snoozes, snooze_escalation) =
+        match snooze_policy(request.max_snoozes, request.snooze_escalation.as_deref()) {
+            Ok(policy) => policy,
+            Err(e) => return bad_request(e),
+        };
+
+    let reminder: ReminderRow = sqlx::query_as(
+        r#"
+        INSERT INTO reminders (
+            user_id, title, description, trigger_type,
+            trigger_config, priority, next_trigger_at,
+            related_entity_id, related_fact_id,
+            max_snoozes, snooze_escalation
+        ) VALUES (
+            $1, $2, $3, $4::reminder_trigger_type,
+            $5, $6, $7,
+            $8, $9,
+            $10, COALESCE($11, 'priority')
+        )
+        RETURNING
+            id, user_id, title, description,
+            trigger_type::text, trigger_config, priority,
+            status::text, next_trigger_at, last_triggered_at,
+            snooze_until, snooze_count, max_snoozes, snooze_escalation, escalated,
+            related_entity_id, related_fact_id,
+            created_at, updated_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(&request.title)
+    .bind(&request.description)
+    .bind(&request.trigger_type)
+    .bind(&request.trigger_config)
+    .bind(priority)
+    .bind(next_trigger_at)
+    .bind(related_entity_id)
+    .bind(related_fact_id)
+    .bind(max_snoozes)
+    .bind(snooze_escalation)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(ReminderResponse::from(reminder)),
+            error: None,
+        },
+    )
+}
 
-        // List reminders
-        ("GET", "/reminders") => {
-            let params = event.query_string_parameters();
-            let status = params.first("status");
-            let trigger_type = params.first("triggerType");
-            let limit: i32 = params
-                .first("limit")
-                .and_then(|l| l.parse().ok())
-                .unwrap_or(50);
-            let offset: i32 = params
-                .first("offset")
-                .and_then(|o| o.parse().ok())
-                .unwrap_or(0);
-
-            let mut query = String::from(
-                r#"
-                SELECT
-                    id, user_id, title, description,
-                    trigger_type::text, trigger_config, priority,
-                    status::text, next_trigger_at, last_triggered_at,
-                    snooze_until, related_entity_id, related_fact_id,
-                    created_at, updated_at
-                FROM reminders
-                WHERE user_id = $1
-                "#,
-            );
-
-            let mut param_num = 2;
-
-            if status.is_some() {
-                query.push_str(&format!(" AND status = ${}::reminder_status", param_num));
-                param_num += 1;
-            }
+/// GET /reminders
+#[utoipa::path(
+    get,
+    path = "/reminders",
+    tag = "reminders",
+    params(
+        ("status" = Option<String>, Query, description = "Only reminders with this status"),
+        ("triggerType" = Option<String>, Query, description = "Only reminders with this trigger type"),
+        ("limit" = Option<i64>, Query, description = "Most reminders to return"),
+        ("cursor" = Option<String>, Query, description = "Cursor returned with the previous page"),
+    ),
+    responses(
+        (status = 200, description = "A page of your reminders (`reminders`, `total`, `nextCursor`, `hasMore`)", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Invalid cursor", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_reminders(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user_id = require_user(&event, &state.db_pool).await?.user_id;
+
+    let params = Query::from_request(&event);
+    let status = params.first("status");
+    let trigger_type = params.first("triggerType");
+    let page_params = match CursorParams::from_query(params.first("limit"), params.first("cursor"), 50) {
+        Ok(p) => p,
+        Err(e) => return bad_request(e.to_string()),
+    };
 
-            if trigger_type.is_some() {
-                query.push_str(&format!(
-                    " AND trigger_type = ${}::reminder_trigger_type",
-                    param_num
-                ));
-                param_num += 1;
-            }
+    let mut query = String::from(
+        r#"
+        SELECT
+            id, user_id, title, description,
+            trigger_type::text, trigger_config, priority,
+            status::text, next_trigger_at, last_triggered_at,
+            snooze_until, snooze_count, max_snoozes, snooze_escalation, escalated,
+            related_entity_id, related_fact_id,
+            created_at, updated_at
+        FROM reminders
+        WHERE user_id = $1
+        "#,
+    );
+
+    // Reminders without a trigger time sort last, keyed as 'infinity'
+    query.push_str(" AND ");
+    query.push_str(&keyset_predicate(
+        "COALESCE(next_trigger_at, 'infinity'::timestamptz)",
+        "timestamptz",
+        "id",
+        2,
+        SortDirection::Asc,
+    ));
+
+    let mut param_num = 4;
+
+    if status.is_some() {
+        query.push_str(&format!(" AND status = ${}::reminder_status", param_num));
+        param_num += 1;
+    }
 
-            query.push_str(&format!(
-                " ORDER BY next_trigger_at ASC NULLS LAST LIMIT ${} OFFSET ${}",
-                param_num,
-                param_num + 1
-            ));
+    if trigger_type.is_some() {
+        query.push_str(&format!(
+            " AND trigger_type = ${}::reminder_trigger_type",
+            param_num
+        ));
+        param_num += 1;
+    }
 
-            // Build the query with dynamic bindings
-            let mut query_builder = sqlx::query_as::<_, ReminderRow>(&query).bind(user_id);
+    query.push_str(&format!(
+        " ORDER BY COALESCE(next_trigger_at, 'infinity'::timestamptz) ASC, id ASC LIMIT ${}",
+        param_num
+    ));
 
-            if let Some(s) = status {
-                query_builder = query_builder.bind(s);
-            }
-            if let Some(t) = trigger_type {
-                query_builder = query_builder.bind(t);
-            }
+    // Build the query with dynamic bindings
+    let mut query_builder = sqlx::query_as::<_, ReminderRow>(&query)
+        .bind(user_id)
+        .bind(page_params.after_key())
+        .bind(page_params.after_id());
 
-            query_builder = query_builder.bind(limit).bind(offset);
+    if let Some(s) = status {
+        query_builder = query_builder.bind(s);
+    }
+    if let Some(t) = trigger_type {
+        query_builder = query_builder.bind(t);
+    }
 
-            let reminders: Vec<ReminderRow> = query_builder
-                .fetch_all(&state.db_pool)
-                .await
-                .map_err(|e| format!("Failed to fetch reminders: {}", e))?;
+    query_builder = query_builder.bind(page_params.fetch_limit());
 
-            let responses: Vec<ReminderResponse> =
-                reminders.into_iter().map(ReminderResponse::from).collect();
+    let reminders: Vec<ReminderRow> = query_builder.fetch_all(&state.db_pool).await?;
 
-            // Get total count
-            let total: i64 = sqlx::query_scalar(
-                "SELECT COUNT(*) FROM reminders WHERE user_id = $1",
-            )
-            .bind(user_id)
-            .fetch_one(&state.db_pool)
-            .await
-            .unwrap_or(0);
-
-            Ok(json_response(
-                200,
-                &ApiResponse {
-                    success: true,
-                    data: Some(serde_json::json!({
-                        "reminders": responses,
-                        "total": total,
-                        "limit": limit,
-                        "offset": offset,
-                    })),
-                    error: None,
-                },
-            )?)
-        }
+    let page = Page::from_rows(reminders, &page_params, |r| {
+        Cursor::new(
+            r.next_trigger_at
+                .map(|dt| dt.to_rfc3339())
+                .unwrap_or_else(|| "infinity".to_string()),
+            r.id,
+        )
+    })
+    .map(ReminderResponse::from);
 
-        // Get single reminder
-        _ if path.starts_with("/reminders/")
-            && !path.contains("/snooze")
-            && method == "GET" =>
-        {
-            let reminder_id = path.trim_start_matches("/reminders/");
-            let reminder_uuid =
-                Uuid::parse_str(reminder_id).map_err(|_| "Invalid reminder ID")?;
-
-            let reminder: Option<ReminderRow> = sqlx::query_as(
-                r#"
-                SELECT
-                    id, user_id, title, description,
-                    trigger_type::text, trigger_config, priority,
-                    status::text, next_trigger_at, last_triggered_at,
-                    snooze_until, related_entity_id, related_fact_id,
-                    created_at, updated_at
-                FROM reminders
-                WHERE id = $1 AND user_id = $2
-                "#,
-            )
-            .bind(reminder_uuid)
-            .bind(user_id)
-            .fetch_optional(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to fetch reminder: {}", e))?;
+    // Get total count
+    let total: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM reminders WHERE user_id = $1",
+    )
+    .bind(user_id)
+    .fetch_one(&state.db_pool)
+    .await
+    .unwrap_or(0);
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "reminders": page.items,
+                "total": total,
+                "limit": page_params.limit,
+                "nextCursor": page.next_cursor,
+                "hasMore": page.has_more,
+            })),
+            error: None,
+        },
+    )
+}
 
-            match reminder {
-                Some(r) => Ok(json_response(
-                    200,
-                    &ApiResponse {
-                        success: true,
-                        data: Some(ReminderResponse::from(r)),
-                        error: None,
-                    },
-                )?),
-                None => Ok(json_response(
-                    404,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Reminder not found".to_string()),
-                    },
-                )?),
-            }
-        }
+/// GET /reminders/{id}
+#[utoipa::path(
+    get,
+    path = "/reminders/{id}",
+    tag = "reminders",
+    params(
+        ("id" = Uuid, Path, description = "Reminder ID"),
+        ("If-None-Match" = Option<String>, Header, description = "`ETag` the client has; answered with 304 if it's current"),
+    ),
+    responses(
+        (status = 200, description = "The reminder", body = ApiResponse<ReminderResponse>, headers(("ETag" = String, description = "Version of the resource, for `If-None-Match` and `If-Match`"))),
+        (status = 304, description = "Not modified since the `If-None-Match` ETag"),
+        (status = 404, description = "Reminder not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn get_reminder(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user_id = require_user(&event, &state.db_pool).await?.user_id;
+    let reminder_id = reminder_id!(params);
+
+    let reminder = match fetch_reminder(&state.db_pool, reminder_id, user_id).await? {
+        Some(r) => r,
+        None => return not_found(),
+    };
 
-        // Update reminder
-        _ if path.starts_with("/reminders/")
-            && !path.contains("/snooze")
-            && method == "PUT" =>
-        {
-            let reminder_id = path.trim_start_matches("/reminders/");
-            let reminder_uuid =
-                Uuid::parse_str(reminder_id).map_err(|_| "Invalid reminder ID")?;
-
-            let body = event.body();
-            let body_str = std::str::from_utf8(body.as_ref()).unwrap_or("{}");
-            let request: UpdateReminderRequest =
-                serde_json::from_str(body_str).map_err(|e| format!("Invalid request: {}", e))?;
-
-            // Validate status if provided
-            if let Some(ref status) = request.status {
-                let valid_statuses = ["active", "paused", "cancelled", "completed"];
-                if !valid_statuses.contains(&status.as_str()) {
-                    return Ok(json_response(
-                        400,
-                        &ApiResponse::<()> {
-                            success: false,
-                            data: None,
-                            error: Some(format!(
-                                "Invalid status. Must be one of: {}",
-                                valid_statuses.join(", ")
-                            )),
-                        },
-                    )?);
-                }
-            }
+    let etag = conditional::etag(reminder.updated_at);
+    if conditional::is_not_modified(&event, &etag) {
+        return Ok(conditional::not_modified(&etag));
+    }
 
-            // Build dynamic update query
-            let mut updates = Vec::new();
-            let mut param_num = 3;
+    Ok(conditional::with_etag(
+        json_response(
+            200,
+            &ApiResponse {
+                success: true,
+                data: Some(ReminderResponse::from(reminder)),
+                error: None,
+            },
+        )?,
+        &etag,
+    ))
+}
 
-            if request.title.is_some() {
-                updates.push(format!("title = ${}", param_num));
-                param_num += 1;
-            }
-            if request.description.is_some() {
-                updates.push(format!("description = ${}", param_num));
-                param_num += 1;
-            }
-            if request.trigger_config.is_some() {
-                updates.push(format!("trigger_config = ${}", param_num));
-                param_num += 1;
-            }
-            if request.priority.is_some() {
-                updates.push(format!("priority = ${}", param_num));
-                param_num += 1;
-            }
-            if request.status.is_some() {
-                updates.push(format!("status = ${}::reminder_status", param_num));
-            }
+/// PUT /reminders/{id}
+#[utoipa::path(
+    put,
+    path = "/reminders/{id}",
+    tag = "reminders",
+    request_body = UpdateReminderRequest,
+    params(
+        ("id" = Uuid, Path, description = "Reminder ID"),
+        ("If-Match" = Option<String>, Header, description = "`ETag` the change is based on; answered with 412 if it's stale"),
+    ),
+    responses(
+        (status = 200, description = "The updated reminder", body = ApiResponse<ReminderResponse>, headers(("ETag" = String, description = "Version of the resource, for `If-None-Match` and `If-Match`"))),
+        (status = 400, description = "Invalid status, trigger config or snooze policy", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Reminder not found", body = ApiError, content_type = "application/problem+json"),
+        (status = 412, description = "The reminder changed since the `If-Match` ETag", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn update_reminder(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user_id = require_user(&event, &state.db_pool).await?.user_id;
+    let reminder_id = reminder_id!(params);
+
+    let request: UpdateReminderRequest = match json_body(&event) {
+        Ok(r) => r,
+        Err(e) => return bad_request(e.to_string()),
+    };
 
-            if updates.is_empty() {
-                return Ok(json_response(
-                    400,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("No fields to update".to_string()),
-                    },
-                )?);
-            }
+    // Validate status if provided
+    if let Some(ref status) = request.status {
+        let valid_statuses = ["active", "paused", "cancelled", "completed"];
+        if !valid_statuses.contains(&status.as_str()) {
+            return bad_request(format!(
+                "Invalid status. Must be one of: {}",
+                valid_statuses.join(", ")
+            ));
+        }
+    }
 
-            updates.push("updated_at = NOW()".to_string());
-
-            let query = format!(
-                r#"
-                UPDATE reminders
-                SET {}
-                WHERE id = $1 AND user_id = $2
-                RETURNING
-                    id, user_id, title, description,
-                    trigger_type::text, trigger_config, priority,
-                    status::text, next_trigger_at, last_triggered_at,
-                    snooze_until, related_entity_id, related_fact_id,
-                    created_at, updated_at
-                "#,
-                updates.join(", ")
-            );
-
-            let mut query_builder = sqlx::query_as::<_, ReminderRow>(&query)
-                .bind(reminder_uuid)
-                .bind(user_id);
-
-            if let Some(ref title) = request.title {
-                query_builder = query_builder.bind(title);
-            }
-            if let Some(ref description) = request.description {
-                query_builder = query_builder.bind(description);
-            }
-            if let Some(ref trigger_config) = request.trigger_config {
-                query_builder = query_builder.bind(trigger_config);
-            }
-            if let Some(priority) = request.priority {
-                query_builder = query_builder.bind(priority);
+    let (max_snoozes, snooze_escalation) =
+        match snooze_policy(request.max_snoozes, request.snooze_escalation.as_deref()) {
+            Ok(policy) => policy,
+            Err(e) => return bad_request(e),
+        };
+
+    // A new trigger config reschedules the reminder
+    let next_trigger_at = match request.trigger_config {
+        Some(ref trigger_config) => {
+            let existing = match fetch_reminder(&state.db_pool, reminder_id, user_id).await? {
+                Some(r) => r,
+                None => return not_found(),
+            };
+            let timezone = user_timezone(&state.db_pool, user_id).await?;
+            if existing.trigger_type == "time"
+                && matches!(LeaveInTime::from_config(trigger_config), Ok(Some(_)))
+                && existing.related_entity_id.is_none()
+            {
+                return bad_request("inTimeToLeave needs a relatedEntityId to travel to");
             }
-            if let Some(ref status) = request.status {
-                query_builder = query_builder.bind(status);
+            match calculate_next_trigger(
+                &existing.trigger_type,
+                trigger_config,
+                timezone,
+                Utc::now(),
+            ) {
+                Ok(next) => Some(next),
+                Err(e) => return bad_request(e.to_string()),
             }
+        }
+        None => None,
+    };
+
+    // Build dynamic update query
+    let mut updates = Vec::new();
+    let mut param_num = 3;
+
+    if request.title.is_some() {
+        updates.push(format!("title = ${}", param_num));
+        param_num += 1;
+    }
+    if request.description.is_some() {
+        updates.push(format!("description = ${}", param_num));
+        param_num += 1;
+    }
+    if request.trigger_config.is_some() {
+        updates.push(format!("trigger_config = ${}", param_num));
+        param_num += 1;
+        updates.push(format!("next_trigger_at = ${}", param_num));
+        param_num += 1;
+        // The evaluator re-estimates travel for the new config
+        updates.push("travel_seconds = NULL".to_string());
+        updates.push("travel_estimated_at = NULL".to_string());
+    }
+    if request.priority.is_some() {
+        updates.push(format!("priority = ${}", param_num));
+        param_num += 1;
+    }
+    if request.max_snoozes.is_some() {
+        updates.push(format!("max_snoozes = ${}", param_num));
+        param_num += 1;
+    }
+    if snooze_escalation.is_some() {
+        updates.push(format!("snooze_escalation = ${}", param_num));
+        param_num += 1;
+    }
+    if request.status.is_some() {
+        updates.push(format!("status = ${}::reminder_status", param_num));
+        param_num += 1;
+    }
 
-            let reminder: Option<ReminderRow> = query_builder
-                .fetch_optional(&state.db_pool)
-                .await
-                .map_err(|e| format!("Failed to update reminder: {}", e))?;
+    if updates.is_empty() {
+        return bad_request("No fields to update");
+    }
+
+    let if_match = IfMatch::from_request(&event);
+    if if_match == IfMatch::Never {
+        return precondition_failed(&state.db_pool, reminder_id, user_id).await;
+    }
+
+    updates.push("updated_at = NOW()".to_string());
+
+    // With If-Match, only the version the client last saw is updated
+    let query = format!(
+        r#"
+        UPDATE reminders
+        SET {}
+        WHERE id = $1 AND user_id = $2
+          AND (${version}::timestamptz IS NULL OR updated_at = ${version})
+        RETURNING
+            id, user_id, title, description,
+            trigger_type::text, trigger_config, priority,
+            status::text, next_trigger_at, last_triggered_at,
+            snooze_until, snooze_count, max_snoozes, snooze_escalation, escalated,
+            related_entity_id, related_fact_id,
+            created_at, updated_at
+        "#,
+        updates.join(", "),
+        version = param_num
+    );
+
+    let mut query_builder = sqlx::query_as::<_, ReminderRow>(&query)
+        .bind(reminder_id)
+        .bind(user_id);
+
+    if let Some(ref title) = request.title {
+        query_builder = query_builder.bind(title);
+    }
+    if let Some(ref description) = request.description {
+        query_builder = query_builder.bind(description);
+    }
+    if let Some(ref trigger_config) = request.trigger_config {
+        query_builder = query_builder.bind(trigger_config).bind(next_trigger_at.flatten());
+    }
+    if let Some(priority) = request.priority {
+        query_builder = query_builder.bind(priority);
+    }
+    if request.max_snoozes.is_some() {
+        query_builder = query_builder.bind(max_snoozes);
+    }
+    if let Some(escalation) = snooze_escalation {
+        query_builder = query_builder.bind(escalation);
+    }
+    if let Some(ref status) = request.status {
+        query_builder = query_builder.bind(status);
+    }
+    query_builder = query_builder.bind(if_match.expected());
+
+    let reminder: Option<ReminderRow> = query_builder.fetch_optional(&state.db_pool).await?;
 
-            match reminder {
-                Some(r) => Ok(json_response(
+    match reminder {
+        Some(r) => {
+            let etag = conditional::etag(r.updated_at);
+            Ok(conditional::with_etag(
+                json_response(
                     200,
                     &ApiResponse {
                         success: true,
                         data: Some(ReminderResponse::from(r)),
                         error: None,
                     },
-                )?),
-                None => Ok(json_response(
-                    404,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Reminder not found".to_string()),
-                    },
-                )?),
-            }
+                )?,
+                &etag,
+            ))
         }
+        None if if_match.expected().is_some() => {
+            precondition_failed(&state.db_pool, reminder_id, user_id).await
+        }
+        None => not_found(),
+    }
+}
 
-        // Snooze reminder
-        _ if path.ends_with("/snooze") && method == "POST" => {
-            let reminder_id = path
-                .trim_start_matches("/reminders/")
-                .trim_end_matches("/snooze");
-            let reminder_uuid =
-                Uuid::parse_str(reminder_id).map_err(|_| "Invalid reminder ID")?;
-
-            let body = event.body();
-            let body_str = std::str::from_utf8(body.as_ref()).unwrap_or("{}");
-            let request: SnoozeReminderRequest =
-                serde_json::from_str(body_str).map_err(|e| format!("Invalid request: {}", e))?;
-
-            let snooze_until = DateTime::parse_from_rfc3339(&request.snooze_until)
-                .map_err(|_| "Invalid snooze_until datetime")?
-                .with_timezone(&Utc);
-
-            let reminder: Option<ReminderRow> = sqlx::query_as(
-                r#"
-                UPDATE reminders
-                SET snooze_until = $3, updated_at = NOW()
-                WHERE id = $1 AND user_id = $2
-                RETURNING
-                    id, user_id, title, description,
-                    trigger_type::text, trigger_config, priority,
-                    status::text, next_trigger_at, last_triggered_at,
-                    snooze_until, related_entity_id, related_fact_id,
-                    created_at, updated_at
-                "#,
-            )
-            .bind(reminder_uuid)
-            .bind(user_id)
-            .bind(snooze_until)
-            .fetch_optional(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to snooze reminder: {}", e))?;
+/// `412` for an update whose `If-Match` no longer holds, with the current
+/// `ETag` (or `404` if the reminder is gone).
+async fn precondition_failed(
+    pool: &PgPool,
+    reminder_id: Uuid,
+    user_id: Uuid,
+) -> Result<Response<Body>, Error> {
+    let current = match fetch_reminder(pool, reminder_id, user_id).await? {
+        Some(r) => r,
+        None => return not_found(),
+    };
 
-            match reminder {
-                Some(r) => Ok(json_response(
-                    200,
-                    &ApiResponse {
-                        success: true,
-                        data: Some(ReminderResponse::from(r)),
-                        error: None,
-                    },
-                )?),
-                None => Ok(json_response(
-                    404,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Reminder not found".to_string()),
-                    },
-                )?),
-            }
+    Ok(conditional::with_etag(
+        error_response(
+            412,
+            "Reminder was changed by someone else; reload it and try again",
+        )?,
+        &conditional::etag(current.updated_at),
+    ))
+}
+
+/// POST /reminders/{id}/simulate - evaluator dry run (no side effects)
+#[utoipa::path(
+    post,
+    path = "/reminders/{id}/simulate",
+    tag = "reminders",
+    request_body(content = SimulateReminderRequest, description = "Optional; an empty body simulates the current time"),
+    params(
+        ("id" = Uuid, Path, description = "Reminder ID"),
+    ),
+    responses(
+        (status = 200, description = "What the evaluator would do at `now`, step by step", body = ApiResponse<SimulationResponse>),
+        (status = 400, description = "Invalid `now`", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Reminder not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn simulate(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user_id = require_user(&event, &state.db_pool).await?.user_id;
+    let reminder_id = reminder_id!(params);
+
+    let request: SimulateReminderRequest = if event.body().as_ref().iter().all(u8::is_ascii_whitespace) {
+        SimulateReminderRequest { now: None }
+    } else {
+        match json_body(&event) {
+            Ok(r) => r,
+            Err(e) => return bad_request(e.to_string()),
         }
+    };
 
-        // Delete reminder
-        _ if path.starts_with("/reminders/") && method == "DELETE" => {
-            let reminder_id = path.trim_start_matches("/reminders/");
-            let reminder_uuid =
-                Uuid::parse_str(reminder_id).map_err(|_| "Invalid reminder ID")?;
-
-            let result = sqlx::query(
-                r#"
-                UPDATE reminders
-                SET status = 'cancelled', updated_at = NOW()
-                WHERE id = $1 AND user_id = $2
-                "#,
-            )
-            .bind(reminder_uuid)
-            .bind(user_id)
-            .execute(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to delete reminder: {}", e))?;
-
-            if result.rows_affected() == 0 {
-                return Ok(json_response(
-                    404,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Reminder not found".to_string()),
-                    },
-                )?);
+    let now = match request.now {
+        Some(ref n) => match DateTime::parse_from_rfc3339(n) {
+            Ok(dt) => dt.with_timezone(&Utc),
+            Err(_) => return bad_request("Invalid now datetime"),
+        },
+        None => Utc::now(),
+    };
+
+    let reminder = match fetch_reminder(&state.db_pool, reminder_id, user_id).await? {
+        Some(r) => r,
+        None => return not_found(),
+    };
+
+    let simulation = simulate_reminder(&state.db_pool, &reminder, now).await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(simulation),
+            error: None,
+        },
+    )
+}
+
+/// POST /reminders/{id}/snooze
+#[utoipa::path(
+    post,
+    path = "/reminders/{id}/snooze",
+    tag = "reminders",
+    request_body = SnoozeReminderRequest,
+    params(
+        ("id" = Uuid, Path, description = "Reminder ID"),
+    ),
+    responses(
+        (status = 200, description = "The snoozed reminder; it fires again when the snooze ends", body = ApiResponse<ReminderResponse>),
+        (status = 400, description = "Invalid time or preset, a reminder that can't be snoozed, or too many snoozes", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Reminder not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn snooze_reminder(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user_id = require_user(&event, &state.db_pool).await?.user_id;
+    let reminder_id = reminder_id!(params);
+
+    let request: SnoozeReminderRequest = match json_body(&event) {
+        Ok(r) => r,
+        Err(e) => return bad_request(e.to_string()),
+    };
+
+    let snooze_until = match (request.snooze_until.as_deref(), request.preset.as_deref()) {
+        (Some(until), None) => match DateTime::parse_from_rfc3339(until) {
+            Ok(dt) => dt.with_timezone(&Utc),
+            Err(_) => return bad_request("Invalid snooze_until datetime"),
+        },
+        (None, Some(preset)) => {
+            let timezone = user_timezone(&state.db_pool, user_id).await?;
+            match resolve_snooze_preset(preset, Utc::now(), timezone) {
+                Some(until) => until,
+                None => return bad_request(format!("Invalid or past snooze preset: {}", preset)),
             }
+        }
+        _ => return bad_request("Provide one of snoozeUntil or preset"),
+    };
 
-            Ok(json_response(
-                200,
-                &ApiResponse {
-                    success: true,
-                    data: Some(serde_json::json!({
-                        "message": "Reminder cancelled",
-                        "reminderId": reminder_id,
-                    })),
-                    error: None,
-                },
-            )?)
+    let existing = match fetch_reminder(&state.db_pool, reminder_id, user_id).await? {
+        Some(r) => r,
+        None => return not_found(),
+    };
+
+    if !matches!(existing.status.as_str(), "active" | "triggered") {
+        return bad_request(format!("Cannot snooze a {} reminder", existing.status));
+    }
+
+    let escalation =
+        SnoozeEscalation::parse(&existing.snooze_escalation).unwrap_or(SnoozeEscalation::Priority);
+    let escalate = match snooze_decision(existing.snooze_count, existing.max_snoozes, escalation) {
+        SnoozeDecision::Allow => false,
+        SnoozeDecision::Escalate => true,
+        SnoozeDecision::Refuse => {
+            return bad_request(format!(
+                "Reminder has already been snoozed {} times",
+                existing.snooze_count
+            ))
         }
+    };
 
-        _ => Ok(json_response(
-            404,
-            &ApiResponse::<()> {
-                success: false,
-                data: None,
-                error: Some("Not found".to_string()),
+    // The reminder fires again when the snooze ends; a recurring reminder then
+    // moves on to its next occurrence as usual
+    let reminder: Option<ReminderRow> = sqlx::query_as(
+        r#"
+        UPDATE reminders
+        SET snooze_until = $3,
+            next_trigger_at = $3,
+            status = 'active',
+            snooze_count = snooze_count + 1,
+            escalated = escalated OR $4,
+            updated_at = NOW()
+        WHERE id = $1 AND user_id = $2
+        RETURNING
+            id, user_id, title, description,
+            trigger_type::text, trigger_config, priority,
+            status::text, next_trigger_at, last_triggered_at,
+            snooze_until, snooze_count, max_snoozes, snooze_escalation, escalated,
+            related_entity_id, related_fact_id,
+            created_at, updated_at
+        "#,
+    )
+    .bind(reminder_id)
+    .bind(user_id)
+    .bind(snooze_until)
+    .bind(escalate)
+    .fetch_optional(&state.db_pool)
+    .await?;
+
+    match reminder {
+        Some(r) => json_response(
+            200,
+            &ApiResponse {
+                success: true,
+                data: Some(ReminderResponse::from(r)),
+                error: None,
             },
-        )?),
+        ),
+        None => not_found(),
     }
 }
 
+/// Outcome of an occurrence, counting pending ones due before the cutoff bound
+/// at `$param` as missed.
+fn outcome_sql(param: usize) -> String {
+    format!(
+        "CASE WHEN o.outcome = 'pending' AND o.due_at < ${} THEN 'missed' ELSE o.outcome END",
+        param
+    )
+}
+
+/// POST /reminders/{id}/complete
+///
+/// Completes the occurrence awaiting action. One-off reminders become
+/// `completed`; a recurring reminder stays active, and completing it before it
+/// fires skips that occurrence.
+#[utoipa::path(
+    post,
+    path = "/reminders/{id}/complete",
+    tag = "reminders",
+    params(
+        ("id" = Uuid, Path, description = "Reminder ID"),
+    ),
+    responses(
+        (status = 200, description = "The reminder; recurring reminders stay active", body = ApiResponse<ReminderResponse>),
+        (status = 400, description = "The reminder is already completed or cancelled", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Reminder not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn complete_reminder(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user_id = require_user(&event, &state.db_pool).await?.user_id;
+    let reminder_id = reminder_id!(params);
+
+    let reminder = match fetch_reminder(&state.db_pool, reminder_id, user_id).await? {
+        Some(r) => r,
+        None => return not_found(),
+    };
+
+    if matches!(reminder.status.as_str(), "completed" | "cancelled") {
+        return bad_request(format!("Reminder is already {}", reminder.status));
+    }
+
+    let timezone = user_timezone(&state.db_pool, user_id).await?;
+    let schedule = (reminder.trigger_type == "recurring" && reminder.status == "active")
+        .then(|| Schedule::from_config(&reminder.trigger_config, timezone).ok())
+        .flatten();
+
+    let mut tx = state.db_pool.begin().await?;
+
+    let completed: Option<DateTime<Utc>> = sqlx::query_scalar(
+        r#"
+        UPDATE reminder_occurrences
+        SET outcome = 'completed', completed_at = NOW()
+        WHERE id = (
+            SELECT id FROM reminder_occurrences
+            WHERE reminder_id = $1 AND outcome = 'pending'
+            ORDER BY due_at DESC
+            LIMIT 1
+        )
+        RETURNING due_at
+        "#,
+    )
+    .bind(reminder_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let mut next_trigger_at = reminder.next_trigger_at;
+    if completed.is_none() {
+        // Done before it fired: complete the upcoming occurrence
+        let due_at = reminder.next_trigger_at.unwrap_or_else(Utc::now);
+        sqlx::query(
+            r#"
+            INSERT INTO reminder_occurrences (reminder_id, user_id, due_at, outcome, completed_at)
+            VALUES ($1, $2, $3, 'completed', NOW())
+            "#,
+        )
+        .bind(reminder_id)
+        .bind(user_id)
+        .bind(due_at)
+        .execute(&mut *tx)
+        .await?;
+
+        if let Some(ref schedule) = schedule {
+            next_trigger_at = schedule.next_after(due_at);
+        }
+    }
+
+    let status = if schedule.is_some() && next_trigger_at.is_some() {
+        "active"
+    } else {
+        "completed"
+    };
+
+    let updated: ReminderRow = sqlx::query_as(
+        r#"
+        UPDATE reminders
+        SET status = $3::reminder_status,
+            next_trigger_at = $4,
+            snooze_until = NULL,
+            snooze_count = 0,
+            escalated = false,
+            updated_at = NOW()
+        WHERE id = $1 AND user_id = $2
+        RETURNING
+            id, user_id, title, description,
+            trigger_type::text, trigger_config, priority,
+            status::text, next_trigger_at, last_triggered_at,
+            snooze_until, snooze_count, max_snoozes, snooze_escalation, escalated,
+            related_entity_id, related_fact_id,
+            created_at, updated_at
+        "#,
+    )
+    .bind(reminder_id)
+    .bind(user_id)
+    .bind(status)
+    .bind(next_trigger_at)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(ReminderResponse::from(updated)),
+            error: None,
+        },
+    )
+}
+
+/// GET /reminders/history
+///
+/// Occurrences newest first, filtered by `from`/`to` (RFC 3339), `outcome`
+/// and `reminderId`, with completed vs missed counts per week over the same
+/// range (the last `HISTORY_STATS_WEEKS` weeks when `from` is not given).
+#[utoipa::path(
+    get,
+    path = "/reminders/history",
+    tag = "reminders",
+    params(
+        ("from" = Option<DateTime<Utc>>, Query, description = "Occurrences due at or after this time"),
+        ("to" = Option<DateTime<Utc>>, Query, description = "Occurrences due before this time"),
+        ("outcome" = Option<String>, Query, description = "`completed`, `missed` or `pending`"),
+        ("reminderId" = Option<Uuid>, Query, description = "Only this reminder's occurrences"),
+        ("limit" = Option<i64>, Query, description = "Most occurrences to return"),
+        ("cursor" = Option<String>, Query, description = "Cursor returned with the previous page"),
+    ),
+    responses(
+        (status = 200, description = "Occurrences newest first (`occurrences`, `nextCursor`, `hasMore`) and completed vs missed counts by week (`stats`)", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Invalid filter or cursor", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn reminder_history(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user_id = require_user(&event, &state.db_pool).await?.user_id;
+
+    let params = Query::from_request(&event);
+    let (from, to, reminder_id) = match (
+        params.get::<DateTime<Utc>>("from"),
+        params.get::<DateTime<Utc>>("to"),
+        params.get::<Uuid>("reminderId"),
+    ) {
+        (Ok(from), Ok(to), Ok(reminder_id)) => (from, to, reminder_id),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => return bad_request(e.to_string()),
+    };
+    let outcome = params.first("outcome");
+    if let Some(outcome) = outcome {
+        if !["completed", "missed", "pending"].contains(&outcome) {
+            return bad_request("Invalid outcome. Must be one of: completed, missed, pending");
+        }
+    }
+    let page_params = match CursorParams::from_query(params.first("limit"), params.first("cursor"), 50) {
+        Ok(p) => p,
+        Err(e) => return bad_request(e.to_string()),
+    };
+
+    let now = Utc::now();
+    let missed_before = now - Duration::hours(MISSED_AFTER_HOURS);
+
+    let query = format!(
+        r#"
+        SELECT
+            o.id, o.reminder_id, r.title, r.trigger_type::text AS trigger_type,
+            o.due_at, {outcome} AS outcome, o.completed_at
+        FROM reminder_occurrences o
+        JOIN reminders r ON r.id = o.reminder_id
+        WHERE o.user_id = $1
+          AND {keyset}
+          AND ($5::timestamptz IS NULL OR o.due_at >= $5)
+          AND ($6::timestamptz IS NULL OR o.due_at < $6)
+          AND ($7::uuid IS NULL OR o.reminder_id = $7)
+          AND ($8::text IS NULL OR {outcome} = $8)
+        ORDER BY o.due_at DESC, o.id DESC
+        LIMIT $9
+        "#,
+        outcome = outcome_sql(4),
+        keyset = keyset_predicate("o.due_at", "timestamptz", "o.id", 2, SortDirection::Desc),
+    );
+
+    let rows: Vec<OccurrenceRow> = sqlx::query_as(&query)
+        .bind(user_id)
+        .bind(page_params.after_key())
+        .bind(page_params.after_id())
+        .bind(missed_before)
+        .bind(from)
+        .bind(to)
+        .bind(reminder_id)
+        .bind(outcome)
+        .bind(page_params.fetch_limit())
+        .fetch_all(&state.db_pool)
+        .await?;
+
+    let page = Page::from_rows(rows, &page_params, |o| Cursor::new(o.due_at.to_rfc3339(), o.id))
+        .map(OccurrenceResponse::from);
+
+    // Weeks start on Monday in the user's timezone
+    let timezone = user_timezone(&state.db_pool, user_id).await?;
+    let stats_from = from.unwrap_or(now - Duration::weeks(HISTORY_STATS_WEEKS));
+
+    let weeks: Vec<WeekStats> = sqlx::query_as(&format!(
+        r#"
+        SELECT
+            (date_trunc('week', o.due_at AT TIME ZONE $2))::date AS week_start,
+            COUNT(*) FILTER (WHERE {outcome} = 'completed') AS completed,
+            COUNT(*) FILTER (WHERE {outcome} = 'missed') AS missed
+        FROM reminder_occurrences o
+        WHERE o.user_id = $1
+          AND o.due_at >= $4
+          AND ($5::timestamptz IS NULL OR o.due_at < $5)
+          AND ($6::uuid IS NULL OR o.reminder_id = $6)
+        GROUP BY 1
+        ORDER BY 1 DESC
+        "#,
+        outcome = outcome_sql(3),
+    ))
+    .bind(user_id)
+    .bind(timezone.name())
+    .bind(missed_before)
+    .bind(stats_from)
+    .bind(to)
+    .bind(reminder_id)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let completed: i64 = weeks.iter().map(|w| w.completed).sum();
+    let missed: i64 = weeks.iter().map(|w| w.missed).sum();
+    let completion_rate = if completed + missed > 0 {
+        Some(completed as f64 / (completed + missed) as f64)
+    } else {
+        None
+    };
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "occurrences": page.items,
+                "limit": page_params.limit,
+                "nextCursor": page.next_cursor,
+                "hasMore": page.has_more,
+                "stats": {
+                    "from": stats_from.to_rfc3339(),
+                    "completed": completed,
+                    "missed": missed,
+                    "completionRate": completion_rate,
+                    "weeks": weeks,
+                },
+            })),
+            error: None,
+        },
+    )
+}
+
+/// DELETE /reminders/{id} - cancels the reminder
+#[utoipa::path(
+    delete,
+    path = "/reminders/{id}",
+    tag = "reminders",
+    params(
+        ("id" = Uuid, Path, description = "Reminder ID"),
+    ),
+    responses(
+        (status = 200, description = "Reminder cancelled", body = ApiResponse<serde_json::Value>),
+        (status = 404, description = "Reminder not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn delete_reminder(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user_id = require_user(&event, &state.db_pool).await?.user_id;
+    let reminder_id = reminder_id!(params);
+
+    let result = sqlx::query(
+        r#"
+        UPDATE reminders
+        SET status = 'cancelled', updated_at = NOW()
+        WHERE id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(reminder_id)
+    .bind(user_id)
+    .execute(&state.db_pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return not_found();
+    }
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "message": "Reminder cancelled",
+                "reminderId": reminder_id.to_string(),
+            })),
+            error: None,
+        },
+    )
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .post("/reminders", create_reminder)
+        .get("/reminders", list_reminders)
+        .get("/reminders/history", reminder_history)
+        .get("/reminders/{id}", get_reminder)
+        .put("/reminders/{id}", update_reminder)
+        .delete("/reminders/{id}", delete_reminder)
+        .post("/reminders/{id}/snooze", snooze_reminder)
+        .post("/reminders/{id}/complete", complete_reminder)
+        .post("/reminders/{id}/simulate", simulate)
+}
+
 fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
     let json = serde_json::to_string(body)?;
     Ok(Response::builder()
         .status(status)
         .header("Content-Type", "application/json")
-        .header("Access-Control-Allow-Origin", "*")
         .body(Body::from(json))?)
 }
 
@@ -749,11 +1545,12 @@ async fn main() -> Result<(), Error> {
         .init();
 
     let state = Arc::new(AppState::new().await?);
-    let state_clone = state.clone();
+    let router = Arc::new(router());
 
     run(service_fn(move |event| {
-        let state = state_clone.clone();
-        async move { handler(state, event).await }
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
     }))
     .await
 }
@@ -0,0 +1,231 @@
+//! Sharing Lambda - What relationships share, person by person.
+//!
+//! Endpoints:
+//! - GET /shared-with-me - People whose facts and entities you can see, with
+//!   counts (`?userId=` lists what you see of one of them)
+//! - GET /shared-by-me - People who can see your facts and entities, with
+//!   counts (`?userId=` lists what one of them sees; `&accessTier=` previews
+//!   it at another tier before you change it)
+//!
+//! Access comes from `user_access_cache`, so people reached through someone
+//! else are listed too (`hopCount` > 1). See `shared::sharing`.
+
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::Serialize;
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, Query, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::sharing::{
+    self, Direction, SharedItems, SharedUser, DEFAULT_ITEM_LIMIT, MAX_ITEM_LIMIT,
+};
+use shared::ApiError;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// What is shared with one person
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SharedWithUserResponse {
+    user_id: String,
+    /// Tier the person actually has, when previewing another one
+    current_access_tier: i16,
+    #[serde(flatten)]
+    items: SharedItems,
+}
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+/// Everyone sharing in `direction`, or with `?userId=` what is shared with or
+/// by that one person.
+async fn list_shared(
+    state: Arc<AppState>,
+    event: Request,
+    direction: Direction,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let query = Query::from_request(&event);
+
+    let other_id = match query.get::<Uuid>("userId") {
+        Ok(id) => id,
+        Err(e) => return error_response(400, e.to_string()),
+    };
+    let preview_tier = match query.get::<i16>("accessTier") {
+        Ok(tier) => tier,
+        Err(e) => return error_response(400, e.to_string()),
+    };
+    let limit = match query.get::<i64>("limit") {
+        Ok(limit) => limit.unwrap_or(DEFAULT_ITEM_LIMIT).clamp(1, MAX_ITEM_LIMIT),
+        Err(e) => return error_response(400, e.to_string()),
+    };
+
+    let Some(other_id) = other_id else {
+        if preview_tier.is_some() {
+            return error_response(400, "accessTier needs a userId");
+        }
+
+        let users: Vec<SharedUser> =
+            sharing::shared_users(&state.db_pool, user.user_id, direction).await?;
+
+        return json_response(
+            200,
+            &ApiResponse {
+                success: true,
+                data: Some(users),
+                error: None,
+            },
+        );
+    };
+
+    // Previews are of your own data: what others see of you is yours to tune
+    if preview_tier.is_some() && direction == Direction::WithMe {
+        return error_response(400, "accessTier only applies to /shared-by-me");
+    }
+    let preview_tier = match preview_tier.map(sharing::validate_access_tier).transpose() {
+        Ok(tier) => tier,
+        Err(e) => return error_response(400, e.to_string()),
+    };
+
+    let (viewer_id, owner_id) = direction.viewer_and_owner(user.user_id, other_id);
+    let Some(current_tier) = sharing::access_tier(&state.db_pool, viewer_id, owner_id).await?
+    else {
+        return error_response(404, "Nothing is shared with this user");
+    };
+
+    let items = sharing::shared_items(
+        &state.db_pool,
+        owner_id,
+        preview_tier.unwrap_or(current_tier),
+        limit,
+    )
+    .await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(SharedWithUserResponse {
+                user_id: other_id.to_string(),
+                current_access_tier: current_tier,
+                items,
+            }),
+            error: None,
+        },
+    )
+}
+
+/// GET /shared-with-me
+#[utoipa::path(
+    get,
+    path = "/shared-with-me",
+    tag = "sharing",
+    params(
+        ("userId" = Option<Uuid>, Query, description = "List what you see of this person"),
+        ("limit" = Option<i64>, Query, description = "Most facts and entities listed with `userId`"),
+    ),
+    responses(
+        (status = 200, description = "People whose facts and entities you can see, with counts; with `userId`, what you see of that person (`userId`, `currentAccessTier`, `facts`, `entities`)", body = ApiResponse<Vec<SharedUser>>),
+        (status = 400, description = "Invalid `userId` or `limit`, or `accessTier` given", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Nothing is shared with this user", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn shared_with_me(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    list_shared(state, event, Direction::WithMe).await
+}
+
+/// GET /shared-by-me
+#[utoipa::path(
+    get,
+    path = "/shared-by-me",
+    tag = "sharing",
+    params(
+        ("userId" = Option<Uuid>, Query, description = "List what this person sees"),
+        ("accessTier" = Option<i16>, Query, description = "Preview what they'd see at this tier (needs `userId`)"),
+        ("limit" = Option<i64>, Query, description = "Most facts and entities listed with `userId`"),
+    ),
+    responses(
+        (status = 200, description = "People who can see your facts and entities, with counts; with `userId`, what that person sees (`userId`, `currentAccessTier`, `facts`, `entities`)", body = ApiResponse<Vec<SharedUser>>),
+        (status = 400, description = "Invalid `userId`, `accessTier` or `limit`", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Nothing is shared with this user", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn shared_by_me(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    list_shared(state, event, Direction::ByMe).await
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .get("/shared-with-me", shared_with_me)
+        .get("/shared-by-me", shared_by_me)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
@@ -0,0 +1,322 @@
+//! SMS Phone Lambda - Registers phone numbers for SMS ingestion and query.
+//!
+//! Endpoints:
+//! - GET /sms/phone - Registered number and verification status
+//! - POST /sms/phone - Text a one-time code to a number
+//! - POST /sms/phone/verify - Confirm the number with the code
+//! - DELETE /sms/phone - Remove the number
+
+use chrono::{DateTime, Utc};
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::{Deserialize, Serialize};
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::sms::{
+    create_verification, normalize_phone, send_sms, unlink, verify_code,
+    VERIFICATION_CODE_TTL_MINUTES,
+};
+use shared::ApiError;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+
+/// Rejection for numbers that can't be normalized to E.164
+const INVALID_PHONE_MESSAGE: &str =
+    "phoneNumber must be a valid number, with a +country code outside North America";
+
+/// Phone status row from database
+#[derive(Debug, sqlx::FromRow)]
+struct PhoneRow {
+    phone_number: Option<String>,
+    phone_verified_at: Option<DateTime<Utc>>,
+}
+
+/// Register phone request
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct RegisterPhoneRequest {
+    phone_number: String,
+}
+
+/// Verify phone request
+#[derive(Debug, Deserialize, ToSchema)]
+struct VerifyPhoneRequest {
+    code: String,
+}
+
+/// Phone status API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct PhoneStatusResponse {
+    verified: bool,
+    phone_number: Option<String>,
+    verified_at: Option<String>,
+}
+
+/// Verification sent API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct VerificationSentResponse {
+    phone_number: String,
+    expires_at: String,
+}
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+    sns_client: aws_sdk_sns::Client,
+    /// Two-way number that codes are sent from and texts are received on
+    origination_number: Option<String>,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self {
+            db_pool,
+            sns_client: aws_sdk_sns::Client::new(&config),
+            origination_number: std::env::var("SMS_ORIGINATION_NUMBER").ok(),
+        })
+    }
+}
+
+/// GET /sms/phone
+#[utoipa::path(
+    get,
+    path = "/sms/phone",
+    tag = "sms",
+    responses(
+        (status = 200, description = "Your registered number and whether it's verified", body = ApiResponse<PhoneStatusResponse>),
+    ),
+    security(("cognito" = []))
+)]
+async fn get_phone(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let phone: PhoneRow =
+        sqlx::query_as("SELECT phone_number, phone_verified_at FROM users WHERE id = $1")
+            .bind(user.user_id)
+            .fetch_one(&state.db_pool)
+            .await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(PhoneStatusResponse {
+                verified: phone.phone_verified_at.is_some(),
+                phone_number: phone.phone_number,
+                verified_at: phone.phone_verified_at.map(|t| t.to_rfc3339()),
+            }),
+            error: None,
+        },
+    )
+}
+
+/// POST /sms/phone
+#[utoipa::path(
+    post,
+    path = "/sms/phone",
+    tag = "sms",
+    request_body = RegisterPhoneRequest,
+    responses(
+        (status = 202, description = "A one-time code was texted to the number", body = ApiResponse<VerificationSentResponse>),
+        (status = 400, description = "Not a valid phone number", body = ApiError, content_type = "application/problem+json"),
+        (status = 429, description = "Too many codes requested", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn register_phone(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let request: RegisterPhoneRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let phone_number = match normalize_phone(&request.phone_number) {
+        Some(phone_number) => phone_number,
+        None => return error_response(400, INVALID_PHONE_MESSAGE),
+    };
+
+    let (code, expires_at) =
+        match create_verification(&state.db_pool, user.user_id, &phone_number).await? {
+            Some(verification) => verification,
+            None => return error_response(429, "Too many codes requested. Try again later."),
+        };
+
+    let message = format!(
+        "Your Second Brain verification code is {}. It expires in {} minutes.",
+        code, VERIFICATION_CODE_TTL_MINUTES
+    );
+    send_sms(
+        &state.sns_client,
+        &phone_number,
+        state.origination_number.as_deref(),
+        &message,
+    )
+    .await?;
+
+    info!(user_id = %user.user_id, "Sent phone verification code");
+
+    json_response(
+        202,
+        &ApiResponse {
+            success: true,
+            data: Some(VerificationSentResponse {
+                phone_number,
+                expires_at: expires_at.to_rfc3339(),
+            }),
+            error: None,
+        },
+    )
+}
+
+/// POST /sms/phone/verify
+#[utoipa::path(
+    post,
+    path = "/sms/phone/verify",
+    tag = "sms",
+    request_body = VerifyPhoneRequest,
+    responses(
+        (status = 200, description = "The number is verified", body = ApiResponse<PhoneStatusResponse>),
+        (status = 400, description = "Invalid or expired code", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn verify_phone(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let request: VerifyPhoneRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let phone_number = match verify_code(&state.db_pool, user.user_id, &request.code).await? {
+        Some(phone_number) => phone_number,
+        None => return error_response(400, "Invalid or expired code"),
+    };
+
+    info!(user_id = %user.user_id, "Verified phone number");
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(PhoneStatusResponse {
+                verified: true,
+                phone_number: Some(phone_number),
+                verified_at: Some(Utc::now().to_rfc3339()),
+            }),
+            error: None,
+        },
+    )
+}
+
+/// DELETE /sms/phone
+#[utoipa::path(
+    delete,
+    path = "/sms/phone",
+    tag = "sms",
+    responses(
+        (status = 200, description = "Number removed"),
+        (status = 404, description = "No phone number registered", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn delete_phone(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+
+    let removed = unlink(&state.db_pool, user.user_id).await?;
+
+    if !removed {
+        return error_response(404, "No phone number registered");
+    }
+
+    info!(user_id = %user.user_id, "Removed phone number");
+
+    json_response(
+        200,
+        &ApiResponse::<()> {
+            success: true,
+            data: None,
+            error: None,
+        },
+    )
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .get("/sms/phone", get_phone)
+        .post("/sms/phone", register_phone)
+        .delete("/sms/phone", delete_phone)
+        .post("/sms/phone/verify", verify_phone)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
@@ -0,0 +1,1061 @@
+//! Spaces Lambda - Handles family spaces.
+//!
+//! Endpoints:
+//! - GET /families/{familyId}/spaces - Spaces you're in (admins see every space)
+//! - POST /families/{familyId}/spaces - Create a space
+//! - GET /spaces/{id} - Space details and members
+//! - PUT /spaces/{id} - Rename or describe a space
+//! - DELETE /spaces/{id} - Delete an empty space (its creator or a family admin)
+//! - POST /spaces/{id}/members - Add a family member to a space
+//! - DELETE /spaces/{id}/members/{userId} - Remove a member (or leave)
+//! - POST /spaces/{id}/items - Move family facts and entities into a space
+//! - DELETE /spaces/{id}/items - Move them back to the whole family
+//!
+//! A space's facts and entities are only visible to its members (see
+//! `shared::access::space_clause`). Family admins can manage any space,
+//! including adding themselves, but only members read what's in it.
+
+use chrono::{DateTime, Utc};
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::{Deserialize, Serialize};
+use shared::access::space_clause;
+use shared::audit::{self, AuditAction, AuditResource};
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::ApiError;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Longest space name accepted
+const MAX_NAME_LENGTH: usize = 100;
+
+/// Most facts plus entities moved in one request
+const MAX_ITEMS: usize = 100;
+
+/// Columns selected into `SpaceRow` (from `family_spaces s`, with the
+/// requesting user bound at `$2`)
+const SPACE_COLUMNS: &str = r#"
+    s.id, s.family_id, s.name, s.description, s.created_by, s.created_at, s.updated_at,
+    (SELECT COUNT(*) FROM family_space_members m WHERE m.space_id = s.id) AS member_count,
+    (SELECT COUNT(*) FROM facts f WHERE f.space_id = s.id AND f.deleted_at IS NULL) AS fact_count,
+    (SELECT COUNT(*) FROM entities e WHERE e.space_id = s.id AND e.deleted_at IS NULL) AS entity_count,
+    EXISTS (
+        SELECT 1 FROM family_space_members m WHERE m.space_id = s.id AND m.user_id = $2
+    ) AS is_member
+"#;
+
+/// Space row from database
+#[derive(Debug, sqlx::FromRow)]
+struct SpaceRow {
+    id: Uuid,
+    family_id: Uuid,
+    name: String,
+    description: Option<String>,
+    created_by: Option<Uuid>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    member_count: i64,
+    fact_count: i64,
+    entity_count: i64,
+    is_member: bool,
+}
+
+/// Space member row from database
+#[derive(Debug, sqlx::FromRow)]
+struct MemberRow {
+    user_id: Uuid,
+    email: String,
+    display_name: Option<String>,
+    added_at: DateTime<Utc>,
+}
+
+/// Create space request
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct CreateSpaceRequest {
+    name: String,
+    description: Option<String>,
+    /// Family members to add besides the creator
+    #[serde(default)]
+    member_ids: Vec<Uuid>,
+}
+
+/// Update space request
+#[derive(Debug, Deserialize, ToSchema)]
+struct UpdateSpaceRequest {
+    name: Option<String>,
+    /// An empty description clears it
+    description: Option<String>,
+}
+
+/// Add member request
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct AddMemberRequest {
+    user_id: Uuid,
+}
+
+/// Facts and entities to move into or out of a space
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct ItemsRequest {
+    #[serde(default)]
+    fact_ids: Vec<Uuid>,
+    #[serde(default)]
+    entity_ids: Vec<Uuid>,
+}
+
+/// Space API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct SpaceResponse {
+    id: String,
+    family_id: String,
+    name: String,
+    description: Option<String>,
+    created_by: Option<String>,
+    member_count: i64,
+    fact_count: i64,
+    entity_count: i64,
+    /// Whether the requesting user can see the space's contents
+    is_member: bool,
+    created_at: String,
+    updated_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    members: Option<Vec<MemberResponse>>,
+}
+
+impl From<SpaceRow> for SpaceResponse {
+    fn from(row: SpaceRow) -> Self {
+        Self {
+            id: row.id.to_string(),
+            family_id: row.family_id.to_string(),
+            name: row.name,
+            description: row.description,
+            created_by: row.created_by.map(|id| id.to_string()),
+            member_count: row.member_count,
+            fact_count: row.fact_count,
+            entity_count: row.entity_count,
+            is_member: row.is_member,
+            created_at: row.created_at.to_rfc3339(),
+            updated_at: row.updated_at.to_rfc3339(),
+            members: None,
+        }
+    }
+}
+
+/// Space member API response
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct MemberResponse {
+    user_id: String,
+    email: String,
+    display_name: Option<String>,
+    added_at: String,
+}
+
+impl From<MemberRow> for MemberResponse {
+    fn from(row: MemberRow) -> Self {
+        Self {
+            user_id: row.user_id.to_string(),
+            email: row.email,
+            display_name: row.display_name,
+            added_at: row.added_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Items moved by an items request
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+struct MovedResponse {
+    facts: Vec<String>,
+    entities: Vec<String>,
+}
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+/// The requesting user's standing in a space and its family
+#[derive(Debug, sqlx::FromRow)]
+struct SpaceAccess {
+    family_id: Uuid,
+    created_by: Option<Uuid>,
+    is_member: bool,
+    is_admin: bool,
+}
+
+impl SpaceAccess {
+    /// Members and family admins manage a space.
+    fn can_manage(&self) -> bool {
+        self.is_member || self.is_admin
+    }
+}
+
+/// The user's role in a family, or `None` if they aren't a member.
+async fn family_role(
+    pool: &PgPool,
+    family_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<String>, Error> {
+    let role = sqlx::query_scalar(
+        "SELECT role::text FROM family_members WHERE family_id = $1 AND user_id = $2",
+    )
+    .bind(family_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(role)
+}
+
+/// The user's access to a space, or `None` if the space doesn't exist or
+/// they aren't in its family.
+async fn space_access(
+    pool: &PgPool,
+    space_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<SpaceAccess>, Error> {
+    let access = sqlx::query_as(
+        r#"
+        SELECT s.family_id, s.created_by,
+               EXISTS (
+                   SELECT 1 FROM family_space_members m
+                   WHERE m.space_id = s.id AND m.user_id = $2
+               ) AS is_member,
+               fm.role = 'admin' AS is_admin
+        FROM family_spaces s
+        JOIN family_members fm ON fm.family_id = s.family_id AND fm.user_id = $2
+        WHERE s.id = $1
+        "#,
+    )
+    .bind(space_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(access)
+}
+
+/// A trimmed, non-empty name no longer than `MAX_NAME_LENGTH`.
+fn valid_name(name: &str) -> Option<&str> {
+    let name = name.trim();
+    (!name.is_empty() && name.chars().count() <= MAX_NAME_LENGTH).then_some(name)
+}
+
+async fn fetch_space(pool: &PgPool, space_id: Uuid, user_id: Uuid) -> Result<SpaceRow, Error> {
+    let space = sqlx::query_as(&format!(
+        "SELECT {} FROM family_spaces s WHERE s.id = $1",
+        SPACE_COLUMNS
+    ))
+    .bind(space_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(space)
+}
+
+/// GET /families/{familyId}/spaces
+#[utoipa::path(
+    get,
+    path = "/families/{familyId}/spaces",
+    tag = "spaces",
+    params(
+        ("familyId" = Uuid, Path, description = "Family ID"),
+    ),
+    responses(
+        (status = 200, description = "Spaces you're in; admins see every space", body = ApiResponse<Vec<SpaceResponse>>),
+        (status = 403, description = "Not a member of this family", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_spaces(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let family_id = params.id("familyId", "Invalid family ID")?;
+
+    let Some(role) = family_role(&state.db_pool, family_id, user.user_id).await? else {
+        return error_response(403, "Not a member of this family");
+    };
+
+    let spaces: Vec<SpaceRow> = sqlx::query_as(&format!(
+        r#"
+        SELECT {} FROM family_spaces s
+        WHERE s.family_id = $1
+        AND ($3 OR EXISTS (
+            SELECT 1 FROM family_space_members m WHERE m.space_id = s.id AND m.user_id = $2
+        ))
+        ORDER BY s.name
+        "#,
+        SPACE_COLUMNS
+    ))
+    .bind(family_id)
+    .bind(user.user_id)
+    .bind(role == "admin")
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(
+                spaces
+                    .into_iter()
+                    .map(SpaceResponse::from)
+                    .collect::<Vec<_>>(),
+            ),
+            error: None,
+        },
+    )
+}
+
+/// POST /families/{familyId}/spaces
+#[utoipa::path(
+    post,
+    path = "/families/{familyId}/spaces",
+    tag = "spaces",
+    request_body = CreateSpaceRequest,
+    params(
+        ("familyId" = Uuid, Path, description = "Family ID"),
+    ),
+    responses(
+        (status = 201, description = "The space, with you as a member", body = ApiResponse<SpaceResponse>),
+        (status = 400, description = "Invalid name, or members outside the family", body = ApiError, content_type = "application/problem+json"),
+        (status = 403, description = "Not a member of this family", body = ApiError, content_type = "application/problem+json"),
+        (status = 409, description = "The family already has a space with that name", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn create_space(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let family_id = params.id("familyId", "Invalid family ID")?;
+
+    if family_role(&state.db_pool, family_id, user.user_id)
+        .await?
+        .is_none()
+    {
+        return error_response(403, "Not a member of this family");
+    }
+
+    let request: CreateSpaceRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let Some(name) = valid_name(&request.name) else {
+        return error_response(
+            400,
+            format!("name must be 1 to {} characters", MAX_NAME_LENGTH),
+        );
+    };
+    let description = request
+        .description
+        .as_deref()
+        .map(str::trim)
+        .filter(|d| !d.is_empty());
+
+    let mut member_ids = request.member_ids;
+    member_ids.push(user.user_id);
+    member_ids.sort();
+    member_ids.dedup();
+
+    let family_members: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM family_members WHERE family_id = $1 AND user_id = ANY($2)",
+    )
+    .bind(family_id)
+    .bind(&member_ids)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    if family_members != member_ids.len() as i64 {
+        return error_response(400, "Space members must be members of the family");
+    }
+
+    let mut tx = state.db_pool.begin().await?;
+
+    let space_id: Uuid = match sqlx::query_scalar(
+        r#"
+        INSERT INTO family_spaces (family_id, name, description, created_by)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id
+        "#,
+    )
+    .bind(family_id)
+    .bind(name)
+    .bind(description)
+    .bind(user.user_id)
+    .fetch_one(&mut *tx)
+    .await
+    {
+        Ok(id) => id,
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return error_response(409, "The family already has a space with that name");
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO family_space_members (space_id, user_id, added_by)
+        SELECT $1, UNNEST($2::uuid[]), $3
+        "#,
+    )
+    .bind(space_id)
+    .bind(&member_ids)
+    .bind(user.user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    info!(user_id = %user.user_id, family_id = %family_id, space_id = %space_id, "Created space");
+
+    let space = fetch_space(&state.db_pool, space_id, user.user_id).await?;
+    json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(SpaceResponse::from(space)),
+            error: None,
+        },
+    )
+}
+
+/// GET /spaces/{id}
+#[utoipa::path(
+    get,
+    path = "/spaces/{id}",
+    tag = "spaces",
+    params(
+        ("id" = Uuid, Path, description = "Space ID"),
+    ),
+    responses(
+        (status = 200, description = "The space and its members", body = ApiResponse<SpaceResponse>),
+        (status = 404, description = "Space not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn get_space(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let space_id = params.id("id", "Invalid space ID")?;
+
+    match space_access(&state.db_pool, space_id, user.user_id).await? {
+        Some(access) if access.can_manage() => {}
+        _ => return error_response(404, "Space not found"),
+    }
+
+    let space = fetch_space(&state.db_pool, space_id, user.user_id).await?;
+
+    let members: Vec<MemberRow> = sqlx::query_as(
+        r#"
+        SELECT u.id AS user_id, u.email, u.display_name, m.added_at
+        FROM family_space_members m
+        JOIN users u ON u.id = m.user_id
+        WHERE m.space_id = $1
+        ORDER BY m.added_at, u.email
+        "#,
+    )
+    .bind(space_id)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(SpaceResponse {
+                members: Some(members.into_iter().map(MemberResponse::from).collect()),
+                ..SpaceResponse::from(space)
+            }),
+            error: None,
+        },
+    )
+}
+
+/// PUT /spaces/{id}
+#[utoipa::path(
+    put,
+    path = "/spaces/{id}",
+    tag = "spaces",
+    request_body = UpdateSpaceRequest,
+    params(
+        ("id" = Uuid, Path, description = "Space ID"),
+    ),
+    responses(
+        (status = 200, description = "The updated space", body = ApiResponse<SpaceResponse>),
+        (status = 400, description = "Invalid name", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Space not found", body = ApiError, content_type = "application/problem+json"),
+        (status = 409, description = "The family already has a space with that name", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn update_space(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let space_id = params.id("id", "Invalid space ID")?;
+
+    match space_access(&state.db_pool, space_id, user.user_id).await? {
+        Some(access) if access.can_manage() => {}
+        _ => return error_response(404, "Space not found"),
+    }
+
+    let request: UpdateSpaceRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let name = match request.name.as_deref() {
+        Some(name) => match valid_name(name) {
+            Some(name) => Some(name),
+            None => {
+                return error_response(
+                    400,
+                    format!("name must be 1 to {} characters", MAX_NAME_LENGTH),
+                )
+            }
+        },
+        None => None,
+    };
+    let description = request.description.as_deref().map(str::trim);
+
+    let result = sqlx::query(
+        r#"
+        UPDATE family_spaces
+        SET name = COALESCE($2, name),
+            description = CASE WHEN $3::text IS NULL THEN description
+                               ELSE NULLIF($3, '') END,
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(space_id)
+    .bind(name)
+    .bind(description)
+    .execute(&state.db_pool)
+    .await;
+
+    match result {
+        Ok(_) => {}
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return error_response(409, "The family already has a space with that name");
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    let space = fetch_space(&state.db_pool, space_id, user.user_id).await?;
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(SpaceResponse::from(space)),
+            error: None,
+        },
+    )
+}
+
+/// DELETE /spaces/{id}
+#[utoipa::path(
+    delete,
+    path = "/spaces/{id}",
+    tag = "spaces",
+    params(
+        ("id" = Uuid, Path, description = "Space ID"),
+    ),
+    responses(
+        (status = 200, description = "Space deleted"),
+        (status = 403, description = "Only the space's creator or a family admin can delete it", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Space not found", body = ApiError, content_type = "application/problem+json"),
+        (status = 409, description = "The space still has facts or entities", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn delete_space(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let space_id = params.id("id", "Invalid space ID")?;
+
+    let access = match space_access(&state.db_pool, space_id, user.user_id).await? {
+        Some(access) if access.can_manage() => access,
+        _ => return error_response(404, "Space not found"),
+    };
+    if !access.is_admin && access.created_by != Some(user.user_id) {
+        return error_response(
+            403,
+            "Only the space's creator or a family admin can delete it",
+        );
+    }
+
+    // Deleting a space must not quietly show its contents to the whole family;
+    // trashed rows count too, since they can be restored
+    let in_use: bool = sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (SELECT 1 FROM facts WHERE space_id = $1)
+            OR EXISTS (SELECT 1 FROM entities WHERE space_id = $1)
+        "#,
+    )
+    .bind(space_id)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    if in_use {
+        return error_response(
+            409,
+            "Move the space's facts and entities out (or purge them from the trash) first",
+        );
+    }
+
+    sqlx::query("DELETE FROM family_spaces WHERE id = $1")
+        .bind(space_id)
+        .execute(&state.db_pool)
+        .await?;
+
+    info!(user_id = %user.user_id, family_id = %access.family_id, space_id = %space_id, "Deleted space");
+
+    json_response(
+        200,
+        &ApiResponse::<()> {
+            success: true,
+            data: None,
+            error: None,
+        },
+    )
+}
+
+/// POST /spaces/{id}/members
+#[utoipa::path(
+    post,
+    path = "/spaces/{id}/members",
+    tag = "spaces",
+    request_body = AddMemberRequest,
+    params(
+        ("id" = Uuid, Path, description = "Space ID"),
+    ),
+    responses(
+        (status = 200, description = "The space with its new member", body = ApiResponse<SpaceResponse>),
+        (status = 400, description = "Not a member of the family", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Space not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn add_member(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let space_id = params.id("id", "Invalid space ID")?;
+
+    let access = match space_access(&state.db_pool, space_id, user.user_id).await? {
+        Some(access) if access.can_manage() => access,
+        _ => return error_response(404, "Space not found"),
+    };
+
+    let request: AddMemberRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    if family_role(&state.db_pool, access.family_id, request.user_id)
+        .await?
+        .is_none()
+    {
+        return error_response(400, "Space members must be members of the family");
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO family_space_members (space_id, user_id, added_by)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (space_id, user_id) DO NOTHING
+        "#,
+    )
+    .bind(space_id)
+    .bind(request.user_id)
+    .bind(user.user_id)
+    .execute(&state.db_pool)
+    .await?;
+
+    info!(user_id = %user.user_id, space_id = %space_id, member_id = %request.user_id, "Added space member");
+
+    let space = fetch_space(&state.db_pool, space_id, user.user_id).await?;
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(SpaceResponse::from(space)),
+            error: None,
+        },
+    )
+}
+
+/// DELETE /spaces/{id}/members/{userId}
+#[utoipa::path(
+    delete,
+    path = "/spaces/{id}/members/{userId}",
+    tag = "spaces",
+    params(
+        ("id" = Uuid, Path, description = "Space ID"),
+        ("userId" = Uuid, Path, description = "Member's user ID"),
+    ),
+    responses(
+        (status = 200, description = "Member removed"),
+        (status = 404, description = "Space or member not found", body = ApiError, content_type = "application/problem+json"),
+        (status = 409, description = "A space needs at least one member", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn remove_member(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let space_id = params.id("id", "Invalid space ID")?;
+    let member_id = params.id("userId", "Invalid user ID")?;
+
+    match space_access(&state.db_pool, space_id, user.user_id).await? {
+        Some(access) if access.can_manage() => {}
+        _ => return error_response(404, "Space not found"),
+    }
+
+    // A space nobody is in would hide its contents from everyone
+    let removed: Option<bool> = sqlx::query_scalar(
+        r#"
+        DELETE FROM family_space_members m
+        WHERE m.space_id = $1 AND m.user_id = $2
+        AND EXISTS (
+            SELECT 1 FROM family_space_members other
+            WHERE other.space_id = $1 AND other.user_id <> $2
+        )
+        RETURNING true
+        "#,
+    )
+    .bind(space_id)
+    .bind(member_id)
+    .fetch_optional(&state.db_pool)
+    .await?;
+
+    if removed.is_none() {
+        let is_member: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM family_space_members WHERE space_id = $1 AND user_id = $2)",
+        )
+        .bind(space_id)
+        .bind(member_id)
+        .fetch_one(&state.db_pool)
+        .await?;
+
+        return if is_member {
+            error_response(409, "A space needs at least one member")
+        } else {
+            error_response(404, "Member not found")
+        };
+    }
+
+    info!(user_id = %user.user_id, space_id = %space_id, member_id = %member_id, "Removed space member");
+
+    json_response(
+        200,
+        &ApiResponse::<()> {
+            success: true,
+            data: None,
+            error: None,
+        },
+    )
+}
+
+/// Set `space_id` on the family's facts or entities in `ids`, recording each
+/// change. Moving into a space takes rows the user can see; moving out takes
+/// rows in `space_id`. Returns the IDs moved.
+async fn move_items(
+    pool: &PgPool,
+    user_id: Uuid,
+    resource: AuditResource,
+    family_id: Uuid,
+    space_id: Uuid,
+    ids: &[Uuid],
+    into_space: bool,
+) -> Result<Vec<Uuid>, Error> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let (table, alias) = match resource {
+        AuditResource::Fact => ("facts", "f"),
+        _ => ("entities", "e"),
+    };
+    let eligible = if into_space {
+        format!(
+            "{a}.space_id IS DISTINCT FROM $1 AND {}",
+            space_clause(alias, 4),
+            a = alias
+        )
+    } else {
+        format!("{}.space_id = $1", alias)
+    };
+
+    // Snapshots for the audit log before anything changes
+    let sql = format!(
+        r#"
+        SELECT {a}.id, to_jsonb({a}) - 'search_vector'
+        FROM {t} {a}
+        WHERE {a}.id = ANY($2)
+        AND {a}.owner_type = 'family' AND {a}.owner_id = $3
+        AND {a}.deleted_at IS NULL
+        AND {e}
+        "#,
+        a = alias,
+        t = table,
+        e = eligible,
+    );
+    let mut query = sqlx::query_as::<_, (Uuid, serde_json::Value)>(&sql)
+        .bind(space_id)
+        .bind(ids)
+        .bind(family_id);
+    if into_space {
+        query = query.bind(user_id);
+    }
+    let before = query.fetch_all(pool).await?;
+
+    let mut moved = Vec::with_capacity(before.len());
+    for (id, before) in before {
+        sqlx::query(&format!(
+            "UPDATE {} SET space_id = $2, updated_at = NOW() WHERE id = $1",
+            table
+        ))
+        .bind(id)
+        .bind(into_space.then_some(space_id))
+        .execute(pool)
+        .await?;
+
+        audit::record_change(
+            pool,
+            user_id,
+            AuditAction::Update,
+            resource,
+            id,
+            Some(before),
+        )
+        .await;
+        moved.push(id);
+    }
+
+    Ok(moved)
+}
+
+/// Shared body of the items endpoints.
+async fn set_items(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+    into_space: bool,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let space_id = params.id("id", "Invalid space ID")?;
+
+    // Only members can see (and so choose) what goes in
+    let access = match space_access(&state.db_pool, space_id, user.user_id).await? {
+        Some(access) if access.can_manage() => access,
+        _ => return error_response(404, "Space not found"),
+    };
+    if !access.is_member {
+        return error_response(403, "Only the space's members can move items in or out");
+    }
+
+    let request: ItemsRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+    if request.fact_ids.len() + request.entity_ids.len() > MAX_ITEMS {
+        return error_response(
+            400,
+            format!("At most {} items can be moved at once", MAX_ITEMS),
+        );
+    }
+
+    let facts = move_items(
+        &state.db_pool,
+        user.user_id,
+        AuditResource::Fact,
+        access.family_id,
+        space_id,
+        &request.fact_ids,
+        into_space,
+    )
+    .await?;
+    let entities = move_items(
+        &state.db_pool,
+        user.user_id,
+        AuditResource::Entity,
+        access.family_id,
+        space_id,
+        &request.entity_ids,
+        into_space,
+    )
+    .await?;
+
+    info!(
+        user_id = %user.user_id,
+        space_id = %space_id,
+        facts = facts.len(),
+        entities = entities.len(),
+        into_space,
+        "Moved space items"
+    );
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(MovedResponse {
+                facts: facts.iter().map(Uuid::to_string).collect(),
+                entities: entities.iter().map(Uuid::to_string).collect(),
+            }),
+            error: None,
+        },
+    )
+}
+
+/// POST /spaces/{id}/items
+#[utoipa::path(
+    post,
+    path = "/spaces/{id}/items",
+    tag = "spaces",
+    request_body = ItemsRequest,
+    params(
+        ("id" = Uuid, Path, description = "Space ID"),
+    ),
+    responses(
+        (status = 200, description = "The facts and entities moved into the space", body = ApiResponse<MovedResponse>),
+        (status = 400, description = "Too many items", body = ApiError, content_type = "application/problem+json"),
+        (status = 403, description = "Only the space's members can move items in or out", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Space not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn add_items(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    set_items(state, event, params, true).await
+}
+
+/// DELETE /spaces/{id}/items
+#[utoipa::path(
+    delete,
+    path = "/spaces/{id}/items",
+    tag = "spaces",
+    request_body = ItemsRequest,
+    params(
+        ("id" = Uuid, Path, description = "Space ID"),
+    ),
+    responses(
+        (status = 200, description = "The facts and entities moved back to the whole family", body = ApiResponse<MovedResponse>),
+        (status = 400, description = "Too many items", body = ApiError, content_type = "application/problem+json"),
+        (status = 403, description = "Only the space's members can move items in or out", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Space not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn remove_items(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    set_items(state, event, params, false).await
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .get("/families/{familyId}/spaces", list_spaces)
+        .post("/families/{familyId}/spaces", create_space)
+        .get("/spaces/{id}", get_space)
+        .put("/spaces/{id}", update_space)
+        .delete("/spaces/{id}", delete_space)
+        .post("/spaces/{id}/members", add_member)
+        .delete("/spaces/{id}/members/{userId}", remove_member)
+        .post("/spaces/{id}/items", add_items)
+        .delete("/spaces/{id}/items", remove_items)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
@@ -2,26 +2,44 @@
 //!
 //! Endpoints:
 //! - POST /tags - Create a tag
-//! - GET /tags - List/search tags
-//! - GET /tags/{id} - Get tag details
-//! - PUT /tags/{id} - Update tag
+//! - GET /tags - List/search tags (`?limit=&cursor=`)
+//! - GET /tags/{id} - Get tag details (with an `ETag`; honors `If-None-Match`)
+//! - PUT /tags/{id} - Update tag (honors `If-Match`)
 //! - DELETE /tags/{id} - Delete tag
+//! - POST /tags/{id}/move - Move/rename a tag and its descendants to a new path
+//! - POST /tags/suggestions - Suggest tags for a fact or content (`mode`: keyword or embedding)
+//! - POST /tags/rules - Create an auto-tagging rule
+//! - GET /tags/rules - List auto-tagging rules
+//! - DELETE /tags/rules/{id} - Delete an auto-tagging rule
 //! - POST /facts/{id}/tags - Apply tags to a fact
 //! - GET /facts/{id}/tags - Get fact's tags
 //! - DELETE /facts/{id}/tags/{tagId} - Remove tag from fact
-//! - GET /tags/{id}/facts - Get facts with a specific tag
+//! - GET /tags/{id}/facts - Get facts with a specific tag (`?limit=&cursor=`)
 
 use lambda_http::{run, service_fn, Body, Error, Request, RequestExt, Response};
 use serde::{Deserialize, Serialize};
-use sqlx::postgres::PgPoolOptions;
+use shared::access::{space_clause, visibility_clause};
+use shared::audit::{self, AuditAction, AuditResource};
+use shared::conditional::{self, IfMatch};
+use shared::embeddings::to_pgvector;
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::models::{keyset_predicate, Cursor, CursorParams, Page, SortDirection};
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::tag_rules::RuleConditions;
+use shared::tag_suggestions::{fact_embedding, suggest_by_centroid};
+use shared::{ApiError, EmbeddingClient};
 use sqlx::PgPool;
 use std::sync::Arc;
 use tracing::info;
 use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 /// Create tag request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct CreateTagRequest {
     name: String,
     path: String,
@@ -32,7 +50,7 @@ struct CreateTagRequest {
 }
 
 /// Update tag request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct UpdateTagRequest {
     name: Option<String>,
     description: Option<String>,
@@ -40,15 +58,48 @@ struct UpdateTagRequest {
     icon: Option<String>,
 }
 
+/// Move tag request
+#[derive(Debug, Deserialize, ToSchema)]
+struct MoveTagRequest {
+    /// New full path; the parent is the tag at the path's prefix
+    path: String,
+    name: Option<String>,
+}
+
+/// Create tag rule request
+#[derive(Debug, Deserialize, ToSchema)]
+struct CreateTagRuleRequest {
+    name: Option<String>,
+    /// Tag applied to matching facts
+    tag_path: String,
+    /// Matches when any keyword appears in the content
+    keywords: Option<Vec<String>>,
+    /// Matches facts about an entity of this type
+    entity_type: Option<String>,
+    /// Matches facts from this source (voice, text, import, calendar, inferred)
+    source: Option<String>,
+    confidence: Option<f64>,
+}
+
 /// Apply tags request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 struct ApplyTagsRequest {
     tag_paths: Vec<String>,
     confidence: Option<f64>,
 }
 
+/// Tag suggestion request: a fact or some content
+#[derive(Debug, Deserialize, ToSchema)]
+struct SuggestRequest {
+    fact_id: Option<String>,
+    content: Option<String>,
+    entity_type: Option<String>,
+    /// `keyword` (default) or `embedding`
+    mode: Option<String>,
+}
+
 /// Tag response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct TagResponse {
     id: String,
     name: String,
@@ -61,7 +112,7 @@ struct TagResponse {
     children: Vec<TagChildResponse>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct TagChildResponse {
     id: String,
     name: String,
@@ -69,7 +120,7 @@ struct TagChildResponse {
 }
 
 /// Fact with tags response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct FactWithTagsResponse {
     id: String,
     content: String,
@@ -78,7 +129,7 @@ struct FactWithTagsResponse {
     tags: Vec<TagSummary>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct TagSummary {
     id: String,
     name: String,
@@ -96,7 +147,7 @@ struct TagStatsResponse {
 }
 
 /// API response wrapper
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 struct ApiResponse<T> {
     success: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -108,912 +159,1648 @@ struct ApiResponse<T> {
 /// Application state
 struct AppState {
     db_pool: PgPool,
+    embedding_client: EmbeddingClient,
 }
 
 impl AppState {
     async fn new() -> Result<Self, Error> {
         let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
-        let secrets_client = aws_sdk_secretsmanager::Client::new(&config);
-
-        let db_secret_arn =
-            std::env::var("DB_SECRET_ARN").map_err(|_| "DB_SECRET_ARN not set")?;
-
-        let db_secret = secrets_client
-            .get_secret_value()
-            .secret_id(&db_secret_arn)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to get DB secret: {}", e))?;
-
-        let db_creds: serde_json::Value =
-            serde_json::from_str(db_secret.secret_string().unwrap_or("{}"))?;
-
-        let db_host = std::env::var("DB_HOST").map_err(|_| "DB_HOST not set")?;
-        let db_name = std::env::var("DB_NAME").unwrap_or_else(|_| "second_brain".to_string());
-        let db_user = db_creds["username"].as_str().unwrap_or("sbadmin");
-        let db_pass = db_creds["password"].as_str().unwrap_or("");
-
-        let database_url = format!(
-            "postgres://{}:{}@{}:5432/{}",
-            db_user, db_pass, db_host, db_name
-        );
 
-        let db_pool = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(&database_url)
+        let db_pool = shared::db::connect(&config)
             .await
             .map_err(|e| format!("Failed to connect to database: {}", e))?;
 
-        Ok(Self { db_pool })
+        let bedrock_client = aws_sdk_bedrockruntime::Client::new(&config);
+        let embedding_client = match std::env::var("EMBEDDING_MODEL_ID") {
+            Ok(model_id) => EmbeddingClient::with_model(bedrock_client, model_id),
+            Err(_) => EmbeddingClient::new(bedrock_client),
+        };
+
+        Ok(Self {
+            db_pool,
+            embedding_client,
+        })
     }
 }
 
-/// Extract user_id from Cognito claims
-fn extract_user_id(event: &Request) -> Result<Uuid, Error> {
-    let context = event
-        .request_context_ref()
-        .ok_or("Missing request context")?;
-
-    let claims = context
-        .authorizer()
-        .and_then(|a| a.fields.get("claims"))
-        .ok_or("Missing claims in authorizer context")?;
-
-    let sub = claims
-        .get("sub")
-        .and_then(|v| v.as_str())
-        .ok_or("Missing sub claim")?;
+/// Whether the user can see the fact.
+async fn can_access_fact(
+    pool: &PgPool,
+    fact_id: Uuid,
+    user_id: Uuid,
+    family_ids: &[Uuid],
+) -> Result<bool, Error> {
+    let has_access: bool = sqlx::query_scalar(&format!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM facts f
+            WHERE f.id = $1
+            AND f.deleted_at IS NULL
+            AND (
+                (f.owner_type = 'user' AND f.owner_id = $2)
+                OR (f.owner_type = 'family' AND f.owner_id = ANY($3) AND {})
+            )
+        )
+        "#,
+        space_clause("f", 2)
+    ))
+    .bind(fact_id)
+    .bind(user_id)
+    .bind(family_ids)
+    .fetch_one(pool)
+    .await?;
 
-    Uuid::parse_str(sub).map_err(|e| format!("Invalid user_id: {}", e).into())
+    Ok(has_access)
 }
 
-async fn handler(state: Arc<AppState>, event: Request) -> Result<Response<Body>, Error> {
-    let raw_path = event.uri().path();
-    // Strip /api stage prefix if present (API Gateway REST API includes stage in path)
-    let path = raw_path.strip_prefix("/api").unwrap_or(raw_path);
-    let method = event.method().as_str();
+/// POST /tags
+#[utoipa::path(
+    post,
+    path = "/tags",
+    tag = "tags",
+    request_body = CreateTagRequest,
+    responses(
+        (status = 201, description = "The tag (`tag_id`, `name`, `path`)", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Invalid path", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn create_tag(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let request: CreateTagRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
 
-    info!("Received request: method={}, path={} (raw: {})", method, path, raw_path);
+    // Validate path format
+    if !request.path.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '/') {
+        return error_response(
+            400,
+            "Path must contain only lowercase letters, numbers, underscores, and slashes",
+        );
+    }
 
-    let cognito_sub = match extract_user_id(&event) {
-        Ok(id) => id,
-        Err(e) => {
-            return Ok(json_response(
-                401,
-                &ApiResponse::<()> {
-                    success: false,
-                    data: None,
-                    error: Some(format!("Authentication required: {}", e)),
-                },
-            )?);
-        }
+    // Find parent tag if specified
+    let parent_id: Option<Uuid> = if let Some(parent_path) = &request.parent_path {
+        sqlx::query_scalar("SELECT id FROM tags WHERE path = $1 AND deleted_at IS NULL")
+            .bind(parent_path)
+            .fetch_optional(&state.db_pool)
+            .await?
+    } else {
+        None
     };
 
-    // Look up database user_id from Cognito sub
-    let user_id: Uuid = match sqlx::query_scalar::<_, Uuid>(
-        "SELECT id FROM users WHERE cognito_sub = $1::text"
-    )
-    .bind(cognito_sub)
-    .fetch_optional(&state.db_pool)
-    .await
-    .map_err(|e| format!("Failed to lookup user: {}", e))? {
-        Some(id) => id,
-        None => {
-            return Ok(json_response(
-                401,
-                &ApiResponse::<()> {
-                    success: false,
-                    data: None,
-                    error: Some("User not registered".to_string()),
-                },
-            )?);
-        }
-    };
+    let tag_id = Uuid::new_v4();
 
-    // Get user's family IDs for permission checks
-    let family_ids: Vec<Uuid> = sqlx::query_scalar(
-        "SELECT family_id FROM family_members WHERE user_id = $1"
+    sqlx::query(
+        r#"
+        INSERT INTO tags (id, name, path, parent_id, description, color, icon, owner_type, owner_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, 'user', $8)
+        "#
     )
+    .bind(tag_id)
+    .bind(&request.name)
+    .bind(&request.path)
+    .bind(parent_id)
+    .bind(&request.description)
+    .bind(&request.color)
+    .bind(&request.icon)
     .bind(user_id)
-    .fetch_all(&state.db_pool)
-    .await
-    .unwrap_or_default();
-
-    match (method, path) {
-        // Create tag
-        ("POST", "/tags") => {
-            let request: CreateTagRequest = match shared::parse_json_body(event.body())? {
-                Ok(r) => r,
-                Err(response) => return Ok(response),
-            };
-
-            // Validate path format
-            if !request.path.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '/') {
-                return Ok(json_response(
-                    400,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Path must contain only lowercase letters, numbers, underscores, and slashes".to_string()),
-                    },
-                )?);
-            }
-
-            // Find parent tag if specified
-            let parent_id: Option<Uuid> = if let Some(parent_path) = &request.parent_path {
-                sqlx::query_scalar("SELECT id FROM tags WHERE path = $1")
-                    .bind(parent_path)
-                    .fetch_optional(&state.db_pool)
-                    .await
-                    .map_err(|e| format!("Failed to find parent tag: {}", e))?
-            } else {
-                None
-            };
+    .execute(&state.db_pool)
+    .await?;
+
+    audit::record_change(&state.db_pool, user_id, AuditAction::Create, AuditResource::Tag, tag_id, None).await;
+
+    info!("Created tag {} ({})", request.name, request.path);
+
+    Ok(json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "tag_id": tag_id.to_string(),
+                "name": request.name,
+                "path": request.path,
+            })),
+            error: None,
+        },
+    )?)
+}
 
-            let tag_id = Uuid::new_v4();
+/// GET /tags
+#[utoipa::path(
+    get,
+    path = "/tags",
+    tag = "tags",
+    params(
+        ("q" = Option<String>, Query, description = "Tags whose name contains this"),
+        ("prefix" = Option<String>, Query, description = "Tags under this path"),
+        ("include_system" = Option<bool>, Query, description = "Include system tags (default true)"),
+        ("limit" = Option<i64>, Query, description = "Most tags to return"),
+        ("cursor" = Option<String>, Query, description = "Cursor returned with the previous page"),
+    ),
+    responses(
+        (status = 200, description = "A page of tags (`tags`, `next_cursor`, `has_more`)", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Invalid cursor", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_tags(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+    let family_ids = user.family_ids;
+
+    let params = event.query_string_parameters();
+    let query = params.first("q");
+    let prefix = params.first("prefix");
+    let include_system = params.first("include_system")
+        .map(|v| v == "true")
+        .unwrap_or(true);
+    let page_params = match CursorParams::from_query(params.first("limit"), params.first("cursor"), 50) {
+        Ok(p) => p,
+        Err(e) => {
+            return error_response(400, e.to_string())
+        }
+    };
 
-            sqlx::query(
-                r#"
-                INSERT INTO tags (id, name, path, parent_id, description, color, icon, owner_type, owner_id)
-                VALUES ($1, $2, $3, $4, $5, $6, $7, 'user', $8)
-                "#
+    // Name search pages by name; everything else pages by path
+    let sort_by_name = prefix.is_none() && query.is_some();
+
+    let tags: Vec<(Uuid, String, String, Option<String>, Option<String>, Option<String>, bool, i64)> =
+        if let Some(prefix_path) = prefix {
+            // Autocomplete: search by path prefix
+            sqlx::query_as(
+                &format!(r#"
+                SELECT t.id, t.name, t.path, t.description, t.color, t.icon,
+                       (t.owner_type IS NULL) as is_system,
+                       COALESCE(COUNT(ft.fact_id), 0) as fact_count
+                FROM tags t
+                LEFT JOIN fact_tags ft ON ft.tag_id = t.id
+                WHERE t.path LIKE $1 || '%'
+                AND t.deleted_at IS NULL
+                AND (
+                    t.owner_type IS NULL
+                    OR (t.owner_type = 'user' AND t.owner_id = $2)
+                    OR (t.owner_type = 'family' AND t.owner_id = ANY($3))
+                )
+                AND {}
+                GROUP BY t.id
+                ORDER BY t.path, t.id
+                LIMIT $4
+                "#, keyset_predicate("t.path", "text", "t.id", 5, SortDirection::Asc))
+            )
+            .bind(prefix_path)
+            .bind(user_id)
+            .bind(&family_ids)
+            .bind(page_params.fetch_limit())
+            .bind(page_params.after_key())
+            .bind(page_params.after_id())
+            .fetch_all(&state.db_pool)
+            .await
+        } else if let Some(q) = query {
+            // Search by name
+            sqlx::query_as(
+                &format!(r#"
+                SELECT t.id, t.name, t.path, t.description, t.color, t.icon,
+                       (t.owner_type IS NULL) as is_system,
+                       COALESCE(COUNT(ft.fact_id), 0) as fact_count
+                FROM tags t
+                LEFT JOIN fact_tags ft ON ft.tag_id = t.id
+                WHERE t.name ILIKE $1
+                AND t.deleted_at IS NULL
+                AND (
+                    t.owner_type IS NULL
+                    OR (t.owner_type = 'user' AND t.owner_id = $2)
+                    OR (t.owner_type = 'family' AND t.owner_id = ANY($3))
+                )
+                AND {}
+                GROUP BY t.id
+                ORDER BY t.name, t.id
+                LIMIT $4
+                "#, keyset_predicate("t.name", "text", "t.id", 5, SortDirection::Asc))
+            )
+            .bind(format!("%{}%", q))
+            .bind(user_id)
+            .bind(&family_ids)
+            .bind(page_params.fetch_limit())
+            .bind(page_params.after_key())
+            .bind(page_params.after_id())
+            .fetch_all(&state.db_pool)
+            .await
+        } else {
+            // List all accessible tags
+            let system_filter = if include_system { "1=1" } else { "t.owner_type IS NOT NULL" };
+            sqlx::query_as(
+                &format!(r#"
+                SELECT t.id, t.name, t.path, t.description, t.color, t.icon,
+                       (t.owner_type IS NULL) as is_system,
+                       COALESCE(COUNT(ft.fact_id), 0) as fact_count
+                FROM tags t
+                LEFT JOIN fact_tags ft ON ft.tag_id = t.id
+                WHERE {}
+                AND t.deleted_at IS NULL
+                AND (
+                    t.owner_type IS NULL
+                    OR (t.owner_type = 'user' AND t.owner_id = $1)
+                    OR (t.owner_type = 'family' AND t.owner_id = ANY($2))
+                )
+                AND {}
+                GROUP BY t.id
+                ORDER BY t.path, t.id
+                LIMIT $3
+                "#, system_filter, keyset_predicate("t.path", "text", "t.id", 4, SortDirection::Asc))
             )
-            .bind(tag_id)
-            .bind(&request.name)
-            .bind(&request.path)
-            .bind(parent_id)
-            .bind(&request.description)
-            .bind(&request.color)
-            .bind(&request.icon)
             .bind(user_id)
-            .execute(&state.db_pool)
+            .bind(&family_ids)
+            .bind(page_params.fetch_limit())
+            .bind(page_params.after_key())
+            .bind(page_params.after_id())
+            .fetch_all(&state.db_pool)
             .await
-            .map_err(|e| format!("Failed to create tag: {}", e))?;
+        }?;
+
+    let page = Page::from_rows(tags, &page_params, |row| {
+        let key = if sort_by_name { &row.1 } else { &row.2 };
+        Cursor::new(key.clone(), row.0)
+    })
+    .map(|(id, name, path, description, color, icon, is_system, fact_count)| {
+        serde_json::json!({
+            "id": id.to_string(),
+            "name": name,
+            "path": path,
+            "description": description,
+            "color": color,
+            "icon": icon,
+            "is_system": is_system,
+            "fact_count": fact_count,
+        })
+    });
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "count": page.items.len(),
+                "tags": page.items,
+                "next_cursor": page.next_cursor,
+                "has_more": page.has_more,
+            })),
+            error: None,
+        },
+    )?)
+}
 
-            info!("Created tag {} ({})", request.name, request.path);
+/// GET /tags/stats
+#[utoipa::path(
+    get,
+    path = "/tags/stats",
+    tag = "tags",
+    params(
+        ("root" = Option<String>, Query, description = "Only tags under this path"),
+    ),
+    responses(
+        (status = 200, description = "Fact counts by tag path", body = ApiResponse<serde_json::Value>),
+    ),
+    security(("cognito" = []))
+)]
+async fn tag_stats(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+    let family_ids = user.family_ids;
+
+    let params = event.query_string_parameters();
+    let root_path = params.first("root").unwrap_or("");
+
+    let stats: Vec<(String, String, i64)> = sqlx::query_as(
+        r#"
+        SELECT t.path, t.name, COALESCE(COUNT(ft.fact_id), 0) as fact_count
+        FROM tags t
+        LEFT JOIN fact_tags ft ON ft.tag_id = t.id
+        WHERE t.path LIKE $1 || '%'
+        AND t.deleted_at IS NULL
+        AND (
+            t.owner_type IS NULL
+            OR (t.owner_type = 'user' AND t.owner_id = $2)
+            OR (t.owner_type = 'family' AND t.owner_id = ANY($3))
+        )
+        GROUP BY t.id
+        ORDER BY t.path
+        "#
+    )
+    .bind(root_path)
+    .bind(user_id)
+    .bind(&family_ids)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let response: Vec<serde_json::Value> = stats.into_iter()
+        .map(|(path, name, fact_count)| {
+            serde_json::json!({
+                "path": path,
+                "name": name,
+                "fact_count": fact_count,
+            })
+        })
+        .collect();
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(response),
+            error: None,
+        },
+    )?)
+}
 
-            Ok(json_response(
-                201,
-                &ApiResponse {
-                    success: true,
-                    data: Some(serde_json::json!({
-                        "tag_id": tag_id.to_string(),
-                        "name": request.name,
-                        "path": request.path,
-                    })),
-                    error: None,
-                },
-            )?)
-        }
+/// POST /tags/suggestions
+#[utoipa::path(
+    post,
+    path = "/tags/suggestions",
+    tag = "tags",
+    request_body = SuggestRequest,
+    responses(
+        (status = 200, description = "Suggested tags with the reason for each", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Neither `fact_id` nor `content`, or an unknown mode", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Fact not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn suggest_tags(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+    let family_ids = user.family_ids;
+
+    let request: SuggestRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
 
-        // List/search tags
-        ("GET", "/tags") => {
-            let params = event.query_string_parameters();
-            let query = params.first("q");
-            let prefix = params.first("prefix");
-            let include_system = params.first("include_system")
-                .map(|v| v == "true")
-                .unwrap_or(true);
-            let limit: i64 = params.first("limit")
-                .and_then(|l| l.parse().ok())
-                .unwrap_or(50);
-
-            let tags: Vec<(Uuid, String, String, Option<String>, Option<String>, Option<String>, bool, i64)> =
-                if let Some(prefix_path) = prefix {
-                    // Autocomplete: search by path prefix
-                    sqlx::query_as(
-                        r#"
-                        SELECT t.id, t.name, t.path, t.description, t.color, t.icon,
-                               (t.owner_type IS NULL) as is_system,
-                               COALESCE(COUNT(ft.fact_id), 0) as fact_count
-                        FROM tags t
-                        LEFT JOIN fact_tags ft ON ft.tag_id = t.id
-                        WHERE t.path LIKE $1 || '%'
-                        AND (
-                            t.owner_type IS NULL
-                            OR (t.owner_type = 'user' AND t.owner_id = $2)
-                            OR (t.owner_type = 'family' AND t.owner_id = ANY($3))
-                        )
-                        GROUP BY t.id
-                        ORDER BY t.path
-                        LIMIT $4
-                        "#
-                    )
-                    .bind(prefix_path)
-                    .bind(user_id)
-                    .bind(&family_ids)
-                    .bind(limit)
-                    .fetch_all(&state.db_pool)
-                    .await
-                } else if let Some(q) = query {
-                    // Search by name
-                    sqlx::query_as(
+    match request.mode.as_deref().unwrap_or("keyword") {
+        "keyword" => {}
+        "embedding" => {
+            let fact_id = match &request.fact_id {
+                Some(id) => Some(Uuid::parse_str(id).map_err(|_| "Invalid fact ID")?),
+                None => None,
+            };
+
+            let fact_content: Option<String> = match fact_id {
+                Some(id) => {
+                    let content: Option<String> = sqlx::query_scalar(&format!(
                         r#"
-                        SELECT t.id, t.name, t.path, t.description, t.color, t.icon,
-                               (t.owner_type IS NULL) as is_system,
-                               COALESCE(COUNT(ft.fact_id), 0) as fact_count
-                        FROM tags t
-                        LEFT JOIN fact_tags ft ON ft.tag_id = t.id
-                        WHERE t.name ILIKE $1
+                        SELECT content FROM facts
+                        WHERE id = $1
+                        AND deleted_at IS NULL
                         AND (
-                            t.owner_type IS NULL
-                            OR (t.owner_type = 'user' AND t.owner_id = $2)
-                            OR (t.owner_type = 'family' AND t.owner_id = ANY($3))
+                            (owner_type = 'user' AND owner_id = $2)
+                            OR (owner_type = 'family' AND owner_id = ANY($3) AND {})
                         )
-                        GROUP BY t.id
-                        ORDER BY fact_count DESC, t.name
-                        LIMIT $4
-                        "#
-                    )
-                    .bind(format!("%{}%", q))
+                        "#,
+                        space_clause("facts", 2)
+                    ))
+                    .bind(id)
                     .bind(user_id)
                     .bind(&family_ids)
-                    .bind(limit)
-                    .fetch_all(&state.db_pool)
-                    .await
-                } else {
-                    // List all accessible tags
-                    let system_filter = if include_system { "1=1" } else { "t.owner_type IS NOT NULL" };
-                    sqlx::query_as(
-                        &format!(r#"
-                        SELECT t.id, t.name, t.path, t.description, t.color, t.icon,
-                               (t.owner_type IS NULL) as is_system,
-                               COALESCE(COUNT(ft.fact_id), 0) as fact_count
-                        FROM tags t
-                        LEFT JOIN fact_tags ft ON ft.tag_id = t.id
-                        WHERE {}
-                        AND (
-                            t.owner_type IS NULL
-                            OR (t.owner_type = 'user' AND t.owner_id = $1)
-                            OR (t.owner_type = 'family' AND t.owner_id = ANY($2))
-                        )
-                        GROUP BY t.id
-                        ORDER BY t.path
-                        LIMIT $3
-                        "#, system_filter)
+                    .fetch_optional(&state.db_pool)
+                    .await?;
+
+                    if content.is_none() {
+                        return error_response(404, "Fact not found");
+                    }
+                    content
+                }
+                None => None,
+            };
+
+            let stored = match fact_id {
+                Some(id) => fact_embedding(&state.db_pool, id).await?,
+                None => None,
+            };
+
+            // Facts not indexed yet (and draft content) are embedded on the fly
+            let (embedding, model_id) = match stored {
+                Some(stored) => stored,
+                None => {
+                    let Some(text) = request.content.as_deref().or(fact_content.as_deref()) else {
+                        return error_response(400, "fact_id or content is required");
+                    };
+                    let vector = state.embedding_client.embed(text).await?;
+                    (
+                        to_pgvector(&vector),
+                        state.embedding_client.model_id().to_string(),
                     )
-                    .bind(user_id)
-                    .bind(&family_ids)
-                    .bind(limit)
-                    .fetch_all(&state.db_pool)
-                    .await
                 }
-                .map_err(|e| format!("Failed to fetch tags: {}", e))?;
+            };
+
+            let suggestions = suggest_by_centroid(
+                &state.db_pool,
+                &embedding,
+                &model_id,
+                user_id,
+                &family_ids,
+                fact_id,
+                5,
+            )
+            .await?;
 
-            let response: Vec<serde_json::Value> = tags.into_iter()
-                .map(|(id, name, path, description, color, icon, is_system, fact_count)| {
+            let suggestions: Vec<serde_json::Value> = suggestions
+                .into_iter()
+                .map(|s| {
                     serde_json::json!({
-                        "id": id.to_string(),
-                        "name": name,
-                        "path": path,
-                        "description": description,
-                        "color": color,
-                        "icon": icon,
-                        "is_system": is_system,
-                        "fact_count": fact_count,
+                        "path": s.path,
+                        "name": s.name,
+                        "reason": format!("similar to {} facts tagged {}", s.fact_count, s.path),
+                        "confidence": s.confidence,
+                        "similarity": s.similarity,
                     })
                 })
                 .collect();
 
-            Ok(json_response(
+            return Ok(json_response(
                 200,
                 &ApiResponse {
                     success: true,
                     data: Some(serde_json::json!({
-                        "count": response.len(),
-                        "tags": response,
+                        "mode": "embedding",
+                        "suggestions": suggestions,
                     })),
                     error: None,
                 },
-            )?)
+            )?);
         }
+        _ => {
+            return error_response(400, "mode must be one of: keyword, embedding");
+        }
+    }
 
-        // Tag statistics (tree view)
-        ("GET", "/tags/stats") => {
-            let params = event.query_string_parameters();
-            let root_path = params.first("root").unwrap_or("");
-
-            let stats: Vec<(String, String, i64)> = sqlx::query_as(
+    let mut suggestions: Vec<serde_json::Value> = Vec::new();
+
+    // If fact_id provided, get suggestions based on entity and similar facts
+    if let Some(fact_id_str) = &request.fact_id {
+        let fact_id = Uuid::parse_str(fact_id_str)
+            .map_err(|_| "Invalid fact ID")?;
+
+        // Get fact's entity type for suggestions
+        let entity_type: Option<String> = sqlx::query_scalar(
+            r#"
+            SELECT e.entity_type::text
+            FROM facts f
+            JOIN entities e ON e.id = f.about_entity_id
+            WHERE f.id = $1
+            "#
+        )
+        .bind(fact_id)
+        .fetch_optional(&state.db_pool)
+        .await?;
+
+        // Suggest tags commonly used with this entity type
+        if let Some(et) = entity_type {
+            let entity_tags: Vec<(String, String, i64)> = sqlx::query_as(
                 r#"
-                SELECT t.path, t.name, COALESCE(COUNT(ft.fact_id), 0) as fact_count
+                SELECT t.path, t.name, COUNT(*) as usage_count
                 FROM tags t
-                LEFT JOIN fact_tags ft ON ft.tag_id = t.id
-                WHERE t.path LIKE $1 || '%'
-                AND (
-                    t.owner_type IS NULL
-                    OR (t.owner_type = 'user' AND t.owner_id = $2)
-                    OR (t.owner_type = 'family' AND t.owner_id = ANY($3))
-                )
+                JOIN fact_tags ft ON ft.tag_id = t.id
+                JOIN facts f ON f.id = ft.fact_id
+                JOIN entities e ON e.id = f.about_entity_id
+                WHERE e.entity_type::text = $1
+                AND t.deleted_at IS NULL AND f.deleted_at IS NULL
+                AND (t.owner_type IS NULL OR t.owner_type = 'user' AND t.owner_id = $2)
+                AND ft.fact_id != $3
                 GROUP BY t.id
-                ORDER BY t.path
+                ORDER BY usage_count DESC
+                LIMIT 5
                 "#
             )
-            .bind(root_path)
+            .bind(&et)
             .bind(user_id)
-            .bind(&family_ids)
+            .bind(fact_id)
             .fetch_all(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to fetch tag stats: {}", e))?;
+            .await?;
+
+            for (path, name, usage) in entity_tags {
+                let confidence = (0.5 + (usage as f64 / 20.0)).min(0.9);
+                suggestions.push(serde_json::json!({
+                    "path": path,
+                    "name": name,
+                    "reason": format!("commonly used with {} entities", et),
+                    "confidence": confidence,
+                }));
+            }
+        }
+    }
 
-            let response: Vec<serde_json::Value> = stats.into_iter()
-                .map(|(path, name, fact_count)| {
-                    serde_json::json!({
-                        "path": path,
-                        "name": name,
-                        "fact_count": fact_count,
-                    })
-                })
-                .collect();
+    // Suggest based on content keywords if provided
+    if let Some(content) = &request.content {
+        let keyword_tags: Vec<(String, String)> = sqlx::query_as(
+            r#"
+            SELECT t.path, t.name
+            FROM tags t
+            WHERE (t.owner_type IS NULL OR t.owner_type = 'user' AND t.owner_id = $2)
+            AND t.deleted_at IS NULL
+            AND ($1 ILIKE '%' || t.name || '%')
+            LIMIT 5
+            "#
+        )
+        .bind(content)
+        .bind(user_id)
+        .fetch_all(&state.db_pool)
+        .await?;
+
+        for (path, name) in keyword_tags {
+            if !suggestions.iter().any(|s| s["path"] == path) {
+                suggestions.push(serde_json::json!({
+                    "path": path,
+                    "name": name,
+                    "reason": "keyword match in content",
+                    "confidence": 0.7,
+                }));
+            }
+        }
+    }
 
-            Ok(json_response(
-                200,
-                &ApiResponse {
-                    success: true,
-                    data: Some(response),
-                    error: None,
-                },
-            )?)
+    // Sort by confidence
+    suggestions.sort_by(|a, b| {
+        let conf_a = a["confidence"].as_f64().unwrap_or(0.0);
+        let conf_b = b["confidence"].as_f64().unwrap_or(0.0);
+        conf_b.partial_cmp(&conf_a).unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "suggestions": suggestions,
+            })),
+            error: None,
+        },
+    )?)
+}
+
+/// POST /tags/rules
+#[utoipa::path(
+    post,
+    path = "/tags/rules",
+    tag = "tags",
+    request_body = CreateTagRuleRequest,
+    responses(
+        (status = 201, description = "The rule; existing facts are tagged by the next backfill run", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Invalid conditions or confidence, or the tag doesn't exist", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn create_rule(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+    let family_ids = user.family_ids;
+
+    let request: CreateTagRuleRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let conditions = match RuleConditions::parse(request.keywords, request.entity_type, request.source) {
+        Ok(c) => c,
+        Err(e) => {
+            return error_response(400, e.to_string());
         }
+    };
 
-        // Tag suggestions for a specific fact
-        ("POST", "/tags/suggestions") => {
-            #[derive(Deserialize)]
-            struct SuggestRequest {
-                fact_id: Option<String>,
-                content: Option<String>,
-                entity_type: Option<String>,
-            }
+    let confidence = request.confidence.unwrap_or(0.9);
+    if !(0.0..=1.0).contains(&confidence) {
+        return error_response(400, "confidence must be between 0 and 1");
+    }
 
-            let request: SuggestRequest = match shared::parse_json_body(event.body())? {
-                Ok(r) => r,
-                Err(response) => return Ok(response),
-            };
+    // The user's own tag wins over a family or system tag at the same path
+    let tag_id: Option<Uuid> = sqlx::query_scalar(
+        r#"
+        SELECT id FROM tags
+        WHERE path = $1
+        AND deleted_at IS NULL
+        AND (
+            owner_type IS NULL
+            OR (owner_type = 'user' AND owner_id = $2)
+            OR (owner_type = 'family' AND owner_id = ANY($3))
+        )
+        ORDER BY (owner_type = 'user') DESC NULLS LAST
+        LIMIT 1
+        "#
+    )
+    .bind(&request.tag_path)
+    .bind(user_id)
+    .bind(&family_ids)
+    .fetch_optional(&state.db_pool)
+    .await?;
 
-            let mut suggestions: Vec<serde_json::Value> = Vec::new();
-
-            // If fact_id provided, get suggestions based on entity and similar facts
-            if let Some(fact_id_str) = &request.fact_id {
-                let fact_id = Uuid::parse_str(fact_id_str)
-                    .map_err(|_| "Invalid fact ID")?;
-
-                // Get fact's entity type for suggestions
-                let entity_type: Option<String> = sqlx::query_scalar(
-                    r#"
-                    SELECT e.entity_type::text
-                    FROM facts f
-                    JOIN entities e ON e.id = f.about_entity_id
-                    WHERE f.id = $1
-                    "#
-                )
-                .bind(fact_id)
-                .fetch_optional(&state.db_pool)
-                .await
-                .map_err(|e| format!("Failed to fetch fact: {}", e))?;
-
-                // Suggest tags commonly used with this entity type
-                if let Some(et) = entity_type {
-                    let entity_tags: Vec<(String, String, i64)> = sqlx::query_as(
-                        r#"
-                        SELECT t.path, t.name, COUNT(*) as usage_count
-                        FROM tags t
-                        JOIN fact_tags ft ON ft.tag_id = t.id
-                        JOIN facts f ON f.id = ft.fact_id
-                        JOIN entities e ON e.id = f.about_entity_id
-                        WHERE e.entity_type::text = $1
-                        AND (t.owner_type IS NULL OR t.owner_type = 'user' AND t.owner_id = $2)
-                        AND ft.fact_id != $3
-                        GROUP BY t.id
-                        ORDER BY usage_count DESC
-                        LIMIT 5
-                        "#
-                    )
-                    .bind(&et)
-                    .bind(user_id)
-                    .bind(fact_id)
-                    .fetch_all(&state.db_pool)
-                    .await
-                    .map_err(|e| format!("Failed to fetch suggestions: {}", e))?;
-
-                    for (path, name, usage) in entity_tags {
-                        let confidence = (0.5 + (usage as f64 / 20.0)).min(0.9);
-                        suggestions.push(serde_json::json!({
-                            "path": path,
-                            "name": name,
-                            "reason": format!("commonly used with {} entities", et),
-                            "confidence": confidence,
-                        }));
-                    }
-                }
-            }
+    let Some(tag_id) = tag_id else {
+        return error_response(400, format!("Tag '{}' not found", request.tag_path));
+    };
 
-            // Suggest based on content keywords if provided
-            if let Some(content) = &request.content {
-                let keyword_tags: Vec<(String, String)> = sqlx::query_as(
-                    r#"
-                    SELECT t.path, t.name
-                    FROM tags t
-                    WHERE (t.owner_type IS NULL OR t.owner_type = 'user' AND t.owner_id = $2)
-                    AND ($1 ILIKE '%' || t.name || '%')
-                    LIMIT 5
-                    "#
-                )
-                .bind(content)
-                .bind(user_id)
-                .fetch_all(&state.db_pool)
-                .await
-                .map_err(|e| format!("Failed to fetch keyword suggestions: {}", e))?;
-
-                for (path, name) in keyword_tags {
-                    if !suggestions.iter().any(|s| s["path"] == path) {
-                        suggestions.push(serde_json::json!({
-                            "path": path,
-                            "name": name,
-                            "reason": "keyword match in content",
-                            "confidence": 0.7,
-                        }));
-                    }
-                }
-            }
+    let name = request.name
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| request.tag_path.clone());
+
+    let rule_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO tag_rules (user_id, name, tag_id, keywords, entity_type, source, confidence)
+        VALUES ($1, $2, $3, $4, $5::entity_type, $6::fact_source, $7)
+        RETURNING id
+        "#
+    )
+    .bind(user_id)
+    .bind(&name)
+    .bind(tag_id)
+    .bind(&conditions.keywords)
+    .bind(&conditions.entity_type)
+    .bind(&conditions.source)
+    .bind(confidence)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    info!("Created tag rule {} -> {}", rule_id, request.tag_path);
+
+    Ok(json_response(
+        201,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "rule_id": rule_id.to_string(),
+                "name": name,
+                "tag_path": request.tag_path,
+                "keywords": conditions.keywords,
+                "entity_type": conditions.entity_type,
+                "source": conditions.source,
+                "confidence": confidence,
+                // Existing facts are tagged by the next backfill run
+                "backfill_pending": true,
+            })),
+            error: None,
+        },
+    )?)
+}
 
-            // Sort by confidence
-            suggestions.sort_by(|a, b| {
-                let conf_a = a["confidence"].as_f64().unwrap_or(0.0);
-                let conf_b = b["confidence"].as_f64().unwrap_or(0.0);
-                conf_b.partial_cmp(&conf_a).unwrap_or(std::cmp::Ordering::Equal)
-            });
+/// GET /tags/rules
+#[utoipa::path(
+    get,
+    path = "/tags/rules",
+    tag = "tags",
+    responses(
+        (status = 200, description = "Your auto-tagging rules", body = ApiResponse<serde_json::Value>),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_rules(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let rules: Vec<(Uuid, String, String, Vec<String>, Option<String>, Option<String>, f64, bool, i32, Option<chrono::DateTime<chrono::Utc>>)> = sqlx::query_as(
+        r#"
+        SELECT r.id, r.name, t.path, r.keywords, r.entity_type::text, r.source::text,
+               r.confidence::float8, r.enabled, r.applied_count, r.applied_through
+        FROM tag_rules r
+        JOIN tags t ON t.id = r.tag_id
+        WHERE r.user_id = $1
+        ORDER BY r.created_at
+        "#
+    )
+    .bind(user_id)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let response: Vec<serde_json::Value> = rules.into_iter()
+        .map(|(id, name, tag_path, keywords, entity_type, source, confidence, enabled, applied_count, applied_through)| {
+            serde_json::json!({
+                "id": id.to_string(),
+                "name": name,
+                "tag_path": tag_path,
+                "keywords": keywords,
+                "entity_type": entity_type,
+                "source": source,
+                "confidence": confidence,
+                "enabled": enabled,
+                "applied_count": applied_count,
+                "backfill_pending": applied_through.is_none(),
+            })
+        })
+        .collect();
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(response),
+            error: None,
+        },
+    )?)
+}
 
-            Ok(json_response(
-                200,
-                &ApiResponse {
-                    success: true,
-                    data: Some(serde_json::json!({
-                        "suggestions": suggestions,
-                    })),
-                    error: None,
-                },
-            )?)
-        }
+/// DELETE /tags/rules/{id} (tags the rule already applied are kept)
+#[utoipa::path(
+    delete,
+    path = "/tags/rules/{id}",
+    tag = "tags",
+    params(
+        ("id" = Uuid, Path, description = "Tag rule ID"),
+    ),
+    responses(
+        (status = 200, description = "Rule deleted; tags it applied are kept", body = ApiResponse<serde_json::Value>),
+        (status = 404, description = "Tag rule not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn delete_rule(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let rule_id = params.id("id", "Invalid rule ID")?;
+
+    let deleted = sqlx::query("DELETE FROM tag_rules WHERE id = $1 AND user_id = $2")
+        .bind(rule_id)
+        .bind(user_id)
+        .execute(&state.db_pool)
+        .await?
+        .rows_affected();
+
+    if deleted == 0 {
+        return error_response(404, "Tag rule not found");
+    }
 
-        // Fact tagging routes
-        _ if path.starts_with("/facts/") && path.contains("/tags") => {
-            let path_parts: Vec<&str> = path
-                .trim_start_matches("/facts/")
-                .split('/')
-                .collect();
+    info!("Deleted tag rule {}", rule_id);
 
-            let fact_id = Uuid::parse_str(path_parts[0])
-                .map_err(|_| "Invalid fact ID")?;
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({"message": "Tag rule deleted"})),
+            error: None,
+        },
+    )?)
+}
 
-            // Verify access to fact
-            let has_access: bool = sqlx::query_scalar(
-                r#"
-                SELECT EXISTS(
-                    SELECT 1 FROM facts f
-                    WHERE f.id = $1
-                    AND (
-                        (f.owner_type = 'user' AND f.owner_id = $2)
-                        OR (f.owner_type = 'family' AND f.owner_id = ANY($3))
-                    )
-                )
-                "#
-            )
-            .bind(fact_id)
-            .bind(user_id)
-            .bind(&family_ids)
-            .fetch_one(&state.db_pool)
-            .await
-            .map_err(|e| format!("Failed to verify access: {}", e))?;
-
-            if !has_access {
-                return Ok(json_response(
-                    404,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Fact not found".to_string()),
-                    },
-                )?);
-            }
+/// GET /facts/{id}/tags
+#[utoipa::path(
+    get,
+    path = "/facts/{id}/tags",
+    tag = "tags",
+    params(
+        ("id" = Uuid, Path, description = "Fact ID"),
+    ),
+    responses(
+        (status = 200, description = "The fact's tags", body = ApiResponse<Vec<TagSummary>>),
+        (status = 404, description = "Fact not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_fact_tags(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+    let family_ids = user.family_ids;
+
+    let fact_id = params.id("id", "Invalid fact ID")?;
+    if !can_access_fact(&state.db_pool, fact_id, user_id, &family_ids).await? {
+        return error_response(404, "Fact not found");
+    }
 
-            match (method, path_parts.get(1), path_parts.get(2)) {
-                // Get fact's tags
-                ("GET", Some(&"tags"), None) => {
-                    let tags: Vec<TagSummary> = sqlx::query_as::<_, (Uuid, String, String, Option<String>)>(
-                        r#"
-                        SELECT t.id, t.name, t.path, t.color
-                        FROM tags t
-                        JOIN fact_tags ft ON ft.tag_id = t.id
-                        WHERE ft.fact_id = $1
-                        ORDER BY t.path
-                        "#
-                    )
-                    .bind(fact_id)
-                    .fetch_all(&state.db_pool)
-                    .await
-                    .map_err(|e| format!("Failed to fetch tags: {}", e))?
-                    .into_iter()
-                    .map(|(id, name, path, color)| TagSummary {
+    let tags: Vec<TagSummary> = sqlx::query_as::<_, (Uuid, String, String, Option<String>)>(
+        r#"
+        SELECT t.id, t.name, t.path, t.color
+        FROM tags t
+        JOIN fact_tags ft ON ft.tag_id = t.id
+        WHERE ft.fact_id = $1
+        AND t.deleted_at IS NULL
+        ORDER BY t.path
+        "#
+    )
+    .bind(fact_id)
+    .fetch_all(&state.db_pool)
+    .await?
+    .into_iter()
+    .map(|(id, name, path, color)| TagSummary {
+        id: id.to_string(),
+        name,
+        path,
+        color,
+    })
+    .collect();
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(tags),
+            error: None,
+        },
+    )?)
+}
+
+/// POST /facts/{id}/tags
+#[utoipa::path(
+    post,
+    path = "/facts/{id}/tags",
+    tag = "tags",
+    request_body = ApplyTagsRequest,
+    params(
+        ("id" = Uuid, Path, description = "Fact ID"),
+    ),
+    responses(
+        (status = 200, description = "The tag paths applied; unknown paths are skipped", body = ApiResponse<serde_json::Value>),
+        (status = 404, description = "Fact not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn apply_tags(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+    let family_ids = user.family_ids;
+
+    let fact_id = params.id("id", "Invalid fact ID")?;
+    if !can_access_fact(&state.db_pool, fact_id, user_id, &family_ids).await? {
+        return error_response(404, "Fact not found");
+    }
+
+    let request: ApplyTagsRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
+
+    let before = audit::snapshot(&state.db_pool, AuditResource::FactTags, fact_id).await;
+    let confidence = request.confidence.unwrap_or(1.0);
+
+    // Look up every path and tag the fact in one statement
+    let found: Vec<String> = sqlx::query_scalar(
+        r#"
+        WITH matched AS (
+            SELECT DISTINCT ON (path) id, path
+            FROM tags
+            WHERE path = ANY($2) AND deleted_at IS NULL
+            ORDER BY path, id
+        ), applied AS (
+            INSERT INTO fact_tags (fact_id, tag_id, confidence, assigned_by)
+            SELECT $1, id, $3, 'user' FROM matched
+            ON CONFLICT (fact_id, tag_id) DO UPDATE SET
+                confidence = EXCLUDED.confidence
+            RETURNING tag_id
+        )
+        SELECT m.path FROM matched m JOIN applied a ON a.tag_id = m.id
+        "#
+    )
+    .bind(fact_id)
+    .bind(&request.tag_paths)
+    .bind(confidence)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    // In the order they were requested
+    let applied: Vec<String> = request
+        .tag_paths
+        .iter()
+        .filter(|path| found.contains(path))
+        .cloned()
+        .collect();
+
+    if !applied.is_empty() {
+        audit::record_change(&state.db_pool, user_id, AuditAction::Update, AuditResource::FactTags, fact_id, before).await;
+    }
+
+    info!("Applied {} tags to fact {}", applied.len(), fact_id);
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "fact_id": fact_id.to_string(),
+                "tags_applied": applied,
+            })),
+            error: None,
+        },
+    )?)
+}
+
+/// DELETE /facts/{id}/tags/{tagId}
+#[utoipa::path(
+    delete,
+    path = "/facts/{id}/tags/{tagId}",
+    tag = "tags",
+    params(
+        ("id" = Uuid, Path, description = "Fact ID"),
+        ("tagId" = Uuid, Path, description = "Tag ID"),
+    ),
+    responses(
+        (status = 200, description = "Tag removed", body = ApiResponse<serde_json::Value>),
+        (status = 404, description = "Fact not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn remove_fact_tag(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+    let family_ids = user.family_ids;
+
+    let fact_id = params.id("id", "Invalid fact ID")?;
+    if !can_access_fact(&state.db_pool, fact_id, user_id, &family_ids).await? {
+        return error_response(404, "Fact not found");
+    }
+
+    let tag_id = params.id("tagId", "Invalid tag ID")?;
+
+    let before = audit::snapshot(&state.db_pool, AuditResource::FactTags, fact_id).await;
+
+    let removed = sqlx::query("DELETE FROM fact_tags WHERE fact_id = $1 AND tag_id = $2")
+        .bind(fact_id)
+        .bind(tag_id)
+        .execute(&state.db_pool)
+        .await?;
+
+    if removed.rows_affected() > 0 {
+        audit::record_change(&state.db_pool, user_id, AuditAction::Update, AuditResource::FactTags, fact_id, before).await;
+    }
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({"message": "Tag removed"})),
+            error: None,
+        },
+    )?)
+}
+
+/// GET /tags/{id}
+#[utoipa::path(
+    get,
+    path = "/tags/{id}",
+    tag = "tags",
+    params(
+        ("id" = Uuid, Path, description = "Tag ID"),
+        ("If-None-Match" = Option<String>, Header, description = "`ETag` the client has; answered with 304 if it's current"),
+    ),
+    responses(
+        (status = 200, description = "The tag with its children and fact count", body = ApiResponse<TagResponse>, headers(("ETag" = String, description = "Version of the resource, for `If-None-Match` and `If-Match`"))),
+        (status = 304, description = "Not modified since the `If-None-Match` ETag"),
+        (status = 404, description = "Tag not found", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn get_tag(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let _user = require_user(&event, &state.db_pool).await?;
+
+    let tag_id = params.id("id", "Invalid tag ID")?;
+
+    let tag = sqlx::query_as::<_, (Uuid, String, String, Option<String>, Option<String>, Option<String>, bool, i64, chrono::DateTime<chrono::Utc>)>(
+        r#"
+        SELECT t.id, t.name, t.path, t.description, t.color, t.icon,
+               (t.owner_type IS NULL) as is_system,
+               COALESCE(COUNT(ft.fact_id), 0) as fact_count, t.updated_at
+        FROM tags t
+        LEFT JOIN fact_tags ft ON ft.tag_id = t.id
+        WHERE t.id = $1
+        AND t.deleted_at IS NULL
+        GROUP BY t.id
+        "#
+    )
+    .bind(tag_id)
+    .fetch_optional(&state.db_pool)
+    .await?;
+
+    if let Some((id, name, path, description, color, icon, is_system, fact_count, updated_at)) = tag {
+        let etag = conditional::etag(updated_at);
+        if conditional::is_not_modified(&event, &etag) {
+            return Ok(conditional::not_modified(&etag));
+        }
+
+        // Get children
+        let children: Vec<TagChildResponse> = sqlx::query_as::<_, (Uuid, String, String)>(
+            "SELECT id, name, path FROM tags WHERE parent_id = $1 AND deleted_at IS NULL ORDER BY name"
+        )
+        .bind(tag_id)
+        .fetch_all(&state.db_pool)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(id, name, path)| TagChildResponse {
+            id: id.to_string(),
+            name,
+            path,
+        })
+        .collect();
+
+        Ok(conditional::with_etag(
+            json_response(
+                200,
+                &ApiResponse {
+                    success: true,
+                    data: Some(TagResponse {
                         id: id.to_string(),
                         name,
                         path,
+                        description,
                         color,
-                    })
-                    .collect();
-
-                    Ok(json_response(
-                        200,
-                        &ApiResponse {
-                            success: true,
-                            data: Some(tags),
-                            error: None,
-                        },
-                    )?)
-                }
+                        icon,
+                        is_system,
+                        fact_count,
+                        children,
+                    }),
+                    error: None,
+                },
+            )?,
+            &etag,
+        ))
+    } else {
+        error_response(404, "Tag not found")
+    }
+}
 
-                // Apply tags to fact
-                ("POST", Some(&"tags"), None) => {
-                    let request: ApplyTagsRequest = match shared::parse_json_body(event.body())? {
-                        Ok(r) => r,
-                        Err(response) => return Ok(response),
-                    };
+/// PUT /tags/{id}
+#[utoipa::path(
+    put,
+    path = "/tags/{id}",
+    tag = "tags",
+    request_body = UpdateTagRequest,
+    params(
+        ("id" = Uuid, Path, description = "Tag ID"),
+        ("If-Match" = Option<String>, Header, description = "`ETag` the change is based on; answered with 412 if it's stale"),
+    ),
+    responses(
+        (status = 200, description = "Tag updated", body = ApiResponse<serde_json::Value>, headers(("ETag" = String, description = "Version of the resource, for `If-None-Match` and `If-Match`"))),
+        (status = 403, description = "Cannot modify system tags", body = ApiError, content_type = "application/problem+json"),
+        (status = 412, description = "The tag changed since the `If-Match` ETag", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn update_tag(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let tag_id = params.id("id", "Invalid tag ID")?;
+
+    // Check if it's a system tag
+    let is_system: bool = sqlx::query_scalar(
+        "SELECT owner_type IS NULL FROM tags WHERE id = $1"
+    )
+    .bind(tag_id)
+    .fetch_one(&state.db_pool)
+    .await?;
 
-                    let confidence = request.confidence.unwrap_or(1.0);
-                    let mut applied = Vec::new();
+    if is_system {
+        return error_response(403, "Cannot modify system tags");
+    }
 
-                    for tag_path in &request.tag_paths {
-                        // Find tag by path
-                        let tag_id: Option<Uuid> = sqlx::query_scalar(
-                            "SELECT id FROM tags WHERE path = $1"
-                        )
-                        .bind(tag_path)
-                        .fetch_optional(&state.db_pool)
-                        .await
-                        .map_err(|e| format!("Failed to find tag: {}", e))?;
-
-                        if let Some(tid) = tag_id {
-                            sqlx::query(
-                                r#"
-                                INSERT INTO fact_tags (fact_id, tag_id, confidence, assigned_by)
-                                VALUES ($1, $2, $3, 'user')
-                                ON CONFLICT (fact_id, tag_id) DO UPDATE SET
-                                    confidence = EXCLUDED.confidence
-                                "#
-                            )
-                            .bind(fact_id)
-                            .bind(tid)
-                            .bind(confidence)
-                            .execute(&state.db_pool)
-                            .await
-                            .map_err(|e| format!("Failed to apply tag: {}", e))?;
-
-                            applied.push(tag_path.clone());
-                        }
-                    }
+    let request: UpdateTagRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
 
-                    info!("Applied {} tags to fact {}", applied.len(), fact_id);
-
-                    Ok(json_response(
-                        200,
-                        &ApiResponse {
-                            success: true,
-                            data: Some(serde_json::json!({
-                                "fact_id": fact_id.to_string(),
-                                "tags_applied": applied,
-                            })),
-                            error: None,
-                        },
-                    )?)
-                }
+    let if_match = IfMatch::from_request(&event);
+    let before = audit::snapshot(&state.db_pool, AuditResource::Tag, tag_id).await;
+
+    // The row is locked until commit, so a concurrent If-Match
+    // update sees this one's version and fails. Returns the
+    // current version instead if the If-Match doesn't match.
+    let updated = shared::db::with_tx(&state.db_pool, async |tx| {
+        let current: chrono::DateTime<chrono::Utc> = sqlx::query_scalar(
+            "SELECT updated_at FROM tags WHERE id = $1 FOR UPDATE"
+        )
+        .bind(tag_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if !if_match.matches(current) {
+            return Ok(Err(current));
+        }
 
-                // Remove tag from fact
-                ("DELETE", Some(&"tags"), Some(tag_id_str)) => {
-                    let tag_id = Uuid::parse_str(tag_id_str)
-                        .map_err(|_| "Invalid tag ID")?;
-
-                    sqlx::query("DELETE FROM fact_tags WHERE fact_id = $1 AND tag_id = $2")
-                        .bind(fact_id)
-                        .bind(tag_id)
-                        .execute(&state.db_pool)
-                        .await
-                        .map_err(|e| format!("Failed to remove tag: {}", e))?;
-
-                    Ok(json_response(
-                        200,
-                        &ApiResponse {
-                            success: true,
-                            data: Some(serde_json::json!({"message": "Tag removed"})),
-                            error: None,
-                        },
-                    )?)
-                }
+        if let Some(name) = &request.name {
+            sqlx::query("UPDATE tags SET name = $2 WHERE id = $1")
+                .bind(tag_id)
+                .bind(name)
+                .execute(&mut *tx)
+                .await?;
+        }
+        if let Some(desc) = &request.description {
+            sqlx::query("UPDATE tags SET description = $2 WHERE id = $1")
+                .bind(tag_id)
+                .bind(desc)
+                .execute(&mut *tx)
+                .await?;
+        }
+        if let Some(color) = &request.color {
+            sqlx::query("UPDATE tags SET color = $2 WHERE id = $1")
+                .bind(tag_id)
+                .bind(color)
+                .execute(&mut *tx)
+                .await?;
+        }
+        if let Some(icon) = &request.icon {
+            sqlx::query("UPDATE tags SET icon = $2 WHERE id = $1")
+                .bind(tag_id)
+                .bind(icon)
+                .execute(&mut *tx)
+                .await?;
+        }
 
-                _ => Ok(json_response(
-                    405,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Method not allowed".to_string()),
-                    },
-                )?),
-            }
+        // Read back, since the trigger stamps each update
+        let updated_at: chrono::DateTime<chrono::Utc> = sqlx::query_scalar(
+            "SELECT updated_at FROM tags WHERE id = $1"
+        )
+        .bind(tag_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        Ok::<_, Error>(Ok(updated_at))
+    })
+    .await?;
+
+    let updated_at = match updated {
+        Ok(updated_at) => updated_at,
+        Err(current) => {
+            return Ok(conditional::with_etag(
+                error_response(
+                    412,
+                    "Tag was changed by someone else; reload it and try again",
+                )?,
+                &conditional::etag(current),
+            ));
         }
+    };
 
-        // Tag-specific routes
-        _ if path.starts_with("/tags/") => {
-            let path_parts: Vec<&str> = path
-                .trim_start_matches("/tags/")
-                .split('/')
-                .collect();
+    audit::record_change(&state.db_pool, user_id, AuditAction::Update, AuditResource::Tag, tag_id, before).await;
 
-            // Handle /tags/stats separately (already handled above)
-            if path_parts[0] == "stats" {
-                return Ok(json_response(
-                    404,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Not found".to_string()),
-                    },
-                )?);
-            }
+    info!("Updated tag {}", tag_id);
 
-            let tag_id = Uuid::parse_str(path_parts[0])
-                .map_err(|_| "Invalid tag ID")?;
+    Ok(conditional::with_etag(
+        json_response(
+            200,
+            &ApiResponse {
+                success: true,
+                data: Some(serde_json::json!({"message": "Tag updated"})),
+                error: None,
+            },
+        )?,
+        &conditional::etag(updated_at),
+    ))
+}
 
-            match (method, path_parts.get(1)) {
-                // Get tag details
-                ("GET", None) => {
-                    let tag = sqlx::query_as::<_, (Uuid, String, String, Option<String>, Option<String>, Option<String>, bool, i64)>(
-                        r#"
-                        SELECT t.id, t.name, t.path, t.description, t.color, t.icon,
-                               (t.owner_type IS NULL) as is_system,
-                               COALESCE(COUNT(ft.fact_id), 0) as fact_count
-                        FROM tags t
-                        LEFT JOIN fact_tags ft ON ft.tag_id = t.id
-                        WHERE t.id = $1
-                        GROUP BY t.id
-                        "#
-                    )
-                    .bind(tag_id)
-                    .fetch_optional(&state.db_pool)
-                    .await
-                    .map_err(|e| format!("Failed to fetch tag: {}", e))?;
+/// DELETE /tags/{id}
+#[utoipa::path(
+    delete,
+    path = "/tags/{id}",
+    tag = "tags",
+    params(
+        ("id" = Uuid, Path, description = "Tag ID"),
+    ),
+    responses(
+        (status = 200, description = "The tag and its descendants were moved to the trash", body = ApiResponse<serde_json::Value>),
+        (status = 403, description = "Cannot delete system tags", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn delete_tag(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+
+    let tag_id = params.id("id", "Invalid tag ID")?;
+
+    // Check if it's a system tag
+    let is_system: bool = sqlx::query_scalar(
+        "SELECT owner_type IS NULL FROM tags WHERE id = $1"
+    )
+    .bind(tag_id)
+    .fetch_one(&state.db_pool)
+    .await?;
 
-                    if let Some((id, name, path, description, color, icon, is_system, fact_count)) = tag {
-                        // Get children
-                        let children: Vec<TagChildResponse> = sqlx::query_as::<_, (Uuid, String, String)>(
-                            "SELECT id, name, path FROM tags WHERE parent_id = $1 ORDER BY name"
-                        )
-                        .bind(tag_id)
-                        .fetch_all(&state.db_pool)
-                        .await
-                        .unwrap_or_default()
-                        .into_iter()
-                        .map(|(id, name, path)| TagChildResponse {
-                            id: id.to_string(),
-                            name,
-                            path,
-                        })
-                        .collect();
-
-                        Ok(json_response(
-                            200,
-                            &ApiResponse {
-                                success: true,
-                                data: Some(TagResponse {
-                                    id: id.to_string(),
-                                    name,
-                                    path,
-                                    description,
-                                    color,
-                                    icon,
-                                    is_system,
-                                    fact_count,
-                                    children,
-                                }),
-                                error: None,
-                            },
-                        )?)
-                    } else {
-                        Ok(json_response(
-                            404,
-                            &ApiResponse::<()> {
-                                success: false,
-                                data: None,
-                                error: Some("Tag not found".to_string()),
-                            },
-                        )?)
-                    }
-                }
+    if is_system {
+        return error_response(403, "Cannot delete system tags");
+    }
 
-                // Update tag
-                ("PUT", None) => {
-                    // Check if it's a system tag
-                    let is_system: bool = sqlx::query_scalar(
-                        "SELECT owner_type IS NULL FROM tags WHERE id = $1"
-                    )
-                    .bind(tag_id)
-                    .fetch_one(&state.db_pool)
-                    .await
-                    .map_err(|e| format!("Failed to check tag: {}", e))?;
-
-                    if is_system {
-                        return Ok(json_response(
-                            403,
-                            &ApiResponse::<()> {
-                                success: false,
-                                data: None,
-                                error: Some("Cannot modify system tags".to_string()),
-                            },
-                        )?);
-                    }
+    let before = audit::snapshot(&state.db_pool, AuditResource::Tag, tag_id).await;
 
-                    let request: UpdateTagRequest = match shared::parse_json_body(event.body())? {
-                        Ok(r) => r,
-                        Err(response) => return Ok(response),
-                    };
+    let deleted = shared::trash::delete_tag(&state.db_pool, tag_id, user_id).await?;
 
-                    if let Some(name) = &request.name {
-                        sqlx::query("UPDATE tags SET name = $2 WHERE id = $1")
-                            .bind(tag_id)
-                            .bind(name)
-                            .execute(&state.db_pool)
-                            .await?;
-                    }
-                    if let Some(desc) = &request.description {
-                        sqlx::query("UPDATE tags SET description = $2 WHERE id = $1")
-                            .bind(tag_id)
-                            .bind(desc)
-                            .execute(&state.db_pool)
-                            .await?;
-                    }
-                    if let Some(color) = &request.color {
-                        sqlx::query("UPDATE tags SET color = $2 WHERE id = $1")
-                            .bind(tag_id)
-                            .bind(color)
-                            .execute(&state.db_pool)
-                            .await?;
-                    }
-                    if let Some(icon) = &request.icon {
-                        sqlx::query("UPDATE tags SET icon = $2 WHERE id = $1")
-                            .bind(tag_id)
-                            .bind(icon)
-                            .execute(&state.db_pool)
-                            .await?;
-                    }
+    if deleted > 0 {
+        audit::record_change(&state.db_pool, user_id, AuditAction::Delete, AuditResource::Tag, tag_id, before).await;
+    }
 
-                    info!("Updated tag {}", tag_id);
+    info!("Moved tag {} to trash ({} with descendants)", tag_id, deleted);
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "message": "Tag moved to trash",
+                "deleted": deleted,
+                "restore_within_days": shared::trash::RETENTION_DAYS,
+            })),
+            error: None,
+        },
+    )?)
+}
 
-                    Ok(json_response(
-                        200,
-                        &ApiResponse {
-                            success: true,
-                            data: Some(serde_json::json!({"message": "Tag updated"})),
-                            error: None,
-                        },
-                    )?)
-                }
+/// POST /tags/{id}/move
+#[utoipa::path(
+    post,
+    path = "/tags/{id}/move",
+    tag = "tags",
+    request_body = MoveTagRequest,
+    params(
+        ("id" = Uuid, Path, description = "Tag ID"),
+    ),
+    responses(
+        (status = 200, description = "The tag's old and new path and how many descendants moved", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Invalid path, a move under itself, or the new parent doesn't exist", body = ApiError, content_type = "application/problem+json"),
+        (status = 403, description = "Cannot move system tags", body = ApiError, content_type = "application/problem+json"),
+        (status = 404, description = "Tag not found", body = ApiError, content_type = "application/problem+json"),
+        (status = 409, description = "Tags already exist at the new paths", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn move_tag(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+    let family_ids = user.family_ids;
+
+    let tag_id = params.id("id", "Invalid tag ID")?;
+
+    let request: MoveTagRequest = match shared::parse_json_body(event.body())? {
+        Ok(r) => r,
+        Err(response) => return Ok(response),
+    };
 
-                // Delete tag
-                ("DELETE", None) => {
-                    // Check if it's a system tag
-                    let is_system: bool = sqlx::query_scalar(
-                        "SELECT owner_type IS NULL FROM tags WHERE id = $1"
-                    )
-                    .bind(tag_id)
-                    .fetch_one(&state.db_pool)
-                    .await
-                    .map_err(|e| format!("Failed to check tag: {}", e))?;
-
-                    if is_system {
-                        return Ok(json_response(
-                            403,
-                            &ApiResponse::<()> {
-                                success: false,
-                                data: None,
-                                error: Some("Cannot delete system tags".to_string()),
-                            },
-                        )?);
-                    }
+    let new_path = request.path.trim().to_string();
+    if !is_valid_path(&new_path) {
+        return error_response(
+            400,
+            "Path must be slash-separated segments of lowercase letters, numbers, and underscores",
+        );
+    }
 
-                    sqlx::query("DELETE FROM tags WHERE id = $1")
-                        .bind(tag_id)
-                        .execute(&state.db_pool)
-                        .await
-                        .map_err(|e| format!("Failed to delete tag: {}", e))?;
-
-                    info!("Deleted tag {}", tag_id);
-
-                    Ok(json_response(
-                        200,
-                        &ApiResponse {
-                            success: true,
-                            data: Some(serde_json::json!({"message": "Tag deleted"})),
-                            error: None,
-                        },
-                    )?)
-                }
+    let before = audit::snapshot(&state.db_pool, AuditResource::Tag, tag_id).await;
+
+    let mut tx = state.db_pool.begin().await?;
+
+    let tag = sqlx::query_as::<_, (Option<String>, Option<Uuid>, String)>(
+        r#"
+        SELECT owner_type, owner_id, path FROM tags
+        WHERE id = $1
+        AND deleted_at IS NULL
+        AND (
+            owner_type IS NULL
+            OR (owner_type = 'user' AND owner_id = $2)
+            OR (owner_type = 'family' AND owner_id = ANY($3))
+        )
+        FOR UPDATE
+        "#
+    )
+    .bind(tag_id)
+    .bind(user_id)
+    .bind(&family_ids)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let (owner_type, owner_id, old_path) = match tag {
+        Some((Some(owner_type), owner_id, path)) => (owner_type, owner_id, path),
+        Some((None, _, _)) => {
+            return error_response(403, "Cannot move system tags");
+        }
+        None => {
+            return error_response(404, "Tag not found");
+        }
+    };
 
-                // Get facts with this tag
-                ("GET", Some(&"facts")) => {
-                    let params = event.query_string_parameters();
-                    let limit: i64 = params.first("limit")
-                        .and_then(|l| l.parse().ok())
-                        .unwrap_or(50);
+    if new_path.starts_with(&format!("{}/", old_path)) {
+        return error_response(400, "Cannot move a tag under itself");
+    }
 
-                    let facts: Vec<FactWithTagsResponse> = sqlx::query_as::<_, (Uuid, String, i16, chrono::DateTime<chrono::Utc>)>(
-                        r#"
-                        SELECT f.id, f.content, f.importance, f.recorded_at
-                        FROM facts f
-                        JOIN fact_tags ft ON ft.fact_id = f.id
-                        WHERE ft.tag_id = $1
-                        AND (
-                            (f.owner_type = 'user' AND f.owner_id = $2)
-                            OR (f.owner_type = 'family' AND f.owner_id = ANY($3))
-                        )
-                        ORDER BY f.importance DESC, f.recorded_at DESC
-                        LIMIT $4
-                        "#
-                    )
-                    .bind(tag_id)
-                    .bind(user_id)
-                    .bind(&family_ids)
-                    .bind(limit)
-                    .fetch_all(&state.db_pool)
-                    .await
-                    .map_err(|e| format!("Failed to fetch facts: {}", e))?
-                    .into_iter()
-                    .map(|(id, content, importance, recorded_at)| FactWithTagsResponse {
-                        id: id.to_string(),
-                        content,
-                        importance,
-                        recorded_at: recorded_at.to_rfc3339(),
-                        tags: vec![], // We already know the tag
-                    })
-                    .collect();
-
-                    Ok(json_response(
-                        200,
-                        &ApiResponse {
-                            success: true,
-                            data: Some(serde_json::json!({
-                                "tag_id": tag_id.to_string(),
-                                "count": facts.len(),
-                                "facts": facts,
-                            })),
-                            error: None,
-                        },
-                    )?)
-                }
+    // The tag and its descendants, locked so concurrent moves serialize
+    let subtree: Vec<(Uuid, String)> = sqlx::query_as(
+        r#"
+        SELECT id, path FROM tags
+        WHERE owner_type = $1 AND owner_id = $2
+        AND (path = $3 OR starts_with(path, $3 || '/'))
+        ORDER BY path
+        FOR UPDATE
+        "#
+    )
+    .bind(&owner_type)
+    .bind(owner_id)
+    .bind(&old_path)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let subtree_ids: Vec<Uuid> = subtree.iter().map(|(id, _)| *id).collect();
+    let new_paths: Vec<String> = subtree
+        .iter()
+        .map(|(_, path)| format!("{}{}", new_path, &path[old_path.len()..]))
+        .collect();
+
+    // Paths already taken by tags outside the moved subtree
+    let conflicts: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT path FROM tags
+        WHERE owner_type = $1 AND owner_id = $2
+        AND path = ANY($3)
+        AND NOT (id = ANY($4))
+        AND deleted_at IS NULL
+        ORDER BY path
+        "#
+    )
+    .bind(&owner_type)
+    .bind(owner_id)
+    .bind(&new_paths)
+    .bind(&subtree_ids)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if !conflicts.is_empty() {
+        return error_response(
+            409,
+            format!("Tag paths already exist: {}", conflicts.join(", ")),
+        );
+    }
 
-                _ => Ok(json_response(
-                    405,
-                    &ApiResponse::<()> {
-                        success: false,
-                        data: None,
-                        error: Some("Method not allowed".to_string()),
-                    },
-                )?),
+    // New parent: the tag at the path's prefix, in this scope or system-wide
+    let parent_id: Option<Uuid> = match parent_path(&new_path) {
+        Some(parent) => {
+            let parent_id: Option<Uuid> = sqlx::query_scalar(
+                r#"
+                SELECT id FROM tags
+                WHERE path = $1
+                AND deleted_at IS NULL
+                AND (owner_type IS NULL OR (owner_type = $2 AND owner_id = $3))
+                ORDER BY owner_type IS NULL
+                LIMIT 1
+                "#
+            )
+            .bind(parent)
+            .bind(&owner_type)
+            .bind(owner_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            if parent_id.is_none() {
+                return error_response(
+                    400,
+                    format!("Parent tag '{}' not found", parent),
+                );
             }
+            parent_id
         }
+        None => None,
+    };
 
-        _ => Ok(json_response(
-            404,
-            &ApiResponse::<()> {
-                success: false,
-                data: None,
-                error: Some("Not found".to_string()),
-            },
-        )?),
+    let moved = sqlx::query(
+        r#"
+        UPDATE tags t SET path = moved.new_path
+        FROM UNNEST($1::uuid[], $2::text[]) AS moved(id, new_path)
+        WHERE t.id = moved.id
+        "#
+    )
+    .bind(&subtree_ids)
+    .bind(&new_paths)
+    .execute(&mut *tx)
+    .await;
+
+    match moved {
+        Ok(_) => {}
+        // Unique path index: a path was taken since the conflict check
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return error_response(409, "Tag path conflict");
+        }
+        Err(e) => return Err(e.into()),
     }
+
+    sqlx::query("UPDATE tags SET parent_id = $2, name = COALESCE($3, name) WHERE id = $1")
+        .bind(tag_id)
+        .bind(parent_id)
+        .bind(request.name.as_deref().map(str::trim).filter(|n| !n.is_empty()))
+        .execute(&mut *tx)
+        .await?;
+
+    // Re-point descendants at the tag now at their parent path
+    sqlx::query(
+        r#"
+        UPDATE tags c SET parent_id = p.id
+        FROM tags p
+        WHERE c.id = ANY($1) AND c.id != $2
+        AND p.owner_type = c.owner_type AND p.owner_id = c.owner_id
+        AND p.path = regexp_replace(c.path, '/[^/]+$', '')
+        "#
+    )
+    .bind(&subtree_ids)
+    .bind(tag_id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    audit::record_change(&state.db_pool, user_id, AuditAction::Update, AuditResource::Tag, tag_id, before).await;
+
+    info!("Moved tag {} from {} to {} ({} descendants)", tag_id, old_path, new_path, subtree_ids.len() - 1);
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "tag_id": tag_id.to_string(),
+                "old_path": old_path,
+                "path": new_path,
+                "parent_id": parent_id.map(|id| id.to_string()),
+                "descendants_moved": subtree_ids.len() - 1,
+            })),
+            error: None,
+        },
+    )?)
+}
+
+/// GET /tags/{id}/facts
+#[utoipa::path(
+    get,
+    path = "/tags/{id}/facts",
+    tag = "tags",
+    params(
+        ("id" = Uuid, Path, description = "Tag ID"),
+        ("limit" = Option<i64>, Query, description = "Most facts to return"),
+        ("cursor" = Option<String>, Query, description = "Cursor returned with the previous page"),
+    ),
+    responses(
+        (status = 200, description = "A page of facts with the tag (`facts`, `next_cursor`, `has_more`)", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Invalid cursor", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_tag_facts(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let user_id = user.user_id;
+    let family_ids = user.family_ids;
+
+    let tag_id = params.id("id", "Invalid tag ID")?;
+
+    let params = event.query_string_parameters();
+    let page_params = match CursorParams::from_query(params.first("limit"), params.first("cursor"), 50) {
+        Ok(p) => p,
+        Err(e) => {
+            return error_response(400, e.to_string())
+        }
+    };
+
+    let rows = sqlx::query_as::<_, (Uuid, String, i16, chrono::DateTime<chrono::Utc>)>(
+        &format!(r#"
+        SELECT f.id, f.content, f.importance, f.recorded_at
+        FROM facts f
+        JOIN fact_tags ft ON ft.fact_id = f.id
+        WHERE ft.tag_id = $1
+        AND f.deleted_at IS NULL
+        AND {}
+        AND {}
+        ORDER BY f.recorded_at DESC, f.id DESC
+        LIMIT $4
+        "#, visibility_clause("f", 2), keyset_predicate("f.recorded_at", "timestamptz", "f.id", 5, SortDirection::Desc))
+    )
+    .bind(tag_id)
+    .bind(user_id)
+    .bind(&family_ids)
+    .bind(page_params.fetch_limit())
+    .bind(page_params.after_key())
+    .bind(page_params.after_id())
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let page = Page::from_rows(rows, &page_params, |row| Cursor::new(row.3.to_rfc3339(), row.0))
+        .map(|(id, content, importance, recorded_at)| FactWithTagsResponse {
+            id: id.to_string(),
+            content,
+            importance,
+            recorded_at: recorded_at.to_rfc3339(),
+            tags: vec![], // We already know the tag
+        });
+
+    Ok(json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "tag_id": tag_id.to_string(),
+                "count": page.items.len(),
+                "facts": page.items,
+                "next_cursor": page.next_cursor,
+                "has_more": page.has_more,
+            })),
+            error: None,
+        },
+    )?)
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .post("/tags", create_tag)
+        .get("/tags", list_tags)
+        .get("/tags/stats", tag_stats)
+        .post("/tags/suggestions", suggest_tags)
+        .post("/tags/rules", create_rule)
+        .get("/tags/rules", list_rules)
+        .delete("/tags/rules/{id}", delete_rule)
+        .get("/facts/{id}/tags", list_fact_tags)
+        .post("/facts/{id}/tags", apply_tags)
+        .delete("/facts/{id}/tags/{tagId}", remove_fact_tag)
+        .get("/tags/{id}", get_tag)
+        .put("/tags/{id}", update_tag)
+        .delete("/tags/{id}", delete_tag)
+        .post("/tags/{id}/move", move_tag)
+        .get("/tags/{id}/facts", list_tag_facts)
 }
 
 fn json_response<T: Serialize>(status: u16, data: &T) -> Result<Response<Body>, Error> {
     Ok(Response::builder()
         .status(status)
         .header("content-type", "application/json")
-        .body(Body::from(serde_json::to_string(data)?))
-        .expect("Failed to build response"))
+        .body(Body::from(serde_json::to_string(data)?))?)
+}
+
+/// Whether a tag path is well formed: lowercase segments separated by single slashes.
+fn is_valid_path(path: &str) -> bool {
+    !path.is_empty()
+        && path.split('/').all(|segment| {
+            !segment.is_empty()
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        })
+}
+
+/// Path of a tag's parent, or `None` for top-level tags.
+fn parent_path(path: &str) -> Option<&str> {
+    path.rsplit_once('/').map(|(parent, _)| parent)
 }
 
 #[tokio::main]
@@ -1024,10 +1811,12 @@ async fn main() -> Result<(), Error> {
         .init();
 
     let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
 
     run(service_fn(move |event| {
         let state = Arc::clone(&state);
-        async move { handler(state, event).await }
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
     }))
     .await
 }
@@ -0,0 +1,213 @@
+//! Trash Lambda - Deleted facts, entities and tags.
+//!
+//! Endpoints:
+//! - GET /trash - Items the user deleted, most recent first (`?kind=fact|entity|tag&limit=`)
+//! - POST /trash/{id}/restore - Restore an item (tags come back with their descendants)
+//!
+//! Deleted items stay in the trash for `shared::trash::RETENTION_DAYS` before
+//! `trash_purge` removes them for good.
+
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::Serialize;
+use shared::audit::{self, AuditAction, AuditResource};
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, Query, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::trash::{self, Restore, TrashKind, MAX_LIST, RETENTION_DAYS};
+use shared::ApiError;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Items listed when `limit` isn't given
+const DEFAULT_LIMIT: i64 = 50;
+
+/// API response wrapper
+#[derive(Debug, Serialize, ToSchema)]
+struct ApiResponse<T> {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+/// GET /trash
+#[utoipa::path(
+    get,
+    path = "/trash",
+    tag = "trash",
+    params(
+        ("kind" = Option<String>, Query, description = "`fact`, `entity` or `tag`"),
+        ("limit" = Option<i64>, Query, description = "Most items to return"),
+    ),
+    responses(
+        (status = 200, description = "Items you deleted, most recent first, and `retentionDays` before they're purged", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Unknown `kind` or invalid `limit`", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn list_trash(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let query = Query::from_request(&event);
+
+    let kind = match query.first("kind") {
+        Some(kind) => match TrashKind::parse(kind) {
+            Some(kind) => Some(kind),
+            None => return error_response(400, "kind must be one of: fact, entity, tag"),
+        },
+        None => None,
+    };
+    let limit = match query.get::<i64>("limit") {
+        Ok(limit) => limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIST),
+        Err(e) => return error_response(400, e.to_string()),
+    };
+
+    let items =
+        trash::list_trash(&state.db_pool, user.user_id, &user.family_ids, kind, limit).await?;
+
+    json_response(
+        200,
+        &ApiResponse {
+            success: true,
+            data: Some(serde_json::json!({
+                "retentionDays": RETENTION_DAYS,
+                "items": items,
+            })),
+            error: None,
+        },
+    )
+}
+
+/// POST /trash/{id}/restore
+#[utoipa::path(
+    post,
+    path = "/trash/{id}/restore",
+    tag = "trash",
+    params(
+        ("id" = Uuid, Path, description = "ID of the fact, entity or tag"),
+    ),
+    responses(
+        (status = 200, description = "The item was restored (`id`, `kind`, `restored`); tags come back with their descendants", body = ApiResponse<serde_json::Value>),
+        (status = 404, description = "Item not found in trash", body = ApiError, content_type = "application/problem+json"),
+        (status = 409, description = "A tag whose parent is in the trash, or whose path is taken", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn restore_item(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let id: Uuid = match params.get("id") {
+        Ok(id) => id,
+        Err(e) => return error_response(400, e.to_string()),
+    };
+
+    // The ID alone doesn't say which table the item is in
+    let mut before = None;
+    for resource in [
+        AuditResource::Fact,
+        AuditResource::Entity,
+        AuditResource::Tag,
+    ] {
+        before = audit::snapshot(&state.db_pool, resource, id).await;
+        if before.is_some() {
+            break;
+        }
+    }
+
+    match trash::restore(&state.db_pool, user.user_id, &user.family_ids, id).await? {
+        Restore::Restored { kind, count } => {
+            audit::record_change(
+                &state.db_pool,
+                user.user_id,
+                AuditAction::Restore,
+                kind.into(),
+                id,
+                before,
+            )
+            .await;
+            info!("Restored {} {} ({} rows)", kind.as_str(), id, count);
+            json_response(
+                200,
+                &ApiResponse {
+                    success: true,
+                    data: Some(serde_json::json!({
+                        "id": id,
+                        "kind": kind,
+                        "restored": count,
+                    })),
+                    error: None,
+                },
+            )
+        }
+        Restore::NotFound => error_response(404, "Item not found in trash"),
+        Restore::Conflict(message) => error_response(409, message),
+    }
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .get("/trash", list_trash)
+        .post("/trash/{id}/restore", restore_item)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
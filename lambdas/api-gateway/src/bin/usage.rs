@@ -0,0 +1,147 @@
+//! Usage Lambda - Token usage and estimated spend.
+//!
+//! Endpoints:
+//! - GET /usage - The caller's queries, tokens and estimated cost for a month,
+//!   in total and by model (`?month=YYYY-MM`, default the current month)
+//! - GET /v1/queries/{id} - A past answer with the facts it cited
+//!
+//! Queries are logged by `POST /query` and the chat surfaces through
+//! `shared::usage`.
+
+use lambda_http::{run, service_fn, Body, Error, Request, Response};
+use serde::Serialize;
+use shared::http::error_response;
+use shared::metrics::RequestMetrics;
+use shared::ratelimit::RateLimit;
+use shared::router::{require_user, Cors, PathParams, Query, RequestLogger, RequireAuth, Router};
+use shared::shaping::ResponseShaping;
+use shared::usage::{self, MonthlyUsage, PastQuery};
+use shared::{ApiError, ApiResponse};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+/// GET /usage
+#[utoipa::path(
+    get,
+    path = "/usage",
+    tag = "usage",
+    params(
+        ("month" = Option<String>, Query, description = "`YYYY-MM`; the current month by default"),
+    ),
+    responses(
+        (status = 200, description = "The caller's usage for the month", body = ApiResponse<MonthlyUsage>),
+        (status = 400, description = "Invalid month", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn get_usage(
+    state: Arc<AppState>,
+    event: Request,
+    _params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let query = Query::from_request(&event);
+
+    let month = query
+        .first("month")
+        .map(str::to_string)
+        .unwrap_or_else(usage::current_month);
+
+    let report = match usage::monthly_usage(&state.db_pool, user.user_id, &month).await {
+        Ok(report) => report,
+        Err(shared::Error::Validation(e)) => return error_response(400, e),
+        Err(e) => return Err(e.into()),
+    };
+
+    json_response(200, &ApiResponse::success(report))
+}
+
+/// GET /v1/queries/{id}
+#[utoipa::path(
+    get,
+    path = "/v1/queries/{id}",
+    tag = "knowledge",
+    params(
+        ("id" = Uuid, Path, description = "The `query_id` the answer was returned with"),
+    ),
+    responses(
+        (status = 200, description = "The question, its answer and the facts it cited", body = ApiResponse<PastQuery>),
+        (status = 404, description = "Not one of the caller's queries", body = ApiError, content_type = "application/problem+json"),
+    ),
+    security(("cognito" = []))
+)]
+async fn get_query(
+    state: Arc<AppState>,
+    event: Request,
+    params: PathParams,
+) -> Result<Response<Body>, Error> {
+    let user = require_user(&event, &state.db_pool).await?;
+    let query_id = match params.get::<Uuid>("id") {
+        Ok(id) => id,
+        Err(_) => return error_response(400, "Invalid query ID"),
+    };
+
+    let query = usage::past_query(&state.db_pool, user.user_id, &user.family_ids, query_id).await?;
+
+    match query {
+        Some(query) => json_response(200, &ApiResponse::success(query)),
+        None => error_response(404, "Query not found"),
+    }
+}
+
+fn router() -> Router<AppState> {
+    Router::new()
+        .layer(RequestLogger)
+        .layer(RequestMetrics)
+        .layer(ResponseShaping)
+        .layer(Cors::from_env())
+        .layer(RequireAuth)
+        .layer(RateLimit::default())
+        .get("/usage", get_usage)
+        .get("/v1/queries/{id}", get_query)
+}
+
+fn json_response<T: Serialize>(status: u16, body: &T) -> Result<Response<Body>, Error> {
+    let json = serde_json::to_string(body)?;
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(json))?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let router = Arc::new(router());
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        let router = Arc::clone(&router);
+        async move { router.dispatch(state, event).await }
+    }))
+    .await
+}
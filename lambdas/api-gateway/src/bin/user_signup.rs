@@ -5,7 +5,7 @@
 
 use lambda_runtime::{run, service_fn, Error, LambdaEvent};
 use serde::{Deserialize, Serialize};
-use sqlx::postgres::PgPoolOptions;
+use shared::metrics;
 use sqlx::PgPool;
 use std::sync::Arc;
 use tracing::{error, info};
@@ -66,34 +66,8 @@ struct AppState {
 impl AppState {
     async fn new() -> Result<Self, Error> {
         let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
-        let secrets_client = aws_sdk_secretsmanager::Client::new(&config);
 
-        let db_secret_arn =
-            std::env::var("DB_SECRET_ARN").map_err(|_| "DB_SECRET_ARN not set")?;
-
-        let db_secret = secrets_client
-            .get_secret_value()
-            .secret_id(&db_secret_arn)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to get DB secret: {}", e))?;
-
-        let db_creds: serde_json::Value =
-            serde_json::from_str(db_secret.secret_string().unwrap_or("{}"))?;
-
-        let db_host = std::env::var("DB_HOST").map_err(|_| "DB_HOST not set")?;
-        let db_name = std::env::var("DB_NAME").unwrap_or_else(|_| "second_brain".to_string());
-        let db_user = db_creds["username"].as_str().unwrap_or("sbadmin");
-        let db_pass = db_creds["password"].as_str().unwrap_or("");
-
-        let database_url = format!(
-            "postgres://{}:{}@{}:5432/{}",
-            db_user, db_pass, db_host, db_name
-        );
-
-        let db_pool = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(&database_url)
+        let db_pool = shared::db::connect(&config)
             .await
             .map_err(|e| format!("Failed to connect to database: {}", e))?;
 
@@ -205,7 +179,7 @@ async fn main() -> Result<(), Error> {
 
     run(service_fn(move |event| {
         let state = Arc::clone(&state);
-        async move { handler(state, event).await }
+        async move { metrics::track_invocation("post_confirmation", handler(state, event)).await }
     }))
     .await
 }
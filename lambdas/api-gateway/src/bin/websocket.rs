@@ -0,0 +1,294 @@
+//! WebSocket Lambda - Connections to the real-time API.
+//!
+//! Routes:
+//! - `$connect` - Redeem the `?ticket=` from `POST /realtime/ticket` and
+//!   record the connection
+//! - `$disconnect` - Forget the connection
+//! - `$default` - Client messages (`shared::realtime::ClientMessage`):
+//!   `query` asks the agents a question (rate limited like `POST /query`),
+//!   `ping` keeps the connection alive
+//!
+//! Facts and reminders are pushed by the `realtime_dispatcher` Lambda. Query
+//! answers are sent as `agent_chunk` frames followed by `agent_done`; the
+//! agents answer in one piece, so the chunks let clients render long answers
+//! progressively and keep frames within API Gateway's limit.
+
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use shared::metrics;
+use shared::ratelimit::{Limit, RateLimit, QUERIES_PER_MINUTE};
+use shared::realtime::{
+    chunk_text, Broadcaster, ClientMessage, ConnectionStore, ConnectionUser, ServerMessage,
+    CHUNK_CHARS,
+};
+use shared::AgentClient;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
+
+/// Source reported to the agents
+const SOURCE: &str = "websocket";
+
+/// API Gateway WebSocket proxy event (the fields used here)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WebSocketEvent {
+    request_context: RequestContext,
+    #[serde(default)]
+    query_string_parameters: Option<HashMap<String, String>>,
+    #[serde(default)]
+    body: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RequestContext {
+    route_key: String,
+    connection_id: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WebSocketResponse {
+    status_code: u16,
+}
+
+impl WebSocketResponse {
+    fn status(status_code: u16) -> Self {
+        Self { status_code }
+    }
+}
+
+struct AppState {
+    broadcaster: Broadcaster,
+    agent_client: AgentClient,
+    rate_limit: RateLimit,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let table_name = std::env::var("CONNECTIONS_TABLE_NAME")
+            .map_err(|_| "CONNECTIONS_TABLE_NAME not set")?;
+        let endpoint = std::env::var("WEBSOCKET_CALLBACK_URL")
+            .map_err(|_| "WEBSOCKET_CALLBACK_URL not set")?;
+        let agent_function_name = std::env::var("AGENT_FUNCTION_NAME")
+            .unwrap_or_else(|_| "second-brain-agents".to_string());
+
+        let store = ConnectionStore::new(aws_sdk_dynamodb::Client::new(&config), table_name);
+
+        Ok(Self {
+            broadcaster: Broadcaster::new(&config, &endpoint, store),
+            agent_client: AgentClient::new(
+                aws_sdk_lambda::Client::new(&config),
+                agent_function_name,
+            ),
+            rate_limit: RateLimit::new(Limit::per_minute(QUERIES_PER_MINUTE)),
+        })
+    }
+}
+
+/// `$connect`: a missing, used or expired ticket is refused with 401.
+async fn connect(
+    state: &AppState,
+    connection_id: &str,
+    ticket: Option<&str>,
+) -> Result<WebSocketResponse, Error> {
+    let Some(ticket) = ticket.filter(|t| !t.is_empty()) else {
+        return Ok(WebSocketResponse::status(401));
+    };
+
+    let Some(user) = state.broadcaster.store().redeem_ticket(ticket).await? else {
+        info!(connection_id, "Refused connection with invalid ticket");
+        return Ok(WebSocketResponse::status(401));
+    };
+
+    state
+        .broadcaster
+        .store()
+        .add_connection(connection_id, &user)
+        .await?;
+
+    info!(connection_id, user_id = %user.user_id, "Connected");
+    Ok(WebSocketResponse::status(200))
+}
+
+/// Answer a `query` message on the connection it came from.
+async fn answer_query(
+    state: &AppState,
+    connection_id: &str,
+    user: &ConnectionUser,
+    request_id: String,
+    query: &str,
+    session_id: Option<String>,
+) -> Result<(), Error> {
+    let family_ids = user.family_ids.iter().map(|id| id.to_string()).collect();
+
+    let response = match state
+        .agent_client
+        .query(query, &user.cognito_sub, family_ids, session_id, SOURCE)
+        .await
+    {
+        Ok(response) => response,
+        Err(e) => {
+            warn!(connection_id, error = %e, "Agent query failed");
+            state
+                .broadcaster
+                .send(
+                    connection_id,
+                    &ServerMessage::AgentError {
+                        request_id,
+                        error: "The agents couldn't answer that".to_string(),
+                    },
+                )
+                .await?;
+            return Ok(());
+        }
+    };
+
+    for (seq, text) in chunk_text(&response.response, CHUNK_CHARS)
+        .into_iter()
+        .enumerate()
+    {
+        let chunk = ServerMessage::AgentChunk {
+            request_id: request_id.clone(),
+            seq: seq as u32,
+            text,
+        };
+        // The client disconnected mid-answer
+        if !state.broadcaster.send(connection_id, &chunk).await? {
+            return Ok(());
+        }
+    }
+
+    let agents_used = response
+        .metadata
+        .and_then(|m| m.agents_used)
+        .unwrap_or_default();
+    state
+        .broadcaster
+        .send(
+            connection_id,
+            &ServerMessage::AgentDone {
+                request_id,
+                agents_used,
+            },
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// `$default`: client messages. Unknown messages are refused with 400.
+async fn message(
+    state: &AppState,
+    connection_id: &str,
+    body: Option<&str>,
+) -> Result<WebSocketResponse, Error> {
+    let message: ClientMessage = match body.map(serde_json::from_str) {
+        Some(Ok(message)) => message,
+        _ => return Ok(WebSocketResponse::status(400)),
+    };
+
+    match message {
+        ClientMessage::Ping => {
+            state
+                .broadcaster
+                .send(connection_id, &ServerMessage::Pong)
+                .await?;
+        }
+        ClientMessage::Query {
+            request_id,
+            query,
+            session_id,
+        } => {
+            let Some(user) = state
+                .broadcaster
+                .store()
+                .connection_user(connection_id)
+                .await?
+            else {
+                return Ok(WebSocketResponse::status(401));
+            };
+
+            if query.trim().is_empty() {
+                state
+                    .broadcaster
+                    .send(
+                        connection_id,
+                        &ServerMessage::AgentError {
+                            request_id,
+                            error: "Query cannot be empty".to_string(),
+                        },
+                    )
+                    .await?;
+                return Ok(WebSocketResponse::status(200));
+            }
+
+            if let Some(wait) = state.rate_limit.take_user(&user.cognito_sub, "WS", "query") {
+                state
+                    .broadcaster
+                    .send(
+                        connection_id,
+                        &ServerMessage::AgentError {
+                            request_id,
+                            error: format!("Too many questions, try again in {}s", wait),
+                        },
+                    )
+                    .await?;
+                return Ok(WebSocketResponse::status(200));
+            }
+
+            answer_query(state, connection_id, &user, request_id, &query, session_id).await?;
+        }
+    }
+
+    Ok(WebSocketResponse::status(200))
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    event: LambdaEvent<WebSocketEvent>,
+) -> Result<WebSocketResponse, Error> {
+    let event = event.payload;
+    let connection_id = event.request_context.connection_id.as_str();
+
+    match event.request_context.route_key.as_str() {
+        "$connect" => {
+            let ticket = event
+                .query_string_parameters
+                .as_ref()
+                .and_then(|params| params.get("ticket"))
+                .map(String::as_str);
+            connect(&state, connection_id, ticket).await
+        }
+        "$disconnect" => {
+            state
+                .broadcaster
+                .store()
+                .remove_connection(connection_id)
+                .await?;
+            info!(connection_id, "Disconnected");
+            Ok(WebSocketResponse::status(200))
+        }
+        _ => message(&state, connection_id, event.body.as_deref()).await,
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        async move { metrics::track_invocation("route", handler(state, event)).await }
+    }))
+    .await
+}
@@ -0,0 +1,236 @@
+//! Discord Gateway relay.
+//!
+//! Discord only delivers direct messages over the Gateway websocket, so this
+//! long-running process holds the connection and forwards each
+//! `MESSAGE_CREATE` dispatch to the webhook Lambda as an asynchronous
+//! invocation, signed for [`gateway::EVENT_PATH`] (see
+//! `shared::auth::sign_internal`). The Lambda decides what to answer.
+//!
+//! It heartbeats as Discord asks and starts a new session after a reconnect
+//! request, an invalid session, a missed heartbeat acknowledgement or a
+//! dropped connection. Events sent while it is reconnecting are lost.
+//!
+//! Configuration (environment):
+//! - `DISCORD_SECRET_ARN` - Secret with the `bot_token` (default
+//!   `second-brain/discord`)
+//! - `INTERNAL_AUTH_SECRET_ARN` - Internal signing keys
+//! - `DISCORD_WEBHOOK_FUNCTION` - Lambda to forward to (default
+//!   `second-brain-discord-webhook`)
+//!
+//! Run it anywhere always-on with the `second-brain-discord-relay` managed
+//! policy: `cargo run --release --bin discord_relay`.
+
+use aws_sdk_lambda::primitives::Blob;
+use aws_sdk_lambda::types::InvocationType;
+use discord_webhook::gateway::{
+    self, Frame, RelayPayload, OP_DISPATCH, OP_HEARTBEAT, OP_HEARTBEAT_ACK, OP_INVALID_SESSION,
+    OP_RECONNECT,
+};
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use serde::Deserialize;
+use shared::auth::{sign_internal, INTERNAL_AUTH_SECRET_ENV};
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Discord API base URL
+const DISCORD_API_BASE: &str = "https://discord.com/api/v10";
+
+/// Secret holding the bot credentials, unless `DISCORD_SECRET_ARN` is set
+const DISCORD_SECRET_ID: &str = "second-brain/discord";
+
+/// Lambda forwarded to, unless `DISCORD_WEBHOOK_FUNCTION` is set
+const DEFAULT_FUNCTION_NAME: &str = "second-brain-discord-webhook";
+
+/// Wait before starting a new session (Discord asks for 1-5s after an
+/// invalid session)
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// `GET /gateway/bot` response
+#[derive(Debug, Deserialize)]
+struct GatewayBot {
+    url: String,
+}
+
+struct Relay {
+    http_client: reqwest::Client,
+    lambda_client: aws_sdk_lambda::Client,
+    function_name: String,
+    bot_token: String,
+}
+
+impl Relay {
+    async fn new() -> Result<Self, Error> {
+        // Fail now rather than on every message
+        if std::env::var(INTERNAL_AUTH_SECRET_ENV).is_err() {
+            return Err(format!("{} is not set", INTERNAL_AUTH_SECRET_ENV).into());
+        }
+
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        let secrets_client = aws_sdk_secretsmanager::Client::new(&config);
+        let secret_id =
+            std::env::var("DISCORD_SECRET_ARN").unwrap_or_else(|_| DISCORD_SECRET_ID.to_string());
+
+        let secret = shared::secrets::get_secret(&secrets_client, &secret_id)
+            .await
+            .map_err(|e| format!("Failed to get Discord secret: {}", e))?;
+        let credentials: serde_json::Value = serde_json::from_str(&secret)?;
+        let bot_token = credentials["bot_token"]
+            .as_str()
+            .filter(|t| !t.is_empty())
+            .ok_or("Missing 'bot_token' in Discord secret")?
+            .to_string();
+
+        Ok(Self {
+            http_client: reqwest::Client::new(),
+            lambda_client: aws_sdk_lambda::Client::new(&config),
+            function_name: std::env::var("DISCORD_WEBHOOK_FUNCTION")
+                .unwrap_or_else(|_| DEFAULT_FUNCTION_NAME.to_string()),
+            bot_token,
+        })
+    }
+
+    async fn gateway_url(&self) -> Result<String, Error> {
+        let gateway: GatewayBot = self
+            .http_client
+            .get(format!("{}/gateway/bot", DISCORD_API_BASE))
+            .header("Authorization", format!("Bot {}", self.bot_token))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(format!("{}/?v=10&encoding=json", gateway.url))
+    }
+
+    /// One Gateway session, until Discord closes it or asks for a new one
+    async fn run_session(&self) -> Result<(), Error> {
+        let (socket, _) = tokio_tungstenite::connect_async(self.gateway_url().await?).await?;
+        let (mut sink, mut stream) = socket.split();
+
+        let interval = loop {
+            match next_frame(&mut stream).await? {
+                Some(frame) => {
+                    if let Some(interval) = frame.heartbeat_interval() {
+                        break interval;
+                    }
+                }
+                None => return Err("Gateway closed before Hello".into()),
+            }
+        };
+
+        send(&mut sink, &Frame::identify(&self.bot_token)).await?;
+        info!(
+            "Identified with the Gateway, heartbeat every {}ms",
+            interval
+        );
+
+        let mut heartbeat = tokio::time::interval(Duration::from_millis(interval));
+        // The first tick is immediate; heartbeats start an interval in
+        heartbeat.tick().await;
+        let mut seq = None;
+        let mut acked = true;
+
+        loop {
+            tokio::select! {
+                _ = heartbeat.tick() => {
+                    if !acked {
+                        return Err("Heartbeat was not acknowledged".into());
+                    }
+                    acked = false;
+                    send(&mut sink, &Frame::heartbeat(seq)).await?;
+                }
+                frame = next_frame(&mut stream) => {
+                    let Some(frame) = frame? else {
+                        return Ok(());
+                    };
+                    if frame.s.is_some() {
+                        seq = frame.s;
+                    }
+
+                    match frame.op {
+                        OP_DISPATCH => {
+                            if let Some(payload) = frame.into_forwarded() {
+                                if let Err(e) = self.forward(&payload).await {
+                                    error!("Failed to forward gateway event: {}", e);
+                                }
+                            }
+                        }
+                        OP_HEARTBEAT => send(&mut sink, &Frame::heartbeat(seq)).await?,
+                        OP_HEARTBEAT_ACK => acked = true,
+                        OP_RECONNECT | OP_INVALID_SESSION => {
+                            info!("Gateway asked for a new session (op {})", frame.op);
+                            return Ok(());
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    /// Invoke the webhook Lambda with the signed event, without waiting for it
+    async fn forward(&self, payload: &RelayPayload) -> Result<(), Error> {
+        let signed = sign_internal(payload, "POST", gateway::EVENT_PATH, "", &[]).await?;
+
+        self.lambda_client
+            .invoke()
+            .function_name(&self.function_name)
+            .invocation_type(InvocationType::Event)
+            .payload(Blob::new(serde_json::to_vec(&signed)?))
+            .send()
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// The next Gateway frame, or `None` once the connection is closed
+async fn next_frame<S>(stream: &mut S) -> Result<Option<Frame>, Error>
+where
+    S: Stream<Item = Result<Message, tungstenite::Error>> + Unpin,
+{
+    while let Some(message) = stream.next().await {
+        match message? {
+            Message::Text(text) => return Ok(Some(serde_json::from_str(&text)?)),
+            Message::Close(close) => {
+                info!("Gateway closed the connection: {:?}", close);
+                return Ok(None);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(None)
+}
+
+async fn send<S>(sink: &mut S, frame: &Frame) -> Result<(), Error>
+where
+    S: Sink<Message, Error = tungstenite::Error> + Unpin,
+{
+    sink.send(Message::Text(serde_json::to_string(frame)?))
+        .await?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let relay = Relay::new().await?;
+    info!("Relaying direct messages to {}", relay.function_name);
+
+    loop {
+        if let Err(e) = relay.run_session().await {
+            warn!("Gateway session ended: {}", e);
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
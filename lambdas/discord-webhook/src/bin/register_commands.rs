@@ -0,0 +1,150 @@
+//! Register the bot's slash commands with Discord.
+//!
+//! Uploads [`COMMANDS`] with a bulk overwrite, so commands removed from the
+//! definitions are removed from Discord as well. Credentials come from the
+//! `second-brain/discord` secret (`application_id`, `bot_token`).
+//!
+//! Usage:
+//! - `cargo run --bin register_commands` - Register to the default guild (instant)
+//! - `cargo run --bin register_commands -- --guild <id>` - Register to another guild
+//! - `cargo run --bin register_commands -- --global` - Register globally (up to 1 hour to propagate)
+//! - `cargo run --bin register_commands -- --dry-run` - Print the commands without registering
+//!
+//! Set `AWS_PROFILE` to use a different AWS profile.
+
+use discord_webhook::commands::COMMANDS;
+use serde::Deserialize;
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Discord API base URL
+const DISCORD_API_BASE: &str = "https://discord.com/api/v10";
+
+/// Default guild/server ID
+const DEFAULT_GUILD_ID: &str = "1438958317513740421";
+
+/// Secret holding the bot credentials
+const DISCORD_SECRET_ID: &str = "second-brain/discord";
+
+/// Command line options
+#[derive(Debug)]
+struct Options {
+    guild_id: Option<String>,
+    dry_run: bool,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, Error> {
+        let mut options = Self {
+            guild_id: Some(DEFAULT_GUILD_ID.to_string()),
+            dry_run: false,
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--global" => options.guild_id = None,
+                "--guild" => {
+                    let guild_id = args.next().ok_or("--guild requires a guild ID")?;
+                    options.guild_id = Some(guild_id);
+                }
+                "--dry-run" => options.dry_run = true,
+                other => return Err(format!("Unknown argument: {}", other).into()),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// Bot credentials from Secrets Manager
+#[derive(Debug, Deserialize)]
+struct DiscordCredentials {
+    application_id: String,
+    bot_token: String,
+}
+
+/// Command as returned by Discord after registration
+#[derive(Debug, Deserialize)]
+struct RegisteredCommand {
+    name: String,
+    description: String,
+}
+
+async fn load_credentials() -> Result<DiscordCredentials, Error> {
+    let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+    let secrets_client = aws_sdk_secretsmanager::Client::new(&config);
+
+    let secret = secrets_client
+        .get_secret_value()
+        .secret_id(DISCORD_SECRET_ID)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to get secret '{}': {}", DISCORD_SECRET_ID, e))?;
+
+    let credentials = serde_json::from_str(secret.secret_string().unwrap_or("{}"))
+        .map_err(|e| format!("Missing 'application_id' or 'bot_token' in secret: {}", e))?;
+
+    Ok(credentials)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let options = Options::parse(std::env::args().skip(1))?;
+
+    let scope = match &options.guild_id {
+        Some(guild_id) => format!("guild {}", guild_id),
+        None => "global".to_string(),
+    };
+    println!("Registering {} commands ({})...", COMMANDS.len(), scope);
+
+    if options.dry_run {
+        println!("DRY RUN - Commands that would be registered:");
+        println!("{}", serde_json::to_string_pretty(COMMANDS)?);
+        return Ok(());
+    }
+
+    let credentials = load_credentials().await?;
+
+    let url = match &options.guild_id {
+        Some(guild_id) => format!(
+            "{}/applications/{}/guilds/{}/commands",
+            DISCORD_API_BASE, credentials.application_id, guild_id
+        ),
+        None => format!(
+            "{}/applications/{}/commands",
+            DISCORD_API_BASE, credentials.application_id
+        ),
+    };
+
+    // PUT replaces all commands (idempotent)
+    let response = reqwest::Client::new()
+        .put(&url)
+        .header("Authorization", format!("Bot {}", credentials.bot_token))
+        .json(COMMANDS)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to register commands: {}", e))?;
+
+    let status = response.status();
+    match status.as_u16() {
+        200 => {
+            let registered: Vec<RegisteredCommand> = response.json().await?;
+            println!("Successfully registered {} commands:", registered.len());
+            for command in registered {
+                println!("  /{} - {}", command.name, command.description);
+            }
+
+            if options.guild_id.is_none() {
+                println!();
+                println!("Note: Global commands may take up to 1 hour to appear in Discord.");
+            }
+            Ok(())
+        }
+        401 => Err("Invalid bot token. Check your credentials in Secrets Manager.".into()),
+        403 => Err("Bot lacks permission. Ensure the bot has 'applications.commands' scope.".into()),
+        _ => {
+            let body = response.text().await.unwrap_or_default();
+            Err(format!("Error registering commands: {} {}", status, body).into())
+        }
+    }
+}
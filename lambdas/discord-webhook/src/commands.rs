@@ -0,0 +1,327 @@
+//! Slash command definitions.
+//!
+//! The single source of truth for the bot's command schemas: `register_commands`
+//! uploads [`COMMANDS`] to Discord, and the webhook reads command arguments
+//! through [`text_option`], so a renamed option can't silently drift from its handler.
+
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+/// Discord application command option types
+const OPTION_SUB_COMMAND: u8 = 1;
+const OPTION_STRING: u8 = 3;
+const OPTION_BOOLEAN: u8 = 5;
+const OPTION_ATTACHMENT: u8 = 11;
+
+/// Kind of a command option
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionKind {
+    /// Subcommand with its own options (`/list by-tag <tag>`)
+    SubCommand(&'static [CommandOption]),
+    /// Free-text argument
+    String { required: bool },
+    /// True/false flag
+    Boolean { required: bool },
+    /// Uploaded file
+    Attachment { required: bool },
+}
+
+/// A command option or subcommand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandOption {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub kind: OptionKind,
+}
+
+/// A top-level slash command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Command {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub options: &'static [CommandOption],
+}
+
+const fn text(name: &'static str, description: &'static str) -> CommandOption {
+    CommandOption {
+        name,
+        description,
+        kind: OptionKind::String { required: true },
+    }
+}
+
+/// Optional flag making the response visible only to the caller.
+pub const PRIVATE_OPTION: CommandOption = CommandOption {
+    name: "private",
+    description: "Only show the response to you",
+    kind: OptionKind::Boolean { required: false },
+};
+
+const fn subcommand(
+    name: &'static str,
+    description: &'static str,
+    options: &'static [CommandOption],
+) -> CommandOption {
+    CommandOption {
+        name,
+        description,
+        kind: OptionKind::SubCommand(options),
+    }
+}
+
+const LIST_BY_TAG_OPTIONS: &[CommandOption] = &[text(
+    "tag",
+    "Tag name or path (e.g., 'health' or 'family/school')",
+)];
+
+const LIST_BY_PERSON_OPTIONS: &[CommandOption] = &[text("person", "The person's name")];
+
+/// Every command the bot handles, in the order Discord lists them.
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "remember",
+        description: "Save a fact or piece of information to your knowledge base",
+        options: &[
+            text(
+                "fact",
+                "The information to remember (e.g., 'John's birthday is March 15')",
+            ),
+            PRIVATE_OPTION,
+        ],
+    },
+    Command {
+        name: "save",
+        description: "Save information to your knowledge base (alias for /remember)",
+        options: &[text("fact", "The information to save"), PRIVATE_OPTION],
+    },
+    Command {
+        name: "remember-detailed",
+        description: "Save a fact with who it's about, a date and importance (opens a form)",
+        options: &[],
+    },
+    Command {
+        name: "ask",
+        description: "Ask a question about your stored knowledge",
+        options: &[
+            text(
+                "question",
+                "Your question (e.g., 'When is John's birthday?')",
+            ),
+            PRIVATE_OPTION,
+        ],
+    },
+    Command {
+        name: "query",
+        description: "Query your knowledge base (alias for /ask)",
+        options: &[text("question", "Your query"), PRIVATE_OPTION],
+    },
+    Command {
+        name: "briefing",
+        description: "Get your personalized morning briefing with calendar, reminders, and updates",
+        options: &[PRIVATE_OPTION],
+    },
+    Command {
+        name: "edit",
+        description: "Edit or correct a fact in your knowledge base",
+        options: &[
+            text("fact", "The fact to change (e.g., 'John's birthday')"),
+            text(
+                "content",
+                "What it should say now (e.g., 'John's birthday is March 16')",
+            ),
+            PRIVATE_OPTION,
+        ],
+    },
+    Command {
+        name: "list",
+        description: "Browse the facts in your knowledge base",
+        options: &[
+            subcommand("recent", "Most recently saved facts", &[]),
+            subcommand("by-tag", "Facts with a tag", LIST_BY_TAG_OPTIONS),
+            subcommand("by-person", "Facts about a person", LIST_BY_PERSON_OPTIONS),
+        ],
+    },
+    Command {
+        name: "forget",
+        description: "Remove a fact from your knowledge base",
+        options: &[
+            text("fact", "The fact to forget (e.g., 'John's birthday')"),
+            PRIVATE_OPTION,
+        ],
+    },
+    Command {
+        name: "undo",
+        description: "Undo your last /edit or /forget (for 5 minutes after it)",
+        options: &[PRIVATE_OPTION],
+    },
+    Command {
+        name: "remind",
+        description: "Set a reminder",
+        options: &[
+            text(
+                "message",
+                "What and when (e.g., 'Call the dentist tomorrow at 9am')",
+            ),
+            PRIVATE_OPTION,
+        ],
+    },
+    Command {
+        name: "transcribe",
+        description: "Transcribe a voice note and save what it says",
+        options: &[
+            CommandOption {
+                name: "audio",
+                description: "Voice note or audio file (mp3, m4a, wav, ogg, ...)",
+                kind: OptionKind::Attachment { required: true },
+            },
+            PRIVATE_OPTION,
+        ],
+    },
+    Command {
+        name: "link",
+        description: "Link your Discord account to Second Brain",
+        options: &[text("code", "Link code from Settings → Discord in the app")],
+    },
+];
+
+/// Look up a command by name.
+pub fn find(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|c| c.name == name)
+}
+
+/// Whether a command accepts [`PRIVATE_OPTION`].
+pub fn supports_private(command: &str) -> bool {
+    find(command).is_some_and(|c| c.options.contains(&PRIVATE_OPTION))
+}
+
+/// Name of a command's free-text argument, if it takes one directly.
+pub fn text_option(command: &str) -> Option<&'static str> {
+    find(command)?
+        .options
+        .iter()
+        .find(|o| matches!(o.kind, OptionKind::String { .. }))
+        .map(|o| o.name)
+}
+
+impl Serialize for CommandOption {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("name", self.name)?;
+        map.serialize_entry("description", self.description)?;
+        match self.kind {
+            OptionKind::SubCommand(options) => {
+                map.serialize_entry("type", &OPTION_SUB_COMMAND)?;
+                if !options.is_empty() {
+                    map.serialize_entry("options", options)?;
+                }
+            }
+            OptionKind::String { required } => {
+                map.serialize_entry("type", &OPTION_STRING)?;
+                map.serialize_entry("required", &required)?;
+            }
+            OptionKind::Boolean { required } => {
+                map.serialize_entry("type", &OPTION_BOOLEAN)?;
+                map.serialize_entry("required", &required)?;
+            }
+            OptionKind::Attachment { required } => {
+                map.serialize_entry("type", &OPTION_ATTACHMENT)?;
+                map.serialize_entry("required", &required)?;
+            }
+        }
+        map.end()
+    }
+}
+
+impl Serialize for Command {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("name", self.name)?;
+        map.serialize_entry("description", self.description)?;
+        if !self.options.is_empty() {
+            map.serialize_entry("options", self.options)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn valid_name(name: &str) -> bool {
+        (1..=32).contains(&name.len())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+    }
+
+    fn is_required(option: &CommandOption) -> bool {
+        matches!(
+            option.kind,
+            OptionKind::String { required: true }
+                | OptionKind::Boolean { required: true }
+                | OptionKind::Attachment { required: true }
+        )
+    }
+
+    fn check_options(options: &[CommandOption]) {
+        // Discord rejects required options after optional ones
+        assert!(options
+            .windows(2)
+            .all(|pair| is_required(&pair[0]) || !is_required(&pair[1])));
+        for option in options {
+            assert!(valid_name(option.name), "invalid option name {}", option.name);
+            assert!((1..=100).contains(&option.description.chars().count()));
+            if let OptionKind::SubCommand(nested) = option.kind {
+                check_options(nested);
+            }
+        }
+    }
+
+    #[test]
+    fn test_commands_within_discord_limits() {
+        for (i, command) in COMMANDS.iter().enumerate() {
+            assert!(valid_name(command.name), "invalid command name {}", command.name);
+            assert!((1..=100).contains(&command.description.chars().count()));
+            assert!(
+                COMMANDS[..i].iter().all(|c| c.name != command.name),
+                "duplicate command {}",
+                command.name
+            );
+            check_options(command.options);
+        }
+    }
+
+    #[test]
+    fn test_text_option() {
+        assert_eq!(text_option("ask"), Some("question"));
+        assert_eq!(text_option("remember"), Some("fact"));
+        assert_eq!(text_option("edit"), Some("fact"));
+        assert_eq!(text_option("list"), None);
+        assert_eq!(text_option("briefing"), None);
+        assert_eq!(text_option("transcribe"), None);
+    }
+
+    #[test]
+    fn test_supports_private() {
+        assert!(supports_private("ask"));
+        assert!(supports_private("briefing"));
+        assert!(!supports_private("list"));
+        assert!(!supports_private("link"));
+    }
+
+    #[test]
+    fn test_serialize_subcommands() {
+        let list = serde_json::to_value(find("list").unwrap()).unwrap();
+        assert_eq!(list["options"][0]["type"], 1);
+        assert!(list["options"][0].get("options").is_none());
+        assert_eq!(list["options"][1]["options"][0]["type"], 3);
+        assert_eq!(list["options"][1]["options"][0]["required"], true);
+    }
+
+    #[test]
+    fn test_serialize_attachment() {
+        let transcribe = serde_json::to_value(find("transcribe").unwrap()).unwrap();
+        assert_eq!(transcribe["options"][0]["type"], 11);
+        assert_eq!(transcribe["options"][0]["required"], true);
+    }
+}
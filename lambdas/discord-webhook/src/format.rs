@@ -0,0 +1,189 @@
+//! Formatting agent responses for Discord.
+//!
+//! Agent answers are Markdown of arbitrary length; Discord caps message content
+//! at 2000 characters and embed descriptions at 4096, and only renders a subset
+//! of Markdown. Responses are converted with [`to_discord_markdown`], split with
+//! `shared::markdown::split` and rendered as embeds by [`answer_embeds`].
+
+use serde_json::Value;
+use shared::agents::AgentCitation;
+use shared::markdown::{heading, split, truncate, FENCE};
+
+/// Longest message content Discord accepts
+pub const MESSAGE_MAX_CHARS: usize = 2000;
+
+/// Longest embed description Discord accepts
+pub const EMBED_DESCRIPTION_MAX_CHARS: usize = 4096;
+
+/// Embed accent color (Discord blurple)
+pub const EMBED_COLOR: u32 = 0x5865F2;
+
+/// Embed title, field name and field value limits
+const EMBED_TITLE_MAX_CHARS: usize = 256;
+const EMBED_FIELD_NAME_MAX_CHARS: usize = 256;
+const EMBED_FIELD_VALUE_MAX_CHARS: usize = 1024;
+
+/// Fields per embed (Discord allows 25; more citations than this aren't useful)
+const MAX_CITATION_FIELDS: usize = 10;
+
+/// Convert Markdown to what Discord renders.
+///
+/// - `####` and deeper headings become bold lines (Discord renders `#` to `###`)
+/// - Horizontal rules are dropped
+/// - Tables are wrapped in code blocks so their columns line up
+/// - `<br>` tags become line breaks
+pub fn to_discord_markdown(text: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_code = false;
+    let mut in_table = false;
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with(FENCE) {
+            if in_table {
+                lines.push(FENCE.to_string());
+                in_table = false;
+            }
+            in_code = !in_code;
+            lines.push(line.to_string());
+            continue;
+        }
+
+        if in_code {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        let is_table_row = trimmed.len() > 1 && trimmed.starts_with('|') && trimmed.ends_with('|');
+        if is_table_row != in_table {
+            lines.push(FENCE.to_string());
+            in_table = is_table_row;
+        }
+        if is_table_row {
+            lines.push(trimmed.to_string());
+            continue;
+        }
+
+        if let Some((_, heading)) = heading(trimmed).filter(|(level, _)| *level >= 4) {
+            lines.push(format!("**{}**", heading));
+        } else if is_rule(trimmed) {
+            lines.push(String::new());
+        } else {
+            lines.push(line.replace("<br>", "\n").replace("<br/>", "\n"));
+        }
+    }
+
+    if in_table {
+        lines.push(FENCE.to_string());
+    }
+
+    lines.join("\n")
+}
+
+/// `---`, `***` or `___` on a line of its own.
+fn is_rule(line: &str) -> bool {
+    let marks: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3
+        && ['-', '*', '_']
+            .iter()
+            .any(|m| marks.chars().all(|c| c == *m))
+}
+
+/// Render an answer as one or more embeds.
+///
+/// The first embed carries the title; the last carries cited facts as fields
+/// and the confidence in its footer. Each embed is sent as its own message.
+pub fn answer_embeds(
+    title: &str,
+    text: &str,
+    citations: &[AgentCitation],
+    confidence: Option<f32>,
+) -> Vec<Value> {
+    let formatted = to_discord_markdown(text);
+    let mut descriptions = split(&formatted, EMBED_DESCRIPTION_MAX_CHARS);
+    if descriptions.is_empty() {
+        descriptions.push(String::new());
+    }
+
+    let count = descriptions.len();
+    descriptions
+        .into_iter()
+        .enumerate()
+        .map(|(i, description)| {
+            let mut embed = serde_json::json!({
+                "description": description,
+                "color": EMBED_COLOR,
+            });
+
+            if i == 0 && !title.trim().is_empty() {
+                embed["title"] = Value::String(truncate(title.trim(), EMBED_TITLE_MAX_CHARS));
+            }
+
+            if i + 1 == count {
+                let fields: Vec<Value> = citations
+                    .iter()
+                    .take(MAX_CITATION_FIELDS)
+                    .enumerate()
+                    .map(|(n, citation)| {
+                        let name = citation
+                            .title
+                            .clone()
+                            .unwrap_or_else(|| format!("Source {}", n + 1));
+                        serde_json::json!({
+                            "name": truncate(&name, EMBED_FIELD_NAME_MAX_CHARS),
+                            "value": truncate(&citation.content, EMBED_FIELD_VALUE_MAX_CHARS),
+                        })
+                    })
+                    .collect();
+                if !fields.is_empty() {
+                    embed["fields"] = Value::Array(fields);
+                }
+
+                if let Some(confidence) = confidence {
+                    let percent = (confidence.clamp(0.0, 1.0) * 100.0).round();
+                    embed["footer"] = serde_json::json!({ "text": format!("Confidence: {}%", percent) });
+                }
+            }
+
+            embed
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_discord_markdown() {
+        let text = "#### Details\n---\n| a | b |\n|---|---|\n| 1 | 2 |\nDone<br>now";
+        assert_eq!(
+            to_discord_markdown(text),
+            "**Details**\n\n```\n| a | b |\n|---|---|\n| 1 | 2 |\n```\nDone\nnow"
+        );
+        // Code blocks are left alone
+        assert_eq!(to_discord_markdown("```\n#### x\n```"), "```\n#### x\n```");
+        assert_eq!(to_discord_markdown("## Kept"), "## Kept");
+    }
+
+    #[test]
+    fn test_answer_embeds() {
+        let citations = vec![AgentCitation {
+            title: None,
+            content: "Emma's recital is Friday".to_string(),
+        }];
+        let embeds = answer_embeds("When is the recital?", "Friday at 6pm", &citations, Some(0.87));
+        assert_eq!(embeds.len(), 1);
+        assert_eq!(embeds[0]["title"], "When is the recital?");
+        assert_eq!(embeds[0]["fields"][0]["name"], "Source 1");
+        assert_eq!(embeds[0]["footer"]["text"], "Confidence: 87%");
+
+        let long = "word ".repeat(2000);
+        let embeds = answer_embeds("Title", &long, &citations, None);
+        assert!(embeds.len() > 1);
+        assert!(embeds[1].get("title").is_none());
+        assert!(embeds[0].get("fields").is_none());
+        assert!(embeds.last().unwrap().get("fields").is_some());
+    }
+}
@@ -0,0 +1,245 @@
+//! Discord Gateway messages shared by the `discord_relay` binary and the
+//! webhook Lambda.
+//!
+//! The relay holds the Gateway websocket (see [`Frame`]) and forwards each
+//! `MESSAGE_CREATE` dispatch as a [`RelayPayload`], signed for
+//! [`EVENT_PATH`]; the Lambda answers those [`direct_message`] picks out.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Operation the relay signs forwarded gateway events for
+pub const EVENT_PATH: &str = "/discord/gateway-event";
+
+/// The only dispatch the relay forwards
+pub const MESSAGE_CREATE: &str = "MESSAGE_CREATE";
+
+/// `DIRECT_MESSAGES` and `MESSAGE_CONTENT`
+pub const INTENTS: u64 = (1 << 12) | (1 << 15);
+
+/// Gateway opcodes
+pub const OP_DISPATCH: u8 = 0;
+pub const OP_HEARTBEAT: u8 = 1;
+pub const OP_IDENTIFY: u8 = 2;
+pub const OP_RECONNECT: u8 = 7;
+pub const OP_INVALID_SESSION: u8 = 9;
+pub const OP_HELLO: u8 = 10;
+pub const OP_HEARTBEAT_ACK: u8 = 11;
+
+/// Gateway event forwarded by the relay
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelayPayload {
+    pub gateway_event: GatewayEvent,
+}
+
+/// Discord gateway dispatch event
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GatewayEvent {
+    /// Event name (e.g. `MESSAGE_CREATE`)
+    pub t: String,
+    /// Event data
+    pub d: Value,
+}
+
+/// `MESSAGE_CREATE` event data
+#[derive(Debug, Deserialize)]
+pub struct GatewayMessage {
+    pub id: String,
+    pub channel_id: String,
+    /// Absent for direct messages
+    pub guild_id: Option<String>,
+    pub author: GatewayAuthor,
+    #[serde(default)]
+    pub content: String,
+}
+
+/// Author of a gateway message
+#[derive(Debug, Deserialize)]
+pub struct GatewayAuthor {
+    pub id: String,
+    pub username: String,
+    #[serde(default)]
+    pub bot: bool,
+}
+
+/// The direct message a person sent, if that is what `event` is. Guild
+/// messages, bot authors (including our own replies) and empty messages are
+/// `None`; a `MESSAGE_CREATE` that doesn't parse is an error.
+pub fn direct_message(event: GatewayEvent) -> Result<Option<GatewayMessage>, serde_json::Error> {
+    if event.t != MESSAGE_CREATE {
+        return Ok(None);
+    }
+
+    let message: GatewayMessage = serde_json::from_value(event.d)?;
+    let direct =
+        !message.author.bot && message.guild_id.is_none() && !message.content.trim().is_empty();
+
+    Ok(direct.then_some(message))
+}
+
+/// A Gateway websocket frame, sent or received
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Frame {
+    pub op: u8,
+    #[serde(default)]
+    pub d: Value,
+    /// Sequence number of a dispatch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub s: Option<u64>,
+    /// Event name of a dispatch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub t: Option<String>,
+}
+
+impl Frame {
+    /// Identify as the bot with [`INTENTS`]
+    pub fn identify(bot_token: &str) -> Self {
+        Self::new(
+            OP_IDENTIFY,
+            serde_json::json!({
+                "token": bot_token,
+                "intents": INTENTS,
+                "properties": {
+                    "os": std::env::consts::OS,
+                    "browser": "second-brain-relay",
+                    "device": "second-brain-relay",
+                },
+            }),
+        )
+    }
+
+    /// Heartbeat carrying the last sequence number seen
+    pub fn heartbeat(seq: Option<u64>) -> Self {
+        Self::new(OP_HEARTBEAT, serde_json::json!(seq))
+    }
+
+    fn new(op: u8, d: Value) -> Self {
+        Self {
+            op,
+            d,
+            s: None,
+            t: None,
+        }
+    }
+
+    /// Milliseconds between heartbeats, from a Hello
+    pub fn heartbeat_interval(&self) -> Option<u64> {
+        self.d["heartbeat_interval"]
+            .as_u64()
+            .filter(|_| self.op == OP_HELLO)
+    }
+
+    /// The event to forward, if this is a `MESSAGE_CREATE` dispatch
+    pub fn into_forwarded(self) -> Option<RelayPayload> {
+        match (self.op, self.t) {
+            (OP_DISPATCH, Some(t)) if t == MESSAGE_CREATE => Some(RelayPayload {
+                gateway_event: GatewayEvent { t, d: self.d },
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message_create(d: Value) -> GatewayEvent {
+        GatewayEvent {
+            t: MESSAGE_CREATE.to_string(),
+            d,
+        }
+    }
+
+    fn dm(content: &str) -> Value {
+        json!({
+            "id": "1",
+            "channel_id": "2",
+            "author": { "id": "3", "username": "sam" },
+            "content": content,
+        })
+    }
+
+    #[test]
+    fn test_parses_relayed_direct_message() {
+        let payload: RelayPayload = serde_json::from_value(json!({
+            "gateway_event": { "t": "MESSAGE_CREATE", "d": dm("When is Emma's recital?") },
+            "internal_auth": { "signature": "..." },
+        }))
+        .unwrap();
+
+        let message = direct_message(payload.gateway_event).unwrap().unwrap();
+        assert_eq!(message.channel_id, "2");
+        assert_eq!(message.author.id, "3");
+        assert_eq!(message.author.username, "sam");
+        assert_eq!(message.content, "When is Emma's recital?");
+    }
+
+    #[test]
+    fn test_ignores_other_messages() {
+        let mut guild = dm("hi");
+        guild["guild_id"] = json!("4");
+        let mut bot = dm("hi");
+        bot["author"]["bot"] = json!(true);
+        let mut no_content = dm("");
+        no_content.as_object_mut().unwrap().remove("content");
+
+        for d in [guild, bot, dm("  "), no_content] {
+            assert!(direct_message(message_create(d)).unwrap().is_none());
+        }
+
+        let ready = GatewayEvent {
+            t: "READY".to_string(),
+            d: json!({}),
+        };
+        assert!(direct_message(ready).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_malformed_message_is_an_error() {
+        assert!(direct_message(message_create(json!({ "id": "1" }))).is_err());
+    }
+
+    #[test]
+    fn test_forwards_only_message_create() {
+        let dispatch: Frame = serde_json::from_value(json!({
+            "op": 0, "s": 42, "t": "MESSAGE_CREATE", "d": dm("hi"),
+        }))
+        .unwrap();
+        assert_eq!(dispatch.s, Some(42));
+
+        // What the relay sends is what the Lambda reads
+        let forwarded = serde_json::to_value(dispatch.into_forwarded().unwrap()).unwrap();
+        let payload: RelayPayload = serde_json::from_value(forwarded).unwrap();
+        assert!(direct_message(payload.gateway_event).unwrap().is_some());
+
+        let ready: Frame =
+            serde_json::from_value(json!({ "op": 0, "s": 1, "t": "READY", "d": {} })).unwrap();
+        assert!(ready.into_forwarded().is_none());
+        let ack: Frame = serde_json::from_value(json!({ "op": 11 })).unwrap();
+        assert!(ack.into_forwarded().is_none());
+    }
+
+    #[test]
+    fn test_hello_and_heartbeat() {
+        let hello: Frame =
+            serde_json::from_value(json!({ "op": 10, "d": { "heartbeat_interval": 41250 } }))
+                .unwrap();
+        assert_eq!(hello.heartbeat_interval(), Some(41250));
+        assert_eq!(Frame::heartbeat(Some(7)).heartbeat_interval(), None);
+
+        let heartbeat = serde_json::to_value(Frame::heartbeat(None)).unwrap();
+        assert_eq!(heartbeat, json!({ "op": 1, "d": null }));
+        let heartbeat = serde_json::to_value(Frame::heartbeat(Some(7))).unwrap();
+        assert_eq!(heartbeat, json!({ "op": 1, "d": 7 }));
+    }
+
+    #[test]
+    fn test_identify() {
+        let identify = serde_json::to_value(Frame::identify("token")).unwrap();
+        assert_eq!(identify["op"], 2);
+        assert_eq!(identify["d"]["token"], "token");
+        assert_eq!(identify["d"]["intents"], 4096 + 32768);
+    }
+}
@@ -0,0 +1,6 @@
+//! Discord bot definitions shared by the webhook Lambda and the
+//! `register_commands` and `discord_relay` binaries.
+
+pub mod commands;
+pub mod format;
+pub mod gateway;
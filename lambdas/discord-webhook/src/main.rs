@@ -4,25 +4,161 @@
 //! processes slash commands, and invokes the agent system.
 //!
 //! Uses deferred responses to handle Discord's 3-second timeout requirement.
+//! Follow-ups go through an SQS queue (`FOLLOW_UP_QUEUE_URL`) and are retried
+//! with exponential backoff; Discord 429s are retried after `Retry-After`. A
+//! follow-up that exhausts its retries edits the response to an error and is
+//! dead-lettered.
+//!
+//! `/list` reads facts straight from the database (when `DB_HOST` is set) and
+//! renders them as paginated embeds.
+//!
+//! Buttons arrive as message component interactions and are dispatched on their
+//! `custom_id` (see [`ComponentAction`]): list pagination, "Save this" on answers,
+//! "Forget" on saved facts, and "Snooze 1h" on reminder notifications.
+//!
+//! `/briefing` serves today's pre-generated briefing from the database and only
+//! generates one live when it is missing.
+//!
+//! Direct messages arrive as `MESSAGE_CREATE` gateway events forwarded by the
+//! `discord_relay` binary (a direct Lambda invocation, see [`gateway`], signed
+//! like follow-ups); the agent classifies each message as a question or
+//! something to remember.
+//!
+//! Each DM channel and each thread `/ask` is used in is kept as a conversation
+//! (see `shared::conversations`), so follow-up questions see the earlier turns.
+//!
+//! Commands act on the registered user linked to the Discord account (`/link`
+//! redeems a code issued by the API); unlinked users are told how to link.
+//!
+//! Commands that accept the `private` option answer ephemerally when it is set,
+//! otherwise publicly in the channel or thread where they were used.
+//!
+//! Agent responses are converted to Discord Markdown and sent as embeds (see
+//! [`format`]); responses too long for one message continue in follow-ups.
+//!
+//! Command schemas live in [`commands`] and are uploaded with the `register_commands` binary.
+//!
+//! `/remember-detailed` opens a modal with content, entity, date and importance
+//! fields; its submission is turned into a structured [`IngestRequest`].
+//!
+//! `/transcribe` takes an audio attachment, transcribes it with Amazon Transcribe
+//! in the follow-up (staging the audio in `VOICE_NOTE_BUCKET`) and ingests the
+//! text as a voice fact.
+//!
+//! Follow-ups are signed (see `shared::auth::sign_internal`) and checked
+//! before they act as the user in them.
+//!
+//! `/edit` and `/forget` go to the internal facts Lambda (see
+//! `shared::fact_actions`) rather than the agent. When the description
+//! matches several facts the reply lists them with a button each; editing
+//! one opens a modal with the new wording to confirm. The change can be
+//! undone for a few minutes, with the reply's "Undo" button or `/undo`.
 
 use aws_sdk_lambda::primitives::Blob;
+use aws_sdk_transcribe::types::{Media, MediaFormat, TranscriptionJobStatus};
+use discord_webhook::commands;
+use discord_webhook::format::{self, answer_embeds, MESSAGE_MAX_CHARS};
+use discord_webhook::gateway::{self, GatewayEvent, RelayPayload};
 use ed25519_dalek::{Signature, Verifier, VerifyingKey};
 use lambda_runtime::{service_fn, Error, LambdaEvent};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use shared::AgentClient;
+use shared::access::space_clause;
+use shared::briefings::{generate_briefing, todays_briefing};
+use shared::conversations;
+use shared::diagnostics::{agent_trace, record_sample, Sample};
+use shared::discord_links::redeem_link_code;
+use shared::fact_actions::{
+    self, FactActionRequest, FactActionResponse, FactCandidate, FactTarget, UndoToken, DELETE_PATH,
+    EDIT_PATH, UNDO_PATH,
+};
+use shared::agents::{AgentMetadata, DirectFallback};
+use shared::auth::{sign_internal, verify_internal};
+use shared::markdown::{split, truncate};
+use shared::metrics;
+use shared::{
+    AgentClient, AgentRequest, AgentResponse, AuthenticatedUser, AuthorizedUser, IngestRequest,
+};
+use sqlx::PgPool;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{error, info, warn};
 use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// Operation follow-ups (invoked or queued) are signed for
+const FOLLOW_UP_PATH: &str = "/discord/follow-up";
 
 /// Discord interaction types
 const INTERACTION_PING: u8 = 1;
 const INTERACTION_APPLICATION_COMMAND: u8 = 2;
+const INTERACTION_MESSAGE_COMPONENT: u8 = 3;
+const INTERACTION_MODAL_SUBMIT: u8 = 5;
 
 /// Discord response types
 const RESPONSE_PONG: u8 = 1;
 const RESPONSE_CHANNEL_MESSAGE: u8 = 4;
 const RESPONSE_DEFERRED_CHANNEL_MESSAGE: u8 = 5;
+const RESPONSE_DEFERRED_UPDATE_MESSAGE: u8 = 6;
+const RESPONSE_MODAL: u8 = 9;
+
+/// Discord channel types of threads (announcement, public, private)
+const THREAD_CHANNEL_TYPES: [u8; 3] = [10, 11, 12];
+
+/// `custom_id` of the `/remember-detailed` modal
+const REMEMBER_MODAL_ID: &str = "remember_detailed";
+
+/// `custom_id` prefix of the modal confirming an edit, followed by the fact ID
+const EDIT_MODAL_PREFIX: &str = "edit_fact:";
+
+/// Embed field carrying the new wording of an edit awaiting a choice of fact
+const NEW_WORDING_FIELD: &str = "New wording";
+
+/// Longest embed field value
+const EMBED_FIELD_MAX_CHARS: usize = 1024;
+
+/// Reply to anyone whose Discord account isn't linked to a registered user
+const UNLINKED_MESSAGE: &str = "Your Discord account isn't linked to Second Brain yet. \
+Get a link code from Settings → Discord in the app, then run `/link` with it here.";
+
+/// Facts shown per `/list` page
+const LIST_PAGE_SIZE: i64 = 5;
+
+/// Longest fact excerpt shown in a `/list` embed
+const LIST_EXCERPT_CHARS: usize = 200;
+
+/// Longest tag/person argument carried in a button `custom_id` (100 char limit)
+const LIST_ARG_MAX_CHARS: usize = 64;
+
+/// Deliveries of a queued follow-up before it is dead-lettered
+/// (must match `maxReceiveCount` on the queue's redrive policy)
+const FOLLOW_UP_MAX_RECEIVES: u32 = 3;
+
+/// Delay before redelivering a failed follow-up; doubles on each retry and
+/// stays well inside the 15-minute interaction token lifetime
+const FOLLOW_UP_RETRY_BASE_SECS: i32 = 20;
+
+/// Attempts at a Discord API call that is rate limited (429) or unavailable (5xx)
+const DISCORD_MAX_ATTEMPTS: u32 = 4;
+
+/// Backoff between Discord attempts when no `Retry-After` is given; doubles each attempt
+const DISCORD_RETRY_BASE_MS: u64 = 500;
+
+/// Longest `Retry-After` honoured before giving up to the queue's redelivery
+const DISCORD_RETRY_MAX: Duration = Duration::from_secs(10);
+
+/// Largest voice note `/transcribe` accepts (Discord's default upload limit)
+const VOICE_NOTE_MAX_BYTES: u64 = 25 * 1024 * 1024;
+
+/// Prefix voice notes and their transcripts are written under in `VOICE_NOTE_BUCKET`
+const VOICE_NOTE_PREFIX: &str = "voice-notes/";
+
+/// How often to check on a transcription job, and how long to wait for it
+const TRANSCRIBE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const TRANSCRIBE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Final edit when a follow-up has exhausted its retries
+const FOLLOW_UP_FAILED_MESSAGE: &str = "Sorry, something went wrong. Please try again.";
 
 /// Discord interaction request
 #[derive(Debug, Deserialize, Clone)]
@@ -34,20 +170,129 @@ struct DiscordInteraction {
     member: Option<GuildMember>,
     user: Option<DiscordUser>,
     application_id: Option<String>,
+    /// Message a clicked component is attached to
+    message: Option<InteractionMessage>,
+    /// Channel the interaction was used in
+    channel: Option<InteractionChannel>,
 }
 
-/// Discord interaction data (for slash commands)
+/// Channel an interaction was used in
+#[derive(Debug, Deserialize, Clone)]
+struct InteractionChannel {
+    id: String,
+    #[serde(rename = "type")]
+    channel_type: u8,
+}
+
+impl InteractionChannel {
+    fn is_thread(&self) -> bool {
+        THREAD_CHANNEL_TYPES.contains(&self.channel_type)
+    }
+}
+
+/// Message attached to a component interaction
+#[derive(Debug, Deserialize, Clone)]
+struct InteractionMessage {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    embeds: Vec<MessageEmbed>,
+}
+
+impl InteractionMessage {
+    /// Text of the message, including answers rendered as embeds
+    fn text(&self) -> String {
+        let descriptions = self
+            .embeds
+            .iter()
+            .filter_map(|e| e.description.as_deref())
+            .map(str::trim);
+
+        std::iter::once(self.content.trim())
+            .chain(descriptions)
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// Value of the first embed field named `name`
+    fn field(&self, name: &str) -> Option<&str> {
+        self.embeds
+            .iter()
+            .flat_map(|e| e.fields.iter())
+            .find(|f| f.name == name)
+            .map(|f| f.value.as_str())
+    }
+}
+
+/// Embed on a message we sent
+#[derive(Debug, Deserialize, Clone)]
+struct MessageEmbed {
+    description: Option<String>,
+    #[serde(default)]
+    fields: Vec<EmbedField>,
+}
+
+/// Named field of an embed
+#[derive(Debug, Deserialize, Clone)]
+struct EmbedField {
+    name: String,
+    value: String,
+}
+
+/// Discord interaction data (slash commands and message components)
 #[derive(Debug, Deserialize, Clone)]
 struct InteractionData {
+    #[serde(default)]
     name: String,
     options: Option<Vec<CommandOption>>,
+    /// Set on message component and modal submit interactions
+    custom_id: Option<String>,
+    /// Action rows of submitted modal fields
+    components: Option<Vec<ModalRow>>,
+    /// Objects referenced by option values (attachments by ID)
+    resolved: Option<ResolvedData>,
+}
+
+/// Objects referenced by command options
+#[derive(Debug, Deserialize, Clone)]
+struct ResolvedData {
+    #[serde(default)]
+    attachments: std::collections::HashMap<String, Attachment>,
+}
+
+/// File uploaded with a command (also the `/transcribe` follow-up message)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Attachment {
+    filename: String,
+    url: String,
+    content_type: Option<String>,
+    #[serde(default)]
+    size: u64,
+}
+
+/// Action row in a modal submission
+#[derive(Debug, Deserialize, Clone)]
+struct ModalRow {
+    #[serde(default)]
+    components: Vec<ModalField>,
+}
+
+/// Text input value in a modal submission
+#[derive(Debug, Deserialize, Clone)]
+struct ModalField {
+    custom_id: String,
+    #[serde(default)]
+    value: String,
 }
 
-/// Discord command option
+/// Discord command option (or subcommand, which carries nested options)
 #[derive(Debug, Deserialize, Clone)]
 struct CommandOption {
     name: String,
+    #[serde(default)]
     value: serde_json::Value,
+    options: Option<Vec<CommandOption>>,
 }
 
 /// Discord guild member
@@ -75,13 +320,14 @@ struct DiscordResponse {
 /// Discord response data
 #[derive(Debug, Serialize)]
 struct ResponseData {
+    #[serde(skip_serializing_if = "String::is_empty")]
     content: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     flags: Option<u32>,
 }
 
 /// Payload for async follow-up processing
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct FollowUpPayload {
     follow_up: bool,
     application_id: String,
@@ -90,6 +336,49 @@ struct FollowUpPayload {
     message: String,
     user_id: String,
     username: String,
+    /// Extra follow-up messages (long answers) are ephemeral too
+    #[serde(default)]
+    private: bool,
+    /// Thread the command was used in; `/ask` there continues its conversation
+    #[serde(default)]
+    thread_id: Option<String>,
+}
+
+/// SQS event wrapper (queued follow-ups)
+#[derive(Debug, Deserialize)]
+struct SqsEvent {
+    #[serde(rename = "Records")]
+    records: Vec<SqsRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SqsRecord {
+    #[serde(rename = "messageId")]
+    message_id: String,
+    #[serde(rename = "receiptHandle")]
+    receipt_handle: String,
+    body: String,
+    #[serde(default)]
+    attributes: SqsRecordAttributes,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SqsRecordAttributes {
+    #[serde(rename = "ApproximateReceiveCount", default)]
+    approximate_receive_count: Option<String>,
+}
+
+/// Partial batch response so only failed follow-ups are retried
+#[derive(Debug, Serialize)]
+struct SqsBatchResponse {
+    #[serde(rename = "batchItemFailures")]
+    batch_item_failures: Vec<BatchItemFailure>,
+}
+
+#[derive(Debug, Serialize)]
+struct BatchItemFailure {
+    #[serde(rename = "itemIdentifier")]
+    item_identifier: String,
 }
 
 /// API Gateway proxy request (simplified)
@@ -129,13 +418,341 @@ impl ApiGatewayResponse {
     }
 }
 
+/// Which facts a `/list` page shows
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ListFilter {
+    Recent,
+    ByTag(String),
+    ByPerson(String),
+}
+
+/// A single `/list` page, serialized into button `custom_id`s as
+/// `list:{recent|tag|person}:{page}:{arg}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ListQuery {
+    filter: ListFilter,
+    page: i64,
+}
+
+impl ListQuery {
+    /// Build from the `/list` subcommand options.
+    fn from_options(options: Option<&Vec<CommandOption>>) -> Option<Self> {
+        let subcommand = options?.first()?;
+        let arg = subcommand
+            .options
+            .as_ref()
+            .and_then(|opts| opts.first())
+            .and_then(|o| o.value.as_str())
+            .map(|v| v.trim().chars().take(LIST_ARG_MAX_CHARS).collect::<String>());
+
+        let filter = match (subcommand.name.as_str(), arg) {
+            ("recent", _) => ListFilter::Recent,
+            ("by-tag", Some(tag)) if !tag.is_empty() => ListFilter::ByTag(tag),
+            ("by-person", Some(person)) if !person.is_empty() => ListFilter::ByPerson(person),
+            _ => return None,
+        };
+
+        Some(Self { filter, page: 0 })
+    }
+
+    fn parse(spec: &str) -> Option<Self> {
+        let mut parts = spec.splitn(4, ':');
+        if parts.next()? != "list" {
+            return None;
+        }
+        let kind = parts.next()?;
+        let page: i64 = parts.next()?.parse().ok().filter(|p| *p >= 0)?;
+        let arg = parts.next().unwrap_or_default().to_string();
+
+        let filter = match kind {
+            "recent" => ListFilter::Recent,
+            "tag" if !arg.is_empty() => ListFilter::ByTag(arg),
+            "person" if !arg.is_empty() => ListFilter::ByPerson(arg),
+            _ => return None,
+        };
+
+        Some(Self { filter, page })
+    }
+
+    fn to_spec(&self) -> String {
+        let (kind, arg) = match &self.filter {
+            ListFilter::Recent => ("recent", ""),
+            ListFilter::ByTag(tag) => ("tag", tag.as_str()),
+            ListFilter::ByPerson(person) => ("person", person.as_str()),
+        };
+        format!("list:{}:{}:{}", kind, self.page, arg)
+    }
+
+    fn with_page(&self, page: i64) -> Self {
+        Self {
+            filter: self.filter.clone(),
+            page,
+        }
+    }
+
+    fn title(&self) -> String {
+        match &self.filter {
+            ListFilter::Recent => "Recent facts".to_string(),
+            ListFilter::ByTag(tag) => format!("Facts tagged \"{}\"", tag),
+            ListFilter::ByPerson(person) => format!("Facts about {}", person),
+        }
+    }
+}
+
+/// Action encoded in a message component's `custom_id`
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ComponentAction {
+    /// `list:...` - show another `/list` page
+    List(ListQuery),
+    /// `save` - save the message the button is attached to as a fact
+    Save,
+    /// `forget` - forget the fact described by the message
+    Forget,
+    /// `forget:{fact_id}` - forget a fact picked from several matches
+    ForgetFact(Uuid),
+    /// `edit:{fact_id}` - edit a fact picked from several matches
+    EditFact(Uuid),
+    /// `undo:{token}` - undo an edit or deletion
+    Undo(Uuid),
+    /// `snooze:{reminder_id}:{minutes}` - snooze a reminder
+    Snooze { reminder_id: Uuid, minutes: i32 },
+}
+
+impl ComponentAction {
+    fn parse(custom_id: &str) -> Option<Self> {
+        match custom_id.split(':').next()? {
+            "list" => ListQuery::parse(custom_id).map(ComponentAction::List),
+            "save" => Some(ComponentAction::Save),
+            "forget" => match custom_id.split_once(':') {
+                Some((_, id)) => Uuid::parse_str(id).ok().map(ComponentAction::ForgetFact),
+                None => Some(ComponentAction::Forget),
+            },
+            "edit" => {
+                let (_, id) = custom_id.split_once(':')?;
+                Uuid::parse_str(id).ok().map(ComponentAction::EditFact)
+            }
+            "undo" => {
+                let (_, token) = custom_id.split_once(':')?;
+                Uuid::parse_str(token).ok().map(ComponentAction::Undo)
+            }
+            "snooze" => {
+                let mut parts = custom_id.splitn(3, ':').skip(1);
+                let reminder_id = Uuid::parse_str(parts.next()?).ok()?;
+                let minutes = parts.next()?.parse().ok().filter(|m| (1..=1440).contains(m))?;
+                Some(ComponentAction::Snooze { reminder_id, minutes })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A single-button action row
+fn button_row(label: &str, custom_id: &str) -> Value {
+    serde_json::json!([{
+        "type": 1,
+        "components": [{
+            "type": 2,
+            "style": 2,
+            "label": label,
+            "custom_id": custom_id,
+        }],
+    }])
+}
+
+/// Ephemeral channel message response
+/// Transcribe media format for an audio file name, by extension.
+fn media_format(filename: &str) -> Option<MediaFormat> {
+    let ext = filename
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "mp3" => Some(MediaFormat::Mp3),
+        "mp4" | "m4a" => Some(MediaFormat::Mp4),
+        "wav" => Some(MediaFormat::Wav),
+        "flac" => Some(MediaFormat::Flac),
+        "ogg" | "oga" | "opus" => Some(MediaFormat::Ogg),
+        "webm" => Some(MediaFormat::Webm),
+        "amr" => Some(MediaFormat::Amr),
+        _ => None,
+    }
+}
+
+/// The audio attached to a `/transcribe` command, or a reply explaining why it
+/// can't be transcribed.
+fn voice_note(data: &InteractionData) -> Result<&Attachment, &'static str> {
+    let attachment = data
+        .options
+        .as_ref()
+        .and_then(|opts| opts.iter().find(|o| o.name == "audio"))
+        .and_then(|o| o.value.as_str())
+        .and_then(|id| data.resolved.as_ref()?.attachments.get(id))
+        .ok_or("Attach a voice note or audio file to transcribe.")?;
+
+    if media_format(&attachment.filename).is_none() {
+        return Err("That file type isn't supported. Try mp3, m4a, wav, flac, ogg, webm or amr.");
+    }
+    if attachment.size > VOICE_NOTE_MAX_BYTES {
+        return Err("That file is too large. Voice notes can be up to 25 MB.");
+    }
+
+    Ok(attachment)
+}
+
+fn ephemeral_response(content: &str) -> Result<Value, Error> {
+    Ok(serde_json::to_value(ApiGatewayResponse::json(
+        200,
+        &DiscordResponse {
+            response_type: RESPONSE_CHANNEL_MESSAGE,
+            data: Some(ResponseData {
+                content: content.to_string(),
+                flags: Some(64),
+            }),
+        },
+    )?)?)
+}
+
+/// Modal for `/remember-detailed`.
+fn remember_modal() -> Value {
+    let field = |custom_id: &str, label: &str, style: u8, required: bool, max_length: u32, placeholder: &str| {
+        serde_json::json!({
+            "type": 1,
+            "components": [{
+                "type": 4,
+                "custom_id": custom_id,
+                "label": label,
+                "style": style,
+                "required": required,
+                "max_length": max_length,
+                "placeholder": placeholder,
+            }],
+        })
+    };
+
+    serde_json::json!({
+        "type": RESPONSE_MODAL,
+        "data": {
+            "custom_id": REMEMBER_MODAL_ID,
+            "title": "Remember something",
+            "components": [
+                field("content", "What should I remember?", 2, true, 2000, "Emma's piano recital is at 6pm in the school hall"),
+                field("entity", "Who or what is it about?", 1, false, 100, "Emma"),
+                field("date", "Date (YYYY-MM-DD)", 1, false, 10, "2026-11-03"),
+                field("importance", "Importance (1-5)", 1, false, 1, "3"),
+            ],
+        },
+    })
+}
+
+/// Modal confirming the new wording of a fact picked from several matches.
+fn edit_modal(fact_id: Uuid, content: &str) -> Value {
+    serde_json::json!({
+        "type": RESPONSE_MODAL,
+        "data": {
+            "custom_id": format!("{}{}", EDIT_MODAL_PREFIX, fact_id),
+            "title": "Edit fact",
+            "components": [{
+                "type": 1,
+                "components": [{
+                    "type": 4,
+                    "custom_id": "content",
+                    "label": "What should it say now?",
+                    "style": 2,
+                    "required": true,
+                    "max_length": 2000,
+                    "value": content,
+                }],
+            }],
+        },
+    })
+}
+
+/// Build an [`IngestRequest`] from a `/remember-detailed` modal submission.
+fn parse_remember_modal(rows: &[ModalRow]) -> Result<IngestRequest, String> {
+    let value = |id: &str| {
+        rows.iter()
+            .flat_map(|row| row.components.iter())
+            .find(|field| field.custom_id == id)
+            .map(|field| field.value.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+
+    let valid_from = match value("date") {
+        Some(date) => Some(
+            chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+                .map_err(|_| format!("\"{}\" isn't a date. Use YYYY-MM-DD.", date))?,
+        ),
+        None => None,
+    };
+
+    let importance = match value("importance") {
+        Some(importance) => Some(
+            importance
+                .parse::<i16>()
+                .map_err(|_| "Importance must be a number from 1 to 5.".to_string())?,
+        ),
+        None => None,
+    };
+
+    let request = IngestRequest {
+        content: value("content").unwrap_or_default(),
+        visibility_tier: None,
+        about_entity: value("entity"),
+        valid_from,
+        importance,
+    };
+
+    request.validate().map_err(|e| match e {
+        shared::Error::Validation(message) => message,
+        other => other.to_string(),
+    })?;
+
+    Ok(request)
+}
+
+/// `/edit`, `/forget` and `/undo` arguments, carried to the follow-up as
+/// JSON: the fact (described, or picked by ID from several matches), for
+/// edits its new content, and for undos the token of the change (the latest
+/// change when unset). The "Forget" button on saved facts sends plain text
+/// instead.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct FactChange {
+    #[serde(flatten)]
+    target: FactTarget,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    undo_token: Option<Uuid>,
+}
+
+/// Fact row shown in a `/list` embed
+#[derive(Debug, sqlx::FromRow)]
+struct ListedFact {
+    content: String,
+    recorded_at: chrono::DateTime<chrono::Utc>,
+}
+
 /// Application state
 struct AppState {
     agent_client: AgentClient,
     lambda_client: aws_sdk_lambda::Client,
+    sqs_client: aws_sdk_sqs::Client,
+    s3_client: aws_sdk_s3::Client,
+    transcribe_client: aws_sdk_transcribe::Client,
     http_client: reqwest::Client,
     discord_public_key: VerifyingKey,
     function_name: String,
+    /// Internal facts Lambda `/edit` and `/forget` are sent to
+    fact_actions_function: String,
+    /// Follow-up queue (None falls back to an async self-invoke without retries)
+    follow_up_queue_url: Option<String>,
+    /// Bucket `/transcribe` stages voice notes in (None disables `/transcribe`)
+    voice_note_bucket: Option<String>,
+    /// Database pool for `/list` (None if DB_HOST is not configured)
+    db_pool: Option<PgPool>,
+    /// Bot token for replying to direct messages (None if DISCORD_SECRET_ARN is not configured)
+    bot_token: Option<String>,
 }
 
 impl AppState {
@@ -149,6 +766,12 @@ impl AppState {
         let function_name = std::env::var("AWS_LAMBDA_FUNCTION_NAME")
             .unwrap_or_else(|_| "second-brain-discord-webhook".to_string());
 
+        let fact_actions_function = std::env::var("FACT_ACTIONS_FUNCTION_NAME")
+            .unwrap_or_else(|_| "second-brain-internal_facts".to_string());
+
+        let follow_up_queue_url = std::env::var("FOLLOW_UP_QUEUE_URL").ok();
+        let voice_note_bucket = std::env::var("VOICE_NOTE_BUCKET").ok();
+
         let public_key_hex = std::env::var("DISCORD_PUBLIC_KEY")
             .map_err(|_| "DISCORD_PUBLIC_KEY not set")?;
 
@@ -162,38 +785,96 @@ impl AppState {
         let verifying_key = VerifyingKey::from_bytes(&public_key)
             .map_err(|e| format!("Invalid public key: {}", e))?;
 
+        let db_pool = if std::env::var("DB_HOST").is_ok() {
+            let pool = shared::db::connect(&config)
+                .await
+                .map_err(|e| format!("Failed to connect to database: {}", e))?;
+            Some(pool)
+        } else {
+            None
+        };
+
+        let bot_token = match std::env::var("DISCORD_SECRET_ARN") {
+            Ok(discord_secret_arn) => load_bot_token(&config, &discord_secret_arn).await?,
+            Err(_) => None,
+        };
+
+        // Simple questions are answered from the database while the agents
+        // are down, and every answer is logged for GET /v1/queries/{id}
+        let mut agent_client = AgentClient::new(lambda_client.clone(), agent_function);
+        if let Some(pool) = &db_pool {
+            agent_client = agent_client
+                .with_fallback(DirectFallback::from_env(&config, pool.clone()))
+                .with_query_log(pool.clone());
+        }
+
         Ok(Self {
-            agent_client: AgentClient::new(lambda_client.clone(), agent_function),
+            agent_client,
             lambda_client,
+            sqs_client: aws_sdk_sqs::Client::new(&config),
+            s3_client: aws_sdk_s3::Client::new(&config),
+            transcribe_client: aws_sdk_transcribe::Client::new(&config),
             http_client: reqwest::Client::new(),
             discord_public_key: verifying_key,
             function_name,
+            fact_actions_function,
+            follow_up_queue_url,
+            voice_note_bucket,
+            db_pool,
+            bot_token,
         })
     }
 
-    /// Send follow-up message to Discord via webhook
-    async fn send_follow_up(
+    /// Send a Discord API request, retrying on rate limits (429) and server
+    /// errors with the `Retry-After` delay or exponential backoff
+    ///
+    /// Returns the last response; callers check its status.
+    async fn send_discord(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, Error> {
+        let mut attempt = 1;
+        loop {
+            let response = request
+                .try_clone()
+                .ok_or("Discord request can't be retried")?
+                .send()
+                .await
+                .map_err(|e| format!("Discord request failed: {}", e))?;
+
+            let status = response.status();
+            let retryable = status.as_u16() == 429 || status.is_server_error();
+            if !retryable || attempt >= DISCORD_MAX_ATTEMPTS {
+                return Ok(response);
+            }
+
+            let delay = retry_after(&response).unwrap_or_else(|| {
+                Duration::from_millis(DISCORD_RETRY_BASE_MS * 2u64.pow(attempt - 1))
+            });
+            warn!(
+                %status,
+                attempt,
+                delay_ms = delay.as_millis() as u64,
+                "Discord request throttled, retrying"
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Send follow-up message to Discord via webhook, replacing the original
+    /// interaction message (content, embeds, components)
+    async fn edit_original(
         &self,
         application_id: &str,
         interaction_token: &str,
-        content: &str,
+        payload: &Value,
     ) -> Result<(), Error> {
         let url = format!(
             "https://discord.com/api/v10/webhooks/{}/{}/messages/@original",
             application_id, interaction_token
         );
 
-        let payload = serde_json::json!({
-            "content": content
-        });
-
         let response = self
-            .http_client
-            .patch(&url)
-            .json(&payload)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to send follow-up: {}", e))?;
+            .send_discord(self.http_client.patch(&url).json(payload))
+            .await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -206,9 +887,133 @@ impl AppState {
         Ok(())
     }
 
-    /// Invoke self asynchronously for follow-up processing
+    /// Send an additional follow-up message for an interaction
+    async fn create_follow_up(
+        &self,
+        application_id: &str,
+        interaction_token: &str,
+        payload: &Value,
+        private: bool,
+    ) -> Result<(), Error> {
+        let url = format!(
+            "https://discord.com/api/v10/webhooks/{}/{}",
+            application_id, interaction_token
+        );
+
+        let mut payload = payload.clone();
+        if private {
+            payload["flags"] = serde_json::json!(64);
+        }
+
+        let response = self
+            .send_discord(self.http_client.post(&url).json(&payload))
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Discord webhook failed: {} - {}", status, body);
+            return Err(format!("Discord webhook failed: {}", status).into());
+        }
+
+        Ok(())
+    }
+
+    /// Send a (possibly multi-message) reply: the first message replaces the
+    /// deferred response, the rest are posted as follow-ups in order
+    async fn send_follow_ups(&self, payload: &FollowUpPayload, messages: &[Value]) -> Result<(), Error> {
+        let (first, rest) = match messages.split_first() {
+            Some(split) => split,
+            None => return Ok(()),
+        };
+
+        self.edit_original(&payload.application_id, &payload.interaction_token, first)
+            .await?;
+
+        for message in rest {
+            self.create_follow_up(
+                &payload.application_id,
+                &payload.interaction_token,
+                message,
+                payload.private,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Post a message to a channel as the bot, replying to `reply_to` if set
+    async fn send_channel_message(
+        &self,
+        bot_token: &str,
+        channel_id: &str,
+        content: &str,
+        reply_to: Option<&str>,
+    ) -> Result<(), Error> {
+        let url = format!("https://discord.com/api/v10/channels/{}/messages", channel_id);
+
+        let mut payload = serde_json::json!({ "content": content });
+        if let Some(message_id) = reply_to {
+            payload["message_reference"] = serde_json::json!({ "message_id": message_id });
+        }
+
+        let response = self
+            .send_discord(
+                self.http_client
+                    .post(&url)
+                    .header("Authorization", format!("Bot {}", bot_token))
+                    .json(&payload),
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("Discord message failed: {} - {}", status, body);
+            return Err(format!("Discord message failed: {}", status).into());
+        }
+
+        Ok(())
+    }
+
+    /// Show the typing indicator in a channel while the agent works
+    async fn trigger_typing(&self, bot_token: &str, channel_id: &str) -> Result<(), Error> {
+        let url = format!("https://discord.com/api/v10/channels/{}/typing", channel_id);
+
+        self.http_client
+            .post(&url)
+            .header("Authorization", format!("Bot {}", bot_token))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to trigger typing: {}", e))?
+            .error_for_status()
+            .map_err(|e| format!("Failed to trigger typing: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Queue a follow-up for processing, or invoke self asynchronously when
+    /// no queue is configured
     async fn invoke_follow_up(&self, payload: &FollowUpPayload) -> Result<(), Error> {
-        let payload_json = serde_json::to_vec(payload)?;
+        let payload = sign_internal(payload, "POST", FOLLOW_UP_PATH, &payload.user_id, &[])
+            .await
+            .map_err(|e| format!("Failed to sign follow-up: {}", e))?;
+
+        if let Some(queue_url) = &self.follow_up_queue_url {
+            self.sqs_client
+                .send_message()
+                .queue_url(queue_url)
+                .message_body(serde_json::to_string(&payload)?)
+                .send()
+                .await
+                .map_err(|e| format!("Failed to queue follow-up: {}", e))?;
+
+            info!("Follow-up queued");
+            return Ok(());
+        }
+
+        let payload_json = serde_json::to_vec(&payload)?;
 
         self.lambda_client
             .invoke()
@@ -224,6 +1029,38 @@ impl AppState {
     }
 }
 
+/// Delay requested by a rate-limited Discord response, capped at [`DISCORD_RETRY_MAX`]
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let seconds: f64 = response
+        .headers()
+        .get("retry-after")?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()?;
+
+    (seconds.is_finite() && seconds >= 0.0)
+        .then(|| Duration::from_secs_f64(seconds).min(DISCORD_RETRY_MAX))
+}
+
+async fn load_bot_token(
+    config: &aws_config::SdkConfig,
+    discord_secret_arn: &str,
+) -> Result<Option<String>, Error> {
+    let secrets_client = aws_sdk_secretsmanager::Client::new(config);
+
+    let discord_secret = shared::secrets::get_secret(&secrets_client, discord_secret_arn)
+        .await
+        .map_err(|e| format!("Failed to get Discord secret: {}", e))?;
+
+    let discord_creds: serde_json::Value = serde_json::from_str(&discord_secret)?;
+
+    Ok(discord_creds["bot_token"]
+        .as_str()
+        .filter(|t| !t.is_empty())
+        .map(String::from))
+}
+
 /// Verify Discord signature
 fn verify_signature(
     public_key: &VerifyingKey,
@@ -250,11 +1087,40 @@ fn verify_signature(
 async fn handler(state: Arc<AppState>, event: LambdaEvent<Value>) -> Result<Value, Error> {
     let (payload, _context) = event.into_parts();
 
-    // Check if this is a direct follow-up invocation (not from API Gateway)
-    if let Ok(follow_up) = serde_json::from_value::<FollowUpPayload>(payload.clone()) {
-        if follow_up.follow_up {
-            info!(
-                "Processing follow-up for command '{}' from user {}",
+    // Queued follow-ups (SQS event source)
+    if payload.get("Records").is_some() {
+        let sqs_event: SqsEvent = serde_json::from_value(payload)?;
+        let response = handle_follow_up_queue(state, sqs_event).await;
+        return Ok(serde_json::to_value(response)?);
+    }
+
+    // Gateway events forwarded by the bot relay (direct invocation). They act as
+    // no user until the author's linked account is looked up.
+    if let Ok(relay) = serde_json::from_value::<RelayPayload>(payload.clone()) {
+        if let Err(e) = verify_internal(&payload, "POST", gateway::EVENT_PATH, "", &[]).await {
+            warn!(
+                "Rejected relayed gateway event '{}': {}",
+                relay.gateway_event.t, e
+            );
+            return Ok(serde_json::json!({"status": "unauthorized"}));
+        }
+        return handle_gateway_event(state, relay.gateway_event).await;
+    }
+
+    // Check if this is a direct follow-up invocation (not from API Gateway)
+    if let Ok(follow_up) = serde_json::from_value::<FollowUpPayload>(payload.clone()) {
+        if follow_up.follow_up {
+            if let Err(e) =
+                verify_internal(&payload, "POST", FOLLOW_UP_PATH, &follow_up.user_id, &[]).await
+            {
+                warn!(
+                    "Rejected follow-up for command '{}': {}",
+                    follow_up.command_name, e
+                );
+                return Ok(serde_json::json!({"status": "unauthorized"}));
+            }
+            info!(
+                "Processing follow-up for command '{}' from user {}",
                 follow_up.command_name, follow_up.username
             );
             return handle_follow_up(state, follow_up).await;
@@ -350,16 +1216,64 @@ async fn handler(state: Arc<AppState>, event: LambdaEvent<Value>) -> Result<Valu
             data.name, user.username
         );
 
-        // Extract command arguments
-        let message = data
-            .options
-            .as_ref()
-            .and_then(|opts| {
-                opts.iter()
-                    .find(|o| o.name == "message" || o.name == "question" || o.name == "fact")
-                    .and_then(|o| o.value.as_str().map(String::from))
-            })
-            .unwrap_or_default();
+        // Structured entry is collected in a modal before anything is deferred
+        if data.name == "remember-detailed" {
+            return Ok(serde_json::to_value(ApiGatewayResponse::json(200, &remember_modal())?)?);
+        }
+
+        // Extract command arguments (/list carries its page spec as the message)
+        let message = if data.name == "list" {
+            match ListQuery::from_options(data.options.as_ref()) {
+                Some(query) => query.to_spec(),
+                None => {
+                    return Ok(serde_json::to_value(ApiGatewayResponse::json(
+                        200,
+                        &DiscordResponse {
+                            response_type: RESPONSE_CHANNEL_MESSAGE,
+                            data: Some(ResponseData {
+                                content: "Usage: /list recent, /list by-tag <tag>, or /list by-person <name>".to_string(),
+                                flags: Some(64),
+                            }),
+                        },
+                    )?)?);
+                }
+            }
+        } else if matches!(data.name.as_str(), "edit" | "forget" | "undo") {
+            let option = |name: &str| {
+                data.options
+                    .as_ref()?
+                    .iter()
+                    .find(|o| o.name == name)?
+                    .value
+                    .as_str()
+                    .map(String::from)
+            };
+            let change = FactChange {
+                target: FactTarget {
+                    fact_id: None,
+                    query: option("fact"),
+                },
+                content: option("content"),
+                undo_token: None,
+            };
+            serde_json::to_string(&change)?
+        } else if data.name == "transcribe" {
+            // The attachment is only described in the interaction, so pass it on as JSON
+            match voice_note(data) {
+                Ok(attachment) => serde_json::to_string(attachment)?,
+                Err(reply) => return ephemeral_response(reply),
+            }
+        } else {
+            let option_name = commands::text_option(&data.name);
+            data.options
+                .as_ref()
+                .and_then(|opts| {
+                    opts.iter()
+                        .find(|o| Some(o.name.as_str()) == option_name)
+                        .and_then(|o| o.value.as_str().map(String::from))
+                })
+                .unwrap_or_default()
+        };
 
         // Get application ID and token for follow-up
         let application_id = interaction
@@ -382,6 +1296,13 @@ async fn handler(state: Arc<AppState>, event: LambdaEvent<Value>) -> Result<Valu
             )?)?);
         }
 
+        // Visibility is fixed by the deferred response: follow-ups edit it, so they land
+        // in the channel or thread the command was used in, publicly unless `private`
+        // was set (/list and /link are always private)
+        let private = data.name == "list"
+            || data.name == "link"
+            || is_private(&data.name, data.options.as_deref());
+
         // Create follow-up payload and invoke asynchronously
         let follow_up_payload = FollowUpPayload {
             follow_up: true,
@@ -391,6 +1312,12 @@ async fn handler(state: Arc<AppState>, event: LambdaEvent<Value>) -> Result<Valu
             message,
             user_id: user.id,
             username: user.username,
+            private,
+            thread_id: interaction
+                .channel
+                .as_ref()
+                .filter(|channel| channel.is_thread())
+                .map(|channel| channel.id.clone()),
         };
 
         if let Err(e) = state.invoke_follow_up(&follow_up_payload).await {
@@ -408,15 +1335,34 @@ async fn handler(state: Arc<AppState>, event: LambdaEvent<Value>) -> Result<Valu
         }
 
         // Return deferred response immediately
+        let response_data = if private {
+            Some(ResponseData {
+                content: String::new(),
+                flags: Some(64),
+            })
+        } else {
+            None
+        };
+
         return Ok(serde_json::to_value(ApiGatewayResponse::json(
             200,
             &DiscordResponse {
                 response_type: RESPONSE_DEFERRED_CHANNEL_MESSAGE,
-                data: None,
+                data: response_data,
             },
         )?)?);
     }
 
+    // Handle button clicks on messages we sent
+    if interaction.interaction_type == INTERACTION_MESSAGE_COMPONENT {
+        return handle_component(&state, &interaction).await;
+    }
+
+    // Handle modal submissions
+    if interaction.interaction_type == INTERACTION_MODAL_SUBMIT {
+        return handle_modal_submit(&state, &interaction).await;
+    }
+
     // Unknown interaction type
     Ok(serde_json::to_value(ApiGatewayResponse::new(
         400,
@@ -425,65 +1371,1187 @@ async fn handler(state: Arc<AppState>, event: LambdaEvent<Value>) -> Result<Valu
     ))?)
 }
 
+/// Whether the caller asked for a private (ephemeral) response.
+fn is_private(command: &str, options: Option<&[CommandOption]>) -> bool {
+    commands::supports_private(command)
+        && options
+            .unwrap_or_default()
+            .iter()
+            .any(|o| o.name == commands::PRIVATE_OPTION.name && o.value.as_bool() == Some(true))
+}
+
+/// Dispatch a message component (button) interaction.
+async fn handle_component(state: &AppState, interaction: &DiscordInteraction) -> Result<Value, Error> {
+    let custom_id = interaction
+        .data
+        .as_ref()
+        .and_then(|d| d.custom_id.clone())
+        .unwrap_or_default();
+
+    let user = interaction
+        .member
+        .as_ref()
+        .map(|m| &m.user)
+        .or(interaction.user.as_ref())
+        .cloned();
+
+    let (action, user) = match (ComponentAction::parse(&custom_id), user) {
+        (Some(a), Some(u)) => (a, u),
+        _ => {
+            warn!("Unknown component custom_id: {}", custom_id);
+            return ephemeral_response("That button is no longer supported.");
+        }
+    };
+
+    info!("Processing component '{}' from user {}", custom_id, user.username);
+
+    let message_content = interaction
+        .message
+        .as_ref()
+        .map(InteractionMessage::text)
+        .unwrap_or_default();
+
+    let (command_name, message, response_type) = match action {
+        ComponentAction::Snooze { reminder_id, minutes } => {
+            let pool = match &state.db_pool {
+                Some(pool) => pool,
+                None => return ephemeral_response("Snoozing isn't available right now."),
+            };
+
+            return match snooze_reminder(pool, &user.id, reminder_id, minutes).await {
+                Ok(Some(until)) => ephemeral_response(&format!(
+                    "Snoozed until <t:{}:t>.",
+                    until.timestamp()
+                )),
+                Ok(None) => ephemeral_response("That reminder can't be snoozed anymore."),
+                Err(e) => {
+                    error!("Failed to snooze reminder: {}", e);
+                    ephemeral_response("Sorry, I couldn't snooze that reminder. Please try again.")
+                }
+            };
+        }
+        // Pagination edits the message in place
+        ComponentAction::List(query) => ("list", query.to_spec(), RESPONSE_DEFERRED_UPDATE_MESSAGE),
+        ComponentAction::Save | ComponentAction::Forget if message_content.is_empty() => {
+            return ephemeral_response("There's nothing in that message to act on.");
+        }
+        ComponentAction::Save => ("save", message_content, RESPONSE_DEFERRED_CHANNEL_MESSAGE),
+        ComponentAction::Forget => ("forget", message_content, RESPONSE_DEFERRED_CHANNEL_MESSAGE),
+        ComponentAction::ForgetFact(fact_id) => {
+            let change = FactChange {
+                target: FactTarget {
+                    fact_id: Some(fact_id),
+                    query: None,
+                },
+                content: None,
+                undo_token: None,
+            };
+            (
+                "forget",
+                serde_json::to_string(&change)?,
+                RESPONSE_DEFERRED_CHANNEL_MESSAGE,
+            )
+        }
+        ComponentAction::Undo(token) => {
+            let change = FactChange {
+                undo_token: Some(token),
+                ..Default::default()
+            };
+            (
+                "undo",
+                serde_json::to_string(&change)?,
+                RESPONSE_DEFERRED_CHANNEL_MESSAGE,
+            )
+        }
+        // The new wording is confirmed in a modal before anything changes
+        ComponentAction::EditFact(fact_id) => {
+            let content = interaction
+                .message
+                .as_ref()
+                .and_then(|m| m.field(NEW_WORDING_FIELD))
+                .unwrap_or_default();
+            return Ok(serde_json::to_value(ApiGatewayResponse::json(
+                200,
+                &edit_modal(fact_id, content),
+            )?)?);
+        }
+    };
+
+    let application_id = interaction
+        .application_id
+        .clone()
+        .unwrap_or_else(|| std::env::var("DISCORD_APPLICATION_ID").unwrap_or_default());
+
+    let follow_up_payload = FollowUpPayload {
+        follow_up: true,
+        application_id,
+        interaction_token: interaction.token.clone().unwrap_or_default(),
+        command_name: command_name.to_string(),
+        message,
+        user_id: user.id,
+        username: user.username,
+        private: response_type == RESPONSE_DEFERRED_CHANNEL_MESSAGE,
+        thread_id: None,
+    };
+
+    if let Err(e) = state.invoke_follow_up(&follow_up_payload).await {
+        error!("Failed to invoke follow-up: {}", e);
+        return ephemeral_response("Sorry, something went wrong. Please try again.");
+    }
+
+    // Save/forget results are shown only to the clicker
+    let data = (response_type == RESPONSE_DEFERRED_CHANNEL_MESSAGE).then(|| ResponseData {
+        content: String::new(),
+        flags: Some(64),
+    });
+
+    Ok(serde_json::to_value(ApiGatewayResponse::json(
+        200,
+        &DiscordResponse {
+            response_type,
+            data,
+        },
+    )?)?)
+}
+
+/// Handle a modal submission (`/remember-detailed` and edit confirmations).
+async fn handle_modal_submit(state: &AppState, interaction: &DiscordInteraction) -> Result<Value, Error> {
+    let data = interaction.data.as_ref();
+    let custom_id = data
+        .and_then(|d| d.custom_id.as_deref())
+        .unwrap_or_default();
+    if let Some(fact_id) = custom_id.strip_prefix(EDIT_MODAL_PREFIX) {
+        return match Uuid::parse_str(fact_id) {
+            Ok(fact_id) => handle_edit_modal(state, interaction, fact_id).await,
+            Err(_) => ephemeral_response("That form is no longer supported."),
+        };
+    }
+    if custom_id != REMEMBER_MODAL_ID {
+        warn!("Unknown modal submission");
+        return ephemeral_response("That form is no longer supported.");
+    }
+
+    let user = match interaction
+        .member
+        .as_ref()
+        .map(|m| &m.user)
+        .or(interaction.user.as_ref())
+        .cloned()
+    {
+        Some(user) => user,
+        None => return ephemeral_response("Couldn't identify you. Please try again."),
+    };
+
+    let rows = data.and_then(|d| d.components.as_deref()).unwrap_or_default();
+    let request = match parse_remember_modal(rows) {
+        Ok(request) => request,
+        Err(message) => return ephemeral_response(&message),
+    };
+
+    info!("Processing detailed remember from user {}", user.username);
+
+    let application_id = interaction
+        .application_id
+        .clone()
+        .unwrap_or_else(|| std::env::var("DISCORD_APPLICATION_ID").unwrap_or_default());
+
+    // The structured request travels to the follow-up as JSON
+    let follow_up_payload = FollowUpPayload {
+        follow_up: true,
+        application_id,
+        interaction_token: interaction.token.clone().unwrap_or_default(),
+        command_name: "remember-detailed".to_string(),
+        message: serde_json::to_string(&request)?,
+        user_id: user.id,
+        username: user.username,
+        private: false,
+        thread_id: None,
+    };
+
+    if let Err(e) = state.invoke_follow_up(&follow_up_payload).await {
+        error!("Failed to invoke follow-up: {}", e);
+        return ephemeral_response("Sorry, something went wrong. Please try again.");
+    }
+
+    Ok(serde_json::to_value(ApiGatewayResponse::json(
+        200,
+        &DiscordResponse {
+            response_type: RESPONSE_DEFERRED_CHANNEL_MESSAGE,
+            data: None,
+        },
+    )?)?)
+}
+
+/// Handle the modal confirming the new wording of a picked fact.
+async fn handle_edit_modal(
+    state: &AppState,
+    interaction: &DiscordInteraction,
+    fact_id: Uuid,
+) -> Result<Value, Error> {
+    let user = match interaction
+        .member
+        .as_ref()
+        .map(|m| &m.user)
+        .or(interaction.user.as_ref())
+        .cloned()
+    {
+        Some(user) => user,
+        None => return ephemeral_response("Couldn't identify you. Please try again."),
+    };
+
+    let content = interaction
+        .data
+        .as_ref()
+        .and_then(|d| d.components.as_deref())
+        .unwrap_or_default()
+        .iter()
+        .flat_map(|row| row.components.iter())
+        .find(|field| field.custom_id == "content")
+        .map(|field| field.value.trim().to_string())
+        .filter(|content| !content.is_empty());
+
+    let Some(content) = content else {
+        return ephemeral_response("The new wording can't be empty.");
+    };
+
+    let application_id = interaction
+        .application_id
+        .clone()
+        .unwrap_or_else(|| std::env::var("DISCORD_APPLICATION_ID").unwrap_or_default());
+
+    let change = FactChange {
+        target: FactTarget {
+            fact_id: Some(fact_id),
+            query: None,
+        },
+        content: Some(content),
+        undo_token: None,
+    };
+
+    // Like the Forget button, the result is shown only to whoever picked
+    let follow_up_payload = FollowUpPayload {
+        follow_up: true,
+        application_id,
+        interaction_token: interaction.token.clone().unwrap_or_default(),
+        command_name: "edit".to_string(),
+        message: serde_json::to_string(&change)?,
+        user_id: user.id,
+        username: user.username,
+        private: true,
+        thread_id: None,
+    };
+
+    if let Err(e) = state.invoke_follow_up(&follow_up_payload).await {
+        error!("Failed to invoke follow-up: {}", e);
+        return ephemeral_response("Sorry, something went wrong. Please try again.");
+    }
+
+    Ok(serde_json::to_value(ApiGatewayResponse::json(
+        200,
+        &DiscordResponse {
+            response_type: RESPONSE_DEFERRED_CHANNEL_MESSAGE,
+            data: Some(ResponseData {
+                content: String::new(),
+                flags: Some(64),
+            }),
+        },
+    )?)?)
+}
+
+/// Snooze a reminder owned by the Discord user, returning the new snooze time.
+///
+/// Triggered one-off reminders are re-armed so they fire again after the snooze.
+async fn snooze_reminder(
+    pool: &PgPool,
+    discord_id: &str,
+    reminder_id: Uuid,
+    minutes: i32,
+) -> Result<Option<chrono::DateTime<chrono::Utc>>, Error> {
+    let snoozed_until = sqlx::query_scalar(
+        r#"
+        UPDATE reminders r
+        SET status = 'active',
+            snooze_until = NOW() + make_interval(mins => $3),
+            next_trigger_at = LEAST(
+                COALESCE(r.next_trigger_at, 'infinity'::timestamptz),
+                NOW() + make_interval(mins => $3)
+            ),
+            updated_at = NOW()
+        FROM users u
+        WHERE r.id = $1
+        AND r.user_id = u.id
+        AND u.discord_id = $2
+        AND r.status IN ('active', 'triggered', 'snoozed')
+        RETURNING r.snooze_until
+        "#,
+    )
+    .bind(reminder_id)
+    .bind(discord_id)
+    .bind(minutes)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to snooze reminder: {}", e))?;
+
+    Ok(snoozed_until)
+}
+
+/// Resolve a linked Discord account to its registered user, if any.
+async fn resolve_discord_user(pool: &PgPool, discord_id: &str) -> Result<Option<AuthorizedUser>, Error> {
+    let cognito_sub: Option<String> =
+        sqlx::query_scalar("SELECT cognito_sub FROM users WHERE discord_id = $1")
+            .bind(discord_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("Failed to lookup user: {}", e))?;
+
+    let cognito_sub = match cognito_sub {
+        Some(sub) => sub,
+        None => return Ok(None),
+    };
+
+    match AuthorizedUser::resolve(
+        AuthenticatedUser {
+            user_id: cognito_sub,
+            email: None,
+            family_ids: Vec::new(),
+        },
+        pool,
+    )
+    .await
+    {
+        Ok(user) => Ok(Some(user)),
+        Err(shared::Error::Auth(_)) => Ok(None),
+        Err(e) => Err(format!("Failed to lookup user: {}", e).into()),
+    }
+}
+
+/// Resolve the Discord user to their registered account, or the reply explaining why not.
+async fn require_linked_user(state: &AppState, discord_id: &str) -> Result<AuthorizedUser, &'static str> {
+    let pool = state
+        .db_pool
+        .as_ref()
+        .ok_or("Second Brain isn't available right now. Please try again later.")?;
+
+    match resolve_discord_user(pool, discord_id).await {
+        Ok(Some(user)) => Ok(user),
+        Ok(None) => Err(UNLINKED_MESSAGE),
+        Err(e) => {
+            error!("Failed to resolve Discord user: {}", e);
+            Err("Sorry, something went wrong. Please try again.")
+        }
+    }
+}
+
+/// Redeem a `/link` code for the Discord user, returning the reply.
+async fn link_account(state: &AppState, payload: &FollowUpPayload) -> &'static str {
+    let pool = match &state.db_pool {
+        Some(pool) => pool,
+        None => return "Linking isn't available right now.",
+    };
+
+    match redeem_link_code(pool, &payload.user_id, &payload.message).await {
+        Ok(Some(user_id)) => {
+            info!(%user_id, "Linked Discord user {}", payload.username);
+            "Linked! You can now use Second Brain from Discord."
+        }
+        Ok(None) => "That code is invalid or has expired. Get a new one from Settings → Discord in the app.",
+        Err(e) => {
+            error!("Failed to link Discord account: {}", e);
+            "Sorry, I couldn't link your account. Please try again."
+        }
+    }
+}
+
+/// Ask the agent in the user's conversation at `external_key` (a DM channel or
+/// thread), sending its earlier turns and storing the exchange. Without a
+/// database the message is sent on its own.
+async fn invoke_in_conversation(
+    state: &AppState,
+    user: &AuthorizedUser,
+    external_key: &str,
+    mut request: AgentRequest,
+) -> shared::Result<AgentResponse> {
+    let conversation = match &state.db_pool {
+        Some(pool) => match conversations::for_external_key(
+            pool,
+            user.user_id,
+            "discord",
+            external_key,
+            &request.message,
+        )
+        .await
+        {
+            Ok(id) => Some((pool, id)),
+            Err(e) => {
+                warn!("Answering without conversation context: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+
+    let Some((pool, conversation_id)) = conversation else {
+        return state.agent_client.invoke(request).await;
+    };
+
+    request.conversation_id = Some(conversation_id.to_string());
+    request.conversation_history = conversations::history(pool, conversation_id)
+        .await
+        .unwrap_or_else(|e| {
+            warn!("Failed to load conversation history: {}", e);
+            Vec::new()
+        });
+
+    let question = request.message.clone();
+    let started = Instant::now();
+    let response = state.agent_client.invoke(request).await?;
+
+    let latency_ms = started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+    if let Err(e) = conversations::record_exchange(
+        pool,
+        conversation_id,
+        &question,
+        &response.response,
+        response.metadata.as_ref().and_then(|m| m.model_id.as_deref()),
+        Some(latency_ms),
+    )
+    .await
+    {
+        warn!("Failed to record conversation: {}", e);
+    }
+
+    Ok(response)
+}
+
+/// Record a diagnostics sample if the linked user has debug mode on (best effort).
+async fn capture_sample(
+    pool: &PgPool,
+    user: &AuthorizedUser,
+    operation: &str,
+    request: &str,
+    result: &shared::Result<AgentResponse>,
+    started: Instant,
+) {
+    let error = result.as_ref().err().map(|e| e.to_string());
+    let sample = Sample {
+        user_id: user.user_id,
+        source: "discord",
+        operation,
+        request,
+        response: result.as_ref().ok().map(|r| r.response.as_str()),
+        error: error.as_deref(),
+        trace: result
+            .as_ref()
+            .map(agent_trace)
+            .unwrap_or_else(|_| serde_json::json!({})),
+        latency_ms: Some(started.elapsed().as_millis().min(i32::MAX as u128) as i32),
+    };
+
+    if let Err(e) = record_sample(pool, sample).await {
+        warn!("Failed to record diagnostics sample: {}", e);
+    }
+}
+
+/// Today's briefing for a linked Discord user: the stored copy when the
+/// dispatcher already generated it, otherwise generated (and stored) live.
+async fn fetch_briefing(state: &AppState, pool: &PgPool, user: &AuthorizedUser) -> Result<String, Error> {
+    if let Some(briefing) = todays_briefing(pool, user.user_id, "morning").await? {
+        return Ok(briefing.content);
+    }
+
+    let briefing = generate_briefing(
+        pool,
+        &state.agent_client,
+        user.user_id,
+        &user.family_ids,
+        "morning",
+        "discord",
+    )
+    .await?;
+
+    Ok(briefing.content)
+}
+
+/// Fetch one page of facts visible to a Discord user.
+///
+/// Returns `None` if the Discord account isn't linked to a Second Brain user.
+async fn fetch_list_page(
+    pool: &PgPool,
+    discord_id: &str,
+    query: &ListQuery,
+) -> Result<Option<Vec<ListedFact>>, Error> {
+    let user = match resolve_discord_user(pool, discord_id).await? {
+        Some(user) => user,
+        None => return Ok(None),
+    };
+
+    let filter_sql = match &query.filter {
+        ListFilter::Recent => "",
+        ListFilter::ByTag(_) => {
+            r#"
+            AND EXISTS (
+                SELECT 1 FROM fact_tags ft
+                JOIN tags t ON t.id = ft.tag_id
+                WHERE ft.fact_id = f.id
+                AND (t.path = LOWER($5) OR t.path LIKE LOWER($5) || '/%' OR t.name ILIKE $5)
+            )
+            "#
+        }
+        ListFilter::ByPerson(_) => {
+            r#"
+            AND EXISTS (
+                SELECT 1 FROM entities e
+                WHERE e.entity_type = 'person'
+                AND (e.normalized_name = LOWER(TRIM($5)) OR LOWER(TRIM($5)) = ANY(SELECT LOWER(a) FROM UNNEST(e.aliases) a))
+                AND (
+                    f.about_entity_id = e.id
+                    OR EXISTS (SELECT 1 FROM entity_mentions em WHERE em.fact_id = f.id AND em.entity_id = e.id)
+                )
+            )
+            "#
+        }
+    };
+
+    let sql = format!(
+        r#"
+        SELECT f.content, f.recorded_at
+        FROM facts f
+        WHERE (
+            (f.owner_type = 'user' AND f.owner_id = $1)
+            OR (f.owner_type = 'family' AND f.owner_id = ANY($2) AND {})
+        )
+        AND f.superseded_by IS NULL
+        {}
+        ORDER BY f.recorded_at DESC, f.id DESC
+        LIMIT $3 OFFSET $4
+        "#,
+        space_clause("f", 1),
+        filter_sql
+    );
+
+    let mut q = sqlx::query_as::<_, ListedFact>(&sql)
+        .bind(user.user_id)
+        .bind(&user.family_ids)
+        .bind(LIST_PAGE_SIZE + 1)
+        .bind(query.page * LIST_PAGE_SIZE);
+
+    match &query.filter {
+        ListFilter::Recent => {}
+        ListFilter::ByTag(arg) | ListFilter::ByPerson(arg) => q = q.bind(arg),
+    }
+
+    let facts = q
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch facts: {}", e))?;
+
+    Ok(Some(facts))
+}
+
+/// Render a `/list` page as an embed with prev/next buttons.
+fn render_list_page(query: &ListQuery, mut facts: Vec<ListedFact>) -> Value {
+    let has_more = facts.len() as i64 > LIST_PAGE_SIZE;
+    facts.truncate(LIST_PAGE_SIZE as usize);
+
+    let description = if facts.is_empty() {
+        "No facts found.".to_string()
+    } else {
+        facts
+            .iter()
+            .enumerate()
+            .map(|(i, fact)| {
+                let mut excerpt: String = fact.content.chars().take(LIST_EXCERPT_CHARS).collect();
+                if fact.content.chars().count() > LIST_EXCERPT_CHARS {
+                    excerpt.push('…');
+                }
+                format!(
+                    "**{}.** {}\n*{}*",
+                    query.page * LIST_PAGE_SIZE + i as i64 + 1,
+                    excerpt,
+                    fact.recorded_at.format("%b %-d, %Y")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    };
+
+    serde_json::json!({
+        "content": "",
+        "embeds": [{
+            "title": query.title(),
+            "description": description,
+            "color": 0x5865F2,
+            "footer": { "text": format!("Page {}", query.page + 1) },
+        }],
+        "components": [{
+            "type": 1,
+            "components": [
+                {
+                    "type": 2,
+                    "style": 2,
+                    "label": "◀ Prev",
+                    "custom_id": query.with_page((query.page - 1).max(0)).to_spec(),
+                    "disabled": query.page == 0,
+                },
+                {
+                    "type": 2,
+                    "style": 2,
+                    "label": "Next ▶",
+                    "custom_id": query.with_page(query.page + 1).to_spec(),
+                    "disabled": !has_more,
+                },
+            ],
+        }],
+    })
+}
+
+/// Handle `/list` and its pagination buttons
+async fn handle_list(state: &AppState, payload: &FollowUpPayload) -> Result<Value, Error> {
+    let query = ListQuery::parse(&payload.message);
+
+    let message = match (&state.db_pool, query) {
+        (Some(pool), Some(query)) => match fetch_list_page(pool, &payload.user_id, &query).await {
+            Ok(Some(facts)) => render_list_page(&query, facts),
+            Ok(None) => serde_json::json!({ "content": UNLINKED_MESSAGE }),
+            Err(e) => {
+                error!("Failed to list facts: {}", e);
+                serde_json::json!({ "content": "Sorry, I couldn't list your facts. Please try again." })
+            }
+        },
+        (None, _) => serde_json::json!({ "content": "Listing facts isn't available right now." }),
+        (_, None) => serde_json::json!({ "content": "Invalid list request." }),
+    };
+
+    Ok(message)
+}
+
+/// Handle a relayed gateway event: answer direct messages through the agent.
+///
+/// The agent's router decides whether a message is a question or something to
+/// remember; each DM channel is kept as one conversation.
+async fn handle_gateway_event(state: Arc<AppState>, event: GatewayEvent) -> Result<Value, Error> {
+    // Only direct messages from people (never our own replies)
+    let Some(message) = gateway::direct_message(event)? else {
+        return Ok(serde_json::json!({"status": "ignored"}));
+    };
+
+    let bot_token = match &state.bot_token {
+        Some(token) => token,
+        None => {
+            error!("Received direct message but no bot token is configured");
+            return Ok(serde_json::json!({"status": "error"}));
+        }
+    };
+
+    info!("Processing direct message from user {}", message.author.username);
+
+    let user = match require_linked_user(&state, &message.author.id).await {
+        Ok(user) => user,
+        Err(reply) => {
+            if let Err(e) = state
+                .send_channel_message(bot_token, &message.channel_id, reply, Some(&message.id))
+                .await
+            {
+                error!("Failed to reply to direct message: {}", e);
+            }
+            return Ok(serde_json::json!({"status": "ok"}));
+        }
+    };
+
+    if let Err(e) = state.trigger_typing(bot_token, &message.channel_id).await {
+        warn!("Failed to trigger typing: {}", e);
+    }
+
+    let started = Instant::now();
+    let result = invoke_in_conversation(
+        &state,
+        &user,
+        &format!("dm-{}", message.channel_id),
+        AgentRequest {
+            message: message.content.clone(),
+            user_id: user.user_id.to_string(),
+            family_ids: user.family_ids.iter().map(Uuid::to_string).collect(),
+            device_id: None,
+            conversation_id: Some(format!("discord-dm-{}", message.channel_id)),
+            conversation_history: Vec::new(),
+            intent: None,
+            source: "discord".to_string(),
+            modality: None,
+            metadata: None,
+        },
+    )
+    .await;
+
+    if let Some(pool) = &state.db_pool {
+        capture_sample(pool, &user, "dm", &message.content, &result, started).await;
+    }
+
+    let reply = match result {
+        Ok(resp) => resp.response,
+        Err(e) => {
+            error!("Agent error: {}", e);
+            "Sorry, I couldn't process that message. Please try again.".to_string()
+        }
+    };
+
+    // Long replies are split across messages; only the first quotes the question
+    let formatted = format::to_discord_markdown(&reply);
+    for (i, part) in split(&formatted, MESSAGE_MAX_CHARS).iter().enumerate() {
+        let reply_to = (i == 0).then_some(message.id.as_str());
+        if let Err(e) = state
+            .send_channel_message(bot_token, &message.channel_id, part, reply_to)
+            .await
+        {
+            error!("Failed to reply to direct message: {}", e);
+            break;
+        }
+    }
+
+    Ok(serde_json::json!({"status": "ok"}))
+}
+
+/// Edit, forget or undo a change to a fact through the internal facts
+/// Lambda, returning the reply messages.
+async fn change_fact(
+    state: &AppState,
+    user: &AuthorizedUser,
+    command: &str,
+    change: FactChange,
+) -> Vec<Value> {
+    let (path, verb) = match command {
+        "edit" => (EDIT_PATH, "edit that fact"),
+        "forget" => (DELETE_PATH, "forget that fact"),
+        _ => (UNDO_PATH, "undo that"),
+    };
+    let request = FactActionRequest {
+        path: path.to_string(),
+        user_id: user.user_id,
+        family_ids: user.family_ids.clone(),
+        target: change.target,
+        content: change.content,
+        undo_token: change.undo_token,
+    };
+
+    let response =
+        fact_actions::invoke(&state.lambda_client, &state.fact_actions_function, &request)
+            .await
+            .unwrap_or_else(|e| {
+                error!("Fact action failed: {}", e);
+                FactActionResponse::Error {
+                    message: "Please try again.".to_string(),
+                }
+            });
+
+    let text = |content: String| vec![serde_json::json!({ "content": content })];
+    let embeds = |title: &str, description: &str| -> Vec<Value> {
+        answer_embeds(title, description, &[], None)
+            .into_iter()
+            .map(|embed| serde_json::json!({ "embeds": [embed] }))
+            .collect()
+    };
+    // Changes can be undone for a few minutes, from the button or `/undo`
+    let undoable = |mut messages: Vec<Value>, undo: &UndoToken| {
+        if let Some(last) = messages.last_mut() {
+            last["content"] = Value::String(format!(
+                "Changed your mind? Undo until <t:{}:t>.",
+                undo.expires_at.timestamp()
+            ));
+            last["components"] = button_row("Undo", &format!("undo:{}", undo.token));
+        }
+        messages
+    };
+
+    match response {
+        FactActionResponse::Edited {
+            fact,
+            previous_content,
+            undo,
+        } => undoable(
+            embeds(
+                "Updated",
+                &format!("~~{}~~\n{}", previous_content, fact.content),
+            ),
+            &undo,
+        ),
+        FactActionResponse::Deleted {
+            fact,
+            restore_within_days,
+            undo,
+        } => undoable(
+            embeds(
+                "Forgotten",
+                &format!(
+                    "{}\n\nIt stays in the trash for {} days in case you change your mind.",
+                    fact.content, restore_within_days
+                ),
+            ),
+            &undo,
+        ),
+        FactActionResponse::Undone { fact } => embeds("Undone", &fact.content),
+        FactActionResponse::Ambiguous { candidates } => {
+            vec![render_candidates(
+                command == "edit",
+                request.content.as_deref(),
+                &candidates,
+            )]
+        }
+        FactActionResponse::NotFound if command == "undo" => {
+            text("There's nothing to undo.".to_string())
+        }
+        FactActionResponse::NotFound => text(format!(
+            "I couldn't find a fact like that to {}. Try other words, or browse with `/list`.",
+            command
+        )),
+        FactActionResponse::Error { message } => {
+            text(format!("Sorry, I couldn't {}. {}", verb, message))
+        }
+    }
+}
+
+/// Facts matching an `/edit` or `/forget` description, with a button to
+/// pick each. Edits carry their new wording in a field for the modal.
+fn render_candidates(editing: bool, content: Option<&str>, candidates: &[FactCandidate]) -> Value {
+    let description = candidates
+        .iter()
+        .enumerate()
+        .map(|(i, fact)| {
+            let about = fact
+                .entity_name
+                .as_deref()
+                .map(|name| format!("about {}, ", name))
+                .unwrap_or_default();
+            format!(
+                "**{}.** {}\n*{}saved {}*",
+                i + 1,
+                truncate(&fact.content, LIST_EXCERPT_CHARS),
+                about,
+                fact.recorded_at.format("%b %-d, %Y")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let mut embed = serde_json::json!({
+        "title": format!("Which fact should I {}?", if editing { "edit" } else { "forget" }),
+        "description": description,
+        "color": 0x5865F2,
+    });
+    if let (true, Some(content)) = (editing, content) {
+        embed["fields"] = serde_json::json!([{
+            "name": NEW_WORDING_FIELD,
+            "value": truncate(content, EMBED_FIELD_MAX_CHARS),
+        }]);
+    }
+
+    let action = if editing { "edit" } else { "forget" };
+    let buttons: Vec<Value> = candidates
+        .iter()
+        .enumerate()
+        .map(|(i, fact)| {
+            serde_json::json!({
+                "type": 2,
+                "style": if editing { 1 } else { 4 },
+                "label": (i + 1).to_string(),
+                "custom_id": format!("{}:{}", action, fact.id),
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "content": "",
+        "embeds": [embed],
+        "components": [{ "type": 1, "components": buttons }],
+    })
+}
+
+/// Title of the embed an agent response is shown in
+fn embed_title(command_name: &str, message: &str) -> String {
+    match command_name {
+        // Echo the question so public answers make sense on their own
+        "ask" | "query" => message.to_string(),
+        "briefing" => "Your briefing".to_string(),
+        "remember" | "remember-detailed" | "save" => "Saved".to_string(),
+        "edit" => "Updated".to_string(),
+        "forget" => "Forgotten".to_string(),
+        "remind" => "Reminder".to_string(),
+        "transcribe" => "Voice note saved".to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Transcribe a `/transcribe` voice note with Amazon Transcribe.
+///
+/// The audio is staged in `VOICE_NOTE_BUCKET` (which expires it) and the job is
+/// polled until done; voice notes are short enough to finish within the follow-up.
+async fn transcribe_voice_note(state: &AppState, attachment: &Attachment) -> Result<String, Error> {
+    let bucket = state
+        .voice_note_bucket
+        .as_deref()
+        .ok_or("VOICE_NOTE_BUCKET not set")?;
+    let format = media_format(&attachment.filename).ok_or("Unsupported audio format")?;
+
+    let audio = state
+        .http_client
+        .get(&attachment.url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download voice note: {}", e))?
+        .error_for_status()
+        .map_err(|e| format!("Failed to download voice note: {}", e))?
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to download voice note: {}", e))?;
+
+    let id = Uuid::new_v4();
+    let audio_key = format!("{}{}/{}", VOICE_NOTE_PREFIX, id, attachment.filename);
+    let transcript_key = format!("{}{}/transcript.json", VOICE_NOTE_PREFIX, id);
+
+    state
+        .s3_client
+        .put_object()
+        .bucket(bucket)
+        .key(&audio_key)
+        .body(audio.into())
+        .send()
+        .await
+        .map_err(|e| format!("Failed to stage voice note: {}", e))?;
+
+    let job_name = format!("second-brain-discord-{}", id);
+    state
+        .transcribe_client
+        .start_transcription_job()
+        .transcription_job_name(&job_name)
+        .media(Media::builder().media_file_uri(format!("s3://{}/{}", bucket, audio_key)).build())
+        .media_format(format)
+        .identify_language(true)
+        .output_bucket_name(bucket)
+        .output_key(&transcript_key)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to start transcription: {}", e))?;
+
+    info!(job_name, size = attachment.size, "Started voice note transcription");
+
+    let started = Instant::now();
+    loop {
+        tokio::time::sleep(TRANSCRIBE_POLL_INTERVAL).await;
+
+        let job = state
+            .transcribe_client
+            .get_transcription_job()
+            .transcription_job_name(&job_name)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to check transcription: {}", e))?;
+        let job = job.transcription_job();
+
+        match job.and_then(|j| j.transcription_job_status()) {
+            Some(TranscriptionJobStatus::Completed) => break,
+            Some(TranscriptionJobStatus::Failed) => {
+                let reason = job.and_then(|j| j.failure_reason()).unwrap_or("unknown reason");
+                return Err(format!("Transcription failed: {}", reason).into());
+            }
+            _ if started.elapsed() > TRANSCRIBE_TIMEOUT => {
+                return Err("Transcription timed out".into());
+            }
+            _ => {}
+        }
+    }
+
+    let output = state
+        .s3_client
+        .get_object()
+        .bucket(bucket)
+        .key(&transcript_key)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to read transcript: {}", e))?;
+    let bytes = output
+        .body
+        .collect()
+        .await
+        .map_err(|e| format!("Failed to read transcript: {}", e))?
+        .into_bytes();
+
+    let output: Value = serde_json::from_slice(&bytes)?;
+    let transcript = output["results"]["transcripts"][0]["transcript"]
+        .as_str()
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+
+    Ok(transcript)
+}
+
 /// Handle follow-up processing (async invocation)
 async fn handle_follow_up(state: Arc<AppState>, payload: FollowUpPayload) -> Result<Value, Error> {
+    if payload.command_name == "list" {
+        let message = handle_list(&state, &payload).await?;
+        state
+            .edit_original(&payload.application_id, &payload.interaction_token, &message)
+            .await?;
+        return Ok(serde_json::json!({"status": "ok"}));
+    }
+
+    if payload.command_name == "link" {
+        let message = serde_json::json!({ "content": link_account(&state, &payload).await });
+        state
+            .edit_original(&payload.application_id, &payload.interaction_token, &message)
+            .await?;
+        return Ok(serde_json::json!({"status": "ok"}));
+    }
+
+    // Everything else acts on the linked Second Brain account
+    let user = match require_linked_user(&state, &payload.user_id).await {
+        Ok(user) => user,
+        Err(reply) => {
+            let message = serde_json::json!({ "content": reply });
+            state
+                .edit_original(&payload.application_id, &payload.interaction_token, &message)
+                .await?;
+            return Ok(serde_json::json!({"status": "ok"}));
+        }
+    };
+    let agent_user_id = user.user_id.to_string();
+    let family_ids: Vec<String> = user.family_ids.iter().map(Uuid::to_string).collect();
+
+    // Structured /edit, /forget and /undo; plain descriptions still go to the agent
+    if let ("edit" | "forget" | "undo", Ok(change)) = (
+        payload.command_name.as_str(),
+        serde_json::from_str::<FactChange>(&payload.message),
+    ) {
+        let messages = change_fact(&state, &user, &payload.command_name, change).await;
+        state.send_follow_ups(&payload, &messages).await?;
+        return Ok(serde_json::json!({"status": "ok"}));
+    }
+
+    let mut failed = false;
+    let mut metadata: Option<AgentMetadata> = None;
     let response_text = match payload.command_name.as_str() {
         "remember" | "save" => {
             match state
                 .agent_client
-                .ingest(&payload.message, &payload.user_id, vec![], "discord")
+                .ingest(&payload.message, &agent_user_id, family_ids.clone(), "discord")
                 .await
             {
                 Ok(resp) => resp.response,
                 Err(e) => {
                     error!("Agent error: {}", e);
+                    failed = true;
                     "Sorry, I couldn't save that. Please try again.".to_string()
                 }
             }
         }
-        "ask" | "query" => {
+        "remember-detailed" => {
+            let request: IngestRequest = serde_json::from_str(&payload.message)?;
             match state
                 .agent_client
-                .query(&payload.message, &payload.user_id, vec![], None, "discord")
+                .ingest(&request.agent_message(), &agent_user_id, family_ids.clone(), "discord")
                 .await
             {
                 Ok(resp) => resp.response,
                 Err(e) => {
                     error!("Agent error: {}", e);
-                    "Sorry, I couldn't process that query. Please try again.".to_string()
+                    failed = true;
+                    "Sorry, I couldn't save that. Please try again.".to_string()
                 }
             }
         }
-        "briefing" => {
-            match state
-                .agent_client
-                .query(
-                    "Give me my morning briefing",
-                    &payload.user_id,
-                    vec![],
-                    None,
-                    "discord",
-                )
-                .await
-            {
-                Ok(resp) => resp.response,
+        "ask" | "query" => {
+            let started = Instant::now();
+            let result = match &payload.thread_id {
+                Some(thread_id) => {
+                    invoke_in_conversation(
+                        &state,
+                        &user,
+                        &format!("thread-{}", thread_id),
+                        AgentRequest {
+                            message: payload.message.clone(),
+                            user_id: agent_user_id.clone(),
+                            family_ids: family_ids.clone(),
+                            device_id: None,
+                            conversation_id: None,
+                            conversation_history: Vec::new(),
+                            intent: Some("query".to_string()),
+                            source: "discord".to_string(),
+                            modality: None,
+                            metadata: None,
+                        },
+                    )
+                    .await
+                }
+                None => {
+                    state
+                        .agent_client
+                        .query(&payload.message, &agent_user_id, family_ids.clone(), None, "discord")
+                        .await
+                }
+            };
+
+            if let Some(pool) = &state.db_pool {
+                capture_sample(pool, &user, "query", &payload.message, &result, started).await;
+            }
+
+            match result {
+                Ok(resp) => {
+                    metadata = resp.metadata;
+                    resp.response
+                }
                 Err(e) => {
                     error!("Agent error: {}", e);
-                    "Sorry, I couldn't generate your briefing. Please try again.".to_string()
+                    failed = true;
+                    "Sorry, I couldn't process that query. Please try again.".to_string()
                 }
             }
         }
+        "briefing" => {
+            // Prefer the pre-generated briefing; fall back to asking the agent
+            // directly when it can't be loaded or stored
+            let stored = match &state.db_pool {
+                Some(pool) => fetch_briefing(&state, pool, &user)
+                    .await
+                    .map(Some)
+                    .unwrap_or_else(|e| {
+                        warn!("Stored briefing unavailable: {}", e);
+                        None
+                    }),
+                None => None,
+            };
+
+            match stored {
+                Some(briefing) => briefing,
+                None => match state
+                    .agent_client
+                    .query(
+                        "Give me my morning briefing",
+                        &agent_user_id,
+                        family_ids.clone(),
+                        None,
+                        "discord",
+                    )
+                    .await
+                {
+                    Ok(resp) => resp.response,
+                    Err(e) => {
+                        error!("Agent error: {}", e);
+                        failed = true;
+                        "Sorry, I couldn't generate your briefing. Please try again.".to_string()
+                    }
+                },
+            }
+        }
         "edit" => {
             // Route edit requests through query with clear intent
             let edit_message = format!("Please edit this fact: {}", payload.message);
             match state
                 .agent_client
-                .query(&edit_message, &payload.user_id, vec![], None, "discord")
+                .query(&edit_message, &agent_user_id, family_ids.clone(), None, "discord")
                 .await
             {
                 Ok(resp) => resp.response,
                 Err(e) => {
                     error!("Agent error: {}", e);
+                    failed = true;
                     "Sorry, I couldn't edit that fact. Please try again.".to_string()
                 }
             }
@@ -493,35 +2561,208 @@ async fn handle_follow_up(state: Arc<AppState>, payload: FollowUpPayload) -> Res
             let forget_message = format!("Please delete/forget this fact: {}", payload.message);
             match state
                 .agent_client
-                .query(&forget_message, &payload.user_id, vec![], None, "discord")
+                .query(&forget_message, &agent_user_id, family_ids.clone(), None, "discord")
                 .await
             {
                 Ok(resp) => resp.response,
                 Err(e) => {
                     error!("Agent error: {}", e);
+                    failed = true;
                     "Sorry, I couldn't forget that. Please try again.".to_string()
                 }
             }
         }
-        _ => format!("Unknown command: {}", payload.command_name),
+        "remind" => {
+            // Route reminder requests through query with clear intent
+            let remind_message = format!("Please set a reminder: {}", payload.message);
+            match state
+                .agent_client
+                .query(&remind_message, &agent_user_id, family_ids, None, "discord")
+                .await
+            {
+                Ok(resp) => resp.response,
+                Err(e) => {
+                    error!("Agent error: {}", e);
+                    failed = true;
+                    "Sorry, I couldn't set that reminder. Please try again.".to_string()
+                }
+            }
+        }
+        "transcribe" => {
+            let attachment: Attachment = serde_json::from_str(&payload.message)?;
+            match transcribe_voice_note(&state, &attachment).await {
+                Ok(transcript) if transcript.is_empty() => {
+                    failed = true;
+                    "I couldn't hear any speech in that voice note.".to_string()
+                }
+                Ok(transcript) => match state
+                    .agent_client
+                    .invoke(AgentRequest {
+                        message: transcript.clone(),
+                        user_id: agent_user_id.clone(),
+                        family_ids,
+                        device_id: None,
+                        conversation_id: None,
+                        conversation_history: Vec::new(),
+                        intent: Some("ingest".to_string()),
+                        source: "discord".to_string(),
+                        modality: Some("voice".to_string()),
+                        metadata: None,
+                    })
+                    .await
+                {
+                    // Show what was heard so mistranscriptions are easy to spot
+                    Ok(resp) => format!(
+                        "> {}\n\n{}",
+                        transcript.replace('\n', "\n> "),
+                        resp.response
+                    ),
+                    Err(e) => {
+                        error!("Agent error: {}", e);
+                        failed = true;
+                        "Sorry, I couldn't save that voice note. Please try again.".to_string()
+                    }
+                },
+                Err(e) => {
+                    error!("Transcription failed: {}", e);
+                    failed = true;
+                    "Sorry, I couldn't transcribe that voice note. Please try again.".to_string()
+                }
+            }
+        }
+        _ => {
+            failed = true;
+            format!("Unknown command: {}", payload.command_name)
+        }
     };
 
-    // Send the follow-up message to Discord
-    if let Err(e) = state
-        .send_follow_up(
-            &payload.application_id,
-            &payload.interaction_token,
+    // Successful agent responses render as embeds, errors as plain messages;
+    // either way long responses are split across several messages
+    let mut messages: Vec<Value> = if failed {
+        split(&response_text, MESSAGE_MAX_CHARS)
+            .into_iter()
+            .map(|content| serde_json::json!({ "content": content }))
+            .collect()
+    } else {
+        let metadata = metadata.as_ref();
+        answer_embeds(
+            &embed_title(&payload.command_name, &payload.message),
             &response_text,
+            metadata
+                .and_then(|m| m.citations.as_deref())
+                .unwrap_or_default(),
+            metadata.and_then(|m| m.confidence),
         )
-        .await
-    {
-        error!("Failed to send follow-up message: {}", e);
+        .into_iter()
+        .map(|embed| serde_json::json!({ "embeds": [embed] }))
+        .collect()
+    };
+
+    // Offer a one-click follow-up action on successful answers and saves
+    if let (false, Some(last)) = (failed, messages.last_mut()) {
+        match payload.command_name.as_str() {
+            "ask" | "query" => last["components"] = button_row("Save this", "save"),
+            "remember" | "remember-detailed" | "save" | "transcribe" => {
+                last["components"] = button_row("Forget", "forget")
+            }
+            _ => {}
+        }
     }
 
+    // Send the follow-up message(s) to Discord; a failure is retried by the queue
+    state.send_follow_ups(&payload, &messages).await?;
+
     // Return success for async invocation
     Ok(serde_json::json!({"status": "ok"}))
 }
 
+/// Process queued follow-ups, reporting failures for redelivery.
+///
+/// Failed follow-ups are made visible again after an exponentially growing
+/// delay. On the last delivery the user gets a final "something went wrong"
+/// edit instead of a perpetual "thinking…", and the message moves to the DLQ.
+async fn handle_follow_up_queue(state: Arc<AppState>, event: SqsEvent) -> SqsBatchResponse {
+    let mut failures = Vec::new();
+
+    for record in event.records {
+        let body: Value = match serde_json::from_str(&record.body) {
+            Ok(body) => body,
+            Err(e) => {
+                // Malformed messages can never succeed; drop them
+                error!(message_id = %record.message_id, "Invalid follow-up message: {}", e);
+                continue;
+            }
+        };
+        let payload: FollowUpPayload = match serde_json::from_value(body.clone()) {
+            Ok(payload) => payload,
+            Err(e) => {
+                error!(message_id = %record.message_id, "Invalid follow-up message: {}", e);
+                continue;
+            }
+        };
+        // Nor can messages without a valid signature
+        if let Err(e) = verify_internal(&body, "POST", FOLLOW_UP_PATH, &payload.user_id, &[]).await
+        {
+            error!(message_id = %record.message_id, "Rejected follow-up message: {}", e);
+            continue;
+        }
+
+        let receive_count: u32 = record
+            .attributes
+            .approximate_receive_count
+            .as_deref()
+            .and_then(|c| c.parse().ok())
+            .unwrap_or(1);
+
+        info!(
+            message_id = %record.message_id,
+            receive_count,
+            "Processing queued follow-up for command '{}' from user {}",
+            payload.command_name,
+            payload.username
+        );
+
+        if let Err(e) = handle_follow_up(Arc::clone(&state), payload.clone()).await {
+            error!(message_id = %record.message_id, receive_count, "Follow-up failed: {}", e);
+
+            if receive_count >= FOLLOW_UP_MAX_RECEIVES {
+                let message = serde_json::json!({
+                    "content": FOLLOW_UP_FAILED_MESSAGE,
+                    "embeds": [],
+                    "components": [],
+                });
+                if let Err(e) = state
+                    .edit_original(&payload.application_id, &payload.interaction_token, &message)
+                    .await
+                {
+                    error!("Failed to send failure message: {}", e);
+                }
+            } else if let Some(queue_url) = &state.follow_up_queue_url {
+                let delay = FOLLOW_UP_RETRY_BASE_SECS * 2i32.pow(receive_count.saturating_sub(1));
+                if let Err(e) = state
+                    .sqs_client
+                    .change_message_visibility()
+                    .queue_url(queue_url)
+                    .receipt_handle(&record.receipt_handle)
+                    .visibility_timeout(delay)
+                    .send()
+                    .await
+                {
+                    warn!("Failed to delay follow-up retry: {}", e);
+                }
+            }
+
+            failures.push(BatchItemFailure {
+                item_identifier: record.message_id,
+            });
+        }
+    }
+
+    SqsBatchResponse {
+        batch_item_failures: failures,
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     tracing_subscriber::fmt()
@@ -533,7 +2774,7 @@ async fn main() -> Result<(), Error> {
 
     lambda_runtime::run(service_fn(move |event| {
         let state = Arc::clone(&state);
-        async move { handler(state, event).await }
+        async move { metrics::track_invocation("interaction", handler(state, event)).await }
     }))
     .await
 }
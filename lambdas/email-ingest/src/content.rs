@@ -0,0 +1,197 @@
+//! Turning an email into fact text.
+//!
+//! Replies and forwards carry the whole thread, so quoted text and signatures
+//! are removed with [`strip_reply`] before the subject and body are combined by
+//! [`compose_fact`].
+
+/// Longest fact ingested from one email (the rest is dropped)
+pub const MAX_FACT_CHARS: usize = 16_000;
+
+/// Attachment extensions the drop folder can ingest
+const SUPPORTED_EXTENSIONS: &[&str] = &[
+    "txt", "text", "md", "markdown", "jpg", "jpeg", "png", "tif", "tiff", "mp3", "mp4", "m4a",
+    "wav", "flac", "ogg", "webm", "amr",
+];
+
+/// Subject prefixes added by mail clients
+const SUBJECT_PREFIXES: &[&str] = &["re:", "fwd:", "fw:"];
+
+/// Whether a line starts the quoted part of a reply or forward.
+fn is_reply_header(line: &str, next: Option<&str>) -> bool {
+    let next = next.map(str::trim).unwrap_or_default();
+
+    // "On Mon, 6 Oct 2026 at 09:00, Sam <sam@example.com> wrote:" (possibly wrapped)
+    if line.starts_with("On ") && (line.ends_with("wrote:") || next.ends_with("wrote:")) {
+        return true;
+    }
+
+    // Outlook: "-----Original Message-----" or a rule followed by From:/Sent:
+    if line.starts_with("-----") && line.contains("Original Message") {
+        return true;
+    }
+    if line.len() >= 10 && line.chars().all(|c| c == '_') {
+        return true;
+    }
+    if line.starts_with("From:") && (next.starts_with("Sent:") || next.starts_with("Date:")) {
+        return true;
+    }
+
+    line.starts_with("---------- Forwarded message")
+}
+
+/// Whether a (trimmed) line starts a signature; the `-- ` delimiter's
+/// trailing space is often lost.
+fn is_signature(line: &str) -> bool {
+    line == "--" || line.starts_with("Sent from my ") || line.starts_with("Get Outlook for ")
+}
+
+/// Remove quoted replies, forwarded headers and signatures from a plain-text body.
+pub fn strip_reply(text: &str) -> String {
+    let text = text.replace("\r\n", "\n");
+    let lines: Vec<&str> = text.lines().collect();
+
+    let mut kept = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if is_reply_header(trimmed, lines.get(i + 1).copied()) || is_signature(trimmed) {
+            break;
+        }
+        if trimmed.starts_with('>') {
+            continue;
+        }
+        kept.push(line.trim_end());
+    }
+
+    kept.join("\n").trim().to_string()
+}
+
+/// Subject without `Re:`/`Fwd:` prefixes.
+fn clean_subject(subject: &str) -> &str {
+    let mut subject = subject.trim();
+    while let Some(rest) = SUBJECT_PREFIXES.iter().find_map(|prefix| {
+        subject
+            .get(..prefix.len())
+            .filter(|head| head.eq_ignore_ascii_case(prefix))
+            .map(|_| subject[prefix.len()..].trim_start())
+    }) {
+        subject = rest;
+    }
+    subject
+}
+
+/// Combine a subject and stripped body into the text to ingest, or `None` if
+/// both are empty.
+pub fn compose_fact(subject: Option<&str>, body: &str) -> Option<String> {
+    let subject = subject.map(clean_subject).unwrap_or_default();
+    let body = body.trim();
+
+    let fact = match (subject.is_empty(), body.is_empty()) {
+        (true, true) => return None,
+        (false, true) => subject.to_string(),
+        (true, false) => body.to_string(),
+        (false, false) => format!("{}\n\n{}", subject, body),
+    };
+
+    Some(match fact.char_indices().nth(MAX_FACT_CHARS) {
+        Some((idx, _)) => fact[..idx].to_string(),
+        None => fact,
+    })
+}
+
+/// File name safe to use as an S3 key segment, or `None` if nothing is left.
+///
+/// Directories are dropped and anything other than letters, digits, `.`, `-`
+/// and `_` becomes `_`.
+pub fn safe_file_name(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name).trim();
+    let safe: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    let safe = safe.trim_start_matches('.');
+    (!safe.is_empty()).then(|| safe.to_string())
+}
+
+/// Whether the drop folder can ingest an attachment with this name.
+pub fn is_supported_attachment(name: &str) -> bool {
+    name.rsplit_once('.')
+        .map(|(_, ext)| SUPPORTED_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_reply() {
+        let text = "Emma's recital is Friday at 6.\r\n\r\nOn Mon, 6 Oct 2026 at 09:00, Sam <sam@example.com> wrote:\r\n> When is it?\r\n";
+        assert_eq!(strip_reply(text), "Emma's recital is Friday at 6.");
+
+        let wrapped =
+            "Noted.\n\nOn Mon, 6 Oct 2026 at 09:00, Sam Smith <sam@example.com>\nwrote:\n> x";
+        assert_eq!(strip_reply(wrapped), "Noted.");
+
+        let outlook = "Gate code is 4821\n\nFrom: Sam\nSent: Monday\nTo: me";
+        assert_eq!(strip_reply(outlook), "Gate code is 4821");
+
+        let signed = "Dentist moved to Tuesday\n-- \nSam\n555-0100";
+        assert_eq!(strip_reply(signed), "Dentist moved to Tuesday");
+
+        let mobile = "Buy milk\n\nSent from my iPhone";
+        assert_eq!(strip_reply(mobile), "Buy milk");
+
+        let inline = "> quoted\nmine\n> more quoted";
+        assert_eq!(strip_reply(inline), "mine");
+    }
+
+    #[test]
+    fn test_compose_fact() {
+        assert_eq!(
+            compose_fact(Some("Re: Fwd: Recital"), "Friday at 6").as_deref(),
+            Some("Recital\n\nFriday at 6")
+        );
+        assert_eq!(
+            compose_fact(Some("Gate code 4821"), "").as_deref(),
+            Some("Gate code 4821")
+        );
+        assert_eq!(compose_fact(None, " body ").as_deref(), Some("body"));
+        assert_eq!(compose_fact(Some("RE:"), ""), None);
+
+        let long = compose_fact(None, &"a".repeat(MAX_FACT_CHARS + 10)).unwrap();
+        assert_eq!(long.chars().count(), MAX_FACT_CHARS);
+    }
+
+    #[test]
+    fn test_safe_file_name() {
+        assert_eq!(
+            safe_file_name("receipt 1.png").as_deref(),
+            Some("receipt_1.png")
+        );
+        assert_eq!(
+            safe_file_name("../../etc/passwd").as_deref(),
+            Some("passwd")
+        );
+        assert_eq!(
+            safe_file_name("C:\\scans\\note.txt").as_deref(),
+            Some("note.txt")
+        );
+        assert_eq!(safe_file_name(".."), None);
+        assert_eq!(safe_file_name(""), None);
+    }
+
+    #[test]
+    fn test_is_supported_attachment() {
+        assert!(is_supported_attachment("scan.JPG"));
+        assert!(is_supported_attachment("memo.m4a"));
+        assert!(!is_supported_attachment("invoice.pdf"));
+        assert!(!is_supported_attachment("README"));
+    }
+}
@@ -0,0 +1,7 @@
+//! Inbound email ingestion.
+//!
+//! Users email facts to the inbound address; SES stores the raw message in S3
+//! and invokes the `email_ingest` Lambda, which ingests the body and hands
+//! attachments to the drop folder.
+
+pub mod content;
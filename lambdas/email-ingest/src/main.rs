@@ -0,0 +1,528 @@
+//! Email Ingest Lambda - Saves emails sent to the inbound address as facts.
+//!
+//! An SES receipt rule stores each message under `inbound/{message_id}` in the
+//! inbound bucket, then invokes this Lambda, which:
+//! 1. Checks the SES verdicts (virus, spam, DMARC) and matches the
+//!    From address to a registered user's email
+//! 2. Strips quoted replies and signatures and ingests the subject and body with
+//!    `source=email`
+//! 3. Copies supported attachments into the sender's drop folder
+//!    (`drop/{cognito_sub}/email/{message_id}/`), where `drop_folder_ingest`
+//!    OCRs, transcribes or ingests them
+//! 4. Replies to the sender with a confirmation
+//!
+//! Mail from unknown or unauthenticated senders is recorded as rejected and not
+//! answered, so forged senders can't be used to bounce mail to third parties.
+
+use aws_sdk_ses::types::{Body, Content, Destination, Message};
+use email_ingest::content::{compose_fact, is_supported_attachment, safe_file_name, strip_reply};
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use mail_parser::{MessageParser, MimeHeaders};
+use serde::{Deserialize, Serialize};
+use shared::metrics;
+use shared::{AgentClient, AuthenticatedUser, AuthorizedUser};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// Prefix the SES receipt rule stores raw messages under
+const INBOUND_PREFIX: &str = "inbound/";
+
+/// Drop folder prefix attachments are copied to: `drop/{cognito_sub}/email/...`
+const DROP_PREFIX: &str = "drop/";
+
+/// SES event wrapper
+#[derive(Debug, Deserialize)]
+struct SesEvent {
+    #[serde(rename = "Records")]
+    records: Vec<SesRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SesRecord {
+    ses: SesMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct SesMessage {
+    mail: SesMail,
+    receipt: SesReceipt,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SesMail {
+    message_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SesReceipt {
+    dmarc_verdict: Verdict,
+    spam_verdict: Verdict,
+    virus_verdict: Verdict,
+}
+
+#[derive(Debug, Deserialize)]
+struct Verdict {
+    status: String,
+}
+
+impl Verdict {
+    fn passed(&self) -> bool {
+        self.status == "PASS"
+    }
+}
+
+impl SesReceipt {
+    /// Whether the message is clean and the From domain is authenticated.
+    ///
+    /// Only DMARC ties SPF and DKIM to the From address: a passing SPF or DKIM
+    /// check on its own may be for the forger's domain, and a domain without a
+    /// DMARC policy (`GRAY`) can't be authenticated.
+    fn trusted(&self) -> bool {
+        self.virus_verdict.passed()
+            && self.spam_verdict.status != "FAIL"
+            && self.dmarc_verdict.passed()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct IngestResponse {
+    ingested: u32,
+    rejected: u32,
+}
+
+/// Result of processing one message
+enum Outcome {
+    Ingested,
+    Rejected,
+    Duplicate,
+}
+
+struct AppState {
+    db_pool: PgPool,
+    s3_client: aws_sdk_s3::Client,
+    ses_client: aws_sdk_ses::Client,
+    agent_client: AgentClient,
+    inbound_bucket: String,
+    drop_folder_bucket: Option<String>,
+    from_email: String,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        let inbound_bucket =
+            std::env::var("INBOUND_EMAIL_BUCKET").map_err(|_| "INBOUND_EMAIL_BUCKET not set")?;
+        let from_email = std::env::var("FROM_EMAIL").map_err(|_| "FROM_EMAIL not set")?;
+        let agent_function_name = std::env::var("AGENT_FUNCTION_NAME")
+            .unwrap_or_else(|_| "second-brain-agents".to_string());
+
+        Ok(Self {
+            db_pool,
+            s3_client: aws_sdk_s3::Client::new(&config),
+            ses_client: aws_sdk_ses::Client::new(&config),
+            agent_client: AgentClient::new(
+                aws_sdk_lambda::Client::new(&config),
+                agent_function_name,
+            ),
+            inbound_bucket,
+            drop_folder_bucket: std::env::var("DROP_FOLDER_BUCKET").ok(),
+            from_email,
+        })
+    }
+}
+
+/// Record a message as received; returns false if it was already processed.
+async fn claim_message(pool: &PgPool, message_id: &str) -> Result<bool, Error> {
+    let claimed: Option<String> = sqlx::query_scalar(
+        r#"
+        INSERT INTO inbound_emails (message_id)
+        VALUES ($1)
+        ON CONFLICT (message_id) DO NOTHING
+        RETURNING message_id
+        "#,
+    )
+    .bind(message_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to record message: {}", e))?;
+
+    Ok(claimed.is_some())
+}
+
+/// Release a claim so a retried invocation processes the message again.
+async fn release_message(pool: &PgPool, message_id: &str) {
+    if let Err(e) =
+        sqlx::query("DELETE FROM inbound_emails WHERE message_id = $1 AND status = 'received'")
+            .bind(message_id)
+            .execute(pool)
+            .await
+    {
+        error!(message_id, error = %e, "Failed to release message");
+    }
+}
+
+async fn finish_message(
+    pool: &PgPool,
+    message_id: &str,
+    user_id: Option<Uuid>,
+    sender: Option<&str>,
+    status: &str,
+    attachments: usize,
+    error_message: Option<&str>,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE inbound_emails
+        SET user_id = $2, sender = $3, status = $4, attachments = $5,
+            error_message = $6, updated_at = NOW()
+        WHERE message_id = $1
+        "#,
+    )
+    .bind(message_id)
+    .bind(user_id)
+    .bind(sender)
+    .bind(status)
+    .bind(attachments as i32)
+    .bind(error_message)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to update message: {}", e))?;
+
+    Ok(())
+}
+
+/// Resolve the registered user with this email address.
+async fn resolve_sender(state: &AppState, email: &str) -> Result<Option<AuthorizedUser>, Error> {
+    let cognito_sub: Option<String> =
+        sqlx::query_scalar("SELECT cognito_sub FROM users WHERE LOWER(email) = LOWER($1)")
+            .bind(email)
+            .fetch_optional(&state.db_pool)
+            .await
+            .map_err(|e| format!("Failed to lookup user: {}", e))?;
+
+    let cognito_sub = match cognito_sub {
+        Some(sub) => sub,
+        None => return Ok(None),
+    };
+
+    match AuthorizedUser::resolve(
+        AuthenticatedUser {
+            user_id: cognito_sub,
+            email: Some(email.to_string()),
+            family_ids: Vec::new(),
+        },
+        &state.db_pool,
+    )
+    .await
+    {
+        Ok(user) => Ok(Some(user)),
+        Err(shared::Error::Auth(_)) => Ok(None),
+        Err(e) => Err(format!("Failed to lookup user: {}", e).into()),
+    }
+}
+
+async fn send_reply(
+    state: &AppState,
+    to_email: &str,
+    subject: &str,
+    body: &str,
+) -> Result<(), Error> {
+    let subject = Content::builder()
+        .data(subject)
+        .charset("UTF-8")
+        .build()
+        .map_err(|e| format!("Failed to build subject: {}", e))?;
+
+    let text_content = Content::builder()
+        .data(body)
+        .charset("UTF-8")
+        .build()
+        .map_err(|e| format!("Failed to build text body: {}", e))?;
+
+    let message = Message::builder()
+        .subject(subject)
+        .body(Body::builder().text(text_content).build())
+        .build();
+
+    state
+        .ses_client
+        .send_email()
+        .source(&state.from_email)
+        .destination(Destination::builder().to_addresses(to_email).build())
+        .message(message)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send email: {}", e))?;
+
+    Ok(())
+}
+
+/// Process one SES message. Returns an error only for failures worth retrying.
+async fn process_message(state: &AppState, record: &SesMessage) -> Result<Outcome, Error> {
+    let message_id = record.mail.message_id.as_str();
+
+    if !claim_message(&state.db_pool, message_id).await? {
+        info!(message_id, "Skipping duplicate delivery");
+        return Ok(Outcome::Duplicate);
+    }
+
+    let raw = state
+        .s3_client
+        .get_object()
+        .bucket(&state.inbound_bucket)
+        .key(format!("{}{}", INBOUND_PREFIX, message_id))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to read message: {}", e))?
+        .body
+        .collect()
+        .await
+        .map_err(|e| format!("Failed to read message body: {}", e))?
+        .into_bytes();
+
+    let email = match MessageParser::default().parse(&raw[..]) {
+        Some(email) => email,
+        None => {
+            warn!(message_id, "Unparseable message");
+            finish_message(
+                &state.db_pool,
+                message_id,
+                None,
+                None,
+                "rejected",
+                0,
+                Some("Unparseable message"),
+            )
+            .await?;
+            return Ok(Outcome::Rejected);
+        }
+    };
+
+    let sender = email
+        .from()
+        .and_then(|from| from.first())
+        .and_then(|addr| addr.address())
+        .map(str::to_string);
+
+    let sender = match sender {
+        Some(sender) if record.receipt.trusted() => sender,
+        _ => {
+            warn!(message_id, "Rejecting unauthenticated message");
+            finish_message(
+                &state.db_pool,
+                message_id,
+                None,
+                sender.as_deref(),
+                "rejected",
+                0,
+                Some("Sender not authenticated"),
+            )
+            .await?;
+            return Ok(Outcome::Rejected);
+        }
+    };
+
+    let user = match resolve_sender(state, &sender).await? {
+        Some(user) => user,
+        None => {
+            warn!(message_id, "Rejecting message from unregistered sender");
+            finish_message(
+                &state.db_pool,
+                message_id,
+                None,
+                Some(&sender),
+                "rejected",
+                0,
+                Some("Sender not registered"),
+            )
+            .await?;
+            return Ok(Outcome::Rejected);
+        }
+    };
+
+    // Attachments go through the drop folder, which handles OCR and transcription
+    let mut queued = Vec::new();
+    let mut skipped = Vec::new();
+    for part in email.attachments() {
+        let inline = part
+            .content_disposition()
+            .map(|d| d.ctype().eq_ignore_ascii_case("inline"))
+            .unwrap_or(false);
+        let name = match part.attachment_name().and_then(safe_file_name) {
+            Some(name) if !inline => name,
+            _ => continue,
+        };
+
+        let bucket = match &state.drop_folder_bucket {
+            Some(bucket) if is_supported_attachment(&name) => bucket,
+            _ => {
+                skipped.push(name);
+                continue;
+            }
+        };
+
+        state
+            .s3_client
+            .put_object()
+            .bucket(bucket)
+            .key(format!(
+                "{}{}/email/{}/{}",
+                DROP_PREFIX, user.cognito_sub, message_id, name
+            ))
+            .body(part.contents().to_vec().into())
+            .send()
+            .await
+            .map_err(|e| format!("Failed to store attachment: {}", e))?;
+        queued.push(name);
+    }
+
+    let body = email
+        .body_text(0)
+        .map(|b| strip_reply(&b))
+        .unwrap_or_default();
+    let subject = email.subject().unwrap_or_default().to_string();
+    let fact = compose_fact(Some(&subject), &body);
+
+    let family_ids: Vec<String> = user.family_ids.iter().map(Uuid::to_string).collect();
+    let (status, error_message, mut reply) = match &fact {
+        None => (
+            "ingested",
+            None,
+            "There was no text to save in your email.".to_string(),
+        ),
+        Some(fact) => match state
+            .agent_client
+            .ingest(fact, &user.user_id.to_string(), family_ids, "email")
+            .await
+        {
+            Ok(resp) => ("ingested", None, resp.response),
+            Err(e) => {
+                error!(message_id, error = %e, "Agent error");
+                (
+                    "failed",
+                    Some(e.to_string()),
+                    "Sorry, I couldn't save your email. Please try again later.".to_string(),
+                )
+            }
+        },
+    };
+
+    if !queued.is_empty() {
+        reply.push_str(&format!(
+            "\n\nProcessing attachments: {}",
+            queued.join(", ")
+        ));
+    }
+    if !skipped.is_empty() {
+        reply.push_str(&format!(
+            "\n\nSkipped unsupported attachments: {}",
+            skipped.join(", ")
+        ));
+    }
+
+    finish_message(
+        &state.db_pool,
+        message_id,
+        Some(user.user_id),
+        Some(&sender),
+        status,
+        queued.len(),
+        error_message.as_deref(),
+    )
+    .await?;
+    info!(message_id, user_id = %user.user_id, attachments = queued.len(), status, "Processed inbound email");
+
+    let reply_subject = if subject.is_empty() {
+        "Saved to Second Brain".to_string()
+    } else {
+        format!("Re: {}", subject)
+    };
+    if let Err(e) = send_reply(state, &sender, &reply_subject, &reply).await {
+        warn!(message_id, error = %e, "Failed to send confirmation");
+    }
+
+    Ok(Outcome::Ingested)
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    event: LambdaEvent<SesEvent>,
+) -> Result<IngestResponse, Error> {
+    let mut ingested = 0u32;
+    let mut rejected = 0u32;
+
+    for record in &event.payload.records {
+        let message_id = record.ses.mail.message_id.as_str();
+
+        match process_message(&state, &record.ses).await {
+            Ok(Outcome::Ingested) => ingested += 1,
+            Ok(Outcome::Rejected) => rejected += 1,
+            Ok(Outcome::Duplicate) => {}
+            Err(e) => {
+                // Let Lambda retry the invocation
+                error!(message_id, error = %e, "Failed to process inbound email");
+                release_message(&state.db_pool, message_id).await;
+                return Err(e);
+            }
+        }
+    }
+
+    Ok(IngestResponse { ingested, rejected })
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let state_clone = state.clone();
+
+    run(service_fn(move |event| {
+        let state = state_clone.clone();
+        async move { metrics::track_invocation("inbound_email", handler(state, event)).await }
+    }))
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receipt(spf: &str, dkim: &str, dmarc: &str) -> SesReceipt {
+        serde_json::from_value(serde_json::json!({
+            "spfVerdict": { "status": spf },
+            "dkimVerdict": { "status": dkim },
+            "dmarcVerdict": { "status": dmarc },
+            "spamVerdict": { "status": "PASS" },
+            "virusVerdict": { "status": "PASS" },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_trusted() {
+        assert!(receipt("PASS", "PASS", "PASS").trusted());
+        assert!(receipt("FAIL", "PASS", "PASS").trusted());
+        assert!(!receipt("PASS", "PASS", "FAIL").trusted());
+    }
+
+    #[test]
+    fn test_untrusted_without_dmarc() {
+        // SPF and DKIM pass for the forger's own domain, while the From
+        // domain publishes no DMARC policy
+        assert!(!receipt("PASS", "PASS", "GRAY").trusted());
+    }
+}
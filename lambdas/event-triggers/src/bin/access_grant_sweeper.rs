@@ -0,0 +1,79 @@
+//! Access Grant Sweeper Lambda - Deletes access grants long over.
+//!
+//! Runs daily via EventBridge. Grants stop working the moment they expire or
+//! are revoked (reads check their window), and stay listed in
+//! `/access-grants` for `shared::access_grants::HISTORY_DAYS` so people can
+//! see what was shared; after that they are deleted (see
+//! `shared::access_grants::sweep_ended`).
+
+use chrono::{Duration, Utc};
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use shared::access_grants::{sweep_ended, HISTORY_DAYS};
+use shared::metrics;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+
+#[derive(Debug, Default, Deserialize)]
+struct SweepEvent {
+    #[serde(default)]
+    detail_type: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SweepResponse {
+    grants_deleted: u64,
+}
+
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    event: LambdaEvent<SweepEvent>,
+) -> Result<SweepResponse, Error> {
+    info!(detail_type = %event.payload.detail_type, "Starting access grant sweep");
+
+    let cutoff = Utc::now() - Duration::days(HISTORY_DAYS);
+    let deleted = sweep_ended(&state.db_pool, cutoff)
+        .await
+        .map_err(|e| format!("Failed to sweep access grants: {}", e))?;
+
+    info!(grants = deleted, "Access grant sweep complete");
+
+    Ok(SweepResponse {
+        grants_deleted: deleted,
+    })
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let state_clone = state.clone();
+
+    run(service_fn(move |event| {
+        let state = state_clone.clone();
+        async move { metrics::track_invocation("access_grant_sweeper", handler(state, event)).await }
+    }))
+    .await
+}
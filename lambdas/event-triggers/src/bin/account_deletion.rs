@@ -0,0 +1,649 @@
+//! Account Deletion Lambda - Carries out scheduled account deletions.
+//!
+//! Invoked asynchronously by the account API Lambda (`DELETE /account`) with
+//! the ID of an `account_deletions` row, and every 15 minutes by EventBridge to
+//! retry deletions that haven't finished. Each run claims the deletion for
+//! a lease, then works through the remaining `DeletionStep`s, recording each
+//! one on the row as it completes so a failed run resumes where it stopped.
+//! Every step is safe to repeat: logins, secrets and rows that are already
+//! gone count as done.
+//!
+//! After `MAX_DELETION_ATTEMPTS` failed runs the deletion is marked failed
+//! for an operator to look at.
+
+use aws_sdk_cognitoidentityprovider::Client as CognitoClient;
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use shared::account_deletion::{
+    successor, user_secret_names, DeletionStep, FamilyDataPolicy, FamilyMember,
+    DELETION_LEASE_MINUTES, MAX_DELETION_ATTEMPTS,
+};
+use shared::metrics;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::sync::Arc;
+use tracing::{error, info};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// Invocation payload. Scheduled EventBridge events carry no deletion ID and
+/// sweep every unfinished deletion.
+#[derive(Debug, Deserialize)]
+struct DeletionEvent {
+    #[serde(default)]
+    deletion_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+struct DeletionResult {
+    processed: usize,
+    completed: usize,
+    failed: usize,
+}
+
+/// A claimed deletion
+#[derive(Debug, sqlx::FromRow)]
+struct DeletionRow {
+    id: Uuid,
+    user_id: Uuid,
+    cognito_sub: String,
+    family_data_policy: String,
+    completed_steps: Vec<String>,
+    attempts: i32,
+}
+
+struct AppState {
+    db_pool: PgPool,
+    cognito_client: CognitoClient,
+    secrets_client: aws_sdk_secretsmanager::Client,
+    user_pool_id: String,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        let secrets_client = aws_sdk_secretsmanager::Client::new(&config);
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        let user_pool_id = std::env::var("USER_POOL_ID").map_err(|_| "USER_POOL_ID not set")?;
+
+        Ok(Self {
+            db_pool,
+            cognito_client: CognitoClient::new(&config),
+            secrets_client,
+            user_pool_id,
+        })
+    }
+}
+
+/// Claim a scheduled deletion whose lease is free, counting the attempt.
+async fn claim_deletion(pool: &PgPool, deletion_id: Uuid) -> Result<Option<DeletionRow>, Error> {
+    let row = sqlx::query_as(
+        r#"
+        UPDATE account_deletions
+        SET processing_started_at = NOW(), attempts = attempts + 1
+        WHERE id = $1 AND status = 'scheduled'
+          AND (processing_started_at IS NULL
+               OR processing_started_at < NOW() - make_interval(mins => $2))
+        RETURNING id, user_id, cognito_sub, family_data_policy, completed_steps, attempts
+        "#,
+    )
+    .bind(deletion_id)
+    .bind(DELETION_LEASE_MINUTES as i32)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to claim deletion: {}", e))?;
+
+    Ok(row)
+}
+
+/// Record a finished step and what it did.
+async fn record_step(
+    pool: &PgPool,
+    deletion_id: Uuid,
+    step: DeletionStep,
+    summary: serde_json::Value,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE account_deletions
+        SET completed_steps = array_append(completed_steps, $2),
+            summary = summary || jsonb_build_object($2::text, $3::jsonb)
+        WHERE id = $1 AND NOT ($2 = ANY(completed_steps))
+        "#,
+    )
+    .bind(deletion_id)
+    .bind(step.as_str())
+    .bind(summary)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to record {} step: {}", step.as_str(), e))?;
+
+    Ok(())
+}
+
+/// Sign the user out everywhere and disable the login.
+async fn revoke_access(state: &AppState, cognito_sub: &str) -> Result<serde_json::Value, Error> {
+    let signed_out = state
+        .cognito_client
+        .admin_user_global_sign_out()
+        .user_pool_id(&state.user_pool_id)
+        .username(cognito_sub)
+        .send()
+        .await;
+    if let Err(e) = signed_out {
+        if !e
+            .as_service_error()
+            .is_some_and(|se| se.is_user_not_found_exception())
+        {
+            return Err(format!("Failed to sign user out: {}", e).into());
+        }
+        return Ok(json!({ "login": "not_found" }));
+    }
+
+    state
+        .cognito_client
+        .admin_disable_user()
+        .user_pool_id(&state.user_pool_id)
+        .username(cognito_sub)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to disable user: {}", e))?;
+
+    Ok(json!({ "login": "disabled" }))
+}
+
+/// Delete the user's calendar and contacts OAuth tokens.
+async fn purge_secrets(state: &AppState, user_id: Uuid) -> Result<serde_json::Value, Error> {
+    let mut deleted = 0;
+    for name in user_secret_names(user_id) {
+        let result = state
+            .secrets_client
+            .delete_secret()
+            .secret_id(&name)
+            .force_delete_without_recovery(true)
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => deleted += 1,
+            Err(e)
+                if e.as_service_error()
+                    .is_some_and(|se| se.is_resource_not_found_exception()) => {}
+            Err(e) => return Err(format!("Failed to delete secret {}: {}", name, e).into()),
+        }
+    }
+
+    Ok(json!({ "secrets": deleted }))
+}
+
+/// Delete facts by ID, first clearing `superseded_by` links to them from
+/// facts that stay.
+async fn delete_facts(tx: &mut Transaction<'_, Postgres>, ids: &[Uuid]) -> Result<u64, Error> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    sqlx::query(
+        "UPDATE facts SET superseded_by = NULL WHERE superseded_by = ANY($1) AND NOT (id = ANY($1))",
+    )
+    .bind(ids)
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| format!("Failed to unlink superseded facts: {}", e))?;
+
+    let result = sqlx::query("DELETE FROM facts WHERE id = ANY($1)")
+        .bind(ids)
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| format!("Failed to delete facts: {}", e))?;
+
+    Ok(result.rows_affected())
+}
+
+/// Delete entities by ID, first detaching facts that stay from them.
+async fn delete_entities(tx: &mut Transaction<'_, Postgres>, ids: &[Uuid]) -> Result<u64, Error> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    sqlx::query("UPDATE facts SET about_entity_id = NULL WHERE about_entity_id = ANY($1)")
+        .bind(ids)
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| format!("Failed to detach facts from entities: {}", e))?;
+
+    let result = sqlx::query("DELETE FROM entities WHERE id = ANY($1)")
+        .bind(ids)
+        .execute(&mut **tx)
+        .await
+        .map_err(|e| format!("Failed to delete entities: {}", e))?;
+
+    Ok(result.rows_affected())
+}
+
+/// IDs from `table` (`facts` or `entities`) owned by a family, optionally
+/// only those created by `created_by`.
+async fn family_owned_ids(
+    tx: &mut Transaction<'_, Postgres>,
+    table: &str,
+    family_id: Uuid,
+    created_by: Option<Uuid>,
+) -> Result<Vec<Uuid>, Error> {
+    let ids = sqlx::query_scalar(&format!(
+        r#"
+        SELECT id FROM {}
+        WHERE owner_type = 'family' AND owner_id = $1
+          AND ($2::uuid IS NULL OR created_by = $2)
+        "#,
+        table
+    ))
+    .bind(family_id)
+    .bind(created_by)
+    .fetch_all(&mut **tx)
+    .await
+    .map_err(|e| format!("Failed to list family {}: {}", table, e))?;
+
+    Ok(ids)
+}
+
+/// Apply the family data policy and leave every family the user belongs to
+/// or created data for.
+async fn leave_families(
+    pool: &PgPool,
+    user_id: Uuid,
+    policy: FamilyDataPolicy,
+) -> Result<serde_json::Value, Error> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let family_ids: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        SELECT family_id FROM family_members WHERE user_id = $1
+        UNION
+        SELECT owner_id FROM facts WHERE owner_type = 'family' AND created_by = $1
+        UNION
+        SELECT owner_id FROM entities WHERE owner_type = 'family' AND created_by = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to list families: {}", e))?;
+
+    let (mut handed_over, mut deleted_families) = (0, 0);
+    let (mut facts_reassigned, mut facts_deleted) = (0, 0);
+    let (mut entities_reassigned, mut entities_deleted) = (0, 0);
+
+    for family_id in family_ids {
+        let members: Vec<FamilyMember> = sqlx::query_as(
+            r#"
+            SELECT user_id, role::text AS role, joined_at
+            FROM family_members
+            WHERE family_id = $1 AND user_id <> $2
+            "#,
+        )
+        .bind(family_id)
+        .bind(user_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to list family members: {}", e))?;
+
+        let Some(successor) = successor(&members) else {
+            // Last member: the family and everything it owns go
+            let facts = family_owned_ids(&mut tx, "facts", family_id, None).await?;
+            facts_deleted += delete_facts(&mut tx, &facts).await?;
+            let entities = family_owned_ids(&mut tx, "entities", family_id, None).await?;
+            entities_deleted += delete_entities(&mut tx, &entities).await?;
+
+            sqlx::query("DELETE FROM tags WHERE owner_type = 'family' AND owner_id = $1")
+                .bind(family_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("Failed to delete family tags: {}", e))?;
+
+            sqlx::query("DELETE FROM families WHERE id = $1")
+                .bind(family_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("Failed to delete family: {}", e))?;
+
+            deleted_families += 1;
+            continue;
+        };
+
+        match policy {
+            FamilyDataPolicy::Keep => {
+                for (table, reassigned) in [
+                    ("facts", &mut facts_reassigned),
+                    ("entities", &mut entities_reassigned),
+                ] {
+                    let result = sqlx::query(&format!(
+                        r#"
+                        UPDATE {} SET created_by = $3
+                        WHERE owner_type = 'family' AND owner_id = $1 AND created_by = $2
+                        "#,
+                        table
+                    ))
+                    .bind(family_id)
+                    .bind(user_id)
+                    .bind(successor)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| format!("Failed to reassign family {}: {}", table, e))?;
+                    *reassigned += result.rows_affected();
+                }
+            }
+            FamilyDataPolicy::Delete => {
+                let facts = family_owned_ids(&mut tx, "facts", family_id, Some(user_id)).await?;
+                facts_deleted += delete_facts(&mut tx, &facts).await?;
+                let entities =
+                    family_owned_ids(&mut tx, "entities", family_id, Some(user_id)).await?;
+                entities_deleted += delete_entities(&mut tx, &entities).await?;
+            }
+        }
+
+        sqlx::query(
+            "UPDATE devices SET registered_by = $3 WHERE family_id = $1 AND registered_by = $2",
+        )
+        .bind(family_id)
+        .bind(user_id)
+        .bind(successor)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to reassign family devices: {}", e))?;
+
+        sqlx::query("UPDATE families SET created_by = $3 WHERE id = $1 AND created_by = $2")
+            .bind(family_id)
+            .bind(user_id)
+            .bind(successor)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to reassign family: {}", e))?;
+
+        // A family is never left without an admin
+        if !members.iter().any(|m| m.role == "admin") {
+            sqlx::query(
+                "UPDATE family_members SET role = 'admin' WHERE family_id = $1 AND user_id = $2",
+            )
+            .bind(family_id)
+            .bind(successor)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to promote family admin: {}", e))?;
+        }
+
+        sqlx::query("DELETE FROM family_members WHERE family_id = $1 AND user_id = $2")
+            .bind(family_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to leave family: {}", e))?;
+
+        handed_over += 1;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit: {}", e))?;
+
+    Ok(json!({
+        "families_left": handed_over,
+        "families_deleted": deleted_families,
+        "facts_reassigned": facts_reassigned,
+        "facts_deleted": facts_deleted,
+        "entities_reassigned": entities_reassigned,
+        "entities_deleted": entities_deleted,
+    }))
+}
+
+/// Delete the user's own data and clear references to them from data that
+/// stays with other users.
+async fn delete_personal_data(pool: &PgPool, user_id: Uuid) -> Result<serde_json::Value, Error> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let facts: Vec<Uuid> =
+        sqlx::query_scalar("SELECT id FROM facts WHERE owner_type = 'user' AND owner_id = $1")
+            .bind(user_id)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to list facts: {}", e))?;
+    let facts_deleted = delete_facts(&mut tx, &facts).await?;
+
+    let entities: Vec<Uuid> =
+        sqlx::query_scalar("SELECT id FROM entities WHERE owner_type = 'user' AND owner_id = $1")
+            .bind(user_id)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to list entities: {}", e))?;
+    let entities_deleted = delete_entities(&mut tx, &entities).await?;
+
+    let tags_deleted = sqlx::query("DELETE FROM tags WHERE owner_type = 'user' AND owner_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to delete tags: {}", e))?
+        .rows_affected();
+
+    // Family devices were handed on with the family; these are personal
+    let devices_deleted = sqlx::query("DELETE FROM devices WHERE registered_by = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to delete devices: {}", e))?
+        .rows_affected();
+
+    // Snapshots of the user's own data would outlive it in the audit log
+    let audit_entries_deleted = sqlx::query("DELETE FROM audit_log WHERE owner_user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to delete audit entries: {}", e))?
+        .rows_affected();
+
+    // Anything the user created in another user's space is credited to that
+    // user, and nullable references are cleared
+    let statements = [
+        "UPDATE facts SET created_by = owner_id WHERE created_by = $1 AND owner_type = 'user'",
+        "UPDATE entities SET created_by = owner_id WHERE created_by = $1 AND owner_type = 'user'",
+        "UPDATE entities SET linked_user_id = NULL WHERE linked_user_id = $1",
+        "UPDATE entity_attributes SET created_by = NULL WHERE created_by = $1",
+        "UPDATE entity_relationships SET created_by = NULL WHERE created_by = $1",
+        "UPDATE relationships SET created_by = NULL WHERE created_by = $1",
+        "UPDATE families SET created_by = NULL WHERE created_by = $1",
+        "UPDATE family_members SET invited_by = NULL WHERE invited_by = $1",
+    ];
+    for statement in statements {
+        sqlx::query(statement)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to clear user references: {}", e))?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit: {}", e))?;
+
+    Ok(json!({
+        "facts": facts_deleted,
+        "entities": entities_deleted,
+        "tags": tags_deleted,
+        "devices": devices_deleted,
+        "audit_entries": audit_entries_deleted,
+    }))
+}
+
+/// Delete the user row; everything else keyed by the user cascades.
+async fn delete_user(pool: &PgPool, user_id: Uuid) -> Result<serde_json::Value, Error> {
+    let result = sqlx::query("DELETE FROM users WHERE id = $1")
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to delete user: {}", e))?;
+
+    Ok(json!({ "users": result.rows_affected() }))
+}
+
+/// Delete the Cognito user.
+async fn delete_login(state: &AppState, cognito_sub: &str) -> Result<serde_json::Value, Error> {
+    let result = state
+        .cognito_client
+        .admin_delete_user()
+        .user_pool_id(&state.user_pool_id)
+        .username(cognito_sub)
+        .send()
+        .await;
+
+    match result {
+        Ok(_) => Ok(json!({ "login": "deleted" })),
+        Err(e)
+            if e.as_service_error()
+                .is_some_and(|se| se.is_user_not_found_exception()) =>
+        {
+            Ok(json!({ "login": "not_found" }))
+        }
+        Err(e) => Err(format!("Failed to delete login: {}", e).into()),
+    }
+}
+
+async fn run_step(
+    state: &AppState,
+    deletion: &DeletionRow,
+    policy: FamilyDataPolicy,
+    step: DeletionStep,
+) -> Result<serde_json::Value, Error> {
+    match step {
+        DeletionStep::RevokeAccess => revoke_access(state, &deletion.cognito_sub).await,
+        DeletionStep::PurgeSecrets => purge_secrets(state, deletion.user_id).await,
+        DeletionStep::Families => leave_families(&state.db_pool, deletion.user_id, policy).await,
+        DeletionStep::PersonalData => delete_personal_data(&state.db_pool, deletion.user_id).await,
+        DeletionStep::DeleteUser => delete_user(&state.db_pool, deletion.user_id).await,
+        DeletionStep::DeleteLogin => delete_login(state, &deletion.cognito_sub).await,
+    }
+}
+
+/// Run a claimed deletion's remaining steps. Returns whether it completed.
+async fn process_deletion(state: &AppState, deletion: DeletionRow) -> Result<bool, Error> {
+    let policy = FamilyDataPolicy::parse(&deletion.family_data_policy).unwrap_or_default();
+
+    for step in DeletionStep::remaining(&deletion.completed_steps) {
+        match run_step(state, &deletion, policy, step).await {
+            Ok(summary) => {
+                record_step(&state.db_pool, deletion.id, step, summary).await?;
+                info!(deletion_id = %deletion.id, step = step.as_str(), "Deletion step completed");
+            }
+            Err(e) => {
+                let give_up = deletion.attempts >= MAX_DELETION_ATTEMPTS;
+                error!(
+                    deletion_id = %deletion.id,
+                    step = step.as_str(),
+                    attempts = deletion.attempts,
+                    "Deletion step failed: {}",
+                    e
+                );
+
+                sqlx::query(
+                    r#"
+                    UPDATE account_deletions
+                    SET status = CASE WHEN $3 THEN 'failed'::account_deletion_status
+                                      ELSE status END,
+                        last_error = $2, processing_started_at = NULL
+                    WHERE id = $1
+                    "#,
+                )
+                .bind(deletion.id)
+                .bind(format!("{}: {}", step.as_str(), e))
+                .bind(give_up)
+                .execute(&state.db_pool)
+                .await
+                .map_err(|e| format!("Failed to record deletion failure: {}", e))?;
+
+                return Ok(false);
+            }
+        }
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE account_deletions
+        SET status = 'completed', completed_at = NOW(), processing_started_at = NULL,
+            last_error = NULL
+        WHERE id = $1
+        "#,
+    )
+    .bind(deletion.id)
+    .execute(&state.db_pool)
+    .await
+    .map_err(|e| format!("Failed to complete deletion: {}", e))?;
+
+    info!(deletion_id = %deletion.id, user_id = %deletion.user_id, "Account deleted");
+    Ok(true)
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    event: LambdaEvent<DeletionEvent>,
+) -> Result<DeletionResult, Error> {
+    let deletion_ids: Vec<Uuid> = match event.payload.deletion_id {
+        Some(id) => vec![id],
+        None => sqlx::query_scalar(
+            "SELECT id FROM account_deletions WHERE status = 'scheduled' ORDER BY requested_at",
+        )
+        .fetch_all(&state.db_pool)
+        .await
+        .map_err(|e| format!("Failed to list deletions: {}", e))?,
+    };
+
+    let mut result = DeletionResult {
+        processed: 0,
+        completed: 0,
+        failed: 0,
+    };
+
+    for deletion_id in deletion_ids {
+        let Some(deletion) = claim_deletion(&state.db_pool, deletion_id).await? else {
+            continue;
+        };
+
+        result.processed += 1;
+        if process_deletion(&state, deletion).await? {
+            result.completed += 1;
+        } else {
+            result.failed += 1;
+        }
+    }
+
+    info!(
+        processed = result.processed,
+        completed = result.completed,
+        failed = result.failed,
+        "Account deletion run finished"
+    );
+
+    Ok(result)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        async move { metrics::track_invocation("account_deletion", handler(state, event)).await }
+    }))
+    .await
+}
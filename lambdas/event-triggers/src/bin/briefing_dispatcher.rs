@@ -3,15 +3,21 @@
 //! This Lambda runs hourly via EventBridge and:
 //! 1. Queries users whose briefing time matches the current hour in their timezone
 //! 2. Invokes the agent system to generate briefings for each user
-//! 3. Queues notifications for delivery
+//! 3. Stores them in `briefing_history` so `GET /briefings/today` and Discord's
+//!    `/briefing` can serve them instantly
+//!
+//! Users who already have today's briefing are skipped, so re-runs are cheap.
 
-use aws_sdk_lambda::primitives::Blob;
 use chrono::{Timelike, Utc};
 use lambda_runtime::{run, service_fn, Error, LambdaEvent};
 use serde::{Deserialize, Serialize};
-use sqlx::postgres::PgPoolOptions;
+use shared::briefings::{generate_briefing, todays_briefing};
+use shared::AgentClient;
+use shared::metrics;
 use sqlx::PgPool;
 use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 use tracing::{error, info};
 use tracing_subscriber::EnvFilter;
 use uuid::Uuid;
@@ -25,60 +31,34 @@ struct ScheduledEvent {
     briefing_type: Option<String>,
 }
 
+/// Briefings generated concurrently (bounded by the DB pool size)
+const MAX_CONCURRENT_BRIEFINGS: usize = 5;
+
 #[derive(Debug, Serialize)]
 struct DispatcherResponse {
     users_processed: u32,
     briefings_triggered: u32,
+    already_generated: u32,
     errors: u32,
 }
 
-#[derive(Debug, Serialize)]
-struct AgentRequest {
-    message: String,
-    user_id: String,
-    family_ids: Vec<String>,
-    intent: String,
-    source: String,
+/// Outcome of preparing one user's briefing
+enum BriefingOutcome {
+    Generated,
+    AlreadyGenerated,
 }
 
 struct AppState {
     db_pool: PgPool,
-    lambda_client: aws_sdk_lambda::Client,
-    agent_function_name: String,
+    agent_client: AgentClient,
 }
 
 impl AppState {
     async fn new() -> Result<Self, Error> {
         let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
-        let secrets_client = aws_sdk_secretsmanager::Client::new(&config);
         let lambda_client = aws_sdk_lambda::Client::new(&config);
 
-        let db_secret_arn =
-            std::env::var("DB_SECRET_ARN").map_err(|_| "DB_SECRET_ARN not set")?;
-
-        let db_secret = secrets_client
-            .get_secret_value()
-            .secret_id(&db_secret_arn)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to get DB secret: {}", e))?;
-
-        let db_creds: serde_json::Value =
-            serde_json::from_str(db_secret.secret_string().unwrap_or("{}"))?;
-
-        let db_host = std::env::var("DB_HOST").map_err(|_| "DB_HOST not set")?;
-        let db_name = std::env::var("DB_NAME").unwrap_or_else(|_| "second_brain".to_string());
-        let db_user = db_creds["username"].as_str().unwrap_or("sbadmin");
-        let db_pass = db_creds["password"].as_str().unwrap_or("");
-
-        let database_url = format!(
-            "postgres://{}:{}@{}:5432/{}",
-            db_user, db_pass, db_host, db_name
-        );
-
-        let db_pool = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(&database_url)
+        let db_pool = shared::db::connect(&config)
             .await
             .map_err(|e| format!("Failed to connect to database: {}", e))?;
 
@@ -87,8 +67,7 @@ impl AppState {
 
         Ok(Self {
             db_pool,
-            lambda_client,
-            agent_function_name,
+            agent_client: AgentClient::new(lambda_client, agent_function_name),
         })
     }
 }
@@ -100,6 +79,7 @@ struct BriefingUser {
     email: Option<String>,
     timezone: String,
     preferred_channel: String,
+    family_ids: Vec<Uuid>,
 }
 
 async fn get_users_for_briefing(
@@ -120,7 +100,8 @@ async fn get_users_for_briefing(
                 WHEN unp.push_enabled THEN 'push'
                 WHEN unp.email_enabled THEN 'email'
                 ELSE 'push'
-            END as preferred_channel
+            END as preferred_channel,
+            ARRAY(SELECT fm.family_id FROM family_members fm WHERE fm.user_id = u.id) as family_ids
         FROM users u
         LEFT JOIN user_notification_preferences unp ON unp.user_id = u.id
         WHERE
@@ -144,42 +125,33 @@ async fn trigger_briefing(
     state: &AppState,
     user: &BriefingUser,
     briefing_type: &str,
-) -> Result<(), Error> {
-    let message = match briefing_type {
-        "morning" => "Generate my morning briefing".to_string(),
-        "evening" => "Generate my evening summary".to_string(),
-        _ => format!("Generate my {} briefing", briefing_type),
-    };
-
-    let request = AgentRequest {
-        message,
-        user_id: user.user_id.to_string(),
-        family_ids: vec![], // Would fetch from DB in production
-        intent: "briefing".to_string(),
-        source: "scheduler".to_string(),
-    };
-
-    let payload = serde_json::to_vec(&request)
-        .map_err(|e| format!("Failed to serialize request: {}", e))?;
+) -> Result<BriefingOutcome, Error> {
+    if todays_briefing(&state.db_pool, user.user_id, briefing_type)
+        .await?
+        .is_some()
+    {
+        return Ok(BriefingOutcome::AlreadyGenerated);
+    }
 
-    // Invoke agent asynchronously (don't wait for response)
-    state
-        .lambda_client
-        .invoke()
-        .function_name(&state.agent_function_name)
-        .invocation_type(aws_sdk_lambda::types::InvocationType::Event) // Async
-        .payload(Blob::new(payload))
-        .send()
-        .await
-        .map_err(|e| format!("Failed to invoke agent: {}", e))?;
+    let briefing = generate_briefing(
+        &state.db_pool,
+        &state.agent_client,
+        user.user_id,
+        &user.family_ids,
+        briefing_type,
+        "scheduler",
+    )
+    .await?;
 
     info!(
         user_id = %user.user_id,
         briefing_type = briefing_type,
-        "Triggered briefing generation"
+        briefing_id = %briefing.id,
+        generation_time_ms = briefing.generation_time_ms,
+        "Generated and stored briefing"
     );
 
-    Ok(())
+    Ok(BriefingOutcome::Generated)
 }
 
 async fn handler(
@@ -204,33 +176,59 @@ async fn handler(
 
     info!(users_found = users.len(), "Found users for briefing");
 
+    let users_processed = users.len() as u32;
     let mut briefings_triggered = 0u32;
+    let mut already_generated = 0u32;
     let mut errors = 0u32;
 
-    // Trigger briefings for each user
-    for user in &users {
-        match trigger_briefing(&state, user, &briefing_type).await {
-            Ok(_) => briefings_triggered += 1,
-            Err(e) => {
+    // Generate briefings concurrently; each one waits on the agent for several seconds
+    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_BRIEFINGS));
+    let mut tasks = JoinSet::new();
+
+    for user in users {
+        let state = Arc::clone(&state);
+        let semaphore = Arc::clone(&semaphore);
+        let briefing_type = briefing_type.clone();
+
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let result = trigger_briefing(&state, &user, &briefing_type)
+                .await
+                .map_err(|e| e.to_string());
+            (user.user_id, result)
+        });
+    }
+
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((_, Ok(BriefingOutcome::Generated))) => briefings_triggered += 1,
+            Ok((_, Ok(BriefingOutcome::AlreadyGenerated))) => already_generated += 1,
+            Ok((user_id, Err(e))) => {
                 error!(
-                    user_id = %user.user_id,
+                    user_id = %user_id,
                     error = %e,
-                    "Failed to trigger briefing"
+                    "Failed to generate briefing"
                 );
                 errors += 1;
             }
+            Err(e) => {
+                error!(error = %e, "Briefing task panicked");
+                errors += 1;
+            }
         }
     }
 
     let response = DispatcherResponse {
-        users_processed: users.len() as u32,
+        users_processed,
         briefings_triggered,
+        already_generated,
         errors,
     };
 
     info!(
         users_processed = response.users_processed,
         briefings_triggered = response.briefings_triggered,
+        already_generated = response.already_generated,
         errors = response.errors,
         "Briefing dispatch complete"
     );
@@ -250,7 +248,7 @@ async fn main() -> Result<(), Error> {
 
     run(service_fn(move |event| {
         let state = state_clone.clone();
-        async move { handler(state, event).await }
+        async move { metrics::track_invocation("briefing_dispatcher", handler(state, event)).await }
     }))
     .await
 }
@@ -1,18 +1,67 @@
-//! Calendar Sync Lambda - Syncs external calendars (Google, Outlook).
+//! Calendar Sync Lambda - Syncs external calendars (Google, Outlook, iCal feeds).
 //!
 //! This Lambda runs on a schedule (EventBridge) to sync calendar events
 //! from connected external calendars into the Second Brain database.
+//!
+//! Google calendars sync incrementally: the first run (and a daily resync
+//! that rolls the window forward) lists the whole window and stores Google's
+//! `nextSyncToken` in `calendar_sync_state`; later runs send the token and
+//! receive only changed events, including cancellations, which are deleted.
+//!
+//! Users sync concurrently (each with a timeout), and one user's failure is
+//! reported in the response without affecting the others.
+//!
+//! After syncing, events changed since their last extraction have their
+//! attendees linked to person entities and are recorded as meeting facts (see
+//! `shared::calendar_extraction`).
+//!
+//! iCal feed subscriptions (`calendar_subscriptions`) are fetched with the
+//! ETag and Last-Modified from the previous fetch, so an unchanged feed costs
+//! a 304 and no database writes. Changed feeds are parsed and expanded with
+//! `shared::ical`, and occurrences that disappeared from the feed are removed.
 
 use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
 use lambda_runtime::{run, service_fn, Error, LambdaEvent};
 use serde::{Deserialize, Serialize};
-use sqlx::postgres::PgPoolOptions;
+use shared::calendar_extraction::{
+    attendee_name, is_person_email, meeting_fact, ExtractionPreferences, MAX_EVENTS_PER_RUN,
+};
+use shared::ical::{expand, parse_feed};
+use shared::metrics;
+use shared::public_http;
+use shared::recurrence::user_timezone;
+use shared::tag_rules::apply_tag_rules;
 use sqlx::PgPool;
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 use tracing::{error, info, warn};
 use tracing_subscriber::EnvFilter;
 use uuid::Uuid;
 
+/// Days ahead of now that events are synced
+const SYNC_DAYS: i64 = 30;
+
+/// Hours between full Google resyncs, which move the sync window forward
+const FULL_RESYNC_HOURS: i64 = 24;
+
+/// Google calendar synced for each connection
+const GOOGLE_CALENDAR_ID: &str = "primary";
+
+/// Largest iCal feed downloaded
+const MAX_FEED_BYTES: usize = 10 * 1024 * 1024;
+
+/// Timeout for fetching an iCal feed
+const FEED_TIMEOUT_SECS: u64 = 30;
+
+/// Users synced concurrently (bounded by the DB pool size)
+const MAX_CONCURRENT_USERS: usize = 5;
+
+/// Time allowed for one user's sync; the rest is picked up next run
+const USER_SYNC_TIMEOUT_SECS: u64 = 60;
+
 /// EventBridge scheduled event
 #[derive(Debug, Deserialize)]
 struct ScheduledEvent {
@@ -23,12 +72,59 @@ struct ScheduledEvent {
 }
 
 /// Sync response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize)]
 struct SyncResponse {
     users_synced: u32,
+    users_failed: u32,
+    feeds_synced: u32,
+    feeds_unchanged: u32,
     events_updated: u32,
     events_created: u32,
-    errors: Vec<String>,
+    events_removed: u32,
+    events_extracted: u32,
+    attendees_linked: u32,
+    entities_created: u32,
+    facts_recorded: u32,
+    /// What failed, per user; the other users' results still count
+    failures: Vec<SyncFailure>,
+}
+
+/// A failed part of a user's sync
+#[derive(Debug, Serialize)]
+struct SyncFailure {
+    user_id: String,
+    /// `google`, `feed:{id}`, `extraction` or `timeout`
+    source: String,
+    error: String,
+}
+
+/// Everything to sync for one user
+#[derive(Debug, Default)]
+struct UserSyncJob {
+    google: Option<CalendarConnection>,
+    subscriptions: Vec<CalendarSubscription>,
+}
+
+/// Outcome of one user's sync
+#[derive(Debug, Default)]
+struct UserSyncReport {
+    feeds_synced: u32,
+    feeds_unchanged: u32,
+    events_created: u32,
+    events_updated: u32,
+    events_removed: u32,
+    extraction: Extraction,
+    failures: Vec<SyncFailure>,
+}
+
+impl UserSyncReport {
+    fn fail(&mut self, user_id: Uuid, source: impl Into<String>, error: impl ToString) {
+        self.failures.push(SyncFailure {
+            user_id: user_id.to_string(),
+            source: source.into(),
+            error: error.to_string(),
+        });
+    }
 }
 
 /// User calendar connection info
@@ -39,6 +135,86 @@ struct CalendarConnection {
     secret_name: String,
 }
 
+/// iCal feed subscription
+#[derive(Debug, sqlx::FromRow)]
+struct CalendarSubscription {
+    id: Uuid,
+    user_id: Uuid,
+    feed_url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+/// Result of syncing a changed iCal feed
+#[derive(Debug, Default)]
+struct FeedSync {
+    created: u32,
+    updated: u32,
+    removed: u32,
+}
+
+/// Incremental sync state for a connection's calendar
+#[derive(Debug, sqlx::FromRow)]
+struct SyncState {
+    sync_token: Option<String>,
+    last_full_sync_at: Option<DateTime<Utc>>,
+}
+
+/// Events to fetch from Google
+enum GoogleSyncRequest<'a> {
+    /// Every event in a window
+    Full {
+        time_min: DateTime<Utc>,
+        time_max: DateTime<Utc>,
+    },
+    /// Events changed since the sync that returned `sync_token`
+    Incremental { sync_token: &'a str },
+}
+
+/// Events fetched from Google, and the token for the next incremental sync
+struct GoogleSyncResult {
+    events: Vec<GoogleCalendarEvent>,
+    next_sync_token: Option<String>,
+}
+
+/// Result of syncing a user's Google calendar
+#[derive(Debug, Default)]
+struct GoogleSync {
+    created: u32,
+    updated: u32,
+    removed: u32,
+    full: bool,
+}
+
+/// Calendar event awaiting extraction
+#[derive(Debug, sqlx::FromRow)]
+struct ExtractionEvent {
+    id: Uuid,
+    title: String,
+    start_time: DateTime<Utc>,
+    visibility_tier: i16,
+    extracted_fact_id: Option<Uuid>,
+}
+
+/// Event attendee, with the entity it's linked to
+#[derive(Debug, sqlx::FromRow)]
+struct AttendeeRow {
+    id: Uuid,
+    entity_id: Option<Uuid>,
+    entity_name: Option<String>,
+    email: Option<String>,
+    display_name: Option<String>,
+}
+
+/// Result of extracting a user's events
+#[derive(Debug, Default)]
+struct Extraction {
+    events: u32,
+    attendees_linked: u32,
+    entities_created: u32,
+    facts_recorded: u32,
+}
+
 /// Google Calendar tokens from Secrets Manager
 #[derive(Debug, Deserialize)]
 struct GoogleTokens {
@@ -54,7 +230,10 @@ struct GoogleCalendarEvent {
     summary: Option<String>,
     description: Option<String>,
     location: Option<String>,
+    /// Absent on cancelled events returned by incremental syncs
+    #[serde(default)]
     start: GoogleEventTime,
+    #[serde(default)]
     end: GoogleEventTime,
     attendees: Option<Vec<GoogleAttendee>>,
     #[serde(rename = "recurringEventId")]
@@ -62,7 +241,7 @@ struct GoogleCalendarEvent {
     status: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 struct GoogleEventTime {
     #[serde(rename = "dateTime")]
     date_time: Option<String>,
@@ -87,6 +266,9 @@ struct GoogleCalendarListResponse {
     items: Option<Vec<GoogleCalendarEvent>>,
     #[serde(rename = "nextPageToken")]
     next_page_token: Option<String>,
+    /// Returned on the last page
+    #[serde(rename = "nextSyncToken")]
+    next_sync_token: Option<String>,
 }
 
 /// Application state
@@ -94,6 +276,9 @@ struct AppState {
     db_pool: PgPool,
     secrets_client: aws_sdk_secretsmanager::Client,
     http_client: reqwest::Client,
+    /// For subscribed feeds, which are user-supplied URLs: only connects to
+    /// public addresses
+    feed_client: reqwest::Client,
     google_client_id: String,
     google_client_secret: String,
 }
@@ -104,32 +289,7 @@ impl AppState {
         let secrets_client = aws_sdk_secretsmanager::Client::new(&config);
 
         // Get database credentials
-        let db_secret_arn =
-            std::env::var("DB_SECRET_ARN").map_err(|_| "DB_SECRET_ARN not set")?;
-
-        let db_secret = secrets_client
-            .get_secret_value()
-            .secret_id(&db_secret_arn)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to get DB secret: {}", e))?;
-
-        let db_creds: serde_json::Value =
-            serde_json::from_str(db_secret.secret_string().unwrap_or("{}"))?;
-
-        let db_host = std::env::var("DB_HOST").map_err(|_| "DB_HOST not set")?;
-        let db_name = std::env::var("DB_NAME").unwrap_or_else(|_| "second_brain".to_string());
-        let db_user = db_creds["username"].as_str().unwrap_or("sbadmin");
-        let db_pass = db_creds["password"].as_str().unwrap_or("");
-
-        let database_url = format!(
-            "postgres://{}:{}@{}:5432/{}",
-            db_user, db_pass, db_host, db_name
-        );
-
-        let db_pool = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(&database_url)
+        let db_pool = shared::db::connect(&config)
             .await
             .map_err(|e| format!("Failed to connect to database: {}", e))?;
 
@@ -137,20 +297,17 @@ impl AppState {
         let google_secret_arn = std::env::var("GOOGLE_OAUTH_SECRET_ARN")
             .unwrap_or_else(|_| "second-brain/google-oauth".to_string());
 
-        let google_secret = secrets_client
-            .get_secret_value()
-            .secret_id(&google_secret_arn)
-            .send()
+        let google_secret = shared::secrets::get_secret(&secrets_client, &google_secret_arn)
             .await
             .map_err(|e| format!("Failed to get Google OAuth secret: {}", e))?;
 
-        let google_creds: serde_json::Value =
-            serde_json::from_str(google_secret.secret_string().unwrap_or("{}"))?;
+        let google_creds: serde_json::Value = serde_json::from_str(&google_secret)?;
 
         Ok(Self {
             db_pool,
             secrets_client,
             http_client: reqwest::Client::new(),
+            feed_client: public_http::client(std::time::Duration::from_secs(FEED_TIMEOUT_SECS))?,
             google_client_id: google_creds["client_id"]
                 .as_str()
                 .unwrap_or("")
@@ -233,26 +390,39 @@ impl AppState {
             .to_string())
     }
 
-    /// Fetch events from Google Calendar API
+    /// Fetch events from Google Calendar API.
+    ///
+    /// Returns `None` if Google rejected the sync token (410 Gone), in which
+    /// case a full sync is needed.
     async fn fetch_google_events(
         &self,
         access_token: &str,
-        time_min: DateTime<Utc>,
-        time_max: DateTime<Utc>,
-    ) -> Result<Vec<GoogleCalendarEvent>, Error> {
+        request: &GoogleSyncRequest<'_>,
+    ) -> Result<Option<GoogleSyncResult>, Error> {
         let mut all_events = Vec::new();
         let mut page_token: Option<String> = None;
 
         loop {
+            // orderBy and the time bounds can't be combined with a sync token,
+            // so the full sync leaves out orderBy too
             let mut url = format!(
-                "https://www.googleapis.com/calendar/v3/calendars/primary/events?\
-                timeMin={}&timeMax={}&singleEvents=true&orderBy=startTime&maxResults=250",
-                urlencoding::encode(&time_min.to_rfc3339()),
-                urlencoding::encode(&time_max.to_rfc3339())
+                "https://www.googleapis.com/calendar/v3/calendars/{}/events?\
+                singleEvents=true&maxResults=250",
+                GOOGLE_CALENDAR_ID
             );
+            match request {
+                GoogleSyncRequest::Full { time_min, time_max } => url.push_str(&format!(
+                    "&timeMin={}&timeMax={}",
+                    urlencoding::encode(&time_min.to_rfc3339()),
+                    urlencoding::encode(&time_max.to_rfc3339())
+                )),
+                GoogleSyncRequest::Incremental { sync_token } => {
+                    url.push_str(&format!("&syncToken={}", urlencoding::encode(sync_token)))
+                }
+            }
 
             if let Some(token) = &page_token {
-                url.push_str(&format!("&pageToken={}", token));
+                url.push_str(&format!("&pageToken={}", urlencoding::encode(token)));
             }
 
             let response = self
@@ -263,6 +433,10 @@ impl AppState {
                 .await
                 .map_err(|e| format!("Calendar API request failed: {}", e))?;
 
+            if response.status() == reqwest::StatusCode::GONE {
+                return Ok(None);
+            }
+
             if !response.status().is_success() {
                 let error_text = response.text().await.unwrap_or_default();
                 return Err(format!("Calendar API error: {}", error_text).into());
@@ -279,18 +453,70 @@ impl AppState {
 
             page_token = calendar_response.next_page_token;
             if page_token.is_none() {
-                break;
+                return Ok(Some(GoogleSyncResult {
+                    events: all_events,
+                    next_sync_token: calendar_response.next_sync_token,
+                }));
             }
         }
+    }
 
-        Ok(all_events)
+    /// Sync state for a connection's calendar (empty if never synced)
+    async fn get_sync_state(&self, connection: &CalendarConnection) -> Result<SyncState, Error> {
+        let state: Option<SyncState> = sqlx::query_as(
+            r#"
+            SELECT sync_token, last_full_sync_at
+            FROM calendar_sync_state
+            WHERE user_id = $1 AND provider = $2 AND calendar_id = $3
+            "#,
+        )
+        .bind(connection.user_id)
+        .bind(&connection.provider)
+        .bind(GOOGLE_CALENDAR_ID)
+        .fetch_optional(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to get sync state: {}", e))?;
+
+        Ok(state.unwrap_or(SyncState {
+            sync_token: None,
+            last_full_sync_at: None,
+        }))
     }
 
-    /// Sync events for a single user
-    async fn sync_user_events(
+    /// Store the token for the next incremental sync
+    async fn save_sync_state(
         &self,
         connection: &CalendarConnection,
-    ) -> Result<(u32, u32), Error> {
+        sync_token: Option<&str>,
+        full: bool,
+    ) -> Result<(), Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO calendar_sync_state (
+                user_id, provider, calendar_id, sync_token, last_full_sync_at, last_synced_at
+            )
+            VALUES ($1, $2, $3, $4, CASE WHEN $5 THEN NOW() END, NOW())
+            ON CONFLICT (user_id, provider, calendar_id) DO UPDATE SET
+                sync_token = EXCLUDED.sync_token,
+                last_full_sync_at = COALESCE(EXCLUDED.last_full_sync_at, calendar_sync_state.last_full_sync_at),
+                last_synced_at = NOW(),
+                updated_at = NOW()
+            "#,
+        )
+        .bind(connection.user_id)
+        .bind(&connection.provider)
+        .bind(GOOGLE_CALENDAR_ID)
+        .bind(sync_token)
+        .bind(full)
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to save sync state: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Sync events for a single user
+    async fn sync_user_events(&self, connection: &CalendarConnection) -> Result<GoogleSync, Error> {
         // Get user's tokens from Secrets Manager
         let secret_value = self
             .secrets_client
@@ -310,25 +536,54 @@ impl AppState {
             tokens.access_token
         };
 
-        // Fetch events for next 30 days
+        let state = self.get_sync_state(connection).await?;
         let time_min = Utc::now();
-        let time_max = time_min + Duration::days(30);
-
-        let events = self
-            .fetch_google_events(&access_token, time_min, time_max)
-            .await?;
+        let time_max = time_min + Duration::days(SYNC_DAYS);
+        let resync_due = state
+            .last_full_sync_at
+            .is_none_or(|at| time_min - at >= Duration::hours(FULL_RESYNC_HOURS));
+
+        // Incremental sync, falling back to a full sync of the window when
+        // there's no token, it's time to move the window, or Google expired it
+        let incremental = match state.sync_token.as_deref() {
+            Some(sync_token) if !resync_due => {
+                let request = GoogleSyncRequest::Incremental { sync_token };
+                let result = self.fetch_google_events(&access_token, &request).await?;
+                if result.is_none() {
+                    info!("Sync token expired for user {}", connection.user_id);
+                }
+                result
+            }
+            _ => None,
+        };
+        let (result, full) = match incremental {
+            Some(result) => (result, false),
+            None => {
+                let request = GoogleSyncRequest::Full { time_min, time_max };
+                let result = self
+                    .fetch_google_events(&access_token, &request)
+                    .await?
+                    .ok_or("Calendar API rejected a full sync")?;
+                (result, true)
+            }
+        };
 
-        let mut created = 0u32;
-        let mut updated = 0u32;
+        let mut sync = GoogleSync {
+            full,
+            ..Default::default()
+        };
+        let mut seen_ids = Vec::with_capacity(result.events.len());
 
-        for event in events {
-            // Skip cancelled events
+        for event in &result.events {
+            // Cancelled events (incremental syncs report deletions this way)
             if event.status.as_deref() == Some("cancelled") {
+                sync.removed += self.delete_google_event(connection.user_id, &event.id).await?;
                 continue;
             }
 
             let (start_time, all_day) = parse_event_time(&event.start)?;
             let (end_time, _) = parse_event_time(&event.end)?;
+            seen_ids.push(event.id.clone());
 
             // Upsert event
             let result = sqlx::query_scalar::<_, bool>(
@@ -349,6 +604,7 @@ impl AppState {
                     end_time = EXCLUDED.end_time,
                     all_day = EXCLUDED.all_day,
                     is_recurring = EXCLUDED.is_recurring,
+                    synced_at = NOW(),
                     updated_at = NOW()
                 RETURNING (xmax = 0)
                 "#,
@@ -370,9 +626,9 @@ impl AppState {
             match result {
                 Ok(is_insert) => {
                     if is_insert {
-                        created += 1;
+                        sync.created += 1;
                     } else {
-                        updated += 1;
+                        sync.updated += 1;
                     }
                 }
                 Err(e) => {
@@ -395,6 +651,7 @@ impl AppState {
                         SELECT ce.id, $2, $3, $4
                         FROM calendar_events ce
                         WHERE ce.external_id = $1 AND ce.user_id = $5
+                          AND ce.external_provider = 'google'
                         ON CONFLICT (event_id, email) DO UPDATE SET
                             display_name = EXCLUDED.display_name,
                             response_status = EXCLUDED.response_status
@@ -411,7 +668,624 @@ impl AppState {
             }
         }
 
-        Ok((created, updated))
+        // A full sync lists the whole window, so anything else in it is gone
+        if full {
+            let removed = sqlx::query(
+                r#"
+                DELETE FROM calendar_events
+                WHERE user_id = $1 AND external_provider = 'google'
+                  AND start_time >= $2 AND start_time < $3
+                  AND NOT (external_id = ANY($4))
+                "#,
+            )
+            .bind(connection.user_id)
+            .bind(time_min)
+            .bind(time_max)
+            .bind(&seen_ids)
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| format!("Failed to remove deleted events: {}", e))?;
+            sync.removed += removed.rows_affected() as u32;
+        }
+
+        self.save_sync_state(connection, result.next_sync_token.as_deref(), full)
+            .await?;
+
+        Ok(sync)
+    }
+
+    /// Delete a cancelled Google event; a cancelled recurring event removes
+    /// its `{id}_{start}` instances too.
+    async fn delete_google_event(&self, user_id: Uuid, event_id: &str) -> Result<u32, Error> {
+        let escaped = event_id
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let instance_pattern = format!("{}\\_%", escaped);
+        let result = sqlx::query(
+            r#"
+            DELETE FROM calendar_events
+            WHERE user_id = $1 AND external_provider = 'google'
+              AND (external_id = $2 OR external_id LIKE $3)
+            "#,
+        )
+        .bind(user_id)
+        .bind(event_id)
+        .bind(&instance_pattern)
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to delete event {}: {}", event_id, e))?;
+
+        Ok(result.rows_affected() as u32)
+    }
+
+    /// Users with events changed since their last extraction
+    async fn get_users_to_extract(&self, user_id: Option<Uuid>) -> Result<Vec<Uuid>, Error> {
+        let users = sqlx::query_scalar(
+            r#"
+            SELECT DISTINCT user_id
+            FROM calendar_events
+            WHERE (extracted_at IS NULL OR updated_at > extracted_at)
+              AND ($1::uuid IS NULL OR user_id = $1)
+              AND NOT EXISTS (
+                  SELECT 1 FROM calendar_extraction_preferences p
+                  WHERE p.user_id = calendar_events.user_id AND NOT p.enabled
+              )
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to query users to extract: {}", e))?;
+
+        Ok(users)
+    }
+
+    /// Link a user's changed events' attendees to entities and record them as
+    /// facts, as their extraction preferences allow.
+    async fn extract_user_events(&self, user_id: Uuid) -> Result<Extraction, Error> {
+        let prefs: ExtractionPreferences = sqlx::query_as(
+            r#"
+            SELECT enabled, create_entities, record_facts
+            FROM calendar_extraction_preferences
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to get extraction preferences: {}", e))?
+        .unwrap_or_default();
+
+        let mut extraction = Extraction::default();
+        if !prefs.enabled {
+            return Ok(extraction);
+        }
+
+        let user_email: String = sqlx::query_scalar("SELECT email FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(&self.db_pool)
+            .await
+            .map_err(|e| format!("Failed to get user: {}", e))?;
+        let timezone = user_timezone(&self.db_pool, user_id).await?;
+
+        let events: Vec<ExtractionEvent> = sqlx::query_as(
+            r#"
+            SELECT id, title, start_time, visibility_tier, extracted_fact_id
+            FROM calendar_events
+            WHERE user_id = $1 AND (extracted_at IS NULL OR updated_at > extracted_at)
+            ORDER BY start_time
+            LIMIT $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(MAX_EVENTS_PER_RUN)
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to query events to extract: {}", e))?;
+
+        for event in events {
+            let attendees: Vec<AttendeeRow> = sqlx::query_as(
+                r#"
+                SELECT a.id, a.entity_id, e.name AS entity_name, a.email, a.display_name
+                FROM calendar_event_attendees a
+                LEFT JOIN entities e ON e.id = a.entity_id
+                WHERE a.event_id = $1
+                ORDER BY a.display_name NULLS LAST, a.email
+                "#,
+            )
+            .bind(event.id)
+            .fetch_all(&self.db_pool)
+            .await
+            .map_err(|e| format!("Failed to query attendees: {}", e))?;
+
+            let mut linked: Vec<(Uuid, String)> = Vec::new();
+            for attendee in attendees {
+                if let (Some(entity_id), Some(name)) = (attendee.entity_id, attendee.entity_name) {
+                    linked.push((entity_id, name));
+                    continue;
+                }
+
+                let email = match attendee.email.as_deref() {
+                    Some(email)
+                        if is_person_email(email) && !email.eq_ignore_ascii_case(&user_email) =>
+                    {
+                        email
+                    }
+                    _ => continue,
+                };
+
+                let entity = match self
+                    .find_person_entity(user_id, email, attendee.display_name.as_deref())
+                    .await?
+                {
+                    Some(entity) => Some(entity),
+                    None if prefs.create_entities => {
+                        extraction.entities_created += 1;
+                        Some(
+                            self.create_person_entity(
+                                user_id,
+                                email,
+                                attendee.display_name.as_deref(),
+                            )
+                            .await?,
+                        )
+                    }
+                    None => None,
+                };
+
+                if let Some((entity_id, name)) = entity {
+                    sqlx::query("UPDATE calendar_event_attendees SET entity_id = $2 WHERE id = $1")
+                        .bind(attendee.id)
+                        .bind(entity_id)
+                        .execute(&self.db_pool)
+                        .await
+                        .map_err(|e| format!("Failed to link attendee: {}", e))?;
+                    extraction.attendees_linked += 1;
+                    linked.push((entity_id, name));
+                }
+            }
+
+            linked.sort_by(|a, b| a.1.cmp(&b.1));
+            linked.dedup_by_key(|(entity_id, _)| *entity_id);
+
+            let fact_id = if prefs.record_facts && !linked.is_empty() {
+                extraction.facts_recorded += 1;
+                Some(self.record_meeting_fact(user_id, &event, &linked, timezone).await?)
+            } else {
+                event.extracted_fact_id
+            };
+
+            sqlx::query(
+                r#"
+                UPDATE calendar_events
+                SET extracted_at = NOW(), extracted_fact_id = $2
+                WHERE id = $1
+                "#,
+            )
+            .bind(event.id)
+            .bind(fact_id)
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| format!("Failed to mark event extracted: {}", e))?;
+            extraction.events += 1;
+        }
+
+        Ok(extraction)
+    }
+
+    /// The user's person entity for an attendee: by email attribute or alias,
+    /// else by name when exactly one entity has it.
+    async fn find_person_entity(
+        &self,
+        user_id: Uuid,
+        email: &str,
+        display_name: Option<&str>,
+    ) -> Result<Option<(Uuid, String)>, Error> {
+        let by_email: Option<(Uuid, String)> = sqlx::query_as(
+            r#"
+            SELECT e.id, e.name
+            FROM entities e
+            WHERE e.owner_type = 'user' AND e.owner_id = $1 AND e.entity_type = 'person'
+              AND (
+                  LOWER($2) = ANY(SELECT LOWER(alias) FROM UNNEST(e.aliases) AS alias)
+                  OR EXISTS (
+                      SELECT 1 FROM entity_attributes ea
+                      WHERE ea.entity_id = e.id AND ea.attribute_name = 'email'
+                        AND ea.valid_to IS NULL AND LOWER(ea.attribute_value) = LOWER($2)
+                  )
+              )
+            ORDER BY e.created_at
+            LIMIT 1
+            "#,
+        )
+        .bind(user_id)
+        .bind(email.trim())
+        .fetch_optional(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to match attendee by email: {}", e))?;
+
+        if by_email.is_some() {
+            return Ok(by_email);
+        }
+
+        let name = match display_name.map(str::trim).filter(|n| !n.is_empty()) {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        let by_name: Vec<(Uuid, String)> = sqlx::query_as(
+            r#"
+            SELECT id, name
+            FROM entities
+            WHERE owner_type = 'user' AND owner_id = $1 AND entity_type = 'person'
+              AND normalized_name = LOWER(TRIM($2))
+            LIMIT 2
+            "#,
+        )
+        .bind(user_id)
+        .bind(name)
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to match attendee by name: {}", e))?;
+
+        // Ambiguous names are left unlinked rather than guessed
+        Ok(match <[_; 1]>::try_from(by_name) {
+            Ok([entity]) => {
+                self.add_entity_email(entity.0, user_id, email).await?;
+                Some(entity)
+            }
+            Err(_) => None,
+        })
+    }
+
+    /// Create a person entity for an unknown attendee
+    async fn create_person_entity(
+        &self,
+        user_id: Uuid,
+        email: &str,
+        display_name: Option<&str>,
+    ) -> Result<(Uuid, String), Error> {
+        let name = attendee_name(display_name, email);
+        let entity_id: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO entities (entity_type, name, aliases, metadata,
+                                  owner_type, owner_id, created_by, visibility_tier)
+            VALUES ('person', $1, $2, '{"source": "calendar"}', 'user', $3, $3, 3)
+            RETURNING id
+            "#,
+        )
+        .bind(&name)
+        .bind(vec![email.trim().to_lowercase()])
+        .bind(user_id)
+        .fetch_one(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to create entity: {}", e))?;
+
+        self.add_entity_email(entity_id, user_id, email).await?;
+        info!("Created person entity {} for attendee of user {}", entity_id, user_id);
+
+        Ok((entity_id, name))
+    }
+
+    /// Record an attendee's email on the entity so later meetings match it
+    async fn add_entity_email(&self, entity_id: Uuid, user_id: Uuid, email: &str) -> Result<(), Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO entity_attributes (entity_id, attribute_name, attribute_value, created_by)
+            SELECT $1, 'email', LOWER($2), $3
+            WHERE NOT EXISTS (
+                SELECT 1 FROM entity_attributes
+                WHERE entity_id = $1 AND attribute_name = 'email'
+                  AND valid_to IS NULL AND LOWER(attribute_value) = LOWER($2)
+            )
+            "#,
+        )
+        .bind(entity_id)
+        .bind(email.trim())
+        .bind(user_id)
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to add entity email: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Insert (or update, for a changed event) the meeting's fact and its
+    /// entity mentions.
+    async fn record_meeting_fact(
+        &self,
+        user_id: Uuid,
+        event: &ExtractionEvent,
+        attendees: &[(Uuid, String)],
+        timezone: Tz,
+    ) -> Result<Uuid, Error> {
+        let names: Vec<String> = attendees.iter().map(|(_, name)| name.clone()).collect();
+        let date = event.start_time.with_timezone(&timezone).date_naive();
+        let content = meeting_fact(&event.title, &names, date);
+        // A one-on-one meeting is about that person
+        let about = match attendees {
+            [(entity_id, _)] => Some(*entity_id),
+            _ => None,
+        };
+
+        let mut tx = self
+            .db_pool
+            .begin()
+            .await
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        let updated: Option<Uuid> = match event.extracted_fact_id {
+            Some(fact_id) => sqlx::query_scalar(
+                r#"
+                UPDATE facts
+                SET content = $2, about_entity_id = $3, valid_from = $4, valid_to = $4,
+                    visibility_tier = $5, updated_at = NOW()
+                WHERE id = $1 AND superseded_by IS NULL
+                RETURNING id
+                "#,
+            )
+            .bind(fact_id)
+            .bind(&content)
+            .bind(about)
+            .bind(date)
+            .bind(event.visibility_tier)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to update fact: {}", e))?,
+            None => None,
+        };
+
+        let fact_id = match updated {
+            Some(fact_id) => fact_id,
+            None => sqlx::query_scalar(
+                r#"
+                INSERT INTO facts (
+                    owner_type, owner_id, created_by, content, source,
+                    visibility_tier, about_entity_id, valid_from, valid_to
+                )
+                VALUES ('user', $1, $1, $2, 'calendar', $3, $4, $5, $5)
+                RETURNING id
+                "#,
+            )
+            .bind(user_id)
+            .bind(&content)
+            .bind(event.visibility_tier)
+            .bind(about)
+            .bind(date)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to record fact: {}", e))?,
+        };
+
+        sqlx::query("DELETE FROM entity_mentions WHERE fact_id = $1")
+            .bind(fact_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to clear mentions: {}", e))?;
+
+        let entity_ids: Vec<Uuid> = attendees.iter().map(|(id, _)| *id).collect();
+        sqlx::query(
+            r#"
+            INSERT INTO entity_mentions (fact_id, entity_id, role)
+            SELECT $1, entity_id, 'reference' FROM UNNEST($2::uuid[]) AS entity_id
+            "#,
+        )
+        .bind(fact_id)
+        .bind(&entity_ids)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to record mentions: {}", e))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| format!("Failed to commit fact: {}", e))?;
+
+        // Rules are re-checked by tag_rule_backfill if this fails
+        if let Err(e) = apply_tag_rules(&self.db_pool, fact_id).await {
+            warn!(fact_id = %fact_id, error = %e, "Failed to apply tag rules");
+        }
+
+        Ok(fact_id)
+    }
+
+    /// iCal feed subscriptions, for one user or everyone
+    async fn get_subscriptions(
+        &self,
+        user_id: Option<Uuid>,
+    ) -> Result<Vec<CalendarSubscription>, Error> {
+        let subscriptions = sqlx::query_as(
+            r#"
+            SELECT id, user_id, feed_url, etag, last_modified
+            FROM calendar_subscriptions
+            WHERE $1::uuid IS NULL OR user_id = $1
+            ORDER BY last_synced_at NULLS FIRST
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to query calendar subscriptions: {}", e))?;
+
+        Ok(subscriptions)
+    }
+
+    /// Fetch and import an iCal feed; `None` if it hasn't changed since the
+    /// last sync.
+    async fn sync_subscription(
+        &self,
+        subscription: &CalendarSubscription,
+    ) -> Result<Option<FeedSync>, Error> {
+        // Literal IP hosts aren't resolved, so the client can't check them
+        let feed_url = reqwest::Url::parse(&subscription.feed_url)
+            .map_err(|e| format!("Invalid feed URL: {}", e))?;
+        if !public_http::is_public_host(&feed_url) {
+            return Err("Feed host is not public".into());
+        }
+
+        let mut request = self
+            .feed_client
+            .get(feed_url)
+            .header("Accept", "text/calendar");
+        if let Some(etag) = &subscription.etag {
+            request = request.header("If-None-Match", etag);
+        }
+        if let Some(last_modified) = &subscription.last_modified {
+            request = request.header("If-Modified-Since", last_modified);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Feed request failed: {}", e))?;
+
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            self.record_subscription_sync(subscription.id, None, None)
+                .await?;
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(format!("Feed returned HTTP {}", response.status()).into());
+        }
+        if response
+            .content_length()
+            .is_some_and(|length| length as usize > MAX_FEED_BYTES)
+        {
+            return Err("Feed is too large".into());
+        }
+
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header("etag");
+        let last_modified = header("last-modified");
+
+        let body = response
+            .text()
+            .await
+            .map_err(|e| format!("Failed to read feed: {}", e))?;
+        if body.len() > MAX_FEED_BYTES {
+            return Err("Feed is too large".into());
+        }
+
+        let timezone = user_timezone(&self.db_pool, subscription.user_id).await?;
+        let events = parse_feed(&body, timezone)?;
+
+        let time_min = Utc::now();
+        let time_max = time_min + Duration::days(SYNC_DAYS);
+        let instances = expand(&events, time_min, time_max);
+
+        let calendar_id = subscription.id.to_string();
+        let mut sync = FeedSync::default();
+        let mut external_ids = Vec::with_capacity(instances.len());
+
+        for instance in &instances {
+            let event = instance.event;
+            let result = sqlx::query_scalar::<_, bool>(
+                r#"
+                INSERT INTO calendar_events (
+                    user_id, external_id, external_provider, external_calendar_id,
+                    title, description, location,
+                    start_time, end_time, all_day,
+                    is_recurring, recurrence_rule, visibility_tier
+                )
+                VALUES ($1, $2, 'ics', $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                ON CONFLICT (external_id, external_provider, user_id)
+                DO UPDATE SET
+                    external_calendar_id = EXCLUDED.external_calendar_id,
+                    title = EXCLUDED.title,
+                    description = EXCLUDED.description,
+                    location = EXCLUDED.location,
+                    start_time = EXCLUDED.start_time,
+                    end_time = EXCLUDED.end_time,
+                    all_day = EXCLUDED.all_day,
+                    is_recurring = EXCLUDED.is_recurring,
+                    recurrence_rule = EXCLUDED.recurrence_rule,
+                    synced_at = NOW(),
+                    updated_at = NOW()
+                RETURNING (xmax = 0)
+                "#,
+            )
+            .bind(subscription.user_id)
+            .bind(&instance.external_id)
+            .bind(&calendar_id)
+            .bind(event.summary.as_deref().unwrap_or("(No title)"))
+            .bind(&event.description)
+            .bind(&event.location)
+            .bind(instance.start)
+            .bind(instance.end)
+            .bind(event.all_day)
+            .bind(instance.is_recurring)
+            .bind(&event.rrule)
+            .bind(3i16) // Default visibility tier
+            .fetch_one(&self.db_pool)
+            .await;
+
+            match result {
+                Ok(true) => sync.created += 1,
+                Ok(false) => sync.updated += 1,
+                Err(e) => warn!("Failed to upsert event {}: {}", instance.external_id, e),
+            }
+            external_ids.push(instance.external_id.clone());
+        }
+
+        // Upcoming occurrences no longer in the feed were deleted or cancelled
+        let removed = sqlx::query(
+            r#"
+            DELETE FROM calendar_events
+            WHERE user_id = $1 AND external_provider = 'ics' AND external_calendar_id = $2
+              AND end_time > $3 AND NOT (external_id = ANY($4))
+            "#,
+        )
+        .bind(subscription.user_id)
+        .bind(&calendar_id)
+        .bind(time_min)
+        .bind(&external_ids)
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to remove stale events: {}", e))?;
+        sync.removed = removed.rows_affected() as u32;
+
+        self.record_subscription_sync(subscription.id, Some((etag, last_modified)), None)
+            .await?;
+
+        Ok(Some(sync))
+    }
+
+    /// Store a subscription's sync result. `validators` (ETag, Last-Modified)
+    /// are only replaced after a full import, so a failed one is refetched.
+    async fn record_subscription_sync(
+        &self,
+        subscription_id: Uuid,
+        validators: Option<(Option<String>, Option<String>)>,
+        error: Option<&str>,
+    ) -> Result<(), Error> {
+        let replace = validators.is_some();
+        let (etag, last_modified) = validators.unwrap_or_default();
+        sqlx::query(
+            r#"
+            UPDATE calendar_subscriptions
+            SET etag = CASE WHEN $4 THEN $2 ELSE etag END,
+                last_modified = CASE WHEN $4 THEN $3 ELSE last_modified END,
+                last_synced_at = CASE WHEN $5::text IS NULL THEN NOW() ELSE last_synced_at END,
+                last_error = $5,
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(subscription_id)
+        .bind(etag)
+        .bind(last_modified)
+        .bind(replace)
+        .bind(error)
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to update subscription: {}", e))?;
+
+        Ok(())
     }
 }
 
@@ -433,63 +1307,190 @@ fn parse_event_time(time: &GoogleEventTime) -> Result<(DateTime<Utc>, bool), Err
     }
 }
 
+/// Sync one user's Google calendar and feeds, then extract their events.
+/// Each part's failure is reported without stopping the others.
+async fn sync_user(state: &AppState, user_id: Uuid, job: UserSyncJob) -> UserSyncReport {
+    let mut report = UserSyncReport::default();
+
+    if let Some(connection) = &job.google {
+        match state.sync_user_events(connection).await {
+            Ok(sync) => {
+                info!(
+                    "Synced user {} ({}): {} created, {} updated, {} removed",
+                    user_id,
+                    if sync.full { "full" } else { "incremental" },
+                    sync.created,
+                    sync.updated,
+                    sync.removed
+                );
+                report.events_created += sync.created;
+                report.events_updated += sync.updated;
+                report.events_removed += sync.removed;
+            }
+            Err(e) => {
+                error!("Failed to sync user {}: {}", user_id, e);
+                report.fail(user_id, "google", e);
+            }
+        }
+    }
+
+    for subscription in &job.subscriptions {
+        match state.sync_subscription(subscription).await {
+            Ok(Some(sync)) => {
+                info!(
+                    "Synced feed {} for user {}: {} created, {} updated, {} removed",
+                    subscription.id, user_id, sync.created, sync.updated, sync.removed
+                );
+                report.feeds_synced += 1;
+                report.events_created += sync.created;
+                report.events_updated += sync.updated;
+                report.events_removed += sync.removed;
+            }
+            Ok(None) => report.feeds_unchanged += 1,
+            Err(e) => {
+                error!("Failed to sync feed {}: {}", subscription.id, e);
+                let message = e.to_string();
+                if let Err(e) = state
+                    .record_subscription_sync(subscription.id, None, Some(&message))
+                    .await
+                {
+                    warn!("Failed to record feed error {}: {}", subscription.id, e);
+                }
+                report.fail(user_id, format!("feed:{}", subscription.id), message);
+            }
+        }
+    }
+
+    match state.extract_user_events(user_id).await {
+        Ok(extraction) => report.extraction = extraction,
+        Err(e) => {
+            error!("Failed to extract events for user {}: {}", user_id, e);
+            report.fail(user_id, "extraction", e);
+        }
+    }
+
+    report
+}
+
 async fn handler(
     state: Arc<AppState>,
     event: LambdaEvent<ScheduledEvent>,
 ) -> Result<SyncResponse, Error> {
     info!("Starting calendar sync");
 
-    let mut response = SyncResponse {
-        users_synced: 0,
-        events_updated: 0,
-        events_created: 0,
-        errors: Vec::new(),
+    let user_filter = match &event.payload.user_id {
+        Some(user_id) => {
+            Some(Uuid::parse_str(user_id).map_err(|e| format!("Invalid user_id: {}", e))?)
+        }
+        None => None,
     };
 
     // Get users to sync
-    let connections = if let Some(user_id) = &event.payload.user_id {
+    let connections = if let Some(user_uuid) = user_filter {
         // Sync specific user
-        let user_uuid = Uuid::parse_str(user_id)
-            .map_err(|e| format!("Invalid user_id: {}", e))?;
         vec![CalendarConnection {
             user_id: user_uuid,
             provider: "google".to_string(),
-            secret_name: format!("second-brain/calendar/{}", user_id),
+            secret_name: format!("second-brain/calendar/{}", user_uuid),
         }]
     } else {
         // Sync all connected users
         state.get_connected_users().await?
     };
+    let subscriptions = state.get_subscriptions(user_filter).await?;
+    let pending_extraction = state.get_users_to_extract(user_filter).await?;
 
-    info!("Found {} users with connected calendars", connections.len());
+    info!(
+        "Found {} users with connected calendars, {} iCal feed subscriptions, {} users with events to extract",
+        connections.len(),
+        subscriptions.len(),
+        pending_extraction.len()
+    );
 
+    // One job per user, so a user's calendars sync in order and their
+    // extraction sees everything just synced
+    let mut jobs: BTreeMap<Uuid, UserSyncJob> = BTreeMap::new();
     for connection in connections {
-        match state.sync_user_events(&connection).await {
-            Ok((created, updated)) => {
-                info!(
-                    "Synced user {}: {} created, {} updated",
-                    connection.user_id, created, updated
+        let user_id = connection.user_id;
+        jobs.entry(user_id).or_default().google = Some(connection);
+    }
+    for subscription in subscriptions {
+        jobs.entry(subscription.user_id)
+            .or_default()
+            .subscriptions
+            .push(subscription);
+    }
+    for user_id in pending_extraction {
+        jobs.entry(user_id).or_default();
+    }
+
+    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_USERS));
+    let mut tasks = JoinSet::new();
+
+    for (user_id, job) in jobs {
+        let state = Arc::clone(&state);
+        let semaphore = Arc::clone(&semaphore);
+
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let timeout = std::time::Duration::from_secs(USER_SYNC_TIMEOUT_SECS);
+            let report = tokio::time::timeout(timeout, sync_user(&state, user_id, job)).await;
+            (user_id, report)
+        });
+    }
+
+    let mut response = SyncResponse::default();
+    while let Some(joined) = tasks.join_next().await {
+        let (user_id, mut report) = match joined {
+            Ok((user_id, Ok(report))) => (user_id, report),
+            Ok((user_id, Err(_))) => {
+                warn!("Sync for user {} timed out", user_id);
+                let mut report = UserSyncReport::default();
+                report.fail(
+                    user_id,
+                    "timeout",
+                    format!("Sync took longer than {}s", USER_SYNC_TIMEOUT_SECS),
                 );
-                response.users_synced += 1;
-                response.events_created += created;
-                response.events_updated += updated;
+                (user_id, report)
             }
             Err(e) => {
-                error!("Failed to sync user {}: {}", connection.user_id, e);
-                response.errors.push(format!(
-                    "User {}: {}",
-                    connection.user_id, e
-                ));
+                error!("Calendar sync task panicked: {}", e);
+                response.users_failed += 1;
+                continue;
             }
+        };
+
+        if report.failures.is_empty() {
+            response.users_synced += 1;
+        } else {
+            info!("User {} synced with {} failures", user_id, report.failures.len());
+            response.users_failed += 1;
         }
+        response.feeds_synced += report.feeds_synced;
+        response.feeds_unchanged += report.feeds_unchanged;
+        response.events_created += report.events_created;
+        response.events_updated += report.events_updated;
+        response.events_removed += report.events_removed;
+        response.events_extracted += report.extraction.events;
+        response.attendees_linked += report.extraction.attendees_linked;
+        response.entities_created += report.extraction.entities_created;
+        response.facts_recorded += report.extraction.facts_recorded;
+        response.failures.append(&mut report.failures);
     }
 
     info!(
-        "Calendar sync complete: {} users, {} created, {} updated, {} errors",
+        "Calendar sync complete: {} users ({} failed), {} feeds ({} unchanged), {} created, {} updated, {} removed, {} extracted ({} attendees linked, {} entities created, {} facts)",
         response.users_synced,
+        response.users_failed,
+        response.feeds_synced,
+        response.feeds_unchanged,
         response.events_created,
         response.events_updated,
-        response.errors.len()
+        response.events_removed,
+        response.events_extracted,
+        response.attendees_linked,
+        response.entities_created,
+        response.facts_recorded
     );
 
     Ok(response)
@@ -506,7 +1507,7 @@ async fn main() -> Result<(), Error> {
 
     run(service_fn(move |event| {
         let state = Arc::clone(&state);
-        async move { handler(state, event).await }
+        async move { metrics::track_invocation("calendar_sync", handler(state, event)).await }
     }))
     .await
 }
@@ -0,0 +1,771 @@
+//! Contacts Sync Lambda - Syncs Google Contacts into person entities.
+//!
+//! Runs on a schedule for every row in `contact_connections` (written by the
+//! contacts OAuth callback). The first run lists all of a user's connections
+//! from the People API and stores its `nextSyncToken`; later runs fetch only
+//! changed and deleted contacts, falling back to a full sync when Google
+//! expires the token.
+//!
+//! Each contact is linked to a person entity (an existing one matched by email
+//! or unambiguous name, else a new one) and its emails, phone numbers and
+//! birthday are added as attributes without overwriting ones the user already
+//! has. New links are claimed with `ON CONFLICT DO NOTHING` inside the
+//! entity's transaction, so overlapping runs can't create duplicates.
+
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use shared::contacts::{
+    token_secret_name, ContactRecord, GoogleConnectionsPage, GooglePerson, GOOGLE_PERSON_FIELDS,
+    GOOGLE_PROVIDER,
+};
+use shared::metrics;
+use sqlx::{PgConnection, PgPool};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// Contacts requested per People API page (the maximum)
+const PAGE_SIZE: u32 = 1000;
+
+/// Users synced concurrently (bounded by the DB pool size)
+const MAX_CONCURRENT_USERS: usize = 5;
+
+/// Time allowed for one user's sync; the rest is picked up next run
+const USER_SYNC_TIMEOUT_SECS: u64 = 120;
+
+/// EventBridge scheduled event
+#[derive(Debug, Deserialize)]
+struct ScheduledEvent {
+    #[serde(default)]
+    detail_type: String,
+    /// Optional: sync only specific user
+    user_id: Option<String>,
+}
+
+/// Sync response
+#[derive(Debug, Default, Serialize)]
+struct SyncResponse {
+    users_synced: u32,
+    users_failed: u32,
+    contacts_created: u32,
+    contacts_linked: u32,
+    contacts_updated: u32,
+    contacts_unchanged: u32,
+    contacts_removed: u32,
+    attributes_added: u32,
+    /// Users whose sync failed; the other users' results still count
+    failures: Vec<SyncFailure>,
+}
+
+/// A user's failed sync
+#[derive(Debug, Serialize)]
+struct SyncFailure {
+    user_id: String,
+    error: String,
+}
+
+/// Outcome of one user's sync
+#[derive(Debug, Default)]
+struct ContactSync {
+    full: bool,
+    created: u32,
+    linked: u32,
+    updated: u32,
+    unchanged: u32,
+    removed: u32,
+    attributes_added: u32,
+}
+
+/// What happened to one contact
+enum ContactOutcome {
+    /// New person entity created
+    Created(u32),
+    /// Linked to an existing entity
+    Linked(u32),
+    /// Linked entity updated from a changed contact
+    Updated(u32),
+    /// Same etag as last sync, linked entity deleted, or claimed by another run
+    Unchanged,
+}
+
+/// Connected address book
+#[derive(Debug, sqlx::FromRow)]
+struct ContactConnection {
+    user_id: Uuid,
+    sync_token: Option<String>,
+}
+
+/// Google tokens from Secrets Manager
+#[derive(Debug, Deserialize)]
+struct GoogleTokens {
+    access_token: String,
+    refresh_token: Option<String>,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+    secrets_client: aws_sdk_secretsmanager::Client,
+    http_client: reqwest::Client,
+    google_client_id: String,
+    google_client_secret: String,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        let secrets_client = aws_sdk_secretsmanager::Client::new(&config);
+
+        // Get database credentials
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        // Get Google OAuth credentials
+        let google_secret_arn = std::env::var("GOOGLE_OAUTH_SECRET_ARN")
+            .unwrap_or_else(|_| "second-brain/google-oauth".to_string());
+
+        let google_secret = shared::secrets::get_secret(&secrets_client, &google_secret_arn)
+            .await
+            .map_err(|e| format!("Failed to get Google OAuth secret: {}", e))?;
+
+        let google_creds: serde_json::Value = serde_json::from_str(&google_secret)?;
+
+        Ok(Self {
+            db_pool,
+            secrets_client,
+            http_client: reqwest::Client::new(),
+            google_client_id: google_creds["client_id"].as_str().unwrap_or("").to_string(),
+            google_client_secret: google_creds["client_secret"]
+                .as_str()
+                .unwrap_or("")
+                .to_string(),
+        })
+    }
+
+    /// Connected users, or just `user_filter`'s connection
+    async fn get_connections(
+        &self,
+        user_filter: Option<Uuid>,
+    ) -> Result<Vec<ContactConnection>, Error> {
+        let connections = sqlx::query_as::<_, ContactConnection>(
+            r#"
+            SELECT user_id, sync_token
+            FROM contact_connections
+            WHERE provider = $1 AND ($2::uuid IS NULL OR user_id = $2)
+            ORDER BY last_synced_at NULLS FIRST
+            "#,
+        )
+        .bind(GOOGLE_PROVIDER)
+        .bind(user_filter)
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to get contact connections: {}", e))?;
+
+        Ok(connections)
+    }
+
+    /// Fresh access token for a user's contacts connection
+    async fn access_token(&self, user_id: Uuid) -> Result<String, Error> {
+        let secret_value = self
+            .secrets_client
+            .get_secret_value()
+            .secret_id(token_secret_name(user_id))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to get user tokens: {}", e))?;
+
+        let tokens: GoogleTokens =
+            serde_json::from_str(secret_value.secret_string().unwrap_or("{}"))?;
+
+        let refresh_token = match tokens.refresh_token {
+            Some(refresh_token) => refresh_token,
+            None => return Ok(tokens.access_token),
+        };
+
+        let params = [
+            ("refresh_token", refresh_token.as_str()),
+            ("client_id", &self.google_client_id),
+            ("client_secret", &self.google_client_secret),
+            ("grant_type", "refresh_token"),
+        ];
+
+        let response = self
+            .http_client
+            .post("https://oauth2.googleapis.com/token")
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| format!("Token refresh request failed: {}", e))?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(format!("Token refresh failed: {}", error_text).into());
+        }
+
+        let token_response: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse token response: {}", e))?;
+
+        Ok(token_response["access_token"]
+            .as_str()
+            .ok_or("Missing access_token in refresh response")?
+            .to_string())
+    }
+
+    /// List contacts, all of them or only changes since `sync_token`.
+    ///
+    /// Returns `None` if Google rejected the sync token, in which case a full
+    /// sync is needed.
+    async fn fetch_contacts(
+        &self,
+        access_token: &str,
+        sync_token: Option<&str>,
+    ) -> Result<Option<(Vec<GooglePerson>, Option<String>)>, Error> {
+        let mut people = Vec::new();
+        let mut page_token: Option<String> = None;
+
+        loop {
+            let mut url = format!(
+                "https://people.googleapis.com/v1/people/me/connections?\
+                personFields={}&pageSize={}&requestSyncToken=true",
+                GOOGLE_PERSON_FIELDS, PAGE_SIZE
+            );
+            if let Some(token) = sync_token {
+                url.push_str(&format!("&syncToken={}", urlencoding::encode(token)));
+            }
+            if let Some(token) = &page_token {
+                url.push_str(&format!("&pageToken={}", urlencoding::encode(token)));
+            }
+
+            let response = self
+                .http_client
+                .get(&url)
+                .header("Authorization", format!("Bearer {}", access_token))
+                .send()
+                .await
+                .map_err(|e| format!("People API request failed: {}", e))?;
+
+            let status = response.status();
+            if !status.is_success() {
+                let error_text = response.text().await.unwrap_or_default();
+                // Expired tokens come back as 410 or 400 EXPIRED_SYNC_TOKEN
+                if sync_token.is_some()
+                    && (status == reqwest::StatusCode::GONE
+                        || error_text.contains("EXPIRED_SYNC_TOKEN"))
+                {
+                    return Ok(None);
+                }
+                return Err(format!("People API error ({}): {}", status, error_text).into());
+            }
+
+            let page: GoogleConnectionsPage = response
+                .json()
+                .await
+                .map_err(|e| format!("Failed to parse People API response: {}", e))?;
+
+            people.extend(page.connections);
+
+            page_token = page.next_page_token;
+            if page_token.is_none() {
+                return Ok(Some((people, page.next_sync_token)));
+            }
+        }
+    }
+
+    /// Sync one user's contacts
+    async fn sync_user_contacts(
+        &self,
+        connection: &ContactConnection,
+    ) -> Result<ContactSync, Error> {
+        let user_id = connection.user_id;
+        let access_token = self.access_token(user_id).await?;
+
+        let incremental = match connection.sync_token.as_deref() {
+            Some(sync_token) => {
+                let result = self.fetch_contacts(&access_token, Some(sync_token)).await?;
+                if result.is_none() {
+                    info!("Contacts sync token expired for user {}", user_id);
+                }
+                result
+            }
+            None => None,
+        };
+
+        let (people, next_sync_token, full) = match incremental {
+            Some((people, token)) => (people, token, false),
+            None => {
+                let (people, token) = self
+                    .fetch_contacts(&access_token, None)
+                    .await?
+                    .ok_or("People API rejected a full sync")?;
+                (people, token, true)
+            }
+        };
+
+        let mut sync = ContactSync {
+            full,
+            ..Default::default()
+        };
+        let mut seen = HashSet::new();
+
+        for person in &people {
+            seen.insert(person.resource_name.as_str());
+
+            if person.metadata.deleted {
+                sync.removed += self.unlink_contact(user_id, &person.resource_name).await?;
+                continue;
+            }
+
+            let contact = match ContactRecord::from_person(person) {
+                Some(contact) => contact,
+                None => continue,
+            };
+
+            match self.sync_contact(user_id, &contact).await? {
+                ContactOutcome::Created(added) => {
+                    sync.created += 1;
+                    sync.attributes_added += added;
+                }
+                ContactOutcome::Linked(added) => {
+                    sync.linked += 1;
+                    sync.attributes_added += added;
+                }
+                ContactOutcome::Updated(added) => {
+                    sync.updated += 1;
+                    sync.attributes_added += added;
+                }
+                ContactOutcome::Unchanged => sync.unchanged += 1,
+            }
+        }
+
+        // A full listing is authoritative: links to contacts no longer in it
+        // were deleted while we weren't receiving changes
+        if full {
+            let seen: Vec<String> = seen.into_iter().map(str::to_string).collect();
+            sync.removed += sqlx::query(
+                r#"
+                DELETE FROM contact_links
+                WHERE user_id = $1 AND provider = $2 AND NOT (resource_name = ANY($3))
+                "#,
+            )
+            .bind(user_id)
+            .bind(GOOGLE_PROVIDER)
+            .bind(&seen)
+            .execute(&self.db_pool)
+            .await
+            .map_err(|e| format!("Failed to remove stale contact links: {}", e))?
+            .rows_affected() as u32;
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE contact_connections SET
+                sync_token = $3,
+                last_full_sync_at = CASE WHEN $4 THEN NOW() ELSE last_full_sync_at END,
+                last_synced_at = NOW(),
+                last_error = NULL,
+                updated_at = NOW()
+            WHERE user_id = $1 AND provider = $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(GOOGLE_PROVIDER)
+        .bind(next_sync_token)
+        .bind(full)
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to save sync state: {}", e))?;
+
+        Ok(sync)
+    }
+
+    /// Forget a contact deleted in Google; its entity stays
+    async fn unlink_contact(&self, user_id: Uuid, resource_name: &str) -> Result<u32, Error> {
+        let removed = sqlx::query(
+            "DELETE FROM contact_links WHERE user_id = $1 AND provider = $2 AND resource_name = $3",
+        )
+        .bind(user_id)
+        .bind(GOOGLE_PROVIDER)
+        .bind(resource_name)
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to unlink contact: {}", e))?
+        .rows_affected();
+
+        Ok(removed as u32)
+    }
+
+    /// Link a contact to a person entity and add its details
+    async fn sync_contact(
+        &self,
+        user_id: Uuid,
+        contact: &ContactRecord,
+    ) -> Result<ContactOutcome, Error> {
+        let link: Option<(Option<Uuid>, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT entity_id, etag FROM contact_links
+            WHERE user_id = $1 AND provider = $2 AND resource_name = $3
+            "#,
+        )
+        .bind(user_id)
+        .bind(GOOGLE_PROVIDER)
+        .bind(&contact.resource_name)
+        .fetch_optional(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to get contact link: {}", e))?;
+
+        let linked_entity = match link {
+            // The user deleted the entity; don't bring it back
+            Some((None, _)) => return Ok(ContactOutcome::Unchanged),
+            Some((Some(_), etag)) if etag.is_some() && etag == contact.etag => {
+                return Ok(ContactOutcome::Unchanged)
+            }
+            Some((Some(entity_id), _)) => Some(entity_id),
+            None => None,
+        };
+
+        let mut tx = self.db_pool.begin().await?;
+
+        let outcome = match linked_entity {
+            Some(entity_id) => {
+                let added = add_contact_attributes(&mut tx, entity_id, user_id, contact).await?;
+                sqlx::query(
+                    r#"
+                    UPDATE contact_links SET etag = $4, updated_at = NOW()
+                    WHERE user_id = $1 AND provider = $2 AND resource_name = $3
+                    "#,
+                )
+                .bind(user_id)
+                .bind(GOOGLE_PROVIDER)
+                .bind(&contact.resource_name)
+                .bind(&contact.etag)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("Failed to update contact link: {}", e))?;
+                ContactOutcome::Updated(added)
+            }
+            None => {
+                let existing = find_person_entity(&mut tx, user_id, contact).await?;
+                let entity_id = match existing {
+                    Some(entity_id) => entity_id,
+                    None => create_person_entity(&mut tx, user_id, contact).await?,
+                };
+
+                let claimed: Option<Uuid> = sqlx::query_scalar(
+                    r#"
+                    INSERT INTO contact_links (user_id, provider, resource_name, entity_id, etag)
+                    VALUES ($1, $2, $3, $4, $5)
+                    ON CONFLICT (user_id, provider, resource_name) DO NOTHING
+                    RETURNING entity_id
+                    "#,
+                )
+                .bind(user_id)
+                .bind(GOOGLE_PROVIDER)
+                .bind(&contact.resource_name)
+                .bind(entity_id)
+                .bind(&contact.etag)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(|e| format!("Failed to link contact: {}", e))?;
+
+                if claimed.is_none() {
+                    // Another run linked it first; drop anything created here
+                    tx.rollback().await?;
+                    return Ok(ContactOutcome::Unchanged);
+                }
+
+                let added = add_contact_attributes(&mut tx, entity_id, user_id, contact).await?;
+                if existing.is_some() {
+                    ContactOutcome::Linked(added)
+                } else {
+                    info!(
+                        "Created person entity {} from contact for user {}",
+                        entity_id, user_id
+                    );
+                    ContactOutcome::Created(added)
+                }
+            }
+        };
+
+        tx.commit().await?;
+        Ok(outcome)
+    }
+
+    /// Record a failed sync on the connection for the status endpoint
+    async fn record_sync_error(&self, user_id: Uuid, error: &str) -> Result<(), Error> {
+        sqlx::query(
+            r#"
+            UPDATE contact_connections SET last_error = $3, updated_at = NOW()
+            WHERE user_id = $1 AND provider = $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(GOOGLE_PROVIDER)
+        .bind(error)
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to record sync error: {}", e))?;
+
+        Ok(())
+    }
+}
+
+/// The user's person entity for a contact: by email attribute or alias, else
+/// by name when exactly one entity has it. Entities already linked to another
+/// contact are left alone.
+async fn find_person_entity(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    contact: &ContactRecord,
+) -> Result<Option<Uuid>, Error> {
+    let by_email: Option<Uuid> = sqlx::query_scalar(
+        r#"
+        SELECT e.id
+        FROM entities e
+        WHERE e.owner_type = 'user' AND e.owner_id = $1 AND e.entity_type = 'person'
+          AND NOT EXISTS (
+              SELECT 1 FROM contact_links l WHERE l.entity_id = e.id AND l.user_id = $1
+          )
+          AND (
+              EXISTS (
+                  SELECT 1 FROM UNNEST(e.aliases) AS alias WHERE LOWER(alias) = ANY($2)
+              )
+              OR EXISTS (
+                  SELECT 1 FROM entity_attributes ea
+                  WHERE ea.entity_id = e.id AND ea.attribute_name = 'email'
+                    AND ea.valid_to IS NULL AND LOWER(ea.attribute_value) = ANY($2)
+              )
+          )
+        ORDER BY e.created_at
+        LIMIT 1
+        "#,
+    )
+    .bind(user_id)
+    .bind(&contact.emails)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to match contact by email: {}", e))?;
+
+    if by_email.is_some() {
+        return Ok(by_email);
+    }
+
+    let by_name: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        SELECT e.id
+        FROM entities e
+        WHERE e.owner_type = 'user' AND e.owner_id = $1 AND e.entity_type = 'person'
+          AND e.normalized_name = LOWER(TRIM($2))
+          AND NOT EXISTS (
+              SELECT 1 FROM contact_links l WHERE l.entity_id = e.id AND l.user_id = $1
+          )
+        LIMIT 2
+        "#,
+    )
+    .bind(user_id)
+    .bind(&contact.name)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to match contact by name: {}", e))?;
+
+    // Ambiguous names get a new entity rather than a guess
+    Ok(match by_name.as_slice() {
+        [entity_id] => Some(*entity_id),
+        _ => None,
+    })
+}
+
+/// Create a person entity for a contact
+async fn create_person_entity(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    contact: &ContactRecord,
+) -> Result<Uuid, Error> {
+    let entity_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO entities (entity_type, name, metadata,
+                              owner_type, owner_id, created_by, visibility_tier)
+        VALUES ('person', $1, '{"source": "google_contacts"}', 'user', $2, $2, 3)
+        RETURNING id
+        "#,
+    )
+    .bind(&contact.name)
+    .bind(user_id)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to create entity: {}", e))?;
+
+    Ok(entity_id)
+}
+
+/// Add the contact's emails, phones and birthday that the entity doesn't
+/// have yet. A birthday is only added when the entity has none, so one the
+/// user entered wins. Returns how many attributes were added.
+async fn add_contact_attributes(
+    conn: &mut PgConnection,
+    entity_id: Uuid,
+    user_id: Uuid,
+    contact: &ContactRecord,
+) -> Result<u32, Error> {
+    let attributes = contact
+        .emails
+        .iter()
+        .map(|email| ("email", email.as_str(), false))
+        .chain(
+            contact
+                .phones
+                .iter()
+                .map(|phone| ("phone", phone.as_str(), false)),
+        )
+        .chain(
+            contact
+                .birthday
+                .iter()
+                .map(|birthday| ("birthday", birthday.as_str(), true)),
+        );
+
+    let mut added = 0;
+    for (name, value, single_valued) in attributes {
+        added += sqlx::query(
+            r#"
+            INSERT INTO entity_attributes (entity_id, attribute_name, attribute_value, created_by)
+            SELECT $1, $2, $3, $4
+            WHERE NOT EXISTS (
+                SELECT 1 FROM entity_attributes
+                WHERE entity_id = $1 AND attribute_name = $2
+                  AND valid_to IS NULL AND superseded_by IS NULL
+                  AND ($5 OR LOWER(attribute_value) = LOWER($3))
+            )
+            "#,
+        )
+        .bind(entity_id)
+        .bind(name)
+        .bind(value)
+        .bind(user_id)
+        .bind(single_valued)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to add contact {}: {}", name, e))?
+        .rows_affected() as u32;
+    }
+
+    Ok(added)
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    event: LambdaEvent<ScheduledEvent>,
+) -> Result<SyncResponse, Error> {
+    info!("Starting contacts sync ({})", event.payload.detail_type);
+
+    let user_filter = match &event.payload.user_id {
+        Some(user_id) => {
+            Some(Uuid::parse_str(user_id).map_err(|e| format!("Invalid user_id: {}", e))?)
+        }
+        None => None,
+    };
+
+    let connections = state.get_connections(user_filter).await?;
+    info!("Found {} users with connected contacts", connections.len());
+
+    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_USERS));
+    let mut tasks = JoinSet::new();
+
+    for connection in connections {
+        let state = Arc::clone(&state);
+        let semaphore = Arc::clone(&semaphore);
+
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let timeout = std::time::Duration::from_secs(USER_SYNC_TIMEOUT_SECS);
+            let result =
+                match tokio::time::timeout(timeout, state.sync_user_contacts(&connection)).await {
+                    Ok(Ok(sync)) => Ok(sync),
+                    Ok(Err(e)) => Err(e.to_string()),
+                    Err(_) => Err(format!("Sync took longer than {}s", USER_SYNC_TIMEOUT_SECS)),
+                };
+
+            if let Err(e) = &result {
+                if let Err(record_error) = state.record_sync_error(connection.user_id, e).await {
+                    warn!(
+                        "Failed to record contacts sync error for user {}: {}",
+                        connection.user_id, record_error
+                    );
+                }
+            }
+            (connection.user_id, result)
+        });
+    }
+
+    let mut response = SyncResponse::default();
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((user_id, Ok(sync))) => {
+                info!(
+                    "Synced contacts for user {} ({}): {} created, {} linked, {} updated, {} removed",
+                    user_id,
+                    if sync.full { "full" } else { "incremental" },
+                    sync.created,
+                    sync.linked,
+                    sync.updated,
+                    sync.removed
+                );
+                response.users_synced += 1;
+                response.contacts_created += sync.created;
+                response.contacts_linked += sync.linked;
+                response.contacts_updated += sync.updated;
+                response.contacts_unchanged += sync.unchanged;
+                response.contacts_removed += sync.removed;
+                response.attributes_added += sync.attributes_added;
+            }
+            Ok((user_id, Err(e))) => {
+                error!("Failed to sync contacts for user {}: {}", user_id, e);
+                response.users_failed += 1;
+                response.failures.push(SyncFailure {
+                    user_id: user_id.to_string(),
+                    error: e,
+                });
+            }
+            Err(e) => {
+                error!("Contacts sync task panicked: {}", e);
+                response.users_failed += 1;
+            }
+        }
+    }
+
+    info!(
+        "Contacts sync complete: {} users ({} failed), {} created, {} linked, {} updated, {} unchanged, {} removed, {} attributes added",
+        response.users_synced,
+        response.users_failed,
+        response.contacts_created,
+        response.contacts_linked,
+        response.contacts_updated,
+        response.contacts_unchanged,
+        response.contacts_removed,
+        response.attributes_added
+    );
+
+    Ok(response)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        async move { metrics::track_invocation("contacts_sync", handler(state, event)).await }
+    }))
+    .await
+}
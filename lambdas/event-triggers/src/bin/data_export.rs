@@ -0,0 +1,200 @@
+//! Data Export Lambda - Builds full account data export bundles.
+//!
+//! Invoked asynchronously by the export API Lambda (`POST /export`) with the
+//! ID of a `data_exports` row. Every dataset in `shared::data_export::DATASETS`
+//! is queried for the user and written, as JSON and CSV, into a zip bundle in
+//! the exports bucket; the row then records the object key, size and row
+//! counts, and `GET /export/{id}` hands out a presigned download link.
+//!
+//! Failures are recorded on the row rather than returned, so Lambda's
+//! automatic retries of async invocations don't run the export again.
+
+use aws_sdk_s3::primitives::ByteStream;
+use chrono::{Duration, Utc};
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use shared::data_export::{
+    build_bundle, export_key, record_counts, Dataset, DATASETS, EXPORT_RETENTION_DAYS,
+};
+use shared::metrics;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{error, info};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// Invocation payload
+#[derive(Debug, Deserialize)]
+struct ExportEvent {
+    export_id: Uuid,
+}
+
+#[derive(Debug, Serialize)]
+struct ExportResult {
+    export_id: Uuid,
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+struct AppState {
+    db_pool: PgPool,
+    s3_client: aws_sdk_s3::Client,
+    exports_bucket: String,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        let exports_bucket =
+            std::env::var("EXPORTS_BUCKET").map_err(|_| "EXPORTS_BUCKET not set")?;
+
+        Ok(Self {
+            db_pool,
+            s3_client: aws_sdk_s3::Client::new(&config),
+            exports_bucket,
+        })
+    }
+}
+
+/// Claim a pending export, returning its user. `None` if it was already
+/// claimed (a retried invocation) or doesn't exist.
+async fn claim_export(pool: &PgPool, export_id: Uuid) -> Result<Option<Uuid>, Error> {
+    let user_id = sqlx::query_scalar(
+        r#"
+        UPDATE data_exports SET status = 'running', started_at = NOW()
+        WHERE id = $1 AND status = 'pending'
+        RETURNING user_id
+        "#,
+    )
+    .bind(export_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to claim export: {}", e))?;
+
+    Ok(user_id)
+}
+
+/// Query every dataset, build the bundle and store it.
+async fn run_export(state: &AppState, export_id: Uuid, user_id: Uuid) -> Result<(), Error> {
+    let mut datasets: Vec<(&Dataset, Vec<serde_json::Value>)> = Vec::with_capacity(DATASETS.len());
+    for dataset in DATASETS {
+        let rows: Vec<serde_json::Value> = sqlx::query_scalar(&dataset.json_query())
+            .bind(user_id)
+            .fetch_all(&state.db_pool)
+            .await
+            .map_err(|e| format!("Failed to export {}: {}", dataset.name, e))?;
+        datasets.push((dataset, rows));
+    }
+
+    let generated_at = Utc::now();
+    let bundle = build_bundle(user_id, generated_at, &datasets)?;
+    let size = bundle.len() as i64;
+    let key = export_key(user_id, export_id);
+
+    state
+        .s3_client
+        .put_object()
+        .bucket(&state.exports_bucket)
+        .key(&key)
+        .content_type("application/zip")
+        .body(ByteStream::from(bundle))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to store export: {}", e))?;
+
+    sqlx::query(
+        r#"
+        UPDATE data_exports
+        SET status = 'completed', object_key = $2, size_bytes = $3, record_counts = $4,
+            completed_at = $5, expires_at = $6
+        WHERE id = $1
+        "#,
+    )
+    .bind(export_id)
+    .bind(&key)
+    .bind(size)
+    .bind(record_counts(&datasets))
+    .bind(generated_at)
+    .bind(generated_at + Duration::days(EXPORT_RETENTION_DAYS))
+    .execute(&state.db_pool)
+    .await
+    .map_err(|e| format!("Failed to update export: {}", e))?;
+
+    info!(
+        export_id = %export_id,
+        user_id = %user_id,
+        size_bytes = size,
+        "Data export completed"
+    );
+
+    Ok(())
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    event: LambdaEvent<ExportEvent>,
+) -> Result<ExportResult, Error> {
+    let export_id = event.payload.export_id;
+
+    let Some(user_id) = claim_export(&state.db_pool, export_id).await? else {
+        info!(export_id = %export_id, "Export already claimed; skipping");
+        return Ok(ExportResult {
+            export_id,
+            status: "skipped".to_string(),
+            error: None,
+        });
+    };
+
+    info!(export_id = %export_id, user_id = %user_id, "Starting data export");
+
+    match run_export(&state, export_id, user_id).await {
+        Ok(()) => Ok(ExportResult {
+            export_id,
+            status: "completed".to_string(),
+            error: None,
+        }),
+        Err(e) => {
+            error!(export_id = %export_id, "Data export failed: {}", e);
+            sqlx::query(
+                r#"
+                UPDATE data_exports
+                SET status = 'failed', error_message = $2, completed_at = NOW()
+                WHERE id = $1
+                "#,
+            )
+            .bind(export_id)
+            .bind(e.to_string())
+            .execute(&state.db_pool)
+            .await
+            .map_err(|e| format!("Failed to record export failure: {}", e))?;
+
+            Ok(ExportResult {
+                export_id,
+                status: "failed".to_string(),
+                error: Some(e.to_string()),
+            })
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        async move { metrics::track_invocation("data_export", handler(state, event)).await }
+    }))
+    .await
+}
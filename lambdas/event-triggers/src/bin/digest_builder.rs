@@ -0,0 +1,278 @@
+//! Digest Builder Lambda - Batches low-priority notifications into digests.
+//!
+//! This Lambda runs hourly via EventBridge and:
+//! 1. Finds users with low-priority notifications held by `notification_sender`
+//! 2. Checks whether their morning or evening digest is due in their timezone
+//!    (digests go out at the briefing times in `user_notification_preferences`)
+//! 3. Combines the held notifications into one `digest` notification and marks
+//!    them sent as part of it
+//! 4. Publishes the digest to the notification topic for delivery by email or
+//!    Discord
+//!
+//! Users who turned digests off have anything still held flushed on the next run.
+
+use aws_sdk_sns::Client as SnsClient;
+use chrono::Utc;
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use shared::digest::{
+    compose_digest, digest_channel, due_slot, DigestItem, DigestPreferences, DigestSlot,
+    DIGEST_MAX_PRIORITY,
+};
+use shared::metrics;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+struct ScheduledEvent {
+    #[serde(default)]
+    detail_type: String,
+}
+
+#[derive(Debug, Serialize)]
+struct BuilderResponse {
+    users_checked: u32,
+    digests_queued: u32,
+    notifications_batched: u32,
+    errors: u32,
+}
+
+/// User with held notifications, and their digest preferences
+#[derive(Debug, sqlx::FromRow)]
+struct HeldUser {
+    user_id: Uuid,
+    #[sqlx(flatten)]
+    prefs: DigestPreferences,
+}
+
+struct AppState {
+    db_pool: PgPool,
+    sns_client: SnsClient,
+    notification_topic_arn: Option<String>,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self {
+            db_pool,
+            sns_client: SnsClient::new(&config),
+            notification_topic_arn: std::env::var("NOTIFICATION_TOPIC_ARN").ok(),
+        })
+    }
+}
+
+/// Users with held notifications (preferences default like the table's columns).
+async fn get_held_users(pool: &PgPool) -> Result<Vec<HeldUser>, Error> {
+    let users: Vec<HeldUser> = sqlx::query_as(
+        r#"
+        SELECT
+            u.id AS user_id,
+            COALESCE(unp.digest_mode, 'morning') AS digest_mode,
+            COALESCE(unp.morning_briefing_time, '07:00'::time) AS morning_briefing_time,
+            COALESCE(unp.evening_briefing_time, '18:00'::time) AS evening_briefing_time,
+            COALESCE(unp.timezone, 'America/New_York') AS timezone,
+            COALESCE(unp.push_enabled, true) AS push_enabled,
+            COALESCE(unp.email_enabled, true) AS email_enabled,
+            COALESCE(unp.discord_enabled, false) AS discord_enabled
+        FROM users u
+        LEFT JOIN user_notification_preferences unp ON unp.user_id = u.id
+        WHERE EXISTS (
+            SELECT 1 FROM notifications n
+            WHERE n.user_id = u.id AND n.status = 'pending' AND n.priority <= $1
+              AND (n.deferred_until IS NULL OR n.deferred_until <= NOW())
+        )
+        "#,
+    )
+    .bind(DIGEST_MAX_PRIORITY)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to query users: {}", e))?;
+
+    Ok(users)
+}
+
+/// Combine the user's held notifications into a digest notification.
+///
+/// Returns the digest ID, title and number of notifications batched, or `None`
+/// if another run already took them.
+async fn build_digest(
+    pool: &PgPool,
+    user: &HeldUser,
+    slot: DigestSlot,
+) -> Result<Option<(Uuid, String, usize)>, Error> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let items: Vec<DigestItem> = sqlx::query_as(
+        r#"
+        SELECT id, notification_type::text, title, body
+        FROM notifications
+        WHERE user_id = $1 AND status = 'pending' AND priority <= $2
+          AND (deferred_until IS NULL OR deferred_until <= NOW())
+        ORDER BY created_at
+        FOR UPDATE SKIP LOCKED
+        "#,
+    )
+    .bind(user.user_id)
+    .bind(DIGEST_MAX_PRIORITY)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to fetch held notifications: {}", e))?;
+
+    if items.is_empty() {
+        return Ok(None);
+    }
+
+    let (title, body) = compose_digest(slot, &items);
+
+    let digest_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO notifications (
+            user_id, notification_type, title, body, channel
+        ) VALUES ($1, 'digest', $2, $3, $4::notification_channel)
+        RETURNING id
+        "#,
+    )
+    .bind(user.user_id)
+    .bind(&title)
+    .bind(&body)
+    .bind(digest_channel(&user.prefs))
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to queue digest: {}", e))?;
+
+    let item_ids: Vec<Uuid> = items.iter().map(|item| item.id).collect();
+    sqlx::query(
+        r#"
+        UPDATE notifications
+        SET status = 'sent', sent_at = NOW(), digest_id = $1, updated_at = NOW()
+        WHERE id = ANY($2)
+        "#,
+    )
+    .bind(digest_id)
+    .bind(&item_ids)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to mark notifications batched: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit digest: {}", e))?;
+
+    Ok(Some((digest_id, title, items.len())))
+}
+
+async fn publish_to_sns(state: &AppState, notification_id: Uuid, title: &str) -> Result<(), Error> {
+    if let Some(topic_arn) = &state.notification_topic_arn {
+        let message = serde_json::json!({
+            "notification_id": notification_id.to_string(),
+            "type": "digest",
+            "title": title,
+        });
+
+        state
+            .sns_client
+            .publish()
+            .topic_arn(topic_arn)
+            .message(message.to_string())
+            .send()
+            .await
+            .map_err(|e| format!("Failed to publish to SNS: {}", e))?;
+    }
+
+    Ok(())
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    _event: LambdaEvent<ScheduledEvent>,
+) -> Result<BuilderResponse, Error> {
+    info!("Starting digest build");
+
+    let now = Utc::now();
+    let users = get_held_users(&state.db_pool).await?;
+
+    info!(
+        users_found = users.len(),
+        "Found users with held notifications"
+    );
+
+    let mut digests_queued = 0u32;
+    let mut notifications_batched = 0u32;
+    let mut errors = 0u32;
+
+    for user in &users {
+        let slot = match due_slot(&user.prefs, now) {
+            Some(slot) => slot,
+            None => continue,
+        };
+
+        match build_digest(&state.db_pool, user, slot).await {
+            Ok(Some((digest_id, title, count))) => {
+                info!(
+                    user_id = %user.user_id,
+                    digest_id = %digest_id,
+                    notifications = count,
+                    "Queued digest"
+                );
+                digests_queued += 1;
+                notifications_batched += count as u32;
+
+                // The digest row stays pending if publishing fails
+                if let Err(e) = publish_to_sns(&state, digest_id, &title).await {
+                    warn!(digest_id = %digest_id, error = %e, "Failed to publish digest");
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                error!(user_id = %user.user_id, error = %e, "Failed to build digest");
+                errors += 1;
+            }
+        }
+    }
+
+    let response = BuilderResponse {
+        users_checked: users.len() as u32,
+        digests_queued,
+        notifications_batched,
+        errors,
+    };
+
+    info!(
+        users_checked = response.users_checked,
+        digests_queued = response.digests_queued,
+        notifications_batched = response.notifications_batched,
+        errors = response.errors,
+        "Digest build complete"
+    );
+
+    Ok(response)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let state_clone = state.clone();
+
+    run(service_fn(move |event| {
+        let state = state_clone.clone();
+        async move { metrics::track_invocation("digest_builder", handler(state, event)).await }
+    }))
+    .await
+}
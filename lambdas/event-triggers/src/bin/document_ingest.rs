@@ -0,0 +1,691 @@
+//! Document Ingest Lambda - Ingests PDFs and scanned mail with Textract.
+//!
+//! Documents uploaded to `documents/{cognito_sub}/...` in the drop folder bucket
+//! produce S3 notifications that arrive via SQS. Each one starts an asynchronous
+//! Textract text detection job (multi-page PDFs and TIFFs need the async API);
+//! Textract publishes completion to SNS, which delivers to the same queue. The
+//! detected text is split into chunks (see `shared::documents`) and each chunk is
+//! handed to the ingestion agent with metadata pointing back at the original
+//! object. Facts created from a chunk are linked to the document and pages in
+//! `document_ingest_facts`.
+//!
+//! Every document is recorded in `document_ingests`. Failures are recorded there
+//! and the user is sent a system notification.
+
+use aws_sdk_sns::Client as SnsClient;
+use aws_sdk_textract::types::{BlockType, DocumentLocation, NotificationChannel, S3Object};
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use shared::digest::DIGEST_MAX_PRIORITY;
+use shared::documents::{chunk_pages, ChunkSource, MAX_CHUNKS, MAX_CHUNK_CHARS};
+use shared::metrics;
+use shared::reminders::{preferred_channel, NotificationPreferences};
+use shared::s3_ingest::{decode_key, file_name, resolve_user, ProcessError, MAX_ATTEMPTS};
+use shared::{AgentClient, AuthorizedUser};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// Prefix users upload documents to: `documents/{cognito_sub}/...`
+const DOCUMENTS_PREFIX: &str = "documents/";
+
+/// Source reported to the agents
+const SOURCE: &str = "document";
+
+/// SQS event wrapper
+#[derive(Debug, Deserialize)]
+struct SqsEvent {
+    #[serde(rename = "Records")]
+    records: Vec<SqsRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SqsRecord {
+    #[serde(rename = "messageId")]
+    message_id: String,
+    body: String,
+}
+
+/// SQS message body: an S3 notification or a Textract job completion
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum QueueMessage {
+    TextractJob(TextractJobNotification),
+    S3(S3Notification),
+}
+
+/// Textract job completion (SNS raw message delivery)
+#[derive(Debug, Deserialize)]
+struct TextractJobNotification {
+    #[serde(rename = "JobId")]
+    job_id: String,
+    #[serde(rename = "Status")]
+    status: String,
+}
+
+/// S3 event notification
+#[derive(Debug, Deserialize)]
+struct S3Notification {
+    // Absent on the s3:TestEvent sent when the notification is configured
+    #[serde(rename = "Records", default)]
+    records: Vec<S3Record>,
+}
+
+#[derive(Debug, Deserialize)]
+struct S3Record {
+    s3: S3Entity,
+}
+
+#[derive(Debug, Deserialize)]
+struct S3Entity {
+    bucket: S3Bucket,
+    object: S3ObjectInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct S3Bucket {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct S3ObjectInfo {
+    key: String,
+    #[serde(default)]
+    size: Option<i64>,
+    #[serde(rename = "eTag", default)]
+    e_tag: Option<String>,
+}
+
+/// SQS partial batch response
+#[derive(Debug, Serialize)]
+struct SqsBatchResponse {
+    #[serde(rename = "batchItemFailures")]
+    batch_item_failures: Vec<BatchItemFailure>,
+}
+
+#[derive(Debug, Serialize)]
+struct BatchItemFailure {
+    #[serde(rename = "itemIdentifier")]
+    item_identifier: String,
+}
+
+/// Manifest row for an uploaded document
+#[derive(Debug, sqlx::FromRow)]
+struct DocumentRow {
+    id: Uuid,
+    user_id: Uuid,
+    bucket: String,
+    object_key: String,
+    status: String,
+    chunks_ingested: i32,
+    attempts: i16,
+}
+
+struct AppState {
+    db_pool: PgPool,
+    textract_client: aws_sdk_textract::Client,
+    sns_client: SnsClient,
+    agent_client: AgentClient,
+    notification_topic_arn: Option<String>,
+    /// SNS topic Textract reports job completion to, and the role it publishes with
+    textract_topic_arn: String,
+    textract_role_arn: String,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        let agent_function_name = std::env::var("AGENT_FUNCTION_NAME")
+            .unwrap_or_else(|_| "second-brain-agents".to_string());
+
+        let textract_topic_arn =
+            std::env::var("TEXTRACT_TOPIC_ARN").map_err(|_| "TEXTRACT_TOPIC_ARN not set")?;
+        let textract_role_arn =
+            std::env::var("TEXTRACT_ROLE_ARN").map_err(|_| "TEXTRACT_ROLE_ARN not set")?;
+
+        Ok(Self {
+            db_pool,
+            textract_client: aws_sdk_textract::Client::new(&config),
+            sns_client: SnsClient::new(&config),
+            agent_client: AgentClient::new(
+                aws_sdk_lambda::Client::new(&config),
+                agent_function_name,
+            ),
+            notification_topic_arn: std::env::var("NOTIFICATION_TOPIC_ARN").ok(),
+            textract_topic_arn,
+            textract_role_arn,
+        })
+    }
+}
+
+/// Whether Textract can read the file (by extension).
+fn is_supported(key: &str) -> bool {
+    let ext = key
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_lowercase())
+        .unwrap_or_default();
+
+    matches!(
+        ext.as_str(),
+        "pdf" | "tif" | "tiff" | "jpg" | "jpeg" | "png"
+    )
+}
+
+/// Record a document, returning its row (attempts incremented on redelivery).
+async fn upsert_document(
+    pool: &PgPool,
+    user_id: Uuid,
+    bucket: &str,
+    key: &str,
+    etag: &str,
+    size: Option<i64>,
+) -> Result<DocumentRow, sqlx::Error> {
+    sqlx::query_as(
+        r#"
+        INSERT INTO document_ingests (user_id, bucket, object_key, etag, size_bytes)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (bucket, object_key, etag) DO UPDATE SET
+            attempts = document_ingests.attempts + 1,
+            updated_at = NOW()
+        RETURNING id, user_id, bucket, object_key, status::text, chunks_ingested, attempts
+        "#,
+    )
+    .bind(user_id)
+    .bind(bucket)
+    .bind(key)
+    .bind(etag)
+    .bind(size)
+    .fetch_one(pool)
+    .await
+}
+
+/// Mark a document as failed and notify its owner.
+async fn fail_document(
+    state: &AppState,
+    document: &DocumentRow,
+    reason: &str,
+) -> Result<(), Error> {
+    warn!(document_id = %document.id, reason, "Document ingest failed");
+
+    sqlx::query(
+        r#"
+        UPDATE document_ingests
+        SET status = 'failed', error_message = $2, processed_at = NOW(), updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(document.id)
+    .bind(reason)
+    .execute(&state.db_pool)
+    .await
+    .map_err(|e| format!("Failed to update document: {}", e))?;
+
+    let prefs: NotificationPreferences = sqlx::query_as(
+        r#"
+        SELECT
+            push_enabled,
+            email_enabled,
+            discord_enabled,
+            quiet_hours_enabled,
+            quiet_hours_start,
+            quiet_hours_end,
+            timezone
+        FROM user_notification_preferences
+        WHERE user_id = $1
+        "#,
+    )
+    .bind(document.user_id)
+    .fetch_optional(&state.db_pool)
+    .await
+    .map_err(|e| format!("Failed to query preferences: {}", e))?
+    .unwrap_or_default();
+
+    let title = "Couldn't import a document";
+    let body = format!("{}: {}", file_name(&document.object_key), reason);
+
+    let notification_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO notifications (
+            user_id, notification_type, title, body, channel,
+            source_entity_id, source_entity_type, priority
+        ) VALUES ($1, 'system', $2, $3, $4::notification_channel, $5, 'document_ingest', $6)
+        RETURNING id
+        "#,
+    )
+    .bind(document.user_id)
+    .bind(title)
+    .bind(&body)
+    .bind(preferred_channel(&prefs))
+    .bind(document.id)
+    // Import failures can wait for the user's digest
+    .bind(DIGEST_MAX_PRIORITY)
+    .fetch_one(&state.db_pool)
+    .await
+    .map_err(|e| format!("Failed to queue notification: {}", e))?;
+
+    if let Some(topic_arn) = &state.notification_topic_arn {
+        let message = serde_json::json!({
+            "notification_id": notification_id.to_string(),
+            "type": "system",
+            "title": title,
+        });
+
+        state
+            .sns_client
+            .publish()
+            .topic_arn(topic_arn)
+            .message(serde_json::to_string(&message).unwrap_or_default())
+            .send()
+            .await
+            .map_err(|e| format!("Failed to publish to SNS: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// Start the Textract job for a document.
+async fn start_text_detection(
+    state: &AppState,
+    document: &DocumentRow,
+) -> Result<(), ProcessError> {
+    let location = DocumentLocation::builder()
+        .s3_object(
+            S3Object::builder()
+                .bucket(&document.bucket)
+                .name(&document.object_key)
+                .build(),
+        )
+        .build();
+
+    let channel = NotificationChannel::builder()
+        .sns_topic_arn(&state.textract_topic_arn)
+        .role_arn(&state.textract_role_arn)
+        .build()?;
+
+    let output = state
+        .textract_client
+        .start_document_text_detection()
+        .document_location(location)
+        .notification_channel(channel)
+        // Redeliveries of the same upload get the same job back
+        .client_request_token(document.id.simple().to_string())
+        .job_tag(document.id.simple().to_string())
+        .send()
+        .await?;
+
+    let job_id = output
+        .job_id()
+        .ok_or_else(|| ProcessError::Transient("Textract returned no job ID".to_string()))?;
+
+    sqlx::query(
+        r#"
+        UPDATE document_ingests
+        SET status = 'extracting', textract_job_id = $2, attempts = 0, updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(document.id)
+    .bind(job_id)
+    .execute(&state.db_pool)
+    .await?;
+
+    info!(document_id = %document.id, job_id, "Started text detection");
+    Ok(())
+}
+
+/// Detected text of a finished job, one string per page.
+async fn detected_pages(state: &AppState, job_id: &str) -> Result<Vec<String>, ProcessError> {
+    let mut pages: Vec<Vec<String>> = Vec::new();
+    let mut next_token: Option<String> = None;
+
+    loop {
+        let output = state
+            .textract_client
+            .get_document_text_detection()
+            .job_id(job_id)
+            .max_results(1000)
+            .set_next_token(next_token.take())
+            .send()
+            .await?;
+
+        for block in output.blocks() {
+            if block.block_type() != Some(&BlockType::Line) {
+                continue;
+            }
+            let (Some(text), Some(page)) = (block.text(), block.page()) else {
+                continue;
+            };
+            let index = (page.max(1) - 1) as usize;
+            if pages.len() <= index {
+                pages.resize_with(index + 1, Vec::new);
+            }
+            pages[index].push(text.to_string());
+        }
+
+        match output.next_token() {
+            Some(token) => next_token = Some(token.to_string()),
+            None => break,
+        }
+    }
+
+    Ok(pages.into_iter().map(|lines| lines.join("\n")).collect())
+}
+
+/// Hand a finished document to the ingestion agent chunk by chunk, resuming
+/// after the chunks an earlier invocation already ingested.
+async fn ingest_document(
+    state: &AppState,
+    user: &AuthorizedUser,
+    document: &DocumentRow,
+    job_id: &str,
+) -> Result<(), ProcessError> {
+    let pages = detected_pages(state, job_id).await?;
+    let mut chunks = chunk_pages(&pages, MAX_CHUNK_CHARS);
+    if chunks.is_empty() {
+        return Err(ProcessError::Permanent(
+            "No text found in document".to_string(),
+        ));
+    }
+    if chunks.len() > MAX_CHUNKS {
+        warn!(
+            document_id = %document.id,
+            chunks = chunks.len(),
+            "Document too long; ingesting the first {} chunks",
+            MAX_CHUNKS
+        );
+        chunks.truncate(MAX_CHUNKS);
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE document_ingests
+        SET status = 'ingesting', page_count = $2, chunk_count = $3, updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(document.id)
+    .bind(pages.len() as i32)
+    .bind(chunks.len() as i32)
+    .execute(&state.db_pool)
+    .await?;
+
+    let family_ids: Vec<String> = user.family_ids.iter().map(|id| id.to_string()).collect();
+    let total = chunks.len();
+
+    for (index, chunk) in chunks
+        .iter()
+        .enumerate()
+        .skip(document.chunks_ingested.max(0) as usize)
+    {
+        let source = ChunkSource::new(
+            document.id,
+            &document.bucket,
+            &document.object_key,
+            index,
+            total,
+            chunk,
+        );
+
+        let response = state
+            .agent_client
+            .ingest_with_metadata(
+                &chunk.text,
+                &user.cognito_sub,
+                family_ids.clone(),
+                SOURCE,
+                Some(serde_json::to_value(&source)?),
+            )
+            .await?;
+
+        let fact_ids = response
+            .metadata
+            .and_then(|m| m.fact_ids)
+            .unwrap_or_default();
+
+        let mut tx = state.db_pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO document_ingest_facts (document_id, fact_id, chunk_index, first_page, last_page)
+            SELECT $1, f.id, $3, $4, $5 FROM facts f WHERE f.id = ANY($2)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(document.id)
+        .bind(&fact_ids)
+        .bind(index as i32)
+        .bind(chunk.first_page)
+        .bind(chunk.last_page)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "UPDATE document_ingests SET chunks_ingested = $2, updated_at = NOW() WHERE id = $1",
+        )
+        .bind(document.id)
+        .bind(index as i32 + 1)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        info!(
+            document_id = %document.id,
+            chunk = index + 1,
+            chunks = total,
+            facts = fact_ids.len(),
+            "Ingested document chunk"
+        );
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE document_ingests
+        SET status = 'completed', error_message = NULL, processed_at = NOW(), updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(document.id)
+    .execute(&state.db_pool)
+    .await?;
+
+    info!(document_id = %document.id, chunks = total, "Ingested document");
+    Ok(())
+}
+
+/// Handle a new object under `documents/`.
+async fn handle_upload(
+    state: &AppState,
+    bucket: &str,
+    object: &S3ObjectInfo,
+) -> Result<(), ProcessError> {
+    let key = decode_key(&object.key);
+
+    let cognito_sub = match key
+        .strip_prefix(DOCUMENTS_PREFIX)
+        .and_then(|k| k.split_once('/'))
+    {
+        Some((sub, rest)) if !sub.is_empty() && !rest.is_empty() && !rest.ends_with('/') => sub,
+        _ => {
+            warn!(key, "Ignoring object outside a user documents folder");
+            return Ok(());
+        }
+    };
+
+    let user = match resolve_user(&state.db_pool, cognito_sub).await? {
+        Some(u) => u,
+        None => {
+            warn!(key, "Ignoring document for unknown user");
+            return Ok(());
+        }
+    };
+
+    let etag = object.e_tag.clone().unwrap_or_default();
+    let document = upsert_document(
+        &state.db_pool,
+        user.user_id,
+        bucket,
+        &key,
+        &etag,
+        object.size,
+    )
+    .await?;
+
+    if document.status != "pending" && document.status != "failed" {
+        info!(document_id = %document.id, status = %document.status, "Skipping duplicate delivery");
+        return Ok(());
+    }
+
+    let result = if is_supported(&key) {
+        start_text_detection(state, &document).await
+    } else {
+        Err(ProcessError::Permanent(
+            "Unsupported document type (use PDF, TIFF, JPEG or PNG)".to_string(),
+        ))
+    };
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(ProcessError::Permanent(reason)) => fail_document(state, &document, &reason)
+            .await
+            .map_err(ProcessError::from),
+        Err(ProcessError::Transient(reason)) if document.attempts >= MAX_ATTEMPTS => {
+            fail_document(state, &document, &reason)
+                .await
+                .map_err(ProcessError::from)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handle a Textract job completion.
+async fn handle_job(state: &AppState, job: &TextractJobNotification) -> Result<(), ProcessError> {
+    let document: Option<DocumentRow> = sqlx::query_as(
+        r#"
+        UPDATE document_ingests
+        SET attempts = attempts + 1, updated_at = NOW()
+        WHERE textract_job_id = $1 AND status IN ('extracting', 'ingesting')
+        RETURNING id, user_id, bucket, object_key, status::text, chunks_ingested, attempts
+        "#,
+    )
+    .bind(&job.job_id)
+    .fetch_optional(&state.db_pool)
+    .await?;
+
+    let document = match document {
+        Some(d) => d,
+        None => return Ok(()),
+    };
+
+    let cognito_sub: Option<String> =
+        sqlx::query_scalar("SELECT cognito_sub FROM users WHERE id = $1")
+            .bind(document.user_id)
+            .fetch_optional(&state.db_pool)
+            .await?;
+
+    let user = match cognito_sub {
+        Some(sub) => resolve_user(&state.db_pool, &sub).await?,
+        None => None,
+    };
+    let user = match user {
+        Some(u) => u,
+        None => return Ok(()),
+    };
+
+    let result = if job.status == "SUCCEEDED" {
+        ingest_document(state, &user, &document, &job.job_id).await
+    } else {
+        Err(ProcessError::Permanent(format!(
+            "Text detection {}",
+            job.status.to_lowercase()
+        )))
+    };
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(ProcessError::Permanent(reason)) => fail_document(state, &document, &reason)
+            .await
+            .map_err(ProcessError::from),
+        Err(ProcessError::Transient(reason)) if document.attempts >= MAX_ATTEMPTS => {
+            fail_document(state, &document, &reason)
+                .await
+                .map_err(ProcessError::from)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+async fn handle_sqs(state: &AppState, event: SqsEvent) -> SqsBatchResponse {
+    let mut failures = Vec::new();
+
+    for record in event.records {
+        let message: QueueMessage = match serde_json::from_str(&record.body) {
+            Ok(m) => m,
+            Err(e) => {
+                error!(message_id = %record.message_id, error = %e, "Invalid queue message");
+                continue;
+            }
+        };
+
+        let result = match &message {
+            QueueMessage::TextractJob(job) => handle_job(state, job).await,
+            QueueMessage::S3(notification) => {
+                let mut result = Ok(());
+                for s3_record in &notification.records {
+                    result =
+                        handle_upload(state, &s3_record.s3.bucket.name, &s3_record.s3.object).await;
+                    if result.is_err() {
+                        break;
+                    }
+                }
+                result
+            }
+        };
+
+        if let Err(ProcessError::Transient(e) | ProcessError::Permanent(e)) = result {
+            error!(message_id = %record.message_id, error = %e, "Failed to process document");
+            failures.push(BatchItemFailure {
+                item_identifier: record.message_id.clone(),
+            });
+        }
+    }
+
+    SqsBatchResponse {
+        batch_item_failures: failures,
+    }
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    event: LambdaEvent<SqsEvent>,
+) -> Result<SqsBatchResponse, Error> {
+    let response = handle_sqs(&state, event.payload).await;
+    info!(
+        failures = response.batch_item_failures.len(),
+        "Document ingest batch complete"
+    );
+    Ok(response)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        async move { metrics::track_invocation("document_ingest", handler(state, event)).await }
+    }))
+    .await
+}
@@ -0,0 +1,713 @@
+//! Drop Folder Ingest Lambda - Ingests files dropped into a user's S3 prefix.
+//!
+//! Files uploaded to `drop/{cognito_sub}/...` (from the mobile share sheet, rclone,
+//! etc.) produce S3 notifications that arrive via SQS. Each file is routed by type:
+//! - text (`.txt`, `.md`) is ingested as-is
+//! - images are OCR'd with Textract, then ingested
+//! - audio (voice memos dictated on mobile, recordings) starts a Transcribe job
+//!   with speaker labels; its output lands under `transcripts/` and arrives as
+//!   another notification. The speaker-labeled segments are stored in
+//!   `drop_folder_transcript_segments` and the transcript is ingested as voice
+//!
+//! Every file is recorded in `drop_folder_files`. Failures are recorded there and
+//! the user is sent a system notification. Transcribe job failures arrive as
+//! EventBridge "Transcribe Job State Change" events.
+
+use aws_sdk_sns::Client as SnsClient;
+use aws_sdk_textract::types::{BlockType, Document, S3Object};
+use aws_sdk_transcribe::types::{Media, Settings};
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use shared::digest::DIGEST_MAX_PRIORITY;
+use shared::metrics;
+use shared::reminders::{preferred_channel, NotificationPreferences};
+use shared::s3_ingest::{decode_key, file_name, resolve_user, ProcessError, MAX_ATTEMPTS};
+use shared::transcripts::{Transcript, MAX_SPEAKERS};
+use shared::{AgentClient, AgentRequest, AuthorizedUser};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// Prefix users drop files into: `drop/{cognito_sub}/...`
+const DROP_PREFIX: &str = "drop/";
+
+/// Prefix Transcribe writes job output to: `transcripts/{manifest_id}.json`
+const TRANSCRIPT_PREFIX: &str = "transcripts/";
+
+/// Largest text file ingested directly.
+const MAX_TEXT_BYTES: i64 = 64 * 1024;
+
+/// SQS event wrapper
+#[derive(Debug, Deserialize)]
+struct SqsEvent {
+    #[serde(rename = "Records")]
+    records: Vec<SqsRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SqsRecord {
+    #[serde(rename = "messageId")]
+    message_id: String,
+    body: String,
+}
+
+/// S3 event notification (SQS message body)
+#[derive(Debug, Deserialize)]
+struct S3Notification {
+    // Absent on the s3:TestEvent sent when the notification is configured
+    #[serde(rename = "Records", default)]
+    records: Vec<S3Record>,
+}
+
+#[derive(Debug, Deserialize)]
+struct S3Record {
+    s3: S3Entity,
+}
+
+#[derive(Debug, Deserialize)]
+struct S3Entity {
+    bucket: S3Bucket,
+    object: S3ObjectInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct S3Bucket {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct S3ObjectInfo {
+    key: String,
+    #[serde(default)]
+    size: Option<i64>,
+    #[serde(rename = "eTag", default)]
+    e_tag: Option<String>,
+}
+
+/// EventBridge event for Transcribe job state changes
+#[derive(Debug, Deserialize)]
+struct TranscribeJobEvent {
+    detail: TranscribeJobDetail,
+}
+
+#[derive(Debug, Deserialize)]
+struct TranscribeJobDetail {
+    #[serde(rename = "TranscriptionJobName")]
+    job_name: String,
+    #[serde(rename = "TranscriptionJobStatus")]
+    status: String,
+}
+
+/// SQS partial batch response
+#[derive(Debug, Serialize)]
+struct SqsBatchResponse {
+    #[serde(rename = "batchItemFailures")]
+    batch_item_failures: Vec<BatchItemFailure>,
+}
+
+#[derive(Debug, Serialize)]
+struct BatchItemFailure {
+    #[serde(rename = "itemIdentifier")]
+    item_identifier: String,
+}
+
+/// How a dropped file is ingested
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileKind {
+    Text,
+    Audio,
+    Image,
+    Unsupported,
+}
+
+impl FileKind {
+    fn from_key(key: &str) -> Self {
+        let ext = key
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_lowercase())
+            .unwrap_or_default();
+
+        match ext.as_str() {
+            "txt" | "text" | "md" | "markdown" => FileKind::Text,
+            "mp3" | "mp4" | "m4a" | "wav" | "flac" | "ogg" | "webm" | "amr" => FileKind::Audio,
+            "jpg" | "jpeg" | "png" | "tif" | "tiff" => FileKind::Image,
+            _ => FileKind::Unsupported,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            FileKind::Text => "text",
+            FileKind::Audio => "audio",
+            FileKind::Image => "image",
+            FileKind::Unsupported => "unsupported",
+        }
+    }
+}
+
+/// Manifest row for a dropped file
+#[derive(Debug, sqlx::FromRow)]
+struct ManifestRow {
+    id: Uuid,
+    user_id: Uuid,
+    object_key: String,
+    status: String,
+    attempts: i16,
+}
+
+struct AppState {
+    db_pool: PgPool,
+    s3_client: aws_sdk_s3::Client,
+    textract_client: aws_sdk_textract::Client,
+    transcribe_client: aws_sdk_transcribe::Client,
+    sns_client: SnsClient,
+    agent_client: AgentClient,
+    notification_topic_arn: Option<String>,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        let agent_function_name = std::env::var("AGENT_FUNCTION_NAME")
+            .unwrap_or_else(|_| "second-brain-agents".to_string());
+
+        Ok(Self {
+            db_pool,
+            s3_client: aws_sdk_s3::Client::new(&config),
+            textract_client: aws_sdk_textract::Client::new(&config),
+            transcribe_client: aws_sdk_transcribe::Client::new(&config),
+            sns_client: SnsClient::new(&config),
+            agent_client: AgentClient::new(aws_sdk_lambda::Client::new(&config), agent_function_name),
+            notification_topic_arn: std::env::var("NOTIFICATION_TOPIC_ARN").ok(),
+        })
+    }
+}
+
+fn transcription_job_name(manifest_id: Uuid) -> String {
+    format!("second-brain-drop-{}", manifest_id)
+}
+
+/// Record a file in the manifest, returning its row (attempts incremented on redelivery).
+async fn upsert_manifest(
+    pool: &PgPool,
+    user_id: Uuid,
+    bucket: &str,
+    key: &str,
+    etag: &str,
+    size: Option<i64>,
+    kind: FileKind,
+) -> Result<ManifestRow, sqlx::Error> {
+    sqlx::query_as(
+        r#"
+        INSERT INTO drop_folder_files (user_id, bucket, object_key, etag, size_bytes, file_kind)
+        VALUES ($1, $2, $3, $4, $5, $6::drop_file_kind)
+        ON CONFLICT (bucket, object_key, etag) DO UPDATE SET
+            attempts = drop_folder_files.attempts + 1,
+            updated_at = NOW()
+        RETURNING id, user_id, object_key, status::text, attempts
+        "#,
+    )
+    .bind(user_id)
+    .bind(bucket)
+    .bind(key)
+    .bind(etag)
+    .bind(size)
+    .bind(kind.as_str())
+    .fetch_one(pool)
+    .await
+}
+
+async fn mark_completed(pool: &PgPool, manifest_id: Uuid, extracted_chars: usize) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE drop_folder_files
+        SET status = 'completed', extracted_chars = $2, error_message = NULL,
+            processed_at = NOW(), updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(manifest_id)
+    .bind(extracted_chars as i32)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Replace the speaker-labeled segments stored for a transcribed file.
+async fn store_segments(pool: &PgPool, manifest_id: Uuid, transcript: &Transcript) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM drop_folder_transcript_segments WHERE file_id = $1")
+        .bind(manifest_id)
+        .execute(&mut *tx)
+        .await?;
+
+    for (index, segment) in transcript.segments.iter().enumerate() {
+        sqlx::query(
+            r#"
+            INSERT INTO drop_folder_transcript_segments
+                (file_id, segment_index, speaker_label, start_secs, end_secs, content)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(manifest_id)
+        .bind(index as i32)
+        .bind(&segment.speaker)
+        .bind(segment.start_secs as f32)
+        .bind(segment.end_secs as f32)
+        .bind(&segment.text)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    sqlx::query("UPDATE drop_folder_files SET speaker_count = $2, updated_at = NOW() WHERE id = $1")
+        .bind(manifest_id)
+        .bind(transcript.speaker_count() as i16)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await
+}
+
+/// Mark a file as failed and notify its owner.
+async fn fail_file(state: &AppState, manifest: &ManifestRow, reason: &str) -> Result<(), Error> {
+    warn!(manifest_id = %manifest.id, reason, "Drop folder file failed");
+
+    sqlx::query(
+        r#"
+        UPDATE drop_folder_files
+        SET status = 'failed', error_message = $2, processed_at = NOW(), updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(manifest.id)
+    .bind(reason)
+    .execute(&state.db_pool)
+    .await
+    .map_err(|e| format!("Failed to update manifest: {}", e))?;
+
+    let prefs: NotificationPreferences = sqlx::query_as(
+        r#"
+        SELECT
+            push_enabled,
+            email_enabled,
+            discord_enabled,
+            quiet_hours_enabled,
+            quiet_hours_start,
+            quiet_hours_end,
+            timezone
+        FROM user_notification_preferences
+        WHERE user_id = $1
+        "#,
+    )
+    .bind(manifest.user_id)
+    .fetch_optional(&state.db_pool)
+    .await
+    .map_err(|e| format!("Failed to query preferences: {}", e))?
+    .unwrap_or_default();
+
+    let title = "Couldn't import a dropped file";
+    let body = format!("{}: {}", file_name(&manifest.object_key), reason);
+
+    let notification_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO notifications (
+            user_id, notification_type, title, body, channel,
+            source_entity_id, source_entity_type, priority
+        ) VALUES ($1, 'system', $2, $3, $4::notification_channel, $5, 'drop_folder_file', $6)
+        RETURNING id
+        "#,
+    )
+    .bind(manifest.user_id)
+    .bind(title)
+    .bind(&body)
+    .bind(preferred_channel(&prefs))
+    .bind(manifest.id)
+    // Import failures can wait for the user's digest
+    .bind(DIGEST_MAX_PRIORITY)
+    .fetch_one(&state.db_pool)
+    .await
+    .map_err(|e| format!("Failed to queue notification: {}", e))?;
+
+    if let Some(topic_arn) = &state.notification_topic_arn {
+        let message = serde_json::json!({
+            "notification_id": notification_id.to_string(),
+            "type": "system",
+            "title": title,
+        });
+
+        state
+            .sns_client
+            .publish()
+            .topic_arn(topic_arn)
+            .message(serde_json::to_string(&message).unwrap_or_default())
+            .send()
+            .await
+            .map_err(|e| format!("Failed to publish to SNS: {}", e))?;
+    }
+
+    Ok(())
+}
+
+async fn read_object(state: &AppState, bucket: &str, key: &str) -> Result<Vec<u8>, ProcessError> {
+    let object = state
+        .s3_client
+        .get_object()
+        .bucket(bucket)
+        .key(key)
+        .send()
+        .await?;
+
+    let bytes = object.body.collect().await?.into_bytes();
+    Ok(bytes.to_vec())
+}
+
+async fn extract_image_text(state: &AppState, bucket: &str, key: &str) -> Result<String, ProcessError> {
+    let document = Document::builder()
+        .s3_object(S3Object::builder().bucket(bucket).name(key).build())
+        .build();
+
+    let output = state
+        .textract_client
+        .detect_document_text()
+        .document(document)
+        .send()
+        .await?;
+
+    let lines: Vec<&str> = output
+        .blocks()
+        .iter()
+        .filter(|b| b.block_type() == Some(&BlockType::Line))
+        .filter_map(|b| b.text())
+        .collect();
+
+    Ok(lines.join("\n"))
+}
+
+async fn start_transcription(
+    state: &AppState,
+    manifest_id: Uuid,
+    bucket: &str,
+    key: &str,
+) -> Result<(), ProcessError> {
+    let job_name = transcription_job_name(manifest_id);
+
+    state
+        .transcribe_client
+        .start_transcription_job()
+        .transcription_job_name(&job_name)
+        .media(Media::builder().media_file_uri(format!("s3://{}/{}", bucket, key)).build())
+        .identify_language(true)
+        .settings(
+            Settings::builder()
+                .show_speaker_labels(true)
+                .max_speaker_labels(MAX_SPEAKERS)
+                .build(),
+        )
+        .output_bucket_name(bucket)
+        .output_key(format!("{}{}.json", TRANSCRIPT_PREFIX, manifest_id))
+        .send()
+        .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE drop_folder_files
+        SET status = 'transcribing', transcription_job = $2, updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(manifest_id)
+    .bind(&job_name)
+    .execute(&state.db_pool)
+    .await?;
+
+    info!(manifest_id = %manifest_id, job_name, "Started transcription job");
+    Ok(())
+}
+
+/// Hand extracted content to the ingestion agent and complete the manifest row.
+/// `modality` is `voice` for transcribed audio.
+async fn ingest_content(
+    state: &AppState,
+    user: &AuthorizedUser,
+    manifest_id: Uuid,
+    content: &str,
+    modality: Option<&str>,
+) -> Result<(), ProcessError> {
+    let content = content.trim();
+    if content.is_empty() {
+        return Err(ProcessError::Permanent("No content found in file".to_string()));
+    }
+
+    let family_ids = user.family_ids.iter().map(|id| id.to_string()).collect();
+    state
+        .agent_client
+        .invoke(AgentRequest {
+            message: content.to_string(),
+            user_id: user.cognito_sub.clone(),
+            family_ids,
+            device_id: None,
+            conversation_id: None,
+            conversation_history: Vec::new(),
+            intent: Some("ingest".to_string()),
+            source: "drop_folder".to_string(),
+            modality: modality.map(str::to_string),
+            metadata: None,
+        })
+        .await?;
+
+    mark_completed(&state.db_pool, manifest_id, content.chars().count()).await?;
+    info!(manifest_id = %manifest_id, chars = content.len(), "Ingested dropped file");
+    Ok(())
+}
+
+async fn process_upload(
+    state: &AppState,
+    user: &AuthorizedUser,
+    manifest: &ManifestRow,
+    kind: FileKind,
+    bucket: &str,
+    size: Option<i64>,
+) -> Result<(), ProcessError> {
+    let key = &manifest.object_key;
+
+    match kind {
+        FileKind::Unsupported => Err(ProcessError::Permanent("Unsupported file type".to_string())),
+        FileKind::Text => {
+            if size.unwrap_or(0) > MAX_TEXT_BYTES {
+                return Err(ProcessError::Permanent(format!(
+                    "Text files must be under {} KB",
+                    MAX_TEXT_BYTES / 1024
+                )));
+            }
+            let bytes = read_object(state, bucket, key).await?;
+            let text = String::from_utf8(bytes)
+                .map_err(|_| ProcessError::Permanent("File is not valid UTF-8 text".to_string()))?;
+            ingest_content(state, user, manifest.id, &text, None).await
+        }
+        FileKind::Image => {
+            let text = extract_image_text(state, bucket, key).await?;
+            ingest_content(state, user, manifest.id, &text, None).await
+        }
+        FileKind::Audio => start_transcription(state, manifest.id, bucket, key).await,
+    }
+}
+
+/// Handle a new object under `drop/`.
+async fn handle_upload(state: &AppState, bucket: &str, object: &S3ObjectInfo) -> Result<(), ProcessError> {
+    let key = decode_key(&object.key);
+
+    let (cognito_sub, rest) = match key.strip_prefix(DROP_PREFIX).and_then(|k| k.split_once('/')) {
+        Some((sub, rest)) if !sub.is_empty() && !rest.is_empty() && !rest.ends_with('/') => (sub, rest),
+        _ => {
+            warn!(key, "Ignoring object outside a user drop folder");
+            return Ok(());
+        }
+    };
+
+    let user = match resolve_user(&state.db_pool, cognito_sub).await? {
+        Some(u) => u,
+        None => {
+            warn!(key, "Ignoring drop for unknown user");
+            return Ok(());
+        }
+    };
+
+    let kind = FileKind::from_key(rest);
+    let etag = object.e_tag.clone().unwrap_or_default();
+    let manifest = upsert_manifest(&state.db_pool, user.user_id, bucket, &key, &etag, object.size, kind).await?;
+
+    if manifest.status == "completed" || manifest.status == "transcribing" {
+        info!(manifest_id = %manifest.id, status = %manifest.status, "Skipping duplicate delivery");
+        return Ok(());
+    }
+
+    match process_upload(state, &user, &manifest, kind, bucket, object.size).await {
+        Ok(()) => Ok(()),
+        Err(ProcessError::Permanent(reason)) => fail_file(state, &manifest, &reason)
+            .await
+            .map_err(ProcessError::from),
+        Err(ProcessError::Transient(reason)) if manifest.attempts >= MAX_ATTEMPTS => {
+            fail_file(state, &manifest, &reason).await.map_err(ProcessError::from)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Handle a Transcribe output object under `transcripts/`.
+async fn handle_transcript(state: &AppState, bucket: &str, object: &S3ObjectInfo) -> Result<(), ProcessError> {
+    let key = decode_key(&object.key);
+
+    // Transcribe also writes a write-access check file here
+    let manifest_id = match key
+        .strip_prefix(TRANSCRIPT_PREFIX)
+        .and_then(|k| k.strip_suffix(".json"))
+        .and_then(|id| Uuid::parse_str(id).ok())
+    {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    let manifest: Option<ManifestRow> = sqlx::query_as(
+        "SELECT id, user_id, object_key, status::text, attempts FROM drop_folder_files WHERE id = $1",
+    )
+    .bind(manifest_id)
+    .fetch_optional(&state.db_pool)
+    .await?;
+
+    let manifest = match manifest {
+        Some(m) if m.status == "transcribing" => m,
+        _ => return Ok(()),
+    };
+
+    let cognito_sub: Option<String> = sqlx::query_scalar("SELECT cognito_sub FROM users WHERE id = $1")
+        .bind(manifest.user_id)
+        .fetch_optional(&state.db_pool)
+        .await?;
+
+    let user = match cognito_sub {
+        Some(sub) => resolve_user(&state.db_pool, &sub).await?,
+        None => None,
+    };
+    let user = match user {
+        Some(u) => u,
+        None => return Ok(()),
+    };
+
+    let result = async {
+        let bytes = read_object(state, bucket, &key).await?;
+        let transcript = Transcript::parse(&bytes)
+            .ok_or_else(|| ProcessError::Permanent("Unreadable transcription output".to_string()))?;
+        store_segments(&state.db_pool, manifest.id, &transcript).await?;
+        ingest_content(state, &user, manifest.id, &transcript.labeled_text(), Some("voice")).await
+    }
+    .await;
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(ProcessError::Permanent(reason)) => fail_file(state, &manifest, &reason)
+            .await
+            .map_err(ProcessError::from),
+        Err(e) => Err(e),
+    }
+}
+
+async fn handle_sqs(state: &AppState, event: SqsEvent) -> SqsBatchResponse {
+    let mut failures = Vec::new();
+
+    for record in event.records {
+        let notification: S3Notification = match serde_json::from_str(&record.body) {
+            Ok(n) => n,
+            Err(e) => {
+                error!(message_id = %record.message_id, error = %e, "Invalid S3 notification");
+                continue;
+            }
+        };
+
+        for s3_record in notification.records {
+            let bucket = &s3_record.s3.bucket.name;
+            let object = &s3_record.s3.object;
+
+            let result = if object.key.starts_with(TRANSCRIPT_PREFIX) {
+                handle_transcript(state, bucket, object).await
+            } else {
+                handle_upload(state, bucket, object).await
+            };
+
+            if let Err(ProcessError::Transient(e) | ProcessError::Permanent(e)) = result {
+                error!(message_id = %record.message_id, key = %object.key, error = %e, "Failed to process dropped file");
+                failures.push(BatchItemFailure {
+                    item_identifier: record.message_id.clone(),
+                });
+                break;
+            }
+        }
+    }
+
+    SqsBatchResponse {
+        batch_item_failures: failures,
+    }
+}
+
+async fn handle_transcribe_event(state: &AppState, event: TranscribeJobEvent) -> Result<(), Error> {
+    if event.detail.status != "FAILED" {
+        return Ok(());
+    }
+
+    let manifest: Option<ManifestRow> = sqlx::query_as(
+        r#"
+        SELECT id, user_id, object_key, status::text, attempts
+        FROM drop_folder_files
+        WHERE transcription_job = $1 AND status = 'transcribing'
+        "#,
+    )
+    .bind(&event.detail.job_name)
+    .fetch_optional(&state.db_pool)
+    .await
+    .map_err(|e| format!("Failed to look up transcription job: {}", e))?;
+
+    let manifest = match manifest {
+        Some(m) => m,
+        None => return Ok(()),
+    };
+
+    let reason = state
+        .transcribe_client
+        .get_transcription_job()
+        .transcription_job_name(&event.detail.job_name)
+        .send()
+        .await
+        .ok()
+        .and_then(|r| r.transcription_job().and_then(|j| j.failure_reason()).map(String::from))
+        .unwrap_or_else(|| "Transcription failed".to_string());
+
+    fail_file(state, &manifest, &reason).await
+}
+
+async fn handler(state: Arc<AppState>, event: LambdaEvent<Value>) -> Result<Value, Error> {
+    let (payload, _context) = event.into_parts();
+
+    if payload.get("Records").is_some() {
+        let sqs_event: SqsEvent = serde_json::from_value(payload)?;
+        let response = handle_sqs(&state, sqs_event).await;
+        info!(
+            failures = response.batch_item_failures.len(),
+            "Drop folder batch complete"
+        );
+        return Ok(serde_json::to_value(response)?);
+    }
+
+    if payload.get("detail-type").and_then(|t| t.as_str()) == Some("Transcribe Job State Change") {
+        let event: TranscribeJobEvent = serde_json::from_value(payload)?;
+        handle_transcribe_event(&state, event).await?;
+        return Ok(serde_json::json!({ "status": "ok" }));
+    }
+
+    warn!("Ignoring unrecognized event");
+    Ok(serde_json::json!({ "status": "ignored" }))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        async move { metrics::track_invocation("drop_folder_ingest", handler(state, event)).await }
+    }))
+    .await
+}
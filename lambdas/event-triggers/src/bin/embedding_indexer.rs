@@ -0,0 +1,460 @@
+//! Embedding Indexer Lambda - Generates vector embeddings for facts.
+//!
+//! This Lambda is the ingestion half of semantic search. It runs in two modes:
+//! 1. SQS-triggered: `FactCreated` and `FactUpdated` events are queued as
+//!    `{"fact_id": ...}` messages. Failed messages are reported back as partial
+//!    batch failures so SQS retries them (and eventually moves them to the DLQ).
+//! 2. Scheduled (EventBridge): sweeps facts with missing or stale embeddings so
+//!    anything that slipped past the queue is eventually indexed.
+//!
+//! Failures are recorded in `fact_embedding_failures` against the version of
+//! the fact tried. Content the model rejects fails for good, and a fact that
+//! fails `MAX_FAILED_ATTEMPTS` times is given up on, so the sweep doesn't try
+//! the same facts on every run ahead of the rest; editing the fact tries again.
+
+use chrono::{DateTime, Utc};
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use shared::embeddings::to_pgvector;
+use shared::metrics;
+use shared::tag_rules::apply_tag_rules;
+use shared::EmbeddingClient;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// Maximum attempts per fact when calling the embedding model.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Base delay for exponential backoff between attempts.
+const RETRY_BASE_DELAY_MS: u64 = 200;
+
+/// Default number of facts processed per scheduled sweep.
+const DEFAULT_SWEEP_BATCH_SIZE: i64 = 100;
+
+/// Failed indexing runs after which a fact version is given up on.
+const MAX_FAILED_ATTEMPTS: i32 = 5;
+
+/// SQS event wrapper
+#[derive(Debug, Deserialize)]
+struct SqsEvent {
+    #[serde(rename = "Records")]
+    records: Vec<SqsRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SqsRecord {
+    #[serde(rename = "messageId")]
+    message_id: String,
+    body: String,
+}
+
+/// Message body published when a fact is inserted or updated
+#[derive(Debug, Deserialize)]
+struct FactChangedMessage {
+    fact_id: String,
+}
+
+/// EventBridge scheduled event
+#[derive(Debug, Deserialize)]
+struct ScheduledEvent {
+    #[serde(default)]
+    batch_size: Option<i64>,
+}
+
+/// SQS partial batch response
+#[derive(Debug, Serialize)]
+struct SqsBatchResponse {
+    #[serde(rename = "batchItemFailures")]
+    batch_item_failures: Vec<BatchItemFailure>,
+}
+
+#[derive(Debug, Serialize)]
+struct BatchItemFailure {
+    #[serde(rename = "itemIdentifier")]
+    item_identifier: String,
+}
+
+/// Scheduled sweep response
+#[derive(Debug, Serialize)]
+struct SweepResponse {
+    facts_found: u32,
+    embeddings_stored: u32,
+    errors: u32,
+}
+
+/// Fact needing an embedding
+#[derive(Debug, sqlx::FromRow)]
+struct FactRow {
+    id: Uuid,
+    content: String,
+    updated_at: DateTime<Utc>,
+}
+
+struct AppState {
+    db_pool: PgPool,
+    embedding_client: EmbeddingClient,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        let bedrock_client = aws_sdk_bedrockruntime::Client::new(&config);
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        let embedding_client = match std::env::var("EMBEDDING_MODEL_ID") {
+            Ok(model_id) => EmbeddingClient::with_model(bedrock_client, model_id),
+            Err(_) => EmbeddingClient::new(bedrock_client),
+        };
+
+        Ok(Self {
+            db_pool,
+            embedding_client,
+        })
+    }
+}
+
+/// Fetch the given facts, skipping deleted ones and any whose embedding is
+/// already current or that have been given up on.
+async fn get_facts_needing_embeddings(
+    pool: &PgPool,
+    fact_ids: &[Uuid],
+) -> Result<Vec<FactRow>, Error> {
+    let facts: Vec<FactRow> = sqlx::query_as(
+        r#"
+        SELECT f.id, f.content, f.updated_at
+        FROM facts f
+        LEFT JOIN fact_embeddings fe ON fe.fact_id = f.id
+        LEFT JOIN fact_embedding_failures ff
+            ON ff.fact_id = f.id AND ff.fact_updated_at = f.updated_at
+        WHERE f.id = ANY($1)
+        AND f.deleted_at IS NULL
+        AND (fe.fact_id IS NULL OR fe.created_at < f.updated_at)
+        AND (ff.fact_id IS NULL OR (NOT ff.permanent AND ff.attempts < $2))
+        "#,
+    )
+    .bind(fact_ids)
+    .bind(MAX_FAILED_ATTEMPTS)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch facts: {}", e))?;
+
+    Ok(facts)
+}
+
+/// Find live facts with missing or stale embeddings (oldest first), skipping
+/// those that have been given up on.
+async fn get_unindexed_facts(pool: &PgPool, limit: i64) -> Result<Vec<FactRow>, Error> {
+    let facts: Vec<FactRow> = sqlx::query_as(
+        r#"
+        SELECT f.id, f.content, f.updated_at
+        FROM facts f
+        LEFT JOIN fact_embeddings fe ON fe.fact_id = f.id
+        LEFT JOIN fact_embedding_failures ff
+            ON ff.fact_id = f.id AND ff.fact_updated_at = f.updated_at
+        WHERE f.deleted_at IS NULL
+        AND (fe.fact_id IS NULL OR fe.created_at < f.updated_at)
+        AND (ff.fact_id IS NULL OR (NOT ff.permanent AND ff.attempts < $2))
+        ORDER BY f.updated_at ASC
+        LIMIT $1
+        "#,
+    )
+    .bind(limit)
+    .bind(MAX_FAILED_ATTEMPTS)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to query unindexed facts: {}", e))?;
+
+    Ok(facts)
+}
+
+/// Generate an embedding with exponential backoff on failure. Validation
+/// errors (content the model can't embed) are returned at once.
+async fn embed_with_retry(client: &EmbeddingClient, text: &str) -> shared::Result<Vec<f32>> {
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        match client.embed(text).await {
+            Ok(embedding) => return Ok(embedding),
+            Err(e @ shared::Error::Validation(_)) => return Err(e),
+            Err(e) if attempt < MAX_ATTEMPTS => {
+                let delay = RETRY_BASE_DELAY_MS * 2u64.pow(attempt - 1);
+                warn!(attempt, delay_ms = delay, error = %e, "Embedding failed, retrying");
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+async fn store_embedding(
+    state: &AppState,
+    fact_id: Uuid,
+    embedding: &[f32],
+    fact_updated_at: DateTime<Utc>,
+) -> shared::Result<()> {
+    // Stamp with the fact version we embedded (not NOW()) so an update that
+    // lands while we embed still leaves this row stale for the next pass.
+    sqlx::query(
+        r#"
+        INSERT INTO fact_embeddings (fact_id, embedding, model_id, created_at)
+        VALUES ($1, $2::vector, $3, $4)
+        ON CONFLICT (fact_id) DO UPDATE SET
+            embedding = EXCLUDED.embedding,
+            model_id = EXCLUDED.model_id,
+            created_at = EXCLUDED.created_at
+        "#,
+    )
+    .bind(fact_id)
+    .bind(to_pgvector(embedding))
+    .bind(state.embedding_client.model_id())
+    .bind(fact_updated_at)
+    .execute(&state.db_pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Record a failed attempt at indexing this version of a fact. Attempts
+/// start over when the fact has been edited since the last failure.
+async fn record_failure(
+    pool: &PgPool,
+    fact: &FactRow,
+    error: &shared::Error,
+) -> shared::Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO fact_embedding_failures (fact_id, fact_updated_at, permanent, last_error)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (fact_id) DO UPDATE SET
+            attempts = CASE
+                WHEN fact_embedding_failures.fact_updated_at = EXCLUDED.fact_updated_at
+                THEN fact_embedding_failures.attempts + 1
+                ELSE 1
+            END,
+            fact_updated_at = EXCLUDED.fact_updated_at,
+            permanent = EXCLUDED.permanent,
+            last_error = EXCLUDED.last_error,
+            failed_at = NOW()
+        "#,
+    )
+    .bind(fact.id)
+    .bind(fact.updated_at)
+    .bind(is_permanent(error))
+    .bind(error.to_string())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Whether retrying can't fix an indexing failure
+fn is_permanent(error: &shared::Error) -> bool {
+    matches!(error, shared::Error::Validation(_))
+}
+
+async fn embed_and_store(state: &AppState, fact: &FactRow) -> shared::Result<()> {
+    let embedding = embed_with_retry(&state.embedding_client, &fact.content).await?;
+    store_embedding(state, fact.id, &embedding, fact.updated_at).await
+}
+
+async fn index_fact(state: &AppState, fact: &FactRow) -> shared::Result<()> {
+    if let Err(e) = embed_and_store(state, fact).await {
+        if let Err(record_error) = record_failure(&state.db_pool, fact, &e).await {
+            warn!(fact_id = %fact.id, error = %record_error, "Failed to record indexing failure");
+        }
+        return Err(e);
+    }
+
+    sqlx::query("DELETE FROM fact_embedding_failures WHERE fact_id = $1")
+        .bind(fact.id)
+        .execute(&state.db_pool)
+        .await?;
+
+    // New or edited content may now match the creator's tag rules; a failure
+    // here shouldn't fail indexing (tag_rule_backfill catches up)
+    if let Err(e) = apply_tag_rules(&state.db_pool, fact.id).await {
+        warn!(fact_id = %fact.id, error = %e, "Failed to apply tag rules");
+    }
+
+    Ok(())
+}
+
+async fn handle_sqs(state: &AppState, event: SqsEvent) -> Result<SqsBatchResponse, Error> {
+    let mut failures = Vec::new();
+    let mut message_ids: HashMap<Uuid, Vec<String>> = HashMap::new();
+
+    for record in event.records {
+        match serde_json::from_str::<FactChangedMessage>(&record.body)
+            .ok()
+            .and_then(|m| Uuid::parse_str(&m.fact_id).ok())
+        {
+            Some(fact_id) => message_ids
+                .entry(fact_id)
+                .or_default()
+                .push(record.message_id),
+            None => {
+                // Malformed messages will never succeed; drop them instead of retrying
+                error!(message_id = %record.message_id, "Invalid embedding message body");
+            }
+        }
+    }
+
+    let fact_ids: Vec<Uuid> = message_ids.keys().copied().collect();
+    let facts = match get_facts_needing_embeddings(&state.db_pool, &fact_ids).await {
+        Ok(f) => f,
+        Err(e) => {
+            // Whole batch failed - let SQS redeliver everything
+            error!(error = %e, "Failed to load facts for embedding");
+            return Ok(SqsBatchResponse {
+                batch_item_failures: message_ids
+                    .into_values()
+                    .flatten()
+                    .map(|id| BatchItemFailure {
+                        item_identifier: id,
+                    })
+                    .collect(),
+            });
+        }
+    };
+
+    info!(
+        messages = fact_ids.len(),
+        facts_to_index = facts.len(),
+        "Indexing facts"
+    );
+
+    for fact in &facts {
+        match index_fact(state, fact).await {
+            Ok(()) => {}
+            // Redelivering won't help; the failure is recorded
+            Err(e) if is_permanent(&e) => {
+                warn!(fact_id = %fact.id, error = %e, "Fact can't be embedded");
+            }
+            Err(e) => {
+                error!(fact_id = %fact.id, error = %e, "Failed to index fact");
+                if let Some(ids) = message_ids.get(&fact.id) {
+                    failures.extend(ids.iter().map(|id| BatchItemFailure {
+                        item_identifier: id.clone(),
+                    }));
+                }
+            }
+        }
+    }
+
+    Ok(SqsBatchResponse {
+        batch_item_failures: failures,
+    })
+}
+
+async fn handle_sweep(state: &AppState, event: ScheduledEvent) -> Result<SweepResponse, Error> {
+    let batch_size = event.batch_size.unwrap_or(DEFAULT_SWEEP_BATCH_SIZE);
+    let facts = get_unindexed_facts(&state.db_pool, batch_size).await?;
+
+    let mut embeddings_stored = 0u32;
+    let mut errors = 0u32;
+
+    for fact in &facts {
+        match index_fact(state, fact).await {
+            Ok(_) => embeddings_stored += 1,
+            Err(e) => {
+                error!(fact_id = %fact.id, error = %e, "Failed to index fact");
+                errors += 1;
+            }
+        }
+    }
+
+    Ok(SweepResponse {
+        facts_found: facts.len() as u32,
+        embeddings_stored,
+        errors,
+    })
+}
+
+async fn handler(state: Arc<AppState>, event: LambdaEvent<Value>) -> Result<Value, Error> {
+    let (payload, _context) = event.into_parts();
+
+    if payload.get("Records").is_some() {
+        let sqs_event: SqsEvent = serde_json::from_value(payload)?;
+        let response = handle_sqs(&state, sqs_event).await?;
+        info!(
+            failures = response.batch_item_failures.len(),
+            "Embedding batch complete"
+        );
+        return Ok(serde_json::to_value(response)?);
+    }
+
+    let scheduled: ScheduledEvent =
+        serde_json::from_value(payload).unwrap_or(ScheduledEvent { batch_size: None });
+    let response = handle_sweep(&state, scheduled).await?;
+    info!(
+        facts_found = response.facts_found,
+        embeddings_stored = response.embeddings_stored,
+        errors = response.errors,
+        "Embedding sweep complete"
+    );
+    Ok(serde_json::to_value(response)?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        async move { metrics::track_invocation("embedding_indexer", handler(state, event)).await }
+    }))
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test(migrator = "migrations::MIGRATOR")]
+    #[ignore = "needs DATABASE_URL"]
+    async fn test_deleted_facts_are_not_indexed(pool: PgPool) {
+        let user_id: Uuid = sqlx::query_scalar(
+            "INSERT INTO users (cognito_sub, email, display_name)
+             VALUES ('sub', 'sam@example.com', 'Sam') RETURNING id",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+        let mut fact_ids = Vec::new();
+        for (content, deleted) in [("Gate code is 4821", false), ("Old gate code", true)] {
+            let id: Uuid = sqlx::query_scalar(
+                "INSERT INTO facts (owner_type, owner_id, created_by, content, deleted_at)
+                 VALUES ('user', $1, $1, $2, CASE WHEN $3 THEN NOW() END) RETURNING id",
+            )
+            .bind(user_id)
+            .bind(content)
+            .bind(deleted)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+            fact_ids.push(id);
+        }
+
+        let ids = |facts: Vec<FactRow>| facts.into_iter().map(|f| f.id).collect::<Vec<_>>();
+        let live = vec![fact_ids[0]];
+        let swept = get_unindexed_facts(&pool, 10).await.unwrap();
+        assert_eq!(ids(swept), live);
+        let queued = get_facts_needing_embeddings(&pool, &fact_ids);
+        assert_eq!(ids(queued.await.unwrap()), live);
+    }
+}
@@ -0,0 +1,533 @@
+//! Feed Poller Lambda - Summarizes new RSS/Atom feed items.
+//!
+//! Runs hourly via EventBridge. Each enabled feed in `rss_feeds` is fetched
+//! with the ETag and Last-Modified from its previous poll, so an unchanged
+//! feed costs a 304. Items are parsed with `shared::feeds` and deduplicated by
+//! GUID against `rss_feed_items`; each new item is handed to the ingestion
+//! agent to summarize, and the facts it creates are lowered to
+//! `FEED_FACT_IMPORTANCE` and tagged `reading/feeds`.
+//!
+//! At most `MAX_NEW_ITEMS_PER_POLL` items are summarized per feed and poll,
+//! newest first; older ones wait for the next poll. On a feed's first poll the
+//! older items are instead marked as seen, so subscribing to a feed with a
+//! long archive doesn't summarize all of it.
+//!
+//! Feeds poll concurrently (each with a timeout), and one feed's failure is
+//! stored in its `last_error` without affecting the others.
+
+use chrono::{DateTime, Utc};
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use shared::feeds::{
+    item_message, parse_feed, unseen_items, FeedItem, FEED_FACT_IMPORTANCE, FEED_TAG_PATH,
+    MAX_FEED_BYTES, MAX_NEW_ITEMS_PER_POLL,
+};
+use shared::metrics;
+use shared::public_http;
+use shared::{AgentClient, AuthenticatedUser, AuthorizedUser};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// Source reported to the agents
+const SOURCE: &str = "feed";
+
+/// Timeout for fetching a feed
+const FEED_TIMEOUT_SECS: u64 = 30;
+
+/// Time allowed for one feed's poll; unfinished items are picked up next run
+const FEED_POLL_TIMEOUT_SECS: u64 = 180;
+
+/// Feeds polled concurrently (bounded by the DB pool size)
+const MAX_CONCURRENT_FEEDS: usize = 5;
+
+/// EventBridge scheduled event
+#[derive(Debug, Deserialize)]
+struct ScheduledEvent {
+    #[serde(default)]
+    detail_type: String,
+    /// Optional: poll only this user's feeds
+    user_id: Option<String>,
+}
+
+/// Poll response
+#[derive(Debug, Default, Serialize)]
+struct PollResponse {
+    feeds_polled: u32,
+    feeds_unchanged: u32,
+    feeds_failed: u32,
+    items_summarized: u32,
+    items_skipped: u32,
+    facts_created: u32,
+    /// What failed, per feed; the other feeds' results still count
+    failures: Vec<PollFailure>,
+}
+
+/// A failed feed poll
+#[derive(Debug, Serialize)]
+struct PollFailure {
+    feed_id: String,
+    error: String,
+}
+
+/// Enabled feed, with its owner
+#[derive(Debug, sqlx::FromRow)]
+struct FeedRow {
+    id: Uuid,
+    user_id: Uuid,
+    cognito_sub: String,
+    feed_url: String,
+    name: Option<String>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    last_polled_at: Option<DateTime<Utc>>,
+}
+
+/// Outcome of a feed that changed
+#[derive(Debug, Default)]
+struct FeedPoll {
+    items_summarized: u32,
+    items_skipped: u32,
+    facts_created: u32,
+}
+
+/// Application state
+struct AppState {
+    db_pool: PgPool,
+    http_client: reqwest::Client,
+    agent_client: AgentClient,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        let agent_function_name = std::env::var("AGENT_FUNCTION_NAME")
+            .unwrap_or_else(|_| "second-brain-agents".to_string());
+
+        Ok(Self {
+            db_pool,
+            http_client: public_http::client(std::time::Duration::from_secs(FEED_TIMEOUT_SECS))?,
+            agent_client: AgentClient::new(
+                aws_sdk_lambda::Client::new(&config),
+                agent_function_name,
+            ),
+        })
+    }
+
+    /// Enabled feeds, for one user or everyone, least recently polled first
+    async fn get_feeds(&self, user_id: Option<Uuid>) -> Result<Vec<FeedRow>, Error> {
+        let feeds = sqlx::query_as(
+            r#"
+            SELECT f.id, f.user_id, u.cognito_sub, f.feed_url, f.name,
+                   f.etag, f.last_modified, f.last_polled_at
+            FROM rss_feeds f
+            JOIN users u ON u.id = f.user_id
+            WHERE f.enabled AND ($1::uuid IS NULL OR f.user_id = $1)
+            ORDER BY f.last_polled_at NULLS FIRST
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to query feeds: {}", e))?;
+
+        Ok(feeds)
+    }
+
+    /// The system `reading/feeds` tag
+    async fn feed_tag_id(&self) -> Result<Option<Uuid>, Error> {
+        let tag_id =
+            sqlx::query_scalar("SELECT id FROM tags WHERE owner_type IS NULL AND path = $1")
+                .bind(FEED_TAG_PATH)
+                .fetch_optional(&self.db_pool)
+                .await
+                .map_err(|e| format!("Failed to look up feed tag: {}", e))?;
+
+        Ok(tag_id)
+    }
+
+    /// Fetch a feed and summarize its new items; `None` if it hasn't changed
+    /// since the last poll.
+    async fn poll_feed(
+        &self,
+        feed: &FeedRow,
+        tag_id: Option<Uuid>,
+    ) -> Result<Option<FeedPoll>, Error> {
+        // Literal IP hosts aren't resolved, so the client can't check them
+        let feed_url =
+            reqwest::Url::parse(&feed.feed_url).map_err(|e| format!("Invalid feed URL: {}", e))?;
+        if !public_http::is_public_host(&feed_url) {
+            return Err("Feed host is not public".into());
+        }
+
+        let mut request = self.http_client.get(feed_url).header(
+            "Accept",
+            "application/rss+xml, application/atom+xml, application/xml;q=0.9, */*;q=0.8",
+        );
+        if let Some(etag) = &feed.etag {
+            request = request.header("If-None-Match", etag);
+        }
+        if let Some(last_modified) = &feed.last_modified {
+            request = request.header("If-Modified-Since", last_modified);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Feed request failed: {}", e))?;
+
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            self.record_poll(feed.id, None, None, None).await?;
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(format!("Feed returned HTTP {}", response.status()).into());
+        }
+        if response
+            .content_length()
+            .is_some_and(|length| length as usize > MAX_FEED_BYTES)
+        {
+            return Err("Feed is too large".into());
+        }
+
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header("etag");
+        let last_modified = header("last-modified");
+
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| format!("Failed to read feed: {}", e))?;
+        if body.len() > MAX_FEED_BYTES {
+            return Err("Feed is too large".into());
+        }
+
+        let parsed = parse_feed(&body)?;
+        let feed_name = feed
+            .name
+            .clone()
+            .or_else(|| parsed.title.clone())
+            .unwrap_or_else(|| "Untitled feed".to_string());
+
+        let guids: Vec<&str> = parsed.items.iter().map(|i| i.guid.as_str()).collect();
+        let seen: Vec<String> = sqlx::query_scalar(
+            "SELECT guid FROM rss_feed_items WHERE feed_id = $1 AND guid = ANY($2)",
+        )
+        .bind(feed.id)
+        .bind(&guids)
+        .fetch_all(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to query seen items: {}", e))?;
+        let seen: HashSet<String> = seen.into_iter().collect();
+
+        let mut new_items = unseen_items(parsed.items, &seen);
+        let older = new_items.split_off(new_items.len().min(MAX_NEW_ITEMS_PER_POLL));
+
+        let mut poll = FeedPoll::default();
+
+        // A new subscription's archive is marked as seen, not summarized
+        if feed.last_polled_at.is_none() {
+            for item in &older {
+                self.record_item(feed.id, item, &[]).await?;
+                poll.items_skipped += 1;
+            }
+        }
+
+        let user = self.resolve_user(&feed.cognito_sub).await?;
+        let family_ids: Vec<String> = user.family_ids.iter().map(|id| id.to_string()).collect();
+
+        for item in &new_items {
+            let metadata = serde_json::json!({
+                "feed_id": feed.id,
+                "guid": item.guid,
+                "url": item.link,
+            });
+
+            let response = self
+                .agent_client
+                .ingest_with_metadata(
+                    &item_message(&feed_name, item),
+                    &feed.cognito_sub,
+                    family_ids.clone(),
+                    SOURCE,
+                    Some(metadata),
+                )
+                .await
+                .map_err(|e| format!("Failed to summarize item {}: {}", item.guid, e))?;
+
+            let fact_ids = response
+                .metadata
+                .and_then(|m| m.fact_ids)
+                .unwrap_or_default();
+
+            self.file_facts(feed.user_id, &fact_ids, tag_id).await?;
+            self.record_item(feed.id, item, &fact_ids).await?;
+
+            poll.items_summarized += 1;
+            poll.facts_created += fact_ids.len() as u32;
+        }
+
+        self.record_poll(
+            feed.id,
+            Some((etag, last_modified)),
+            parsed.title.as_deref(),
+            None,
+        )
+        .await?;
+
+        Ok(Some(poll))
+    }
+
+    /// Resolve a feed owner's family memberships.
+    async fn resolve_user(&self, cognito_sub: &str) -> Result<AuthorizedUser, Error> {
+        let user = AuthenticatedUser {
+            user_id: cognito_sub.to_string(),
+            email: None,
+            family_ids: Vec::new(),
+        };
+
+        AuthorizedUser::resolve(user, &self.db_pool)
+            .await
+            .map_err(|e| format!("Failed to resolve user: {}", e).into())
+    }
+
+    /// Lower the importance of facts summarized from an item and tag them.
+    async fn file_facts(
+        &self,
+        user_id: Uuid,
+        fact_ids: &[Uuid],
+        tag_id: Option<Uuid>,
+    ) -> Result<(), Error> {
+        if fact_ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut tx = self
+            .db_pool
+            .begin()
+            .await
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        sqlx::query(
+            r#"
+            UPDATE facts SET importance = $3, updated_at = NOW()
+            WHERE id = ANY($1) AND created_by = $2
+            "#,
+        )
+        .bind(fact_ids)
+        .bind(user_id)
+        .bind(FEED_FACT_IMPORTANCE)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to update fact importance: {}", e))?;
+
+        if let Some(tag_id) = tag_id {
+            sqlx::query(
+                r#"
+                INSERT INTO fact_tags (fact_id, tag_id)
+                SELECT f.id, $2 FROM facts f WHERE f.id = ANY($1)
+                ON CONFLICT (fact_id, tag_id) DO NOTHING
+                "#,
+            )
+            .bind(fact_ids)
+            .bind(tag_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to tag facts: {}", e))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| format!("Failed to commit: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Mark an item as seen, with the facts summarized from it.
+    async fn record_item(
+        &self,
+        feed_id: Uuid,
+        item: &FeedItem,
+        fact_ids: &[Uuid],
+    ) -> Result<(), Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO rss_feed_items (feed_id, guid, title, link, published_at, fact_ids)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (feed_id, guid) DO NOTHING
+            "#,
+        )
+        .bind(feed_id)
+        .bind(&item.guid)
+        .bind(&item.title)
+        .bind(&item.link)
+        .bind(item.published)
+        .bind(fact_ids)
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to record item: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Store a feed's poll result. `validators` (ETag, Last-Modified) are only
+    /// replaced after a full poll, so a failed one is refetched. Unnamed feeds
+    /// take the feed's own title.
+    async fn record_poll(
+        &self,
+        feed_id: Uuid,
+        validators: Option<(Option<String>, Option<String>)>,
+        title: Option<&str>,
+        error: Option<&str>,
+    ) -> Result<(), Error> {
+        let replace = validators.is_some();
+        let (etag, last_modified) = validators.unwrap_or_default();
+        sqlx::query(
+            r#"
+            UPDATE rss_feeds
+            SET etag = CASE WHEN $4 THEN $2 ELSE etag END,
+                last_modified = CASE WHEN $4 THEN $3 ELSE last_modified END,
+                name = COALESCE(name, LEFT($5, 255)),
+                last_polled_at = CASE WHEN $6::text IS NULL THEN NOW() ELSE last_polled_at END,
+                last_error = $6,
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(feed_id)
+        .bind(etag)
+        .bind(last_modified)
+        .bind(replace)
+        .bind(title)
+        .bind(error)
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| format!("Failed to update feed: {}", e))?;
+
+        Ok(())
+    }
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    event: LambdaEvent<ScheduledEvent>,
+) -> Result<PollResponse, Error> {
+    info!(detail_type = %event.payload.detail_type, "Starting feed poll");
+
+    let user_filter = match &event.payload.user_id {
+        Some(user_id) => {
+            Some(Uuid::parse_str(user_id).map_err(|e| format!("Invalid user_id: {}", e))?)
+        }
+        None => None,
+    };
+
+    let feeds = state.get_feeds(user_filter).await?;
+    let tag_id = state.feed_tag_id().await?;
+    if tag_id.is_none() {
+        warn!(
+            "System tag {} not found; feed facts won't be tagged",
+            FEED_TAG_PATH
+        );
+    }
+
+    info!("Found {} enabled feeds", feeds.len());
+
+    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_FEEDS));
+    let mut tasks = JoinSet::new();
+
+    for feed in feeds {
+        let state = Arc::clone(&state);
+        let semaphore = Arc::clone(&semaphore);
+
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let timeout = std::time::Duration::from_secs(FEED_POLL_TIMEOUT_SECS);
+            let result = match tokio::time::timeout(timeout, state.poll_feed(&feed, tag_id)).await {
+                Ok(result) => result.map_err(|e| e.to_string()),
+                Err(_) => Err(format!("Poll took longer than {}s", FEED_POLL_TIMEOUT_SECS)),
+            };
+
+            if let Err(e) = &result {
+                if let Err(record_error) = state
+                    .record_poll(feed.id, None, None, Some(e.as_str()))
+                    .await
+                {
+                    error!(feed_id = %feed.id, "Failed to record poll error: {}", record_error);
+                }
+            }
+            (feed.id, result)
+        });
+    }
+
+    let mut response = PollResponse::default();
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((_, Ok(Some(poll)))) => {
+                response.feeds_polled += 1;
+                response.items_summarized += poll.items_summarized;
+                response.items_skipped += poll.items_skipped;
+                response.facts_created += poll.facts_created;
+            }
+            Ok((_, Ok(None))) => {
+                response.feeds_polled += 1;
+                response.feeds_unchanged += 1;
+            }
+            Ok((feed_id, Err(e))) => {
+                warn!(feed_id = %feed_id, "Feed poll failed: {}", e);
+                response.feeds_failed += 1;
+                response.failures.push(PollFailure {
+                    feed_id: feed_id.to_string(),
+                    error: e,
+                });
+            }
+            Err(e) => {
+                error!("Feed poll task panicked: {}", e);
+                response.feeds_failed += 1;
+            }
+        }
+    }
+
+    info!(
+        "Feed poll complete: {} feeds ({} unchanged, {} failed), {} items summarized ({} facts), {} skipped",
+        response.feeds_polled,
+        response.feeds_unchanged,
+        response.feeds_failed,
+        response.items_summarized,
+        response.facts_created,
+        response.items_skipped
+    );
+
+    Ok(response)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        async move { metrics::track_invocation("feed_poller", handler(state, event)).await }
+    }))
+    .await
+}
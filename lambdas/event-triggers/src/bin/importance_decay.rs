@@ -0,0 +1,76 @@
+//! Importance Decay Lambda - Lowers the importance of stale facts.
+//!
+//! Runs weekly via EventBridge. Current facts that haven't been recorded,
+//! reviewed or decayed in `shared::fact_review::DECAY_AFTER_DAYS` lose one
+//! importance level (see `shared::fact_review::decay_importance`), so facts
+//! the user never revisits gradually sink below the ones they keep
+//! confirming from the review queue.
+
+use chrono::Utc;
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use shared::fact_review::decay_importance;
+use shared::metrics;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+
+#[derive(Debug, Default, Deserialize)]
+struct DecayEvent {
+    #[serde(default)]
+    detail_type: String,
+}
+
+#[derive(Debug, Serialize)]
+struct DecayResponse {
+    facts_decayed: u64,
+}
+
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    event: LambdaEvent<DecayEvent>,
+) -> Result<DecayResponse, Error> {
+    info!(detail_type = %event.payload.detail_type, "Starting importance decay");
+
+    let facts_decayed = decay_importance(&state.db_pool, Utc::now())
+        .await
+        .map_err(|e| format!("Failed to decay importance: {}", e))?;
+
+    info!(facts_decayed, "Importance decay complete");
+
+    Ok(DecayResponse { facts_decayed })
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let state_clone = state.clone();
+
+    run(service_fn(move |event| {
+        let state = state_clone.clone();
+        async move { metrics::track_invocation("importance_decay", handler(state, event)).await }
+    }))
+    .await
+}
@@ -0,0 +1,360 @@
+//! Ingest Batch Lambda - Processes large batch ingests.
+//!
+//! Invoked asynchronously by the ingest API Lambda (`POST /ingest/batch`) with
+//! the ID of an `ingest_batches` row holding more items than are ingested
+//! synchronously. Items go through the same pipeline as `POST /ingest` (see
+//! `shared::ingest`), `SYNC_BATCH_ITEMS` at a time with bounded concurrency;
+//! each item's result is recorded on the row as soon as it finishes, so
+//! `GET /ingest/batch/{id}` can report progress and a job that stops partway
+//! only redoes the items that were in flight.
+//!
+//! A job running out of time hands the rest of the batch to a fresh
+//! invocation of this Lambda. One that dies instead (a crash, a timeout) stops
+//! renewing its `BATCH_LEASE_MINUTES` lease; EventBridge invokes this Lambda
+//! every few minutes without a batch ID to find such jobs, and ones that
+//! were never started, and resume them. After `MAX_BATCH_ATTEMPTS`
+//! resumptions a job is marked failed and its items cleared.
+//!
+//! Failures are recorded on the row rather than returned, so Lambda's
+//! automatic retries of async invocations don't ingest the items again.
+
+use aws_sdk_lambda::primitives::Blob;
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use shared::ingest::{
+    remaining_items, tally, BatchItemResult, IngestPipeline, IngestingItems, BATCH_LEASE_MINUTES,
+    MAX_BATCH_ATTEMPTS, SYNC_BATCH_ITEMS,
+};
+use shared::metrics;
+use shared::{AuthenticatedUser, AuthorizedUser, IngestRequest};
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// Time left in an invocation below which the rest of the batch is handed to
+/// a fresh one (a chunk of items takes well under this)
+const HAND_OFF_SECS: u64 = 120;
+
+/// Invocation payload. Scheduled EventBridge events carry no batch ID and
+/// sweep for jobs to resume.
+#[derive(Debug, Deserialize)]
+struct BatchEvent {
+    #[serde(default)]
+    batch_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum BatchOutput {
+    Batch(BatchResult),
+    Sweep { resumed: usize },
+}
+
+#[derive(Debug, Serialize)]
+struct BatchResult {
+    batch_id: Uuid,
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl BatchResult {
+    fn new(batch_id: Uuid, status: &str, error: Option<String>) -> BatchOutput {
+        BatchOutput::Batch(Self {
+            batch_id,
+            status: status.to_string(),
+            error,
+        })
+    }
+}
+
+/// Claimed batch, with its owner and the results recorded so far
+#[derive(Debug, sqlx::FromRow)]
+struct ClaimedBatch {
+    cognito_sub: String,
+    items: sqlx::types::Json<Vec<IngestRequest>>,
+    results: sqlx::types::Json<Vec<BatchItemResult>>,
+    attempts: i32,
+}
+
+struct AppState {
+    pipeline: Arc<IngestPipeline>,
+    db_pool: PgPool,
+    lambda_client: aws_sdk_lambda::Client,
+    /// This Lambda, for handing batches on
+    function_name: String,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        let function_name = std::env::var("AWS_LAMBDA_FUNCTION_NAME")
+            .unwrap_or_else(|_| "second-brain-ingest_batch".to_string());
+
+        Ok(Self {
+            pipeline: Arc::new(IngestPipeline::from_env(&config, Some(db_pool.clone()))),
+            db_pool,
+            lambda_client: aws_sdk_lambda::Client::new(&config),
+            function_name,
+        })
+    }
+}
+
+/// Claim a batch that's waiting to run, or whose job stopped renewing its
+/// lease (counting the attempt). `None` if another run holds it (a retried
+/// invocation), it's finished or it doesn't exist.
+async fn claim_batch(pool: &PgPool, batch_id: Uuid) -> Result<Option<ClaimedBatch>, Error> {
+    let batch = sqlx::query_as(
+        r#"
+        UPDATE ingest_batches b
+        SET status = 'running', started_at = COALESCE(b.started_at, NOW()),
+            heartbeat_at = NOW(),
+            attempts = b.attempts + CASE WHEN b.status = 'running' THEN 1 ELSE 0 END
+        FROM users u
+        WHERE b.id = $1 AND u.id = b.user_id
+          AND (b.status = 'pending'
+               OR (b.status = 'running'
+                   AND b.heartbeat_at < NOW() - make_interval(mins => $2)))
+        RETURNING u.cognito_sub, b.items, b.results, b.attempts
+        "#,
+    )
+    .bind(batch_id)
+    .bind(BATCH_LEASE_MINUTES as i32)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to claim batch: {}", e))?;
+
+    Ok(batch)
+}
+
+/// Start a run of this Lambda for a batch.
+async fn invoke(state: &AppState, batch_id: Uuid) -> Result<(), Error> {
+    let payload = serde_json::to_vec(&serde_json::json!({ "batch_id": batch_id }))?;
+    state
+        .lambda_client
+        .invoke()
+        .function_name(&state.function_name)
+        .invocation_type(aws_sdk_lambda::types::InvocationType::Event)
+        .payload(Blob::new(payload))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to invoke {}: {}", state.function_name, e))?;
+
+    Ok(())
+}
+
+/// Record a finished item's result, renewing the lease.
+async fn record_result(
+    pool: &PgPool,
+    batch_id: Uuid,
+    result: &BatchItemResult,
+) -> Result<(), Error> {
+    let (stored, failed) = tally(std::slice::from_ref(result));
+
+    sqlx::query(
+        r#"
+        UPDATE ingest_batches
+        SET results = results || jsonb_build_array($2::jsonb),
+            stored_items = stored_items + $3, failed_items = failed_items + $4,
+            heartbeat_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(batch_id)
+    .bind(sqlx::types::Json(result))
+    .bind(stored as i32)
+    .bind(failed as i32)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to record batch result: {}", e))?;
+
+    Ok(())
+}
+
+/// Mark a batch failed, dropping the items it didn't get to.
+async fn fail_batch(pool: &PgPool, batch_id: Uuid, message: &str) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE ingest_batches
+        SET status = 'failed', error_message = $2, items = '[]', completed_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(batch_id)
+    .bind(message)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to record batch failure: {}", e))?;
+
+    Ok(())
+}
+
+/// Ingest the items of a claimed batch that have no result yet, chunk by
+/// chunk. Returns whether the batch finished, or was handed on to a fresh
+/// invocation when this one ran short of time.
+async fn run_batch(
+    state: &AppState,
+    batch_id: Uuid,
+    batch: ClaimedBatch,
+    deadline: SystemTime,
+) -> Result<bool, Error> {
+    // Family membership as it is now, not when the batch was requested
+    let user = AuthorizedUser::resolve(
+        AuthenticatedUser {
+            user_id: batch.cognito_sub,
+            email: None,
+            family_ids: Vec::new(),
+        },
+        &state.db_pool,
+    )
+    .await
+    .map_err(|e| format!("Failed to lookup user: {}", e))?;
+    let user = AuthenticatedUser::from(user);
+
+    let remaining = remaining_items(&batch.items.0, &batch.results.0);
+    for chunk in remaining.chunks(SYNC_BATCH_ITEMS) {
+        let time_left = deadline
+            .duration_since(SystemTime::now())
+            .unwrap_or_default();
+        if time_left < Duration::from_secs(HAND_OFF_SECS) {
+            sqlx::query(
+                "UPDATE ingest_batches SET status = 'pending', heartbeat_at = NOW() WHERE id = $1",
+            )
+            .bind(batch_id)
+            .execute(&state.db_pool)
+            .await
+            .map_err(|e| format!("Failed to release batch: {}", e))?;
+
+            // If this fails the sweep starts the batch once its lease is up
+            invoke(state, batch_id).await?;
+            info!(batch_id = %batch_id, "Handed ingest batch to a fresh invocation");
+            return Ok(false);
+        }
+
+        let mut ingesting = IngestingItems::start(
+            Arc::clone(&state.pipeline),
+            chunk.to_vec(),
+            &user.user_id,
+            &user.family_ids,
+            "api",
+        );
+        while let Some(result) = ingesting.next().await {
+            record_result(&state.db_pool, batch_id, &result).await?;
+        }
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE ingest_batches
+        SET status = 'completed', items = '[]', error_message = NULL, completed_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(batch_id)
+    .execute(&state.db_pool)
+    .await
+    .map_err(|e| format!("Failed to update batch: {}", e))?;
+
+    info!(batch_id = %batch_id, items = batch.items.0.len(), "Ingest batch completed");
+
+    Ok(true)
+}
+
+/// Start runs for batches whose job died partway (its lease is up) or that
+/// were never started. Returns how many were found.
+async fn sweep(state: &AppState) -> Result<usize, Error> {
+    let stale: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        SELECT id FROM ingest_batches
+        WHERE status IN ('pending', 'running')
+          AND COALESCE(heartbeat_at, created_at) < NOW() - make_interval(mins => $1)
+        ORDER BY created_at
+        "#,
+    )
+    .bind(BATCH_LEASE_MINUTES as i32)
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(|e| format!("Failed to list stalled batches: {}", e))?;
+
+    for batch_id in &stale {
+        match invoke(state, *batch_id).await {
+            Ok(()) => info!(batch_id = %batch_id, "Resuming stalled ingest batch"),
+            Err(e) => error!(batch_id = %batch_id, "Failed to resume ingest batch: {}", e),
+        }
+    }
+
+    Ok(stale.len())
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    event: LambdaEvent<BatchEvent>,
+) -> Result<BatchOutput, Error> {
+    let Some(batch_id) = event.payload.batch_id else {
+        let resumed = sweep(&state).await?;
+        info!(resumed, "Ingest batch sweep finished");
+        return Ok(BatchOutput::Sweep { resumed });
+    };
+    let deadline = UNIX_EPOCH + Duration::from_millis(event.context.deadline);
+
+    let Some(batch) = claim_batch(&state.db_pool, batch_id).await? else {
+        info!(batch_id = %batch_id, "Batch already claimed; skipping");
+        return Ok(BatchResult::new(batch_id, "skipped", None));
+    };
+
+    if batch.attempts > MAX_BATCH_ATTEMPTS {
+        let message = format!("Stopped responding {} times", batch.attempts);
+        error!(batch_id = %batch_id, "Giving up on ingest batch: {}", message);
+        fail_batch(&state.db_pool, batch_id, &message).await?;
+        return Ok(BatchResult::new(batch_id, "failed", Some(message)));
+    }
+
+    info!(
+        batch_id = %batch_id,
+        items = batch.items.0.len(),
+        done = batch.results.0.len(),
+        attempts = batch.attempts,
+        "Starting ingest batch"
+    );
+
+    match run_batch(&state, batch_id, batch, deadline).await {
+        Ok(true) => Ok(BatchResult::new(batch_id, "completed", None)),
+        Ok(false) => Ok(BatchResult::new(batch_id, "handed_off", None)),
+        Err(e) => {
+            // Left running: once the lease is up the sweep resumes it
+            warn!(batch_id = %batch_id, "Ingest batch stopped: {}", e);
+            sqlx::query("UPDATE ingest_batches SET error_message = $2 WHERE id = $1")
+                .bind(batch_id)
+                .bind(e.to_string())
+                .execute(&state.db_pool)
+                .await
+                .map_err(|e| format!("Failed to record batch error: {}", e))?;
+
+            Ok(BatchResult::new(batch_id, "stopped", Some(e.to_string())))
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        async move { metrics::track_invocation("ingest_batch", handler(state, event)).await }
+    }))
+    .await
+}
@@ -3,16 +3,40 @@
 //! This Lambda is triggered by SNS and:
 //! 1. Receives notification ID from SNS message
 //! 2. Fetches notification details from database
-//! 3. Sends via appropriate channel (push, email, discord)
-//! 4. Updates notification status in database
+//! 3. Sends via appropriate channel (push, email, discord, alexa)
+//! 4. Updates notification status and delivery receipt in database
+//!
+//! Low-priority notifications are left pending for `digest_builder` unless the
+//! user has turned digests off.
+//!
+//! Push notifications go to every active device in `push_devices`, through FCM
+//! or APNs depending on `device_platform`. Tokens the provider reports as
+//! invalid are deactivated.
+//!
+//! Alexa notifications go to every linked Alexa account that granted the
+//! notifications permission (`alexa_links`), as proactive events sent with the
+//! skill's Login with Amazon client credentials (`ALEXA_SECRET_ARN`).
 
 use aws_sdk_ses::types::{Body, Content, Destination, Message};
 use chrono::Utc;
 use lambda_runtime::{run, service_fn, Error, LambdaEvent};
 use serde::{Deserialize, Serialize};
-use sqlx::postgres::PgPoolOptions;
+use serde_json::{json, Value};
+use shared::alexa::{
+    events_url, message_alert, token_request, LWA_TOKEN_LIFETIME_SECS, LWA_TOKEN_URL,
+};
+use shared::digest::{is_digest_priority, DigestMode};
+use shared::events::NotificationSent;
+use shared::metrics;
+use shared::push::{
+    apns_payload, apns_provider_token, apns_token_invalid, fcm_assertion, fcm_request,
+    fcm_token_invalid, PushMessage, PushPlatform, APNS_PRODUCTION_HOST, APNS_SANDBOX_HOST,
+    APNS_TOKEN_REUSE_SECS, FCM_TOKEN_LIFETIME_SECS,
+};
+use shared::EventPublisher;
+use sqlx::types::Json;
 use sqlx::PgPool;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use tracing::{error, info, warn};
 use tracing_subscriber::EnvFilter;
 use uuid::Uuid;
@@ -49,6 +73,7 @@ struct NotificationMessage {
 #[derive(Debug, Serialize)]
 struct SenderResponse {
     notifications_sent: u32,
+    held_for_digest: u32,
     errors: u32,
 }
 
@@ -62,6 +87,7 @@ struct NotificationRow {
     body: String,
     channel: String,
     reminder_id: Option<Uuid>,
+    priority: i16,
 }
 
 /// User contact info
@@ -69,14 +95,89 @@ struct NotificationRow {
 struct UserContact {
     email: Option<String>,
     discord_user_id: Option<String>,
-    push_token: Option<String>,
+}
+
+/// Active push device from database
+#[derive(Debug, sqlx::FromRow)]
+struct PushDeviceRow {
+    id: Uuid,
+    device_platform: String,
+    push_token: String,
+}
+
+/// Push provider credentials (`PUSH_SECRET_ARN`); either may be absent
+#[derive(Debug, Deserialize)]
+struct PushCredentials {
+    fcm: Option<FcmCredentials>,
+    apns: Option<ApnsCredentials>,
+}
+
+/// Fields of the Firebase service account key JSON
+#[derive(Debug, Deserialize)]
+struct FcmCredentials {
+    project_id: String,
+    client_email: String,
+    private_key: String,
+    #[serde(default = "default_token_uri")]
+    token_uri: String,
+}
+
+fn default_token_uri() -> String {
+    "https://oauth2.googleapis.com/token".to_string()
+}
+
+/// APNs token-based authentication (`.p8` key)
+#[derive(Debug, Deserialize)]
+struct ApnsCredentials {
+    key_id: String,
+    team_id: String,
+    private_key: String,
+    bundle_id: String,
+    #[serde(default)]
+    sandbox: bool,
+}
+
+/// Alexa skill credentials (`ALEXA_SECRET_ARN`), from the skill's
+/// Permissions page in the Alexa developer console
+#[derive(Debug, Deserialize)]
+struct AlexaCredentials {
+    client_id: String,
+    client_secret: String,
+    /// Send to the development stage (skills not yet certified)
+    #[serde(default)]
+    development: bool,
+}
+
+/// Linked Alexa account from database
+#[derive(Debug, sqlx::FromRow)]
+struct AlexaLinkRow {
+    alexa_user_id: String,
+    api_endpoint: String,
+}
+
+/// Result of sending to one device
+enum PushOutcome {
+    Sent(String),
+    /// The provider will never accept this token again
+    InvalidToken(String),
+    Failed(String),
 }
 
 struct AppState {
     db_pool: PgPool,
     ses_client: aws_sdk_ses::Client,
+    http_client: reqwest::Client,
     discord_webhook_url: Option<String>,
     from_email: String,
+    event_publisher: Option<EventPublisher>,
+    push_credentials: Option<PushCredentials>,
+    /// Cached FCM access token and its expiry (Unix seconds)
+    fcm_token: Mutex<Option<(String, i64)>>,
+    /// Cached APNs provider token and when it was issued (Unix seconds)
+    apns_token: Mutex<Option<(String, i64)>>,
+    alexa_credentials: Option<AlexaCredentials>,
+    /// Cached Login with Amazon access token and its expiry (Unix seconds)
+    alexa_token: Mutex<Option<(String, i64)>>,
 }
 
 impl AppState {
@@ -85,32 +186,7 @@ impl AppState {
         let secrets_client = aws_sdk_secretsmanager::Client::new(&config);
         let ses_client = aws_sdk_ses::Client::new(&config);
 
-        let db_secret_arn =
-            std::env::var("DB_SECRET_ARN").map_err(|_| "DB_SECRET_ARN not set")?;
-
-        let db_secret = secrets_client
-            .get_secret_value()
-            .secret_id(&db_secret_arn)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to get DB secret: {}", e))?;
-
-        let db_creds: serde_json::Value =
-            serde_json::from_str(db_secret.secret_string().unwrap_or("{}"))?;
-
-        let db_host = std::env::var("DB_HOST").map_err(|_| "DB_HOST not set")?;
-        let db_name = std::env::var("DB_NAME").unwrap_or_else(|_| "second_brain".to_string());
-        let db_user = db_creds["username"].as_str().unwrap_or("sbadmin");
-        let db_pass = db_creds["password"].as_str().unwrap_or("");
-
-        let database_url = format!(
-            "postgres://{}:{}@{}:5432/{}",
-            db_user, db_pass, db_host, db_name
-        );
-
-        let db_pool = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(&database_url)
+        let db_pool = shared::db::connect(&config)
             .await
             .map_err(|e| format!("Failed to connect to database: {}", e))?;
 
@@ -118,11 +194,40 @@ impl AppState {
         let from_email = std::env::var("FROM_EMAIL")
             .unwrap_or_else(|_| "noreply@secondbrain.app".to_string());
 
+        let event_publisher = EventPublisher::from_env(&config);
+
+        let push_credentials = match std::env::var("PUSH_SECRET_ARN") {
+            Ok(arn) => {
+                let secret = shared::secrets::get_secret(&secrets_client, &arn)
+                    .await
+                    .map_err(|e| format!("Failed to get push secret: {}", e))?;
+                Some(serde_json::from_str(&secret)?)
+            }
+            Err(_) => None,
+        };
+
+        let alexa_credentials = match std::env::var("ALEXA_SECRET_ARN") {
+            Ok(arn) => {
+                let secret = shared::secrets::get_secret(&secrets_client, &arn)
+                    .await
+                    .map_err(|e| format!("Failed to get Alexa secret: {}", e))?;
+                Some(serde_json::from_str(&secret)?)
+            }
+            Err(_) => None,
+        };
+
         Ok(Self {
             db_pool,
             ses_client,
+            http_client: reqwest::Client::new(),
             discord_webhook_url,
             from_email,
+            event_publisher,
+            push_credentials,
+            fcm_token: Mutex::new(None),
+            apns_token: Mutex::new(None),
+            alexa_credentials,
+            alexa_token: Mutex::new(None),
         })
     }
 }
@@ -132,9 +237,10 @@ async fn get_notification(pool: &PgPool, notification_id: Uuid) -> Result<Option
         r#"
         SELECT
             id, user_id, notification_type::text,
-            title, body, channel::text, reminder_id
+            title, body, channel::text, reminder_id, priority
         FROM notifications
         WHERE id = $1 AND status = 'pending'
+          AND (deferred_until IS NULL OR deferred_until <= NOW())
         "#,
     )
     .bind(notification_id)
@@ -148,13 +254,9 @@ async fn get_notification(pool: &PgPool, notification_id: Uuid) -> Result<Option
 async fn get_user_contact(pool: &PgPool, user_id: Uuid) -> Result<Option<UserContact>, Error> {
     let contact: Option<UserContact> = sqlx::query_as(
         r#"
-        SELECT
-            u.email,
-            up.discord_user_id,
-            up.push_token
-        FROM users u
-        LEFT JOIN user_profiles up ON up.user_id = u.id
-        WHERE u.id = $1
+        SELECT email, discord_id AS discord_user_id
+        FROM users
+        WHERE id = $1
         "#,
     )
     .bind(user_id)
@@ -165,11 +267,24 @@ async fn get_user_contact(pool: &PgPool, user_id: Uuid) -> Result<Option<UserCon
     Ok(contact)
 }
 
+async fn get_digest_mode(pool: &PgPool, user_id: Uuid) -> Result<DigestMode, Error> {
+    let mode: Option<String> = sqlx::query_scalar(
+        "SELECT digest_mode FROM user_notification_preferences WHERE user_id = $1",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch digest mode: {}", e))?;
+
+    Ok(mode.map_or(DigestMode::Morning, |m| DigestMode::parse(&m)))
+}
+
 async fn update_notification_status(
     pool: &PgPool,
     notification_id: Uuid,
     status: &str,
-    delivery_info: Option<&str>,
+    delivery_info: Option<&Value>,
+    error_message: Option<&str>,
 ) -> Result<(), Error> {
     let sent_at = if status == "sent" {
         Some(Utc::now())
@@ -183,6 +298,7 @@ async fn update_notification_status(
         SET status = $2::notification_status,
             sent_at = COALESCE($3, sent_at),
             delivery_info = COALESCE($4, delivery_info),
+            error_message = $5,
             updated_at = NOW()
         WHERE id = $1
         "#,
@@ -190,7 +306,8 @@ async fn update_notification_status(
     .bind(notification_id)
     .bind(status)
     .bind(sent_at)
-    .bind(delivery_info)
+    .bind(delivery_info.map(Json))
+    .bind(error_message)
     .execute(pool)
     .await
     .map_err(|e| format!("Failed to update notification status: {}", e))?;
@@ -203,7 +320,7 @@ async fn send_email(
     to_email: &str,
     title: &str,
     body: &str,
-) -> Result<String, Error> {
+) -> Result<Value, Error> {
     let subject = Content::builder()
         .data(title)
         .charset("UTF-8")
@@ -265,54 +382,463 @@ async fn send_email(
         .await
         .map_err(|e| format!("Failed to send email: {}", e))?;
 
-    Ok(result.message_id().to_string())
+    Ok(json!({ "message_id": result.message_id() }))
 }
 
+/// Send a Discord message, with a "Snooze 1h" button for reminders.
+///
+/// Buttons are handled by the discord-webhook Lambda and only render when the
+/// webhook is owned by the bot application.
 async fn send_discord(
     state: &AppState,
     discord_user_id: &str,
     title: &str,
     body: &str,
-) -> Result<String, Error> {
+    reminder_id: Option<Uuid>,
+) -> Result<Value, Error> {
     let webhook_url = state
         .discord_webhook_url
         .as_ref()
         .ok_or("Discord webhook URL not configured")?;
 
-    let payload = serde_json::json!({
+    let mut payload = serde_json::json!({
         "content": format!("<@{}> **{}**\n{}", discord_user_id, title, body),
         "allowed_mentions": {
             "users": [discord_user_id]
         }
     });
 
+    if let Some(reminder_id) = reminder_id {
+        payload["components"] = serde_json::json!([{
+            "type": 1,
+            "components": [{
+                "type": 2,
+                "style": 2,
+                "label": "Snooze 1h",
+                "custom_id": format!("snooze:{}:60", reminder_id),
+            }],
+        }]);
+    }
+
     let client = reqwest::Client::new();
-    let response = client
-        .post(webhook_url)
-        .json(&payload)
+    let mut request = client.post(webhook_url).json(&payload);
+    if reminder_id.is_some() {
+        request = request.query(&[("with_components", "true")]);
+    }
+
+    let response = request
         .send()
         .await
         .map_err(|e| format!("Failed to send Discord message: {}", e))?;
 
     if response.status().is_success() {
-        Ok("discord_sent".to_string())
+        Ok(json!({ "discord_user_id": discord_user_id }))
     } else {
         Err(format!("Discord webhook failed: {}", response.status()).into())
     }
 }
 
-async fn send_push(_push_token: &str, _title: &str, _body: &str) -> Result<String, Error> {
-    // Push notifications would typically use Firebase Cloud Messaging or similar
-    // For now, we log and return success
-    warn!("Push notifications not implemented - would send to token");
-    Ok("push_pending".to_string())
+/// Cached token if it's still usable, per `valid`.
+fn cached_token(cache: &Mutex<Option<(String, i64)>>, valid: impl Fn(i64) -> bool) -> Option<String> {
+    cache
+        .lock()
+        .ok()
+        .and_then(|cached| cached.as_ref().filter(|(_, at)| valid(*at)).map(|(t, _)| t.clone()))
+}
+
+fn store_token(cache: &Mutex<Option<(String, i64)>>, token: Option<(String, i64)>) {
+    if let Ok(mut cached) = cache.lock() {
+        *cached = token;
+    }
+}
+
+/// OAuth access token for FCM, exchanged from a signed service account assertion.
+async fn fcm_access_token(state: &AppState, creds: &FcmCredentials) -> Result<String, Error> {
+    let now = Utc::now().timestamp();
+    if let Some(token) = cached_token(&state.fcm_token, |expires_at| expires_at - 60 > now) {
+        return Ok(token);
+    }
+
+    let assertion = fcm_assertion(&creds.client_email, &creds.private_key, &creds.token_uri, now)?;
+    let response: Value = state
+        .http_client
+        .post(&creds.token_uri)
+        .form(&[
+            ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+            ("assertion", assertion.as_str()),
+        ])
+        .send()
+        .await
+        .map_err(|e| format!("Failed to request FCM token: {}", e))?
+        .error_for_status()
+        .map_err(|e| format!("FCM token request failed: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse FCM token: {}", e))?;
+
+    let token = response["access_token"]
+        .as_str()
+        .ok_or("FCM token response has no access_token")?
+        .to_string();
+    let expires_in = response["expires_in"].as_i64().unwrap_or(FCM_TOKEN_LIFETIME_SECS);
+
+    store_token(&state.fcm_token, Some((token.clone(), now + expires_in)));
+    Ok(token)
+}
+
+async fn send_fcm(
+    state: &AppState,
+    creds: &FcmCredentials,
+    token: &str,
+    message: &PushMessage<'_>,
+) -> Result<PushOutcome, Error> {
+    let access_token = fcm_access_token(state, creds).await?;
+    let url = format!(
+        "https://fcm.googleapis.com/v1/projects/{}/messages:send",
+        creds.project_id
+    );
+
+    let response = state
+        .http_client
+        .post(url)
+        .bearer_auth(access_token)
+        .json(&fcm_request(token, message))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send FCM message: {}", e))?;
+
+    let status = response.status().as_u16();
+    let body: Value = response.json().await.unwrap_or(Value::Null);
+
+    if (200..300).contains(&status) {
+        return Ok(PushOutcome::Sent(body["name"].as_str().unwrap_or_default().to_string()));
+    }
+
+    let error = format!(
+        "FCM {}: {}",
+        status,
+        body["error"]["message"].as_str().unwrap_or("unknown error")
+    );
+    if status == 401 {
+        // Access token revoked or expired early; fetch a new one next time
+        store_token(&state.fcm_token, None);
+    }
+
+    Ok(if fcm_token_invalid(status, &body) {
+        PushOutcome::InvalidToken(error)
+    } else {
+        PushOutcome::Failed(error)
+    })
+}
+
+async fn send_apns(
+    state: &AppState,
+    creds: &ApnsCredentials,
+    token: &str,
+    message: &PushMessage<'_>,
+) -> Result<PushOutcome, Error> {
+    let now = Utc::now().timestamp();
+    let provider_token = match cached_token(&state.apns_token, |issued_at| now - issued_at < APNS_TOKEN_REUSE_SECS) {
+        Some(provider_token) => provider_token,
+        None => {
+            let provider_token = apns_provider_token(&creds.key_id, &creds.team_id, &creds.private_key, now)?;
+            store_token(&state.apns_token, Some((provider_token.clone(), now)));
+            provider_token
+        }
+    };
+
+    let host = if creds.sandbox { APNS_SANDBOX_HOST } else { APNS_PRODUCTION_HOST };
+    let response = state
+        .http_client
+        .post(format!("{}/3/device/{}", host, token))
+        .header("authorization", format!("bearer {}", provider_token))
+        .header("apns-topic", &creds.bundle_id)
+        .header("apns-push-type", "alert")
+        .header("apns-priority", "10")
+        .json(&apns_payload(message))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send APNs message: {}", e))?;
+
+    let status = response.status().as_u16();
+    let apns_id = response
+        .headers()
+        .get("apns-id")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+
+    if (200..300).contains(&status) {
+        return Ok(PushOutcome::Sent(apns_id));
+    }
+
+    let body: Value = response.json().await.unwrap_or(Value::Null);
+    let reason = body["reason"].as_str();
+    if reason == Some("ExpiredProviderToken") {
+        store_token(&state.apns_token, None);
+    }
+
+    let error = format!("APNs {}: {}", status, reason.unwrap_or("unknown error"));
+    Ok(if apns_token_invalid(status, reason) {
+        PushOutcome::InvalidToken(error)
+    } else {
+        PushOutcome::Failed(error)
+    })
+}
+
+async fn get_push_devices(pool: &PgPool, user_id: Uuid) -> Result<Vec<PushDeviceRow>, Error> {
+    let devices = sqlx::query_as(
+        r#"
+        SELECT id, device_platform, push_token
+        FROM push_devices
+        WHERE user_id = $1 AND is_active
+        ORDER BY created_at
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch push devices: {}", e))?;
+
+    Ok(devices)
+}
+
+/// Record the outcome on the device: deactivate invalid tokens, note deliveries.
+async fn record_push_outcome(pool: &PgPool, device_id: Uuid, outcome: &PushOutcome) -> Result<(), Error> {
+    let query = match outcome {
+        PushOutcome::Sent(_) => sqlx::query(
+            "UPDATE push_devices SET last_delivered_at = NOW(), last_error = NULL, updated_at = NOW() WHERE id = $1",
+        )
+        .bind(device_id),
+        PushOutcome::InvalidToken(error) => sqlx::query(
+            r#"
+            UPDATE push_devices
+            SET is_active = false, invalidated_at = NOW(), last_error = $2, updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(device_id)
+        .bind(error),
+        PushOutcome::Failed(error) => {
+            sqlx::query("UPDATE push_devices SET last_error = $2, updated_at = NOW() WHERE id = $1")
+                .bind(device_id)
+                .bind(error)
+        }
+    };
+
+    query
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to update push device: {}", e))?;
+
+    Ok(())
+}
+
+/// Send to every active device of the user, returning the delivery receipt.
+///
+/// Fails (after recording the receipt) unless at least one device accepted it.
+async fn send_push(state: &AppState, notification: &NotificationRow) -> Result<Value, Error> {
+    let devices = get_push_devices(&state.db_pool, notification.user_id).await?;
+    if devices.is_empty() {
+        return Err("User has no registered push devices".into());
+    }
+
+    let mut data = vec![
+        ("notification_id", notification.id.to_string()),
+        ("type", notification.notification_type.clone()),
+    ];
+    if let Some(reminder_id) = notification.reminder_id {
+        data.push(("reminder_id", reminder_id.to_string()));
+    }
+    let message = PushMessage {
+        title: &notification.title,
+        body: &notification.body,
+        data,
+    };
+
+    let credentials = state.push_credentials.as_ref();
+    let mut deliveries = Vec::new();
+    let mut delivered = 0;
+
+    for device in &devices {
+        let outcome = match PushPlatform::parse(&device.device_platform) {
+            Some(PushPlatform::Fcm) => match credentials.and_then(|c| c.fcm.as_ref()) {
+                Some(creds) => send_fcm(state, creds, &device.push_token, &message).await,
+                None => Ok(PushOutcome::Failed("FCM is not configured".to_string())),
+            },
+            Some(PushPlatform::Apns) => match credentials.and_then(|c| c.apns.as_ref()) {
+                Some(creds) => send_apns(state, creds, &device.push_token, &message).await,
+                None => Ok(PushOutcome::Failed("APNs is not configured".to_string())),
+            },
+            None => Ok(PushOutcome::InvalidToken(format!(
+                "Unknown platform: {}",
+                device.device_platform
+            ))),
+        }
+        .unwrap_or_else(|e| PushOutcome::Failed(e.to_string()));
+
+        if let Err(e) = record_push_outcome(&state.db_pool, device.id, &outcome).await {
+            warn!(device_id = %device.id, error = %e, "Failed to record push outcome");
+        }
+
+        let mut receipt = match &outcome {
+            PushOutcome::Sent(message_id) => {
+                delivered += 1;
+                json!({ "status": "sent", "message_id": message_id })
+            }
+            PushOutcome::InvalidToken(error) => {
+                info!(device_id = %device.id, "Deactivated invalid push token");
+                json!({ "status": "invalid_token", "error": error })
+            }
+            PushOutcome::Failed(error) => json!({ "status": "failed", "error": error }),
+        };
+        receipt["device_id"] = json!(device.id);
+        receipt["platform"] = json!(device.device_platform);
+        deliveries.push(receipt);
+    }
+
+    let delivery_info = json!({ "deliveries": deliveries });
+    if delivered == 0 {
+        // Record the per-device receipts along with the failure
+        let error = "No push device accepted the notification";
+        update_notification_status(
+            &state.db_pool,
+            notification.id,
+            "failed",
+            Some(&delivery_info),
+            Some(error),
+        )
+        .await?;
+        return Err(error.into());
+    }
+
+    Ok(delivery_info)
+}
+
+/// Login with Amazon access token for the Proactive Events API (client credentials).
+async fn alexa_access_token(state: &AppState, creds: &AlexaCredentials) -> Result<String, Error> {
+    let now = Utc::now().timestamp();
+    if let Some(token) = cached_token(&state.alexa_token, |expires_at| expires_at - 60 > now) {
+        return Ok(token);
+    }
+
+    let response: Value = state
+        .http_client
+        .post(LWA_TOKEN_URL)
+        .form(&token_request(&creds.client_id, &creds.client_secret))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to request Alexa token: {}", e))?
+        .error_for_status()
+        .map_err(|e| format!("Alexa token request failed: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Alexa token: {}", e))?;
+
+    let token = response["access_token"]
+        .as_str()
+        .ok_or("Alexa token response has no access_token")?
+        .to_string();
+    let expires_in = response["expires_in"].as_i64().unwrap_or(LWA_TOKEN_LIFETIME_SECS);
+
+    store_token(&state.alexa_token, Some((token.clone(), now + expires_in)));
+    Ok(token)
+}
+
+async fn get_alexa_links(pool: &PgPool, user_id: Uuid) -> Result<Vec<AlexaLinkRow>, Error> {
+    let links = sqlx::query_as(
+        r#"
+        SELECT alexa_user_id, api_endpoint
+        FROM alexa_links
+        WHERE user_id = $1 AND notifications_enabled
+        ORDER BY last_seen_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch Alexa links: {}", e))?;
+
+    Ok(links)
+}
+
+/// Announce the notification on every linked Alexa account.
+///
+/// Alexa only says there is a new message from Second Brain; the user asks the
+/// skill to hear it. Fails unless at least one account accepted the event.
+async fn send_alexa(state: &AppState, notification: &NotificationRow) -> Result<Value, Error> {
+    let creds = state
+        .alexa_credentials
+        .as_ref()
+        .ok_or("Alexa is not configured")?;
+    let links = get_alexa_links(&state.db_pool, notification.user_id).await?;
+    if links.is_empty() {
+        return Err("User has no Alexa account with notifications enabled".into());
+    }
+
+    let access_token = alexa_access_token(state, creds).await?;
+    let reference_id = notification.id.to_string();
+    let mut deliveries = Vec::new();
+    let mut delivered = 0;
+
+    for link in &links {
+        let event = message_alert(
+            &link.alexa_user_id,
+            &reference_id,
+            "Second Brain",
+            notification.priority,
+            Utc::now(),
+        );
+        let result = state
+            .http_client
+            .post(events_url(&link.api_endpoint, creds.development))
+            .bearer_auth(&access_token)
+            .json(&event)
+            .send()
+            .await;
+
+        let mut receipt = match result {
+            Ok(response) if response.status().is_success() => {
+                delivered += 1;
+                json!({ "status": "sent" })
+            }
+            Ok(response) => {
+                let status = response.status();
+                if status.as_u16() == 401 || status.as_u16() == 403 {
+                    // Token revoked or expired early; fetch a new one next time
+                    store_token(&state.alexa_token, None);
+                }
+                let body = response.text().await.unwrap_or_default();
+                json!({ "status": "failed", "error": format!("Alexa {}: {}", status, body) })
+            }
+            Err(e) => json!({ "status": "failed", "error": e.to_string() }),
+        };
+        receipt["alexa_user_id"] = json!(link.alexa_user_id);
+        deliveries.push(receipt);
+    }
+
+    let delivery_info = json!({ "deliveries": deliveries });
+    if delivered == 0 {
+        let error = "No Alexa account accepted the notification";
+        update_notification_status(
+            &state.db_pool,
+            notification.id,
+            "failed",
+            Some(&delivery_info),
+            Some(error),
+        )
+        .await?;
+        return Err(error.into());
+    }
+
+    Ok(delivery_info)
 }
 
 async fn send_notification(
     state: &AppState,
     notification: &NotificationRow,
     contact: &UserContact,
-) -> Result<String, Error> {
+) -> Result<Value, Error> {
     match notification.channel.as_str() {
         "email" => {
             let email = contact
@@ -326,15 +852,17 @@ async fn send_notification(
                 .discord_user_id
                 .as_ref()
                 .ok_or("User has no Discord ID")?;
-            send_discord(state, discord_id, &notification.title, &notification.body).await
-        }
-        "push" => {
-            let push_token = contact
-                .push_token
-                .as_ref()
-                .ok_or("User has no push token")?;
-            send_push(push_token, &notification.title, &notification.body).await
+            send_discord(
+                state,
+                discord_id,
+                &notification.title,
+                &notification.body,
+                notification.reminder_id,
+            )
+            .await
         }
+        "push" => send_push(state, notification).await,
+        "alexa" => send_alexa(state, notification).await,
         _ => Err(format!("Unknown channel: {}", notification.channel).into()),
     }
 }
@@ -346,6 +874,7 @@ async fn handler(
     info!("Processing notification sender event");
 
     let mut notifications_sent = 0u32;
+    let mut held_for_digest = 0u32;
     let mut errors = 0u32;
 
     for record in &event.payload.records {
@@ -373,7 +902,7 @@ async fn handler(
         let notification = match get_notification(&state.db_pool, notification_id).await {
             Ok(Some(n)) => n,
             Ok(None) => {
-                warn!(notification_id = %notification_id, "Notification not found or already sent");
+                warn!(notification_id = %notification_id, "Notification not found, already sent, or deferred");
                 continue;
             }
             Err(e) => {
@@ -383,6 +912,23 @@ async fn handler(
             }
         };
 
+        // Low-priority notifications wait for the user's digest
+        if is_digest_priority(notification.priority) {
+            match get_digest_mode(&state.db_pool, notification.user_id).await {
+                Ok(DigestMode::Off) => {}
+                Ok(_) => {
+                    info!(notification_id = %notification_id, "Held for digest");
+                    held_for_digest += 1;
+                    continue;
+                }
+                Err(e) => {
+                    error!(notification_id = %notification_id, error = %e, "Failed to fetch digest mode");
+                    errors += 1;
+                    continue;
+                }
+            }
+        }
+
         // Fetch user contact info
         let contact = match get_user_contact(&state.db_pool, notification.user_id).await {
             Ok(Some(c)) => c,
@@ -392,6 +938,7 @@ async fn handler(
                     &state.db_pool,
                     notification_id,
                     "failed",
+                    None,
                     Some("User not found"),
                 )
                 .await
@@ -407,8 +954,10 @@ async fn handler(
         };
 
         // Send notification
-        match send_notification(&state, &notification, &contact).await {
-            Ok(delivery_id) => {
+        let result = send_notification(&state, &notification, &contact).await;
+        metrics::record_delivery(&notification.channel, result.is_ok());
+        match result {
+            Ok(delivery_info) => {
                 info!(
                     notification_id = %notification_id,
                     channel = %notification.channel,
@@ -418,11 +967,24 @@ async fn handler(
                     &state.db_pool,
                     notification_id,
                     "sent",
-                    Some(&delivery_id),
+                    Some(&delivery_info),
+                    None,
                 )
                 .await
                 .ok();
                 notifications_sent += 1;
+
+                if let Some(publisher) = &state.event_publisher {
+                    let event = NotificationSent {
+                        notification_id,
+                        user_id: notification.user_id,
+                        notification_type: notification.notification_type.clone(),
+                        channel: notification.channel.clone(),
+                    };
+                    if let Err(e) = publisher.publish(&event).await {
+                        warn!(notification_id = %notification_id, error = %e, "Failed to publish NotificationSent");
+                    }
+                }
             }
             Err(e) => {
                 error!(
@@ -435,6 +997,7 @@ async fn handler(
                     &state.db_pool,
                     notification_id,
                     "failed",
+                    None,
                     Some(&e.to_string()),
                 )
                 .await
@@ -446,11 +1009,13 @@ async fn handler(
 
     let response = SenderResponse {
         notifications_sent,
+        held_for_digest,
         errors,
     };
 
     info!(
         sent = response.notifications_sent,
+        held_for_digest = response.held_for_digest,
         errors = response.errors,
         "Notification sender complete"
     );
@@ -470,7 +1035,7 @@ async fn main() -> Result<(), Error> {
 
     run(service_fn(move |event| {
         let state = state_clone.clone();
-        async move { handler(state, event).await }
+        async move { metrics::track_invocation("notification_sender", handler(state, event)).await }
     }))
     .await
 }
@@ -0,0 +1,409 @@
+//! Occasion Scanner Lambda - Creates reminders for birthdays and anniversaries.
+//!
+//! This Lambda runs daily via EventBridge and, for each user with occasion
+//! reminders enabled:
+//! 1. Reads birthday/anniversary attributes on their entities and their
+//!    families' entities (see `shared::occasions`)
+//! 2. Creates a yearly recurring reminder `occasion_reminder_days` before each
+//!    new one, tracked in `occasion_reminders`
+//! 3. Updates reminders whose date, name or lead time changed
+//! 4. Removes reminders for attributes that were superseded or deleted
+//!
+//! Reminders the user deleted stay deleted.
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use shared::metrics;
+use shared::occasions::{
+    fetch_occasion_attributes, reminder_description, reminder_time, reminder_title,
+    OccasionAttribute, OccasionDate, OccasionKind, MAX_DAYS_BEFORE,
+};
+use shared::recurrence::{user_timezone, Schedule};
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tracing::{error, info};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+struct ScheduledEvent {
+    #[serde(default)]
+    detail_type: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ScanResponse {
+    users_scanned: u32,
+    reminders_created: u32,
+    reminders_updated: u32,
+    reminders_removed: u32,
+    unreadable_dates: u32,
+    errors: u32,
+}
+
+/// User with occasion reminders enabled
+#[derive(Debug, sqlx::FromRow)]
+struct OccasionUser {
+    user_id: Uuid,
+    occasion_reminder_days: i16,
+}
+
+/// An attribute's tracked reminder (`None` once the user deleted it)
+#[derive(Debug, sqlx::FromRow)]
+struct TrackedReminder {
+    attribute_id: Uuid,
+    reminder_id: Option<Uuid>,
+    title: Option<String>,
+    trigger_config: Option<Value>,
+}
+
+#[derive(Debug, Default)]
+struct UserScan {
+    created: u32,
+    updated: u32,
+    removed: u32,
+    unreadable: u32,
+}
+
+/// Reminder fields for one occasion
+struct OccasionReminder {
+    title: String,
+    description: String,
+    trigger_config: Value,
+    next_trigger_at: Option<DateTime<Utc>>,
+}
+
+impl OccasionReminder {
+    fn new(
+        attribute: &OccasionAttribute,
+        kind: OccasionKind,
+        date: &OccasionDate,
+        days_before: i16,
+        timezone: Tz,
+        now: DateTime<Utc>,
+    ) -> Result<Self, Error> {
+        let trigger_config = json!({
+            "cron": date.reminder_cron(days_before, reminder_time()),
+            "occasion": kind.as_str(),
+        });
+        let next_trigger_at = Schedule::from_config(&trigger_config, timezone)
+            .map_err(|e| format!("Invalid occasion schedule: {}", e))?
+            .next_after(now);
+
+        Ok(Self {
+            title: reminder_title(kind, &attribute.entity_name, days_before),
+            description: reminder_description(kind, date),
+            trigger_config,
+            next_trigger_at,
+        })
+    }
+}
+
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+/// Users with occasion reminders enabled (preferences default like the
+/// table's columns).
+async fn get_occasion_users(pool: &PgPool) -> Result<Vec<OccasionUser>, Error> {
+    let users: Vec<OccasionUser> = sqlx::query_as(
+        r#"
+        SELECT
+            u.id AS user_id,
+            COALESCE(unp.occasion_reminder_days, 7::smallint) AS occasion_reminder_days
+        FROM users u
+        LEFT JOIN user_notification_preferences unp ON unp.user_id = u.id
+        WHERE COALESCE(unp.occasion_reminders_enabled, true)
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to query users: {}", e))?;
+
+    Ok(users)
+}
+
+async fn get_tracked_reminders(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<HashMap<Uuid, TrackedReminder>, Error> {
+    let tracked: Vec<TrackedReminder> = sqlx::query_as(
+        r#"
+        SELECT o.attribute_id, o.reminder_id, r.title, r.trigger_config
+        FROM occasion_reminders o
+        LEFT JOIN reminders r ON r.id = o.reminder_id
+        WHERE o.user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch occasion reminders: {}", e))?;
+
+    Ok(tracked.into_iter().map(|t| (t.attribute_id, t)).collect())
+}
+
+async fn create_occasion_reminder(
+    pool: &PgPool,
+    user_id: Uuid,
+    attribute: &OccasionAttribute,
+    reminder: &OccasionReminder,
+) -> Result<(), Error> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let reminder_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO reminders (
+            user_id, title, description, trigger_type, trigger_config,
+            next_trigger_at, related_entity_id, metadata
+        ) VALUES ($1, $2, $3, 'recurring', $4, $5, $6, '{"source": "occasion"}')
+        RETURNING id
+        "#,
+    )
+    .bind(user_id)
+    .bind(&reminder.title)
+    .bind(&reminder.description)
+    .bind(&reminder.trigger_config)
+    .bind(reminder.next_trigger_at)
+    .bind(attribute.entity_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to create reminder: {}", e))?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO occasion_reminders (user_id, attribute_id, reminder_id)
+        VALUES ($1, $2, $3)
+        "#,
+    )
+    .bind(user_id)
+    .bind(attribute.attribute_id)
+    .bind(reminder_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to track occasion reminder: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit occasion reminder: {}", e))?;
+
+    Ok(())
+}
+
+async fn update_occasion_reminder(
+    pool: &PgPool,
+    reminder_id: Uuid,
+    reminder: &OccasionReminder,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE reminders
+        SET title = $2, description = $3, trigger_config = $4,
+            next_trigger_at = $5, updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(reminder_id)
+    .bind(&reminder.title)
+    .bind(&reminder.description)
+    .bind(&reminder.trigger_config)
+    .bind(reminder.next_trigger_at)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to update reminder: {}", e))?;
+
+    Ok(())
+}
+
+/// Remove reminders for attributes that are no longer current, and any
+/// occasion reminders left behind by deleted attributes.
+async fn remove_stale_reminders(
+    pool: &PgPool,
+    user_id: Uuid,
+    stale_attribute_ids: &[Uuid],
+) -> Result<u32, Error> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    sqlx::query("DELETE FROM occasion_reminders WHERE user_id = $1 AND attribute_id = ANY($2)")
+        .bind(user_id)
+        .bind(stale_attribute_ids)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to untrack occasion reminders: {}", e))?;
+
+    let removed = sqlx::query(
+        r#"
+        DELETE FROM reminders r
+        WHERE r.user_id = $1 AND r.metadata->>'source' = 'occasion'
+          AND NOT EXISTS (SELECT 1 FROM occasion_reminders o WHERE o.reminder_id = r.id)
+        "#,
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to remove occasion reminders: {}", e))?
+    .rows_affected();
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit occasion cleanup: {}", e))?;
+
+    Ok(removed as u32)
+}
+
+async fn scan_user(
+    pool: &PgPool,
+    user: &OccasionUser,
+    now: DateTime<Utc>,
+) -> Result<UserScan, Error> {
+    let mut scan = UserScan::default();
+
+    let timezone = user_timezone(pool, user.user_id)
+        .await
+        .map_err(|e| format!("Failed to fetch timezone: {}", e))?;
+    let attributes = fetch_occasion_attributes(pool, user.user_id)
+        .await
+        .map_err(|e| format!("Failed to fetch occasion attributes: {}", e))?;
+    let tracked = get_tracked_reminders(pool, user.user_id).await?;
+    let days_before = user.occasion_reminder_days.clamp(0, MAX_DAYS_BEFORE);
+
+    let mut current = HashSet::new();
+
+    for attribute in &attributes {
+        let (kind, date) = match attribute.parse() {
+            Some(parsed) => parsed,
+            None => {
+                scan.unreadable += 1;
+                continue;
+            }
+        };
+        current.insert(attribute.attribute_id);
+
+        let reminder = OccasionReminder::new(attribute, kind, &date, days_before, timezone, now)?;
+
+        match tracked.get(&attribute.attribute_id) {
+            None => {
+                create_occasion_reminder(pool, user.user_id, attribute, &reminder).await?;
+                scan.created += 1;
+            }
+            Some(TrackedReminder {
+                reminder_id: Some(reminder_id),
+                title,
+                trigger_config,
+                ..
+            }) => {
+                let changed = title.as_deref() != Some(reminder.title.as_str())
+                    || trigger_config.as_ref() != Some(&reminder.trigger_config);
+                if changed {
+                    update_occasion_reminder(pool, *reminder_id, &reminder).await?;
+                    scan.updated += 1;
+                }
+            }
+            // The user deleted this reminder
+            Some(_) => {}
+        }
+    }
+
+    let stale: Vec<Uuid> = tracked
+        .keys()
+        .filter(|id| !current.contains(*id))
+        .copied()
+        .collect();
+    scan.removed = remove_stale_reminders(pool, user.user_id, &stale).await?;
+
+    Ok(scan)
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    _event: LambdaEvent<ScheduledEvent>,
+) -> Result<ScanResponse, Error> {
+    info!("Starting occasion scan");
+
+    let now = Utc::now();
+    let users = get_occasion_users(&state.db_pool).await?;
+
+    let mut response = ScanResponse {
+        users_scanned: users.len() as u32,
+        reminders_created: 0,
+        reminders_updated: 0,
+        reminders_removed: 0,
+        unreadable_dates: 0,
+        errors: 0,
+    };
+
+    for user in &users {
+        match scan_user(&state.db_pool, user, now).await {
+            Ok(scan) => {
+                if scan.created + scan.updated + scan.removed > 0 {
+                    info!(
+                        user_id = %user.user_id,
+                        created = scan.created,
+                        updated = scan.updated,
+                        removed = scan.removed,
+                        "Updated occasion reminders"
+                    );
+                }
+                response.reminders_created += scan.created;
+                response.reminders_updated += scan.updated;
+                response.reminders_removed += scan.removed;
+                response.unreadable_dates += scan.unreadable;
+            }
+            Err(e) => {
+                error!(user_id = %user.user_id, error = %e, "Failed to scan occasions");
+                response.errors += 1;
+            }
+        }
+    }
+
+    info!(
+        users_scanned = response.users_scanned,
+        reminders_created = response.reminders_created,
+        reminders_updated = response.reminders_updated,
+        reminders_removed = response.reminders_removed,
+        unreadable_dates = response.unreadable_dates,
+        errors = response.errors,
+        "Occasion scan complete"
+    );
+
+    Ok(response)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let state_clone = state.clone();
+
+    run(service_fn(move |event| {
+        let state = state_clone.clone();
+        async move { metrics::track_invocation("occasion_scanner", handler(state, event)).await }
+    }))
+    .await
+}
@@ -0,0 +1,962 @@
+//! Photo Ingest Lambda - Turns uploaded photos into facts.
+//!
+//! Photos uploaded to `photos/{cognito_sub}/...` in the drop folder bucket
+//! produce S3 notifications that arrive via SQS. For each photo this reads the
+//! EXIF capture time and GPS position, stores a thumbnail, asks Rekognition for
+//! labels, and matches each detected face against the face collection, where
+//! person entities' photos are indexed (see `enroll_entity_faces`). The GPS
+//! position resolves to a nearby place entity, or else a reverse-geocoded place
+//! name. The result is recorded as a fact ("Photo of Max at Lake Tahoe on
+//! 2024-07-04") mentioning the people and place, and each recognized person gets
+//! a dated `entity_locations` row for where the photo was taken.
+//!
+//! Every photo is recorded in `photo_ingests`. Failures are recorded there and
+//! the user is sent a system notification.
+
+use aws_sdk_rekognition::primitives::Blob;
+use aws_sdk_rekognition::types::{Image, QualityFilter, S3Object};
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_sns::Client as SnsClient;
+use chrono::{NaiveDate, NaiveDateTime};
+use exif::{In, Tag, Value};
+use image::{DynamicImage, ImageFormat};
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use shared::access::space_clause;
+use shared::digest::DIGEST_MAX_PRIORITY;
+use shared::metrics;
+use shared::photos::{
+    face_crop, gps_coordinate, is_supported_photo, parse_exif_datetime, photo_description,
+    thumbnail_key, FACE_MATCH_THRESHOLD, MAX_FACES, MAX_LABELS, MAX_PHOTO_BYTES,
+    MIN_LABEL_CONFIDENCE, NEARBY_PLACE_METERS, THUMBNAIL_SIZE,
+};
+use shared::reminders::{preferred_channel, NotificationPreferences};
+use shared::s3_ingest::{decode_key, file_name, resolve_user, ProcessError, MAX_ATTEMPTS};
+use shared::AuthorizedUser;
+use sqlx::PgPool;
+use std::io::Cursor;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// Prefix users upload photos to: `photos/{cognito_sub}/...`
+const PHOTOS_PREFIX: &str = "photos/";
+
+/// `entity_locations` label for where a person was photographed
+const PHOTO_LOCATION_LABEL: &str = "photo";
+
+/// Importance of photo facts; most photos are minor memories
+const PHOTO_IMPORTANCE: i16 = 2;
+
+/// Person entity photos indexed per invocation
+const MAX_ENROLLMENTS: i64 = 20;
+
+/// SQS event wrapper
+#[derive(Debug, Deserialize)]
+struct SqsEvent {
+    #[serde(rename = "Records")]
+    records: Vec<SqsRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SqsRecord {
+    #[serde(rename = "messageId")]
+    message_id: String,
+    body: String,
+}
+
+/// S3 event notification
+#[derive(Debug, Deserialize)]
+struct S3Notification {
+    // Absent on the s3:TestEvent sent when the notification is configured
+    #[serde(rename = "Records", default)]
+    records: Vec<S3Record>,
+}
+
+#[derive(Debug, Deserialize)]
+struct S3Record {
+    s3: S3Entity,
+}
+
+#[derive(Debug, Deserialize)]
+struct S3Entity {
+    bucket: S3Bucket,
+    object: S3ObjectInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct S3Bucket {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct S3ObjectInfo {
+    key: String,
+    #[serde(default)]
+    size: Option<i64>,
+    #[serde(rename = "eTag", default)]
+    e_tag: Option<String>,
+}
+
+/// SQS partial batch response
+#[derive(Debug, Serialize)]
+struct SqsBatchResponse {
+    #[serde(rename = "batchItemFailures")]
+    batch_item_failures: Vec<BatchItemFailure>,
+}
+
+#[derive(Debug, Serialize)]
+struct BatchItemFailure {
+    #[serde(rename = "itemIdentifier")]
+    item_identifier: String,
+}
+
+/// Manifest row for an uploaded photo
+#[derive(Debug, sqlx::FromRow)]
+struct PhotoRow {
+    id: Uuid,
+    user_id: Uuid,
+    bucket: String,
+    object_key: String,
+    status: String,
+    attempts: i16,
+}
+
+/// Person entity whose photo needs indexing into the face collection
+#[derive(Debug, sqlx::FromRow)]
+struct FaceEnrollment {
+    id: Uuid,
+    photo_key: String,
+    face_id: Option<String>,
+}
+
+/// A person recognized in a photo
+#[derive(Debug)]
+struct RecognizedPerson {
+    entity_id: Uuid,
+    name: String,
+    similarity: f32,
+}
+
+/// What the photo's EXIF data says
+#[derive(Debug, Default)]
+struct PhotoMetadata {
+    taken_at: Option<NaiveDateTime>,
+    /// (latitude, longitude)
+    position: Option<(f64, f64)>,
+    orientation: u32,
+}
+
+struct AppState {
+    db_pool: PgPool,
+    s3_client: aws_sdk_s3::Client,
+    rekognition_client: aws_sdk_rekognition::Client,
+    location_client: aws_sdk_location::Client,
+    sns_client: SnsClient,
+    notification_topic_arn: Option<String>,
+    /// Rekognition collection person entities' faces are indexed into
+    face_collection_id: String,
+    /// Bucket holding entity photos; face matching is skipped without it
+    entity_photos_bucket: Option<String>,
+    /// Amazon Location place index for reverse geocoding
+    place_index_name: Option<String>,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        let face_collection_id =
+            std::env::var("FACE_COLLECTION_ID").map_err(|_| "FACE_COLLECTION_ID not set")?;
+
+        Ok(Self {
+            db_pool,
+            s3_client: aws_sdk_s3::Client::new(&config),
+            rekognition_client: aws_sdk_rekognition::Client::new(&config),
+            location_client: aws_sdk_location::Client::new(&config),
+            sns_client: SnsClient::new(&config),
+            notification_topic_arn: std::env::var("NOTIFICATION_TOPIC_ARN").ok(),
+            face_collection_id,
+            entity_photos_bucket: std::env::var("ENTITY_PHOTOS_BUCKET").ok(),
+            place_index_name: std::env::var("PLACE_INDEX_NAME").ok(),
+        })
+    }
+}
+
+/// Read capture time, GPS position and orientation from EXIF data. Photos
+/// without EXIF (screenshots, most PNGs) just have none of them.
+fn read_metadata(bytes: &[u8]) -> PhotoMetadata {
+    let exif = match exif::Reader::new().read_from_container(&mut Cursor::new(bytes)) {
+        Ok(exif) => exif,
+        Err(_) => return PhotoMetadata::default(),
+    };
+
+    let ascii = |tag: Tag| match exif.get_field(tag, In::PRIMARY).map(|f| &f.value) {
+        Some(Value::Ascii(values)) => values
+            .first()
+            .map(|v| String::from_utf8_lossy(v).into_owned()),
+        _ => None,
+    };
+    let dms = |tag: Tag| match exif.get_field(tag, In::PRIMARY).map(|f| &f.value) {
+        Some(Value::Rational(values)) if values.len() >= 3 => {
+            Some([values[0].to_f64(), values[1].to_f64(), values[2].to_f64()])
+        }
+        _ => None,
+    };
+
+    let taken_at = ascii(Tag::DateTimeOriginal)
+        .and_then(|v| parse_exif_datetime(&v))
+        .or_else(|| ascii(Tag::DateTime).and_then(|v| parse_exif_datetime(&v)));
+
+    let latitude = dms(Tag::GPSLatitude)
+        .zip(ascii(Tag::GPSLatitudeRef))
+        .and_then(|(v, r)| gps_coordinate(v, &r));
+    let longitude = dms(Tag::GPSLongitude)
+        .zip(ascii(Tag::GPSLongitudeRef))
+        .and_then(|(v, r)| gps_coordinate(v, &r));
+
+    let orientation = exif
+        .get_field(Tag::Orientation, In::PRIMARY)
+        .and_then(|f| f.value.get_uint(0))
+        .unwrap_or(1);
+
+    PhotoMetadata {
+        taken_at,
+        position: latitude.zip(longitude),
+        orientation,
+    }
+}
+
+/// Rotate an image upright according to its EXIF orientation.
+fn upright(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+/// Encode an image as JPEG.
+fn encode_jpeg(image: &DynamicImage) -> Result<Vec<u8>, image::ImageError> {
+    let mut buffer = Cursor::new(Vec::new());
+    DynamicImage::ImageRgb8(image.to_rgb8()).write_to(&mut buffer, ImageFormat::Jpeg)?;
+    Ok(buffer.into_inner())
+}
+
+/// Record a photo, returning its row (attempts incremented on redelivery).
+async fn upsert_photo(
+    pool: &PgPool,
+    user_id: Uuid,
+    bucket: &str,
+    key: &str,
+    etag: &str,
+    size: Option<i64>,
+) -> Result<PhotoRow, sqlx::Error> {
+    sqlx::query_as(
+        r#"
+        INSERT INTO photo_ingests (user_id, bucket, object_key, etag, size_bytes)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (bucket, object_key, etag) DO UPDATE SET
+            attempts = photo_ingests.attempts + 1,
+            updated_at = NOW()
+        RETURNING id, user_id, bucket, object_key, status::text, attempts
+        "#,
+    )
+    .bind(user_id)
+    .bind(bucket)
+    .bind(key)
+    .bind(etag)
+    .bind(size)
+    .fetch_one(pool)
+    .await
+}
+
+/// Mark a photo as failed and notify its owner.
+async fn fail_photo(state: &AppState, photo: &PhotoRow, reason: &str) -> Result<(), Error> {
+    warn!(photo_id = %photo.id, reason, "Photo ingest failed");
+
+    sqlx::query(
+        r#"
+        UPDATE photo_ingests
+        SET status = 'failed', error_message = $2, processed_at = NOW(), updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(photo.id)
+    .bind(reason)
+    .execute(&state.db_pool)
+    .await
+    .map_err(|e| format!("Failed to update photo: {}", e))?;
+
+    let prefs: NotificationPreferences = sqlx::query_as(
+        r#"
+        SELECT
+            push_enabled,
+            email_enabled,
+            discord_enabled,
+            quiet_hours_enabled,
+            quiet_hours_start,
+            quiet_hours_end,
+            timezone
+        FROM user_notification_preferences
+        WHERE user_id = $1
+        "#,
+    )
+    .bind(photo.user_id)
+    .fetch_optional(&state.db_pool)
+    .await
+    .map_err(|e| format!("Failed to query preferences: {}", e))?
+    .unwrap_or_default();
+
+    let title = "Couldn't import a photo";
+    let body = format!("{}: {}", file_name(&photo.object_key), reason);
+
+    let notification_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO notifications (
+            user_id, notification_type, title, body, channel,
+            source_entity_id, source_entity_type, priority
+        ) VALUES ($1, 'system', $2, $3, $4::notification_channel, $5, 'photo_ingest', $6)
+        RETURNING id
+        "#,
+    )
+    .bind(photo.user_id)
+    .bind(title)
+    .bind(&body)
+    .bind(preferred_channel(&prefs))
+    .bind(photo.id)
+    // Import failures can wait for the user's digest
+    .bind(DIGEST_MAX_PRIORITY)
+    .fetch_one(&state.db_pool)
+    .await
+    .map_err(|e| format!("Failed to queue notification: {}", e))?;
+
+    if let Some(topic_arn) = &state.notification_topic_arn {
+        let message = serde_json::json!({
+            "notification_id": notification_id.to_string(),
+            "type": "system",
+            "title": title,
+        });
+
+        state
+            .sns_client
+            .publish()
+            .topic_arn(topic_arn)
+            .message(serde_json::to_string(&message).unwrap_or_default())
+            .send()
+            .await
+            .map_err(|e| format!("Failed to publish to SNS: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// Index the photos of the user's person entities that are new or changed
+/// since they were last indexed, replacing any previous face.
+async fn enroll_entity_faces(
+    state: &AppState,
+    user: &AuthorizedUser,
+    photos_bucket: &str,
+) -> Result<(), ProcessError> {
+    let enrollments: Vec<FaceEnrollment> = sqlx::query_as(
+        r#"
+        SELECT id, photo_key, face_id
+        FROM entities
+        WHERE entity_type = 'person'
+          AND photo_key IS NOT NULL
+          AND face_indexed_key IS DISTINCT FROM photo_key
+          AND deleted_at IS NULL
+          AND ((owner_type = 'user' AND owner_id = $1)
+               OR (owner_type = 'family' AND owner_id = ANY($2)))
+        ORDER BY photo_updated_at DESC NULLS LAST
+        LIMIT $3
+        "#,
+    )
+    .bind(user.user_id)
+    .bind(&user.family_ids)
+    .bind(MAX_ENROLLMENTS)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    for entity in enrollments {
+        if let Some(old_face_id) = &entity.face_id {
+            if let Err(e) = state
+                .rekognition_client
+                .delete_faces()
+                .collection_id(&state.face_collection_id)
+                .face_ids(old_face_id)
+                .send()
+                .await
+            {
+                warn!(entity_id = %entity.id, error = %e, "Failed to delete replaced face");
+            }
+        }
+
+        let image = Image::builder()
+            .s3_object(
+                S3Object::builder()
+                    .bucket(photos_bucket)
+                    .name(&entity.photo_key)
+                    .build(),
+            )
+            .build();
+
+        let face_id = match state
+            .rekognition_client
+            .index_faces()
+            .collection_id(&state.face_collection_id)
+            .image(image)
+            .external_image_id(entity.id.to_string())
+            .max_faces(1)
+            .quality_filter(QualityFilter::Auto)
+            .send()
+            .await
+        {
+            Ok(output) => output
+                .face_records()
+                .first()
+                .and_then(|r| r.face())
+                .and_then(|f| f.face_id())
+                .map(str::to_string),
+            Err(e) => {
+                let unusable = e.as_service_error().is_some_and(|se| {
+                    se.is_invalid_parameter_exception()
+                        || se.is_invalid_image_format_exception()
+                        || se.is_invalid_s3_object_exception()
+                        || se.is_image_too_large_exception()
+                });
+                if !unusable {
+                    return Err(e.into());
+                }
+                warn!(entity_id = %entity.id, error = %e, "Entity photo can't be indexed");
+                None
+            }
+        };
+
+        sqlx::query("UPDATE entities SET face_id = $2, face_indexed_key = $3 WHERE id = $1")
+            .bind(entity.id)
+            .bind(&face_id)
+            .bind(&entity.photo_key)
+            .execute(&state.db_pool)
+            .await?;
+
+        info!(entity_id = %entity.id, indexed = face_id.is_some(), "Indexed entity face");
+    }
+
+    Ok(())
+}
+
+/// Labels Rekognition detects in a photo, most confident first.
+async fn detect_labels(state: &AppState, photo: &PhotoRow) -> Result<Vec<String>, ProcessError> {
+    let output = state
+        .rekognition_client
+        .detect_labels()
+        .image(
+            Image::builder()
+                .s3_object(
+                    S3Object::builder()
+                        .bucket(&photo.bucket)
+                        .name(&photo.object_key)
+                        .build(),
+                )
+                .build(),
+        )
+        .max_labels(MAX_LABELS)
+        .min_confidence(MIN_LABEL_CONFIDENCE)
+        .send()
+        .await?;
+
+    Ok(output
+        .labels()
+        .iter()
+        .filter_map(|l| l.name())
+        .map(str::to_string)
+        .collect())
+}
+
+/// People in the photo: each detected face, cropped out of the image, is
+/// searched for in the face collection, and matches are kept if they are
+/// person entities the user can see. Best match first.
+async fn recognize_people(
+    state: &AppState,
+    user: &AuthorizedUser,
+    photo: &PhotoRow,
+    image: &DynamicImage,
+) -> Result<Vec<RecognizedPerson>, ProcessError> {
+    let faces = state
+        .rekognition_client
+        .detect_faces()
+        .image(
+            Image::builder()
+                .s3_object(
+                    S3Object::builder()
+                        .bucket(&photo.bucket)
+                        .name(&photo.object_key)
+                        .build(),
+                )
+                .build(),
+        )
+        .send()
+        .await?;
+
+    let mut matches: Vec<(Uuid, f32)> = Vec::new();
+
+    for face in faces.face_details().iter().take(MAX_FACES) {
+        let Some(bbox) = face.bounding_box() else {
+            continue;
+        };
+        let Some((x, y, width, height)) = face_crop(
+            bbox.left().unwrap_or_default(),
+            bbox.top().unwrap_or_default(),
+            bbox.width().unwrap_or_default(),
+            bbox.height().unwrap_or_default(),
+            image.width(),
+            image.height(),
+        ) else {
+            continue;
+        };
+        let crop = encode_jpeg(&image.crop_imm(x, y, width, height))?;
+
+        let output = match state
+            .rekognition_client
+            .search_faces_by_image()
+            .collection_id(&state.face_collection_id)
+            .image(Image::builder().bytes(Blob::new(crop)).build())
+            .face_match_threshold(FACE_MATCH_THRESHOLD)
+            .max_faces(1)
+            .send()
+            .await
+        {
+            Ok(output) => output,
+            // The crop holds no face Rekognition can search with
+            Err(e)
+                if e.as_service_error()
+                    .is_some_and(|se| se.is_invalid_parameter_exception()) =>
+            {
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        for face_match in output.face_matches() {
+            let entity_id = face_match
+                .face()
+                .and_then(|f| f.external_image_id())
+                .and_then(|id| id.parse::<Uuid>().ok());
+            if let (Some(entity_id), Some(similarity)) = (entity_id, face_match.similarity()) {
+                matches.push((entity_id, similarity));
+            }
+        }
+    }
+
+    if matches.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let entity_ids: Vec<Uuid> = matches.iter().map(|(id, _)| *id).collect();
+    let visible: Vec<(Uuid, String)> = sqlx::query_as(&format!(
+        r#"
+        SELECT id, name FROM entities
+        WHERE id = ANY($1)
+          AND entity_type = 'person'
+          AND deleted_at IS NULL
+          AND ((owner_type = 'user' AND owner_id = $2)
+               OR (owner_type = 'family' AND owner_id = ANY($3) AND {}))
+        "#,
+        space_clause("entities", 2)
+    ))
+    .bind(&entity_ids)
+    .bind(user.user_id)
+    .bind(&user.family_ids)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let mut people: Vec<RecognizedPerson> = visible
+        .into_iter()
+        .map(|(entity_id, name)| RecognizedPerson {
+            entity_id,
+            name,
+            similarity: matches
+                .iter()
+                .filter(|(id, _)| *id == entity_id)
+                .map(|(_, s)| *s)
+                .fold(0.0, f32::max),
+        })
+        .collect();
+    people.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+
+    Ok(people)
+}
+
+/// Closest place entity the user can see within `NEARBY_PLACE_METERS`.
+async fn nearby_place(
+    state: &AppState,
+    user: &AuthorizedUser,
+    (latitude, longitude): (f64, f64),
+) -> Result<Option<(Uuid, String)>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        r#"
+        SELECT e.id, e.name
+        FROM entities e
+        JOIN entity_locations l ON l.entity_id = e.id
+        WHERE e.entity_type = 'place'
+          AND e.deleted_at IS NULL
+          AND ((e.owner_type = 'user' AND e.owner_id = $1)
+               OR (e.owner_type = 'family' AND e.owner_id = ANY($2) AND {}))
+          AND l.valid_to IS NULL
+          AND ST_DWithin(l.location, ST_SetSRID(ST_MakePoint($3, $4), 4326)::geography, $5)
+        ORDER BY ST_Distance(l.location, ST_SetSRID(ST_MakePoint($3, $4), 4326)::geography)
+        LIMIT 1
+        "#,
+        space_clause("e", 1)
+    ))
+    .bind(user.user_id)
+    .bind(&user.family_ids)
+    .bind(longitude)
+    .bind(latitude)
+    .bind(NEARBY_PLACE_METERS)
+    .fetch_optional(&state.db_pool)
+    .await
+}
+
+/// Town or address at a position, from the place index.
+async fn reverse_geocode(state: &AppState, (latitude, longitude): (f64, f64)) -> Option<String> {
+    let index_name = state.place_index_name.as_ref()?;
+
+    let output = match state
+        .location_client
+        .search_place_index_for_position()
+        .index_name(index_name)
+        .position(longitude)
+        .position(latitude)
+        .max_results(1)
+        .send()
+        .await
+    {
+        Ok(output) => output,
+        Err(e) => {
+            warn!(error = %e, "Reverse geocoding failed");
+            return None;
+        }
+    };
+
+    output
+        .results()
+        .first()
+        .and_then(|r| r.place())
+        .and_then(|p| p.municipality().or(p.label()))
+        .map(str::to_string)
+}
+
+/// Label, recognize and record a photo.
+async fn process_photo(
+    state: &AppState,
+    user: &AuthorizedUser,
+    photo: &PhotoRow,
+) -> Result<(), ProcessError> {
+    let object = state
+        .s3_client
+        .get_object()
+        .bucket(&photo.bucket)
+        .key(&photo.object_key)
+        .send()
+        .await?;
+
+    if object.content_length().unwrap_or_default() > MAX_PHOTO_BYTES {
+        return Err(ProcessError::Permanent(format!(
+            "Photo is larger than {} MB",
+            MAX_PHOTO_BYTES / (1024 * 1024)
+        )));
+    }
+    let bytes = object.body.collect().await?.into_bytes();
+
+    let metadata = read_metadata(&bytes);
+    let image = image::load_from_memory(&bytes)
+        .map(|image| upright(image, metadata.orientation))
+        .map_err(|e| ProcessError::Permanent(format!("Unreadable image: {}", e)))?;
+
+    let thumbnail = thumbnail_key(photo.id);
+    state
+        .s3_client
+        .put_object()
+        .bucket(&photo.bucket)
+        .key(&thumbnail)
+        .content_type("image/jpeg")
+        .body(ByteStream::from(encode_jpeg(
+            &image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE),
+        )?))
+        .send()
+        .await?;
+
+    let labels = detect_labels(state, photo).await?;
+
+    let people = match &state.entity_photos_bucket {
+        Some(photos_bucket) => {
+            enroll_entity_faces(state, user, photos_bucket).await?;
+            recognize_people(state, user, photo, &image).await?
+        }
+        None => Vec::new(),
+    };
+
+    let (place_entity, place_name) = match metadata.position {
+        Some(position) => match nearby_place(state, user, position).await? {
+            Some((id, name)) => (Some(id), Some(name)),
+            None => (None, reverse_geocode(state, position).await),
+        },
+        None => (None, None),
+    };
+
+    let taken_on: Option<NaiveDate> = metadata.taken_at.map(|t| t.date());
+    let names: Vec<String> = people.iter().map(|p| p.name.clone()).collect();
+    let content = photo_description(&names, &labels, place_name.as_deref(), taken_on);
+    let (latitude, longitude) = metadata.position.unzip();
+
+    let mut tx = state.db_pool.begin().await?;
+
+    let fact_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO facts (
+            owner_type, owner_id, created_by, content, source,
+            importance, about_entity_id, valid_from
+        )
+        VALUES ('user', $1, $1, $2, 'import', $3, $4, $5)
+        RETURNING id
+        "#,
+    )
+    .bind(user.user_id)
+    .bind(&content)
+    .bind(PHOTO_IMPORTANCE)
+    .bind(people.first().map(|p| p.entity_id))
+    .bind(taken_on)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    for person in &people {
+        sqlx::query(
+            r#"
+            INSERT INTO entity_mentions (fact_id, entity_id, role, confidence)
+            VALUES ($1, $2, 'subject', ROUND(($3::float8 / 100)::numeric, 2))
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(fact_id)
+        .bind(person.entity_id)
+        .bind(person.similarity as f64)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "INSERT INTO photo_ingest_entities (photo_id, entity_id, similarity) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+        )
+        .bind(photo.id)
+        .bind(person.entity_id)
+        .bind(person.similarity)
+        .execute(&mut *tx)
+        .await?;
+
+        // Where they were on the day the photo was taken
+        if let (Some((latitude, longitude)), Some(date)) = (metadata.position, taken_on) {
+            sqlx::query(
+                r#"
+                INSERT INTO entity_locations (entity_id, label, address_raw, location,
+                                              geocode_source, geocoded_at, valid_from, valid_to)
+                VALUES ($1, $2, $3, ST_SetSRID(ST_MakePoint($4, $5), 4326)::geography,
+                        'exif', NOW(), $6, $6)
+                "#,
+            )
+            .bind(person.entity_id)
+            .bind(PHOTO_LOCATION_LABEL)
+            .bind(&place_name)
+            .bind(longitude)
+            .bind(latitude)
+            .bind(date)
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    if let Some(place_id) = place_entity {
+        sqlx::query(
+            r#"
+            INSERT INTO entity_mentions (fact_id, entity_id, role, confidence)
+            VALUES ($1, $2, 'location', 1.0)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(fact_id)
+        .bind(place_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE photo_ingests
+        SET status = 'completed',
+            taken_at = $2,
+            location = ST_SetSRID(ST_MakePoint($3::float8, $4::float8), 4326)::geography,
+            place_name = $5,
+            labels = $6,
+            fact_id = $7,
+            thumbnail_key = $8,
+            error_message = NULL,
+            processed_at = NOW(),
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(photo.id)
+    .bind(metadata.taken_at)
+    .bind(longitude)
+    .bind(latitude)
+    .bind(&place_name)
+    .bind(&labels)
+    .bind(fact_id)
+    .bind(&thumbnail)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    info!(
+        photo_id = %photo.id,
+        %fact_id,
+        people = people.len(),
+        labels = labels.len(),
+        "Ingested photo"
+    );
+    Ok(())
+}
+
+/// Handle a new object under `photos/`.
+async fn handle_upload(
+    state: &AppState,
+    bucket: &str,
+    object: &S3ObjectInfo,
+) -> Result<(), ProcessError> {
+    let key = decode_key(&object.key);
+
+    let cognito_sub = match key
+        .strip_prefix(PHOTOS_PREFIX)
+        .and_then(|k| k.split_once('/'))
+    {
+        Some((sub, rest)) if !sub.is_empty() && !rest.is_empty() && !rest.ends_with('/') => sub,
+        _ => {
+            warn!(key, "Ignoring object outside a user photos folder");
+            return Ok(());
+        }
+    };
+
+    let user = match resolve_user(&state.db_pool, cognito_sub).await? {
+        Some(u) => u,
+        None => {
+            warn!(key, "Ignoring photo for unknown user");
+            return Ok(());
+        }
+    };
+
+    let etag = object.e_tag.clone().unwrap_or_default();
+    let photo = upsert_photo(
+        &state.db_pool,
+        user.user_id,
+        bucket,
+        &key,
+        &etag,
+        object.size,
+    )
+    .await?;
+
+    if photo.status != "pending" && photo.status != "failed" {
+        info!(photo_id = %photo.id, status = %photo.status, "Skipping duplicate delivery");
+        return Ok(());
+    }
+
+    let result = if is_supported_photo(&key) {
+        process_photo(state, &user, &photo).await
+    } else {
+        Err(ProcessError::Permanent(
+            "Unsupported photo type (use JPEG or PNG)".to_string(),
+        ))
+    };
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(ProcessError::Permanent(reason)) => fail_photo(state, &photo, &reason)
+            .await
+            .map_err(ProcessError::from),
+        Err(ProcessError::Transient(reason)) if photo.attempts >= MAX_ATTEMPTS => {
+            fail_photo(state, &photo, &reason)
+                .await
+                .map_err(ProcessError::from)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+async fn handle_sqs(state: &AppState, event: SqsEvent) -> SqsBatchResponse {
+    let mut failures = Vec::new();
+
+    for record in event.records {
+        let notification: S3Notification = match serde_json::from_str(&record.body) {
+            Ok(n) => n,
+            Err(e) => {
+                error!(message_id = %record.message_id, error = %e, "Invalid S3 notification");
+                continue;
+            }
+        };
+
+        for s3_record in &notification.records {
+            if let Err(ProcessError::Transient(e) | ProcessError::Permanent(e)) =
+                handle_upload(state, &s3_record.s3.bucket.name, &s3_record.s3.object).await
+            {
+                error!(message_id = %record.message_id, error = %e, "Failed to process photo");
+                failures.push(BatchItemFailure {
+                    item_identifier: record.message_id.clone(),
+                });
+                break;
+            }
+        }
+    }
+
+    SqsBatchResponse {
+        batch_item_failures: failures,
+    }
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    event: LambdaEvent<SqsEvent>,
+) -> Result<SqsBatchResponse, Error> {
+    let response = handle_sqs(&state, event.payload).await;
+    info!(
+        failures = response.batch_item_failures.len(),
+        "Photo ingest batch complete"
+    );
+    Ok(response)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        async move { metrics::track_invocation("photo_ingest", handler(state, event)).await }
+    }))
+    .await
+}
@@ -0,0 +1,129 @@
+//! Realtime Dispatcher Lambda - Pushes domain events to WebSocket clients.
+//!
+//! Subscribed to the domain event bus (see `docs/design/domain-events.md`).
+//! `FactCreated` goes to the user who created the fact and
+//! `ReminderTriggered` to the reminder's user, on every connection they have
+//! open (see `shared::realtime`). Users without a connection are skipped.
+
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use shared::events::{DomainEvent, EventEnvelope, FactCreated, ReminderTriggered};
+use shared::metrics;
+use shared::realtime::{Broadcaster, ConnectionStore, ServerMessage};
+use std::sync::Arc;
+use tracing::{info, warn};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// EventBridge event (the fields used here)
+#[derive(Debug, Deserialize)]
+struct BusEvent {
+    #[serde(rename = "detail-type", default)]
+    detail_type: String,
+    #[serde(default)]
+    detail: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+struct DispatchResponse {
+    connections_notified: usize,
+}
+
+struct AppState {
+    broadcaster: Broadcaster,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let table_name = std::env::var("CONNECTIONS_TABLE_NAME")
+            .map_err(|_| "CONNECTIONS_TABLE_NAME not set")?;
+        let endpoint = std::env::var("WEBSOCKET_CALLBACK_URL")
+            .map_err(|_| "WEBSOCKET_CALLBACK_URL not set")?;
+
+        let store = ConnectionStore::new(aws_sdk_dynamodb::Client::new(&config), table_name);
+
+        Ok(Self {
+            broadcaster: Broadcaster::new(&config, &endpoint, store),
+        })
+    }
+}
+
+fn parse_detail<E: DomainEvent + DeserializeOwned>(detail: serde_json::Value) -> Result<E, Error> {
+    let envelope: EventEnvelope<E> = serde_json::from_value(detail)
+        .map_err(|e| format!("Invalid {} detail: {}", E::DETAIL_TYPE, e))?;
+    Ok(envelope.data)
+}
+
+/// The user to notify and the message to send, or `None` for events this
+/// Lambda doesn't forward.
+fn message_for(event: BusEvent) -> Result<Option<(Uuid, ServerMessage)>, Error> {
+    match event.detail_type.as_str() {
+        FactCreated::DETAIL_TYPE => {
+            let fact: FactCreated = parse_detail(event.detail)?;
+            Ok(Some((
+                fact.created_by,
+                ServerMessage::FactCreated {
+                    fact_id: fact.fact_id,
+                    source: fact.source,
+                },
+            )))
+        }
+        ReminderTriggered::DETAIL_TYPE => {
+            let reminder: ReminderTriggered = parse_detail(event.detail)?;
+            Ok(Some((
+                reminder.user_id,
+                ServerMessage::ReminderFired {
+                    reminder_id: reminder.reminder_id,
+                    notification_id: reminder.notification_id,
+                },
+            )))
+        }
+        _ => Ok(None),
+    }
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    event: LambdaEvent<BusEvent>,
+) -> Result<DispatchResponse, Error> {
+    let detail_type = event.payload.detail_type.clone();
+
+    let Some((user_id, message)) = message_for(event.payload)? else {
+        warn!(detail_type = %detail_type, "Ignoring unexpected event");
+        return Ok(DispatchResponse {
+            connections_notified: 0,
+        });
+    };
+
+    let connections_notified = state.broadcaster.send_to_user(user_id, &message).await?;
+
+    info!(
+        detail_type = %detail_type,
+        user_id = %user_id,
+        connections_notified,
+        "Dispatched event"
+    );
+
+    Ok(DispatchResponse {
+        connections_notified,
+    })
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        async move { metrics::track_invocation("realtime_dispatcher", handler(state, event)).await }
+    }))
+    .await
+}
@@ -4,13 +4,37 @@
 //! 1. Queries reminders whose trigger time has passed
 //! 2. Evaluates trigger conditions
 //! 3. Queues notifications for delivery
-//! 4. Updates reminder status (triggered or reschedules recurring)
-
+//! 4. Updates reminder status (triggered or reschedules recurring in the user's
+//!    timezone, see `shared::recurrence`)
+//! 5. Records the occurrence for the reminder history API
+//!
+//! Notifications that come due during the user's quiet hours are queued with
+//! `deferred_until` set to when quiet hours end, and published by a later run
+//! once that time has passed. A user's `reminder` type override can turn
+//! reminder notifications off, pick their channel or let them through quiet
+//! hours (see `shared::notification_preferences`).
+//!
+//! Each run first reschedules reminders set to fire in time to leave (see
+//! `shared::reminders::LeaveInTime`) whose time is near: the travel time from
+//! the user's last known location (their latest location history ping) to the
+//! related entity is estimated with the Amazon Location route calculator,
+//! re-estimated every few minutes, and `next_trigger_at` moved to when they
+//! need to leave.
+
+use aws_sdk_location::types::TravelMode;
 use aws_sdk_sns::Client as SnsClient;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use lambda_runtime::{run, service_fn, Error, LambdaEvent};
 use serde::{Deserialize, Serialize};
-use sqlx::postgres::PgPoolOptions;
+use shared::EventPublisher;
+use shared::events::ReminderTriggered;
+use shared::metrics;
+use shared::recurrence::Schedule;
+use shared::reminders::{
+    channel_for, deferred_until, escalated_delivery, scheduled_at, LeaveInTime,
+    NotificationPreferences, SnoozeEscalation, TRAVEL_LOCATION_MAX_AGE_MINUTES,
+    TRAVEL_LOOKAHEAD_HOURS, TRAVEL_REFRESH_MINUTES,
+};
 use sqlx::PgPool;
 use std::sync::Arc;
 use tracing::{error, info, warn};
@@ -27,7 +51,10 @@ struct ScheduledEvent {
 struct EvaluatorResponse {
     reminders_evaluated: u32,
     notifications_queued: u32,
+    notifications_deferred: u32,
+    notifications_released: u32,
     reminders_rescheduled: u32,
+    travel_times_estimated: u32,
     errors: u32,
 }
 
@@ -35,49 +62,32 @@ struct AppState {
     db_pool: PgPool,
     sns_client: SnsClient,
     notification_topic_arn: Option<String>,
+    event_publisher: Option<EventPublisher>,
+    location_client: aws_sdk_location::Client,
+    /// Amazon Location route calculator; travel isn't estimated without one
+    route_calculator_name: Option<String>,
 }
 
 impl AppState {
     async fn new() -> Result<Self, Error> {
         let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
-        let secrets_client = aws_sdk_secretsmanager::Client::new(&config);
         let sns_client = SnsClient::new(&config);
 
-        let db_secret_arn =
-            std::env::var("DB_SECRET_ARN").map_err(|_| "DB_SECRET_ARN not set")?;
-
-        let db_secret = secrets_client
-            .get_secret_value()
-            .secret_id(&db_secret_arn)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to get DB secret: {}", e))?;
-
-        let db_creds: serde_json::Value =
-            serde_json::from_str(db_secret.secret_string().unwrap_or("{}"))?;
-
-        let db_host = std::env::var("DB_HOST").map_err(|_| "DB_HOST not set")?;
-        let db_name = std::env::var("DB_NAME").unwrap_or_else(|_| "second_brain".to_string());
-        let db_user = db_creds["username"].as_str().unwrap_or("sbadmin");
-        let db_pass = db_creds["password"].as_str().unwrap_or("");
-
-        let database_url = format!(
-            "postgres://{}:{}@{}:5432/{}",
-            db_user, db_pass, db_host, db_name
-        );
-
-        let db_pool = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(&database_url)
+        let db_pool = shared::db::connect(&config)
             .await
             .map_err(|e| format!("Failed to connect to database: {}", e))?;
 
         let notification_topic_arn = std::env::var("NOTIFICATION_TOPIC_ARN").ok();
 
+        let event_publisher = EventPublisher::from_env(&config);
+
         Ok(Self {
             db_pool,
             sns_client,
             notification_topic_arn,
+            event_publisher,
+            location_client: aws_sdk_location::Client::new(&config),
+            route_calculator_name: std::env::var("ROUTE_CALCULATOR_NAME").ok(),
         })
     }
 }
@@ -90,19 +100,191 @@ struct PendingReminder {
     title: String,
     description: Option<String>,
     trigger_type: String,
+    trigger_config: serde_json::Value,
     priority: i16,
+    next_trigger_at: DateTime<Utc>,
+    /// Set when the reminder is firing again after a snooze
+    snooze_until: Option<DateTime<Utc>>,
+    snooze_escalation: String,
+    escalated: bool,
 }
 
-/// User notification preferences
+/// Reminder to leave in time whose travel time is due an estimate
 #[derive(Debug, sqlx::FromRow)]
-struct UserPreferences {
-    push_enabled: bool,
-    email_enabled: bool,
-    discord_enabled: bool,
-    quiet_hours_enabled: bool,
-    quiet_hours_start: Option<chrono::NaiveTime>,
-    quiet_hours_end: Option<chrono::NaiveTime>,
-    timezone: String,
+struct TravelReminder {
+    id: Uuid,
+    user_id: Uuid,
+    trigger_config: serde_json::Value,
+    related_entity_id: Uuid,
+    /// Previous estimate, kept if a new one can't be made
+    travel_seconds: Option<i32>,
+}
+
+async fn get_travel_reminders(pool: &PgPool) -> Result<Vec<TravelReminder>, Error> {
+    let reminders: Vec<TravelReminder> = sqlx::query_as(
+        r#"
+        SELECT id, user_id, trigger_config, related_entity_id, travel_seconds
+        FROM reminders
+        WHERE status = 'active'
+          AND trigger_type = 'time'
+          AND trigger_config->>'inTimeToLeave' = 'true'
+          AND related_entity_id IS NOT NULL
+          AND snooze_until IS NULL
+          AND next_trigger_at > NOW()
+          AND next_trigger_at <= NOW() + make_interval(hours => $1)
+          AND (travel_estimated_at IS NULL
+               OR travel_estimated_at <= NOW() - make_interval(mins => $2))
+        ORDER BY next_trigger_at ASC
+        LIMIT 100
+        "#,
+    )
+    .bind(TRAVEL_LOOKAHEAD_HOURS as i32)
+    .bind(TRAVEL_REFRESH_MINUTES as i32)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to query travel reminders: {}", e))?;
+
+    Ok(reminders)
+}
+
+/// The user's latest location history ping, if recent enough to route from.
+async fn last_known_location(pool: &PgPool, user_id: Uuid) -> Result<Option<(f64, f64)>, Error> {
+    let position: Option<(f64, f64)> = sqlx::query_as(
+        r#"
+        SELECT ST_Y(location::geometry), ST_X(location::geometry)
+        FROM location_pings
+        WHERE user_id = $1
+          AND recorded_at >= NOW() - make_interval(mins => $2)
+        ORDER BY recorded_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(user_id)
+    .bind(TRAVEL_LOCATION_MAX_AGE_MINUTES as i32)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to query last known location: {}", e))?;
+
+    Ok(position)
+}
+
+/// Where an entity currently is.
+async fn entity_location(pool: &PgPool, entity_id: Uuid) -> Result<Option<(f64, f64)>, Error> {
+    let position: Option<(f64, f64)> = sqlx::query_as(
+        r#"
+        SELECT ST_Y(location::geometry), ST_X(location::geometry)
+        FROM entity_locations
+        WHERE entity_id = $1 AND valid_to IS NULL AND location IS NOT NULL
+        ORDER BY valid_from DESC NULLS LAST, created_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(entity_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to query entity location: {}", e))?;
+
+    Ok(position)
+}
+
+/// Estimated travel time in seconds from the user's last known location to
+/// the reminder's related entity, leaving now. `None` if either position is
+/// unknown or no route calculator is configured.
+async fn estimate_travel(
+    state: &AppState,
+    reminder: &TravelReminder,
+    leave: &LeaveInTime,
+) -> Result<Option<i32>, Error> {
+    let Some(calculator_name) = &state.route_calculator_name else {
+        return Ok(None);
+    };
+    let Some((from_lat, from_lon)) = last_known_location(&state.db_pool, reminder.user_id).await?
+    else {
+        return Ok(None);
+    };
+    let Some((to_lat, to_lon)) =
+        entity_location(&state.db_pool, reminder.related_entity_id).await?
+    else {
+        return Ok(None);
+    };
+
+    let output = state
+        .location_client
+        .calculate_route()
+        .calculator_name(calculator_name)
+        .set_departure_position(Some(vec![from_lon, from_lat]))
+        .set_destination_position(Some(vec![to_lon, to_lat]))
+        .travel_mode(TravelMode::from(leave.route_travel_mode().as_str()))
+        .depart_now(true)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to calculate route: {}", e))?;
+
+    Ok(output
+        .summary()
+        .map(|summary| summary.duration_seconds().round() as i32))
+}
+
+/// Move reminders to leave in time to when the user needs to leave. Returns
+/// how many travel times were estimated, and how many reminders failed.
+async fn schedule_travel_reminders(state: &AppState) -> Result<(u32, u32), Error> {
+    let reminders = get_travel_reminders(&state.db_pool).await?;
+    let mut estimated = 0u32;
+    let mut errors = 0u32;
+
+    for reminder in &reminders {
+        let (Ok(Some(leave)), Some(arrive_at)) = (
+            LeaveInTime::from_config(&reminder.trigger_config),
+            scheduled_at(&reminder.trigger_config),
+        ) else {
+            continue;
+        };
+
+        let travel_seconds = match estimate_travel(state, reminder, &leave).await {
+            Ok(Some(seconds)) => {
+                estimated += 1;
+                Some(seconds)
+            }
+            Ok(None) => reminder.travel_seconds,
+            Err(e) => {
+                warn!(reminder_id = %reminder.id, error = %e, "Failed to estimate travel time");
+                reminder.travel_seconds
+            }
+        };
+
+        // Already time to leave: fire in this run
+        let next_trigger_at = leave.leave_at(arrive_at, travel_seconds).max(Utc::now());
+
+        if let Err(e) = sqlx::query(
+            r#"
+            UPDATE reminders
+            SET next_trigger_at = $2,
+                travel_seconds = $3,
+                travel_estimated_at = NOW(),
+                updated_at = NOW()
+            WHERE id = $1 AND status = 'active' AND snooze_until IS NULL
+            "#,
+        )
+        .bind(reminder.id)
+        .bind(next_trigger_at)
+        .bind(travel_seconds)
+        .execute(&state.db_pool)
+        .await
+        {
+            error!(reminder_id = %reminder.id, error = %e, "Failed to reschedule reminder for travel");
+            errors += 1;
+            continue;
+        }
+
+        info!(
+            reminder_id = %reminder.id,
+            travel_seconds,
+            next_trigger_at = %next_trigger_at,
+            "Scheduled reminder to leave in time"
+        );
+    }
+
+    Ok((estimated, errors))
 }
 
 async fn get_pending_reminders(pool: &PgPool, limit: i32) -> Result<Vec<PendingReminder>, Error> {
@@ -114,7 +296,12 @@ async fn get_pending_reminders(pool: &PgPool, limit: i32) -> Result<Vec<PendingR
             r.title,
             r.description,
             r.trigger_type::text as trigger_type,
-            r.priority
+            r.trigger_config,
+            r.priority,
+            r.next_trigger_at,
+            r.snooze_until,
+            r.snooze_escalation,
+            r.escalated
         FROM reminders r
         WHERE r.status = 'active'
         AND r.next_trigger_at <= NOW()
@@ -134,17 +321,19 @@ async fn get_pending_reminders(pool: &PgPool, limit: i32) -> Result<Vec<PendingR
 async fn get_user_preferences(
     pool: &PgPool,
     user_id: Uuid,
-) -> Result<Option<UserPreferences>, Error> {
-    let prefs: Option<UserPreferences> = sqlx::query_as(
+) -> Result<Option<NotificationPreferences>, Error> {
+    let prefs: Option<NotificationPreferences> = sqlx::query_as(
         r#"
         SELECT
             push_enabled,
             email_enabled,
             discord_enabled,
+            alexa_enabled,
             quiet_hours_enabled,
             quiet_hours_start,
             quiet_hours_end,
-            timezone
+            timezone,
+            type_overrides
         FROM user_notification_preferences
         WHERE user_id = $1
         "#,
@@ -157,43 +346,13 @@ async fn get_user_preferences(
     Ok(prefs)
 }
 
-fn is_in_quiet_hours(prefs: &UserPreferences) -> bool {
-    if !prefs.quiet_hours_enabled {
-        return false;
-    }
-
-    let (start, end) = match (prefs.quiet_hours_start, prefs.quiet_hours_end) {
-        (Some(s), Some(e)) => (s, e),
-        _ => return false,
-    };
-
-    let now = Utc::now().time();
-
-    if start <= end {
-        now >= start && now < end
-    } else {
-        // Wrapping range (e.g., 22:00 - 07:00)
-        now >= start || now < end
-    }
-}
-
-fn get_preferred_channel(prefs: &UserPreferences) -> &str {
-    if prefs.discord_enabled {
-        "discord"
-    } else if prefs.push_enabled {
-        "push"
-    } else if prefs.email_enabled {
-        "email"
-    } else {
-        "push"
-    }
-}
-
 async fn queue_notification(
     pool: &PgPool,
     user_id: Uuid,
     reminder: &PendingReminder,
     channel: &str,
+    priority: i16,
+    deferred_until: Option<DateTime<Utc>>,
 ) -> Result<Uuid, Error> {
     let body = reminder
         .description
@@ -203,8 +362,9 @@ async fn queue_notification(
     let notification_id: Uuid = sqlx::query_scalar(
         r#"
         INSERT INTO notifications (
-            user_id, notification_type, title, body, channel, reminder_id
-        ) VALUES ($1, 'reminder', $2, $3, $4::notification_channel, $5)
+            user_id, notification_type, title, body, channel, reminder_id, priority,
+            deferred_until
+        ) VALUES ($1, 'reminder', $2, $3, $4::notification_channel, $5, $6, $7)
         RETURNING id
         "#,
     )
@@ -213,6 +373,8 @@ async fn queue_notification(
     .bind(&body)
     .bind(channel)
     .bind(reminder.id)
+    .bind(priority)
+    .bind(deferred_until)
     .fetch_one(pool)
     .await
     .map_err(|e| format!("Failed to queue notification: {}", e))?;
@@ -220,24 +382,34 @@ async fn queue_notification(
     Ok(notification_id)
 }
 
+/// Mark a reminder triggered, or reschedule it to `next_trigger_at` if it recurs.
+///
+/// The snooze is cleared; a new occurrence (not a snooze ending) also resets
+/// the snooze count and escalation.
 async fn update_reminder_status(
     pool: &PgPool,
-    reminder_id: Uuid,
-    trigger_type: &str,
+    reminder: &PendingReminder,
+    next_trigger_at: Option<DateTime<Utc>>,
 ) -> Result<bool, Error> {
-    let is_recurring = trigger_type == "recurring";
+    let is_recurring = next_trigger_at.is_some();
+    let snoozed = reminder.snooze_until.is_some();
 
     if is_recurring {
         sqlx::query(
             r#"
             UPDATE reminders
             SET last_triggered_at = NOW(),
-                next_trigger_at = calculate_next_trigger(trigger_type, trigger_config, NOW()),
+                next_trigger_at = $2,
+                snooze_until = NULL,
+                snooze_count = CASE WHEN $3 THEN snooze_count ELSE 0 END,
+                escalated = escalated AND $3,
                 updated_at = NOW()
             WHERE id = $1
             "#,
         )
-        .bind(reminder_id)
+        .bind(reminder.id)
+        .bind(next_trigger_at)
+        .bind(snoozed)
         .execute(pool)
         .await
         .map_err(|e| format!("Failed to reschedule reminder: {}", e))?;
@@ -247,11 +419,15 @@ async fn update_reminder_status(
             UPDATE reminders
             SET status = 'triggered',
                 last_triggered_at = NOW(),
+                snooze_until = NULL,
+                snooze_count = CASE WHEN $2 THEN snooze_count ELSE 0 END,
+                escalated = escalated AND $2,
                 updated_at = NOW()
             WHERE id = $1
             "#,
         )
-        .bind(reminder_id)
+        .bind(reminder.id)
+        .bind(snoozed)
         .execute(pool)
         .await
         .map_err(|e| format!("Failed to update reminder status: {}", e))?;
@@ -260,11 +436,113 @@ async fn update_reminder_status(
     Ok(is_recurring)
 }
 
-async fn publish_to_sns(state: &AppState, notification_id: Uuid, title: &str) -> Result<(), Error> {
+/// Record that the reminder fired, marking any earlier occurrence still
+/// awaiting completion as missed.
+///
+/// A reminder firing again after a snooze is the same occurrence, so the one
+/// already pending is kept.
+async fn record_occurrence(pool: &PgPool, reminder: &PendingReminder) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        WITH missed AS (
+            UPDATE reminder_occurrences
+            SET outcome = 'missed'
+            WHERE reminder_id = $1 AND outcome = 'pending' AND NOT $4
+        )
+        INSERT INTO reminder_occurrences (reminder_id, user_id, due_at)
+        SELECT $1, $2, $3
+        WHERE NOT ($4 AND EXISTS (
+            SELECT 1 FROM reminder_occurrences
+            WHERE reminder_id = $1 AND outcome = 'pending'
+        ))
+        "#,
+    )
+    .bind(reminder.id)
+    .bind(reminder.user_id)
+    .bind(reminder.next_trigger_at)
+    .bind(reminder.snooze_until.is_some())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to record occurrence: {}", e))?;
+
+    Ok(())
+}
+
+/// Deferred notification whose quiet hours have ended
+#[derive(Debug, sqlx::FromRow)]
+struct ReleasedNotification {
+    id: Uuid,
+    notification_type: String,
+    title: String,
+}
+
+/// Publish pending notifications whose quiet hours have ended, returning how
+/// many were sent.
+///
+/// Each notification's `deferred_until` is only cleared once SNS has taken
+/// it, so one that fails to publish stays deferred and is retried on the next
+/// run. The rows stay locked until then, so overlapping runs can't publish
+/// them twice.
+async fn release_deferred(state: &AppState) -> Result<u32, Error> {
+    let mut tx = state
+        .db_pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let due: Vec<ReleasedNotification> = sqlx::query_as(
+        r#"
+        SELECT id, notification_type::text, title
+        FROM notifications
+        WHERE status = 'pending' AND deferred_until <= NOW()
+        FOR UPDATE SKIP LOCKED
+        "#,
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to fetch deferred notifications: {}", e))?;
+
+    let mut released = 0u32;
+    for notification in &due {
+        if let Err(e) = publish_to_sns(
+            state,
+            notification.id,
+            &notification.notification_type,
+            &notification.title,
+        )
+        .await
+        {
+            warn!(notification_id = %notification.id, error = %e, "Failed to publish to SNS");
+            continue;
+        }
+
+        sqlx::query(
+            "UPDATE notifications SET deferred_until = NULL, updated_at = NOW() WHERE id = $1",
+        )
+        .bind(notification.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to release deferred notification: {}", e))?;
+        released += 1;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to release deferred notifications: {}", e))?;
+
+    Ok(released)
+}
+
+async fn publish_to_sns(
+    state: &AppState,
+    notification_id: Uuid,
+    notification_type: &str,
+    title: &str,
+) -> Result<(), Error> {
     if let Some(topic_arn) = &state.notification_topic_arn {
         let message = serde_json::json!({
             "notification_id": notification_id.to_string(),
-            "type": "reminder",
+            "type": notification_type,
             "title": title,
         });
 
@@ -287,26 +565,43 @@ async fn handler(
 ) -> Result<EvaluatorResponse, Error> {
     info!("Starting reminder evaluation");
 
+    let mut notifications_released = 0u32;
+    let mut errors = 0u32;
+
+    // Send notifications held through quiet hours that have now ended
+    match release_deferred(&state).await {
+        Ok(released) => notifications_released = released,
+        Err(e) => {
+            error!(error = %e, "Failed to release deferred notifications");
+            errors += 1;
+        }
+    }
+
+    // Reminders to leave in time may now be due
+    let mut travel_times_estimated = 0u32;
+    match schedule_travel_reminders(&state).await {
+        Ok((estimated, failed)) => {
+            travel_times_estimated = estimated;
+            errors += failed;
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to schedule reminders to leave in time");
+            errors += 1;
+        }
+    }
+
     let reminders = get_pending_reminders(&state.db_pool, 100).await?;
 
     info!(reminders_found = reminders.len(), "Found pending reminders");
 
     let mut notifications_queued = 0u32;
+    let mut notifications_deferred = 0u32;
     let mut reminders_rescheduled = 0u32;
-    let mut errors = 0u32;
 
     for reminder in &reminders {
         let prefs = match get_user_preferences(&state.db_pool, reminder.user_id).await {
             Ok(Some(p)) => p,
-            Ok(None) => UserPreferences {
-                push_enabled: true,
-                email_enabled: true,
-                discord_enabled: false,
-                quiet_hours_enabled: false,
-                quiet_hours_start: None,
-                quiet_hours_end: None,
-                timezone: "America/New_York".to_string(),
-            },
+            Ok(None) => NotificationPreferences::default(),
             Err(e) => {
                 error!(reminder_id = %reminder.id, error = %e, "Failed to get user preferences");
                 errors += 1;
@@ -314,27 +609,78 @@ async fn handler(
             }
         };
 
-        if is_in_quiet_hours(&prefs) {
-            info!(reminder_id = %reminder.id, "Skipping notification during quiet hours");
-            continue;
+        let held_until = deferred_until(&prefs, "reminder", Utc::now());
+
+        // Reminders snoozed past their limit are escalated; otherwise the
+        // user may have turned reminder notifications off
+        let delivery = match SnoozeEscalation::parse(&reminder.snooze_escalation)
+            .filter(|_| reminder.escalated)
+        {
+            Some(escalation) => Some(escalated_delivery(escalation, &prefs, reminder.priority)),
+            None => channel_for(&prefs, "reminder").map(|channel| (channel, reminder.priority)),
+        };
+        match delivery {
+            None => {
+                info!(reminder_id = %reminder.id, "Reminder notifications are turned off; not notifying");
+            }
+            Some((channel, priority)) => match queue_notification(
+                &state.db_pool,
+                reminder.user_id,
+                reminder,
+                channel,
+                priority,
+                held_until,
+            )
+            .await
+            {
+                Ok(notification_id) => {
+                    notifications_queued += 1;
+                    if let Some(until) = held_until {
+                        info!(
+                            reminder_id = %reminder.id,
+                            deferred_until = %until,
+                            "Deferred notification until quiet hours end"
+                        );
+                        notifications_deferred += 1;
+                    } else if let Err(e) =
+                        publish_to_sns(&state, notification_id, "reminder", &reminder.title).await
+                    {
+                        warn!(notification_id = %notification_id, error = %e, "Failed to publish to SNS");
+                    }
+                    if let Some(publisher) = &state.event_publisher {
+                        let event = ReminderTriggered {
+                            reminder_id: reminder.id,
+                            user_id: reminder.user_id,
+                            trigger_type: reminder.trigger_type.clone(),
+                            notification_id,
+                            channel: channel.to_string(),
+                        };
+                        if let Err(e) = publisher.publish(&event).await {
+                            warn!(reminder_id = %reminder.id, error = %e, "Failed to publish ReminderTriggered");
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!(reminder_id = %reminder.id, error = %e, "Failed to queue notification");
+                    errors += 1;
+                    continue;
+                }
+            },
         }
 
-        let channel = get_preferred_channel(&prefs);
-        match queue_notification(&state.db_pool, reminder.user_id, reminder, channel).await {
-            Ok(notification_id) => {
-                notifications_queued += 1;
-                if let Err(e) = publish_to_sns(&state, notification_id, &reminder.title).await {
-                    warn!(notification_id = %notification_id, error = %e, "Failed to publish to SNS");
+        let next_trigger_at = if reminder.trigger_type == "recurring" {
+            match Schedule::from_config(&reminder.trigger_config, prefs.tz()) {
+                Ok(schedule) => schedule.next_after(Utc::now()),
+                Err(e) => {
+                    warn!(reminder_id = %reminder.id, error = %e, "Invalid recurrence; not rescheduling");
+                    None
                 }
             }
-            Err(e) => {
-                error!(reminder_id = %reminder.id, error = %e, "Failed to queue notification");
-                errors += 1;
-                continue;
-            }
-        }
+        } else {
+            None
+        };
 
-        match update_reminder_status(&state.db_pool, reminder.id, &reminder.trigger_type).await {
+        match update_reminder_status(&state.db_pool, reminder, next_trigger_at).await {
             Ok(was_rescheduled) => {
                 if was_rescheduled {
                     reminders_rescheduled += 1;
@@ -345,18 +691,28 @@ async fn handler(
                 errors += 1;
             }
         }
+
+        if let Err(e) = record_occurrence(&state.db_pool, reminder).await {
+            warn!(reminder_id = %reminder.id, error = %e, "Failed to record reminder occurrence");
+        }
     }
 
     let response = EvaluatorResponse {
         reminders_evaluated: reminders.len() as u32,
         notifications_queued,
+        notifications_deferred,
+        notifications_released,
         reminders_rescheduled,
+        travel_times_estimated,
         errors,
     };
 
     info!(
         reminders_evaluated = response.reminders_evaluated,
         notifications_queued = response.notifications_queued,
+        notifications_deferred = response.notifications_deferred,
+        notifications_released = response.notifications_released,
+        travel_times_estimated = response.travel_times_estimated,
         "Reminder evaluation complete"
     );
 
@@ -375,7 +731,7 @@ async fn main() -> Result<(), Error> {
 
     run(service_fn(move |event| {
         let state = state_clone.clone();
-        async move { handler(state, event).await }
+        async move { metrics::track_invocation("reminder_evaluator", handler(state, event)).await }
     }))
     .await
 }
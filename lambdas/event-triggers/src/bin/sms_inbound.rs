@@ -0,0 +1,240 @@
+//! SMS Inbound Lambda - Answers and saves texts sent to the two-way SMS number.
+//!
+//! This Lambda is triggered by SNS and:
+//! 1. Receives an inbound text published by Pinpoint two-way SMS
+//! 2. Maps the sender's number to a user who verified it (see `/sms/phone`)
+//! 3. Routes `remember ...` to ingestion and anything else to the query agent
+//! 4. Replies by SMS from the number the text was sent to
+//!
+//! Texts from unregistered numbers get a reply explaining how to register.
+//! STOP/HELP keywords are handled by Pinpoint before they reach this Lambda.
+
+use chrono::Utc;
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use shared::metrics;
+use shared::sms::{parse_message, send_sms, to_sms_text, SmsCommand, HELP_TEXT};
+use shared::{AgentClient, AuthenticatedUser, AuthorizedUser};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// Reply to numbers that aren't registered to a user
+const UNREGISTERED_MESSAGE: &str = "This number isn't registered with Second Brain. \
+Add it in the app under Settings > SMS, then text again.";
+
+/// Reply when the agent fails
+const AGENT_ERROR_MESSAGE: &str = "Sorry, I couldn't process that. Please try again.";
+
+/// SNS Event wrapper
+#[derive(Debug, Deserialize)]
+struct SnsEvent {
+    #[serde(rename = "Records")]
+    records: Vec<SnsRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SnsRecord {
+    #[serde(rename = "Sns")]
+    sns: SnsMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct SnsMessage {
+    #[serde(rename = "Message")]
+    message: String,
+}
+
+/// Inbound text published by Pinpoint two-way SMS
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InboundSms {
+    /// Sender's number
+    origination_number: String,
+    /// Our two-way number the text was sent to
+    destination_number: String,
+    message_body: String,
+    #[serde(default)]
+    inbound_message_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct InboundResponse {
+    replied: u32,
+    errors: u32,
+}
+
+struct AppState {
+    db_pool: PgPool,
+    sns_client: aws_sdk_sns::Client,
+    agent_client: AgentClient,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        let agent_function_name = std::env::var("AGENT_FUNCTION_NAME")
+            .unwrap_or_else(|_| "second-brain-agents".to_string());
+
+        // Answers are logged for GET /v1/queries/{id}
+        let agent_client =
+            AgentClient::new(aws_sdk_lambda::Client::new(&config), agent_function_name)
+                .with_query_log(db_pool.clone());
+
+        Ok(Self {
+            db_pool,
+            sns_client: aws_sdk_sns::Client::new(&config),
+            agent_client,
+        })
+    }
+}
+
+/// Resolve the user who verified a phone number.
+async fn resolve_phone_user(
+    state: &AppState,
+    phone_number: &str,
+) -> Result<Option<AuthorizedUser>, Error> {
+    let cognito_sub: Option<String> = sqlx::query_scalar(
+        "SELECT cognito_sub FROM users WHERE phone_number = $1 AND phone_verified_at IS NOT NULL",
+    )
+    .bind(phone_number)
+    .fetch_optional(&state.db_pool)
+    .await
+    .map_err(|e| format!("Failed to lookup user: {}", e))?;
+
+    let cognito_sub = match cognito_sub {
+        Some(sub) => sub,
+        None => return Ok(None),
+    };
+
+    match AuthorizedUser::resolve(
+        AuthenticatedUser {
+            user_id: cognito_sub,
+            email: None,
+            family_ids: Vec::new(),
+        },
+        &state.db_pool,
+    )
+    .await
+    {
+        Ok(user) => Ok(Some(user)),
+        Err(shared::Error::Auth(_)) => Ok(None),
+        Err(e) => Err(format!("Failed to lookup user: {}", e).into()),
+    }
+}
+
+/// Work out the reply to an inbound text.
+async fn respond(state: &AppState, sms: &InboundSms) -> Result<String, Error> {
+    let user = match resolve_phone_user(state, &sms.origination_number).await? {
+        Some(user) => user,
+        None => {
+            info!("Text from unregistered number");
+            return Ok(UNREGISTERED_MESSAGE.to_string());
+        }
+    };
+
+    let agent_user_id = user.user_id.to_string();
+    let family_ids: Vec<String> = user.family_ids.iter().map(Uuid::to_string).collect();
+
+    let reply = match parse_message(&sms.message_body) {
+        SmsCommand::Help => return Ok(HELP_TEXT.to_string()),
+        SmsCommand::Remember(fact) => {
+            info!(user_id = %user.user_id, "Ingesting text");
+            state
+                .agent_client
+                .ingest(fact, &agent_user_id, family_ids, "sms")
+                .await
+        }
+        SmsCommand::Ask(question) => {
+            info!(user_id = %user.user_id, "Answering text");
+            // Follow-up questions share context for the day
+            let conversation_id = format!("sms-{}-{}", user.user_id, Utc::now().format("%Y-%m-%d"));
+            state
+                .agent_client
+                .query(
+                    question,
+                    &agent_user_id,
+                    family_ids,
+                    Some(conversation_id),
+                    "sms",
+                )
+                .await
+        }
+    };
+
+    Ok(match reply {
+        Ok(resp) => to_sms_text(&resp.response),
+        Err(e) => {
+            error!(error = %e, "Agent error");
+            AGENT_ERROR_MESSAGE.to_string()
+        }
+    })
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    event: LambdaEvent<SnsEvent>,
+) -> Result<InboundResponse, Error> {
+    let mut replied = 0u32;
+    let mut errors = 0u32;
+
+    for record in &event.payload.records {
+        let sms: InboundSms = match serde_json::from_str(&record.sns.message) {
+            Ok(sms) => sms,
+            Err(e) => {
+                error!(error = %e, "Failed to parse inbound SMS");
+                errors += 1;
+                continue;
+            }
+        };
+
+        info!(inbound_message_id = ?sms.inbound_message_id, "Processing inbound SMS");
+
+        let reply = respond(&state, &sms).await?;
+        if reply.is_empty() {
+            warn!("Empty reply; nothing sent");
+            continue;
+        }
+
+        match send_sms(
+            &state.sns_client,
+            &sms.origination_number,
+            Some(&sms.destination_number),
+            &reply,
+        )
+        .await
+        {
+            Ok(()) => replied += 1,
+            Err(e) => {
+                error!(error = %e, "Failed to send SMS reply");
+                errors += 1;
+            }
+        }
+    }
+
+    Ok(InboundResponse { replied, errors })
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let state_clone = state.clone();
+
+    run(service_fn(move |event| {
+        let state = state_clone.clone();
+        async move { metrics::track_invocation("sms_inbound", handler(state, event)).await }
+    }))
+    .await
+}
@@ -0,0 +1,270 @@
+//! Tag Rule Backfill Lambda - Applies auto-tagging rules to existing facts.
+//!
+//! Runs every 15 minutes via EventBridge. For each user with enabled rules it
+//! pages through the facts they created after each rule's `applied_through`
+//! watermark and tags the ones that match (see `shared::tag_rules`), then
+//! advances the watermark. A new rule has no watermark, so its first run
+//! backfills the user's whole history; later runs pick up facts from any
+//! ingest path that didn't evaluate rules itself.
+//!
+//! Can also be invoked directly with `{"user_id": ..., "rule_id": ..., "full": true}`
+//! to re-run one user's (or one rule's) rules over all their facts.
+
+use chrono::{DateTime, Duration, Utc};
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use shared::metrics;
+use shared::tag_rules::{apply_rule, facts_page, user_rules, RuleFact};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::{error, info};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// Facts evaluated per page
+const PAGE_SIZE: i64 = 500;
+
+/// Facts created this recently are left for the next run, so a slow
+/// transaction committing an older `created_at` isn't skipped
+const SETTLE_MINUTES: i64 = 5;
+
+/// Stop starting new users after this long; the rest continue next run
+const TIME_BUDGET_SECS: u64 = 240;
+
+#[derive(Debug, Default, Deserialize)]
+struct BackfillEvent {
+    #[serde(default)]
+    detail_type: String,
+    user_id: Option<Uuid>,
+    rule_id: Option<Uuid>,
+    /// Ignore watermarks and evaluate every fact
+    #[serde(default)]
+    full: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct BackfillResponse {
+    users_processed: u32,
+    users_deferred: u32,
+    facts_evaluated: u64,
+    tags_applied: u64,
+    errors: u32,
+}
+
+#[derive(Debug, Default)]
+struct UserBackfill {
+    facts_evaluated: u64,
+    tags_applied: u64,
+}
+
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+/// Users with enabled rules, least recently applied first.
+async fn get_rule_users(pool: &PgPool, user_id: Option<Uuid>) -> Result<Vec<Uuid>, Error> {
+    let users: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        SELECT user_id FROM tag_rules
+        WHERE enabled
+        AND ($1::uuid IS NULL OR user_id = $1)
+        GROUP BY user_id
+        ORDER BY MIN(COALESCE(applied_through, '-infinity'::timestamptz))
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to query rule users: {}", e))?;
+
+    Ok(users)
+}
+
+/// `applied_through` of a user's enabled rules.
+async fn get_watermarks(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<HashMap<Uuid, Option<DateTime<Utc>>>, Error> {
+    let rows: Vec<(Uuid, Option<DateTime<Utc>>)> =
+        sqlx::query_as("SELECT id, applied_through FROM tag_rules WHERE user_id = $1 AND enabled")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| format!("Failed to query rule watermarks: {}", e))?;
+
+    Ok(rows.into_iter().collect())
+}
+
+async fn backfill_user(
+    pool: &PgPool,
+    user_id: Uuid,
+    event: &BackfillEvent,
+    until: DateTime<Utc>,
+) -> Result<UserBackfill, Error> {
+    let watermarks = get_watermarks(pool, user_id).await?;
+    let rules: Vec<_> = user_rules(pool, user_id)
+        .await
+        .map_err(|e| format!("Failed to load rules: {}", e))?
+        .into_iter()
+        .filter(|r| event.rule_id.is_none() || event.rule_id == Some(r.id))
+        .filter_map(|r| {
+            let watermark = if event.full {
+                None
+            } else {
+                *watermarks.get(&r.id)?
+            };
+            Some((r, watermark))
+        })
+        .collect();
+
+    let mut backfill = UserBackfill::default();
+    if rules.is_empty() {
+        return Ok(backfill);
+    }
+
+    // Start from the oldest watermark; rules skip facts they've already seen
+    let start = rules
+        .iter()
+        .map(|(_, watermark)| *watermark)
+        .min()
+        .flatten();
+    let mut after: Option<(DateTime<Utc>, Uuid)> = start.map(|at| (at, Uuid::from_u128(u128::MAX)));
+
+    loop {
+        let facts = facts_page(pool, user_id, after, until, PAGE_SIZE)
+            .await
+            .map_err(|e| format!("Failed to load facts: {}", e))?;
+        let Some(last) = facts.last() else {
+            break;
+        };
+        after = Some((last.created_at, last.id));
+        backfill.facts_evaluated += facts.len() as u64;
+
+        for (rule, watermark) in &rules {
+            let unseen: Vec<RuleFact> = match watermark {
+                Some(watermark) => facts
+                    .iter()
+                    .filter(|f| f.created_at > *watermark)
+                    .cloned()
+                    .collect(),
+                None => facts.clone(),
+            };
+            backfill.tags_applied += apply_rule(pool, rule, &unseen)
+                .await
+                .map_err(|e| format!("Failed to apply rule {}: {}", rule.id, e))?;
+        }
+
+        if (facts.len() as i64) < PAGE_SIZE {
+            break;
+        }
+    }
+
+    let rule_ids: Vec<Uuid> = rules.iter().map(|(rule, _)| rule.id).collect();
+    sqlx::query(
+        r#"
+        UPDATE tag_rules
+        SET applied_through = GREATEST(COALESCE(applied_through, $2), $2)
+        WHERE id = ANY($1)
+        "#,
+    )
+    .bind(&rule_ids)
+    .bind(until)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to advance watermarks: {}", e))?;
+
+    Ok(backfill)
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    event: LambdaEvent<BackfillEvent>,
+) -> Result<BackfillResponse, Error> {
+    let event = event.payload;
+    info!(
+        detail_type = %event.detail_type,
+        full = event.full,
+        "Starting tag rule backfill"
+    );
+
+    let started = Instant::now();
+    let until = Utc::now() - Duration::minutes(SETTLE_MINUTES);
+    let users = get_rule_users(&state.db_pool, event.user_id).await?;
+
+    let mut response = BackfillResponse {
+        users_processed: 0,
+        users_deferred: 0,
+        facts_evaluated: 0,
+        tags_applied: 0,
+        errors: 0,
+    };
+
+    for user_id in &users {
+        if started.elapsed().as_secs() >= TIME_BUDGET_SECS {
+            response.users_deferred += 1;
+            continue;
+        }
+
+        match backfill_user(&state.db_pool, *user_id, &event, until).await {
+            Ok(backfill) => {
+                if backfill.tags_applied > 0 {
+                    info!(
+                        user_id = %user_id,
+                        facts_evaluated = backfill.facts_evaluated,
+                        tags_applied = backfill.tags_applied,
+                        "Applied tag rules"
+                    );
+                }
+                response.users_processed += 1;
+                response.facts_evaluated += backfill.facts_evaluated;
+                response.tags_applied += backfill.tags_applied;
+            }
+            Err(e) => {
+                error!(user_id = %user_id, error = %e, "Failed to apply tag rules");
+                response.errors += 1;
+            }
+        }
+    }
+
+    info!(
+        users_processed = response.users_processed,
+        users_deferred = response.users_deferred,
+        facts_evaluated = response.facts_evaluated,
+        tags_applied = response.tags_applied,
+        errors = response.errors,
+        "Tag rule backfill complete"
+    );
+
+    Ok(response)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let state_clone = state.clone();
+
+    run(service_fn(move |event| {
+        let state = state_clone.clone();
+        async move { metrics::track_invocation("tag_rule_backfill", handler(state, event)).await }
+    }))
+    .await
+}
@@ -0,0 +1,87 @@
+//! Trash Purge Lambda - Permanently removes items deleted long ago.
+//!
+//! Runs daily via EventBridge. Facts, entities and tags deleted more than
+//! `shared::trash::RETENTION_DAYS` ago are removed for good (see
+//! `shared::trash::purge_expired`); until then they can be restored from
+//! `/trash`.
+
+use chrono::{Duration, Utc};
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use shared::metrics;
+use shared::trash::{purge_expired, RETENTION_DAYS};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+
+#[derive(Debug, Default, Deserialize)]
+struct PurgeEvent {
+    #[serde(default)]
+    detail_type: String,
+}
+
+#[derive(Debug, Serialize)]
+struct PurgeResponse {
+    facts_purged: u64,
+    entities_purged: u64,
+    tags_purged: u64,
+}
+
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    event: LambdaEvent<PurgeEvent>,
+) -> Result<PurgeResponse, Error> {
+    info!(detail_type = %event.payload.detail_type, "Starting trash purge");
+
+    let cutoff = Utc::now() - Duration::days(RETENTION_DAYS);
+    let purged = purge_expired(&state.db_pool, cutoff)
+        .await
+        .map_err(|e| format!("Failed to purge trash: {}", e))?;
+
+    info!(
+        facts = purged.facts,
+        entities = purged.entities,
+        tags = purged.tags,
+        "Trash purge complete"
+    );
+
+    Ok(PurgeResponse {
+        facts_purged: purged.facts,
+        entities_purged: purged.entities,
+        tags_purged: purged.tags,
+    })
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let state_clone = state.clone();
+
+    run(service_fn(move |event| {
+        let state = state_clone.clone();
+        async move { metrics::track_invocation("trash_purge", handler(state, event)).await }
+    }))
+    .await
+}
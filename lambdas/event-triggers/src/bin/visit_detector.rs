@@ -0,0 +1,315 @@
+//! Visit Detector Lambda - Turns location pings into visits.
+//!
+//! Runs every 15 minutes via EventBridge. For each user with location history
+//! turned on, clusters their unprocessed pings into visits (see
+//! `shared::location_history::detect_visits`), links each visit to the
+//! nearest place entity they can see, and records a fact for visits to
+//! places ("Visited Lake Tahoe from 14:05 to 16:40") unless they've turned
+//! visit facts off. Pings that may belong to a stay still going on are left
+//! for the next run.
+//!
+//! Each run first deletes pings and visits past their owner's retention
+//! period, and the pings of users who have turned location history off.
+
+use chrono::Utc;
+use chrono_tz::Tz;
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use shared::access::space_clause;
+use shared::location_history::{
+    detect_visits, purge_expired, visit_description, DetectedVisit, Ping, VISIT_PLACE_METERS,
+};
+use shared::metrics;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{error, info};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// Importance of visit facts; most visits are minor memories
+const VISIT_IMPORTANCE: i16 = 2;
+
+/// Most pings clustered per user per run; the rest wait for the next run
+const MAX_PINGS_PER_RUN: i64 = 5000;
+
+#[derive(Debug, Default, Deserialize)]
+struct DetectEvent {
+    #[serde(default)]
+    detail_type: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct DetectResponse {
+    users_processed: u32,
+    visits_recorded: u32,
+    facts_recorded: u32,
+    pings_purged: u64,
+    visits_purged: u64,
+    errors: u32,
+}
+
+/// A user with pings to process
+#[derive(Debug, sqlx::FromRow)]
+struct PendingUser {
+    user_id: Uuid,
+    family_ids: Vec<Uuid>,
+    record_visit_facts: bool,
+    timezone: String,
+}
+
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+/// Users with location history on and unprocessed pings (timezones default
+/// like `user_notification_preferences`).
+async fn pending_users(pool: &PgPool) -> Result<Vec<PendingUser>, sqlx::Error> {
+    sqlx::query_as(
+        r#"
+        SELECT
+            s.user_id,
+            ARRAY(SELECT fm.family_id FROM family_members fm WHERE fm.user_id = s.user_id) AS family_ids,
+            s.record_visit_facts,
+            COALESCE(unp.timezone, 'America/New_York') AS timezone
+        FROM location_history_settings s
+        LEFT JOIN user_notification_preferences unp ON unp.user_id = s.user_id
+        WHERE s.enabled
+          AND EXISTS (
+              SELECT 1 FROM location_pings p
+              WHERE p.user_id = s.user_id AND p.processed_at IS NULL
+          )
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Nearest place entity to a visit that the user can see, with its name.
+async fn nearest_place(
+    pool: &PgPool,
+    user: &PendingUser,
+    visit: &DetectedVisit,
+) -> Result<Option<(Uuid, String)>, sqlx::Error> {
+    sqlx::query_as(&format!(
+        r#"
+        SELECT e.id, e.name
+        FROM entities e
+        JOIN entity_locations l ON l.entity_id = e.id
+        WHERE e.entity_type = 'place'
+          AND e.deleted_at IS NULL
+          AND ((e.owner_type = 'user' AND e.owner_id = $1)
+               OR (e.owner_type = 'family' AND e.owner_id = ANY($2) AND {}))
+          AND l.valid_to IS NULL
+          AND ST_DWithin(l.location, ST_SetSRID(ST_MakePoint($3, $4), 4326)::geography, $5)
+        ORDER BY ST_Distance(l.location, ST_SetSRID(ST_MakePoint($3, $4), 4326)::geography)
+        LIMIT 1
+        "#,
+        space_clause("e", 1)
+    ))
+    .bind(user.user_id)
+    .bind(&user.family_ids)
+    .bind(visit.longitude)
+    .bind(visit.latitude)
+    .bind(VISIT_PLACE_METERS)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Cluster one user's pings and record the visits. Returns the visits and
+/// facts recorded.
+async fn process_user(state: &AppState, user: &PendingUser) -> Result<(u32, u32), Error> {
+    let pings: Vec<Ping> = sqlx::query_as(
+        r#"
+        SELECT ST_Y(location::geometry) AS latitude,
+               ST_X(location::geometry) AS longitude,
+               recorded_at
+        FROM location_pings
+        WHERE user_id = $1 AND processed_at IS NULL
+        ORDER BY recorded_at
+        LIMIT $2
+        "#,
+    )
+    .bind(user.user_id)
+    .bind(MAX_PINGS_PER_RUN)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let Some(last_loaded) = pings.last().map(|p| p.recorded_at) else {
+        return Ok((0, 0));
+    };
+
+    // Later pings weren't loaded, so the last stay may continue into them
+    let now = if pings.len() as i64 == MAX_PINGS_PER_RUN {
+        last_loaded
+    } else {
+        Utc::now()
+    };
+    let detection = detect_visits(&pings, now);
+    let tz: Tz = user
+        .timezone
+        .parse()
+        .unwrap_or(chrono_tz::America::New_York);
+
+    let mut tx = state.db_pool.begin().await?;
+    let mut facts = 0;
+
+    for visit in &detection.visits {
+        let place = nearest_place(&state.db_pool, user, visit).await?;
+
+        let fact_id: Option<Uuid> = match (&place, user.record_visit_facts) {
+            (Some((place_id, place_name)), true) => {
+                let arrived_at = visit.arrived_at.with_timezone(&tz);
+                let content =
+                    visit_description(place_name, arrived_at, visit.departed_at.with_timezone(&tz));
+
+                let fact_id: Uuid = sqlx::query_scalar(
+                    r#"
+                    INSERT INTO facts (
+                        owner_type, owner_id, created_by, content, source,
+                        importance, about_entity_id, valid_from
+                    )
+                    VALUES ('user', $1, $1, $2, 'inferred', $3, $4, $5)
+                    RETURNING id
+                    "#,
+                )
+                .bind(user.user_id)
+                .bind(&content)
+                .bind(VISIT_IMPORTANCE)
+                .bind(place_id)
+                .bind(arrived_at.date_naive())
+                .fetch_one(&mut *tx)
+                .await?;
+
+                sqlx::query(
+                    r#"
+                    INSERT INTO entity_mentions (fact_id, entity_id, role, confidence)
+                    VALUES ($1, $2, 'location', 1.0)
+                    ON CONFLICT DO NOTHING
+                    "#,
+                )
+                .bind(fact_id)
+                .bind(place_id)
+                .execute(&mut *tx)
+                .await?;
+
+                facts += 1;
+                Some(fact_id)
+            }
+            _ => None,
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO location_visits (user_id, location, place_entity_id,
+                                         arrived_at, departed_at, ping_count, fact_id)
+            VALUES ($1, ST_SetSRID(ST_MakePoint($2, $3), 4326)::geography, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(user.user_id)
+        .bind(visit.longitude)
+        .bind(visit.latitude)
+        .bind(place.as_ref().map(|(id, _)| *id))
+        .bind(visit.arrived_at)
+        .bind(visit.departed_at)
+        .bind(visit.ping_count)
+        .bind(fact_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    // Pings uploaded since they were loaded, or still in an open stay, wait
+    sqlx::query(
+        r#"
+        UPDATE location_pings SET processed_at = NOW()
+        WHERE user_id = $1
+          AND processed_at IS NULL
+          AND recorded_at <= $2
+          AND ($3::timestamptz IS NULL OR recorded_at < $3)
+        "#,
+    )
+    .bind(user.user_id)
+    .bind(last_loaded)
+    .bind(detection.open_from)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((detection.visits.len() as u32, facts))
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    event: LambdaEvent<DetectEvent>,
+) -> Result<DetectResponse, Error> {
+    info!(detail_type = %event.payload.detail_type, "Starting visit detection");
+
+    let (pings_purged, visits_purged) = purge_expired(&state.db_pool)
+        .await
+        .map_err(|e| format!("Failed to purge location history: {}", e))?;
+
+    let users = pending_users(&state.db_pool)
+        .await
+        .map_err(|e| format!("Failed to query users: {}", e))?;
+
+    let mut response = DetectResponse {
+        pings_purged,
+        visits_purged,
+        ..Default::default()
+    };
+
+    for user in &users {
+        match process_user(&state, user).await {
+            Ok((visits, facts)) => {
+                response.users_processed += 1;
+                response.visits_recorded += visits;
+                response.facts_recorded += facts;
+            }
+            Err(e) => {
+                error!(user_id = %user.user_id, error = %e, "Failed to detect visits");
+                response.errors += 1;
+            }
+        }
+    }
+
+    info!(
+        users = response.users_processed,
+        visits = response.visits_recorded,
+        facts = response.facts_recorded,
+        pings_purged,
+        visits_purged,
+        errors = response.errors,
+        "Visit detection complete"
+    );
+
+    Ok(response)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let state_clone = state.clone();
+
+    run(service_fn(move |event| {
+        let state = state_clone.clone();
+        async move { metrics::track_invocation("visit_detector", handler(state, event)).await }
+    }))
+    .await
+}
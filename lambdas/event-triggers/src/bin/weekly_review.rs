@@ -0,0 +1,494 @@
+//! Weekly Review Lambda - Emails each user a summary of their week.
+//!
+//! This Lambda runs hourly via EventBridge and:
+//! 1. Finds users whose weekly review is due in their timezone (their review
+//!    day, from the evening briefing time on)
+//! 2. Gathers the facts they added, entities they created and reminders they
+//!    completed over the past seven days
+//! 3. Asks the agent for a short narrative of the week
+//! 4. Emails the review as HTML via SES, with links back to the app
+//!
+//! Each week is claimed in `weekly_reviews` before sending, so re-runs never
+//! send it twice. Weeks with nothing to report are skipped.
+
+use aws_sdk_ses::types::{Body, Content, Destination, Message};
+use aws_sdk_ses::Client as SesClient;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use shared::metrics;
+use shared::weekly_review::{
+    due_week_ending, narrative_prompt, render_html, render_text, subject, ReviewItem,
+    ReviewPreferences, WeekSummary, MAX_REVIEW_ITEMS, REVIEW_DAYS,
+};
+use shared::{AgentClient, AgentRequest};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+struct ScheduledEvent {
+    #[serde(default)]
+    detail_type: String,
+}
+
+/// Reviews prepared concurrently (bounded by the DB pool size)
+const MAX_CONCURRENT_REVIEWS: usize = 5;
+
+#[derive(Debug, Serialize)]
+struct ReviewResponse {
+    users_checked: u32,
+    reviews_sent: u32,
+    already_sent: u32,
+    empty_weeks: u32,
+    errors: u32,
+}
+
+/// Outcome of preparing one user's review
+enum ReviewOutcome {
+    Sent,
+    AlreadySent,
+    Empty,
+}
+
+/// User with weekly reviews enabled
+#[derive(Debug, sqlx::FromRow)]
+struct ReviewUser {
+    user_id: Uuid,
+    email: String,
+    family_ids: Vec<Uuid>,
+    #[sqlx(flatten)]
+    prefs: ReviewPreferences,
+}
+
+struct AppState {
+    db_pool: PgPool,
+    agent_client: AgentClient,
+    ses_client: SesClient,
+    from_email: String,
+    app_url: String,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        let lambda_client = aws_sdk_lambda::Client::new(&config);
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        let agent_function_name = std::env::var("AGENT_FUNCTION_NAME")
+            .unwrap_or_else(|_| "second-brain-agents".to_string());
+
+        Ok(Self {
+            db_pool,
+            agent_client: AgentClient::new(lambda_client, agent_function_name),
+            ses_client: SesClient::new(&config),
+            from_email: std::env::var("FROM_EMAIL")
+                .unwrap_or_else(|_| "noreply@secondbrain.app".to_string()),
+            app_url: std::env::var("APP_URL")
+                .unwrap_or_else(|_| "https://secondbrain.app".to_string()),
+        })
+    }
+}
+
+/// Users who get weekly reviews by email (preferences default like the
+/// table's columns).
+async fn get_review_users(pool: &PgPool) -> Result<Vec<ReviewUser>, Error> {
+    let users: Vec<ReviewUser> = sqlx::query_as(
+        r#"
+        SELECT
+            u.id AS user_id,
+            u.email,
+            ARRAY(SELECT fm.family_id FROM family_members fm WHERE fm.user_id = u.id) AS family_ids,
+            COALESCE(unp.weekly_review_day, 0::smallint) AS weekly_review_day,
+            COALESCE(unp.evening_briefing_time, '18:00'::time) AS evening_briefing_time,
+            COALESCE(unp.timezone, 'America/New_York') AS timezone
+        FROM users u
+        LEFT JOIN user_notification_preferences unp ON unp.user_id = u.id
+        WHERE COALESCE(unp.weekly_review_enabled, true)
+          AND COALESCE(unp.email_enabled, true)
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to query users: {}", e))?;
+
+    Ok(users)
+}
+
+async fn review_exists(
+    pool: &PgPool,
+    user_id: Uuid,
+    week_ending: NaiveDate,
+) -> Result<bool, Error> {
+    let exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM weekly_reviews WHERE user_id = $1 AND week_ending = $2)",
+    )
+    .bind(user_id)
+    .bind(week_ending)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to check weekly review: {}", e))?;
+
+    Ok(exists)
+}
+
+/// Facts, entities and completed reminders since `since`.
+async fn get_week_summary(
+    pool: &PgPool,
+    user_id: Uuid,
+    since: DateTime<Utc>,
+) -> Result<WeekSummary, Error> {
+    let limit = MAX_REVIEW_ITEMS as i64;
+
+    let (fact_count, entity_count, reminder_count): (i64, i64, i64) = sqlx::query_as(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM facts
+             WHERE created_by = $1 AND created_at >= $2) AS fact_count,
+            (SELECT COUNT(*) FROM entities
+             WHERE created_by = $1 AND created_at >= $2) AS entity_count,
+            (SELECT COUNT(*) FROM reminder_occurrences
+             WHERE user_id = $1 AND outcome = 'completed'
+               AND COALESCE(completed_at, due_at) >= $2) AS reminder_count
+        "#,
+    )
+    .bind(user_id)
+    .bind(since)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to count week's activity: {}", e))?;
+
+    let facts: Vec<ReviewItem> = sqlx::query_as(
+        r#"
+        SELECT id, content AS title
+        FROM facts
+        WHERE created_by = $1 AND created_at >= $2
+        ORDER BY importance DESC, created_at DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(user_id)
+    .bind(since)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch week's facts: {}", e))?;
+
+    let entities: Vec<ReviewItem> = sqlx::query_as(
+        r#"
+        SELECT id, name AS title
+        FROM entities
+        WHERE created_by = $1 AND created_at >= $2
+        ORDER BY created_at DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(user_id)
+    .bind(since)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch week's entities: {}", e))?;
+
+    // A recurring reminder completed several times is listed once
+    let reminders: Vec<ReviewItem> = sqlx::query_as(
+        r#"
+        SELECT r.id, r.title
+        FROM reminder_occurrences ro
+        JOIN reminders r ON r.id = ro.reminder_id
+        WHERE ro.user_id = $1 AND ro.outcome = 'completed'
+          AND COALESCE(ro.completed_at, ro.due_at) >= $2
+        GROUP BY r.id, r.title
+        ORDER BY MAX(COALESCE(ro.completed_at, ro.due_at)) DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(user_id)
+    .bind(since)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch week's reminders: {}", e))?;
+
+    Ok(WeekSummary {
+        facts,
+        fact_count,
+        entities,
+        entity_count,
+        reminders,
+        reminder_count,
+    })
+}
+
+/// The agent's narrative of the week, or `None` if it fails (the review is
+/// still sent without one).
+async fn generate_narrative(
+    state: &AppState,
+    user: &ReviewUser,
+    summary: &WeekSummary,
+) -> Option<String> {
+    let request = AgentRequest {
+        message: narrative_prompt(summary),
+        user_id: user.user_id.to_string(),
+        family_ids: user.family_ids.iter().map(Uuid::to_string).collect(),
+        device_id: None,
+        conversation_id: None,
+        conversation_history: Vec::new(),
+        intent: Some("query".to_string()),
+        source: "scheduler".to_string(),
+        modality: None,
+        metadata: None,
+    };
+
+    match state.agent_client.invoke(request).await {
+        Ok(response) => Some(response.response),
+        Err(e) => {
+            warn!(user_id = %user.user_id, error = %e, "Failed to generate weekly narrative");
+            None
+        }
+    }
+}
+
+/// Claim the week for the user; `None` if another run already did.
+async fn claim_review(
+    pool: &PgPool,
+    user_id: Uuid,
+    week_ending: NaiveDate,
+    summary: &WeekSummary,
+    narrative: Option<&str>,
+) -> Result<Option<Uuid>, Error> {
+    let review_id: Option<Uuid> = sqlx::query_scalar(
+        r#"
+        INSERT INTO weekly_reviews (
+            user_id, week_ending, fact_count, entity_count, reminders_completed, narrative
+        ) VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (user_id, week_ending) DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(user_id)
+    .bind(week_ending)
+    .bind(summary.fact_count as i32)
+    .bind(summary.entity_count as i32)
+    .bind(summary.reminder_count as i32)
+    .bind(narrative)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to claim weekly review: {}", e))?;
+
+    Ok(review_id)
+}
+
+async fn send_email(
+    state: &AppState,
+    to_email: &str,
+    title: &str,
+    html: String,
+    text: String,
+) -> Result<(), Error> {
+    let subject = Content::builder()
+        .data(title)
+        .charset("UTF-8")
+        .build()
+        .map_err(|e| format!("Failed to build subject: {}", e))?;
+
+    let html_content = Content::builder()
+        .data(html)
+        .charset("UTF-8")
+        .build()
+        .map_err(|e| format!("Failed to build body: {}", e))?;
+
+    let text_content = Content::builder()
+        .data(text)
+        .charset("UTF-8")
+        .build()
+        .map_err(|e| format!("Failed to build text body: {}", e))?;
+
+    let body = Body::builder()
+        .html(html_content)
+        .text(text_content)
+        .build();
+
+    let message = Message::builder().subject(subject).body(body).build();
+
+    let destination = Destination::builder().to_addresses(to_email).build();
+
+    state
+        .ses_client
+        .send_email()
+        .source(&state.from_email)
+        .destination(destination)
+        .message(message)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send email: {}", e))?;
+
+    Ok(())
+}
+
+async fn send_review(
+    state: &AppState,
+    user: &ReviewUser,
+    week_ending: NaiveDate,
+    now: DateTime<Utc>,
+) -> Result<ReviewOutcome, Error> {
+    if review_exists(&state.db_pool, user.user_id, week_ending).await? {
+        return Ok(ReviewOutcome::AlreadySent);
+    }
+
+    let since = now - Duration::days(REVIEW_DAYS);
+    let summary = get_week_summary(&state.db_pool, user.user_id, since).await?;
+    if summary.is_empty() {
+        return Ok(ReviewOutcome::Empty);
+    }
+
+    let narrative = generate_narrative(state, user, &summary).await;
+
+    let review_id = match claim_review(
+        &state.db_pool,
+        user.user_id,
+        week_ending,
+        &summary,
+        narrative.as_deref(),
+    )
+    .await?
+    {
+        Some(id) => id,
+        None => return Ok(ReviewOutcome::AlreadySent),
+    };
+
+    let html = render_html(&summary, narrative.as_deref(), week_ending, &state.app_url);
+    let text = render_text(&summary, narrative.as_deref(), week_ending, &state.app_url);
+
+    if let Err(e) = send_email(state, &user.email, &subject(week_ending), html, text).await {
+        // Release the claim so a later run today retries
+        if let Err(release) = sqlx::query("DELETE FROM weekly_reviews WHERE id = $1")
+            .bind(review_id)
+            .execute(&state.db_pool)
+            .await
+        {
+            warn!(review_id = %review_id, error = %release, "Failed to release weekly review");
+        }
+        return Err(e);
+    }
+
+    sqlx::query("UPDATE weekly_reviews SET sent_at = NOW() WHERE id = $1")
+        .bind(review_id)
+        .execute(&state.db_pool)
+        .await
+        .map_err(|e| format!("Failed to mark weekly review sent: {}", e))?;
+
+    info!(
+        user_id = %user.user_id,
+        review_id = %review_id,
+        facts = summary.fact_count,
+        entities = summary.entity_count,
+        reminders = summary.reminder_count,
+        "Sent weekly review"
+    );
+
+    Ok(ReviewOutcome::Sent)
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    _event: LambdaEvent<ScheduledEvent>,
+) -> Result<ReviewResponse, Error> {
+    info!("Starting weekly reviews");
+
+    let now = Utc::now();
+    let users = get_review_users(&state.db_pool).await?;
+    let users_checked = users.len() as u32;
+
+    let due: Vec<(ReviewUser, NaiveDate)> = users
+        .into_iter()
+        .filter_map(|user| due_week_ending(&user.prefs, now).map(|week| (user, week)))
+        .collect();
+
+    info!(
+        users_checked = users_checked,
+        users_due = due.len(),
+        "Found users due a weekly review"
+    );
+
+    let mut reviews_sent = 0u32;
+    let mut already_sent = 0u32;
+    let mut empty_weeks = 0u32;
+    let mut errors = 0u32;
+
+    // Prepare reviews concurrently; each one waits on the agent for several seconds
+    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_REVIEWS));
+    let mut tasks = JoinSet::new();
+
+    for (user, week_ending) in due {
+        let state = Arc::clone(&state);
+        let semaphore = Arc::clone(&semaphore);
+
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let result = send_review(&state, &user, week_ending, now)
+                .await
+                .map_err(|e| e.to_string());
+            (user.user_id, result)
+        });
+    }
+
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((_, Ok(ReviewOutcome::Sent))) => reviews_sent += 1,
+            Ok((_, Ok(ReviewOutcome::AlreadySent))) => already_sent += 1,
+            Ok((_, Ok(ReviewOutcome::Empty))) => empty_weeks += 1,
+            Ok((user_id, Err(e))) => {
+                error!(user_id = %user_id, error = %e, "Failed to send weekly review");
+                errors += 1;
+            }
+            Err(e) => {
+                error!(error = %e, "Weekly review task panicked");
+                errors += 1;
+            }
+        }
+    }
+
+    let response = ReviewResponse {
+        users_checked,
+        reviews_sent,
+        already_sent,
+        empty_weeks,
+        errors,
+    };
+
+    info!(
+        users_checked = response.users_checked,
+        reviews_sent = response.reviews_sent,
+        already_sent = response.already_sent,
+        empty_weeks = response.empty_weeks,
+        errors = response.errors,
+        "Weekly reviews complete"
+    );
+
+    Ok(response)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+    let state_clone = state.clone();
+
+    run(service_fn(move |event| {
+        let state = state_clone.clone();
+        async move { metrics::track_invocation("weekly_review", handler(state, event)).await }
+    }))
+    .await
+}
@@ -2,6 +2,7 @@
 
 use lambda_runtime::{run, service_fn, Error, LambdaEvent};
 use serde::{Deserialize, Serialize};
+use shared::metrics;
 use tracing_subscriber::EnvFilter;
 
 #[derive(Debug, Deserialize)]
@@ -44,5 +45,5 @@ async fn main() -> Result<(), Error> {
         .json()
         .init();
 
-    run(service_fn(handler)).await
+    run(service_fn(|event| metrics::track_invocation("geocode", handler(event)))).await
 }
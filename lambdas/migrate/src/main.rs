@@ -0,0 +1,92 @@
+//! Migrate Lambda - Applies the schema migrations embedded in the `migrations` crate.
+//!
+//! Invoked by the deploy after the stack updates, or by hand:
+//!
+//! ```text
+//! aws lambda invoke --function-name second-brain-migrate \
+//!     --payload '{"action": "status"}' response.json
+//! ```
+//!
+//! The default action is `migrate`. A failed migration fails the invocation,
+//! which fails the deploy.
+
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use migrations::Report;
+use serde::Deserialize;
+use shared::metrics;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Action {
+    #[default]
+    Migrate,
+    Status,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct MigrateRequest {
+    #[serde(default)]
+    action: Action,
+}
+
+struct AppState {
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        Ok(Self { db_pool })
+    }
+}
+
+async fn handler(
+    state: Arc<AppState>,
+    event: LambdaEvent<serde_json::Value>,
+) -> Result<Report, Error> {
+    // Deploy triggers send an empty or unrelated payload, which migrates
+    let request: MigrateRequest = if event.payload.is_null() {
+        MigrateRequest::default()
+    } else {
+        serde_json::from_value(event.payload)?
+    };
+
+    let report = match request.action {
+        Action::Migrate => migrations::run(&state.db_pool).await?,
+        Action::Status => migrations::status(&state.db_pool).await?,
+    };
+
+    info!(
+        applied = ?report.applied,
+        current_version = ?report.current_version,
+        pending = report.pending.len(),
+        "Migration {:?} complete",
+        request.action
+    );
+    Ok(report)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+
+    run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        async move { metrics::track_invocation("migrate", handler(state, event)).await }
+    }))
+    .await
+}
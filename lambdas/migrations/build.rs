@@ -0,0 +1,4 @@
+// Rebuild when a migration is added or edited, since they're embedded.
+fn main() {
+    println!("cargo:rerun-if-changed=../../migrations");
+}
@@ -0,0 +1,186 @@
+//! Database schema migrations.
+//!
+//! The SQL files in the repository's `migrations/` directory are embedded at
+//! compile time, so a build of the `migrate` Lambda always carries the schema
+//! the Rust code was written against. [`run`] applies whatever is pending while
+//! holding a Postgres advisory lock, so overlapping deploys apply each
+//! migration once.
+//!
+//! Databases migrated by the old Python runner keep their history in
+//! `schema_migrations`. The first run copies it into sqlx's `_sqlx_migrations`
+//! so those migrations aren't applied again.
+
+use serde::Serialize;
+use sqlx::migrate::{Migrate, MigrateError, Migrator};
+use sqlx::pool::PoolConnection;
+use sqlx::{PgConnection, PgPool, Postgres};
+use std::collections::BTreeSet;
+use tracing::info;
+
+/// Every migration in `migrations/`, in version order.
+pub static MIGRATOR: Migrator = sqlx::migrate!("../../migrations");
+
+/// Advisory lock held while migrating ("SecondBr" in ASCII).
+const LOCK_KEY: i64 = 0x5365_636f_6e64_4272;
+
+/// Where the schema stands after a run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Report {
+    /// Versions applied by this run
+    pub applied: Vec<i64>,
+    /// Latest version applied to the database
+    pub current_version: Option<i64>,
+    /// Versions not yet applied
+    pub pending: Vec<i64>,
+}
+
+impl Report {
+    fn new(applied: Vec<i64>, in_database: &BTreeSet<i64>) -> Self {
+        Self {
+            applied,
+            current_version: in_database.last().copied(),
+            pending: MIGRATOR
+                .iter()
+                .filter(|m| m.migration_type.is_up_migration())
+                .map(|m| m.version)
+                .filter(|v| !in_database.contains(v))
+                .collect(),
+        }
+    }
+}
+
+/// Apply all pending migrations.
+pub async fn run(pool: &PgPool) -> Result<Report, MigrateError> {
+    let mut conn = lock(pool).await?;
+    let result = apply(&mut conn).await;
+    unlock(&mut conn).await?;
+    result
+}
+
+/// Report applied and pending migrations without applying any.
+pub async fn status(pool: &PgPool) -> Result<Report, MigrateError> {
+    let mut conn = lock(pool).await?;
+    let result = async {
+        prepare(&mut conn).await?;
+        Ok(Report::new(Vec::new(), &applied_versions(&mut conn).await?))
+    }
+    .await;
+    unlock(&mut conn).await?;
+    result
+}
+
+async fn apply(conn: &mut PgConnection) -> Result<Report, MigrateError> {
+    prepare(conn).await?;
+    let before = applied_versions(conn).await?;
+    MIGRATOR.run(&mut *conn).await?;
+    let after = applied_versions(conn).await?;
+
+    let applied: Vec<i64> = after.difference(&before).copied().collect();
+    info!(?applied, "Applied migrations");
+    Ok(Report::new(applied, &after))
+}
+
+/// Take the migration lock on a connection, waiting for any other run to finish.
+async fn lock(pool: &PgPool) -> Result<PoolConnection<Postgres>, MigrateError> {
+    let mut conn = pool.acquire().await?;
+    sqlx::query("SELECT pg_advisory_lock($1)")
+        .bind(LOCK_KEY)
+        .execute(&mut *conn)
+        .await?;
+    Ok(conn)
+}
+
+async fn unlock(conn: &mut PgConnection) -> Result<(), MigrateError> {
+    sqlx::query("SELECT pg_advisory_unlock($1)")
+        .bind(LOCK_KEY)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Make sure the history table exists and includes any legacy history.
+async fn prepare(conn: &mut PgConnection) -> Result<(), MigrateError> {
+    conn.ensure_migrations_table().await?;
+    adopt_legacy_history(conn).await
+}
+
+async fn applied_versions(conn: &mut PgConnection) -> Result<BTreeSet<i64>, MigrateError> {
+    Ok(conn
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|m| m.version)
+        .collect())
+}
+
+/// Record migrations applied by the Python runner (`schema_migrations`) as
+/// applied, if sqlx has no history of its own yet.
+async fn adopt_legacy_history(conn: &mut PgConnection) -> Result<(), MigrateError> {
+    if !conn.list_applied_migrations().await?.is_empty() {
+        return Ok(());
+    }
+
+    let legacy_table: Option<String> =
+        sqlx::query_scalar("SELECT to_regclass('schema_migrations')::text")
+            .fetch_one(&mut *conn)
+            .await?;
+    if legacy_table.is_none() {
+        return Ok(());
+    }
+
+    let versions: Vec<String> = sqlx::query_scalar("SELECT version FROM schema_migrations")
+        .fetch_all(&mut *conn)
+        .await?;
+    let versions = legacy_versions(&versions);
+
+    let mut adopted = 0;
+    for migration in MIGRATOR.iter().filter(|m| versions.contains(&m.version)) {
+        sqlx::query(
+            r#"
+            INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+            VALUES ($1, $2, TRUE, $3, 0)
+            "#,
+        )
+        .bind(migration.version)
+        .bind(&*migration.description)
+        .bind(&*migration.checksum)
+        .execute(&mut *conn)
+        .await?;
+        adopted += 1;
+    }
+
+    info!(adopted, "Adopted migration history from schema_migrations");
+    Ok(())
+}
+
+/// Parse the Python runner's zero-padded versions (`"014"`).
+fn legacy_versions(versions: &[String]) -> BTreeSet<i64> {
+    versions.iter().filter_map(|v| v.parse().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_unique() {
+        let versions: Vec<i64> = MIGRATOR.iter().map(|m| m.version).collect();
+        let unique: BTreeSet<i64> = versions.iter().copied().collect();
+        assert_eq!(versions.len(), unique.len());
+        assert_eq!(versions.first(), Some(&1));
+    }
+
+    #[test]
+    fn report_lists_unapplied_versions() {
+        let in_database: BTreeSet<i64> = MIGRATOR.iter().map(|m| m.version).skip(1).collect();
+        let report = Report::new(Vec::new(), &in_database);
+        assert_eq!(report.pending, vec![1]);
+        assert_eq!(report.current_version, in_database.last().copied());
+    }
+
+    #[test]
+    fn parses_legacy_versions() {
+        let versions = vec!["001".to_string(), "014".to_string(), "bogus".to_string()];
+        assert_eq!(legacy_versions(&versions), BTreeSet::from([1, 14]));
+    }
+}
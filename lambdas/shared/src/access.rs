@@ -0,0 +1,302 @@
+//! Row-level access for reads.
+//!
+//! Facts and entities carry a `visibility_tier` from 1 (closest relationships
+//! only) to 4 (anyone the owner has a relationship with). A user reads a row
+//! if they own it, belong to the owning family, or reach the owner through the
+//! relationship graph with an access tier no higher than the row's visibility
+//! tier. Relationship access comes from
+//! `user_access_cache`, which `refresh_user_access_cache` keeps current as
+//! relationships change.
+//!
+//! Family rows can also be put in one of the family's spaces (`space_id`), and
+//! then only the space's members read them; see [`space_clause`].
+//!
+//! Owners (and family admins, for family rows) can also grant someone a tier
+//! for a limited time, like a babysitter for a weekend; see [`grant_clause`]
+//! and `access_grants`.
+//!
+//! Writes stay with owners and family members; only reads are widened.
+
+/// Closest relationships (spouse, parent)
+pub const TIER_INTIMATE: i16 = 1;
+
+/// Anyone with a relationship to the owner
+pub const TIER_RELATIONSHIPS: i16 = 4;
+
+/// SQL condition that the row aliased `alias` (with a `space_id` column) is
+/// outside any space or in one the user bound at `$user_param` is a member of.
+///
+/// Hand-written family arms (`owner_type = 'family' AND owner_id = ANY(...)`)
+/// add this so space rows stay with the space's members.
+pub fn space_clause(alias: &str, user_param: usize) -> String {
+    format!(
+        r#"({a}.space_id IS NULL OR EXISTS (
+                SELECT 1 FROM family_space_members sm
+                WHERE sm.space_id = {a}.space_id AND sm.user_id = ${u}
+            ))"#,
+        a = alias,
+        u = user_param,
+    )
+}
+
+/// SQL condition that the row aliased `alias` (with `id`, `owner_type`,
+/// `owner_id`, `visibility_tier` and `space_id` columns) is readable by the
+/// user bound at `$user_param` through an access grant in effect now. Rows in
+/// a space are never granted.
+///
+/// A grant scoped to an entity covers that entity and the facts about or
+/// mentioning it; one scoped to a tag covers the facts carrying it, and no
+/// entities. The scope is matched on `{alias}.id` alone so the clause works
+/// for both facts and entities.
+pub fn grant_clause(alias: &str, user_param: usize) -> String {
+    format!(
+        r#"({a}.space_id IS NULL AND EXISTS (
+                SELECT 1 FROM access_grants g
+                WHERE g.grantee_user_id = ${u}
+                  AND g.owner_type = {a}.owner_type
+                  AND g.owner_id = {a}.owner_id
+                  AND g.access_tier <= {a}.visibility_tier
+                  AND g.revoked_at IS NULL
+                  AND g.starts_at <= NOW()
+                  AND g.expires_at > NOW()
+                  AND (g.entity_id IS NULL
+                       OR g.entity_id = {a}.id
+                       OR EXISTS (
+                           SELECT 1 FROM facts sf
+                           WHERE sf.id = {a}.id AND sf.about_entity_id = g.entity_id
+                       )
+                       OR EXISTS (
+                           SELECT 1 FROM entity_mentions sem
+                           WHERE sem.fact_id = {a}.id AND sem.entity_id = g.entity_id
+                       ))
+                  AND (g.tag_id IS NULL OR EXISTS (
+                           SELECT 1 FROM fact_tags sft
+                           WHERE sft.fact_id = {a}.id AND sft.tag_id = g.tag_id
+                       ))
+            ))"#,
+        a = alias,
+        u = user_param,
+    )
+}
+
+/// SQL condition that the row aliased `alias` (with `owner_type`, `owner_id`,
+/// `visibility_tier` and `space_id` columns) is readable by the user bound at
+/// `$user_param`, whose family IDs are bound at `$user_param + 1`:
+///
+/// ```ignore
+/// visibility_clause("f", 3)
+/// // ((f.owner_type = 'user' AND f.owner_id = $3)
+/// //  OR (f.owner_type = 'family' AND f.owner_id = ANY($4) AND (f.space_id IS NULL OR ...))
+/// //  OR (f.owner_type = 'user' AND EXISTS (SELECT 1 FROM user_access_cache uac ...))
+/// //  OR (f.space_id IS NULL AND EXISTS (SELECT 1 FROM access_grants g ...)))
+/// ```
+pub fn visibility_clause(alias: &str, user_param: usize) -> String {
+    format!(
+        r#"(
+            ({a}.owner_type = 'user' AND {a}.owner_id = ${u})
+            OR ({a}.owner_type = 'family' AND {a}.owner_id = ANY(${f}) AND {s})
+            OR ({a}.owner_type = 'user' AND EXISTS (
+                SELECT 1 FROM user_access_cache uac
+                WHERE uac.viewer_user_id = ${u}
+                  AND uac.target_user_id = {a}.owner_id
+                  AND uac.access_tier <= {a}.visibility_tier
+            ))
+            OR {g}
+        )"#,
+        a = alias,
+        u = user_param,
+        f = user_param + 1,
+        s = space_clause(alias, user_param),
+        g = grant_clause(alias, user_param),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    #[test]
+    fn relationships_read_rows_at_or_below_their_tier() {
+        // A viewer at tier 2 reads tiers 2-4, not tier 1: the cached access
+        // tier must be at most the row's visibility tier
+        let clause = visibility_clause("f", 1);
+        let (before, arm) = clause.split_once("FROM user_access_cache uac").unwrap();
+        let arm = &arm[..arm.find("))").unwrap()];
+        assert!(arm.contains("uac.viewer_user_id = $1"));
+        assert!(arm.contains("uac.target_user_id = f.owner_id"));
+        assert!(arm.contains("uac.access_tier <= f.visibility_tier"));
+        // Only rows users own are reached through their relationships
+        let opening = before.rsplit_once("OR (").unwrap().1;
+        assert!(opening.starts_with("f.owner_type = 'user' AND EXISTS"));
+    }
+
+    #[test]
+    fn grants_read_rows_at_or_below_their_tier() {
+        let clause = visibility_clause("f", 1);
+        assert!(clause.contains(&grant_clause("f", 1)));
+        assert!(grant_clause("f", 1).contains("g.access_tier <= f.visibility_tier"));
+    }
+
+    #[test]
+    fn builds_clause_for_alias_and_params() {
+        let clause = visibility_clause("e", 4);
+        assert!(clause.contains("e.owner_type = 'user' AND e.owner_id = $4"));
+        assert!(clause.contains("e.owner_id = ANY($5)"));
+        assert!(clause.contains("uac.viewer_user_id = $4"));
+        assert!(clause.contains("uac.target_user_id = e.owner_id"));
+        assert!(clause.contains("uac.access_tier <= e.visibility_tier"));
+        assert!(clause.contains("e.space_id IS NULL"));
+        assert!(clause.contains("sm.space_id = e.space_id AND sm.user_id = $4"));
+        assert!(clause.contains("g.grantee_user_id = $4"));
+    }
+
+    #[test]
+    fn builds_space_clause() {
+        let clause = space_clause("f", 2);
+        assert!(clause.starts_with("(f.space_id IS NULL OR EXISTS"));
+        assert!(clause.contains("FROM family_space_members sm"));
+        assert!(clause.contains("sm.user_id = $2"));
+    }
+
+    #[test]
+    fn builds_grant_clause() {
+        let clause = grant_clause("f", 3);
+        assert!(clause.starts_with("(f.space_id IS NULL AND EXISTS"));
+        assert!(clause.contains("g.grantee_user_id = $3"));
+        assert!(clause.contains("g.owner_type = f.owner_type"));
+        assert!(clause.contains("g.access_tier <= f.visibility_tier"));
+        assert!(clause.contains("g.expires_at > NOW()"));
+        assert!(clause.contains("g.entity_id IS NULL"));
+        assert!(clause.contains("sem.fact_id = f.id AND sem.entity_id = g.entity_id"));
+        assert!(clause.contains("sft.fact_id = f.id AND sft.tag_id = g.tag_id"));
+    }
+
+    async fn insert_user(pool: &PgPool, name: &str) -> Uuid {
+        sqlx::query_scalar(
+            "INSERT INTO users (cognito_sub, email, display_name)
+             VALUES ($1, $1 || '@example.com', $1) RETURNING id",
+        )
+        .bind(name)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    /// One fact at each tier, owned by `owner_type`/`owner_id`
+    async fn insert_facts(pool: &PgPool, owner_type: &str, owner_id: Uuid, created_by: Uuid) {
+        for tier in TIER_INTIMATE..=TIER_RELATIONSHIPS {
+            sqlx::query(
+                "INSERT INTO facts (owner_type, owner_id, created_by, content, visibility_tier)
+                 VALUES ($1, $2, $3, 'A fact', $4)",
+            )
+            .bind(owner_type)
+            .bind(owner_id)
+            .bind(created_by)
+            .bind(tier)
+            .execute(pool)
+            .await
+            .unwrap();
+        }
+    }
+
+    /// Tiers of the facts the viewer reads
+    async fn visible_tiers(pool: &PgPool, viewer_id: Uuid, family_ids: &[Uuid]) -> Vec<i16> {
+        sqlx::query_scalar(&format!(
+            "SELECT f.visibility_tier FROM facts f WHERE {} ORDER BY 1",
+            visibility_clause("f", 1)
+        ))
+        .bind(viewer_id)
+        .bind(family_ids)
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    #[sqlx::test(migrator = "migrations::MIGRATOR")]
+    #[ignore = "needs DATABASE_URL"]
+    async fn test_user_facts_by_viewer(pool: PgPool) {
+        let owner = insert_user(&pool, "owner").await;
+        let spouse = insert_user(&pool, "spouse").await;
+        let friend = insert_user(&pool, "friend").await;
+        let sitter = insert_user(&pool, "sitter").await;
+        let stranger = insert_user(&pool, "stranger").await;
+        insert_facts(&pool, "user", owner, owner).await;
+
+        for (viewer, tier) in [(spouse, TIER_INTIMATE), (friend, 3)] {
+            sqlx::query(
+                "INSERT INTO user_access_cache
+                     (viewer_user_id, target_user_id, access_tier, relationship_path, hop_count)
+                 VALUES ($1, $2, $3, '{}', 1)",
+            )
+            .bind(viewer)
+            .bind(owner)
+            .bind(tier)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        // A grant in effect at tier 2, and one that has expired at tier 1
+        for (tier, starts, expires) in [(2i16, "-1 day", "1 day"), (1, "-3 days", "-1 day")] {
+            sqlx::query(
+                "INSERT INTO access_grants
+                     (grantor_user_id, grantee_user_id, owner_type, owner_id, access_tier,
+                      starts_at, expires_at)
+                 VALUES ($1, $2, 'user', $1, $3, NOW() + $4::interval, NOW() + $5::interval)",
+            )
+            .bind(owner)
+            .bind(sitter)
+            .bind(tier)
+            .bind(starts)
+            .bind(expires)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(visible_tiers(&pool, owner, &[]).await, vec![1, 2, 3, 4]);
+        assert_eq!(visible_tiers(&pool, spouse, &[]).await, vec![1, 2, 3, 4]);
+        assert_eq!(visible_tiers(&pool, friend, &[]).await, vec![3, 4]);
+        assert_eq!(visible_tiers(&pool, sitter, &[]).await, vec![2, 3, 4]);
+        assert!(visible_tiers(&pool, stranger, &[]).await.is_empty());
+    }
+
+    #[sqlx::test(migrator = "migrations::MIGRATOR")]
+    #[ignore = "needs DATABASE_URL"]
+    async fn test_family_facts_by_viewer(pool: PgPool) {
+        let member = insert_user(&pool, "member").await;
+        let relative = insert_user(&pool, "relative").await;
+        let family: Uuid =
+            sqlx::query_scalar("INSERT INTO families (name) VALUES ('Smiths') RETURNING id")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        sqlx::query("INSERT INTO family_members (family_id, user_id) VALUES ($1, $2)")
+            .bind(family)
+            .bind(member)
+            .execute(&pool)
+            .await
+            .unwrap();
+        insert_facts(&pool, "family", family, member).await;
+
+        // Members read every tier; relationships never reach family rows
+        sqlx::query(
+            "INSERT INTO user_access_cache
+                 (viewer_user_id, target_user_id, access_tier, relationship_path, hop_count)
+             VALUES ($1, $2, 1, '{}', 1)",
+        )
+        .bind(relative)
+        .bind(member)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(
+            visible_tiers(&pool, member, &[family]).await,
+            vec![1, 2, 3, 4]
+        );
+        assert!(visible_tiers(&pool, relative, &[]).await.is_empty());
+    }
+}
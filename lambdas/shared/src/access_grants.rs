@@ -0,0 +1,455 @@
+//! Time-boxed access grants.
+//!
+//! A grant lets someone read the owner's facts and entities at or above an
+//! access tier for a while without a relationship: a babysitter gets tier 3
+//! for the weekend, say, with the kids' medical facts at tier 3. Users grant
+//! their own data and family admins the family's; rows in a family space are
+//! never granted. A grant can be narrowed to one entity and the facts about
+//! it, or to the facts with one tag (see [`NewGrant::scoped`]). Reads check grants through
+//! [`crate::access::visibility_clause`], so a grant stops working the moment
+//! it expires or is revoked. The `access_grant_sweeper` Lambda deletes grants
+//! once they have been over for [`HISTORY_DAYS`].
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::access::{TIER_INTIMATE, TIER_RELATIONSHIPS};
+use crate::{Error, Result};
+
+/// Longest a grant can last
+pub const MAX_GRANT_DAYS: i64 = 30;
+
+/// Days ended grants stay listed before the sweeper deletes them
+pub const HISTORY_DAYS: i64 = 30;
+
+/// Whose rows a grant covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrantOwner {
+    User(Uuid),
+    Family(Uuid),
+}
+
+impl GrantOwner {
+    pub fn owner_type(&self) -> &'static str {
+        match self {
+            Self::User(_) => "user",
+            Self::Family(_) => "family",
+        }
+    }
+
+    pub fn owner_id(&self) -> Uuid {
+        match self {
+            Self::User(id) | Self::Family(id) => *id,
+        }
+    }
+}
+
+/// A grant to create, already checked by [`NewGrant::new`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewGrant {
+    pub grantee_user_id: Uuid,
+    pub owner: GrantOwner,
+    pub access_tier: i16,
+    pub reason: Option<String>,
+    pub starts_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// Only this entity and the facts about or mentioning it
+    pub entity_id: Option<Uuid>,
+    /// Only facts with this tag
+    pub tag_id: Option<Uuid>,
+}
+
+impl NewGrant {
+    /// Check a grant's tier and window. A missing or past `starts_at` means now.
+    pub fn new(
+        grantee_user_id: Uuid,
+        owner: GrantOwner,
+        access_tier: i16,
+        reason: Option<String>,
+        starts_at: Option<DateTime<Utc>>,
+        expires_at: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<Self> {
+        if !(TIER_INTIMATE..=TIER_RELATIONSHIPS).contains(&access_tier) {
+            return Err(Error::Validation(format!(
+                "accessTier must be between {} and {}",
+                TIER_INTIMATE, TIER_RELATIONSHIPS
+            )));
+        }
+
+        let starts_at = starts_at.map_or(now, |starts_at| starts_at.max(now));
+        if expires_at <= starts_at {
+            return Err(Error::Validation(
+                "expiresAt must be after the grant starts".to_string(),
+            ));
+        }
+        if expires_at - starts_at > Duration::days(MAX_GRANT_DAYS) {
+            return Err(Error::Validation(format!(
+                "A grant can last at most {} days",
+                MAX_GRANT_DAYS
+            )));
+        }
+
+        let reason = reason
+            .map(|r| r.trim().to_string())
+            .filter(|r| !r.is_empty());
+
+        Ok(Self {
+            grantee_user_id,
+            owner,
+            access_tier,
+            reason,
+            starts_at,
+            expires_at,
+            entity_id: None,
+            tag_id: None,
+        })
+    }
+
+    /// Narrow the grant to an entity and/or a tag. The caller checks they
+    /// belong to the grant's owner.
+    pub fn scoped(mut self, entity_id: Option<Uuid>, tag_id: Option<Uuid>) -> Self {
+        self.entity_id = entity_id;
+        self.tag_id = tag_id;
+        self
+    }
+}
+
+/// A grant as listed to its grantor and grantee
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessGrant {
+    pub id: Uuid,
+    pub grantor_user_id: Uuid,
+    pub grantor_name: Option<String>,
+    pub grantee_user_id: Uuid,
+    pub grantee_name: Option<String>,
+    /// `user` or `family`
+    pub owner_type: String,
+    pub owner_id: Uuid,
+    pub access_tier: i16,
+    pub reason: Option<String>,
+    /// Set when the grant covers only this entity and the facts about it
+    pub entity_id: Option<Uuid>,
+    /// Set when the grant covers only facts with this tag
+    pub tag_id: Option<Uuid>,
+    pub starts_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    /// `scheduled`, `active`, `expired` or `revoked`
+    pub status: String,
+}
+
+/// Columns selected into [`AccessGrant`] from `access_grants g`
+const GRANT_COLUMNS: &str = r#"
+    g.id, g.grantor_user_id, gr.display_name AS grantor_name,
+    g.grantee_user_id, ge.display_name AS grantee_name,
+    g.owner_type, g.owner_id, g.access_tier, g.reason, g.entity_id, g.tag_id,
+    g.starts_at, g.expires_at, g.revoked_at, g.created_at,
+    CASE
+        WHEN g.revoked_at IS NOT NULL THEN 'revoked'
+        WHEN g.expires_at <= NOW() THEN 'expired'
+        WHEN g.starts_at > NOW() THEN 'scheduled'
+        ELSE 'active'
+    END AS status
+"#;
+
+/// Save a grant. The caller checks the grantor may share the owner's rows.
+pub async fn create_grant(
+    pool: &PgPool,
+    grantor_id: Uuid,
+    grant: &NewGrant,
+) -> Result<AccessGrant> {
+    let grant_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO access_grants
+            (grantor_user_id, grantee_user_id, owner_type, owner_id, access_tier, reason,
+             starts_at, expires_at, entity_id, tag_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        RETURNING id
+        "#,
+    )
+    .bind(grantor_id)
+    .bind(grant.grantee_user_id)
+    .bind(grant.owner.owner_type())
+    .bind(grant.owner.owner_id())
+    .bind(grant.access_tier)
+    .bind(&grant.reason)
+    .bind(grant.starts_at)
+    .bind(grant.expires_at)
+    .bind(grant.entity_id)
+    .bind(grant.tag_id)
+    .fetch_one(pool)
+    .await?;
+
+    let grant = sqlx::query_as(&format!(
+        r#"
+        SELECT {} FROM access_grants g
+        JOIN users gr ON gr.id = g.grantor_user_id
+        JOIN users ge ON ge.id = g.grantee_user_id
+        WHERE g.id = $1
+        "#,
+        GRANT_COLUMNS
+    ))
+    .bind(grant_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(grant)
+}
+
+/// Grants the user gave or received, plus those of families they admin,
+/// soonest to expire first. Ended grants are listed until they are swept.
+pub async fn list_grants(pool: &PgPool, user_id: Uuid) -> Result<Vec<AccessGrant>> {
+    let grants = sqlx::query_as(&format!(
+        r#"
+        SELECT {} FROM access_grants g
+        JOIN users gr ON gr.id = g.grantor_user_id
+        JOIN users ge ON ge.id = g.grantee_user_id
+        WHERE g.grantor_user_id = $1
+           OR g.grantee_user_id = $1
+           OR (g.owner_type = 'family' AND EXISTS (
+                SELECT 1 FROM family_members fm
+                WHERE fm.family_id = g.owner_id AND fm.user_id = $1 AND fm.role = 'admin'
+           ))
+        ORDER BY g.revoked_at IS NOT NULL, g.expires_at
+        "#,
+        GRANT_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(grants)
+}
+
+/// End a grant early. Its grantor, its grantee (giving it up) and, for family
+/// grants, the family's admins can. Returns whether a grant still in effect or
+/// yet to start was revoked.
+pub async fn revoke_grant(pool: &PgPool, grant_id: Uuid, user_id: Uuid) -> Result<bool> {
+    let revoked = sqlx::query(
+        r#"
+        UPDATE access_grants g
+        SET revoked_at = NOW(), revoked_by = $2
+        WHERE g.id = $1
+          AND g.revoked_at IS NULL
+          AND g.expires_at > NOW()
+          AND (
+              g.grantor_user_id = $2
+              OR g.grantee_user_id = $2
+              OR (g.owner_type = 'family' AND EXISTS (
+                  SELECT 1 FROM family_members fm
+                  WHERE fm.family_id = g.owner_id AND fm.user_id = $2 AND fm.role = 'admin'
+              ))
+          )
+        "#,
+    )
+    .bind(grant_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(revoked > 0)
+}
+
+/// Delete grants that expired or were revoked before `cutoff`, returning how
+/// many were deleted.
+pub async fn sweep_ended(pool: &PgPool, cutoff: DateTime<Utc>) -> Result<u64> {
+    let deleted =
+        sqlx::query("DELETE FROM access_grants WHERE expires_at <= $1 OR revoked_at <= $1")
+            .bind(cutoff)
+            .execute(pool)
+            .await?
+            .rows_affected();
+
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_grant(
+        access_tier: i16,
+        starts_at: Option<DateTime<Utc>>,
+        expires_at: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<NewGrant> {
+        NewGrant::new(
+            Uuid::new_v4(),
+            GrantOwner::User(Uuid::new_v4()),
+            access_tier,
+            None,
+            starts_at,
+            expires_at,
+            now,
+        )
+    }
+
+    #[test]
+    fn test_grant_starts_now_by_default() {
+        let now = Utc::now();
+        let expires_at = now + Duration::days(2);
+
+        let grant = new_grant(3, None, expires_at, now).unwrap();
+        assert_eq!(grant.starts_at, now);
+
+        // A start in the past also means now
+        let grant = new_grant(3, Some(now - Duration::hours(1)), expires_at, now).unwrap();
+        assert_eq!(grant.starts_at, now);
+    }
+
+    #[test]
+    fn test_grant_window() {
+        let now = Utc::now();
+        let friday = now + Duration::days(3);
+
+        let grant = new_grant(3, Some(friday), friday + Duration::days(2), now).unwrap();
+        assert_eq!(grant.starts_at, friday);
+
+        assert!(new_grant(3, None, now, now).is_err());
+        assert!(new_grant(3, Some(friday), friday - Duration::hours(1), now).is_err());
+
+        let too_long = now + Duration::days(MAX_GRANT_DAYS) + Duration::seconds(1);
+        assert!(new_grant(3, None, too_long, now).is_err());
+    }
+
+    #[test]
+    fn test_grant_tier() {
+        let now = Utc::now();
+        let expires_at = now + Duration::days(1);
+        for tier in 1..=4 {
+            assert!(new_grant(tier, None, expires_at, now).is_ok());
+        }
+        assert!(new_grant(0, None, expires_at, now).is_err());
+        assert!(new_grant(5, None, expires_at, now).is_err());
+    }
+
+    #[test]
+    fn test_grant_reason_is_trimmed() {
+        let now = Utc::now();
+        let owner = GrantOwner::Family(Uuid::new_v4());
+        let expires_at = now + Duration::days(1);
+
+        let reason = Some("  Babysitting  ".to_string());
+        let grant = NewGrant::new(Uuid::new_v4(), owner, 3, reason, None, expires_at, now).unwrap();
+        assert_eq!(grant.reason.as_deref(), Some("Babysitting"));
+        assert_eq!(grant.owner.owner_type(), "family");
+
+        let reason = Some(" ".to_string());
+        let grant = NewGrant::new(Uuid::new_v4(), owner, 3, reason, None, expires_at, now).unwrap();
+        assert_eq!(grant.reason, None);
+    }
+
+    async fn insert_user(pool: &PgPool, name: &str) -> Uuid {
+        sqlx::query_scalar(
+            "INSERT INTO users (cognito_sub, email, display_name)
+             VALUES ($1, $1 || '@example.com', $1) RETURNING id",
+        )
+        .bind(name)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn insert_fact(pool: &PgPool, owner_id: Uuid, about: Option<Uuid>) -> Uuid {
+        sqlx::query_scalar(
+            "INSERT INTO facts (owner_type, owner_id, created_by, content, about_entity_id)
+             VALUES ('user', $1, $1, 'A fact', $2) RETURNING id",
+        )
+        .bind(owner_id)
+        .bind(about)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    /// IDs of the owner's facts the viewer reads, sorted
+    async fn visible_facts(pool: &PgPool, viewer_id: Uuid) -> Vec<Uuid> {
+        let mut ids: Vec<Uuid> = sqlx::query_scalar(&format!(
+            "SELECT f.id FROM facts f WHERE {}",
+            crate::access::visibility_clause("f", 1)
+        ))
+        .bind(viewer_id)
+        .bind(Vec::<Uuid>::new())
+        .fetch_all(pool)
+        .await
+        .unwrap();
+        ids.sort();
+        ids
+    }
+
+    #[sqlx::test(migrator = "migrations::MIGRATOR")]
+    #[ignore = "needs DATABASE_URL"]
+    async fn test_scoped_grant_hides_other_facts(pool: PgPool) {
+        let owner_id = insert_user(&pool, "parent").await;
+        let sitter_id = insert_user(&pool, "sitter").await;
+
+        let maya: Uuid = sqlx::query_scalar(
+            "INSERT INTO entities (owner_type, owner_id, created_by, entity_type, name)
+             VALUES ('user', $1, $1, 'person', 'Maya') RETURNING id",
+        )
+        .bind(owner_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        let medical: Uuid = sqlx::query_scalar(
+            "INSERT INTO tags (owner_type, owner_id, name, path)
+             VALUES ('user', $1, 'Medical', 'medical') RETURNING id",
+        )
+        .bind(owner_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+        let about_maya = insert_fact(&pool, owner_id, Some(maya)).await;
+        let mentions_maya = insert_fact(&pool, owner_id, None).await;
+        let tagged = insert_fact(&pool, owner_id, None).await;
+        let finances = insert_fact(&pool, owner_id, None).await;
+
+        sqlx::query("INSERT INTO entity_mentions (fact_id, entity_id) VALUES ($1, $2)")
+            .bind(mentions_maya)
+            .bind(maya)
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO fact_tags (fact_id, tag_id) VALUES ($1, $2)")
+            .bind(tagged)
+            .bind(medical)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(visible_facts(&pool, sitter_id).await.is_empty());
+
+        let now = Utc::now();
+        let owner = GrantOwner::User(owner_id);
+        let expires_at = now + Duration::days(2);
+        let grant = NewGrant::new(sitter_id, owner, 2, None, None, expires_at, now)
+            .unwrap()
+            .scoped(Some(maya), None);
+        let created = create_grant(&pool, owner_id, &grant).await.unwrap();
+        assert_eq!(created.entity_id, Some(maya));
+
+        let mut expected = vec![about_maya, mentions_maya];
+        expected.sort();
+        assert_eq!(visible_facts(&pool, sitter_id).await, expected);
+
+        assert!(revoke_grant(&pool, created.id, owner_id).await.unwrap());
+        let grant = grant.scoped(None, Some(medical));
+        create_grant(&pool, owner_id, &grant).await.unwrap();
+        assert_eq!(visible_facts(&pool, sitter_id).await, vec![tagged]);
+
+        // An unscoped grant still reads everything at its tier
+        let grant = grant.scoped(None, None);
+        create_grant(&pool, owner_id, &grant).await.unwrap();
+        let mut all = vec![about_maya, mentions_maya, tagged, finances];
+        all.sort();
+        assert_eq!(visible_facts(&pool, sitter_id).await, all);
+    }
+}
@@ -0,0 +1,203 @@
+//! Account deletion (right to be forgotten).
+//!
+//! `DELETE /account` records a request in `account_deletions`; the
+//! `account_deletion` Lambda then works through [`DeletionStep`]s in order,
+//! recording each finished step on the row so a retried run resumes where
+//! the last one stopped. The row outlives the user as the audit record: it
+//! keeps no personal data beyond the IDs and per-step counts.
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// Runs after which a failing deletion is marked failed for an operator
+pub const MAX_DELETION_ATTEMPTS: i32 = 5;
+
+/// Minutes a run holds a deletion before another run may pick it up
+pub const DELETION_LEASE_MINUTES: i64 = 10;
+
+/// What happens to family-owned facts and entities the user created
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FamilyDataPolicy {
+    /// Keep them for the family, credited to another member
+    #[default]
+    Keep,
+    /// Delete them with the account
+    Delete,
+}
+
+impl FamilyDataPolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "keep" => Some(Self::Keep),
+            "delete" => Some(Self::Delete),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Keep => "keep",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+/// One step of an account deletion, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeletionStep {
+    /// Sign the user out everywhere and disable their Cognito login
+    RevokeAccess,
+    /// Delete calendar and contacts OAuth tokens from Secrets Manager
+    PurgeSecrets,
+    /// Apply the family data policy, hand what the user created for each
+    /// family on to another member (or delete families the user was the last
+    /// member of) and leave them
+    Families,
+    /// Delete the user's own facts, entities, tags and personal devices, and
+    /// clear their name from what other users own
+    PersonalData,
+    /// Delete the user row (cascading to everything else keyed by user)
+    DeleteUser,
+    /// Delete the Cognito user
+    DeleteLogin,
+}
+
+impl DeletionStep {
+    pub const ALL: [DeletionStep; 6] = [
+        Self::RevokeAccess,
+        Self::PurgeSecrets,
+        Self::Families,
+        Self::PersonalData,
+        Self::DeleteUser,
+        Self::DeleteLogin,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::RevokeAccess => "revoke_access",
+            Self::PurgeSecrets => "purge_secrets",
+            Self::Families => "families",
+            Self::PersonalData => "personal_data",
+            Self::DeleteUser => "delete_user",
+            Self::DeleteLogin => "delete_login",
+        }
+    }
+
+    /// Steps not yet in `completed`, in order.
+    pub fn remaining(completed: &[String]) -> Vec<DeletionStep> {
+        Self::ALL
+            .into_iter()
+            .filter(|step| !completed.iter().any(|done| done == step.as_str()))
+            .collect()
+    }
+}
+
+/// A family member other than the departing user
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct FamilyMember {
+    pub user_id: Uuid,
+    /// `admin`, `member` or `child`
+    pub role: String,
+    pub joined_at: DateTime<Utc>,
+}
+
+/// Member who takes over what the departing user created for a family: the
+/// longest-standing admin, else adult member, else child. `None` if the user
+/// was the last member.
+pub fn successor(members: &[FamilyMember]) -> Option<Uuid> {
+    let earliest = |role: &str| {
+        members
+            .iter()
+            .filter(|m| m.role == role)
+            .min_by_key(|m| m.joined_at)
+            .map(|m| m.user_id)
+    };
+
+    earliest("admin")
+        .or_else(|| earliest("member"))
+        .or_else(|| earliest("child"))
+}
+
+/// Secrets Manager names holding a user's OAuth tokens.
+pub fn user_secret_names(user_id: Uuid) -> Vec<String> {
+    vec![
+        format!("second-brain/calendar/{}", user_id),
+        crate::contacts::token_secret_name(user_id),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn member(n: u128, role: &str, day: u32) -> FamilyMember {
+        FamilyMember {
+            user_id: Uuid::from_u128(n),
+            role: role.to_string(),
+            joined_at: Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn parses_policies() {
+        assert_eq!(
+            FamilyDataPolicy::parse("keep"),
+            Some(FamilyDataPolicy::Keep)
+        );
+        assert_eq!(
+            FamilyDataPolicy::parse(" DELETE "),
+            Some(FamilyDataPolicy::Delete)
+        );
+        assert_eq!(FamilyDataPolicy::parse("orphan"), None);
+        assert_eq!(FamilyDataPolicy::default().as_str(), "keep");
+    }
+
+    #[test]
+    fn resumes_after_completed_steps() {
+        assert_eq!(DeletionStep::remaining(&[]), DeletionStep::ALL.to_vec());
+        assert_eq!(
+            DeletionStep::remaining(&[
+                "revoke_access".to_string(),
+                "purge_secrets".to_string(),
+                "families".to_string(),
+            ]),
+            vec![
+                DeletionStep::PersonalData,
+                DeletionStep::DeleteUser,
+                DeletionStep::DeleteLogin,
+            ]
+        );
+    }
+
+    #[test]
+    fn picks_family_successor() {
+        let members = vec![
+            member(1, "member", 1),
+            member(2, "admin", 5),
+            member(3, "admin", 3),
+        ];
+        assert_eq!(successor(&members), Some(Uuid::from_u128(3)));
+
+        let members = vec![member(1, "child", 1), member(2, "member", 9)];
+        assert_eq!(successor(&members), Some(Uuid::from_u128(2)));
+
+        assert_eq!(
+            successor(&[member(1, "child", 1)]),
+            Some(Uuid::from_u128(1))
+        );
+        assert_eq!(successor(&[]), None);
+    }
+
+    #[test]
+    fn names_user_secrets() {
+        let id = Uuid::nil();
+        assert_eq!(
+            user_secret_names(id),
+            vec![
+                "second-brain/calendar/00000000-0000-0000-0000-000000000000",
+                "second-brain/contacts/00000000-0000-0000-0000-000000000000",
+            ]
+        );
+    }
+}
@@ -1,10 +1,48 @@
 //! AgentCore client for invoking Python agents.
+//!
+//! When the agent function can't be reached, simple queries can be answered
+//! by a [`DirectFallback`] instead: facts are retrieved from the database and
+//! a small model answers from them through Bedrock, so chat surfaces degrade
+//! to a plainer answer rather than an error.
+//!
+//! Clients given a pool with [`AgentClient::with_query_log`] log each answered
+//! query, with the facts it was based on, in `query_sessions`.
 
 use aws_sdk_bedrockagentruntime::Client as BedrockAgentClient;
+use aws_sdk_bedrockruntime::types::{
+    ContentBlock, ConversationRole, InferenceConfiguration, Message, SystemContentBlock,
+};
 use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::time::{Duration, Instant};
+use tracing::warn;
+use uuid::Uuid;
 
+use crate::access::visibility_clause;
+use crate::auth::sign_internal;
+use crate::conversations::ConversationTurn;
+use crate::embeddings::{to_pgvector, EmbeddingClient};
+use crate::fact_search::{search_facts, SearchFilters};
+use crate::metrics;
+use crate::usage::{self, QueryUsage};
 use crate::{Error, Result};
 
+/// Model the fallback answers with (overridden by `FALLBACK_MODEL_ID`)
+pub const DEFAULT_FALLBACK_MODEL: &str = "us.anthropic.claude-3-haiku-20240307-v1:0";
+
+/// Facts retrieved for a fallback answer
+pub const FALLBACK_FACT_LIMIT: i64 = 8;
+
+/// Longest fallback answer, in tokens
+const FALLBACK_MAX_TOKENS: i32 = 512;
+
+/// Reported in `agents_used` for fallback answers
+pub const FALLBACK_AGENT: &str = "direct_fallback";
+
+/// Operation agent invocations are signed for (checked by the agents'
+/// `internal_auth.verify`)
+pub const AGENT_INVOKE_PATH: &str = "/agents/invoke";
+
 /// Request to the agent system.
 #[derive(Debug, Serialize)]
 pub struct AgentRequest {
@@ -18,10 +56,20 @@ pub struct AgentRequest {
     pub device_id: Option<String>,
     /// Conversation/session ID
     pub conversation_id: Option<String>,
+    /// Earlier messages in the conversation, oldest first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conversation_history: Vec<ConversationTurn>,
     /// Pre-classified intent (optional)
     pub intent: Option<String>,
     /// Source platform
     pub source: String,
+    /// How the message was captured (`voice` for transcribed audio; text if unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modality: Option<String>,
+    /// Where the message came from, for sources that can point back at it
+    /// (e.g. the document and pages an ingested chunk was extracted from)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
 }
 
 /// Response from the agent system.
@@ -37,6 +85,19 @@ pub struct AgentResponse {
     pub conversation_id: Option<String>,
     /// Additional metadata
     pub metadata: Option<AgentMetadata>,
+    /// Set when the query was logged (see [`AgentClient::with_query_log`])
+    #[serde(skip)]
+    pub query_id: Option<Uuid>,
+}
+
+impl AgentResponse {
+    /// Facts retrieved while answering, most relevant first.
+    pub fn source_fact_ids(&self) -> &[Uuid] {
+        self.metadata
+            .as_ref()
+            .and_then(|m| m.source_fact_ids.as_deref())
+            .unwrap_or_default()
+    }
 }
 
 /// Metadata about agent execution.
@@ -50,6 +111,28 @@ pub struct AgentMetadata {
     pub agents_used: Option<Vec<String>>,
     /// Number of handoffs
     pub handoff_count: Option<u32>,
+    /// Facts the answer was based on (only sent by agents that track them)
+    pub citations: Option<Vec<AgentCitation>>,
+    /// Confidence in the answer, 0.0-1.0 (only sent by agents that estimate it)
+    pub confidence: Option<f32>,
+    /// Facts stored (only sent by the ingestion pipeline)
+    pub fact_ids: Option<Vec<Uuid>>,
+    /// Facts retrieved while answering, most relevant first (only sent for
+    /// queries)
+    pub source_fact_ids: Option<Vec<Uuid>>,
+    /// Prompt tokens used, across all agents that ran
+    pub input_tokens: Option<u32>,
+    /// Completion tokens used, across all agents that ran
+    pub output_tokens: Option<u32>,
+}
+
+/// A fact cited in an agent's answer.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AgentCitation {
+    /// Short label, e.g. the entity the fact is about
+    pub title: Option<String>,
+    /// Fact content
+    pub content: String,
 }
 
 /// Client for invoking the agent system.
@@ -58,6 +141,10 @@ pub struct AgentClient {
     lambda_client: aws_sdk_lambda::Client,
     /// Agent Lambda function name/ARN
     agent_function_name: String,
+    /// Answers simple queries when the agent function is unavailable
+    fallback: Option<DirectFallback>,
+    /// Where answered queries are logged
+    query_log: Option<PgPool>,
 }
 
 impl AgentClient {
@@ -66,13 +153,68 @@ impl AgentClient {
         Self {
             lambda_client,
             agent_function_name,
+            fallback: None,
+            query_log: None,
         }
     }
 
-    /// Invoke the agent system.
+    /// Answer simple queries with `fallback` when the agent function can't be
+    /// reached.
+    pub fn with_fallback(mut self, fallback: DirectFallback) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Log each answered query (its response, latency, token usage and
+    /// source facts) in `query_sessions`, setting the response's `query_id`.
+    /// Surfaces that log queries themselves, like `POST /query`, don't.
+    pub fn with_query_log(mut self, pool: PgPool) -> Self {
+        self.query_log = Some(pool);
+        self
+    }
+
+    /// Invoke the agent system, falling back to a direct answer for simple
+    /// queries when it is unavailable (see [`is_simple_query`]).
+    ///
+    /// Records `AgentLatency` and `AgentErrors` per intent, fallback answers included.
     pub async fn invoke(&self, request: AgentRequest) -> Result<AgentResponse> {
-        let payload = serde_json::to_vec(&request)
-            .map_err(|e| Error::Serialization(e))?;
+        let started = Instant::now();
+        let mut result = match (self.invoke_agent(&request).await, &self.fallback) {
+            (Err(Error::Aws(e)), Some(fallback)) if is_simple_query(&request) => {
+                warn!(error = %e, "Agent unavailable, answering directly");
+                fallback.answer(&request).await
+            }
+            (result, _) => result,
+        };
+
+        let elapsed = started.elapsed();
+        let intent = request.intent.as_deref().unwrap_or("unclassified");
+        metrics::record_agent(intent, elapsed, result.is_ok());
+
+        if let (Ok(response), Some(pool)) = (&mut result, &self.query_log) {
+            if intent == "query" {
+                match log_query(pool, &request, response, elapsed).await {
+                    Ok(id) => response.query_id = Some(id),
+                    Err(e) => warn!(error = %e, "Failed to log query"),
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Invoke the agent function, signing the request so the agents can
+    /// trust its user (see [`crate::auth::sign_internal`]).
+    async fn invoke_agent(&self, request: &AgentRequest) -> Result<AgentResponse> {
+        let payload = sign_internal(
+            request,
+            "POST",
+            AGENT_INVOKE_PATH,
+            &request.user_id,
+            &request.family_ids,
+        )
+        .await?;
+        let payload = serde_json::to_vec(&payload).map_err(Error::Serialization)?;
 
         let response = self
             .lambda_client
@@ -105,6 +247,28 @@ impl AgentClient {
         family_ids: Vec<String>,
         conversation_id: Option<String>,
         source: &str,
+    ) -> Result<AgentResponse> {
+        self.query_with_history(
+            message,
+            user_id,
+            family_ids,
+            conversation_id,
+            Vec::new(),
+            source,
+        )
+        .await
+    }
+
+    /// Invoke for a follow-up query, with the conversation's earlier
+    /// messages (see `conversations::history`).
+    pub async fn query_with_history(
+        &self,
+        message: &str,
+        user_id: &str,
+        family_ids: Vec<String>,
+        conversation_id: Option<String>,
+        conversation_history: Vec<ConversationTurn>,
+        source: &str,
     ) -> Result<AgentResponse> {
         self.invoke(AgentRequest {
             message: message.to_string(),
@@ -112,8 +276,11 @@ impl AgentClient {
             family_ids,
             device_id: None,
             conversation_id,
+            conversation_history,
             intent: Some("query".to_string()),
             source: source.to_string(),
+            modality: None,
+            metadata: None,
         })
         .await
     }
@@ -125,6 +292,19 @@ impl AgentClient {
         user_id: &str,
         family_ids: Vec<String>,
         source: &str,
+    ) -> Result<AgentResponse> {
+        self.ingest_with_metadata(message, user_id, family_ids, source, None)
+            .await
+    }
+
+    /// Invoke for ingestion of content that carries source metadata.
+    pub async fn ingest_with_metadata(
+        &self,
+        message: &str,
+        user_id: &str,
+        family_ids: Vec<String>,
+        source: &str,
+        metadata: Option<serde_json::Value>,
     ) -> Result<AgentResponse> {
         self.invoke(AgentRequest {
             message: message.to_string(),
@@ -132,8 +312,11 @@ impl AgentClient {
             family_ids,
             device_id: None,
             conversation_id: None,
+            conversation_history: Vec::new(),
             intent: Some("ingest".to_string()),
             source: source.to_string(),
+            modality: None,
+            metadata,
         })
         .await
     }
@@ -159,9 +342,403 @@ impl AgentClient {
             family_ids,
             device_id: None,
             conversation_id: None,
+            conversation_history: Vec::new(),
             intent: Some("taxonomy".to_string()),
             source: "api".to_string(),
+            modality: None,
+            metadata: None,
         })
         .await
     }
 }
+
+/// Log an answered query in `query_sessions`, returning its ID. Callers send
+/// either the Cognito subject or the user ID as `user_id`.
+async fn log_query(
+    pool: &PgPool,
+    request: &AgentRequest,
+    response: &AgentResponse,
+    elapsed: Duration,
+) -> Result<Uuid> {
+    let user_id: Uuid =
+        sqlx::query_scalar("SELECT id FROM users WHERE cognito_sub = $1 OR id::text = $1")
+            .bind(&request.user_id)
+            .fetch_optional(pool)
+            .await?
+            .ok_or_else(|| Error::NotFound("User not found".to_string()))?;
+
+    let metadata = response.metadata.as_ref();
+    let agents_used = metadata
+        .and_then(|m| m.agents_used.clone())
+        .unwrap_or_default();
+
+    usage::record_query(
+        pool,
+        QueryUsage {
+            user_id,
+            source: &request.source,
+            query: &request.message,
+            response: &response.response,
+            agents_used: &agents_used,
+            model_id: metadata.and_then(|m| m.model_id.as_deref()),
+            conversation_id: None,
+            input_tokens: metadata.and_then(|m| m.input_tokens).unwrap_or(0),
+            output_tokens: metadata.and_then(|m| m.output_tokens).unwrap_or(0),
+            duration_ms: elapsed.as_millis().min(i32::MAX as u128) as i32,
+            source_fact_ids: response.source_fact_ids(),
+        },
+    )
+    .await
+}
+
+/// Whether a request can be answered without the agents: a query, or an
+/// unclassified message that asks a question. Anything that stores or changes
+/// data needs the agents.
+pub fn is_simple_query(request: &AgentRequest) -> bool {
+    match request.intent.as_deref() {
+        Some(intent) => intent == "query",
+        None => request.message.trim_end().ends_with('?'),
+    }
+}
+
+/// A fact retrieved for a fallback answer.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct RetrievedFact {
+    pub id: Uuid,
+    pub content: String,
+    pub entity_name: Option<String>,
+}
+
+/// Answers queries directly against Bedrock from retrieved facts.
+///
+/// Facts are found by embedding similarity, or by full-text search when the
+/// question can't be embedded, among those the user can read.
+pub struct DirectFallback {
+    bedrock: aws_sdk_bedrockruntime::Client,
+    embeddings: EmbeddingClient,
+    pool: PgPool,
+    model_id: String,
+}
+
+impl DirectFallback {
+    /// Create a fallback answering with [`DEFAULT_FALLBACK_MODEL`] or
+    /// `FALLBACK_MODEL_ID` when set.
+    pub fn from_env(config: &aws_config::SdkConfig, pool: PgPool) -> Self {
+        let bedrock = aws_sdk_bedrockruntime::Client::new(config);
+        Self {
+            embeddings: EmbeddingClient::new(bedrock.clone()),
+            bedrock,
+            pool,
+            model_id: std::env::var("FALLBACK_MODEL_ID")
+                .unwrap_or_else(|_| DEFAULT_FALLBACK_MODEL.to_string()),
+        }
+    }
+
+    /// Answer `request` from the user's facts.
+    pub async fn answer(&self, request: &AgentRequest) -> Result<AgentResponse> {
+        let (user_id, family_ids) = self.resolve_user(request).await?;
+        let facts = self
+            .retrieve(&request.message, user_id, &family_ids)
+            .await?;
+
+        let (response, usage) = if facts.is_empty() {
+            ("I don't have any information about that.".to_string(), None)
+        } else {
+            self.generate(&request.message, &facts, &request.conversation_history)
+                .await?
+        };
+
+        let source_fact_ids = facts.iter().map(|fact| fact.id).collect();
+
+        Ok(AgentResponse {
+            status: "success".to_string(),
+            response,
+            user_id: request.user_id.clone(),
+            conversation_id: request.conversation_id.clone(),
+            query_id: None,
+            metadata: Some(AgentMetadata {
+                source: Some(request.source.clone()),
+                model_id: Some(self.model_id.clone()),
+                agents_used: Some(vec![FALLBACK_AGENT.to_string()]),
+                handoff_count: None,
+                citations: Some(
+                    facts
+                        .into_iter()
+                        .map(|fact| AgentCitation {
+                            title: fact.entity_name,
+                            content: fact.content,
+                        })
+                        .collect(),
+                ),
+                confidence: None,
+                fact_ids: None,
+                source_fact_ids: Some(source_fact_ids),
+                input_tokens: usage.map(|(input, _)| input),
+                output_tokens: usage.map(|(_, output)| output),
+            }),
+        })
+    }
+
+    /// The user's ID and families. Callers send either the Cognito subject or
+    /// the user ID as `user_id`.
+    async fn resolve_user(&self, request: &AgentRequest) -> Result<(Uuid, Vec<Uuid>)> {
+        let user_id: Uuid =
+            sqlx::query_scalar("SELECT id FROM users WHERE cognito_sub = $1 OR id::text = $1")
+                .bind(&request.user_id)
+                .fetch_optional(&self.pool)
+                .await?
+                .ok_or_else(|| Error::NotFound("User not found".to_string()))?;
+
+        let mut family_ids: Vec<Uuid> = request
+            .family_ids
+            .iter()
+            .filter_map(|id| id.parse().ok())
+            .collect();
+        if family_ids.is_empty() {
+            family_ids =
+                sqlx::query_scalar("SELECT family_id FROM family_members WHERE user_id = $1")
+                    .bind(user_id)
+                    .fetch_all(&self.pool)
+                    .await?;
+        }
+
+        Ok((user_id, family_ids))
+    }
+
+    /// The facts most relevant to `question` that the user can read.
+    async fn retrieve(
+        &self,
+        question: &str,
+        user_id: Uuid,
+        family_ids: &[Uuid],
+    ) -> Result<Vec<RetrievedFact>> {
+        let embedding = match self.embeddings.embed(question).await {
+            Ok(embedding) => embedding,
+            Err(e) => {
+                warn!(error = %e, "Embedding failed, retrieving by full-text search");
+                let (hits, _) = search_facts(
+                    &self.pool,
+                    question,
+                    user_id,
+                    family_ids,
+                    &SearchFilters::default(),
+                    FALLBACK_FACT_LIMIT,
+                    0,
+                )
+                .await?;
+                return Ok(hits
+                    .into_iter()
+                    .map(|hit| RetrievedFact {
+                        id: hit.id,
+                        content: hit.content,
+                        entity_name: hit.entity_name,
+                    })
+                    .collect());
+            }
+        };
+
+        let facts = sqlx::query_as(&format!(
+            r#"
+            SELECT f.id, f.content, e.name AS entity_name
+            FROM facts f
+            JOIN fact_embeddings fe ON fe.fact_id = f.id AND fe.model_id = $2
+            LEFT JOIN entities e ON e.id = f.about_entity_id
+            WHERE f.deleted_at IS NULL
+            AND f.superseded_by IS NULL
+            AND {}
+            ORDER BY fe.embedding <=> $1::vector
+            LIMIT $5
+            "#,
+            visibility_clause("f", 3)
+        ))
+        .bind(to_pgvector(&embedding))
+        .bind(self.embeddings.model_id())
+        .bind(user_id)
+        .bind(family_ids)
+        .bind(FALLBACK_FACT_LIMIT)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(facts)
+    }
+
+    /// Ask the model to answer `question` from `facts`, returning the answer
+    /// and the input/output tokens used, when reported.
+    async fn generate(
+        &self,
+        question: &str,
+        facts: &[RetrievedFact],
+        history: &[ConversationTurn],
+    ) -> Result<(String, Option<(u32, u32)>)> {
+        let message = Message::builder()
+            .role(ConversationRole::User)
+            .content(ContentBlock::Text(question.to_string()))
+            .build()
+            .map_err(|e| Error::Internal(format!("Failed to build message: {}", e)))?;
+
+        let response = self
+            .bedrock
+            .converse()
+            .model_id(&self.model_id)
+            .system(SystemContentBlock::Text(fallback_prompt(facts, history)))
+            .messages(message)
+            .inference_config(
+                InferenceConfiguration::builder()
+                    .max_tokens(FALLBACK_MAX_TOKENS)
+                    .temperature(0.2)
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(|e| Error::Aws(format!("Failed to invoke fallback model: {}", e)))?;
+
+        let answer = response
+            .output()
+            .and_then(|output| output.as_message().ok())
+            .map(|message| {
+                message
+                    .content()
+                    .iter()
+                    .filter_map(|block| block.as_text().ok())
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join("")
+            })
+            .filter(|answer| !answer.trim().is_empty())
+            .ok_or_else(|| Error::Aws("Empty response from fallback model".to_string()))?;
+
+        let usage = response.usage().map(|usage| {
+            (
+                usage.input_tokens().max(0) as u32,
+                usage.output_tokens().max(0) as u32,
+            )
+        });
+
+        Ok((answer.trim().to_string(), usage))
+    }
+}
+
+/// System prompt for a fallback answer: the retrieved facts and any earlier
+/// turns of the conversation.
+pub fn fallback_prompt(facts: &[RetrievedFact], history: &[ConversationTurn]) -> String {
+    let mut prompt = String::from(
+        "You answer questions about the user's personal knowledge base. \
+         Answer briefly, using only the facts below. If they don't answer the \
+         question, say you don't have that information.\n\nFacts:\n",
+    );
+
+    for fact in facts {
+        match &fact.entity_name {
+            Some(entity) => prompt.push_str(&format!("- [{}] {}\n", entity, fact.content)),
+            None => prompt.push_str(&format!("- {}\n", fact.content)),
+        }
+    }
+
+    if !history.is_empty() {
+        prompt.push_str("\nEarlier in this conversation:\n");
+        for turn in history {
+            prompt.push_str(&format!("{}: {}\n", turn.role, turn.content));
+        }
+    }
+
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(message: &str, intent: Option<&str>) -> AgentRequest {
+        AgentRequest {
+            message: message.to_string(),
+            user_id: "user".to_string(),
+            family_ids: Vec::new(),
+            device_id: None,
+            conversation_id: None,
+            conversation_history: Vec::new(),
+            intent: intent.map(String::from),
+            source: "api".to_string(),
+            modality: None,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn queries_are_simple() {
+        assert!(is_simple_query(&request(
+            "Where does Sam work",
+            Some("query")
+        )));
+        assert!(is_simple_query(&request("Where does Sam work? ", None)));
+    }
+
+    #[test]
+    fn writes_need_the_agents() {
+        assert!(!is_simple_query(&request(
+            "Sam works at Acme",
+            Some("ingest")
+        )));
+        assert!(!is_simple_query(&request("Sam works at Acme", None)));
+        assert!(!is_simple_query(&request(
+            "Suggest tags?",
+            Some("taxonomy")
+        )));
+    }
+
+    #[test]
+    fn prompt_lists_facts_and_history() {
+        let prompt = fallback_prompt(
+            &[
+                RetrievedFact {
+                    id: Uuid::new_v4(),
+                    content: "Works at Acme".to_string(),
+                    entity_name: Some("Sam".to_string()),
+                },
+                RetrievedFact {
+                    id: Uuid::new_v4(),
+                    content: "Dentist on Friday".to_string(),
+                    entity_name: None,
+                },
+            ],
+            &[ConversationTurn {
+                role: "user".to_string(),
+                content: "Who is Sam?".to_string(),
+            }],
+        );
+
+        assert!(prompt.contains("- [Sam] Works at Acme\n"));
+        assert!(prompt.contains("- Dentist on Friday\n"));
+        assert!(prompt.contains("Earlier in this conversation:\nuser: Who is Sam?\n"));
+    }
+
+    #[test]
+    fn prompt_omits_empty_history() {
+        assert!(!fallback_prompt(&[], &[]).contains("Earlier in this conversation"));
+    }
+
+    #[test]
+    fn responses_carry_their_sources() {
+        let fact_id = Uuid::new_v4();
+        let response: AgentResponse = serde_json::from_value(serde_json::json!({
+            "status": "success",
+            "response": "Sam works at Acme",
+            "user_id": "sub",
+            "conversation_id": null,
+            "metadata": { "source": "slack", "source_fact_ids": [fact_id] },
+        }))
+        .unwrap();
+        assert_eq!(response.source_fact_ids(), &[fact_id]);
+        assert_eq!(response.query_id, None);
+
+        // Older agents don't report sources
+        let response: AgentResponse = serde_json::from_value(serde_json::json!({
+            "status": "success",
+            "response": "Hello",
+            "user_id": "sub",
+            "conversation_id": null,
+            "metadata": null,
+        }))
+        .unwrap();
+        assert!(response.source_fact_ids().is_empty());
+    }
+}
@@ -0,0 +1,190 @@
+//! Alexa notifications through the Proactive Events API.
+//!
+//! The skill records each linked Alexa user in `alexa_links`, along with the
+//! regional API endpoint and whether they granted the notifications
+//! permission. `notification_sender` authenticates as the skill with a Login
+//! with Amazon client-credentials token and sends a Unicast
+//! `AMAZON.MessageAlert.Activated` event, which Alexa announces as a new
+//! message from Second Brain. This module builds those requests; the HTTP calls
+//! are made by `notification_sender`.
+
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use serde_json::{json, Value};
+
+/// Login with Amazon token endpoint
+pub const LWA_TOKEN_URL: &str = "https://api.amazon.com/auth/o2/token";
+
+/// Scope of the client-credentials token for sending proactive events
+pub const PROACTIVE_EVENTS_SCOPE: &str = "alexa::proactive_events";
+
+/// Permission the user grants the skill in the Alexa app to receive notifications
+pub const NOTIFICATIONS_PERMISSION: &str = "alexa::devices:all:notifications:write";
+
+/// Endpoint used when a link has none recorded (North America)
+pub const DEFAULT_API_ENDPOINT: &str = "https://api.amazonalexa.com";
+
+/// Seconds an LWA access token is valid for when the response doesn't say
+pub const LWA_TOKEN_LIFETIME_SECS: i64 = 3600;
+
+/// How long Alexa keeps an undelivered event; the API allows 5 minutes to 24 hours
+pub const EVENT_EXPIRY_HOURS: i64 = 24;
+
+/// The event schema notifications are sent as
+pub const MESSAGE_ALERT_EVENT: &str = "AMAZON.MessageAlert.Activated";
+
+/// Priority at and above which events are marked urgent
+const URGENT_PRIORITY: i16 = 4;
+
+/// Form body for the LWA client-credentials token request.
+pub fn token_request<'a>(
+    client_id: &'a str,
+    client_secret: &'a str,
+) -> [(&'static str, &'a str); 4] {
+    [
+        ("grant_type", "client_credentials"),
+        ("client_id", client_id),
+        ("client_secret", client_secret),
+        ("scope", PROACTIVE_EVENTS_SCOPE),
+    ]
+}
+
+/// URL events are posted to: the live stage, or the development stage for
+/// skills that haven't been certified yet.
+pub fn events_url(api_endpoint: &str, development: bool) -> String {
+    let endpoint = api_endpoint.trim_end_matches('/');
+    if development {
+        format!("{}/v1/proactiveEvents/stages/development", endpoint)
+    } else {
+        format!("{}/v1/proactiveEvents", endpoint)
+    }
+}
+
+/// Request body announcing a new message to one Alexa user.
+///
+/// The message schema carries no text, so Alexa only says there is a message
+/// from `creator`; the user asks the skill for the details. `reference_id`
+/// identifies the event (the notification ID) so a retry replaces it.
+pub fn message_alert(
+    alexa_user_id: &str,
+    reference_id: &str,
+    creator: &str,
+    priority: i16,
+    now: DateTime<Utc>,
+) -> Value {
+    let mut message_group = json!({
+        "creator": { "name": creator },
+        "count": 1,
+    });
+    if priority >= URGENT_PRIORITY {
+        message_group["urgency"] = json!("URGENT");
+    }
+
+    json!({
+        "timestamp": now.to_rfc3339_opts(SecondsFormat::Millis, true),
+        "referenceId": reference_id,
+        "expiryTime": (now + Duration::hours(EVENT_EXPIRY_HOURS))
+            .to_rfc3339_opts(SecondsFormat::Millis, true),
+        "event": {
+            "name": MESSAGE_ALERT_EVENT,
+            "payload": {
+                "state": {
+                    "status": "UNREAD",
+                    "freshness": "NEW",
+                },
+                "messageGroup": message_group,
+            },
+        },
+        "relevantAudience": {
+            "type": "Unicast",
+            "payload": { "user": alexa_user_id },
+        },
+    })
+}
+
+/// Whether a skill permission event's body grants notifications.
+///
+/// `AlexaSkillEvent.SkillPermissionAccepted` and `SkillPermissionChanged` list
+/// every permission currently granted.
+pub fn grants_notifications(event_body: &Value) -> bool {
+    event_body["acceptedPermissions"]
+        .as_array()
+        .is_some_and(|permissions| {
+            permissions
+                .iter()
+                .any(|p| p["scope"].as_str() == Some(NOTIFICATIONS_PERMISSION))
+        })
+}
+
+/// Whether an `AlexaSkillEvent.ProactiveSubscriptionChanged` body still
+/// subscribes the user to the events `notification_sender` sends.
+pub fn subscribes_to_messages(event_body: &Value) -> bool {
+    event_body["subscriptions"]
+        .as_array()
+        .is_some_and(|subscriptions| {
+            subscriptions
+                .iter()
+                .any(|s| s["eventName"].as_str() == Some(MESSAGE_ALERT_EVENT))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_events_url() {
+        assert_eq!(
+            events_url("https://api.eu.amazonalexa.com/", false),
+            "https://api.eu.amazonalexa.com/v1/proactiveEvents"
+        );
+        assert_eq!(
+            events_url(DEFAULT_API_ENDPOINT, true),
+            "https://api.amazonalexa.com/v1/proactiveEvents/stages/development"
+        );
+    }
+
+    #[test]
+    fn test_message_alert() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 16, 9, 30, 0).unwrap();
+        let event = message_alert("amzn1.ask.account.A", "n-1", "Second Brain", 4, now);
+
+        assert_eq!(event["referenceId"], "n-1");
+        assert_eq!(event["timestamp"], "2026-10-16T09:30:00.000Z");
+        assert_eq!(event["expiryTime"], "2026-10-17T09:30:00.000Z");
+        assert_eq!(
+            event["relevantAudience"]["payload"]["user"],
+            "amzn1.ask.account.A"
+        );
+        assert_eq!(
+            event["event"]["payload"]["messageGroup"]["creator"]["name"],
+            "Second Brain"
+        );
+        assert_eq!(
+            event["event"]["payload"]["messageGroup"]["urgency"],
+            "URGENT"
+        );
+
+        let routine = message_alert("amzn1.ask.account.A", "n-2", "Second Brain", 2, now);
+        assert!(routine["event"]["payload"]["messageGroup"]
+            .get("urgency")
+            .is_none());
+    }
+
+    #[test]
+    fn test_grants_notifications() {
+        let accepted = json!({
+            "acceptedPermissions": [
+                { "scope": "alexa::profile:email:read" },
+                { "scope": NOTIFICATIONS_PERMISSION },
+            ]
+        });
+        assert!(grants_notifications(&accepted));
+        assert!(!grants_notifications(&json!({ "acceptedPermissions": [] })));
+        assert!(!grants_notifications(&Value::Null));
+
+        let subscribed = json!({ "subscriptions": [{ "eventName": MESSAGE_ALERT_EVENT }] });
+        assert!(subscribes_to_messages(&subscribed));
+        assert!(!subscribes_to_messages(&json!({ "subscriptions": [] })));
+    }
+}
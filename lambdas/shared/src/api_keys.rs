@@ -0,0 +1,306 @@
+//! Personal API keys for programmatic access.
+//!
+//! Scripts and home-automation integrations can't sign in through Cognito,
+//! so users issue themselves keys with `POST /api-keys` and send them in the
+//! `X-Api-Key` header. [`crate::AuthorizedUser::from_request`] accepts a key
+//! wherever it would accept Cognito claims. Only a SHA-256 hash of each key
+//! is stored (hashed by Postgres, like handoff share tokens), and a `read`
+//! key can only make GET requests.
+
+use chrono::{DateTime, Utc};
+use lambda_http::Request;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{Error, Result};
+
+/// Header scripts send their key in
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Every key starts with this, so leaked keys are easy to search for
+pub const KEY_PREFIX: &str = "sb_";
+
+/// Characters of a key kept to tell it apart (`sb_` plus 8)
+pub const DISPLAY_PREFIX_LEN: usize = 11;
+
+/// Most keys a user can have that aren't revoked
+pub const MAX_KEYS_PER_USER: i64 = 20;
+
+/// Longest key name
+pub const MAX_NAME_LEN: usize = 100;
+
+/// What a key may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// GET requests only
+    Read,
+    /// Everything, including ingesting facts
+    Write,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+        }
+    }
+
+    pub fn parse(scope: &str) -> Option<Self> {
+        match scope {
+            "read" => Some(Self::Read),
+            "write" => Some(Self::Write),
+            _ => None,
+        }
+    }
+
+    /// Whether a request with this HTTP method is allowed.
+    pub fn allows(&self, method: &str) -> bool {
+        match self {
+            Self::Read => matches!(method, "GET" | "HEAD" | "OPTIONS"),
+            Self::Write => true,
+        }
+    }
+}
+
+/// A key as listed to its owner; the key itself is never stored
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub name: String,
+    /// `read` or `write`
+    pub scope: String,
+    /// Start of the key, e.g. `sb_1a2b3c4d`
+    pub key_prefix: String,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A newly issued key, returned once
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IssuedKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    /// Only returned now; store it somewhere safe
+    pub key: String,
+}
+
+/// The user a key belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyOwner {
+    pub key_id: Uuid,
+    pub user_id: Uuid,
+    pub cognito_sub: String,
+    pub scope: Scope,
+}
+
+/// Random key (`sb_` plus two v4 UUIDs, 244 bits of randomness).
+pub fn generate_key() -> String {
+    format!(
+        "{}{}{}",
+        KEY_PREFIX,
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+/// The part of a key kept to tell it apart.
+pub fn display_prefix(key: &str) -> &str {
+    key.get(..DISPLAY_PREFIX_LEN).unwrap_or(key)
+}
+
+/// The API key a request carries, if any.
+pub fn key_from_request(req: &Request) -> Option<&str> {
+    req.headers()
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|key| key.starts_with(KEY_PREFIX))
+}
+
+/// Check a key's name.
+pub fn validate_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(Error::Validation("name must not be empty".to_string()));
+    }
+    if name.chars().count() > MAX_NAME_LEN {
+        return Err(Error::Validation(format!(
+            "name must be at most {} characters",
+            MAX_NAME_LEN
+        )));
+    }
+    Ok(name.to_string())
+}
+
+/// Columns selected into [`ApiKey`]
+const KEY_COLUMNS: &str =
+    "id, name, scope, key_prefix, last_used_at, expires_at, revoked_at, created_at";
+
+/// Issue a key. Returns `Error::Conflict` if the user already has
+/// [`MAX_KEYS_PER_USER`] keys in use.
+pub async fn create_key(
+    pool: &PgPool,
+    user_id: Uuid,
+    name: &str,
+    scope: Scope,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<IssuedKey> {
+    let name = validate_name(name)?;
+    if expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+        return Err(Error::Validation(
+            "expiresAt must be in the future".to_string(),
+        ));
+    }
+
+    let in_use: i64 = sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*) FROM api_keys
+        WHERE user_id = $1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > NOW())
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    if in_use >= MAX_KEYS_PER_USER {
+        return Err(Error::Conflict(format!(
+            "You can have at most {} API keys; revoke one first",
+            MAX_KEYS_PER_USER
+        )));
+    }
+
+    let key = generate_key();
+    let api_key: ApiKey = sqlx::query_as(&format!(
+        r#"
+        INSERT INTO api_keys (user_id, name, scope, key_prefix, key_hash, expires_at)
+        VALUES ($1, $2, $3, $4, sha256(convert_to($5, 'UTF8')), $6)
+        RETURNING {}
+        "#,
+        KEY_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(&name)
+    .bind(scope.as_str())
+    .bind(display_prefix(&key))
+    .bind(&key)
+    .bind(expires_at)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(IssuedKey { api_key, key })
+}
+
+/// The user's keys, newest first, including revoked and expired ones.
+pub async fn list_keys(pool: &PgPool, user_id: Uuid) -> Result<Vec<ApiKey>> {
+    let keys = sqlx::query_as(&format!(
+        "SELECT {} FROM api_keys WHERE user_id = $1 ORDER BY created_at DESC",
+        KEY_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(keys)
+}
+
+/// Revoke one of the user's keys. Returns whether a key in use was revoked.
+pub async fn revoke_key(pool: &PgPool, key_id: Uuid, user_id: Uuid) -> Result<bool> {
+    let revoked = sqlx::query(
+        "UPDATE api_keys SET revoked_at = NOW() WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+    )
+    .bind(key_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(revoked > 0)
+}
+
+/// Look up the owner of a key and note that it was used. Returns
+/// `Error::Auth` for unknown, revoked and expired keys.
+pub async fn authenticate(pool: &PgPool, key: &str) -> Result<KeyOwner> {
+    let row: Option<(Uuid, Uuid, String, String)> = sqlx::query_as(
+        r#"
+        UPDATE api_keys k SET last_used_at = NOW()
+        FROM users u
+        WHERE k.key_hash = sha256(convert_to($1, 'UTF8'))
+          AND k.revoked_at IS NULL
+          AND (k.expires_at IS NULL OR k.expires_at > NOW())
+          AND u.id = k.user_id
+        RETURNING k.id, k.user_id, u.cognito_sub, k.scope
+        "#,
+    )
+    .bind(key)
+    .fetch_optional(pool)
+    .await?;
+
+    let (key_id, user_id, cognito_sub, scope) =
+        row.ok_or_else(|| Error::Auth("Invalid or revoked API key".to_string()))?;
+    let scope = Scope::parse(&scope)
+        .ok_or_else(|| Error::Auth(format!("Unknown API key scope: {}", scope)))?;
+
+    Ok(KeyOwner {
+        key_id,
+        user_id,
+        cognito_sub,
+        scope,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_key() {
+        let key = generate_key();
+        assert!(key.starts_with(KEY_PREFIX));
+        assert_eq!(key.len(), KEY_PREFIX.len() + 64);
+        assert_ne!(key, generate_key());
+        assert_eq!(display_prefix(&key).len(), DISPLAY_PREFIX_LEN);
+        assert!(key.starts_with(display_prefix(&key)));
+    }
+
+    #[test]
+    fn test_scope_allows() {
+        assert!(Scope::Read.allows("GET"));
+        assert!(!Scope::Read.allows("POST"));
+        assert!(!Scope::Read.allows("DELETE"));
+        assert!(Scope::Write.allows("POST"));
+        assert_eq!(Scope::parse("write"), Some(Scope::Write));
+        assert_eq!(Scope::parse("admin"), None);
+    }
+
+    #[test]
+    fn test_key_from_request() {
+        let req = lambda_http::http::Request::builder()
+            .header("X-Api-Key", " sb_abc ")
+            .body(lambda_http::Body::Empty)
+            .unwrap();
+        assert_eq!(key_from_request(&req), Some("sb_abc"));
+
+        let req = lambda_http::http::Request::builder()
+            .header("X-Api-Key", "not-ours")
+            .body(lambda_http::Body::Empty)
+            .unwrap();
+        assert_eq!(key_from_request(&req), None);
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert_eq!(
+            validate_name("  Home Assistant ").unwrap(),
+            "Home Assistant"
+        );
+        assert!(validate_name("   ").is_err());
+        assert!(validate_name(&"x".repeat(MAX_NAME_LEN + 1)).is_err());
+    }
+}
@@ -0,0 +1,306 @@
+//! Audit log of changes to user data.
+//!
+//! Mutating endpoints record who created, updated or deleted a fact, entity,
+//! tag, relationship or family membership in `audit_log`, with a JSON snapshot
+//! of the row before and after the change. Each entry is attributed to the
+//! user or family owning the row, which decides who can read it through
+//! `GET /audit`: users see changes to their own data and changes they made;
+//! family admins and the family's creator see changes to the family's data.
+//!
+//! Recording never fails the request: the change has already been made, so
+//! an entry that can't be written is logged instead.
+
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::trash::TrashKind;
+use crate::Result;
+
+/// What was done
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    Restore,
+    Merge,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Create => "create",
+            Self::Update => "update",
+            Self::Delete => "delete",
+            Self::Restore => "restore",
+            Self::Merge => "merge",
+        }
+    }
+}
+
+/// What it was done to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditResource {
+    Fact,
+    /// A fact's tag assignments (keyed by fact ID)
+    FactTags,
+    Entity,
+    EntityRelationship,
+    Tag,
+    /// User-to-user relationship (access tiers)
+    Relationship,
+    FamilyMember,
+}
+
+impl AuditResource {
+    pub const ALL: [AuditResource; 7] = [
+        Self::Fact,
+        Self::FactTags,
+        Self::Entity,
+        Self::EntityRelationship,
+        Self::Tag,
+        Self::Relationship,
+        Self::FamilyMember,
+    ];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|r| r.as_str() == value)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Fact => "fact",
+            Self::FactTags => "fact_tags",
+            Self::Entity => "entity",
+            Self::EntityRelationship => "entity_relationship",
+            Self::Tag => "tag",
+            Self::Relationship => "relationship",
+            Self::FamilyMember => "family_member",
+        }
+    }
+
+    /// Query returning the row with ID `$1` as a JSON object. Snapshots
+    /// include the owner columns `owner_scope` reads.
+    fn snapshot_query(&self) -> &'static str {
+        match self {
+            Self::Fact => "SELECT to_jsonb(t) - 'search_vector' FROM facts t WHERE t.id = $1",
+            Self::FactTags => {
+                r#"
+                SELECT jsonb_build_object(
+                    'fact_id', f.id,
+                    'owner_type', f.owner_type,
+                    'owner_id', f.owner_id,
+                    'tags', COALESCE(
+                        jsonb_agg(t.path ORDER BY t.path) FILTER (WHERE t.id IS NOT NULL),
+                        '[]'::jsonb
+                    )
+                )
+                FROM facts f
+                LEFT JOIN fact_tags ft ON ft.fact_id = f.id
+                LEFT JOIN tags t ON t.id = ft.tag_id
+                WHERE f.id = $1
+                GROUP BY f.id
+                "#
+            }
+            Self::Entity => "SELECT to_jsonb(t) FROM entities t WHERE t.id = $1",
+            Self::EntityRelationship => {
+                r#"
+                SELECT to_jsonb(r) || jsonb_build_object(
+                    'owner_type', e.owner_type, 'owner_id', e.owner_id
+                )
+                FROM entity_relationships r
+                JOIN entities e ON e.id = r.source_entity_id
+                WHERE r.id = $1
+                "#
+            }
+            Self::Tag => "SELECT to_jsonb(t) FROM tags t WHERE t.id = $1",
+            Self::Relationship => "SELECT to_jsonb(t) FROM relationships t WHERE t.id = $1",
+            Self::FamilyMember => "SELECT to_jsonb(t) FROM family_members t WHERE t.id = $1",
+        }
+    }
+}
+
+impl From<TrashKind> for AuditResource {
+    fn from(kind: TrashKind) -> Self {
+        match kind {
+            TrashKind::Fact => Self::Fact,
+            TrashKind::Entity => Self::Entity,
+            TrashKind::Tag => Self::Tag,
+        }
+    }
+}
+
+/// Who an entry belongs to: `(owner user, family)`. Read from whichever
+/// snapshot exists; system tags belong to nobody.
+pub fn owner_scope(
+    resource: AuditResource,
+    before: Option<&Value>,
+    after: Option<&Value>,
+) -> (Option<Uuid>, Option<Uuid>) {
+    let Some(snapshot) = after.or(before) else {
+        return (None, None);
+    };
+    let uuid = |key: &str| {
+        snapshot
+            .get(key)
+            .and_then(Value::as_str)
+            .and_then(|s| Uuid::parse_str(s).ok())
+    };
+
+    match resource {
+        AuditResource::Relationship => (uuid("source_user_id"), None),
+        AuditResource::FamilyMember => (None, uuid("family_id")),
+        _ => match snapshot.get("owner_type").and_then(Value::as_str) {
+            Some("user") => (uuid("owner_id"), None),
+            Some("family") => (None, uuid("owner_id")),
+            _ => (None, None),
+        },
+    }
+}
+
+/// Current state of a row, or `None` if it doesn't exist (or can't be read).
+pub async fn snapshot(pool: &PgPool, resource: AuditResource, id: Uuid) -> Option<Value> {
+    match sqlx::query_scalar(resource.snapshot_query())
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+    {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            tracing::warn!(
+                "Failed to snapshot {} {} for audit: {}",
+                resource.as_str(),
+                id,
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Record a change made by `actor_id`, snapshotting the row as it is now.
+/// `before` is the snapshot taken before the change (`None` for creates).
+pub async fn record_change(
+    pool: &PgPool,
+    actor_id: Uuid,
+    action: AuditAction,
+    resource: AuditResource,
+    resource_id: Uuid,
+    before: Option<Value>,
+) {
+    let after = snapshot(pool, resource, resource_id).await;
+    record(pool, actor_id, action, resource, resource_id, before, after).await;
+}
+
+/// Record a change with both snapshots given.
+pub async fn record(
+    pool: &PgPool,
+    actor_id: Uuid,
+    action: AuditAction,
+    resource: AuditResource,
+    resource_id: Uuid,
+    before: Option<Value>,
+    after: Option<Value>,
+) {
+    if let Err(e) = insert(pool, actor_id, action, resource, resource_id, before, after).await {
+        tracing::warn!(
+            actor_id = %actor_id,
+            "Failed to record audit entry for {} {} {}: {}",
+            action.as_str(),
+            resource.as_str(),
+            resource_id,
+            e
+        );
+    }
+}
+
+async fn insert(
+    pool: &PgPool,
+    actor_id: Uuid,
+    action: AuditAction,
+    resource: AuditResource,
+    resource_id: Uuid,
+    before: Option<Value>,
+    after: Option<Value>,
+) -> Result<()> {
+    let (owner_user_id, family_id) = owner_scope(resource, before.as_ref(), after.as_ref());
+
+    sqlx::query(
+        r#"
+        INSERT INTO audit_log (actor_id, action, resource_type, resource_id,
+                               owner_user_id, family_id, before, after)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        "#,
+    )
+    .bind(actor_id)
+    .bind(action.as_str())
+    .bind(resource.as_str())
+    .bind(resource_id)
+    .bind(owner_user_id)
+    .bind(family_id)
+    .bind(before)
+    .bind(after)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const USER: &str = "00000000-0000-0000-0000-000000000001";
+    const FAMILY: &str = "00000000-0000-0000-0000-000000000002";
+
+    #[test]
+    fn parses_resources() {
+        for resource in AuditResource::ALL {
+            assert_eq!(AuditResource::parse(resource.as_str()), Some(resource));
+        }
+        assert_eq!(AuditResource::parse("users"), None);
+    }
+
+    #[test]
+    fn scopes_owned_rows() {
+        let user_fact = json!({ "owner_type": "user", "owner_id": USER });
+        assert_eq!(
+            owner_scope(AuditResource::Fact, None, Some(&user_fact)),
+            (Some(Uuid::parse_str(USER).unwrap()), None)
+        );
+
+        // Deleted rows are scoped by their last snapshot
+        let family_entity = json!({ "owner_type": "family", "owner_id": FAMILY });
+        assert_eq!(
+            owner_scope(AuditResource::Entity, Some(&family_entity), None),
+            (None, Some(Uuid::parse_str(FAMILY).unwrap()))
+        );
+
+        let system_tag = json!({ "owner_type": null, "owner_id": null });
+        assert_eq!(
+            owner_scope(AuditResource::Tag, None, Some(&system_tag)),
+            (None, None)
+        );
+        assert_eq!(owner_scope(AuditResource::Tag, None, None), (None, None));
+    }
+
+    #[test]
+    fn scopes_relationships_and_memberships() {
+        let relationship = json!({ "source_user_id": USER, "target_user_id": FAMILY });
+        assert_eq!(
+            owner_scope(AuditResource::Relationship, Some(&relationship), None),
+            (Some(Uuid::parse_str(USER).unwrap()), None)
+        );
+
+        let member = json!({ "family_id": FAMILY, "user_id": USER, "role": "member" });
+        assert_eq!(
+            owner_scope(AuditResource::FamilyMember, None, Some(&member)),
+            (None, Some(Uuid::parse_str(FAMILY).unwrap()))
+        );
+    }
+}
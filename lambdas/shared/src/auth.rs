@@ -1,8 +1,21 @@
-//! JWT authentication utilities.
+//! JWT authentication utilities, and signing of internal Lambda invocations.
 
+use hmac::{Hmac, Mac};
 use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use lambda_http::{Request, RequestExt};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
 
+use crate::api_keys;
+use crate::device_auth;
+use crate::metrics;
+use crate::ratelimit;
+use crate::secrets::{get_secret, refresh_secret};
 use crate::{Error, Result};
 
 /// JWT claims from Cognito.
@@ -58,6 +71,236 @@ impl TryFrom<CognitoClaims> for AuthenticatedUser {
     }
 }
 
+impl AuthenticatedUser {
+    /// Extract the user from the Cognito authorizer claims on an API Gateway request.
+    pub fn from_request(req: &Request) -> Result<Self> {
+        let claims = req
+            .request_context_ref()
+            .and_then(|ctx| ctx.authorizer().and_then(|a| a.fields.get("claims").cloned()))
+            .ok_or_else(|| Error::Auth("Missing claims".to_string()))?;
+
+        extract_user_from_context(&claims)
+    }
+}
+
+impl From<AuthorizedUser> for AuthenticatedUser {
+    /// For Lambdas that identify users by Cognito subject, such as ingest.
+    fn from(user: AuthorizedUser) -> Self {
+        Self {
+            user_id: user.cognito_sub,
+            email: user.email,
+            family_ids: user.family_ids.iter().map(Uuid::to_string).collect(),
+        }
+    }
+}
+
+/// How long a resolved user stays cached in a warm container.
+///
+/// Family membership changes made through another container can take up to
+/// this long to be observed.
+pub const USER_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// A Cognito user (or API key or device token holder) resolved to their
+/// database identity.
+#[derive(Debug, Clone)]
+pub struct AuthorizedUser {
+    /// Database user id (`users.id`)
+    pub user_id: Uuid,
+    /// Cognito subject
+    pub cognito_sub: String,
+    /// User's email
+    pub email: Option<String>,
+    /// Families the user belongs to (`family_members.family_id`)
+    pub family_ids: Vec<Uuid>,
+    /// Set when the request was made with an API key rather than Cognito
+    pub api_key_id: Option<Uuid>,
+    /// Set when the request was made with a device access token rather than
+    /// Cognito (`device_sessions.id`)
+    pub device_session_id: Option<Uuid>,
+}
+
+struct CachedUser {
+    user_id: Uuid,
+    family_ids: Vec<Uuid>,
+    cached_at: Instant,
+}
+
+fn user_cache() -> &'static Mutex<HashMap<String, CachedUser>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, CachedUser>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+impl AuthorizedUser {
+    /// Decode the request's Cognito claims and resolve the database user and families.
+    ///
+    /// Requests without claims may carry an API key instead (see
+    /// [`crate::api_keys`]; a `read` key only authorizes GET requests) or a
+    /// device access token (see [`crate::device_auth`]).
+    /// Lookups are cached in memory per container for [`USER_CACHE_TTL`].
+    /// Returns `Error::Auth` if the request has no claims, valid key or
+    /// token, or the user is not registered, and `Error::RateLimited` if the
+    /// caller is over the router's rate limit (see [`crate::ratelimit`]).
+    pub async fn from_request(req: &Request, pool: &PgPool) -> Result<Self> {
+        let user = Self::authenticate(req, pool).await;
+        ratelimit::check_caller(req, user.as_ref().ok().map(|u| u.cognito_sub.as_str()))?;
+        user
+    }
+
+    async fn authenticate(req: &Request, pool: &PgPool) -> Result<Self> {
+        let claims_error = match AuthenticatedUser::from_request(req) {
+            Ok(claims) => return Self::resolve(claims, pool).await,
+            Err(e) => e,
+        };
+        if let Some(key) = api_keys::key_from_request(req) {
+            return Self::from_api_key(key, req.method().as_str(), pool).await;
+        }
+        if let Some(token) = device_auth::token_from_request(req) {
+            return Self::from_device_token(token, pool).await;
+        }
+        Err(claims_error)
+    }
+
+    /// Resolve the owner of an API key making a `method` request.
+    ///
+    /// Keys aren't cached, so revoking one takes effect immediately.
+    pub async fn from_api_key(key: &str, method: &str, pool: &PgPool) -> Result<Self> {
+        let owner = api_keys::authenticate(pool, key).await?;
+        if !owner.scope.allows(method) {
+            return Err(Error::Auth("This API key is read-only".to_string()));
+        }
+
+        let family_ids = Self::families(owner.user_id, pool).await?;
+
+        Ok(Self {
+            user_id: owner.user_id,
+            cognito_sub: owner.cognito_sub,
+            email: None,
+            family_ids,
+            api_key_id: Some(owner.key_id),
+            device_session_id: None,
+        })
+    }
+
+    /// Resolve the owner of a device access token.
+    pub async fn from_device_token(token: &str, pool: &PgPool) -> Result<Self> {
+        let owner = device_auth::authenticate(pool, token).await?;
+        let family_ids = Self::families(owner.user_id, pool).await?;
+
+        Ok(Self {
+            user_id: owner.user_id,
+            cognito_sub: owner.cognito_sub,
+            email: None,
+            family_ids,
+            api_key_id: None,
+            device_session_id: Some(owner.session_id),
+        })
+    }
+
+    async fn families(user_id: Uuid, pool: &PgPool) -> Result<Vec<Uuid>> {
+        let family_ids = metrics::timed_query(
+            "resolve_user_families",
+            sqlx::query_scalar("SELECT family_id FROM family_members WHERE user_id = $1")
+                .bind(user_id)
+                .fetch_all(pool),
+        )
+        .await?;
+
+        Ok(family_ids)
+    }
+
+    /// Resolve an already-authenticated Cognito user against the database.
+    pub async fn resolve(user: AuthenticatedUser, pool: &PgPool) -> Result<Self> {
+        if let Some((user_id, family_ids)) = Self::cached(&user.user_id) {
+            return Ok(Self {
+                user_id,
+                cognito_sub: user.user_id,
+                email: user.email,
+                family_ids,
+                api_key_id: None,
+                device_session_id: None,
+            });
+        }
+
+        let user_id: Uuid = metrics::timed_query(
+            "resolve_user",
+            sqlx::query_scalar("SELECT id FROM users WHERE cognito_sub = $1")
+                .bind(&user.user_id)
+                .fetch_optional(pool),
+        )
+        .await?
+        .ok_or_else(|| Error::Auth("User not registered".to_string()))?;
+
+        let family_ids: Vec<Uuid> = metrics::timed_query(
+            "resolve_user_families",
+            sqlx::query_scalar("SELECT family_id FROM family_members WHERE user_id = $1")
+                .bind(user_id)
+                .fetch_all(pool),
+        )
+        .await?;
+
+        if let Ok(mut cache) = user_cache().lock() {
+            cache.insert(
+                user.user_id.clone(),
+                CachedUser {
+                    user_id,
+                    family_ids: family_ids.clone(),
+                    cached_at: Instant::now(),
+                },
+            );
+        }
+
+        Ok(Self {
+            user_id,
+            cognito_sub: user.user_id,
+            email: user.email,
+            family_ids,
+            api_key_id: None,
+            device_session_id: None,
+        })
+    }
+
+    /// Whether the user signed in with Cognito themselves, rather than
+    /// calling with an API key or a device access token.
+    pub fn is_interactive(&self) -> bool {
+        self.api_key_id.is_none() && self.device_session_id.is_none()
+    }
+
+    /// The user, if they signed in with Cognito; `Error::Unauthorized` for
+    /// API keys and device tokens. Credentials that can hand out further
+    /// credentials (API keys, device approvals) need this, so a leaked key
+    /// or token can't outlive its own revocation.
+    pub fn require_interactive(self) -> Result<Self> {
+        if self.is_interactive() {
+            Ok(self)
+        } else {
+            Err(Error::Unauthorized(
+                "Sign in to the app to do this; API keys and devices can't".to_string(),
+            ))
+        }
+    }
+
+    /// Drop a cached entry, e.g. after the user's family membership changes.
+    pub fn invalidate(cognito_sub: &str) {
+        if let Ok(mut cache) = user_cache().lock() {
+            cache.remove(cognito_sub);
+        }
+    }
+
+    fn cached(cognito_sub: &str) -> Option<(Uuid, Vec<Uuid>)> {
+        let mut cache = user_cache().lock().ok()?;
+        match cache.get(cognito_sub) {
+            Some(entry) if entry.cached_at.elapsed() < USER_CACHE_TTL => {
+                Some((entry.user_id, entry.family_ids.clone()))
+            }
+            Some(_) => {
+                cache.remove(cognito_sub);
+                None
+            }
+            None => None,
+        }
+    }
+}
+
 /// Validate a JWT token and extract user information.
 ///
 /// Note: In production, this should validate against Cognito's JWKS endpoint.
@@ -110,6 +353,345 @@ pub fn extract_user_from_context(
     })
 }
 
+/// Cognito group whose members can use admin endpoints.
+pub const ADMIN_GROUP: &str = "admin";
+
+/// Parse the `cognito:groups` claim.
+///
+/// API Gateway flattens the list into a string such as `"[admin, family]"` or
+/// `"admin,family"`; a JSON array is accepted too.
+pub fn claim_groups(claims: &serde_json::Value) -> Vec<String> {
+    match claims.get("cognito:groups") {
+        Some(serde_json::Value::Array(groups)) => groups
+            .iter()
+            .filter_map(|g| g.as_str().map(String::from))
+            .collect(),
+        Some(serde_json::Value::String(groups)) => groups
+            .trim_matches(|c| c == '[' || c == ']')
+            .split([',', ' '])
+            .filter(|g| !g.is_empty())
+            .map(String::from)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Whether the request's Cognito user belongs to `group`.
+pub fn request_has_group(req: &Request, group: &str) -> bool {
+    req.request_context_ref()
+        .and_then(|ctx| ctx.authorizer().and_then(|a| a.fields.get("claims").cloned()))
+        .map(|claims| claim_groups(&claims).iter().any(|g| g == group))
+        .unwrap_or(false)
+}
+
+// ===========================================
+// Internal invocations
+// ===========================================
+
+/// Env var with the ARN of the secret internal invocations are signed with
+pub const INTERNAL_AUTH_SECRET_ENV: &str = "INTERNAL_AUTH_SECRET_ARN";
+
+/// Payload field carrying an internal invocation's [`InternalAuth`]
+pub const INTERNAL_AUTH_FIELD: &str = "internal_auth";
+
+/// Oldest internal signature accepted, in seconds. Queued and retried
+/// Discord follow-ups are useless after 15 minutes anyway, as their
+/// interaction tokens expire.
+pub const INTERNAL_AUTH_MAX_AGE_SECS: i64 = 900;
+
+/// Least time between reloads of the internal secret forced by a signature
+/// that doesn't match it, so bad signatures can't make every call read
+/// Secrets Manager.
+const INTERNAL_KEY_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Signature a Lambda adds to the payloads it invokes other Lambdas (or
+/// itself) with, so the receiver can trust the operation asked of it, the
+/// payload and the `user_id` and `family_ids` it acts as.
+///
+/// `signature` is a hex HMAC-SHA256 of [`internal_signing_input`], keyed
+/// with the internal secret. The Python agents check it too
+/// (`agents/src/shared/internal_auth.py`), so both sides change together.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InternalAuth {
+    /// Function that signed, e.g. `second-brain-discord-webhook`
+    pub caller: String,
+    /// Unix time in seconds
+    pub issued_at: i64,
+    pub signature: String,
+}
+
+/// Keys in the internal secret, `{"current": "...", "previous": "..."}`.
+/// Rotating moves `current` to `previous`, so payloads signed with the old
+/// key are still accepted while they are in flight.
+#[derive(Clone, Deserialize)]
+pub struct InternalKeys {
+    pub current: String,
+    #[serde(default)]
+    pub previous: Option<String>,
+}
+
+/// What an internal signature vouches for besides its caller and time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InternalClaims<'a> {
+    /// Operation asked of the receiver, e.g. `POST` and
+    /// `/internal/facts/resolve-and-edit`
+    pub method: &'a str,
+    pub path: &'a str,
+    /// [`internal_body_hash`] of the payload
+    pub body_hash: String,
+    pub user_id: &'a str,
+    pub family_ids: &'a [String],
+}
+
+/// What an internal signature covers: the caller, when it signed, the
+/// operation, the payload and the identity the receiver will act as, one per
+/// line.
+pub fn internal_signing_input(caller: &str, issued_at: i64, claims: &InternalClaims) -> String {
+    format!(
+        "{}\n{}\n{}\n{}\n{}\n{}\n{}",
+        caller,
+        issued_at,
+        claims.method,
+        claims.path,
+        claims.body_hash,
+        claims.user_id,
+        claims.family_ids.join(",")
+    )
+}
+
+/// Hex SHA-256 of a payload without its [`INTERNAL_AUTH_FIELD`], written as
+/// compact JSON with object keys sorted so both sides hash the same bytes
+/// however the payload was serialized in between.
+pub fn internal_body_hash(payload: &serde_json::Value) -> String {
+    let mut canonical = String::new();
+    match payload {
+        serde_json::Value::Object(map) => {
+            let body: serde_json::Map<_, _> = map
+                .iter()
+                .filter(|(key, _)| *key != INTERNAL_AUTH_FIELD)
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+            write_canonical(&mut canonical, &serde_json::Value::Object(body));
+        }
+        other => write_canonical(&mut canonical, other),
+    }
+    hex::encode(Sha256::digest(canonical.as_bytes()))
+}
+
+fn write_canonical(out: &mut String, value: &serde_json::Value) {
+    match value {
+        serde_json::Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(out, item);
+            }
+            out.push(']');
+        }
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(out, item);
+            }
+            out.push('}');
+        }
+        scalar => out.push_str(&scalar.to_string()),
+    }
+}
+
+fn internal_mac(key: &str, input: &str) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(input.as_bytes());
+    mac
+}
+
+impl InternalAuth {
+    /// Sign `claims` with the current key. `now` is the current Unix time in
+    /// seconds.
+    pub fn sign(keys: &InternalKeys, caller: &str, claims: &InternalClaims, now: i64) -> Self {
+        let input = internal_signing_input(caller, now, claims);
+        Self {
+            caller: caller.to_string(),
+            issued_at: now,
+            signature: hex::encode(internal_mac(&keys.current, &input).finalize().into_bytes()),
+        }
+    }
+
+    /// Check that the signature covers `claims`, was made with the current
+    /// or previous key and is at most [`INTERNAL_AUTH_MAX_AGE_SECS`] old.
+    /// Returns `Error::Auth` otherwise.
+    pub fn verify(&self, keys: &InternalKeys, claims: &InternalClaims, now: i64) -> Result<()> {
+        if (now - self.issued_at).abs() > INTERNAL_AUTH_MAX_AGE_SECS {
+            return Err(Error::Auth("Internal signature has expired".to_string()));
+        }
+
+        let expected = hex::decode(&self.signature)
+            .map_err(|_| Error::Auth("Malformed internal signature".to_string()))?;
+        let input = internal_signing_input(&self.caller, self.issued_at, claims);
+
+        // Constant-time comparison
+        let valid = std::iter::once(&keys.current)
+            .chain(keys.previous.iter())
+            .filter(|key| !key.is_empty())
+            .any(|key| internal_mac(key, &input).verify_slice(&expected).is_ok());
+
+        if valid {
+            Ok(())
+        } else {
+            Err(Error::Auth("Invalid internal signature".to_string()))
+        }
+    }
+}
+
+/// The process's internal secret, read through `crate::secrets`.
+struct InternalSigner {
+    client: aws_sdk_secretsmanager::Client,
+    secret_arn: String,
+    /// This function's name, sent as the caller
+    caller: String,
+    /// When the keys were last reloaded for a signature that didn't match
+    refreshed_at: Mutex<Option<Instant>>,
+}
+
+impl InternalSigner {
+    /// Loaded on first use; `Error::Config` when [`INTERNAL_AUTH_SECRET_ENV`]
+    /// is unset, so nothing is sent or accepted unsigned.
+    async fn shared() -> Result<&'static Self> {
+        static SIGNER: tokio::sync::OnceCell<Option<InternalSigner>> =
+            tokio::sync::OnceCell::const_new();
+
+        SIGNER
+            .get_or_init(|| async {
+                let secret_arn = std::env::var(INTERNAL_AUTH_SECRET_ENV).ok()?;
+                let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+                Some(Self {
+                    client: aws_sdk_secretsmanager::Client::new(&config),
+                    secret_arn,
+                    caller: std::env::var("AWS_LAMBDA_FUNCTION_NAME")
+                        .unwrap_or_else(|_| "local".to_string()),
+                    refreshed_at: Mutex::new(None),
+                })
+            })
+            .await
+            .as_ref()
+            .ok_or_else(|| Error::Config(format!("{} is not set", INTERNAL_AUTH_SECRET_ENV)))
+    }
+
+    /// The keys, cached like other secrets unless `refresh` is set.
+    async fn keys(&self, refresh: bool) -> Result<InternalKeys> {
+        let secret = if refresh {
+            refresh_secret(&self.client, &self.secret_arn).await?
+        } else {
+            get_secret(&self.client, &self.secret_arn).await?
+        };
+
+        serde_json::from_str(&secret)
+            .map_err(|e| Error::Config(format!("Invalid internal auth secret: {}", e)))
+    }
+
+    /// Whether a signature that didn't match may reload the keys, at most
+    /// once per [`INTERNAL_KEY_REFRESH_INTERVAL`].
+    fn claim_refresh(&self) -> bool {
+        let Ok(mut refreshed_at) = self.refreshed_at.lock() else {
+            return false;
+        };
+        let now = Instant::now();
+        if !refresh_due(*refreshed_at, now) {
+            return false;
+        }
+        *refreshed_at = Some(now);
+        true
+    }
+}
+
+/// Whether keys last reloaded at `refreshed_at` may be reloaded at `now`.
+fn refresh_due(refreshed_at: Option<Instant>, now: Instant) -> bool {
+    refreshed_at.is_none_or(|at| now.duration_since(at) >= INTERNAL_KEY_REFRESH_INTERVAL)
+}
+
+/// Serialize an invocation payload (a struct or map) with an
+/// [`INTERNAL_AUTH_FIELD`] signed for the operation `method` `path`, the
+/// payload itself and `user_id` and `family_ids`. Fails when no internal
+/// secret is configured.
+pub async fn sign_internal<T: Serialize>(
+    payload: &T,
+    method: &str,
+    path: &str,
+    user_id: &str,
+    family_ids: &[String],
+) -> Result<serde_json::Value> {
+    let mut value = serde_json::to_value(payload)?;
+    let signer = InternalSigner::shared().await?;
+
+    let claims = InternalClaims {
+        method,
+        path,
+        body_hash: internal_body_hash(&value),
+        user_id,
+        family_ids,
+    };
+    let keys = signer.keys(false).await?;
+    let auth = InternalAuth::sign(
+        &keys,
+        &signer.caller,
+        &claims,
+        chrono::Utc::now().timestamp(),
+    );
+
+    value
+        .as_object_mut()
+        .ok_or_else(|| Error::Internal("Internal payloads must be JSON objects".to_string()))?
+        .insert(INTERNAL_AUTH_FIELD.to_string(), serde_json::to_value(auth)?);
+
+    Ok(value)
+}
+
+/// Check the [`INTERNAL_AUTH_FIELD`] of an invocation payload before
+/// performing `method` `path` as its `user_id` and `family_ids`. Returns
+/// `Error::Auth` when it is missing, invalid or signed for another operation
+/// or payload, and `Error::Config` when no internal secret is configured:
+/// nothing passes unchecked.
+pub async fn verify_internal(
+    payload: &serde_json::Value,
+    method: &str,
+    path: &str,
+    user_id: &str,
+    family_ids: &[String],
+) -> Result<()> {
+    let signer = InternalSigner::shared().await?;
+
+    let auth: InternalAuth = payload
+        .get(INTERNAL_AUTH_FIELD)
+        .and_then(|auth| serde_json::from_value(auth.clone()).ok())
+        .ok_or_else(|| Error::Auth("Missing internal signature".to_string()))?;
+
+    let claims = InternalClaims {
+        method,
+        path,
+        body_hash: internal_body_hash(payload),
+        user_id,
+        family_ids,
+    };
+    let now = chrono::Utc::now().timestamp();
+    match auth.verify(&signer.keys(false).await?, &claims, now) {
+        // The key may have been rotated since it was cached
+        Err(Error::Auth(_)) if signer.claim_refresh() => {
+            auth.verify(&signer.keys(true).await?, &claims, now)
+        }
+        result => result,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -131,4 +713,184 @@ mod tests {
         let user = AuthenticatedUser::try_from(claims).unwrap();
         assert_eq!(user.family_ids, vec!["family-1", "family-2"]);
     }
+
+    fn user(api_key_id: Option<Uuid>, device_session_id: Option<Uuid>) -> AuthorizedUser {
+        AuthorizedUser {
+            user_id: Uuid::new_v4(),
+            cognito_sub: "user-123".to_string(),
+            email: None,
+            family_ids: Vec::new(),
+            api_key_id,
+            device_session_id,
+        }
+    }
+
+    #[test]
+    fn test_require_interactive() {
+        assert!(user(None, None).require_interactive().is_ok());
+        assert!(matches!(
+            user(Some(Uuid::new_v4()), None).require_interactive(),
+            Err(Error::Unauthorized(_))
+        ));
+        assert!(matches!(
+            user(None, Some(Uuid::new_v4())).require_interactive(),
+            Err(Error::Unauthorized(_))
+        ));
+    }
+
+    #[test]
+    fn test_claim_groups() {
+        let flattened = serde_json::json!({ "cognito:groups": "[admin, family]" });
+        assert_eq!(claim_groups(&flattened), vec!["admin", "family"]);
+
+        let array = serde_json::json!({ "cognito:groups": ["admin"] });
+        assert_eq!(claim_groups(&array), vec!["admin"]);
+
+        assert!(claim_groups(&serde_json::json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_user_cache_invalidate() {
+        let user_id = Uuid::new_v4();
+        user_cache().lock().unwrap().insert(
+            "sub-cache-test".to_string(),
+            CachedUser {
+                user_id,
+                family_ids: vec![],
+                cached_at: Instant::now(),
+            },
+        );
+
+        assert_eq!(AuthorizedUser::cached("sub-cache-test").map(|(id, _)| id), Some(user_id));
+        AuthorizedUser::invalidate("sub-cache-test");
+        assert!(AuthorizedUser::cached("sub-cache-test").is_none());
+    }
+
+    fn internal_keys(current: &str, previous: Option<&str>) -> InternalKeys {
+        InternalKeys {
+            current: current.to_string(),
+            previous: previous.map(String::from),
+        }
+    }
+
+    fn claims<'a>(user_id: &'a str, family_ids: &'a [String]) -> InternalClaims<'a> {
+        InternalClaims {
+            method: "POST",
+            path: "/internal/facts/resolve-and-edit",
+            body_hash: internal_body_hash(&serde_json::json!({"query": "dentist"})),
+            user_id,
+            family_ids,
+        }
+    }
+
+    #[test]
+    fn test_internal_auth_round_trip() {
+        let keys = internal_keys("key-2", None);
+        let families = vec!["family-1".to_string(), "family-2".to_string()];
+        let signed = claims("user-1", &families);
+        let auth = InternalAuth::sign(&keys, "discord-webhook", &signed, 1_000);
+
+        assert!(auth.verify(&keys, &signed, 1_000).is_ok());
+        assert!(auth
+            .verify(&keys, &claims("user-2", &families), 1_000)
+            .is_err());
+        assert!(auth
+            .verify(&keys, &claims("user-1", &families[..1]), 1_000)
+            .is_err());
+        assert!(auth
+            .verify(&internal_keys("other", None), &signed, 1_000)
+            .is_err());
+
+        let forged = InternalAuth {
+            caller: "someone-else".to_string(),
+            ..auth.clone()
+        };
+        assert!(forged.verify(&keys, &signed, 1_000).is_err());
+    }
+
+    #[test]
+    fn test_internal_auth_covers_operation_and_payload() {
+        let keys = internal_keys("key-1", None);
+        let signed = claims("user-1", &[]);
+        let auth = InternalAuth::sign(&keys, "discord-webhook", &signed, 1_000);
+
+        let other_path = InternalClaims {
+            path: "/internal/facts/resolve-and-delete",
+            ..signed.clone()
+        };
+        assert!(auth.verify(&keys, &other_path, 1_000).is_err());
+
+        let other_method = InternalClaims {
+            method: "DELETE",
+            ..signed.clone()
+        };
+        assert!(auth.verify(&keys, &other_method, 1_000).is_err());
+
+        let other_body = InternalClaims {
+            body_hash: internal_body_hash(&serde_json::json!({"query": "dentist!"})),
+            ..signed.clone()
+        };
+        assert!(auth.verify(&keys, &other_body, 1_000).is_err());
+    }
+
+    #[test]
+    fn test_internal_auth_rotation_and_expiry() {
+        let old = internal_keys("key-1", None);
+        let signed = claims("user-1", &[]);
+        let auth = InternalAuth::sign(&old, "slack-webhook", &signed, 1_000);
+
+        let rotated = internal_keys("key-2", Some("key-1"));
+        assert!(auth.verify(&rotated, &signed, 1_000).is_ok());
+        assert!(auth
+            .verify(&internal_keys("key-3", Some("key-2")), &signed, 1_000)
+            .is_err());
+
+        let late = 1_000 + INTERNAL_AUTH_MAX_AGE_SECS + 1;
+        assert!(auth.verify(&old, &signed, late).is_err());
+    }
+
+    #[test]
+    fn test_internal_key_refresh_interval() {
+        let now = Instant::now();
+        assert!(refresh_due(None, now));
+        assert!(!refresh_due(Some(now), now + Duration::from_secs(5)));
+        assert!(refresh_due(Some(now), now + INTERNAL_KEY_REFRESH_INTERVAL));
+    }
+
+    #[test]
+    fn test_internal_signing_input() {
+        // Must match agents/src/shared/internal_auth.py
+        let families = ["a".to_string(), "b".to_string()];
+        let claims = InternalClaims {
+            method: "POST",
+            path: "/agents/invoke",
+            body_hash: "abc".to_string(),
+            user_id: "user",
+            family_ids: &families,
+        };
+        assert_eq!(
+            internal_signing_input("caller", 42, &claims),
+            "caller\n42\nPOST\n/agents/invoke\nabc\nuser\na,b"
+        );
+    }
+
+    #[test]
+    fn test_internal_body_hash() {
+        // Key order and the signature itself don't change the hash
+        let signed = serde_json::json!({
+            "user_id": "u", "query": "é", "n": [1, true, null],
+            INTERNAL_AUTH_FIELD: {"signature": "x"},
+        });
+        let reordered = serde_json::json!({"n": [1, true, null], "query": "é", "user_id": "u"});
+        assert_eq!(internal_body_hash(&signed), internal_body_hash(&reordered));
+
+        // Must match agents/src/shared/internal_auth.py: the SHA-256 of
+        // '{"n":[1,true,null],"query":"é","user_id":"u"}'
+        assert_eq!(
+            internal_body_hash(&reordered),
+            hex::encode(Sha256::digest(
+                r#"{"n":[1,true,null],"query":"é","user_id":"u"}"#.as_bytes()
+            ))
+        );
+    }
 }
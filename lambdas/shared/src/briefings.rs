@@ -0,0 +1,253 @@
+//! Stored briefings shared by the briefing dispatcher, the briefings API and Discord.
+//!
+//! The scheduled dispatcher generates briefings ahead of time and stores them in
+//! `briefing_history`; readers serve today's stored copy and only fall back to
+//! live generation through the agent when none exists. [`briefing_audio`]
+//! reads a briefing aloud for voice assistants and audio playback.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::time::Instant;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::relationship_health::{briefing_note, stale_relationships, DEFAULT_STALE_DAYS};
+use crate::tts::{Speech, SpeechInput, TtsError, TtsService, VoiceOptions};
+use crate::{AgentClient, AgentRequest, Error, Result};
+
+/// Stale relationships mentioned in a morning briefing
+const BRIEFING_STALE_RELATIONSHIPS: i64 = 3;
+
+/// Briefing types that can be generated and stored.
+pub const BRIEFING_TYPES: [&str; 2] = ["morning", "evening"];
+
+/// A briefing from `briefing_history`.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredBriefing {
+    pub id: Uuid,
+    pub briefing_type: String,
+    pub content: String,
+    pub generated_at: DateTime<Utc>,
+    pub generation_time_ms: Option<i32>,
+}
+
+/// Prompt sent to the agent for a briefing type.
+pub fn briefing_prompt(briefing_type: &str) -> String {
+    match briefing_type {
+        "morning" => "Generate my morning briefing".to_string(),
+        "evening" => "Generate my evening summary".to_string(),
+        other => format!("Generate my {} briefing", other),
+    }
+}
+
+/// Validate a briefing type from user input.
+pub fn parse_briefing_type(value: Option<&str>) -> Result<&'static str> {
+    let value = value.unwrap_or("morning");
+    BRIEFING_TYPES
+        .iter()
+        .find(|t| **t == value)
+        .copied()
+        .ok_or_else(|| {
+            Error::Validation(format!(
+                "Invalid briefing type. Must be one of: {}",
+                BRIEFING_TYPES.join(", ")
+            ))
+        })
+}
+
+/// Latest briefing of `briefing_type` generated today in the user's timezone.
+pub async fn todays_briefing(
+    pool: &sqlx::PgPool,
+    user_id: Uuid,
+    briefing_type: &str,
+) -> Result<Option<StoredBriefing>> {
+    let briefing = sqlx::query_as(
+        r#"
+        SELECT
+            bh.id, bh.briefing_type, bh.content,
+            bh.generated_at, bh.generation_time_ms
+        FROM briefing_history bh
+        LEFT JOIN user_notification_preferences unp ON unp.user_id = bh.user_id
+        WHERE bh.user_id = $1
+        AND bh.briefing_type = $2
+        AND bh.generated_at >= (
+            date_trunc('day', NOW() AT TIME ZONE COALESCE(unp.timezone, 'America/New_York'))
+            AT TIME ZONE COALESCE(unp.timezone, 'America/New_York')
+        )
+        ORDER BY bh.generated_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(user_id)
+    .bind(briefing_type)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(briefing)
+}
+
+/// Generate a briefing through the agent and store it in `briefing_history`.
+pub async fn generate_briefing(
+    pool: &sqlx::PgPool,
+    agent_client: &AgentClient,
+    user_id: Uuid,
+    family_ids: &[Uuid],
+    briefing_type: &str,
+    source: &str,
+) -> Result<StoredBriefing> {
+    let started = Instant::now();
+
+    let mut message = briefing_prompt(briefing_type);
+    if briefing_type == "morning" {
+        // Best effort; a briefing without relationship nudges is still useful
+        match stale_relationships(
+            pool,
+            user_id,
+            family_ids,
+            DEFAULT_STALE_DAYS,
+            BRIEFING_STALE_RELATIONSHIPS,
+        )
+        .await
+        {
+            Ok(stale) => {
+                if let Some(note) = briefing_note(&stale, Utc::now()) {
+                    message.push_str("\n\n");
+                    message.push_str(&note);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to load stale relationships: {}", e),
+        }
+    }
+
+    let response = agent_client
+        .invoke(AgentRequest {
+            message,
+            user_id: user_id.to_string(),
+            family_ids: family_ids.iter().map(Uuid::to_string).collect(),
+            device_id: None,
+            conversation_id: None,
+            conversation_history: Vec::new(),
+            intent: Some("query".to_string()),
+            source: source.to_string(),
+            modality: None,
+            metadata: None,
+        })
+        .await?;
+
+    let generation_time_ms = started.elapsed().as_millis().min(i32::MAX as u128) as i32;
+    let model_id = response.metadata.as_ref().and_then(|m| m.model_id.clone());
+
+    let briefing = sqlx::query_as(
+        r#"
+        INSERT INTO briefing_history (
+            user_id, briefing_type, content, model_id, generation_time_ms
+        ) VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, briefing_type, content, generated_at, generation_time_ms
+        "#,
+    )
+    .bind(user_id)
+    .bind(briefing_type)
+    .bind(&response.response)
+    .bind(model_id)
+    .bind(generation_time_ms)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(briefing)
+}
+
+/// A briefing's markdown as text to be read aloud.
+///
+/// Headings, list markers, emphasis and link targets are dropped, and each
+/// line ends as a sentence so the voice pauses between items.
+pub fn briefing_speech(content: &str) -> SpeechInput {
+    let lines: Vec<String> = content
+        .lines()
+        .map(spoken_line)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            if line.ends_with(['.', '!', '?', ':']) {
+                line
+            } else {
+                format!("{}.", line)
+            }
+        })
+        .collect();
+
+    SpeechInput::Text(lines.join("\n"))
+}
+
+fn spoken_line(line: &str) -> String {
+    let line = line.trim().trim_start_matches('#').trim_start();
+    let line = ["- ", "* ", "+ ", "• "]
+        .iter()
+        .find_map(|marker| line.strip_prefix(marker))
+        .unwrap_or(line);
+    let line = match line.split_once(". ") {
+        Some((number, rest)) if number.chars().all(|c| c.is_ascii_digit()) => rest,
+        _ => line,
+    };
+
+    // [text](url) -> text
+    let mut spoken = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(open) = rest.find('[') {
+        let Some(close) = rest[open..].find("](").map(|i| open + i) else {
+            break;
+        };
+        let Some(end) = rest[close..].find(')').map(|i| close + i) else {
+            break;
+        };
+        spoken.push_str(&rest[..open]);
+        spoken.push_str(&rest[open + 1..close]);
+        rest = &rest[end + 1..];
+    }
+    spoken.push_str(rest);
+
+    spoken
+        .replace(['*', '`'], "")
+        .replace("__", "")
+        .trim()
+        .to_string()
+}
+
+/// Read a briefing aloud, stored with a presigned URL when `tts` has an audio bucket.
+pub async fn briefing_audio(
+    tts: &TtsService,
+    briefing: &StoredBriefing,
+    options: &VoiceOptions,
+) -> std::result::Result<Speech, TtsError> {
+    tts.speak(&briefing_speech(&briefing.content), options)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_briefing_type() {
+        assert_eq!(parse_briefing_type(None).unwrap(), "morning");
+        assert_eq!(parse_briefing_type(Some("evening")).unwrap(), "evening");
+        assert!(matches!(
+            parse_briefing_type(Some("weekly")),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_briefing_prompt() {
+        assert_eq!(briefing_prompt("morning"), "Generate my morning briefing");
+        assert_eq!(briefing_prompt("evening"), "Generate my evening summary");
+    }
+
+    #[test]
+    fn test_briefing_speech() {
+        let content = "## Today\n\n- **Dentist** at 3pm\n1. Call [Sam](https://example.com/sam)\nHave a good day!";
+        assert_eq!(
+            briefing_speech(content),
+            SpeechInput::Text("Today.\nDentist at 3pm.\nCall Sam.\nHave a good day!".to_string())
+        );
+    }
+}
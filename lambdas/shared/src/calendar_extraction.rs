@@ -0,0 +1,171 @@
+//! Calendar event extraction.
+//!
+//! After `calendar_sync` stores events, attendees are linked to the user's
+//! person entities (by email attribute, alias or name), unknown attendees can
+//! become new person entities, and each meeting is recorded as a fact such as
+//! "Meeting with Dr. Smith on 2025-03-02". What runs is configurable per user
+//! (`calendar_extraction_preferences`).
+
+use chrono::NaiveDate;
+
+/// Events extracted per user per sync run
+pub const MAX_EVENTS_PER_RUN: i64 = 200;
+
+/// Longest event title included in a fact
+const MAX_TITLE_CHARS: usize = 120;
+
+/// Per-user extraction settings (defaults used when a user has no row)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::FromRow)]
+pub struct ExtractionPreferences {
+    /// Link attendees to existing entities
+    pub enabled: bool,
+    /// Create person entities for attendees that match none
+    pub create_entities: bool,
+    /// Record a fact for each meeting with known attendees
+    pub record_facts: bool,
+}
+
+impl Default for ExtractionPreferences {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            create_entities: true,
+            record_facts: true,
+        }
+    }
+}
+
+/// Whether an attendee address is a person rather than a room, group or
+/// mailing robot.
+pub fn is_person_email(email: &str) -> bool {
+    let email = email.trim().to_ascii_lowercase();
+    let (local, domain) = match email.split_once('@') {
+        Some((local, domain)) if !local.is_empty() && domain.contains('.') => (local, domain),
+        _ => return false,
+    };
+
+    let room_domains = [
+        "resource.calendar.google.com",
+        "group.calendar.google.com",
+        "group.v.calendar.google.com",
+        "import.calendar.google.com",
+    ];
+    let robots = [
+        "noreply",
+        "no-reply",
+        "donotreply",
+        "do-not-reply",
+        "calendar",
+        "notifications",
+    ];
+
+    !room_domains.contains(&domain) && !robots.contains(&local)
+}
+
+/// Name for a new person entity: the display name, or the email's local part
+/// (`jane.doe@...` becomes "Jane Doe").
+pub fn attendee_name(display_name: Option<&str>, email: &str) -> String {
+    if let Some(name) = display_name
+        .map(str::trim)
+        .filter(|n| !n.is_empty() && !n.contains('@'))
+    {
+        return name.to_string();
+    }
+
+    let local = email.split('@').next().unwrap_or(email);
+    let local = local.split('+').next().unwrap_or(local);
+    let words: Vec<String> = local
+        .split(['.', '_', '-'])
+        .filter(|w| !w.is_empty())
+        .map(|w| {
+            let mut chars = w.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect();
+
+    if words.is_empty() {
+        email.trim().to_string()
+    } else {
+        words.join(" ")
+    }
+}
+
+/// Fact recorded for a meeting, e.g. "Meeting with Dr. Smith and Ann Lee on
+/// 2025-03-02 (Quarterly review)".
+pub fn meeting_fact(title: &str, attendees: &[String], date: NaiveDate) -> String {
+    let names = match attendees {
+        [] => String::new(),
+        [one] => one.clone(),
+        [rest @ .., last] => format!("{} and {}", rest.join(", "), last),
+    };
+
+    let mut fact = if names.is_empty() {
+        format!("Meeting on {}", date.format("%Y-%m-%d"))
+    } else {
+        format!("Meeting with {} on {}", names, date.format("%Y-%m-%d"))
+    };
+
+    let title = title.trim();
+    if !title.is_empty() && title != "(No title)" {
+        let title: String = match title.char_indices().nth(MAX_TITLE_CHARS) {
+            Some((idx, _)) => format!("{}…", title[..idx].trim_end()),
+            None => title.to_string(),
+        };
+        fact.push_str(&format!(" ({})", title));
+    }
+
+    fact
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_person_email() {
+        assert!(is_person_email("jane.doe@example.com"));
+        assert!(is_person_email("Dr.Smith@Clinic.org"));
+        assert!(!is_person_email("c_1888@resource.calendar.google.com"));
+        assert!(!is_person_email("team@group.calendar.google.com"));
+        assert!(!is_person_email("noreply@example.com"));
+        assert!(!is_person_email("not-an-email"));
+        assert!(!is_person_email("@example.com"));
+    }
+
+    #[test]
+    fn test_attendee_name() {
+        assert_eq!(
+            attendee_name(Some(" Dr. Smith "), "x@example.com"),
+            "Dr. Smith"
+        );
+        assert_eq!(attendee_name(None, "jane.doe+work@example.com"), "Jane Doe");
+        assert_eq!(
+            attendee_name(Some("ann_lee@example.com"), "ann_lee@example.com"),
+            "Ann Lee"
+        );
+        assert_eq!(attendee_name(Some(""), "bob@example.com"), "Bob");
+    }
+
+    #[test]
+    fn test_meeting_fact() {
+        let date = NaiveDate::from_ymd_opt(2025, 3, 2).unwrap();
+        assert_eq!(
+            meeting_fact("(No title)", &["Dr. Smith".to_string()], date),
+            "Meeting with Dr. Smith on 2025-03-02"
+        );
+        assert_eq!(
+            meeting_fact(
+                "Quarterly review",
+                &["Ann".to_string(), "Bob".to_string(), "Cy".to_string()],
+                date
+            ),
+            "Meeting with Ann, Bob and Cy on 2025-03-02 (Quarterly review)"
+        );
+
+        let long = "x".repeat(200);
+        assert!(meeting_fact(&long, &[], date).ends_with("…)"));
+    }
+}
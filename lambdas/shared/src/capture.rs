@@ -0,0 +1,398 @@
+//! Web page capture from the browser extension.
+//!
+//! `POST /capture` takes the page the user is on (URL, title, any selected
+//! text), fetches it server-side and pulls out the readable article text the
+//! way reader modes do: the `<article>` or `<main>` element if there is one,
+//! otherwise the body, keeping paragraphs, headings and list items and
+//! skipping navigation, headers, footers and sidebars. The page becomes a
+//! `bookmark` entity and the text is summarized into facts by the ingestion
+//! agent.
+
+use scraper::{ElementRef, Html, Selector};
+use std::net::IpAddr;
+use url::{Host, Url};
+
+/// Longest URL accepted
+pub const MAX_URL_CHARS: usize = 2048;
+
+/// Largest page body downloaded
+pub const MAX_PAGE_BYTES: usize = 2 * 1024 * 1024;
+
+/// Most article text handed to the ingestion agent
+pub const MAX_ARTICLE_CHARS: usize = 8000;
+
+/// Most selected text kept
+pub const MAX_SELECTION_CHARS: usize = 4000;
+
+/// Longest stored bookmark name (`entities.name`)
+const MAX_TITLE_CHARS: usize = 500;
+
+/// Elements read as article text
+const TEXT_BLOCKS: &str = "p, h1, h2, h3, h4, li, blockquote, pre";
+
+/// Elements whose contents aren't article text
+const SKIPPED_ANCESTORS: &[&str] = &["nav", "header", "footer", "aside", "form", "figure"];
+
+/// Block elements that contain other text blocks; their nested blocks are
+/// read as part of them
+const BLOCK_ANCESTORS: &[&str] = &["p", "h1", "h2", "h3", "h4", "li", "blockquote", "pre"];
+
+/// Readable content extracted from a page
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Article {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub site_name: Option<String>,
+    /// Text blocks separated by blank lines, at most `MAX_ARTICLE_CHARS`
+    pub text: String,
+}
+
+/// Validate a URL to capture: http(s) only, and not a host on a private
+/// network (the page is fetched from inside the VPC).
+pub fn parse_capture_url(raw: &str) -> Result<Url, &'static str> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Err("url is required");
+    }
+    if raw.chars().count() > MAX_URL_CHARS {
+        return Err("url is too long");
+    }
+
+    let url = Url::parse(raw).map_err(|_| "url is not a valid URL")?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err("url must be http or https");
+    }
+    if !url.username().is_empty() || url.password().is_some() {
+        return Err("url must not contain credentials");
+    }
+    if !is_public_host(&url) {
+        return Err("url must point to a public website");
+    }
+
+    Ok(url)
+}
+
+/// Whether a URL's host may be fetched: a public IP address, or a name that
+/// isn't reserved for local networks.
+pub fn is_public_host(url: &Url) -> bool {
+    match url.host() {
+        Some(Host::Ipv4(ip)) => is_public_ip(IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => is_public_ip(IpAddr::V6(ip)),
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+            domain.contains('.')
+                && ![".localhost", ".local", ".internal", ".lan"]
+                    .iter()
+                    .any(|suffix| domain.ends_with(suffix))
+        }
+        None => false,
+    }
+}
+
+/// Whether an address is routable on the public internet.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_unspecified()
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (a == 100 && (64..128).contains(&b))
+                || a == 0
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                // Unique local, fc00::/7
+                || (first & 0xfe00) == 0xfc00
+                // Link local, fe80::/10
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// Extract the title, description and readable text of an HTML page.
+pub fn extract_article(html: &str) -> Article {
+    let document = Html::parse_document(html);
+
+    let title = meta_content(&document, r#"meta[property="og:title"]"#)
+        .or_else(|| first_text(&document, "title"));
+    let description = meta_content(&document, r#"meta[property="og:description"]"#)
+        .or_else(|| meta_content(&document, r#"meta[name="description"]"#));
+    let site_name = meta_content(&document, r#"meta[property="og:site_name"]"#);
+
+    let root = ["article", "main", "[role=main]", "body"]
+        .iter()
+        .find_map(|selector| select_first(&document, selector));
+
+    let mut text = String::new();
+    if let Some(root) = root {
+        let blocks = Selector::parse(TEXT_BLOCKS).expect("valid selector");
+        for block in root.select(&blocks) {
+            if has_ancestor(block, SKIPPED_ANCESTORS) || has_ancestor(block, BLOCK_ANCESTORS) {
+                continue;
+            }
+            let block_text = collapse_whitespace(&block.text().collect::<String>());
+            if block_text.is_empty() {
+                continue;
+            }
+            if !text.is_empty() {
+                text.push_str("\n\n");
+            }
+            text.push_str(&block_text);
+            if text.chars().count() >= MAX_ARTICLE_CHARS {
+                break;
+            }
+        }
+    }
+
+    Article {
+        title: title.map(|t| truncate(&t, MAX_TITLE_CHARS)),
+        description,
+        site_name,
+        text: truncate(&text, MAX_ARTICLE_CHARS),
+    }
+}
+
+/// Bookmark name for a capture: the page's own title, the one the extension
+/// sent, or the URL's host and path.
+pub fn bookmark_name(article: Option<&Article>, title: Option<&str>, url: &Url) -> String {
+    article
+        .and_then(|a| a.title.as_deref())
+        .or(title)
+        .map(collapse_whitespace)
+        .filter(|t| !t.is_empty())
+        .map(|t| truncate(&t, MAX_TITLE_CHARS))
+        .unwrap_or_else(|| {
+            let host = url.host_str().unwrap_or_default();
+            truncate(
+                &format!("{}{}", host, url.path().trim_end_matches('/')),
+                MAX_TITLE_CHARS,
+            )
+        })
+}
+
+/// Message handed to the ingestion agent for a captured page.
+pub fn capture_message(
+    name: &str,
+    url: &Url,
+    selected_text: Option<&str>,
+    article: Option<&Article>,
+) -> String {
+    let mut message = format!(
+        "I saved this web page: \"{}\" ({}). Summarize the key facts worth remembering from it.",
+        name, url
+    );
+
+    if let Some(selection) = selected_text.map(str::trim).filter(|s| !s.is_empty()) {
+        message.push_str("\n\nThe part I highlighted:\n");
+        message.push_str(&truncate(selection, MAX_SELECTION_CHARS));
+    }
+    if let Some(description) = article.and_then(|a| a.description.as_deref()) {
+        message.push_str("\n\nPage description: ");
+        message.push_str(description.trim());
+    }
+    if let Some(text) = article.map(|a| a.text.as_str()).filter(|t| !t.is_empty()) {
+        message.push_str("\n\nPage text:\n");
+        message.push_str(text);
+    }
+
+    message
+}
+
+fn select_first<'a>(document: &'a Html, selector: &str) -> Option<ElementRef<'a>> {
+    let selector = Selector::parse(selector).ok()?;
+    document.select(&selector).next()
+}
+
+fn first_text(document: &Html, selector: &str) -> Option<String> {
+    select_first(document, selector)
+        .map(|e| collapse_whitespace(&e.text().collect::<String>()))
+        .filter(|t| !t.is_empty())
+}
+
+fn meta_content(document: &Html, selector: &str) -> Option<String> {
+    select_first(document, selector)
+        .and_then(|e| e.value().attr("content"))
+        .map(collapse_whitespace)
+        .filter(|t| !t.is_empty())
+}
+
+fn has_ancestor(element: ElementRef, names: &[&str]) -> bool {
+    element.ancestors().any(|node| {
+        node.value()
+            .as_element()
+            .is_some_and(|e| names.contains(&e.name()))
+    })
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    text.chars().take(max_chars).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"
+        <html>
+          <head>
+            <title>Sourdough basics | Bread Blog</title>
+            <meta name="description" content="How to keep a starter alive.">
+            <meta property="og:site_name" content="Bread Blog">
+          </head>
+          <body>
+            <nav><ul><li>Home</li><li>Recipes</li></ul></nav>
+            <header><h1>Bread Blog</h1></header>
+            <article>
+              <h1>Sourdough   basics</h1>
+              <p>Feed the starter   every
+                 12 hours.</p>
+              <ul><li>Flour <p>and water</p></li></ul>
+              <aside><p>Subscribe!</p></aside>
+              <script>track()</script>
+            </article>
+            <footer><p>© 2026</p></footer>
+          </body>
+        </html>
+    "#;
+
+    #[test]
+    fn extracts_article_text() {
+        let article = extract_article(PAGE);
+
+        assert_eq!(
+            article.title.as_deref(),
+            Some("Sourdough basics | Bread Blog")
+        );
+        assert_eq!(
+            article.description.as_deref(),
+            Some("How to keep a starter alive.")
+        );
+        assert_eq!(article.site_name.as_deref(), Some("Bread Blog"));
+        assert_eq!(
+            article.text,
+            "Sourdough basics\n\nFeed the starter every 12 hours.\n\nFlour and water"
+        );
+    }
+
+    #[test]
+    fn falls_back_to_body_and_og_title() {
+        let article = extract_article(
+            r#"<html><head><meta property="og:title" content="Launch notes"></head>
+               <body><p>First.</p><footer><p>Links</p></footer><p>Second.</p></body></html>"#,
+        );
+
+        assert_eq!(article.title.as_deref(), Some("Launch notes"));
+        assert_eq!(article.text, "First.\n\nSecond.");
+    }
+
+    #[test]
+    fn article_text_is_bounded() {
+        let html = format!("<p>{}</p>", "word ".repeat(5000));
+        assert_eq!(
+            extract_article(&html).text.chars().count(),
+            MAX_ARTICLE_CHARS
+        );
+    }
+
+    #[test]
+    fn validates_capture_urls() {
+        assert!(parse_capture_url("https://example.com/post?id=1").is_ok());
+        assert!(parse_capture_url(" http://blog.example.org ").is_ok());
+
+        assert_eq!(parse_capture_url(""), Err("url is required"));
+        assert_eq!(
+            parse_capture_url("ftp://example.com"),
+            Err("url must be http or https")
+        );
+        assert_eq!(
+            parse_capture_url("https://user:pw@example.com"),
+            Err("url must not contain credentials")
+        );
+        for private in [
+            "http://localhost:8080",
+            "http://127.0.0.1/",
+            "http://169.254.169.254/latest/meta-data",
+            "http://10.0.3.7",
+            "http://[::1]/",
+            "http://[fd00::1]/",
+            "http://db.internal/",
+            "http://intranet/",
+        ] {
+            assert_eq!(
+                parse_capture_url(private),
+                Err("url must point to a public website"),
+                "{}",
+                private
+            );
+        }
+    }
+
+    #[test]
+    fn classifies_ips() {
+        assert!(is_public_ip("93.184.216.34".parse().unwrap()));
+        assert!(is_public_ip("2606:2800:220:1::1".parse().unwrap()));
+        assert!(!is_public_ip("192.168.1.1".parse().unwrap()));
+        assert!(!is_public_ip("100.100.0.1".parse().unwrap()));
+        assert!(!is_public_ip("::ffff:10.0.0.1".parse().unwrap()));
+        assert!(!is_public_ip("fe80::1".parse().unwrap()));
+    }
+
+    #[test]
+    fn names_bookmarks() {
+        let url = Url::parse("https://example.com/posts/sourdough/").unwrap();
+        let article = Article {
+            title: Some("Sourdough basics".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            bookmark_name(Some(&article), Some("Tab title"), &url),
+            "Sourdough basics"
+        );
+        assert_eq!(
+            bookmark_name(None, Some("  Tab\ntitle "), &url),
+            "Tab title"
+        );
+        assert_eq!(
+            bookmark_name(None, None, &url),
+            "example.com/posts/sourdough"
+        );
+    }
+
+    #[test]
+    fn builds_agent_message() {
+        let url = Url::parse("https://example.com/a").unwrap();
+        let article = Article {
+            description: Some("About A.".to_string()),
+            text: "A is great.".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            capture_message("A", &url, Some(" the good part "), Some(&article)),
+            "I saved this web page: \"A\" (https://example.com/a). Summarize the key facts \
+             worth remembering from it.\n\nThe part I highlighted:\nthe good part\n\n\
+             Page description: About A.\n\nPage text:\nA is great."
+        );
+        assert_eq!(
+            capture_message("A", &url, None, None),
+            "I saved this web page: \"A\" (https://example.com/a). Summarize the key facts \
+             worth remembering from it."
+        );
+    }
+}
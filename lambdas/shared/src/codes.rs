@@ -0,0 +1,63 @@
+//! Short random codes people type or read out: Discord link codes, family
+//! join codes, device user codes and SMS verification codes.
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+/// `len` characters drawn uniformly from `alphabet` (ASCII, at most 256
+/// characters) with the operating system's CSPRNG.
+///
+/// Random bytes are mapped onto the alphabet by remainder; bytes from the
+/// incomplete last round of the alphabet would favour its first characters,
+/// so they are drawn again.
+pub fn random_code(len: usize, alphabet: &[u8]) -> String {
+    assert!(
+        (1..=256).contains(&alphabet.len()),
+        "alphabet must have 1 to 256 characters"
+    );
+    let limit = 256 - 256 % alphabet.len();
+
+    let mut code = String::with_capacity(len);
+    let mut bytes = [0u8; 32];
+    while code.len() < len {
+        OsRng.fill_bytes(&mut bytes);
+        for b in bytes.iter().map(|b| *b as usize).filter(|b| *b < limit) {
+            if code.len() == len {
+                break;
+            }
+            code.push(alphabet[b % alphabet.len()] as char);
+        }
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_code() {
+        let alphabet = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+        let code = random_code(8, alphabet);
+        assert_eq!(code.len(), 8);
+        assert!(code.bytes().all(|b| alphabet.contains(&b)));
+        assert_ne!(code, random_code(8, alphabet));
+        assert_eq!(random_code(0, alphabet), "");
+    }
+
+    #[test]
+    fn test_random_code_uses_whole_alphabet() {
+        // 20 doesn't divide 256, so an unrejected remainder would be skewed
+        let alphabet = b"BCDFGHJKLMNPQRSTVWXZ";
+        let mut counts = [0usize; 20];
+        for b in random_code(20_000, alphabet).bytes() {
+            counts[alphabet.iter().position(|a| *a == b).unwrap()] += 1;
+        }
+        // 1000 expected each; 800 is more than six standard deviations out
+        assert!(
+            counts.iter().all(|n| (800..1200).contains(n)),
+            "{:?}",
+            counts
+        );
+    }
+}
@@ -0,0 +1,164 @@
+//! Conditional requests, with ETags derived from a row's `updated_at`.
+//!
+//! GETs send the resource's [`etag`] and answer `If-None-Match` with a `304`
+//! ([`is_not_modified`]). PUTs honor `If-Match` ([`IfMatch`]) so an edit made
+//! against an old copy fails with `412` instead of overwriting a change
+//! another family member made in the meantime.
+
+use chrono::{DateTime, Utc};
+use lambda_http::http::header::{ETAG, IF_MATCH, IF_NONE_MATCH};
+use lambda_http::http::HeaderValue;
+use lambda_http::{Body, Request, Response};
+
+/// ETag for a resource last updated at `updated_at`.
+pub fn etag(updated_at: DateTime<Utc>) -> String {
+    format!("\"{:x}\"", updated_at.timestamp_micros())
+}
+
+/// The `updated_at` an ETag from [`etag`] was made from.
+fn parse_etag(tag: &str) -> Option<DateTime<Utc>> {
+    let micros = tag.trim().strip_prefix('"')?.strip_suffix('"')?;
+    DateTime::from_timestamp_micros(i64::from_str_radix(micros, 16).ok()?)
+}
+
+fn header<'a>(req: &'a Request, name: &lambda_http::http::HeaderName) -> Option<&'a str> {
+    req.headers().get(name).and_then(|v| v.to_str().ok())
+}
+
+/// Whether the client's `If-None-Match` already has `etag`, so a `304` can be
+/// sent instead of the resource.
+pub fn is_not_modified(req: &Request, etag: &str) -> bool {
+    header(req, &IF_NONE_MATCH).is_some_and(|tags| none_match_hits(tags, etag))
+}
+
+/// `If-None-Match` uses weak comparison: `W/` prefixes are ignored.
+fn none_match_hits(tags: &str, etag: &str) -> bool {
+    tags.split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+}
+
+/// An update's `If-Match` precondition.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IfMatch {
+    /// No `If-Match` (or `*`): the update applies to whatever is current
+    Any,
+    /// Only update the version last updated at this time
+    Version(DateTime<Utc>),
+    /// Only ETags we never issue, so the precondition can't hold
+    Never,
+}
+
+impl IfMatch {
+    /// The request's `If-Match` precondition.
+    pub fn from_request(req: &Request) -> Self {
+        Self::parse(header(req, &IF_MATCH))
+    }
+
+    fn parse(header: Option<&str>) -> Self {
+        let Some(tags) = header.map(str::trim).filter(|t| !t.is_empty()) else {
+            return Self::Any;
+        };
+        if tags == "*" {
+            return Self::Any;
+        }
+
+        // If-Match uses strong comparison, so weak tags never match
+        tags.split(',')
+            .find_map(parse_etag)
+            .map(Self::Version)
+            .unwrap_or(Self::Never)
+    }
+
+    /// The `updated_at` an update must find, if it is conditional. Bind as a
+    /// `timestamptz` in `($N::timestamptz IS NULL OR updated_at = $N)`.
+    pub fn expected(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Version(at) => Some(*at),
+            _ => None,
+        }
+    }
+
+    /// Whether a resource last updated at `updated_at` meets the precondition.
+    pub fn matches(&self, updated_at: DateTime<Utc>) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Version(at) => *at == updated_at,
+            Self::Never => false,
+        }
+    }
+}
+
+/// Set the `ETag` header on `response`.
+pub fn with_etag(mut response: Response<Body>, etag: &str) -> Response<Body> {
+    if let Ok(value) = HeaderValue::from_str(etag) {
+        response.headers_mut().insert(ETAG, value);
+    }
+    response
+}
+
+/// `304 Not Modified` for a resource the client already has.
+pub fn not_modified(etag: &str) -> Response<Body> {
+    with_etag(
+        Response::builder()
+            .status(304)
+            .body(Body::Empty)
+            .expect("Failed to build not modified response"),
+        etag,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 16, 9, 30, 0).unwrap() + chrono::Duration::microseconds(123)
+    }
+
+    #[test]
+    fn etags_round_trip() {
+        let tag = etag(at());
+        assert!(tag.starts_with('"') && tag.ends_with('"'));
+        assert_eq!(parse_etag(&tag), Some(at()));
+        assert_eq!(parse_etag("\"not-ours\""), None);
+        assert_eq!(parse_etag("unquoted"), None);
+    }
+
+    #[test]
+    fn etags_change_with_updated_at() {
+        assert_ne!(etag(at()), etag(at() + chrono::Duration::microseconds(1)));
+    }
+
+    #[test]
+    fn if_none_match_is_weak() {
+        let tag = etag(at());
+        assert!(none_match_hits(&tag, &tag));
+        assert!(none_match_hits(&format!("W/{}", tag), &tag));
+        assert!(none_match_hits(&format!("\"abc\", {}", tag), &tag));
+        assert!(none_match_hits("*", &tag));
+        assert!(!none_match_hits("\"abc\"", &tag));
+    }
+
+    #[test]
+    fn if_match_preconditions() {
+        let tag = etag(at());
+        assert_eq!(IfMatch::parse(None), IfMatch::Any);
+        assert_eq!(IfMatch::parse(Some("*")), IfMatch::Any);
+        assert_eq!(IfMatch::parse(Some(&tag)), IfMatch::Version(at()));
+        assert_eq!(IfMatch::parse(Some(&format!("W/{}", tag))), IfMatch::Never);
+        assert_eq!(IfMatch::parse(Some("\"not-ours\"")), IfMatch::Never);
+    }
+
+    #[test]
+    fn if_match_compares_versions() {
+        let later = at() + chrono::Duration::seconds(1);
+        assert!(IfMatch::Any.matches(later));
+        assert!(IfMatch::Version(at()).matches(at()));
+        assert!(!IfMatch::Version(at()).matches(later));
+        assert!(!IfMatch::Never.matches(at()));
+        assert_eq!(IfMatch::Version(at()).expected(), Some(at()));
+        assert_eq!(IfMatch::Any.expected(), None);
+    }
+}
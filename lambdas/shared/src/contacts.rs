@@ -0,0 +1,291 @@
+//! Syncing address-book contacts into person entities.
+//!
+//! Users connect Google Contacts through `/contacts/oauth/*` (tokens are kept
+//! in Secrets Manager like calendar tokens) and `contacts_sync` pulls their
+//! connections from the People API. Each contact becomes, or is matched to, a
+//! person entity carrying its emails, phone numbers and birthday as
+//! attributes; `contact_links` remembers which entity a contact maps to.
+
+use serde::Deserialize;
+use uuid::Uuid;
+
+/// Provider name stored on connections and links
+pub const GOOGLE_PROVIDER: &str = "google";
+
+/// OAuth scope for read-only contacts access
+pub const GOOGLE_CONTACTS_SCOPE: &str = "https://www.googleapis.com/auth/contacts.readonly";
+
+/// People API fields read for each contact
+pub const GOOGLE_PERSON_FIELDS: &str = "names,emailAddresses,phoneNumbers,birthdays,metadata";
+
+/// Secrets Manager name holding a user's contacts tokens
+pub fn token_secret_name(user_id: Uuid) -> String {
+    format!("second-brain/contacts/{}", user_id)
+}
+
+/// People API `people.connections.list` response page
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoogleConnectionsPage {
+    #[serde(default)]
+    pub connections: Vec<GooglePerson>,
+    pub next_page_token: Option<String>,
+    /// Returned on the last page when `requestSyncToken` was set
+    pub next_sync_token: Option<String>,
+}
+
+/// A contact from the People API
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GooglePerson {
+    pub resource_name: String,
+    pub etag: Option<String>,
+    #[serde(default)]
+    pub metadata: GooglePersonMetadata,
+    #[serde(default)]
+    pub names: Vec<GoogleName>,
+    #[serde(default)]
+    pub email_addresses: Vec<GoogleValue>,
+    #[serde(default)]
+    pub phone_numbers: Vec<GooglePhoneNumber>,
+    #[serde(default)]
+    pub birthdays: Vec<GoogleBirthday>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct GooglePersonMetadata {
+    /// Set on contacts deleted since the sync token was issued
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+/// Per-field metadata; `primary` marks the contact's preferred value
+#[derive(Debug, Default, Deserialize)]
+pub struct GoogleFieldMetadata {
+    #[serde(default)]
+    pub primary: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoogleName {
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub metadata: GoogleFieldMetadata,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct GoogleValue {
+    pub value: Option<String>,
+    #[serde(default)]
+    pub metadata: GoogleFieldMetadata,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GooglePhoneNumber {
+    pub value: Option<String>,
+    /// E.164 form, when Google could parse the number
+    pub canonical_form: Option<String>,
+    #[serde(default)]
+    pub metadata: GoogleFieldMetadata,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct GoogleBirthday {
+    pub date: Option<GoogleDate>,
+    #[serde(default)]
+    pub metadata: GoogleFieldMetadata,
+}
+
+/// Partial date; `year` is absent (or 0) when unknown
+#[derive(Debug, Default, Deserialize)]
+pub struct GoogleDate {
+    pub year: Option<i32>,
+    pub month: Option<u32>,
+    pub day: Option<u32>,
+}
+
+/// A contact reduced to what is stored on its entity
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContactRecord {
+    pub resource_name: String,
+    pub etag: Option<String>,
+    pub name: String,
+    /// Lowercased, primary first
+    pub emails: Vec<String>,
+    /// Canonical form when known, primary first
+    pub phones: Vec<String>,
+    /// `YYYY-MM-DD`, or `--MM-DD` without a year (see `shared::occasions`)
+    pub birthday: Option<String>,
+}
+
+impl ContactRecord {
+    /// Reduce a People API contact, or `None` for deleted contacts and ones
+    /// with neither a name nor an email to name the entity by.
+    pub fn from_person(person: &GooglePerson) -> Option<Self> {
+        if person.metadata.deleted {
+            return None;
+        }
+
+        let emails = dedup(
+            primary_first(&person.email_addresses, |e| &e.metadata)
+                .filter_map(|e| e.value.as_deref())
+                .map(|e| e.trim().to_lowercase()),
+        );
+        let phones = dedup(
+            primary_first(&person.phone_numbers, |p| &p.metadata)
+                .filter_map(|p| p.canonical_form.as_deref().or(p.value.as_deref()))
+                .map(|p| p.trim().to_string()),
+        );
+
+        let name = primary_first(&person.names, |n| &n.metadata)
+            .filter_map(|n| n.display_name.as_deref())
+            .map(str::trim)
+            .find(|n| !n.is_empty())
+            .map(str::to_string)
+            .or_else(|| emails.first().cloned())?;
+
+        let birthday = primary_first(&person.birthdays, |b| &b.metadata)
+            .filter_map(|b| b.date.as_ref())
+            .find_map(birthday_value);
+
+        Some(Self {
+            resource_name: person.resource_name.clone(),
+            etag: person.etag.clone(),
+            name,
+            emails,
+            phones,
+            birthday,
+        })
+    }
+}
+
+/// Items with the primary one(s) first, otherwise in Google's order.
+fn primary_first<'a, T>(
+    items: &'a [T],
+    metadata: impl Fn(&T) -> &GoogleFieldMetadata,
+) -> impl Iterator<Item = &'a T> {
+    let (primary, rest): (Vec<&T>, Vec<&T>) = items.iter().partition(|i| metadata(i).primary);
+    primary.into_iter().chain(rest)
+}
+
+/// Non-empty values in order, without repeats.
+fn dedup(values: impl Iterator<Item = String>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::new();
+    for value in values {
+        if !value.is_empty() && !unique.contains(&value) {
+            unique.push(value);
+        }
+    }
+    unique
+}
+
+/// Attribute value for a birthday, or `None` if the month/day is missing or invalid.
+pub fn birthday_value(date: &GoogleDate) -> Option<String> {
+    let month = date.month.filter(|m| (1..=12).contains(m))?;
+    let day = date.day.filter(|d| *d >= 1)?;
+
+    match date.year.filter(|y| *y > 0) {
+        Some(year) => chrono::NaiveDate::from_ymd_opt(year, month, day)
+            .map(|d| d.format("%Y-%m-%d").to_string()),
+        // Validate against a leap year so Feb 29 is kept
+        None => chrono::NaiveDate::from_ymd_opt(2000, month, day)
+            .map(|_| format!("--{:02}-{:02}", month, day)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn person(json: serde_json::Value) -> GooglePerson {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn reduces_full_contact() {
+        let contact = ContactRecord::from_person(&person(serde_json::json!({
+            "resourceName": "people/c123",
+            "etag": "%EgUBAi43PRoEAQIFByIMR1JxV2Z2",
+            "names": [{"displayName": "Robert Smith", "metadata": {"primary": true}}],
+            "emailAddresses": [
+                {"value": "bob@work.example"},
+                {"value": " Bob@Home.Example ", "metadata": {"primary": true}},
+                {"value": "bob@home.example"}
+            ],
+            "phoneNumbers": [
+                {"value": "(555) 010-2000", "canonicalForm": "+15550102000"},
+                {"value": "555 0100"}
+            ],
+            "birthdays": [{"date": {"year": 1980, "month": 3, "day": 15}}]
+        })))
+        .unwrap();
+
+        assert_eq!(contact.resource_name, "people/c123");
+        assert_eq!(contact.name, "Robert Smith");
+        assert_eq!(contact.emails, vec!["bob@home.example", "bob@work.example"]);
+        assert_eq!(contact.phones, vec!["+15550102000", "555 0100"]);
+        assert_eq!(contact.birthday.as_deref(), Some("1980-03-15"));
+    }
+
+    #[test]
+    fn names_contact_by_email_without_name() {
+        let contact = ContactRecord::from_person(&person(serde_json::json!({
+            "resourceName": "people/c1",
+            "names": [{"displayName": "  "}],
+            "emailAddresses": [{"value": "ann@example.com"}]
+        })))
+        .unwrap();
+
+        assert_eq!(contact.name, "ann@example.com");
+        assert_eq!(contact.birthday, None);
+    }
+
+    #[test]
+    fn skips_deleted_and_unnamed_contacts() {
+        let deleted = person(serde_json::json!({
+            "resourceName": "people/c1",
+            "metadata": {"deleted": true},
+            "names": [{"displayName": "Ann"}]
+        }));
+        assert_eq!(ContactRecord::from_person(&deleted), None);
+
+        let unnamed = person(serde_json::json!({
+            "resourceName": "people/c2",
+            "phoneNumbers": [{"value": "555 0100"}]
+        }));
+        assert_eq!(ContactRecord::from_person(&unnamed), None);
+    }
+
+    #[test]
+    fn formats_birthdays() {
+        let date = |year, month, day| GoogleDate {
+            year,
+            month: Some(month),
+            day: Some(day),
+        };
+        assert_eq!(
+            birthday_value(&date(None, 2, 29)).as_deref(),
+            Some("--02-29")
+        );
+        assert_eq!(
+            birthday_value(&date(Some(0), 7, 4)).as_deref(),
+            Some("--07-04")
+        );
+        assert_eq!(
+            birthday_value(&date(Some(1990), 12, 1)).as_deref(),
+            Some("1990-12-01")
+        );
+        assert_eq!(birthday_value(&date(Some(1990), 2, 30)), None);
+        assert_eq!(birthday_value(&date(None, 13, 1)), None);
+        assert_eq!(
+            birthday_value(&GoogleDate {
+                year: Some(1990),
+                month: None,
+                day: Some(1),
+            }),
+            None
+        );
+    }
+}
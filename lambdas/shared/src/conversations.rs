@@ -0,0 +1,193 @@
+//! Conversation sessions, so follow-up questions ("what about his brother?")
+//! are answered with the earlier turns in view.
+//!
+//! A conversation is started by the first question asked without an ID
+//! ([`start`]) or, on chat platforms, found by the platform's own ID for the
+//! place it happens, such as a Discord thread ([`for_external_key`]). Each
+//! exchange is stored with [`record_exchange`] and the latest
+//! [`HISTORY_TURNS`] are sent to the agents with the next question.
+
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::Result;
+
+/// Messages (questions and answers) sent to the agents as history.
+pub const HISTORY_TURNS: i64 = 10;
+
+/// Longest conversation title, taken from the first question.
+pub const MAX_TITLE_CHARS: usize = 80;
+
+/// One earlier message, as the agents expect it in `conversation_history`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversationTurn {
+    /// `user` or `assistant`
+    pub role: String,
+    pub content: String,
+}
+
+/// Title for a conversation starting with `question`: its first line, cut to
+/// [`MAX_TITLE_CHARS`].
+pub fn title_from(question: &str) -> String {
+    let line = question.trim().lines().next().unwrap_or_default().trim();
+
+    match line.char_indices().nth(MAX_TITLE_CHARS) {
+        Some((idx, _)) => format!("{}…", line[..idx].trim_end()),
+        None => line.to_string(),
+    }
+}
+
+/// Start a conversation for `user_id` whose first question is `question`.
+pub async fn start(pool: &PgPool, user_id: Uuid, source: &str, question: &str) -> Result<Uuid> {
+    let id = sqlx::query_scalar(
+        r#"
+        INSERT INTO conversations (user_id, source, title)
+        VALUES ($1, $2, $3)
+        RETURNING id
+        "#,
+    )
+    .bind(user_id)
+    .bind(source)
+    .bind(title_from(question))
+    .fetch_one(pool)
+    .await?;
+
+    Ok(id)
+}
+
+/// Whether `conversation_id` exists and belongs to `user_id`.
+pub async fn belongs_to(pool: &PgPool, conversation_id: Uuid, user_id: Uuid) -> Result<bool> {
+    let exists = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM conversations WHERE id = $1 AND user_id = $2)",
+    )
+    .bind(conversation_id)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(exists)
+}
+
+/// The conversation `user_id` has at the platform's `external_key` (e.g. a
+/// Discord thread ID), started with `question` if there is none yet.
+pub async fn for_external_key(
+    pool: &PgPool,
+    user_id: Uuid,
+    source: &str,
+    external_key: &str,
+    question: &str,
+) -> Result<Uuid> {
+    // The no-op update makes RETURNING yield the existing row
+    let id = sqlx::query_scalar(
+        r#"
+        INSERT INTO conversations (user_id, source, external_key, title)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id, source, external_key) WHERE external_key IS NOT NULL
+        DO UPDATE SET source = EXCLUDED.source
+        RETURNING id
+        "#,
+    )
+    .bind(user_id)
+    .bind(source)
+    .bind(external_key)
+    .bind(title_from(question))
+    .fetch_one(pool)
+    .await?;
+
+    Ok(id)
+}
+
+/// The latest [`HISTORY_TURNS`] messages of a conversation, oldest first.
+pub async fn history(pool: &PgPool, conversation_id: Uuid) -> Result<Vec<ConversationTurn>> {
+    let rows: Vec<(String, String)> = sqlx::query_as(
+        r#"
+        SELECT role, content FROM (
+            SELECT role::text AS role, content, created_at, id
+            FROM messages
+            WHERE conversation_id = $1 AND role IN ('user', 'assistant')
+            ORDER BY created_at DESC, id DESC
+            LIMIT $2
+        ) latest
+        ORDER BY created_at, id
+        "#,
+    )
+    .bind(conversation_id)
+    .bind(HISTORY_TURNS)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(role, content)| ConversationTurn { role, content })
+        .collect())
+}
+
+/// Store a question and its answer.
+pub async fn record_exchange(
+    pool: &PgPool,
+    conversation_id: Uuid,
+    question: &str,
+    answer: &str,
+    model_id: Option<&str>,
+    latency_ms: Option<i32>,
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    // The answer is stamped after the question so they list in order
+    sqlx::query(
+        r#"
+        INSERT INTO messages (conversation_id, role, content, input_modality,
+                              output_modality, model_id, latency_ms, created_at)
+        VALUES ($1, 'user', $2, 'text', NULL, NULL, NULL, NOW()),
+               ($1, 'assistant', $3, NULL, 'text', $4, $5, NOW() + INTERVAL '1 microsecond')
+        "#,
+    )
+    .bind(conversation_id)
+    .bind(question)
+    .bind(answer)
+    .bind(model_id)
+    .bind(latency_ms)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("UPDATE conversations SET last_message_at = NOW() WHERE id = $1")
+        .bind(conversation_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn title_is_first_line() {
+        assert_eq!(
+            title_from("  Who is Sam's brother?\nAnd his kids?"),
+            "Who is Sam's brother?"
+        );
+    }
+
+    #[test]
+    fn long_titles_are_cut() {
+        let title = title_from(&"é".repeat(MAX_TITLE_CHARS + 10));
+        assert_eq!(title.chars().count(), MAX_TITLE_CHARS + 1);
+        assert!(title.ends_with('…'));
+    }
+
+    #[test]
+    fn turns_serialize_as_agents_expect() {
+        let turn = ConversationTurn {
+            role: "user".to_string(),
+            content: "Where does Sam work?".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&turn).unwrap(),
+            serde_json::json!({"role": "user", "content": "Where does Sam work?"})
+        );
+    }
+}
@@ -0,0 +1,257 @@
+//! CORS for the HTTP API Lambdas.
+//!
+//! Router binaries add [`Cors`] as middleware; other HTTP handlers are wrapped
+//! in [`with_cors`]. Either way, preflight (`OPTIONS`) requests are answered
+//! without reaching the handler and every response gets the
+//! `Access-Control-*` headers. What is allowed comes from the environment:
+//!
+//! - `CORS_ALLOWED_ORIGINS`: comma-separated origins, or `*` (the default) for any
+//! - `CORS_ALLOWED_METHODS` and `CORS_ALLOWED_HEADERS`: replace the defaults
+//! - `CORS_MAX_AGE_SECONDS`: how long browsers may cache a preflight answer
+//!
+//! With an origin list, a listed request `Origin` is echoed back (with
+//! `Vary: Origin`); other origins get no `Access-Control-Allow-Origin`, so
+//! browsers refuse to hand them the response.
+
+use lambda_http::http::HeaderValue;
+use lambda_http::{Body, Request, Response};
+use std::future::Future;
+use std::sync::OnceLock;
+
+use crate::router::{HandlerResult, Middleware, RequestInfo};
+
+/// Methods allowed when `CORS_ALLOWED_METHODS` is unset.
+pub const DEFAULT_METHODS: &str = "GET,POST,PUT,PATCH,DELETE,OPTIONS";
+/// Request headers allowed when `CORS_ALLOWED_HEADERS` is unset.
+pub const DEFAULT_HEADERS: &str = "Content-Type,Authorization,If-Match,If-None-Match";
+/// Response headers browsers may read, e.g. `ETag`.
+pub const EXPOSE_HEADERS: &str = "ETag,Retry-After";
+/// Preflight cache lifetime when `CORS_MAX_AGE_SECONDS` is unset.
+pub const DEFAULT_MAX_AGE_SECONDS: u64 = 600;
+
+/// Origins allowed to read responses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AllowedOrigins {
+    Any,
+    List(Vec<String>),
+}
+
+impl AllowedOrigins {
+    /// Parse `*` or a comma-separated list of origins.
+    pub fn parse(value: &str) -> Self {
+        let origins: Vec<String> = value
+            .split(',')
+            .map(|o| o.trim().trim_end_matches('/'))
+            .filter(|o| !o.is_empty())
+            .map(String::from)
+            .collect();
+
+        if origins.is_empty() || origins.iter().any(|o| o == "*") {
+            Self::Any
+        } else {
+            Self::List(origins)
+        }
+    }
+}
+
+/// Adds CORS headers and answers preflight requests.
+#[derive(Debug, Clone)]
+pub struct Cors {
+    pub allowed_origins: AllowedOrigins,
+    pub allow_methods: String,
+    pub allow_headers: String,
+    /// Response headers browsers may read
+    pub expose_headers: String,
+    pub max_age_seconds: u64,
+}
+
+impl Default for Cors {
+    fn default() -> Self {
+        Self {
+            allowed_origins: AllowedOrigins::Any,
+            allow_methods: DEFAULT_METHODS.to_string(),
+            allow_headers: DEFAULT_HEADERS.to_string(),
+            expose_headers: EXPOSE_HEADERS.to_string(),
+            max_age_seconds: DEFAULT_MAX_AGE_SECONDS,
+        }
+    }
+}
+
+impl Cors {
+    /// Configuration from the `CORS_*` environment variables (see the module docs).
+    pub fn from_env() -> Self {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    fn from_lookup(var: impl Fn(&str) -> Option<String>) -> Self {
+        let defaults = Self::default();
+        Self {
+            allowed_origins: var("CORS_ALLOWED_ORIGINS")
+                .map(|v| AllowedOrigins::parse(&v))
+                .unwrap_or(defaults.allowed_origins),
+            allow_methods: var("CORS_ALLOWED_METHODS").unwrap_or(defaults.allow_methods),
+            allow_headers: var("CORS_ALLOWED_HEADERS").unwrap_or(defaults.allow_headers),
+            expose_headers: defaults.expose_headers,
+            max_age_seconds: var("CORS_MAX_AGE_SECONDS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_age_seconds),
+        }
+    }
+
+    /// The `Access-Control-Allow-Origin` value for a request from `origin`,
+    /// or `None` if that origin isn't allowed.
+    pub fn allow_origin(&self, origin: Option<&str>) -> Option<String> {
+        match &self.allowed_origins {
+            AllowedOrigins::Any => Some("*".to_string()),
+            AllowedOrigins::List(origins) => origin
+                .filter(|o| origins.iter().any(|allowed| allowed == o))
+                .map(String::from),
+        }
+    }
+
+    /// Answer a preflight request; [`Cors::apply`] adds the CORS headers.
+    pub fn preflight(&self) -> Response<Body> {
+        Response::builder()
+            .status(204)
+            .header("access-control-max-age", self.max_age_seconds)
+            .body(Body::Empty)
+            .unwrap_or_default()
+    }
+
+    /// Add the CORS headers for a request from `origin` to its response.
+    pub fn apply(&self, origin: Option<&str>, response: &mut Response<Body>) {
+        let headers = response.headers_mut();
+
+        if let AllowedOrigins::List(_) = self.allowed_origins {
+            headers.append("vary", HeaderValue::from_static("Origin"));
+        }
+        let Some(allow_origin) = self.allow_origin(origin) else {
+            return;
+        };
+
+        for (name, value) in [
+            ("access-control-allow-origin", allow_origin.as_str()),
+            ("access-control-allow-methods", &self.allow_methods),
+            ("access-control-allow-headers", &self.allow_headers),
+            ("access-control-expose-headers", &self.expose_headers),
+        ] {
+            if let Ok(value) = value.parse() {
+                headers.insert(name, value);
+            }
+        }
+    }
+}
+
+impl Middleware for Cors {
+    fn before(&self, req: &Request, _info: &RequestInfo) -> Option<Response<Body>> {
+        (req.method().as_str() == "OPTIONS").then(|| self.preflight())
+    }
+
+    fn after(&self, info: &RequestInfo, response: &mut Response<Body>) {
+        self.apply(info.origin.as_deref(), response);
+    }
+}
+
+/// The request's `Origin` header.
+pub fn request_origin(req: &Request) -> Option<String> {
+    req.headers()
+        .get("origin")
+        .and_then(|v| v.to_str().ok())
+        .map(String::from)
+}
+
+/// Run an HTTP handler that doesn't use the router with CORS handled as the
+/// [`Cors`] middleware would, configured from the environment.
+pub async fn with_cors<F, Fut>(req: Request, handler: F) -> HandlerResult
+where
+    F: FnOnce(Request) -> Fut,
+    Fut: Future<Output = HandlerResult>,
+{
+    static CORS: OnceLock<Cors> = OnceLock::new();
+    let cors = CORS.get_or_init(Cors::from_env);
+
+    let origin = request_origin(&req);
+    let mut response = if req.method().as_str() == "OPTIONS" {
+        cors.preflight()
+    } else {
+        handler(req).await?
+    };
+    cors.apply(origin.as_deref(), &mut response);
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn cors(vars: &[(&str, &str)]) -> Cors {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Cors::from_lookup(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn any_origin_by_default() {
+        let cors = cors(&[]);
+        assert_eq!(cors.allowed_origins, AllowedOrigins::Any);
+        assert_eq!(cors.allow_origin(None).as_deref(), Some("*"));
+
+        let mut response = Response::new(Body::Empty);
+        cors.apply(Some("https://anywhere.example"), &mut response);
+        assert_eq!(response.headers()["access-control-allow-origin"], "*");
+        assert!(response.headers().get("vary").is_none());
+    }
+
+    #[test]
+    fn origin_list_echoes_listed_origins_only() {
+        let cors = cors(&[(
+            "CORS_ALLOWED_ORIGINS",
+            "https://app.example.com/, https://mirror.local",
+        )]);
+
+        let mut allowed = Response::new(Body::Empty);
+        cors.apply(Some("https://app.example.com"), &mut allowed);
+        assert_eq!(
+            allowed.headers()["access-control-allow-origin"],
+            "https://app.example.com"
+        );
+        assert_eq!(allowed.headers()["vary"], "Origin");
+
+        let mut refused = Response::new(Body::Empty);
+        cors.apply(Some("https://evil.example"), &mut refused);
+        assert!(refused
+            .headers()
+            .get("access-control-allow-origin")
+            .is_none());
+        assert_eq!(cors.allow_origin(None), None);
+    }
+
+    #[test]
+    fn preflight_is_cacheable() {
+        let cors = cors(&[
+            ("CORS_MAX_AGE_SECONDS", "3600"),
+            ("CORS_ALLOWED_METHODS", "GET,POST"),
+        ]);
+        let mut response = cors.preflight();
+        cors.apply(Some("https://app.example.com"), &mut response);
+
+        assert_eq!(response.status(), 204);
+        assert_eq!(response.headers()["access-control-max-age"], "3600");
+        assert_eq!(
+            response.headers()["access-control-allow-methods"],
+            "GET,POST"
+        );
+    }
+
+    #[test]
+    fn wildcard_anywhere_allows_any() {
+        assert_eq!(
+            AllowedOrigins::parse("https://a.example,*"),
+            AllowedOrigins::Any
+        );
+        assert_eq!(AllowedOrigins::parse(""), AllowedOrigins::Any);
+    }
+}
@@ -0,0 +1,421 @@
+//! Full account data export.
+//!
+//! `POST /export` records a job in `data_exports` and hands it to the
+//! `data_export` Lambda, which runs each dataset's query, writes every dataset
+//! as both JSON and CSV into a zip bundle, and stores it in the exports bucket
+//! for download through a presigned URL (`GET /export/{id}`).
+//!
+//! Unlike the graph export (`shared::graph_export`), which covers everything
+//! the user can see, this covers the data held about the user: their account,
+//! the facts and entities they own or created, their tags, reminders,
+//! calendar events, feedback and interactions, including soft-deleted rows.
+
+use std::io::{Cursor, Write};
+
+use chrono::{DateTime, Utc};
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::graph_export::csv_field;
+use crate::{Error, Result};
+
+/// How long a presigned download URL is valid
+pub const DOWNLOAD_URL_TTL_SECS: u64 = 60 * 60;
+
+/// Days a finished bundle is kept (matches the exports bucket lifecycle rule)
+pub const EXPORT_RETENTION_DAYS: i64 = 7;
+
+/// A table (or join) exported for the user. `query` selects `columns` and
+/// takes the user's ID as `$1`.
+#[derive(Debug)]
+pub struct Dataset {
+    pub name: &'static str,
+    pub columns: &'static [&'static str],
+    pub query: &'static str,
+}
+
+impl Dataset {
+    /// Query returning each row as a JSON object.
+    pub fn json_query(&self) -> String {
+        format!("SELECT row_to_json(t) FROM ({}) t", self.query)
+    }
+}
+
+/// Everything exported, in bundle order
+pub const DATASETS: &[Dataset] = &[
+    Dataset {
+        name: "account",
+        columns: &[
+            "id",
+            "email",
+            "display_name",
+            "status",
+            "settings",
+            "created_at",
+            "last_active_at",
+        ],
+        query: r#"
+            SELECT id, email, display_name, status::text AS status, settings,
+                   created_at, last_active_at
+            FROM users WHERE id = $1
+        "#,
+    },
+    Dataset {
+        name: "facts",
+        columns: &[
+            "id",
+            "content",
+            "source",
+            "importance",
+            "confidence",
+            "visibility_tier",
+            "owner_type",
+            "about_entity_id",
+            "valid_from",
+            "valid_to",
+            "recorded_at",
+            "created_at",
+            "updated_at",
+            "superseded_by",
+            "deleted_at",
+        ],
+        query: r#"
+            SELECT f.id, f.content, f.source::text AS source, f.importance,
+                   f.confidence::float8 AS confidence, f.visibility_tier, f.owner_type,
+                   f.about_entity_id, f.valid_from, f.valid_to, f.recorded_at,
+                   f.created_at, f.updated_at, f.superseded_by, f.deleted_at
+            FROM facts f
+            WHERE (f.owner_type = 'user' AND f.owner_id = $1) OR f.created_by = $1
+            ORDER BY f.created_at
+        "#,
+    },
+    Dataset {
+        name: "entities",
+        columns: &[
+            "id",
+            "entity_type",
+            "name",
+            "aliases",
+            "description",
+            "metadata",
+            "owner_type",
+            "created_at",
+            "updated_at",
+            "deleted_at",
+        ],
+        query: r#"
+            SELECT e.id, e.entity_type::text AS entity_type, e.name, e.aliases,
+                   e.description, e.metadata, e.owner_type, e.created_at,
+                   e.updated_at, e.deleted_at
+            FROM entities e
+            WHERE (e.owner_type = 'user' AND e.owner_id = $1) OR e.created_by = $1
+            ORDER BY e.created_at
+        "#,
+    },
+    Dataset {
+        name: "tags",
+        columns: &[
+            "id",
+            "name",
+            "path",
+            "parent_id",
+            "description",
+            "color",
+            "icon",
+            "created_at",
+            "deleted_at",
+        ],
+        query: r#"
+            SELECT t.id, t.name, t.path, t.parent_id, t.description, t.color, t.icon,
+                   t.created_at, t.deleted_at
+            FROM tags t
+            WHERE t.owner_type = 'user' AND t.owner_id = $1
+            ORDER BY t.path
+        "#,
+    },
+    Dataset {
+        name: "fact_tags",
+        columns: &[
+            "fact_id",
+            "tag_id",
+            "tag_path",
+            "confidence",
+            "assigned_by",
+            "created_at",
+        ],
+        query: r#"
+            SELECT ft.fact_id, ft.tag_id, t.path AS tag_path,
+                   ft.confidence::float8 AS confidence, ft.assigned_by, ft.created_at
+            FROM fact_tags ft
+            JOIN facts f ON f.id = ft.fact_id
+            JOIN tags t ON t.id = ft.tag_id
+            WHERE (f.owner_type = 'user' AND f.owner_id = $1) OR f.created_by = $1
+            ORDER BY ft.created_at
+        "#,
+    },
+    Dataset {
+        name: "reminders",
+        columns: &[
+            "id",
+            "title",
+            "description",
+            "trigger_type",
+            "trigger_config",
+            "status",
+            "priority",
+            "tags",
+            "next_trigger_at",
+            "last_triggered_at",
+            "related_fact_id",
+            "related_entity_id",
+            "created_at",
+            "updated_at",
+        ],
+        query: r#"
+            SELECT r.id, r.title, r.description, r.trigger_type::text AS trigger_type,
+                   r.trigger_config, r.status::text AS status, r.priority, r.tags,
+                   r.next_trigger_at, r.last_triggered_at, r.related_fact_id,
+                   r.related_entity_id, r.created_at, r.updated_at
+            FROM reminders r
+            WHERE r.user_id = $1
+            ORDER BY r.created_at
+        "#,
+    },
+    Dataset {
+        name: "calendar_events",
+        columns: &[
+            "id",
+            "title",
+            "description",
+            "location",
+            "start_time",
+            "end_time",
+            "all_day",
+            "timezone",
+            "external_provider",
+            "is_recurring",
+            "recurrence_rule",
+            "created_at",
+        ],
+        query: r#"
+            SELECT c.id, c.title, c.description, c.location, c.start_time, c.end_time,
+                   c.all_day, c.timezone, c.external_provider, c.is_recurring,
+                   c.recurrence_rule, c.created_at
+            FROM calendar_events c
+            WHERE c.user_id = $1
+            ORDER BY c.start_time
+        "#,
+    },
+    Dataset {
+        name: "feedback",
+        columns: &[
+            "id",
+            "feedback_type",
+            "context_type",
+            "context_id",
+            "action",
+            "rating",
+            "metadata",
+            "created_at",
+        ],
+        query: r#"
+            SELECT fb.id, fb.feedback_type, fb.context_type, fb.context_id, fb.action,
+                   fb.rating, fb.metadata, fb.created_at
+            FROM user_feedback fb
+            WHERE fb.user_id = $1
+            ORDER BY fb.created_at
+        "#,
+    },
+    Dataset {
+        name: "interactions",
+        columns: &[
+            "id",
+            "event_type",
+            "target_id",
+            "query_id",
+            "position",
+            "source",
+            "metadata",
+            "occurred_at",
+        ],
+        query: r#"
+            SELECT ie.id, ie.event_type, ie.target_id, ie.query_id, ie.position,
+                   ie.source, ie.metadata, ie.occurred_at
+            FROM interaction_events ie
+            WHERE ie.user_id = $1
+            ORDER BY ie.occurred_at
+        "#,
+    },
+];
+
+/// Object key of an export bundle in the exports bucket.
+pub fn export_key(user_id: Uuid, export_id: Uuid) -> String {
+    format!("exports/{}/{}.zip", user_id, export_id)
+}
+
+/// File name offered for download.
+pub fn export_file_name(generated_at: DateTime<Utc>) -> String {
+    format!(
+        "second-brain-export-{}.zip",
+        generated_at.format("%Y-%m-%d")
+    )
+}
+
+/// Rows per dataset, stored in `data_exports.record_counts`.
+pub fn record_counts(datasets: &[(&Dataset, Vec<Value>)]) -> Value {
+    let counts: Map<String, Value> = datasets
+        .iter()
+        .map(|(dataset, rows)| (dataset.name.to_string(), json!(rows.len())))
+        .collect();
+    Value::Object(counts)
+}
+
+/// CSV with a header row; nested values (arrays, objects) are written as JSON.
+pub fn render_csv(columns: &[&str], rows: &[Value]) -> String {
+    let mut out = columns.join(",");
+    out.push('\n');
+
+    for row in rows {
+        let fields: Vec<String> = columns
+            .iter()
+            .map(|column| csv_field(&csv_value(row.get(*column).unwrap_or(&Value::Null))))
+            .collect();
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+
+    out
+}
+
+fn csv_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Zip bundle with `manifest.json` and each dataset as `{name}.json` and
+/// `{name}.csv`.
+pub fn build_bundle(
+    user_id: Uuid,
+    generated_at: DateTime<Utc>,
+    datasets: &[(&Dataset, Vec<Value>)],
+) -> Result<Vec<u8>> {
+    let zip_error = |e: zip::result::ZipError| Error::Internal(format!("Zip error: {}", e));
+    let io_error = |e: std::io::Error| Error::Internal(format!("Zip write error: {}", e));
+
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+
+    let manifest = json!({
+        "user_id": user_id,
+        "generated_at": generated_at.to_rfc3339(),
+        "datasets": record_counts(datasets),
+    });
+    zip.start_file("manifest.json", options)
+        .map_err(zip_error)?;
+    zip.write_all(&serde_json::to_vec_pretty(&manifest)?)
+        .map_err(io_error)?;
+
+    for (dataset, rows) in datasets {
+        zip.start_file(format!("{}.json", dataset.name), options)
+            .map_err(zip_error)?;
+        zip.write_all(&serde_json::to_vec_pretty(rows)?)
+            .map_err(io_error)?;
+
+        zip.start_file(format!("{}.csv", dataset.name), options)
+            .map_err(zip_error)?;
+        zip.write_all(render_csv(dataset.columns, rows).as_bytes())
+            .map_err(io_error)?;
+    }
+
+    Ok(zip.finish().map_err(zip_error)?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::io::Read;
+
+    #[test]
+    fn dataset_queries_select_their_columns() {
+        for dataset in DATASETS {
+            for column in dataset.columns {
+                assert!(
+                    dataset.query.contains(column),
+                    "{} query doesn't select {}",
+                    dataset.name,
+                    column
+                );
+            }
+            assert!(
+                dataset.query.contains("$1"),
+                "{} isn't user-scoped",
+                dataset.name
+            );
+        }
+    }
+
+    #[test]
+    fn renders_csv() {
+        let rows = vec![
+            json!({
+                "id": 1,
+                "content": "Max likes \"Lego\", trains",
+                "aliases": ["Maxi", "M"],
+                "deleted_at": null,
+            }),
+            json!({ "id": 2, "content": "Line\nbreak", "aliases": [] }),
+        ];
+
+        assert_eq!(
+            render_csv(&["id", "content", "aliases", "deleted_at"], &rows),
+            "id,content,aliases,deleted_at\n\
+             1,\"Max likes \"\"Lego\"\", trains\",\"[\"\"Maxi\"\",\"\"M\"\"]\",\n\
+             2,\"Line\nbreak\",[],\n"
+        );
+        assert_eq!(render_csv(&["id"], &[]), "id\n");
+    }
+
+    #[test]
+    fn bundles_datasets_as_json_and_csv() {
+        let user_id = Uuid::nil();
+        let generated_at = Utc.with_ymd_and_hms(2024, 7, 4, 12, 0, 0).unwrap();
+        let facts = &DATASETS[1];
+        let datasets = vec![(facts, vec![json!({ "id": "a", "content": "Hi" })])];
+
+        let bundle = build_bundle(user_id, generated_at, &datasets).unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(bundle)).unwrap();
+
+        let mut names: Vec<String> = archive.file_names().map(str::to_string).collect();
+        names.sort();
+        assert_eq!(names, vec!["facts.csv", "facts.json", "manifest.json"]);
+
+        let mut manifest = String::new();
+        archive
+            .by_name("manifest.json")
+            .unwrap()
+            .read_to_string(&mut manifest)
+            .unwrap();
+        let manifest: Value = serde_json::from_str(&manifest).unwrap();
+        assert_eq!(manifest["datasets"], json!({ "facts": 1 }));
+        assert_eq!(manifest["generated_at"], "2024-07-04T12:00:00+00:00");
+    }
+
+    #[test]
+    fn names_exports() {
+        let id = Uuid::nil();
+        assert_eq!(
+            export_key(id, id),
+            "exports/00000000-0000-0000-0000-000000000000/00000000-0000-0000-0000-000000000000.zip"
+        );
+        assert_eq!(
+            export_file_name(Utc.with_ymd_and_hms(2024, 7, 4, 12, 0, 0).unwrap()),
+            "second-brain-export-2024-07-04.zip"
+        );
+    }
+}
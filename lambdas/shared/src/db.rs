@@ -1,10 +1,329 @@
 //! Database connection management.
+//!
+//! Lambdas connect with [`connect`], which reads `DB_HOST`, `DB_NAME` and
+//! `DB_PORT` and authenticates according to `DB_AUTH_MODE`:
+//!
+//! - `secret` (default): the username and password stored in the Secrets
+//!   Manager secret named by `DB_SECRET_ARN`. The secret is re-read when it
+//!   leaves the secrets cache and whenever the database rejects it, so
+//!   rotation doesn't break warm containers.
+//! - `iam`: an RDS IAM auth token for `DB_IAM_USER`, so the Lambda holds no
+//!   long-lived password. Tokens are only checked when a connection is opened
+//!   and expire after 15 minutes, so a background task re-signs the pool's
+//!   connect options before they do.
+//!
+//! Either mode works against the instance or an RDS Proxy endpoint in `DB_HOST`.
 
-use sqlx::postgres::{PgPool, PgPoolOptions};
-use std::time::Duration;
+use aws_credential_types::provider::ProvideCredentials;
+use aws_sigv4::http_request::{
+    sign, SignableBody, SignableRequest, SignatureLocation, SigningSettings,
+};
+use aws_sigv4::sign::v4;
+use sqlx::postgres::{PgConnectOptions, PgConnection, PgPool, PgPoolOptions, PgSslMode};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
 
+use crate::secrets::{cache_ttl, get_database_credentials, refresh_database_credentials};
 use crate::{Config, Error, Result};
 
+/// Database name used when `DB_NAME` is unset
+pub const DEFAULT_DB_NAME: &str = "second_brain";
+
+/// Postgres port used when `DB_PORT` is unset
+pub const DEFAULT_PORT: u16 = 5432;
+
+/// Username used when the credentials secret doesn't name one
+pub const DEFAULT_USERNAME: &str = "sbadmin";
+
+/// Database user for IAM auth when `DB_IAM_USER` is unset (see migration 055)
+pub const DEFAULT_IAM_USER: &str = "sb_lambda";
+
+/// How long an RDS IAM auth token is accepted for
+pub const IAM_TOKEN_LIFETIME: Duration = Duration::from_secs(15 * 60);
+
+/// Age at which the pool's IAM token is replaced, well inside its lifetime
+const IAM_TOKEN_REFRESH_AFTER: Duration = Duration::from_secs(10 * 60);
+
+/// How often the refresh task checks the credentials' age. Frozen Lambda
+/// containers don't run it, so it is checked often enough to catch up soon
+/// after a thaw.
+const CREDENTIAL_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Connections per Lambda container
+const MAX_CONNECTIONS: u32 = 5;
+
+/// How the database authenticates a Lambda.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthMode {
+    /// Password from the Secrets Manager secret with this ARN
+    Secret { secret_arn: String },
+    /// RDS IAM auth token for this database user
+    Iam { username: String },
+}
+
+/// Where and how to connect, from the environment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbSettings {
+    pub host: String,
+    pub port: u16,
+    pub name: String,
+    pub auth: AuthMode,
+}
+
+impl DbSettings {
+    /// Read settings from `DB_HOST`, `DB_PORT`, `DB_NAME`, `DB_AUTH_MODE`,
+    /// `DB_SECRET_ARN` and `DB_IAM_USER`.
+    pub fn from_env() -> Result<Self> {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    fn from_lookup(var: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let host = var("DB_HOST").ok_or_else(|| Error::Config("DB_HOST not set".to_string()))?;
+        let port = match var("DB_PORT") {
+            Some(port) => port
+                .parse()
+                .map_err(|_| Error::Config(format!("Invalid DB_PORT: {}", port)))?,
+            None => DEFAULT_PORT,
+        };
+        let name = var("DB_NAME").unwrap_or_else(|| DEFAULT_DB_NAME.to_string());
+
+        let auth = match var("DB_AUTH_MODE").as_deref().unwrap_or("secret") {
+            "secret" => AuthMode::Secret {
+                secret_arn: var("DB_SECRET_ARN")
+                    .ok_or_else(|| Error::Config("DB_SECRET_ARN not set".to_string()))?,
+            },
+            "iam" => AuthMode::Iam {
+                username: var("DB_IAM_USER").unwrap_or_else(|| DEFAULT_IAM_USER.to_string()),
+            },
+            other => {
+                return Err(Error::Config(format!(
+                    "Invalid DB_AUTH_MODE: {} (expected secret or iam)",
+                    other
+                )))
+            }
+        };
+
+        Ok(Self {
+            host,
+            port,
+            name,
+            auth,
+        })
+    }
+
+    fn connect_options(&self, username: &str, password: &str) -> PgConnectOptions {
+        PgConnectOptions::new()
+            .host(&self.host)
+            .port(self.port)
+            .database(&self.name)
+            .username(username)
+            .password(password)
+    }
+}
+
+/// Connect to the database configured in the environment (see the module docs).
+pub async fn connect(config: &aws_config::SdkConfig) -> Result<PgPool> {
+    connect_with(config, &DbSettings::from_env()?).await
+}
+
+/// Connect with explicit settings.
+///
+/// If the database rejects a cached password, the secret has probably been
+/// rotated, so it is re-read from Secrets Manager and the connection retried once.
+pub async fn connect_with(config: &aws_config::SdkConfig, settings: &DbSettings) -> Result<PgPool> {
+    let pool_options = PgPoolOptions::new().max_connections(MAX_CONNECTIONS);
+    let pool = match pool_options
+        .clone()
+        .connect_with(login(config, settings, false).await?)
+        .await
+    {
+        Ok(pool) => pool,
+        Err(e) if is_auth_failure(&e) && matches!(settings.auth, AuthMode::Secret { .. }) => {
+            warn!(error = %e, "Database rejected cached credentials, refreshing secret");
+            pool_options
+                .connect_with(login(config, settings, true).await?)
+                .await
+                .map_err(Error::Database)?
+        }
+        Err(e) => return Err(Error::Database(e)),
+    };
+
+    spawn_credential_refresh(pool.clone(), config.clone(), settings.clone());
+    if let AuthMode::Iam { username } = &settings.auth {
+        info!(user = %username, "Connected to database with IAM auth");
+    }
+    Ok(pool)
+}
+
+/// Connect options carrying current credentials. `refresh` bypasses the
+/// secrets cache, for when the cached password has been rejected.
+async fn login(
+    config: &aws_config::SdkConfig,
+    settings: &DbSettings,
+    refresh: bool,
+) -> Result<PgConnectOptions> {
+    match &settings.auth {
+        AuthMode::Secret { secret_arn } => {
+            let client = aws_sdk_secretsmanager::Client::new(config);
+            let creds = if refresh {
+                refresh_database_credentials(&client, secret_arn).await?
+            } else {
+                get_database_credentials(&client, secret_arn).await?
+            };
+            let username = if creds.username.is_empty() {
+                DEFAULT_USERNAME
+            } else {
+                &creds.username
+            };
+            Ok(settings.connect_options(username, &creds.password))
+        }
+        AuthMode::Iam { username } => {
+            let token = iam_auth_token(config, settings, username).await?;
+            // RDS only accepts IAM tokens over TLS
+            Ok(settings
+                .connect_options(username, &token)
+                .ssl_mode(PgSslMode::Require))
+        }
+    }
+}
+
+/// Whether Postgres refused the credentials (`invalid_password` or
+/// `invalid_authorization_specification`).
+fn is_auth_failure(error: &sqlx::Error) -> bool {
+    error
+        .as_database_error()
+        .and_then(|e| e.code())
+        .is_some_and(|code| code == "28P01" || code == "28000")
+}
+
+/// Keep the pool's credentials fresh so new connections don't use an expired
+/// IAM token or a rotated password. Open connections are unaffected.
+///
+/// Stops once the pool is closed.
+fn spawn_credential_refresh(pool: PgPool, config: aws_config::SdkConfig, settings: DbSettings) {
+    let refresh_after = match settings.auth {
+        AuthMode::Secret { .. } => cache_ttl(),
+        AuthMode::Iam { .. } => IAM_TOKEN_REFRESH_AFTER,
+    };
+
+    tokio::spawn(async move {
+        let mut signed_at = SystemTime::now();
+        let mut interval = tokio::time::interval(CREDENTIAL_CHECK_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        while !pool.is_closed() {
+            interval.tick().await;
+            let age = signed_at.elapsed().unwrap_or_default();
+            if age < refresh_after {
+                continue;
+            }
+
+            match login(&config, &settings, false).await {
+                Ok(options) => {
+                    pool.set_connect_options(options);
+                    signed_at = SystemTime::now();
+                }
+                // Retried on the next tick
+                Err(e) => warn!(error = %e, "Failed to refresh database credentials"),
+            }
+        }
+    });
+}
+
+/// Generate an RDS IAM auth token: a SigV4-presigned `connect` request for
+/// `username`, without its scheme, used as the connection password.
+pub async fn iam_auth_token(
+    config: &aws_config::SdkConfig,
+    settings: &DbSettings,
+    username: &str,
+) -> Result<String> {
+    let credentials = config
+        .credentials_provider()
+        .ok_or_else(|| Error::Aws("No AWS credentials provider configured".to_string()))?
+        .provide_credentials()
+        .await
+        .map_err(|e| Error::Aws(format!("Failed to load AWS credentials: {}", e)))?;
+    let region = config
+        .region()
+        .ok_or_else(|| Error::Aws("No AWS region configured".to_string()))?
+        .to_string();
+
+    let identity = credentials.into();
+    let mut signing_settings = SigningSettings::default();
+    signing_settings.expires_in = Some(IAM_TOKEN_LIFETIME);
+    signing_settings.signature_location = SignatureLocation::QueryParams;
+
+    let signing_params = v4::SigningParams::builder()
+        .identity(&identity)
+        .region(&region)
+        .name("rds-db")
+        .time(SystemTime::now())
+        .settings(signing_settings)
+        .build()
+        .map_err(|e| Error::Aws(format!("Failed to build IAM token signing params: {}", e)))?;
+
+    let mut url = url::Url::parse(&format!("https://{}:{}/", settings.host, settings.port))
+        .map_err(|e| Error::Config(format!("Invalid DB_HOST: {}", e)))?;
+    url.query_pairs_mut()
+        .append_pair("Action", "connect")
+        .append_pair("DBUser", username);
+
+    let request = SignableRequest::new(
+        "GET",
+        url.as_str(),
+        std::iter::empty(),
+        SignableBody::Bytes(&[]),
+    )
+    .map_err(|e| Error::Aws(format!("Failed to sign IAM token: {}", e)))?;
+    let (instructions, _signature) = sign(request, &signing_params.into())
+        .map_err(|e| Error::Aws(format!("Failed to sign IAM token: {}", e)))?
+        .into_parts();
+
+    for (name, value) in instructions.params() {
+        url.query_pairs_mut().append_pair(name, value);
+    }
+
+    Ok(url.as_str().trim_start_matches("https://").to_string())
+}
+
+/// Run `f` in a transaction, committing if it returns `Ok` and rolling back if
+/// it returns `Err`, so a change spanning several statements is never half
+/// applied. Audit records and other best-effort writes belong after it, on
+/// the pool, so they see the committed rows.
+///
+/// ```ignore
+/// db::with_tx(&pool, async |tx| {
+///     sqlx::query("UPDATE tags SET name = $2 WHERE id = $1")
+///         .bind(tag_id)
+///         .bind(&name)
+///         .execute(&mut *tx)
+///         .await?;
+///     Ok::<_, Error>(())
+/// })
+/// .await?;
+/// ```
+pub async fn with_tx<T, E, F>(pool: &PgPool, f: F) -> std::result::Result<T, E>
+where
+    F: AsyncFnOnce(&mut PgConnection) -> std::result::Result<T, E>,
+    E: From<sqlx::Error>,
+{
+    let mut tx = pool.begin().await?;
+    match f(&mut tx).await {
+        Ok(value) => {
+            tx.commit().await?;
+            Ok(value)
+        }
+        Err(e) => {
+            // Dropping the transaction would also roll it back, but not until
+            // the connection is next used
+            if let Err(rollback) = tx.rollback().await {
+                warn!(error = %rollback, "Failed to roll back transaction");
+            }
+            Err(e)
+        }
+    }
+}
+
 /// Create a database connection pool.
 pub async fn create_pool(config: &Config, password: &str) -> Result<PgPool> {
     let database_url = format!(
@@ -24,3 +343,64 @@ pub async fn create_pool(config: &Config, password: &str) -> Result<PgPool> {
 
     Ok(pool)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn settings(vars: &[(&str, &str)]) -> Result<DbSettings> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        DbSettings::from_lookup(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn defaults_to_secret_auth() {
+        let settings =
+            settings(&[("DB_HOST", "db.internal"), ("DB_SECRET_ARN", "arn:secret")]).unwrap();
+        assert_eq!(
+            settings,
+            DbSettings {
+                host: "db.internal".to_string(),
+                port: DEFAULT_PORT,
+                name: DEFAULT_DB_NAME.to_string(),
+                auth: AuthMode::Secret {
+                    secret_arn: "arn:secret".to_string()
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn iam_auth_needs_no_secret() {
+        let settings = settings(&[
+            ("DB_HOST", "proxy.internal"),
+            ("DB_PORT", "6432"),
+            ("DB_AUTH_MODE", "iam"),
+        ])
+        .unwrap();
+        assert_eq!(settings.port, 6432);
+        assert_eq!(
+            settings.auth,
+            AuthMode::Iam {
+                username: DEFAULT_IAM_USER.to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_bad_settings() {
+        assert!(settings(&[("DB_SECRET_ARN", "arn:secret")]).is_err());
+        assert!(settings(&[("DB_HOST", "db.internal")]).is_err());
+        assert!(settings(&[("DB_HOST", "db.internal"), ("DB_AUTH_MODE", "password")]).is_err());
+        assert!(settings(&[
+            ("DB_HOST", "db.internal"),
+            ("DB_AUTH_MODE", "iam"),
+            ("DB_PORT", "postgres")
+        ])
+        .is_err());
+    }
+}
@@ -0,0 +1,578 @@
+//! OAuth 2.0 device authorization (RFC 8628) for CLI and TV clients.
+//!
+//! Devices that can't show the Cognito sign-in page start a flow with
+//! `POST /auth/device/code`, show the user code, and poll `POST /auth/token`
+//! while the user approves the code on the web (`POST /auth/device/approve`).
+//! Once approved, the device gets its own access and refresh tokens; they
+//! aren't Cognito tokens, so like API keys they are checked by
+//! [`crate::AuthorizedUser::from_request`] rather than API Gateway. Refreshing
+//! rotates both tokens. Codes and tokens are stored as SHA-256 hashes, hashed
+//! by Postgres like API keys.
+
+use chrono::{DateTime, Duration, Utc};
+use lambda_http::Request;
+use serde::Serialize;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::codes::random_code;
+use crate::{Error, Result};
+
+/// Grant type devices poll `/auth/token` with
+pub const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Grant type for refreshing tokens
+pub const REFRESH_TOKEN_GRANT: &str = "refresh_token";
+
+/// Minutes a device code can be approved for
+pub const CODE_TTL_MINUTES: i64 = 10;
+
+/// Seconds devices wait between polls to start with
+pub const POLL_INTERVAL_SECS: i32 = 5;
+
+/// Seconds an access token lasts
+pub const ACCESS_TOKEN_TTL_SECS: i64 = 3600;
+
+/// Days a refresh token lasts; each refresh starts it again
+pub const REFRESH_TOKEN_DAYS: i64 = 30;
+
+/// Access tokens start with this, so they can be told apart from JWTs
+pub const ACCESS_TOKEN_PREFIX: &str = "sbat_";
+
+/// Refresh tokens start with this
+pub const REFRESH_TOKEN_PREFIX: &str = "sbrt_";
+
+/// Letters user codes are made of: no vowels, so codes don't spell words
+const USER_CODE_ALPHABET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+
+/// Letters in a user code, shown as two groups of four
+const USER_CODE_LEN: usize = 8;
+
+/// Most characters of a client name kept
+pub const MAX_CLIENT_NAME_LEN: usize = 100;
+
+/// Response to `POST /auth/device/code` (RFC 8628 section 3.2)
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DeviceCode {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: String,
+    pub expires_in: i64,
+    pub interval: i32,
+}
+
+/// Tokens issued to a device (RFC 6749 section 5.1)
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Tokens {
+    pub access_token: String,
+    pub token_type: &'static str,
+    pub expires_in: i64,
+    pub refresh_token: String,
+}
+
+/// Why `/auth/token` didn't issue tokens (RFC 8628 section 3.5)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenError {
+    /// The user hasn't approved the code yet
+    AuthorizationPending,
+    /// The device is polling too often; its interval went up
+    SlowDown,
+    AccessDenied,
+    ExpiredToken,
+    /// Unknown or already used code or refresh token
+    InvalidGrant,
+}
+
+impl TokenError {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AuthorizationPending => "authorization_pending",
+            Self::SlowDown => "slow_down",
+            Self::AccessDenied => "access_denied",
+            Self::ExpiredToken => "expired_token",
+            Self::InvalidGrant => "invalid_grant",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::AuthorizationPending => "The code hasn't been approved yet",
+            Self::SlowDown => "Polling too often; wait longer between requests",
+            Self::AccessDenied => "The code was denied",
+            Self::ExpiredToken => "The code expired; start again",
+            Self::InvalidGrant => "Unknown, used or expired code or token",
+        }
+    }
+}
+
+/// A device signed in through the device flow, as listed to its user
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceSession {
+    pub id: Uuid,
+    pub client_name: String,
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// When the device must refresh by or sign in again
+    pub refresh_expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The user a device access token belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceOwner {
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+    pub cognito_sub: String,
+}
+
+/// Random user code such as `WDJB-MJHT`.
+pub fn generate_user_code() -> String {
+    let letters = random_code(USER_CODE_LEN, USER_CODE_ALPHABET);
+    format!("{}-{}", &letters[..4], &letters[4..])
+}
+
+/// A user code as typed (any case, with or without the dash or spaces), in
+/// the form it was issued, or `None` if it can't be one.
+pub fn normalize_user_code(input: &str) -> Option<String> {
+    let letters: String = input
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let valid =
+        letters.len() == USER_CODE_LEN && letters.bytes().all(|b| USER_CODE_ALPHABET.contains(&b));
+    valid.then(|| format!("{}-{}", &letters[..4], &letters[4..]))
+}
+
+/// Random secret with `prefix` (two v4 UUIDs, 244 bits of randomness).
+fn generate_secret(prefix: &str) -> String {
+    format!(
+        "{}{}{}",
+        prefix,
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+/// The device access token a request carries as a bearer token, if any.
+pub fn token_from_request(req: &Request) -> Option<&str> {
+    req.headers()
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| token.starts_with(ACCESS_TOKEN_PREFIX))
+}
+
+/// Check a client name, e.g. `Living room TV`.
+pub fn validate_client_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(Error::Validation(
+            "client_name must not be empty".to_string(),
+        ));
+    }
+    Ok(name.chars().take(MAX_CLIENT_NAME_LEN).collect())
+}
+
+/// What a poll of a device code gets, given the code's state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollStep {
+    /// Issue tokens
+    Issue,
+    /// Refuse; `slow_down` also raises the interval
+    Refuse(TokenError),
+}
+
+/// Decide a poll (RFC 8628 section 3.5). Polling sooner than `interval`
+/// seconds after the last poll is `slow_down`.
+pub fn poll_step(
+    status: &str,
+    expires_at: DateTime<Utc>,
+    last_polled_at: Option<DateTime<Utc>>,
+    interval: i32,
+    now: DateTime<Utc>,
+) -> PollStep {
+    if expires_at <= now {
+        return PollStep::Refuse(TokenError::ExpiredToken);
+    }
+    match status {
+        "approved" => PollStep::Issue,
+        "denied" => PollStep::Refuse(TokenError::AccessDenied),
+        "pending" => match last_polled_at {
+            Some(last) if now - last < Duration::seconds(interval.into()) => {
+                PollStep::Refuse(TokenError::SlowDown)
+            }
+            _ => PollStep::Refuse(TokenError::AuthorizationPending),
+        },
+        // Tokens were already issued for the code
+        _ => PollStep::Refuse(TokenError::InvalidGrant),
+    }
+}
+
+/// Start a flow for a device. `verification_uri` is the web page where
+/// users enter codes.
+pub async fn start(pool: &PgPool, client_name: &str, verification_uri: &str) -> Result<DeviceCode> {
+    let client_name = validate_client_name(client_name)?;
+
+    // Codes are only useful for minutes; clear out old ones as we go
+    sqlx::query("DELETE FROM device_authorizations WHERE expires_at < NOW() - INTERVAL '1 day'")
+        .execute(pool)
+        .await?;
+
+    let device_code = generate_secret("");
+    let expires_at = Utc::now() + Duration::minutes(CODE_TTL_MINUTES);
+
+    // A new user code could clash with one still pending; try again if so
+    let mut attempts = 0;
+    let user_code = loop {
+        let user_code = generate_user_code();
+        let inserted = sqlx::query(
+            r#"
+            INSERT INTO device_authorizations
+                (device_code_hash, user_code, client_name, poll_interval, expires_at)
+            VALUES (sha256(convert_to($1, 'UTF8')), $2, $3, $4, $5)
+            ON CONFLICT (user_code) WHERE status = 'pending' DO NOTHING
+            "#,
+        )
+        .bind(&device_code)
+        .bind(&user_code)
+        .bind(&client_name)
+        .bind(POLL_INTERVAL_SECS)
+        .bind(expires_at)
+        .execute(pool)
+        .await?
+        .rows_affected();
+
+        if inserted > 0 {
+            break user_code;
+        }
+        attempts += 1;
+        if attempts >= 5 {
+            return Err(Error::Conflict("Couldn't allocate a user code".to_string()));
+        }
+    };
+
+    Ok(DeviceCode {
+        verification_uri_complete: format!("{}?code={}", verification_uri, user_code),
+        verification_uri: verification_uri.to_string(),
+        device_code,
+        user_code,
+        expires_in: CODE_TTL_MINUTES * 60,
+        interval: POLL_INTERVAL_SECS,
+    })
+}
+
+/// Approve (or deny) a pending code as `user_id`. Returns the name of the
+/// client the code was for, or `None` if no pending code matches.
+pub async fn approve(
+    pool: &PgPool,
+    user_code: &str,
+    user_id: Uuid,
+    approved: bool,
+) -> Result<Option<String>> {
+    let Some(user_code) = normalize_user_code(user_code) else {
+        return Ok(None);
+    };
+
+    let client_name = sqlx::query_scalar(
+        r#"
+        UPDATE device_authorizations
+        SET status = $3, user_id = $2
+        WHERE user_code = $1 AND status = 'pending' AND expires_at > NOW()
+        RETURNING client_name
+        "#,
+    )
+    .bind(&user_code)
+    .bind(user_id)
+    .bind(if approved { "approved" } else { "denied" })
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(client_name)
+}
+
+/// Poll for tokens with a device code. `Ok(Err(_))` is a refusal to send
+/// the device, not a failure.
+pub async fn poll(
+    pool: &PgPool,
+    device_code: &str,
+) -> Result<std::result::Result<Tokens, TokenError>> {
+    let now = Utc::now();
+    let mut tx = pool.begin().await?;
+
+    let row: Option<(
+        Uuid,
+        String,
+        DateTime<Utc>,
+        Option<DateTime<Utc>>,
+        i32,
+        Option<Uuid>,
+        String,
+    )> = sqlx::query_as(
+        r#"
+            SELECT id, status, expires_at, last_polled_at, poll_interval, user_id, client_name
+            FROM device_authorizations
+            WHERE device_code_hash = sha256(convert_to($1, 'UTF8'))
+            FOR UPDATE
+            "#,
+    )
+    .bind(device_code)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some((id, status, expires_at, last_polled_at, interval, user_id, client_name)) = row else {
+        return Ok(Err(TokenError::InvalidGrant));
+    };
+
+    let step = poll_step(&status, expires_at, last_polled_at, interval, now);
+    let slow_down = step == PollStep::Refuse(TokenError::SlowDown);
+    sqlx::query(
+        r#"
+        UPDATE device_authorizations
+        SET last_polled_at = $2,
+            poll_interval = poll_interval + CASE WHEN $3 THEN 5 ELSE 0 END,
+            status = CASE WHEN $4 THEN 'issued' ELSE status END
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(now)
+    .bind(slow_down)
+    .bind(step == PollStep::Issue)
+    .execute(&mut *tx)
+    .await?;
+
+    let tokens = match (step, user_id) {
+        (PollStep::Issue, Some(user_id)) => {
+            let tokens = new_tokens();
+            sqlx::query(
+                r#"
+                INSERT INTO device_sessions
+                    (user_id, client_name, access_token_hash, access_expires_at,
+                     refresh_token_hash, refresh_expires_at)
+                VALUES ($1, $2, sha256(convert_to($3, 'UTF8')), $4, sha256(convert_to($5, 'UTF8')), $6)
+                "#,
+            )
+            .bind(user_id)
+            .bind(&client_name)
+            .bind(&tokens.access_token)
+            .bind(now + Duration::seconds(ACCESS_TOKEN_TTL_SECS))
+            .bind(&tokens.refresh_token)
+            .bind(now + Duration::days(REFRESH_TOKEN_DAYS))
+            .execute(&mut *tx)
+            .await?;
+            Ok(tokens)
+        }
+        (PollStep::Issue, None) => Err(TokenError::InvalidGrant),
+        (PollStep::Refuse(e), _) => Err(e),
+    };
+
+    tx.commit().await?;
+    Ok(tokens)
+}
+
+/// Swap a refresh token for new tokens. The old refresh token stops working.
+pub async fn refresh(
+    pool: &PgPool,
+    refresh_token: &str,
+) -> Result<std::result::Result<Tokens, TokenError>> {
+    let now = Utc::now();
+    let tokens = new_tokens();
+
+    let refreshed = sqlx::query(
+        r#"
+        UPDATE device_sessions
+        SET access_token_hash = sha256(convert_to($2, 'UTF8')),
+            access_expires_at = $3,
+            refresh_token_hash = sha256(convert_to($4, 'UTF8')),
+            refresh_expires_at = $5
+        WHERE refresh_token_hash = sha256(convert_to($1, 'UTF8'))
+          AND refresh_expires_at > $6
+        "#,
+    )
+    .bind(refresh_token)
+    .bind(&tokens.access_token)
+    .bind(now + Duration::seconds(ACCESS_TOKEN_TTL_SECS))
+    .bind(&tokens.refresh_token)
+    .bind(now + Duration::days(REFRESH_TOKEN_DAYS))
+    .bind(now)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(if refreshed > 0 {
+        Ok(tokens)
+    } else {
+        Err(TokenError::InvalidGrant)
+    })
+}
+
+fn new_tokens() -> Tokens {
+    Tokens {
+        access_token: generate_secret(ACCESS_TOKEN_PREFIX),
+        token_type: "Bearer",
+        expires_in: ACCESS_TOKEN_TTL_SECS,
+        refresh_token: generate_secret(REFRESH_TOKEN_PREFIX),
+    }
+}
+
+/// Look up the owner of an access token and note that it was used. Returns
+/// `Error::Auth` for unknown and expired tokens.
+pub async fn authenticate(pool: &PgPool, access_token: &str) -> Result<DeviceOwner> {
+    let row: Option<(Uuid, Uuid, String)> = sqlx::query_as(
+        r#"
+        UPDATE device_sessions s SET last_used_at = NOW()
+        FROM users u
+        WHERE s.access_token_hash = sha256(convert_to($1, 'UTF8'))
+          AND s.access_expires_at > NOW()
+          AND u.id = s.user_id
+        RETURNING s.id, s.user_id, u.cognito_sub
+        "#,
+    )
+    .bind(access_token)
+    .fetch_optional(pool)
+    .await?;
+
+    let (session_id, user_id, cognito_sub) =
+        row.ok_or_else(|| Error::Auth("Invalid or expired access token".to_string()))?;
+
+    Ok(DeviceOwner {
+        session_id,
+        user_id,
+        cognito_sub,
+    })
+}
+
+/// Devices the user has signed in, most recently used first.
+pub async fn list_sessions(pool: &PgPool, user_id: Uuid) -> Result<Vec<DeviceSession>> {
+    let sessions = sqlx::query_as(
+        r#"
+        SELECT id, client_name, last_used_at, refresh_expires_at, created_at
+        FROM device_sessions
+        WHERE user_id = $1 AND refresh_expires_at > NOW()
+        ORDER BY COALESCE(last_used_at, created_at) DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(sessions)
+}
+
+/// Sign a device out; its tokens stop working at once. Returns whether the
+/// user had such a device.
+pub async fn revoke_session(pool: &PgPool, session_id: Uuid, user_id: Uuid) -> Result<bool> {
+    let deleted = sqlx::query("DELETE FROM device_sessions WHERE id = $1 AND user_id = $2")
+        .bind(session_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+
+    Ok(deleted > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_code() {
+        let code = generate_user_code();
+        assert_eq!(code.len(), 9);
+        assert_eq!(&code[4..5], "-");
+        assert_eq!(normalize_user_code(&code), Some(code.clone()));
+        assert_eq!(
+            normalize_user_code(&code.to_lowercase().replace('-', " ")),
+            Some(code)
+        );
+
+        // Vowels and digits are never issued
+        assert_eq!(normalize_user_code("ABCD-EFGH"), None);
+        assert_eq!(normalize_user_code("WDJB-MJH"), None);
+        assert_eq!(
+            normalize_user_code("wdjbmjht"),
+            Some("WDJB-MJHT".to_string())
+        );
+    }
+
+    #[test]
+    fn test_poll_step() {
+        let now = Utc::now();
+        let expires_at = now + Duration::minutes(5);
+
+        assert_eq!(
+            poll_step("pending", expires_at, None, 5, now),
+            PollStep::Refuse(TokenError::AuthorizationPending)
+        );
+        assert_eq!(
+            poll_step(
+                "pending",
+                expires_at,
+                Some(now - Duration::seconds(2)),
+                5,
+                now
+            ),
+            PollStep::Refuse(TokenError::SlowDown)
+        );
+        assert_eq!(
+            poll_step(
+                "pending",
+                expires_at,
+                Some(now - Duration::seconds(6)),
+                5,
+                now
+            ),
+            PollStep::Refuse(TokenError::AuthorizationPending)
+        );
+        assert_eq!(
+            poll_step("approved", expires_at, None, 5, now),
+            PollStep::Issue
+        );
+        assert_eq!(
+            poll_step("denied", expires_at, None, 5, now),
+            PollStep::Refuse(TokenError::AccessDenied)
+        );
+        assert_eq!(
+            poll_step("issued", expires_at, None, 5, now),
+            PollStep::Refuse(TokenError::InvalidGrant)
+        );
+        assert_eq!(
+            poll_step("approved", now, None, 5, now),
+            PollStep::Refuse(TokenError::ExpiredToken)
+        );
+    }
+
+    #[test]
+    fn test_token_from_request() {
+        let req = lambda_http::http::Request::builder()
+            .header("Authorization", "Bearer sbat_abc")
+            .body(lambda_http::Body::Empty)
+            .unwrap();
+        assert_eq!(token_from_request(&req), Some("sbat_abc"));
+
+        // Cognito JWTs are left to API Gateway
+        let req = lambda_http::http::Request::builder()
+            .header("Authorization", "Bearer eyJhbGciOi")
+            .body(lambda_http::Body::Empty)
+            .unwrap();
+        assert_eq!(token_from_request(&req), None);
+    }
+
+    #[test]
+    fn test_validate_client_name() {
+        assert_eq!(
+            validate_client_name(" Living room TV ").unwrap(),
+            "Living room TV"
+        );
+        assert!(validate_client_name("").is_err());
+        assert_eq!(
+            validate_client_name(&"x".repeat(150)).unwrap().len(),
+            MAX_CLIENT_NAME_LEN
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Opt-in debug mode: PII-scrubbed request/response samples for investigating
+//! "the bot answered wrong" reports.
+//!
+//! Users turn on a time-limited debug session; while it is active, callers of the
+//! agent record samples with [`record_sample`]. Samples expire after
+//! [`SAMPLE_RETENTION_DAYS`] and are only readable through the admin endpoint.
+
+use serde_json::Value;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{AgentResponse, Result};
+
+/// Longest debug session a user can opt in to.
+pub const MAX_SESSION_MINUTES: i64 = 24 * 60;
+
+/// How long captured samples are kept.
+pub const SAMPLE_RETENTION_DAYS: i32 = 14;
+
+/// Longest request/response text stored per sample.
+pub const MAX_SAMPLE_CHARS: usize = 4000;
+
+/// Minimum digits in a run before it is treated as a phone/account number.
+const MIN_REDACTED_DIGITS: usize = 7;
+
+/// Characters allowed inside a phone/account number run.
+const NUMBER_SEPARATORS: [char; 6] = [' ', '-', '.', '(', ')', '+'];
+
+/// Redact emails and long digit runs (phone, card, account numbers) and truncate.
+///
+/// ISO dates (`2026-10-15`) are kept since they are usually what a wrong answer is about.
+pub fn scrub(text: &str) -> String {
+    let scrubbed = scrub_numbers(&scrub_emails(text));
+
+    match scrubbed.char_indices().nth(MAX_SAMPLE_CHARS) {
+        Some((idx, _)) => format!("{}…", &scrubbed[..idx]),
+        None => scrubbed,
+    }
+}
+
+fn scrub_emails(text: &str) -> String {
+    text.split_inclusive(char::is_whitespace)
+        .map(|piece| {
+            let word = piece.trim_end();
+            let core = word.trim_matches(|c: char| matches!(c, ',' | '.' | ';' | ':' | '(' | ')' | '<' | '>' | '"' | '\''));
+            if is_email(core) {
+                piece.replacen(core, "[email]", 1)
+            } else {
+                piece.to_string()
+            }
+        })
+        .collect()
+}
+
+fn is_email(word: &str) -> bool {
+    match word.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !domain.contains('@')
+        }
+        None => false,
+    }
+}
+
+fn scrub_numbers(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let starts_number = chars[i].is_ascii_digit()
+            || (matches!(chars[i], '+' | '(')
+                && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()));
+
+        if !starts_number {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        }
+
+        // Extend over digits and separators, ending on the last digit
+        let mut end = i;
+        let mut j = i;
+        while j < chars.len() && (chars[j].is_ascii_digit() || NUMBER_SEPARATORS.contains(&chars[j])) {
+            if chars[j].is_ascii_digit() {
+                end = j + 1;
+            }
+            j += 1;
+        }
+
+        let candidate: String = chars[i..end].iter().collect();
+        let digits = candidate.chars().filter(char::is_ascii_digit).count();
+
+        if digits >= MIN_REDACTED_DIGITS && !is_iso_date(&candidate) {
+            out.push_str("[number]");
+        } else {
+            out.push_str(&candidate);
+        }
+        i = end;
+    }
+
+    out
+}
+
+fn is_iso_date(candidate: &str) -> bool {
+    chrono::NaiveDate::parse_from_str(candidate, "%Y-%m-%d").is_ok()
+}
+
+/// Retrieval trace recorded alongside a sample.
+pub fn agent_trace(response: &AgentResponse) -> Value {
+    let metadata = response.metadata.as_ref();
+    serde_json::json!({
+        "status": response.status,
+        "conversation_id": response.conversation_id,
+        "model_id": metadata.and_then(|m| m.model_id.clone()),
+        "agents_used": metadata.and_then(|m| m.agents_used.clone()).unwrap_or_default(),
+        "handoff_count": metadata.and_then(|m| m.handoff_count),
+    })
+}
+
+/// A request/response pair to capture.
+#[derive(Debug)]
+pub struct Sample<'a> {
+    pub user_id: Uuid,
+    pub source: &'a str,
+    pub operation: &'a str,
+    pub request: &'a str,
+    pub response: Option<&'a str>,
+    pub error: Option<&'a str>,
+    pub trace: Value,
+    pub latency_ms: Option<i32>,
+}
+
+/// Record a sample if the user has an active debug session.
+///
+/// Returns whether a sample was stored. Content is scrubbed here, so callers
+/// pass the raw request and response.
+pub async fn record_sample(pool: &PgPool, sample: Sample<'_>) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO diagnostic_samples (
+            user_id, session_id, source, operation,
+            request_text, response_text, error_message,
+            retrieval_trace, latency_ms, expires_at
+        )
+        SELECT $1, ds.id, $2, $3, $4, $5, $6, $7, $8, NOW() + make_interval(days => $9)
+        FROM debug_sessions ds
+        WHERE ds.user_id = $1 AND ds.expires_at > NOW()
+        ORDER BY ds.started_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(sample.user_id)
+    .bind(sample.source)
+    .bind(sample.operation)
+    .bind(scrub(sample.request))
+    .bind(sample.response.map(scrub))
+    .bind(sample.error.map(scrub))
+    .bind(&sample.trace)
+    .bind(sample.latency_ms)
+    .bind(SAMPLE_RETENTION_DAYS)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Delete samples past their retention window.
+pub async fn purge_expired(pool: &PgPool) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM diagnostic_samples WHERE expires_at <= NOW()")
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrub_emails() {
+        assert_eq!(
+            scrub("Email jane.doe@example.com, or (bob@mail.co.uk) today"),
+            "Email [email], or ([email]) today"
+        );
+        assert_eq!(scrub("Meet @ the park"), "Meet @ the park");
+    }
+
+    #[test]
+    fn test_scrub_numbers() {
+        assert_eq!(scrub("Call +1 (555) 123-4567 now"), "Call [number] now");
+        assert_eq!(scrub("Card 4111 1111 1111 1111."), "Card [number].");
+        assert_eq!(scrub("Room 42 at 10:30 on 2026-10-15"), "Room 42 at 10:30 on 2026-10-15");
+    }
+
+    #[test]
+    fn test_scrub_truncates() {
+        let long = "a".repeat(MAX_SAMPLE_CHARS + 10);
+        assert_eq!(scrub(&long).chars().count(), MAX_SAMPLE_CHARS + 1);
+    }
+}
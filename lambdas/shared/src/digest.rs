@@ -0,0 +1,307 @@
+//! Notification digests.
+//!
+//! Low-priority notifications (priority 1) are held instead of being sent
+//! immediately, then `digest_builder` combines each user's pending ones into a
+//! single morning and/or evening message at their briefing times.
+
+use chrono::{DateTime, NaiveTime, Timelike, Utc};
+use chrono_tz::Tz;
+
+/// Notifications at or below this priority are batched into digests
+pub const DIGEST_MAX_PRIORITY: i16 = 1;
+
+/// Most notifications listed in one digest; the rest are counted
+pub const MAX_DIGEST_ITEMS: usize = 25;
+
+/// Longest notification body shown in a digest
+const MAX_ITEM_BODY_CHARS: usize = 200;
+
+/// Whether a notification is held for the user's digest.
+pub fn is_digest_priority(priority: i16) -> bool {
+    priority <= DIGEST_MAX_PRIORITY
+}
+
+/// When a user's digests go out (`user_notification_preferences.digest_mode`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestMode {
+    /// Low-priority notifications are sent immediately
+    Off,
+    Morning,
+    Evening,
+    Both,
+}
+
+impl DigestMode {
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "off" => DigestMode::Off,
+            "evening" => DigestMode::Evening,
+            "both" => DigestMode::Both,
+            _ => DigestMode::Morning,
+        }
+    }
+
+    fn includes(&self, slot: DigestSlot) -> bool {
+        matches!(
+            (self, slot),
+            (DigestMode::Both, _)
+                | (DigestMode::Morning, DigestSlot::Morning)
+                | (DigestMode::Evening, DigestSlot::Evening)
+        )
+    }
+}
+
+/// A digest delivery time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestSlot {
+    Morning,
+    Evening,
+    /// Digests were turned off with notifications still held; send them now
+    Flush,
+}
+
+impl DigestSlot {
+    pub fn title(&self) -> &'static str {
+        match self {
+            DigestSlot::Morning => "Your morning digest",
+            DigestSlot::Evening => "Your evening digest",
+            DigestSlot::Flush => "Your notification digest",
+        }
+    }
+}
+
+/// User preferences that decide when and where digests go.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DigestPreferences {
+    pub digest_mode: String,
+    pub morning_briefing_time: NaiveTime,
+    pub evening_briefing_time: NaiveTime,
+    pub timezone: String,
+    pub push_enabled: bool,
+    pub email_enabled: bool,
+    pub discord_enabled: bool,
+}
+
+impl Default for DigestPreferences {
+    /// Defaults used when a user has no preferences row.
+    fn default() -> Self {
+        Self {
+            digest_mode: "morning".to_string(),
+            morning_briefing_time: NaiveTime::from_hms_opt(7, 0, 0).unwrap_or_default(),
+            evening_briefing_time: NaiveTime::from_hms_opt(18, 0, 0).unwrap_or_default(),
+            timezone: "America/New_York".to_string(),
+            push_enabled: true,
+            email_enabled: true,
+            discord_enabled: false,
+        }
+    }
+}
+
+impl DigestPreferences {
+    pub fn mode(&self) -> DigestMode {
+        DigestMode::parse(&self.digest_mode)
+    }
+}
+
+/// The digest due for delivery in the hour containing `now`, if any.
+///
+/// A slot is due when the user's local hour matches the briefing time's hour,
+/// so an hourly run delivers each digest once. Unknown timezones fall back to
+/// UTC.
+pub fn due_slot(prefs: &DigestPreferences, now: DateTime<Utc>) -> Option<DigestSlot> {
+    let mode = prefs.mode();
+    if mode == DigestMode::Off {
+        return Some(DigestSlot::Flush);
+    }
+
+    let local_hour = match prefs.timezone.parse::<Tz>() {
+        Ok(tz) => now.with_timezone(&tz).hour(),
+        Err(_) => now.hour(),
+    };
+
+    [
+        (DigestSlot::Morning, prefs.morning_briefing_time),
+        (DigestSlot::Evening, prefs.evening_briefing_time),
+    ]
+    .into_iter()
+    .find(|(slot, time)| mode.includes(*slot) && time.hour() == local_hour)
+    .map(|(slot, _)| slot)
+}
+
+/// Channel a digest is sent on; it reads best as a Discord message or email.
+pub fn digest_channel(prefs: &DigestPreferences) -> &'static str {
+    if prefs.discord_enabled {
+        "discord"
+    } else if prefs.email_enabled {
+        "email"
+    } else {
+        "push"
+    }
+}
+
+/// A held notification to include in a digest
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DigestItem {
+    pub id: uuid::Uuid,
+    pub notification_type: String,
+    pub title: String,
+    pub body: String,
+}
+
+/// Section heading for a notification type
+fn section_heading(notification_type: &str) -> &'static str {
+    match notification_type {
+        "reminder" => "Reminders",
+        "calendar" => "Calendar",
+        "birthday" => "Birthdays",
+        "handoff" => "Handoffs",
+        "proactive" => "Suggestions",
+        _ => "Updates",
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => format!("{}…", text[..idx].trim_end()),
+        None => text.to_string(),
+    }
+}
+
+/// Title and body for a digest of `items` (oldest first), grouped by type.
+pub fn compose_digest(slot: DigestSlot, items: &[DigestItem]) -> (String, String) {
+    let title = match items.len() {
+        1 => format!("{} (1 update)", slot.title()),
+        n => format!("{} ({} updates)", slot.title(), n),
+    };
+
+    let shown = &items[..items.len().min(MAX_DIGEST_ITEMS)];
+
+    // Sections in order of first appearance
+    let mut sections: Vec<(&'static str, Vec<String>)> = Vec::new();
+    for item in shown {
+        let heading = section_heading(&item.notification_type);
+        let line = if item.body.trim().is_empty() || item.body.trim() == item.title.trim() {
+            format!("• {}", item.title.trim())
+        } else {
+            format!(
+                "• {}: {}",
+                item.title.trim(),
+                truncate(&item.body, MAX_ITEM_BODY_CHARS)
+            )
+        };
+
+        match sections.iter_mut().find(|(h, _)| *h == heading) {
+            Some((_, lines)) => lines.push(line),
+            None => sections.push((heading, vec![line])),
+        }
+    }
+
+    let mut body = sections
+        .into_iter()
+        .map(|(heading, lines)| format!("{}\n{}", heading, lines.join("\n")))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    if items.len() > shown.len() {
+        body.push_str(&format!("\n\n…and {} more", items.len() - shown.len()));
+    }
+
+    (title, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn item(notification_type: &str, title: &str, body: &str) -> DigestItem {
+        DigestItem {
+            id: uuid::Uuid::nil(),
+            notification_type: notification_type.to_string(),
+            title: title.to_string(),
+            body: body.to_string(),
+        }
+    }
+
+    fn prefs(mode: &str, timezone: &str) -> DigestPreferences {
+        DigestPreferences {
+            digest_mode: mode.to_string(),
+            timezone: timezone.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_is_digest_priority() {
+        assert!(is_digest_priority(1));
+        assert!(!is_digest_priority(2));
+        assert!(!is_digest_priority(5));
+    }
+
+    #[test]
+    fn test_due_slot() {
+        // 11:00 UTC is 07:00 in New York (EDT) and 18:00 UTC is 14:00
+        let morning = Utc.with_ymd_and_hms(2026, 10, 16, 11, 30, 0).unwrap();
+        let afternoon = Utc.with_ymd_and_hms(2026, 10, 16, 18, 0, 0).unwrap();
+        let evening = Utc.with_ymd_and_hms(2026, 10, 16, 22, 5, 0).unwrap();
+
+        let ny = prefs("morning", "America/New_York");
+        assert_eq!(due_slot(&ny, morning), Some(DigestSlot::Morning));
+        assert_eq!(due_slot(&ny, afternoon), None);
+        assert_eq!(due_slot(&ny, evening), None);
+
+        let both = prefs("both", "America/New_York");
+        assert_eq!(due_slot(&both, evening), Some(DigestSlot::Evening));
+
+        let utc = prefs("evening", "Not/AZone");
+        assert_eq!(due_slot(&utc, afternoon), Some(DigestSlot::Evening));
+
+        assert_eq!(
+            due_slot(&prefs("off", "UTC"), afternoon),
+            Some(DigestSlot::Flush)
+        );
+    }
+
+    #[test]
+    fn test_digest_channel() {
+        assert_eq!(digest_channel(&DigestPreferences::default()), "email");
+        let discord = DigestPreferences {
+            discord_enabled: true,
+            ..Default::default()
+        };
+        assert_eq!(digest_channel(&discord), "discord");
+    }
+
+    #[test]
+    fn test_compose_digest() {
+        let items = vec![
+            item("reminder", "Water plants", "Water plants"),
+            item(
+                "system",
+                "Couldn't import a dropped file",
+                "scan.pdf: unsupported",
+            ),
+            item("reminder", "Library books", "Due Friday"),
+        ];
+
+        let (title, body) = compose_digest(DigestSlot::Morning, &items);
+        assert_eq!(title, "Your morning digest (3 updates)");
+        assert_eq!(
+            body,
+            "Reminders\n• Water plants\n• Library books: Due Friday\n\n\
+             Updates\n• Couldn't import a dropped file: scan.pdf: unsupported"
+        );
+    }
+
+    #[test]
+    fn test_compose_digest_caps_items() {
+        let items: Vec<DigestItem> = (0..MAX_DIGEST_ITEMS + 3)
+            .map(|i| item("reminder", &format!("R{}", i), ""))
+            .collect();
+
+        let (_, body) = compose_digest(DigestSlot::Evening, &items);
+        assert_eq!(body.matches('•').count(), MAX_DIGEST_ITEMS);
+        assert!(body.ends_with("…and 3 more"));
+    }
+}
@@ -0,0 +1,164 @@
+//! Linking Discord accounts to registered users.
+//!
+//! A signed-in user requests a short code from the API and runs `/link <code>`
+//! in Discord; redeeming the code sets `users.discord_id`. The Discord bot only
+//! talks to the agent on behalf of linked accounts.
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::codes::random_code;
+use crate::Result;
+
+/// How long a link code can be redeemed.
+pub const LINK_CODE_TTL_MINUTES: i32 = 10;
+
+/// Characters in a link code (no 0/O or 1/I to avoid misreads).
+const CODE_ALPHABET: &[u8; 32] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// Characters in a link code, excluding the separator.
+const CODE_LEN: usize = 8;
+
+/// Generate a code in its display form (`ABCD-EFGH`).
+pub fn generate_link_code() -> String {
+    let code = random_code(CODE_LEN, CODE_ALPHABET);
+    format!("{}-{}", &code[..CODE_LEN / 2], &code[CODE_LEN / 2..])
+}
+
+/// Canonical form of a user-entered code, or `None` if it can't be a valid code.
+///
+/// Case, spaces and dashes are ignored.
+pub fn normalize_link_code(input: &str) -> Option<String> {
+    let code: String = input
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    let valid = code.len() == CODE_LEN && code.bytes().all(|b| CODE_ALPHABET.contains(&b));
+    valid.then_some(code)
+}
+
+/// Issue a new link code for a user, replacing any unredeemed ones.
+///
+/// Returns the code in display form and when it expires.
+pub async fn create_link_code(pool: &PgPool, user_id: Uuid) -> Result<(String, DateTime<Utc>)> {
+    let code = generate_link_code();
+    let canonical = normalize_link_code(&code).unwrap_or_default();
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM discord_link_codes WHERE user_id = $1 AND consumed_at IS NULL")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let expires_at = sqlx::query_scalar(
+        r#"
+        INSERT INTO discord_link_codes (user_id, code_hash, expires_at)
+        VALUES ($1, sha256(convert_to($2, 'UTF8')), NOW() + make_interval(mins => $3))
+        RETURNING expires_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(&canonical)
+    .bind(LINK_CODE_TTL_MINUTES)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((code, expires_at))
+}
+
+/// Redeem a code for a Discord account, returning the linked user.
+///
+/// Returns `None` for unknown, expired or already used codes. A Discord
+/// account linked to another user is moved to the code's user.
+pub async fn redeem_link_code(pool: &PgPool, discord_id: &str, code: &str) -> Result<Option<Uuid>> {
+    let code = match normalize_link_code(code) {
+        Some(code) => code,
+        None => return Ok(None),
+    };
+
+    let mut tx = pool.begin().await?;
+
+    let user_id: Option<Uuid> = sqlx::query_scalar(
+        r#"
+        UPDATE discord_link_codes
+        SET consumed_at = NOW(), discord_id = $2
+        WHERE code_hash = sha256(convert_to($1, 'UTF8'))
+        AND consumed_at IS NULL
+        AND expires_at > NOW()
+        RETURNING user_id
+        "#,
+    )
+    .bind(&code)
+    .bind(discord_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let user_id = match user_id {
+        Some(user_id) => user_id,
+        None => return Ok(None),
+    };
+
+    // discord_id is unique, so release it from any previous user first
+    sqlx::query(
+        "UPDATE users SET discord_id = NULL, discord_linked_at = NULL WHERE discord_id = $1 AND id <> $2",
+    )
+    .bind(discord_id)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("UPDATE users SET discord_id = $1, discord_linked_at = NOW() WHERE id = $2")
+        .bind(discord_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(Some(user_id))
+}
+
+/// Remove a user's Discord link. Returns whether one existed.
+pub async fn unlink(pool: &PgPool, user_id: Uuid) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE users
+        SET discord_id = NULL, discord_linked_at = NULL
+        WHERE id = $1 AND discord_id IS NOT NULL
+        "#,
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_link_code() {
+        let code = generate_link_code();
+        assert_eq!(code.len(), CODE_LEN + 1);
+        assert_eq!(code.chars().nth(CODE_LEN / 2), Some('-'));
+        assert!(normalize_link_code(&code).is_some());
+    }
+
+    #[test]
+    fn test_normalize_link_code() {
+        assert_eq!(normalize_link_code("abcd-efgh").as_deref(), Some("ABCDEFGH"));
+        assert_eq!(normalize_link_code(" ABCD EFGH ").as_deref(), Some("ABCDEFGH"));
+        assert_eq!(normalize_link_code("ABCD-EFG"), None);
+        // 0, O, 1 and I are never issued
+        assert_eq!(normalize_link_code("ABCD-EFG0"), None);
+        assert_eq!(normalize_link_code("ABCD-EFGI"), None);
+    }
+}
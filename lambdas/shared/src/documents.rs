@@ -0,0 +1,205 @@
+//! Chunking of documents extracted with Textract.
+//!
+//! The `document_ingest` Lambda turns each uploaded PDF or scan into pages
+//! of text, then hands it to the ingestion agent a chunk at a time so long
+//! documents stay within what one extraction pass handles well. Chunks break
+//! between lines, never mid-line unless a single line is longer than a
+//! chunk, and remember which pages they came from.
+
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Largest chunk handed to the ingestion agent
+pub const MAX_CHUNK_CHARS: usize = 4000;
+
+/// Most chunks ingested from one document
+pub const MAX_CHUNKS: usize = 25;
+
+/// A run of document text and the pages it spans (1-based)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub text: String,
+    pub first_page: i32,
+    pub last_page: i32,
+}
+
+/// Split page texts into chunks of at most `max_chars` characters. Pages
+/// are separated by a blank line; empty pages are skipped.
+pub fn chunk_pages(pages: &[String], max_chars: usize) -> Vec<Chunk> {
+    let max_chars = max_chars.max(1);
+    let mut chunks = Vec::new();
+    let mut current: Option<Chunk> = None;
+    let mut current_chars = 0;
+
+    for (index, page) in pages.iter().enumerate() {
+        let page_number = index as i32 + 1;
+        let mut first_line = true;
+
+        for line in page.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            for piece in split_long(line, max_chars) {
+                let separator = match &current {
+                    None => "",
+                    Some(_) if first_line => "\n\n",
+                    Some(_) => "\n",
+                };
+                let piece_chars = piece.chars().count();
+
+                if current.is_some() && current_chars + separator.len() + piece_chars > max_chars {
+                    chunks.extend(current.take());
+                    current_chars = 0;
+                }
+
+                match &mut current {
+                    Some(chunk) => {
+                        chunk.text.push_str(separator);
+                        chunk.text.push_str(piece);
+                        chunk.last_page = page_number;
+                        current_chars += separator.len() + piece_chars;
+                    }
+                    None => {
+                        current = Some(Chunk {
+                            text: piece.to_string(),
+                            first_page: page_number,
+                            last_page: page_number,
+                        });
+                        current_chars = piece_chars;
+                    }
+                }
+                first_line = false;
+            }
+        }
+    }
+
+    chunks.extend(current);
+    chunks
+}
+
+/// Pieces of at most `max_chars` characters, split on character boundaries.
+fn split_long(line: &str, max_chars: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut rest = line;
+
+    while rest.chars().count() > max_chars {
+        let split_at = rest
+            .char_indices()
+            .nth(max_chars)
+            .map(|(i, _)| i)
+            .unwrap_or(rest.len());
+        pieces.push(&rest[..split_at]);
+        rest = &rest[split_at..];
+    }
+    pieces.push(rest);
+
+    pieces
+}
+
+/// Source metadata sent with each ingested chunk, pointing back at the
+/// original object
+#[derive(Debug, Clone, Serialize)]
+pub struct ChunkSource {
+    pub document_id: Uuid,
+    pub s3_uri: String,
+    pub file_name: String,
+    /// 1-based position of this chunk in the document
+    pub chunk: usize,
+    pub chunks: usize,
+    pub first_page: i32,
+    pub last_page: i32,
+}
+
+impl ChunkSource {
+    pub fn new(
+        document_id: Uuid,
+        bucket: &str,
+        key: &str,
+        index: usize,
+        chunks: usize,
+        chunk: &Chunk,
+    ) -> Self {
+        Self {
+            document_id,
+            s3_uri: format!("s3://{}/{}", bucket, key),
+            file_name: key.rsplit('/').next().unwrap_or(key).to_string(),
+            chunk: index + 1,
+            chunks,
+            first_page: chunk.first_page,
+            last_page: chunk.last_page,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pages(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn short_document_is_one_chunk() {
+        let chunks = chunk_pages(
+            &pages(&["Electric bill\nDue Nov 3", "", "Amount: $84.20"]),
+            100,
+        );
+
+        assert_eq!(
+            chunks,
+            vec![Chunk {
+                text: "Electric bill\nDue Nov 3\n\nAmount: $84.20".to_string(),
+                first_page: 1,
+                last_page: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn chunks_break_between_lines() {
+        let chunks = chunk_pages(&pages(&["aaaa\nbbbb\ncccc", "dddd"]), 10);
+
+        let texts: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, vec!["aaaa\nbbbb", "cccc\n\ndddd"]);
+        assert_eq!((chunks[0].first_page, chunks[0].last_page), (1, 1));
+        assert_eq!((chunks[1].first_page, chunks[1].last_page), (1, 2));
+        assert!(chunks.iter().all(|c| c.text.chars().count() <= 10));
+    }
+
+    #[test]
+    fn long_lines_are_split() {
+        let chunks = chunk_pages(&pages(&["ééééééé"]), 3);
+
+        let texts: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(texts, vec!["ééé", "ééé", "é"]);
+    }
+
+    #[test]
+    fn empty_document_has_no_chunks() {
+        assert!(chunk_pages(&pages(&["", "  \n "]), 100).is_empty());
+        assert!(chunk_pages(&[], 100).is_empty());
+    }
+
+    #[test]
+    fn source_points_at_object() {
+        let chunk = Chunk {
+            text: "x".to_string(),
+            first_page: 2,
+            last_page: 3,
+        };
+        let source = ChunkSource::new(
+            Uuid::nil(),
+            "drop-bucket",
+            "documents/sub/mail/letter.pdf",
+            1,
+            4,
+            &chunk,
+        );
+
+        assert_eq!(
+            source.s3_uri,
+            "s3://drop-bucket/documents/sub/mail/letter.pdf"
+        );
+        assert_eq!(source.file_name, "letter.pdf");
+        assert_eq!((source.chunk, source.chunks), (2, 4));
+        assert_eq!((source.first_page, source.last_page), (2, 3));
+    }
+}
@@ -0,0 +1,110 @@
+//! Text embedding generation using Amazon Titan via Bedrock.
+
+use aws_sdk_bedrockruntime::primitives::Blob;
+use aws_sdk_bedrockruntime::Client as BedrockClient;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+/// Default embedding model (matches the `fact_embeddings.model_id` column default).
+pub const DEFAULT_EMBEDDING_MODEL: &str = "amazon.titan-embed-text-v2:0";
+
+/// Embedding dimensions (matches the `VECTOR(1024)` column type).
+pub const EMBEDDING_DIMENSIONS: usize = 1024;
+
+/// Titan embeddings request body.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TitanEmbeddingRequest<'a> {
+    input_text: &'a str,
+    dimensions: usize,
+    normalize: bool,
+}
+
+/// Titan embeddings response body.
+#[derive(Debug, Deserialize)]
+struct TitanEmbeddingResponse {
+    embedding: Vec<f32>,
+}
+
+/// Client for generating text embeddings.
+pub struct EmbeddingClient {
+    client: BedrockClient,
+    model_id: String,
+}
+
+impl EmbeddingClient {
+    /// Create a new embedding client using the default Titan model.
+    pub fn new(client: BedrockClient) -> Self {
+        Self {
+            client,
+            model_id: DEFAULT_EMBEDDING_MODEL.to_string(),
+        }
+    }
+
+    /// Create with a specific model.
+    pub fn with_model(client: BedrockClient, model_id: impl Into<String>) -> Self {
+        Self {
+            client,
+            model_id: model_id.into(),
+        }
+    }
+
+    /// Model ID used for generated embeddings.
+    pub fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    /// Generate a normalized embedding for the given text.
+    pub async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        if text.trim().is_empty() {
+            return Err(Error::Validation("Cannot embed empty text".to_string()));
+        }
+
+        let body = serde_json::to_vec(&TitanEmbeddingRequest {
+            input_text: text,
+            dimensions: EMBEDDING_DIMENSIONS,
+            normalize: true,
+        })?;
+
+        let response = self
+            .client
+            .invoke_model()
+            .model_id(&self.model_id)
+            .content_type("application/json")
+            .accept("application/json")
+            .body(Blob::new(body))
+            .send()
+            .await
+            .map_err(|e| Error::Aws(format!("Failed to invoke embedding model: {}", e)))?;
+
+        let parsed: TitanEmbeddingResponse = serde_json::from_slice(response.body().as_ref())
+            .map_err(|e| Error::Aws(format!("Failed to parse embedding response: {}", e)))?;
+
+        if parsed.embedding.len() != EMBEDDING_DIMENSIONS {
+            return Err(Error::Aws(format!(
+                "Unexpected embedding dimensions: {}",
+                parsed.embedding.len()
+            )));
+        }
+
+        Ok(parsed.embedding)
+    }
+}
+
+/// Format an embedding as a pgvector literal (e.g. `[0.1,0.2,0.3]`) for binding as `$n::vector`.
+pub fn to_pgvector(embedding: &[f32]) -> String {
+    let values: Vec<String> = embedding.iter().map(|v| v.to_string()).collect();
+    format!("[{}]", values.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_pgvector() {
+        assert_eq!(to_pgvector(&[0.5, -1.0, 0.25]), "[0.5,-1,0.25]");
+        assert_eq!(to_pgvector(&[]), "[]");
+    }
+}
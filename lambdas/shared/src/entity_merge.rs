@@ -0,0 +1,368 @@
+//! Merging duplicate entities.
+//!
+//! Extraction creates a new entity whenever a name doesn't resolve, so the
+//! same person can end up as "Bob" and "Robert Smith". Merging moves
+//! everything that points at the source entity onto the target in one
+//! transaction, records an `entity_merges` audit row and deletes the source.
+
+use serde::Serialize;
+use sqlx::{PgPool, Postgres, Transaction};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{Error, Result};
+
+/// What a merge moved onto the target entity.
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct MergeSummary {
+    pub merge_id: Uuid,
+    pub target_entity_id: Uuid,
+    pub source_entity_id: Uuid,
+    pub facts_reparented: i64,
+    pub mentions_moved: i64,
+    pub attributes_moved: i64,
+    pub locations_moved: i64,
+    pub relationships_moved: i64,
+    pub aliases: Vec<String>,
+}
+
+/// Entity fields read before a merge.
+#[derive(Debug, sqlx::FromRow)]
+struct MergeEntity {
+    name: String,
+    entity_type: String,
+    description: Option<String>,
+    aliases: Vec<String>,
+    metadata: serde_json::Value,
+}
+
+/// Target aliases after absorbing the source's.
+///
+/// The source name is added when `keep_alias` is set. Names are compared
+/// case-insensitively, the target's own name is never an alias and the
+/// target's aliases keep their order ahead of the source's.
+pub fn merged_aliases(
+    target_name: &str,
+    target_aliases: &[String],
+    source_name: &str,
+    source_aliases: &[String],
+    keep_alias: bool,
+) -> Vec<String> {
+    let source_name = keep_alias.then_some(source_name);
+    let mut seen = vec![target_name.trim().to_lowercase()];
+    let mut aliases = Vec::new();
+
+    for alias in target_aliases
+        .iter()
+        .map(String::as_str)
+        .chain(source_name)
+        .chain(source_aliases.iter().map(String::as_str))
+    {
+        let alias = alias.trim();
+        let key = alias.to_lowercase();
+        if alias.is_empty() || seen.contains(&key) {
+            continue;
+        }
+        seen.push(key);
+        aliases.push(alias.to_string());
+    }
+
+    aliases
+}
+
+/// Merge `source_id` into `target_id`.
+///
+/// Callers check that `merged_by` can see both entities. Duplicates that
+/// would violate the target's unique indexes are resolved in the target's
+/// favour: matching current attributes are superseded by the target's,
+/// clashing current locations are closed, and relationships that would
+/// duplicate one of the target's (or point the target at itself) are dropped.
+pub async fn merge_entities(
+    pool: &PgPool,
+    target_id: Uuid,
+    source_id: Uuid,
+    keep_alias: bool,
+    merged_by: Uuid,
+) -> Result<MergeSummary> {
+    if target_id == source_id {
+        return Err(Error::Validation(
+            "Cannot merge an entity into itself".to_string(),
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let target = lock_entity(&mut tx, target_id).await?;
+    let source = lock_entity(&mut tx, source_id).await?;
+
+    let mut summary = MergeSummary {
+        merge_id: Uuid::new_v4(),
+        target_entity_id: target_id,
+        source_entity_id: source_id,
+        aliases: merged_aliases(
+            &target.name,
+            &target.aliases,
+            &source.name,
+            &source.aliases,
+            keep_alias,
+        ),
+        ..Default::default()
+    };
+
+    summary.facts_reparented =
+        sqlx::query("UPDATE facts SET about_entity_id = $1 WHERE about_entity_id = $2")
+            .bind(target_id)
+            .bind(source_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected() as i64;
+
+    summary.mentions_moved = sqlx::query(
+        r#"
+        UPDATE entity_mentions sm SET entity_id = $1
+        WHERE sm.entity_id = $2
+        AND NOT EXISTS (
+            SELECT 1 FROM entity_mentions tm
+            WHERE tm.fact_id = sm.fact_id AND tm.role = sm.role AND tm.entity_id = $1
+        )
+        "#,
+    )
+    .bind(target_id)
+    .bind(source_id)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected() as i64;
+
+    // Current source attributes the target already has become history
+    sqlx::query(
+        r#"
+        UPDATE entity_attributes sa
+        SET superseded_by = ta.id, valid_to = COALESCE(sa.valid_to, CURRENT_DATE)
+        FROM entity_attributes ta
+        WHERE sa.entity_id = $2
+        AND ta.entity_id = $1
+        AND sa.superseded_by IS NULL
+        AND ta.superseded_by IS NULL
+        AND ta.valid_to IS NULL
+        AND LOWER(sa.attribute_name) = LOWER(ta.attribute_name)
+        AND sa.attribute_value = ta.attribute_value
+        "#,
+    )
+    .bind(target_id)
+    .bind(source_id)
+    .execute(&mut *tx)
+    .await?;
+
+    summary.attributes_moved =
+        sqlx::query("UPDATE entity_attributes SET entity_id = $1 WHERE entity_id = $2")
+            .bind(target_id)
+            .bind(source_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected() as i64;
+
+    // Only one current location per label, and per place
+    sqlx::query(
+        r#"
+        UPDATE entity_locations sl SET valid_to = CURRENT_DATE
+        WHERE sl.entity_id = $2
+        AND sl.valid_to IS NULL
+        AND EXISTS (
+            SELECT 1 FROM entity_locations tl
+            WHERE tl.entity_id = $1
+            AND (tl.label = sl.label OR tl.place_id = sl.place_id)
+            AND tl.valid_to IS NULL
+        )
+        "#,
+    )
+    .bind(target_id)
+    .bind(source_id)
+    .execute(&mut *tx)
+    .await?;
+
+    summary.locations_moved =
+        sqlx::query("UPDATE entity_locations SET entity_id = $1 WHERE entity_id = $2")
+            .bind(target_id)
+            .bind(source_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected() as i64;
+
+    sqlx::query(
+        r#"
+        DELETE FROM entity_relationships sr
+        WHERE (sr.source_entity_id = $2 AND sr.target_entity_id = $1)
+           OR (sr.source_entity_id = $1 AND sr.target_entity_id = $2)
+           OR (sr.valid_to IS NULL AND EXISTS (
+                SELECT 1 FROM entity_relationships tr
+                WHERE tr.valid_to IS NULL
+                AND tr.relationship_type = sr.relationship_type
+                AND (
+                    (sr.source_entity_id = $2 AND tr.source_entity_id = $1
+                     AND tr.target_entity_id = sr.target_entity_id)
+                    OR (sr.target_entity_id = $2 AND tr.target_entity_id = $1
+                        AND tr.source_entity_id = sr.source_entity_id)
+                )
+           ))
+        "#,
+    )
+    .bind(target_id)
+    .bind(source_id)
+    .execute(&mut *tx)
+    .await?;
+
+    summary.relationships_moved = sqlx::query(
+        r#"
+        UPDATE entity_relationships
+        SET source_entity_id = CASE WHEN source_entity_id = $2 THEN $1 ELSE source_entity_id END,
+            target_entity_id = CASE WHEN target_entity_id = $2 THEN $1 ELSE target_entity_id END,
+            updated_at = NOW()
+        WHERE source_entity_id = $2 OR target_entity_id = $2
+        "#,
+    )
+    .bind(target_id)
+    .bind(source_id)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected() as i64;
+
+    sqlx::query(
+        r#"
+        DELETE FROM calendar_event_attendees sa
+        WHERE sa.entity_id = $2
+        AND EXISTS (
+            SELECT 1 FROM calendar_event_attendees ta
+            WHERE ta.event_id = sa.event_id
+            AND ta.entity_id = $1
+            AND COALESCE(ta.email, '') = COALESCE(sa.email, '')
+        )
+        "#,
+    )
+    .bind(target_id)
+    .bind(source_id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("UPDATE calendar_event_attendees SET entity_id = $1 WHERE entity_id = $2")
+        .bind(target_id)
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query("UPDATE reminders SET related_entity_id = $1 WHERE related_entity_id = $2")
+        .bind(target_id)
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await?;
+
+    // Keep synced contacts pointing at the surviving entity
+    sqlx::query("UPDATE contact_links SET entity_id = $1 WHERE entity_id = $2")
+        .bind(target_id)
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE entities
+        SET aliases = $2, description = COALESCE(description, $3), updated_at = NOW()
+        WHERE id = $1
+        "#,
+    )
+    .bind(target_id)
+    .bind(&summary.aliases)
+    .bind(&source.description)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO entity_merges (id, target_entity_id, source_entity_id, source_name,
+                                   source_entity_type, source_snapshot, facts_reparented,
+                                   attributes_moved, locations_moved, relationships_moved,
+                                   kept_alias, merged_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        "#,
+    )
+    .bind(summary.merge_id)
+    .bind(target_id)
+    .bind(source_id)
+    .bind(&source.name)
+    .bind(&source.entity_type)
+    .bind(serde_json::json!({
+        "description": source.description,
+        "aliases": source.aliases,
+        "metadata": source.metadata,
+    }))
+    .bind(summary.facts_reparented as i32)
+    .bind(summary.attributes_moved as i32)
+    .bind(summary.locations_moved as i32)
+    .bind(summary.relationships_moved as i32)
+    .bind(keep_alias)
+    .bind(merged_by)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("DELETE FROM entities WHERE id = $1")
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(summary)
+}
+
+/// Lock an entity row for the rest of the merge.
+async fn lock_entity(tx: &mut Transaction<'_, Postgres>, entity_id: Uuid) -> Result<MergeEntity> {
+    sqlx::query_as::<_, MergeEntity>(
+        r#"
+        SELECT name, entity_type::text AS entity_type, description, aliases, metadata
+        FROM entities WHERE id = $1
+        FOR UPDATE
+        "#,
+    )
+    .bind(entity_id)
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or_else(|| Error::NotFound(format!("Entity {} not found", entity_id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn keeps_source_name_as_alias() {
+        let aliases = merged_aliases("Robert Smith", &strings(&["Rob"]), "Bob", &[], true);
+        assert_eq!(aliases, strings(&["Rob", "Bob"]));
+    }
+
+    #[test]
+    fn drops_source_name_without_keep_alias() {
+        let aliases = merged_aliases("Robert Smith", &[], "Bob", &strings(&["Bobby"]), false);
+        assert_eq!(aliases, strings(&["Bobby"]));
+    }
+
+    #[test]
+    fn dedupes_case_insensitively() {
+        let aliases = merged_aliases(
+            "Robert Smith",
+            &strings(&["Bob"]),
+            "bob",
+            &strings(&["ROBERT SMITH", " Bobby ", "bobby", ""]),
+            true,
+        );
+        assert_eq!(aliases, strings(&["Bob", "Bobby"]));
+    }
+
+    #[test]
+    fn never_aliases_target_name() {
+        let aliases = merged_aliases("Bob", &[], "BOB", &[], true);
+        assert!(aliases.is_empty());
+    }
+}
@@ -0,0 +1,87 @@
+//! Entity photos stored in S3.
+//!
+//! Clients ask `POST /entities/{id}/photo` for a presigned PUT URL and upload
+//! the image straight to the photos bucket; the object key is kept on the
+//! entity (`entities.photo_key`) and entity details carry a short-lived
+//! presigned GET URL for it.
+
+use uuid::Uuid;
+
+/// Largest photo accepted
+pub const MAX_PHOTO_BYTES: i64 = 10 * 1024 * 1024;
+
+/// How long a presigned upload URL is valid
+pub const UPLOAD_URL_TTL_SECS: u64 = 15 * 60;
+
+/// How long a presigned download URL is valid
+pub const DOWNLOAD_URL_TTL_SECS: u64 = 60 * 60;
+
+/// Accepted image types and the extension their objects get
+const PHOTO_TYPES: &[(&str, &str)] = &[
+    ("image/jpeg", "jpg"),
+    ("image/png", "png"),
+    ("image/webp", "webp"),
+    ("image/heic", "heic"),
+];
+
+/// Normalized content type and object extension, or `None` if the type isn't accepted.
+pub fn photo_type(content_type: &str) -> Option<(&'static str, &'static str)> {
+    let content_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let content_type = if content_type == "image/jpg" {
+        "image/jpeg".to_string()
+    } else {
+        content_type
+    };
+
+    PHOTO_TYPES
+        .iter()
+        .find(|(accepted, _)| *accepted == content_type)
+        .copied()
+}
+
+/// Accepted content types, for error messages.
+pub fn accepted_types() -> String {
+    PHOTO_TYPES
+        .iter()
+        .map(|(content_type, _)| *content_type)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Object key for a new upload. Each upload gets its own key so cached
+/// URLs for the previous photo never show the new one.
+pub fn photo_key(entity_id: Uuid, upload_id: Uuid, extension: &str) -> String {
+    format!("entity-photos/{}/{}.{}", entity_id, upload_id, extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_content_types() {
+        assert_eq!(photo_type("image/jpeg"), Some(("image/jpeg", "jpg")));
+        assert_eq!(photo_type("Image/JPG"), Some(("image/jpeg", "jpg")));
+        assert_eq!(
+            photo_type("image/png; charset=binary"),
+            Some(("image/png", "png"))
+        );
+        assert_eq!(photo_type("image/gif"), None);
+        assert_eq!(photo_type("application/pdf"), None);
+    }
+
+    #[test]
+    fn keys_are_scoped_to_entity() {
+        let entity_id = Uuid::from_u128(1);
+        let upload_id = Uuid::from_u128(2);
+        assert_eq!(
+            photo_key(entity_id, upload_id, "png"),
+            format!("entity-photos/{}/{}.png", entity_id, upload_id)
+        );
+    }
+}
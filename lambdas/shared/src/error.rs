@@ -1,6 +1,23 @@
 //! Error types for Second Brain Lambda functions.
+//!
+//! [`Error`] is what shared code returns; [`ApiError`] is what an API client
+//! sees. Every failed API request answers with an RFC 9457
+//! `application/problem+json` body carrying a machine-readable [`ErrorCode`]:
+//!
+//! ```json
+//! {"type": "about:blank", "title": "Not Found", "status": 404,
+//!  "code": "NOT_FOUND", "detail": "Reminder not found"}
+//! ```
 
+use lambda_http::http::header::RETRY_AFTER;
+use lambda_http::{Body, Response};
+use serde::Serialize;
 use thiserror::Error;
+use tracing::error;
+use utoipa::ToSchema;
+
+/// Content type of error responses.
+pub const PROBLEM_JSON: &str = "application/problem+json";
 
 /// Result type alias using our Error type.
 pub type Result<T> = std::result::Result<T, Error>;
@@ -36,6 +53,14 @@ pub enum Error {
     #[error("Not found: {0}")]
     NotFound(String),
 
+    /// Conflict with the current state, e.g. a duplicate
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    /// Too many requests; retry after this many seconds
+    #[error("Rate limited: retry after {0}s")]
+    RateLimited(u64),
+
     /// Serialization error
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
@@ -53,7 +78,274 @@ impl Error {
             Error::Auth(_) => 401,
             Error::Unauthorized(_) => 403,
             Error::NotFound(_) => 404,
+            Error::Conflict(_) => 409,
+            Error::RateLimited(_) => 429,
             _ => 500,
         }
     }
 }
+
+/// Machine-readable error codes, stable across releases for clients to branch on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// Malformed or invalid input (400)
+    Validation,
+    /// Missing or invalid credentials (401)
+    Unauthorized,
+    /// Authenticated, but not allowed to touch the resource (403)
+    Forbidden,
+    /// No such resource, or not one the caller can see (404)
+    NotFound,
+    /// Method not supported on the path (405)
+    MethodNotAllowed,
+    /// Duplicate or otherwise conflicting with current state (409)
+    Conflict,
+    /// `If-Match` no longer holds (412)
+    PreconditionFailed,
+    /// Too many requests; see `Retry-After` (429)
+    RateLimited,
+    /// Something went wrong on our side (500)
+    Internal,
+    /// A dependency (Bedrock, a provider API) failed or is down (502/503)
+    Unavailable,
+}
+
+impl ErrorCode {
+    /// The code for an HTTP error status.
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            401 => Self::Unauthorized,
+            403 => Self::Forbidden,
+            404 | 410 => Self::NotFound,
+            405 => Self::MethodNotAllowed,
+            409 => Self::Conflict,
+            412 => Self::PreconditionFailed,
+            429 => Self::RateLimited,
+            502..=504 => Self::Unavailable,
+            400..=499 => Self::Validation,
+            _ => Self::Internal,
+        }
+    }
+
+    /// Default HTTP status for the code.
+    pub fn status(self) -> u16 {
+        match self {
+            Self::Validation => 400,
+            Self::Unauthorized => 401,
+            Self::Forbidden => 403,
+            Self::NotFound => 404,
+            Self::MethodNotAllowed => 405,
+            Self::Conflict => 409,
+            Self::PreconditionFailed => 412,
+            Self::RateLimited => 429,
+            Self::Internal => 500,
+            Self::Unavailable => 503,
+        }
+    }
+}
+
+/// An API error as sent to clients: an RFC 9457 problem details object.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ApiError {
+    /// Always `about:blank`; `code` identifies the problem
+    #[serde(rename = "type")]
+    pub problem_type: &'static str,
+    /// Reason phrase of `status`
+    pub title: &'static str,
+    /// HTTP status code
+    pub status: u16,
+    /// Machine-readable error code
+    pub code: ErrorCode,
+    /// Human-readable explanation of this occurrence
+    pub detail: String,
+    /// Seconds to wait, sent as `Retry-After`
+    #[serde(skip)]
+    pub retry_after: Option<u64>,
+}
+
+impl ApiError {
+    /// An error with the code's default status.
+    pub fn new(code: ErrorCode, detail: impl Into<String>) -> Self {
+        Self::with_status(code.status(), code, detail)
+    }
+
+    /// An error for an HTTP status, with the code inferred from it.
+    pub fn from_status(status: u16, detail: impl Into<String>) -> Self {
+        Self::with_status(status, ErrorCode::from_status(status), detail)
+    }
+
+    fn with_status(status: u16, code: ErrorCode, detail: impl Into<String>) -> Self {
+        Self {
+            problem_type: "about:blank",
+            title: reason_phrase(status),
+            status,
+            code,
+            detail: detail.into(),
+            retry_after: None,
+        }
+    }
+
+    pub fn validation(detail: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Validation, detail)
+    }
+
+    pub fn not_found(detail: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NotFound, detail)
+    }
+
+    pub fn forbidden(detail: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Forbidden, detail)
+    }
+
+    pub fn conflict(detail: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Conflict, detail)
+    }
+
+    /// A `429` telling the client how long to wait.
+    pub fn rate_limited(retry_after: u64) -> Self {
+        Self {
+            retry_after: Some(retry_after),
+            ..Self::new(ErrorCode::RateLimited, "Too many requests, slow down")
+        }
+    }
+
+    /// A generic 500; the cause is logged, not sent to the client.
+    pub fn internal(cause: impl std::fmt::Display) -> Self {
+        error!("Internal error: {}", cause);
+        Self::new(ErrorCode::Internal, "Internal server error")
+    }
+
+    /// The `application/problem+json` response for this error.
+    pub fn into_response(self) -> std::result::Result<Response<Body>, lambda_http::Error> {
+        let mut builder = Response::builder()
+            .status(self.status)
+            .header("content-type", PROBLEM_JSON);
+        if let Some(retry_after) = self.retry_after {
+            builder = builder.header(RETRY_AFTER, retry_after);
+        }
+        Ok(builder.body(Body::from(serde_json::to_string(&self)?))?)
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: {}", self.code, self.detail)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<Error> for ApiError {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Database(e) => e.into(),
+            Error::Validation(msg) => Self::validation(msg),
+            Error::Auth(msg) => Self::new(ErrorCode::Unauthorized, msg),
+            Error::Unauthorized(msg) => Self::forbidden(msg),
+            Error::NotFound(msg) => Self::not_found(msg),
+            Error::Conflict(msg) => Self::conflict(msg),
+            Error::RateLimited(wait) => Self::rate_limited(wait),
+            Error::Aws(msg) => {
+                error!("AWS error: {}", msg);
+                Self::new(ErrorCode::Unavailable, "A backing service is unavailable")
+            }
+            e @ (Error::Config(_) | Error::Serialization(_) | Error::Internal(_)) => Self::internal(e),
+        }
+    }
+}
+
+/// Postgres `unique_violation`.
+const UNIQUE_VIOLATION: &str = "23505";
+/// Postgres `foreign_key_violation`.
+const FOREIGN_KEY_VIOLATION: &str = "23503";
+
+impl From<sqlx::Error> for ApiError {
+    fn from(error: sqlx::Error) -> Self {
+        match &error {
+            sqlx::Error::RowNotFound => Self::not_found("Not found"),
+            sqlx::Error::Database(db) => match db.code().as_deref() {
+                Some(UNIQUE_VIOLATION) => Self::conflict("Already exists"),
+                Some(FOREIGN_KEY_VIOLATION) => Self::validation("Refers to a record that doesn't exist"),
+                _ => Self::internal(error),
+            },
+            _ => Self::internal(error),
+        }
+    }
+}
+
+/// Deserialization failures are the client's bad input.
+impl From<serde_json::Error> for ApiError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::validation(format!("Invalid request body: {}", error))
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    lambda_http::http::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|s| s.canonical_reason())
+        .unwrap_or("Error")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn problem_json_shape() {
+        let body = serde_json::to_value(ApiError::not_found("Reminder not found")).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "type": "about:blank",
+                "title": "Not Found",
+                "status": 404,
+                "code": "NOT_FOUND",
+                "detail": "Reminder not found",
+            })
+        );
+    }
+
+    #[test]
+    fn status_maps_to_code() {
+        assert_eq!(ErrorCode::from_status(400), ErrorCode::Validation);
+        assert_eq!(ErrorCode::from_status(403), ErrorCode::Forbidden);
+        assert_eq!(ErrorCode::from_status(409), ErrorCode::Conflict);
+        assert_eq!(ErrorCode::from_status(422), ErrorCode::Validation);
+        assert_eq!(ErrorCode::from_status(502), ErrorCode::Unavailable);
+
+        let error = ApiError::from_status(502, "Failed to generate briefing");
+        assert_eq!((error.status, error.title), (502, "Bad Gateway"));
+    }
+
+    #[test]
+    fn converts_shared_errors() {
+        let error = ApiError::from(Error::Unauthorized("Not a member".to_string()));
+        assert_eq!((error.code, error.status), (ErrorCode::Forbidden, 403));
+
+        let error = ApiError::from(Error::Database(sqlx::Error::RowNotFound));
+        assert_eq!(error.code, ErrorCode::NotFound);
+
+        // Internals aren't leaked to the client
+        let error = ApiError::from(Error::Config("DB_HOST not set".to_string()));
+        assert_eq!(error.code, ErrorCode::Internal);
+        assert!(!error.detail.contains("DB_HOST"));
+    }
+
+    #[test]
+    fn rate_limits_carry_retry_after() {
+        let response = ApiError::from(Error::RateLimited(20))
+            .into_response()
+            .unwrap();
+        assert_eq!(response.status(), 429);
+        assert_eq!(response.headers()[RETRY_AFTER], "20");
+    }
+
+    #[test]
+    fn bad_json_is_a_validation_error() {
+        let error: ApiError = serde_json::from_str::<serde_json::Value>("{").unwrap_err().into();
+        assert_eq!((error.code, error.status), (ErrorCode::Validation, 400));
+        assert!(error.detail.starts_with("Invalid request body"));
+    }
+}
@@ -0,0 +1,316 @@
+//! Domain event publication to a custom EventBridge bus.
+//!
+//! Every event is wrapped in a versioned [`EventEnvelope`] so consumers can
+//! evolve independently. Bump an event's `SCHEMA_VERSION` on any breaking change to
+//! its payload and update [`EVENT_REGISTRY`] to match.
+
+use aws_sdk_eventbridge::types::PutEventsRequestEntry;
+use aws_sdk_eventbridge::Client as EventBridgeClient;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub use crate::models::EventEnvelope;
+use crate::{Error, Result};
+
+/// EventBridge `source` for all Second Brain domain events.
+pub const EVENT_SOURCE: &str = "second-brain";
+
+/// A domain event that can be published to the event bus.
+pub trait DomainEvent: Serialize {
+    /// EventBridge `detail-type` (e.g. `FactCreated`).
+    const DETAIL_TYPE: &'static str;
+    /// Version of the payload schema.
+    const SCHEMA_VERSION: u32;
+}
+
+/// Registry entry describing a published event type.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct EventTypeInfo {
+    pub detail_type: &'static str,
+    pub schema_version: u32,
+    pub description: &'static str,
+}
+
+/// All event types published to the bus.
+pub const EVENT_REGISTRY: &[EventTypeInfo] = &[
+    EventTypeInfo {
+        detail_type: FactCreated::DETAIL_TYPE,
+        schema_version: FactCreated::SCHEMA_VERSION,
+        description: "A fact was stored",
+    },
+    EventTypeInfo {
+        detail_type: FactUpdated::DETAIL_TYPE,
+        schema_version: FactUpdated::SCHEMA_VERSION,
+        description: "A fact's content was changed",
+    },
+    EventTypeInfo {
+        detail_type: EntityMerged::DETAIL_TYPE,
+        schema_version: EntityMerged::SCHEMA_VERSION,
+        description: "One or more entities were merged into a target entity",
+    },
+    EventTypeInfo {
+        detail_type: ReminderTriggered::DETAIL_TYPE,
+        schema_version: ReminderTriggered::SCHEMA_VERSION,
+        description: "A reminder fired and a notification was queued",
+    },
+    EventTypeInfo {
+        detail_type: NotificationSent::DETAIL_TYPE,
+        schema_version: NotificationSent::SCHEMA_VERSION,
+        description: "A notification was delivered to a channel",
+    },
+];
+
+/// A fact was stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FactCreated {
+    pub fact_id: Uuid,
+    pub owner_type: String,
+    pub owner_id: Uuid,
+    pub created_by: Uuid,
+    pub source: String,
+}
+
+impl DomainEvent for FactCreated {
+    const DETAIL_TYPE: &'static str = "FactCreated";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+/// A fact's content was changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FactUpdated {
+    pub fact_id: Uuid,
+    pub owner_type: String,
+    pub owner_id: Uuid,
+    pub updated_by: Uuid,
+}
+
+impl DomainEvent for FactUpdated {
+    const DETAIL_TYPE: &'static str = "FactUpdated";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+/// One or more entities were merged into a target entity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityMerged {
+    pub target_entity_id: Uuid,
+    pub merged_entity_ids: Vec<Uuid>,
+    pub merged_by: Uuid,
+    pub facts_reparented: i64,
+}
+
+impl DomainEvent for EntityMerged {
+    const DETAIL_TYPE: &'static str = "EntityMerged";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+/// A reminder fired and a notification was queued.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReminderTriggered {
+    pub reminder_id: Uuid,
+    pub user_id: Uuid,
+    pub trigger_type: String,
+    pub notification_id: Uuid,
+    pub channel: String,
+}
+
+impl DomainEvent for ReminderTriggered {
+    const DETAIL_TYPE: &'static str = "ReminderTriggered";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+/// A notification was delivered to a channel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationSent {
+    pub notification_id: Uuid,
+    pub user_id: Uuid,
+    pub notification_type: String,
+    pub channel: String,
+}
+
+impl DomainEvent for NotificationSent {
+    const DETAIL_TYPE: &'static str = "NotificationSent";
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+/// `FactCreated` events for newly stored facts, read back from the database
+/// (ingestion agents only report the IDs). Facts that no longer exist are
+/// skipped.
+async fn facts_created(pool: &PgPool, fact_ids: &[Uuid], source: &str) -> Result<Vec<FactCreated>> {
+    if fact_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let rows: Vec<(Uuid, String, Uuid, Uuid)> = sqlx::query_as(
+        r#"
+        SELECT id, owner_type, owner_id, created_by
+        FROM facts
+        WHERE id = ANY($1)
+        ORDER BY recorded_at, id
+        "#,
+    )
+    .bind(fact_ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(fact_id, owner_type, owner_id, created_by)| FactCreated {
+            fact_id,
+            owner_type,
+            owner_id,
+            created_by,
+            source: source.to_string(),
+        })
+        .collect())
+}
+
+/// Publisher for domain events.
+pub struct EventPublisher {
+    client: EventBridgeClient,
+    bus_name: String,
+}
+
+impl EventPublisher {
+    /// Create a new publisher for the given bus.
+    pub fn new(client: EventBridgeClient, bus_name: String) -> Self {
+        Self { client, bus_name }
+    }
+
+    /// Publisher for the bus named by `EVENT_BUS_NAME`, or `None` (publication
+    /// skipped) when it is unset.
+    pub fn from_env(config: &aws_config::SdkConfig) -> Option<Self> {
+        std::env::var("EVENT_BUS_NAME")
+            .ok()
+            .map(|bus| Self::new(EventBridgeClient::new(config), bus))
+    }
+
+    /// Publish an event, returning its generated event id.
+    pub async fn publish<E: DomainEvent>(&self, event: &E) -> Result<Uuid> {
+        let envelope = EventEnvelope::new(E::SCHEMA_VERSION, event);
+        let event_id = envelope.event_id;
+        let detail = serde_json::to_string(&envelope)?;
+
+        let entry = PutEventsRequestEntry::builder()
+            .event_bus_name(&self.bus_name)
+            .source(EVENT_SOURCE)
+            .detail_type(E::DETAIL_TYPE)
+            .detail(detail)
+            .build();
+
+        let response = self
+            .client
+            .put_events()
+            .entries(entry)
+            .send()
+            .await
+            .map_err(|e| Error::Aws(format!("Failed to publish {}: {}", E::DETAIL_TYPE, e)))?;
+
+        if response.failed_entry_count() > 0 {
+            let message = response
+                .entries()
+                .first()
+                .and_then(|e| e.error_message())
+                .unwrap_or("unknown error");
+            return Err(Error::Aws(format!(
+                "EventBridge rejected {}: {}",
+                E::DETAIL_TYPE,
+                message
+            )));
+        }
+
+        Ok(event_id)
+    }
+
+    /// Publish `FactCreated` for each of `fact_ids`. Failures are logged
+    /// rather than returned: the facts are stored either way.
+    pub async fn publish_facts_created(&self, pool: &PgPool, fact_ids: &[Uuid], source: &str) {
+        let events = match facts_created(pool, fact_ids, source).await {
+            Ok(events) => events,
+            Err(e) => {
+                tracing::warn!("Failed to load created facts for events: {}", e);
+                return;
+            }
+        };
+
+        for event in events {
+            if let Err(e) = self.publish(&event).await {
+                tracing::warn!(fact_id = %event.fact_id, "Failed to publish FactCreated: {}", e);
+            }
+        }
+    }
+
+    /// Publish `FactUpdated` for a fact whose content `updated_by` changed.
+    /// Failures are logged rather than returned, as for `FactCreated`.
+    pub async fn publish_fact_updated(&self, pool: &PgPool, fact_id: Uuid, updated_by: Uuid) {
+        let owner: Option<(String, Uuid)> =
+            match sqlx::query_as("SELECT owner_type, owner_id FROM facts WHERE id = $1")
+                .bind(fact_id)
+                .fetch_optional(pool)
+                .await
+            {
+                Ok(owner) => owner,
+                Err(e) => {
+                    tracing::warn!(%fact_id, "Failed to load updated fact for event: {}", e);
+                    return;
+                }
+            };
+        let Some((owner_type, owner_id)) = owner else {
+            return;
+        };
+
+        let event = FactUpdated {
+            fact_id,
+            owner_type,
+            owner_id,
+            updated_by,
+        };
+        if let Err(e) = self.publish(&event).await {
+            tracing::warn!(%fact_id, "Failed to publish FactUpdated: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_registry_detail_types_unique() {
+        let types: HashSet<&str> = EVENT_REGISTRY.iter().map(|e| e.detail_type).collect();
+        assert_eq!(types.len(), EVENT_REGISTRY.len());
+    }
+
+    #[test]
+    fn test_envelope_shape() {
+        let event = NotificationSent {
+            notification_id: Uuid::nil(),
+            user_id: Uuid::nil(),
+            notification_type: "reminder".to_string(),
+            channel: "email".to_string(),
+        };
+        let envelope = EventEnvelope::new(NotificationSent::SCHEMA_VERSION, &event);
+        let json = serde_json::to_value(&envelope).unwrap();
+        assert_eq!(json["schema_version"], 1);
+        assert_eq!(json["data"]["channel"], "email");
+        assert_eq!(json["event_id"], envelope.event_id.to_string());
+    }
+
+    #[test]
+    fn test_envelope_round_trip() {
+        let event = EntityMerged {
+            target_entity_id: Uuid::nil(),
+            merged_entity_ids: vec![Uuid::nil()],
+            merged_by: Uuid::nil(),
+            facts_reparented: 3,
+        };
+        let detail =
+            serde_json::to_string(&EventEnvelope::new(EntityMerged::SCHEMA_VERSION, &event))
+                .unwrap();
+
+        let parsed: EventEnvelope<serde_json::Value> = serde_json::from_str(&detail).unwrap();
+        assert_eq!(parsed.schema_version, EntityMerged::SCHEMA_VERSION);
+        assert_eq!(parsed.data["facts_reparented"], 3);
+    }
+}
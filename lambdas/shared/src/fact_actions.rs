@@ -0,0 +1,649 @@
+//! Structured edits and deletions of a fact the user describes.
+//!
+//! Chat commands like Discord's `/edit` and `/forget` name a fact loosely
+//! ("John's birthday") rather than by ID. [`resolve_fact`] turns that into
+//! the one fact meant: by ID when the caller has one, otherwise by full-text
+//! search over the facts the user can change. When several facts match
+//! equally well the caller gets them back as candidates to choose from, and
+//! repeats the request with the chosen one's ID.
+//!
+//! Changes apply at once but come with an undo token, good for
+//! [`UNDO_WINDOW_SECS`], that reverts them (see [`undo`]): a forgotten fact
+//! comes back out of the trash and an edited one gets its previous content
+//! back, unless it has been changed again since.
+//!
+//! The `internal_facts` Lambda serves this to the webhooks and agents, which
+//! invoke it directly (see [`invoke`]) with a [`FactActionRequest`] for
+//! [`EDIT_PATH`], [`DELETE_PATH`] or [`UNDO_PATH`], signed like other
+//! internal payloads.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::access::space_clause;
+use crate::auth::sign_internal;
+use crate::{Error, Result};
+
+/// Resolve a fact and replace its content
+pub const EDIT_PATH: &str = "/internal/facts/resolve-and-edit";
+
+/// Resolve a fact and move it to the trash
+pub const DELETE_PATH: &str = "/internal/facts/resolve-and-delete";
+
+/// Revert an edit or deletion by its undo token (the latest without one)
+pub const UNDO_PATH: &str = "/internal/facts/undo";
+
+/// Seconds an edit or deletion can be undone for
+pub const UNDO_WINDOW_SECS: i64 = 300;
+
+/// Most candidates offered when a description matches several facts
+pub const MAX_CANDIDATES: i64 = 5;
+
+/// Longest fact content accepted by an edit
+pub const MAX_CONTENT_CHARS: usize = 4000;
+
+/// The fact to act on: its ID, or a description to search for.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FactTarget {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fact_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+}
+
+/// A fact a target resolved to, or one of several it might mean.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct FactCandidate {
+    pub id: Uuid,
+    pub content: String,
+    pub recorded_at: DateTime<Utc>,
+    /// Name of the entity the fact is about
+    pub entity_name: Option<String>,
+}
+
+/// What a target resolved to
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    Found(FactCandidate),
+    /// Several facts match; best match first
+    Ambiguous(Vec<FactCandidate>),
+    NotFound,
+}
+
+/// Payload of an `internal_facts` invocation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FactActionRequest {
+    /// [`EDIT_PATH`] or [`DELETE_PATH`]
+    pub path: String,
+    pub user_id: Uuid,
+    #[serde(default)]
+    pub family_ids: Vec<Uuid>,
+    #[serde(flatten)]
+    pub target: FactTarget,
+    /// New content (edits only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// Token of the change to revert (undo only; the latest change if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undo_token: Option<Uuid>,
+}
+
+/// Reverts an edit or deletion until it expires
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UndoToken {
+    pub token: Uuid,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Result of an `internal_facts` invocation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum FactActionResponse {
+    Edited {
+        fact: FactCandidate,
+        previous_content: String,
+        undo: UndoToken,
+    },
+    Deleted {
+        fact: FactCandidate,
+        restore_within_days: i64,
+        undo: UndoToken,
+    },
+    Undone {
+        fact: FactCandidate,
+    },
+    /// Nothing was changed; repeat the request with one of these IDs
+    Ambiguous {
+        candidates: Vec<FactCandidate>,
+    },
+    NotFound,
+    /// The request was invalid or failed
+    Error {
+        message: String,
+    },
+}
+
+/// Invoke the `internal_facts` Lambda, signing the request for its user.
+pub async fn invoke(
+    lambda_client: &aws_sdk_lambda::Client,
+    function_name: &str,
+    request: &FactActionRequest,
+) -> Result<FactActionResponse> {
+    let family_ids: Vec<String> = request.family_ids.iter().map(Uuid::to_string).collect();
+    let payload = sign_internal(
+        request,
+        "POST",
+        &request.path,
+        &request.user_id.to_string(),
+        &family_ids,
+    )
+    .await?;
+    let payload = serde_json::to_vec(&payload).map_err(Error::Serialization)?;
+
+    let response = lambda_client
+        .invoke()
+        .function_name(function_name)
+        .payload(aws_sdk_lambda::primitives::Blob::new(payload))
+        .send()
+        .await
+        .map_err(|e| Error::Aws(format!("Failed to invoke fact actions: {}", e)))?;
+
+    if let Some(error) = response.function_error() {
+        return Err(Error::Aws(format!("Fact actions failed: {}", error)));
+    }
+
+    let payload = response
+        .payload()
+        .ok_or_else(|| Error::Aws("No response payload from fact actions".to_string()))?;
+
+    serde_json::from_slice(payload.as_ref())
+        .map_err(|e| Error::Aws(format!("Failed to parse fact actions response: {}", e)))
+}
+
+/// Facts aliased `f` that the user bound at `$1` (with their families at
+/// `$2`) can change: their own and their families', as in fact review.
+fn changeable_clause() -> String {
+    format!(
+        r#"f.deleted_at IS NULL AND f.superseded_by IS NULL
+        AND ((f.owner_type = 'user' AND f.owner_id = $1)
+             OR (f.owner_type = 'family' AND f.owner_id = ANY($2) AND {}))"#,
+        space_clause("f", 1)
+    )
+}
+
+/// Pick the fact a search for `query` meant from its matches, best first.
+/// One match, or a match whose content is exactly the query, is the fact;
+/// otherwise the user has to choose.
+pub fn pick(query: &str, mut matches: Vec<FactCandidate>) -> Resolution {
+    let query = query.trim();
+    if let Some(exact) = matches
+        .iter()
+        .position(|m| m.content.trim().eq_ignore_ascii_case(query))
+    {
+        return Resolution::Found(matches.swap_remove(exact));
+    }
+
+    match matches.len() {
+        0 => Resolution::NotFound,
+        1 => Resolution::Found(matches.remove(0)),
+        _ => Resolution::Ambiguous(matches),
+    }
+}
+
+/// Find the fact a target means among those the user can change.
+pub async fn resolve_fact(
+    pool: &sqlx::PgPool,
+    user_id: Uuid,
+    family_ids: &[Uuid],
+    target: &FactTarget,
+) -> Result<Resolution> {
+    if let Some(fact_id) = target.fact_id {
+        let fact: Option<FactCandidate> = sqlx::query_as(&format!(
+            r#"
+            SELECT f.id, f.content, f.recorded_at, e.name AS entity_name
+            FROM facts f
+            LEFT JOIN entities e ON e.id = f.about_entity_id
+            WHERE f.id = $3 AND {}
+            "#,
+            changeable_clause()
+        ))
+        .bind(user_id)
+        .bind(family_ids)
+        .bind(fact_id)
+        .fetch_optional(pool)
+        .await?;
+
+        return Ok(fact.map_or(Resolution::NotFound, Resolution::Found));
+    }
+
+    let query = target
+        .query
+        .as_deref()
+        .map(str::trim)
+        .filter(|q| !q.is_empty())
+        .ok_or_else(|| Error::Validation("fact_id or query is required".to_string()))?;
+
+    let matches: Vec<FactCandidate> = sqlx::query_as(&format!(
+        r#"
+        WITH q AS (SELECT websearch_to_tsquery('english', $3) AS query)
+        SELECT f.id, f.content, f.recorded_at, e.name AS entity_name
+        FROM facts f
+        CROSS JOIN q
+        LEFT JOIN entities e ON e.id = f.about_entity_id
+        WHERE f.search_vector @@ q.query AND {}
+        ORDER BY ts_rank(f.search_vector, q.query) DESC, f.recorded_at DESC
+        LIMIT $4
+        "#,
+        changeable_clause()
+    ))
+    .bind(user_id)
+    .bind(family_ids)
+    .bind(query)
+    .bind(MAX_CANDIDATES)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(pick(query, matches))
+}
+
+/// Replace the content of a fact the user can change, correcting it in
+/// place (the embedding indexer re-embeds it). Returns the edited fact, or
+/// `None` if there is no such fact.
+pub async fn edit_fact(
+    pool: &sqlx::PgPool,
+    user_id: Uuid,
+    family_ids: &[Uuid],
+    fact_id: Uuid,
+    content: &str,
+) -> Result<Option<FactCandidate>> {
+    let content = content.trim();
+    if content.is_empty() {
+        return Err(Error::Validation("content must not be empty".to_string()));
+    }
+    if content.chars().count() > MAX_CONTENT_CHARS {
+        return Err(Error::Validation(format!(
+            "content must be at most {} characters",
+            MAX_CONTENT_CHARS
+        )));
+    }
+
+    let edited = sqlx::query_as(&format!(
+        r#"
+        UPDATE facts f
+        SET content = $4, updated_at = NOW()
+        WHERE f.id = $3 AND {}
+        RETURNING f.id, f.content, f.recorded_at,
+                  (SELECT e.name FROM entities e WHERE e.id = f.about_entity_id) AS entity_name
+        "#,
+        changeable_clause()
+    ))
+    .bind(user_id)
+    .bind(family_ids)
+    .bind(fact_id)
+    .bind(content)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(edited)
+}
+
+/// Issue the undo token of a change just made to a fact: a deletion, or an
+/// edit when `edit` holds the content before and after it. The user's
+/// expired tokens are cleared out on the way.
+pub async fn issue_undo(
+    pool: &sqlx::PgPool,
+    user_id: Uuid,
+    fact_id: Uuid,
+    edit: Option<(&str, &str)>,
+) -> Result<UndoToken> {
+    sqlx::query("DELETE FROM fact_undo_tokens WHERE user_id = $1 AND expires_at < NOW()")
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    let token = sqlx::query_as(
+        r#"
+        INSERT INTO fact_undo_tokens (
+            user_id, fact_id, action, previous_content, applied_content, expires_at
+        )
+        VALUES ($1, $2, $3, $4, $5, NOW() + make_interval(secs => $6))
+        RETURNING id AS token, expires_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(fact_id)
+    .bind(if edit.is_some() { "edit" } else { "delete" })
+    .bind(edit.map(|(previous, _)| previous))
+    .bind(edit.map(|(_, applied)| applied))
+    .bind(UNDO_WINDOW_SECS as f64)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(token)
+}
+
+/// Undo token as stored
+#[derive(Debug, sqlx::FromRow)]
+struct StoredUndo {
+    id: Uuid,
+    fact_id: Uuid,
+    action: String,
+    previous_content: Option<String>,
+    applied_content: Option<String>,
+    expired: bool,
+}
+
+/// A reverted change
+#[derive(Debug, Clone, PartialEq)]
+pub struct Undone {
+    /// The fact as it is again
+    pub fact: FactCandidate,
+    /// Whether the fact came back from the trash (otherwise an edit was reverted)
+    pub restored: bool,
+}
+
+/// Revert the change an undo token was issued for, or without a token the
+/// user's latest change not undone yet. Returns `None` if there is no such
+/// change. Expired tokens and facts changed since are `Error::Conflict`.
+pub async fn undo(
+    pool: &sqlx::PgPool,
+    user_id: Uuid,
+    token: Option<Uuid>,
+) -> Result<Option<Undone>> {
+    let mut tx = pool.begin().await?;
+
+    let stored: Option<StoredUndo> = sqlx::query_as(
+        r#"
+        SELECT id, fact_id, action, previous_content, applied_content,
+               expires_at < NOW() AS expired
+        FROM fact_undo_tokens
+        WHERE ($1::uuid IS NULL OR id = $1) AND user_id = $2 AND undone_at IS NULL
+        ORDER BY created_at DESC
+        LIMIT 1
+        FOR UPDATE
+        "#,
+    )
+    .bind(token)
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(stored) = stored else {
+        return Ok(None);
+    };
+    if stored.expired {
+        return Err(Error::Conflict(format!(
+            "Changes can only be undone for {} minutes",
+            UNDO_WINDOW_SECS / 60
+        )));
+    }
+
+    // The token was issued after checking the user could change the fact
+    let restored = stored.action == "delete";
+    let fact: Option<FactCandidate> = if !restored {
+        sqlx::query_as(
+            r#"
+            UPDATE facts f
+            SET content = $2, updated_at = NOW()
+            WHERE f.id = $1 AND f.content = $3 AND f.deleted_at IS NULL
+            RETURNING f.id, f.content, f.recorded_at,
+                      (SELECT e.name FROM entities e WHERE e.id = f.about_entity_id) AS entity_name
+            "#,
+        )
+        .bind(stored.fact_id)
+        .bind(&stored.previous_content)
+        .bind(&stored.applied_content)
+        .fetch_optional(&mut *tx)
+        .await?
+    } else {
+        sqlx::query_as(
+            r#"
+            UPDATE facts f
+            SET deleted_at = NULL, deleted_by = NULL
+            WHERE f.id = $1 AND f.deleted_at IS NOT NULL
+            RETURNING f.id, f.content, f.recorded_at,
+                      (SELECT e.name FROM entities e WHERE e.id = f.about_entity_id) AS entity_name
+            "#,
+        )
+        .bind(stored.fact_id)
+        .fetch_optional(&mut *tx)
+        .await?
+    };
+
+    let Some(fact) = fact else {
+        return Err(Error::Conflict(
+            "The fact has changed since, so it can't be undone".to_string(),
+        ));
+    };
+
+    sqlx::query("UPDATE fact_undo_tokens SET undone_at = NOW() WHERE id = $1")
+        .bind(stored.id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(Some(Undone { fact, restored }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::PgPool;
+
+    fn candidate(content: &str) -> FactCandidate {
+        FactCandidate {
+            id: Uuid::new_v4(),
+            content: content.to_string(),
+            recorded_at: Utc::now(),
+            entity_name: None,
+        }
+    }
+
+    #[test]
+    fn test_pick() {
+        assert_eq!(pick("john birthday", Vec::new()), Resolution::NotFound);
+
+        let only = candidate("John's birthday is March 15");
+        assert_eq!(
+            pick("john birthday", vec![only.clone()]),
+            Resolution::Found(only)
+        );
+
+        let several = vec![
+            candidate("John's birthday is March 15"),
+            candidate("John's birthday party is at the lake"),
+        ];
+        assert_eq!(
+            pick("john birthday", several.clone()),
+            Resolution::Ambiguous(several.clone())
+        );
+
+        // Quoting a fact word for word picks it out
+        assert_eq!(
+            pick(" john's birthday party is at the lake ", several.clone()),
+            Resolution::Found(several[1].clone())
+        );
+    }
+
+    #[test]
+    fn test_request_round_trip() {
+        let request: FactActionRequest = serde_json::from_value(serde_json::json!({
+            "path": EDIT_PATH,
+            "user_id": Uuid::nil(),
+            "query": "john birthday",
+            "content": "John's birthday is March 16",
+            "internal_auth": {"caller": "test", "issued_at": 0, "signature": ""},
+        }))
+        .unwrap();
+        assert_eq!(request.target.fact_id, None);
+        assert_eq!(request.target.query.as_deref(), Some("john birthday"));
+        assert!(request.family_ids.is_empty());
+        assert_eq!(request.undo_token, None);
+
+        let value = serde_json::to_value(&request).unwrap();
+        assert_eq!(value["query"], "john birthday");
+        assert!(value.get("fact_id").is_none());
+    }
+
+    #[test]
+    fn test_response_status() {
+        let value = serde_json::to_value(FactActionResponse::NotFound).unwrap();
+        assert_eq!(value, serde_json::json!({"status": "not_found"}));
+
+        let ambiguous = FactActionResponse::Ambiguous {
+            candidates: vec![candidate("John's birthday is March 15")],
+        };
+        let value = serde_json::to_value(&ambiguous).unwrap();
+        assert_eq!(value["status"], "ambiguous");
+        assert_eq!(
+            serde_json::from_value::<FactActionResponse>(value).unwrap(),
+            ambiguous
+        );
+
+        let token = Uuid::new_v4();
+        let deleted = FactActionResponse::Deleted {
+            fact: candidate("John's birthday is March 15"),
+            restore_within_days: 30,
+            undo: UndoToken {
+                token,
+                expires_at: Utc::now(),
+            },
+        };
+        let value = serde_json::to_value(&deleted).unwrap();
+        assert_eq!(value["status"], "deleted");
+        assert_eq!(value["undo"]["token"], token.to_string());
+    }
+
+    async fn insert_user(pool: &PgPool, name: &str) -> Uuid {
+        sqlx::query_scalar(
+            "INSERT INTO users (cognito_sub, email, display_name)
+             VALUES ($1, $1 || '@example.com', $1) RETURNING id",
+        )
+        .bind(name)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn insert_fact(pool: &PgPool, owner_id: Uuid, content: &str) -> Uuid {
+        sqlx::query_scalar(
+            "INSERT INTO facts (owner_type, owner_id, created_by, content)
+             VALUES ('user', $1, $1, $2) RETURNING id",
+        )
+        .bind(owner_id)
+        .bind(content)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn content(pool: &PgPool, fact_id: Uuid) -> String {
+        sqlx::query_scalar("SELECT content FROM facts WHERE id = $1")
+            .bind(fact_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn in_trash(pool: &PgPool, fact_id: Uuid) -> bool {
+        sqlx::query_scalar("SELECT deleted_at IS NOT NULL FROM facts WHERE id = $1")
+            .bind(fact_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    /// Edit a fact the way `internal_facts` does and issue its undo token
+    async fn edit(pool: &PgPool, user_id: Uuid, fact_id: Uuid, applied: &str) -> Uuid {
+        let previous = content(pool, fact_id).await;
+        edit_fact(pool, user_id, &[], fact_id, applied)
+            .await
+            .unwrap()
+            .unwrap();
+        let edit = Some((previous.as_str(), applied));
+        issue_undo(pool, user_id, fact_id, edit)
+            .await
+            .unwrap()
+            .token
+    }
+
+    #[sqlx::test(migrator = "migrations::MIGRATOR")]
+    #[ignore = "needs DATABASE_URL"]
+    async fn test_undo_within_window(pool: PgPool) {
+        let user_id = insert_user(&pool, "sam").await;
+        let fact_id = insert_fact(&pool, user_id, "John's birthday is March 15").await;
+
+        let token = edit(&pool, user_id, fact_id, "John's birthday is March 16").await;
+        let undone = undo(&pool, user_id, Some(token)).await.unwrap().unwrap();
+        assert!(!undone.restored);
+        assert_eq!(undone.fact.content, "John's birthday is March 15");
+        assert_eq!(content(&pool, fact_id).await, "John's birthday is March 15");
+
+        // Without a token the latest change is undone: here a deletion
+        assert!(crate::trash::delete_fact(&pool, fact_id, user_id)
+            .await
+            .unwrap());
+        issue_undo(&pool, user_id, fact_id, None).await.unwrap();
+        let undone = undo(&pool, user_id, None).await.unwrap().unwrap();
+        assert!(undone.restored);
+        assert_eq!(undone.fact.id, fact_id);
+        assert!(!in_trash(&pool, fact_id).await);
+    }
+
+    #[sqlx::test(migrator = "migrations::MIGRATOR")]
+    #[ignore = "needs DATABASE_URL"]
+    async fn test_undo_expired_token(pool: PgPool) {
+        let user_id = insert_user(&pool, "sam").await;
+        let fact_id = insert_fact(&pool, user_id, "John's birthday is March 15").await;
+
+        let token = edit(&pool, user_id, fact_id, "John's birthday is March 16").await;
+        sqlx::query("UPDATE fact_undo_tokens SET expires_at = NOW() - INTERVAL '1 second'")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let result = undo(&pool, user_id, Some(token)).await;
+        assert!(matches!(result, Err(Error::Conflict(_))));
+        assert_eq!(content(&pool, fact_id).await, "John's birthday is March 16");
+    }
+
+    #[sqlx::test(migrator = "migrations::MIGRATOR")]
+    #[ignore = "needs DATABASE_URL"]
+    async fn test_undo_twice(pool: PgPool) {
+        let user_id = insert_user(&pool, "sam").await;
+        let fact_id = insert_fact(&pool, user_id, "John's birthday is March 15").await;
+
+        let token = edit(&pool, user_id, fact_id, "John's birthday is March 16").await;
+        assert!(undo(&pool, user_id, Some(token)).await.unwrap().is_some());
+
+        // The token is spent, and there is no other change left to undo
+        assert_eq!(undo(&pool, user_id, Some(token)).await.unwrap(), None);
+        assert_eq!(undo(&pool, user_id, None).await.unwrap(), None);
+        assert_eq!(content(&pool, fact_id).await, "John's birthday is March 15");
+
+        // A change made since can't be reverted by an older token
+        let first = edit(&pool, user_id, fact_id, "John's birthday is March 16").await;
+        edit(&pool, user_id, fact_id, "John's birthday is March 17").await;
+        let result = undo(&pool, user_id, Some(first)).await;
+        assert!(matches!(result, Err(Error::Conflict(_))));
+        assert_eq!(content(&pool, fact_id).await, "John's birthday is March 17");
+    }
+
+    #[sqlx::test(migrator = "migrations::MIGRATOR")]
+    #[ignore = "needs DATABASE_URL"]
+    async fn test_undo_other_users_token(pool: PgPool) {
+        let user_id = insert_user(&pool, "sam").await;
+        let other_id = insert_user(&pool, "alex").await;
+        let fact_id = insert_fact(&pool, user_id, "John's birthday is March 15").await;
+
+        let token = edit(&pool, user_id, fact_id, "John's birthday is March 16").await;
+        assert_eq!(undo(&pool, other_id, Some(token)).await.unwrap(), None);
+        assert_eq!(undo(&pool, other_id, None).await.unwrap(), None);
+        assert_eq!(content(&pool, fact_id).await, "John's birthday is March 16");
+
+        // The token is still good for its owner
+        assert!(undo(&pool, user_id, Some(token)).await.unwrap().is_some());
+    }
+}
@@ -0,0 +1,274 @@
+//! Files attached to facts, stored in S3.
+//!
+//! Clients ask `POST /facts/{id}/attachments` for a presigned PUT URL and
+//! upload the file straight to the attachments bucket, so "remember this
+//! receipt" can keep the receipt itself. Rows in `fact_attachments` carry
+//! the object key and declared metadata; fact responses list attachments
+//! with short-lived presigned GET URLs.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::Result;
+
+/// Largest file accepted
+pub const MAX_ATTACHMENT_BYTES: i64 = 25 * 1024 * 1024;
+
+/// Most attachments on one fact
+pub const MAX_ATTACHMENTS_PER_FACT: i64 = 20;
+
+/// Longest stored file name
+const MAX_FILE_NAME_CHARS: usize = 200;
+
+/// How long a presigned upload URL is valid
+pub const UPLOAD_URL_TTL_SECS: u64 = 15 * 60;
+
+/// How long a presigned download URL is valid
+pub const DOWNLOAD_URL_TTL_SECS: u64 = 60 * 60;
+
+/// Accepted content types, the extension their objects get, and their kind
+const ATTACHMENT_TYPES: &[(&str, &str, &str)] = &[
+    ("image/jpeg", "jpg", "photo"),
+    ("image/png", "png", "photo"),
+    ("image/webp", "webp", "photo"),
+    ("image/heic", "heic", "photo"),
+    ("application/pdf", "pdf", "document"),
+    ("audio/mpeg", "mp3", "audio"),
+    ("audio/mp4", "m4a", "audio"),
+    ("audio/wav", "wav", "audio"),
+    ("audio/webm", "webm", "audio"),
+    ("audio/ogg", "ogg", "audio"),
+];
+
+/// An accepted attachment type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttachmentType {
+    pub content_type: &'static str,
+    pub extension: &'static str,
+    /// `photo`, `document` or `audio`
+    pub kind: &'static str,
+}
+
+/// Normalized type for a declared content type, or `None` if it isn't accepted.
+pub fn attachment_type(content_type: &str) -> Option<AttachmentType> {
+    let content_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let content_type = match content_type.as_str() {
+        "image/jpg" => "image/jpeg",
+        "audio/mp3" => "audio/mpeg",
+        "audio/x-wav" | "audio/wave" => "audio/wav",
+        "audio/x-m4a" => "audio/mp4",
+        other => other,
+    };
+
+    ATTACHMENT_TYPES
+        .iter()
+        .find(|(accepted, _, _)| *accepted == content_type)
+        .map(|&(content_type, extension, kind)| AttachmentType {
+            content_type,
+            extension,
+            kind,
+        })
+}
+
+/// Accepted content types, for error messages.
+pub fn accepted_types() -> String {
+    ATTACHMENT_TYPES
+        .iter()
+        .map(|(content_type, _, _)| *content_type)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Object key for a new attachment.
+pub fn attachment_key(fact_id: Uuid, attachment_id: Uuid, extension: &str) -> String {
+    format!(
+        "fact-attachments/{}/{}.{}",
+        fact_id, attachment_id, extension
+    )
+}
+
+/// File name to store and offer on download: no directories or control
+/// characters, bounded length, and a fallback when nothing usable is left.
+pub fn clean_file_name(file_name: Option<&str>, extension: &str) -> String {
+    let base = file_name
+        .unwrap_or_default()
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default();
+    let cleaned: String = base
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_FILE_NAME_CHARS)
+        .collect();
+    let cleaned = cleaned.trim();
+
+    if cleaned.is_empty() || cleaned.chars().all(|c| c == '.') {
+        format!("attachment.{}", extension)
+    } else {
+        cleaned.to_string()
+    }
+}
+
+/// A file attached to a fact
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub id: Uuid,
+    pub fact_id: Uuid,
+    pub file_name: String,
+    pub content_type: String,
+    pub kind: String,
+    pub size_bytes: i64,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip)]
+    pub object_key: String,
+    /// Presigned download URL, filled in by the API
+    #[sqlx(skip)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// Record an attachment whose upload URL is being issued.
+pub async fn insert_attachment(
+    pool: &sqlx::PgPool,
+    fact_id: Uuid,
+    user_id: Uuid,
+    file_name: &str,
+    file_type: AttachmentType,
+    size_bytes: i64,
+) -> Result<Attachment> {
+    let attachment_id = Uuid::new_v4();
+
+    let attachment = sqlx::query_as(
+        r#"
+        INSERT INTO fact_attachments
+            (id, fact_id, object_key, file_name, content_type, kind, size_bytes, uploaded_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id, fact_id, file_name, content_type, kind, size_bytes, created_at, object_key
+        "#,
+    )
+    .bind(attachment_id)
+    .bind(fact_id)
+    .bind(attachment_key(fact_id, attachment_id, file_type.extension))
+    .bind(file_name)
+    .bind(file_type.content_type)
+    .bind(file_type.kind)
+    .bind(size_bytes)
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(attachment)
+}
+
+/// Number of attachments on a fact.
+pub async fn count_attachments(pool: &sqlx::PgPool, fact_id: Uuid) -> Result<i64> {
+    let count = sqlx::query_scalar("SELECT COUNT(*) FROM fact_attachments WHERE fact_id = $1")
+        .bind(fact_id)
+        .fetch_one(pool)
+        .await?;
+
+    Ok(count)
+}
+
+/// Attachments on any of `fact_ids`, oldest first.
+pub async fn list_attachments(pool: &sqlx::PgPool, fact_ids: &[Uuid]) -> Result<Vec<Attachment>> {
+    if fact_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let attachments = sqlx::query_as(
+        r#"
+        SELECT id, fact_id, file_name, content_type, kind, size_bytes, created_at, object_key
+        FROM fact_attachments
+        WHERE fact_id = ANY($1)
+        ORDER BY created_at, id
+        "#,
+    )
+    .bind(fact_ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(attachments)
+}
+
+/// Remove an attachment from a fact, returning its object key so the caller
+/// can delete the file.
+pub async fn delete_attachment(
+    pool: &sqlx::PgPool,
+    fact_id: Uuid,
+    attachment_id: Uuid,
+) -> Result<Option<String>> {
+    let key = sqlx::query_scalar(
+        "DELETE FROM fact_attachments WHERE id = $1 AND fact_id = $2 RETURNING object_key",
+    )
+    .bind(attachment_id)
+    .bind(fact_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_content_types() {
+        assert_eq!(
+            attachment_type("Image/JPG"),
+            Some(AttachmentType {
+                content_type: "image/jpeg",
+                extension: "jpg",
+                kind: "photo",
+            })
+        );
+        assert_eq!(
+            attachment_type("application/pdf; charset=binary").map(|t| t.kind),
+            Some("document")
+        );
+        assert_eq!(
+            attachment_type("audio/x-m4a").map(|t| (t.content_type, t.extension)),
+            Some(("audio/mp4", "m4a"))
+        );
+        assert_eq!(attachment_type("image/gif"), None);
+        assert_eq!(attachment_type("application/zip"), None);
+    }
+
+    #[test]
+    fn keys_are_scoped_to_fact() {
+        let fact_id = Uuid::from_u128(1);
+        let attachment_id = Uuid::from_u128(2);
+        assert_eq!(
+            attachment_key(fact_id, attachment_id, "pdf"),
+            format!("fact-attachments/{}/{}.pdf", fact_id, attachment_id)
+        );
+    }
+
+    #[test]
+    fn cleans_file_names() {
+        assert_eq!(
+            clean_file_name(Some(" receipt-0412.jpg "), "jpg"),
+            "receipt-0412.jpg"
+        );
+        assert_eq!(clean_file_name(Some("../../etc/passwd"), "pdf"), "passwd");
+        assert_eq!(
+            clean_file_name(Some("C:\\Users\\me\\scan\u{0}.pdf"), "pdf"),
+            "scan.pdf"
+        );
+        assert_eq!(clean_file_name(Some(".."), "pdf"), "attachment.pdf");
+        assert_eq!(clean_file_name(None, "m4a"), "attachment.m4a");
+        assert_eq!(
+            clean_file_name(Some(&"a".repeat(500)), "pdf").len(),
+            MAX_FILE_NAME_CHARS
+        );
+    }
+}
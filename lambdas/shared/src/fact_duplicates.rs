@@ -0,0 +1,367 @@
+//! Duplicate fact detection.
+//!
+//! Ingesting "Mom's birthday is June 3" twice shouldn't leave two facts. Each
+//! newly ingested fact is compared with the recent facts the owner already
+//! recorded about the same entity, by trigram similarity of the text and
+//! cosine similarity of the embeddings. A repeat is merged into the earlier
+//! fact (its entity mentions and tags carried over, the new fact moved to the
+//! trash) only when the normalized text is identical, or the embeddings
+//! confirm a near-verbatim match that states the same numbers; anything else
+//! close ("June 3" after "June 30") is kept but flagged. Either way the new
+//! fact's `duplicate_of` points at the earlier one.
+//!
+//! Facts that supersede an earlier one (see `supersession`) are updates, not
+//! repeats, and are settled before duplicates are looked for.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::embeddings::to_pgvector;
+use crate::tag_suggestions::fact_embedding;
+use crate::{EmbeddingClient, Result};
+
+/// Trigram similarity at which a fact repeats an earlier one word for word
+pub const MERGE_TRIGRAM: f64 = 0.9;
+
+/// Cosine similarity that confirms a near-verbatim repeat
+pub const MERGE_SIMILARITY: f64 = 0.9;
+
+/// Trigram similarity at which a fact is flagged as a likely duplicate
+pub const FLAG_TRIGRAM: f64 = 0.75;
+
+/// Cosine similarity at which a rewording is flagged, given some overlap in
+/// the text (`MIN_TRIGRAM`)
+pub const FLAG_SIMILARITY: f64 = 0.95;
+
+/// Text overlap below which facts are never duplicates
+const MIN_TRIGRAM: f64 = 0.5;
+
+/// How far back earlier facts are compared
+const RECENT_DAYS: i64 = 90;
+
+/// Closest candidates considered per new fact
+const MAX_CANDIDATES: i64 = 5;
+
+/// What happened to a duplicate fact
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateKind {
+    /// Folded into the earlier fact and moved to the trash
+    Merged,
+    /// Kept, with `duplicate_of` set for review
+    Flagged,
+}
+
+/// Whether a new fact is a duplicate of an earlier one, given their
+/// normalized text and similarities. `similarity` is `None` when the earlier
+/// fact hasn't been embedded yet, as when the same thing is ingested twice in
+/// quick succession; without it only identical text is merged.
+pub fn classify(
+    content: &str,
+    earlier: &str,
+    trigram: f64,
+    similarity: Option<f64>,
+) -> Option<DuplicateKind> {
+    if content == earlier {
+        return Some(DuplicateKind::Merged);
+    }
+    if trigram < MIN_TRIGRAM {
+        return None;
+    }
+    if trigram >= MERGE_TRIGRAM
+        && similarity.is_some_and(|s| s >= MERGE_SIMILARITY)
+        && numbers(content) == numbers(earlier)
+    {
+        return Some(DuplicateKind::Merged);
+    }
+    if trigram >= FLAG_TRIGRAM || similarity.is_some_and(|s| s >= FLAG_SIMILARITY) {
+        return Some(DuplicateKind::Flagged);
+    }
+    None
+}
+
+/// The numbers in a text, in order: dates and amounts that differ by a digit
+/// look alike to trigrams and embeddings but aren't the same fact.
+fn numbers(text: &str) -> Vec<&str> {
+    text.split(|c: char| !c.is_ascii_digit())
+        .filter(|n| !n.is_empty())
+        .collect()
+}
+
+/// A newly ingested fact found to duplicate an earlier one
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Duplicate {
+    pub duplicate_of: Uuid,
+    pub kind: DuplicateKind,
+    pub trigram_similarity: f64,
+    pub similarity: Option<f64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct NewFact {
+    owner_type: String,
+    owner_id: Uuid,
+    created_by: Uuid,
+    about_entity_id: Option<Uuid>,
+    content: String,
+    content_normalized: String,
+    importance: i16,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(sqlx::FromRow)]
+struct Candidate {
+    id: Uuid,
+    content_normalized: String,
+    trigram: f64,
+}
+
+/// Check a newly ingested fact against the owner's recent facts about the
+/// same entity, merging or flagging it if it repeats one of them.
+pub async fn detect_duplicate(
+    pool: &sqlx::PgPool,
+    embedding_client: &EmbeddingClient,
+    fact_id: Uuid,
+) -> Result<Option<Duplicate>> {
+    let fact: Option<NewFact> = sqlx::query_as(
+        r#"
+        SELECT owner_type, owner_id, created_by, about_entity_id, content,
+               content_normalized, importance, created_at
+        FROM facts
+        WHERE id = $1 AND deleted_at IS NULL AND duplicate_of IS NULL
+        "#,
+    )
+    .bind(fact_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(fact) = fact else {
+        return Ok(None);
+    };
+
+    let candidates: Vec<Candidate> = sqlx::query_as(
+        r#"
+        SELECT f.id, f.content_normalized,
+               similarity(f.content_normalized, $1)::float8 AS trigram
+        FROM facts f
+        WHERE f.about_entity_id IS NOT DISTINCT FROM $2
+        AND f.owner_type = $3 AND f.owner_id = $4
+        AND f.id != $5
+        AND f.deleted_at IS NULL
+        AND f.superseded_by IS NULL
+        AND f.duplicate_of IS NULL
+        AND f.created_at <= $6 AND f.created_at >= $7
+        AND similarity(f.content_normalized, $1) >= $8
+        ORDER BY trigram DESC, f.created_at
+        LIMIT $9
+        "#,
+    )
+    .bind(&fact.content_normalized)
+    .bind(fact.about_entity_id)
+    .bind(&fact.owner_type)
+    .bind(fact.owner_id)
+    .bind(fact_id)
+    .bind(fact.created_at)
+    .bind(fact.created_at - Duration::days(RECENT_DAYS))
+    .bind(MIN_TRIGRAM)
+    .bind(MAX_CANDIDATES)
+    .fetch_all(pool)
+    .await?;
+
+    if candidates.is_empty() {
+        return Ok(None);
+    }
+
+    // New facts usually haven't been indexed yet
+    let (embedding, model_id) = match fact_embedding(pool, fact_id).await? {
+        Some(stored) => stored,
+        None => {
+            let vector = embedding_client.embed(&fact.content).await?;
+            (
+                to_pgvector(&vector),
+                embedding_client.model_id().to_string(),
+            )
+        }
+    };
+
+    let ids: Vec<Uuid> = candidates.iter().map(|c| c.id).collect();
+    let similarities: Vec<(Uuid, f64)> = sqlx::query_as(
+        r#"
+        SELECT fact_id, (1 - (embedding <=> $2::vector))::float8
+        FROM fact_embeddings
+        WHERE fact_id = ANY($1) AND model_id = $3
+        "#,
+    )
+    .bind(&ids)
+    .bind(&embedding)
+    .bind(&model_id)
+    .fetch_all(pool)
+    .await?;
+
+    for candidate in candidates {
+        let similarity = similarities
+            .iter()
+            .find(|(id, _)| *id == candidate.id)
+            .map(|(_, s)| *s);
+        let Some(kind) = classify(
+            &fact.content_normalized,
+            &candidate.content_normalized,
+            candidate.trigram,
+            similarity,
+        ) else {
+            continue;
+        };
+
+        match kind {
+            DuplicateKind::Merged => merge_into(pool, &fact, fact_id, candidate.id).await?,
+            DuplicateKind::Flagged => {
+                sqlx::query("UPDATE facts SET duplicate_of = $2 WHERE id = $1")
+                    .bind(fact_id)
+                    .bind(candidate.id)
+                    .execute(pool)
+                    .await?;
+            }
+        }
+
+        return Ok(Some(Duplicate {
+            duplicate_of: candidate.id,
+            kind,
+            trigram_similarity: candidate.trigram,
+            similarity,
+        }));
+    }
+
+    Ok(None)
+}
+
+/// Fold a repeated fact into the earlier one and move it to the trash.
+async fn merge_into(
+    pool: &sqlx::PgPool,
+    fact: &NewFact,
+    fact_id: Uuid,
+    existing_id: Uuid,
+) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    // Offsets point into the repeated text, so they aren't carried over
+    sqlx::query(
+        r#"
+        INSERT INTO entity_mentions (fact_id, entity_id, role, confidence)
+        SELECT $1, entity_id, role, confidence
+        FROM entity_mentions
+        WHERE fact_id = $2
+        ON CONFLICT (fact_id, entity_id, role) DO NOTHING
+        "#,
+    )
+    .bind(existing_id)
+    .bind(fact_id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO fact_tags (fact_id, tag_id, confidence, assigned_by)
+        SELECT $1, tag_id, confidence, assigned_by
+        FROM fact_tags
+        WHERE fact_id = $2
+        ON CONFLICT (fact_id, tag_id) DO NOTHING
+        "#,
+    )
+    .bind(existing_id)
+    .bind(fact_id)
+    .execute(&mut *tx)
+    .await?;
+
+    // Saying something twice doesn't make it less important
+    sqlx::query(
+        "UPDATE facts SET importance = $2, updated_at = NOW() WHERE id = $1 AND importance < $2",
+    )
+    .bind(existing_id)
+    .bind(fact.importance)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        UPDATE facts
+        SET duplicate_of = $2, deleted_at = NOW(), deleted_by = $3
+        WHERE id = $1 AND deleted_at IS NULL
+        "#,
+    )
+    .bind(fact_id)
+    .bind(existing_id)
+    .bind(fact.created_by)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BIRTHDAY: &str = "mom's birthday is june 3";
+
+    #[test]
+    fn repeats_are_merged() {
+        assert_eq!(
+            classify(BIRTHDAY, BIRTHDAY, 1.0, Some(0.99)),
+            Some(DuplicateKind::Merged)
+        );
+        // Ingested twice before the first was indexed
+        assert_eq!(
+            classify(BIRTHDAY, BIRTHDAY, 1.0, None),
+            Some(DuplicateKind::Merged)
+        );
+        assert_eq!(
+            classify(BIRTHDAY, "moms birthday is june 3", 0.92, Some(0.93)),
+            Some(DuplicateKind::Merged)
+        );
+    }
+
+    #[test]
+    fn near_misses_are_flagged_not_merged() {
+        // A digit off: close in text and meaning, but a different date
+        let near_miss = "mom's birthday is june 30";
+        assert_eq!(
+            classify(near_miss, BIRTHDAY, 0.93, None),
+            Some(DuplicateKind::Flagged)
+        );
+        assert_eq!(
+            classify(near_miss, BIRTHDAY, 0.93, Some(0.98)),
+            Some(DuplicateKind::Flagged)
+        );
+        // Not yet embedded, so nothing confirms the match
+        assert_eq!(
+            classify("moms birthday is june 3", BIRTHDAY, 0.95, None),
+            Some(DuplicateKind::Flagged)
+        );
+    }
+
+    #[test]
+    fn close_rewordings_are_flagged() {
+        // Similar text that means something else isn't merged
+        assert_eq!(
+            classify("a", "b", 0.92, Some(0.8)),
+            Some(DuplicateKind::Flagged)
+        );
+        assert_eq!(classify("a", "b", 0.8, None), Some(DuplicateKind::Flagged));
+        // Different words, same meaning
+        assert_eq!(
+            classify("a", "b", 0.6, Some(0.97)),
+            Some(DuplicateKind::Flagged)
+        );
+    }
+
+    #[test]
+    fn distinct_facts_are_not_duplicates() {
+        assert_eq!(classify("a", "b", 0.6, Some(0.9)), None);
+        assert_eq!(classify("a", "b", 0.6, None), None);
+        // Embeddings alone aren't enough
+        assert_eq!(classify("a", "b", 0.3, Some(0.99)), None);
+    }
+}
@@ -0,0 +1,509 @@
+//! Importance decay and the fact review queue.
+//!
+//! Facts that nobody has looked at in a while matter less: the
+//! `importance_decay` Lambda lowers their importance one level per
+//! `DECAY_AFTER_DAYS` without a review. Separately, current facts about
+//! attributes that tend to change (where someone lives or works, their phone
+//! number) come up for review once their review interval has passed since
+//! they were recorded or last reviewed. The user confirms, updates or
+//! archives each one; confirming doubles the interval, spaced-repetition
+//! style, so facts that keep holding come up less and less often.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::access::space_clause;
+use crate::supersession::{supersedes_on, Validity};
+use crate::{Error, Result};
+
+/// Days without a review after which a fact loses one importance level
+pub const DECAY_AFTER_DAYS: i32 = 180;
+
+/// Importance never decays below this
+pub const MIN_IMPORTANCE: i16 = 1;
+
+/// Facts the user marked this important never decay
+pub const PROTECTED_IMPORTANCE: i16 = 5;
+
+/// Longest gap between reviews of a fact
+pub const MAX_INTERVAL_DAYS: i32 = 730;
+
+/// Default page size of the review queue
+pub const DEFAULT_LIMIT: i64 = 20;
+
+/// Largest page size of the review queue
+pub const MAX_LIMIT: i64 = 100;
+
+/// Words that mark a fact as describing an attribute that changes over time
+pub const CHANGING_ATTRIBUTE_TERMS: &[&str] = &[
+    "lives",
+    "living",
+    "moved",
+    "address",
+    "apartment",
+    "works",
+    "working",
+    "job",
+    "employer",
+    "boss",
+    "role",
+    "position",
+    "salary",
+    "phone",
+    "number",
+    "email",
+    "school",
+    "studying",
+    "student",
+    "grade",
+    "dating",
+    "boyfriend",
+    "girlfriend",
+    "partner",
+    "engaged",
+    "drives",
+    "car",
+    "doctor",
+    "dentist",
+    "gym",
+    "favorite",
+    "favourite",
+    "diet",
+    "allergic",
+    "owns",
+    "uses",
+    "subscribed",
+    "plan",
+    "rent",
+    "mortgage",
+];
+
+/// Case-insensitive Postgres regex matching any of `CHANGING_ATTRIBUTE_TERMS`
+/// as a whole word.
+pub fn changing_attribute_pattern() -> String {
+    format!(r"\m({})\M", CHANGING_ATTRIBUTE_TERMS.join("|"))
+}
+
+/// Interval until the next review after a fact is confirmed.
+pub fn next_interval(current_days: i32) -> i32 {
+    current_days.saturating_mul(2).clamp(1, MAX_INTERVAL_DAYS)
+}
+
+/// What the user decided about a fact in the review queue
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReviewAction {
+    /// Still true; review again later
+    Confirm,
+    /// No longer true as stated; replace it with `content` from `valid_from`
+    /// (today if unset)
+    Update {
+        content: String,
+        valid_from: Option<NaiveDate>,
+    },
+    /// No longer true; close it off on `valid_to` (today if unset)
+    Archive { valid_to: Option<NaiveDate> },
+}
+
+impl ReviewAction {
+    /// Parse request values: `action` is `confirm`, `update` (needs
+    /// `content`) or `archive`, and dates are `YYYY-MM-DD`.
+    pub fn parse(
+        action: &str,
+        content: Option<&str>,
+        valid_from: Option<&str>,
+        valid_to: Option<&str>,
+    ) -> Result<Self> {
+        match action {
+            "confirm" => Ok(Self::Confirm),
+            "update" => {
+                let content = content.map(str::trim).unwrap_or_default();
+                if content.is_empty() {
+                    return Err(Error::Validation(
+                        "content is required to update a fact".to_string(),
+                    ));
+                }
+                Ok(Self::Update {
+                    content: content.to_string(),
+                    valid_from: parse_date("valid_from", valid_from)?,
+                })
+            }
+            "archive" => Ok(Self::Archive {
+                valid_to: parse_date("valid_to", valid_to)?,
+            }),
+            other => Err(Error::Validation(format!(
+                "Unknown action '{}' (use confirm, update or archive)",
+                other
+            ))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Confirm => "confirm",
+            Self::Update { .. } => "update",
+            Self::Archive { .. } => "archive",
+        }
+    }
+}
+
+fn parse_date(name: &str, value: Option<&str>) -> Result<Option<NaiveDate>> {
+    value
+        .map(|d| {
+            NaiveDate::parse_from_str(d, "%Y-%m-%d").map_err(|_| {
+                Error::Validation(format!("Invalid {} date format (use YYYY-MM-DD)", name))
+            })
+        })
+        .transpose()
+}
+
+/// A fact due for review
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewFact {
+    pub id: Uuid,
+    pub content: String,
+    pub importance: i16,
+    pub recorded_at: DateTime<Utc>,
+    pub valid_from: Option<NaiveDate>,
+    pub last_reviewed_at: Option<DateTime<Utc>>,
+    pub review_interval_days: i32,
+    pub due_at: DateTime<Utc>,
+    pub entity_id: Option<Uuid>,
+    pub entity_name: Option<String>,
+}
+
+/// Result of reviewing a fact
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReviewOutcome {
+    pub fact_id: Uuid,
+    pub action: &'static str,
+    /// When a confirmed fact comes up again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_review_at: Option<DateTime<Utc>>,
+    /// The fact that replaced an updated one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replaced_by: Option<Uuid>,
+    /// When an updated or archived fact stopped being true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub valid_to: Option<NaiveDate>,
+}
+
+/// Current facts the user can see that are due for review, most overdue
+/// first.
+pub async fn review_queue(
+    pool: &sqlx::PgPool,
+    user_id: Uuid,
+    family_ids: &[Uuid],
+    limit: i64,
+) -> Result<Vec<ReviewFact>> {
+    let facts = sqlx::query_as(&format!(
+        r#"
+        SELECT * FROM (
+            SELECT f.id, f.content, f.importance, f.recorded_at, f.valid_from,
+                   f.last_reviewed_at, f.review_interval_days,
+                   COALESCE(f.last_reviewed_at, f.recorded_at)
+                       + make_interval(days => f.review_interval_days) AS due_at,
+                   e.id AS entity_id, e.name AS entity_name
+            FROM facts f
+            LEFT JOIN entities e ON e.id = f.about_entity_id AND e.deleted_at IS NULL
+            WHERE f.valid_to IS NULL
+            AND f.superseded_by IS NULL
+            AND f.deleted_at IS NULL
+            AND NOT f.is_recurring
+            AND (
+                (f.owner_type = 'user' AND f.owner_id = $1)
+                OR (f.owner_type = 'family' AND f.owner_id = ANY($2) AND {})
+            )
+            AND f.content ~* $3
+        ) due
+        WHERE due_at <= NOW()
+        ORDER BY due_at, importance DESC, id
+        LIMIT $4
+        "#,
+        space_clause("f", 1)
+    ))
+    .bind(user_id)
+    .bind(family_ids)
+    .bind(changing_attribute_pattern())
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(facts)
+}
+
+#[derive(sqlx::FromRow)]
+struct ReviewedFact {
+    valid_from: Option<NaiveDate>,
+    recorded_at: DateTime<Utc>,
+    review_interval_days: i32,
+}
+
+/// Apply the user's decision to a current fact they can see. Returns `None`
+/// if there is no such fact.
+pub async fn review_fact(
+    pool: &sqlx::PgPool,
+    user_id: Uuid,
+    family_ids: &[Uuid],
+    fact_id: Uuid,
+    action: &ReviewAction,
+) -> Result<Option<ReviewOutcome>> {
+    let mut tx = pool.begin().await?;
+
+    let fact: Option<ReviewedFact> = sqlx::query_as(&format!(
+        r#"
+        SELECT valid_from, recorded_at, review_interval_days FROM facts
+        WHERE id = $1
+        AND valid_to IS NULL AND superseded_by IS NULL AND deleted_at IS NULL
+        AND ((owner_type = 'user' AND owner_id = $2)
+             OR (owner_type = 'family' AND owner_id = ANY($3) AND {}))
+        FOR UPDATE
+        "#,
+        space_clause("facts", 2)
+    ))
+    .bind(fact_id)
+    .bind(user_id)
+    .bind(family_ids)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(fact) = fact else {
+        return Ok(None);
+    };
+    let today = Utc::now().date_naive();
+    let old = Validity {
+        valid_from: fact.valid_from,
+        valid_to: None,
+        recorded_on: fact.recorded_at.date_naive(),
+    };
+
+    let outcome = match action {
+        ReviewAction::Confirm => {
+            let next_review_at: DateTime<Utc> = sqlx::query_scalar(
+                r#"
+                UPDATE facts
+                SET last_reviewed_at = NOW(),
+                    review_interval_days = $2,
+                    updated_at = NOW()
+                WHERE id = $1
+                RETURNING NOW() + make_interval(days => review_interval_days)
+                "#,
+            )
+            .bind(fact_id)
+            .bind(next_interval(fact.review_interval_days))
+            .fetch_one(&mut *tx)
+            .await?;
+
+            ReviewOutcome {
+                fact_id,
+                action: action.as_str(),
+                next_review_at: Some(next_review_at),
+                replaced_by: None,
+                valid_to: None,
+            }
+        }
+
+        ReviewAction::Update {
+            content,
+            valid_from,
+        } => {
+            let new = Validity {
+                valid_from: Some(valid_from.unwrap_or(today)),
+                valid_to: None,
+                recorded_on: today,
+            };
+            let Some(valid_to) = supersedes_on(&old, &new) else {
+                return Err(Error::Validation(
+                    "valid_from must not be before the fact started".to_string(),
+                ));
+            };
+
+            let new_id: Uuid = sqlx::query_scalar(
+                r#"
+                INSERT INTO facts (
+                    owner_type, owner_id, created_by, content, source,
+                    importance, confidence, visibility_tier, about_entity_id,
+                    valid_from, last_reviewed_at
+                )
+                SELECT owner_type, owner_id, $2, $3, 'text',
+                       importance, confidence, visibility_tier, about_entity_id,
+                       $4, NOW()
+                FROM facts WHERE id = $1
+                RETURNING id
+                "#,
+            )
+            .bind(fact_id)
+            .bind(user_id)
+            .bind(content)
+            .bind(valid_to)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            // The replacement is about the same people and topics
+            sqlx::query(
+                r#"
+                INSERT INTO entity_mentions (fact_id, entity_id, role, confidence)
+                SELECT $2, entity_id, role, confidence FROM entity_mentions WHERE fact_id = $1
+                ON CONFLICT DO NOTHING
+                "#,
+            )
+            .bind(fact_id)
+            .bind(new_id)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(
+                r#"
+                INSERT INTO fact_tags (fact_id, tag_id, confidence, assigned_by)
+                SELECT $2, tag_id, confidence, assigned_by FROM fact_tags WHERE fact_id = $1
+                ON CONFLICT DO NOTHING
+                "#,
+            )
+            .bind(fact_id)
+            .bind(new_id)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(
+                r#"
+                UPDATE facts
+                SET valid_to = $2, superseded_by = $3, last_reviewed_at = NOW(), updated_at = NOW()
+                WHERE id = $1
+                "#,
+            )
+            .bind(fact_id)
+            .bind(valid_to)
+            .bind(new_id)
+            .execute(&mut *tx)
+            .await?;
+
+            ReviewOutcome {
+                fact_id,
+                action: action.as_str(),
+                next_review_at: None,
+                replaced_by: Some(new_id),
+                valid_to: Some(valid_to),
+            }
+        }
+
+        ReviewAction::Archive { valid_to } => {
+            let valid_to = valid_to.unwrap_or(today);
+            if valid_to < fact.valid_from.unwrap_or(old.recorded_on) {
+                return Err(Error::Validation(
+                    "valid_to must not be before the fact started".to_string(),
+                ));
+            }
+
+            sqlx::query(
+                r#"
+                UPDATE facts
+                SET valid_to = $2, last_reviewed_at = NOW(), updated_at = NOW()
+                WHERE id = $1
+                "#,
+            )
+            .bind(fact_id)
+            .bind(valid_to)
+            .execute(&mut *tx)
+            .await?;
+
+            ReviewOutcome {
+                fact_id,
+                action: action.as_str(),
+                next_review_at: None,
+                replaced_by: None,
+                valid_to: Some(valid_to),
+            }
+        }
+    };
+
+    tx.commit().await?;
+
+    Ok(Some(outcome))
+}
+
+/// Lower by one level the importance of current facts that haven't been
+/// recorded, reviewed or decayed in `DECAY_AFTER_DAYS`. Returns how many
+/// facts decayed.
+pub async fn decay_importance(pool: &sqlx::PgPool, now: DateTime<Utc>) -> Result<u64> {
+    let decayed = sqlx::query(
+        r#"
+        UPDATE facts
+        SET importance = importance - 1, importance_decayed_at = $1
+        WHERE importance > $3 AND importance < $4
+        AND valid_to IS NULL
+        AND superseded_by IS NULL
+        AND deleted_at IS NULL
+        AND NOT is_recurring
+        AND GREATEST(recorded_at, last_reviewed_at, importance_decayed_at)
+            < $1 - make_interval(days => $2)
+        "#,
+    )
+    .bind(now)
+    .bind(DECAY_AFTER_DAYS)
+    .bind(MIN_IMPORTANCE)
+    .bind(PROTECTED_IMPORTANCE)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(decayed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intervals_double_up_to_the_cap() {
+        assert_eq!(next_interval(90), 180);
+        assert_eq!(next_interval(360), 720);
+        assert_eq!(next_interval(720), MAX_INTERVAL_DAYS);
+        assert_eq!(next_interval(i32::MAX), MAX_INTERVAL_DAYS);
+    }
+
+    #[test]
+    fn pattern_matches_whole_words() {
+        let pattern = changing_attribute_pattern();
+        assert!(pattern.starts_with(r"\m(lives|living|"));
+        assert!(pattern.ends_with(r"|mortgage)\M"));
+    }
+
+    #[test]
+    fn parses_actions() {
+        assert_eq!(
+            ReviewAction::parse("confirm", None, None, None).unwrap(),
+            ReviewAction::Confirm
+        );
+        assert_eq!(
+            ReviewAction::parse("update", Some("  Works at Acme "), Some("2026-09-01"), None)
+                .unwrap(),
+            ReviewAction::Update {
+                content: "Works at Acme".to_string(),
+                valid_from: NaiveDate::from_ymd_opt(2026, 9, 1),
+            }
+        );
+        assert_eq!(
+            ReviewAction::parse("archive", None, None, None).unwrap(),
+            ReviewAction::Archive { valid_to: None }
+        );
+    }
+
+    #[test]
+    fn rejects_bad_actions() {
+        assert!(matches!(
+            ReviewAction::parse("snooze", None, None, None),
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            ReviewAction::parse("update", Some("  "), None, None),
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            ReviewAction::parse("archive", None, None, Some("yesterday")),
+            Err(Error::Validation(_))
+        ));
+    }
+}
@@ -0,0 +1,250 @@
+//! Full-text fact search.
+//!
+//! Facts carry a generated `search_vector` (see migration 040). Queries use
+//! `websearch_to_tsquery`, so users can type `"exact phrase"`, `or` and
+//! `-excluded` the way they would in a search engine. Results are ordered by
+//! `ts_rank` and come with a highlighted snippet; no LLM is involved.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::access::visibility_clause;
+use crate::fact_attachments::Attachment;
+use crate::metrics;
+use crate::{Error, Result};
+
+/// Default page size
+pub const DEFAULT_LIMIT: i64 = 20;
+
+/// Largest page size
+pub const MAX_LIMIT: i64 = 100;
+
+/// Most tag or entity filters per query
+pub const MAX_FILTERS: usize = 20;
+
+/// Marks around matched terms in snippets
+pub const HIGHLIGHT_START: &str = "<mark>";
+pub const HIGHLIGHT_STOP: &str = "</mark>";
+
+/// Optional filters; facts must match every one that is set
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchFilters {
+    /// Tag paths; a fact matches if it carries any of them or a descendant
+    pub tags: Vec<String>,
+    /// A fact matches if it is about or mentions any of these entities
+    pub entity_ids: Vec<Uuid>,
+    /// Inclusive range over `valid_from`, falling back to when it was recorded
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl SearchFilters {
+    /// Parse query-string values: comma-separated `tags` and `entity_ids`,
+    /// and `YYYY-MM-DD` dates.
+    pub fn parse(
+        tags: Option<&str>,
+        entity_ids: Option<&str>,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<Self> {
+        let tags: Vec<String> = split_list(tags)
+            .map(|t| t.trim_matches('/').to_lowercase())
+            .filter(|t| !t.is_empty())
+            .collect();
+
+        let entity_ids = split_list(entity_ids)
+            .map(|id| {
+                Uuid::parse_str(id)
+                    .map_err(|_| Error::Validation(format!("Invalid entity ID: {}", id)))
+            })
+            .collect::<Result<Vec<Uuid>>>()?;
+
+        if tags.len() > MAX_FILTERS || entity_ids.len() > MAX_FILTERS {
+            return Err(Error::Validation(format!(
+                "At most {} tags and {} entities can be filtered on",
+                MAX_FILTERS, MAX_FILTERS
+            )));
+        }
+
+        let from = parse_date("from", from)?;
+        let to = parse_date("to", to)?;
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                return Err(Error::Validation("from must not be after to".to_string()));
+            }
+        }
+
+        Ok(Self {
+            tags,
+            entity_ids,
+            from,
+            to,
+        })
+    }
+}
+
+fn split_list(value: Option<&str>) -> impl Iterator<Item = &str> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
+fn parse_date(name: &str, value: Option<&str>) -> Result<Option<NaiveDate>> {
+    value
+        .map(|d| {
+            NaiveDate::parse_from_str(d, "%Y-%m-%d").map_err(|_| {
+                Error::Validation(format!("Invalid {} date format (use YYYY-MM-DD)", name))
+            })
+        })
+        .transpose()
+}
+
+/// A matching fact
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub id: Uuid,
+    pub content: String,
+    /// Content fragments with matched terms wrapped in `<mark>`
+    pub snippet: String,
+    pub rank: f32,
+    pub importance: i16,
+    pub recorded_at: DateTime<Utc>,
+    pub valid_from: Option<NaiveDate>,
+    pub valid_to: Option<NaiveDate>,
+    pub entity_id: Option<Uuid>,
+    pub entity_name: Option<String>,
+    /// Filled in by the API
+    #[sqlx(skip)]
+    pub attachments: Vec<Attachment>,
+}
+
+/// `ts_headline` options for snippets
+fn headline_options() -> String {
+    format!(
+        "StartSel={}, StopSel={}, MaxFragments=2, MaxWords=20, MinWords=5, FragmentDelimiter=\" … \"",
+        HIGHLIGHT_START, HIGHLIGHT_STOP
+    )
+}
+
+/// Facts the user can see matching `query`, best first, plus whether there
+/// are more after this page.
+pub async fn search_facts(
+    pool: &sqlx::PgPool,
+    query: &str,
+    user_id: Uuid,
+    family_ids: &[Uuid],
+    filters: &SearchFilters,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<SearchHit>, bool)> {
+    let sql = format!(
+        r#"
+        WITH q AS (SELECT websearch_to_tsquery('english', $1) AS query)
+        SELECT f.id, f.content,
+               ts_headline('english', f.content, q.query, $2) AS snippet,
+               ts_rank(f.search_vector, q.query) AS rank,
+               f.importance, f.recorded_at, f.valid_from, f.valid_to,
+               e.id AS entity_id, e.name AS entity_name
+        FROM facts f
+        CROSS JOIN q
+        LEFT JOIN entities e ON e.id = f.about_entity_id
+        WHERE f.search_vector @@ q.query
+        AND f.deleted_at IS NULL
+        AND {}
+        AND (cardinality($5::text[]) = 0 OR EXISTS (
+            SELECT 1 FROM fact_tags ft
+            JOIN tags t ON t.id = ft.tag_id
+            WHERE ft.fact_id = f.id
+            AND EXISTS (
+                SELECT 1 FROM UNNEST($5::text[]) AS wanted(path)
+                WHERE t.path = wanted.path OR starts_with(t.path, wanted.path || '/')
+            )
+        ))
+        AND (cardinality($6::uuid[]) = 0
+            OR f.about_entity_id = ANY($6)
+            OR EXISTS (
+                SELECT 1 FROM entity_mentions em
+                WHERE em.fact_id = f.id AND em.entity_id = ANY($6)
+            ))
+        AND ($7::date IS NULL OR COALESCE(f.valid_from, f.recorded_at::date) >= $7)
+        AND ($8::date IS NULL OR COALESCE(f.valid_from, f.recorded_at::date) <= $8)
+        ORDER BY rank DESC, f.recorded_at DESC, f.id
+        LIMIT $9 OFFSET $10
+        "#,
+        visibility_clause("f", 3)
+    );
+    let mut hits: Vec<SearchHit> = metrics::timed_query(
+        "search_facts",
+        sqlx::query_as(&sql)
+            .bind(query)
+            .bind(headline_options())
+            .bind(user_id)
+            .bind(family_ids)
+            .bind(&filters.tags)
+            .bind(&filters.entity_ids)
+            .bind(filters.from)
+            .bind(filters.to)
+            .bind(limit + 1)
+            .bind(offset)
+            .fetch_all(pool),
+    )
+    .await?;
+
+    let has_more = hits.len() as i64 > limit;
+    hits.truncate(limit as usize);
+
+    Ok((hits, has_more))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_filters() {
+        let entity = Uuid::new_v4();
+        let filters = SearchFilters::parse(
+            Some("Finance/Bills/, ,health"),
+            Some(&format!(" {} ", entity)),
+            Some("2026-01-01"),
+            Some("2026-06-30"),
+        )
+        .unwrap();
+
+        assert_eq!(filters.tags, vec!["finance/bills", "health"]);
+        assert_eq!(filters.entity_ids, vec![entity]);
+        assert_eq!(filters.from, NaiveDate::from_ymd_opt(2026, 1, 1));
+        assert_eq!(filters.to, NaiveDate::from_ymd_opt(2026, 6, 30));
+
+        assert_eq!(
+            SearchFilters::parse(None, None, None, None).unwrap(),
+            SearchFilters::default()
+        );
+    }
+
+    #[test]
+    fn rejects_bad_filters() {
+        assert!(matches!(
+            SearchFilters::parse(None, Some("not-a-uuid"), None, None),
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            SearchFilters::parse(None, None, Some("01/02/2026"), None),
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            SearchFilters::parse(None, None, Some("2026-02-01"), Some("2026-01-01")),
+            Err(Error::Validation(_))
+        ));
+
+        let many = vec!["a"; MAX_FILTERS + 1].join(",");
+        assert!(matches!(
+            SearchFilters::parse(Some(&many), None, None, None),
+            Err(Error::Validation(_))
+        ));
+    }
+}
@@ -0,0 +1,335 @@
+//! Joining a family by typing a short code.
+//!
+//! A family admin issues a code in the app and reads it out or texts it; the
+//! person joining enters it on their phone. Codes are six characters, expire
+//! (after an hour by default), stop working after a number of uses and carry
+//! the role new members get. Only a hash of each code is stored, and wrong
+//! guesses are counted per user so short codes can't be brute forced.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::codes::random_code;
+use crate::{Error, Result};
+
+/// How long a code can be redeemed when the request doesn't say.
+pub const DEFAULT_TTL_MINUTES: i32 = 60;
+
+/// Shortest and longest expiry an admin can choose.
+pub const MIN_TTL_MINUTES: i32 = 5;
+pub const MAX_TTL_MINUTES: i32 = 7 * 24 * 60;
+
+/// Uses allowed when the request doesn't say, and the most an admin can allow.
+pub const DEFAULT_MAX_USES: i32 = 1;
+pub const MAX_USES_LIMIT: i32 = 20;
+
+/// Wrong codes a user can enter within [`ATTEMPT_WINDOW_MINUTES`].
+pub const MAX_FAILED_ATTEMPTS: i64 = 10;
+pub const ATTEMPT_WINDOW_MINUTES: i32 = 60;
+
+/// Characters in a join code (no 0/O or 1/I to avoid misreads).
+const CODE_ALPHABET: &[u8; 32] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// Characters in a join code.
+const CODE_LEN: usize = 6;
+
+/// Roles a code can grant (`family_role`).
+const ROLES: [&str; 3] = ["admin", "member", "child"];
+
+/// Codes to try before giving up on finding one that isn't in use.
+const GENERATE_ATTEMPTS: usize = 5;
+
+/// What a new code grants and for how long.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinCodeOptions {
+    pub ttl_minutes: i32,
+    pub max_uses: i32,
+    pub role: String,
+}
+
+impl Default for JoinCodeOptions {
+    fn default() -> Self {
+        Self {
+            ttl_minutes: DEFAULT_TTL_MINUTES,
+            max_uses: DEFAULT_MAX_USES,
+            role: "member".to_string(),
+        }
+    }
+}
+
+impl JoinCodeOptions {
+    /// Options from a request's optional fields, with defaults for the rest.
+    pub fn new(
+        expires_in_minutes: Option<i32>,
+        max_uses: Option<i32>,
+        role: Option<&str>,
+    ) -> Result<Self> {
+        let defaults = Self::default();
+
+        let ttl_minutes = expires_in_minutes.unwrap_or(defaults.ttl_minutes);
+        if !(MIN_TTL_MINUTES..=MAX_TTL_MINUTES).contains(&ttl_minutes) {
+            return Err(Error::Validation(format!(
+                "expires_in_minutes must be between {} and {}",
+                MIN_TTL_MINUTES, MAX_TTL_MINUTES
+            )));
+        }
+
+        let max_uses = max_uses.unwrap_or(defaults.max_uses);
+        if !(1..=MAX_USES_LIMIT).contains(&max_uses) {
+            return Err(Error::Validation(format!(
+                "max_uses must be between 1 and {}",
+                MAX_USES_LIMIT
+            )));
+        }
+
+        let role = match role {
+            Some(role) => role.trim().to_lowercase(),
+            None => defaults.role,
+        };
+        if !ROLES.contains(&role.as_str()) {
+            return Err(Error::Validation(format!(
+                "role must be one of: {}",
+                ROLES.join(", ")
+            )));
+        }
+
+        Ok(Self {
+            ttl_minutes,
+            max_uses,
+            role,
+        })
+    }
+}
+
+/// A newly issued code. The code itself can't be retrieved later.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct JoinCode {
+    pub code: String,
+    pub family_id: Uuid,
+    pub role: String,
+    pub max_uses: i32,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Result of entering a join code.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JoinOutcome {
+    /// The user was added to the family.
+    Joined {
+        family_id: Uuid,
+        member_id: Uuid,
+        role: String,
+    },
+    /// The user was already a member; the code wasn't used up.
+    AlreadyMember { family_id: Uuid },
+    /// Unknown, expired or used up.
+    Invalid,
+    /// Too many wrong codes recently; nothing was checked.
+    TooManyAttempts,
+}
+
+/// Generate a code.
+pub fn generate_join_code() -> String {
+    random_code(CODE_LEN, CODE_ALPHABET)
+}
+
+/// Canonical form of a user-entered code, or `None` if it can't be a valid code.
+///
+/// Case, spaces and dashes are ignored.
+pub fn normalize_join_code(input: &str) -> Option<String> {
+    let code: String = input
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    let valid = code.len() == CODE_LEN && code.bytes().all(|b| CODE_ALPHABET.contains(&b));
+    valid.then_some(code)
+}
+
+/// Issue a code for joining a family. The caller checks the creator is an admin.
+pub async fn create_join_code(
+    pool: &PgPool,
+    family_id: Uuid,
+    created_by: Uuid,
+    options: &JoinCodeOptions,
+) -> Result<JoinCode> {
+    sqlx::query("DELETE FROM family_join_codes WHERE family_id = $1 AND expires_at <= NOW()")
+        .bind(family_id)
+        .execute(pool)
+        .await?;
+
+    // Six characters leave room for collisions with other families' codes
+    for _ in 0..GENERATE_ATTEMPTS {
+        let code = generate_join_code();
+
+        let expires_at: Option<DateTime<Utc>> = sqlx::query_scalar(
+            r#"
+            INSERT INTO family_join_codes (family_id, created_by, code_hash, role, max_uses, expires_at)
+            VALUES ($1, $2, sha256(convert_to($3, 'UTF8')), $4::family_role, $5, NOW() + make_interval(mins => $6))
+            ON CONFLICT (code_hash) DO NOTHING
+            RETURNING expires_at
+            "#,
+        )
+        .bind(family_id)
+        .bind(created_by)
+        .bind(&code)
+        .bind(&options.role)
+        .bind(options.max_uses)
+        .bind(options.ttl_minutes)
+        .fetch_optional(pool)
+        .await?;
+
+        if let Some(expires_at) = expires_at {
+            return Ok(JoinCode {
+                code,
+                family_id,
+                role: options.role.clone(),
+                max_uses: options.max_uses,
+                expires_at,
+            });
+        }
+    }
+
+    Err(Error::Conflict(
+        "Could not generate a unique join code".to_string(),
+    ))
+}
+
+/// Join the family a code belongs to.
+///
+/// Adds the user with the code's role and counts a use. Wrong codes are
+/// recorded against the user, who gets [`JoinOutcome::TooManyAttempts`] after
+/// [`MAX_FAILED_ATTEMPTS`] of them in [`ATTEMPT_WINDOW_MINUTES`].
+pub async fn redeem_join_code(pool: &PgPool, user_id: Uuid, code: &str) -> Result<JoinOutcome> {
+    let failures: i64 = sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*) FROM family_join_attempts
+        WHERE user_id = $1 AND attempted_at > NOW() - make_interval(mins => $2)
+        "#,
+    )
+    .bind(user_id)
+    .bind(ATTEMPT_WINDOW_MINUTES)
+    .fetch_one(pool)
+    .await?;
+
+    if failures >= MAX_FAILED_ATTEMPTS {
+        return Ok(JoinOutcome::TooManyAttempts);
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let join_code: Option<(Uuid, Uuid, String, Option<Uuid>)> = match normalize_join_code(code) {
+        Some(code) => {
+            sqlx::query_as(
+                r#"
+                SELECT id, family_id, role::text, created_by
+                FROM family_join_codes
+                WHERE code_hash = sha256(convert_to($1, 'UTF8'))
+                AND expires_at > NOW()
+                AND use_count < max_uses
+                FOR UPDATE
+                "#,
+            )
+            .bind(&code)
+            .fetch_optional(&mut *tx)
+            .await?
+        }
+        None => None,
+    };
+
+    let Some((code_id, family_id, role, created_by)) = join_code else {
+        sqlx::query(
+            "DELETE FROM family_join_attempts WHERE user_id = $1 AND attempted_at <= NOW() - make_interval(mins => $2)",
+        )
+        .bind(user_id)
+        .bind(ATTEMPT_WINDOW_MINUTES)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("INSERT INTO family_join_attempts (user_id) VALUES ($1)")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        return Ok(JoinOutcome::Invalid);
+    };
+
+    let member_id: Option<Uuid> = sqlx::query_scalar(
+        r#"
+        INSERT INTO family_members (family_id, user_id, role, invited_by)
+        VALUES ($1, $2, $3::family_role, $4)
+        ON CONFLICT (family_id, user_id) DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(family_id)
+    .bind(user_id)
+    .bind(&role)
+    .bind(created_by)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(member_id) = member_id else {
+        return Ok(JoinOutcome::AlreadyMember { family_id });
+    };
+
+    sqlx::query("UPDATE family_join_codes SET use_count = use_count + 1 WHERE id = $1")
+        .bind(code_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(JoinOutcome::Joined {
+        family_id,
+        member_id,
+        role,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_join_code() {
+        let code = generate_join_code();
+        assert_eq!(code.len(), CODE_LEN);
+        assert_eq!(normalize_join_code(&code), Some(code));
+    }
+
+    #[test]
+    fn test_normalize_join_code() {
+        assert_eq!(normalize_join_code("abc-def").as_deref(), Some("ABCDEF"));
+        assert_eq!(normalize_join_code(" AB CD EF ").as_deref(), Some("ABCDEF"));
+        assert_eq!(normalize_join_code("ABCDE"), None);
+        assert_eq!(normalize_join_code("ABCDEFG"), None);
+        // 0, O, 1 and I are never issued
+        assert_eq!(normalize_join_code("ABCDE0"), None);
+        assert_eq!(normalize_join_code("ABCDEI"), None);
+    }
+
+    #[test]
+    fn test_join_code_options() {
+        assert_eq!(
+            JoinCodeOptions::new(None, None, None).unwrap(),
+            JoinCodeOptions::default()
+        );
+
+        let options = JoinCodeOptions::new(Some(24 * 60), Some(4), Some(" Child ")).unwrap();
+        assert_eq!(options.ttl_minutes, 24 * 60);
+        assert_eq!(options.max_uses, 4);
+        assert_eq!(options.role, "child");
+
+        assert!(JoinCodeOptions::new(Some(1), None, None).is_err());
+        assert!(JoinCodeOptions::new(Some(MAX_TTL_MINUTES + 1), None, None).is_err());
+        assert!(JoinCodeOptions::new(None, Some(0), None).is_err());
+        assert!(JoinCodeOptions::new(None, Some(MAX_USES_LIMIT + 1), None).is_err());
+        assert!(JoinCodeOptions::new(None, None, Some("owner")).is_err());
+    }
+}
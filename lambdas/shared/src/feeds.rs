@@ -0,0 +1,311 @@
+//! RSS and Atom feed items.
+//!
+//! Users subscribe to feeds (blogs, newsletters with a feed) in `rss_feeds`.
+//! The `feed_poller` Lambda fetches each enabled feed, parses it here, and
+//! hands items it hasn't seen (by GUID) to the ingestion agent to summarize;
+//! the resulting facts are kept at low importance and tagged `reading/feeds`.
+
+use chrono::{DateTime, Utc};
+use scraper::Html;
+use std::collections::HashSet;
+
+use crate::{Error, Result};
+
+/// Largest feed document downloaded
+pub const MAX_FEED_BYTES: usize = 5 * 1024 * 1024;
+
+/// Most new items summarized per feed on one poll; the rest wait for the
+/// next poll (or, on a feed's first poll, are marked as seen)
+pub const MAX_NEW_ITEMS_PER_POLL: usize = 5;
+
+/// Longest item text handed to the agent, in characters
+pub const MAX_ITEM_CHARS: usize = 4000;
+
+/// Importance of facts summarized from feed items
+pub const FEED_FACT_IMPORTANCE: i16 = 1;
+
+/// System tag applied to facts summarized from feed items
+pub const FEED_TAG_PATH: &str = "reading/feeds";
+
+/// Longest item title kept, in characters
+const MAX_TITLE_CHARS: usize = 500;
+
+/// A parsed feed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feed {
+    pub title: Option<String>,
+    pub items: Vec<FeedItem>,
+}
+
+/// One feed entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedItem {
+    /// The item's `<guid>`/`<id>`, or its link (or title) if it has none
+    pub guid: String,
+    pub title: Option<String>,
+    pub link: Option<String>,
+    pub published: Option<DateTime<Utc>>,
+    /// Plain text of the item's content or summary, truncated
+    pub text: String,
+}
+
+/// Parse an RSS (0.9x, 1.0, 2.0), Atom or JSON feed.
+///
+/// Items with no GUID, link or title can't be told apart between polls and
+/// are skipped. Errors if the document isn't a feed (e.g. an HTML page).
+pub fn parse_feed(body: &[u8]) -> Result<Feed> {
+    // Items without an ID get an empty one here and fall back to their link
+    // below; feed-rs would otherwise invent a random ID on every parse
+    let parser = feed_rs::parser::Builder::new()
+        .id_generator(|_, _, _| String::new())
+        .build();
+    let feed = parser
+        .parse(body)
+        .map_err(|e| Error::Validation(format!("Not a valid feed: {}", e)))?;
+
+    let items = feed
+        .entries
+        .into_iter()
+        .filter_map(|entry| {
+            let title = entry
+                .title
+                .map(|t| truncate(&html_to_text(&t.content), MAX_TITLE_CHARS))
+                .filter(|t| !t.is_empty());
+            let link = entry
+                .links
+                .first()
+                .map(|l| l.href.trim().to_string())
+                .filter(|l| !l.is_empty());
+            let guid = Some(entry.id.trim().to_string())
+                .filter(|id| !id.is_empty())
+                .or_else(|| link.clone())
+                .or_else(|| title.clone())?;
+            let body = entry
+                .content
+                .and_then(|c| c.body)
+                .or_else(|| entry.summary.map(|s| s.content))
+                .unwrap_or_default();
+
+            Some(FeedItem {
+                guid,
+                title,
+                link,
+                published: entry.published.or(entry.updated),
+                text: truncate(&html_to_text(&body), MAX_ITEM_CHARS),
+            })
+        })
+        .collect();
+
+    Ok(Feed {
+        title: feed
+            .title
+            .map(|t| html_to_text(&t.content))
+            .filter(|t| !t.is_empty()),
+        items,
+    })
+}
+
+/// Items whose GUID isn't in `seen`, newest first. Items without a date keep
+/// their feed order after the dated ones; repeated GUIDs are dropped.
+pub fn unseen_items(items: Vec<FeedItem>, seen: &HashSet<String>) -> Vec<FeedItem> {
+    let mut returned = HashSet::new();
+    let mut unseen: Vec<FeedItem> = items
+        .into_iter()
+        .filter(|item| !seen.contains(&item.guid) && returned.insert(item.guid.clone()))
+        .collect();
+
+    // Stable, so undated items and ties keep the feed's order
+    unseen.sort_by(|a, b| match (a.published, b.published) {
+        (Some(a), Some(b)) => b.cmp(&a),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+    unseen
+}
+
+/// Message handed to the ingestion agent for a new feed item.
+pub fn item_message(feed_name: &str, item: &FeedItem) -> String {
+    let mut message = format!(
+        "New item from the feed \"{}\": \"{}\"",
+        feed_name,
+        item.title.as_deref().unwrap_or("(untitled)")
+    );
+    if let Some(link) = &item.link {
+        message.push_str(&format!(" ({})", link));
+    }
+    if let Some(published) = item.published {
+        message.push_str(&format!(", published {}", published.format("%Y-%m-%d")));
+    }
+    message.push_str(
+        ". Summarize what it's about in one or two facts worth remembering as something I read.",
+    );
+
+    if !item.text.is_empty() {
+        message.push_str("\n\nItem text:\n");
+        message.push_str(&item.text);
+    }
+
+    message
+}
+
+/// Text content of an HTML fragment, whitespace collapsed.
+pub fn html_to_text(html: &str) -> String {
+    let fragment = Html::parse_fragment(html);
+    let text = fragment.root_element().text().collect::<Vec<_>>().join(" ");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    text.chars().take(max_chars).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/">
+          <channel>
+            <title>Bread Blog</title>
+            <link>https://bread.example.com/</link>
+            <item>
+              <title>Sourdough basics</title>
+              <link>https://bread.example.com/sourdough</link>
+              <guid isPermaLink="false">post-42</guid>
+              <pubDate>Tue, 02 Jul 2024 09:00:00 GMT</pubDate>
+              <description>Short summary.</description>
+              <content:encoded><![CDATA[<p>Feed your <b>starter</b> daily.</p><p>Keep it warm.</p>]]></content:encoded>
+            </item>
+            <item>
+              <title>No guid here</title>
+              <link>https://bread.example.com/no-guid</link>
+              <description>&lt;p&gt;Rye &amp;amp; spelt.&lt;/p&gt;</description>
+            </item>
+            <item>
+              <description>Neither link nor title.</description>
+            </item>
+          </channel>
+        </rss>"#;
+
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+        <feed xmlns="http://www.w3.org/2005/Atom">
+          <title>Weekly Letter</title>
+          <id>urn:uuid:60a76c80-d399-11d9-b93C-0003939e0af6</id>
+          <updated>2024-07-05T12:00:00Z</updated>
+          <entry>
+            <title>Issue 12</title>
+            <link href="https://letter.example.com/12"/>
+            <id>urn:uuid:1225c695-cfb8-4ebb-aaaa-80da344efa6a</id>
+            <updated>2024-07-05T12:00:00Z</updated>
+            <summary>This week: tide pools.</summary>
+          </entry>
+        </feed>"#;
+
+    fn item(guid: &str, published: Option<DateTime<Utc>>) -> FeedItem {
+        FeedItem {
+            guid: guid.to_string(),
+            title: Some(guid.to_string()),
+            link: None,
+            published,
+            text: String::new(),
+        }
+    }
+
+    #[test]
+    fn parses_rss_items() {
+        let feed = parse_feed(RSS.as_bytes()).unwrap();
+
+        assert_eq!(feed.title.as_deref(), Some("Bread Blog"));
+        assert_eq!(feed.items.len(), 2);
+
+        let first = &feed.items[0];
+        assert_eq!(first.guid, "post-42");
+        assert_eq!(first.title.as_deref(), Some("Sourdough basics"));
+        assert_eq!(
+            first.link.as_deref(),
+            Some("https://bread.example.com/sourdough")
+        );
+        assert_eq!(
+            first.published,
+            Some(Utc.with_ymd_and_hms(2024, 7, 2, 9, 0, 0).unwrap())
+        );
+        assert_eq!(first.text, "Feed your starter daily. Keep it warm.");
+
+        // Without a guid the link identifies the item
+        let second = &feed.items[1];
+        assert_eq!(second.guid, "https://bread.example.com/no-guid");
+        assert_eq!(second.text, "Rye & spelt.");
+    }
+
+    #[test]
+    fn parses_atom_entries() {
+        let feed = parse_feed(ATOM.as_bytes()).unwrap();
+
+        assert_eq!(feed.title.as_deref(), Some("Weekly Letter"));
+        assert_eq!(feed.items.len(), 1);
+        assert_eq!(
+            feed.items[0].guid,
+            "urn:uuid:1225c695-cfb8-4ebb-aaaa-80da344efa6a"
+        );
+        assert_eq!(
+            feed.items[0].published,
+            Some(Utc.with_ymd_and_hms(2024, 7, 5, 12, 0, 0).unwrap())
+        );
+        assert_eq!(feed.items[0].text, "This week: tide pools.");
+    }
+
+    #[test]
+    fn rejects_non_feeds() {
+        assert!(parse_feed(b"<html><body>Sign in</body></html>").is_err());
+        assert!(parse_feed(b"").is_err());
+    }
+
+    #[test]
+    fn orders_unseen_items_newest_first() {
+        let day = |d| Some(Utc.with_ymd_and_hms(2024, 7, d, 0, 0, 0).unwrap());
+        let items = vec![
+            item("old", day(1)),
+            item("undated", None),
+            item("seen", day(9)),
+            item("new", day(3)),
+            item("new", day(3)),
+        ];
+        let seen = HashSet::from(["seen".to_string()]);
+
+        let guids: Vec<String> = unseen_items(items, &seen)
+            .into_iter()
+            .map(|i| i.guid)
+            .collect();
+
+        assert_eq!(guids, vec!["new", "old", "undated"]);
+    }
+
+    #[test]
+    fn builds_agent_message() {
+        let feed = parse_feed(RSS.as_bytes()).unwrap();
+
+        assert_eq!(
+            item_message("Bread Blog", &feed.items[0]),
+            "New item from the feed \"Bread Blog\": \"Sourdough basics\" \
+             (https://bread.example.com/sourdough), published 2024-07-02. \
+             Summarize what it's about in one or two facts worth remembering as something I read.\
+             \n\nItem text:\nFeed your starter daily. Keep it warm."
+        );
+        assert_eq!(
+            item_message("Letter", &item("x", None)),
+            "New item from the feed \"Letter\": \"x\". \
+             Summarize what it's about in one or two facts worth remembering as something I read."
+        );
+    }
+
+    #[test]
+    fn strips_html() {
+        assert_eq!(
+            html_to_text("<p>One</p>\n<p>Two &amp; <em>three</em></p>"),
+            "One Two & three"
+        );
+        assert_eq!(html_to_text("plain text"), "plain text");
+    }
+}
@@ -0,0 +1,523 @@
+//! Map images from Amazon Location Service.
+//!
+//! [`static_map`] renders points (an entity's locations) as a static map
+//! image with the Maps API's `GetStaticMap`, so chat surfaces can show a map
+//! card. The Maps API authorizes requests with SigV4, so the image URL is
+//! presigned with the Lambda's credentials ([`presign`]) and can be used
+//! directly as an image source until it expires.
+//!
+//! Entity locations keep the geocoder's normalized address
+//! ([`NormalizedAddress`]) and a canonical `place_id` derived from it by a
+//! trigger (migration 069), so the same address written two ways is one
+//! place. Stores check [`find_duplicate_location`] before adding a location
+//! an entity already has under another label.
+//!
+//! Nearby search can cluster results into a grid for maps zoomed out too far
+//! to show every pin ([`cluster_cell_degrees`]).
+
+use aws_credential_types::provider::ProvideCredentials;
+use aws_sigv4::http_request::{
+    sign, SignableBody, SignableRequest, SignatureLocation, SigningSettings,
+};
+use aws_sigv4::sign::v4;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::time::{Duration, SystemTime};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{Error, Result};
+
+/// SigV4 service name of the Amazon Location Maps API
+const MAPS_SERVICE: &str = "geo-maps";
+
+/// Map style used when `MAP_STYLE` is unset
+pub const DEFAULT_MAP_STYLE: &str = "Standard";
+
+/// Image size used when a request doesn't give one, in pixels
+pub const DEFAULT_MAP_WIDTH: u32 = 600;
+pub const DEFAULT_MAP_HEIGHT: u32 = 400;
+
+/// Smallest and largest image sides the Maps API renders, in pixels
+pub const MIN_MAP_SIDE: u32 = 64;
+pub const MAX_MAP_SIDE: u32 = 1400;
+
+/// Zoom for a map of a single point: a few streets around it
+pub const SINGLE_POINT_ZOOM: f64 = 15.0;
+
+/// Pixels kept clear around the points on a map of several
+const MAP_PADDING: u32 = 50;
+
+/// Most points marked on one map; the rest are left off
+pub const MAX_MAP_POINTS: usize = 20;
+
+/// Longest marker label
+const MAX_LABEL_CHARS: usize = 40;
+
+/// How long a presigned map URL is valid
+pub const MAP_URL_TTL_SECS: u64 = 60 * 60;
+
+/// Current locations of one entity closer than this are the same place
+pub const DUPLICATE_LOCATION_METERS: f64 = 25.0;
+
+/// Grid cells across a 256px map tile when clustering for a zoom level, so
+/// clusters are about 32px apart
+const CLUSTER_CELLS_PER_TILE: f64 = 8.0;
+
+/// Grid cells across the search diameter when clustering without a zoom level
+const CLUSTER_CELLS_ACROSS: f64 = 10.0;
+
+/// Highest map zoom level clusters are sized for
+pub const MAX_CLUSTER_ZOOM: f64 = 22.0;
+
+/// Smallest grid cell, about a meter
+const MIN_CLUSTER_CELL_DEGREES: f64 = 0.00001;
+
+/// Approximate length of a degree of latitude
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// Address components from the geocoder, stored in
+/// `entity_locations.address_normalized`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NormalizedAddress {
+    /// Full formatted address, e.g. "123 Main St, Springfield, IL, 62701, USA"
+    pub label: Option<String>,
+    pub address_number: Option<String>,
+    pub street: Option<String>,
+    pub municipality: Option<String>,
+    pub region: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+}
+
+/// The address a location's `place_id` is derived from: the geocoded label
+/// if there is one, else the address as entered. Matches the trigger.
+pub fn place_address<'a>(normalized: Option<&'a NormalizedAddress>, raw: &'a str) -> &'a str {
+    normalized
+        .and_then(|n| n.label.as_deref())
+        .filter(|label| !label.trim().is_empty())
+        .unwrap_or(raw)
+}
+
+/// Side of the grid cells nearby results are clustered into, in degrees. For
+/// a map zoom level a cell is an eighth of a tile; otherwise the search
+/// diameter is split into ten.
+pub fn cluster_cell_degrees(zoom: Option<f64>, radius_meters: f64) -> f64 {
+    let degrees = match zoom {
+        Some(zoom) => {
+            360.0 / (2f64.powf(zoom.clamp(0.0, MAX_CLUSTER_ZOOM)) * CLUSTER_CELLS_PER_TILE)
+        }
+        None => 2.0 * radius_meters / CLUSTER_CELLS_ACROSS / METERS_PER_DEGREE,
+    };
+    degrees.max(MIN_CLUSTER_CELL_DEGREES)
+}
+
+/// A current location an entity already has at the same place.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DuplicateLocation {
+    pub id: Uuid,
+    pub label: String,
+}
+
+/// The entity's current location, under a label other than `label`, at the
+/// same place as a new one: the same `place_id` (the geocoder's, or derived
+/// from `address`), or within [`DUPLICATE_LOCATION_METERS`] of `position`
+/// (latitude, longitude).
+pub async fn find_duplicate_location(
+    pool: &PgPool,
+    entity_id: Uuid,
+    label: &str,
+    place_id: Option<&str>,
+    address: &str,
+    position: Option<(f64, f64)>,
+) -> Result<Option<DuplicateLocation>> {
+    let (latitude, longitude) = position.unzip();
+
+    let duplicate = sqlx::query_as(
+        r#"
+        SELECT id, label
+        FROM entity_locations
+        WHERE entity_id = $1
+          AND valid_to IS NULL
+          AND label <> $2
+          AND (place_id = COALESCE($3, 'addr:' || normalize_address($4))
+               OR ($5::float8 IS NOT NULL
+                   AND ST_DWithin(location, ST_SetSRID(ST_MakePoint($6, $5), 4326)::geography, $7)))
+        ORDER BY updated_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(entity_id)
+    .bind(label)
+    .bind(place_id)
+    .bind(address)
+    .bind(latitude)
+    .bind(longitude)
+    .bind(DUPLICATE_LOCATION_METERS)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(duplicate)
+}
+
+/// A point to mark on a map.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapPoint {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub label: Option<String>,
+}
+
+/// How a static map is rendered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapOptions {
+    /// Maps API style (`Standard`, `Monochrome`, `Hybrid` or `Satellite`)
+    pub style: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for MapOptions {
+    fn default() -> Self {
+        Self {
+            style: DEFAULT_MAP_STYLE.to_string(),
+            width: DEFAULT_MAP_WIDTH,
+            height: DEFAULT_MAP_HEIGHT,
+        }
+    }
+}
+
+impl MapOptions {
+    /// Default options with the style from `MAP_STYLE`.
+    pub fn from_env() -> Self {
+        Self {
+            style: std::env::var("MAP_STYLE").unwrap_or_else(|_| DEFAULT_MAP_STYLE.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// The same options at another size, clamped to what the Maps API renders.
+    pub fn with_size(&self, width: Option<u32>, height: Option<u32>) -> Self {
+        Self {
+            style: self.style.clone(),
+            width: width
+                .unwrap_or(self.width)
+                .clamp(MIN_MAP_SIDE, MAX_MAP_SIDE),
+            height: height
+                .unwrap_or(self.height)
+                .clamp(MIN_MAP_SIDE, MAX_MAP_SIDE),
+        }
+    }
+}
+
+/// A presigned static map image.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct StaticMap {
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+    /// Points marked on the map
+    pub points: usize,
+    /// Seconds until `url` stops working
+    pub expires_in: u64,
+}
+
+/// Render `points` as a static map and presign its URL (see the module docs).
+pub async fn static_map(
+    config: &aws_config::SdkConfig,
+    points: &[MapPoint],
+    options: &MapOptions,
+) -> Result<StaticMap> {
+    let region = config
+        .region()
+        .ok_or_else(|| Error::Aws("No AWS region configured".to_string()))?
+        .to_string();
+
+    let url = static_map_request(&region, points, options)?;
+    let url = presign(
+        config,
+        url,
+        MAPS_SERVICE,
+        Duration::from_secs(MAP_URL_TTL_SECS),
+    )
+    .await?;
+
+    let options = options.with_size(None, None);
+    Ok(StaticMap {
+        url,
+        width: options.width,
+        height: options.height,
+        points: points.len().min(MAX_MAP_POINTS),
+        expires_in: MAP_URL_TTL_SECS,
+    })
+}
+
+/// Unsigned `GetStaticMap` URL marking `points`. One point is centered at
+/// [`SINGLE_POINT_ZOOM`]; several are fitted into the image.
+pub fn static_map_request(
+    region: &str,
+    points: &[MapPoint],
+    options: &MapOptions,
+) -> Result<url::Url> {
+    let points = &points[..points.len().min(MAX_MAP_POINTS)];
+    if points.is_empty() {
+        return Err(Error::Validation("No locations to map".to_string()));
+    }
+    if let Some(point) = points
+        .iter()
+        .find(|p| !(-90.0..=90.0).contains(&p.latitude) || !(-180.0..=180.0).contains(&p.longitude))
+    {
+        return Err(Error::Validation(format!(
+            "Invalid coordinates: {}, {}",
+            point.latitude, point.longitude
+        )));
+    }
+
+    let options = options.with_size(None, None);
+    let mut params = vec![
+        ("style", options.style.clone()),
+        ("width", options.width.to_string()),
+        ("height", options.height.to_string()),
+    ];
+
+    let first = &points[0];
+    let same_place = points.iter().all(|p| position(p) == position(first));
+    if same_place {
+        params.push(("center", position(first)));
+        params.push(("zoom", SINGLE_POINT_ZOOM.to_string()));
+    } else {
+        let positions: Vec<String> = points.iter().map(position).collect();
+        params.push(("bounded-positions", positions.join(",")));
+        params.push(("padding", MAP_PADDING.to_string()));
+    }
+
+    let markers: Vec<String> = points
+        .iter()
+        .map(|p| match p.label.as_deref().map(clean_label) {
+            Some(label) if !label.is_empty() => format!("point:{};label={}", position(p), label),
+            _ => format!("point:{}", position(p)),
+        })
+        .collect();
+    params.push(("compact-overlay", markers.join("|")));
+
+    let mut url = url::Url::parse(&format!(
+        "https://maps.geo.{}.amazonaws.com/v2/static/map",
+        region
+    ))
+    .map_err(|e| Error::Config(format!("Invalid map URL: {}", e)))?;
+    url.set_query(Some(&encode_query(
+        params.iter().map(|(name, value)| (*name, value.as_str())),
+    )));
+
+    Ok(url)
+}
+
+/// Presign a GET of `url` for `service` with SigV4 query parameters, valid
+/// for `expires_in`.
+pub async fn presign(
+    config: &aws_config::SdkConfig,
+    mut url: url::Url,
+    service: &str,
+    expires_in: Duration,
+) -> Result<String> {
+    let credentials = config
+        .credentials_provider()
+        .ok_or_else(|| Error::Aws("No AWS credentials provider configured".to_string()))?
+        .provide_credentials()
+        .await
+        .map_err(|e| Error::Aws(format!("Failed to load AWS credentials: {}", e)))?;
+    let region = config
+        .region()
+        .ok_or_else(|| Error::Aws("No AWS region configured".to_string()))?
+        .to_string();
+
+    let identity = credentials.into();
+    let mut signing_settings = SigningSettings::default();
+    signing_settings.expires_in = Some(expires_in);
+    signing_settings.signature_location = SignatureLocation::QueryParams;
+
+    let signing_params = v4::SigningParams::builder()
+        .identity(&identity)
+        .region(&region)
+        .name(service)
+        .time(SystemTime::now())
+        .settings(signing_settings)
+        .build()
+        .map_err(|e| Error::Aws(format!("Failed to build signing params: {}", e)))?;
+
+    let request = SignableRequest::new(
+        "GET",
+        url.as_str(),
+        std::iter::empty(),
+        SignableBody::Bytes(&[]),
+    )
+    .map_err(|e| Error::Aws(format!("Failed to sign {} request: {}", service, e)))?;
+    let (instructions, _signature) = sign(request, &signing_params.into())
+        .map_err(|e| Error::Aws(format!("Failed to sign {} request: {}", service, e)))?
+        .into_parts();
+
+    let signature = encode_query(instructions.params().iter().map(|(n, v)| (*n, &**v)));
+    let query = match url.query() {
+        Some(query) if !query.is_empty() => format!("{}&{}", query, signature),
+        _ => signature,
+    };
+    url.set_query(Some(&query));
+
+    Ok(url.to_string())
+}
+
+/// `longitude,latitude`, the order the Maps API takes positions in.
+fn position(point: &MapPoint) -> String {
+    format!("{:.6},{:.6}", point.longitude, point.latitude)
+}
+
+/// A marker label without the compact overlay's separators.
+fn clean_label(label: &str) -> String {
+    label
+        .chars()
+        .filter(|c| !matches!(c, ';' | '|' | '=') && !c.is_control())
+        .take(MAX_LABEL_CHARS)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Query string with spaces as `%20` rather than `+`, which SigV4 would
+/// canonicalize differently from the service.
+fn encode_query<'a>(params: impl Iterator<Item = (&'a str, &'a str)>) -> String {
+    params
+        .map(|(name, value)| format!("{}={}", encode(name), encode(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn encode(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes())
+        .collect::<String>()
+        .replace('+', "%20")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(latitude: f64, longitude: f64, label: Option<&str>) -> MapPoint {
+        MapPoint {
+            latitude,
+            longitude,
+            label: label.map(str::to_string),
+        }
+    }
+
+    fn param(url: &url::Url, name: &str) -> Option<String> {
+        url.query_pairs()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.into_owned())
+    }
+
+    #[test]
+    fn single_point_is_centered() {
+        let url = static_map_request(
+            "us-east-1",
+            &[point(39.0968, -120.0324, Some("Lake Tahoe"))],
+            &MapOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(url.host_str(), Some("maps.geo.us-east-1.amazonaws.com"));
+        assert_eq!(url.path(), "/v2/static/map");
+        assert_eq!(
+            param(&url, "center").as_deref(),
+            Some("-120.032400,39.096800")
+        );
+        assert_eq!(param(&url, "zoom").as_deref(), Some("15"));
+        assert_eq!(param(&url, "bounded-positions"), None);
+        assert_eq!(
+            param(&url, "compact-overlay").as_deref(),
+            Some("point:-120.032400,39.096800;label=Lake Tahoe")
+        );
+        assert!(url.query().unwrap().contains("Lake%20Tahoe"));
+    }
+
+    #[test]
+    fn several_points_are_fitted() {
+        let url = static_map_request(
+            "us-east-1",
+            &[point(40.0, -74.0, Some("Home")), point(40.1, -74.1, None)],
+            &MapOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(param(&url, "center"), None);
+        assert_eq!(
+            param(&url, "bounded-positions").as_deref(),
+            Some("-74.000000,40.000000,-74.100000,40.100000")
+        );
+        assert_eq!(
+            param(&url, "compact-overlay").as_deref(),
+            Some("point:-74.000000,40.000000;label=Home|point:-74.100000,40.100000")
+        );
+    }
+
+    #[test]
+    fn rejects_nothing_to_map_and_bad_coordinates() {
+        let options = MapOptions::default();
+        assert!(matches!(
+            static_map_request("us-east-1", &[], &options),
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            static_map_request("us-east-1", &[point(91.0, 0.0, None)], &options),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn size_is_clamped() {
+        let options = MapOptions::default().with_size(Some(10), Some(5000));
+        assert_eq!(
+            (options.width, options.height),
+            (MIN_MAP_SIDE, MAX_MAP_SIDE)
+        );
+        let options = MapOptions::default().with_size(None, Some(300));
+        assert_eq!((options.width, options.height), (DEFAULT_MAP_WIDTH, 300));
+    }
+
+    #[test]
+    fn place_address_prefers_geocoded_label() {
+        let geocoded = NormalizedAddress {
+            label: Some("123 Main St, Springfield, IL, 62701, USA".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            place_address(Some(&geocoded), "123 main street"),
+            "123 Main St, Springfield, IL, 62701, USA"
+        );
+        assert_eq!(
+            place_address(Some(&NormalizedAddress::default()), "123 main street"),
+            "123 main street"
+        );
+        assert_eq!(place_address(None, "123 main street"), "123 main street");
+    }
+
+    #[test]
+    fn cluster_cells_follow_zoom_or_radius() {
+        // The world is one tile at zoom 0 and two at zoom 1
+        assert_eq!(cluster_cell_degrees(Some(0.0), 1000.0), 45.0);
+        assert_eq!(cluster_cell_degrees(Some(1.0), 1000.0), 22.5);
+        assert_eq!(
+            cluster_cell_degrees(Some(40.0), 1000.0),
+            cluster_cell_degrees(Some(MAX_CLUSTER_ZOOM), 1000.0)
+        );
+
+        let cell = cluster_cell_degrees(None, 5000.0);
+        assert!((cell * METERS_PER_DEGREE - 1000.0).abs() < 1e-6);
+        assert_eq!(cluster_cell_degrees(None, 0.0), MIN_CLUSTER_CELL_DEGREES);
+    }
+
+    #[test]
+    fn labels_lose_overlay_separators() {
+        assert_eq!(clean_label(" Mom's; house|=x "), "Mom's housex");
+        assert_eq!(clean_label(&"a".repeat(100)).len(), MAX_LABEL_CHARS);
+    }
+}
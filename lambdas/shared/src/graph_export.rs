@@ -0,0 +1,646 @@
+//! Exporting the knowledge graph for external tools.
+//!
+//! `GET /export/graph` renders the entities a user can see and the
+//! relationships between them as GraphML (Gephi, yEd, NetworkX), a Cypher
+//! script or Neo4j bulk-import CSV, and their facts as schema.org JSON-LD.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::access::space_clause;
+use crate::Result;
+
+/// Output format for a graph export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// GraphML document with entities as nodes and relationships as edges
+    GraphMl,
+    /// Cypher script that recreates the graph in Neo4j
+    Cypher,
+    /// `neo4j-admin import` node file
+    Neo4jNodes,
+    /// `neo4j-admin import` relationship file
+    Neo4jRelationships,
+    /// Facts (with the entities they're about) as schema.org JSON-LD
+    JsonLd,
+}
+
+impl ExportFormat {
+    /// Parse the `format` query parameter.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "graphml" => Some(ExportFormat::GraphMl),
+            "cypher" => Some(ExportFormat::Cypher),
+            "neo4j-nodes" => Some(ExportFormat::Neo4jNodes),
+            "neo4j-relationships" => Some(ExportFormat::Neo4jRelationships),
+            "jsonld" | "json-ld" => Some(ExportFormat::JsonLd),
+            _ => None,
+        }
+    }
+
+    /// Accepted `format` values, for error messages.
+    pub fn names() -> &'static str {
+        "graphml, cypher, neo4j-nodes, neo4j-relationships, jsonld"
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::GraphMl => "application/graphml+xml; charset=utf-8",
+            ExportFormat::Cypher => "text/plain; charset=utf-8",
+            ExportFormat::Neo4jNodes | ExportFormat::Neo4jRelationships => {
+                "text/csv; charset=utf-8"
+            }
+            ExportFormat::JsonLd => "application/ld+json",
+        }
+    }
+
+    /// Download file name (`Content-Disposition`).
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ExportFormat::GraphMl => "second-brain-graph.graphml",
+            ExportFormat::Cypher => "second-brain-graph.cypher",
+            ExportFormat::Neo4jNodes => "second-brain-nodes.csv",
+            ExportFormat::Neo4jRelationships => "second-brain-relationships.csv",
+            ExportFormat::JsonLd => "second-brain-facts.jsonld",
+        }
+    }
+
+    /// Whether the export needs facts rather than relationships.
+    pub fn includes_facts(&self) -> bool {
+        matches!(self, ExportFormat::JsonLd)
+    }
+}
+
+/// Entity (graph node)
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ExportEntity {
+    pub id: Uuid,
+    pub entity_type: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub aliases: Vec<String>,
+    pub fact_count: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Relationship between two exported entities (graph edge)
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ExportRelationship {
+    pub id: Uuid,
+    pub source_entity_id: Uuid,
+    pub target_entity_id: Uuid,
+    pub relationship_type: String,
+    pub valid_from: Option<NaiveDate>,
+    pub valid_to: Option<NaiveDate>,
+}
+
+/// Current fact
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ExportFact {
+    pub id: Uuid,
+    pub content: String,
+    pub about_entity_id: Option<Uuid>,
+    pub mentioned_entity_ids: Vec<Uuid>,
+    pub valid_from: Option<NaiveDate>,
+    pub valid_to: Option<NaiveDate>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Entities owned by the user or one of their families.
+pub async fn fetch_entities(
+    pool: &PgPool,
+    user_id: Uuid,
+    family_ids: &[Uuid],
+) -> Result<Vec<ExportEntity>> {
+    let entities = sqlx::query_as::<_, ExportEntity>(&format!(
+        r#"
+        SELECT e.id, e.entity_type::text AS entity_type, e.name, e.description, e.aliases,
+               (SELECT COUNT(*) FROM facts f
+                WHERE f.about_entity_id = e.id AND f.deleted_at IS NULL) AS fact_count,
+               e.created_at
+        FROM entities e
+        WHERE ((e.owner_type = 'user' AND e.owner_id = $1)
+               OR (e.owner_type = 'family' AND e.owner_id = ANY($2) AND {}))
+        AND e.deleted_at IS NULL
+        ORDER BY e.name, e.id
+        "#,
+        space_clause("e", 1)
+    ))
+    .bind(user_id)
+    .bind(family_ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entities)
+}
+
+/// Relationships whose ends are both visible to the user, including past ones.
+pub async fn fetch_relationships(
+    pool: &PgPool,
+    user_id: Uuid,
+    family_ids: &[Uuid],
+) -> Result<Vec<ExportRelationship>> {
+    let relationships = sqlx::query_as::<_, ExportRelationship>(&format!(
+        r#"
+        SELECT er.id, er.source_entity_id, er.target_entity_id, er.relationship_type,
+               er.valid_from, er.valid_to
+        FROM entity_relationships er
+        JOIN entities s ON s.id = er.source_entity_id
+        JOIN entities t ON t.id = er.target_entity_id
+        WHERE ((s.owner_type = 'user' AND s.owner_id = $1)
+               OR (s.owner_type = 'family' AND s.owner_id = ANY($2) AND {}))
+        AND ((t.owner_type = 'user' AND t.owner_id = $1)
+             OR (t.owner_type = 'family' AND t.owner_id = ANY($2) AND {}))
+        AND s.deleted_at IS NULL AND t.deleted_at IS NULL
+        ORDER BY er.created_at, er.id
+        "#,
+        space_clause("s", 1),
+        space_clause("t", 1),
+    ))
+    .bind(user_id)
+    .bind(family_ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(relationships)
+}
+
+/// Current (non-superseded) facts owned by the user or one of their families.
+pub async fn fetch_facts(
+    pool: &PgPool,
+    user_id: Uuid,
+    family_ids: &[Uuid],
+) -> Result<Vec<ExportFact>> {
+    let facts = sqlx::query_as::<_, ExportFact>(&format!(
+        r#"
+        SELECT f.id, f.content, f.about_entity_id,
+               COALESCE(ARRAY(
+                   SELECT DISTINCT em.entity_id FROM entity_mentions em WHERE em.fact_id = f.id
+               ), '{{}}') AS mentioned_entity_ids,
+               f.valid_from, f.valid_to, f.recorded_at
+        FROM facts f
+        WHERE ((f.owner_type = 'user' AND f.owner_id = $1)
+               OR (f.owner_type = 'family' AND f.owner_id = ANY($2) AND {}))
+        AND f.superseded_by IS NULL
+        AND f.deleted_at IS NULL
+        ORDER BY f.recorded_at, f.id
+        "#,
+        space_clause("f", 1)
+    ))
+    .bind(user_id)
+    .bind(family_ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(facts)
+}
+
+/// Escape text for XML content and attribute values.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than tab/newline aren't allowed in XML 1.0
+            c if c.is_control() && !matches!(c, '\t' | '\n' | '\r') => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// GraphML document of the entity graph.
+///
+/// Aliases are joined with `; ` since GraphML has no list type.
+pub fn render_graphml(entities: &[ExportEntity], relationships: &[ExportRelationship]) -> String {
+    let mut out = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:schemaLocation="http://graphml.graphdrawing.org/xmlns http://graphml.graphdrawing.org/xmlns/1.0/graphml.xsd">
+  <key id="name" for="node" attr.name="name" attr.type="string"/>
+  <key id="entity_type" for="node" attr.name="entity_type" attr.type="string"/>
+  <key id="description" for="node" attr.name="description" attr.type="string"/>
+  <key id="aliases" for="node" attr.name="aliases" attr.type="string"/>
+  <key id="fact_count" for="node" attr.name="fact_count" attr.type="long"/>
+  <key id="relationship_type" for="edge" attr.name="relationship_type" attr.type="string"/>
+  <key id="valid_from" for="edge" attr.name="valid_from" attr.type="string"/>
+  <key id="valid_to" for="edge" attr.name="valid_to" attr.type="string"/>
+  <graph id="second-brain" edgedefault="directed">
+"#,
+    );
+
+    for entity in entities {
+        out.push_str(&format!("    <node id=\"{}\">\n", entity.id));
+        out.push_str(&format!(
+            "      <data key=\"name\">{}</data>\n",
+            escape_xml(&entity.name)
+        ));
+        out.push_str(&format!(
+            "      <data key=\"entity_type\">{}</data>\n",
+            escape_xml(&entity.entity_type)
+        ));
+        if let Some(description) = &entity.description {
+            out.push_str(&format!(
+                "      <data key=\"description\">{}</data>\n",
+                escape_xml(description)
+            ));
+        }
+        if !entity.aliases.is_empty() {
+            out.push_str(&format!(
+                "      <data key=\"aliases\">{}</data>\n",
+                escape_xml(&entity.aliases.join("; "))
+            ));
+        }
+        out.push_str(&format!(
+            "      <data key=\"fact_count\">{}</data>\n",
+            entity.fact_count
+        ));
+        out.push_str("    </node>\n");
+    }
+
+    for rel in relationships {
+        out.push_str(&format!(
+            "    <edge id=\"{}\" source=\"{}\" target=\"{}\">\n",
+            rel.id, rel.source_entity_id, rel.target_entity_id
+        ));
+        out.push_str(&format!(
+            "      <data key=\"relationship_type\">{}</data>\n",
+            escape_xml(&rel.relationship_type)
+        ));
+        if let Some(valid_from) = rel.valid_from {
+            out.push_str(&format!(
+                "      <data key=\"valid_from\">{}</data>\n",
+                valid_from
+            ));
+        }
+        if let Some(valid_to) = rel.valid_to {
+            out.push_str(&format!(
+                "      <data key=\"valid_to\">{}</data>\n",
+                valid_to
+            ));
+        }
+        out.push_str("    </edge>\n");
+    }
+
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+/// Neo4j node label for an entity type (`person` -> `Person`).
+fn node_label(entity_type: &str) -> String {
+    let mut chars = entity_type.chars().filter(|c| c.is_ascii_alphanumeric());
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() => {
+            first.to_ascii_uppercase().to_string() + &chars.collect::<String>()
+        }
+        _ => "Custom".to_string(),
+    }
+}
+
+/// Neo4j relationship type for a relationship (`works_at` -> `WORKS_AT`).
+fn relationship_label(relationship_type: &str) -> String {
+    let label: String = relationship_type
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    match label.chars().next() {
+        Some(first) if first.is_ascii_alphabetic() => label,
+        Some(_) => format!("REL_{}", label),
+        None => "RELATED_TO".to_string(),
+    }
+}
+
+/// Single-quoted Cypher string literal.
+fn cypher_string(value: &str) -> String {
+    let mut literal = String::with_capacity(value.len() + 2);
+    literal.push('\'');
+    for c in value.chars() {
+        match c {
+            '\\' => literal.push_str("\\\\"),
+            '\'' => literal.push_str("\\'"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            _ => literal.push(c),
+        }
+    }
+    literal.push('\'');
+    literal
+}
+
+/// Cypher script that recreates the graph.
+///
+/// Nodes are `MERGE`d on `id` so running the script twice doesn't duplicate
+/// them; every node also gets an `Entity` label for the uniqueness constraint.
+pub fn render_cypher(entities: &[ExportEntity], relationships: &[ExportRelationship]) -> String {
+    let mut out = String::from(
+        "// Second Brain graph export\n\
+         CREATE CONSTRAINT entity_id IF NOT EXISTS FOR (e:Entity) REQUIRE e.id IS UNIQUE;\n\n",
+    );
+
+    for entity in entities {
+        let aliases: Vec<String> = entity.aliases.iter().map(|a| cypher_string(a)).collect();
+        out.push_str(&format!(
+            "MERGE (e:Entity {{id: {}}}) SET e:{}, e.name = {}, e.entity_type = {}, e.aliases = [{}], e.fact_count = {}",
+            cypher_string(&entity.id.to_string()),
+            node_label(&entity.entity_type),
+            cypher_string(&entity.name),
+            cypher_string(&entity.entity_type),
+            aliases.join(", "),
+            entity.fact_count,
+        ));
+        if let Some(description) = &entity.description {
+            out.push_str(&format!(", e.description = {}", cypher_string(description)));
+        }
+        out.push_str(";\n");
+    }
+
+    if !relationships.is_empty() {
+        out.push('\n');
+    }
+
+    for rel in relationships {
+        let mut props = vec![format!("id: {}", cypher_string(&rel.id.to_string()))];
+        if let Some(valid_from) = rel.valid_from {
+            props.push(format!("valid_from: date('{}')", valid_from));
+        }
+        if let Some(valid_to) = rel.valid_to {
+            props.push(format!("valid_to: date('{}')", valid_to));
+        }
+        out.push_str(&format!(
+            "MATCH (a:Entity {{id: {}}}), (b:Entity {{id: {}}}) MERGE (a)-[:{} {{{}}}]->(b);\n",
+            cypher_string(&rel.source_entity_id.to_string()),
+            cypher_string(&rel.target_entity_id.to_string()),
+            relationship_label(&rel.relationship_type),
+            props.join(", "),
+        ));
+    }
+
+    out
+}
+
+/// CSV field, quoted when it contains a delimiter, quote or line break.
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// `neo4j-admin import` node file (array delimiter `;`).
+pub fn render_neo4j_nodes(entities: &[ExportEntity]) -> String {
+    let mut out = String::from(
+        "id:ID,name,entity_type,description,aliases:string[],fact_count:long,:LABEL\n",
+    );
+    for entity in entities {
+        let aliases: Vec<String> = entity.aliases.iter().map(|a| a.replace(';', ",")).collect();
+        let fields = [
+            entity.id.to_string(),
+            entity.name.clone(),
+            entity.entity_type.clone(),
+            entity.description.clone().unwrap_or_default(),
+            aliases.join(";"),
+            entity.fact_count.to_string(),
+            format!("Entity;{}", node_label(&entity.entity_type)),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// `neo4j-admin import` relationship file.
+pub fn render_neo4j_relationships(relationships: &[ExportRelationship]) -> String {
+    let mut out = String::from(":START_ID,:END_ID,:TYPE,id,valid_from:date,valid_to:date\n");
+    for rel in relationships {
+        let fields = [
+            rel.source_entity_id.to_string(),
+            rel.target_entity_id.to_string(),
+            relationship_label(&rel.relationship_type),
+            rel.id.to_string(),
+            rel.valid_from.map(|d| d.to_string()).unwrap_or_default(),
+            rel.valid_to.map(|d| d.to_string()).unwrap_or_default(),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// schema.org type for an entity type.
+fn schema_type(entity_type: &str) -> &'static str {
+    match entity_type {
+        "person" => "Person",
+        "organization" => "Organization",
+        "place" => "Place",
+        "project" => "Project",
+        "event" => "Event",
+        "product" => "Product",
+        _ => "Thing",
+    }
+}
+
+fn node_id(id: Uuid) -> String {
+    format!("urn:uuid:{}", id)
+}
+
+/// Facts as a schema.org JSON-LD graph.
+///
+/// Each fact is a `Claim` whose `about` and `mentions` point at the
+/// entities, which are included as typed nodes so references resolve.
+/// Validity becomes an ISO 8601 `temporalCoverage` interval (`..` for
+/// an open end).
+pub fn render_jsonld(entities: &[ExportEntity], facts: &[ExportFact]) -> Value {
+    let mut graph: Vec<Value> = entities
+        .iter()
+        .map(|entity| {
+            let mut node = json!({
+                "@id": node_id(entity.id),
+                "@type": schema_type(&entity.entity_type),
+                "name": entity.name,
+            });
+            if let Some(description) = &entity.description {
+                node["description"] = json!(description);
+            }
+            if !entity.aliases.is_empty() {
+                node["alternateName"] = json!(entity.aliases);
+            }
+            node
+        })
+        .collect();
+
+    graph.extend(facts.iter().map(|fact| {
+        let mut node = json!({
+            "@id": node_id(fact.id),
+            "@type": "Claim",
+            "text": fact.content,
+            "dateCreated": fact.recorded_at.to_rfc3339(),
+        });
+        if let Some(about) = fact.about_entity_id {
+            node["about"] = json!({"@id": node_id(about)});
+        }
+        if !fact.mentioned_entity_ids.is_empty() {
+            node["mentions"] = fact
+                .mentioned_entity_ids
+                .iter()
+                .map(|id| json!({"@id": node_id(*id)}))
+                .collect();
+        }
+        if fact.valid_from.is_some() || fact.valid_to.is_some() {
+            let bound =
+                |d: Option<NaiveDate>| d.map(|d| d.to_string()).unwrap_or_else(|| "..".to_string());
+            node["temporalCoverage"] = json!(format!(
+                "{}/{}",
+                bound(fact.valid_from),
+                bound(fact.valid_to)
+            ));
+        }
+        node
+    }));
+
+    json!({
+        "@context": "https://schema.org",
+        "@graph": graph,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn entity(id: u128, name: &str, entity_type: &str) -> ExportEntity {
+        ExportEntity {
+            id: Uuid::from_u128(id),
+            entity_type: entity_type.to_string(),
+            name: name.to_string(),
+            description: None,
+            aliases: vec![],
+            fact_count: 0,
+            created_at: Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap(),
+        }
+    }
+
+    fn relationship(source: u128, target: u128, relationship_type: &str) -> ExportRelationship {
+        ExportRelationship {
+            id: Uuid::from_u128(100),
+            source_entity_id: Uuid::from_u128(source),
+            target_entity_id: Uuid::from_u128(target),
+            relationship_type: relationship_type.to_string(),
+            valid_from: NaiveDate::from_ymd_opt(2020, 5, 1),
+            valid_to: None,
+        }
+    }
+
+    #[test]
+    fn parses_formats() {
+        assert_eq!(ExportFormat::parse("GraphML"), Some(ExportFormat::GraphMl));
+        assert_eq!(ExportFormat::parse("json-ld"), Some(ExportFormat::JsonLd));
+        assert_eq!(
+            ExportFormat::parse("neo4j-relationships"),
+            Some(ExportFormat::Neo4jRelationships)
+        );
+        assert_eq!(ExportFormat::parse("gexf"), None);
+    }
+
+    #[test]
+    fn graphml_escapes_and_links_nodes() {
+        let mut alice = entity(1, "Alice <Ally> & Co", "person");
+        alice.aliases = vec!["Al".to_string(), "Ally".to_string()];
+        let acme = entity(2, "Acme", "organization");
+        let xml = render_graphml(&[alice, acme], &[relationship(1, 2, "works_at")]);
+
+        assert!(xml.contains("<data key=\"name\">Alice &lt;Ally&gt; &amp; Co</data>"));
+        assert!(xml.contains("<data key=\"aliases\">Al; Ally</data>"));
+        assert!(xml.contains(&format!(
+            "source=\"{}\" target=\"{}\"",
+            Uuid::from_u128(1),
+            Uuid::from_u128(2)
+        )));
+        assert!(xml.contains("<data key=\"valid_from\">2020-05-01</data>"));
+        assert!(!xml.contains("key=\"valid_to\">"));
+        assert!(xml.trim_end().ends_with("</graphml>"));
+    }
+
+    #[test]
+    fn cypher_labels_and_quotes() {
+        let bob = entity(1, "Bob's Shop", "place");
+        let script = render_cypher(&[bob], &[relationship(1, 2, "located in")]);
+
+        assert!(script.contains("SET e:Place, e.name = 'Bob\\'s Shop'"));
+        assert!(script.contains("MERGE (a)-[:LOCATED_IN {id: "));
+        assert!(script.contains("valid_from: date('2020-05-01')"));
+    }
+
+    #[test]
+    fn relationship_labels_are_valid_identifiers() {
+        assert_eq!(relationship_label("parent_of"), "PARENT_OF");
+        assert_eq!(relationship_label("co-founder"), "CO_FOUNDER");
+        assert_eq!(relationship_label("2nd cousin"), "REL_2ND_COUSIN");
+        assert_eq!(relationship_label("  "), "RELATED_TO");
+        assert_eq!(node_label("organization"), "Organization");
+        assert_eq!(node_label(""), "Custom");
+    }
+
+    #[test]
+    fn neo4j_csv_quotes_fields() {
+        let mut acme = entity(2, "Acme, Inc.", "organization");
+        acme.description = Some("Says \"hi\"".to_string());
+        acme.aliases = vec!["ACME".to_string(), "Acme Corp".to_string()];
+        let csv = render_neo4j_nodes(&[acme]);
+        let row = csv.lines().nth(1).unwrap();
+
+        assert!(row.contains(
+            ",\"Acme, Inc.\",organization,\"Says \"\"hi\"\"\",ACME;Acme Corp,0,Entity;Organization"
+        ));
+
+        let rels = render_neo4j_relationships(&[relationship(1, 2, "works_at")]);
+        assert!(rels.starts_with(":START_ID,:END_ID,:TYPE,"));
+        assert!(rels.lines().nth(1).unwrap().ends_with(",2020-05-01,"));
+    }
+
+    #[test]
+    fn jsonld_links_facts_to_entities() {
+        let alice = entity(1, "Alice", "person");
+        let fact = ExportFact {
+            id: Uuid::from_u128(50),
+            content: "Alice works at Acme".to_string(),
+            about_entity_id: Some(Uuid::from_u128(1)),
+            mentioned_entity_ids: vec![Uuid::from_u128(2)],
+            valid_from: NaiveDate::from_ymd_opt(2024, 1, 1),
+            valid_to: None,
+            recorded_at: Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap(),
+        };
+        let doc = render_jsonld(&[alice], &[fact]);
+        let graph = doc["@graph"].as_array().unwrap();
+
+        assert_eq!(doc["@context"], "https://schema.org");
+        assert_eq!(graph[0]["@type"], "Person");
+        assert_eq!(graph[1]["@type"], "Claim");
+        assert_eq!(
+            graph[1]["about"]["@id"],
+            format!("urn:uuid:{}", Uuid::from_u128(1))
+        );
+        assert_eq!(
+            graph[1]["mentions"][0]["@id"],
+            format!("urn:uuid:{}", Uuid::from_u128(2))
+        );
+        assert_eq!(graph[1]["temporalCoverage"], "2024-01-01/..");
+    }
+}
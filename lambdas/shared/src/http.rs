@@ -3,9 +3,14 @@
 use lambda_http::{Body, Response};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use utoipa::ToSchema;
 
-/// Standard API response wrapper.
-#[derive(Debug, Serialize)]
+use crate::error::ApiError;
+
+/// Standard API response wrapper for successful requests.
+///
+/// Failures are sent as [`ApiError`] problem details instead.
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ApiResponse<T> {
     pub success: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -22,14 +27,6 @@ impl<T> ApiResponse<T> {
             error: None,
         }
     }
-
-    pub fn error(message: impl Into<String>) -> ApiResponse<()> {
-        ApiResponse {
-            success: false,
-            data: None,
-            error: Some(message.into()),
-        }
-    }
 }
 
 /// Create a JSON response with the given status code and data.
@@ -37,13 +34,13 @@ pub fn json_response<T: Serialize>(status: u16, data: &T) -> Result<Response<Bod
     Ok(Response::builder()
         .status(status)
         .header("content-type", "application/json")
-        .body(Body::from(serde_json::to_string(data)?))
-        .expect("Failed to build response"))
+        .body(Body::from(serde_json::to_string(data)?))?)
 }
 
-/// Create an error response with the given status code and message.
+/// Create an `application/problem+json` error response with the given status
+/// code and message; the [`ErrorCode`](crate::error::ErrorCode) follows the status.
 pub fn error_response(status: u16, message: impl Into<String>) -> Result<Response<Body>, lambda_http::Error> {
-    json_response(status, &ApiResponse::<()>::error(message))
+    ApiError::from_status(status, message).into_response()
 }
 
 /// Parse request body as JSON, returning a 400 response on failure.
@@ -53,10 +50,7 @@ pub fn error_response(status: u16, message: impl Into<String>) -> Result<Respons
 pub fn parse_json_body<T: DeserializeOwned>(body: &Body) -> Result<Result<T, Response<Body>>, lambda_http::Error> {
     match serde_json::from_slice(body.as_ref()) {
         Ok(parsed) => Ok(Ok(parsed)),
-        Err(e) => {
-            let response = error_response(400, format!("Invalid request body: {}", e))?;
-            Ok(Err(response))
-        }
+        Err(e) => Ok(Err(ApiError::from(e).into_response()?)),
     }
 }
 
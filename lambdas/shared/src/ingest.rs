@@ -0,0 +1,540 @@
+//! Fact ingestion pipeline.
+//!
+//! Shared by the ingest API and batch imports: the agent stores the facts an
+//! item describes, older facts they replace are closed off (see
+//! `supersession`), repeats of recent facts among the rest are merged or
+//! flagged (see `fact_duplicates`), and the facts left standing are announced
+//! on the event bus.
+//!
+//! Batches (`POST /ingest/batch`) run through the same pipeline a few items
+//! at a time. Up to `SYNC_BATCH_ITEMS` are ingested while the caller waits;
+//! larger imports are recorded in `ingest_batches` and processed by the
+//! `ingest_batch` Lambda, which records each item's result on the row as it
+//! finishes so a job that stops partway resumes with the items left.
+
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::fact_duplicates::{detect_duplicate, Duplicate, DuplicateKind};
+use crate::supersession::detect_supersession;
+use crate::{
+    AgentClient, AgentResponse, EmbeddingClient, Error, EventPublisher, IngestRequest,
+    IngestResponse, Result,
+};
+
+/// Most items accepted in one batch
+pub const MAX_BATCH_ITEMS: usize = 500;
+
+/// Largest batch ingested synchronously (API Gateway gives up after 29
+/// seconds); bigger ones become a background job
+pub const SYNC_BATCH_ITEMS: usize = 8;
+
+/// Items of a batch in flight at once (each waits on the agent for seconds)
+pub const MAX_CONCURRENT_ITEMS: usize = 4;
+
+/// Minutes a batch job can go without finishing an item before it's taken
+/// to have died and is resumed
+pub const BATCH_LEASE_MINUTES: i64 = 5;
+
+/// Times a batch job is resumed after dying before it's marked failed
+pub const MAX_BATCH_ATTEMPTS: i32 = 3;
+
+/// Agent, database and event clients items are ingested with.
+pub struct IngestPipeline {
+    pub agent_client: AgentClient,
+    /// Duplicates and supersession are only checked when set
+    pub db_pool: Option<PgPool>,
+    pub embedding_client: EmbeddingClient,
+    /// Set when `EVENT_BUS_NAME` is configured (needs `db_pool` too)
+    pub event_publisher: Option<EventPublisher>,
+}
+
+impl IngestPipeline {
+    /// Clients from the Lambda environment (`AGENT_FUNCTION_NAME`,
+    /// `EMBEDDING_MODEL_ID`, `EVENT_BUS_NAME`).
+    pub fn from_env(config: &aws_config::SdkConfig, db_pool: Option<PgPool>) -> Self {
+        let agent_function = std::env::var("AGENT_FUNCTION_NAME")
+            .unwrap_or_else(|_| "second-brain-agents".to_string());
+
+        let bedrock_client = aws_sdk_bedrockruntime::Client::new(config);
+        let embedding_client = match std::env::var("EMBEDDING_MODEL_ID") {
+            Ok(model_id) => EmbeddingClient::with_model(bedrock_client, model_id),
+            Err(_) => EmbeddingClient::new(bedrock_client),
+        };
+
+        Self {
+            agent_client: AgentClient::new(aws_sdk_lambda::Client::new(config), agent_function),
+            db_pool,
+            embedding_client,
+            event_publisher: EventPublisher::from_env(config),
+        }
+    }
+
+    /// Validate an item, hand it to the agent and settle the facts it stored.
+    pub async fn ingest(
+        &self,
+        request: &IngestRequest,
+        user_id: &str,
+        family_ids: Vec<String>,
+        source: &str,
+    ) -> Result<IngestResponse> {
+        request.validate()?;
+        let response = self
+            .agent_client
+            .ingest(&request.agent_message(), user_id, family_ids, source)
+            .await?;
+        Ok(self.settle(response, source).await)
+    }
+
+    /// Close off the older facts the stored ones replace, merge or flag
+    /// repeats among the rest and announce them.
+    pub async fn settle(&self, agent_response: AgentResponse, source: &str) -> IngestResponse {
+        let fact_ids = agent_response
+            .metadata
+            .as_ref()
+            .and_then(|m| m.fact_ids.clone())
+            .unwrap_or_default();
+
+        // Updates first, so an edited date or amount supersedes the old fact
+        // rather than being taken for a repeat of it
+        let superseded = match &self.db_pool {
+            Some(pool) => supersede_facts(pool, &self.embedding_client, &fact_ids).await,
+            None => vec![None; fact_ids.len()],
+        };
+        let superseded_fact_id = superseded.iter().flatten().copied().next();
+
+        let fresh_ids: Vec<Uuid> = fact_ids
+            .iter()
+            .zip(&superseded)
+            .filter(|(_, superseded)| superseded.is_none())
+            .map(|(id, _)| *id)
+            .collect();
+        let fresh_duplicates = match &self.db_pool {
+            Some(pool) => find_duplicates(pool, &self.embedding_client, &fresh_ids).await,
+            None => vec![None; fresh_ids.len()],
+        };
+        let mut fresh_duplicates = fresh_duplicates.into_iter();
+        let duplicates: Vec<Option<Duplicate>> = superseded
+            .iter()
+            .map(|superseded| match superseded {
+                Some(_) => None,
+                None => fresh_duplicates.next().flatten(),
+            })
+            .collect();
+        let duplicate_of = duplicates.iter().flatten().map(|d| d.duplicate_of).next();
+
+        // Merged repeats are in the trash; the earlier facts stand in for them
+        let merged_into = |duplicate: &Option<Duplicate>| match duplicate {
+            Some(d) if d.kind == DuplicateKind::Merged => Some(d.duplicate_of),
+            _ => None,
+        };
+        let stored_ids: Vec<Uuid> = fact_ids
+            .iter()
+            .zip(&duplicates)
+            .filter(|(_, duplicate)| merged_into(duplicate).is_none())
+            .map(|(id, _)| *id)
+            .collect();
+        let fact_id = fact_ids
+            .iter()
+            .zip(&duplicates)
+            .map(|(id, duplicate)| merged_into(duplicate).unwrap_or(*id))
+            .next();
+
+        if let (Some(pool), Some(publisher)) = (&self.db_pool, &self.event_publisher) {
+            publisher
+                .publish_facts_created(pool, &stored_ids, source)
+                .await;
+        }
+
+        IngestResponse {
+            // Legacy agent mode doesn't report the facts it stored
+            fact_id: fact_id.unwrap_or_else(Uuid::new_v4),
+            message: agent_response.response,
+            entities_created: vec![], // TODO: Extract from agent response
+            superseded_fact_id,
+            duplicate_of,
+        }
+    }
+}
+
+/// Merge or flag newly stored facts that repeat earlier ones (best effort),
+/// returning the duplicate found for each.
+async fn find_duplicates(
+    pool: &PgPool,
+    embedding_client: &EmbeddingClient,
+    fact_ids: &[Uuid],
+) -> Vec<Option<Duplicate>> {
+    let mut duplicates = Vec::with_capacity(fact_ids.len());
+    for fact_id in fact_ids {
+        let duplicate = match detect_duplicate(pool, embedding_client, *fact_id).await {
+            Ok(duplicate) => duplicate,
+            Err(e) => {
+                warn!("Duplicate check failed for fact {}: {}", fact_id, e);
+                None
+            }
+        };
+        if let Some(duplicate) = &duplicate {
+            info!(
+                "Fact {} duplicates {} ({:?}, trigram {:.2})",
+                fact_id, duplicate.duplicate_of, duplicate.kind, duplicate.trigram_similarity
+            );
+        }
+        duplicates.push(duplicate);
+    }
+    duplicates
+}
+
+/// Close off older facts the newly stored ones replace (best effort),
+/// returning the fact each one superseded.
+async fn supersede_facts(
+    pool: &PgPool,
+    embedding_client: &EmbeddingClient,
+    fact_ids: &[Uuid],
+) -> Vec<Option<Uuid>> {
+    let mut superseded = Vec::with_capacity(fact_ids.len());
+    for fact_id in fact_ids {
+        let supersession = match detect_supersession(pool, embedding_client, *fact_id).await {
+            Ok(supersession) => supersession,
+            Err(e) => {
+                warn!("Supersession check failed for fact {}: {}", fact_id, e);
+                None
+            }
+        };
+        if let Some(supersession) = &supersession {
+            info!(
+                "Fact {} supersedes {} (similarity {:.2})",
+                fact_id, supersession.superseded_fact_id, supersession.similarity
+            );
+        }
+        superseded.push(supersession.map(|s| s.superseded_fact_id));
+    }
+    superseded
+}
+
+/// Batch ingest request payload.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchIngestRequest {
+    /// Items to ingest, e.g. imported notes, each as for `/ingest`
+    pub items: Vec<IngestRequest>,
+}
+
+impl BatchIngestRequest {
+    /// Check the batch as a whole; invalid items fail on their own.
+    pub fn validate(&self) -> Result<()> {
+        if self.items.is_empty() {
+            return Err(Error::Validation("items cannot be empty".to_string()));
+        }
+        if self.items.len() > MAX_BATCH_ITEMS {
+            return Err(Error::Validation(format!(
+                "A batch can hold at most {} items",
+                MAX_BATCH_ITEMS
+            )));
+        }
+        Ok(())
+    }
+
+    /// Whether the batch is too large to ingest while the caller waits.
+    pub fn needs_job(&self) -> bool {
+        self.items.len() > SYNC_BATCH_ITEMS
+    }
+}
+
+/// What happened to one item of a batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchItemStatus {
+    /// Stored as new facts
+    Stored,
+    /// Repeated an earlier fact and was merged into it
+    Duplicate,
+    Failed,
+}
+
+/// Result for one item of a batch, in request order.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchItemResult {
+    /// Position of the item in the request
+    pub index: usize,
+    pub status: BatchItemStatus,
+    pub fact_id: Option<Uuid>,
+    pub superseded_fact_id: Option<Uuid>,
+    pub duplicate_of: Option<Uuid>,
+    pub error: Option<String>,
+}
+
+impl BatchItemResult {
+    pub fn new(index: usize, outcome: Result<IngestResponse>) -> Self {
+        match outcome {
+            Ok(response) => Self {
+                index,
+                // A merged item reports the earlier fact as its own
+                status: if response.duplicate_of == Some(response.fact_id) {
+                    BatchItemStatus::Duplicate
+                } else {
+                    BatchItemStatus::Stored
+                },
+                fact_id: Some(response.fact_id),
+                superseded_fact_id: response.superseded_fact_id,
+                duplicate_of: response.duplicate_of,
+                error: None,
+            },
+            Err(e) => Self {
+                index,
+                status: BatchItemStatus::Failed,
+                fact_id: None,
+                superseded_fact_id: None,
+                duplicate_of: None,
+                error: Some(match e {
+                    Error::Validation(message) => message,
+                    _ => "Failed to store fact".to_string(),
+                }),
+            },
+        }
+    }
+}
+
+/// Items being ingested, yielding each result as its item finishes.
+pub struct IngestingItems {
+    tasks: JoinSet<BatchItemResult>,
+    /// Items not yet yielded, in case a task dies
+    unfinished: Vec<usize>,
+}
+
+impl IngestingItems {
+    /// Start ingesting items, each with its position in the batch, with at
+    /// most `MAX_CONCURRENT_ITEMS` in flight. Dropping this aborts the rest.
+    pub fn start(
+        pipeline: Arc<IngestPipeline>,
+        items: Vec<(usize, IngestRequest)>,
+        user_id: &str,
+        family_ids: &[String],
+        source: &str,
+    ) -> Self {
+        let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_ITEMS));
+        let mut tasks = JoinSet::new();
+        let unfinished = items.iter().map(|(index, _)| *index).collect();
+
+        for (index, item) in items {
+            let pipeline = Arc::clone(&pipeline);
+            let semaphore = Arc::clone(&semaphore);
+            let user_id = user_id.to_string();
+            let family_ids = family_ids.to_vec();
+            let source = source.to_string();
+
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                let outcome = pipeline.ingest(&item, &user_id, family_ids, &source).await;
+                if let Err(e) = &outcome {
+                    warn!(index, "Batch item failed: {}", e);
+                }
+                BatchItemResult::new(index, outcome)
+            });
+        }
+
+        Self { tasks, unfinished }
+    }
+
+    /// The next item's result, in the order they finish; `None` once every
+    /// item has one.
+    pub async fn next(&mut self) -> Option<BatchItemResult> {
+        while let Some(joined) = self.tasks.join_next().await {
+            match joined {
+                Ok(result) => {
+                    self.unfinished.retain(|index| *index != result.index);
+                    return Some(result);
+                }
+                Err(e) => warn!("Batch item task panicked: {}", e),
+            }
+        }
+
+        // Account for any item whose task died
+        let index = self.unfinished.pop()?;
+        Some(BatchItemResult::new(
+            index,
+            Err(Error::Internal("Item was not processed".to_string())),
+        ))
+    }
+}
+
+/// Ingest items with at most `MAX_CONCURRENT_ITEMS` in flight, numbering
+/// them from `first_index`. Results come back in item order.
+pub async fn ingest_items(
+    pipeline: Arc<IngestPipeline>,
+    items: Vec<IngestRequest>,
+    first_index: usize,
+    user_id: &str,
+    family_ids: &[String],
+    source: &str,
+) -> Vec<BatchItemResult> {
+    let count = items.len();
+    let numbered = items
+        .into_iter()
+        .enumerate()
+        .map(|(offset, item)| (first_index + offset, item))
+        .collect();
+    let mut ingesting = IngestingItems::start(pipeline, numbered, user_id, family_ids, source);
+
+    let mut results = Vec::with_capacity(count);
+    while let Some(result) = ingesting.next().await {
+        results.push(result);
+    }
+
+    results.sort_by_key(|r| r.index);
+    results
+}
+
+/// The items of a batch that don't have a result yet, with their positions.
+pub fn remaining_items(
+    items: &[IngestRequest],
+    results: &[BatchItemResult],
+) -> Vec<(usize, IngestRequest)> {
+    items
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !results.iter().any(|r| r.index == *index))
+        .map(|(index, item)| (index, item.clone()))
+        .collect()
+}
+
+/// Batch ingest response payload. `batch_id` is set when the batch was
+/// too large to ingest at once; poll `GET /ingest/batch/{id}` for results.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BatchIngestResponse {
+    pub batch_id: Option<Uuid>,
+    /// `completed` for synchronous batches; `pending`, `running`,
+    /// `completed` or `failed` for jobs
+    pub status: String,
+    pub total_items: usize,
+    pub stored_items: usize,
+    pub failed_items: usize,
+    /// Items processed so far
+    pub results: Vec<BatchItemResult>,
+    /// Why a job failed
+    pub error: Option<String>,
+}
+
+impl BatchIngestResponse {
+    /// Response for a batch ingested synchronously.
+    pub fn completed(results: Vec<BatchItemResult>) -> Self {
+        let (stored_items, failed_items) = tally(&results);
+        Self {
+            batch_id: None,
+            status: "completed".to_string(),
+            total_items: results.len(),
+            stored_items,
+            failed_items,
+            results,
+            error: None,
+        }
+    }
+}
+
+/// Items that didn't fail and items that did.
+pub fn tally(results: &[BatchItemResult]) -> (usize, usize) {
+    let failed = results
+        .iter()
+        .filter(|r| r.status == BatchItemStatus::Failed)
+        .count();
+    (results.len() - failed, failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(fact_id: Uuid, duplicate_of: Option<Uuid>) -> IngestResponse {
+        IngestResponse {
+            fact_id,
+            message: "Stored".to_string(),
+            entities_created: vec![],
+            superseded_fact_id: None,
+            duplicate_of,
+        }
+    }
+
+    #[test]
+    fn test_batch_validation() {
+        let item = IngestRequest {
+            content: "Mom's birthday is June 3".to_string(),
+            ..Default::default()
+        };
+
+        let empty = BatchIngestRequest { items: vec![] };
+        assert!(empty.validate().is_err());
+
+        let small = BatchIngestRequest {
+            items: vec![item.clone(); SYNC_BATCH_ITEMS],
+        };
+        assert!(small.validate().is_ok());
+        assert!(!small.needs_job());
+
+        let large = BatchIngestRequest {
+            items: vec![item.clone(); SYNC_BATCH_ITEMS + 1],
+        };
+        assert!(large.validate().is_ok());
+        assert!(large.needs_job());
+
+        let too_large = BatchIngestRequest {
+            items: vec![item; MAX_BATCH_ITEMS + 1],
+        };
+        assert!(too_large.validate().is_err());
+    }
+
+    #[test]
+    fn test_item_results() {
+        let fact_id = Uuid::new_v4();
+        let earlier = Uuid::new_v4();
+
+        let stored = BatchItemResult::new(0, Ok(response(fact_id, None)));
+        assert_eq!(stored.status, BatchItemStatus::Stored);
+        assert_eq!(stored.fact_id, Some(fact_id));
+
+        // Flagged duplicates are still stored
+        let flagged = BatchItemResult::new(1, Ok(response(fact_id, Some(earlier))));
+        assert_eq!(flagged.status, BatchItemStatus::Stored);
+
+        let merged = BatchItemResult::new(2, Ok(response(earlier, Some(earlier))));
+        assert_eq!(merged.status, BatchItemStatus::Duplicate);
+
+        let invalid = BatchItemResult::new(
+            3,
+            Err(Error::Validation("Content cannot be empty".to_string())),
+        );
+        assert_eq!(invalid.status, BatchItemStatus::Failed);
+        assert_eq!(invalid.error.as_deref(), Some("Content cannot be empty"));
+
+        // Internal errors aren't passed on
+        let failed = BatchItemResult::new(4, Err(Error::Internal("agent timeout".to_string())));
+        assert_eq!(failed.error.as_deref(), Some("Failed to store fact"));
+
+        assert_eq!(tally(&[stored, flagged, merged, invalid, failed]), (3, 2));
+    }
+
+    #[test]
+    fn test_remaining_items() {
+        let items: Vec<IngestRequest> = (0..4)
+            .map(|n| IngestRequest {
+                content: format!("Note {}", n),
+                ..Default::default()
+            })
+            .collect();
+
+        assert_eq!(remaining_items(&items, &[]).len(), 4);
+
+        // Results are recorded as items finish, not in order
+        let done = [
+            BatchItemResult::new(2, Ok(response(Uuid::new_v4(), None))),
+            BatchItemResult::new(0, Err(Error::Internal("agent timeout".to_string()))),
+        ];
+        let remaining = remaining_items(&items, &done);
+        let indexes: Vec<usize> = remaining.iter().map(|(index, _)| *index).collect();
+        assert_eq!(indexes, vec![1, 3]);
+        assert_eq!(remaining[1].1.content, "Note 3");
+    }
+}
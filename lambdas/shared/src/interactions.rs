@@ -0,0 +1,364 @@
+//! Implicit feedback from interactions.
+//!
+//! Thumbs up and down are rare, so we also log what people do: which facts a
+//! query or search returned, which of those they clicked or expanded, and
+//! which notifications they opened or dismissed. Clients send batches to
+//! `POST /v1/events`; Lambdas that return results log them with
+//! [`Interactions`]. Everything lands in `interaction_events`, and the events
+//! that say something about satisfaction also become implicit `user_feedback`
+//! rows (see [`implicit_feedback`]), so they count in `user_feedback_stats`
+//! alongside explicit feedback.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{Error, Result};
+
+/// Most events accepted in one batch
+pub const MAX_BATCH: usize = 100;
+
+/// Something a user did (or was shown)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InteractionKind {
+    /// A fact was in a query's or search's results
+    FactReturned,
+    FactClicked,
+    FactExpanded,
+    NotificationOpened,
+    NotificationDismissed,
+}
+
+impl InteractionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::FactReturned => "fact_returned",
+            Self::FactClicked => "fact_clicked",
+            Self::FactExpanded => "fact_expanded",
+            Self::NotificationOpened => "notification_opened",
+            Self::NotificationDismissed => "notification_dismissed",
+        }
+    }
+}
+
+/// One interaction, as sent by clients
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct InteractionEvent {
+    #[serde(rename = "type")]
+    pub kind: InteractionKind,
+    /// The fact or notification
+    pub target_id: Uuid,
+    /// Query or search that returned the fact
+    #[serde(default)]
+    pub query_id: Option<Uuid>,
+    /// Rank of the fact in the results, from 0
+    #[serde(default)]
+    pub position: Option<i32>,
+    /// Defaults to when the event is recorded
+    #[serde(default)]
+    pub occurred_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub metadata: Option<Value>,
+}
+
+/// The `user_feedback` row an event implies
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImplicitFeedback {
+    pub feedback_type: &'static str,
+    pub context_type: &'static str,
+    pub context_id: Uuid,
+    pub action: &'static str,
+}
+
+/// What an event says about satisfaction, if anything.
+///
+/// Opening a notification counts as acting on it and dismissing it as
+/// dismissing it. Clicking or expanding a fact a query returned counts as
+/// being satisfied with the query. Being returned a fact says nothing, and
+/// neither does clicking a fact outside any query.
+pub fn implicit_feedback(event: &InteractionEvent) -> Option<ImplicitFeedback> {
+    match event.kind {
+        InteractionKind::NotificationOpened => Some(ImplicitFeedback {
+            feedback_type: "notification_action",
+            context_type: "notification",
+            context_id: event.target_id,
+            action: "accepted",
+        }),
+        InteractionKind::NotificationDismissed => Some(ImplicitFeedback {
+            feedback_type: "notification_action",
+            context_type: "notification",
+            context_id: event.target_id,
+            action: "dismissed",
+        }),
+        InteractionKind::FactClicked | InteractionKind::FactExpanded => {
+            event.query_id.map(|query_id| ImplicitFeedback {
+                feedback_type: "query_satisfaction",
+                context_type: "query",
+                context_id: query_id,
+                action: "accepted",
+            })
+        }
+        InteractionKind::FactReturned => None,
+    }
+}
+
+/// Check a batch's size.
+pub fn validate_batch(events: &[InteractionEvent]) -> Result<()> {
+    if events.is_empty() {
+        return Err(Error::Validation("events must not be empty".to_string()));
+    }
+    if events.len() > MAX_BATCH {
+        return Err(Error::Validation(format!(
+            "At most {} events can be sent at once",
+            MAX_BATCH
+        )));
+    }
+    Ok(())
+}
+
+/// Rows written by [`record_events`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Recorded {
+    pub events: u64,
+    /// Implicit feedback rows; repeats for the same query or notification
+    /// aren't counted twice
+    pub feedback: u64,
+}
+
+/// Log a batch of events and the implicit feedback they imply.
+///
+/// Each query and notification gets at most one feedback row, whether
+/// explicit or implicit, so clicking three results doesn't count as three
+/// satisfied queries.
+pub async fn record_events(
+    pool: &PgPool,
+    user_id: Uuid,
+    source: &str,
+    events: &[InteractionEvent],
+) -> Result<Recorded> {
+    let now = Utc::now();
+    let mut recorded = Recorded::default();
+    let mut tx = pool.begin().await?;
+
+    for event in events {
+        sqlx::query(
+            r#"
+            INSERT INTO interaction_events
+                (user_id, event_type, target_id, query_id, position, source, metadata, occurred_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(user_id)
+        .bind(event.kind.as_str())
+        .bind(event.target_id)
+        .bind(event.query_id)
+        .bind(event.position)
+        .bind(source)
+        .bind(
+            event
+                .metadata
+                .clone()
+                .unwrap_or_else(|| serde_json::json!({})),
+        )
+        .bind(event.occurred_at.unwrap_or(now))
+        .execute(&mut *tx)
+        .await?;
+        recorded.events += 1;
+
+        let Some(feedback) = implicit_feedback(event) else {
+            continue;
+        };
+
+        let feedback_id: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            INSERT INTO user_feedback (user_id, feedback_type, context_type, context_id, action, metadata)
+            SELECT $1, $2, $3, $4, $5, jsonb_build_object('implicit', true, 'event', $6::text)
+            WHERE NOT EXISTS (
+                SELECT 1 FROM user_feedback
+                WHERE user_id = $1 AND feedback_type = $2 AND context_id = $4
+            )
+            RETURNING id
+            "#,
+        )
+        .bind(user_id)
+        .bind(feedback.feedback_type)
+        .bind(feedback.context_type)
+        .bind(feedback.context_id)
+        .bind(feedback.action)
+        .bind(event.kind.as_str())
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(feedback_id) = feedback_id else {
+            continue;
+        };
+        recorded.feedback += 1;
+
+        if feedback.context_type == "query" {
+            sqlx::query(
+                "UPDATE query_sessions SET feedback_id = $1 WHERE id = $2 AND user_id = $3 AND feedback_id IS NULL",
+            )
+            .bind(feedback_id)
+            .bind(feedback.context_id)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    tx.commit().await?;
+    Ok(recorded)
+}
+
+/// Events collected while handling a request, recorded together.
+///
+/// ```ignore
+/// let mut interactions = Interactions::new(user_id, "api");
+/// interactions.returned(search_id, &fact_ids, 0);
+/// interactions.record(&pool).await;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Interactions {
+    user_id: Uuid,
+    source: String,
+    events: Vec<InteractionEvent>,
+}
+
+impl Interactions {
+    pub fn new(user_id: Uuid, source: &str) -> Self {
+        Self {
+            user_id,
+            source: source.to_string(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Note an event.
+    pub fn push(&mut self, event: InteractionEvent) {
+        self.events.push(event);
+    }
+
+    /// Note the facts a query or search returned, in rank order, the first
+    /// at `first_position` (the page's offset).
+    pub fn returned(&mut self, query_id: Uuid, fact_ids: &[Uuid], first_position: i32) {
+        for (position, fact_id) in (first_position..).zip(fact_ids) {
+            self.push(InteractionEvent {
+                kind: InteractionKind::FactReturned,
+                target_id: *fact_id,
+                query_id: Some(query_id),
+                position: Some(position),
+                occurred_at: None,
+                metadata: None,
+            });
+        }
+    }
+
+    pub fn events(&self) -> &[InteractionEvent] {
+        &self.events
+    }
+
+    /// Record the events noted so far. Logging is best effort: failures are
+    /// logged rather than failing the request.
+    pub async fn record(self, pool: &PgPool) {
+        for batch in self.events.chunks(MAX_BATCH) {
+            if let Err(e) = record_events(pool, self.user_id, &self.source, batch).await {
+                tracing::warn!(error = %e, "Failed to record interactions");
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: InteractionKind, query_id: Option<Uuid>) -> InteractionEvent {
+        InteractionEvent {
+            kind,
+            target_id: Uuid::new_v4(),
+            query_id,
+            position: None,
+            occurred_at: None,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_event() {
+        let target = Uuid::new_v4();
+        let json = serde_json::json!({
+            "type": "fact_clicked",
+            "targetId": target,
+            "position": 2,
+        });
+        let event: InteractionEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(event.kind, InteractionKind::FactClicked);
+        assert_eq!(event.target_id, target);
+        assert_eq!(event.position, Some(2));
+        assert_eq!(event.query_id, None);
+
+        let json = serde_json::json!({ "type": "fact_liked", "targetId": target });
+        assert!(serde_json::from_value::<InteractionEvent>(json).is_err());
+    }
+
+    #[test]
+    fn test_notification_feedback() {
+        let opened = event(InteractionKind::NotificationOpened, None);
+        let feedback = implicit_feedback(&opened).unwrap();
+        assert_eq!(feedback.feedback_type, "notification_action");
+        assert_eq!(feedback.context_id, opened.target_id);
+        assert_eq!(feedback.action, "accepted");
+
+        let dismissed = event(InteractionKind::NotificationDismissed, None);
+        assert_eq!(implicit_feedback(&dismissed).unwrap().action, "dismissed");
+    }
+
+    #[test]
+    fn test_fact_feedback_needs_a_query() {
+        let query_id = Uuid::new_v4();
+
+        let clicked = event(InteractionKind::FactClicked, Some(query_id));
+        let feedback = implicit_feedback(&clicked).unwrap();
+        assert_eq!(feedback.feedback_type, "query_satisfaction");
+        assert_eq!(feedback.context_id, query_id);
+
+        let expanded = event(InteractionKind::FactExpanded, Some(query_id));
+        assert!(implicit_feedback(&expanded).is_some());
+
+        assert!(implicit_feedback(&event(InteractionKind::FactClicked, None)).is_none());
+        assert!(implicit_feedback(&event(InteractionKind::FactReturned, Some(query_id))).is_none());
+    }
+
+    #[test]
+    fn test_validate_batch() {
+        assert!(validate_batch(&[]).is_err());
+
+        let batch = vec![event(InteractionKind::FactReturned, None); MAX_BATCH];
+        assert!(validate_batch(&batch).is_ok());
+
+        let batch = vec![event(InteractionKind::FactReturned, None); MAX_BATCH + 1];
+        assert!(validate_batch(&batch).is_err());
+    }
+
+    #[test]
+    fn test_returned_keeps_rank() {
+        let query_id = Uuid::new_v4();
+        let facts = [Uuid::new_v4(), Uuid::new_v4()];
+
+        let mut interactions = Interactions::new(Uuid::new_v4(), "api");
+        interactions.returned(query_id, &facts, 20);
+
+        let events = interactions.events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].target_id, facts[1]);
+        assert_eq!(events[1].position, Some(21));
+        assert_eq!(events[1].query_id, Some(query_id));
+    }
+}
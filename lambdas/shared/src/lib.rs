@@ -2,21 +2,87 @@
 //!
 //! This crate provides common utilities, types, and clients used across all Lambda functions.
 
+pub mod access;
+pub mod access_grants;
+pub mod account_deletion;
 pub mod agents;
+pub mod alexa;
+pub mod api_keys;
+pub mod audit;
 pub mod auth;
+pub mod briefings;
+pub mod calendar_extraction;
+pub mod capture;
+pub mod codes;
+pub mod conditional;
 pub mod config;
+pub mod contacts;
+pub mod conversations;
+pub mod cors;
+pub mod data_export;
 pub mod db;
+pub mod device_auth;
+pub mod diagnostics;
+pub mod digest;
+pub mod documents;
+pub mod discord_links;
+pub mod embeddings;
+pub mod entity_merge;
+pub mod entity_photos;
 pub mod error;
+pub mod events;
+pub mod fact_actions;
+pub mod fact_attachments;
+pub mod fact_duplicates;
+pub mod fact_review;
+pub mod fact_search;
+pub mod family_join_codes;
+pub mod feeds;
+pub mod geo;
+pub mod graph_export;
 pub mod http;
+pub mod ical;
+pub mod ingest;
+pub mod interactions;
+pub mod location_history;
+pub mod markdown;
+pub mod metrics;
 pub mod models;
+pub mod notification_preferences;
+pub mod occasions;
+pub mod openapi;
+pub mod photos;
+pub mod public_http;
+pub mod push;
+pub mod ratelimit;
+pub mod realtime;
+pub mod recurrence;
+pub mod relationship_health;
+pub mod relationships;
+pub mod reminders;
+pub mod router;
+pub mod s3_ingest;
 pub mod secrets;
+pub mod shaping;
+pub mod sharing;
+pub mod sms;
+pub mod supersession;
+pub mod tag_rules;
+pub mod tag_suggestions;
+pub mod transcripts;
+pub mod trash;
 pub mod tts;
+pub mod usage;
+pub mod weekly_review;
 
 pub use agents::{AgentClient, AgentRequest, AgentResponse};
-pub use auth::{validate_token, extract_user_from_context, AuthenticatedUser, CognitoClaims};
+pub use auth::{validate_token, extract_user_from_context, AuthenticatedUser, AuthorizedUser, CognitoClaims};
 pub use config::Config;
-pub use error::{Error, Result};
+pub use embeddings::EmbeddingClient;
+pub use error::{ApiError, Error, ErrorCode, Result};
+pub use events::{DomainEvent, EventPublisher};
 pub use http::{json_response, error_response, parse_json_body, ApiResponse};
-pub use models::{QueryRequest, QueryResponse, IngestRequest, IngestResponse, UserContext};
-pub use secrets::{get_secret, get_database_credentials, DatabaseCredentials};
+pub use models::{QueryRequest, QueryResponse, IngestRequest, IngestResponse, UserContext, Cursor, CursorParams, Page};
+pub use router::{PathParams, Query, Router};
+pub use secrets::{get_secret, get_database_credentials, refresh_secret, DatabaseCredentials};
 pub use tts::{TtsService, TtsError};
@@ -0,0 +1,663 @@
+//! Location history: pings from the mobile app and the visits found in them.
+//!
+//! The app uploads batches of positions with `POST /location-pings/batch`
+//! while the user has location history turned on (it is off by default). The
+//! `visit_detector` Lambda clusters each user's unprocessed pings into visits,
+//! stays of [`MIN_VISIT_MINUTES`] or more within [`VISIT_RADIUS_METERS`],
+//! links them to the nearest place entity, and records a fact such as
+//! "Visited Lake Tahoe from 14:05 to 16:40" for visits to places. Pings and
+//! visits are deleted after the user's retention period, and pings as soon as
+//! location history is turned off.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{Error, Result};
+
+/// Most pings in one batch
+pub const MAX_BATCH_PINGS: usize = 1000;
+
+/// Pings less accurate than this (in meters) are dropped
+pub const MAX_ACCURACY_METERS: f64 = 200.0;
+
+/// How far in the future a ping's time may be, allowing for clock skew
+pub const MAX_CLOCK_SKEW_MINUTES: i64 = 5;
+
+/// Pings within this distance of a visit's centre belong to it, in meters
+pub const VISIT_RADIUS_METERS: f64 = 100.0;
+
+/// Shortest stay counted as a visit
+pub const MIN_VISIT_MINUTES: i64 = 10;
+
+/// Longest silence between two pings of one visit. Phones ping rarely while
+/// still, so this is generous; a longer gap ends the visit.
+pub const MAX_PING_GAP_MINUTES: i64 = 120;
+
+/// How close a place entity must be to a visit's centre to be linked, in
+/// meters
+pub const VISIT_PLACE_METERS: f64 = 150.0;
+
+/// Retention when the user hasn't chosen one
+pub const DEFAULT_RETENTION_DAYS: i32 = 30;
+
+/// Longest retention allowed
+pub const MAX_RETENTION_DAYS: i32 = 365;
+
+/// Mean radius of the Earth, in meters
+const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+/// A user's location history settings.
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Settings {
+    /// Pings are refused while this is off
+    pub enabled: bool,
+    /// Days pings and visits are kept
+    pub retention_days: i32,
+    /// Record facts for visits to place entities
+    pub record_visit_facts: bool,
+    /// `None` until the user first saves their settings
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl Default for Settings {
+    /// The column defaults, used when a user has no settings row.
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            retention_days: DEFAULT_RETENTION_DAYS,
+            record_visit_facts: true,
+            updated_at: None,
+        }
+    }
+}
+
+/// Changes to a user's settings; fields left out are kept.
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsUpdate {
+    pub enabled: Option<bool>,
+    pub retention_days: Option<i32>,
+    pub record_visit_facts: Option<bool>,
+}
+
+impl SettingsUpdate {
+    /// Apply the changes to `current` and check the result.
+    pub fn apply(self, current: Settings) -> Result<Settings> {
+        let settings = Settings {
+            enabled: self.enabled.unwrap_or(current.enabled),
+            retention_days: self.retention_days.unwrap_or(current.retention_days),
+            record_visit_facts: self
+                .record_visit_facts
+                .unwrap_or(current.record_visit_facts),
+            updated_at: current.updated_at,
+        };
+
+        if !(1..=MAX_RETENTION_DAYS).contains(&settings.retention_days) {
+            return Err(Error::Validation(format!(
+                "retentionDays must be between 1 and {}",
+                MAX_RETENTION_DAYS
+            )));
+        }
+
+        Ok(settings)
+    }
+}
+
+/// One position uploaded by the app.
+#[derive(Debug, Clone, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PingInput {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Horizontal accuracy in meters, if the device reported one
+    #[serde(default)]
+    pub accuracy: Option<f64>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// Check a batch of pings, returning those worth keeping. Malformed pings
+/// fail the whole batch; inaccurate ones and those already past the
+/// retention period are dropped.
+pub fn prepare_batch(
+    pings: Vec<PingInput>,
+    retention_days: i32,
+    now: DateTime<Utc>,
+) -> Result<Vec<PingInput>> {
+    if pings.is_empty() {
+        return Err(Error::Validation("pings must not be empty".to_string()));
+    }
+    if pings.len() > MAX_BATCH_PINGS {
+        return Err(Error::Validation(format!(
+            "A batch holds at most {} pings",
+            MAX_BATCH_PINGS
+        )));
+    }
+
+    let latest = now + Duration::minutes(MAX_CLOCK_SKEW_MINUTES);
+    let oldest = now - Duration::days(retention_days as i64);
+
+    for (i, ping) in pings.iter().enumerate() {
+        if !ping.latitude.is_finite() || !(-90.0..=90.0).contains(&ping.latitude) {
+            return Err(Error::Validation(format!("pings[{}]: invalid latitude", i)));
+        }
+        if !ping.longitude.is_finite() || !(-180.0..=180.0).contains(&ping.longitude) {
+            return Err(Error::Validation(format!(
+                "pings[{}]: invalid longitude",
+                i
+            )));
+        }
+        if ping.accuracy.is_some_and(|a| !a.is_finite() || a < 0.0) {
+            return Err(Error::Validation(format!("pings[{}]: invalid accuracy", i)));
+        }
+        if ping.recorded_at > latest {
+            return Err(Error::Validation(format!(
+                "pings[{}]: recordedAt is in the future",
+                i
+            )));
+        }
+    }
+
+    Ok(pings
+        .into_iter()
+        .filter(|p| p.accuracy.map_or(true, |a| a <= MAX_ACCURACY_METERS))
+        .filter(|p| p.recorded_at >= oldest)
+        .collect())
+}
+
+/// A stored ping, as the visit detector reads it.
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct Ping {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// A stay found in a user's pings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedVisit {
+    /// Centre of the visit's pings
+    pub latitude: f64,
+    pub longitude: f64,
+    pub arrived_at: DateTime<Utc>,
+    pub departed_at: DateTime<Utc>,
+    pub ping_count: i32,
+}
+
+/// Visits found in a run of pings.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Detection {
+    pub visits: Vec<DetectedVisit>,
+    /// Pings recorded at or after this may still be part of a stay that
+    /// hasn't ended, so they are left for the next run. `None` when every
+    /// ping was dealt with.
+    pub open_from: Option<DateTime<Utc>>,
+}
+
+/// Great-circle distance between two positions, in meters.
+pub fn distance_meters((lat1, lon1): (f64, f64), (lat2, lon2): (f64, f64)) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_lon = (lon2 - lon1).to_radians();
+
+    let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_METERS * a.sqrt().asin()
+}
+
+/// Pings being gathered into a possible visit.
+struct Cluster {
+    latitude: f64,
+    longitude: f64,
+    first: DateTime<Utc>,
+    last: DateTime<Utc>,
+    count: i32,
+}
+
+impl Cluster {
+    fn start(ping: &Ping) -> Self {
+        Self {
+            latitude: ping.latitude,
+            longitude: ping.longitude,
+            first: ping.recorded_at,
+            last: ping.recorded_at,
+            count: 1,
+        }
+    }
+
+    /// Whether `ping` continues this stay.
+    fn accepts(&self, ping: &Ping) -> bool {
+        ping.recorded_at - self.last <= Duration::minutes(MAX_PING_GAP_MINUTES)
+            && distance_meters(
+                (self.latitude, self.longitude),
+                (ping.latitude, ping.longitude),
+            ) <= VISIT_RADIUS_METERS
+    }
+
+    /// Add a ping, moving the centre to the mean of the cluster's pings.
+    fn add(&mut self, ping: &Ping) {
+        let n = self.count as f64;
+        self.latitude = (self.latitude * n + ping.latitude) / (n + 1.0);
+        self.longitude = (self.longitude * n + ping.longitude) / (n + 1.0);
+        self.last = ping.recorded_at;
+        self.count += 1;
+    }
+
+    /// The visit this cluster makes, if the stay was long enough.
+    fn into_visit(self) -> Option<DetectedVisit> {
+        (self.last - self.first >= Duration::minutes(MIN_VISIT_MINUTES)).then_some(DetectedVisit {
+            latitude: self.latitude,
+            longitude: self.longitude,
+            arrived_at: self.first,
+            departed_at: self.last,
+            ping_count: self.count,
+        })
+    }
+}
+
+/// Cluster pings (sorted by time) into visits. The last cluster is left open
+/// unless it has been silent for [`MAX_PING_GAP_MINUTES`], since the user
+/// may still be there.
+pub fn detect_visits(pings: &[Ping], now: DateTime<Utc>) -> Detection {
+    let mut detection = Detection::default();
+    let Some((first, rest)) = pings.split_first() else {
+        return detection;
+    };
+
+    let mut cluster = Cluster::start(first);
+    for ping in rest {
+        if cluster.accepts(ping) {
+            cluster.add(ping);
+        } else {
+            let ended = std::mem::replace(&mut cluster, Cluster::start(ping));
+            detection.visits.extend(ended.into_visit());
+        }
+    }
+
+    if now - cluster.last > Duration::minutes(MAX_PING_GAP_MINUTES) {
+        detection.visits.extend(cluster.into_visit());
+    } else {
+        detection.open_from = Some(cluster.first);
+    }
+
+    detection
+}
+
+/// Fact content for a visit, with times in the user's timezone:
+/// "Visited Lake Tahoe from 14:05 to 16:40".
+pub fn visit_description<Tz: chrono::TimeZone>(
+    place: &str,
+    arrived_at: DateTime<Tz>,
+    departed_at: DateTime<Tz>,
+) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    if arrived_at.date_naive() == departed_at.date_naive() {
+        format!(
+            "Visited {} from {} to {}",
+            place.trim(),
+            arrived_at.format("%H:%M"),
+            departed_at.format("%H:%M")
+        )
+    } else {
+        format!(
+            "Visited {} from {} to {}",
+            place.trim(),
+            arrived_at.format("%Y-%m-%d %H:%M"),
+            departed_at.format("%Y-%m-%d %H:%M")
+        )
+    }
+}
+
+/// A visit as listed by `GET /location-history/visits`.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Visit {
+    pub id: Uuid,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub place_entity_id: Option<Uuid>,
+    pub place_name: Option<String>,
+    pub arrived_at: DateTime<Utc>,
+    pub departed_at: DateTime<Utc>,
+    pub ping_count: i32,
+    pub fact_id: Option<Uuid>,
+}
+
+/// What [`delete_history`] removed.
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedHistory {
+    pub pings: u64,
+    pub visits: u64,
+    /// Visit facts moved to the trash
+    pub facts: u64,
+}
+
+/// A user's settings, or the defaults if they haven't saved any.
+pub async fn load_settings(pool: &PgPool, user_id: Uuid) -> Result<Settings> {
+    let settings: Option<Settings> = sqlx::query_as(
+        r#"
+        SELECT enabled, retention_days, record_visit_facts, updated_at
+        FROM location_history_settings
+        WHERE user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(settings.unwrap_or_default())
+}
+
+/// Save a user's settings (already checked by [`SettingsUpdate::apply`]).
+pub async fn save_settings(pool: &PgPool, user_id: Uuid, settings: &Settings) -> Result<Settings> {
+    let saved = sqlx::query_as(
+        r#"
+        INSERT INTO location_history_settings (
+            user_id, enabled, retention_days, record_visit_facts, updated_at
+        ) VALUES ($1, $2, $3, $4, NOW())
+        ON CONFLICT (user_id) DO UPDATE SET
+            enabled = EXCLUDED.enabled,
+            retention_days = EXCLUDED.retention_days,
+            record_visit_facts = EXCLUDED.record_visit_facts,
+            updated_at = NOW()
+        RETURNING enabled, retention_days, record_visit_facts, updated_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(settings.enabled)
+    .bind(settings.retention_days)
+    .bind(settings.record_visit_facts)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(saved)
+}
+
+/// Store pings (already checked by [`prepare_batch`]), skipping any the
+/// user already uploaded. Returns how many were new.
+pub async fn insert_pings(pool: &PgPool, user_id: Uuid, pings: &[PingInput]) -> Result<u64> {
+    let latitudes: Vec<f64> = pings.iter().map(|p| p.latitude).collect();
+    let longitudes: Vec<f64> = pings.iter().map(|p| p.longitude).collect();
+    let accuracies: Vec<Option<f64>> = pings.iter().map(|p| p.accuracy).collect();
+    let recorded: Vec<DateTime<Utc>> = pings.iter().map(|p| p.recorded_at).collect();
+
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO location_pings (user_id, location, accuracy_meters, recorded_at)
+        SELECT $1, ST_SetSRID(ST_MakePoint(p.longitude, p.latitude), 4326)::geography,
+               p.accuracy::real, p.recorded_at
+        FROM UNNEST($2::float8[], $3::float8[], $4::float8[], $5::timestamptz[])
+            AS p(latitude, longitude, accuracy, recorded_at)
+        ON CONFLICT (user_id, recorded_at) DO NOTHING
+        "#,
+    )
+    .bind(user_id)
+    .bind(&latitudes)
+    .bind(&longitudes)
+    .bind(&accuracies)
+    .bind(&recorded)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(inserted)
+}
+
+/// A user's visits, most recent first.
+pub async fn list_visits(
+    pool: &PgPool,
+    user_id: Uuid,
+    since: Option<DateTime<Utc>>,
+    limit: i64,
+) -> Result<Vec<Visit>> {
+    let visits = sqlx::query_as(
+        r#"
+        SELECT v.id,
+               ST_Y(v.location::geometry) AS latitude,
+               ST_X(v.location::geometry) AS longitude,
+               v.place_entity_id, e.name AS place_name,
+               v.arrived_at, v.departed_at, v.ping_count, v.fact_id
+        FROM location_visits v
+        LEFT JOIN entities e ON e.id = v.place_entity_id AND e.deleted_at IS NULL
+        WHERE v.user_id = $1
+          AND ($2::timestamptz IS NULL OR v.departed_at >= $2)
+        ORDER BY v.arrived_at DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(user_id)
+    .bind(since)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(visits)
+}
+
+/// Delete all of a user's pings and visits, and move their visit facts to
+/// the trash when `include_facts` is set.
+pub async fn delete_history(
+    pool: &PgPool,
+    user_id: Uuid,
+    include_facts: bool,
+) -> Result<DeletedHistory> {
+    let mut tx = pool.begin().await?;
+
+    let facts = if include_facts {
+        sqlx::query(
+            r#"
+            UPDATE facts SET deleted_at = NOW()
+            WHERE id IN (SELECT fact_id FROM location_visits WHERE user_id = $1)
+              AND deleted_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+    } else {
+        0
+    };
+
+    let visits = sqlx::query("DELETE FROM location_visits WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    let pings = sqlx::query("DELETE FROM location_pings WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    tx.commit().await?;
+
+    Ok(DeletedHistory {
+        pings,
+        visits,
+        facts,
+    })
+}
+
+/// Delete pings and visits past their owner's retention period, and pings of
+/// users who have turned location history off. Returns the pings and visits
+/// deleted.
+pub async fn purge_expired(pool: &PgPool) -> Result<(u64, u64)> {
+    let pings = sqlx::query(
+        r#"
+        DELETE FROM location_pings p
+        WHERE NOT EXISTS (
+            SELECT 1 FROM location_history_settings s
+            WHERE s.user_id = p.user_id
+              AND s.enabled
+              AND p.recorded_at >= NOW() - make_interval(days => s.retention_days)
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    let visits = sqlx::query(
+        r#"
+        DELETE FROM location_visits v
+        USING location_history_settings s
+        WHERE s.user_id = v.user_id
+          AND v.departed_at < NOW() - make_interval(days => s.retention_days)
+        "#,
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok((pings, visits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap() + Duration::minutes(minutes)
+    }
+
+    fn ping(latitude: f64, longitude: f64, minutes: i64) -> Ping {
+        Ping {
+            latitude,
+            longitude,
+            recorded_at: at(minutes),
+        }
+    }
+
+    fn input(latitude: f64, accuracy: Option<f64>, minutes: i64) -> PingInput {
+        PingInput {
+            latitude,
+            longitude: -120.0,
+            accuracy,
+            recorded_at: at(minutes),
+        }
+    }
+
+    #[test]
+    fn measures_distances() {
+        // One degree of latitude is about 111.2 km
+        let d = distance_meters((39.0, -120.0), (40.0, -120.0));
+        assert!((d - 111_195.0).abs() < 10.0);
+        assert_eq!(distance_meters((39.0, -120.0), (39.0, -120.0)), 0.0);
+    }
+
+    #[test]
+    fn applies_settings_updates() {
+        let update = SettingsUpdate {
+            enabled: Some(true),
+            retention_days: Some(90),
+            ..Default::default()
+        };
+        let settings = update.apply(Settings::default()).unwrap();
+        assert!(settings.enabled);
+        assert_eq!(settings.retention_days, 90);
+        assert!(settings.record_visit_facts);
+
+        let update = SettingsUpdate {
+            retention_days: Some(MAX_RETENTION_DAYS + 1),
+            ..Default::default()
+        };
+        assert!(matches!(
+            update.apply(Settings::default()),
+            Err(Error::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn prepares_batches() {
+        let now = at(0);
+        let kept = prepare_batch(
+            vec![
+                input(39.0, Some(10.0), -5),
+                input(39.0, Some(500.0), -4),
+                input(39.0, None, -3),
+                input(39.0, None, -60 * 24 * 31),
+            ],
+            30,
+            now,
+        )
+        .unwrap();
+        assert_eq!(kept.len(), 2);
+
+        assert!(prepare_batch(vec![], 30, now).is_err());
+        assert!(prepare_batch(vec![input(91.0, None, 0)], 30, now).is_err());
+        assert!(prepare_batch(vec![input(39.0, Some(-1.0), 0)], 30, now).is_err());
+        assert!(prepare_batch(vec![input(39.0, None, 10)], 30, now).is_err());
+        assert!(prepare_batch(vec![input(39.0, None, 0); MAX_BATCH_PINGS + 1], 30, now).is_err());
+    }
+
+    #[test]
+    fn detects_visits_between_moves() {
+        // 30 minutes at one place, a drive, then 5 minutes somewhere else
+        let pings = vec![
+            ping(39.0000, -120.0000, 0),
+            ping(39.0002, -120.0001, 15),
+            ping(39.0001, -120.0000, 30),
+            ping(39.0500, -120.0500, 40),
+            ping(39.1000, -120.1000, 50),
+            ping(39.1001, -120.1000, 55),
+        ];
+
+        let detection = detect_visits(&pings, at(300));
+        assert_eq!(detection.visits.len(), 1);
+        let visit = &detection.visits[0];
+        assert_eq!(visit.arrived_at, at(0));
+        assert_eq!(visit.departed_at, at(30));
+        assert_eq!(visit.ping_count, 3);
+        assert!((visit.latitude - 39.0001).abs() < 1e-7);
+        // The last stay has long gone quiet
+        assert_eq!(detection.open_from, None);
+    }
+
+    #[test]
+    fn leaves_the_current_stay_open() {
+        let pings = vec![
+            ping(39.0, -120.0, 0),
+            ping(39.0, -120.0, 20),
+            ping(39.1, -120.1, 40),
+            ping(39.1, -120.1, 60),
+        ];
+
+        let detection = detect_visits(&pings, at(70));
+        assert_eq!(detection.visits.len(), 1);
+        assert_eq!(detection.open_from, Some(at(40)));
+    }
+
+    #[test]
+    fn long_gaps_end_visits() {
+        let pings = vec![
+            ping(39.0, -120.0, 0),
+            ping(39.0, -120.0, 20),
+            ping(39.0, -120.0, 20 + MAX_PING_GAP_MINUTES + 1),
+            ping(39.0, -120.0, 40 + MAX_PING_GAP_MINUTES + 1),
+        ];
+
+        let detection = detect_visits(&pings, at(1000));
+        assert_eq!(detection.visits.len(), 2);
+        assert_eq!(detection.visits[0].departed_at, at(20));
+    }
+
+    #[test]
+    fn describes_visits() {
+        assert_eq!(
+            visit_description("Lake Tahoe ", at(125), at(280)),
+            "Visited Lake Tahoe from 14:05 to 16:40"
+        );
+        assert_eq!(
+            visit_description("Cabin", at(600), at(1300)),
+            "Visited Cabin from 2026-10-16 22:00 to 2026-10-17 09:40"
+        );
+    }
+}
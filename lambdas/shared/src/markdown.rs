@@ -0,0 +1,179 @@
+//! Markdown helpers for the chat platforms.
+//!
+//! Agent answers are Markdown of arbitrary length. Slack, Discord and SMS each
+//! render a different subset of it (SMS none) and cap how long a message can
+//! be, so each converts answers its own way, but from the same pieces:
+//! [`heading`], [`list_item`] and [`rewrite_links`] to recognise the syntax,
+//! [`split`] and [`truncate`] to fit the result.
+
+/// Code block fence
+pub const FENCE: &str = "```";
+
+/// Level and text of a `#` to `######` heading.
+pub fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let text = line[level..].strip_prefix(' ')?;
+    (1..=6).contains(&level).then_some((level, text.trim()))
+}
+
+/// Text of a `-` or `*` list item.
+pub fn list_item(line: &str) -> Option<&str> {
+    line.strip_prefix("- ").or_else(|| line.strip_prefix("* "))
+}
+
+/// Rewrite each `[label](url)` with `render(label, url)`, leaving brackets
+/// that aren't links as they are.
+pub fn rewrite_links(text: &str, render: impl Fn(&str, &str) -> String) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(open) = rest.find('[') {
+        let after = &rest[open + 1..];
+        let link = after.find("](").and_then(|close| {
+            let target = &after[close + 2..];
+            let end = target.find(')')?;
+            Some((&after[..close], &target[..end], &target[end + 1..]))
+        });
+
+        match link {
+            Some((label, url, remainder)) if !label.contains('[') && !url.contains(' ') => {
+                out.push_str(&rest[..open]);
+                out.push_str(&render(label, url));
+                rest = remainder;
+            }
+            _ => {
+                out.push_str(&rest[..=open]);
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Split text into parts of at most `max_chars` characters.
+///
+/// Prefers paragraph, then line, then word boundaries. A code block cut by a
+/// split is closed at the end of one part and reopened at the start of the next.
+pub fn split(text: &str, max_chars: usize) -> Vec<String> {
+    // Room for a reopened fence at the start and a closing fence at the end
+    let budget = max_chars.saturating_sub(2 * (FENCE.len() + 1)).max(1);
+
+    let mut parts = Vec::new();
+    let mut rest = text.trim();
+    let mut reopen_fence = false;
+
+    while !rest.is_empty() {
+        let (piece, remainder) = match rest.char_indices().nth(budget) {
+            None => (rest, ""),
+            Some((limit, _)) => {
+                let window = &rest[..limit];
+                let split = window
+                    .rfind("\n\n")
+                    .or_else(|| window.rfind('\n'))
+                    .or_else(|| window.rfind(' '))
+                    .filter(|i| *i > 0)
+                    .unwrap_or(limit);
+                (&rest[..split], &rest[split..])
+            }
+        };
+
+        let mut part = String::with_capacity(piece.len() + 2 * FENCE.len() + 2);
+        if reopen_fence {
+            part.push_str(FENCE);
+            part.push('\n');
+        }
+        part.push_str(piece.trim_end());
+
+        // An odd number of fences leaves this part's state flipped
+        if piece.matches(FENCE).count() % 2 == 1 {
+            reopen_fence = !reopen_fence;
+        }
+        if reopen_fence && !remainder.trim().is_empty() {
+            part.push('\n');
+            part.push_str(FENCE);
+        }
+
+        parts.push(part);
+        rest = remainder.trim_start();
+    }
+
+    parts
+}
+
+/// Truncate to `max_chars`, marking the cut with an ellipsis.
+pub fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars.saturating_sub(1)) {
+        Some((idx, _)) if text.chars().count() > max_chars => format!("{}…", &text[..idx]),
+        _ => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heading() {
+        assert_eq!(heading("## Plans "), Some((2, "Plans")));
+        assert_eq!(heading("#### Details"), Some((4, "Details")));
+        assert_eq!(heading("#hashtag"), None);
+        assert_eq!(heading("####### Too deep"), None);
+        assert_eq!(heading("Plain"), None);
+    }
+
+    #[test]
+    fn test_list_item() {
+        assert_eq!(list_item("- one"), Some("one"));
+        assert_eq!(list_item("* two"), Some("two"));
+        assert_eq!(list_item("*bold*"), None);
+    }
+
+    #[test]
+    fn test_rewrite_links() {
+        let render = |label: &str, url: &str| format!("{} ({})", label, url);
+        assert_eq!(
+            rewrite_links("See [the map](https://example.com) now", render),
+            "See the map (https://example.com) now"
+        );
+        assert_eq!(
+            rewrite_links("[not a link] (x)", render),
+            "[not a link] (x)"
+        );
+        assert_eq!(rewrite_links("[a [b](c)", render), "[a b (c)");
+    }
+
+    #[test]
+    fn test_split_prefers_boundaries() {
+        let text = format!("{}\n\n{}", "a".repeat(30), "b".repeat(30));
+        assert_eq!(split(&text, 50), vec!["a".repeat(30), "b".repeat(30)]);
+        assert_eq!(split("short", 50), vec!["short"]);
+        assert!(split("", 50).is_empty());
+    }
+
+    #[test]
+    fn test_split_within_limit() {
+        let text = "word ".repeat(1000);
+        let parts = split(&text, 2000);
+        assert!(parts.len() > 1);
+        assert!(parts.iter().all(|p| p.chars().count() <= 2000));
+    }
+
+    #[test]
+    fn test_split_reopens_code_blocks() {
+        let text = format!("```\n{}\n```", "line\n".repeat(20));
+        let parts = split(&text, 40);
+        assert!(parts.len() > 1);
+        for p in &parts {
+            assert!(p.chars().count() <= 40);
+            assert_eq!(p.matches(FENCE).count() % 2, 0, "unbalanced part: {:?}", p);
+        }
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("exactly10!", 10), "exactly10!");
+        assert_eq!(truncate("a longer text", 5), "a lo…");
+        assert_eq!(truncate("héllo wörld", 6).chars().count(), 6);
+    }
+}
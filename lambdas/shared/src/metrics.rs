@@ -0,0 +1,302 @@
+//! CloudWatch metrics in Embedded Metric Format (EMF).
+//!
+//! Metrics are written to stdout as structured log lines that CloudWatch Logs
+//! extracts into metrics, so recording one costs no API call. Every record has
+//! the same dimensions: `Service` (the Lambda function name) and `Operation`
+//! (a route such as `GET /reminders/{id}`, an agent intent or a notification
+//! channel), and is also rolled up by `Service` alone for function-wide alarms.
+//!
+//! Router binaries add the [`RequestMetrics`] middleware; other HTTP handlers
+//! are wrapped in [`track_request`] and event handlers in [`track_invocation`]:
+//!
+//! ```ignore
+//! run(service_fn(move |event| {
+//!     let state = Arc::clone(&state);
+//!     async move { metrics::track_invocation("evaluate", handler(state, event)).await }
+//! }))
+//! ```
+
+use chrono::Utc;
+use lambda_http::{Body, Request, Response};
+use serde_json::{json, Map, Value};
+use std::fmt::Display;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use crate::router::{HandlerResult, Middleware, RequestInfo};
+
+/// Namespace metrics are published under, unless `METRICS_NAMESPACE` is set.
+pub const DEFAULT_NAMESPACE: &str = "SecondBrain";
+
+/// Requests (or event invocations) handled
+pub const REQUESTS: &str = "Requests";
+/// Time to handle a request or invocation
+pub const LATENCY: &str = "Latency";
+/// Requests answered with a 5xx, or invocations that failed
+pub const ERRORS: &str = "Errors";
+/// Requests answered with a 4xx
+pub const CLIENT_ERRORS: &str = "ClientErrors";
+/// Time spent in a database query
+pub const DB_QUERY_TIME: &str = "DbQueryTime";
+/// Time for the agents (or the direct fallback) to answer
+pub const AGENT_LATENCY: &str = "AgentLatency";
+/// Agent invocations that failed
+pub const AGENT_ERRORS: &str = "AgentErrors";
+/// Notifications handed to their channel
+pub const NOTIFICATIONS_DELIVERED: &str = "NotificationsDelivered";
+/// Notifications their channel refused or that couldn't be sent
+pub const NOTIFICATIONS_FAILED: &str = "NotificationsFailed";
+
+/// Reported as the `Operation` of requests that matched no route.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// EMF units used by this module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Count,
+    Milliseconds,
+}
+
+impl Unit {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Count => "Count",
+            Self::Milliseconds => "Milliseconds",
+        }
+    }
+}
+
+/// Metrics recorded together for one operation, emitted as a single EMF record.
+#[derive(Debug, Clone)]
+pub struct MetricSet {
+    operation: String,
+    values: Vec<(&'static str, f64, Unit)>,
+    properties: Map<String, Value>,
+}
+
+impl MetricSet {
+    pub fn new(operation: impl Into<String>) -> Self {
+        Self {
+            operation: operation.into(),
+            values: Vec::new(),
+            properties: Map::new(),
+        }
+    }
+
+    /// Add a count.
+    pub fn count(mut self, name: &'static str, value: u64) -> Self {
+        self.values.push((name, value as f64, Unit::Count));
+        self
+    }
+
+    /// Add a duration, in milliseconds.
+    pub fn duration(mut self, name: &'static str, elapsed: Duration) -> Self {
+        self.values
+            .push((name, elapsed.as_secs_f64() * 1000.0, Unit::Milliseconds));
+        self
+    }
+
+    /// Attach a value that is logged with the record but isn't a dimension
+    /// (searchable in Logs Insights without adding metric cardinality).
+    pub fn property(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.properties.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// Write the record to stdout for CloudWatch to pick up.
+    pub fn emit(self) {
+        if self.values.is_empty() {
+            return;
+        }
+        println!("{}", self.to_emf(&namespace(), &service(), Utc::now().timestamp_millis()));
+    }
+
+    fn to_emf(&self, namespace: &str, service: &str, timestamp: i64) -> Value {
+        let definitions: Vec<Value> = self
+            .values
+            .iter()
+            .map(|(name, _, unit)| json!({ "Name": name, "Unit": unit.as_str() }))
+            .collect();
+
+        let mut record = self.properties.clone();
+        record.insert(
+            "_aws".to_string(),
+            json!({
+                "Timestamp": timestamp,
+                "CloudWatchMetrics": [{
+                    "Namespace": namespace,
+                    "Dimensions": [["Service"], ["Service", "Operation"]],
+                    "Metrics": definitions,
+                }],
+            }),
+        );
+        record.insert("Service".to_string(), service.into());
+        record.insert("Operation".to_string(), self.operation.clone().into());
+        for (name, value, _) in &self.values {
+            record.insert(name.to_string(), json!(value));
+        }
+        Value::Object(record)
+    }
+}
+
+fn namespace() -> String {
+    std::env::var("METRICS_NAMESPACE").unwrap_or_else(|_| DEFAULT_NAMESPACE.to_string())
+}
+
+/// The Lambda function name, or `local` outside Lambda.
+fn service() -> String {
+    std::env::var("AWS_LAMBDA_FUNCTION_NAME").unwrap_or_else(|_| "local".to_string())
+}
+
+/// Record a handled HTTP request.
+pub fn record_request(operation: &str, status: u16, latency: Duration) {
+    MetricSet::new(operation)
+        .count(REQUESTS, 1)
+        .duration(LATENCY, latency)
+        .count(ERRORS, u64::from(status >= 500))
+        .count(CLIENT_ERRORS, u64::from((400..500).contains(&status)))
+        .property("StatusCode", status)
+        .emit();
+}
+
+/// Record an agent invocation; `operation` is its intent.
+pub fn record_agent(operation: &str, latency: Duration, succeeded: bool) {
+    MetricSet::new(operation)
+        .duration(AGENT_LATENCY, latency)
+        .count(AGENT_ERRORS, u64::from(!succeeded))
+        .emit();
+}
+
+/// Record a notification delivery attempt on `channel` (`email`, `push`, ...).
+pub fn record_delivery(channel: &str, delivered: bool) {
+    MetricSet::new(channel)
+        .count(NOTIFICATIONS_DELIVERED, u64::from(delivered))
+        .count(NOTIFICATIONS_FAILED, u64::from(!delivered))
+        .emit();
+}
+
+/// Run a database query, recording how long it took as `DbQueryTime`.
+pub async fn timed_query<T>(operation: &str, query: impl Future<Output = T>) -> T {
+    let started = Instant::now();
+    let result = query.await;
+    MetricSet::new(operation)
+        .duration(DB_QUERY_TIME, started.elapsed())
+        .emit();
+    result
+}
+
+/// Run an event handler, recording the invocation, its latency and whether it failed.
+pub async fn track_invocation<T, E: Display>(
+    operation: &str,
+    handler: impl Future<Output = std::result::Result<T, E>>,
+) -> std::result::Result<T, E> {
+    let started = Instant::now();
+    let result = handler.await;
+    let mut metrics = MetricSet::new(operation)
+        .count(REQUESTS, 1)
+        .duration(LATENCY, started.elapsed())
+        .count(ERRORS, u64::from(result.is_err()));
+    if let Err(e) = &result {
+        metrics = metrics.property("Error", e.to_string());
+    }
+    metrics.emit();
+    result
+}
+
+/// Run an HTTP handler that doesn't use the router, recording the request.
+///
+/// Handler errors are counted as 500s, which is what API Gateway returns for them.
+pub async fn track_request<F, Fut>(req: Request, handler: F) -> HandlerResult
+where
+    F: FnOnce(Request) -> Fut,
+    Fut: Future<Output = HandlerResult>,
+{
+    let raw_path = req.uri().path();
+    let path = raw_path.strip_prefix("/api").unwrap_or(raw_path);
+    let operation = request_operation(req.method().as_str(), path);
+    let started = Instant::now();
+
+    let result = handler(req).await;
+    let status = result.as_ref().map_or(500, |r| r.status().as_u16());
+    record_request(&operation, status, started.elapsed());
+    result
+}
+
+/// An `Operation` for a request path without a route pattern: IDs are replaced
+/// with `{id}` so each resource doesn't become its own metric.
+pub fn request_operation(method: &str, path: &str) -> String {
+    let segments: Vec<&str> = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            let is_id = uuid::Uuid::parse_str(s).is_ok() || s.bytes().all(|b| b.is_ascii_digit());
+            if is_id {
+                "{id}"
+            } else {
+                s
+            }
+        })
+        .collect();
+    format!("{} /{}", method, segments.join("/"))
+}
+
+/// Records `Requests`, `Latency`, `Errors` and `ClientErrors` per route.
+pub struct RequestMetrics;
+
+impl Middleware for RequestMetrics {
+    fn after(&self, info: &RequestInfo, response: &mut Response<Body>) {
+        let operation = format!(
+            "{} {}",
+            info.method,
+            info.route.as_deref().unwrap_or(UNMATCHED_ROUTE)
+        );
+        record_request(&operation, response.status().as_u16(), info.started.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emf_record_declares_its_metrics() {
+        let record = MetricSet::new("GET /reminders/{id}")
+            .count(REQUESTS, 1)
+            .duration(LATENCY, Duration::from_millis(42))
+            .property("StatusCode", 200)
+            .to_emf("SecondBrain", "second-brain-reminders", 1_700_000_000_000);
+
+        let definition = &record["_aws"]["CloudWatchMetrics"][0];
+        assert_eq!(definition["Namespace"], "SecondBrain");
+        assert_eq!(
+            definition["Dimensions"],
+            json!([["Service"], ["Service", "Operation"]])
+        );
+        assert_eq!(
+            definition["Metrics"],
+            json!([
+                { "Name": "Requests", "Unit": "Count" },
+                { "Name": "Latency", "Unit": "Milliseconds" },
+            ])
+        );
+        assert_eq!(record["_aws"]["Timestamp"], 1_700_000_000_000i64);
+
+        assert_eq!(record["Service"], "second-brain-reminders");
+        assert_eq!(record["Operation"], "GET /reminders/{id}");
+        assert_eq!(record["Requests"], 1.0);
+        assert_eq!(record["Latency"], 42.0);
+        assert_eq!(record["StatusCode"], 200);
+    }
+
+    #[test]
+    fn request_operation_replaces_ids() {
+        let id = uuid::Uuid::new_v4();
+        assert_eq!(
+            request_operation("GET", &format!("/entities/{}/facts", id)),
+            "GET /entities/{id}/facts"
+        );
+        assert_eq!(request_operation("DELETE", "/locations/42/"), "DELETE /locations/{id}");
+        assert_eq!(request_operation("POST", "/query"), "POST /query");
+        assert_eq!(request_operation("GET", "/"), "GET /");
+    }
+}
@@ -1,9 +1,14 @@
 //! Shared data models.
 
-use chrono::{DateTime, Utc};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::{Error, Result};
+
 /// User context extracted from JWT.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserContext {
@@ -39,31 +44,486 @@ impl<T> ApiResponse<T> {
 }
 
 /// Query request payload.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct QueryRequest {
     pub query: String,
     pub session_id: Option<String>,
+    /// Conversation to continue (see `conversations`); a new one is started
+    /// when unset
+    #[serde(default)]
+    pub conversation_id: Option<Uuid>,
 }
 
 /// Query response payload.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct QueryResponse {
     pub response: String,
     pub session_id: String,
     pub agents_used: Vec<String>,
+    /// Pass back as `conversation_id` to ask a follow-up
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conversation_id: Option<Uuid>,
+    /// Give feedback on the answer against this ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query_id: Option<Uuid>,
 }
 
 /// Ingest request payload.
-#[derive(Debug, Deserialize)]
+///
+/// Only `content` is required; the structured fields are passed to the agent as
+/// hints alongside the free text.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, ToSchema)]
 pub struct IngestRequest {
     pub content: String,
     pub visibility_tier: Option<i16>,
+    /// Name of the entity the fact is about, e.g. "Emma"
+    #[serde(default)]
+    pub about_entity: Option<String>,
+    /// Date the fact happened or became true
+    #[serde(default)]
+    pub valid_from: Option<NaiveDate>,
+    /// Importance from 1 (trivia) to 5 (critical)
+    #[serde(default)]
+    pub importance: Option<i16>,
+}
+
+impl IngestRequest {
+    /// Validate the request before handing it to the agent.
+    pub fn validate(&self) -> Result<()> {
+        if self.content.trim().is_empty() {
+            return Err(Error::Validation("Content cannot be empty".to_string()));
+        }
+        if let Some(tier) = self.visibility_tier {
+            if !(1..=4).contains(&tier) {
+                return Err(Error::Validation(
+                    "Visibility tier must be between 1 and 4".to_string(),
+                ));
+            }
+        }
+        if let Some(importance) = self.importance {
+            if !(1..=5).contains(&importance) {
+                return Err(Error::Validation(
+                    "Importance must be between 1 and 5".to_string(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Message sent to the agent's ingest intent.
+    pub fn agent_message(&self) -> String {
+        let mut message = match self.visibility_tier {
+            Some(tier) => format!(
+                "Remember this (visibility tier {}): {}",
+                tier,
+                self.content.trim()
+            ),
+            None => format!("Remember this: {}", self.content.trim()),
+        };
+
+        if let Some(entity) = self.about_entity.as_deref().map(str::trim).filter(|e| !e.is_empty()) {
+            message.push_str(&format!("\nAbout: {}", entity));
+        }
+        if let Some(date) = self.valid_from {
+            message.push_str(&format!("\nDate: {}", date.format("%Y-%m-%d")));
+        }
+        if let Some(importance) = self.importance {
+            message.push_str(&format!("\nImportance: {}/5", importance));
+        }
+
+        message
+    }
 }
 
 /// Ingest response payload.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct IngestResponse {
     pub fact_id: Uuid,
     pub message: String,
     pub entities_created: Vec<String>,
+    /// Older fact this one replaced (e.g. a previous address), now closed off
+    pub superseded_fact_id: Option<Uuid>,
+    /// Earlier fact this one repeats. When it was merged, `fact_id` is the
+    /// earlier fact; otherwise the new fact is kept but flagged.
+    pub duplicate_of: Option<Uuid>,
+}
+
+/// What a voice request asks for, whichever assistant it came from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "intent", rename_all = "snake_case")]
+pub enum VoiceIntent {
+    /// The assistant opened the app without a request
+    Welcome,
+    Ask {
+        query: String,
+    },
+    Remember {
+        fact: String,
+    },
+    Briefing {
+        /// `morning` (the default) or `evening`
+        #[serde(default)]
+        briefing_type: Option<String>,
+    },
+    Help,
+    Stop,
+}
+
+/// Voice assistant request, normalized from each assistant's webhook format
+/// (or sent as-is by assistants without an adapter).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoiceRequest {
+    #[serde(flatten)]
+    pub intent: VoiceIntent,
+    /// Account linking token (a Cognito access token)
+    #[serde(default)]
+    pub access_token: Option<String>,
+    /// Assistant conversation; follow-up questions in it share context
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// e.g. `en-US`
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Where the request came from, recorded as the agent request source
+    #[serde(default = "default_assistant")]
+    pub assistant: String,
+}
+
+fn default_assistant() -> String {
+    "voice".to_string()
+}
+
+/// Reply to a [`VoiceRequest`], rendered into the assistant's format.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoiceResponse {
+    /// Text to speak and display
+    pub speech: String,
+    /// Audio to play instead of speaking `speech` (a presigned URL)
+    #[serde(default)]
+    pub audio_url: Option<String>,
+    /// Whether the conversation ends after this reply
+    pub end_session: bool,
+    /// The user needs to link their Second Brain account first
+    #[serde(default)]
+    pub link_account: bool,
+}
+
+impl VoiceResponse {
+    /// Speak and end the conversation.
+    pub fn say(speech: impl Into<String>) -> Self {
+        Self {
+            speech: speech.into(),
+            audio_url: None,
+            end_session: true,
+            link_account: false,
+        }
+    }
+
+    /// Speak and wait for the user's reply.
+    pub fn prompt(speech: impl Into<String>) -> Self {
+        Self {
+            end_session: false,
+            ..Self::say(speech)
+        }
+    }
+
+    /// Ask the user to link their account.
+    pub fn link_account() -> Self {
+        Self {
+            link_account: true,
+            ..Self::say("Please link your Second Brain account to continue.")
+        }
+    }
+}
+
+/// Versioned envelope placed in the EventBridge `detail` field of a domain
+/// event (see `events::EventPublisher`). Consumers deserialize `data` by
+/// matching on `detail-type` and `schema_version`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventEnvelope<E> {
+    pub event_id: Uuid,
+    pub schema_version: u32,
+    pub occurred_at: DateTime<Utc>,
+    pub data: E,
+}
+
+impl<E> EventEnvelope<E> {
+    /// Wrap `data` with a fresh event id, stamped now.
+    pub fn new(schema_version: u32, data: E) -> Self {
+        Self {
+            event_id: Uuid::new_v4(),
+            schema_version,
+            occurred_at: Utc::now(),
+            data,
+        }
+    }
+}
+
+/// Default page size for list endpoints.
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+
+/// Maximum page size a client may request.
+pub const MAX_PAGE_SIZE: i64 = 200;
+
+/// Sort direction for keyset pagination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+/// Position of the last row on a page: its sort key (as text) and its id.
+///
+/// The id is the tie-breaker so rows sharing a sort key are never skipped or repeated.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cursor {
+    #[serde(rename = "k")]
+    pub key: String,
+    #[serde(rename = "i")]
+    pub id: Uuid,
+}
+
+impl Cursor {
+    pub fn new(key: impl Into<String>, id: Uuid) -> Self {
+        Self {
+            key: key.into(),
+            id,
+        }
+    }
+
+    /// Encode as an opaque, URL-safe token.
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    /// Decode a token produced by [`Cursor::encode`].
+    pub fn decode(token: &str) -> crate::Result<Self> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(token)
+            .map_err(|_| crate::Error::Validation("Invalid cursor".to_string()))?;
+        serde_json::from_slice(&bytes)
+            .map_err(|_| crate::Error::Validation("Invalid cursor".to_string()))
+    }
+}
+
+/// Pagination parameters parsed from `?limit=` and `?cursor=`.
+#[derive(Debug, Clone)]
+pub struct CursorParams {
+    pub limit: i64,
+    pub after: Option<Cursor>,
+}
+
+impl CursorParams {
+    /// Parse from raw query string values, clamping the limit to `1..=MAX_PAGE_SIZE`.
+    pub fn from_query(
+        limit: Option<&str>,
+        cursor: Option<&str>,
+        default_limit: i64,
+    ) -> crate::Result<Self> {
+        let limit = limit
+            .and_then(|l| l.parse::<i64>().ok())
+            .unwrap_or(default_limit)
+            .clamp(1, MAX_PAGE_SIZE);
+
+        let after = match cursor {
+            Some(c) if !c.is_empty() => Some(Cursor::decode(c)?),
+            _ => None,
+        };
+
+        Ok(Self { limit, after })
+    }
+
+    /// Rows to fetch: one extra so we can tell whether another page exists.
+    pub fn fetch_limit(&self) -> i64 {
+        self.limit + 1
+    }
+
+    /// Sort key of the cursor, for binding into a [`keyset_predicate`].
+    pub fn after_key(&self) -> Option<&str> {
+        self.after.as_ref().map(|c| c.key.as_str())
+    }
+
+    /// Id of the cursor, for binding into a [`keyset_predicate`].
+    pub fn after_id(&self) -> Option<Uuid> {
+        self.after.as_ref().map(|c| c.id)
+    }
+}
+
+/// A page of results with an opaque cursor for the next page.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub has_more: bool,
+}
+
+impl<T> Page<T> {
+    /// Build a page from rows fetched with [`CursorParams::fetch_limit`].
+    pub fn from_rows(
+        mut rows: Vec<T>,
+        params: &CursorParams,
+        cursor_for: impl Fn(&T) -> Cursor,
+    ) -> Self {
+        let has_more = rows.len() as i64 > params.limit;
+        rows.truncate(params.limit as usize);
+
+        let next_cursor = if has_more {
+            rows.last().map(|row| cursor_for(row).encode())
+        } else {
+            None
+        };
+
+        Self {
+            items: rows,
+            next_cursor,
+            has_more,
+        }
+    }
+
+    /// Convert the items, keeping the cursor.
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+            has_more: self.has_more,
+        }
+    }
+}
+
+/// Build a keyset predicate that is a no-op when no cursor is bound.
+///
+/// `key_expr` is the sort expression, `key_type` the SQL type the cursor key is cast to,
+/// and `param` the index of the key parameter (the id is bound at `param + 1`):
+///
+/// ```ignore
+/// keyset_predicate("f.created_at", "timestamptz", "f.id", 3, SortDirection::Desc)
+/// // ($3::timestamptz IS NULL OR (f.created_at, f.id) < ($3::timestamptz, $4))
+/// ```
+pub fn keyset_predicate(
+    key_expr: &str,
+    key_type: &str,
+    id_column: &str,
+    param: usize,
+    direction: SortDirection,
+) -> String {
+    let op = match direction {
+        SortDirection::Asc => ">",
+        SortDirection::Desc => "<",
+    };
+    format!(
+        "(${p}::{t} IS NULL OR ({k}, {id}) {op} (${p}::{t}, ${n}))",
+        p = param,
+        t = key_type,
+        k = key_expr,
+        id = id_column,
+        op = op,
+        n = param + 1,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = Cursor::new("2026-01-15T09:00:00+00:00", Uuid::new_v4());
+        let decoded = Cursor::decode(&cursor.encode()).unwrap();
+        assert_eq!(decoded, cursor);
+        assert!(Cursor::decode("not-a-cursor").is_err());
+    }
+
+    #[test]
+    fn test_page_from_rows() {
+        let params = CursorParams::from_query(Some("2"), None, DEFAULT_PAGE_SIZE).unwrap();
+        let ids: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+
+        let page = Page::from_rows(ids.clone(), &params, |id| Cursor::new("k", *id));
+        assert_eq!(page.items, ids[..2]);
+        assert!(page.has_more);
+        assert_eq!(Cursor::decode(page.next_cursor.as_deref().unwrap()).unwrap().id, ids[1]);
+
+        let page = Page::from_rows(ids[..1].to_vec(), &params, |id| Cursor::new("k", *id));
+        assert!(!page.has_more);
+        assert!(page.next_cursor.is_none());
+    }
+
+    #[test]
+    fn test_ingest_request_agent_message() {
+        let request = IngestRequest {
+            content: " Emma's recital is at 6pm ".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(request.agent_message(), "Remember this: Emma's recital is at 6pm");
+
+        let request = IngestRequest {
+            content: "Recital at 6pm".to_string(),
+            visibility_tier: Some(2),
+            about_entity: Some("Emma".to_string()),
+            valid_from: NaiveDate::from_ymd_opt(2026, 11, 3),
+            importance: Some(4),
+        };
+        assert_eq!(
+            request.agent_message(),
+            "Remember this (visibility tier 2): Recital at 6pm\nAbout: Emma\nDate: 2026-11-03\nImportance: 4/5"
+        );
+    }
+
+    #[test]
+    fn test_ingest_request_validate() {
+        let valid = IngestRequest {
+            content: "Fact".to_string(),
+            importance: Some(5),
+            ..Default::default()
+        };
+        assert!(valid.validate().is_ok());
+
+        let empty = IngestRequest {
+            content: "  ".to_string(),
+            ..Default::default()
+        };
+        assert!(empty.validate().is_err());
+
+        let bad_importance = IngestRequest {
+            importance: Some(6),
+            ..valid
+        };
+        assert!(bad_importance.validate().is_err());
+    }
+
+    #[test]
+    fn test_voice_request_format() {
+        let request: VoiceRequest = serde_json::from_value(serde_json::json!({
+            "intent": "ask",
+            "query": "When is Emma's recital?",
+            "access_token": "token",
+        }))
+        .unwrap();
+        assert_eq!(
+            request.intent,
+            VoiceIntent::Ask {
+                query: "When is Emma's recital?".to_string()
+            }
+        );
+        assert_eq!(request.assistant, "voice");
+
+        let request: VoiceRequest =
+            serde_json::from_value(serde_json::json!({ "intent": "briefing" })).unwrap();
+        assert_eq!(
+            request.intent,
+            VoiceIntent::Briefing {
+                briefing_type: None
+            }
+        );
+        assert!(request.access_token.is_none());
+    }
+
+    #[test]
+    fn test_keyset_predicate() {
+        assert_eq!(
+            keyset_predicate("f.created_at", "timestamptz", "f.id", 3, SortDirection::Desc),
+            "($3::timestamptz IS NULL OR (f.created_at, f.id) < ($3::timestamptz, $4))"
+        );
+    }
 }
@@ -0,0 +1,526 @@
+//! Notification preferences as managed through `/preferences/notifications`.
+//!
+//! Everything that sends notifications reads `user_notification_preferences`:
+//! the reminder evaluator (channels, quiet hours and per-type overrides, see
+//! [`crate::reminders::NotificationPreferences`]), the briefing dispatcher and
+//! digest builder (briefing times and digest mode), weekly reviews and the
+//! occasion scanner. A user without a row gets the column defaults, which
+//! [`Preferences::default`] mirrors.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveTime, Timelike, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{Error, Result};
+
+/// Notification types (`notification_type`) that can be overridden
+pub const NOTIFICATION_TYPES: &[&str] = &[
+    "reminder",
+    "briefing",
+    "calendar",
+    "birthday",
+    "proactive",
+    "system",
+    "handoff",
+    "digest",
+];
+
+/// Channels notifications can be delivered on
+pub const CHANNELS: &[&str] = &["push", "email", "discord", "alexa"];
+
+/// `digest_mode` values
+pub const DIGEST_MODES: &[&str] = &["off", "morning", "evening", "both"];
+
+/// Most notifications a user can allow per hour
+pub const MAX_NOTIFICATIONS_PER_HOUR: i16 = 60;
+
+/// Most days before an occasion its reminder can be set
+pub const MAX_OCCASION_REMINDER_DAYS: i16 = 60;
+
+/// How one notification type is delivered, overriding the defaults.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeOverride {
+    /// `false` turns the type off
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    /// Deliver on this channel instead of the preferred one
+    #[serde(default)]
+    pub channel: Option<String>,
+    /// Deliver during quiet hours instead of waiting for them to end
+    #[serde(default)]
+    pub ignore_quiet_hours: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// Overrides by notification type
+pub type TypeOverrides = BTreeMap<String, TypeOverride>;
+
+/// A user's notification preferences.
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Preferences {
+    pub push_enabled: bool,
+    pub email_enabled: bool,
+    pub discord_enabled: bool,
+    pub alexa_enabled: bool,
+    pub quiet_hours_enabled: bool,
+    pub quiet_hours_start: Option<NaiveTime>,
+    pub quiet_hours_end: Option<NaiveTime>,
+    pub morning_briefing_enabled: bool,
+    pub morning_briefing_time: NaiveTime,
+    pub evening_briefing_enabled: bool,
+    pub evening_briefing_time: NaiveTime,
+    /// `off`, `morning`, `evening` or `both`
+    pub digest_mode: String,
+    pub weekly_review_enabled: bool,
+    /// 0 = Sunday ... 6 = Saturday
+    pub weekly_review_day: i16,
+    pub occasion_reminders_enabled: bool,
+    pub occasion_reminder_days: i16,
+    /// IANA name, e.g. `Europe/London`
+    pub timezone: String,
+    pub max_notifications_per_hour: i16,
+    #[schema(value_type = BTreeMap<String, TypeOverride>)]
+    pub type_overrides: Json<TypeOverrides>,
+    /// `None` until the user first saves their preferences
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl Default for Preferences {
+    /// The column defaults, used when a user has no preferences row.
+    fn default() -> Self {
+        Self {
+            push_enabled: true,
+            email_enabled: true,
+            discord_enabled: true,
+            alexa_enabled: false,
+            quiet_hours_enabled: false,
+            quiet_hours_start: None,
+            quiet_hours_end: None,
+            morning_briefing_enabled: true,
+            morning_briefing_time: NaiveTime::from_hms_opt(7, 0, 0).unwrap_or_default(),
+            evening_briefing_enabled: false,
+            evening_briefing_time: NaiveTime::from_hms_opt(18, 0, 0).unwrap_or_default(),
+            digest_mode: "morning".to_string(),
+            weekly_review_enabled: true,
+            weekly_review_day: 0,
+            occasion_reminders_enabled: true,
+            occasion_reminder_days: 7,
+            timezone: "America/New_York".to_string(),
+            max_notifications_per_hour: 10,
+            type_overrides: Json(TypeOverrides::new()),
+            updated_at: None,
+        }
+    }
+}
+
+/// Changes to a user's preferences; fields left out are kept.
+#[derive(Debug, Clone, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PreferencesUpdate {
+    pub push_enabled: Option<bool>,
+    pub email_enabled: Option<bool>,
+    pub discord_enabled: Option<bool>,
+    pub alexa_enabled: Option<bool>,
+    pub quiet_hours_enabled: Option<bool>,
+    pub quiet_hours_start: Option<NaiveTime>,
+    pub quiet_hours_end: Option<NaiveTime>,
+    pub morning_briefing_enabled: Option<bool>,
+    pub morning_briefing_time: Option<NaiveTime>,
+    pub evening_briefing_enabled: Option<bool>,
+    pub evening_briefing_time: Option<NaiveTime>,
+    pub digest_mode: Option<String>,
+    pub weekly_review_enabled: Option<bool>,
+    pub weekly_review_day: Option<i16>,
+    pub occasion_reminders_enabled: Option<bool>,
+    pub occasion_reminder_days: Option<i16>,
+    pub timezone: Option<String>,
+    pub max_notifications_per_hour: Option<i16>,
+    /// Replaces all overrides
+    #[schema(value_type = Option<BTreeMap<String, TypeOverride>>)]
+    pub type_overrides: Option<TypeOverrides>,
+}
+
+impl PreferencesUpdate {
+    /// Apply the changes to `current` and check the result.
+    pub fn apply(self, current: Preferences) -> Result<Preferences> {
+        let prefs = Preferences {
+            push_enabled: self.push_enabled.unwrap_or(current.push_enabled),
+            email_enabled: self.email_enabled.unwrap_or(current.email_enabled),
+            discord_enabled: self.discord_enabled.unwrap_or(current.discord_enabled),
+            alexa_enabled: self.alexa_enabled.unwrap_or(current.alexa_enabled),
+            quiet_hours_enabled: self
+                .quiet_hours_enabled
+                .unwrap_or(current.quiet_hours_enabled),
+            quiet_hours_start: self.quiet_hours_start.or(current.quiet_hours_start),
+            quiet_hours_end: self.quiet_hours_end.or(current.quiet_hours_end),
+            morning_briefing_enabled: self
+                .morning_briefing_enabled
+                .unwrap_or(current.morning_briefing_enabled),
+            morning_briefing_time: self
+                .morning_briefing_time
+                .unwrap_or(current.morning_briefing_time),
+            evening_briefing_enabled: self
+                .evening_briefing_enabled
+                .unwrap_or(current.evening_briefing_enabled),
+            evening_briefing_time: self
+                .evening_briefing_time
+                .unwrap_or(current.evening_briefing_time),
+            digest_mode: self.digest_mode.unwrap_or(current.digest_mode),
+            weekly_review_enabled: self
+                .weekly_review_enabled
+                .unwrap_or(current.weekly_review_enabled),
+            weekly_review_day: self.weekly_review_day.unwrap_or(current.weekly_review_day),
+            occasion_reminders_enabled: self
+                .occasion_reminders_enabled
+                .unwrap_or(current.occasion_reminders_enabled),
+            occasion_reminder_days: self
+                .occasion_reminder_days
+                .unwrap_or(current.occasion_reminder_days),
+            timezone: self
+                .timezone
+                .map(|tz| tz.trim().to_string())
+                .unwrap_or(current.timezone),
+            max_notifications_per_hour: self
+                .max_notifications_per_hour
+                .unwrap_or(current.max_notifications_per_hour),
+            type_overrides: self
+                .type_overrides
+                .map(Json)
+                .unwrap_or(current.type_overrides),
+            updated_at: current.updated_at,
+        };
+
+        validate(&prefs)?;
+        Ok(prefs)
+    }
+}
+
+/// Whether `time` falls in the window from `start` to `end`, which wraps
+/// past midnight when `end` is earlier.
+fn in_window(start: NaiveTime, end: NaiveTime, time: NaiveTime) -> bool {
+    if start <= end {
+        time >= start && time < end
+    } else {
+        time >= start || time < end
+    }
+}
+
+/// Check preferences before they are saved.
+pub fn validate(prefs: &Preferences) -> Result<()> {
+    let invalid = |message: String| Err(Error::Validation(message));
+
+    if prefs.timezone.parse::<Tz>().is_err() {
+        return invalid(format!("Unknown timezone: {}", prefs.timezone));
+    }
+
+    let quiet_window = match (prefs.quiet_hours_start, prefs.quiet_hours_end) {
+        (Some(start), Some(end)) if start == end => {
+            return invalid("quietHoursStart and quietHoursEnd must differ".to_string());
+        }
+        (Some(start), Some(end)) => Some((start, end)),
+        _ if prefs.quiet_hours_enabled => {
+            return invalid("Quiet hours need quietHoursStart and quietHoursEnd".to_string());
+        }
+        _ => None,
+    };
+
+    // Briefings and digests go out hourly, at the hour of these times
+    let briefings = [
+        (
+            "morning",
+            prefs.morning_briefing_enabled,
+            prefs.morning_briefing_time,
+        ),
+        (
+            "evening",
+            prefs.evening_briefing_enabled,
+            prefs.evening_briefing_time,
+        ),
+    ];
+    for (name, enabled, time) in briefings {
+        if time.minute() != 0 || time.second() != 0 {
+            return invalid(format!("The {} briefing time must be on the hour", name));
+        }
+        if let (true, true, Some((start, end))) = (enabled, prefs.quiet_hours_enabled, quiet_window)
+        {
+            if in_window(start, end, time) {
+                return invalid(format!(
+                    "The {} briefing time falls in your quiet hours",
+                    name
+                ));
+            }
+        }
+    }
+    if prefs.morning_briefing_enabled
+        && prefs.evening_briefing_enabled
+        && prefs.morning_briefing_time == prefs.evening_briefing_time
+    {
+        return invalid("Morning and evening briefings must be at different times".to_string());
+    }
+
+    if !DIGEST_MODES.contains(&prefs.digest_mode.as_str()) {
+        return invalid(format!(
+            "digestMode must be one of {}",
+            DIGEST_MODES.join(", ")
+        ));
+    }
+    if !(0..=6).contains(&prefs.weekly_review_day) {
+        return invalid("weeklyReviewDay must be between 0 (Sunday) and 6".to_string());
+    }
+    if !(0..=MAX_OCCASION_REMINDER_DAYS).contains(&prefs.occasion_reminder_days) {
+        return invalid(format!(
+            "occasionReminderDays must be between 0 and {}",
+            MAX_OCCASION_REMINDER_DAYS
+        ));
+    }
+    if !(1..=MAX_NOTIFICATIONS_PER_HOUR).contains(&prefs.max_notifications_per_hour) {
+        return invalid(format!(
+            "maxNotificationsPerHour must be between 1 and {}",
+            MAX_NOTIFICATIONS_PER_HOUR
+        ));
+    }
+
+    for (notification_type, type_override) in prefs.type_overrides.iter() {
+        if !NOTIFICATION_TYPES.contains(&notification_type.as_str()) {
+            return invalid(format!("Unknown notification type: {}", notification_type));
+        }
+        if let Some(channel) = &type_override.channel {
+            if !CHANNELS.contains(&channel.as_str()) {
+                return invalid(format!("Unknown channel: {}", channel));
+            }
+            if !prefs.channel_enabled(channel) {
+                return invalid(format!(
+                    "{} notifications can't use {}, which is turned off",
+                    notification_type, channel
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl Preferences {
+    /// Whether delivery on `channel` is turned on.
+    pub fn channel_enabled(&self, channel: &str) -> bool {
+        match channel {
+            "push" => self.push_enabled,
+            "email" => self.email_enabled,
+            "discord" => self.discord_enabled,
+            "alexa" => self.alexa_enabled,
+            _ => false,
+        }
+    }
+}
+
+const PREFERENCE_COLUMNS: &str = r#"
+    push_enabled, email_enabled, discord_enabled, alexa_enabled,
+    quiet_hours_enabled, quiet_hours_start, quiet_hours_end,
+    morning_briefing_enabled, morning_briefing_time,
+    evening_briefing_enabled, evening_briefing_time,
+    digest_mode, weekly_review_enabled, weekly_review_day,
+    occasion_reminders_enabled, occasion_reminder_days,
+    timezone, max_notifications_per_hour, type_overrides, updated_at
+"#;
+
+/// A user's preferences, or the defaults if they haven't saved any.
+pub async fn load(pool: &PgPool, user_id: Uuid) -> Result<Preferences> {
+    let prefs: Option<Preferences> = sqlx::query_as(&format!(
+        "SELECT {} FROM user_notification_preferences WHERE user_id = $1",
+        PREFERENCE_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(prefs.unwrap_or_default())
+}
+
+/// Save a user's preferences (already checked by [`validate`]).
+pub async fn save(pool: &PgPool, user_id: Uuid, prefs: &Preferences) -> Result<Preferences> {
+    let saved = sqlx::query_as(&format!(
+        r#"
+        INSERT INTO user_notification_preferences (
+            user_id, push_enabled, email_enabled, discord_enabled, alexa_enabled,
+            quiet_hours_enabled, quiet_hours_start, quiet_hours_end,
+            morning_briefing_enabled, morning_briefing_time,
+            evening_briefing_enabled, evening_briefing_time,
+            digest_mode, weekly_review_enabled, weekly_review_day,
+            occasion_reminders_enabled, occasion_reminder_days,
+            timezone, max_notifications_per_hour, type_overrides, updated_at
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15,
+                  $16, $17, $18, $19, $20, NOW())
+        ON CONFLICT (user_id) DO UPDATE SET
+            push_enabled = EXCLUDED.push_enabled,
+            email_enabled = EXCLUDED.email_enabled,
+            discord_enabled = EXCLUDED.discord_enabled,
+            alexa_enabled = EXCLUDED.alexa_enabled,
+            quiet_hours_enabled = EXCLUDED.quiet_hours_enabled,
+            quiet_hours_start = EXCLUDED.quiet_hours_start,
+            quiet_hours_end = EXCLUDED.quiet_hours_end,
+            morning_briefing_enabled = EXCLUDED.morning_briefing_enabled,
+            morning_briefing_time = EXCLUDED.morning_briefing_time,
+            evening_briefing_enabled = EXCLUDED.evening_briefing_enabled,
+            evening_briefing_time = EXCLUDED.evening_briefing_time,
+            digest_mode = EXCLUDED.digest_mode,
+            weekly_review_enabled = EXCLUDED.weekly_review_enabled,
+            weekly_review_day = EXCLUDED.weekly_review_day,
+            occasion_reminders_enabled = EXCLUDED.occasion_reminders_enabled,
+            occasion_reminder_days = EXCLUDED.occasion_reminder_days,
+            timezone = EXCLUDED.timezone,
+            max_notifications_per_hour = EXCLUDED.max_notifications_per_hour,
+            type_overrides = EXCLUDED.type_overrides,
+            updated_at = NOW()
+        RETURNING {}
+        "#,
+        PREFERENCE_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(prefs.push_enabled)
+    .bind(prefs.email_enabled)
+    .bind(prefs.discord_enabled)
+    .bind(prefs.alexa_enabled)
+    .bind(prefs.quiet_hours_enabled)
+    .bind(prefs.quiet_hours_start)
+    .bind(prefs.quiet_hours_end)
+    .bind(prefs.morning_briefing_enabled)
+    .bind(prefs.morning_briefing_time)
+    .bind(prefs.evening_briefing_enabled)
+    .bind(prefs.evening_briefing_time)
+    .bind(&prefs.digest_mode)
+    .bind(prefs.weekly_review_enabled)
+    .bind(prefs.weekly_review_day)
+    .bind(prefs.occasion_reminders_enabled)
+    .bind(prefs.occasion_reminder_days)
+    .bind(&prefs.timezone)
+    .bind(prefs.max_notifications_per_hour)
+    .bind(&prefs.type_overrides)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn update(json: serde_json::Value) -> Result<Preferences> {
+        let update: PreferencesUpdate = serde_json::from_value(json).unwrap();
+        update.apply(Preferences::default())
+    }
+
+    #[test]
+    fn test_defaults_are_valid() {
+        assert!(validate(&Preferences::default()).is_ok());
+    }
+
+    #[test]
+    fn test_update_keeps_missing_fields() {
+        let prefs = update(serde_json::json!({
+            "timezone": "Europe/London",
+            "quietHoursEnabled": true,
+            "quietHoursStart": "22:00",
+            "quietHoursEnd": "06:30",
+        }))
+        .unwrap();
+
+        assert_eq!(prefs.timezone, "Europe/London");
+        assert_eq!(prefs.quiet_hours_start, Some(time(22, 0)));
+        assert_eq!(prefs.quiet_hours_end, Some(time(6, 30)));
+        assert!(prefs.push_enabled);
+        assert_eq!(prefs.digest_mode, "morning");
+    }
+
+    #[test]
+    fn test_timezone_must_be_known() {
+        assert!(update(serde_json::json!({ "timezone": "Mars/Olympus" })).is_err());
+        assert!(update(serde_json::json!({ "timezone": "EST" })).is_ok());
+    }
+
+    #[test]
+    fn test_quiet_hours_need_a_range() {
+        assert!(update(serde_json::json!({ "quietHoursEnabled": true })).is_err());
+        assert!(update(serde_json::json!({
+            "quietHoursEnabled": true,
+            "quietHoursStart": "22:00",
+            "quietHoursEnd": "22:00",
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn test_briefings_on_the_hour_outside_quiet_hours() {
+        assert!(update(serde_json::json!({ "morningBriefingTime": "07:30" })).is_err());
+
+        // 07:00 is in 22:00-08:00 quiet hours
+        assert!(update(serde_json::json!({
+            "quietHoursEnabled": true,
+            "quietHoursStart": "22:00",
+            "quietHoursEnd": "08:00",
+        }))
+        .is_err());
+        assert!(update(serde_json::json!({
+            "quietHoursEnabled": true,
+            "quietHoursStart": "22:00",
+            "quietHoursEnd": "08:00",
+            "morningBriefingTime": "08:00",
+        }))
+        .is_ok());
+
+        assert!(update(serde_json::json!({
+            "eveningBriefingEnabled": true,
+            "eveningBriefingTime": "07:00",
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn test_ranges() {
+        assert!(update(serde_json::json!({ "digestMode": "hourly" })).is_err());
+        assert!(update(serde_json::json!({ "weeklyReviewDay": 7 })).is_err());
+        assert!(update(serde_json::json!({ "occasionReminderDays": 61 })).is_err());
+        assert!(update(serde_json::json!({ "maxNotificationsPerHour": 0 })).is_err());
+    }
+
+    #[test]
+    fn test_type_overrides() {
+        let prefs = update(serde_json::json!({
+            "typeOverrides": {
+                "reminder": { "channel": "email", "ignoreQuietHours": true },
+                "proactive": { "enabled": false },
+            },
+        }))
+        .unwrap();
+        let reminder = &prefs.type_overrides["reminder"];
+        assert!(reminder.enabled);
+        assert!(reminder.ignore_quiet_hours);
+        assert!(!prefs.type_overrides["proactive"].enabled);
+
+        assert!(update(serde_json::json!({ "typeOverrides": { "gossip": {} } })).is_err());
+        assert!(update(serde_json::json!({
+            "typeOverrides": { "reminder": { "channel": "pigeon" } },
+        }))
+        .is_err());
+        // Alexa is off by default
+        assert!(update(serde_json::json!({
+            "typeOverrides": { "reminder": { "channel": "alexa" } },
+        }))
+        .is_err());
+    }
+}
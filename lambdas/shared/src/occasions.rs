@@ -0,0 +1,495 @@
+//! Birthdays and anniversaries.
+//!
+//! Occasions are read from entity attributes such as `birthday` or
+//! `anniversary` (values like `1980-03-15`, `--03-15` or `March 15`).
+//! `occasion_scanner` turns each one into a yearly recurring reminder a few
+//! days ahead, and `GET /occasions/upcoming` lists the next ones for widgets.
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime, Timelike};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::access::space_clause;
+use crate::Result;
+
+/// Attribute names read as birthdays
+const BIRTHDAY_ATTRIBUTES: &[&str] = &[
+    "birthday",
+    "birthdate",
+    "birth_date",
+    "date_of_birth",
+    "dob",
+];
+
+/// Attribute names read as anniversaries
+const ANNIVERSARY_ATTRIBUTES: &[&str] = &["anniversary", "wedding_anniversary", "wedding_date"];
+
+/// Days ahead a reminder fires when the user hasn't chosen
+pub const DEFAULT_DAYS_BEFORE: i16 = 7;
+
+/// Longest lead time for occasion reminders
+pub const MAX_DAYS_BEFORE: i16 = 60;
+
+/// Furthest ahead `GET /occasions/upcoming` looks
+pub const MAX_UPCOMING_DAYS: i64 = 366;
+
+/// Local time occasion reminders fire at
+pub fn reminder_time() -> NaiveTime {
+    NaiveTime::from_hms_opt(9, 0, 0).unwrap_or_default()
+}
+
+/// Kind of yearly occasion
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OccasionKind {
+    Birthday,
+    Anniversary,
+}
+
+impl OccasionKind {
+    /// Kind for an attribute name, or `None` if it isn't an occasion.
+    pub fn from_attribute(name: &str) -> Option<Self> {
+        let name = name.trim().to_ascii_lowercase().replace([' ', '-'], "_");
+        if BIRTHDAY_ATTRIBUTES.contains(&name.as_str()) {
+            Some(OccasionKind::Birthday)
+        } else if ANNIVERSARY_ATTRIBUTES.contains(&name.as_str()) {
+            Some(OccasionKind::Anniversary)
+        } else {
+            None
+        }
+    }
+
+    /// All attribute names read as occasions (for `attribute_name = ANY(...)`).
+    pub fn attribute_names() -> Vec<String> {
+        BIRTHDAY_ATTRIBUTES
+            .iter()
+            .chain(ANNIVERSARY_ATTRIBUTES)
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OccasionKind::Birthday => "birthday",
+            OccasionKind::Anniversary => "anniversary",
+        }
+    }
+}
+
+/// Month and day of an occasion, with the year when known
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OccasionDate {
+    pub month: u32,
+    pub day: u32,
+    pub year: Option<i32>,
+}
+
+/// Month for a full or abbreviated (three letters or more) month name
+fn month_number(name: &str) -> Option<u32> {
+    let name = name.to_ascii_lowercase();
+    let months = [
+        "january",
+        "february",
+        "march",
+        "april",
+        "may",
+        "june",
+        "july",
+        "august",
+        "september",
+        "october",
+        "november",
+        "december",
+    ];
+    if name.len() < 3 {
+        return None;
+    }
+    months
+        .iter()
+        .position(|m| m.starts_with(&name))
+        .map(|i| i as u32 + 1)
+}
+
+fn parse_day(token: &str) -> Option<u32> {
+    token
+        .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+        .parse()
+        .ok()
+}
+
+impl OccasionDate {
+    fn new(month: u32, day: u32, year: Option<i32>) -> Option<Self> {
+        // Validated against a leap year so February 29th is allowed
+        NaiveDate::from_ymd_opt(2000, month, day)?;
+        Some(Self { month, day, year })
+    }
+
+    /// Parse an attribute value: `1980-03-15` (or a timestamp starting with
+    /// one), `--03-15`, `03-15`, `3/15/1980`, `3/15`, `March 15`,
+    /// `March 15th, 1980` or `15 March 1980`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+
+        if let Some(date) = value
+            .get(..10)
+            .and_then(|prefix| NaiveDate::parse_from_str(prefix, "%Y-%m-%d").ok())
+        {
+            return Self::new(date.month(), date.day(), Some(date.year()));
+        }
+
+        let numeric = value.trim_start_matches("--");
+        for separator in ['-', '/'] {
+            let parts: Vec<&str> = numeric.split(separator).collect();
+            let numbers: Option<Vec<i32>> = parts.iter().map(|p| p.trim().parse().ok()).collect();
+            match numbers.as_deref() {
+                Some([month, day]) => return Self::new(*month as u32, *day as u32, None),
+                Some([month, day, year]) if separator == '/' && *year >= 1000 => {
+                    return Self::new(*month as u32, *day as u32, Some(*year));
+                }
+                _ => {}
+            }
+        }
+
+        let cleaned = value.replace([',', '.'], " ");
+        let tokens: Vec<&str> = cleaned.split_whitespace().collect();
+        let month_at = tokens.iter().position(|t| month_number(t).is_some())?;
+        let month = month_number(tokens[month_at])?;
+
+        // "March 15" or "15 March"
+        let day = tokens
+            .get(month_at + 1)
+            .and_then(|t| parse_day(t))
+            .filter(|d| (1..=31).contains(d))
+            .or_else(|| {
+                month_at
+                    .checked_sub(1)
+                    .and_then(|i| parse_day(tokens[i]))
+                    .filter(|d| (1..=31).contains(d))
+            })?;
+        let year = tokens
+            .iter()
+            .filter_map(|t| t.parse::<i32>().ok())
+            .find(|y| *y >= 1000);
+
+        Self::new(month, day, year)
+    }
+
+    /// The occasion in `year`; February 29th falls on the 28th in other years.
+    pub fn in_year(&self, year: i32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, self.month, self.day)
+            .or_else(|| NaiveDate::from_ymd_opt(year, self.month, self.day - 1))
+            .unwrap_or_default()
+    }
+
+    /// The next occurrence on or after `today`.
+    pub fn next_on_or_after(&self, today: NaiveDate) -> NaiveDate {
+        let this_year = self.in_year(today.year());
+        if this_year >= today {
+            this_year
+        } else {
+            self.in_year(today.year() + 1)
+        }
+    }
+
+    /// Age or anniversary number reached on `date`, when the year is known.
+    pub fn years_on(&self, date: NaiveDate) -> Option<i32> {
+        self.year
+            .map(|year| date.year() - year)
+            .filter(|years| *years > 0)
+    }
+
+    /// Cron expression for a yearly reminder `days_before` the occasion at
+    /// `time` (month and day taken from a non-leap year).
+    pub fn reminder_cron(&self, days_before: i16, time: NaiveTime) -> String {
+        let at = self.in_year(2001) - Duration::days(days_before.into());
+        format!(
+            "{} {} {} {} *",
+            time.minute(),
+            time.hour(),
+            at.day(),
+            at.month()
+        )
+    }
+}
+
+/// "Jane's birthday" / "Jane's anniversary"
+pub fn occasion_title(kind: OccasionKind, name: &str) -> String {
+    format!("{}'s {}", name.trim(), kind.as_str())
+}
+
+/// Title for the reminder `days_before` an occasion.
+pub fn reminder_title(kind: OccasionKind, name: &str, days_before: i16) -> String {
+    let when = match days_before {
+        0 => "today".to_string(),
+        1 => "tomorrow".to_string(),
+        n => format!("in {} days", n),
+    };
+    format!("{} is {}", occasion_title(kind, name), when)
+}
+
+/// Description for an occasion reminder, e.g. "Birthday on March 15".
+pub fn reminder_description(kind: OccasionKind, date: &OccasionDate) -> String {
+    let label = match kind {
+        OccasionKind::Birthday => "Birthday",
+        OccasionKind::Anniversary => "Anniversary",
+    };
+    let on = date.in_year(2000);
+    match date.year {
+        Some(year) => format!("{} on {} (since {})", label, on.format("%B %-d"), year),
+        None => format!("{} on {}", label, on.format("%B %-d")),
+    }
+}
+
+/// A current occasion attribute on an entity the user can see
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct OccasionAttribute {
+    pub attribute_id: Uuid,
+    pub attribute_name: String,
+    pub attribute_value: String,
+    pub entity_id: Uuid,
+    pub entity_name: String,
+    pub entity_type: String,
+}
+
+impl OccasionAttribute {
+    /// Kind and date, or `None` if the value can't be read as a date.
+    pub fn parse(&self) -> Option<(OccasionKind, OccasionDate)> {
+        Some((
+            OccasionKind::from_attribute(&self.attribute_name)?,
+            OccasionDate::parse(&self.attribute_value)?,
+        ))
+    }
+}
+
+/// Current occasion attributes on the user's entities and their families'.
+pub async fn fetch_occasion_attributes(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Vec<OccasionAttribute>> {
+    let attributes = sqlx::query_as(&format!(
+        r#"
+        SELECT
+            ea.id AS attribute_id, ea.attribute_name, ea.attribute_value,
+            e.id AS entity_id, e.name AS entity_name, e.entity_type::text
+        FROM entity_attributes ea
+        JOIN entities e ON e.id = ea.entity_id
+        WHERE REPLACE(REPLACE(LOWER(TRIM(ea.attribute_name)), ' ', '_'), '-', '_') = ANY($2)
+          AND ea.superseded_by IS NULL
+          AND (ea.valid_to IS NULL OR ea.valid_to >= CURRENT_DATE)
+          AND e.deleted_at IS NULL
+          AND (
+              (e.owner_type = 'user' AND e.owner_id = $1)
+              OR (e.owner_type = 'family' AND e.owner_id IN (
+                  SELECT family_id FROM family_members WHERE user_id = $1
+              ) AND {})
+          )
+        ORDER BY e.name, ea.created_at
+        "#,
+        space_clause("e", 1)
+    ))
+    .bind(user_id)
+    .bind(OccasionKind::attribute_names())
+    .fetch_all(pool)
+    .await?;
+
+    Ok(attributes)
+}
+
+/// An occasion in the upcoming-dates list
+#[derive(Debug, Clone)]
+pub struct UpcomingOccasion {
+    pub entity_id: Uuid,
+    pub entity_name: String,
+    pub entity_type: String,
+    pub occasion_type: OccasionKind,
+    pub title: String,
+    /// Next occurrence
+    pub date: NaiveDate,
+    pub days_until: i64,
+    /// Age turned or anniversary number, when the year is known
+    pub years: Option<i32>,
+}
+
+/// Occasions in the `days` days from `today` (inclusive), soonest first.
+/// Attributes whose values aren't dates are skipped.
+pub fn upcoming(
+    attributes: &[OccasionAttribute],
+    today: NaiveDate,
+    days: i64,
+) -> Vec<UpcomingOccasion> {
+    let mut occasions: Vec<UpcomingOccasion> = attributes
+        .iter()
+        .filter_map(|attribute| {
+            let (kind, date) = attribute.parse()?;
+            let next = date.next_on_or_after(today);
+            let days_until = (next - today).num_days();
+            (days_until <= days).then(|| UpcomingOccasion {
+                entity_id: attribute.entity_id,
+                entity_name: attribute.entity_name.clone(),
+                entity_type: attribute.entity_type.clone(),
+                occasion_type: kind,
+                title: occasion_title(kind, &attribute.entity_name),
+                date: next,
+                days_until,
+                years: date.years_on(next),
+            })
+        })
+        .collect();
+
+    occasions.sort_by(|a, b| {
+        a.date
+            .cmp(&b.date)
+            .then_with(|| a.entity_name.cmp(&b.entity_name))
+    });
+    occasions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn attribute(name: &str, value: &str, entity: &str) -> OccasionAttribute {
+        OccasionAttribute {
+            attribute_id: Uuid::nil(),
+            attribute_name: name.to_string(),
+            attribute_value: value.to_string(),
+            entity_id: Uuid::nil(),
+            entity_name: entity.to_string(),
+            entity_type: "person".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_occasion_kind() {
+        assert_eq!(
+            OccasionKind::from_attribute("Birthday"),
+            Some(OccasionKind::Birthday)
+        );
+        assert_eq!(
+            OccasionKind::from_attribute("date of birth"),
+            Some(OccasionKind::Birthday)
+        );
+        assert_eq!(
+            OccasionKind::from_attribute("wedding-anniversary"),
+            Some(OccasionKind::Anniversary)
+        );
+        assert_eq!(OccasionKind::from_attribute("email"), None);
+        assert!(OccasionKind::attribute_names().contains(&"dob".to_string()));
+    }
+
+    #[test]
+    fn test_parse_date() {
+        let full = OccasionDate::new(3, 15, Some(1980));
+        let no_year = OccasionDate::new(3, 15, None);
+
+        assert_eq!(OccasionDate::parse("1980-03-15"), full);
+        assert_eq!(OccasionDate::parse("1980-03-15T00:00:00Z"), full);
+        assert_eq!(OccasionDate::parse("--03-15"), no_year);
+        assert_eq!(OccasionDate::parse("03-15"), no_year);
+        assert_eq!(OccasionDate::parse("3/15/1980"), full);
+        assert_eq!(OccasionDate::parse("3/15"), no_year);
+        assert_eq!(OccasionDate::parse("March 15"), no_year);
+        assert_eq!(OccasionDate::parse("Mar 15th, 1980"), full);
+        assert_eq!(OccasionDate::parse("15 March 1980"), full);
+        assert_eq!(
+            OccasionDate::parse("Feb 29"),
+            OccasionDate::new(2, 29, None)
+        );
+
+        assert_eq!(OccasionDate::parse("Feb 30"), None);
+        assert_eq!(OccasionDate::parse("13-01"), None);
+        assert_eq!(OccasionDate::parse("sometime in spring"), None);
+        assert_eq!(OccasionDate::parse(""), None);
+    }
+
+    #[test]
+    fn test_next_occurrence() {
+        let birthday = OccasionDate::parse("1980-03-15").unwrap();
+        assert_eq!(
+            birthday.next_on_or_after(date(2026, 3, 15)),
+            date(2026, 3, 15)
+        );
+        assert_eq!(
+            birthday.next_on_or_after(date(2026, 3, 16)),
+            date(2027, 3, 15)
+        );
+        assert_eq!(birthday.years_on(date(2026, 3, 15)), Some(46));
+
+        let leap = OccasionDate::parse("2000-02-29").unwrap();
+        assert_eq!(leap.next_on_or_after(date(2026, 1, 1)), date(2026, 2, 28));
+        assert_eq!(leap.next_on_or_after(date(2027, 12, 1)), date(2028, 2, 29));
+
+        assert_eq!(
+            OccasionDate::parse("March 15")
+                .unwrap()
+                .years_on(date(2026, 3, 15)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_reminder_cron() {
+        let nine = reminder_time();
+        let birthday = OccasionDate::parse("March 15").unwrap();
+        assert_eq!(birthday.reminder_cron(7, nine), "0 9 8 3 *");
+        assert_eq!(birthday.reminder_cron(0, nine), "0 9 15 3 *");
+
+        let new_year = OccasionDate::parse("January 3").unwrap();
+        assert_eq!(new_year.reminder_cron(7, nine), "0 9 27 12 *");
+
+        let leap = OccasionDate::parse("Feb 29").unwrap();
+        assert_eq!(leap.reminder_cron(0, nine), "0 9 28 2 *");
+    }
+
+    #[test]
+    fn test_titles() {
+        assert_eq!(
+            reminder_title(OccasionKind::Birthday, "Jane", 7),
+            "Jane's birthday is in 7 days"
+        );
+        assert_eq!(
+            reminder_title(OccasionKind::Anniversary, "Mom and Dad", 1),
+            "Mom and Dad's anniversary is tomorrow"
+        );
+        assert_eq!(
+            reminder_description(
+                OccasionKind::Birthday,
+                &OccasionDate::parse("1980-03-15").unwrap()
+            ),
+            "Birthday on March 15 (since 1980)"
+        );
+        assert_eq!(
+            reminder_description(
+                OccasionKind::Anniversary,
+                &OccasionDate::parse("June 2").unwrap()
+            ),
+            "Anniversary on June 2"
+        );
+    }
+
+    #[test]
+    fn test_upcoming() {
+        let attributes = vec![
+            attribute("birthday", "1990-11-02", "Ben"),
+            attribute("birthday", "not a date", "Cy"),
+            attribute("anniversary", "--10-20", "Ann"),
+            attribute("birthday", "1985-10-16", "Dee"),
+            attribute("birthday", "1985-09-01", "Eve"),
+        ];
+
+        let occasions = upcoming(&attributes, date(2026, 10, 16), 30);
+        let names: Vec<&str> = occasions.iter().map(|o| o.entity_name.as_str()).collect();
+        assert_eq!(names, vec!["Dee", "Ann", "Ben"]);
+
+        assert_eq!(occasions[0].days_until, 0);
+        assert_eq!(occasions[0].years, Some(41));
+        assert_eq!(occasions[1].occasion_type, OccasionKind::Anniversary);
+        assert_eq!(occasions[1].title, "Ann's anniversary");
+        assert_eq!(occasions[1].years, None);
+        assert_eq!(occasions[2].date, date(2026, 11, 2));
+        assert_eq!(occasions[2].days_until, 17);
+    }
+}
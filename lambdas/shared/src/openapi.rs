@@ -0,0 +1,114 @@
+//! OpenAPI 3 document for the REST API, generated from the request and
+//! response types themselves so client SDKs and docs can't drift from them.
+//!
+//! This is the part common to every endpoint: the API's description, the
+//! Cognito security scheme and the schemas of the shared types (their
+//! `ToSchema` derives). The endpoints themselves are documented by
+//! `#[utoipa::path]` on their handlers, and the `openapi` Lambda adds them to
+//! this to serve `GET /openapi.json`.
+
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use crate::error::{ApiError, ErrorCode};
+use crate::ingest::{BatchIngestRequest, BatchIngestResponse, BatchItemResult, BatchItemStatus};
+use crate::models::{IngestRequest, IngestResponse, QueryRequest, QueryResponse};
+use crate::usage::{ModelUsage, MonthlyUsage, PastQuery, QuerySource};
+
+/// Adds the Cognito bearer token scheme the endpoints require.
+struct CognitoAuth;
+
+impl Modify for CognitoAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "cognito",
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+        );
+    }
+}
+
+/// The REST API's OpenAPI document, without its endpoints.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Second Brain API",
+        description = "Personal and family knowledge base. Endpoints with the `cognito` \
+                       security requirement need a Cognito JWT as \
+                       `Authorization: Bearer <token>`."
+    ),
+    servers((url = "/api", description = "API Gateway stage")),
+    components(schemas(
+        QueryRequest,
+        QueryResponse,
+        IngestRequest,
+        IngestResponse,
+        BatchIngestRequest,
+        BatchIngestResponse,
+        BatchItemResult,
+        BatchItemStatus,
+        MonthlyUsage,
+        ModelUsage,
+        PastQuery,
+        QuerySource,
+        ApiError,
+        ErrorCode
+    )),
+    modifiers(&CognitoAuth),
+    tags(
+        (name = "access", description = "Time-boxed access to facts and entities"),
+        (name = "account", description = "Your account"),
+        (name = "api-keys", description = "Keys for the `/v1` API"),
+        (name = "audit", description = "Who changed what"),
+        (name = "auth", description = "Device sign-in for CLI and TV clients"),
+        (name = "briefings", description = "Daily briefings"),
+        (name = "calendar", description = "Calendar subscriptions and what is extracted from them"),
+        (name = "capture", description = "Web pages saved from the browser extension"),
+        (name = "contacts", description = "Google Contacts connection"),
+        (name = "devices", description = "Push notification devices"),
+        (name = "diagnostics", description = "Debug mode and the requests it samples"),
+        (name = "docs", description = "This document"),
+        (name = "entities", description = "People, places and things, and how they relate"),
+        (name = "export", description = "The knowledge graph, or all of your data"),
+        (name = "facts", description = "Searching, reviewing and attaching files to facts"),
+        (name = "families", description = "Families and their members"),
+        (name = "feedback", description = "Answer ratings and interactions, for learning"),
+        (name = "feeds", description = "RSS and Atom feed subscriptions"),
+        (name = "handoffs", description = "Household handoff briefings between family members"),
+        (name = "integrations", description = "Linking Discord accounts"),
+        (name = "knowledge", description = "Storing facts and asking questions"),
+        (name = "location-history", description = "Location pings from the app and the visits found in them"),
+        (name = "locations", description = "Entity locations and what is near a point"),
+        (name = "occasions", description = "Upcoming birthdays and anniversaries"),
+        (name = "preferences", description = "Notification preferences"),
+        (name = "realtime", description = "Tickets for the WebSocket API"),
+        (name = "relationships", description = "Relationships between users and the access they grant"),
+        (name = "reminders", description = "Reminders and their history"),
+        (name = "sharing", description = "What relationships share, person by person"),
+        (name = "sms", description = "Phone numbers for SMS"),
+        (name = "spaces", description = "Family spaces"),
+        (name = "tags", description = "Tags, auto-tagging rules and tagged facts"),
+        (name = "trash", description = "Deleted facts, entities and tags"),
+        (name = "usage", description = "Token usage and estimated spend"),
+    )
+)]
+pub struct ApiDoc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schemas_follow_the_types() {
+        let doc = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        let schemas = &doc["components"]["schemas"];
+
+        assert_eq!(
+            schemas["QueryRequest"]["required"],
+            serde_json::json!(["query"])
+        );
+        // Usage is serialized camelCase
+        assert!(schemas["MonthlyUsage"]["properties"]["estimatedCostUsd"].is_object());
+        assert!(doc["components"]["securitySchemes"]["cognito"].is_object());
+    }
+}
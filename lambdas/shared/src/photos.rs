@@ -0,0 +1,235 @@
+//! Photo ingestion helpers.
+//!
+//! The `photo_ingest` Lambda reads when and where a photo was taken from its
+//! EXIF data, asks Rekognition what's in it and who (matching faces against
+//! person entities' photos), and records it as a fact such as
+//! "Photo of Max at Lake Tahoe on 2024-07-04". The pure parts live here.
+
+use chrono::{NaiveDate, NaiveDateTime};
+use uuid::Uuid;
+
+/// Largest photo downloaded for processing
+pub const MAX_PHOTO_BYTES: i64 = 20 * 1024 * 1024;
+
+/// Longest edge of a stored thumbnail, in pixels
+pub const THUMBNAIL_SIZE: u32 = 512;
+
+/// Most labels kept from Rekognition
+pub const MAX_LABELS: i32 = 10;
+
+/// Lowest label confidence kept (percent)
+pub const MIN_LABEL_CONFIDENCE: f32 = 80.0;
+
+/// Lowest face similarity treated as a match (percent)
+pub const FACE_MATCH_THRESHOLD: f32 = 90.0;
+
+/// Most faces in one photo looked up in the collection
+pub const MAX_FACES: usize = 10;
+
+/// How close a place entity must be to the photo's GPS position to be used
+/// as its location, in meters
+pub const NEARBY_PLACE_METERS: f64 = 1000.0;
+
+/// Labels named in a description when nobody was recognized
+const DESCRIBED_LABELS: usize = 3;
+
+/// Whether Rekognition can read the file (by extension).
+pub fn is_supported_photo(key: &str) -> bool {
+    let ext = key
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_lowercase())
+        .unwrap_or_default();
+
+    matches!(ext.as_str(), "jpg" | "jpeg" | "png")
+}
+
+/// Object key for a photo's thumbnail in the drop folder bucket.
+pub fn thumbnail_key(photo_id: Uuid) -> String {
+    format!("thumbnails/photos/{}.jpg", photo_id)
+}
+
+/// Decimal degrees from EXIF GPS degrees/minutes/seconds and their `N`/`S`/
+/// `E`/`W` reference, or `None` if out of range.
+pub fn gps_coordinate(dms: [f64; 3], reference: &str) -> Option<f64> {
+    let [degrees, minutes, seconds] = dms;
+    if [degrees, minutes, seconds]
+        .iter()
+        .any(|v| !v.is_finite() || *v < 0.0)
+    {
+        return None;
+    }
+
+    let (sign, max) = match reference.trim().to_ascii_uppercase().as_str() {
+        "N" => (1.0, 90.0),
+        "S" => (-1.0, 90.0),
+        "E" => (1.0, 180.0),
+        "W" => (-1.0, 180.0),
+        _ => return None,
+    };
+
+    let value = degrees + minutes / 60.0 + seconds / 3600.0;
+    (value <= max).then_some(sign * value)
+}
+
+/// Parse an EXIF date/time (`YYYY:MM:DD HH:MM:SS`, local to the camera).
+/// Cameras without a clock write zeros or blanks, which give `None`.
+pub fn parse_exif_datetime(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    NaiveDateTime::parse_from_str(value, "%Y:%m:%d %H:%M:%S").ok()
+}
+
+/// Pixel rectangle `(x, y, width, height)` to crop around a face, from
+/// Rekognition's bounding box (fractions of the image size), padded by a
+/// quarter of the box on each side and clamped to the image.
+pub fn face_crop(
+    left: f32,
+    top: f32,
+    width: f32,
+    height: f32,
+    image_width: u32,
+    image_height: u32,
+) -> Option<(u32, u32, u32, u32)> {
+    if width <= 0.0 || height <= 0.0 || image_width == 0 || image_height == 0 {
+        return None;
+    }
+
+    let pad_x = width * 0.25;
+    let pad_y = height * 0.25;
+    let x0 = ((left - pad_x).max(0.0) * image_width as f32).round() as u32;
+    let y0 = ((top - pad_y).max(0.0) * image_height as f32).round() as u32;
+    let x1 = ((left + width + pad_x).min(1.0) * image_width as f32).round() as u32;
+    let y1 = ((top + height + pad_y).min(1.0) * image_height as f32).round() as u32;
+
+    (x1 > x0 && y1 > y0).then(|| (x0, y0, x1 - x0, y1 - y0))
+}
+
+/// "Max", "Max and Sam", "Max, Sam and Ada"
+pub fn join_names(names: &[String]) -> String {
+    match names {
+        [] => String::new(),
+        [only] => only.clone(),
+        [rest @ .., last] => format!("{} and {}", rest.join(", "), last),
+    }
+}
+
+/// Fact content for a photo: who (or, failing that, what) is in it, where
+/// and when.
+pub fn photo_description(
+    people: &[String],
+    labels: &[String],
+    place: Option<&str>,
+    taken_on: Option<NaiveDate>,
+) -> String {
+    let mut description = if !people.is_empty() {
+        format!("Photo of {}", join_names(people))
+    } else if !labels.is_empty() {
+        let described: Vec<String> = labels
+            .iter()
+            .take(DESCRIBED_LABELS)
+            .map(|l| l.to_lowercase())
+            .collect();
+        format!("Photo of {}", join_names(&described))
+    } else {
+        "Photo".to_string()
+    };
+
+    if let Some(place) = place.map(str::trim).filter(|p| !p.is_empty()) {
+        description.push_str(" at ");
+        description.push_str(place);
+    }
+    if let Some(date) = taken_on {
+        description.push_str(&format!(" on {}", date.format("%Y-%m-%d")));
+    }
+
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn supported_photo_types() {
+        assert!(is_supported_photo("photos/sub/IMG_0001.JPG"));
+        assert!(is_supported_photo("photos/sub/beach.png"));
+        assert!(!is_supported_photo("photos/sub/IMG_0002.HEIC"));
+        assert!(!is_supported_photo("photos/sub/noext"));
+    }
+
+    #[test]
+    fn converts_gps_coordinates() {
+        let lat = gps_coordinate([39.0, 5.0, 47.52], "N").unwrap();
+        assert!((lat - 39.0965333).abs() < 1e-6);
+
+        let lon = gps_coordinate([120.0, 2.0, 24.0], "W").unwrap();
+        assert!((lon + 120.04).abs() < 1e-9);
+
+        assert_eq!(
+            gps_coordinate([33.0, 52.0, 0.0], "s"),
+            Some(-(33.0 + 52.0 / 60.0))
+        );
+        assert_eq!(gps_coordinate([91.0, 0.0, 0.0], "N"), None);
+        assert_eq!(gps_coordinate([10.0, 0.0, 0.0], "X"), None);
+        assert_eq!(gps_coordinate([f64::NAN, 0.0, 0.0], "E"), None);
+    }
+
+    #[test]
+    fn parses_exif_datetimes() {
+        assert_eq!(
+            parse_exif_datetime("2024:07:04 18:32:05\0"),
+            NaiveDate::from_ymd_opt(2024, 7, 4).and_then(|d| d.and_hms_opt(18, 32, 5))
+        );
+        assert_eq!(parse_exif_datetime("0000:00:00 00:00:00"), None);
+        assert_eq!(parse_exif_datetime("    :  :     :  :  "), None);
+        assert_eq!(parse_exif_datetime("2024-07-04 18:32:05"), None);
+    }
+
+    #[test]
+    fn crops_padded_faces() {
+        assert_eq!(
+            face_crop(0.4, 0.4, 0.2, 0.2, 1000, 500),
+            Some((350, 175, 300, 150))
+        );
+        // Clamped at the image edges
+        assert_eq!(
+            face_crop(0.0, 0.9, 0.2, 0.1, 100, 100),
+            Some((0, 88, 25, 12))
+        );
+        assert_eq!(face_crop(0.5, 0.5, 0.0, 0.1, 100, 100), None);
+    }
+
+    #[test]
+    fn joins_names() {
+        assert_eq!(join_names(&[]), "");
+        assert_eq!(join_names(&names(&["Max"])), "Max");
+        assert_eq!(join_names(&names(&["Max", "Sam"])), "Max and Sam");
+        assert_eq!(
+            join_names(&names(&["Max", "Sam", "Ada"])),
+            "Max, Sam and Ada"
+        );
+    }
+
+    #[test]
+    fn describes_photos() {
+        let july_4 = NaiveDate::from_ymd_opt(2024, 7, 4);
+
+        assert_eq!(
+            photo_description(
+                &names(&["Max"]),
+                &names(&["Lake"]),
+                Some("Lake Tahoe"),
+                july_4
+            ),
+            "Photo of Max at Lake Tahoe on 2024-07-04"
+        );
+        assert_eq!(
+            photo_description(&[], &names(&["Beach", "Sea", "Boat", "Sky"]), None, july_4),
+            "Photo of beach, sea and boat on 2024-07-04"
+        );
+        assert_eq!(photo_description(&[], &[], Some(" "), None), "Photo");
+    }
+}
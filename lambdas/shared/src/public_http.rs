@@ -0,0 +1,134 @@
+//! HTTP client for fetching URLs users give us.
+//!
+//! Captured pages, calendar feeds and RSS feeds are fetched from inside the
+//! VPC, so a URL that reaches a private address could read internal services
+//! or the instance metadata endpoint. Checking the URL when it is submitted
+//! isn't enough: a public name can resolve to a private address (or start to,
+//! later), and a public page can redirect to a private one. The [`client`]
+//! built here checks every address a host name resolves to at connection
+//! time ([`PublicResolver`]) and every redirect hop ([`redirect_policy`]);
+//! literal IP hosts, which aren't resolved, are checked by
+//! [`is_public_host`] before each request and hop.
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect::Policy;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+
+pub use crate::capture::{is_public_host, is_public_ip};
+use crate::{Error, Result};
+
+/// Redirects followed per request
+pub const MAX_REDIRECTS: usize = 5;
+
+/// `User-Agent` sent with every request
+pub const USER_AGENT: &str = "SecondBrain/1.0 (+https://secondbrain.app)";
+
+/// Resolves host names with the system resolver, failing for names with any
+/// address that isn't public.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addresses = public_addresses(name.as_str(), 0).await?;
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Follow redirects only to http(s) URLs on public hosts, at most
+/// [`MAX_REDIRECTS`] of them.
+pub fn redirect_policy() -> Policy {
+    Policy::custom(|attempt| {
+        let url = attempt.url();
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else if !matches!(url.scheme(), "http" | "https") || !is_public_host(url) {
+            attempt.error("redirected to a host that is not public")
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// A client that only connects to public addresses, timing out requests
+/// after `timeout`.
+pub fn client(timeout: Duration) -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .redirect(redirect_policy())
+        .dns_resolver(Arc::new(PublicResolver))
+        .user_agent(USER_AGENT)
+        .build()
+        .map_err(|e| Error::Config(format!("Failed to build HTTP client: {}", e)))
+}
+
+/// Check that a URL can be fetched: its host must be public and, if it's a
+/// name, resolve only to public addresses. Returns `Error::Validation`
+/// otherwise, for checking URLs as they are submitted.
+pub async fn ensure_public(url: &Url) -> Result<()> {
+    if !is_public_host(url) {
+        return Err(Error::Validation("host must be public".to_string()));
+    }
+    if let Some(url::Host::Domain(domain)) = url.host() {
+        let port = url.port_or_known_default().unwrap_or(443);
+        public_addresses(domain, port)
+            .await
+            .map_err(|e| Error::Validation(e.to_string()))?;
+    }
+    Ok(())
+}
+
+/// Every address `host` resolves to, if they are all public.
+async fn public_addresses(
+    host: &str,
+    port: u16,
+) -> std::result::Result<Vec<SocketAddr>, Box<dyn std::error::Error + Send + Sync>> {
+    let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await?.collect();
+    if addresses.is_empty() {
+        return Err(format!("{} did not resolve", host).into());
+    }
+    if !addresses.iter().all(|a| is_public_ip(a.ip())) {
+        return Err(format!("{} resolves to an address that is not public", host).into());
+    }
+    Ok(addresses)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_ensure_public_rejects_private_hosts() {
+        for url in [
+            "http://127.0.0.1/",
+            // Decimal and short forms of 127.0.0.1
+            "http://2130706433/",
+            "http://127.1/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://[fd00::1]/",
+            "http://[fe80::1]/",
+            "http://[::ffff:10.0.0.1]/",
+            "http://localhost/",
+        ] {
+            let url = Url::parse(url).unwrap();
+            assert!(
+                matches!(ensure_public(&url).await, Err(Error::Validation(_))),
+                "{} was accepted",
+                url
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolver_rejects_private_addresses() {
+        // Resolved rather than parsed, like a public name pointing inside
+        let name: Name = "127.0.0.1".parse().unwrap();
+        assert!(PublicResolver.resolve(name).await.is_err());
+        assert!(public_addresses("10.0.0.5", 80).await.is_err());
+    }
+}
@@ -0,0 +1,236 @@
+//! Mobile push notifications through Firebase Cloud Messaging and APNs.
+//!
+//! Devices register a token with the platform that issued it
+//! (`push_devices.device_platform`): `fcm` for Android (and iOS apps using the
+//! Firebase SDK) and `apns` for native iOS. This module builds provider
+//! requests and credentials and classifies provider errors; the HTTP calls are
+//! made by `notification_sender`.
+
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use serde_json::{json, Map, Value};
+
+use crate::{Error, Result};
+
+/// OAuth scope for the FCM HTTP v1 API
+pub const FCM_SCOPE: &str = "https://www.googleapis.com/auth/firebase.messaging";
+
+/// Seconds an FCM access token is requested for (Google's maximum)
+pub const FCM_TOKEN_LIFETIME_SECS: i64 = 3600;
+
+/// Seconds an APNs provider token is reused; Apple rejects tokens older than an
+/// hour and refreshes more often than every 20 minutes.
+pub const APNS_TOKEN_REUSE_SECS: i64 = 50 * 60;
+
+pub const APNS_PRODUCTION_HOST: &str = "https://api.push.apple.com";
+pub const APNS_SANDBOX_HOST: &str = "https://api.sandbox.push.apple.com";
+
+/// Push provider a device token belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushPlatform {
+    Fcm,
+    Apns,
+}
+
+impl PushPlatform {
+    /// Parse a `device_platform` value.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "fcm" | "android" => Some(PushPlatform::Fcm),
+            "apns" | "ios" => Some(PushPlatform::Apns),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PushPlatform::Fcm => "fcm",
+            PushPlatform::Apns => "apns",
+        }
+    }
+}
+
+/// Notification content sent to a device
+#[derive(Debug, Clone)]
+pub struct PushMessage<'a> {
+    pub title: &'a str,
+    pub body: &'a str,
+    /// Custom string data for the app (notification and reminder IDs)
+    pub data: Vec<(&'static str, String)>,
+}
+
+impl PushMessage<'_> {
+    fn data_map(&self) -> Map<String, Value> {
+        self.data
+            .iter()
+            .map(|(k, v)| (k.to_string(), Value::String(v.clone())))
+            .collect()
+    }
+}
+
+/// Request body for the FCM HTTP v1 `messages:send` endpoint.
+pub fn fcm_request(token: &str, message: &PushMessage) -> Value {
+    json!({
+        "message": {
+            "token": token,
+            "notification": {
+                "title": message.title,
+                "body": message.body,
+            },
+            "data": message.data_map(),
+            "android": { "priority": "high" },
+        }
+    })
+}
+
+/// Request body for APNs; custom data sits beside `aps`.
+pub fn apns_payload(message: &PushMessage) -> Value {
+    let mut payload = message.data_map();
+    payload.insert(
+        "aps".to_string(),
+        json!({
+            "alert": {
+                "title": message.title,
+                "body": message.body,
+            },
+            "sound": "default",
+        }),
+    );
+    Value::Object(payload)
+}
+
+/// Whether an FCM error means the token will never work again.
+///
+/// `UNREGISTERED` (404) covers uninstalled apps and expired tokens;
+/// `SENDER_ID_MISMATCH` means the token belongs to another Firebase project.
+pub fn fcm_token_invalid(status: u16, body: &Value) -> bool {
+    let codes: Vec<&str> = body["error"]["details"]
+        .as_array()
+        .map(|details| {
+            details
+                .iter()
+                .filter_map(|d| d["errorCode"].as_str())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    status == 404
+        || codes
+            .iter()
+            .any(|code| matches!(*code, "UNREGISTERED" | "SENDER_ID_MISMATCH"))
+}
+
+/// Whether an APNs error `reason` means the token will never work again.
+pub fn apns_token_invalid(status: u16, reason: Option<&str>) -> bool {
+    status == 410
+        || matches!(
+            reason,
+            Some("BadDeviceToken" | "Unregistered" | "DeviceTokenNotForTopic")
+        )
+}
+
+/// Signed APNs provider token (ES256 with the `.p8` key).
+pub fn apns_provider_token(
+    key_id: &str,
+    team_id: &str,
+    private_key_pem: &str,
+    issued_at: i64,
+) -> Result<String> {
+    let mut header = Header::new(Algorithm::ES256);
+    header.kid = Some(key_id.to_string());
+
+    let key = EncodingKey::from_ec_pem(private_key_pem.as_bytes())
+        .map_err(|e| Error::Config(format!("Invalid APNs key: {}", e)))?;
+
+    encode(&header, &json!({ "iss": team_id, "iat": issued_at }), &key)
+        .map_err(|e| Error::Internal(format!("Failed to sign APNs token: {}", e)))
+}
+
+/// Signed assertion exchanged at `token_uri` for an FCM access token
+/// (RS256 with the Firebase service account key).
+pub fn fcm_assertion(
+    client_email: &str,
+    private_key_pem: &str,
+    token_uri: &str,
+    issued_at: i64,
+) -> Result<String> {
+    let key = EncodingKey::from_rsa_pem(private_key_pem.as_bytes())
+        .map_err(|e| Error::Config(format!("Invalid FCM service account key: {}", e)))?;
+
+    let claims = json!({
+        "iss": client_email,
+        "scope": FCM_SCOPE,
+        "aud": token_uri,
+        "iat": issued_at,
+        "exp": issued_at + FCM_TOKEN_LIFETIME_SECS,
+    });
+
+    encode(&Header::new(Algorithm::RS256), &claims, &key)
+        .map_err(|e| Error::Internal(format!("Failed to sign FCM assertion: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message() -> PushMessage<'static> {
+        PushMessage {
+            title: "Reminder",
+            body: "Pick up Emma at 3",
+            data: vec![("notification_id", "n-1".to_string())],
+        }
+    }
+
+    #[test]
+    fn test_platform_parse() {
+        assert_eq!(PushPlatform::parse("FCM"), Some(PushPlatform::Fcm));
+        assert_eq!(PushPlatform::parse("ios"), Some(PushPlatform::Apns));
+        assert_eq!(PushPlatform::parse("web"), None);
+        assert_eq!(PushPlatform::Apns.as_str(), "apns");
+    }
+
+    #[test]
+    fn test_fcm_request() {
+        let request = fcm_request("tok", &message());
+        assert_eq!(request["message"]["token"], "tok");
+        assert_eq!(request["message"]["notification"]["title"], "Reminder");
+        assert_eq!(request["message"]["data"]["notification_id"], "n-1");
+    }
+
+    #[test]
+    fn test_apns_payload() {
+        let payload = apns_payload(&message());
+        assert_eq!(payload["aps"]["alert"]["body"], "Pick up Emma at 3");
+        assert_eq!(payload["notification_id"], "n-1");
+    }
+
+    #[test]
+    fn test_fcm_token_invalid() {
+        let unregistered = json!({
+            "error": {
+                "status": "NOT_FOUND",
+                "details": [{
+                    "@type": "type.googleapis.com/google.firebase.fcm.v1.FcmError",
+                    "errorCode": "UNREGISTERED",
+                }],
+            }
+        });
+        assert!(fcm_token_invalid(404, &unregistered));
+        assert!(fcm_token_invalid(
+            403,
+            &json!({ "error": { "details": [{ "errorCode": "SENDER_ID_MISMATCH" }] } })
+        ));
+        assert!(!fcm_token_invalid(
+            503,
+            &json!({ "error": { "details": [{ "errorCode": "UNAVAILABLE" }] } })
+        ));
+        assert!(!fcm_token_invalid(500, &Value::Null));
+    }
+
+    #[test]
+    fn test_apns_token_invalid() {
+        assert!(apns_token_invalid(410, Some("Unregistered")));
+        assert!(apns_token_invalid(400, Some("BadDeviceToken")));
+        assert!(!apns_token_invalid(429, Some("TooManyRequests")));
+        assert!(!apns_token_invalid(500, None));
+    }
+}
@@ -0,0 +1,361 @@
+//! Per-user rate limiting, so abusive clients and runaway scripts get `429`s
+//! (with `Retry-After`) instead of the database and agents.
+//!
+//! Each user gets a token bucket per limit, kept in the Lambda container:
+//! limits hold per warm container rather than globally, which is enough to
+//! stop a single client hammering an endpoint. Applied as router middleware:
+//!
+//! ```ignore
+//! Router::new()
+//!     .layer(RequireAuth)
+//!     .layer(RateLimit::default().limit("POST", "/export", Limit::per_minute(5)))
+//! ```
+//!
+//! Callers are counted once they're authenticated, by Cognito subject: the
+//! middleware leaves the request's limit in its extensions and
+//! [`AuthorizedUser::from_request`](crate::AuthorizedUser::from_request)
+//! checks it (see [`check_caller`]), so a made-up API key or device token
+//! doesn't get a bucket of its own. Requests without credentials, those
+//! whose credentials turn out to be invalid, and requests to endpoints that
+//! don't authenticate callers (see [`RateLimit::public`]) are counted by
+//! source IP.
+
+use lambda_http::request::RequestContext;
+use lambda_http::{Body, Request, RequestExt, Response};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::error::ApiError;
+use crate::router::{has_credentials, Middleware, PathPattern, RequestInfo};
+use crate::{Error, Result};
+
+/// Requests per minute allowed by default, unless `RATE_LIMIT_PER_MINUTE` is set.
+pub const DEFAULT_PER_MINUTE: u32 = 120;
+
+/// Questions a user can ask a minute, over `POST /query` or the WebSocket;
+/// each one runs the agents.
+pub const QUERIES_PER_MINUTE: u32 = 20;
+
+/// Buckets kept before full (idle) ones are dropped.
+const MAX_BUCKETS: usize = 10_000;
+
+/// A token bucket's size and refill rate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limit {
+    /// Requests that can be made at once
+    pub burst: u32,
+    /// Requests regained per second
+    pub per_second: f64,
+}
+
+impl Limit {
+    /// `count` requests a minute, all of which can be made at once.
+    pub fn per_minute(count: u32) -> Self {
+        Self {
+            burst: count.max(1),
+            per_second: count.max(1) as f64 / 60.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    limit: Limit,
+}
+
+/// Token buckets by caller and limit (0 is the default, then the endpoints').
+type Buckets = Mutex<HashMap<(String, usize), Bucket>>;
+
+/// Rate limit middleware: a default limit, with overrides for specific
+/// endpoints (each with its own bucket).
+pub struct RateLimit {
+    default: Limit,
+    endpoints: Vec<(String, PathPattern, Limit)>,
+    public: Vec<(String, PathPattern)>,
+    buckets: Arc<Buckets>,
+}
+
+/// The limit a request counts against, left in its extensions by the
+/// middleware until the caller is known.
+#[derive(Clone)]
+struct PendingLimit {
+    index: usize,
+    limit: Limit,
+    buckets: Arc<Buckets>,
+}
+
+impl Default for RateLimit {
+    /// [`DEFAULT_PER_MINUTE`], or `RATE_LIMIT_PER_MINUTE` if set.
+    fn default() -> Self {
+        let per_minute = std::env::var("RATE_LIMIT_PER_MINUTE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_PER_MINUTE);
+        Self::new(Limit::per_minute(per_minute))
+    }
+}
+
+impl RateLimit {
+    pub fn new(default: Limit) -> Self {
+        Self {
+            default,
+            endpoints: Vec::new(),
+            public: Vec::new(),
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Limit requests to `method` + `pattern` (e.g. `/export/{id}`) separately.
+    pub fn limit(mut self, method: &str, pattern: &str, limit: Limit) -> Self {
+        self.endpoints
+            .push((method.to_uppercase(), PathPattern::parse(pattern), limit));
+        self
+    }
+
+    /// Count requests to `method` + `pattern` by source IP whatever
+    /// credentials they carry, for endpoints that don't authenticate callers.
+    pub fn public(mut self, method: &str, pattern: &str) -> Self {
+        self.public
+            .push((method.to_uppercase(), PathPattern::parse(pattern)));
+        self
+    }
+
+    /// Take a token for an authenticated user's request, returning the
+    /// seconds to wait if there is none.
+    ///
+    /// For requests that don't come through the router, such as WebSocket
+    /// messages; `method` and `path` pick the endpoint's limit.
+    pub fn take_user(&self, cognito_sub: &str, method: &str, path: &str) -> Option<u64> {
+        self.take(&user_key(cognito_sub), method, path, Instant::now())
+    }
+
+    /// The bucket index and limit for a request: 0 is the default, then the
+    /// endpoints'.
+    fn endpoint(&self, method: &str, path: &str) -> (usize, Limit) {
+        self.endpoints
+            .iter()
+            .position(|(m, pattern, _)| m == method && pattern.matches(path).is_some())
+            .map_or((0, self.default), |i| (i + 1, self.endpoints[i].2))
+    }
+
+    /// Whether a request is counted by source IP up front rather than once
+    /// its caller is authenticated.
+    fn counts_by_ip(&self, req: &Request, info: &RequestInfo) -> bool {
+        !has_credentials(req)
+            || self
+                .public
+                .iter()
+                .any(|(m, pattern)| *m == info.method && pattern.matches(&info.path).is_some())
+    }
+
+    /// Take a token for `caller`'s request, returning the seconds to wait
+    /// if there is none.
+    fn take(&self, caller: &str, method: &str, path: &str, now: Instant) -> Option<u64> {
+        let (index, limit) = self.endpoint(method, path);
+        take(&self.buckets, caller, index, limit, now)
+    }
+}
+
+/// Take a token from `caller`'s bucket `index`, returning the seconds to
+/// wait if there is none.
+fn take(buckets: &Buckets, caller: &str, index: usize, limit: Limit, now: Instant) -> Option<u64> {
+    let mut buckets = buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+    if buckets.len() >= MAX_BUCKETS {
+        buckets.retain(|_, bucket| refill(bucket, now) < bucket.limit.burst as f64);
+    }
+
+    let bucket = buckets
+        .entry((caller.to_string(), index))
+        .or_insert(Bucket {
+            tokens: limit.burst as f64,
+            updated: now,
+            limit,
+        });
+    bucket.tokens = refill(bucket, now);
+    bucket.updated = now;
+
+    if bucket.tokens >= 1.0 {
+        bucket.tokens -= 1.0;
+        None
+    } else {
+        // Rounded to the millisecond first so float error can't add a second
+        let wait = (1.0 - bucket.tokens) / limit.per_second;
+        Some(((wait * 1000.0).round() / 1000.0).ceil().max(1.0) as u64)
+    }
+}
+
+/// Tokens in `bucket` at `now`.
+fn refill(bucket: &Bucket, now: Instant) -> f64 {
+    let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+    (bucket.tokens + elapsed * bucket.limit.per_second).min(bucket.limit.burst as f64)
+}
+
+/// Count a request against the limit the middleware left in it: by the
+/// authenticated caller's Cognito subject, or by source IP when
+/// authentication failed (so guessing keys is limited too).
+///
+/// `Error::RateLimited` when the caller is over the limit. Requests that
+/// didn't come through the middleware aren't limited.
+pub fn check_caller(req: &Request, cognito_sub: Option<&str>) -> Result<()> {
+    let Some(pending) = req.extensions().get::<PendingLimit>() else {
+        return Ok(());
+    };
+    let caller = match cognito_sub {
+        Some(sub) => user_key(sub),
+        None => ip_key(req),
+    };
+    let now = Instant::now();
+    match take(&pending.buckets, &caller, pending.index, pending.limit, now) {
+        Some(wait) => Err(Error::RateLimited(wait)),
+        None => Ok(()),
+    }
+}
+
+fn user_key(cognito_sub: &str) -> String {
+    format!("user:{}", cognito_sub)
+}
+
+fn ip_key(req: &Request) -> String {
+    let ip = match req.request_context_ref() {
+        Some(RequestContext::ApiGatewayV1(ctx)) => ctx.identity.source_ip.clone(),
+        Some(RequestContext::ApiGatewayV2(ctx)) => ctx.http.source_ip.clone(),
+        _ => None,
+    };
+    format!("ip:{}", ip.unwrap_or_default())
+}
+
+impl Middleware for RateLimit {
+    /// Requests without credentials, and any to public endpoints, are
+    /// counted by source IP up front.
+    fn before(&self, req: &Request, info: &RequestInfo) -> Option<Response<Body>> {
+        if !self.counts_by_ip(req, info) {
+            return None;
+        }
+        let wait = self.take(&ip_key(req), &info.method, &info.path, Instant::now())?;
+        ApiError::rate_limited(wait).into_response().ok()
+    }
+
+    /// The rest are counted once authenticated (see [`check_caller`]).
+    fn prepare(&self, req: &mut Request, info: &RequestInfo) {
+        if !self.counts_by_ip(req, info) {
+            let (index, limit) = self.endpoint(&info.method, &info.path);
+            req.extensions_mut().insert(PendingLimit {
+                index,
+                limit,
+                buckets: Arc::clone(&self.buckets),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_keys;
+    use std::time::Duration;
+
+    #[test]
+    fn bursts_then_limits() {
+        let limit = RateLimit::new(Limit::per_minute(3));
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert_eq!(limit.take("user:a", "GET", "/trash", now), None);
+        }
+        // One token comes back every 20s
+        assert_eq!(limit.take("user:a", "GET", "/trash", now), Some(20));
+    }
+
+    #[test]
+    fn tokens_refill_over_time() {
+        let limit = RateLimit::new(Limit::per_minute(60));
+        let now = Instant::now();
+
+        for _ in 0..60 {
+            limit.take("user:a", "GET", "/trash", now);
+        }
+        assert_eq!(limit.take("user:a", "GET", "/trash", now), Some(1));
+        assert_eq!(
+            limit.take("user:a", "GET", "/trash", now + Duration::from_secs(1)),
+            None
+        );
+    }
+
+    #[test]
+    fn callers_have_their_own_buckets() {
+        let limit = RateLimit::new(Limit::per_minute(1));
+        let now = Instant::now();
+
+        assert_eq!(limit.take("user:a", "GET", "/trash", now), None);
+        assert!(limit.take("user:a", "GET", "/trash", now).is_some());
+        assert_eq!(limit.take("user:b", "GET", "/trash", now), None);
+    }
+
+    #[test]
+    fn endpoints_can_have_their_own_limits() {
+        let limit =
+            RateLimit::new(Limit::per_minute(100)).limit("POST", "/export", Limit::per_minute(1));
+        let now = Instant::now();
+
+        assert_eq!(limit.take("user:a", "POST", "/export", now), None);
+        assert_eq!(limit.take("user:a", "POST", "/export", now), Some(60));
+        // Other endpoints still use the default bucket
+        assert_eq!(limit.take("user:a", "GET", "/export/123", now), None);
+    }
+
+    fn info(method: &str, path: &str) -> RequestInfo {
+        RequestInfo {
+            method: method.to_string(),
+            path: path.to_string(),
+            route: None,
+            origin: None,
+            accept_encoding: None,
+            fields: None,
+            started: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn key_holders_are_counted_once_authenticated() {
+        let limit = RateLimit::new(Limit::per_minute(1));
+        let info = info("GET", "/trash");
+        let request = |key: &str| {
+            let mut req = lambda_http::http::Request::builder()
+                .header(api_keys::API_KEY_HEADER, key)
+                .body(Body::Empty)
+                .unwrap();
+            assert!(limit.before(&req, &info).is_none());
+            limit.prepare(&mut req, &info);
+            req
+        };
+
+        assert!(check_caller(&request("sb_one"), Some("sub-a")).is_ok());
+        // A different key for the same user shares the user's bucket
+        assert!(matches!(
+            check_caller(&request("sb_two"), Some("sub-a")),
+            Err(Error::RateLimited(60))
+        ));
+        assert!(check_caller(&request("sb_three"), Some("sub-b")).is_ok());
+    }
+
+    #[test]
+    fn public_endpoints_are_counted_by_ip() {
+        let limit = RateLimit::new(Limit::per_minute(1)).public("GET", "/handoffs/shared/{token}");
+        let info = info("GET", "/handoffs/shared/abc");
+        let request = || {
+            lambda_http::http::Request::builder()
+                .header(api_keys::API_KEY_HEADER, "sb_made_up")
+                .body(Body::Empty)
+                .unwrap()
+        };
+
+        assert!(limit.before(&request(), &info).is_none());
+        // A key header doesn't get around the limit
+        let response = limit.before(&request(), &info).unwrap();
+        assert_eq!(response.status(), 429);
+    }
+}
@@ -0,0 +1,435 @@
+//! Real-time updates over the WebSocket API.
+//!
+//! Clients fetch a one-time ticket from `POST /realtime/ticket` (behind the
+//! Cognito authorizer) and connect with `?ticket=`, since browsers can't set
+//! headers on a WebSocket handshake. Tickets and open connections live in a
+//! DynamoDB table: `pk` is `ticket#<ticket>` or `conn#<connection id>`, and
+//! connections carry `connected_user_id` for the `user-connections` index.
+//! Both expire through the table's `expires_at` TTL.
+//!
+//! [`Broadcaster`] pushes [`ServerMessage`]s to every connection a user has
+//! open, dropping connections API Gateway reports as gone.
+
+use std::collections::HashMap;
+
+use aws_sdk_apigatewaymanagement::primitives::Blob;
+use aws_sdk_dynamodb::types::{AttributeValue, ReturnValue};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{Error, Result};
+
+/// Seconds a connect ticket stays valid
+pub const TICKET_TTL_SECS: i64 = 60;
+
+/// Seconds a connection record is kept; API Gateway closes connections after
+/// two hours
+pub const CONNECTION_TTL_SECS: i64 = 3 * 60 * 60;
+
+/// Longest chunk of an agent answer sent in one frame (API Gateway frames
+/// are at most 32 KB)
+pub const CHUNK_CHARS: usize = 2_000;
+
+/// Index on `connected_user_id`
+pub const USER_CONNECTIONS_INDEX: &str = "user-connections";
+
+/// Message pushed to clients.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+pub enum ServerMessage {
+    /// A fact the user created was stored
+    FactCreated { fact_id: Uuid, source: String },
+    /// One of the user's reminders fired
+    ReminderFired {
+        reminder_id: Uuid,
+        notification_id: Uuid,
+    },
+    /// Part of the answer to a `query` message; concatenate by `seq`
+    AgentChunk {
+        request_id: String,
+        seq: u32,
+        text: String,
+    },
+    /// The answer to a `query` message is complete
+    AgentDone {
+        request_id: String,
+        agents_used: Vec<String>,
+    },
+    /// A `query` message failed
+    AgentError { request_id: String, error: String },
+    /// Reply to `ping`
+    Pong,
+}
+
+/// Message sent by clients, routed on `action`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(
+    tag = "action",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+pub enum ClientMessage {
+    /// Ask the agents a question; the answer arrives as `agent_chunk`s
+    Query {
+        request_id: String,
+        query: String,
+        #[serde(default)]
+        session_id: Option<String>,
+    },
+    /// Keep the connection from idling out (API Gateway closes it after 10
+    /// minutes without traffic)
+    Ping,
+}
+
+/// Who a ticket or connection belongs to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionUser {
+    pub user_id: Uuid,
+    /// Sent to the agents as the user ID
+    pub cognito_sub: String,
+    pub family_ids: Vec<Uuid>,
+}
+
+/// Split `text` into chunks of at most `max_chars` characters, breaking after
+/// whitespace where possible. The chunks concatenate back to `text`.
+pub fn chunk_text(text: &str, max_chars: usize) -> Vec<String> {
+    let max_chars = max_chars.max(1);
+    let mut chunks = Vec::new();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let Some((limit, _)) = rest.char_indices().nth(max_chars) else {
+            chunks.push(rest.to_string());
+            break;
+        };
+
+        // Break after the last whitespace in the window, or mid-word if
+        // there is none
+        let cut = rest[..limit]
+            .char_indices()
+            .rfind(|(_, c)| c.is_whitespace())
+            .map(|(i, c)| i + c.len_utf8())
+            .unwrap_or(limit);
+
+        chunks.push(rest[..cut].to_string());
+        rest = &rest[cut..];
+    }
+
+    chunks
+}
+
+/// Tickets and connections in DynamoDB.
+pub struct ConnectionStore {
+    client: aws_sdk_dynamodb::Client,
+    table_name: String,
+}
+
+impl ConnectionStore {
+    pub fn new(client: aws_sdk_dynamodb::Client, table_name: String) -> Self {
+        Self { client, table_name }
+    }
+
+    /// Issue a one-time connect ticket for `user`.
+    pub async fn create_ticket(&self, user: &ConnectionUser) -> Result<String> {
+        let ticket = Uuid::new_v4().simple().to_string();
+        let expires_at = Utc::now().timestamp() + TICKET_TTL_SECS;
+
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(user_item(
+                format!("ticket#{}", ticket),
+                user,
+                expires_at,
+            )))
+            .send()
+            .await
+            .map_err(|e| Error::Aws(format!("Failed to store ticket: {}", e)))?;
+
+        Ok(ticket)
+    }
+
+    /// Use up a ticket, returning its user if it existed and hadn't expired.
+    pub async fn redeem_ticket(&self, ticket: &str) -> Result<Option<ConnectionUser>> {
+        let output = self
+            .client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("pk", AttributeValue::S(format!("ticket#{}", ticket)))
+            .return_values(ReturnValue::AllOld)
+            .send()
+            .await
+            .map_err(|e| Error::Aws(format!("Failed to redeem ticket: {}", e)))?;
+
+        // TTL deletion lags, so expiry is checked here too
+        Ok(output
+            .attributes()
+            .filter(|item| number(item, "expires_at").is_some_and(|t| t > Utc::now().timestamp()))
+            .and_then(parse_user))
+    }
+
+    /// Record an open connection.
+    pub async fn add_connection(&self, connection_id: &str, user: &ConnectionUser) -> Result<()> {
+        let expires_at = Utc::now().timestamp() + CONNECTION_TTL_SECS;
+        let mut item = user_item(format!("conn#{}", connection_id), user, expires_at);
+        item.insert(
+            "connected_user_id".to_string(),
+            AttributeValue::S(user.user_id.to_string()),
+        );
+
+        self.client
+            .put_item()
+            .table_name(&self.table_name)
+            .set_item(Some(item))
+            .send()
+            .await
+            .map_err(|e| Error::Aws(format!("Failed to store connection: {}", e)))?;
+
+        Ok(())
+    }
+
+    pub async fn remove_connection(&self, connection_id: &str) -> Result<()> {
+        self.client
+            .delete_item()
+            .table_name(&self.table_name)
+            .key("pk", AttributeValue::S(format!("conn#{}", connection_id)))
+            .send()
+            .await
+            .map_err(|e| Error::Aws(format!("Failed to remove connection: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// User a connection belongs to, if it is still recorded.
+    pub async fn connection_user(&self, connection_id: &str) -> Result<Option<ConnectionUser>> {
+        let output = self
+            .client
+            .get_item()
+            .table_name(&self.table_name)
+            .key("pk", AttributeValue::S(format!("conn#{}", connection_id)))
+            .send()
+            .await
+            .map_err(|e| Error::Aws(format!("Failed to look up connection: {}", e)))?;
+
+        Ok(output.item().and_then(parse_user))
+    }
+
+    /// IDs of the connections `user_id` has open.
+    pub async fn user_connections(&self, user_id: Uuid) -> Result<Vec<String>> {
+        let output = self
+            .client
+            .query()
+            .table_name(&self.table_name)
+            .index_name(USER_CONNECTIONS_INDEX)
+            .key_condition_expression("connected_user_id = :user_id")
+            .expression_attribute_values(":user_id", AttributeValue::S(user_id.to_string()))
+            .send()
+            .await
+            .map_err(|e| Error::Aws(format!("Failed to list connections: {}", e)))?;
+
+        Ok(output
+            .items()
+            .iter()
+            .filter_map(|item| item.get("pk")?.as_s().ok()?.strip_prefix("conn#"))
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+fn user_item(
+    pk: String,
+    user: &ConnectionUser,
+    expires_at: i64,
+) -> HashMap<String, AttributeValue> {
+    HashMap::from([
+        ("pk".to_string(), AttributeValue::S(pk)),
+        (
+            "user_id".to_string(),
+            AttributeValue::S(user.user_id.to_string()),
+        ),
+        (
+            "cognito_sub".to_string(),
+            AttributeValue::S(user.cognito_sub.clone()),
+        ),
+        (
+            "family_ids".to_string(),
+            AttributeValue::L(
+                user.family_ids
+                    .iter()
+                    .map(|id| AttributeValue::S(id.to_string()))
+                    .collect(),
+            ),
+        ),
+        (
+            "expires_at".to_string(),
+            AttributeValue::N(expires_at.to_string()),
+        ),
+    ])
+}
+
+fn number(item: &HashMap<String, AttributeValue>, key: &str) -> Option<i64> {
+    item.get(key)?.as_n().ok()?.parse().ok()
+}
+
+fn parse_user(item: &HashMap<String, AttributeValue>) -> Option<ConnectionUser> {
+    let user_id = Uuid::parse_str(item.get("user_id")?.as_s().ok()?).ok()?;
+    let cognito_sub = item.get("cognito_sub")?.as_s().ok()?.clone();
+    let family_ids = item
+        .get("family_ids")
+        .and_then(|v| v.as_l().ok())
+        .map(|ids| {
+            ids.iter()
+                .filter_map(|id| Uuid::parse_str(id.as_s().ok()?).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(ConnectionUser {
+        user_id,
+        cognito_sub,
+        family_ids,
+    })
+}
+
+/// Pushes messages to connected clients.
+pub struct Broadcaster {
+    client: aws_sdk_apigatewaymanagement::Client,
+    store: ConnectionStore,
+}
+
+impl Broadcaster {
+    /// `endpoint` is the WebSocket API's callback URL,
+    /// `https://{api-id}.execute-api.{region}.amazonaws.com/{stage}`.
+    pub fn new(config: &aws_config::SdkConfig, endpoint: &str, store: ConnectionStore) -> Self {
+        let management_config = aws_sdk_apigatewaymanagement::config::Builder::from(config)
+            .endpoint_url(endpoint)
+            .build();
+
+        Self {
+            client: aws_sdk_apigatewaymanagement::Client::from_conf(management_config),
+            store,
+        }
+    }
+
+    pub fn store(&self) -> &ConnectionStore {
+        &self.store
+    }
+
+    /// Send to one connection. Returns `false` (and forgets the connection)
+    /// if the client has gone.
+    pub async fn send(&self, connection_id: &str, message: &ServerMessage) -> Result<bool> {
+        let data = serde_json::to_vec(message)?;
+
+        match self
+            .client
+            .post_to_connection()
+            .connection_id(connection_id)
+            .data(Blob::new(data))
+            .send()
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_gone_exception()) => {
+                self.store.remove_connection(connection_id).await?;
+                Ok(false)
+            }
+            Err(e) => Err(Error::Aws(format!(
+                "Failed to send to connection {}: {}",
+                connection_id, e
+            ))),
+        }
+    }
+
+    /// Send to every connection `user_id` has open, returning how many were
+    /// reached. A failing connection doesn't stop the rest.
+    pub async fn send_to_user(&self, user_id: Uuid, message: &ServerMessage) -> Result<usize> {
+        let mut delivered = 0;
+        for connection_id in self.store.user_connections(user_id).await? {
+            match self.send(&connection_id, message).await {
+                Ok(true) => delivered += 1,
+                Ok(false) => {}
+                Err(e) => tracing::warn!(user_id = %user_id, "{}", e),
+            }
+        }
+        Ok(delivered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn chunks_rejoin_to_the_original() {
+        let text = "The dentist is on Tuesday at 3pm. Bring the insurance card.";
+        let chunks = chunk_text(text, 20);
+        assert_eq!(chunks.concat(), text);
+        assert!(chunks.iter().all(|c| c.chars().count() <= 20));
+        assert_eq!(chunks[0], "The dentist is on ");
+    }
+
+    #[test]
+    fn splits_long_words_and_multibyte_text() {
+        assert_eq!(chunk_text("abcdefgh", 3), vec!["abc", "def", "gh"]);
+        assert_eq!(chunk_text("héllo wörld", 6), vec!["héllo ", "wörld"]);
+        assert_eq!(chunk_text("", 10), Vec::<String>::new());
+        assert_eq!(chunk_text("short", 10), vec!["short"]);
+    }
+
+    #[test]
+    fn serializes_server_messages() {
+        let message = ServerMessage::AgentChunk {
+            request_id: "r1".to_string(),
+            seq: 0,
+            text: "Hi".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            json!({ "type": "agent_chunk", "requestId": "r1", "seq": 0, "text": "Hi" })
+        );
+        assert_eq!(
+            serde_json::to_value(ServerMessage::Pong).unwrap(),
+            json!({ "type": "pong" })
+        );
+    }
+
+    #[test]
+    fn parses_client_messages() {
+        let message: ClientMessage = serde_json::from_value(json!({
+            "action": "query",
+            "requestId": "r1",
+            "query": "When is the dentist?"
+        }))
+        .unwrap();
+        assert_eq!(
+            message,
+            ClientMessage::Query {
+                request_id: "r1".to_string(),
+                query: "When is the dentist?".to_string(),
+                session_id: None,
+            }
+        );
+
+        let ping: ClientMessage = serde_json::from_value(json!({ "action": "ping" })).unwrap();
+        assert_eq!(ping, ClientMessage::Ping);
+        assert!(serde_json::from_value::<ClientMessage>(json!({ "action": "shout" })).is_err());
+    }
+
+    #[test]
+    fn round_trips_connection_users() {
+        let user = ConnectionUser {
+            user_id: Uuid::from_u128(1),
+            cognito_sub: "sub-1".to_string(),
+            family_ids: vec![Uuid::from_u128(2)],
+        };
+        let item = user_item("conn#abc".to_string(), &user, 100);
+        assert_eq!(parse_user(&item), Some(user));
+        assert_eq!(number(&item, "expires_at"), Some(100));
+    }
+}
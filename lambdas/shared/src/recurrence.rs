@@ -0,0 +1,1117 @@
+//! Recurring reminder schedules.
+//!
+//! A `recurring` reminder's `trigger_config` is one of:
+//!
+//! - `{"frequency": "daily", "time": "09:00"}` (`{"time": "09:00"}` alone is daily too)
+//! - `{"frequency": "weekly", "days": ["mon", "thu"], "time": "09:00"}`
+//! - `{"frequency": "monthly", "dayOfMonth": 31, "time": "09:00"}` (clamped to
+//!   the month's last day)
+//! - `{"cron": "0 9 * * 1-5"}` (minute hour day-of-month month day-of-week)
+//! - `{"interval": "2 hours"}` (fixed interval from the last trigger)
+//! - `{"rrule": "FREQ=WEEKLY;BYDAY=MO,WE", "dtstart": "2026-10-19T09:00"}`
+//!   (iCalendar recurrence rule; see [`RRule`])
+//!
+//! Times are wall-clock times in the config's `timezone`, or the user's
+//! preference if it has none, so a 09:00 reminder stays at 09:00 across DST
+//! changes. A time skipped by a DST change fires at the first instant after the
+//! gap; a repeated time fires on its first occurrence.
+
+use chrono::{
+    DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc,
+    Weekday,
+};
+use chrono_tz::Tz;
+use serde_json::Value;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{Error, Result};
+
+/// Days searched for the next cron match (covers Feb 29 every four years)
+const MAX_SEARCH_DAYS: i64 = 366 * 8;
+
+/// Timezone used when a user has no preferences row
+const DEFAULT_TIMEZONE: Tz = Tz::America__New_York;
+
+/// Parsed recurrence rule
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recurrence {
+    Daily(NaiveTime),
+    Weekly { days: Vec<Weekday>, time: NaiveTime },
+    Monthly { day: u32, time: NaiveTime },
+    Cron(CronSchedule),
+    Interval(Duration),
+    RRule(RRule),
+}
+
+/// Recurrence rule and the timezone it is evaluated in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    pub recurrence: Recurrence,
+    pub timezone: Tz,
+}
+
+impl Schedule {
+    /// Parse a `recurring` trigger config; `default_tz` applies when the config
+    /// has no `timezone`.
+    pub fn from_config(config: &Value, default_tz: Tz) -> Result<Self> {
+        // An imported rule's DTSTART;TZID applies when the config has no timezone
+        let name = config.get("timezone").and_then(Value::as_str).or_else(|| {
+            config
+                .get("rrule")
+                .and_then(Value::as_str)
+                .and_then(ical_tzid)
+        });
+
+        let timezone = match name {
+            Some(name) => name
+                .parse()
+                .map_err(|_| Error::Validation(format!("Unknown timezone: {}", name)))?,
+            None => default_tz,
+        };
+
+        Ok(Self {
+            recurrence: parse_recurrence(config, timezone)?,
+            timezone,
+        })
+    }
+
+    /// First occurrence strictly after `after`.
+    ///
+    /// For intervals, `after` is the last trigger time. `None` if a cron
+    /// expression never matches (e.g. February 30th) or an RRULE has ended.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let local_start = after.with_timezone(&self.timezone).date_naive();
+
+        let rule = match &self.recurrence {
+            Recurrence::Interval(interval) => return Some(after + *interval),
+            Recurrence::RRule(rule) => rule,
+            _ => {
+                return self
+                    .occurrences(local_start, MAX_SEARCH_DAYS)
+                    .find(|at| *at > after)
+            }
+        };
+
+        let dtstart = rule
+            .dtstart
+            .and_then(|local| resolve_local(&self.timezone, local));
+        let next = match (rule.count, rule.dtstart) {
+            // COUNT is satisfied by counting occurrences from DTSTART
+            (Some(count), Some(start)) => {
+                let days = (local_start - start.date()).num_days().max(0) + MAX_SEARCH_DAYS;
+                self.occurrences(start.date(), days)
+                    .filter(|at| dtstart.is_none_or(|start| *at >= start))
+                    .take(count)
+                    .find(|at| *at > after)
+            }
+            _ => self
+                .occurrences(local_start, MAX_SEARCH_DAYS)
+                .filter(|at| dtstart.is_none_or(|start| *at >= start))
+                .find(|at| *at > after),
+        };
+
+        next.filter(|at| rule.until.is_none_or(|until| *at <= until))
+    }
+
+    /// Occurrences on `days` local days from `start`, in order.
+    fn occurrences(&self, start: NaiveDate, days: i64) -> impl Iterator<Item = DateTime<Utc>> + '_ {
+        (0..days)
+            .filter_map(move |offset| start.checked_add_signed(Duration::days(offset)))
+            .flat_map(move |date| {
+                self.times_on(date)
+                    .into_iter()
+                    .map(move |t| date.and_time(t))
+            })
+            .filter_map(move |local| resolve_local(&self.timezone, local))
+    }
+
+    /// Local fire times on `date`, in order.
+    fn times_on(&self, date: NaiveDate) -> Vec<NaiveTime> {
+        match &self.recurrence {
+            Recurrence::Daily(time) => vec![*time],
+            Recurrence::Weekly { days, time } => {
+                if days.contains(&date.weekday()) {
+                    vec![*time]
+                } else {
+                    vec![]
+                }
+            }
+            Recurrence::Monthly { day, time } => {
+                if date.day() == (*day).min(days_in_month(date)) {
+                    vec![*time]
+                } else {
+                    vec![]
+                }
+            }
+            Recurrence::Cron(cron) => cron.times_on(date),
+            Recurrence::RRule(rule) => rule.times_on(date),
+            Recurrence::Interval(_) => vec![],
+        }
+    }
+}
+
+/// Local time as a UTC instant; times in a DST gap move past the gap.
+pub(crate) fn resolve_local(tz: &Tz, local: NaiveDateTime) -> Option<DateTime<Utc>> {
+    tz.from_local_datetime(&local)
+        .earliest()
+        .or_else(|| {
+            tz.from_local_datetime(&(local + Duration::hours(1)))
+                .earliest()
+        })
+        .map(|at| at.with_timezone(&Utc))
+}
+
+fn days_in_year(date: NaiveDate) -> u32 {
+    if NaiveDate::from_ymd_opt(date.year(), 2, 29).is_some() {
+        366
+    } else {
+        365
+    }
+}
+
+fn days_in_month(date: NaiveDate) -> u32 {
+    let (year, month) = if date.month() == 12 {
+        (date.year() + 1, 1)
+    } else {
+        (date.year(), date.month() + 1)
+    };
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|first| first.pred_opt())
+        .map_or(31, |last| last.day())
+}
+
+fn parse_time(config: &Value) -> Result<NaiveTime> {
+    let value = config
+        .get("time")
+        .and_then(Value::as_str)
+        .unwrap_or("09:00");
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M:%S"))
+        .map_err(|_| Error::Validation(format!("Invalid time (expected HH:MM): {}", value)))
+}
+
+fn parse_weekday(value: &Value) -> Option<Weekday> {
+    match value {
+        Value::Number(n) => n.as_u64().and_then(|n| cron_weekday(n as u32)),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Cron day-of-week number (0 and 7 are Sunday)
+fn cron_weekday(n: u32) -> Option<Weekday> {
+    match n {
+        0 | 7 => Some(Weekday::Sun),
+        1..=6 => Weekday::try_from(n as u8 - 1).ok(),
+        _ => None,
+    }
+}
+
+/// Parse a Postgres-style interval such as `"1 day"` or `"90 minutes"`.
+fn parse_interval(value: &str) -> Option<Duration> {
+    let mut parts = value.split_whitespace();
+    let amount: i64 = parts.next()?.parse().ok()?;
+    let unit = parts.next().unwrap_or("days").trim_end_matches('s');
+    if parts.next().is_some() || amount <= 0 {
+        return None;
+    }
+
+    match unit {
+        "minute" | "min" => Some(Duration::minutes(amount)),
+        "hour" => Some(Duration::hours(amount)),
+        "day" => Some(Duration::days(amount)),
+        "week" => Some(Duration::weeks(amount)),
+        _ => None,
+    }
+}
+
+fn parse_recurrence(config: &Value, timezone: Tz) -> Result<Recurrence> {
+    if let Some(rrule) = config.get("rrule").and_then(Value::as_str) {
+        let dtstart = config.get("dtstart").and_then(Value::as_str);
+        return RRule::parse(rrule, dtstart, parse_time(config)?, timezone).map(Recurrence::RRule);
+    }
+
+    if let Some(cron) = config.get("cron").and_then(Value::as_str) {
+        return CronSchedule::parse(cron).map(Recurrence::Cron);
+    }
+
+    if let Some(interval) = config.get("interval").and_then(Value::as_str) {
+        return parse_interval(interval)
+            .map(Recurrence::Interval)
+            .ok_or_else(|| Error::Validation(format!("Invalid interval: {}", interval)));
+    }
+
+    let time = parse_time(config)?;
+    match config
+        .get("frequency")
+        .and_then(Value::as_str)
+        .unwrap_or("daily")
+    {
+        "daily" => Ok(Recurrence::Daily(time)),
+        "weekly" => {
+            let days = config
+                .get("days")
+                .and_then(Value::as_array)
+                .map(|days| days.iter().map(parse_weekday).collect::<Option<Vec<_>>>())
+                .ok_or_else(|| Error::Validation("Weekly reminders need days".to_string()))?
+                .ok_or_else(|| Error::Validation("Invalid day in days".to_string()))?;
+            if days.is_empty() {
+                return Err(Error::Validation("Weekly reminders need days".to_string()));
+            }
+            Ok(Recurrence::Weekly { days, time })
+        }
+        "monthly" => {
+            let day = config
+                .get("dayOfMonth")
+                .and_then(Value::as_u64)
+                .filter(|d| (1..=31).contains(d))
+                .ok_or_else(|| {
+                    Error::Validation("Monthly reminders need dayOfMonth (1-31)".to_string())
+                })?;
+            Ok(Recurrence::Monthly {
+                day: day as u32,
+                time,
+            })
+        }
+        other => Err(Error::Validation(format!(
+            "Invalid frequency: {} (expected daily, weekly or monthly)",
+            other
+        ))),
+    }
+}
+
+/// Five-field cron expression: minute, hour, day of month, month, day of week.
+///
+/// Fields accept `*`, numbers, ranges (`1-5`), lists (`1,15`) and steps
+/// (`*/15`, `9-17/2`). Day-of-week also accepts `sun`..`sat`. As in cron, when
+/// both day fields are restricted a day matching either one fires.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    minutes: Vec<u32>,
+    hours: Vec<u32>,
+    days_of_month: Vec<u32>,
+    months: Vec<u32>,
+    days_of_week: Vec<u32>,
+    dom_restricted: bool,
+    dow_restricted: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(Error::Validation(format!(
+                "Cron expression needs 5 fields: {}",
+                expression
+            )));
+        }
+
+        let invalid = || Error::Validation(format!("Invalid cron expression: {}", expression));
+
+        let mut days_of_week = parse_field(fields[4], 0, 7, true).ok_or_else(invalid)?;
+        // 7 is Sunday as well as 0
+        if days_of_week.contains(&7) {
+            days_of_week.retain(|d| *d != 7);
+            if !days_of_week.contains(&0) {
+                days_of_week.insert(0, 0);
+            }
+        }
+
+        Ok(Self {
+            minutes: parse_field(fields[0], 0, 59, false).ok_or_else(invalid)?,
+            hours: parse_field(fields[1], 0, 23, false).ok_or_else(invalid)?,
+            days_of_month: parse_field(fields[2], 1, 31, false).ok_or_else(invalid)?,
+            months: parse_field(fields[3], 1, 12, false).ok_or_else(invalid)?,
+            days_of_week,
+            dom_restricted: fields[2] != "*",
+            dow_restricted: fields[4] != "*",
+        })
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if !self.months.contains(&date.month()) {
+            return false;
+        }
+
+        let dom = self.days_of_month.contains(&date.day());
+        let dow = self
+            .days_of_week
+            .contains(&date.weekday().num_days_from_sunday());
+
+        match (self.dom_restricted, self.dow_restricted) {
+            (true, true) => dom || dow,
+            (true, false) => dom,
+            (false, true) => dow,
+            (false, false) => true,
+        }
+    }
+
+    fn times_on(&self, date: NaiveDate) -> Vec<NaiveTime> {
+        if !self.matches_date(date) {
+            return vec![];
+        }
+
+        self.hours
+            .iter()
+            .flat_map(|h| {
+                self.minutes
+                    .iter()
+                    .filter_map(move |m| NaiveTime::from_hms_opt(*h, *m, 0))
+            })
+            .collect()
+    }
+}
+
+/// Values of one cron field, sorted and deduplicated.
+fn parse_field(field: &str, min: u32, max: u32, weekday_names: bool) -> Option<Vec<u32>> {
+    let value = |s: &str| -> Option<u32> {
+        if weekday_names {
+            if let Ok(day) = s.parse::<Weekday>() {
+                return Some(day.num_days_from_sunday());
+            }
+        }
+        s.parse().ok().filter(|v| (min..=max).contains(v))
+    };
+
+    let mut values = Vec::new();
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|s| *s > 0)?),
+            None => (part, 1),
+        };
+
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((a, b)) => (value(a)?, value(b)?),
+                // "5/10" means every 10 starting at 5
+                None if step > 1 => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            },
+        };
+        if start > end {
+            return None;
+        }
+
+        values.extend((start..=end).step_by(step as usize));
+    }
+
+    values.sort_unstable();
+    values.dedup();
+    Some(values)
+}
+
+/// iCalendar (RFC 5545) recurrence rule.
+///
+/// Supports `FREQ` (`HOURLY`, `DAILY`, `WEEKLY`, `MONTHLY`, `YEARLY`),
+/// `INTERVAL`, `COUNT`, `UNTIL`, `WKST` and the `BYMONTH`, `BYMONTHDAY`,
+/// `BYDAY` (with ordinals such as `2TU` or `-1FR` for monthly and yearly
+/// rules), `BYHOUR`, `BYMINUTE` and `BYSETPOS` parts. The rule may be a bare
+/// `FREQ=...` string or imported text with `DTSTART` and `RRULE:` lines.
+///
+/// `DTSTART` anchors `INTERVAL` and `COUNT` and supplies the day and time
+/// the rule leaves out; without one, times come from the config's `time`.
+/// `BYSETPOS` selects among the matching days of each period.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RRule {
+    frequency: Frequency,
+    interval: u32,
+    count: Option<usize>,
+    until: Option<DateTime<Utc>>,
+    dtstart: Option<NaiveDateTime>,
+    week_start: Weekday,
+    months: Vec<u32>,
+    month_days: Vec<i32>,
+    weekdays: Vec<(Option<i32>, Weekday)>,
+    hours: Vec<u32>,
+    minutes: Vec<u32>,
+    set_positions: Vec<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frequency {
+    Hourly,
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl RRule {
+    /// Parse `rule`; `dtstart` (RFC 3339, local ISO or iCalendar form) is
+    /// used when the text has no `DTSTART` line, and `time` when neither does.
+    pub fn parse(rule: &str, dtstart: Option<&str>, time: NaiveTime, tz: Tz) -> Result<Self> {
+        let invalid =
+            |detail: &str| Error::Validation(format!("Invalid rrule ({}): {}", detail, rule));
+
+        let mut rrule_line = None;
+        let mut dtstart_value = dtstart.map(str::to_string);
+        for line in rule.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let upper = line.to_ascii_uppercase();
+            if upper.starts_with("DTSTART") {
+                // DTSTART;TZID=...:20261016T090000 - the value follows the last ':'
+                dtstart_value = line.rsplit(':').next().map(str::to_string);
+            } else if let Some(rest) = upper.strip_prefix("RRULE:") {
+                rrule_line = Some(rest.to_string());
+            } else if upper.starts_with("FREQ=") || upper.contains(";FREQ=") {
+                rrule_line = Some(upper);
+            } else {
+                return Err(invalid("unexpected line"));
+            }
+        }
+        let rrule_line = rrule_line.ok_or_else(|| invalid("missing FREQ"))?;
+
+        let dtstart = match dtstart_value {
+            Some(value) => {
+                let (start, date_only) =
+                    parse_rrule_datetime(&value, tz).ok_or_else(|| invalid("DTSTART"))?;
+                // A date-only DTSTART starts at the config's time
+                Some(if date_only {
+                    start.date().and_time(time)
+                } else {
+                    start
+                })
+            }
+            None => None,
+        };
+        let start_time = dtstart.map_or(time, |start| start.time());
+
+        let mut frequency = None;
+        let mut parsed = Self {
+            frequency: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            dtstart,
+            week_start: Weekday::Mon,
+            months: vec![],
+            month_days: vec![],
+            weekdays: vec![],
+            hours: vec![],
+            minutes: vec![],
+            set_positions: vec![],
+        };
+
+        for part in rrule_line.split(';').filter(|p| !p.is_empty()) {
+            let (key, value) = part.split_once('=').ok_or_else(|| invalid(part))?;
+            match key {
+                "FREQ" => {
+                    frequency = Some(match value {
+                        "HOURLY" => Frequency::Hourly,
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => {
+                            return Err(invalid(
+                                "FREQ must be HOURLY, DAILY, WEEKLY, MONTHLY or YEARLY",
+                            ))
+                        }
+                    })
+                }
+                "INTERVAL" => {
+                    parsed.interval = value
+                        .parse()
+                        .ok()
+                        .filter(|i| *i > 0)
+                        .ok_or_else(|| invalid(part))?
+                }
+                "COUNT" => {
+                    parsed.count = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|c| *c > 0)
+                            .ok_or_else(|| invalid(part))?,
+                    )
+                }
+                "UNTIL" => {
+                    let (local, date_only) =
+                        parse_rrule_datetime(value, tz).ok_or_else(|| invalid(part))?;
+                    // A date-only UNTIL includes that whole day
+                    let local = if date_only {
+                        local.date().and_hms_opt(23, 59, 59).unwrap_or(local)
+                    } else {
+                        local
+                    };
+                    parsed.until = resolve_local(&tz, local);
+                }
+                "WKST" => parsed.week_start = ical_weekday(value).ok_or_else(|| invalid(part))?,
+                "BYMONTH" => {
+                    parsed.months = parse_list(value, |v| {
+                        v.parse::<u32>().ok().filter(|m| (1..=12).contains(m))
+                    })
+                    .ok_or_else(|| invalid(part))?
+                }
+                "BYMONTHDAY" => {
+                    parsed.month_days = parse_list(value, |v| {
+                        v.parse().ok().filter(|d: &i32| (1..=31).contains(&d.abs()))
+                    })
+                    .ok_or_else(|| invalid(part))?
+                }
+                "BYDAY" => {
+                    parsed.weekdays =
+                        parse_list(value, parse_ical_byday).ok_or_else(|| invalid(part))?
+                }
+                "BYHOUR" => {
+                    parsed.hours = parse_list(value, |v| v.parse::<u32>().ok().filter(|h| *h <= 23))
+                        .ok_or_else(|| invalid(part))?
+                }
+                "BYMINUTE" => {
+                    parsed.minutes =
+                        parse_list(value, |v| v.parse::<u32>().ok().filter(|m| *m <= 59))
+                            .ok_or_else(|| invalid(part))?
+                }
+                "BYSETPOS" => {
+                    parsed.set_positions = parse_list(value, |v| {
+                        v.parse()
+                            .ok()
+                            .filter(|p: &i32| (1..=366).contains(&p.abs()))
+                    })
+                    .ok_or_else(|| invalid(part))?
+                }
+                _ => return Err(invalid(&format!("{} is not supported", key))),
+            }
+        }
+
+        parsed.frequency = frequency.ok_or_else(|| invalid("missing FREQ"))?;
+        parsed.hours.sort_unstable();
+        parsed.hours.dedup();
+        parsed.minutes.sort_unstable();
+        parsed.minutes.dedup();
+        if parsed.hours.is_empty() && parsed.frequency != Frequency::Hourly {
+            parsed.hours.push(start_time.hour());
+        }
+        if parsed.minutes.is_empty() {
+            parsed.minutes.push(start_time.minute());
+        }
+
+        parsed.validate().map_err(invalid)?;
+        Ok(parsed)
+    }
+
+    fn validate(&self) -> std::result::Result<(), &'static str> {
+        let by_period = matches!(self.frequency, Frequency::Monthly | Frequency::Yearly);
+        if !by_period && self.weekdays.iter().any(|(n, _)| n.is_some()) {
+            return Err("BYDAY ordinals need FREQ=MONTHLY or YEARLY");
+        }
+        if !self.set_positions.is_empty()
+            && !matches!(
+                self.frequency,
+                Frequency::Weekly | Frequency::Monthly | Frequency::Yearly
+            )
+        {
+            return Err("BYSETPOS needs FREQ=WEEKLY, MONTHLY or YEARLY");
+        }
+        if self.dtstart.is_none() {
+            if self.interval > 1 || self.count.is_some() {
+                return Err("INTERVAL and COUNT need a DTSTART");
+            }
+            let day_rules = !self.weekdays.is_empty() || !self.month_days.is_empty();
+            if !day_rules && !matches!(self.frequency, Frequency::Hourly | Frequency::Daily) {
+                return Err("needs BYDAY, BYMONTHDAY or a DTSTART");
+            }
+        }
+        Ok(())
+    }
+
+    /// Local fire times on `date`, in order.
+    fn times_on(&self, date: NaiveDate) -> Vec<NaiveTime> {
+        if !self.matches_date(date) {
+            return vec![];
+        }
+
+        let hours: Vec<u32> = match self.frequency {
+            Frequency::Hourly => (0..24)
+                .filter(|h| self.hours.is_empty() || self.hours.contains(h))
+                .filter(|h| self.in_interval(date, *h))
+                .collect(),
+            _ => self.hours.clone(),
+        };
+
+        hours
+            .iter()
+            .flat_map(|h| {
+                self.minutes
+                    .iter()
+                    .filter_map(move |m| NaiveTime::from_hms_opt(*h, *m, 0))
+            })
+            .collect()
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if self.frequency != Frequency::Hourly && !self.in_interval(date, 0) {
+            return false;
+        }
+        if self.set_positions.is_empty() {
+            return self.day_matches(date);
+        }
+
+        let (first, last) = self.period(date);
+        let days: Vec<NaiveDate> = first
+            .iter_days()
+            .take_while(|d| *d <= last)
+            .filter(|d| self.day_matches(*d))
+            .collect();
+
+        self.set_positions.iter().any(|pos| {
+            let index = if *pos > 0 {
+                usize::try_from(pos - 1).ok()
+            } else {
+                days.len().checked_sub(pos.unsigned_abs() as usize)
+            };
+            index.and_then(|i| days.get(i)) == Some(&date)
+        })
+    }
+
+    /// Whether `date` (and `hour`, for hourly rules) falls on an `INTERVAL` step.
+    fn in_interval(&self, date: NaiveDate, hour: u32) -> bool {
+        let start = match self.dtstart {
+            Some(start) if self.interval > 1 => start,
+            _ => return true,
+        };
+
+        let steps = match self.frequency {
+            Frequency::Hourly => {
+                (date - start.date()).num_days() * 24 + hour as i64 - start.hour() as i64
+            }
+            Frequency::Daily => (date - start.date()).num_days(),
+            Frequency::Weekly => (self.week_of(date) - self.week_of(start.date())).num_days() / 7,
+            Frequency::Monthly => {
+                (date.year() - start.year()) as i64 * 12 + date.month() as i64
+                    - start.month() as i64
+            }
+            Frequency::Yearly => (date.year() - start.year()) as i64,
+        };
+        steps.rem_euclid(self.interval as i64) == 0
+    }
+
+    /// First day of the week containing `date`
+    fn week_of(&self, date: NaiveDate) -> NaiveDate {
+        let offset = (date.weekday().num_days_from_monday() + 7
+            - self.week_start.num_days_from_monday())
+            % 7;
+        date - Duration::days(offset as i64)
+    }
+
+    /// First and last day of the `BYSETPOS` period containing `date`
+    fn period(&self, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self.frequency {
+            Frequency::Weekly => {
+                let first = self.week_of(date);
+                (first, first + Duration::days(6))
+            }
+            Frequency::Monthly => (
+                date.with_day(1).unwrap_or(date),
+                date.with_day(days_in_month(date)).unwrap_or(date),
+            ),
+            _ => (
+                date.with_ordinal(1).unwrap_or(date),
+                date.with_ordinal(days_in_year(date)).unwrap_or(date),
+            ),
+        }
+    }
+
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        if !self.months.is_empty() && !self.months.contains(&date.month()) {
+            return false;
+        }
+
+        // Without day rules, the day comes from DTSTART
+        if self.weekdays.is_empty() && self.month_days.is_empty() {
+            let start = match self.dtstart {
+                Some(start) => start.date(),
+                None => return true,
+            };
+            return match self.frequency {
+                Frequency::Hourly | Frequency::Daily => true,
+                Frequency::Weekly => date.weekday() == start.weekday(),
+                Frequency::Monthly => date.day() == start.day(),
+                Frequency::Yearly if self.months.is_empty() => {
+                    date.month() == start.month() && date.day() == start.day()
+                }
+                Frequency::Yearly => date.day() == start.day(),
+            };
+        }
+
+        let month_day = self.month_days.is_empty()
+            || self.month_days.iter().any(|d| {
+                if *d > 0 {
+                    date.day() as i32 == *d
+                } else {
+                    (days_in_month(date) - date.day() + 1) as i32 == -d
+                }
+            });
+        let weekday = self.weekdays.is_empty()
+            || self.weekdays.iter().any(|(n, day)| {
+                *day == date.weekday() && n.is_none_or(|n| self.nth_weekday(date).contains(&n))
+            });
+
+        month_day && weekday
+    }
+
+    /// Ordinals of `date`'s weekday within its month (monthly rules, or yearly
+    /// with `BYMONTH`) or year, counted from the start and from the end.
+    fn nth_weekday(&self, date: NaiveDate) -> [i32; 2] {
+        let (position, length) = if self.frequency == Frequency::Monthly || !self.months.is_empty()
+        {
+            (date.day(), days_in_month(date))
+        } else {
+            (date.ordinal(), days_in_year(date))
+        };
+        [
+            ((position - 1) / 7 + 1) as i32,
+            -(((length - position) / 7 + 1) as i32),
+        ]
+    }
+}
+
+/// `TZID` of an imported rule's `DTSTART` line
+fn ical_tzid(rule: &str) -> Option<&str> {
+    rule.lines()
+        .map(str::trim)
+        .find(|line| line.to_ascii_uppercase().starts_with("DTSTART;"))
+        .and_then(|line| {
+            let params = line.split_once(':')?.0;
+            params
+                .split(';')
+                .find_map(|param| param.strip_prefix("TZID="))
+        })
+}
+
+/// iCalendar `DATE`/`DATE-TIME` (`Z` is UTC), or an RFC 3339 or local ISO
+/// timestamp, as local time in `tz`, and whether it was a date alone.
+fn parse_rrule_datetime(value: &str, tz: Tz) -> Option<(NaiveDateTime, bool)> {
+    let value = value.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Some((at.with_timezone(&tz).naive_local(), false));
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        let at = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some((
+            Utc.from_utc_datetime(&at).with_timezone(&tz).naive_local(),
+            false,
+        ));
+    }
+
+    if let Some(local) = ["%Y%m%dT%H%M%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+    {
+        return Some((local, false));
+    }
+
+    ["%Y%m%d", "%Y-%m-%d"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|local| (local, true))
+}
+
+fn ical_weekday(value: &str) -> Option<Weekday> {
+    match value {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// `BYDAY` entry such as `MO`, `2TU` or `-1FR`
+fn parse_ical_byday(value: &str) -> Option<(Option<i32>, Weekday)> {
+    let split = value.len().checked_sub(2)?;
+    let day = ical_weekday(value.get(split..)?)?;
+    let ordinal = match &value[..split] {
+        "" => None,
+        n => Some(n.parse::<i32>().ok().filter(|n| *n != 0 && n.abs() <= 53)?),
+    };
+    Some((ordinal, day))
+}
+
+fn parse_list<T>(value: &str, parse: impl Fn(&str) -> Option<T>) -> Option<Vec<T>> {
+    value.split(',').map(|v| parse(v.trim())).collect()
+}
+
+/// Timezone from the user's notification preferences (default if unset or unknown).
+pub async fn user_timezone(pool: &PgPool, user_id: Uuid) -> Result<Tz> {
+    let timezone: Option<String> =
+        sqlx::query_scalar("SELECT timezone FROM user_notification_preferences WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(timezone
+        .and_then(|tz| tz.parse().ok())
+        .unwrap_or(DEFAULT_TIMEZONE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const NY: Tz = Tz::America__New_York;
+
+    fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    fn next(config: Value, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        Schedule::from_config(&config, NY)
+            .unwrap()
+            .next_after(after)
+    }
+
+    #[test]
+    fn test_daily_in_user_timezone() {
+        // 09:00 New York is 13:00 UTC in summer
+        let config = json!({ "frequency": "daily", "time": "09:00" });
+        assert_eq!(
+            next(config.clone(), utc(2026, 7, 1, 12, 0)),
+            Some(utc(2026, 7, 1, 13, 0))
+        );
+        assert_eq!(
+            next(config, utc(2026, 7, 1, 13, 0)),
+            Some(utc(2026, 7, 2, 13, 0))
+        );
+
+        // Plain {"time"} is daily; an explicit timezone wins over the user's
+        assert_eq!(
+            next(
+                json!({ "time": "09:00", "timezone": "Europe/London" }),
+                utc(2026, 7, 1, 12, 0)
+            ),
+            Some(utc(2026, 7, 2, 8, 0))
+        );
+    }
+
+    #[test]
+    fn test_daily_across_dst() {
+        // Still 09:00 local after clocks go back on Nov 1 (EDT -> EST)
+        let config = json!({ "time": "09:00" });
+        assert_eq!(
+            next(config, utc(2026, 10, 31, 13, 0)),
+            Some(utc(2026, 11, 1, 14, 0))
+        );
+
+        // 02:30 doesn't exist on Mar 8; it fires at 03:30 EDT
+        let config = json!({ "time": "02:30" });
+        assert_eq!(
+            next(config, utc(2026, 3, 7, 12, 0)),
+            Some(utc(2026, 3, 8, 7, 30))
+        );
+    }
+
+    #[test]
+    fn test_weekly() {
+        // Friday Oct 16 2026 -> next Monday
+        let config = json!({ "frequency": "weekly", "days": ["mon", "Thursday"], "time": "08:15" });
+        assert_eq!(
+            next(config, utc(2026, 10, 16, 12, 0)),
+            Some(utc(2026, 10, 19, 12, 15))
+        );
+
+        assert!(Schedule::from_config(&json!({ "frequency": "weekly", "days": [] }), NY).is_err());
+        assert!(
+            Schedule::from_config(&json!({ "frequency": "weekly", "days": ["someday"] }), NY)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_monthly_clamps_to_month_end() {
+        let config =
+            json!({ "frequency": "monthly", "dayOfMonth": 31, "time": "09:00", "timezone": "UTC" });
+        assert_eq!(
+            next(config.clone(), utc(2026, 1, 31, 10, 0)),
+            Some(utc(2026, 2, 28, 9, 0))
+        );
+        assert_eq!(
+            next(config, utc(2026, 2, 28, 9, 0)),
+            Some(utc(2026, 3, 31, 9, 0))
+        );
+    }
+
+    #[test]
+    fn test_cron() {
+        // Weekdays at 09:00 New York; Friday evening -> Monday
+        let config = json!({ "cron": "0 9 * * 1-5" });
+        assert_eq!(
+            next(config, utc(2026, 10, 16, 22, 0)),
+            Some(utc(2026, 10, 19, 13, 0))
+        );
+
+        let every_15 = json!({ "cron": "*/15 * * * *", "timezone": "UTC" });
+        assert_eq!(
+            next(every_15, utc(2026, 10, 16, 10, 7)),
+            Some(utc(2026, 10, 16, 10, 15))
+        );
+
+        // Day-of-month OR day-of-week when both are restricted
+        let either = json!({ "cron": "0 12 1 * sun", "timezone": "UTC" });
+        assert_eq!(
+            next(either, utc(2026, 10, 16, 0, 0)),
+            Some(utc(2026, 10, 18, 12, 0))
+        );
+
+        let leap_day = json!({ "cron": "0 0 29 2 *", "timezone": "UTC" });
+        assert_eq!(
+            next(leap_day, utc(2026, 10, 16, 0, 0)),
+            Some(utc(2028, 2, 29, 0, 0))
+        );
+
+        let never = json!({ "cron": "0 0 30 2 *", "timezone": "UTC" });
+        assert_eq!(next(never, utc(2026, 10, 16, 0, 0)), None);
+    }
+
+    #[test]
+    fn test_cron_parse() {
+        let cron = CronSchedule::parse("0,30 9-17/4 * * 7").unwrap();
+        assert_eq!(cron.minutes, vec![0, 30]);
+        assert_eq!(cron.hours, vec![9, 13, 17]);
+        assert_eq!(cron.days_of_week, vec![0]);
+
+        assert!(CronSchedule::parse("0 9 * *").is_err());
+        assert!(CronSchedule::parse("60 9 * * *").is_err());
+        assert!(CronSchedule::parse("0 9 * * 1-9").is_err());
+        assert!(CronSchedule::parse("0 17-9 * * *").is_err());
+    }
+
+    #[test]
+    fn test_interval() {
+        let config = json!({ "interval": "90 minutes" });
+        assert_eq!(
+            next(config, utc(2026, 10, 16, 9, 0)),
+            Some(utc(2026, 10, 16, 10, 30))
+        );
+        assert_eq!(parse_interval("1 day"), Some(Duration::days(1)));
+        assert_eq!(parse_interval("2 weeks"), Some(Duration::weeks(2)));
+        assert_eq!(parse_interval("0 days"), None);
+        assert_eq!(parse_interval("1 fortnight"), None);
+    }
+
+    #[test]
+    fn test_rrule_by_day() {
+        // Friday Oct 16 2026 -> Monday 09:00 New York
+        let config = json!({ "rrule": "FREQ=WEEKLY;BYDAY=MO,WE", "time": "09:00" });
+        assert_eq!(
+            next(config, utc(2026, 10, 16, 12, 0)),
+            Some(utc(2026, 10, 19, 13, 0))
+        );
+
+        let last_friday =
+            json!({ "rrule": "FREQ=MONTHLY;BYDAY=-1FR", "time": "10:00", "timezone": "UTC" });
+        assert_eq!(
+            next(last_friday, utc(2026, 10, 16, 12, 0)),
+            Some(utc(2026, 10, 30, 10, 0))
+        );
+
+        let second_tuesday = json!({ "rrule": "RRULE:FREQ=MONTHLY;BYDAY=2TU", "timezone": "UTC" });
+        assert_eq!(
+            next(second_tuesday, utc(2026, 10, 16, 12, 0)),
+            Some(utc(2026, 11, 10, 9, 0))
+        );
+
+        // Last weekday of the month
+        let last_weekday = json!({
+            "rrule": "FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1",
+            "timezone": "UTC"
+        });
+        assert_eq!(
+            next(last_weekday, utc(2026, 10, 31, 0, 0)),
+            Some(utc(2026, 11, 30, 9, 0))
+        );
+    }
+
+    #[test]
+    fn test_rrule_imported_with_dtstart() {
+        // Every other Monday from Oct 5, 08:00 London (BST, then GMT from Oct 25)
+        let config = json!({
+            "rrule": "DTSTART;TZID=Europe/London:20261005T080000\nRRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=MO"
+        });
+        assert_eq!(
+            next(config.clone(), utc(2026, 10, 16, 0, 0)),
+            Some(utc(2026, 10, 19, 7, 0))
+        );
+        assert_eq!(
+            next(config, utc(2026, 10, 19, 7, 0)),
+            Some(utc(2026, 11, 2, 8, 0))
+        );
+
+        // Day and month come from DTSTART
+        let leap_day =
+            json!({ "rrule": "FREQ=YEARLY", "dtstart": "2020-02-29T09:00", "timezone": "UTC" });
+        assert_eq!(
+            next(leap_day, utc(2026, 10, 16, 0, 0)),
+            Some(utc(2028, 2, 29, 9, 0))
+        );
+
+        let hourly = json!({
+            "rrule": "FREQ=HOURLY;INTERVAL=3;BYMINUTE=30",
+            "dtstart": "2026-10-16T09:00",
+            "timezone": "UTC"
+        });
+        assert_eq!(
+            next(hourly, utc(2026, 10, 16, 10, 0)),
+            Some(utc(2026, 10, 16, 12, 30))
+        );
+    }
+
+    #[test]
+    fn test_rrule_count_and_until() {
+        let count = json!({ "rrule": "FREQ=DAILY;COUNT=3", "dtstart": "2026-10-12T09:00", "timezone": "UTC" });
+        assert_eq!(
+            next(count.clone(), utc(2026, 10, 1, 0, 0)),
+            Some(utc(2026, 10, 12, 9, 0))
+        );
+        assert_eq!(
+            next(count.clone(), utc(2026, 10, 13, 9, 0)),
+            Some(utc(2026, 10, 14, 9, 0))
+        );
+        assert_eq!(next(count, utc(2026, 10, 14, 9, 0)), None);
+
+        // A date-only UNTIL includes that day
+        let until = json!({ "rrule": "FREQ=DAILY;UNTIL=20261017", "timezone": "UTC" });
+        assert_eq!(
+            next(until.clone(), utc(2026, 10, 16, 10, 0)),
+            Some(utc(2026, 10, 17, 9, 0))
+        );
+        assert_eq!(next(until, utc(2026, 10, 17, 9, 0)), None);
+    }
+
+    #[test]
+    fn test_rrule_invalid() {
+        for rule in [
+            "FREQ=SECONDLY",
+            "BYDAY=MO",
+            "FREQ=DAILY;BYDAY=1MO",
+            "FREQ=DAILY;BYWEEKNO=1",
+            "FREQ=MONTHLY;BYMONTHDAY=32",
+            "FREQ=DAILY;BYSETPOS=1",
+            // Needs a DTSTART to anchor the interval or supply the day
+            "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO",
+            "FREQ=MONTHLY",
+        ] {
+            assert!(
+                Schedule::from_config(&json!({ "rrule": rule }), NY).is_err(),
+                "{}",
+                rule
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        assert!(Schedule::from_config(&json!({ "time": "9am" }), NY).is_err());
+        assert!(Schedule::from_config(&json!({ "timezone": "Mars/Base" }), NY).is_err());
+        assert!(Schedule::from_config(&json!({ "frequency": "yearly" }), NY).is_err());
+        assert!(
+            Schedule::from_config(&json!({ "frequency": "monthly", "dayOfMonth": 0 }), NY).is_err()
+        );
+    }
+}
@@ -0,0 +1,328 @@
+//! Relationship health from entity interaction history.
+//!
+//! Facts about or mentioning an entity and calendar events it attends are
+//! logged to `entity_interactions` by database triggers (which also keep
+//! `entities.interaction_count` current). From that history we work out how
+//! long it has been since the user last recorded anything about someone, how
+//! often they usually do, and whether the relationship has gone quiet; the
+//! morning briefing mentions the quietest ones.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::access::space_clause;
+use crate::Result;
+
+/// Days without an interaction before a relationship counts as stale
+pub const DEFAULT_STALE_DAYS: i64 = 60;
+
+/// Shortest threshold, however often the user usually interacts with someone
+pub const MIN_STALE_DAYS: i64 = 14;
+
+/// Interactions needed before an entity is worth nudging about in briefings
+pub const MIN_BRIEFING_INTERACTIONS: i32 = 3;
+
+/// How far back the usual gap between interactions is measured
+const HISTORY_DAYS: i64 = 365;
+
+/// Window for `recent_count`
+const RECENT_DAYS: i64 = 90;
+
+/// Overall state of a relationship
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    /// Interacted with recently
+    Active,
+    /// Past halfway to stale, or stale with something already scheduled
+    Cooling,
+    /// Nothing recorded for longer than the threshold
+    Stale,
+    /// No past interactions
+    NoHistory,
+}
+
+/// Relationship health of one entity
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RelationshipHealth {
+    pub entity_id: Uuid,
+    pub entity_name: String,
+    pub entity_type: String,
+    pub interaction_count: i32,
+    /// Past interactions in the last 90 days
+    pub recent_count: i64,
+    pub last_interaction_at: Option<DateTime<Utc>>,
+    pub days_since_last: Option<i64>,
+    /// Next scheduled calendar event with the entity
+    pub next_interaction_at: Option<DateTime<Utc>>,
+    /// Median days between interactions over the last year
+    pub typical_gap_days: Option<i64>,
+    /// Days without an interaction before this relationship is stale
+    pub stale_after_days: i64,
+    pub status: HealthStatus,
+    pub insight: Option<String>,
+}
+
+/// An entity gone quiet, for the briefing
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct StaleRelationship {
+    pub entity_id: Uuid,
+    pub entity_name: String,
+    pub last_interaction_at: DateTime<Utc>,
+}
+
+impl StaleRelationship {
+    pub fn days_since(&self, now: DateTime<Utc>) -> i64 {
+        (now - self.last_interaction_at).num_days()
+    }
+}
+
+/// Median gap in whole days between interactions on distinct days.
+pub fn typical_gap_days(occurrences: &[DateTime<Utc>]) -> Option<i64> {
+    let mut days: Vec<chrono::NaiveDate> = occurrences.iter().map(|o| o.date_naive()).collect();
+    days.sort();
+    days.dedup();
+
+    let mut gaps: Vec<i64> = days
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).num_days())
+        .collect();
+    if gaps.is_empty() {
+        return None;
+    }
+
+    gaps.sort();
+    Some(gaps[gaps.len() / 2])
+}
+
+/// Days without an interaction before a relationship is stale: twice the
+/// usual gap, between `MIN_STALE_DAYS` and `stale_days`.
+pub fn stale_threshold(typical_gap_days: Option<i64>, stale_days: i64) -> i64 {
+    let stale_days = stale_days.max(MIN_STALE_DAYS);
+    match typical_gap_days {
+        Some(gap) => (gap * 2).clamp(MIN_STALE_DAYS, stale_days),
+        None => stale_days,
+    }
+}
+
+/// Status given days since the last interaction and the stale threshold.
+pub fn assess(days_since_last: Option<i64>, threshold: i64, has_upcoming: bool) -> HealthStatus {
+    match days_since_last {
+        None => HealthStatus::NoHistory,
+        Some(days) if days > threshold && !has_upcoming => HealthStatus::Stale,
+        Some(days) if days > threshold / 2 => HealthStatus::Cooling,
+        Some(_) => HealthStatus::Active,
+    }
+}
+
+/// "You haven't recorded anything about Grandma in 60 days"
+pub fn insight_message(name: &str, days_since_last: i64) -> String {
+    format!(
+        "You haven't recorded anything about {} in {} days",
+        name, days_since_last
+    )
+}
+
+/// Note appended to the morning briefing prompt, if any relationships are stale.
+pub fn briefing_note(stale: &[StaleRelationship], now: DateTime<Utc>) -> Option<String> {
+    if stale.is_empty() {
+        return None;
+    }
+
+    let lines: Vec<String> = stale
+        .iter()
+        .map(|s| format!("- {}", insight_message(&s.entity_name, s.days_since(now))))
+        .collect();
+
+    Some(format!(
+        "Relationships that have gone quiet (suggest reconnecting, briefly):\n{}",
+        lines.join("\n")
+    ))
+}
+
+/// Relationship health for an entity the caller has access to.
+pub async fn entity_health(
+    pool: &sqlx::PgPool,
+    entity_id: Uuid,
+    stale_days: i64,
+) -> Result<RelationshipHealth> {
+    let (entity_name, entity_type, interaction_count) = sqlx::query_as::<_, (String, String, i32)>(
+        "SELECT name, entity_type::text, interaction_count FROM entities WHERE id = $1",
+    )
+    .bind(entity_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| crate::Error::NotFound("Entity not found".to_string()))?;
+
+    let now = Utc::now();
+
+    let history: Vec<DateTime<Utc>> = sqlx::query_scalar(
+        r#"
+        SELECT occurred_at FROM entity_interactions
+        WHERE entity_id = $1
+        AND occurred_at <= $2
+        AND occurred_at > $2 - make_interval(days => $3)
+        ORDER BY occurred_at
+        "#,
+    )
+    .bind(entity_id)
+    .bind(now)
+    .bind(HISTORY_DAYS as i32)
+    .fetch_all(pool)
+    .await?;
+
+    // History may not reach back to the last interaction
+    let (last_interaction_at, next_interaction_at) =
+        sqlx::query_as::<_, (Option<DateTime<Utc>>, Option<DateTime<Utc>>)>(
+            r#"
+            SELECT
+                MAX(occurred_at) FILTER (WHERE occurred_at <= $2),
+                MIN(occurred_at) FILTER (WHERE occurred_at > $2 AND source = 'calendar_event')
+            FROM entity_interactions
+            WHERE entity_id = $1
+            "#,
+        )
+        .bind(entity_id)
+        .bind(now)
+        .fetch_one(pool)
+        .await?;
+
+    let recent_since = now - chrono::Duration::days(RECENT_DAYS);
+    let recent_count = history.iter().filter(|o| **o > recent_since).count() as i64;
+
+    let days_since_last = last_interaction_at.map(|last| (now - last).num_days());
+    let typical_gap_days = typical_gap_days(&history);
+    let stale_after_days = stale_threshold(typical_gap_days, stale_days);
+    let status = assess(
+        days_since_last,
+        stale_after_days,
+        next_interaction_at.is_some(),
+    );
+    let insight = match (status, days_since_last) {
+        (HealthStatus::Stale, Some(days)) => Some(insight_message(&entity_name, days)),
+        _ => None,
+    };
+
+    Ok(RelationshipHealth {
+        entity_id,
+        entity_name,
+        entity_type,
+        interaction_count,
+        recent_count,
+        last_interaction_at,
+        days_since_last,
+        next_interaction_at,
+        typical_gap_days,
+        stale_after_days,
+        status,
+        insight,
+    })
+}
+
+/// People the user has an established history with but hasn't interacted
+/// with for `stale_days`, and has nothing scheduled with. Quietest first.
+pub async fn stale_relationships(
+    pool: &sqlx::PgPool,
+    user_id: Uuid,
+    family_ids: &[Uuid],
+    stale_days: i64,
+    limit: i64,
+) -> Result<Vec<StaleRelationship>> {
+    let stale = sqlx::query_as(&format!(
+        r#"
+        SELECT e.id AS entity_id, e.name AS entity_name, last.occurred_at AS last_interaction_at
+        FROM entities e
+        CROSS JOIN LATERAL (
+            SELECT MAX(ei.occurred_at) AS occurred_at
+            FROM entity_interactions ei
+            WHERE ei.entity_id = e.id AND ei.occurred_at <= NOW()
+        ) last
+        WHERE ((e.owner_type = 'user' AND e.owner_id = $1)
+               OR (e.owner_type = 'family' AND e.owner_id = ANY($2) AND {}))
+        AND e.entity_type = 'person'
+        AND e.deleted_at IS NULL
+        AND e.linked_user_id IS DISTINCT FROM $1
+        AND e.interaction_count >= $3
+        AND last.occurred_at < NOW() - make_interval(days => $4)
+        AND NOT EXISTS (
+            SELECT 1 FROM entity_interactions up
+            WHERE up.entity_id = e.id
+            AND up.source = 'calendar_event'
+            AND up.occurred_at > NOW()
+        )
+        ORDER BY last.occurred_at
+        LIMIT $5
+        "#,
+        space_clause("e", 1)
+    ))
+    .bind(user_id)
+    .bind(family_ids)
+    .bind(MIN_BRIEFING_INTERACTIONS)
+    .bind(stale_days as i32)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(stale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, month: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, month, day, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn typical_gap_is_median_of_distinct_days() {
+        assert_eq!(typical_gap_days(&[]), None);
+        assert_eq!(typical_gap_days(&[at(1, 1)]), None);
+        // Same-day interactions count once
+        assert_eq!(typical_gap_days(&[at(1, 1), at(1, 1)]), None);
+        assert_eq!(
+            typical_gap_days(&[at(20, 1), at(1, 1), at(8, 1), at(15, 1), at(1, 3)]),
+            Some(7)
+        );
+    }
+
+    #[test]
+    fn threshold_scales_with_usual_gap() {
+        assert_eq!(stale_threshold(None, 60), 60);
+        assert_eq!(stale_threshold(Some(3), 60), MIN_STALE_DAYS);
+        assert_eq!(stale_threshold(Some(10), 60), 20);
+        assert_eq!(stale_threshold(Some(90), 60), 60);
+        assert_eq!(stale_threshold(None, 1), MIN_STALE_DAYS);
+    }
+
+    #[test]
+    fn assesses_status() {
+        assert_eq!(assess(None, 60, false), HealthStatus::NoHistory);
+        assert_eq!(assess(Some(5), 60, false), HealthStatus::Active);
+        assert_eq!(assess(Some(31), 60, false), HealthStatus::Cooling);
+        assert_eq!(assess(Some(61), 60, false), HealthStatus::Stale);
+        // Something scheduled keeps it from being stale
+        assert_eq!(assess(Some(61), 60, true), HealthStatus::Cooling);
+    }
+
+    #[test]
+    fn builds_briefing_note() {
+        let now = at(1, 3);
+        assert_eq!(briefing_note(&[], now), None);
+
+        let note = briefing_note(
+            &[StaleRelationship {
+                entity_id: Uuid::nil(),
+                entity_name: "Grandma".to_string(),
+                last_interaction_at: now - chrono::Duration::days(60),
+            }],
+            now,
+        )
+        .unwrap();
+        assert!(note.ends_with("- You haven't recorded anything about Grandma in 60 days"));
+    }
+}
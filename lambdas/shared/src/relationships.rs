@@ -0,0 +1,527 @@
+//! Relationship requests and the consent they carry.
+//!
+//! A relationship lets its source see the target's data, so it starts as a
+//! request ([`request`]) that the target accepts at a tier they choose
+//! ([`accept`]) or declines ([`decline`]); the requester can withdraw it while
+//! it is pending ([`withdraw`]). Nothing is granted until the target accepts.
+//!
+//! The source can lower a relationship's tier at any time, but raising it
+//! widens what they see, so it takes a new request the target accepts (see
+//! [`change_access_tier`]). Every change refreshes `user_access_cache`, which
+//! [`crate::access::visibility_clause`] reads.
+
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::access::{TIER_INTIMATE, TIER_RELATIONSHIPS};
+use crate::{Error, Result};
+
+/// Relationship types
+pub const RELATIONSHIP_TYPES: [&str; 8] = [
+    "spouse",
+    "parent",
+    "child",
+    "sibling",
+    "grandparent",
+    "grandchild",
+    "friend",
+    "other",
+];
+
+/// Default access tier for a relationship type
+pub fn default_access_tier(relationship_type: &str) -> i16 {
+    match relationship_type {
+        "spouse" | "parent" => 1,          // Full access
+        "child" => 2,                      // High access (parent sees child's data)
+        "sibling" => 3,                    // Medium access
+        "grandparent" | "grandchild" => 3, // Medium access
+        _ => 4,                            // Friends and others: low access
+    }
+}
+
+/// The reverse relationship type for bidirectional relationships
+pub fn reverse_relationship_type(relationship_type: &str) -> String {
+    match relationship_type {
+        "parent" => "child".to_string(),
+        "child" => "parent".to_string(),
+        "grandparent" => "grandchild".to_string(),
+        "grandchild" => "grandparent".to_string(),
+        // Symmetric relationships
+        "spouse" | "sibling" | "friend" | "other" => relationship_type.to_string(),
+        _ => "other".to_string(),
+    }
+}
+
+fn check_tier(access_tier: i16) -> Result<()> {
+    if !(TIER_INTIMATE..=TIER_RELATIONSHIPS).contains(&access_tier) {
+        return Err(Error::Validation(
+            "Access tier must be between 1 and 4".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// A request waiting for its target to answer
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
+pub struct PendingRequest {
+    pub id: Uuid,
+    pub requester_user_id: Uuid,
+    pub target_user_id: Uuid,
+    pub relationship_type: String,
+    pub requested_access_tier: i16,
+    pub bidirectional: bool,
+}
+
+impl PendingRequest {
+    /// The relationship giving the target access to the requester, with its
+    /// default tier, when the requester asked for one both ways
+    pub fn reverse(&self) -> Option<(String, i16)> {
+        self.bidirectional.then(|| {
+            let reverse_type = reverse_relationship_type(&self.relationship_type);
+            let reverse_tier = default_access_tier(&reverse_type);
+            (reverse_type, reverse_tier)
+        })
+    }
+}
+
+/// Relationships saved by [`accept`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Accepted {
+    pub relationship_id: Uuid,
+    /// The target's relationship to the requester, when bidirectional
+    pub reverse_id: Option<Uuid>,
+}
+
+/// Ask `target_user_id` for a relationship. Asking again replaces an open
+/// request. Returns the request's ID.
+pub async fn request(
+    pool: &PgPool,
+    requester_user_id: Uuid,
+    target_user_id: Uuid,
+    relationship_type: &str,
+    access_tier: i16,
+    bidirectional: bool,
+) -> Result<Uuid> {
+    if !RELATIONSHIP_TYPES.contains(&relationship_type) {
+        return Err(Error::Validation(format!(
+            "Invalid relationship type. Must be one of: {:?}",
+            RELATIONSHIP_TYPES
+        )));
+    }
+    if requester_user_id == target_user_id {
+        return Err(Error::Validation(
+            "Cannot create a relationship with yourself".to_string(),
+        ));
+    }
+    check_tier(access_tier)?;
+
+    let request_id = sqlx::query_scalar(
+        r#"
+        INSERT INTO relationship_requests
+            (requester_user_id, target_user_id, relationship_type, requested_access_tier, bidirectional)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (requester_user_id, target_user_id) WHERE status = 'pending' DO UPDATE SET
+            relationship_type = EXCLUDED.relationship_type,
+            requested_access_tier = EXCLUDED.requested_access_tier,
+            bidirectional = EXCLUDED.bidirectional,
+            created_at = NOW()
+        RETURNING id
+        "#,
+    )
+    .bind(requester_user_id)
+    .bind(target_user_id)
+    .bind(relationship_type)
+    .bind(access_tier)
+    .bind(bidirectional)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(request_id)
+}
+
+/// The pending request `request_id`, if it was sent to `target_user_id`
+pub async fn pending_request(
+    pool: &PgPool,
+    request_id: Uuid,
+    target_user_id: Uuid,
+) -> Result<Option<PendingRequest>> {
+    let pending = sqlx::query_as(
+        r#"
+        SELECT id, requester_user_id, target_user_id, relationship_type,
+               requested_access_tier, bidirectional
+        FROM relationship_requests
+        WHERE id = $1 AND target_user_id = $2 AND status = 'pending'
+        "#,
+    )
+    .bind(request_id)
+    .bind(target_user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(pending)
+}
+
+/// Accept a pending request at `access_tier`: create the relationship (and
+/// the reverse one, if requested) and grant its access. Returns `None` when
+/// the request was answered or withdrawn in the meantime.
+pub async fn accept(
+    pool: &PgPool,
+    pending: &PendingRequest,
+    access_tier: i16,
+) -> Result<Option<Accepted>> {
+    check_tier(access_tier)?;
+    let requester_id = pending.requester_user_id;
+    let target_id = pending.target_user_id;
+    let reverse = pending.reverse();
+
+    // Claim the request, save both directions and refresh the access cache
+    // together, so a request is only accepted once and access never reflects
+    // half a relationship
+    crate::db::with_tx(pool, async |tx| {
+        let claimed: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            UPDATE relationship_requests
+            SET status = 'accepted', granted_access_tier = $2, responded_at = NOW()
+            WHERE id = $1 AND status = 'pending'
+            RETURNING id
+            "#,
+        )
+        .bind(pending.id)
+        .bind(access_tier)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if claimed.is_none() {
+            return Ok(None);
+        }
+
+        let relationship_id = upsert_relationship(
+            tx,
+            requester_id,
+            target_id,
+            &pending.relationship_type,
+            access_tier,
+        )
+        .await?;
+
+        let reverse_id = match &reverse {
+            Some((reverse_type, reverse_tier)) => Some(
+                upsert_relationship(tx, target_id, requester_id, reverse_type, *reverse_tier)
+                    .await?,
+            ),
+            None => None,
+        };
+
+        sqlx::query("UPDATE relationship_requests SET relationship_id = $2 WHERE id = $1")
+            .bind(pending.id)
+            .bind(relationship_id)
+            .execute(&mut *tx)
+            .await?;
+
+        refresh_access_cache(tx, requester_id).await?;
+        if reverse_id.is_some() {
+            refresh_access_cache(tx, target_id).await?;
+        }
+
+        Ok(Some(Accepted {
+            relationship_id,
+            reverse_id,
+        }))
+    })
+    .await
+}
+
+/// Decline a pending request sent to `target_user_id`. Returns whether there
+/// was one.
+pub async fn decline(pool: &PgPool, request_id: Uuid, target_user_id: Uuid) -> Result<bool> {
+    let declined = sqlx::query(
+        r#"
+        UPDATE relationship_requests
+        SET status = 'declined', responded_at = NOW()
+        WHERE id = $1 AND target_user_id = $2 AND status = 'pending'
+        "#,
+    )
+    .bind(request_id)
+    .bind(target_user_id)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(declined > 0)
+}
+
+/// Withdraw a pending request `requester_user_id` sent. Returns whether there
+/// was one.
+pub async fn withdraw(pool: &PgPool, request_id: Uuid, requester_user_id: Uuid) -> Result<bool> {
+    let withdrawn = sqlx::query(
+        "DELETE FROM relationship_requests WHERE id = $1 AND requester_user_id = $2 AND status = 'pending'",
+    )
+    .bind(request_id)
+    .bind(requester_user_id)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(withdrawn > 0)
+}
+
+/// Change the tier of a relationship `source_user_id` holds. Lowering access
+/// (a higher tier) applies at once; raising it is `Error::Unauthorized`,
+/// since the target has to consent to a new request. Returns `false` when the
+/// user holds no such relationship.
+pub async fn change_access_tier(
+    pool: &PgPool,
+    relationship_id: Uuid,
+    source_user_id: Uuid,
+    access_tier: i16,
+) -> Result<bool> {
+    check_tier(access_tier)?;
+
+    let current_tier: Option<i16> = sqlx::query_scalar(
+        "SELECT access_tier FROM relationships WHERE id = $1 AND source_user_id = $2",
+    )
+    .bind(relationship_id)
+    .bind(source_user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(current_tier) = current_tier else {
+        return Ok(false);
+    };
+    if access_tier < current_tier {
+        return Err(Error::Unauthorized(
+            "Raising an access tier needs a new relationship request".to_string(),
+        ));
+    }
+
+    crate::db::with_tx(pool, async |tx| {
+        sqlx::query("UPDATE relationships SET access_tier = $1 WHERE id = $2")
+            .bind(access_tier)
+            .bind(relationship_id)
+            .execute(&mut *tx)
+            .await?;
+
+        refresh_access_cache(tx, source_user_id).await
+    })
+    .await?;
+
+    Ok(true)
+}
+
+/// Create a relationship, or update the type and tier of the existing one
+/// between the same users. Returns its ID.
+async fn upsert_relationship(
+    conn: &mut PgConnection,
+    source_user_id: Uuid,
+    target_user_id: Uuid,
+    relationship_type: &str,
+    access_tier: i16,
+) -> Result<Uuid> {
+    let relationship_id = sqlx::query_scalar(
+        r#"
+        INSERT INTO relationships (id, source_user_id, target_user_id, relationship_type, access_tier)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (source_user_id, target_user_id) DO UPDATE SET
+            relationship_type = EXCLUDED.relationship_type,
+            access_tier = EXCLUDED.access_tier
+        RETURNING id
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(source_user_id)
+    .bind(target_user_id)
+    .bind(relationship_type)
+    .bind(access_tier)
+    .fetch_one(conn)
+    .await?;
+
+    Ok(relationship_id)
+}
+
+/// Refresh the user's `user_access_cache` rows with the database function
+pub async fn refresh_access_cache(conn: &mut PgConnection, user_id: Uuid) -> Result<()> {
+    sqlx::query("SELECT refresh_user_access_cache($1)")
+        .bind(user_id)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_access_tier() {
+        assert_eq!(default_access_tier("spouse"), 1);
+        assert_eq!(default_access_tier("child"), 2);
+        assert_eq!(default_access_tier("sibling"), 3);
+        assert_eq!(default_access_tier("friend"), 4);
+        assert_eq!(default_access_tier("unknown"), 4);
+    }
+
+    #[test]
+    fn test_reverse_relationship_type() {
+        assert_eq!(reverse_relationship_type("parent"), "child");
+        assert_eq!(reverse_relationship_type("grandchild"), "grandparent");
+        assert_eq!(reverse_relationship_type("spouse"), "spouse");
+        assert_eq!(reverse_relationship_type("unknown"), "other");
+    }
+
+    async fn insert_user(pool: &PgPool, name: &str) -> Uuid {
+        sqlx::query_scalar(
+            "INSERT INTO users (cognito_sub, email, display_name)
+             VALUES ($1, $1 || '@example.com', $1) RETURNING id",
+        )
+        .bind(name)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    /// The tier `viewer_id` reads `target_id`'s data at, if any
+    async fn access_tier(pool: &PgPool, viewer_id: Uuid, target_id: Uuid) -> Option<i16> {
+        sqlx::query_scalar(
+            "SELECT access_tier FROM user_access_cache
+             WHERE viewer_user_id = $1 AND target_user_id = $2",
+        )
+        .bind(viewer_id)
+        .bind(target_id)
+        .fetch_optional(pool)
+        .await
+        .unwrap()
+    }
+
+    /// The pending request, as its target sees it
+    async fn find_pending(
+        pool: &PgPool,
+        request_id: Uuid,
+        target_id: Uuid,
+    ) -> Option<PendingRequest> {
+        pending_request(pool, request_id, target_id).await.unwrap()
+    }
+
+    async fn request_status(pool: &PgPool, request_id: Uuid) -> Option<String> {
+        sqlx::query_scalar("SELECT status FROM relationship_requests WHERE id = $1")
+            .bind(request_id)
+            .fetch_optional(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test(migrator = "migrations::MIGRATOR")]
+    #[ignore = "needs DATABASE_URL"]
+    async fn test_accept(pool: PgPool) {
+        let alex = insert_user(&pool, "alex").await;
+        let sam = insert_user(&pool, "sam").await;
+
+        let request_id = request(&pool, alex, sam, "parent", 1, true).await.unwrap();
+        assert_eq!(access_tier(&pool, alex, sam).await, None);
+
+        // Only the target can accept
+        assert!(find_pending(&pool, request_id, alex).await.is_none());
+        let pending = find_pending(&pool, request_id, sam).await.unwrap();
+        assert_eq!(pending.reverse(), Some(("child".to_string(), 2)));
+
+        // The target grants less than was asked for
+        let accepted = accept(&pool, &pending, 2).await.unwrap().unwrap();
+        assert!(accepted.reverse_id.is_some());
+        assert_eq!(access_tier(&pool, alex, sam).await, Some(2));
+        assert_eq!(access_tier(&pool, sam, alex).await, Some(2));
+        let status = request_status(&pool, request_id).await;
+        assert_eq!(status.as_deref(), Some("accepted"));
+
+        // A request is only accepted once
+        assert_eq!(accept(&pool, &pending, 1).await.unwrap(), None);
+        assert_eq!(access_tier(&pool, alex, sam).await, Some(2));
+    }
+
+    #[sqlx::test(migrator = "migrations::MIGRATOR")]
+    #[ignore = "needs DATABASE_URL"]
+    async fn test_decline(pool: PgPool) {
+        let alex = insert_user(&pool, "alex").await;
+        let sam = insert_user(&pool, "sam").await;
+
+        let request_id = request(&pool, alex, sam, "friend", 4, false).await.unwrap();
+        let pending = find_pending(&pool, request_id, sam).await.unwrap();
+
+        // Only the target can decline
+        assert!(!decline(&pool, request_id, alex).await.unwrap());
+        assert!(decline(&pool, request_id, sam).await.unwrap());
+        assert!(!decline(&pool, request_id, sam).await.unwrap());
+        let status = request_status(&pool, request_id).await;
+        assert_eq!(status.as_deref(), Some("declined"));
+
+        assert!(find_pending(&pool, request_id, sam).await.is_none());
+        assert_eq!(accept(&pool, &pending, 4).await.unwrap(), None);
+        assert_eq!(access_tier(&pool, alex, sam).await, None);
+    }
+
+    #[sqlx::test(migrator = "migrations::MIGRATOR")]
+    #[ignore = "needs DATABASE_URL"]
+    async fn test_withdraw(pool: PgPool) {
+        let alex = insert_user(&pool, "alex").await;
+        let sam = insert_user(&pool, "sam").await;
+
+        let request_id = request(&pool, alex, sam, "sibling", 3, false)
+            .await
+            .unwrap();
+        let pending = find_pending(&pool, request_id, sam).await.unwrap();
+
+        // Only the requester can withdraw
+        assert!(!withdraw(&pool, request_id, sam).await.unwrap());
+        assert!(withdraw(&pool, request_id, alex).await.unwrap());
+        assert_eq!(request_status(&pool, request_id).await, None);
+
+        assert_eq!(accept(&pool, &pending, 3).await.unwrap(), None);
+        assert_eq!(access_tier(&pool, alex, sam).await, None);
+    }
+
+    #[sqlx::test(migrator = "migrations::MIGRATOR")]
+    #[ignore = "needs DATABASE_URL"]
+    async fn test_raising_tier_needs_fresh_consent(pool: PgPool) {
+        let alex = insert_user(&pool, "alex").await;
+        let sam = insert_user(&pool, "sam").await;
+
+        let request_id = request(&pool, alex, sam, "friend", 3, false).await.unwrap();
+        let pending = find_pending(&pool, request_id, sam).await.unwrap();
+        let accepted = accept(&pool, &pending, 3).await.unwrap().unwrap();
+        let relationship_id = accepted.relationship_id;
+
+        // Lowering access applies at once; raising it doesn't
+        assert!(change_access_tier(&pool, relationship_id, alex, 4)
+            .await
+            .unwrap());
+        assert_eq!(access_tier(&pool, alex, sam).await, Some(4));
+        let raised = change_access_tier(&pool, relationship_id, alex, 2).await;
+        assert!(matches!(raised, Err(Error::Unauthorized(_))));
+        assert_eq!(access_tier(&pool, alex, sam).await, Some(4));
+
+        // Only the source changes the tier
+        assert!(!change_access_tier(&pool, relationship_id, sam, 4)
+            .await
+            .unwrap());
+
+        // Sam consents to the higher tier with a new request
+        let request_id = request(&pool, alex, sam, "friend", 2, false).await.unwrap();
+        let pending = find_pending(&pool, request_id, sam).await.unwrap();
+        let accepted = accept(&pool, &pending, 2).await.unwrap().unwrap();
+        assert_eq!(accepted.relationship_id, relationship_id);
+        assert_eq!(access_tier(&pool, alex, sam).await, Some(2));
+    }
+
+    #[sqlx::test(migrator = "migrations::MIGRATOR")]
+    #[ignore = "needs DATABASE_URL"]
+    async fn test_invalid_requests(pool: PgPool) {
+        let alex = insert_user(&pool, "alex").await;
+        let sam = insert_user(&pool, "sam").await;
+
+        for (target, relationship_type, tier) in
+            [(alex, "friend", 4), (sam, "rival", 4), (sam, "friend", 5)]
+        {
+            let requested = request(&pool, alex, target, relationship_type, tier, false).await;
+            assert!(matches!(requested, Err(Error::Validation(_))));
+        }
+    }
+}
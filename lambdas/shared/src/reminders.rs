@@ -0,0 +1,690 @@
+//! Reminder evaluation logic shared by the evaluator Lambda and the reminders API.
+//!
+//! Everything here is side-effect free so the same decisions can be replayed
+//! against an arbitrary "now" (see `POST /reminders/{id}/simulate`).
+
+use chrono::{DateTime, Datelike, Duration, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use sqlx::types::Json;
+
+use crate::notification_preferences::{TypeOverride, TypeOverrides, CHANNELS};
+use crate::{Error, Result};
+use utoipa::ToSchema;
+
+/// User notification preferences relevant to reminder delivery.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct NotificationPreferences {
+    pub push_enabled: bool,
+    pub email_enabled: bool,
+    pub discord_enabled: bool,
+    /// Alexa notifications; also needs a linked Alexa account (`alexa_links`)
+    #[sqlx(default)]
+    pub alexa_enabled: bool,
+    pub quiet_hours_enabled: bool,
+    pub quiet_hours_start: Option<NaiveTime>,
+    pub quiet_hours_end: Option<NaiveTime>,
+    pub timezone: String,
+    /// Per-type overrides (see `notification_preferences::TypeOverride`)
+    #[sqlx(default)]
+    pub type_overrides: Json<TypeOverrides>,
+}
+
+impl Default for NotificationPreferences {
+    /// Defaults used when a user has no preferences row.
+    fn default() -> Self {
+        Self {
+            push_enabled: true,
+            email_enabled: true,
+            discord_enabled: false,
+            alexa_enabled: false,
+            quiet_hours_enabled: false,
+            quiet_hours_start: None,
+            quiet_hours_end: None,
+            timezone: "America/New_York".to_string(),
+            type_overrides: Json(TypeOverrides::new()),
+        }
+    }
+}
+
+impl NotificationPreferences {
+    /// The user's timezone, or UTC if it isn't a known IANA name.
+    pub fn tz(&self) -> Tz {
+        self.timezone.parse().unwrap_or(Tz::UTC)
+    }
+
+    /// The user's override for `notification_type`, if any.
+    pub fn override_for(&self, notification_type: &str) -> Option<&TypeOverride> {
+        self.type_overrides.get(notification_type)
+    }
+
+    /// Quiet hours window, if enabled and fully configured.
+    fn quiet_window(&self) -> Option<(NaiveTime, NaiveTime)> {
+        match (self.quiet_hours_enabled, self.quiet_hours_start, self.quiet_hours_end) {
+            (true, Some(start), Some(end)) => Some((start, end)),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `now` falls inside the user's quiet hours, in their timezone.
+pub fn is_in_quiet_hours(prefs: &NotificationPreferences, now: DateTime<Utc>) -> bool {
+    let (start, end) = match prefs.quiet_window() {
+        Some(window) => window,
+        None => return false,
+    };
+
+    let now = now.with_timezone(&prefs.tz()).time();
+
+    if start <= end {
+        now >= start && now < end
+    } else {
+        // Wrapping range (e.g., 22:00 - 07:00)
+        now >= start || now < end
+    }
+}
+
+/// When the quiet hours containing `now` end, or `None` outside quiet hours.
+///
+/// Notifications that come due during quiet hours are held until then rather
+/// than dropped.
+pub fn quiet_hours_end(prefs: &NotificationPreferences, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if !is_in_quiet_hours(prefs, now) {
+        return None;
+    }
+    let (start, end) = prefs.quiet_window()?;
+
+    let tz = prefs.tz();
+    let local = now.with_timezone(&tz);
+
+    // In a wrapping window, the evening part ends tomorrow morning
+    let end_date = if start > end && local.time() >= start {
+        local.date_naive() + Duration::days(1)
+    } else {
+        local.date_naive()
+    };
+    let end_at = end_date.and_time(end);
+
+    // An end time skipped by a DST change ends at the first instant after the gap
+    tz.from_local_datetime(&end_at)
+        .earliest()
+        .or_else(|| tz.from_local_datetime(&(end_at + Duration::hours(1))).earliest())
+        .map(|at| at.with_timezone(&Utc))
+}
+
+/// Pick the delivery channel for a notification.
+pub fn preferred_channel(prefs: &NotificationPreferences) -> &'static str {
+    if prefs.discord_enabled {
+        "discord"
+    } else if prefs.push_enabled {
+        "push"
+    } else if prefs.email_enabled {
+        "email"
+    } else if prefs.alexa_enabled {
+        // Alexa only announces that there is a message, so it's the last choice
+        "alexa"
+    } else {
+        "push"
+    }
+}
+
+/// Channel for a notification of `notification_type`: the one its override
+/// names, else the preferred one. `None` if the user turned the type off.
+pub fn channel_for(prefs: &NotificationPreferences, notification_type: &str) -> Option<&'static str> {
+    match prefs.override_for(notification_type) {
+        Some(type_override) if !type_override.enabled => None,
+        Some(TypeOverride {
+            channel: Some(channel),
+            ..
+        }) => Some(
+            CHANNELS
+                .iter()
+                .copied()
+                .find(|c| c == channel)
+                .unwrap_or_else(|| preferred_channel(prefs)),
+        ),
+        _ => Some(preferred_channel(prefs)),
+    }
+}
+
+/// When a notification of `notification_type` due `now` should be held
+/// until: the end of quiet hours, unless its override lets it through.
+pub fn deferred_until(
+    prefs: &NotificationPreferences,
+    notification_type: &str,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    match prefs.override_for(notification_type) {
+        Some(type_override) if type_override.ignore_quiet_hours => None,
+        _ => quiet_hours_end(prefs, now),
+    }
+}
+
+/// Channels ranked by how hard they are to miss, for escalation
+const ESCALATION_CHANNELS: [&str; 4] = ["push", "email", "discord", "alexa"];
+
+/// Local time `tomorrow_morning` and `next_week` snooze until
+const SNOOZE_MORNING: (u32, u32) = (9, 0);
+
+/// Largest number in a duration preset (keeps the duration in range)
+const MAX_SNOOZE_AMOUNT: i64 = 1_000_000;
+
+/// Local time `this_evening` snoozes until
+const SNOOZE_EVENING: (u32, u32) = (18, 0);
+
+/// What a reminder does once it has been snoozed `max_snoozes` times
+/// (`reminders.snooze_escalation`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnoozeEscalation {
+    /// Later notifications go out one priority higher
+    Priority,
+    /// Later notifications go out on a different channel
+    Channel,
+    /// Further snoozes are refused
+    Block,
+}
+
+impl SnoozeEscalation {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "priority" => Some(SnoozeEscalation::Priority),
+            "channel" => Some(SnoozeEscalation::Channel),
+            "block" => Some(SnoozeEscalation::Block),
+            _ => None,
+        }
+    }
+}
+
+/// Result of applying a reminder's max-snooze policy to one more snooze.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnoozeDecision {
+    /// Under the limit, or the reminder has none
+    Allow,
+    /// Snooze, and escalate the notifications that follow
+    Escalate,
+    /// The limit is reached and the reminder blocks further snoozes
+    Refuse,
+}
+
+/// Decide whether a reminder already snoozed `snooze_count` times may be
+/// snoozed again. Reaching `max_snoozes` escalates; with
+/// [`SnoozeEscalation::Block`] snoozing past it is refused instead.
+pub fn snooze_decision(
+    snooze_count: i32,
+    max_snoozes: Option<i16>,
+    escalation: SnoozeEscalation,
+) -> SnoozeDecision {
+    let max = match max_snoozes {
+        Some(max) => i32::from(max),
+        None => return SnoozeDecision::Allow,
+    };
+    let count = snooze_count + 1;
+
+    match escalation {
+        SnoozeEscalation::Block if count > max => SnoozeDecision::Refuse,
+        SnoozeEscalation::Block => SnoozeDecision::Allow,
+        _ if count >= max => SnoozeDecision::Escalate,
+        _ => SnoozeDecision::Allow,
+    }
+}
+
+/// Channel and priority for an escalated reminder's notifications.
+pub fn escalated_delivery(
+    escalation: SnoozeEscalation,
+    prefs: &NotificationPreferences,
+    priority: i16,
+) -> (&'static str, i16) {
+    let channel = preferred_channel(prefs);
+    match escalation {
+        SnoozeEscalation::Priority => (channel, (priority + 1).min(5)),
+        SnoozeEscalation::Channel => {
+            let enabled = |c: &str| match c {
+                "push" => prefs.push_enabled,
+                "email" => prefs.email_enabled,
+                "alexa" => prefs.alexa_enabled,
+                _ => prefs.discord_enabled,
+            };
+            let other = ESCALATION_CHANNELS
+                .into_iter()
+                .find(|c| *c != channel && enabled(c))
+                .unwrap_or(channel);
+            (other, priority)
+        }
+        SnoozeEscalation::Block => (channel, priority),
+    }
+}
+
+/// Resolve a snooze preset against `now` in the user's timezone.
+///
+/// Presets are durations (`"10m"`, `"1h"`, `"2d"`) or `"tomorrow_morning"`,
+/// `"this_evening"` and `"next_week"` (Monday morning). `None` for an unknown
+/// preset, or `this_evening` once the evening has started.
+pub fn resolve_snooze_preset(preset: &str, now: DateTime<Utc>, tz: Tz) -> Option<DateTime<Utc>> {
+    let today = now.with_timezone(&tz).date_naive();
+    let at = |date: chrono::NaiveDate, (hour, minute): (u32, u32)| {
+        NaiveTime::from_hms_opt(hour, minute, 0)
+            .and_then(|time| crate::recurrence::resolve_local(&tz, date.and_time(time)))
+    };
+
+    match preset {
+        "tomorrow_morning" => at(today + Duration::days(1), SNOOZE_MORNING),
+        "this_evening" => at(today, SNOOZE_EVENING).filter(|evening| *evening > now),
+        "next_week" => {
+            let days = 7 - i64::from(today.weekday().num_days_from_monday());
+            at(today + Duration::days(days), SNOOZE_MORNING)
+        }
+        _ => {
+            let unit = preset.chars().last()?;
+            let amount: i64 = preset[..preset.len() - unit.len_utf8()]
+                .parse()
+                .ok()
+                .filter(|n| (1..=MAX_SNOOZE_AMOUNT).contains(n))?;
+            let duration = match unit {
+                'm' => Duration::minutes(amount),
+                'h' => Duration::hours(amount),
+                'd' => Duration::days(amount),
+                _ => return None,
+            };
+            Some(now + duration)
+        }
+    }
+}
+
+/// Outcome of the due check for a single reminder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DueCheck {
+    /// Reminder is due and should fire.
+    Due,
+    /// Reminder is not in the `active` status.
+    Inactive,
+    /// Reminder has no scheduled trigger time.
+    Unscheduled,
+    /// Trigger time is still in the future.
+    NotYetDue,
+    /// Reminder is snoozed past `now`.
+    Snoozed,
+}
+
+/// Apply the evaluator's due check (mirrors the `get_pending_reminders` query).
+pub fn check_due(
+    status: &str,
+    next_trigger_at: Option<DateTime<Utc>>,
+    snooze_until: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> DueCheck {
+    if status != "active" {
+        return DueCheck::Inactive;
+    }
+
+    match next_trigger_at {
+        None => DueCheck::Unscheduled,
+        Some(at) if at > now => DueCheck::NotYetDue,
+        Some(_) => match snooze_until {
+            Some(until) if until > now => DueCheck::Snoozed,
+            _ => DueCheck::Due,
+        },
+    }
+}
+
+/// When a `time` reminder is for: `scheduledAt`, `triggerAt` or `at` in its
+/// trigger config (RFC 3339).
+pub fn scheduled_at(trigger_config: &serde_json::Value) -> Option<DateTime<Utc>> {
+    trigger_config
+        .get("scheduledAt")
+        .or_else(|| trigger_config.get("triggerAt"))
+        .or_else(|| trigger_config.get("at"))
+        .and_then(|v| v.as_str())
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// Travel modes a reminder can route with (Amazon Location `TravelMode`)
+pub const TRAVEL_MODES: &[&str] = &["car", "truck", "walking"];
+
+/// Minutes to spare on arrival when the reminder doesn't say
+pub const DEFAULT_TRAVEL_BUFFER_MINUTES: i64 = 5;
+
+/// Most minutes to spare a reminder can ask for
+pub const MAX_TRAVEL_BUFFER_MINUTES: i64 = 240;
+
+/// How far ahead of a reminder's time the evaluator starts routing; longer
+/// trips fire this long before
+pub const TRAVEL_LOOKAHEAD_HOURS: i64 = 6;
+
+/// How often a reminder's travel time is re-estimated
+pub const TRAVEL_REFRESH_MINUTES: i64 = 10;
+
+/// Oldest last known location routed from
+pub const TRAVEL_LOCATION_MAX_AGE_MINUTES: i64 = 120;
+
+/// A `time` reminder that fires "in time to leave" for its related entity
+/// rather than at its time: `"inTimeToLeave": true` in the trigger config,
+/// with an optional `travelMode` and `bufferMinutes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaveInTime {
+    /// One of [`TRAVEL_MODES`]
+    pub travel_mode: String,
+    /// Minutes to spare on arrival
+    pub buffer_minutes: i64,
+}
+
+impl LeaveInTime {
+    /// Read the option from a trigger config; `None` if it isn't set.
+    pub fn from_config(trigger_config: &serde_json::Value) -> Result<Option<Self>> {
+        let enabled = trigger_config
+            .get("inTimeToLeave")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        if !enabled {
+            return Ok(None);
+        }
+
+        let travel_mode = match trigger_config.get("travelMode") {
+            None | Some(serde_json::Value::Null) => "car".to_string(),
+            Some(mode) => mode
+                .as_str()
+                .map(|m| m.trim().to_lowercase())
+                .filter(|m| TRAVEL_MODES.contains(&m.as_str()))
+                .ok_or_else(|| {
+                    Error::Validation(format!(
+                        "travelMode must be one of: {}",
+                        TRAVEL_MODES.join(", ")
+                    ))
+                })?,
+        };
+
+        let buffer_minutes = match trigger_config.get("bufferMinutes") {
+            None | Some(serde_json::Value::Null) => DEFAULT_TRAVEL_BUFFER_MINUTES,
+            Some(minutes) => minutes
+                .as_i64()
+                .filter(|m| (0..=MAX_TRAVEL_BUFFER_MINUTES).contains(m))
+                .ok_or_else(|| {
+                    Error::Validation(format!(
+                        "bufferMinutes must be between 0 and {}",
+                        MAX_TRAVEL_BUFFER_MINUTES
+                    ))
+                })?,
+        };
+
+        Ok(Some(Self {
+            travel_mode,
+            buffer_minutes,
+        }))
+    }
+
+    /// Amazon Location's name for the travel mode (`Car`, `Truck`, `Walking`).
+    pub fn route_travel_mode(&self) -> String {
+        let mut chars = self.travel_mode.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect())
+            .unwrap_or_default()
+    }
+
+    /// When to remind the user to leave to arrive by `arrive_at`, given the
+    /// estimated travel time. Without an estimate this is just the buffer
+    /// before; longer trips than the lookahead are capped to it.
+    pub fn leave_at(&self, arrive_at: DateTime<Utc>, travel_seconds: Option<i32>) -> DateTime<Utc> {
+        let travel = Duration::seconds(i64::from(travel_seconds.unwrap_or(0).max(0)))
+            .min(Duration::hours(TRAVEL_LOOKAHEAD_HOURS));
+        arrive_at - travel - Duration::minutes(self.buffer_minutes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 15, hour, minute, 0).unwrap()
+    }
+
+    fn quiet_prefs(timezone: &str) -> NotificationPreferences {
+        NotificationPreferences {
+            quiet_hours_enabled: true,
+            quiet_hours_start: NaiveTime::from_hms_opt(22, 0, 0),
+            quiet_hours_end: NaiveTime::from_hms_opt(7, 0, 0),
+            timezone: timezone.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_quiet_hours_wrapping() {
+        let prefs = quiet_prefs("UTC");
+
+        assert!(is_in_quiet_hours(&prefs, at(23, 30)));
+        assert!(is_in_quiet_hours(&prefs, at(6, 59)));
+        assert!(!is_in_quiet_hours(&prefs, at(7, 0)));
+        assert!(!is_in_quiet_hours(&prefs, at(12, 0)));
+    }
+
+    #[test]
+    fn test_quiet_hours_timezone() {
+        // 22:00-07:00 in New York (EST, UTC-5) is 03:00-12:00 UTC
+        let prefs = quiet_prefs("America/New_York");
+        assert!(is_in_quiet_hours(&prefs, at(3, 30)));
+        assert!(is_in_quiet_hours(&prefs, at(11, 59)));
+        assert!(!is_in_quiet_hours(&prefs, at(23, 30)));
+        assert!(!is_in_quiet_hours(&prefs, at(12, 0)));
+    }
+
+    #[test]
+    fn test_quiet_hours_end() {
+        let prefs = quiet_prefs("America/New_York");
+
+        // 23:30 New York on the 14th -> 07:00 New York on the 15th
+        let late = Utc.with_ymd_and_hms(2026, 1, 15, 4, 30, 0).unwrap();
+        assert_eq!(quiet_hours_end(&prefs, late), Some(at(12, 0)));
+
+        // 05:00 New York -> 07:00 the same morning
+        assert_eq!(quiet_hours_end(&prefs, at(10, 0)), Some(at(12, 0)));
+
+        assert_eq!(quiet_hours_end(&prefs, at(15, 0)), None);
+
+        // Non-wrapping window in UTC
+        let afternoon = NotificationPreferences {
+            quiet_hours_start: NaiveTime::from_hms_opt(13, 0, 0),
+            quiet_hours_end: NaiveTime::from_hms_opt(15, 30, 0),
+            ..quiet_prefs("UTC")
+        };
+        assert_eq!(quiet_hours_end(&afternoon, at(14, 0)), Some(at(15, 30)));
+    }
+
+    #[test]
+    fn test_quiet_hours_end_dst_gap() {
+        // Quiet hours end at 02:30 on the night clocks jump from 02:00 to 03:00
+        let prefs = NotificationPreferences {
+            quiet_hours_start: NaiveTime::from_hms_opt(23, 0, 0),
+            quiet_hours_end: NaiveTime::from_hms_opt(2, 30, 0),
+            ..quiet_prefs("America/New_York")
+        };
+        let now = Utc.with_ymd_and_hms(2026, 3, 8, 5, 0, 0).unwrap(); // 00:00 EST
+        let end = Utc.with_ymd_and_hms(2026, 3, 8, 7, 30, 0).unwrap(); // 03:30 EDT
+        assert_eq!(quiet_hours_end(&prefs, now), Some(end));
+    }
+
+    #[test]
+    fn test_type_overrides() {
+        let mut overrides = TypeOverrides::new();
+        overrides.insert(
+            "reminder".to_string(),
+            TypeOverride {
+                enabled: true,
+                channel: Some("email".to_string()),
+                ignore_quiet_hours: true,
+            },
+        );
+        overrides.insert(
+            "proactive".to_string(),
+            TypeOverride {
+                enabled: false,
+                channel: None,
+                ignore_quiet_hours: false,
+            },
+        );
+        let prefs = NotificationPreferences {
+            type_overrides: Json(overrides),
+            ..quiet_prefs("UTC")
+        };
+
+        assert_eq!(channel_for(&prefs, "reminder"), Some("email"));
+        assert_eq!(channel_for(&prefs, "proactive"), None);
+        assert_eq!(channel_for(&prefs, "calendar"), Some("push"));
+
+        // 23:30 is in quiet hours, which only reminders ignore
+        assert_eq!(deferred_until(&prefs, "reminder", at(23, 30)), None);
+        assert_eq!(
+            deferred_until(&prefs, "calendar", at(23, 30)),
+            quiet_hours_end(&prefs, at(23, 30))
+        );
+    }
+
+    #[test]
+    fn test_snooze_decision() {
+        use SnoozeEscalation::*;
+
+        assert_eq!(snooze_decision(10, None, Block), SnoozeDecision::Allow);
+        assert_eq!(snooze_decision(1, Some(3), Priority), SnoozeDecision::Allow);
+        // The third snooze escalates
+        assert_eq!(snooze_decision(2, Some(3), Priority), SnoozeDecision::Escalate);
+        assert_eq!(snooze_decision(5, Some(3), Channel), SnoozeDecision::Escalate);
+        assert_eq!(snooze_decision(2, Some(3), Block), SnoozeDecision::Allow);
+        assert_eq!(snooze_decision(3, Some(3), Block), SnoozeDecision::Refuse);
+        assert_eq!(SnoozeEscalation::parse("channel"), Some(Channel));
+        assert_eq!(SnoozeEscalation::parse("louder"), None);
+    }
+
+    #[test]
+    fn test_escalated_delivery() {
+        let prefs = NotificationPreferences {
+            discord_enabled: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            escalated_delivery(SnoozeEscalation::Priority, &prefs, 3),
+            ("discord", 4)
+        );
+        assert_eq!(
+            escalated_delivery(SnoozeEscalation::Priority, &prefs, 5),
+            ("discord", 5)
+        );
+        assert_eq!(
+            escalated_delivery(SnoozeEscalation::Channel, &prefs, 3),
+            ("push", 3)
+        );
+
+        // Nothing else enabled: stay on the preferred channel
+        let push_only = NotificationPreferences {
+            email_enabled: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            escalated_delivery(SnoozeEscalation::Channel, &push_only, 3),
+            ("push", 3)
+        );
+
+        // Alexa is preferred only when nothing else is enabled
+        let with_alexa = NotificationPreferences {
+            email_enabled: false,
+            alexa_enabled: true,
+            ..Default::default()
+        };
+        assert_eq!(preferred_channel(&with_alexa), "push");
+        assert_eq!(
+            escalated_delivery(SnoozeEscalation::Channel, &with_alexa, 3),
+            ("alexa", 3)
+        );
+        let alexa_only = NotificationPreferences {
+            push_enabled: false,
+            ..with_alexa
+        };
+        assert_eq!(preferred_channel(&alexa_only), "alexa");
+    }
+
+    #[test]
+    fn test_resolve_snooze_preset() {
+        let ny: Tz = "America/New_York".parse().unwrap();
+        // Thursday Jan 15, 10:00 New York (EST)
+        let now = at(15, 0);
+
+        assert_eq!(resolve_snooze_preset("10m", now, ny), Some(at(15, 10)));
+        assert_eq!(resolve_snooze_preset("1h", now, ny), Some(at(16, 0)));
+        assert_eq!(
+            resolve_snooze_preset("tomorrow_morning", now, ny),
+            Some(Utc.with_ymd_and_hms(2026, 1, 16, 14, 0, 0).unwrap())
+        );
+        assert_eq!(resolve_snooze_preset("this_evening", now, ny), Some(at(23, 0)));
+        assert_eq!(
+            resolve_snooze_preset("next_week", now, ny),
+            Some(Utc.with_ymd_and_hms(2026, 1, 19, 14, 0, 0).unwrap())
+        );
+
+        // 18:30 New York is past the evening
+        assert_eq!(resolve_snooze_preset("this_evening", at(23, 30), ny), None);
+        assert_eq!(resolve_snooze_preset("0m", now, ny), None);
+        assert_eq!(resolve_snooze_preset("later", now, ny), None);
+        assert_eq!(resolve_snooze_preset("", now, ny), None);
+    }
+
+    #[test]
+    fn test_check_due() {
+        let now = at(9, 0);
+        assert_eq!(check_due("active", Some(at(8, 0)), None, now), DueCheck::Due);
+        assert_eq!(check_due("active", Some(at(10, 0)), None, now), DueCheck::NotYetDue);
+        assert_eq!(check_due("active", Some(at(8, 0)), Some(at(9, 30)), now), DueCheck::Snoozed);
+        assert_eq!(check_due("active", None, None, now), DueCheck::Unscheduled);
+        assert_eq!(check_due("completed", Some(at(8, 0)), None, now), DueCheck::Inactive);
+    }
+
+    #[test]
+    fn test_scheduled_at() {
+        let config = serde_json::json!({"at": "2026-01-15T09:00:00Z"});
+        assert_eq!(scheduled_at(&config), Some(at(9, 0)));
+        let config = serde_json::json!({"scheduledAt": "2026-01-15T04:00:00-05:00"});
+        assert_eq!(scheduled_at(&config), Some(at(9, 0)));
+        assert_eq!(scheduled_at(&serde_json::json!({"at": "tomorrow"})), None);
+    }
+
+    #[test]
+    fn test_leave_in_time() {
+        assert_eq!(
+            LeaveInTime::from_config(&serde_json::json!({"at": "2026-01-15T09:00:00Z"})).unwrap(),
+            None
+        );
+
+        let leave = LeaveInTime::from_config(&serde_json::json!({"inTimeToLeave": true}))
+            .unwrap()
+            .unwrap();
+        assert_eq!(leave.travel_mode, "car");
+        assert_eq!(leave.route_travel_mode(), "Car");
+        assert_eq!(leave.buffer_minutes, DEFAULT_TRAVEL_BUFFER_MINUTES);
+
+        let leave = LeaveInTime::from_config(&serde_json::json!({
+            "inTimeToLeave": true,
+            "travelMode": "Walking",
+            "bufferMinutes": 10
+        }))
+        .unwrap()
+        .unwrap();
+        assert_eq!(leave.route_travel_mode(), "Walking");
+        // 25 minutes away with 10 to spare: leave at 8:25 for 9:00
+        assert_eq!(leave.leave_at(at(9, 0), Some(25 * 60)), at(8, 25));
+        assert_eq!(leave.leave_at(at(9, 0), None), at(8, 50));
+        // Trips longer than the lookahead are capped to it
+        assert_eq!(leave.leave_at(at(20, 0), Some(10 * 3600)), at(13, 50));
+
+        for config in [
+            serde_json::json!({"inTimeToLeave": true, "travelMode": "teleport"}),
+            serde_json::json!({"inTimeToLeave": true, "bufferMinutes": -1}),
+            serde_json::json!({"inTimeToLeave": true, "bufferMinutes": "5"}),
+        ] {
+            assert!(matches!(
+                LeaveInTime::from_config(&config),
+                Err(Error::Validation(_))
+            ));
+        }
+    }
+}
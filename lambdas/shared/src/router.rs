@@ -0,0 +1,542 @@
+//! Lightweight HTTP router and middleware for API Gateway Lambdas.
+//!
+//! Routes are registered with path patterns (`/entities/{id}/facts`) and
+//! dispatched to async handlers that receive the shared state, the request,
+//! and the captured [`PathParams`]:
+//!
+//! ```ignore
+//! let router = Router::new()
+//!     .layer(RequestLogger)
+//!     .layer(Cors::from_env())
+//!     .get("/entities/{id}", get_entity)
+//!     .post("/entities", create_entity);
+//!
+//! run(service_fn(move |event| {
+//!     let router = Arc::clone(&router);
+//!     let state = Arc::clone(&state);
+//!     async move { router.dispatch(state, event).await }
+//! }))
+//! ```
+
+use lambda_http::{Body, Request, RequestExt, Response};
+use serde::de::DeserializeOwned;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::api_keys;
+use crate::auth::AuthorizedUser;
+pub use crate::cors::Cors;
+use crate::device_auth;
+use crate::error::ApiError;
+use crate::http::error_response;
+use crate::{Error, Result};
+
+/// Boxed future returned by route handlers.
+pub type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// Result type returned by route handlers.
+pub type HandlerResult = std::result::Result<Response<Body>, lambda_http::Error>;
+
+type BoxedHandler<S> = Box<dyn Fn(Arc<S>, Request, PathParams) -> BoxFuture<HandlerResult> + Send + Sync>;
+
+/// Parameters captured from `{name}` segments of a route pattern.
+#[derive(Debug, Clone, Default)]
+pub struct PathParams(HashMap<String, String>);
+
+impl PathParams {
+    /// Raw value of a captured segment.
+    pub fn raw(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    /// Parse a captured segment (e.g. as a `Uuid`), failing with a validation error.
+    pub fn get<T: FromStr>(&self, name: &str) -> Result<T> {
+        self.raw(name)
+            .ok_or_else(|| Error::Validation(format!("Missing path parameter: {}", name)))?
+            .parse()
+            .map_err(|_| Error::Validation(format!("Invalid {}", name)))
+    }
+
+    /// Parse a captured UUID segment, failing with a validation error that
+    /// reads `message` (e.g. "Invalid entity ID").
+    pub fn id(&self, name: &str, message: &str) -> Result<Uuid> {
+        self.get(name)
+            .map_err(|_| Error::Validation(message.to_string()))
+    }
+}
+
+/// Query string parameters (first value wins for repeated keys).
+#[derive(Debug, Clone, Default)]
+pub struct Query(HashMap<String, String>);
+
+impl Query {
+    /// Extract query string parameters from a request.
+    pub fn from_request(req: &Request) -> Self {
+        let mut params = HashMap::new();
+        for (key, value) in req.query_string_parameters().iter() {
+            params.entry(key.to_string()).or_insert_with(|| value.to_string());
+        }
+        Self(params)
+    }
+
+    /// Raw value of a parameter.
+    pub fn first(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    /// Parse an optional parameter, failing with a validation error if it is malformed.
+    pub fn get<T: FromStr>(&self, name: &str) -> Result<Option<T>> {
+        self.first(name)
+            .map(|v| {
+                v.parse()
+                    .map_err(|_| Error::Validation(format!("Invalid query parameter: {}", name)))
+            })
+            .transpose()
+    }
+}
+
+/// Deserialize a JSON request body, failing with a validation error.
+pub fn json_body<T: DeserializeOwned>(req: &Request) -> Result<T> {
+    serde_json::from_slice(req.body().as_ref())
+        .map_err(|e| Error::Validation(format!("Invalid request body: {}", e)))
+}
+
+/// The request's user, from its Cognito claims, API key or device token.
+///
+/// Fails with `Error::Auth` (a 401 once returned from a handler) when there is
+/// none, and `Error::RateLimited` when the caller is over the rate limit.
+pub async fn require_user(req: &Request, pool: &PgPool) -> Result<AuthorizedUser> {
+    AuthorizedUser::from_request(req, pool).await
+}
+
+/// The request's user, signed in to the app: like [`require_user`], but
+/// failing with `Error::Unauthorized` (a 403) for API keys and device tokens.
+pub async fn require_interactive_user(req: &Request, pool: &PgPool) -> Result<AuthorizedUser> {
+    require_user(req, pool).await?.require_interactive()
+}
+
+/// Convert a shared error into its problem details response.
+pub fn error_to_response(error: Error) -> HandlerResult {
+    ApiError::from(error).into_response()
+}
+
+/// Convert an error returned by a handler into its problem details response.
+///
+/// Handlers can bail out with `?` on an [`ApiError`], a shared [`Error`] or a
+/// `sqlx::Error`, which keep their status; anything else is a 500.
+pub fn handler_error_response(error: lambda_http::Error) -> HandlerResult {
+    let error = match error.downcast::<ApiError>() {
+        Ok(error) => return (*error).into_response(),
+        Err(error) => error,
+    };
+    let error = match error.downcast::<Error>() {
+        Ok(error) => return error_to_response(*error),
+        Err(error) => error,
+    };
+    match error.downcast::<sqlx::Error>() {
+        Ok(error) => ApiError::from(*error).into_response(),
+        Err(error) => ApiError::internal(error).into_response(),
+    }
+}
+
+/// Request details available to middleware.
+#[derive(Debug, Clone)]
+pub struct RequestInfo {
+    pub method: String,
+    pub path: String,
+    /// Pattern of the route whose path matched (for any method), e.g. `/entities/{id}`
+    pub route: Option<String>,
+    /// The `Origin` header, for CORS
+    pub origin: Option<String>,
+    /// The `Accept-Encoding` header, for response compression
+    pub accept_encoding: Option<String>,
+    /// The `fields` query parameter, for sparse fieldsets
+    pub fields: Option<String>,
+    pub started: Instant,
+}
+
+/// Middleware hooks run around every dispatched request.
+pub trait Middleware: Send + Sync {
+    /// Inspect the request before routing. Return a response to short-circuit.
+    fn before(&self, _req: &Request, _info: &RequestInfo) -> Option<Response<Body>> {
+        None
+    }
+
+    /// Leave something in the request's extensions for the handler, once
+    /// the request has passed every `before`.
+    fn prepare(&self, _req: &mut Request, _info: &RequestInfo) {}
+
+    /// Adjust the response (including short-circuited and error responses).
+    fn after(&self, _info: &RequestInfo, _response: &mut Response<Body>) {}
+}
+
+/// Logs method, path, status and latency for each request.
+pub struct RequestLogger;
+
+impl Middleware for RequestLogger {
+    fn after(&self, info: &RequestInfo, response: &mut Response<Body>) {
+        info!(
+            method = %info.method,
+            path = %info.path,
+            status = response.status().as_u16(),
+            latency_ms = info.started.elapsed().as_millis() as u64,
+            "Request complete"
+        );
+    }
+}
+
+/// Rejects requests without Cognito authorizer claims, an API key or a
+/// device access token.
+///
+/// Resolving the database user (and checking the key or token) is left to
+/// the handler; this only guards against requests that never passed through
+/// the Cognito authorizer and don't claim to have a key or token.
+pub struct RequireAuth;
+
+impl Middleware for RequireAuth {
+    fn before(&self, req: &Request, _info: &RequestInfo) -> Option<Response<Body>> {
+        if has_credentials(req) {
+            None
+        } else {
+            error_response(401, "Authentication required").ok()
+        }
+    }
+}
+
+/// Whether a request has Cognito authorizer claims, or claims to have an API
+/// key or device access token.
+pub(crate) fn has_credentials(req: &Request) -> bool {
+    let has_claims = req
+        .request_context_ref()
+        .and_then(|ctx| ctx.authorizer().and_then(|a| a.fields.get("claims").cloned()))
+        .is_some();
+
+    has_claims
+        || api_keys::key_from_request(req).is_some()
+        || device_auth::token_from_request(req).is_some()
+}
+
+/// A route path pattern such as `/reminders/{id}/snooze`.
+#[derive(Debug, Clone)]
+pub(crate) struct PathPattern {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Param(String),
+}
+
+impl PathPattern {
+    pub(crate) fn parse(pattern: &str) -> Self {
+        let segments = split_path(pattern)
+            .map(|s| match s.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                Some(name) => Segment::Param(name.to_string()),
+                None => Segment::Literal(s.to_string()),
+            })
+            .collect();
+        Self { segments }
+    }
+
+    pub(crate) fn matches(&self, path: &str) -> Option<PathParams> {
+        let parts: Vec<&str> = split_path(path).collect();
+        if parts.len() != self.segments.len() {
+            return None;
+        }
+
+        let mut params = HashMap::new();
+        for (segment, part) in self.segments.iter().zip(parts) {
+            match segment {
+                Segment::Literal(lit) if lit == part => {}
+                Segment::Literal(_) => return None,
+                Segment::Param(name) => {
+                    params.insert(name.clone(), part.to_string());
+                }
+            }
+        }
+        Some(PathParams(params))
+    }
+}
+
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|s| !s.is_empty())
+}
+
+struct Route<S> {
+    method: String,
+    path: String,
+    pattern: PathPattern,
+    handler: BoxedHandler<S>,
+}
+
+/// Method + path router with middleware.
+pub struct Router<S> {
+    routes: Vec<Route<S>>,
+    middleware: Vec<Box<dyn Middleware>>,
+    strip_prefix: Option<String>,
+}
+
+impl<S: Send + Sync + 'static> Default for Router<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Send + Sync + 'static> Router<S> {
+    /// Create a router that strips the `/api` stage prefix (API Gateway REST API).
+    pub fn new() -> Self {
+        Self {
+            routes: Vec::new(),
+            middleware: Vec::new(),
+            strip_prefix: Some("/api".to_string()),
+        }
+    }
+
+    /// Override the stage prefix stripped from incoming paths.
+    pub fn strip_prefix(mut self, prefix: Option<&str>) -> Self {
+        self.strip_prefix = prefix.map(String::from);
+        self
+    }
+
+    /// Add middleware. `before` hooks run in registration order, `after` hooks in reverse.
+    pub fn layer(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Box::new(middleware));
+        self
+    }
+
+    /// Register a handler for a method and path pattern.
+    pub fn route<F, Fut>(mut self, method: &str, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Arc<S>, Request, PathParams) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = HandlerResult> + Send + 'static,
+    {
+        self.routes.push(Route {
+            method: method.to_uppercase(),
+            path: pattern.to_string(),
+            pattern: PathPattern::parse(pattern),
+            handler: Box::new(move |state, req, params| Box::pin(handler(state, req, params))),
+        });
+        self
+    }
+
+    pub fn get<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Arc<S>, Request, PathParams) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = HandlerResult> + Send + 'static,
+    {
+        self.route("GET", pattern, handler)
+    }
+
+    pub fn post<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Arc<S>, Request, PathParams) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = HandlerResult> + Send + 'static,
+    {
+        self.route("POST", pattern, handler)
+    }
+
+    pub fn put<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Arc<S>, Request, PathParams) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = HandlerResult> + Send + 'static,
+    {
+        self.route("PUT", pattern, handler)
+    }
+
+    pub fn delete<F, Fut>(self, pattern: &str, handler: F) -> Self
+    where
+        F: Fn(Arc<S>, Request, PathParams) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = HandlerResult> + Send + 'static,
+    {
+        self.route("DELETE", pattern, handler)
+    }
+
+    /// Route a request through middleware to the matching handler.
+    ///
+    /// A handler's `Err` is answered with its problem details response (see
+    /// [`handler_error_response`]), which the middleware still sees.
+    pub async fn dispatch(&self, state: Arc<S>, mut req: Request) -> HandlerResult {
+        let raw_path = req.uri().path();
+        let path = match &self.strip_prefix {
+            Some(prefix) => raw_path.strip_prefix(prefix.as_str()).unwrap_or(raw_path),
+            None => raw_path,
+        }
+        .to_string();
+
+        let mut info = RequestInfo {
+            method: req.method().as_str().to_string(),
+            path,
+            route: None,
+            origin: crate::cors::request_origin(&req),
+            accept_encoding: crate::shaping::request_accept_encoding(&req),
+            fields: crate::shaping::request_fields(&req),
+            started: Instant::now(),
+        };
+
+        let matched = self.find_route(&info.method, &info.path);
+        info.route = match &matched {
+            RouteMatch::Found(route, _) | RouteMatch::WrongMethod(route) => Some(route.path.clone()),
+            RouteMatch::NotFound => None,
+        };
+
+        let mut response = match self.middleware.iter().find_map(|m| m.before(&req, &info)) {
+            Some(response) => response,
+            None => match matched {
+                RouteMatch::Found(route, params) => {
+                    for m in &self.middleware {
+                        m.prepare(&mut req, &info);
+                    }
+                    (route.handler)(state, req, params)
+                        .await
+                        .or_else(handler_error_response)?
+                }
+                RouteMatch::WrongMethod(_) => error_response(405, "Method not allowed")?,
+                RouteMatch::NotFound => error_response(404, "Not found")?,
+            },
+        };
+
+        for m in self.middleware.iter().rev() {
+            m.after(&info, &mut response);
+        }
+
+        Ok(response)
+    }
+
+    fn find_route(&self, method: &str, path: &str) -> RouteMatch<'_, S> {
+        let mut path_matched = None;
+
+        for route in &self.routes {
+            if let Some(params) = route.pattern.matches(path) {
+                if route.method == method {
+                    return RouteMatch::Found(route, params);
+                }
+                path_matched.get_or_insert(route);
+            }
+        }
+
+        match path_matched {
+            Some(route) => RouteMatch::WrongMethod(route),
+            None => RouteMatch::NotFound,
+        }
+    }
+}
+
+/// The route a request resolves to.
+enum RouteMatch<'a, S> {
+    Found(&'a Route<S>, PathParams),
+    /// A route has the path, but not for the request's method
+    WrongMethod(&'a Route<S>),
+    NotFound,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_matching() {
+        let pattern = PathPattern::parse("/entities/{id}/facts");
+
+        let params = pattern.matches("/entities/abc/facts").unwrap();
+        assert_eq!(params.raw("id"), Some("abc"));
+
+        assert!(pattern.matches("/entities/abc").is_none());
+        assert!(pattern.matches("/entities/abc/relationships").is_none());
+        assert!(pattern.matches("/entities/abc/facts/extra").is_none());
+    }
+
+    #[test]
+    fn test_typed_path_params() {
+        let pattern = PathPattern::parse("/reminders/{id}");
+        let id = uuid::Uuid::new_v4();
+
+        let params = pattern.matches(&format!("/reminders/{}", id)).unwrap();
+        assert_eq!(params.get::<uuid::Uuid>("id").unwrap(), id);
+
+        let params = pattern.matches("/reminders/not-a-uuid").unwrap();
+        assert!(matches!(params.get::<uuid::Uuid>("id"), Err(Error::Validation(_))));
+        assert!(matches!(
+            params.id("id", "Invalid reminder ID"),
+            Err(Error::Validation(message)) if message == "Invalid reminder ID"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_require_user_rejects_anonymous_requests() {
+        // Never connects: there are no credentials to look up
+        let pool = PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        let error = require_user(&Request::default(), &pool).await.unwrap_err();
+        assert!(matches!(error, Error::Auth(_)));
+
+        let response = handler_error_response(error.into()).unwrap();
+        assert_eq!(response.status(), 401);
+    }
+
+    #[test]
+    fn test_find_route_reports_pattern() {
+        async fn ok(_: Arc<()>, _: Request, _: PathParams) -> HandlerResult {
+            Ok(Response::new(Body::Empty))
+        }
+        let router = Router::<()>::new().get("/entities/{id}", ok);
+
+        assert!(matches!(
+            router.find_route("GET", "/entities/abc"),
+            RouteMatch::Found(route, _) if route.path == "/entities/{id}"
+        ));
+        assert!(matches!(
+            router.find_route("DELETE", "/entities/abc"),
+            RouteMatch::WrongMethod(route) if route.path == "/entities/{id}"
+        ));
+        assert!(matches!(router.find_route("GET", "/tags"), RouteMatch::NotFound));
+    }
+
+    struct Stamp;
+
+    impl Middleware for Stamp {
+        fn after(&self, _info: &RequestInfo, response: &mut Response<Body>) {
+            response
+                .headers_mut()
+                .insert("x-stamp", "1".parse().unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handler_errors_become_problem_responses() {
+        async fn missing(_: Arc<()>, _: Request, _: PathParams) -> HandlerResult {
+            Err(sqlx::Error::RowNotFound.into())
+        }
+        async fn invalid(_: Arc<()>, _: Request, _: PathParams) -> HandlerResult {
+            Err(Error::Validation("Invalid name".to_string()).into())
+        }
+        async fn broken(_: Arc<()>, _: Request, _: PathParams) -> HandlerResult {
+            Err("something broke".into())
+        }
+        let router = Router::<()>::new()
+            .layer(Stamp)
+            .get("/missing", missing)
+            .get("/invalid", invalid)
+            .get("/broken", broken);
+
+        for (path, status) in [("/missing", 404), ("/invalid", 400), ("/broken", 500)] {
+            let req = lambda_http::http::Request::builder()
+                .uri(path)
+                .body(Body::Empty)
+                .unwrap();
+            let response = router.dispatch(Arc::new(()), req).await.unwrap();
+            assert_eq!(response.status(), status, "{}", path);
+            assert_eq!(
+                response.headers()["content-type"],
+                crate::error::PROBLEM_JSON
+            );
+            assert_eq!(response.headers()["x-stamp"], "1");
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Helpers for the Lambdas that ingest files uploaded to S3:
+//! `drop_folder_ingest`, `photo_ingest` and `document_ingest`.
+//!
+//! Each is invoked with S3 notifications for keys under
+//! `{prefix}/{cognito_sub}/...`, records the file in its own table and
+//! retries transient failures on redelivery up to [`MAX_ATTEMPTS`].
+
+use sqlx::PgPool;
+
+use crate::{AuthenticatedUser, AuthorizedUser, Error};
+
+/// Deliveries after which a transient failure is treated as final.
+pub const MAX_ATTEMPTS: i16 = 3;
+
+/// Failure while processing a file.
+#[derive(Debug)]
+pub enum ProcessError {
+    /// Will never succeed (bad content, unsupported type) - record and notify.
+    Permanent(String),
+    /// May succeed on redelivery (AWS or database errors).
+    Transient(String),
+}
+
+impl<E: std::fmt::Display> From<E> for ProcessError {
+    fn from(e: E) -> Self {
+        ProcessError::Transient(e.to_string())
+    }
+}
+
+/// Decode an S3 notification key (URL-encoded, spaces as `+`).
+pub fn decode_key(key: &str) -> String {
+    let key = key.replace('+', " ");
+    urlencoding::decode(&key)
+        .map(|k| k.into_owned())
+        .unwrap_or(key)
+}
+
+/// Display name for a key (last path segment).
+pub fn file_name(key: &str) -> &str {
+    key.rsplit('/').next().unwrap_or(key)
+}
+
+/// Resolve the database user behind a Cognito sub, or `None` if there's no
+/// such user.
+pub async fn resolve_user(
+    pool: &PgPool,
+    cognito_sub: &str,
+) -> Result<Option<AuthorizedUser>, ProcessError> {
+    let user = AuthenticatedUser {
+        user_id: cognito_sub.to_string(),
+        email: None,
+        family_ids: Vec::new(),
+    };
+
+    match AuthorizedUser::resolve(user, pool).await {
+        Ok(user) => Ok(Some(user)),
+        Err(Error::Auth(_)) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_key() {
+        assert_eq!(
+            decode_key("drop/sub-1/Tax+Return%202025%281%29.pdf"),
+            "drop/sub-1/Tax Return 2025(1).pdf"
+        );
+        assert_eq!(decode_key("photos/sub-1/caf%C3%A9.jpg"), "photos/sub-1/café.jpg");
+        // Left as it is when it isn't valid percent-encoded UTF-8
+        assert_eq!(decode_key("drop/sub-1/%FF.txt"), "drop/sub-1/%FF.txt");
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("documents/sub-1/2026/lease.pdf"), "lease.pdf");
+        assert_eq!(file_name("lease.pdf"), "lease.pdf");
+    }
+
+    #[test]
+    fn test_process_error_from_is_transient() {
+        let error = ProcessError::from(Error::Aws("throttled".to_string()));
+        assert!(matches!(error, ProcessError::Transient(_)));
+    }
+}
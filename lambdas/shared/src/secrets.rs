@@ -1,20 +1,49 @@
 //! AWS Secrets Manager integration.
+//!
+//! Secrets are cached in memory for [`cache_ttl`], so warm containers pick up
+//! rotated values without a redeploy. When the AWS Parameters and Secrets
+//! Lambda Extension layer is attached (it sets
+//! `PARAMETERS_SECRETS_EXTENSION_HTTP_PORT`), reads go through its local
+//! endpoint instead of the Secrets Manager API. A caller that finds a value
+//! stale (e.g. the database rejects a password) calls [`refresh_secret`],
+//! which always reads the current version from Secrets Manager.
 
 use aws_sdk_secretsmanager::Client as SecretsClient;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
 use crate::{Error, Result};
 
+/// How long a secret is cached when `SECRETS_CACHE_TTL_SECONDS` is unset.
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Time allowed for a read from the secrets extension.
+const EXTENSION_TIMEOUT: Duration = Duration::from_secs(2);
+
+struct CachedSecret {
+    value: String,
+    fetched_at: Instant,
+}
+
 /// Cached secrets with lazy initialization.
-static SECRETS_CACHE: OnceLock<RwLock<HashMap<String, String>>> = OnceLock::new();
+static SECRETS_CACHE: OnceLock<RwLock<HashMap<String, CachedSecret>>> = OnceLock::new();
 
-fn get_cache() -> &'static RwLock<HashMap<String, String>> {
+fn get_cache() -> &'static RwLock<HashMap<String, CachedSecret>> {
     SECRETS_CACHE.get_or_init(|| RwLock::new(HashMap::new()))
 }
 
+/// How long secrets are cached: `SECRETS_CACHE_TTL_SECONDS`, or [`DEFAULT_CACHE_TTL`].
+pub fn cache_ttl() -> Duration {
+    std::env::var("SECRETS_CACHE_TTL_SECONDS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_CACHE_TTL)
+}
+
 /// Database credentials from Secrets Manager.
 #[derive(Debug, Deserialize)]
 pub struct DatabaseCredentials {
@@ -25,17 +54,48 @@ pub struct DatabaseCredentials {
     pub dbname: Option<String>,
 }
 
-/// Get a secret value from Secrets Manager with caching.
+/// Get a secret value, cached for [`cache_ttl`].
 pub async fn get_secret(client: &SecretsClient, secret_arn: &str) -> Result<String> {
     // Check cache first
     {
         let cache = get_cache().read().await;
-        if let Some(value) = cache.get(secret_arn) {
-            return Ok(value.clone());
+        if let Some(entry) = cache.get(secret_arn) {
+            if entry.fetched_at.elapsed() < cache_ttl() {
+                return Ok(entry.value.clone());
+            }
         }
     }
 
-    // Fetch from Secrets Manager
+    let value = match extension_port() {
+        Some(port) => fetch_from_extension(port, secret_arn).await?,
+        None => fetch(client, secret_arn).await?,
+    };
+    store(secret_arn, &value).await;
+
+    Ok(value)
+}
+
+/// Read the current version of a secret from Secrets Manager, bypassing both
+/// this cache and the extension's, e.g. after it has been rotated.
+pub async fn refresh_secret(client: &SecretsClient, secret_arn: &str) -> Result<String> {
+    let value = fetch(client, secret_arn).await?;
+    store(secret_arn, &value).await;
+    Ok(value)
+}
+
+async fn store(secret_arn: &str, value: &str) {
+    let mut cache = get_cache().write().await;
+    cache.insert(
+        secret_arn.to_string(),
+        CachedSecret {
+            value: value.to_string(),
+            fetched_at: Instant::now(),
+        },
+    );
+}
+
+/// Fetch a secret from the Secrets Manager API.
+async fn fetch(client: &SecretsClient, secret_arn: &str) -> Result<String> {
     let response = client
         .get_secret_value()
         .secret_id(secret_arn)
@@ -43,18 +103,51 @@ pub async fn get_secret(client: &SecretsClient, secret_arn: &str) -> Result<Stri
         .await
         .map_err(|e| Error::Aws(format!("Failed to get secret: {}", e)))?;
 
-    let secret_string = response
+    response
         .secret_string()
-        .ok_or_else(|| Error::Aws("Secret has no string value".to_string()))?
-        .to_string();
+        .map(String::from)
+        .ok_or_else(|| Error::Aws("Secret has no string value".to_string()))
+}
 
-    // Cache the result
-    {
-        let mut cache = get_cache().write().await;
-        cache.insert(secret_arn.to_string(), secret_string.clone());
-    }
+/// Port of the Parameters and Secrets Lambda Extension, if it is attached.
+fn extension_port() -> Option<u16> {
+    std::env::var("PARAMETERS_SECRETS_EXTENSION_HTTP_PORT")
+        .ok()?
+        .parse()
+        .ok()
+}
 
-    Ok(secret_string)
+#[derive(Deserialize)]
+struct ExtensionResponse {
+    #[serde(rename = "SecretString")]
+    secret_string: Option<String>,
+}
+
+/// Fetch a secret through the extension's local HTTP endpoint.
+async fn fetch_from_extension(port: u16, secret_arn: &str) -> Result<String> {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    let client = CLIENT.get_or_init(reqwest::Client::new);
+
+    let mut url = url::Url::parse(&format!("http://localhost:{}/secretsmanager/get", port))
+        .map_err(|e| Error::Config(format!("Invalid secrets extension port: {}", e)))?;
+    url.query_pairs_mut().append_pair("secretId", secret_arn);
+
+    let token = std::env::var("AWS_SESSION_TOKEN").unwrap_or_default();
+    let response: ExtensionResponse = client
+        .get(url)
+        .header("X-Aws-Parameters-Secrets-Token", token)
+        .timeout(EXTENSION_TIMEOUT)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| Error::Aws(format!("Failed to get secret from extension: {}", e)))?
+        .json()
+        .await
+        .map_err(|e| Error::Aws(format!("Failed to parse extension response: {}", e)))?;
+
+    response
+        .secret_string
+        .ok_or_else(|| Error::Aws("Secret has no string value".to_string()))
 }
 
 /// Get database credentials from Secrets Manager.
@@ -62,9 +155,19 @@ pub async fn get_database_credentials(
     client: &SecretsClient,
     secret_arn: &str,
 ) -> Result<DatabaseCredentials> {
-    let secret_string = get_secret(client, secret_arn).await?;
+    parse_database_credentials(&get_secret(client, secret_arn).await?)
+}
 
-    serde_json::from_str(&secret_string)
+/// Get the current database credentials, bypassing the cache (see [`refresh_secret`]).
+pub async fn refresh_database_credentials(
+    client: &SecretsClient,
+    secret_arn: &str,
+) -> Result<DatabaseCredentials> {
+    parse_database_credentials(&refresh_secret(client, secret_arn).await?)
+}
+
+fn parse_database_credentials(secret_string: &str) -> Result<DatabaseCredentials> {
+    serde_json::from_str(secret_string)
         .map_err(|e| Error::Aws(format!("Failed to parse database credentials: {}", e)))
 }
 
@@ -81,9 +184,19 @@ mod tests {
     #[test]
     fn test_parse_credentials() {
         let json = r#"{"username":"admin","password":"secret123","host":"db.example.com","port":5432,"dbname":"mydb"}"#;
-        let creds: DatabaseCredentials = serde_json::from_str(json).unwrap();
+        let creds = parse_database_credentials(json).unwrap();
         assert_eq!(creds.username, "admin");
         assert_eq!(creds.password, "secret123");
         assert_eq!(creds.host, Some("db.example.com".to_string()));
     }
+
+    #[test]
+    fn test_parse_extension_response() {
+        let json = r#"{"ARN":"arn:aws:secretsmanager:us-east-1:123:secret:db","Name":"db","SecretString":"{\"password\":\"p\"}","VersionStages":["AWSCURRENT"]}"#;
+        let response: ExtensionResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            response.secret_string.as_deref(),
+            Some(r#"{"password":"p"}"#)
+        );
+    }
 }
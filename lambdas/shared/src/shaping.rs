@@ -0,0 +1,342 @@
+//! Response shaping for clients on slow connections.
+//!
+//! Two things happen to a response on its way out:
+//!
+//! - **Sparse fieldsets**: `?fields=id,name,updated_at` keeps only those keys
+//!   of each returned object (every item of a list or page), so clients can
+//!   skip fields they don't display. Successful JSON responses only; errors are
+//!   left whole.
+//! - **Compression**: JSON and text bodies of at least [`MIN_COMPRESS_BYTES`]
+//!   are compressed with brotli or gzip, whichever the `Accept-Encoding`
+//!   header prefers (brotli on a tie).
+//!
+//! Router binaries add [`ResponseShaping`] as middleware; other HTTP handlers
+//! are wrapped in [`with_shaping`].
+
+use flate2::write::GzEncoder;
+use lambda_http::http::HeaderValue;
+use lambda_http::{Body, Request, RequestExt, Response};
+use serde_json::Value;
+use std::future::Future;
+use std::io::Write;
+
+use crate::router::{HandlerResult, Middleware, RequestInfo};
+
+/// Bodies smaller than this are sent uncompressed.
+pub const MIN_COMPRESS_BYTES: usize = 1024;
+
+/// brotli quality; higher levels cost more CPU than they save on the wire here.
+const BROTLI_QUALITY: u32 = 5;
+const BROTLI_WINDOW: u32 = 22;
+
+/// A `Content-Encoding` the API can produce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Brotli => "br",
+            Self::Gzip => "gzip",
+        }
+    }
+
+    /// Pick the encoding an `Accept-Encoding` header prefers, if it accepts either.
+    pub fn negotiate(accept_encoding: &str) -> Option<Self> {
+        let mut brotli = None;
+        let mut gzip = None;
+        let mut any = None;
+
+        for part in accept_encoding.split(',') {
+            let mut params = part.split(';');
+            let coding = params
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase();
+            let quality = params
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            match coding.as_str() {
+                "br" => brotli = Some(quality),
+                "gzip" | "x-gzip" => gzip = Some(quality),
+                "*" => any = Some(quality),
+                _ => {}
+            }
+        }
+
+        let brotli = brotli.or(any).unwrap_or(0.0);
+        let gzip = gzip.or(any).unwrap_or(0.0);
+        if brotli <= 0.0 && gzip <= 0.0 {
+            None
+        } else if brotli >= gzip {
+            Some(Self::Brotli)
+        } else {
+            Some(Self::Gzip)
+        }
+    }
+
+    fn compress(self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::Brotli => {
+                let mut out = Vec::new();
+                {
+                    let mut writer = brotli::CompressorWriter::new(
+                        &mut out,
+                        4096,
+                        BROTLI_QUALITY,
+                        BROTLI_WINDOW,
+                    );
+                    writer.write_all(data)?;
+                    writer.flush()?;
+                }
+                Ok(out)
+            }
+            Self::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::fast());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+/// Parse a `fields` query value into the keys to keep.
+pub fn parse_fields(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Keep only `fields` in the objects a response body returns.
+///
+/// Bodies in the [`ApiResponse`](crate::http::ApiResponse) envelope are
+/// trimmed inside `data`. A list is trimmed item by item, as is a
+/// [`Page`](crate::models::Page)'s `items`; any other object is trimmed itself.
+pub fn select_fields(body: &mut Value, fields: &[String]) {
+    let target = match body {
+        Value::Object(map) if map.contains_key("success") && map.contains_key("data") => {
+            map.get_mut("data").expect("checked above")
+        }
+        other => other,
+    };
+
+    match target {
+        Value::Array(items) => items.iter_mut().for_each(|item| retain(item, fields)),
+        Value::Object(map) => match map.get_mut("items") {
+            Some(Value::Array(items)) => items.iter_mut().for_each(|item| retain(item, fields)),
+            _ => retain(target, fields),
+        },
+        _ => {}
+    }
+}
+
+fn retain(value: &mut Value, fields: &[String]) {
+    if let Value::Object(map) = value {
+        map.retain(|key, _| fields.iter().any(|f| f == key));
+    }
+}
+
+/// Apply the sparse fieldset and compression a request asked for to its response.
+pub fn shape(accept_encoding: Option<&str>, fields: Option<&str>, response: &mut Response<Body>) {
+    if let Some(fields) = fields.map(parse_fields).filter(|f| !f.is_empty()) {
+        trim_response(&fields, response);
+    }
+    if let Some(encoding) = accept_encoding.and_then(Encoding::negotiate) {
+        compress_response(encoding, response);
+    }
+}
+
+fn content_type(response: &Response<Body>) -> &str {
+    response
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+}
+
+fn trim_response(fields: &[String], response: &mut Response<Body>) {
+    if !response.status().is_success() || !content_type(response).starts_with("application/json") {
+        return;
+    }
+    let Ok(mut body) = serde_json::from_slice::<Value>(response.body().as_ref()) else {
+        return;
+    };
+
+    select_fields(&mut body, fields);
+    if let Ok(json) = serde_json::to_string(&body) {
+        *response.body_mut() = Body::from(json);
+    }
+}
+
+fn compress_response(encoding: Encoding, response: &mut Response<Body>) {
+    let content_type = content_type(response);
+    let compressible = content_type.starts_with("application/json")
+        || content_type.starts_with("application/problem+json")
+        || content_type.starts_with("text/");
+    if !compressible
+        || response.headers().contains_key("content-encoding")
+        || response.body().as_ref().len() < MIN_COMPRESS_BYTES
+    {
+        return;
+    }
+
+    // The body depends on Accept-Encoding from here on, so caches must too
+    response
+        .headers_mut()
+        .append("vary", HeaderValue::from_static("Accept-Encoding"));
+
+    let Ok(compressed) = encoding.compress(response.body().as_ref()) else {
+        return;
+    };
+    if compressed.len() >= response.body().as_ref().len() {
+        return;
+    }
+
+    *response.body_mut() = Body::Binary(compressed);
+    let headers = response.headers_mut();
+    headers.insert(
+        "content-encoding",
+        HeaderValue::from_static(encoding.as_str()),
+    );
+    headers.remove("content-length");
+}
+
+/// Shapes every response as described in the module docs.
+pub struct ResponseShaping;
+
+impl Middleware for ResponseShaping {
+    fn after(&self, info: &RequestInfo, response: &mut Response<Body>) {
+        shape(
+            info.accept_encoding.as_deref(),
+            info.fields.as_deref(),
+            response,
+        );
+    }
+}
+
+/// The request's `Accept-Encoding` header.
+pub fn request_accept_encoding(req: &Request) -> Option<String> {
+    req.headers()
+        .get("accept-encoding")
+        .and_then(|v| v.to_str().ok())
+        .map(String::from)
+}
+
+/// The request's `fields` query parameter.
+pub fn request_fields(req: &Request) -> Option<String> {
+    req.query_string_parameters()
+        .first("fields")
+        .map(String::from)
+}
+
+/// Run an HTTP handler that doesn't use the router with its response shaped
+/// as the [`ResponseShaping`] middleware would.
+pub async fn with_shaping<F, Fut>(req: Request, handler: F) -> HandlerResult
+where
+    F: FnOnce(Request) -> Fut,
+    Fut: Future<Output = HandlerResult>,
+{
+    let accept_encoding = request_accept_encoding(&req);
+    let fields = request_fields(&req);
+
+    let mut response = handler(req).await?;
+    shape(accept_encoding.as_deref(), fields.as_deref(), &mut response);
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Read;
+
+    fn json_body(value: &Value) -> Response<Body> {
+        Response::builder()
+            .header("content-type", "application/json")
+            .body(Body::from(value.to_string()))
+            .unwrap()
+    }
+
+    #[test]
+    fn negotiates_preferred_encoding() {
+        assert_eq!(
+            Encoding::negotiate("gzip, deflate, br"),
+            Some(Encoding::Brotli)
+        );
+        assert_eq!(Encoding::negotiate("gzip"), Some(Encoding::Gzip));
+        assert_eq!(Encoding::negotiate("br;q=0.5, gzip"), Some(Encoding::Gzip));
+        assert_eq!(Encoding::negotiate("*"), Some(Encoding::Brotli));
+        assert_eq!(Encoding::negotiate("br;q=0, gzip;q=0"), None);
+        assert_eq!(Encoding::negotiate("identity"), None);
+    }
+
+    #[test]
+    fn trims_envelope_data_and_pages() {
+        let fields = parse_fields("id, name,");
+
+        let mut single = json!({"success": true, "data": {"id": 1, "name": "Ada", "notes": "…"}});
+        select_fields(&mut single, &fields);
+        assert_eq!(
+            single,
+            json!({"success": true, "data": {"id": 1, "name": "Ada"}})
+        );
+
+        let mut page = json!({"success": true, "data": {
+            "items": [{"id": 1, "name": "Ada", "notes": "…"}],
+            "next_cursor": null,
+            "has_more": false,
+        }});
+        select_fields(&mut page, &fields);
+        assert_eq!(page["data"]["items"], json!([{"id": 1, "name": "Ada"}]));
+        assert_eq!(page["data"]["has_more"], json!(false));
+
+        let mut list = json!([{"id": 1, "kind": "person"}]);
+        select_fields(&mut list, &fields);
+        assert_eq!(list, json!([{"id": 1}]));
+    }
+
+    #[test]
+    fn compresses_large_bodies_only() {
+        let items: Vec<Value> = (0..100)
+            .map(|i| json!({"id": i, "content": "the same fact, over and over"}))
+            .collect();
+        let body = json!({"success": true, "data": items});
+
+        let mut large = json_body(&body);
+        shape(Some("gzip"), None, &mut large);
+        assert_eq!(large.headers()["content-encoding"], "gzip");
+        assert_eq!(large.headers()["vary"], "Accept-Encoding");
+
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(large.body().as_ref())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&decoded).unwrap(), body);
+
+        let mut small = json_body(&json!({"success": true}));
+        shape(Some("gzip, br"), None, &mut small);
+        assert!(small.headers().get("content-encoding").is_none());
+    }
+
+    #[test]
+    fn leaves_errors_whole() {
+        let problem = json!({"title": "Not found", "status": 404});
+        let mut response = json_body(&problem);
+        *response.status_mut() = lambda_http::http::StatusCode::NOT_FOUND;
+
+        shape(None, Some("id"), &mut response);
+        assert_eq!(
+            serde_json::from_slice::<Value>(response.body().as_ref()).unwrap(),
+            problem
+        );
+    }
+}
@@ -0,0 +1,259 @@
+//! What relationships share, person by person.
+//!
+//! Relationship access is the `user_access_cache` row from a viewer to an
+//! owner: the viewer reads the owner's own facts and entities whose
+//! `visibility_tier` is no lower than the row's `access_tier` (see
+//! [`crate::access`]). Listing those rows from either end shows a user what
+//! they can see of each related person, and what each of them can see of
+//! theirs, including people reached through someone else. Family-owned rows
+//! aren't shared through relationships, so they aren't listed.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::access::{TIER_INTIMATE, TIER_RELATIONSHIPS};
+use crate::{Error, Result};
+
+/// Facts and entities listed for one person when none is asked for.
+pub const DEFAULT_ITEM_LIMIT: i64 = 100;
+
+/// Most facts and entities listed for one person.
+pub const MAX_ITEM_LIMIT: i64 = 500;
+
+/// Which end of the relationship the user is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Other people's data the user can see
+    WithMe,
+    /// The user's data other people can see
+    ByMe,
+}
+
+impl Direction {
+    /// `user_access_cache` columns holding the user and the other person.
+    fn columns(self) -> (&'static str, &'static str) {
+        match self {
+            Direction::WithMe => ("viewer_user_id", "target_user_id"),
+            Direction::ByMe => ("target_user_id", "viewer_user_id"),
+        }
+    }
+
+    /// Viewer and owner of the data shared between the user and `other`.
+    pub fn viewer_and_owner(self, user_id: Uuid, other: Uuid) -> (Uuid, Uuid) {
+        match self {
+            Direction::WithMe => (user_id, other),
+            Direction::ByMe => (other, user_id),
+        }
+    }
+}
+
+/// Someone the user shares with, in one direction
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedUser {
+    pub user_id: Uuid,
+    pub display_name: Option<String>,
+    pub email: Option<String>,
+    pub access_tier: i16,
+    /// 1 for a direct relationship, more when reached through other people
+    pub hop_count: i16,
+    /// The direct relationship granting the access, if there is one
+    pub relationship_id: Option<Uuid>,
+    pub relationship_type: Option<String>,
+    pub fact_count: i64,
+    pub entity_count: i64,
+}
+
+/// A shared fact
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedFact {
+    pub id: Uuid,
+    pub content: String,
+    pub visibility_tier: i16,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A shared entity
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedEntity {
+    pub id: Uuid,
+    pub entity_type: String,
+    pub name: String,
+    pub visibility_tier: i16,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What one owner shares at an access tier. The counts are totals; the lists
+/// stop at the limit asked for.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedItems {
+    pub access_tier: i16,
+    pub fact_count: i64,
+    pub entity_count: i64,
+    pub facts: Vec<SharedFact>,
+    pub entities: Vec<SharedEntity>,
+}
+
+/// Check an access tier given to preview what a viewer would see.
+pub fn validate_access_tier(access_tier: i16) -> Result<i16> {
+    if (TIER_INTIMATE..=TIER_RELATIONSHIPS).contains(&access_tier) {
+        Ok(access_tier)
+    } else {
+        Err(Error::Validation(format!(
+            "accessTier must be between {} and {}",
+            TIER_INTIMATE, TIER_RELATIONSHIPS
+        )))
+    }
+}
+
+/// Everyone sharing with the user (or shared with by them), closest first.
+pub async fn shared_users(
+    pool: &PgPool,
+    user_id: Uuid,
+    direction: Direction,
+) -> Result<Vec<SharedUser>> {
+    let (user_column, other_column) = direction.columns();
+
+    let users = sqlx::query_as(&format!(
+        r#"
+        SELECT uac.{other} AS user_id, u.display_name, u.email, uac.access_tier, uac.hop_count,
+               r.id AS relationship_id, r.relationship_type::text AS relationship_type,
+               (SELECT COUNT(*) FROM facts f
+                WHERE f.owner_type = 'user' AND f.owner_id = uac.target_user_id
+                  AND f.visibility_tier >= uac.access_tier AND f.deleted_at IS NULL) AS fact_count,
+               (SELECT COUNT(*) FROM entities e
+                WHERE e.owner_type = 'user' AND e.owner_id = uac.target_user_id
+                  AND e.visibility_tier >= uac.access_tier AND e.deleted_at IS NULL) AS entity_count
+        FROM user_access_cache uac
+        JOIN users u ON u.id = uac.{other}
+        LEFT JOIN relationships r ON uac.hop_count = 1 AND r.id = uac.relationship_path[1]
+        WHERE uac.{user} = $1
+        ORDER BY uac.access_tier, uac.hop_count, u.display_name
+        "#,
+        user = user_column,
+        other = other_column,
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(users)
+}
+
+/// The viewer's access tier to the owner's data, or `None` if they have none.
+pub async fn access_tier(pool: &PgPool, viewer_id: Uuid, owner_id: Uuid) -> Result<Option<i16>> {
+    let tier = sqlx::query_scalar(
+        "SELECT access_tier FROM user_access_cache WHERE viewer_user_id = $1 AND target_user_id = $2",
+    )
+    .bind(viewer_id)
+    .bind(owner_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(tier)
+}
+
+/// The owner's facts and entities a viewer with `access_tier` reads, most
+/// private first.
+pub async fn shared_items(
+    pool: &PgPool,
+    owner_id: Uuid,
+    access_tier: i16,
+    limit: i64,
+) -> Result<SharedItems> {
+    let (fact_count, entity_count): (i64, i64) = sqlx::query_as(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM facts
+             WHERE owner_type = 'user' AND owner_id = $1
+               AND visibility_tier >= $2 AND deleted_at IS NULL),
+            (SELECT COUNT(*) FROM entities
+             WHERE owner_type = 'user' AND owner_id = $1
+               AND visibility_tier >= $2 AND deleted_at IS NULL)
+        "#,
+    )
+    .bind(owner_id)
+    .bind(access_tier)
+    .fetch_one(pool)
+    .await?;
+
+    let facts = sqlx::query_as(
+        r#"
+        SELECT id, content, visibility_tier, created_at
+        FROM facts
+        WHERE owner_type = 'user' AND owner_id = $1
+          AND visibility_tier >= $2 AND deleted_at IS NULL
+        ORDER BY visibility_tier, created_at DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(owner_id)
+    .bind(access_tier)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    let entities = sqlx::query_as(
+        r#"
+        SELECT id, entity_type::text AS entity_type, name, visibility_tier, created_at
+        FROM entities
+        WHERE owner_type = 'user' AND owner_id = $1
+          AND visibility_tier >= $2 AND deleted_at IS NULL
+        ORDER BY visibility_tier, name
+        LIMIT $3
+        "#,
+    )
+    .bind(owner_id)
+    .bind(access_tier)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(SharedItems {
+        access_tier,
+        fact_count,
+        entity_count,
+        facts,
+        entities,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_direction_columns() {
+        assert_eq!(
+            Direction::WithMe.columns(),
+            ("viewer_user_id", "target_user_id")
+        );
+        assert_eq!(
+            Direction::ByMe.columns(),
+            ("target_user_id", "viewer_user_id")
+        );
+    }
+
+    #[test]
+    fn test_viewer_and_owner() {
+        let me = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        assert_eq!(Direction::WithMe.viewer_and_owner(me, other), (me, other));
+        assert_eq!(Direction::ByMe.viewer_and_owner(me, other), (other, me));
+    }
+
+    #[test]
+    fn test_validate_access_tier() {
+        for tier in 1..=4 {
+            assert_eq!(validate_access_tier(tier).unwrap(), tier);
+        }
+        assert!(validate_access_tier(0).is_err());
+        assert!(validate_access_tier(5).is_err());
+    }
+}
@@ -0,0 +1,384 @@
+//! SMS ingestion and query.
+//!
+//! A signed-in user registers a phone number in the app and confirms it with a
+//! one-time code texted to it; inbound texts from a verified number are then
+//! routed to the agent as `remember ...` (ingest) or a question (query), and
+//! answered by SMS.
+
+use aws_sdk_sns::types::MessageAttributeValue;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::codes::random_code;
+use crate::markdown::{heading, list_item, rewrite_links, truncate, FENCE};
+use crate::{Error, Result};
+
+/// How long a verification code can be used.
+pub const VERIFICATION_CODE_TTL_MINUTES: i32 = 10;
+
+/// Wrong guesses allowed before a code stops working.
+pub const MAX_VERIFICATION_ATTEMPTS: i16 = 5;
+
+/// Codes that can be sent to a user per hour (each one is a paid SMS).
+pub const MAX_CODES_PER_HOUR: i64 = 5;
+
+/// Longest message SNS delivers (split into segments by the carrier).
+pub const MAX_SMS_CHARS: usize = 1600;
+
+/// Digits in a verification code.
+const CODE_LEN: usize = 6;
+
+/// What an inbound text asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmsCommand<'a> {
+    Ask(&'a str),
+    Remember(&'a str),
+    Help,
+}
+
+/// Reply to `help` and empty texts
+pub const HELP_TEXT: &str = "Second Brain: text \"remember <fact>\" to save something, \
+or ask a question. Reply STOP to opt out.";
+
+/// Canonical E.164 form of a user-entered phone number, or `None` if it can't
+/// be one.
+///
+/// Spaces, dashes, dots and parentheses are ignored. Numbers without a country
+/// code are assumed to be North American (`+1`).
+pub fn normalize_phone(input: &str) -> Option<String> {
+    let trimmed = input.trim();
+    let (international, rest) = match trimmed.strip_prefix('+') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed),
+    };
+
+    let mut digits = String::with_capacity(rest.len());
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => digits.push(c),
+            ' ' | '-' | '.' | '(' | ')' => {}
+            _ => return None,
+        }
+    }
+
+    if international {
+        let valid = (8..=15).contains(&digits.len()) && !digits.starts_with('0');
+        return valid.then(|| format!("+{}", digits));
+    }
+
+    match digits.len() {
+        10 => Some(format!("+1{}", digits)),
+        11 if digits.starts_with('1') => Some(format!("+{}", digits)),
+        _ => None,
+    }
+}
+
+/// Generate a six-digit verification code.
+pub fn generate_verification_code() -> String {
+    random_code(CODE_LEN, b"0123456789")
+}
+
+/// Canonical form of a user-entered code, or `None` if it can't be a valid code.
+fn normalize_code(input: &str) -> Option<String> {
+    let code: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+    let valid = code.len() == CODE_LEN && code.bytes().all(|b| b.is_ascii_digit());
+    valid.then_some(code)
+}
+
+/// Issue a verification code for a phone number, retiring any unused ones.
+///
+/// Returns the code and when it expires, or `None` if the user has asked for
+/// too many codes in the last hour.
+pub async fn create_verification(
+    pool: &PgPool,
+    user_id: Uuid,
+    phone_number: &str,
+) -> Result<Option<(String, DateTime<Utc>)>> {
+    let code = generate_verification_code();
+
+    let mut tx = pool.begin().await?;
+
+    let recent: i64 = sqlx::query_scalar(
+        r#"
+        SELECT COUNT(*) FROM phone_verification_codes
+        WHERE user_id = $1 AND created_at > NOW() - INTERVAL '1 hour'
+        "#,
+    )
+    .bind(user_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if recent >= MAX_CODES_PER_HOUR {
+        return Ok(None);
+    }
+
+    // Expire rather than delete, so retired codes still count toward the limit
+    sqlx::query(
+        r#"
+        UPDATE phone_verification_codes SET expires_at = NOW()
+        WHERE user_id = $1 AND consumed_at IS NULL AND expires_at > NOW()
+        "#,
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    let expires_at = sqlx::query_scalar(
+        r#"
+        INSERT INTO phone_verification_codes (user_id, phone_number, code_hash, expires_at)
+        VALUES ($1, $2, sha256(convert_to($3, 'UTF8')), NOW() + make_interval(mins => $4))
+        RETURNING expires_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(phone_number)
+    .bind(&code)
+    .bind(VERIFICATION_CODE_TTL_MINUTES)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Some((code, expires_at)))
+}
+
+/// Check a code against the user's pending verification, returning the
+/// verified phone number.
+///
+/// Returns `None` for wrong, expired or exhausted codes; every wrong guess
+/// counts toward [`MAX_VERIFICATION_ATTEMPTS`]. A number verified by another
+/// user is moved to this one.
+pub async fn verify_code(pool: &PgPool, user_id: Uuid, code: &str) -> Result<Option<String>> {
+    let code = match normalize_code(code) {
+        Some(code) => code,
+        None => return Ok(None),
+    };
+
+    let mut tx = pool.begin().await?;
+
+    let pending: Option<(Uuid, String, bool)> = sqlx::query_as(
+        r#"
+        UPDATE phone_verification_codes
+        SET attempts = attempts + 1
+        WHERE id = (
+            SELECT id FROM phone_verification_codes
+            WHERE user_id = $1
+            AND consumed_at IS NULL
+            AND expires_at > NOW()
+            AND attempts < $2
+            ORDER BY created_at DESC
+            LIMIT 1
+            FOR UPDATE
+        )
+        RETURNING id, phone_number, code_hash = sha256(convert_to($3, 'UTF8'))
+        "#,
+    )
+    .bind(user_id)
+    .bind(MAX_VERIFICATION_ATTEMPTS)
+    .bind(&code)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let (code_id, phone_number) = match pending {
+        Some((code_id, phone_number, true)) => (code_id, phone_number),
+        _ => {
+            // Keep the counted attempt
+            tx.commit().await?;
+            return Ok(None);
+        }
+    };
+
+    sqlx::query("UPDATE phone_verification_codes SET consumed_at = NOW() WHERE id = $1")
+        .bind(code_id)
+        .execute(&mut *tx)
+        .await?;
+
+    // phone_number is unique, so release it from any previous user first
+    sqlx::query(
+        "UPDATE users SET phone_number = NULL, phone_verified_at = NULL WHERE phone_number = $1 AND id <> $2",
+    )
+    .bind(&phone_number)
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("UPDATE users SET phone_number = $1, phone_verified_at = NOW() WHERE id = $2")
+        .bind(&phone_number)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(Some(phone_number))
+}
+
+/// Remove a user's phone number. Returns whether one existed.
+pub async fn unlink(pool: &PgPool, user_id: Uuid) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        UPDATE users
+        SET phone_number = NULL, phone_verified_at = NULL
+        WHERE id = $1 AND phone_number IS NOT NULL
+        "#,
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Send a transactional SMS through SNS.
+///
+/// `origination_number` replies from the number a text was sent to (a
+/// two-way number), so the conversation stays in one thread.
+pub async fn send_sms(
+    sns_client: &aws_sdk_sns::Client,
+    phone_number: &str,
+    origination_number: Option<&str>,
+    message: &str,
+) -> Result<()> {
+    let attribute = |value: &str| {
+        MessageAttributeValue::builder()
+            .data_type("String")
+            .string_value(value)
+            .build()
+            .map_err(|e| Error::Aws(format!("Invalid SMS attribute: {}", e)))
+    };
+
+    let mut request = sns_client
+        .publish()
+        .phone_number(phone_number)
+        .message(message)
+        .message_attributes("AWS.SNS.SMS.SMSType", attribute("Transactional")?);
+
+    if let Some(number) = origination_number {
+        request = request.message_attributes("AWS.MM.SMS.OriginationNumber", attribute(number)?);
+    }
+
+    request
+        .send()
+        .await
+        .map_err(|e| Error::Aws(format!("Failed to send SMS: {}", e)))?;
+
+    Ok(())
+}
+
+/// Parse an inbound text.
+///
+/// `remember <fact>` (or `save`/`note`) saves; `help` or an empty text asks for
+/// usage; anything else is a question.
+pub fn parse_message(text: &str) -> SmsCommand<'_> {
+    let text = text.trim();
+    let (verb, rest) = match text.split_once(char::is_whitespace) {
+        Some((verb, rest)) => (verb, rest.trim()),
+        None => (text, ""),
+    };
+
+    match verb.trim_end_matches(':').to_lowercase().as_str() {
+        "" | "help" | "?" => SmsCommand::Help,
+        "remember" | "save" | "note" if rest.is_empty() => SmsCommand::Help,
+        "remember" | "save" | "note" => SmsCommand::Remember(rest),
+        _ => SmsCommand::Ask(text),
+    }
+}
+
+/// Render an agent's Markdown answer as a plain-text SMS within [`MAX_SMS_CHARS`].
+///
+/// Headings and emphasis markers are dropped, list items become bullets and
+/// links become `label (url)`.
+pub fn to_sms_text(text: &str) -> String {
+    let plain = text
+        .lines()
+        .filter(|line| !line.trim_start().starts_with(FENCE))
+        .map(|line| {
+            let line = line.trim_start();
+            let line = heading(line).map_or(line, |(_, text)| text);
+            let line = match list_item(line) {
+                Some(item) => format!("• {}", item),
+                None => line.to_string(),
+            };
+            rewrite_links(
+                &line.replace("**", "").replace("__", "").replace('`', ""),
+                |label, url| format!("{} ({})", label, url),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    truncate(plain.trim(), MAX_SMS_CHARS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_phone() {
+        assert_eq!(
+            normalize_phone("+1 (415) 555-0123").as_deref(),
+            Some("+14155550123")
+        );
+        assert_eq!(
+            normalize_phone("415.555.0123").as_deref(),
+            Some("+14155550123")
+        );
+        assert_eq!(
+            normalize_phone("1 415 555 0123").as_deref(),
+            Some("+14155550123")
+        );
+        assert_eq!(
+            normalize_phone("+44 20 7946 0958").as_deref(),
+            Some("+442079460958")
+        );
+        assert_eq!(normalize_phone("555-0123"), None);
+        assert_eq!(normalize_phone("+0 123 456 789"), None);
+        assert_eq!(normalize_phone("+1 415 555 0123 ext 4"), None);
+        assert_eq!(normalize_phone("+1234567890123456"), None);
+    }
+
+    #[test]
+    fn test_generate_verification_code() {
+        let code = generate_verification_code();
+        assert_eq!(code.len(), CODE_LEN);
+        assert_eq!(normalize_code(&code), Some(code));
+    }
+
+    #[test]
+    fn test_normalize_code() {
+        assert_eq!(normalize_code(" 012 345 ").as_deref(), Some("012345"));
+        assert_eq!(normalize_code("12345"), None);
+        assert_eq!(normalize_code("12345a"), None);
+    }
+
+    #[test]
+    fn test_parse_message() {
+        assert_eq!(
+            parse_message("Remember: the garage code is 4821"),
+            SmsCommand::Remember("the garage code is 4821")
+        );
+        assert_eq!(parse_message("save  x"), SmsCommand::Remember("x"));
+        assert_eq!(
+            parse_message("When is Emma's recital?"),
+            SmsCommand::Ask("When is Emma's recital?")
+        );
+        assert_eq!(parse_message(" "), SmsCommand::Help);
+        assert_eq!(parse_message("HELP"), SmsCommand::Help);
+        assert_eq!(parse_message("remember"), SmsCommand::Help);
+    }
+
+    #[test]
+    fn test_to_sms_text() {
+        assert_eq!(
+            to_sms_text("## Friday\n- **Recital** at 6\n* See [the map](https://example.com)"),
+            "Friday\n• Recital at 6\n• See the map (https://example.com)"
+        );
+        assert_eq!(to_sms_text("```\ncode\n```"), "code");
+
+        let long = to_sms_text(&"word ".repeat(1000));
+        assert_eq!(long.chars().count(), MAX_SMS_CHARS);
+        assert!(long.ends_with('…'));
+    }
+}
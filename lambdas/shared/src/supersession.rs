@@ -0,0 +1,263 @@
+//! Fact supersession.
+//!
+//! When a newly ingested fact restates an attribute of an entity the user
+//! already recorded ("Mom lives at 40 Pine Ave" after "Mom lives at 12 Oak
+//! St"), the older fact is closed off: its `valid_to` is set to when the new
+//! one takes effect and `superseded_by` points at the new fact. Candidates are
+//! the entity's current facts whose embeddings are close to the new fact's;
+//! temporal attributes then decide whether the new fact can replace them.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::embeddings::to_pgvector;
+use crate::tag_suggestions::fact_embedding;
+use crate::{EmbeddingClient, Result};
+
+/// Cosine similarity at which two facts about one entity are taken to
+/// describe the same attribute
+pub const SUPERSEDE_SIMILARITY: f64 = 0.82;
+
+/// Closest candidates considered per new fact
+const MAX_CANDIDATES: i64 = 5;
+
+/// When a fact is true
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Validity {
+    pub valid_from: Option<NaiveDate>,
+    pub valid_to: Option<NaiveDate>,
+    /// Stands in for `valid_from` when it isn't set
+    pub recorded_on: NaiveDate,
+}
+
+impl Validity {
+    fn starts(&self) -> NaiveDate {
+        self.valid_from.unwrap_or(self.recorded_on)
+    }
+}
+
+/// The date `new` would close `old` off, if it can supersede it: the new fact
+/// must be open-ended (a bounded fact like a trip is temporary and replaces
+/// nothing), and the old one must have started by then and still be true.
+pub fn supersedes_on(old: &Validity, new: &Validity) -> Option<NaiveDate> {
+    if new.valid_to.is_some() {
+        return None;
+    }
+
+    let effective = new.starts();
+    if old.starts() > effective {
+        return None;
+    }
+    if old.valid_to.is_some_and(|to| to <= effective) {
+        return None;
+    }
+
+    Some(effective)
+}
+
+/// A fact closed off by a newer one
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Supersession {
+    pub superseded_fact_id: Uuid,
+    pub valid_to: NaiveDate,
+    pub similarity: f64,
+}
+
+#[derive(sqlx::FromRow)]
+struct NewFact {
+    owner_type: String,
+    owner_id: Uuid,
+    about_entity_id: Option<Uuid>,
+    content: String,
+    content_normalized: String,
+    valid_from: Option<NaiveDate>,
+    valid_to: Option<NaiveDate>,
+    recorded_at: DateTime<Utc>,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(sqlx::FromRow)]
+struct Candidate {
+    id: Uuid,
+    valid_from: Option<NaiveDate>,
+    valid_to: Option<NaiveDate>,
+    recorded_at: DateTime<Utc>,
+    similarity: f64,
+}
+
+/// Check a newly ingested fact against older facts about the same entity
+/// and close off the one it replaces, if any.
+pub async fn detect_supersession(
+    pool: &sqlx::PgPool,
+    embedding_client: &EmbeddingClient,
+    fact_id: Uuid,
+) -> Result<Option<Supersession>> {
+    let fact: Option<NewFact> = sqlx::query_as(
+        r#"
+        SELECT owner_type, owner_id, about_entity_id, content, content_normalized,
+               valid_from, valid_to, recorded_at, created_at
+        FROM facts
+        WHERE id = $1 AND superseded_by IS NULL
+        "#,
+    )
+    .bind(fact_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(fact) = fact else {
+        return Ok(None);
+    };
+    let Some(entity_id) = fact.about_entity_id else {
+        return Ok(None);
+    };
+    let new = Validity {
+        valid_from: fact.valid_from,
+        valid_to: fact.valid_to,
+        recorded_on: fact.recorded_at.date_naive(),
+    };
+    if new.valid_to.is_some() {
+        return Ok(None);
+    }
+
+    // New facts usually haven't been indexed yet
+    let (embedding, model_id) = match fact_embedding(pool, fact_id).await? {
+        Some(stored) => stored,
+        None => {
+            let vector = embedding_client.embed(&fact.content).await?;
+            (
+                to_pgvector(&vector),
+                embedding_client.model_id().to_string(),
+            )
+        }
+    };
+
+    let candidates: Vec<Candidate> = sqlx::query_as(
+        r#"
+        SELECT f.id, f.valid_from, f.valid_to, f.recorded_at,
+               (1 - (fe.embedding <=> $2::vector))::float8 AS similarity
+        FROM facts f
+        JOIN fact_embeddings fe ON fe.fact_id = f.id
+        WHERE f.about_entity_id = $1
+        AND f.owner_type = $4 AND f.owner_id = $5
+        AND f.id != $6
+        AND f.superseded_by IS NULL
+        AND f.deleted_at IS NULL
+        AND f.created_at <= $7
+        AND f.content_normalized != $8
+        AND fe.model_id = $3
+        AND 1 - (fe.embedding <=> $2::vector) >= $9
+        ORDER BY fe.embedding <=> $2::vector
+        LIMIT $10
+        "#,
+    )
+    .bind(entity_id)
+    .bind(&embedding)
+    .bind(&model_id)
+    .bind(&fact.owner_type)
+    .bind(fact.owner_id)
+    .bind(fact_id)
+    .bind(fact.created_at)
+    .bind(&fact.content_normalized)
+    .bind(SUPERSEDE_SIMILARITY)
+    .bind(MAX_CANDIDATES)
+    .fetch_all(pool)
+    .await?;
+
+    for candidate in candidates {
+        let old = Validity {
+            valid_from: candidate.valid_from,
+            valid_to: candidate.valid_to,
+            recorded_on: candidate.recorded_at.date_naive(),
+        };
+        let Some(valid_to) = supersedes_on(&old, &new) else {
+            continue;
+        };
+
+        let updated = sqlx::query(
+            r#"
+            UPDATE facts
+            SET valid_to = $2, superseded_by = $3, updated_at = NOW()
+            WHERE id = $1 AND superseded_by IS NULL
+            "#,
+        )
+        .bind(candidate.id)
+        .bind(valid_to)
+        .bind(fact_id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+
+        if updated > 0 {
+            return Ok(Some(Supersession {
+                superseded_fact_id: candidate.id,
+                valid_to,
+                similarity: candidate.similarity,
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+    }
+
+    fn validity(
+        valid_from: Option<NaiveDate>,
+        valid_to: Option<NaiveDate>,
+        recorded_on: NaiveDate,
+    ) -> Validity {
+        Validity {
+            valid_from,
+            valid_to,
+            recorded_on,
+        }
+    }
+
+    #[test]
+    fn open_ended_fact_supersedes_current_one() {
+        let old = validity(None, None, date(1, 10));
+        let new = validity(None, None, date(6, 1));
+        assert_eq!(supersedes_on(&old, &new), Some(date(6, 1)));
+
+        // Takes effect from the new fact's own start date
+        let new = validity(Some(date(5, 1)), None, date(6, 1));
+        assert_eq!(supersedes_on(&old, &new), Some(date(5, 1)));
+
+        // A correction recorded the same day still applies
+        let old = validity(None, None, date(6, 1));
+        let new = validity(None, None, date(6, 1));
+        assert_eq!(supersedes_on(&old, &new), Some(date(6, 1)));
+    }
+
+    #[test]
+    fn bounded_facts_supersede_nothing() {
+        let old = validity(None, None, date(1, 10));
+        let trip = validity(Some(date(6, 1)), Some(date(6, 7)), date(5, 20));
+        assert_eq!(supersedes_on(&old, &trip), None);
+    }
+
+    #[test]
+    fn old_fact_must_still_be_true() {
+        let new = validity(Some(date(6, 1)), None, date(6, 1));
+
+        // Already ended
+        let ended = validity(Some(date(1, 1)), Some(date(3, 1)), date(1, 1));
+        assert_eq!(supersedes_on(&ended, &new), None);
+
+        // Starts after the new fact does
+        let later = validity(Some(date(7, 1)), None, date(5, 1));
+        assert_eq!(supersedes_on(&later, &new), None);
+
+        // Scheduled to end after the new fact takes effect
+        let ending = validity(Some(date(1, 1)), Some(date(9, 1)), date(1, 1));
+        assert_eq!(supersedes_on(&ending, &new), Some(date(6, 1)));
+    }
+}
@@ -0,0 +1,351 @@
+//! Auto-tagging rules.
+//!
+//! Users define rules like "content contains 'invoice' → finance/bills" via
+//! `/tags/rules`. Rules are evaluated here, both for single facts as they are
+//! ingested and in batches by the `tag_rule_backfill` Lambda, which applies
+//! each rule to the facts created since it last ran. Tags applied by rules
+//! are stored with `assigned_by = 'rule'`.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{Error, Result};
+
+/// Values of the `entity_type` enum
+pub const ENTITY_TYPES: [&str; 8] = [
+    "person",
+    "organization",
+    "place",
+    "project",
+    "event",
+    "product",
+    "custom",
+    "bookmark",
+];
+
+/// Values of the `fact_source` enum
+pub const FACT_SOURCES: [&str; 5] = ["voice", "text", "import", "calendar", "inferred"];
+
+/// Most keywords a rule may have
+pub const MAX_KEYWORDS: usize = 20;
+
+/// An enabled rule, as evaluated
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct TagRule {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub tag_id: Uuid,
+    /// Lowercased
+    pub keywords: Vec<String>,
+    pub entity_type: Option<String>,
+    pub source: Option<String>,
+    pub confidence: f64,
+}
+
+/// The parts of a fact rules look at
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct RuleFact {
+    pub id: Uuid,
+    pub content: String,
+    /// Type of the entity the fact is about
+    pub entity_type: Option<String>,
+    pub source: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TagRule {
+    /// Whether every condition the rule sets matches the fact.
+    pub fn matches(&self, fact: &RuleFact) -> bool {
+        if let Some(entity_type) = &self.entity_type {
+            if fact.entity_type.as_deref() != Some(entity_type.as_str()) {
+                return false;
+            }
+        }
+        if let Some(source) = &self.source {
+            if fact.source != *source {
+                return false;
+            }
+        }
+        if !self.keywords.is_empty() {
+            let content = fact.content.to_lowercase();
+            return self.keywords.iter().any(|k| content.contains(k.as_str()));
+        }
+        true
+    }
+}
+
+/// Validated rule conditions from user input
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleConditions {
+    pub keywords: Vec<String>,
+    pub entity_type: Option<String>,
+    pub source: Option<String>,
+}
+
+impl RuleConditions {
+    /// Normalize and validate conditions; at least one is required.
+    pub fn parse(
+        keywords: Option<Vec<String>>,
+        entity_type: Option<String>,
+        source: Option<String>,
+    ) -> Result<Self> {
+        let mut normalized: Vec<String> = Vec::new();
+        for keyword in keywords.unwrap_or_default() {
+            let keyword = keyword.trim().to_lowercase();
+            if !keyword.is_empty() && !normalized.contains(&keyword) {
+                normalized.push(keyword);
+            }
+        }
+        if normalized.len() > MAX_KEYWORDS {
+            return Err(Error::Validation(format!(
+                "A rule can have at most {} keywords",
+                MAX_KEYWORDS
+            )));
+        }
+
+        let entity_type = entity_type.map(|t| t.trim().to_lowercase());
+        if let Some(t) = &entity_type {
+            if !ENTITY_TYPES.contains(&t.as_str()) {
+                return Err(Error::Validation(format!(
+                    "entity_type must be one of: {}",
+                    ENTITY_TYPES.join(", ")
+                )));
+            }
+        }
+
+        let source = source.map(|s| s.trim().to_lowercase());
+        if let Some(s) = &source {
+            if !FACT_SOURCES.contains(&s.as_str()) {
+                return Err(Error::Validation(format!(
+                    "source must be one of: {}",
+                    FACT_SOURCES.join(", ")
+                )));
+            }
+        }
+
+        if normalized.is_empty() && entity_type.is_none() && source.is_none() {
+            return Err(Error::Validation(
+                "A rule needs keywords, an entity_type or a source".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            keywords: normalized,
+            entity_type,
+            source,
+        })
+    }
+}
+
+/// Rules applied to a fact
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppliedRules {
+    pub rule_ids: Vec<Uuid>,
+}
+
+/// Enabled rules of a user.
+pub async fn user_rules(pool: &sqlx::PgPool, user_id: Uuid) -> Result<Vec<TagRule>> {
+    let rules = sqlx::query_as(
+        r#"
+        SELECT r.id, r.user_id, r.tag_id, r.keywords, r.entity_type::text AS entity_type,
+               r.source::text AS source, r.confidence::float8 AS confidence
+        FROM tag_rules r
+        JOIN tags t ON t.id = r.tag_id
+        WHERE r.user_id = $1 AND r.enabled
+        AND t.deleted_at IS NULL
+        ORDER BY r.created_at
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rules)
+}
+
+/// Tag `facts` with `rule`'s tag where the rule matches, returning how many
+/// tags were newly applied.
+pub async fn apply_rule(pool: &sqlx::PgPool, rule: &TagRule, facts: &[RuleFact]) -> Result<u64> {
+    let fact_ids: Vec<Uuid> = facts
+        .iter()
+        .filter(|f| rule.matches(f))
+        .map(|f| f.id)
+        .collect();
+    if fact_ids.is_empty() {
+        return Ok(0);
+    }
+
+    let applied = sqlx::query(
+        r#"
+        INSERT INTO fact_tags (fact_id, tag_id, confidence, assigned_by)
+        SELECT fact_id, $2, $3, 'rule' FROM UNNEST($1::uuid[]) AS fact_id
+        ON CONFLICT (fact_id, tag_id) DO NOTHING
+        "#,
+    )
+    .bind(&fact_ids)
+    .bind(rule.tag_id)
+    .bind(rule.confidence)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    if applied > 0 {
+        sqlx::query("UPDATE tag_rules SET applied_count = applied_count + $2 WHERE id = $1")
+            .bind(rule.id)
+            .bind(applied as i32)
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(applied)
+}
+
+/// Evaluate the creator's rules against a newly ingested (or edited) fact.
+pub async fn apply_tag_rules(pool: &sqlx::PgPool, fact_id: Uuid) -> Result<AppliedRules> {
+    let fact = sqlx::query_as::<_, (Uuid, String, Option<String>, String, DateTime<Utc>, Uuid)>(
+        r#"
+        SELECT f.id, f.content, e.entity_type::text, f.source::text, f.created_at, f.created_by
+        FROM facts f
+        LEFT JOIN entities e ON e.id = f.about_entity_id
+        WHERE f.id = $1 AND f.deleted_at IS NULL
+        "#,
+    )
+    .bind(fact_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let Some((id, content, entity_type, source, created_at, created_by)) = fact else {
+        return Ok(AppliedRules::default());
+    };
+    let fact = RuleFact {
+        id,
+        content,
+        entity_type,
+        source,
+        created_at,
+    };
+
+    let mut applied = AppliedRules::default();
+    for rule in user_rules(pool, created_by).await? {
+        if rule.matches(&fact) {
+            apply_rule(pool, &rule, std::slice::from_ref(&fact)).await?;
+            applied.rule_ids.push(rule.id);
+        }
+    }
+
+    Ok(applied)
+}
+
+/// A page of a user's facts created after `(after, after_id)` and no later
+/// than `until`, in creation order.
+pub async fn facts_page(
+    pool: &sqlx::PgPool,
+    user_id: Uuid,
+    after: Option<(DateTime<Utc>, Uuid)>,
+    until: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<RuleFact>> {
+    let (after_at, after_id) = match after {
+        Some((at, id)) => (Some(at), Some(id)),
+        None => (None, None),
+    };
+
+    let facts = sqlx::query_as(
+        r#"
+        SELECT f.id, f.content, e.entity_type::text AS entity_type,
+               f.source::text AS source, f.created_at
+        FROM facts f
+        LEFT JOIN entities e ON e.id = f.about_entity_id
+        WHERE f.created_by = $1
+        AND f.deleted_at IS NULL
+        AND f.created_at <= $2
+        AND ($3::timestamptz IS NULL OR (f.created_at, f.id) > ($3, $4))
+        ORDER BY f.created_at, f.id
+        LIMIT $5
+        "#,
+    )
+    .bind(user_id)
+    .bind(until)
+    .bind(after_at)
+    .bind(after_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(facts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(keywords: &[&str], entity_type: Option<&str>, source: Option<&str>) -> TagRule {
+        TagRule {
+            id: Uuid::nil(),
+            user_id: Uuid::nil(),
+            tag_id: Uuid::nil(),
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            entity_type: entity_type.map(str::to_string),
+            source: source.map(str::to_string),
+            confidence: 0.9,
+        }
+    }
+
+    fn fact(content: &str, entity_type: Option<&str>, source: &str) -> RuleFact {
+        RuleFact {
+            id: Uuid::nil(),
+            content: content.to_string(),
+            entity_type: entity_type.map(str::to_string),
+            source: source.to_string(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn keywords_match_case_insensitively() {
+        let invoices = rule(&["invoice", "receipt"], None, None);
+        assert!(invoices.matches(&fact("Paid the Invoice from ACME", None, "text")));
+        assert!(invoices.matches(&fact("receipt for the couch", None, "voice")));
+        assert!(!invoices.matches(&fact("Dinner with Sam", None, "text")));
+    }
+
+    #[test]
+    fn all_set_conditions_must_match() {
+        let r = rule(&["birthday"], Some("person"), Some("calendar"));
+        assert!(r.matches(&fact("Ann's birthday party", Some("person"), "calendar")));
+        assert!(!r.matches(&fact("Ann's birthday party", Some("person"), "text")));
+        assert!(!r.matches(&fact("Ann's birthday party", None, "calendar")));
+        assert!(!r.matches(&fact("Lunch with Ann", Some("person"), "calendar")));
+
+        let places = rule(&[], Some("place"), None);
+        assert!(places.matches(&fact("Anything at all", Some("place"), "text")));
+    }
+
+    #[test]
+    fn parses_conditions() {
+        let conditions = RuleConditions::parse(
+            Some(vec![" Invoice ".into(), "invoice".into(), "".into()]),
+            Some("Person".into()),
+            None,
+        )
+        .unwrap();
+        assert_eq!(conditions.keywords, vec!["invoice"]);
+        assert_eq!(conditions.entity_type.as_deref(), Some("person"));
+
+        assert!(matches!(
+            RuleConditions::parse(Some(vec!["  ".into()]), None, None),
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            RuleConditions::parse(None, Some("animal".into()), None),
+            Err(Error::Validation(_))
+        ));
+        assert!(matches!(
+            RuleConditions::parse(None, None, Some("fax".into())),
+            Err(Error::Validation(_))
+        ));
+        assert!(RuleConditions::parse(None, None, Some("calendar".into())).is_ok());
+    }
+}
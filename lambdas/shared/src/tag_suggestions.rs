@@ -0,0 +1,145 @@
+//! Embedding-based tag suggestions.
+//!
+//! Each tag's centroid is the mean embedding of the facts already carrying
+//! it. A fact (or draft content) is compared against every centroid the user
+//! can see, and the closest tags are suggested with a confidence derived from
+//! the cosine similarity and how many facts back the centroid.
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::access::space_clause;
+use crate::Result;
+
+/// Tags need this many embedded facts before their centroid is trusted
+pub const MIN_TAGGED_FACTS: i64 = 2;
+
+/// Cosine similarity at which a suggestion has zero confidence
+pub const MIN_SIMILARITY: f64 = 0.3;
+
+/// Tagged facts at which support counts for half the confidence penalty
+const SUPPORT_HALF_POINT: f64 = 2.0;
+
+/// A tag suggested from its centroid
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct CentroidSuggestion {
+    pub tag_id: Uuid,
+    pub path: String,
+    pub name: String,
+    /// Facts whose embeddings make up the centroid
+    pub fact_count: i64,
+    /// Cosine similarity to the centroid
+    pub similarity: f64,
+    #[sqlx(skip)]
+    pub confidence: f64,
+}
+
+/// Confidence for a centroid match: similarity rescaled so `MIN_SIMILARITY`
+/// is 0 and 1.0 is 1, discounted for centroids backed by few facts.
+pub fn centroid_confidence(similarity: f64, fact_count: i64) -> f64 {
+    let scaled = ((similarity - MIN_SIMILARITY) / (1.0 - MIN_SIMILARITY)).clamp(0.0, 1.0);
+    let count = fact_count.max(0) as f64;
+    let support = count / (count + SUPPORT_HALF_POINT);
+    ((scaled * support) * 100.0).round() / 100.0
+}
+
+/// Stored embedding (as a pgvector literal) and its model for a fact.
+pub async fn fact_embedding(
+    pool: &sqlx::PgPool,
+    fact_id: Uuid,
+) -> Result<Option<(String, String)>> {
+    let embedding =
+        sqlx::query_as("SELECT embedding::text, model_id FROM fact_embeddings WHERE fact_id = $1")
+            .bind(fact_id)
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(embedding)
+}
+
+/// Tags whose centroids are closest to `embedding` (a pgvector literal),
+/// best first. Centroids only use embeddings from the same model, facts the
+/// user can see, and never `exclude_fact_id` or tags it already carries.
+pub async fn suggest_by_centroid(
+    pool: &sqlx::PgPool,
+    embedding: &str,
+    model_id: &str,
+    user_id: Uuid,
+    family_ids: &[Uuid],
+    exclude_fact_id: Option<Uuid>,
+    limit: i64,
+) -> Result<Vec<CentroidSuggestion>> {
+    let mut suggestions: Vec<CentroidSuggestion> = sqlx::query_as(&format!(
+        r#"
+        WITH centroids AS (
+            SELECT ft.tag_id, AVG(fe.embedding) AS centroid, COUNT(*) AS fact_count
+            FROM fact_tags ft
+            JOIN facts f ON f.id = ft.fact_id
+            JOIN fact_embeddings fe ON fe.fact_id = ft.fact_id
+            WHERE fe.model_id = $2
+            AND f.deleted_at IS NULL
+            AND ((f.owner_type = 'user' AND f.owner_id = $3)
+                 OR (f.owner_type = 'family' AND f.owner_id = ANY($4) AND {}))
+            AND ($5::uuid IS NULL OR ft.fact_id != $5)
+            GROUP BY ft.tag_id
+            HAVING COUNT(*) >= $6
+        )
+        SELECT t.id AS tag_id, t.path, t.name, c.fact_count,
+               (1 - (c.centroid <=> $1::vector))::float8 AS similarity
+        FROM centroids c
+        JOIN tags t ON t.id = c.tag_id
+        WHERE t.deleted_at IS NULL
+        AND (
+            t.owner_type IS NULL
+            OR (t.owner_type = 'user' AND t.owner_id = $3)
+            OR (t.owner_type = 'family' AND t.owner_id = ANY($4))
+        )
+        AND NOT EXISTS (
+            SELECT 1 FROM fact_tags existing
+            WHERE existing.fact_id = $5 AND existing.tag_id = t.id
+        )
+        AND 1 - (c.centroid <=> $1::vector) > $7
+        ORDER BY c.centroid <=> $1::vector
+        LIMIT $8
+        "#,
+        space_clause("f", 3)
+    ))
+    .bind(embedding)
+    .bind(model_id)
+    .bind(user_id)
+    .bind(family_ids)
+    .bind(exclude_fact_id)
+    .bind(MIN_TAGGED_FACTS)
+    .bind(MIN_SIMILARITY)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    for suggestion in &mut suggestions {
+        suggestion.confidence = centroid_confidence(suggestion.similarity, suggestion.fact_count);
+    }
+    suggestions.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+    Ok(suggestions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confidence_rescales_similarity() {
+        assert_eq!(centroid_confidence(MIN_SIMILARITY, 100), 0.0);
+        assert_eq!(centroid_confidence(0.1, 100), 0.0);
+        assert_eq!(centroid_confidence(1.0, 98), 0.98);
+    }
+
+    #[test]
+    fn confidence_grows_with_support() {
+        let thin = centroid_confidence(0.8, 2);
+        let thick = centroid_confidence(0.8, 40);
+        assert!(thin < thick);
+        assert_eq!(thin, 0.36);
+        assert_eq!(centroid_confidence(0.8, 0), 0.0);
+    }
+}
@@ -0,0 +1,280 @@
+//! Speaker-labeled segments of Amazon Transcribe output.
+//!
+//! Voice memos dropped into the drop folder are transcribed with speaker
+//! labels on. Transcribe reports every word (and punctuation mark) as an item
+//! tagged with its speaker; consecutive items from the same speaker are joined
+//! into a segment, stored in `drop_folder_transcript_segments` and used to
+//! label who said what in the text handed to the ingestion agent.
+
+use serde::Deserialize;
+
+/// Most speakers Transcribe tells apart in one memo
+pub const MAX_SPEAKERS: i32 = 5;
+
+/// A run of speech from one speaker
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    /// Transcribe speaker label (`spk_0`, `spk_1`, ...), if speakers were labeled
+    pub speaker: Option<String>,
+    pub start_secs: f64,
+    pub end_secs: f64,
+    pub text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Output {
+    results: Results,
+}
+
+#[derive(Debug, Deserialize)]
+struct Results {
+    #[serde(default)]
+    transcripts: Vec<TranscriptText>,
+    #[serde(default)]
+    items: Vec<Item>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TranscriptText {
+    transcript: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Item {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    start_time: Option<String>,
+    #[serde(default)]
+    end_time: Option<String>,
+    #[serde(default)]
+    speaker_label: Option<String>,
+    #[serde(default)]
+    alternatives: Vec<Alternative>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Alternative {
+    content: String,
+}
+
+/// A parsed Transcribe output document
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transcript {
+    /// Full transcript text, without speaker labels
+    pub text: String,
+    pub segments: Vec<Segment>,
+}
+
+impl Transcript {
+    /// Parse Transcribe's JSON output, or `None` if it isn't a transcript.
+    pub fn parse(json: &[u8]) -> Option<Self> {
+        let output: Output = serde_json::from_slice(json).ok()?;
+        let text = output
+            .results
+            .transcripts
+            .into_iter()
+            .map(|t| t.transcript)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        Some(Self {
+            text,
+            segments: segments(&output.results.items),
+        })
+    }
+
+    /// Number of distinct speakers.
+    pub fn speaker_count(&self) -> usize {
+        let mut speakers: Vec<&str> = self
+            .segments
+            .iter()
+            .filter_map(|s| s.speaker.as_deref())
+            .collect();
+        speakers.sort_unstable();
+        speakers.dedup();
+        speakers.len()
+    }
+
+    /// Text for ingestion: one "Speaker N:" line per segment when more than
+    /// one person spoke, otherwise the plain transcript.
+    pub fn labeled_text(&self) -> String {
+        if self.speaker_count() < 2 {
+            return self.text.clone();
+        }
+
+        self.segments
+            .iter()
+            .map(|s| match s.speaker.as_deref().and_then(speaker_number) {
+                Some(n) => format!("Speaker {}: {}", n, s.text),
+                None => s.text.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// 1-based speaker number from a `spk_N` label.
+pub fn speaker_number(label: &str) -> Option<u32> {
+    label
+        .strip_prefix("spk_")
+        .and_then(|n| n.parse::<u32>().ok())
+        .map(|n| n + 1)
+}
+
+/// Join consecutive items from the same speaker. Punctuation has no times or
+/// speaker and attaches to the preceding word.
+fn segments(items: &[Item]) -> Vec<Segment> {
+    let mut segments: Vec<Segment> = Vec::new();
+
+    for item in items {
+        let Some(content) = item.alternatives.first().map(|a| a.content.as_str()) else {
+            continue;
+        };
+
+        if item.kind == "punctuation" {
+            if let Some(current) = segments.last_mut() {
+                current.text.push_str(content);
+            }
+            continue;
+        }
+
+        let start = seconds(item.start_time.as_deref());
+        let end = seconds(item.end_time.as_deref()).max(start);
+
+        match segments.last_mut() {
+            Some(current) if current.speaker == item.speaker_label => {
+                current.text.push(' ');
+                current.text.push_str(content);
+                current.end_secs = current.end_secs.max(end);
+            }
+            _ => segments.push(Segment {
+                speaker: item.speaker_label.clone(),
+                start_secs: start,
+                end_secs: end,
+                text: content.to_string(),
+            }),
+        }
+    }
+
+    segments
+}
+
+fn seconds(value: Option<&str>) -> f64 {
+    value
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|v| v.is_finite())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(content: &str, start: &str, end: &str, speaker: &str) -> serde_json::Value {
+        serde_json::json!({
+            "type": "pronunciation",
+            "start_time": start,
+            "end_time": end,
+            "speaker_label": speaker,
+            "alternatives": [{ "confidence": "0.99", "content": content }],
+        })
+    }
+
+    fn punctuation(content: &str) -> serde_json::Value {
+        serde_json::json!({
+            "type": "punctuation",
+            "alternatives": [{ "confidence": "0.0", "content": content }],
+        })
+    }
+
+    fn output(transcript: &str, items: Vec<serde_json::Value>) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "jobName": "second-brain-drop-1",
+            "results": {
+                "transcripts": [{ "transcript": transcript }],
+                "items": items,
+            },
+            "status": "COMPLETED",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn groups_items_by_speaker() {
+        let json = output(
+            "Dentist moved to Friday. Okay, noted.",
+            vec![
+                word("Dentist", "0.1", "0.6", "spk_0"),
+                word("moved", "0.6", "0.9", "spk_0"),
+                word("to", "0.9", "1.0", "spk_0"),
+                word("Friday", "1.0", "1.5", "spk_0"),
+                punctuation("."),
+                word("Okay", "2.0", "2.3", "spk_1"),
+                punctuation(","),
+                word("noted", "2.4", "2.8", "spk_1"),
+                punctuation("."),
+            ],
+        );
+
+        let transcript = Transcript::parse(&json).unwrap();
+
+        assert_eq!(
+            transcript.segments,
+            vec![
+                Segment {
+                    speaker: Some("spk_0".to_string()),
+                    start_secs: 0.1,
+                    end_secs: 1.5,
+                    text: "Dentist moved to Friday.".to_string(),
+                },
+                Segment {
+                    speaker: Some("spk_1".to_string()),
+                    start_secs: 2.0,
+                    end_secs: 2.8,
+                    text: "Okay, noted.".to_string(),
+                },
+            ]
+        );
+        assert_eq!(transcript.speaker_count(), 2);
+        assert_eq!(
+            transcript.labeled_text(),
+            "Speaker 1: Dentist moved to Friday.\nSpeaker 2: Okay, noted."
+        );
+    }
+
+    #[test]
+    fn single_speaker_keeps_plain_text() {
+        let json = output(
+            "Buy milk.",
+            vec![
+                word("Buy", "0.0", "0.2", "spk_0"),
+                word("milk", "0.2", "0.5", "spk_0"),
+                punctuation("."),
+            ],
+        );
+
+        let transcript = Transcript::parse(&json).unwrap();
+
+        assert_eq!(transcript.segments.len(), 1);
+        assert_eq!(transcript.speaker_count(), 1);
+        assert_eq!(transcript.labeled_text(), "Buy milk.");
+    }
+
+    #[test]
+    fn output_without_items_has_no_segments() {
+        let transcript = Transcript::parse(&output("", vec![])).unwrap();
+        assert!(transcript.segments.is_empty());
+        assert_eq!(transcript.text, "");
+
+        assert_eq!(Transcript::parse(b"{\"status\": \"FAILED\"}"), None);
+        assert_eq!(Transcript::parse(b"not json"), None);
+    }
+
+    #[test]
+    fn speaker_numbers_are_one_based() {
+        assert_eq!(speaker_number("spk_0"), Some(1));
+        assert_eq!(speaker_number("spk_12"), Some(13));
+        assert_eq!(speaker_number("speaker"), None);
+    }
+}
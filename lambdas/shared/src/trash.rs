@@ -0,0 +1,422 @@
+//! Soft delete (trash) for facts, entities and tags.
+//!
+//! Deleting sets `deleted_at` (see migration 041) rather than removing the
+//! row; everything that reads these tables skips deleted rows. `GET /trash`
+//! lists what the user deleted, `POST /trash/{id}/restore` brings an item
+//! back, and the `trash_purge` Lambda removes items for good once they have
+//! been in the trash for `RETENTION_DAYS`.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::access::space_clause;
+use crate::Result;
+
+/// Days an item stays in the trash before it is purged
+pub const RETENTION_DAYS: i64 = 30;
+
+/// Most items listed at once
+pub const MAX_LIST: i64 = 200;
+
+/// What kind of row a trash item is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrashKind {
+    Fact,
+    Entity,
+    Tag,
+}
+
+impl TrashKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "fact" => Some(Self::Fact),
+            "entity" => Some(Self::Entity),
+            "tag" => Some(Self::Tag),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Fact => "fact",
+            Self::Entity => "entity",
+            Self::Tag => "tag",
+        }
+    }
+}
+
+/// When an item deleted at `deleted_at` will be purged.
+pub fn purge_at(deleted_at: DateTime<Utc>) -> DateTime<Utc> {
+    deleted_at + Duration::days(RETENTION_DAYS)
+}
+
+/// An item in the trash
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashItem {
+    pub id: Uuid,
+    /// `fact`, `entity` or `tag`
+    pub kind: String,
+    /// Fact content, entity name or tag path
+    pub label: String,
+    /// Entity type, or the number of descendants deleted with a tag
+    pub detail: Option<String>,
+    pub deleted_at: DateTime<Utc>,
+    #[sqlx(skip)]
+    pub purge_at: Option<DateTime<Utc>>,
+}
+
+/// Result of restoring an item
+#[derive(Debug, Clone, PartialEq)]
+pub enum Restore {
+    /// Restored; for tags the count includes descendants deleted with it
+    Restored { kind: TrashKind, count: u64 },
+    /// Not in the caller's trash
+    NotFound,
+    /// Can't be restored as things stand
+    Conflict(String),
+}
+
+/// Rows purged by `purge_expired`
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct PurgeCounts {
+    pub facts: u64,
+    pub entities: u64,
+    pub tags: u64,
+}
+
+/// Move a fact to the trash. Returns whether it was there to delete.
+pub async fn delete_fact(pool: &sqlx::PgPool, fact_id: Uuid, user_id: Uuid) -> Result<bool> {
+    let deleted = sqlx::query(
+        "UPDATE facts SET deleted_at = NOW(), deleted_by = $2 WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(fact_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(deleted > 0)
+}
+
+/// Move an entity to the trash. Returns whether it was there to delete.
+pub async fn delete_entity(pool: &sqlx::PgPool, entity_id: Uuid, user_id: Uuid) -> Result<bool> {
+    let deleted = sqlx::query(
+        "UPDATE entities SET deleted_at = NOW(), deleted_by = $2 WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(entity_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(deleted > 0)
+}
+
+/// Move a tag and its descendants to the trash, returning how many tags
+/// were deleted.
+pub async fn delete_tag(pool: &sqlx::PgPool, tag_id: Uuid, user_id: Uuid) -> Result<u64> {
+    let deleted = sqlx::query(
+        r#"
+        WITH root AS (
+            SELECT owner_type, owner_id, path FROM tags
+            WHERE id = $1 AND deleted_at IS NULL
+        )
+        UPDATE tags t
+        SET deleted_at = NOW(), deleted_by = $2
+        FROM root
+        WHERE t.deleted_at IS NULL
+        AND t.owner_type IS NOT DISTINCT FROM root.owner_type
+        AND t.owner_id IS NOT DISTINCT FROM root.owner_id
+        AND (t.path = root.path OR starts_with(t.path, root.path || '/'))
+        "#,
+    )
+    .bind(tag_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(deleted)
+}
+
+/// Items the user (or their families) deleted, most recent first. Tags
+/// deleted along with a parent are folded into the parent's entry.
+pub async fn list_trash(
+    pool: &sqlx::PgPool,
+    user_id: Uuid,
+    family_ids: &[Uuid],
+    kind: Option<TrashKind>,
+    limit: i64,
+) -> Result<Vec<TrashItem>> {
+    let mut items: Vec<TrashItem> = sqlx::query_as(&format!(
+        r#"
+        SELECT * FROM (
+            SELECT f.id, 'fact' AS kind, LEFT(f.content, 200) AS label,
+                   NULL::text AS detail, f.deleted_at
+            FROM facts f
+            WHERE f.deleted_at IS NOT NULL
+            AND ((f.owner_type = 'user' AND f.owner_id = $1)
+                 OR (f.owner_type = 'family' AND f.owner_id = ANY($2) AND {}))
+
+            UNION ALL
+
+            SELECT e.id, 'entity', e.name, e.entity_type::text, e.deleted_at
+            FROM entities e
+            WHERE e.deleted_at IS NOT NULL
+            AND ((e.owner_type = 'user' AND e.owner_id = $1)
+                 OR (e.owner_type = 'family' AND e.owner_id = ANY($2) AND {}))
+
+            UNION ALL
+
+            SELECT t.id, 'tag', t.path,
+                   (SELECT COUNT(*) FROM tags d
+                    WHERE d.deleted_at = t.deleted_at
+                    AND d.owner_type IS NOT DISTINCT FROM t.owner_type
+                    AND d.owner_id IS NOT DISTINCT FROM t.owner_id
+                    AND starts_with(d.path, t.path || '/'))::text,
+                   t.deleted_at
+            FROM tags t
+            WHERE t.deleted_at IS NOT NULL
+            AND ((t.owner_type = 'user' AND t.owner_id = $1)
+                 OR (t.owner_type = 'family' AND t.owner_id = ANY($2)))
+            AND NOT EXISTS (
+                SELECT 1 FROM tags p
+                WHERE p.id = t.parent_id AND p.deleted_at = t.deleted_at
+            )
+        ) trash
+        WHERE $3::text IS NULL OR kind = $3
+        ORDER BY deleted_at DESC, id
+        LIMIT $4
+        "#,
+        space_clause("f", 1),
+        space_clause("e", 1),
+    ))
+    .bind(user_id)
+    .bind(family_ids)
+    .bind(kind.map(|k| k.as_str()))
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    for item in &mut items {
+        item.purge_at = Some(purge_at(item.deleted_at));
+    }
+
+    Ok(items)
+}
+
+/// Restore a fact, entity or tag (with the descendants deleted alongside it)
+/// from the caller's trash.
+pub async fn restore(
+    pool: &sqlx::PgPool,
+    user_id: Uuid,
+    family_ids: &[Uuid],
+    id: Uuid,
+) -> Result<Restore> {
+    for (kind, table) in [(TrashKind::Fact, "facts"), (TrashKind::Entity, "entities")] {
+        let restored = sqlx::query(&format!(
+            r#"
+            UPDATE {} SET deleted_at = NULL, deleted_by = NULL
+            WHERE id = $1 AND deleted_at IS NOT NULL
+            AND ((owner_type = 'user' AND owner_id = $2)
+                 OR (owner_type = 'family' AND owner_id = ANY($3) AND {}))
+            "#,
+            table,
+            space_clause(table, 2)
+        ))
+        .bind(id)
+        .bind(user_id)
+        .bind(family_ids)
+        .execute(pool)
+        .await?
+        .rows_affected();
+
+        if restored > 0 {
+            return Ok(Restore::Restored {
+                kind,
+                count: restored,
+            });
+        }
+    }
+
+    restore_tag(pool, user_id, family_ids, id).await
+}
+
+async fn restore_tag(
+    pool: &sqlx::PgPool,
+    user_id: Uuid,
+    family_ids: &[Uuid],
+    tag_id: Uuid,
+) -> Result<Restore> {
+    let mut tx = pool.begin().await?;
+
+    let tag: Option<(String, Option<String>, Option<Uuid>, DateTime<Utc>, bool)> = sqlx::query_as(
+        r#"
+        SELECT t.path, t.owner_type, t.owner_id, t.deleted_at,
+               COALESCE(p.deleted_at IS NOT NULL, false) AS parent_deleted
+        FROM tags t
+        LEFT JOIN tags p ON p.id = t.parent_id
+        WHERE t.id = $1 AND t.deleted_at IS NOT NULL
+        AND ((t.owner_type = 'user' AND t.owner_id = $2)
+             OR (t.owner_type = 'family' AND t.owner_id = ANY($3)))
+        FOR UPDATE OF t
+        "#,
+    )
+    .bind(tag_id)
+    .bind(user_id)
+    .bind(family_ids)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some((path, owner_type, owner_id, deleted_at, parent_deleted)) = tag else {
+        return Ok(Restore::NotFound);
+    };
+    if parent_deleted {
+        return Ok(Restore::Conflict(
+            "The parent tag is in the trash; restore it first".to_string(),
+        ));
+    }
+
+    let taken: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT live.path FROM tags live
+        JOIN tags t ON t.path = live.path
+        WHERE live.deleted_at IS NULL
+        AND live.owner_type IS NOT DISTINCT FROM $2
+        AND live.owner_id IS NOT DISTINCT FROM $3
+        AND t.deleted_at = $4
+        AND t.owner_type IS NOT DISTINCT FROM $2
+        AND t.owner_id IS NOT DISTINCT FROM $3
+        AND (t.path = $1 OR starts_with(t.path, $1 || '/'))
+        ORDER BY live.path
+        "#,
+    )
+    .bind(&path)
+    .bind(&owner_type)
+    .bind(owner_id)
+    .bind(deleted_at)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if !taken.is_empty() {
+        return Ok(Restore::Conflict(format!(
+            "Tags already exist at: {}",
+            taken.join(", ")
+        )));
+    }
+
+    let restored = sqlx::query(
+        r#"
+        UPDATE tags SET deleted_at = NULL, deleted_by = NULL
+        WHERE deleted_at = $4
+        AND owner_type IS NOT DISTINCT FROM $2
+        AND owner_id IS NOT DISTINCT FROM $3
+        AND (path = $1 OR starts_with(path, $1 || '/'))
+        "#,
+    )
+    .bind(&path)
+    .bind(&owner_type)
+    .bind(owner_id)
+    .bind(deleted_at)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    tx.commit().await?;
+
+    Ok(Restore::Restored {
+        kind: TrashKind::Tag,
+        count: restored,
+    })
+}
+
+/// Permanently remove everything deleted before `cutoff`.
+pub async fn purge_expired(pool: &sqlx::PgPool, cutoff: DateTime<Utc>) -> Result<PurgeCounts> {
+    let mut tx = pool.begin().await?;
+
+    // Facts that point at purged rows keep existing without the link
+    sqlx::query(
+        r#"
+        UPDATE facts SET superseded_by = NULL
+        WHERE superseded_by IN (SELECT id FROM facts WHERE deleted_at < $1)
+        "#,
+    )
+    .bind(cutoff)
+    .execute(&mut *tx)
+    .await?;
+
+    let facts = sqlx::query("DELETE FROM facts WHERE deleted_at < $1")
+        .bind(cutoff)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    sqlx::query(
+        r#"
+        UPDATE facts SET about_entity_id = NULL
+        WHERE about_entity_id IN (SELECT id FROM entities WHERE deleted_at < $1)
+        "#,
+    )
+    .bind(cutoff)
+    .execute(&mut *tx)
+    .await?;
+
+    let entities = sqlx::query("DELETE FROM entities WHERE deleted_at < $1")
+        .bind(cutoff)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    // Descendants are deleted with their parent, but don't let a stray live
+    // child block the purge
+    sqlx::query(
+        r#"
+        UPDATE tags SET parent_id = NULL
+        WHERE deleted_at IS NULL
+        AND parent_id IN (SELECT id FROM tags WHERE deleted_at < $1)
+        "#,
+    )
+    .bind(cutoff)
+    .execute(&mut *tx)
+    .await?;
+
+    let tags = sqlx::query("DELETE FROM tags WHERE deleted_at < $1")
+        .bind(cutoff)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    tx.commit().await?;
+
+    Ok(PurgeCounts {
+        facts,
+        entities,
+        tags,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn parses_kinds() {
+        for kind in [TrashKind::Fact, TrashKind::Entity, TrashKind::Tag] {
+            assert_eq!(TrashKind::parse(kind.as_str()), Some(kind));
+        }
+        assert_eq!(TrashKind::parse("facts"), None);
+    }
+
+    #[test]
+    fn purges_after_retention() {
+        let deleted_at = Utc.with_ymd_and_hms(2026, 1, 15, 9, 30, 0).unwrap();
+        assert_eq!(
+            purge_at(deleted_at),
+            Utc.with_ymd_and_hms(2026, 2, 14, 9, 30, 0).unwrap()
+        );
+    }
+}
@@ -1,8 +1,35 @@
 //! Text-to-Speech utilities using Amazon Polly.
+//!
+//! [`TtsService::speak`] takes plain text or SSML and [`VoiceOptions`] (voice,
+//! engine, language and speaking rate). Long text is synthesized in chunks and
+//! joined. With an audio bucket configured the MP3 is also stored under
+//! `tts/` and returned with a presigned URL, which is what Alexa's `<audio>`
+//! tag and mobile players need.
+//!
+//! Stored audio doubles as a cache: objects are keyed by a hash of the SSML,
+//! voice and output format, so a briefing or reminder phrase spoken the same
+//! way again is served from S3 without calling Polly, for up to
+//! `TTS_CACHE_TTL_SECONDS` (default 7 days).
 
-use aws_sdk_polly::types::{Engine, OutputFormat, VoiceId};
+use aws_sdk_polly::types::{Engine, LanguageCode, OutputFormat, TextType, VoiceId};
 use aws_sdk_polly::Client as PollyClient;
+use sha2::{Digest, Sha256};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
+use tracing::warn;
+
+/// Polly bills up to 3000 characters of text per request; chunks stay under it.
+pub const MAX_CHUNK_CHARS: usize = 2900;
+
+/// How long a presigned audio URL is valid
+pub const AUDIO_URL_TTL_SECS: u64 = 60 * 60;
+
+/// How long stored audio is reused, unless `TTS_CACHE_TTL_SECONDS` is set. The
+/// bucket must keep `tts/` objects at least a day longer.
+pub const DEFAULT_CACHE_TTL_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// 24 kHz MP3 is 48 kbps, the bitrate Alexa's `<audio>` tag requires.
+const SAMPLE_RATE: &str = "24000";
 
 #[derive(Error, Debug)]
 pub enum TtsError {
@@ -10,6 +37,137 @@ pub enum TtsError {
     SynthesisFailed(String),
     #[error("Invalid audio data")]
     InvalidAudio,
+    #[error("Failed to store audio: {0}")]
+    Storage(String),
+}
+
+/// What to synthesize.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpeechInput {
+    /// Plain text; escaped and wrapped in `<speak>` as needed
+    Text(String),
+    /// An SSML document (`<speak>...</speak>`)
+    Ssml(String),
+}
+
+/// Speaking rate, applied with `<prosody rate="...">`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpeechRate {
+    XSlow,
+    Slow,
+    #[default]
+    Medium,
+    Fast,
+    XFast,
+}
+
+impl SpeechRate {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::XSlow => "x-slow",
+            Self::Slow => "slow",
+            Self::Medium => "medium",
+            Self::Fast => "fast",
+            Self::XFast => "x-fast",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "x-slow" => Some(Self::XSlow),
+            "slow" => Some(Self::Slow),
+            "medium" => Some(Self::Medium),
+            "fast" => Some(Self::Fast),
+            "x-fast" => Some(Self::XFast),
+            _ => None,
+        }
+    }
+}
+
+/// Voice settings for a synthesis request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoiceOptions {
+    pub voice: VoiceId,
+    pub engine: Engine,
+    /// Language for bilingual voices (e.g. `en-IN` for Aditi); the voice's
+    /// default otherwise
+    pub language: Option<LanguageCode>,
+    pub rate: SpeechRate,
+}
+
+impl Default for VoiceOptions {
+    fn default() -> Self {
+        Self {
+            voice: voices::MATTHEW,
+            engine: Engine::Neural,
+            language: None,
+            rate: SpeechRate::Medium,
+        }
+    }
+}
+
+impl VoiceOptions {
+    /// Defaults overridden by `TTS_VOICE`, `TTS_ENGINE`, `TTS_LANGUAGE` and
+    /// `TTS_SPEECH_RATE`.
+    pub fn from_env() -> Self {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    fn from_lookup(var: impl Fn(&str) -> Option<String>) -> Self {
+        let defaults = Self::default();
+        Self {
+            voice: var("TTS_VOICE")
+                .map(|v| VoiceId::from(v.as_str()))
+                .unwrap_or(defaults.voice),
+            engine: var("TTS_ENGINE")
+                .map(|e| Engine::from(e.to_ascii_lowercase().as_str()))
+                .unwrap_or(defaults.engine),
+            language: var("TTS_LANGUAGE").map(|l| LanguageCode::from(l.as_str())),
+            rate: var("TTS_SPEECH_RATE")
+                .and_then(|r| SpeechRate::parse(&r))
+                .unwrap_or(defaults.rate),
+        }
+    }
+
+    /// Use `language` (e.g. an Alexa locale such as `en-GB`).
+    pub fn with_language(mut self, language: &str) -> Self {
+        self.language = Some(LanguageCode::from(language));
+        self
+    }
+
+    pub fn with_rate(mut self, rate: SpeechRate) -> Self {
+        self.rate = rate;
+        self
+    }
+}
+
+/// Synthesized audio.
+#[derive(Debug, Clone)]
+pub struct Speech {
+    /// MP3 audio; `None` when served from the cache
+    pub audio: Option<Vec<u8>>,
+    /// Object key in the audio bucket, when stored
+    pub key: Option<String>,
+    /// Presigned GET URL for the stored audio
+    pub url: Option<String>,
+    /// True when the audio was already stored and Polly wasn't called
+    pub cached: bool,
+}
+
+struct AudioStorage {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+    /// How long stored audio is reused
+    cache_ttl: Duration,
+}
+
+/// Cache lifetime from `TTS_CACHE_TTL_SECONDS`.
+fn cache_ttl() -> Duration {
+    let secs = std::env::var("TTS_CACHE_TTL_SECONDS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_CACHE_TTL_SECS);
+    Duration::from_secs(secs)
 }
 
 /// Text-to-Speech service using Amazon Polly.
@@ -17,6 +175,7 @@ pub struct TtsService {
     client: PollyClient,
     voice_id: VoiceId,
     engine: Engine,
+    storage: Option<AudioStorage>,
 }
 
 impl TtsService {
@@ -26,6 +185,7 @@ impl TtsService {
             client,
             voice_id: VoiceId::Matthew, // Neural voice
             engine: Engine::Neural,
+            storage: None,
         }
     }
 
@@ -35,9 +195,32 @@ impl TtsService {
             client,
             voice_id,
             engine: Engine::Neural,
+            storage: None,
         }
     }
 
+    /// Polly client from `config`, storing audio in `TTS_AUDIO_BUCKET` if set.
+    pub fn from_env(config: &aws_config::SdkConfig) -> Self {
+        let service = Self::new(PollyClient::new(config));
+        match std::env::var("TTS_AUDIO_BUCKET") {
+            Ok(bucket) if !bucket.is_empty() => {
+                service.with_storage(aws_sdk_s3::Client::new(config), bucket)
+            }
+            _ => service,
+        }
+    }
+
+    /// Store synthesized audio in `bucket` so [`TtsService::speak`] can return
+    /// a URL and reuse audio it has produced before.
+    pub fn with_storage(mut self, client: aws_sdk_s3::Client, bucket: String) -> Self {
+        self.storage = Some(AudioStorage {
+            client,
+            bucket,
+            cache_ttl: cache_ttl(),
+        });
+        self
+    }
+
     /// Synthesize text to speech, returning MP3 audio bytes.
     pub async fn synthesize(&self, text: &str) -> Result<Vec<u8>, TtsError> {
         // Limit text length for Polly (max ~3000 characters per request)
@@ -69,14 +252,79 @@ impl TtsService {
 
     /// Synthesize with SSML markup for better control.
     pub async fn synthesize_ssml(&self, ssml: &str) -> Result<Vec<u8>, TtsError> {
+        let options = VoiceOptions {
+            voice: self.voice_id.clone(),
+            engine: self.engine.clone(),
+            ..VoiceOptions::default()
+        };
+        self.synthesize_chunk(ssml, &options).await
+    }
+
+    /// Synthesize `input` with `options`, storing the audio if a bucket is
+    /// configured. Audio already stored for the same input and options is
+    /// returned from the bucket instead.
+    pub async fn speak(
+        &self,
+        input: &SpeechInput,
+        options: &VoiceOptions,
+    ) -> Result<Speech, TtsError> {
+        let chunks = ssml_chunks(input, options.rate);
+        let key = cache_key(&chunks, options);
+
+        if let Some(storage) = &self.storage {
+            if let Some(url) = cached_audio(storage, &key).await {
+                return Ok(Speech {
+                    audio: None,
+                    key: Some(key),
+                    url: Some(url),
+                    cached: true,
+                });
+            }
+        }
+
+        let mut audio = Vec::new();
+        for chunk in &chunks {
+            // MP3 frames concatenate into one playable stream
+            audio.extend(self.synthesize_chunk(chunk, options).await?);
+        }
+        if audio.is_empty() {
+            return Err(TtsError::InvalidAudio);
+        }
+
+        let Some(storage) = &self.storage else {
+            return Ok(Speech {
+                audio: Some(audio),
+                key: None,
+                url: None,
+                cached: false,
+            });
+        };
+
+        store_audio(storage, &key, &audio).await?;
+        let url = presign_audio(storage, &key).await?;
+        Ok(Speech {
+            audio: Some(audio),
+            key: Some(key),
+            url: Some(url),
+            cached: false,
+        })
+    }
+
+    async fn synthesize_chunk(
+        &self,
+        ssml: &str,
+        options: &VoiceOptions,
+    ) -> Result<Vec<u8>, TtsError> {
         let response = self
             .client
             .synthesize_speech()
             .text(ssml)
-            .text_type(aws_sdk_polly::types::TextType::Ssml)
-            .voice_id(self.voice_id.clone())
-            .engine(self.engine.clone())
+            .text_type(TextType::Ssml)
+            .voice_id(options.voice.clone())
+            .engine(options.engine.clone())
+            .set_language_code(options.language.clone())
             .output_format(OutputFormat::Mp3)
+            .sample_rate(SAMPLE_RATE)
             .send()
             .await
             .map_err(|e| TtsError::SynthesisFailed(e.to_string()))?;
@@ -91,6 +339,218 @@ impl TtsService {
     }
 }
 
+/// Presigned URL for audio stored under `key`, if it exists and is within the cache TTL.
+async fn cached_audio(storage: &AudioStorage, key: &str) -> Option<String> {
+    let head = match storage
+        .client
+        .head_object()
+        .bucket(&storage.bucket)
+        .key(key)
+        .send()
+        .await
+    {
+        Ok(head) => head,
+        Err(e) => {
+            if !e.as_service_error().is_some_and(|e| e.is_not_found()) {
+                warn!("Failed to check cached audio {}: {}", key, e);
+            }
+            return None;
+        }
+    };
+
+    let stored_at = head.last_modified()?.secs();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    if !is_fresh(stored_at, now, storage.cache_ttl) {
+        return None;
+    }
+
+    match presign_audio(storage, key).await {
+        Ok(url) => Some(url),
+        Err(e) => {
+            warn!("Failed to presign cached audio {}: {}", key, e);
+            None
+        }
+    }
+}
+
+/// Whether audio stored at `stored_at` (epoch seconds) can still be reused at `now`.
+fn is_fresh(stored_at: i64, now: i64, ttl: Duration) -> bool {
+    now.saturating_sub(stored_at) < ttl.as_secs() as i64
+}
+
+async fn store_audio(storage: &AudioStorage, key: &str, audio: &[u8]) -> Result<(), TtsError> {
+    storage
+        .client
+        .put_object()
+        .bucket(&storage.bucket)
+        .key(key)
+        .content_type("audio/mpeg")
+        .body(audio.to_vec().into())
+        .send()
+        .await
+        .map_err(|e| TtsError::Storage(e.to_string()))?;
+    Ok(())
+}
+
+async fn presign_audio(storage: &AudioStorage, key: &str) -> Result<String, TtsError> {
+    let config = aws_sdk_s3::presigning::PresigningConfig::expires_in(Duration::from_secs(
+        AUDIO_URL_TTL_SECS,
+    ))
+    .map_err(|e| TtsError::Storage(e.to_string()))?;
+
+    let request = storage
+        .client
+        .get_object()
+        .bucket(&storage.bucket)
+        .key(key)
+        .presigned(config)
+        .await
+        .map_err(|e| TtsError::Storage(e.to_string()))?;
+
+    Ok(request.uri().to_string())
+}
+
+/// Object key for the audio of `chunks` spoken with `options`: a hash of
+/// everything that changes the audio. The bucket expires `tts/` objects.
+pub fn cache_key(chunks: &[String], options: &VoiceOptions) -> String {
+    let language = options.language.as_ref().map_or("", |l| l.as_str());
+    let mut hasher = Sha256::new();
+    for part in [
+        options.voice.as_str(),
+        options.engine.as_str(),
+        language,
+        OutputFormat::Mp3.as_str(),
+        SAMPLE_RATE,
+    ]
+    .into_iter()
+    .chain(chunks.iter().map(String::as_str))
+    {
+        // Separate parts so ("ab", "c") and ("a", "bc") differ
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    format!("tts/{}.mp3", hex::encode(hasher.finalize()))
+}
+
+/// Escape text for use inside SSML.
+pub fn escape_ssml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Wrap SSML body content in `<speak>`, with the speaking rate if it isn't the default.
+fn speak(body: &str, rate: SpeechRate) -> String {
+    match rate {
+        SpeechRate::Medium => format!("<speak>{}</speak>", body),
+        rate => format!(
+            r#"<speak><prosody rate="{}">{}</prosody></speak>"#,
+            rate.as_str(),
+            body
+        ),
+    }
+}
+
+/// The SSML documents to synthesize for `input`, one per Polly request.
+///
+/// Text is split at sentence (or, failing that, word) boundaries into chunks
+/// of at most [`MAX_CHUNK_CHARS`]. SSML can't be split safely, so it is sent
+/// whole with its `<speak>` body re-wrapped to apply the rate.
+pub fn ssml_chunks(input: &SpeechInput, rate: SpeechRate) -> Vec<String> {
+    match input {
+        SpeechInput::Text(text) => split_text(text, MAX_CHUNK_CHARS)
+            .iter()
+            .map(|chunk| speak(&escape_ssml(chunk), rate))
+            .collect(),
+        SpeechInput::Ssml(ssml) => vec![speak(ssml_body(ssml), rate)],
+    }
+}
+
+/// The content of an SSML document's `<speak>` element.
+fn ssml_body(ssml: &str) -> &str {
+    let ssml = ssml.trim();
+    let Some(rest) = ssml.strip_prefix("<speak") else {
+        return ssml;
+    };
+    let Some(open_end) = rest.find('>') else {
+        return ssml;
+    };
+    rest[open_end + 1..]
+        .strip_suffix("</speak>")
+        .unwrap_or(&rest[open_end + 1..])
+}
+
+/// Split text into chunks of at most `max_chars`, preferring sentence ends.
+fn split_text(text: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    for sentence in sentences(text) {
+        if current.chars().count() + sentence.chars().count() > max_chars && !current.is_empty() {
+            chunks.push(std::mem::take(&mut current).trim().to_string());
+        }
+        if sentence.chars().count() > max_chars {
+            for word in sentence.split_inclusive(char::is_whitespace) {
+                if current.chars().count() + word.chars().count() > max_chars && !current.is_empty()
+                {
+                    chunks.push(std::mem::take(&mut current).trim().to_string());
+                }
+                current.push_str(word);
+            }
+        } else {
+            current.push_str(sentence);
+        }
+    }
+
+    if !current.trim().is_empty() {
+        chunks.push(current.trim().to_string());
+    }
+    chunks
+}
+
+/// Sentences of `text`, each including its terminator and trailing space.
+fn sentences(text: &str) -> impl Iterator<Item = &str> {
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    std::iter::from_fn(move || {
+        while let Some((i, c)) = chars.next() {
+            let at_boundary = matches!(c, '.' | '!' | '?' | '\n')
+                && chars.peek().is_none_or(|(_, next)| next.is_whitespace());
+            if at_boundary {
+                // Keep the whitespace after the terminator with this sentence
+                let mut end = i + c.len_utf8();
+                while let Some((j, next)) = chars.peek().copied() {
+                    if !next.is_whitespace() {
+                        break;
+                    }
+                    end = j + next.len_utf8();
+                    chars.next();
+                }
+                let sentence = &text[start..end];
+                start = end;
+                return Some(sentence);
+            }
+        }
+        (start < text.len()).then(|| {
+            let sentence = &text[start..];
+            start = text.len();
+            sentence
+        })
+    })
+}
+
 /// Available neural voices for TTS.
 pub mod voices {
     use aws_sdk_polly::types::VoiceId;
@@ -104,3 +564,95 @@ pub mod voices {
     /// British English male voice.
     pub const BRIAN: VoiceId = VoiceId::Brian;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn escapes_text_and_applies_rate() {
+        let chunks = ssml_chunks(
+            &SpeechInput::Text("Tom & Jerry <3".to_string()),
+            SpeechRate::Slow,
+        );
+        assert_eq!(
+            chunks,
+            vec![r#"<speak><prosody rate="slow">Tom &amp; Jerry &lt;3</prosody></speak>"#]
+        );
+    }
+
+    #[test]
+    fn rewraps_ssml_body() {
+        let input =
+            SpeechInput::Ssml(r#"<speak version="1.1">Hi <break time="1s"/> there</speak>"#.into());
+        assert_eq!(
+            ssml_chunks(&input, SpeechRate::Medium),
+            vec![r#"<speak>Hi <break time="1s"/> there</speak>"#]
+        );
+        assert_eq!(
+            ssml_chunks(&input, SpeechRate::Fast),
+            vec![r#"<speak><prosody rate="fast">Hi <break time="1s"/> there</prosody></speak>"#]
+        );
+    }
+
+    #[test]
+    fn splits_long_text_at_sentences() {
+        let text = "One two three. Four five six! Seven eight nine?";
+        assert_eq!(
+            split_text(text, 30),
+            vec!["One two three. Four five six!", "Seven eight nine?"]
+        );
+
+        let run_on = "alpha beta gamma delta";
+        assert_eq!(split_text(run_on, 11), vec!["alpha beta", "gamma delta"]);
+        assert!(split_text("   ", 10).is_empty());
+    }
+
+    #[test]
+    fn cache_key_covers_text_and_voice() {
+        let options = VoiceOptions::default();
+        let chunks = ssml_chunks(&SpeechInput::Text("Take out the bins".into()), options.rate);
+        let key = cache_key(&chunks, &options);
+
+        assert!(key.starts_with("tts/") && key.ends_with(".mp3"));
+        assert_eq!(key, cache_key(&chunks, &options));
+
+        let other_voice = VoiceOptions {
+            voice: VoiceId::Joanna,
+            ..options.clone()
+        };
+        assert_ne!(key, cache_key(&chunks, &other_voice));
+
+        let slower = ssml_chunks(
+            &SpeechInput::Text("Take out the bins".into()),
+            SpeechRate::Slow,
+        );
+        assert_ne!(key, cache_key(&slower, &options));
+    }
+
+    #[test]
+    fn cached_audio_expires() {
+        let ttl = Duration::from_secs(60);
+        assert!(is_fresh(1_000, 1_059, ttl));
+        assert!(!is_fresh(1_000, 1_060, ttl));
+    }
+
+    #[test]
+    fn options_from_env() {
+        let vars: HashMap<&str, &str> = HashMap::from([
+            ("TTS_VOICE", "Joanna"),
+            ("TTS_ENGINE", "Standard"),
+            ("TTS_LANGUAGE", "en-GB"),
+            ("TTS_SPEECH_RATE", "x_slow"),
+        ]);
+        let options = VoiceOptions::from_lookup(|name| vars.get(name).map(|v| v.to_string()));
+
+        assert_eq!(options.voice, VoiceId::Joanna);
+        assert_eq!(options.engine, Engine::Standard);
+        assert_eq!(options.language, Some(LanguageCode::EnGb));
+        assert_eq!(options.rate, SpeechRate::XSlow);
+
+        assert_eq!(VoiceOptions::from_lookup(|_| None), VoiceOptions::default());
+    }
+}
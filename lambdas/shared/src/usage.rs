@@ -0,0 +1,368 @@
+//! Token usage and estimated cost of queries, recorded in `query_sessions`
+//! so spend can be reported per user and month. The facts each answer was
+//! based on are recorded too, so a past answer can be read back with its
+//! citations ([`past_query`]).
+//!
+//! Costs are estimated from list prices ([`price_for`]) when the query runs,
+//! so a later price change doesn't rewrite past months.
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::access::visibility_clause;
+use crate::{Error, Result};
+
+/// Price of a model in USD per 1K tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPrice {
+    pub input_per_1k: f64,
+    pub output_per_1k: f64,
+}
+
+/// List prices by model family, matched against the model ID. Mirrors
+/// `MODEL_COSTS` in the agents' config.
+const PRICES: &[(&str, ModelPrice)] = &[
+    (
+        "haiku",
+        ModelPrice {
+            input_per_1k: 0.00025,
+            output_per_1k: 0.00125,
+        },
+    ),
+    (
+        "sonnet",
+        ModelPrice {
+            input_per_1k: 0.003,
+            output_per_1k: 0.015,
+        },
+    ),
+    (
+        "opus",
+        ModelPrice {
+            input_per_1k: 0.015,
+            output_per_1k: 0.075,
+        },
+    ),
+];
+
+/// Price of `model_id` (a Bedrock model or inference profile ID), if known.
+pub fn price_for(model_id: &str) -> Option<ModelPrice> {
+    let model_id = model_id.to_lowercase();
+    PRICES
+        .iter()
+        .find(|(family, _)| model_id.contains(family))
+        .map(|(_, price)| *price)
+}
+
+/// Estimated cost in USD of a query; 0 for models without a known price.
+pub fn estimate_cost(model_id: &str, input_tokens: u32, output_tokens: u32) -> f64 {
+    price_for(model_id)
+        .map(|price| {
+            (input_tokens as f64 * price.input_per_1k + output_tokens as f64 * price.output_per_1k)
+                / 1000.0
+        })
+        .unwrap_or(0.0)
+}
+
+/// An answered query, as recorded in `query_sessions`.
+#[derive(Debug, Clone)]
+pub struct QueryUsage<'a> {
+    pub user_id: Uuid,
+    pub source: &'a str,
+    pub query: &'a str,
+    pub response: &'a str,
+    pub agents_used: &'a [String],
+    pub model_id: Option<&'a str>,
+    pub conversation_id: Option<Uuid>,
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+    pub duration_ms: i32,
+    /// Facts retrieved while answering, most relevant first
+    pub source_fact_ids: &'a [Uuid],
+}
+
+/// Record an answered query with its estimated cost, returning its ID (the
+/// ID feedback on the answer is given against).
+pub async fn record_query(pool: &PgPool, usage: QueryUsage<'_>) -> Result<Uuid> {
+    let cost = usage
+        .model_id
+        .map(|model| estimate_cost(model, usage.input_tokens, usage.output_tokens))
+        .unwrap_or(0.0);
+
+    let id = sqlx::query_scalar(
+        r#"
+        INSERT INTO query_sessions (user_id, source, query_text, response_text, agents_used,
+                                    model_id, conversation_id, input_tokens, output_tokens,
+                                    estimated_cost_usd, duration_ms, source_fact_ids,
+                                    started_at, completed_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10::float8,
+                $11, $12, NOW() - $11 * INTERVAL '1 millisecond', NOW())
+        RETURNING id
+        "#,
+    )
+    .bind(usage.user_id)
+    .bind(usage.source)
+    .bind(usage.query)
+    .bind(usage.response)
+    .bind(usage.agents_used)
+    .bind(usage.model_id)
+    .bind(usage.conversation_id)
+    .bind(usage.input_tokens.min(i32::MAX as u32) as i32)
+    .bind(usage.output_tokens.min(i32::MAX as u32) as i32)
+    .bind(cost)
+    .bind(usage.duration_ms)
+    .bind(usage.source_fact_ids)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(id)
+}
+
+/// Start and end (exclusive) of the month `month` names, as `YYYY-MM`.
+pub fn month_bounds(month: &str) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let start = NaiveDate::parse_from_str(&format!("{}-01", month.trim()), "%Y-%m-%d")
+        .map_err(|_| Error::Validation("month must be YYYY-MM".to_string()))?;
+    let end = if start.month() == 12 {
+        NaiveDate::from_ymd_opt(start.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(start.year(), start.month() + 1, 1)
+    }
+    .ok_or_else(|| Error::Validation("month is out of range".to_string()))?;
+
+    Ok((
+        start.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc(),
+        end.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc(),
+    ))
+}
+
+/// The current month, as `YYYY-MM`.
+pub fn current_month() -> String {
+    Utc::now().format("%Y-%m").to_string()
+}
+
+/// Usage of one model in a month.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelUsage {
+    /// `unknown` for queries whose model wasn't reported
+    pub model_id: String,
+    pub queries: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub estimated_cost_usd: f64,
+}
+
+/// A user's usage in a month.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MonthlyUsage {
+    /// `YYYY-MM`
+    pub month: String,
+    pub queries: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub estimated_cost_usd: f64,
+    pub avg_latency_ms: Option<f64>,
+    /// Most expensive first
+    pub by_model: Vec<ModelUsage>,
+}
+
+/// `user_id`'s usage in `month` (`YYYY-MM`).
+pub async fn monthly_usage(pool: &PgPool, user_id: Uuid, month: &str) -> Result<MonthlyUsage> {
+    let (start, end) = month_bounds(month)?;
+
+    let by_model: Vec<ModelUsage> = sqlx::query_as(
+        r#"
+        SELECT COALESCE(model_id, 'unknown') AS model_id,
+               COUNT(*) AS queries,
+               COALESCE(SUM(input_tokens), 0)::int8 AS input_tokens,
+               COALESCE(SUM(output_tokens), 0)::int8 AS output_tokens,
+               COALESCE(SUM(estimated_cost_usd), 0)::float8 AS estimated_cost_usd
+        FROM query_sessions
+        WHERE user_id = $1 AND started_at >= $2 AND started_at < $3
+        GROUP BY 1
+        ORDER BY estimated_cost_usd DESC, model_id
+        "#,
+    )
+    .bind(user_id)
+    .bind(start)
+    .bind(end)
+    .fetch_all(pool)
+    .await?;
+
+    let avg_latency_ms: Option<f64> = sqlx::query_scalar(
+        r#"
+        SELECT AVG(duration_ms)::float8
+        FROM query_sessions
+        WHERE user_id = $1 AND started_at >= $2 AND started_at < $3
+        "#,
+    )
+    .bind(user_id)
+    .bind(start)
+    .bind(end)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(MonthlyUsage {
+        month: start.format("%Y-%m").to_string(),
+        queries: by_model.iter().map(|m| m.queries).sum(),
+        input_tokens: by_model.iter().map(|m| m.input_tokens).sum(),
+        output_tokens: by_model.iter().map(|m| m.output_tokens).sum(),
+        estimated_cost_usd: by_model.iter().map(|m| m.estimated_cost_usd).sum(),
+        avg_latency_ms,
+        by_model,
+    })
+}
+
+/// A fact an answer was based on.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QuerySource {
+    pub fact_id: Uuid,
+    pub content: String,
+    /// The entity the fact is about, if any
+    pub entity_name: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// A past answer with the facts it cited.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PastQuery {
+    pub id: Uuid,
+    pub query: String,
+    pub response: Option<String>,
+    /// Where it was asked (`api`, `slack`, `discord`, ...)
+    pub source: String,
+    pub conversation_id: Option<Uuid>,
+    pub agents_used: Vec<String>,
+    pub model_id: Option<String>,
+    pub duration_ms: Option<i32>,
+    pub asked_at: DateTime<Utc>,
+    /// Most relevant first; facts since deleted or no longer visible to the
+    /// user are left out
+    pub sources: Vec<QuerySource>,
+}
+
+#[derive(sqlx::FromRow)]
+struct QueryRow {
+    id: Uuid,
+    query_text: String,
+    response_text: Option<String>,
+    source: String,
+    conversation_id: Option<Uuid>,
+    agents_used: Option<Vec<String>>,
+    model_id: Option<String>,
+    duration_ms: Option<i32>,
+    started_at: DateTime<Utc>,
+}
+
+/// One of `user_id`'s past queries with its sources, or `None` if it isn't
+/// theirs.
+pub async fn past_query(
+    pool: &PgPool,
+    user_id: Uuid,
+    family_ids: &[Uuid],
+    query_id: Uuid,
+) -> Result<Option<PastQuery>> {
+    let row: Option<QueryRow> = sqlx::query_as(
+        r#"
+        SELECT id, query_text, response_text, source, conversation_id, agents_used,
+               model_id, duration_ms, started_at
+        FROM query_sessions
+        WHERE id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(query_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    let sources: Vec<QuerySource> = sqlx::query_as(&format!(
+        r#"
+        SELECT f.id AS fact_id, f.content, e.name AS entity_name, f.recorded_at
+        FROM query_sessions qs
+        CROSS JOIN LATERAL unnest(qs.source_fact_ids) WITH ORDINALITY AS s(fact_id, rank)
+        JOIN facts f ON f.id = s.fact_id
+        LEFT JOIN entities e ON e.id = f.about_entity_id
+        WHERE qs.id = $1
+        AND f.deleted_at IS NULL
+        AND {}
+        ORDER BY s.rank
+        "#,
+        visibility_clause("f", 2)
+    ))
+    .bind(query_id)
+    .bind(user_id)
+    .bind(family_ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(Some(PastQuery {
+        id: row.id,
+        query: row.query_text,
+        response: row.response_text,
+        source: row.source,
+        conversation_id: row.conversation_id,
+        agents_used: row.agents_used.unwrap_or_default(),
+        model_id: row.model_id,
+        duration_ms: row.duration_ms,
+        asked_at: row.started_at,
+        sources,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prices_match_model_families() {
+        assert_eq!(
+            price_for("us.anthropic.claude-3-haiku-20240307-v1:0"),
+            Some(PRICES[0].1)
+        );
+        assert_eq!(
+            price_for("anthropic.claude-3-5-sonnet-20241022-v2:0"),
+            Some(PRICES[1].1)
+        );
+        assert_eq!(price_for("amazon.titan-embed-text-v2:0"), None);
+    }
+
+    #[test]
+    fn cost_is_per_thousand_tokens() {
+        let cost = estimate_cost("anthropic.claude-3-5-sonnet-20241022-v2:0", 2000, 1000);
+        assert!((cost - (0.006 + 0.015)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn unknown_models_cost_nothing() {
+        assert_eq!(estimate_cost("some-other-model", 5000, 5000), 0.0);
+    }
+
+    #[test]
+    fn month_bounds_span_the_month() {
+        let (start, end) = month_bounds("2026-02").unwrap();
+        assert_eq!(start.to_rfc3339(), "2026-02-01T00:00:00+00:00");
+        assert_eq!(end.to_rfc3339(), "2026-03-01T00:00:00+00:00");
+    }
+
+    #[test]
+    fn december_ends_in_january() {
+        let (_, end) = month_bounds("2026-12").unwrap();
+        assert_eq!(end.to_rfc3339(), "2027-01-01T00:00:00+00:00");
+    }
+
+    #[test]
+    fn invalid_months_are_rejected() {
+        assert!(month_bounds("2026-13").is_err());
+        assert!(month_bounds("March").is_err());
+    }
+}
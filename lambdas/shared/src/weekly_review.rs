@@ -0,0 +1,418 @@
+//! Weekly review emails.
+//!
+//! Once a week, on the user's chosen day at their evening briefing time,
+//! `weekly_review` gathers the facts they added, the entities they created and
+//! the reminders they completed over the past seven days, asks the agent for a
+//! short narrative, and emails an HTML summary with links back to the app.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Timelike, Utc};
+use chrono_tz::Tz;
+use uuid::Uuid;
+
+/// Days covered by a review, ending on the review day
+pub const REVIEW_DAYS: i64 = 7;
+
+/// Most items listed per section; the rest are counted
+pub const MAX_REVIEW_ITEMS: usize = 10;
+
+/// Longest item shown in a review
+const MAX_ITEM_CHARS: usize = 160;
+
+/// User preferences that decide when the review goes out.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ReviewPreferences {
+    /// 0 = Sunday ... 6 = Saturday
+    pub weekly_review_day: i16,
+    pub evening_briefing_time: NaiveTime,
+    pub timezone: String,
+}
+
+impl Default for ReviewPreferences {
+    /// Defaults used when a user has no preferences row.
+    fn default() -> Self {
+        Self {
+            weekly_review_day: 0,
+            evening_briefing_time: NaiveTime::from_hms_opt(18, 0, 0).unwrap_or_default(),
+            timezone: "America/New_York".to_string(),
+        }
+    }
+}
+
+/// The local date of the review due at `now`, if any.
+///
+/// A review is due on the review day from the evening briefing hour until
+/// midnight, so an hourly run that fails to send is retried by the next one.
+/// Unknown timezones fall back to UTC.
+pub fn due_week_ending(prefs: &ReviewPreferences, now: DateTime<Utc>) -> Option<NaiveDate> {
+    let local = match prefs.timezone.parse::<Tz>() {
+        Ok(tz) => now.with_timezone(&tz).naive_local(),
+        Err(_) => now.naive_utc(),
+    };
+
+    let is_review_day = local.weekday().num_days_from_sunday() as i16 == prefs.weekly_review_day;
+    (is_review_day && local.hour() >= prefs.evening_briefing_time.hour()).then(|| local.date())
+}
+
+/// A fact, entity or reminder listed in a review
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ReviewItem {
+    pub id: Uuid,
+    pub title: String,
+}
+
+/// What a user did over the review week
+#[derive(Debug, Clone, Default)]
+pub struct WeekSummary {
+    /// Most important facts added, with the total count
+    pub facts: Vec<ReviewItem>,
+    pub fact_count: i64,
+    /// Newest entities created, with the total count
+    pub entities: Vec<ReviewItem>,
+    pub entity_count: i64,
+    /// Reminders completed, with the total count of completions
+    pub reminders: Vec<ReviewItem>,
+    pub reminder_count: i64,
+}
+
+impl WeekSummary {
+    /// Nothing happened; no review is sent.
+    pub fn is_empty(&self) -> bool {
+        self.fact_count == 0 && self.entity_count == 0 && self.reminder_count == 0
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => format!("{}…", text[..idx].trim_end()),
+        None => text.to_string(),
+    }
+}
+
+fn plural(count: i64, singular: &str, plural: &str) -> String {
+    if count == 1 {
+        format!("1 {}", singular)
+    } else {
+        format!("{} {}", count, plural)
+    }
+}
+
+/// "Oct 10 – Oct 16" for the week ending on `week_ending`
+fn week_range(week_ending: NaiveDate) -> String {
+    let start = week_ending - Duration::days(REVIEW_DAYS - 1);
+    format!(
+        "{} – {}",
+        start.format("%b %-d"),
+        week_ending.format("%b %-d")
+    )
+}
+
+/// Email subject for the week ending on `week_ending`.
+pub fn subject(week_ending: NaiveDate) -> String {
+    format!("Your week in review ({})", week_range(week_ending))
+}
+
+/// Message asking the agent for the review's narrative.
+pub fn narrative_prompt(summary: &WeekSummary) -> String {
+    let mut prompt = format!(
+        "Write a short, friendly summary of my past week for my weekly review email, \
+         in two or three sentences of plain text. This week I added {}, created {} and \
+         completed {}.",
+        plural(summary.fact_count, "fact", "facts"),
+        plural(summary.entity_count, "entity", "entities"),
+        plural(summary.reminder_count, "reminder", "reminders"),
+    );
+
+    for section in sections(summary) {
+        if section.items.is_empty() {
+            continue;
+        }
+        prompt.push_str(&format!("\n\n{}:", section.heading));
+        for item in section.items.iter().take(MAX_REVIEW_ITEMS) {
+            prompt.push_str(&format!("\n- {}", truncate(&item.title, MAX_ITEM_CHARS)));
+        }
+    }
+
+    prompt
+}
+
+/// Escape text for HTML element content and attribute values.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A review section: heading, items, total count and the app path items link to
+struct Section<'a> {
+    heading: &'static str,
+    items: &'a [ReviewItem],
+    count: i64,
+    path: &'static str,
+}
+
+fn sections(summary: &WeekSummary) -> [Section<'_>; 3] {
+    [
+        Section {
+            heading: "Facts added",
+            items: &summary.facts,
+            count: summary.fact_count,
+            path: "facts",
+        },
+        Section {
+            heading: "New entities",
+            items: &summary.entities,
+            count: summary.entity_count,
+            path: "entities",
+        },
+        Section {
+            heading: "Reminders completed",
+            items: &summary.reminders,
+            count: summary.reminder_count,
+            path: "reminders",
+        },
+    ]
+}
+
+/// HTML email body. Items link to their page under `app_url`.
+pub fn render_html(
+    summary: &WeekSummary,
+    narrative: Option<&str>,
+    week_ending: NaiveDate,
+    app_url: &str,
+) -> String {
+    let app_url = app_url.trim_end_matches('/');
+    let mut html = String::new();
+
+    html.push_str(
+        "<!DOCTYPE html>\n<html>\n<body style=\"margin:0;padding:24px;background:#f6f7f9;\
+         font-family:-apple-system,'Segoe UI',Helvetica,Arial,sans-serif;color:#1f2933;\">\n\
+         <div style=\"max-width:600px;margin:0 auto;background:#ffffff;border-radius:8px;padding:24px;\">\n",
+    );
+    html.push_str(&format!(
+        "<h1 style=\"font-size:22px;margin:0 0 4px;\">Your week in review</h1>\n\
+         <p style=\"margin:0 0 16px;color:#616e7c;\">{}</p>\n",
+        escape_html(&week_range(week_ending))
+    ));
+
+    if let Some(narrative) = narrative.map(str::trim).filter(|n| !n.is_empty()) {
+        for paragraph in narrative
+            .split("\n\n")
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+        {
+            html.push_str(&format!(
+                "<p style=\"margin:0 0 12px;line-height:1.5;\">{}</p>\n",
+                escape_html(paragraph).replace('\n', "<br>")
+            ));
+        }
+    }
+
+    for section in sections(summary) {
+        if section.count == 0 {
+            continue;
+        }
+
+        html.push_str(&format!(
+            "<h2 style=\"font-size:16px;margin:20px 0 8px;\">{} ({})</h2>\n<ul style=\"margin:0;padding-left:20px;\">\n",
+            section.heading, section.count
+        ));
+        for item in section.items.iter().take(MAX_REVIEW_ITEMS) {
+            html.push_str(&format!(
+                "<li style=\"margin:0 0 6px;\"><a href=\"{}\" style=\"color:#2563eb;text-decoration:none;\">{}</a></li>\n",
+                escape_html(&format!("{}/{}/{}", app_url, section.path, item.id)),
+                escape_html(&truncate(&item.title, MAX_ITEM_CHARS))
+            ));
+        }
+        html.push_str("</ul>\n");
+
+        let shown = section.items.len().min(MAX_REVIEW_ITEMS) as i64;
+        if section.count > shown {
+            html.push_str(&format!(
+                "<p style=\"margin:4px 0 0;\"><a href=\"{}\" style=\"color:#2563eb;\">…and {} more</a></p>\n",
+                escape_html(&format!("{}/{}", app_url, section.path)),
+                section.count - shown
+            ));
+        }
+    }
+
+    html.push_str(&format!(
+        "<p style=\"margin:24px 0 0;font-size:13px;color:#616e7c;\">\
+         <a href=\"{}\" style=\"color:#2563eb;\">Open Second Brain</a> · \
+         <a href=\"{}\" style=\"color:#2563eb;\">Notification settings</a></p>\n\
+         </div>\n</body>\n</html>\n",
+        escape_html(app_url),
+        escape_html(&format!("{}/settings/notifications", app_url))
+    ));
+
+    html
+}
+
+/// Plain-text alternative to [`render_html`].
+pub fn render_text(
+    summary: &WeekSummary,
+    narrative: Option<&str>,
+    week_ending: NaiveDate,
+    app_url: &str,
+) -> String {
+    let app_url = app_url.trim_end_matches('/');
+    let mut text = format!("Your week in review ({})", week_range(week_ending));
+
+    if let Some(narrative) = narrative.map(str::trim).filter(|n| !n.is_empty()) {
+        text.push_str(&format!("\n\n{}", narrative));
+    }
+
+    for section in sections(summary) {
+        if section.count == 0 {
+            continue;
+        }
+
+        text.push_str(&format!("\n\n{} ({})", section.heading, section.count));
+        for item in section.items.iter().take(MAX_REVIEW_ITEMS) {
+            text.push_str(&format!("\n• {}", truncate(&item.title, MAX_ITEM_CHARS)));
+        }
+
+        let shown = section.items.len().min(MAX_REVIEW_ITEMS) as i64;
+        if section.count > shown {
+            text.push_str(&format!("\n…and {} more", section.count - shown));
+        }
+    }
+
+    text.push_str(&format!("\n\nOpen Second Brain: {}", app_url));
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn item(title: &str) -> ReviewItem {
+        ReviewItem {
+            id: Uuid::nil(),
+            title: title.to_string(),
+        }
+    }
+
+    fn prefs(day: i16, timezone: &str) -> ReviewPreferences {
+        ReviewPreferences {
+            weekly_review_day: day,
+            timezone: timezone.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_due_week_ending() {
+        // Sunday 2026-10-18: 22:00 UTC is 18:00 in New York (EDT)
+        let sunday_evening = Utc.with_ymd_and_hms(2026, 10, 18, 22, 30, 0).unwrap();
+        let sunday_noon = Utc.with_ymd_and_hms(2026, 10, 18, 16, 0, 0).unwrap();
+        let monday_night = Utc.with_ymd_and_hms(2026, 10, 20, 3, 0, 0).unwrap();
+        let sunday = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+
+        let ny = prefs(0, "America/New_York");
+        assert_eq!(due_week_ending(&ny, sunday_evening), Some(sunday));
+        assert_eq!(due_week_ending(&ny, sunday_noon), None);
+        assert_eq!(due_week_ending(&ny, monday_night), None);
+
+        // Still Sunday 23:00 in New York at 03:00 UTC Monday
+        let late = Utc.with_ymd_and_hms(2026, 10, 19, 3, 0, 0).unwrap();
+        assert_eq!(due_week_ending(&ny, late), Some(sunday));
+
+        let utc = prefs(1, "Not/AZone");
+        let monday = Utc.with_ymd_and_hms(2026, 10, 19, 18, 0, 0).unwrap();
+        assert_eq!(
+            due_week_ending(&utc, monday),
+            NaiveDate::from_ymd_opt(2026, 10, 19)
+        );
+    }
+
+    #[test]
+    fn test_subject() {
+        let week_ending = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+        assert_eq!(
+            subject(week_ending),
+            "Your week in review (Oct 12 – Oct 18)"
+        );
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html("<b>Tom & \"Jerry's\"</b>"),
+            "&lt;b&gt;Tom &amp; &quot;Jerry&#39;s&quot;&lt;/b&gt;"
+        );
+    }
+
+    #[test]
+    fn test_narrative_prompt() {
+        let summary = WeekSummary {
+            facts: vec![item("Mia starts swim lessons on Tuesday")],
+            fact_count: 1,
+            reminders: vec![item("Renew passport"), item("Water plants")],
+            reminder_count: 2,
+            ..Default::default()
+        };
+
+        let prompt = narrative_prompt(&summary);
+        assert!(prompt.contains("added 1 fact, created 0 entities and completed 2 reminders"));
+        assert!(prompt.contains("Facts added:\n- Mia starts swim lessons on Tuesday"));
+        assert!(prompt.contains("Reminders completed:\n- Renew passport\n- Water plants"));
+        assert!(!prompt.contains("New entities"));
+    }
+
+    #[test]
+    fn test_render_html() {
+        let summary = WeekSummary {
+            facts: vec![item("Tom <3 pizza")],
+            fact_count: 12,
+            ..Default::default()
+        };
+        let week_ending = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+
+        let html = render_html(
+            &summary,
+            Some("A busy week.\n\nMostly food."),
+            week_ending,
+            "https://app.example.com/",
+        );
+        assert!(html.contains("<p style=\"margin:0 0 12px;line-height:1.5;\">A busy week.</p>"));
+        assert!(html.contains("Facts added (12)"));
+        assert!(html.contains(&format!(
+            "href=\"https://app.example.com/facts/{}\"",
+            Uuid::nil()
+        )));
+        assert!(html.contains("Tom &lt;3 pizza"));
+        assert!(html.contains("…and 11 more"));
+        assert!(!html.contains("New entities"));
+        assert!(html.contains("https://app.example.com/settings/notifications"));
+    }
+
+    #[test]
+    fn test_render_text() {
+        let summary = WeekSummary {
+            entities: vec![item("Dr. Smith")],
+            entity_count: 1,
+            ..Default::default()
+        };
+        let week_ending = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+
+        assert_eq!(
+            render_text(&summary, None, week_ending, "https://app.example.com"),
+            "Your week in review (Oct 12 – Oct 18)\n\n\
+             New entities (1)\n• Dr. Smith\n\n\
+             Open Second Brain: https://app.example.com"
+        );
+        assert!(WeekSummary::default().is_empty());
+        assert!(!summary.is_empty());
+    }
+}
@@ -0,0 +1,171 @@
+//! Block Kit rendering for agent responses.
+//!
+//! Slack renders its own `mrkdwn` rather than Markdown and caps section text at
+//! 3000 characters and messages at 50 blocks. Answers are converted with
+//! [`to_mrkdwn`], split into sections and rendered by [`answer_blocks`].
+
+use serde_json::{json, Value};
+use shared::markdown::{heading, list_item, rewrite_links, split, truncate, FENCE};
+
+/// Longest section text Slack accepts
+pub const SECTION_TEXT_MAX_CHARS: usize = 3000;
+
+/// Longest header text Slack accepts
+const HEADER_TEXT_MAX_CHARS: usize = 150;
+
+/// Blocks per message
+const MAX_BLOCKS: usize = 50;
+
+/// `action_id` of the "Save this" button on answers
+pub const SAVE_ACTION_ID: &str = "save";
+
+/// Longest button value Slack accepts (the text saved by "Save this")
+pub const SAVE_VALUE_MAX_CHARS: usize = 2000;
+
+/// Convert Markdown to Slack `mrkdwn`.
+///
+/// - Headings become bold lines
+/// - `**bold**` becomes `*bold*` and `~~strike~~` becomes `~strike~`
+/// - `[label](url)` becomes `<url|label>`
+/// - `-`/`*` list items become bullets
+/// - `&`, `<` and `>` are escaped (except a leading `>` quote)
+pub fn to_mrkdwn(text: &str) -> String {
+    let mut in_code = false;
+
+    text.lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with(FENCE) {
+                in_code = !in_code;
+                return trimmed.to_string();
+            }
+            if in_code {
+                return escape(line);
+            }
+
+            let indent = &line[..line.len() - trimmed.len()];
+            if let Some((_, heading)) = heading(trimmed) {
+                format!("*{}*", inline(heading))
+            } else if let Some(item) = list_item(trimmed) {
+                format!("{}• {}", indent, inline(item))
+            } else if let Some(quote) = trimmed.strip_prefix("> ") {
+                format!("> {}", inline(quote))
+            } else {
+                inline(line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Convert inline Markdown (emphasis and links) on an unescaped line.
+fn inline(text: &str) -> String {
+    let text = escape(text).replace("**", "*").replace("~~", "~");
+    rewrite_links(&text, |label, url| format!("<{}|{}>", url, label))
+}
+
+/// Render an answer as Block Kit blocks: an optional header, the answer in
+/// sections and, if `save` is set, a "Save this" button carrying the answer.
+pub fn answer_blocks(title: Option<&str>, text: &str, save: bool) -> Vec<Value> {
+    let mut blocks = Vec::new();
+
+    if let Some(title) = title.map(str::trim).filter(|t| !t.is_empty()) {
+        blocks.push(json!({
+            "type": "header",
+            "text": { "type": "plain_text", "text": truncate(title, HEADER_TEXT_MAX_CHARS) },
+        }));
+    }
+
+    let reserved = blocks.len() + usize::from(save);
+    let mut sections = split(&to_mrkdwn(text), SECTION_TEXT_MAX_CHARS);
+    if sections.len() > MAX_BLOCKS - reserved {
+        sections.truncate(MAX_BLOCKS - reserved);
+        if let Some(last) = sections.last_mut() {
+            // More follows, so always mark the cut
+            *last = last
+                .chars()
+                .take(SECTION_TEXT_MAX_CHARS - 1)
+                .collect::<String>()
+                + "…";
+        }
+    }
+
+    blocks.extend(sections.into_iter().map(|section| {
+        json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": section },
+        })
+    }));
+
+    if save {
+        blocks.push(json!({
+            "type": "actions",
+            "elements": [{
+                "type": "button",
+                "text": { "type": "plain_text", "text": "Save this" },
+                "action_id": SAVE_ACTION_ID,
+                "value": truncate(text.trim(), SAVE_VALUE_MAX_CHARS),
+            }],
+        }));
+    }
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_mrkdwn() {
+        assert_eq!(to_mrkdwn("## Plans"), "*Plans*");
+        assert_eq!(to_mrkdwn("**Friday** at 6"), "*Friday* at 6");
+        assert_eq!(to_mrkdwn("- one\n  * two"), "• one\n  • two");
+        assert_eq!(
+            to_mrkdwn("See [the docs](https://example.com/a?b=1&c=2)"),
+            "See <https://example.com/a?b=1&amp;c=2|the docs>"
+        );
+        assert_eq!(to_mrkdwn("> quoted <b>"), "> quoted &lt;b&gt;");
+        assert_eq!(to_mrkdwn("[not a link] (x)"), "[not a link] (x)");
+        // Code blocks are only escaped
+        assert_eq!(to_mrkdwn("```\n## **x**\n```"), "```\n## **x**\n```");
+    }
+
+    #[test]
+    fn test_answer_blocks() {
+        let blocks = answer_blocks(Some("When is the recital?"), "Friday at 6pm", true);
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[0]["type"], "header");
+        assert_eq!(blocks[1]["text"]["text"], "Friday at 6pm");
+        assert_eq!(blocks[2]["elements"][0]["action_id"], SAVE_ACTION_ID);
+        assert_eq!(blocks[2]["elements"][0]["value"], "Friday at 6pm");
+
+        let blocks = answer_blocks(None, "Saved.", false);
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0]["type"], "section");
+    }
+
+    #[test]
+    fn test_answer_blocks_within_limits() {
+        let long = "word ".repeat(100_000);
+        let blocks = answer_blocks(Some("Title"), &long, true);
+        assert_eq!(blocks.len(), MAX_BLOCKS);
+        assert!(
+            blocks
+                .iter()
+                .filter(|b| b["type"] == "section")
+                .all(|b| b["text"]["text"].as_str().unwrap().chars().count()
+                    <= SECTION_TEXT_MAX_CHARS)
+        );
+        let value = blocks[MAX_BLOCKS - 1]["elements"][0]["value"]
+            .as_str()
+            .unwrap();
+        assert!(value.chars().count() <= SAVE_VALUE_MAX_CHARS);
+    }
+}
@@ -0,0 +1,93 @@
+//! Parsing `/brain` slash commands and app mentions.
+//!
+//! `/brain ask <question>`, `/brain remember <fact>` and `/brain help`; anything
+//! else is treated as a question, so `/brain when is Emma's recital?` works too.
+//! Mentions (`@Second Brain remember ...`) take the same forms.
+
+/// What a `/brain` command or mention asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrainCommand<'a> {
+    Ask(&'a str),
+    Remember(&'a str),
+    Help,
+}
+
+/// Usage shown for `/brain help` and empty commands
+pub const HELP_TEXT: &str = "*Second Brain*\n\
+• `/brain ask <question>` - Ask about what you've saved\n\
+• `/brain remember <fact>` - Save something\n\
+• `/brain <question>` - Same as `ask`\n\
+Mention me in a channel to ask or remember there; I'll answer in a thread.";
+
+/// Parse the text after `/brain` (or a mention).
+pub fn parse(text: &str) -> BrainCommand<'_> {
+    let text = text.trim();
+    let (verb, rest) = match text.split_once(char::is_whitespace) {
+        Some((verb, rest)) => (verb, rest.trim()),
+        None => (text, ""),
+    };
+
+    match verb.to_lowercase().as_str() {
+        "" | "help" => BrainCommand::Help,
+        "ask" | "query" if rest.is_empty() => BrainCommand::Help,
+        "ask" | "query" => BrainCommand::Ask(rest),
+        "remember" | "save" if rest.is_empty() => BrainCommand::Help,
+        "remember" | "save" => BrainCommand::Remember(rest),
+        _ => BrainCommand::Ask(text),
+    }
+}
+
+/// Remove user mentions (`<@U123>`) from an app mention's text.
+pub fn strip_mentions(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("<@") {
+        match rest[start..].find('>') {
+            Some(end) => {
+                out.push_str(&rest[..start]);
+                rest = &rest[start + end + 1..];
+            }
+            None => break,
+        }
+    }
+    out.push_str(rest);
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("ask when is the recital?"),
+            BrainCommand::Ask("when is the recital?")
+        );
+        assert_eq!(
+            parse("Remember  Emma's recital is Friday"),
+            BrainCommand::Remember("Emma's recital is Friday")
+        );
+        assert_eq!(parse("save x"), BrainCommand::Remember("x"));
+        assert_eq!(
+            parse("when is the recital?"),
+            BrainCommand::Ask("when is the recital?")
+        );
+        assert_eq!(parse(""), BrainCommand::Help);
+        assert_eq!(parse("help"), BrainCommand::Help);
+        assert_eq!(parse("remember"), BrainCommand::Help);
+    }
+
+    #[test]
+    fn test_strip_mentions() {
+        assert_eq!(
+            strip_mentions("<@U0BRAIN> remember the code is 1234"),
+            "remember the code is 1234"
+        );
+        assert_eq!(
+            strip_mentions("hey <@U0BRAIN>  what's up?"),
+            "hey what's up?"
+        );
+        assert_eq!(strip_mentions("no mention"), "no mention");
+    }
+}
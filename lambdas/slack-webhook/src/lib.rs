@@ -0,0 +1,5 @@
+//! Slack app support shared by the webhook Lambda.
+
+pub mod blocks;
+pub mod commands;
+pub mod signature;
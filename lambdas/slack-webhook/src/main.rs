@@ -0,0 +1,770 @@
+//! Slack Webhook Lambda - Handles Slack app requests.
+//!
+//! One endpoint receives everything the Slack app sends, dispatched on the body:
+//! - Slash commands (`/brain ask|remember|help ...`, see [`commands`])
+//! - Events API callbacks: `url_verification` and `app_mention`
+//! - Interactivity (`payload=` form field): the "Save this" button on answers
+//!
+//! Every request is verified with the app's signing secret (see [`signature`]).
+//!
+//! Slack expects an acknowledgement within 3 seconds, so the work is handed to an
+//! async self-invocation ([`FollowUpPayload`]), signed so it can't be forged
+//! (see `shared::auth::sign_internal`). Its result is posted to the
+//! request's `response_url`, or for mentions as a thread reply with `chat.postMessage`.
+//! Answers are rendered as Block Kit (see [`blocks`]).
+//!
+//! Slack users are matched to registered users by `users.slack_user_id`
+//! (`{team_id}:{user_id}`). On first use the Slack profile email is matched to
+//! the account email and the link is recorded; this needs the `users:read` and
+//! `users:read.email` scopes.
+
+use aws_sdk_lambda::primitives::Blob;
+use lambda_runtime::{service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use shared::agents::DirectFallback;
+use shared::auth::{sign_internal, verify_internal};
+use shared::markdown::truncate;
+use shared::metrics;
+use shared::{AgentClient, AuthenticatedUser, AuthorizedUser};
+use slack_webhook::blocks::{answer_blocks, SAVE_ACTION_ID};
+use slack_webhook::commands::{self, BrainCommand, HELP_TEXT};
+use slack_webhook::signature;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// Slack Web API base URL
+const SLACK_API_BASE: &str = "https://slack.com/api";
+
+/// Reply to anyone whose Slack account doesn't match a registered user
+const UNLINKED_MESSAGE: &str = "I couldn't find a Second Brain account for you. \
+Sign up with the same email address you use in Slack, then try again.";
+
+/// Longest plain-text fallback sent alongside blocks (used in notifications)
+const FALLBACK_TEXT_MAX_CHARS: usize = 300;
+
+/// Operation follow-up invocations are signed for
+const FOLLOW_UP_PATH: &str = "/slack/follow-up";
+
+/// Slash command (form encoded)
+#[derive(Debug, Deserialize)]
+struct SlashCommand {
+    team_id: String,
+    user_id: String,
+    user_name: String,
+    #[serde(default)]
+    text: String,
+    response_url: String,
+}
+
+/// Events API envelope
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum EventEnvelope {
+    UrlVerification {
+        challenge: String,
+    },
+    EventCallback {
+        team_id: String,
+        event: SlackEvent,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// Event inside an `event_callback`
+#[derive(Debug, Deserialize)]
+struct SlackEvent {
+    #[serde(rename = "type")]
+    event_type: String,
+    user: Option<String>,
+    /// Set on messages posted by bots (including our own replies)
+    bot_id: Option<String>,
+    #[serde(default)]
+    text: String,
+    channel: Option<String>,
+    ts: Option<String>,
+    /// Set when the mention is itself in a thread
+    thread_ts: Option<String>,
+}
+
+/// Interactivity request (form encoded, JSON in `payload`)
+#[derive(Debug, Deserialize)]
+struct InteractionForm {
+    payload: String,
+}
+
+/// Block Kit interaction payload
+#[derive(Debug, Deserialize)]
+struct InteractionPayload {
+    #[serde(rename = "type")]
+    payload_type: String,
+    user: InteractionUser,
+    team: Option<InteractionTeam>,
+    response_url: Option<String>,
+    #[serde(default)]
+    actions: Vec<BlockAction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InteractionUser {
+    id: String,
+    #[serde(default)]
+    username: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct InteractionTeam {
+    id: String,
+}
+
+/// Clicked button
+#[derive(Debug, Deserialize)]
+struct BlockAction {
+    action_id: String,
+    value: Option<String>,
+}
+
+/// Payload for async follow-up processing
+#[derive(Debug, Serialize, Deserialize)]
+struct FollowUpPayload {
+    follow_up: bool,
+    /// `ask` or `remember`
+    action: String,
+    text: String,
+    team_id: String,
+    slack_user_id: String,
+    username: String,
+    /// Where to respond: a command or button's `response_url`...
+    response_url: Option<String>,
+    /// ...replacing the acknowledgement it showed
+    #[serde(default)]
+    replace_original: bool,
+    /// ...or a thread in a channel (app mentions)
+    channel: Option<String>,
+    thread_ts: Option<String>,
+}
+
+impl FollowUpPayload {
+    /// The identity the follow-up's signature covers
+    fn signed_user(&self) -> String {
+        format!("{}:{}", self.team_id, self.slack_user_id)
+    }
+}
+
+/// API Gateway proxy request
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiGatewayRequest {
+    headers: Option<std::collections::HashMap<String, String>>,
+    body: Option<String>,
+}
+
+/// API Gateway proxy response
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiGatewayResponse {
+    status_code: u16,
+    headers: std::collections::HashMap<String, String>,
+    body: String,
+    is_base64_encoded: bool,
+}
+
+impl ApiGatewayResponse {
+    fn new(status_code: u16, body: &str, content_type: &str) -> Self {
+        let mut headers = std::collections::HashMap::new();
+        headers.insert("content-type".to_string(), content_type.to_string());
+        Self {
+            status_code,
+            headers,
+            body: body.to_string(),
+            is_base64_encoded: false,
+        }
+    }
+
+    fn json<T: Serialize>(status_code: u16, data: &T) -> Result<Self, Error> {
+        let body = serde_json::to_string(data)?;
+        Ok(Self::new(status_code, &body, "application/json"))
+    }
+}
+
+/// Empty 200 acknowledgement
+fn ack() -> Result<Value, Error> {
+    Ok(serde_json::to_value(ApiGatewayResponse::new(
+        200,
+        "",
+        "text/plain",
+    ))?)
+}
+
+/// Immediate reply visible only to the caller
+fn ephemeral(text: &str) -> Result<Value, Error> {
+    Ok(serde_json::to_value(ApiGatewayResponse::json(
+        200,
+        &serde_json::json!({ "response_type": "ephemeral", "text": text }),
+    )?)?)
+}
+
+/// Application state
+struct AppState {
+    agent_client: AgentClient,
+    lambda_client: aws_sdk_lambda::Client,
+    http_client: reqwest::Client,
+    signing_secret: String,
+    bot_token: String,
+    function_name: String,
+    db_pool: PgPool,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        let lambda_client = aws_sdk_lambda::Client::new(&config);
+        let secrets_client = aws_sdk_secretsmanager::Client::new(&config);
+
+        let agent_function = std::env::var("AGENT_FUNCTION_NAME")
+            .unwrap_or_else(|_| "second-brain-agents".to_string());
+
+        let function_name = std::env::var("AWS_LAMBDA_FUNCTION_NAME")
+            .unwrap_or_else(|_| "second-brain-slack-webhook".to_string());
+
+        let slack_secret_arn =
+            std::env::var("SLACK_SECRET_ARN").map_err(|_| "SLACK_SECRET_ARN not set")?;
+
+        let slack_secret = shared::secrets::get_secret(&secrets_client, &slack_secret_arn)
+            .await
+            .map_err(|e| format!("Failed to get Slack secret: {}", e))?;
+
+        let slack_creds: Value = serde_json::from_str(&slack_secret)?;
+
+        let signing_secret = slack_creds["signing_secret"]
+            .as_str()
+            .filter(|s| !s.is_empty())
+            .ok_or("signing_secret missing from Slack secret")?
+            .to_string();
+        let bot_token = slack_creds["bot_token"]
+            .as_str()
+            .filter(|t| !t.is_empty())
+            .ok_or("bot_token missing from Slack secret")?
+            .to_string();
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        // Simple questions are answered from the database while the agents
+        // are down, and every answer is logged for GET /v1/queries/{id}
+        let agent_client = AgentClient::new(lambda_client.clone(), agent_function)
+            .with_fallback(DirectFallback::from_env(&config, db_pool.clone()))
+            .with_query_log(db_pool.clone());
+
+        Ok(Self {
+            agent_client,
+            lambda_client,
+            http_client: reqwest::Client::new(),
+            signing_secret,
+            bot_token,
+            function_name,
+            db_pool,
+        })
+    }
+
+    /// Invoke self asynchronously for follow-up processing
+    async fn invoke_follow_up(&self, payload: &FollowUpPayload) -> Result<(), Error> {
+        let payload = sign_internal(payload, "POST", FOLLOW_UP_PATH, &payload.signed_user(), &[])
+            .await
+            .map_err(|e| format!("Failed to sign follow-up: {}", e))?;
+        let payload_json = serde_json::to_vec(&payload)?;
+
+        self.lambda_client
+            .invoke()
+            .function_name(&self.function_name)
+            .invocation_type(aws_sdk_lambda::types::InvocationType::Event)
+            .payload(Blob::new(payload_json))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to invoke follow-up: {}", e))?;
+
+        info!("Follow-up invocation triggered");
+        Ok(())
+    }
+
+    /// Call a Slack Web API method that takes a JSON body
+    async fn call_api(&self, method: &str, body: &Value) -> Result<Value, Error> {
+        let request = self
+            .http_client
+            .post(format!("{}/{}", SLACK_API_BASE, method))
+            .json(body);
+        self.send_api(method, request).await
+    }
+
+    /// Send a Slack Web API request as the bot, failing unless Slack reports `ok`
+    async fn send_api(
+        &self,
+        method: &str,
+        request: reqwest::RequestBuilder,
+    ) -> Result<Value, Error> {
+        let response: Value = request
+            .bearer_auth(&self.bot_token)
+            .send()
+            .await
+            .map_err(|e| format!("Slack {} failed: {}", method, e))?
+            .json()
+            .await
+            .map_err(|e| format!("Slack {} failed: {}", method, e))?;
+
+        if response["ok"].as_bool() != Some(true) {
+            let reason = response["error"].as_str().unwrap_or("unknown error");
+            return Err(format!("Slack {} failed: {}", method, reason).into());
+        }
+
+        Ok(response)
+    }
+
+    /// Deliver a follow-up message to wherever the request came from
+    async fn respond(&self, payload: &FollowUpPayload, mut message: Value) -> Result<(), Error> {
+        if let Some(response_url) = &payload.response_url {
+            message["response_type"] = serde_json::json!("ephemeral");
+            message["replace_original"] = serde_json::json!(payload.replace_original);
+
+            let response = self
+                .http_client
+                .post(response_url)
+                .json(&message)
+                .send()
+                .await
+                .map_err(|e| format!("Failed to send response: {}", e))?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                error!("Slack response_url failed: {} - {}", status, body);
+                return Err(format!("Slack response_url failed: {}", status).into());
+            }
+        } else if let Some(channel) = &payload.channel {
+            message["channel"] = serde_json::json!(channel);
+            if let Some(thread_ts) = &payload.thread_ts {
+                message["thread_ts"] = serde_json::json!(thread_ts);
+            }
+            self.call_api("chat.postMessage", &message).await?;
+        } else {
+            warn!("Follow-up has nowhere to respond");
+        }
+
+        Ok(())
+    }
+}
+
+/// Case-insensitive header lookup
+fn header<'a>(
+    headers: &'a std::collections::HashMap<String, String>,
+    name: &str,
+) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+async fn handler(state: Arc<AppState>, event: LambdaEvent<Value>) -> Result<Value, Error> {
+    let (payload, _context) = event.into_parts();
+
+    // Check if this is a direct follow-up invocation (not from API Gateway)
+    if let Ok(follow_up) = serde_json::from_value::<FollowUpPayload>(payload.clone()) {
+        if follow_up.follow_up {
+            if let Err(e) = verify_internal(
+                &payload,
+                "POST",
+                FOLLOW_UP_PATH,
+                &follow_up.signed_user(),
+                &[],
+            )
+            .await
+            {
+                warn!("Rejected follow-up '{}': {}", follow_up.action, e);
+                return Ok(serde_json::json!({"status": "unauthorized"}));
+            }
+            info!(
+                "Processing follow-up '{}' from user {}",
+                follow_up.action, follow_up.username
+            );
+            return handle_follow_up(state, follow_up).await;
+        }
+    }
+
+    // This is an API Gateway request
+    let api_request: ApiGatewayRequest = serde_json::from_value(payload)?;
+    let body = api_request.body.unwrap_or_default();
+    let headers = api_request.headers.unwrap_or_default();
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+    let verified = signature::verify(
+        &state.signing_secret,
+        header(&headers, "x-slack-request-timestamp").unwrap_or(""),
+        &body,
+        header(&headers, "x-slack-signature").unwrap_or(""),
+        now,
+    );
+
+    if !verified {
+        warn!("Invalid Slack signature");
+        return Ok(serde_json::to_value(ApiGatewayResponse::new(
+            401,
+            "Invalid signature",
+            "text/plain",
+        ))?);
+    }
+
+    // Slack redelivers events it thinks timed out; the first delivery is already handled
+    if header(&headers, "x-slack-retry-num").is_some() {
+        info!("Ignoring Slack retry");
+        return ack();
+    }
+
+    if body.trim_start().starts_with('{') {
+        return handle_event(&state, &body).await;
+    }
+
+    if let Ok(form) = serde_urlencoded::from_str::<InteractionForm>(&body) {
+        return handle_interaction(&state, &form.payload).await;
+    }
+
+    match serde_urlencoded::from_str::<SlashCommand>(&body) {
+        Ok(command) => handle_command(&state, command).await,
+        Err(e) => {
+            error!("Failed to parse Slack request: {}", e);
+            Ok(serde_json::to_value(ApiGatewayResponse::new(
+                400,
+                "Invalid request",
+                "text/plain",
+            ))?)
+        }
+    }
+}
+
+/// `/brain ...`
+async fn handle_command(state: &AppState, command: SlashCommand) -> Result<Value, Error> {
+    info!("Processing /brain from user {}", command.user_name);
+
+    let (action, text) = match commands::parse(&command.text) {
+        BrainCommand::Ask(text) => ("ask", text),
+        BrainCommand::Remember(text) => ("remember", text),
+        BrainCommand::Help => return ephemeral(HELP_TEXT),
+    };
+
+    let follow_up = FollowUpPayload {
+        follow_up: true,
+        action: action.to_string(),
+        text: text.to_string(),
+        team_id: command.team_id,
+        slack_user_id: command.user_id,
+        username: command.user_name,
+        response_url: Some(command.response_url),
+        replace_original: true,
+        channel: None,
+        thread_ts: None,
+    };
+
+    if let Err(e) = state.invoke_follow_up(&follow_up).await {
+        error!("Failed to invoke follow-up: {}", e);
+        return ephemeral("Sorry, something went wrong. Please try again.");
+    }
+
+    ephemeral(if action == "ask" {
+        "Thinking…"
+    } else {
+        "Saving…"
+    })
+}
+
+/// Events API callbacks
+async fn handle_event(state: &AppState, body: &str) -> Result<Value, Error> {
+    let envelope: EventEnvelope = match serde_json::from_str(body) {
+        Ok(envelope) => envelope,
+        Err(e) => {
+            error!("Failed to parse Slack event: {}", e);
+            return ack();
+        }
+    };
+
+    let (team_id, event) = match envelope {
+        EventEnvelope::UrlVerification { challenge } => {
+            info!("Responding to Slack URL verification");
+            return Ok(serde_json::to_value(ApiGatewayResponse::json(
+                200,
+                &serde_json::json!({ "challenge": challenge }),
+            )?)?);
+        }
+        EventEnvelope::EventCallback { team_id, event } => (team_id, event),
+        EventEnvelope::Other => return ack(),
+    };
+
+    if event.event_type != "app_mention" || event.bot_id.is_some() {
+        return ack();
+    }
+
+    let (user, channel, ts) = match (event.user, event.channel, event.ts) {
+        (Some(user), Some(channel), Some(ts)) => (user, channel, ts),
+        _ => return ack(),
+    };
+
+    let text = commands::strip_mentions(&event.text);
+    let (action, text) = match commands::parse(&text) {
+        BrainCommand::Ask(text) => ("ask", text.to_string()),
+        BrainCommand::Remember(text) => ("remember", text.to_string()),
+        BrainCommand::Help => {
+            if let Err(e) = state
+                .call_api(
+                    "chat.postMessage",
+                    &serde_json::json!({
+                        "channel": channel,
+                        "thread_ts": event.thread_ts.unwrap_or(ts),
+                        "text": HELP_TEXT,
+                    }),
+                )
+                .await
+            {
+                error!("Failed to send help: {}", e);
+            }
+            return ack();
+        }
+    };
+
+    let follow_up = FollowUpPayload {
+        follow_up: true,
+        action: action.to_string(),
+        text,
+        team_id,
+        slack_user_id: user.clone(),
+        username: user,
+        response_url: None,
+        replace_original: false,
+        channel: Some(channel),
+        thread_ts: Some(event.thread_ts.unwrap_or(ts)),
+    };
+
+    if let Err(e) = state.invoke_follow_up(&follow_up).await {
+        error!("Failed to invoke follow-up: {}", e);
+    }
+
+    ack()
+}
+
+/// Block Kit interactions
+async fn handle_interaction(state: &AppState, payload: &str) -> Result<Value, Error> {
+    let interaction: InteractionPayload = match serde_json::from_str(payload) {
+        Ok(interaction) => interaction,
+        Err(e) => {
+            error!("Failed to parse Slack interaction: {}", e);
+            return ack();
+        }
+    };
+
+    if interaction.payload_type != "block_actions" {
+        return ack();
+    }
+
+    let save = interaction
+        .actions
+        .iter()
+        .find(|a| a.action_id == SAVE_ACTION_ID)
+        .and_then(|a| a.value.clone())
+        .filter(|v| !v.trim().is_empty());
+
+    let (text, team) = match (save, interaction.team) {
+        (Some(text), Some(team)) => (text, team),
+        _ => return ack(),
+    };
+
+    let follow_up = FollowUpPayload {
+        follow_up: true,
+        action: "remember".to_string(),
+        text,
+        team_id: team.id,
+        slack_user_id: interaction.user.id,
+        username: interaction.user.username,
+        response_url: interaction.response_url,
+        // Keep the answer; confirm underneath it
+        replace_original: false,
+        channel: None,
+        thread_ts: None,
+    };
+
+    if let Err(e) = state.invoke_follow_up(&follow_up).await {
+        error!("Failed to invoke follow-up: {}", e);
+    }
+
+    ack()
+}
+
+/// Email on a Slack user's profile (needs `users:read.email`)
+async fn fetch_slack_email(state: &AppState, user_id: &str) -> Result<Option<String>, Error> {
+    // Read methods don't accept JSON bodies
+    let url = format!("{}/users.info?user={}", SLACK_API_BASE, user_id);
+    let response = state
+        .send_api("users.info", state.http_client.get(url))
+        .await?;
+
+    Ok(response["user"]["profile"]["email"]
+        .as_str()
+        .filter(|e| !e.is_empty())
+        .map(String::from))
+}
+
+/// Resolve a Slack user to their registered account, linking by email on first use.
+async fn resolve_slack_user(
+    state: &AppState,
+    team_id: &str,
+    user_id: &str,
+) -> Result<Option<AuthorizedUser>, Error> {
+    let slack_user_id = format!("{}:{}", team_id, user_id);
+
+    let linked: Option<String> =
+        sqlx::query_scalar("SELECT cognito_sub FROM users WHERE slack_user_id = $1")
+            .bind(&slack_user_id)
+            .fetch_optional(&state.db_pool)
+            .await
+            .map_err(|e| format!("Failed to lookup user: {}", e))?;
+
+    let cognito_sub = match linked {
+        Some(sub) => sub,
+        None => {
+            let email = match fetch_slack_email(state, user_id).await? {
+                Some(email) => email,
+                None => return Ok(None),
+            };
+
+            let matched: Option<String> = sqlx::query_scalar(
+                r#"
+                UPDATE users SET slack_user_id = $1
+                WHERE LOWER(email) = LOWER($2) AND slack_user_id IS NULL
+                RETURNING cognito_sub
+                "#,
+            )
+            .bind(&slack_user_id)
+            .bind(&email)
+            .fetch_optional(&state.db_pool)
+            .await
+            .map_err(|e| format!("Failed to link user: {}", e))?;
+
+            match matched {
+                Some(sub) => {
+                    info!(slack_user_id, "Linked Slack user by email");
+                    sub
+                }
+                None => return Ok(None),
+            }
+        }
+    };
+
+    match AuthorizedUser::resolve(
+        AuthenticatedUser {
+            user_id: cognito_sub,
+            email: None,
+            family_ids: Vec::new(),
+        },
+        &state.db_pool,
+    )
+    .await
+    {
+        Ok(user) => Ok(Some(user)),
+        Err(shared::Error::Auth(_)) => Ok(None),
+        Err(e) => Err(format!("Failed to lookup user: {}", e).into()),
+    }
+}
+
+/// Message with blocks and a plain-text fallback for notifications
+fn blocks_message(title: Option<&str>, text: &str, save: bool) -> Value {
+    serde_json::json!({
+        "text": truncate(text.trim(), FALLBACK_TEXT_MAX_CHARS),
+        "blocks": answer_blocks(title, text, save),
+    })
+}
+
+/// Handle follow-up processing (async invocation)
+async fn handle_follow_up(state: Arc<AppState>, payload: FollowUpPayload) -> Result<Value, Error> {
+    let user = resolve_slack_user(&state, &payload.team_id, &payload.slack_user_id).await?;
+
+    let message = match user {
+        None => serde_json::json!({ "text": UNLINKED_MESSAGE }),
+        Some(user) => {
+            let agent_user_id = user.user_id.to_string();
+            let family_ids: Vec<String> = user.family_ids.iter().map(Uuid::to_string).collect();
+
+            match payload.action.as_str() {
+                "ask" => {
+                    // Mentions keep context per thread
+                    let conversation_id = payload
+                        .channel
+                        .as_ref()
+                        .zip(payload.thread_ts.as_ref())
+                        .map(|(channel, ts)| format!("slack-{}-{}", channel, ts));
+
+                    match state
+                        .agent_client
+                        .query(
+                            &payload.text,
+                            &agent_user_id,
+                            family_ids,
+                            conversation_id,
+                            "slack",
+                        )
+                        .await
+                    {
+                        Ok(resp) => blocks_message(Some(&payload.text), &resp.response, true),
+                        Err(e) => {
+                            error!("Agent error: {}", e);
+                            serde_json::json!({
+                                "text": "Sorry, I couldn't process that question. Please try again.",
+                            })
+                        }
+                    }
+                }
+                "remember" => match state
+                    .agent_client
+                    .ingest(&payload.text, &agent_user_id, family_ids, "slack")
+                    .await
+                {
+                    Ok(resp) => blocks_message(Some("Saved"), &resp.response, false),
+                    Err(e) => {
+                        error!("Agent error: {}", e);
+                        serde_json::json!({
+                            "text": "Sorry, I couldn't save that. Please try again.",
+                        })
+                    }
+                },
+                other => serde_json::json!({ "text": format!("Unknown action: {}", other) }),
+            }
+        }
+    };
+
+    if let Err(e) = state.respond(&payload, message).await {
+        error!("Failed to send follow-up message: {}", e);
+    }
+
+    // Return success for async invocation
+    Ok(serde_json::json!({"status": "ok"}))
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+
+    lambda_runtime::run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        async move { metrics::track_invocation("event", handler(state, event)).await }
+    }))
+    .await
+}
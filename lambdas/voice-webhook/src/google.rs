@@ -0,0 +1,196 @@
+//! Google Assistant (Actions Builder) conversation webhooks.
+//!
+//! Each scene calls the webhook with a handler named after the intent it
+//! serves: `welcome`, `ask` (`query` parameter), `remember` (`fact`),
+//! `briefing` (optional `briefingType`), `help` and `stop`. Other handlers are
+//! treated as a question when the user said something, so a catch-all scene
+//! works too. With OAuth account linking, the user's token arrives as the
+//! `bearerToken` user parameter.
+
+use serde_json::{json, Value};
+use shared::models::{VoiceIntent, VoiceRequest, VoiceResponse};
+use shared::tts::escape_ssml;
+
+/// Scene that ends the conversation after the reply
+const END_CONVERSATION: &str = "actions.scene.END_CONVERSATION";
+
+/// Source recorded for agent requests from Google Assistant
+pub const ASSISTANT: &str = "google_assistant";
+
+/// Whether a webhook body is a Google Assistant conversation request.
+pub fn is_google_request(body: &Value) -> bool {
+    body["handler"]["name"].is_string() && body["session"].is_object()
+}
+
+/// An intent parameter's resolved value, or what the user said for it.
+fn param<'a>(body: &'a Value, name: &str) -> Option<&'a str> {
+    let param = &body["intent"]["params"][name];
+    param["resolved"]
+        .as_str()
+        .or_else(|| param["original"].as_str())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// Normalize a conversation request.
+pub fn voice_request(body: &Value) -> VoiceRequest {
+    let handler = body["handler"]["name"]
+        .as_str()
+        .unwrap_or_default()
+        .to_lowercase();
+    let utterance = body["intent"]["query"]
+        .as_str()
+        .map(str::trim)
+        .filter(|query| !query.is_empty());
+
+    let intent = match handler.as_str() {
+        "welcome" | "main" => VoiceIntent::Welcome,
+        "ask" | "query" => match param(body, "query").or(utterance) {
+            Some(query) => VoiceIntent::Ask {
+                query: query.to_string(),
+            },
+            None => VoiceIntent::Help,
+        },
+        "remember" | "save" => match param(body, "fact").or(utterance) {
+            Some(fact) => VoiceIntent::Remember {
+                fact: fact.to_string(),
+            },
+            None => VoiceIntent::Help,
+        },
+        "briefing" => VoiceIntent::Briefing {
+            briefing_type: param(body, "briefingType").map(str::to_lowercase),
+        },
+        "stop" | "cancel" => VoiceIntent::Stop,
+        _ => match utterance {
+            Some(query) => VoiceIntent::Ask {
+                query: query.to_string(),
+            },
+            None => VoiceIntent::Help,
+        },
+    };
+
+    VoiceRequest {
+        intent,
+        access_token: body["user"]["params"]["bearerToken"]
+            .as_str()
+            .map(String::from),
+        session_id: body["session"]["id"].as_str().map(String::from),
+        locale: body["user"]["locale"].as_str().map(String::from),
+        assistant: ASSISTANT.to_string(),
+    }
+}
+
+/// Render a reply as the conversation webhook response to `body`.
+pub fn render(body: &Value, response: &VoiceResponse) -> Value {
+    let speech = match &response.audio_url {
+        Some(url) => format!(
+            r#"<speak><audio src="{}">{}</audio></speak>"#,
+            escape_ssml(url),
+            escape_ssml(&response.speech)
+        ),
+        None => response.speech.clone(),
+    };
+
+    let mut reply = json!({
+        "session": {
+            "id": body["session"]["id"],
+            "params": body["session"]["params"].as_object().cloned().unwrap_or_default(),
+        },
+        "prompt": {
+            "override": false,
+            "firstSimple": {
+                "speech": speech,
+                "text": response.speech,
+            },
+        },
+    });
+
+    if response.end_session {
+        reply["scene"] = json!({
+            "name": body["scene"]["name"],
+            "slots": {},
+            "next": { "name": END_CONVERSATION },
+        });
+    }
+    reply
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(handler: &str, params: Value, query: &str) -> Value {
+        json!({
+            "handler": { "name": handler },
+            "intent": { "name": "Ask", "params": params, "query": query },
+            "scene": { "name": "Main" },
+            "session": { "id": "session-1", "params": {} },
+            "user": { "locale": "en-US", "params": { "bearerToken": "token" } },
+        })
+    }
+
+    #[test]
+    fn test_voice_request() {
+        let ask = voice_request(&request(
+            "ask",
+            json!({ "query": { "original": "when is the recital", "resolved": "when is the recital" } }),
+            "ask second brain when is the recital",
+        ));
+        assert_eq!(
+            ask.intent,
+            VoiceIntent::Ask {
+                query: "when is the recital".to_string()
+            }
+        );
+        assert_eq!(ask.access_token.as_deref(), Some("token"));
+        assert_eq!(ask.session_id.as_deref(), Some("session-1"));
+        assert_eq!(ask.assistant, ASSISTANT);
+
+        let briefing = voice_request(&request(
+            "briefing",
+            json!({ "briefingType": { "resolved": "Evening" } }),
+            "",
+        ));
+        assert_eq!(
+            briefing.intent,
+            VoiceIntent::Briefing {
+                briefing_type: Some("evening".to_string())
+            }
+        );
+
+        // Unknown handlers ask what the user said
+        let fallback = voice_request(&request("fallback", json!({}), "where does Sam work"));
+        assert_eq!(
+            fallback.intent,
+            VoiceIntent::Ask {
+                query: "where does Sam work".to_string()
+            }
+        );
+        assert_eq!(
+            voice_request(&request("remember", json!({}), " ")).intent,
+            VoiceIntent::Help
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let body = request("ask", json!({}), "");
+
+        let reply = render(&body, &VoiceResponse::say("Friday at 6"));
+        assert_eq!(reply["session"]["id"], "session-1");
+        assert_eq!(reply["prompt"]["firstSimple"]["speech"], "Friday at 6");
+        assert_eq!(reply["scene"]["next"]["name"], END_CONVERSATION);
+
+        let reply = render(&body, &VoiceResponse::prompt("What else?"));
+        assert!(reply.get("scene").is_none());
+
+        let audio = VoiceResponse {
+            audio_url: Some("https://audio.example/a.mp3?x=1&y=2".to_string()),
+            ..VoiceResponse::say("Your briefing")
+        };
+        assert_eq!(
+            render(&body, &audio)["prompt"]["firstSimple"]["speech"],
+            r#"<speak><audio src="https://audio.example/a.mp3?x=1&amp;y=2">Your briefing</audio></speak>"#
+        );
+    }
+}
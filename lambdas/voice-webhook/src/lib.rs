@@ -0,0 +1,3 @@
+//! Voice assistant support shared by the webhook Lambda.
+
+pub mod google;
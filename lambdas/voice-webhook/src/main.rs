@@ -0,0 +1,352 @@
+//! Voice Webhook Lambda - Answers voice assistants other than Alexa.
+//!
+//! Assistants post to one endpoint. Google Assistant conversation requests are
+//! recognized and translated (see [`google`]); any other assistant can post a
+//! [`VoiceRequest`] and gets a [`VoiceResponse`] back, with the account linking
+//! token in the body or an `Authorization: Bearer` header.
+//!
+//! The intents match the Alexa skill's: ask a question, remember a fact, and
+//! today's briefing (played as Polly audio when `TTS_AUDIO_BUCKET` is set).
+//!
+//! The webhook is public, so linking tokens are checked with Cognito
+//! (`GetUser`) rather than only decoded; the app client used for account
+//! linking needs the `aws.cognito.signin.user.admin` scope.
+
+use aws_sdk_cognitoidentityprovider::Client as CognitoClient;
+use lambda_runtime::{service_fn, Error, LambdaEvent};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use shared::agents::{AgentRequest, DirectFallback};
+use shared::briefings::{
+    briefing_audio, briefing_speech, generate_briefing, parse_briefing_type, todays_briefing,
+};
+use shared::metrics;
+use shared::models::{VoiceIntent, VoiceRequest, VoiceResponse};
+use shared::tts::{SpeechInput, TtsService, VoiceOptions};
+use shared::{AgentClient, AuthenticatedUser, AuthorizedUser};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+use voice_webhook::google;
+
+const HELP_TEXT: &str = "You can ask me a question, tell me something to remember, \
+or ask for your morning or evening briefing.";
+
+/// API Gateway proxy request
+#[derive(Debug, Deserialize)]
+struct ApiGatewayRequest {
+    headers: Option<HashMap<String, String>>,
+    body: Option<String>,
+}
+
+/// API Gateway proxy response
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiGatewayResponse {
+    status_code: u16,
+    headers: HashMap<String, String>,
+    body: String,
+    is_base64_encoded: bool,
+}
+
+impl ApiGatewayResponse {
+    fn new(status_code: u16, body: &str, content_type: &str) -> Self {
+        let mut headers = HashMap::new();
+        headers.insert("content-type".to_string(), content_type.to_string());
+        Self {
+            status_code,
+            headers,
+            body: body.to_string(),
+            is_base64_encoded: false,
+        }
+    }
+
+    fn json<T: Serialize>(status_code: u16, data: &T) -> Result<Self, Error> {
+        let body = serde_json::to_string(data)?;
+        Ok(Self::new(status_code, &body, "application/json"))
+    }
+}
+
+struct AppState {
+    db_pool: PgPool,
+    agent_client: AgentClient,
+    cognito_client: CognitoClient,
+    tts: TtsService,
+}
+
+impl AppState {
+    async fn new() -> Result<Self, Error> {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        let lambda_client = aws_sdk_lambda::Client::new(&config);
+
+        let db_pool = shared::db::connect(&config)
+            .await
+            .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+        let agent_function = std::env::var("AGENT_FUNCTION_NAME")
+            .unwrap_or_else(|_| "second-brain-agents".to_string());
+
+        // Simple questions are answered from the database while the agents
+        // are down, and every answer is logged for GET /v1/queries/{id}
+        let agent_client = AgentClient::new(lambda_client, agent_function)
+            .with_fallback(DirectFallback::from_env(&config, db_pool.clone()))
+            .with_query_log(db_pool.clone());
+
+        Ok(Self {
+            db_pool,
+            agent_client,
+            cognito_client: CognitoClient::new(&config),
+            tts: TtsService::from_env(&config),
+        })
+    }
+}
+
+fn header<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+/// The Second Brain user a linking token belongs to, or `None` if the token
+/// is missing, expired or revoked.
+async fn linked_user(
+    state: &AppState,
+    access_token: Option<&str>,
+) -> Result<Option<AuthorizedUser>, Error> {
+    let Some(token) = access_token else {
+        return Ok(None);
+    };
+
+    let cognito_user = match state
+        .cognito_client
+        .get_user()
+        .access_token(token)
+        .send()
+        .await
+    {
+        Ok(user) => user,
+        Err(e) => {
+            let e = e.into_service_error();
+            if e.is_not_authorized_exception() || e.is_user_not_found_exception() {
+                info!("Voice user not linked: {}", e);
+                return Ok(None);
+            }
+            return Err(format!("Failed to verify token: {}", e).into());
+        }
+    };
+    let attribute = |name: &str| {
+        cognito_user
+            .user_attributes()
+            .iter()
+            .find(|a| a.name() == name)
+            .and_then(|a| a.value())
+            .map(String::from)
+    };
+    let Some(sub) = attribute("sub") else {
+        return Ok(None);
+    };
+
+    let user = AuthenticatedUser {
+        user_id: sub,
+        email: attribute("email"),
+        family_ids: Vec::new(),
+    };
+    match AuthorizedUser::resolve(user, &state.db_pool).await {
+        Ok(user) => Ok(Some(user)),
+        Err(shared::Error::Auth(e)) => {
+            info!("Voice user not registered: {}", e);
+            Ok(None)
+        }
+        Err(e) => Err(format!("Failed to lookup user: {}", e).into()),
+    }
+}
+
+/// Handle a normalized request.
+async fn respond(state: &AppState, request: &VoiceRequest) -> Result<VoiceResponse, Error> {
+    let user = match &request.intent {
+        VoiceIntent::Welcome | VoiceIntent::Help => return Ok(VoiceResponse::prompt(HELP_TEXT)),
+        VoiceIntent::Stop => return Ok(VoiceResponse::say("Goodbye.")),
+        _ => match linked_user(state, request.access_token.as_deref()).await? {
+            Some(user) => user,
+            None => return Ok(VoiceResponse::link_account()),
+        },
+    };
+    let agent_user_id = user.user_id.to_string();
+    let family_ids: Vec<String> = user.family_ids.iter().map(Uuid::to_string).collect();
+
+    match &request.intent {
+        VoiceIntent::Ask { query } => {
+            // Follow-up questions in the same assistant conversation keep context
+            let conversation_id = request
+                .session_id
+                .as_ref()
+                .map(|session| format!("{}-{}", request.assistant, session));
+
+            match state
+                .agent_client
+                .query(
+                    query,
+                    &agent_user_id,
+                    family_ids,
+                    conversation_id,
+                    &request.assistant,
+                )
+                .await
+            {
+                Ok(resp) => Ok(VoiceResponse::prompt(resp.response)),
+                Err(e) => {
+                    error!("Agent error: {}", e);
+                    Ok(VoiceResponse::say(
+                        "Sorry, I couldn't answer that. Please try again.",
+                    ))
+                }
+            }
+        }
+        VoiceIntent::Remember { fact } => match state
+            .agent_client
+            .invoke(AgentRequest {
+                message: fact.clone(),
+                user_id: agent_user_id,
+                family_ids,
+                device_id: None,
+                conversation_id: None,
+                conversation_history: Vec::new(),
+                intent: Some("ingest".to_string()),
+                source: request.assistant.clone(),
+                // Stored as a spoken fact
+                modality: Some("voice".to_string()),
+                metadata: None,
+            })
+            .await
+        {
+            Ok(_) => Ok(VoiceResponse::say("Got it, I'll remember that.")),
+            Err(e) => {
+                error!("Agent error: {}", e);
+                Ok(VoiceResponse::say(
+                    "Sorry, I couldn't save that. Please try again.",
+                ))
+            }
+        },
+        VoiceIntent::Briefing { briefing_type } => {
+            briefing(state, request, &user, briefing_type.as_deref()).await
+        }
+        VoiceIntent::Welcome | VoiceIntent::Help | VoiceIntent::Stop => {
+            Ok(VoiceResponse::prompt(HELP_TEXT))
+        }
+    }
+}
+
+/// Today's briefing, generating it if the dispatcher hasn't yet.
+async fn briefing(
+    state: &AppState,
+    request: &VoiceRequest,
+    user: &AuthorizedUser,
+    briefing_type: Option<&str>,
+) -> Result<VoiceResponse, Error> {
+    let Ok(briefing_type) = parse_briefing_type(briefing_type) else {
+        return Ok(VoiceResponse::say(
+            "I can read your morning or evening briefing.",
+        ));
+    };
+
+    let briefing = match todays_briefing(&state.db_pool, user.user_id, briefing_type).await? {
+        Some(briefing) => briefing,
+        None => generate_briefing(
+            &state.db_pool,
+            &state.agent_client,
+            user.user_id,
+            &user.family_ids,
+            briefing_type,
+            &request.assistant,
+        )
+        .await
+        .map_err(|e| format!("Failed to generate briefing: {}", e))?,
+    };
+
+    let speech = match briefing_speech(&briefing.content) {
+        SpeechInput::Text(text) => text,
+        SpeechInput::Ssml(ssml) => ssml,
+    };
+
+    let mut voice = VoiceOptions::from_env();
+    if let (None, Some(locale)) = (&voice.language, request.locale.as_deref()) {
+        voice = voice.with_language(locale);
+    }
+    let audio_url = match briefing_audio(&state.tts, &briefing, &voice).await {
+        Ok(audio) => audio.url,
+        Err(e) => {
+            warn!("Failed to synthesize briefing: {}", e);
+            None
+        }
+    };
+
+    Ok(VoiceResponse {
+        audio_url,
+        ..VoiceResponse::say(speech)
+    })
+}
+
+async fn handler(state: Arc<AppState>, event: LambdaEvent<Value>) -> Result<Value, Error> {
+    let api_request: ApiGatewayRequest = serde_json::from_value(event.payload)?;
+    let headers = api_request.headers.unwrap_or_default();
+
+    let body = api_request.body.unwrap_or_default();
+    let body: Value = match serde_json::from_str(&body) {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("Invalid voice request body: {}", e);
+            return Ok(serde_json::to_value(ApiGatewayResponse::new(
+                400,
+                "Invalid JSON body",
+                "text/plain",
+            ))?);
+        }
+    };
+
+    let reply = if google::is_google_request(&body) {
+        let request = google::voice_request(&body);
+        info!(assistant = %request.assistant, "Voice request");
+        google::render(&body, &respond(&state, &request).await?)
+    } else {
+        let mut request: VoiceRequest = match serde_json::from_value(body) {
+            Ok(request) => request,
+            Err(e) => {
+                return Ok(serde_json::to_value(ApiGatewayResponse::new(
+                    400,
+                    &format!("Invalid voice request: {}", e),
+                    "text/plain",
+                ))?);
+            }
+        };
+        if request.access_token.is_none() {
+            request.access_token = header(&headers, "authorization")
+                .and_then(|v| v.strip_prefix("Bearer "))
+                .map(String::from);
+        }
+        info!(assistant = %request.assistant, "Voice request");
+        serde_json::to_value(respond(&state, &request).await?)?
+    };
+
+    let response = ApiGatewayResponse::json(200, &reply)?;
+    Ok(serde_json::to_value(response)?)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .json()
+        .init();
+
+    let state = Arc::new(AppState::new().await?);
+
+    lambda_runtime::run(service_fn(move |event| {
+        let state = Arc::clone(&state);
+        async move { metrics::track_invocation("voice_request", handler(state, event)).await }
+    }))
+    .await
+}
@@ -0,0 +1,60 @@
+-- Migration: 016_drop_folder
+-- Description: Manifest of files ingested from the per-user S3 drop folder
+-- Date: 2026-10-15
+
+-- ===========================================
+-- DROP FOLDER MANIFEST
+-- ===========================================
+
+-- Kind of file, decides the ingestion path
+DO $$ BEGIN
+    CREATE TYPE drop_file_kind AS ENUM (
+        'text',           -- Ingested directly as a fact
+        'audio',          -- Transcribed, then ingested
+        'image',          -- OCR'd, then ingested
+        'unsupported'     -- Unknown extension, recorded as failed
+    );
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+-- Processing status
+DO $$ BEGIN
+    CREATE TYPE drop_file_status AS ENUM (
+        'pending',        -- Received, not yet processed
+        'transcribing',   -- Waiting on an async transcription job
+        'completed',      -- Content handed to the ingestion pipeline
+        'failed'          -- Processing failed (see error_message)
+    );
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+CREATE TABLE IF NOT EXISTS drop_folder_files (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+    -- S3 object (etag distinguishes re-uploads of the same key)
+    bucket VARCHAR(255) NOT NULL,
+    object_key TEXT NOT NULL,
+    etag VARCHAR(255) NOT NULL,
+    size_bytes BIGINT,
+
+    file_kind drop_file_kind NOT NULL,
+    status drop_file_status NOT NULL DEFAULT 'pending',
+
+    -- Processing details
+    transcription_job VARCHAR(255),
+    extracted_chars INTEGER,
+    error_message TEXT,
+    attempts SMALLINT NOT NULL DEFAULT 1,
+
+    -- Timestamps
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    processed_at TIMESTAMPTZ,
+
+    UNIQUE (bucket, object_key, etag)
+);
+
+CREATE INDEX IF NOT EXISTS idx_drop_folder_files_user ON drop_folder_files(user_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_drop_folder_files_status ON drop_folder_files(status) WHERE status IN ('pending', 'transcribing');
+CREATE UNIQUE INDEX IF NOT EXISTS idx_drop_folder_files_job ON drop_folder_files(transcription_job) WHERE transcription_job IS NOT NULL;
@@ -0,0 +1,48 @@
+-- Migration: 017_handoffs
+-- Description: Household handoff briefings shared between family members
+-- Date: 2026-10-15
+
+-- ===========================================
+-- HANDOFF BRIEFINGS
+-- ===========================================
+
+-- Handoff notifications get their own type so they can be filtered/analysed
+ALTER TYPE notification_type ADD VALUE IF NOT EXISTS 'handoff';
+
+CREATE TABLE IF NOT EXISTS handoffs (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+
+    -- Who compiled it, who it is for, and the family they share
+    created_by UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    recipient_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    family_id UUID NOT NULL REFERENCES families(id) ON DELETE CASCADE,
+
+    -- Time window the briefing covers (reminders and schedule)
+    window_start TIMESTAMPTZ NOT NULL,
+    window_end TIMESTAMPTZ NOT NULL,
+
+    -- Snapshot of the compiled briefing
+    title VARCHAR(255) NOT NULL,
+    note TEXT,
+    content JSONB NOT NULL,
+    -- {"facts": [...], "reminders": [...], "events": [...]}
+
+    -- Share link (only the SHA-256 of the token is stored)
+    share_token_hash BYTEA NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ,
+
+    -- Delivery
+    notification_id UUID REFERENCES notifications(id) ON DELETE SET NULL,
+    view_count INTEGER NOT NULL DEFAULT 0,
+    last_viewed_at TIMESTAMPTZ,
+
+    -- Timestamps
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT handoffs_window_valid CHECK (window_end > window_start),
+    CONSTRAINT handoffs_not_self CHECK (recipient_id <> created_by)
+);
+
+CREATE INDEX IF NOT EXISTS idx_handoffs_created_by ON handoffs(created_by, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_handoffs_recipient ON handoffs(recipient_id, created_at DESC);
@@ -0,0 +1,6 @@
+-- Migration: 018_briefing_lookup
+-- Description: Index for serving today's stored briefing per user and type
+-- Date: 2026-10-15
+
+CREATE INDEX IF NOT EXISTS idx_briefing_history_user_type
+    ON briefing_history(user_id, briefing_type, generated_at DESC);
@@ -0,0 +1,53 @@
+-- Migration: 019_diagnostics
+-- Description: Opt-in debug mode with PII-scrubbed request/response samples
+-- Date: 2026-10-15
+
+-- ===========================================
+-- DEBUG SESSIONS
+-- ===========================================
+
+-- A user opting in to diagnostics capture for a limited time window
+CREATE TABLE IF NOT EXISTS debug_sessions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+    -- Why the user turned it on (e.g. "the bot keeps getting my dentist wrong")
+    reason TEXT,
+
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+
+    CONSTRAINT debug_sessions_window_valid CHECK (expires_at >= started_at)
+);
+
+CREATE INDEX IF NOT EXISTS idx_debug_sessions_user ON debug_sessions(user_id, expires_at DESC);
+
+-- ===========================================
+-- DIAGNOSTIC SAMPLES
+-- ===========================================
+
+-- Scrubbed request/response pairs captured while a debug session is active
+CREATE TABLE IF NOT EXISTS diagnostic_samples (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    session_id UUID NOT NULL REFERENCES debug_sessions(id) ON DELETE CASCADE,
+
+    -- Where the request came from and what it did
+    source VARCHAR(20) NOT NULL,      -- 'api', 'discord'
+    operation VARCHAR(50) NOT NULL,   -- 'query', 'ingest', ...
+
+    -- PII-scrubbed content (emails and long numbers redacted, truncated)
+    request_text TEXT NOT NULL,
+    response_text TEXT,
+    error_message TEXT,
+
+    -- Agent retrieval trace (agents used, handoffs, model)
+    retrieval_trace JSONB NOT NULL DEFAULT '{}',
+    latency_ms INTEGER,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL DEFAULT NOW() + INTERVAL '14 days'
+);
+
+CREATE INDEX IF NOT EXISTS idx_diagnostic_samples_user ON diagnostic_samples(user_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_diagnostic_samples_expires ON diagnostic_samples(expires_at);
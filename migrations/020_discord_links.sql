@@ -0,0 +1,28 @@
+-- Migration: 020_discord_links
+-- Description: Verification codes for linking Discord accounts to registered users
+-- Date: 2026-10-15
+
+-- ===========================================
+-- DISCORD ACCOUNT LINKING
+-- ===========================================
+
+-- When the current Discord account was linked (users.discord_id)
+ALTER TABLE users ADD COLUMN IF NOT EXISTS discord_linked_at TIMESTAMPTZ;
+
+-- Short-lived codes issued in the app and redeemed with /link in Discord
+CREATE TABLE IF NOT EXISTS discord_link_codes (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+    -- Only the SHA-256 of the normalized code is stored
+    code_hash BYTEA NOT NULL UNIQUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+
+    -- Set when redeemed, with the Discord account that redeemed it
+    consumed_at TIMESTAMPTZ,
+    discord_id VARCHAR(255),
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_discord_link_codes_user ON discord_link_codes(user_id, created_at DESC);
@@ -0,0 +1,11 @@
+-- Migration: 021_slack_identity
+-- Description: Slack identity column for the Slack app
+-- Date: 2026-10-16
+
+-- Slack users are scoped to a workspace, so the column holds {team_id}:{user_id}.
+-- It is filled in on first use by matching the Slack profile email.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS slack_user_id VARCHAR(255) UNIQUE;
+
+CREATE INDEX IF NOT EXISTS idx_users_slack_user_id ON users(slack_user_id) WHERE slack_user_id IS NOT NULL;
+
+COMMENT ON COLUMN users.slack_user_id IS 'Slack {team_id}:{user_id} for linking Slack app interactions';
@@ -0,0 +1,34 @@
+-- Migration: 022_sms_phone
+-- Description: Verified phone numbers and one-time codes for SMS ingestion and query
+-- Date: 2026-10-16
+
+-- ===========================================
+-- SMS PHONE VERIFICATION
+-- ===========================================
+
+-- E.164 phone number; inbound texts are only accepted once it is verified
+ALTER TABLE users ADD COLUMN IF NOT EXISTS phone_number VARCHAR(16) UNIQUE;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS phone_verified_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_users_phone_number ON users(phone_number) WHERE phone_verified_at IS NOT NULL;
+
+-- One-time codes texted to a number to prove the user owns it
+CREATE TABLE IF NOT EXISTS phone_verification_codes (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    phone_number VARCHAR(16) NOT NULL,
+
+    -- Only the SHA-256 of the code is stored
+    code_hash BYTEA NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+
+    -- Wrong guesses; the code stops working after too many
+    attempts SMALLINT NOT NULL DEFAULT 0,
+    consumed_at TIMESTAMPTZ,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_phone_verification_codes_user ON phone_verification_codes(user_id, created_at DESC);
+
+COMMENT ON COLUMN users.phone_number IS 'E.164 phone number for SMS ingestion and query';
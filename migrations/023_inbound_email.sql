@@ -0,0 +1,26 @@
+-- Migration: 023_inbound_email
+-- Description: Inbound emails processed by the email ingestion Lambda
+-- Date: 2026-10-16
+
+-- ===========================================
+-- INBOUND EMAIL
+-- ===========================================
+
+-- One row per SES message, so redelivered invocations are skipped
+CREATE TABLE IF NOT EXISTS inbound_emails (
+    -- SES message ID (also the raw message's S3 key under inbound/)
+    message_id VARCHAR(255) PRIMARY KEY,
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    sender VARCHAR(255),
+
+    -- received -> ingested, or rejected (unknown/unauthenticated sender) or failed
+    status VARCHAR(20) NOT NULL DEFAULT 'received'
+        CHECK (status IN ('received', 'ingested', 'rejected', 'failed')),
+    attachments INTEGER NOT NULL DEFAULT 0,
+    error_message TEXT,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_inbound_emails_user ON inbound_emails(user_id, created_at DESC);
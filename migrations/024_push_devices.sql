@@ -0,0 +1,39 @@
+-- Migration: 024_push_devices
+-- Description: Mobile push tokens (FCM/APNs) and notification delivery receipts
+-- Date: 2026-10-16
+
+-- ===========================================
+-- PUSH DEVICES
+-- ===========================================
+
+-- One row per app install; the platform selects the push provider
+CREATE TABLE IF NOT EXISTS push_devices (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+    device_platform VARCHAR(10) NOT NULL CHECK (device_platform IN ('fcm', 'apns')),
+    push_token TEXT NOT NULL,
+    device_name VARCHAR(255),
+
+    -- Cleared when the provider reports the token invalid
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    invalidated_at TIMESTAMPTZ,
+    last_error TEXT,
+    last_delivered_at TIMESTAMPTZ,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    UNIQUE (device_platform, push_token)
+);
+
+CREATE INDEX IF NOT EXISTS idx_push_devices_user ON push_devices(user_id) WHERE is_active;
+
+-- ===========================================
+-- DELIVERY RECEIPTS
+-- ===========================================
+
+-- Per-channel delivery receipt, e.g. for push:
+-- {"deliveries": [{"device_id": "...", "platform": "apns", "status": "sent", "message_id": "..."}]}
+ALTER TABLE notifications ADD COLUMN IF NOT EXISTS delivery_info JSONB;
+ALTER TABLE notifications ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
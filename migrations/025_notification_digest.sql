@@ -0,0 +1,29 @@
+-- Migration: 025_notification_digest
+-- Description: Batch low-priority notifications into morning/evening digests
+-- Date: 2026-10-16
+
+-- ===========================================
+-- NOTIFICATION PRIORITY
+-- ===========================================
+
+ALTER TYPE notification_type ADD VALUE IF NOT EXISTS 'digest';
+
+-- Same 1 (lowest) to 5 (highest) scale as reminders; priority 1 is held for
+-- the user's digest instead of being sent immediately
+ALTER TABLE notifications ADD COLUMN IF NOT EXISTS priority SMALLINT NOT NULL DEFAULT 3
+    CHECK (priority >= 1 AND priority <= 5);
+
+-- Digest notification a batched notification was delivered in
+ALTER TABLE notifications ADD COLUMN IF NOT EXISTS digest_id UUID REFERENCES notifications(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_notifications_digest_pending ON notifications(user_id, created_at)
+    WHERE status = 'pending' AND priority = 1;
+
+-- ===========================================
+-- DIGEST PREFERENCES
+-- ===========================================
+
+-- Digests go out at the briefing times: 'morning', 'evening', 'both', or
+-- 'off' to send low-priority notifications immediately
+ALTER TABLE user_notification_preferences ADD COLUMN IF NOT EXISTS digest_mode VARCHAR(10) NOT NULL DEFAULT 'morning'
+    CHECK (digest_mode IN ('off', 'morning', 'evening', 'both'));
@@ -0,0 +1,10 @@
+-- Migration: 026_quiet_hours_deferral
+-- Description: Hold notifications that come due during quiet hours until they end
+-- Date: 2026-10-16
+
+-- When set, the notification is not sent before this time; the reminder
+-- evaluator publishes it for delivery once it has passed
+ALTER TABLE notifications ADD COLUMN IF NOT EXISTS deferred_until TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_notifications_deferred ON notifications(deferred_until)
+    WHERE status = 'pending' AND deferred_until IS NOT NULL;
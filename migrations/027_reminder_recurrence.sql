@@ -0,0 +1,19 @@
+-- Migration: 027_reminder_recurrence
+-- Description: Compute recurring reminder triggers in the application, in the user's timezone
+-- Date: 2026-10-16
+
+-- ===========================================
+-- REMINDER UPDATE TRIGGER
+-- ===========================================
+
+-- Recurring reminders are rescheduled by shared::recurrence (API and
+-- reminder evaluator), which evaluates schedules in the user's timezone.
+-- The trigger no longer overwrites the next_trigger_at they write.
+-- calculate_next_trigger() is kept for the agents' mark_reminder_triggered tool.
+CREATE OR REPLACE FUNCTION update_reminder_trigger()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.updated_at := NOW();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,28 @@
+-- Migration: 028_reminder_history
+-- Description: Track each reminder occurrence and whether it was completed or missed
+-- Date: 2026-10-16
+
+-- ===========================================
+-- REMINDER OCCURRENCES
+-- ===========================================
+
+-- One row per time a reminder fires (or is completed ahead of firing).
+-- 'pending' occurrences become 'missed' when the reminder fires again without
+-- being completed; the history API also counts pending ones over a day old
+-- as missed.
+CREATE TABLE IF NOT EXISTS reminder_occurrences (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    reminder_id UUID NOT NULL REFERENCES reminders(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    due_at TIMESTAMPTZ NOT NULL,
+    outcome VARCHAR(10) NOT NULL DEFAULT 'pending'
+        CHECK (outcome IN ('pending', 'completed', 'missed')),
+    completed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_reminder_occurrences_user_due ON reminder_occurrences(user_id, due_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_reminder_occurrences_pending ON reminder_occurrences(reminder_id)
+    WHERE outcome = 'pending';
+
+COMMENT ON TABLE reminder_occurrences IS 'Reminder firings and whether each was completed or missed';
@@ -0,0 +1,28 @@
+-- Migration: 029_reminder_snooze_policy
+-- Description: Count reminder snoozes and escalate reminders snoozed too often
+-- Date: 2026-10-16
+
+-- ===========================================
+-- SNOOZE TRACKING
+-- ===========================================
+
+-- Snoozes of the current occurrence; reset when the next occurrence fires or
+-- the reminder is completed
+ALTER TABLE reminders ADD COLUMN IF NOT EXISTS snooze_count INTEGER NOT NULL DEFAULT 0;
+
+-- ===========================================
+-- MAX-SNOOZE POLICY
+-- ===========================================
+
+-- Snoozes allowed before escalating (NULL for no limit)
+ALTER TABLE reminders ADD COLUMN IF NOT EXISTS max_snoozes SMALLINT
+    CHECK (max_snoozes IS NULL OR max_snoozes >= 1);
+
+-- What happens at the limit: 'priority' sends later notifications one priority
+-- higher, 'channel' sends them on another enabled channel, 'block' refuses
+-- further snoozes
+ALTER TABLE reminders ADD COLUMN IF NOT EXISTS snooze_escalation VARCHAR(10) NOT NULL DEFAULT 'priority'
+    CHECK (snooze_escalation IN ('priority', 'channel', 'block'));
+
+-- Set once the limit is reached; cleared with snooze_count
+ALTER TABLE reminders ADD COLUMN IF NOT EXISTS escalated BOOLEAN NOT NULL DEFAULT false;
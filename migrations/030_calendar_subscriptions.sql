@@ -0,0 +1,34 @@
+-- Migration: 030_calendar_subscriptions
+-- Description: iCal (ICS) feed subscriptions imported by calendar_sync
+-- Date: 2026-10-16
+
+-- ===========================================
+-- CALENDAR SUBSCRIPTIONS
+-- ===========================================
+
+-- A public or private iCal feed URL; its events are stored in calendar_events
+-- with external_provider 'ics' and external_calendar_id set to the
+-- subscription's ID
+CREATE TABLE IF NOT EXISTS calendar_subscriptions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+    -- Private feed URLs embed a secret token; never returned in full by the API
+    feed_url TEXT NOT NULL,
+    name VARCHAR(255),
+
+    -- HTTP validators from the last fetch, sent as If-None-Match and
+    -- If-Modified-Since so unchanged feeds aren't downloaded again
+    etag TEXT,
+    last_modified TEXT,
+
+    last_synced_at TIMESTAMPTZ,
+    last_error TEXT,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    UNIQUE (user_id, feed_url)
+);
+
+CREATE INDEX IF NOT EXISTS idx_calendar_subscriptions_user ON calendar_subscriptions(user_id);
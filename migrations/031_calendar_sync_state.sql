@@ -0,0 +1,29 @@
+-- Migration: 031_calendar_sync_state
+-- Description: Per-connection incremental sync state for external calendars
+-- Date: 2026-10-16
+
+-- ===========================================
+-- CALENDAR SYNC STATE
+-- ===========================================
+
+-- Provider sync token for each synced calendar (Google's nextSyncToken). A
+-- missing token, or a full sync older than a day, makes calendar_sync list
+-- the whole window again; otherwise only changes since the token are fetched
+CREATE TABLE IF NOT EXISTS calendar_sync_state (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR(50) NOT NULL,
+    calendar_id VARCHAR(255) NOT NULL DEFAULT 'primary',
+
+    sync_token TEXT,
+    last_full_sync_at TIMESTAMPTZ,
+    last_synced_at TIMESTAMPTZ,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (user_id, provider, calendar_id)
+);
+
+-- Full syncs remove deleted events by window, incremental syncs by ID
+CREATE INDEX IF NOT EXISTS idx_calendar_events_provider_external
+    ON calendar_events(user_id, external_provider, external_id text_pattern_ops);
@@ -0,0 +1,38 @@
+-- Migration: 032_calendar_extraction
+-- Description: Link calendar attendees to entities and record meetings as facts
+-- Date: 2026-10-16
+
+-- ===========================================
+-- EXTRACTION PREFERENCES
+-- ===========================================
+
+-- Users without a row get the defaults
+CREATE TABLE IF NOT EXISTS calendar_extraction_preferences (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+
+    -- Link attendees to existing person entities (by email, alias or name)
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    -- Create person entities for attendees that match none
+    create_entities BOOLEAN NOT NULL DEFAULT true,
+    -- Record "Meeting with ... on ..." facts
+    record_facts BOOLEAN NOT NULL DEFAULT true,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- ===========================================
+-- EXTRACTION STATE
+-- ===========================================
+
+-- Events are extracted again when updated after extracted_at
+ALTER TABLE calendar_events ADD COLUMN IF NOT EXISTS extracted_at TIMESTAMPTZ;
+ALTER TABLE calendar_events ADD COLUMN IF NOT EXISTS extracted_fact_id UUID REFERENCES facts(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_calendar_events_extraction ON calendar_events(user_id, start_time)
+    WHERE extracted_at IS NULL OR updated_at > extracted_at;
+
+-- calendar_sync upserts attendees with ON CONFLICT (event_id, email), which
+-- needs a matching unique index (NULL emails stay distinct)
+CREATE UNIQUE INDEX IF NOT EXISTS idx_calendar_event_attendees_email
+    ON calendar_event_attendees(event_id, email);
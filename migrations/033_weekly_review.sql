@@ -0,0 +1,37 @@
+-- Migration: 033_weekly_review
+-- Description: Weekly review emails summarizing each user's week
+-- Date: 2026-10-16
+
+-- ===========================================
+-- WEEKLY REVIEW PREFERENCES
+-- ===========================================
+
+-- Reviews go out by email at the evening briefing time on the chosen day
+-- (0 = Sunday ... 6 = Saturday, in the user's timezone)
+ALTER TABLE user_notification_preferences ADD COLUMN IF NOT EXISTS weekly_review_enabled BOOLEAN NOT NULL DEFAULT true;
+ALTER TABLE user_notification_preferences ADD COLUMN IF NOT EXISTS weekly_review_day SMALLINT NOT NULL DEFAULT 0
+    CHECK (weekly_review_day BETWEEN 0 AND 6);
+
+-- ===========================================
+-- WEEKLY REVIEWS
+-- ===========================================
+
+-- One row per user per week, claimed before the email is sent so re-runs
+-- never send the same week twice
+CREATE TABLE IF NOT EXISTS weekly_reviews (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    week_ending DATE NOT NULL,
+
+    fact_count INTEGER NOT NULL DEFAULT 0,
+    entity_count INTEGER NOT NULL DEFAULT 0,
+    reminders_completed INTEGER NOT NULL DEFAULT 0,
+    narrative TEXT,
+
+    sent_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    UNIQUE (user_id, week_ending)
+);
+
+COMMENT ON TABLE weekly_reviews IS 'Weekly review emails sent to each user';
@@ -0,0 +1,34 @@
+-- Migration: 034_occasions
+-- Description: Yearly reminders for birthdays and anniversaries on entities
+-- Date: 2026-10-16
+
+-- ===========================================
+-- OCCASION PREFERENCES
+-- ===========================================
+
+-- occasion_scanner creates a yearly reminder this many days before each
+-- birthday/anniversary attribute on the user's (and their families') entities
+ALTER TABLE user_notification_preferences ADD COLUMN IF NOT EXISTS occasion_reminders_enabled BOOLEAN NOT NULL DEFAULT true;
+ALTER TABLE user_notification_preferences ADD COLUMN IF NOT EXISTS occasion_reminder_days SMALLINT NOT NULL DEFAULT 7
+    CHECK (occasion_reminder_days BETWEEN 0 AND 60);
+
+-- ===========================================
+-- OCCASION REMINDERS
+-- ===========================================
+
+-- Reminder created for each occasion attribute. The row outlives a reminder
+-- the user deletes (reminder_id becomes NULL) so it isn't created again
+CREATE TABLE IF NOT EXISTS occasion_reminders (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    attribute_id UUID NOT NULL REFERENCES entity_attributes(id) ON DELETE CASCADE,
+    reminder_id UUID REFERENCES reminders(id) ON DELETE SET NULL,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (user_id, attribute_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_occasion_reminders_reminder ON occasion_reminders(reminder_id)
+    WHERE reminder_id IS NOT NULL;
+
+COMMENT ON TABLE occasion_reminders IS 'Yearly reminders created for birthday and anniversary attributes';
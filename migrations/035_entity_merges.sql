@@ -0,0 +1,33 @@
+-- Migration: 035_entity_merges
+-- Description: Audit trail for merging duplicate entities
+-- Date: 2026-10-16
+
+-- ===========================================
+-- ENTITY MERGES
+-- ===========================================
+
+-- One row per merge. The source entity is deleted by the merge, so it is
+-- kept by id only, with a snapshot of what it looked like
+CREATE TABLE IF NOT EXISTS entity_merges (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    target_entity_id UUID NOT NULL REFERENCES entities(id) ON DELETE CASCADE,
+    source_entity_id UUID NOT NULL,
+    source_name VARCHAR(500) NOT NULL,
+    source_entity_type VARCHAR(50) NOT NULL,
+    source_snapshot JSONB NOT NULL DEFAULT '{}',
+
+    -- What moved onto the target
+    facts_reparented INTEGER NOT NULL DEFAULT 0,
+    attributes_moved INTEGER NOT NULL DEFAULT 0,
+    locations_moved INTEGER NOT NULL DEFAULT 0,
+    relationships_moved INTEGER NOT NULL DEFAULT 0,
+    kept_alias BOOLEAN NOT NULL,
+
+    merged_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_entity_merges_target ON entity_merges(target_entity_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_entity_merges_source ON entity_merges(source_entity_id);
+
+COMMENT ON TABLE entity_merges IS 'Audit trail of duplicate entities merged into another entity';
@@ -0,0 +1,50 @@
+-- Migration: 036_contacts_sync
+-- Description: Google Contacts connections and contact-to-entity links
+-- Date: 2026-10-16
+
+-- ===========================================
+-- CONTACT CONNECTIONS
+-- ===========================================
+
+-- One row per connected address book, written by the OAuth callback. Tokens
+-- live in Secrets Manager (second-brain/contacts/{user_id}); sync_token is
+-- the People API nextSyncToken for incremental syncs
+CREATE TABLE IF NOT EXISTS contact_connections (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR(50) NOT NULL,
+
+    sync_token TEXT,
+    last_full_sync_at TIMESTAMPTZ,
+    last_synced_at TIMESTAMPTZ,
+    last_error TEXT,
+
+    connected_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (user_id, provider)
+);
+
+-- ===========================================
+-- CONTACT LINKS
+-- ===========================================
+
+-- Person entity each contact was synced into. The row outlives an entity the
+-- user deletes (entity_id becomes NULL) so the contact isn't recreated; the
+-- etag lets unchanged contacts be skipped
+CREATE TABLE IF NOT EXISTS contact_links (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    provider VARCHAR(50) NOT NULL,
+    resource_name VARCHAR(255) NOT NULL,
+    entity_id UUID REFERENCES entities(id) ON DELETE SET NULL,
+    etag VARCHAR(255),
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (user_id, provider, resource_name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_contact_links_entity ON contact_links(entity_id)
+    WHERE entity_id IS NOT NULL;
+
+COMMENT ON TABLE contact_links IS 'Address-book contacts synced into person entities';
@@ -0,0 +1,13 @@
+-- Migration: 037_entity_photos
+-- Description: Photo/avatar object key on entities
+-- Date: 2026-10-16
+
+-- ===========================================
+-- ENTITY PHOTOS
+-- ===========================================
+
+-- Photos live in the entity photos bucket; clients upload them with a
+-- presigned URL from POST /entities/{id}/photo. Only the object key is stored
+
+ALTER TABLE entities ADD COLUMN IF NOT EXISTS photo_key TEXT;
+ALTER TABLE entities ADD COLUMN IF NOT EXISTS photo_updated_at TIMESTAMPTZ;
@@ -0,0 +1,185 @@
+-- Migration: 038_entity_interactions
+-- Description: Interaction history and counts for relationship health
+-- Date: 2026-10-16
+
+-- ===========================================
+-- ENTITY INTERACTIONS
+-- ===========================================
+
+-- One row per fact or calendar event that references an entity, maintained
+-- by the triggers below. occurred_at is when the fact was recorded or when
+-- the event starts, so scheduled events appear as upcoming interactions.
+CREATE TABLE IF NOT EXISTS entity_interactions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    entity_id UUID NOT NULL REFERENCES entities(id) ON DELETE CASCADE,
+    source VARCHAR(20) NOT NULL CHECK (source IN ('fact', 'calendar_event')),
+    source_id UUID NOT NULL,
+    occurred_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT entity_interactions_unique UNIQUE (entity_id, source, source_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_entity_interactions_entity_time
+    ON entity_interactions(entity_id, occurred_at DESC);
+CREATE INDEX IF NOT EXISTS idx_entity_interactions_source
+    ON entity_interactions(source, source_id);
+
+-- Running count of interactions per entity
+ALTER TABLE entities ADD COLUMN IF NOT EXISTS interaction_count INT NOT NULL DEFAULT 0;
+
+-- ===========================================
+-- INTERACTION COUNTS
+-- ===========================================
+
+CREATE OR REPLACE FUNCTION update_entity_interaction_count()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        UPDATE entities
+        SET interaction_count = interaction_count + 1
+        WHERE id = NEW.entity_id;
+        RETURN NEW;
+    END IF;
+
+    -- Rows removed by an entity delete find no entity left to update
+    UPDATE entities
+    SET interaction_count = GREATEST(interaction_count - 1, 0)
+    WHERE id = OLD.entity_id;
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_entity_interaction_count ON entity_interactions;
+CREATE TRIGGER trg_entity_interaction_count
+AFTER INSERT OR DELETE ON entity_interactions
+FOR EACH ROW
+EXECUTE FUNCTION update_entity_interaction_count();
+
+-- ===========================================
+-- FACT INTERACTIONS
+-- ===========================================
+
+-- Facts about an entity (facts.about_entity_id)
+CREATE OR REPLACE FUNCTION record_fact_interaction()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.about_entity_id IS NOT NULL THEN
+        INSERT INTO entity_interactions (entity_id, source, source_id, occurred_at)
+        VALUES (NEW.about_entity_id, 'fact', NEW.id, NEW.recorded_at)
+        ON CONFLICT (entity_id, source, source_id) DO NOTHING;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_record_fact_interaction ON facts;
+CREATE TRIGGER trg_record_fact_interaction
+AFTER INSERT OR UPDATE OF about_entity_id ON facts
+FOR EACH ROW
+EXECUTE FUNCTION record_fact_interaction();
+
+-- Facts mentioning an entity (entity_mentions)
+CREATE OR REPLACE FUNCTION record_mention_interaction()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO entity_interactions (entity_id, source, source_id, occurred_at)
+    SELECT NEW.entity_id, 'fact', f.id, f.recorded_at
+    FROM facts f
+    WHERE f.id = NEW.fact_id
+    ON CONFLICT (entity_id, source, source_id) DO NOTHING;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_record_mention_interaction ON entity_mentions;
+CREATE TRIGGER trg_record_mention_interaction
+AFTER INSERT OR UPDATE OF entity_id ON entity_mentions
+FOR EACH ROW
+EXECUTE FUNCTION record_mention_interaction();
+
+CREATE OR REPLACE FUNCTION remove_fact_interactions()
+RETURNS TRIGGER AS $$
+BEGIN
+    DELETE FROM entity_interactions
+    WHERE source = 'fact' AND source_id = OLD.id;
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_remove_fact_interactions ON facts;
+CREATE TRIGGER trg_remove_fact_interactions
+AFTER DELETE ON facts
+FOR EACH ROW
+EXECUTE FUNCTION remove_fact_interactions();
+
+-- ===========================================
+-- CALENDAR INTERACTIONS
+-- ===========================================
+
+-- Attendees linked to an entity
+CREATE OR REPLACE FUNCTION record_attendee_interaction()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.entity_id IS NOT NULL THEN
+        INSERT INTO entity_interactions (entity_id, source, source_id, occurred_at)
+        SELECT NEW.entity_id, 'calendar_event', ce.id, ce.start_time
+        FROM calendar_events ce
+        WHERE ce.id = NEW.event_id
+        ON CONFLICT (entity_id, source, source_id) DO NOTHING;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_record_attendee_interaction ON calendar_event_attendees;
+CREATE TRIGGER trg_record_attendee_interaction
+AFTER INSERT OR UPDATE OF entity_id ON calendar_event_attendees
+FOR EACH ROW
+EXECUTE FUNCTION record_attendee_interaction();
+
+-- Rescheduled events move their interactions; deleted events remove them
+CREATE OR REPLACE FUNCTION sync_calendar_interactions()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'DELETE' THEN
+        DELETE FROM entity_interactions
+        WHERE source = 'calendar_event' AND source_id = OLD.id;
+        RETURN OLD;
+    END IF;
+
+    UPDATE entity_interactions
+    SET occurred_at = NEW.start_time
+    WHERE source = 'calendar_event' AND source_id = NEW.id;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_sync_calendar_interactions ON calendar_events;
+CREATE TRIGGER trg_sync_calendar_interactions
+AFTER UPDATE OF start_time OR DELETE ON calendar_events
+FOR EACH ROW
+EXECUTE FUNCTION sync_calendar_interactions();
+
+-- ===========================================
+-- BACKFILL
+-- ===========================================
+
+INSERT INTO entity_interactions (entity_id, source, source_id, occurred_at)
+SELECT f.about_entity_id, 'fact', f.id, f.recorded_at
+FROM facts f
+WHERE f.about_entity_id IS NOT NULL
+ON CONFLICT (entity_id, source, source_id) DO NOTHING;
+
+INSERT INTO entity_interactions (entity_id, source, source_id, occurred_at)
+SELECT em.entity_id, 'fact', f.id, f.recorded_at
+FROM entity_mentions em
+JOIN facts f ON f.id = em.fact_id
+ON CONFLICT (entity_id, source, source_id) DO NOTHING;
+
+INSERT INTO entity_interactions (entity_id, source, source_id, occurred_at)
+SELECT cea.entity_id, 'calendar_event', ce.id, ce.start_time
+FROM calendar_event_attendees cea
+JOIN calendar_events ce ON ce.id = cea.event_id
+WHERE cea.entity_id IS NOT NULL
+ON CONFLICT (entity_id, source, source_id) DO NOTHING;
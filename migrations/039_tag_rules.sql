@@ -0,0 +1,49 @@
+-- Migration: 039_tag_rules
+-- Description: User-defined auto-tagging rules
+-- Date: 2026-10-16
+
+-- ===========================================
+-- TAG RULES
+-- ===========================================
+
+-- "content contains 'invoice' -> tag finance/bills". A rule matches a fact
+-- when every condition that is set matches; keywords match if any one of
+-- them appears in the content (case-insensitive). Rules apply to facts the
+-- user created, at ingest time and through the tag_rule_backfill Lambda,
+-- which processes facts created after applied_through (NULL = never run,
+-- so the first run backfills the user's whole history).
+CREATE TABLE IF NOT EXISTS tag_rules (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    tag_id UUID NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+
+    -- Conditions (at least one)
+    keywords TEXT[] NOT NULL DEFAULT '{}',
+    entity_type entity_type,
+    source fact_source,
+
+    confidence DECIMAL(3,2) NOT NULL DEFAULT 0.9 CHECK (confidence BETWEEN 0 AND 1),
+    enabled BOOLEAN NOT NULL DEFAULT true,
+
+    applied_count INT NOT NULL DEFAULT 0,
+    applied_through TIMESTAMPTZ,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT tag_rules_has_condition CHECK (
+        cardinality(keywords) > 0 OR entity_type IS NOT NULL OR source IS NOT NULL
+    )
+);
+
+CREATE INDEX IF NOT EXISTS idx_tag_rules_user ON tag_rules(user_id) WHERE enabled;
+
+-- Tags applied by rules are distinguishable from agent and user tags
+ALTER TABLE fact_tags DROP CONSTRAINT IF EXISTS fact_tags_assigned_by_check;
+ALTER TABLE fact_tags ADD CONSTRAINT fact_tags_assigned_by_check
+    CHECK (assigned_by IN ('agent', 'user', 'rule'));
+
+-- Backfill pages through a user's facts in creation order
+CREATE INDEX IF NOT EXISTS idx_facts_created_by_created
+    ON facts(created_by, created_at, id);
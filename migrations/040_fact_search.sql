@@ -0,0 +1,15 @@
+-- Migration: 040_fact_search
+-- Description: Full-text search vector on facts
+-- Date: 2026-10-16
+
+-- ===========================================
+-- FACT SEARCH VECTOR
+-- ===========================================
+
+-- Generated from content, so every writer (API, agents, calendar sync)
+-- keeps it current without extra work. Searched by GET /facts/search with
+-- websearch_to_tsquery and ranked with ts_rank.
+ALTER TABLE facts ADD COLUMN IF NOT EXISTS search_vector TSVECTOR
+    GENERATED ALWAYS AS (to_tsvector('english', content)) STORED;
+
+CREATE INDEX IF NOT EXISTS idx_facts_search_vector ON facts USING GIN(search_vector);
@@ -0,0 +1,34 @@
+-- Migration: 041_soft_delete
+-- Description: Soft delete (trash) for facts, entities and tags
+-- Date: 2026-10-16
+
+-- ===========================================
+-- DELETED MARKERS
+-- ===========================================
+
+-- Deleting a fact, entity or tag sets deleted_at instead of removing the row.
+-- Deleted rows are hidden from the API, listed by GET /trash, can be brought
+-- back with POST /trash/{id}/restore, and are purged for good by the
+-- trash_purge Lambda 30 days after deletion.
+ALTER TABLE facts ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE facts ADD COLUMN IF NOT EXISTS deleted_by UUID REFERENCES users(id) ON DELETE SET NULL;
+
+ALTER TABLE entities ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE entities ADD COLUMN IF NOT EXISTS deleted_by UUID REFERENCES users(id) ON DELETE SET NULL;
+
+-- A deleted tag takes its descendants with it; they share its deleted_at so
+-- restoring the tag restores the whole subtree
+ALTER TABLE tags ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+ALTER TABLE tags ADD COLUMN IF NOT EXISTS deleted_by UUID REFERENCES users(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_facts_deleted ON facts(deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_entities_deleted ON entities(deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_tags_deleted ON tags(deleted_at) WHERE deleted_at IS NOT NULL;
+
+-- A deleted tag's path is free for a new tag; restoring it then conflicts
+DROP INDEX IF EXISTS idx_tags_unique_path;
+CREATE UNIQUE INDEX idx_tags_unique_path ON tags(
+    COALESCE(owner_type, ''),
+    COALESCE(owner_id, '00000000-0000-0000-0000-000000000000'::UUID),
+    path
+) WHERE deleted_at IS NULL;
@@ -0,0 +1,27 @@
+-- Migration: 042_fact_review
+-- Description: Importance decay and fact review queue
+-- Date: 2026-10-16
+
+-- ===========================================
+-- REVIEW SCHEDULE
+-- ===========================================
+
+-- Facts about attributes that change over time (where someone lives, works,
+-- their phone number) are due for review once review_interval_days have
+-- passed since they were recorded or last reviewed. GET /facts/review lists
+-- due facts; confirming one doubles its interval, spaced-repetition style.
+ALTER TABLE facts ADD COLUMN IF NOT EXISTS last_reviewed_at TIMESTAMPTZ;
+ALTER TABLE facts ADD COLUMN IF NOT EXISTS review_interval_days INTEGER NOT NULL DEFAULT 90
+    CHECK (review_interval_days > 0);
+
+-- ===========================================
+-- IMPORTANCE DECAY
+-- ===========================================
+
+-- Set each time the importance_decay Lambda lowers a fact's importance, so
+-- a fact loses at most one level per decay period
+ALTER TABLE facts ADD COLUMN IF NOT EXISTS importance_decayed_at TIMESTAMPTZ;
+
+-- Current facts, the only ones that decay or come up for review
+CREATE INDEX IF NOT EXISTS idx_facts_reviewable ON facts(owner_type, owner_id)
+    WHERE valid_to IS NULL AND superseded_by IS NULL AND deleted_at IS NULL;
@@ -0,0 +1,24 @@
+-- Migration: 043_fact_attachments
+-- Description: Files (photos, PDFs, audio) attached to facts
+-- Date: 2026-10-16
+
+-- ===========================================
+-- FACT ATTACHMENTS
+-- ===========================================
+
+-- Files live in the fact attachments bucket; clients upload them with a
+-- presigned URL from POST /facts/{id}/attachments. Only the object key and
+-- the metadata declared at upload time are stored here.
+CREATE TABLE IF NOT EXISTS fact_attachments (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    fact_id UUID NOT NULL REFERENCES facts(id) ON DELETE CASCADE,
+    object_key TEXT NOT NULL UNIQUE,
+    file_name TEXT NOT NULL,
+    content_type VARCHAR(100) NOT NULL,
+    kind VARCHAR(10) NOT NULL CHECK (kind IN ('photo', 'document', 'audio')),
+    size_bytes BIGINT NOT NULL CHECK (size_bytes > 0),
+    uploaded_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_fact_attachments_fact ON fact_attachments(fact_id, created_at);
@@ -0,0 +1,68 @@
+-- Migration: 044_document_ingest
+-- Description: Documents (PDFs, scanned mail) ingested via Textract
+-- Date: 2026-10-16
+
+-- ===========================================
+-- DOCUMENT INGESTS
+-- ===========================================
+
+-- Processing status
+DO $$ BEGIN
+    CREATE TYPE document_ingest_status AS ENUM (
+        'pending',        -- Received, text detection not started
+        'extracting',     -- Waiting on an async Textract job
+        'ingesting',      -- Text extracted, chunks being handed to the agents
+        'completed',      -- Every chunk ingested
+        'failed'          -- Processing failed (see error_message)
+    );
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+-- One row per document uploaded under documents/{cognito_sub}/ in the drop
+-- folder bucket. Chunks are ingested in order; chunks_ingested lets a
+-- retried invocation pick up where the last one stopped.
+CREATE TABLE IF NOT EXISTS document_ingests (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+    -- S3 object (etag distinguishes re-uploads of the same key)
+    bucket VARCHAR(255) NOT NULL,
+    object_key TEXT NOT NULL,
+    etag VARCHAR(255) NOT NULL,
+    size_bytes BIGINT,
+
+    status document_ingest_status NOT NULL DEFAULT 'pending',
+    textract_job_id VARCHAR(255),
+    page_count INTEGER,
+    chunk_count INTEGER,
+    chunks_ingested INTEGER NOT NULL DEFAULT 0,
+    error_message TEXT,
+    attempts SMALLINT NOT NULL DEFAULT 1,
+
+    -- Timestamps
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    processed_at TIMESTAMPTZ,
+
+    UNIQUE (bucket, object_key, etag)
+);
+
+CREATE INDEX IF NOT EXISTS idx_document_ingests_user ON document_ingests(user_id, created_at DESC);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_document_ingests_job ON document_ingests(textract_job_id) WHERE textract_job_id IS NOT NULL;
+
+-- ===========================================
+-- FACT SOURCES
+-- ===========================================
+
+-- Links facts back to the document and pages they were extracted from
+CREATE TABLE IF NOT EXISTS document_ingest_facts (
+    document_id UUID NOT NULL REFERENCES document_ingests(id) ON DELETE CASCADE,
+    fact_id UUID NOT NULL REFERENCES facts(id) ON DELETE CASCADE,
+    chunk_index INTEGER NOT NULL,
+    first_page INTEGER NOT NULL,
+    last_page INTEGER NOT NULL,
+
+    PRIMARY KEY (document_id, fact_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_document_ingest_facts_fact ON document_ingest_facts(fact_id);
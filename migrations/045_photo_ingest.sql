@@ -0,0 +1,76 @@
+-- Migration: 045_photo_ingest
+-- Description: Photos ingested with EXIF and Rekognition labels/faces
+-- Date: 2026-10-16
+
+-- ===========================================
+-- PHOTO INGESTS
+-- ===========================================
+
+-- Processing status
+DO $$ BEGIN
+    CREATE TYPE photo_ingest_status AS ENUM (
+        'pending',        -- Received, not yet processed
+        'completed',      -- Labeled and recorded as a fact
+        'failed'          -- Processing failed (see error_message)
+    );
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+-- One row per photo uploaded under photos/{cognito_sub}/ in the drop folder
+-- bucket, with what was read from its EXIF data and detected by Rekognition
+CREATE TABLE IF NOT EXISTS photo_ingests (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+    -- S3 object (etag distinguishes re-uploads of the same key)
+    bucket VARCHAR(255) NOT NULL,
+    object_key TEXT NOT NULL,
+    etag VARCHAR(255) NOT NULL,
+    size_bytes BIGINT,
+
+    status photo_ingest_status NOT NULL DEFAULT 'pending',
+
+    -- From EXIF (DateTimeOriginal, GPS)
+    taken_at TIMESTAMP,
+    location GEOGRAPHY(POINT, 4326),
+    place_name TEXT,
+
+    -- Rekognition labels above the confidence threshold, most confident first
+    labels TEXT[] NOT NULL DEFAULT '{}',
+
+    -- Resulting fact and thumbnail (drop folder bucket, thumbnails/ prefix)
+    fact_id UUID REFERENCES facts(id) ON DELETE SET NULL,
+    thumbnail_key TEXT,
+
+    error_message TEXT,
+    attempts SMALLINT NOT NULL DEFAULT 1,
+
+    -- Timestamps
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    processed_at TIMESTAMPTZ,
+
+    UNIQUE (bucket, object_key, etag)
+);
+
+CREATE INDEX IF NOT EXISTS idx_photo_ingests_user ON photo_ingests(user_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_photo_ingests_fact ON photo_ingests(fact_id) WHERE fact_id IS NOT NULL;
+
+-- People recognized in a photo
+CREATE TABLE IF NOT EXISTS photo_ingest_entities (
+    photo_id UUID NOT NULL REFERENCES photo_ingests(id) ON DELETE CASCADE,
+    entity_id UUID NOT NULL REFERENCES entities(id) ON DELETE CASCADE,
+    similarity REAL NOT NULL,
+
+    PRIMARY KEY (photo_id, entity_id)
+);
+
+-- ===========================================
+-- ENTITY FACES
+-- ===========================================
+
+-- A person's entity photo is indexed into the Rekognition face collection
+-- (external image ID = entity ID) the first time a photo is processed after
+-- it changes. face_indexed_key is the photo_key that face_id came from
+ALTER TABLE entities ADD COLUMN IF NOT EXISTS face_id VARCHAR(100);
+ALTER TABLE entities ADD COLUMN IF NOT EXISTS face_indexed_key TEXT;
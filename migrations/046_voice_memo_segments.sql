@@ -0,0 +1,24 @@
+-- Migration: 046_voice_memo_segments
+-- Description: Speaker-labeled transcript segments for dropped voice memos
+-- Date: 2026-10-16
+
+-- ===========================================
+-- TRANSCRIPT SEGMENTS
+-- ===========================================
+
+-- Audio in the drop folder is transcribed with speaker labels; each run of
+-- speech from one speaker is kept here, in order, with its offsets into the
+-- recording (seconds)
+CREATE TABLE IF NOT EXISTS drop_folder_transcript_segments (
+    file_id UUID NOT NULL REFERENCES drop_folder_files(id) ON DELETE CASCADE,
+    segment_index INTEGER NOT NULL,
+    speaker_label VARCHAR(20),
+    start_secs REAL NOT NULL,
+    end_secs REAL NOT NULL,
+    content TEXT NOT NULL,
+
+    PRIMARY KEY (file_id, segment_index)
+);
+
+-- Distinct speakers Transcribe found in the recording
+ALTER TABLE drop_folder_files ADD COLUMN IF NOT EXISTS speaker_count SMALLINT;
@@ -0,0 +1,40 @@
+-- Migration: 047_web_capture
+-- Description: Bookmark entities and pages captured from the browser extension
+-- Date: 2026-10-16
+
+-- ===========================================
+-- BOOKMARKS
+-- ===========================================
+
+-- Saved web pages are entities; the URL is kept in metadata->>'url'
+ALTER TYPE entity_type ADD VALUE IF NOT EXISTS 'bookmark';
+
+-- Finds the existing bookmark when a page is captured again
+CREATE INDEX IF NOT EXISTS idx_entities_url ON entities(owner_type, owner_id, (metadata->>'url'))
+    WHERE metadata ? 'url';
+
+-- ===========================================
+-- WEB CAPTURES
+-- ===========================================
+
+-- One row per POST /capture: what the extension sent and what was created
+CREATE TABLE IF NOT EXISTS web_captures (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+    url TEXT NOT NULL,
+    title TEXT,
+    selected_text TEXT,
+    -- Reference to a screenshot the extension stored (opaque to the API)
+    screenshot_ref TEXT,
+
+    -- Whether the page itself could be fetched and read
+    page_fetched BOOLEAN NOT NULL DEFAULT false,
+
+    bookmark_entity_id UUID REFERENCES entities(id) ON DELETE SET NULL,
+    fact_ids UUID[] NOT NULL DEFAULT '{}',
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_web_captures_user ON web_captures(user_id, created_at DESC);
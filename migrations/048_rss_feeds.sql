@@ -0,0 +1,70 @@
+-- Migration: 048_rss_feeds
+-- Description: RSS/Atom feed subscriptions polled by feed_poller
+-- Date: 2026-10-16
+
+-- ===========================================
+-- FEEDS
+-- ===========================================
+
+CREATE TABLE IF NOT EXISTS rss_feeds (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+    feed_url TEXT NOT NULL,
+    -- User-chosen name, else the feed's own title once polled
+    name VARCHAR(255),
+
+    -- Disabled feeds are kept (with their seen items) but not polled
+    enabled BOOLEAN NOT NULL DEFAULT true,
+
+    -- HTTP validators from the last fetch, sent as If-None-Match and
+    -- If-Modified-Since so unchanged feeds aren't downloaded again
+    etag TEXT,
+    last_modified TEXT,
+
+    last_polled_at TIMESTAMPTZ,
+    last_error TEXT,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    UNIQUE (user_id, feed_url)
+);
+
+CREATE INDEX IF NOT EXISTS idx_rss_feeds_enabled ON rss_feeds(user_id) WHERE enabled;
+
+-- ===========================================
+-- FEED ITEMS
+-- ===========================================
+
+-- Items seen in a feed, by GUID. Items skipped on a feed's first poll are
+-- recorded with no facts so they aren't summarized later.
+CREATE TABLE IF NOT EXISTS rss_feed_items (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    feed_id UUID NOT NULL REFERENCES rss_feeds(id) ON DELETE CASCADE,
+
+    guid TEXT NOT NULL,
+    title TEXT,
+    link TEXT,
+    published_at TIMESTAMPTZ,
+
+    fact_ids UUID[] NOT NULL DEFAULT '{}',
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    UNIQUE (feed_id, guid)
+);
+
+-- ===========================================
+-- TAGS
+-- ===========================================
+
+INSERT INTO tags (name, path, description)
+SELECT 'reading', 'reading', 'Things read'
+WHERE NOT EXISTS (SELECT 1 FROM tags WHERE owner_type IS NULL AND path = 'reading');
+
+INSERT INTO tags (name, path, parent_id, description)
+SELECT 'feeds', 'reading/feeds', t.id, 'Summaries of RSS and newsletter feed items'
+FROM tags t
+WHERE t.owner_type IS NULL AND t.path = 'reading'
+AND NOT EXISTS (SELECT 1 FROM tags WHERE owner_type IS NULL AND path = 'reading/feeds');
@@ -0,0 +1,41 @@
+-- Migration: 049_data_exports
+-- Description: Full account data export jobs (POST /export)
+-- Date: 2026-10-16
+
+-- ===========================================
+-- DATA EXPORTS
+-- ===========================================
+
+DO $$ BEGIN
+    CREATE TYPE data_export_status AS ENUM ('pending', 'running', 'completed', 'failed');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
+
+-- One row per requested export. The data_export Lambda writes the bundle to
+-- the exports bucket, which expires objects after a week (expires_at).
+CREATE TABLE IF NOT EXISTS data_exports (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+    status data_export_status NOT NULL DEFAULT 'pending',
+
+    -- Zip bundle in the exports bucket, once completed
+    object_key TEXT,
+    size_bytes BIGINT,
+    -- Rows exported per dataset, e.g. {"facts": 120, "entities": 34}
+    record_counts JSONB NOT NULL DEFAULT '{}',
+
+    error_message TEXT,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    started_at TIMESTAMPTZ,
+    completed_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_data_exports_user ON data_exports(user_id, created_at DESC);
+
+-- At most one export in progress per user
+CREATE UNIQUE INDEX IF NOT EXISTS idx_data_exports_in_progress ON data_exports(user_id)
+    WHERE status IN ('pending', 'running');
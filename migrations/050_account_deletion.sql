@@ -0,0 +1,51 @@
+-- Migration: 050_account_deletion
+-- Description: Account deletion requests and audit trail (DELETE /account)
+-- Date: 2026-10-16
+
+-- ===========================================
+-- ACCOUNT DELETIONS
+-- ===========================================
+
+DO $$ BEGIN
+    CREATE TYPE account_deletion_status AS ENUM ('scheduled', 'completed', 'failed');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
+
+-- One row per deletion request, worked through step by step by the
+-- account_deletion Lambda. The row is the audit record and outlives the user,
+-- so user_id deliberately has no foreign key and nothing personal is kept
+-- beyond the IDs.
+CREATE TABLE IF NOT EXISTS account_deletions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL,
+    cognito_sub VARCHAR(255) NOT NULL,
+
+    -- What happens to family-owned facts and entities the user created
+    family_data_policy VARCHAR(10) NOT NULL DEFAULT 'keep'
+        CHECK (family_data_policy IN ('keep', 'delete')),
+
+    status account_deletion_status NOT NULL DEFAULT 'scheduled',
+    -- Steps finished so far, e.g. {revoke_access,purge_secrets}
+    completed_steps TEXT[] NOT NULL DEFAULT '{}',
+    -- Rows affected per step, e.g. {"personal_data": {"facts": 120}}
+    summary JSONB NOT NULL DEFAULT '{}',
+
+    attempts INTEGER NOT NULL DEFAULT 0,
+    -- Lease held by the run currently working on the deletion
+    processing_started_at TIMESTAMPTZ,
+    last_error TEXT,
+
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_account_deletions_user ON account_deletions(user_id);
+
+-- Swept by the account_deletion Lambda to retry unfinished deletions
+CREATE INDEX IF NOT EXISTS idx_account_deletions_scheduled ON account_deletions(requested_at)
+    WHERE status = 'scheduled';
+
+-- At most one open deletion per user
+CREATE UNIQUE INDEX IF NOT EXISTS idx_account_deletions_open ON account_deletions(user_id)
+    WHERE status = 'scheduled';
@@ -0,0 +1,40 @@
+-- Migration: 051_audit_log
+-- Description: Audit log of changes to facts, entities, tags, relationships and family membership
+-- Date: 2026-10-16
+
+-- ===========================================
+-- AUDIT LOG
+-- ===========================================
+
+-- One row per change made through the API, with JSON snapshots of the row
+-- before and after. Entries are kept after the user, family or row they
+-- describe is gone, so the IDs deliberately have no foreign keys.
+CREATE TABLE IF NOT EXISTS audit_log (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+
+    -- Who made the change
+    actor_id UUID NOT NULL,
+    action VARCHAR(20) NOT NULL
+        CHECK (action IN ('create', 'update', 'delete', 'restore', 'merge')),
+
+    resource_type VARCHAR(30) NOT NULL
+        CHECK (resource_type IN ('fact', 'fact_tags', 'entity', 'entity_relationship',
+                                 'tag', 'relationship', 'family_member')),
+    resource_id UUID NOT NULL,
+
+    -- Whose data it is: a user, a family, or neither (system tags)
+    owner_user_id UUID,
+    family_id UUID,
+
+    before JSONB,
+    after JSONB,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_owner_user ON audit_log(owner_user_id, created_at DESC, id DESC)
+    WHERE owner_user_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_audit_log_family ON audit_log(family_id, created_at DESC, id DESC)
+    WHERE family_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_audit_log_actor ON audit_log(actor_id, created_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_audit_log_resource ON audit_log(resource_type, resource_id, created_at DESC);
@@ -0,0 +1,27 @@
+-- Migration: 052_conversation_sessions
+-- Description: Conversation sessions for follow-up questions across the API and chat platforms
+-- Date: 2026-10-16
+
+-- ===========================================
+-- CONVERSATIONS
+-- ===========================================
+
+-- `conversations` and `messages` (008) start being written: each query asked
+-- with a conversation ID stores the question and answer, and the latest turns
+-- are sent to the agents with the next question.
+ALTER TABLE conversations
+    -- Where the conversation happens (api, discord, ...)
+    ADD COLUMN IF NOT EXISTS source VARCHAR(20) NOT NULL DEFAULT 'api',
+    -- Platform's own ID for the conversation (e.g. a Discord thread), so
+    -- messages in the same place resume it
+    ADD COLUMN IF NOT EXISTS external_key TEXT,
+    -- The first question, for listing
+    ADD COLUMN IF NOT EXISTS title TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_conversations_external
+    ON conversations(user_id, source, external_key)
+    WHERE external_key IS NOT NULL;
+
+-- Keyset pagination of GET /conversations/{id}/messages
+CREATE INDEX IF NOT EXISTS idx_messages_conversation_page
+    ON messages(conversation_id, created_at, id);
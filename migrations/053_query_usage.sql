@@ -0,0 +1,22 @@
+-- Migration: 053_query_usage
+-- Description: Token usage and estimated cost per query, for monthly usage reports
+-- Date: 2026-10-16
+
+-- ===========================================
+-- QUERY USAGE
+-- ===========================================
+
+-- `query_sessions` (011) start being written by the query Lambda: one row per
+-- answered query with the tokens it used and what that is estimated to cost,
+-- so spend can be reported per user and month (GET /usage).
+ALTER TABLE query_sessions
+    ADD COLUMN IF NOT EXISTS conversation_id UUID REFERENCES conversations(id) ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS input_tokens INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS output_tokens INTEGER NOT NULL DEFAULT 0,
+    -- Estimated from the model's list price when the query ran
+    ADD COLUMN IF NOT EXISTS estimated_cost_usd NUMERIC(12, 6) NOT NULL DEFAULT 0;
+
+-- Monthly reports read a user's queries by time
+CREATE INDEX IF NOT EXISTS idx_query_sessions_user_started ON query_sessions(user_id, started_at);
+
+COMMENT ON TABLE query_sessions IS 'Tracks query sessions for satisfaction feedback and token usage';
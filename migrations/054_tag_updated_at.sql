@@ -0,0 +1,28 @@
+-- Migration: 054_tag_updated_at
+-- Description: Track when tags change, for ETags and If-Match updates
+-- Date: 2026-10-16
+
+-- ===========================================
+-- TAG VERSIONS
+-- ===========================================
+
+-- Entities and reminders already have `updated_at`; tags are renamed, moved
+-- and restored from several places, so a trigger keeps theirs current.
+ALTER TABLE tags
+    ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+UPDATE tags SET updated_at = created_at;
+
+CREATE OR REPLACE FUNCTION touch_tag_updated_at()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.updated_at := NOW();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_touch_tag_updated_at ON tags;
+CREATE TRIGGER trg_touch_tag_updated_at
+BEFORE UPDATE ON tags
+FOR EACH ROW
+EXECUTE FUNCTION touch_tag_updated_at();
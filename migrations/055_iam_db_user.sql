@@ -0,0 +1,33 @@
+-- Migration: 055_iam_db_user
+-- Description: Database user for Lambdas connecting with RDS IAM auth tokens
+-- Date: 2026-10-16
+
+-- ===========================================
+-- IAM AUTH USER
+-- ===========================================
+
+-- Lambdas with DB_AUTH_MODE=iam log in as sb_lambda with a short-lived token
+-- instead of the Secrets Manager password. The rds_iam role only exists on
+-- RDS; elsewhere the user is created but can't log in.
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_roles WHERE rolname = 'sb_lambda') THEN
+        CREATE ROLE sb_lambda LOGIN;
+    END IF;
+
+    IF EXISTS (SELECT 1 FROM pg_roles WHERE rolname = 'rds_iam') THEN
+        GRANT rds_iam TO sb_lambda;
+    END IF;
+END
+$$;
+
+GRANT USAGE ON SCHEMA public TO sb_lambda;
+GRANT SELECT, INSERT, UPDATE, DELETE ON ALL TABLES IN SCHEMA public TO sb_lambda;
+GRANT USAGE, SELECT ON ALL SEQUENCES IN SCHEMA public TO sb_lambda;
+GRANT EXECUTE ON ALL FUNCTIONS IN SCHEMA public TO sb_lambda;
+
+-- Tables added by later migrations (run as the admin user) are covered too
+ALTER DEFAULT PRIVILEGES IN SCHEMA public
+    GRANT SELECT, INSERT, UPDATE, DELETE ON TABLES TO sb_lambda;
+ALTER DEFAULT PRIVILEGES IN SCHEMA public
+    GRANT USAGE, SELECT ON SEQUENCES TO sb_lambda;
@@ -0,0 +1,15 @@
+-- Migration: 056_entity_locations_unique
+-- Description: Unique partial index on current entity locations, required by
+--              ON CONFLICT (entity_id, label) WHERE valid_to IS NULL.
+--              Previously numbered 014 alongside 014_external_identities, so
+--              the old runner never applied it; it is idempotent for databases
+--              where it was applied by hand.
+-- Date: 2026-10-16
+
+-- Drop the existing non-unique index first
+DROP INDEX IF EXISTS idx_entity_locations_current;
+
+-- Create unique partial index for current locations (valid_to IS NULL)
+CREATE UNIQUE INDEX IF NOT EXISTS idx_entity_locations_current_unique
+ON entity_locations(entity_id, label)
+WHERE valid_to IS NULL;
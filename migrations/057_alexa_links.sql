@@ -0,0 +1,28 @@
+-- Migration: 057_alexa_links
+-- Description: Alexa accounts linked through the skill, for proactive event
+--              notifications (notification_channel 'alexa')
+-- Date: 2026-10-16
+
+-- ===========================================
+-- ALEXA ACCOUNT LINKS
+-- ===========================================
+
+-- Recorded by the skill whenever a linked user talks to it or changes the
+-- skill's permissions; removed when the skill is disabled
+CREATE TABLE IF NOT EXISTS alexa_links (
+    -- Skill-scoped Alexa user ID (amzn1.ask.account.…)
+    alexa_user_id VARCHAR(512) PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+    -- Regional Alexa API endpoint the user's requests arrive from
+    api_endpoint VARCHAR(255) NOT NULL DEFAULT 'https://api.amazonalexa.com',
+
+    -- The user granted the notifications permission in the Alexa app
+    notifications_enabled BOOLEAN NOT NULL DEFAULT false,
+
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_alexa_links_user ON alexa_links(user_id, last_seen_at DESC);
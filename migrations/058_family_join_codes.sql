@@ -0,0 +1,43 @@
+-- Migration: 058_family_join_codes
+-- Description: Short-lived codes for joining a family from a phone
+-- Date: 2026-10-16
+
+-- ===========================================
+-- FAMILY JOIN CODES
+-- ===========================================
+
+-- Issued by a family admin and typed in by the people joining
+CREATE TABLE IF NOT EXISTS family_join_codes (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    family_id UUID NOT NULL REFERENCES families(id) ON DELETE CASCADE,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+
+    -- Only the SHA-256 of the normalized code is stored
+    code_hash BYTEA NOT NULL UNIQUE,
+
+    -- Role members who join with the code get
+    role family_role NOT NULL DEFAULT 'member',
+
+    -- How many people can join with the code before it stops working
+    max_uses INTEGER NOT NULL DEFAULT 1 CHECK (max_uses > 0),
+    use_count INTEGER NOT NULL DEFAULT 0,
+
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_family_join_codes_family ON family_join_codes(family_id, expires_at DESC);
+
+-- ===========================================
+-- FAILED ATTEMPTS
+-- ===========================================
+
+-- Codes are short, so wrong guesses are counted to limit how fast a user can
+-- try them
+CREATE TABLE IF NOT EXISTS family_join_attempts (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    attempted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_family_join_attempts_user ON family_join_attempts(user_id, attempted_at DESC);
@@ -0,0 +1,75 @@
+-- Migration: 059_family_spaces
+-- Description: Spaces within a family (e.g. "Kids School", "House") whose
+--              facts and entities only their members can see
+-- Date: 2026-10-16
+
+-- ===========================================
+-- SPACES
+-- ===========================================
+
+CREATE TABLE IF NOT EXISTS family_spaces (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    family_id UUID NOT NULL REFERENCES families(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    description TEXT,
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT family_spaces_unique_name UNIQUE (family_id, name)
+);
+
+-- Family members who can see a space's facts and entities
+CREATE TABLE IF NOT EXISTS family_space_members (
+    space_id UUID NOT NULL REFERENCES family_spaces(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    added_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    added_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (space_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_family_space_members_user ON family_space_members(user_id);
+
+-- ===========================================
+-- SPACE CONTENTS
+-- ===========================================
+
+-- NULL for the family as a whole. Only family-owned rows can be in a space;
+-- a space can't be deleted while anything is in it.
+ALTER TABLE facts ADD COLUMN IF NOT EXISTS space_id UUID REFERENCES family_spaces(id);
+ALTER TABLE entities ADD COLUMN IF NOT EXISTS space_id UUID REFERENCES family_spaces(id);
+
+ALTER TABLE facts DROP CONSTRAINT IF EXISTS facts_space_family_owned;
+ALTER TABLE facts ADD CONSTRAINT facts_space_family_owned
+    CHECK (space_id IS NULL OR owner_type = 'family');
+
+ALTER TABLE entities DROP CONSTRAINT IF EXISTS entities_space_family_owned;
+ALTER TABLE entities ADD CONSTRAINT entities_space_family_owned
+    CHECK (space_id IS NULL OR owner_type = 'family');
+
+CREATE INDEX IF NOT EXISTS idx_facts_space ON facts(space_id) WHERE space_id IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_entities_space ON entities(space_id) WHERE space_id IS NOT NULL;
+
+-- ===========================================
+-- LEAVING A FAMILY
+-- ===========================================
+
+-- Whoever leaves a family leaves its spaces too
+CREATE OR REPLACE FUNCTION remove_family_space_members()
+RETURNS TRIGGER AS $$
+BEGIN
+    DELETE FROM family_space_members sm
+    USING family_spaces s
+    WHERE sm.space_id = s.id
+      AND s.family_id = OLD.family_id
+      AND sm.user_id = OLD.user_id;
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_remove_family_space_members ON family_members;
+CREATE TRIGGER trg_remove_family_space_members
+AFTER DELETE ON family_members
+FOR EACH ROW
+EXECUTE FUNCTION remove_family_space_members();
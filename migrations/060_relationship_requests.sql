@@ -0,0 +1,42 @@
+-- Migration: 060_relationship_requests
+-- Description: Relationship requests the other person must accept before the
+--              relationship (and the access it grants) is created
+-- Date: 2026-10-16
+
+-- ===========================================
+-- RELATIONSHIP REQUESTS
+-- ===========================================
+
+-- A relationship lets its source see the target's data, so the target decides
+-- whether to accept and at which access tier
+CREATE TABLE IF NOT EXISTS relationship_requests (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    requester_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    target_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    relationship_type VARCHAR(50) NOT NULL,
+
+    -- Tier the requester asked for; the target may grant a different one
+    requested_access_tier SMALLINT NOT NULL CHECK (requested_access_tier BETWEEN 1 AND 4),
+    granted_access_tier SMALLINT CHECK (granted_access_tier BETWEEN 1 AND 4),
+
+    -- Also create the reverse relationship, giving the target access to the
+    -- requester's data
+    bidirectional BOOLEAN NOT NULL DEFAULT false,
+
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'accepted', 'declined')),
+    relationship_id UUID REFERENCES relationships(id) ON DELETE SET NULL,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    responded_at TIMESTAMPTZ,
+
+    CONSTRAINT relationship_requests_no_self CHECK (requester_user_id != target_user_id)
+);
+
+-- One open request per pair; asking again replaces it
+CREATE UNIQUE INDEX IF NOT EXISTS idx_relationship_requests_pending
+    ON relationship_requests(requester_user_id, target_user_id)
+    WHERE status = 'pending';
+
+CREATE INDEX IF NOT EXISTS idx_relationship_requests_target
+    ON relationship_requests(target_user_id, status);
@@ -0,0 +1,44 @@
+-- Migration: 061_access_grants
+-- Description: Time-boxed access to a user's or family's facts and entities
+--              (e.g. a babysitter for a weekend)
+-- Date: 2026-10-16
+
+-- ===========================================
+-- ACCESS GRANTS
+-- ===========================================
+
+-- Lets the grantee read the owner's rows at or above `access_tier` between
+-- `starts_at` and `expires_at`, without a relationship. Owners grant their own
+-- data; family admins grant the family's (never rows in a space).
+CREATE TABLE IF NOT EXISTS access_grants (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    grantor_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    grantee_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+    -- Whose rows are shared: the grantor ('user') or one of their families
+    owner_type VARCHAR(10) NOT NULL CHECK (owner_type IN ('user', 'family')),
+    owner_id UUID NOT NULL,
+
+    access_tier SMALLINT NOT NULL CHECK (access_tier BETWEEN 1 AND 4),
+    reason TEXT,
+
+    starts_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ,
+    revoked_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT access_grants_no_self CHECK (grantor_user_id != grantee_user_id),
+    CONSTRAINT access_grants_window CHECK (expires_at > starts_at)
+);
+
+-- Checked by every visibility query
+CREATE INDEX IF NOT EXISTS idx_access_grants_grantee
+    ON access_grants(grantee_user_id, owner_type, owner_id)
+    WHERE revoked_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_access_grants_grantor ON access_grants(grantor_user_id);
+CREATE INDEX IF NOT EXISTS idx_access_grants_owner ON access_grants(owner_type, owner_id);
+
+-- Found by the expiry sweeper
+CREATE INDEX IF NOT EXISTS idx_access_grants_expires ON access_grants(expires_at);
@@ -0,0 +1,38 @@
+-- Migration: 062_interaction_events
+-- Description: Implicit feedback - which facts were returned, clicked or
+--              expanded and which notifications were opened
+-- Date: 2026-10-16
+
+-- ===========================================
+-- INTERACTION EVENTS
+-- ===========================================
+
+-- Logged in batches by clients (POST /v1/events) and by the API when it
+-- returns results. Clicks, expands and notification opens also become
+-- implicit rows in user_feedback, so they count towards user_feedback_stats.
+CREATE TABLE IF NOT EXISTS interaction_events (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+    -- 'fact_returned', 'fact_clicked', 'fact_expanded',
+    -- 'notification_opened', 'notification_dismissed'
+    event_type VARCHAR(50) NOT NULL,
+
+    -- The fact or notification interacted with
+    target_id UUID NOT NULL,
+
+    -- Query or search the fact was returned by, and its rank in the results
+    query_id UUID,
+    position INTEGER,
+
+    -- Where it happened ('web', 'ios', 'api', ...)
+    source VARCHAR(50) NOT NULL DEFAULT 'api',
+
+    metadata JSONB NOT NULL DEFAULT '{}',
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_interaction_events_user ON interaction_events(user_id, occurred_at DESC);
+CREATE INDEX IF NOT EXISTS idx_interaction_events_target ON interaction_events(target_id, event_type);
+CREATE INDEX IF NOT EXISTS idx_interaction_events_query ON interaction_events(query_id) WHERE query_id IS NOT NULL;
@@ -0,0 +1,16 @@
+-- Migration: 063_query_sources
+-- Description: The facts each answered query was based on, so past answers
+--              can be shown with citations (GET /v1/queries/{id})
+-- Date: 2026-10-16
+
+-- ===========================================
+-- QUERY SOURCES
+-- ===========================================
+
+-- Facts the agents retrieved while answering, most relevant first. Facts are
+-- looked up when the answer is read, so deleted facts and facts the user can
+-- no longer see drop out of its citations.
+ALTER TABLE query_sessions
+    ADD COLUMN IF NOT EXISTS source_fact_ids UUID[] NOT NULL DEFAULT '{}';
+
+COMMENT ON COLUMN query_sessions.source_fact_ids IS 'Facts retrieved while answering, most relevant first';
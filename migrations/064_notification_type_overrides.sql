@@ -0,0 +1,14 @@
+-- Migration: 064_notification_type_overrides
+-- Description: Per-notification-type delivery overrides, managed with the
+--              other preferences through /preferences/notifications
+-- Date: 2026-10-16
+
+-- ===========================================
+-- NOTIFICATION TYPE OVERRIDES
+-- ===========================================
+
+-- Keyed by notification_type, e.g.
+--   {"reminder": {"channel": "email", "ignoreQuietHours": true},
+--    "proactive": {"enabled": false}}
+-- Types without an entry use the channel and quiet hours settings above.
+ALTER TABLE user_notification_preferences ADD COLUMN IF NOT EXISTS type_overrides JSONB NOT NULL DEFAULT '{}';
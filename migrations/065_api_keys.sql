@@ -0,0 +1,30 @@
+-- Migration: 065_api_keys
+-- Description: Personal API keys for scripts and home automation
+-- Date: 2026-10-16
+
+-- ===========================================
+-- API KEYS
+-- ===========================================
+
+-- Issued through POST /api-keys and sent in the X-Api-Key header instead of
+-- a Cognito token. Only a SHA-256 hash of the key is stored; the key itself
+-- is shown once, when it is created.
+CREATE TABLE IF NOT EXISTS api_keys (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+
+    -- 'read' (GET requests only) or 'write' (everything)
+    scope VARCHAR(10) NOT NULL CHECK (scope IN ('read', 'write')),
+
+    -- Start of the key, shown so people can tell their keys apart
+    key_prefix VARCHAR(16) NOT NULL,
+    key_hash BYTEA NOT NULL UNIQUE,
+
+    last_used_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_keys_user ON api_keys(user_id, created_at DESC);
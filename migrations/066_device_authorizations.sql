@@ -0,0 +1,60 @@
+-- Migration: 066_device_authorizations
+-- Description: OAuth device authorization flow for CLI and TV clients
+-- Date: 2026-10-16
+
+-- ===========================================
+-- DEVICE AUTHORIZATIONS
+-- ===========================================
+
+-- A device asks for a code (POST /auth/device/code), the user approves the
+-- short user code on the web and the device polls POST /auth/token until
+-- tokens are issued. Rows older than a day past expiry are deleted as new
+-- codes are handed out.
+CREATE TABLE IF NOT EXISTS device_authorizations (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    device_code_hash BYTEA NOT NULL UNIQUE,
+
+    -- What the user types, e.g. 'WDJB-MJHT'
+    user_code VARCHAR(9) NOT NULL,
+    client_name VARCHAR(100) NOT NULL,
+
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'approved', 'denied', 'issued')),
+    -- Who approved or denied the code
+    user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+
+    -- Seconds the device must wait between polls; raised on slow_down
+    poll_interval INTEGER NOT NULL DEFAULT 5,
+    last_polled_at TIMESTAMPTZ,
+
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- User codes are short, so they only need to be unique while pending
+CREATE UNIQUE INDEX IF NOT EXISTS idx_device_authorizations_pending_code
+    ON device_authorizations(user_code) WHERE status = 'pending';
+
+CREATE INDEX IF NOT EXISTS idx_device_authorizations_expires ON device_authorizations(expires_at);
+
+-- ===========================================
+-- DEVICE SESSIONS
+-- ===========================================
+
+-- Devices signed in through the device flow. Refreshing swaps both tokens;
+-- deleting the row signs the device out.
+CREATE TABLE IF NOT EXISTS device_sessions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    client_name VARCHAR(100) NOT NULL,
+
+    access_token_hash BYTEA NOT NULL UNIQUE,
+    access_expires_at TIMESTAMPTZ NOT NULL,
+    refresh_token_hash BYTEA NOT NULL UNIQUE,
+    refresh_expires_at TIMESTAMPTZ NOT NULL,
+
+    last_used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_device_sessions_user ON device_sessions(user_id);
@@ -0,0 +1,77 @@
+-- Migration: 067_location_history
+-- Description: Location history pings from the mobile app, and the visits detected in them
+-- Date: 2026-10-16
+
+-- ===========================================
+-- LOCATION HISTORY SETTINGS
+-- ===========================================
+
+-- Location history is off until the user turns it on; without a row (or
+-- with enabled = FALSE) pings are refused and nothing is recorded.
+CREATE TABLE IF NOT EXISTS location_history_settings (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    enabled BOOLEAN NOT NULL DEFAULT FALSE,
+
+    -- Pings and visits older than this are deleted by the visit detector.
+    -- Visit facts are ordinary facts and stay until deleted.
+    retention_days INTEGER NOT NULL DEFAULT 30
+        CHECK (retention_days BETWEEN 1 AND 365),
+
+    -- Record a fact ("Visited Lake Tahoe ...") for visits to place entities
+    record_visit_facts BOOLEAN NOT NULL DEFAULT TRUE,
+
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- ===========================================
+-- LOCATION PINGS
+-- ===========================================
+
+-- Raw positions uploaded with POST /location-pings/batch. The app re-sends
+-- batches it isn't sure were received, so pings are unique per user and time.
+CREATE TABLE IF NOT EXISTS location_pings (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    location GEOGRAPHY(POINT, 4326) NOT NULL,
+    accuracy_meters REAL,
+    recorded_at TIMESTAMPTZ NOT NULL,
+
+    -- Set once the visit detector has clustered the ping
+    processed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    UNIQUE (user_id, recorded_at)
+);
+
+CREATE INDEX IF NOT EXISTS idx_location_pings_unprocessed
+    ON location_pings(user_id, recorded_at) WHERE processed_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_location_pings_recorded ON location_pings(recorded_at);
+
+-- ===========================================
+-- LOCATION VISITS
+-- ===========================================
+
+-- Places the user stayed at, clustered from their pings
+CREATE TABLE IF NOT EXISTS location_visits (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+    -- Centre of the visit's pings
+    location GEOGRAPHY(POINT, 4326) NOT NULL,
+    -- Nearest place entity the user can see, if one is close enough
+    place_entity_id UUID REFERENCES entities(id) ON DELETE SET NULL,
+
+    arrived_at TIMESTAMPTZ NOT NULL,
+    departed_at TIMESTAMPTZ NOT NULL,
+    ping_count INTEGER NOT NULL,
+
+    -- The visit fact, when one was recorded
+    fact_id UUID REFERENCES facts(id) ON DELETE SET NULL,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CHECK (departed_at >= arrived_at)
+);
+
+CREATE INDEX IF NOT EXISTS idx_location_visits_user ON location_visits(user_id, arrived_at DESC);
+CREATE INDEX IF NOT EXISTS idx_location_visits_place ON location_visits(place_entity_id);
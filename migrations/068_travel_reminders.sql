@@ -0,0 +1,21 @@
+-- Migration: 068_travel_reminders
+-- Description: Travel time estimates for reminders that fire in time to leave
+-- Date: 2026-10-16
+
+-- ===========================================
+-- TRAVEL ESTIMATES
+-- ===========================================
+
+-- Time reminders with "inTimeToLeave" in their trigger config fire when it's
+-- time to leave for their related entity. The reminder evaluator routes from
+-- the user's last known location and moves next_trigger_at to match.
+
+-- Latest estimated travel time (NULL until one has been made)
+ALTER TABLE reminders ADD COLUMN IF NOT EXISTS travel_seconds INTEGER;
+
+-- When travel was last estimated, successfully or not
+ALTER TABLE reminders ADD COLUMN IF NOT EXISTS travel_estimated_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS idx_reminders_leave_in_time
+    ON reminders(next_trigger_at)
+    WHERE status = 'active' AND trigger_config->>'inTimeToLeave' = 'true';
@@ -0,0 +1,122 @@
+-- Migration: 069_entity_location_places
+-- Description: Normalized addresses and a canonical place_id for entity
+--              locations, so one address isn't stored twice for an entity
+-- Date: 2026-10-16
+
+-- ===========================================
+-- ADDRESS NORMALIZATION
+-- ===========================================
+
+-- Lowercase form of an address without punctuation and with common
+-- abbreviations spelled out, so "123 Main St." and "123 main street" match.
+CREATE OR REPLACE FUNCTION normalize_address(address TEXT)
+RETURNS TEXT AS $$
+DECLARE
+    normalized TEXT;
+    abbreviation TEXT[];
+BEGIN
+    normalized := regexp_replace(lower(COALESCE(address, '')), '[^[:alnum:]]+', ' ', 'g');
+
+    FOREACH abbreviation SLICE 1 IN ARRAY ARRAY[
+        ['united states of america', 'usa'],
+        ['united states', 'usa'],
+        ['us', 'usa'],
+        ['st', 'street'],
+        ['ave', 'avenue'],
+        ['av', 'avenue'],
+        ['rd', 'road'],
+        ['blvd', 'boulevard'],
+        ['dr', 'drive'],
+        ['ln', 'lane'],
+        ['ct', 'court'],
+        ['pl', 'place'],
+        ['sq', 'square'],
+        ['ter', 'terrace'],
+        ['cres', 'crescent'],
+        ['hwy', 'highway'],
+        ['pkwy', 'parkway'],
+        ['apt', 'apartment'],
+        ['ste', 'suite'],
+        ['fl', 'floor'],
+        ['n', 'north'],
+        ['s', 'south'],
+        ['e', 'east'],
+        ['w', 'west'],
+        ['ne', 'northeast'],
+        ['nw', 'northwest'],
+        ['se', 'southeast'],
+        ['sw', 'southwest']
+    ] LOOP
+        normalized := regexp_replace(
+            normalized, '\m' || abbreviation[1] || '\M', abbreviation[2], 'g'
+        );
+    END LOOP;
+
+    RETURN NULLIF(trim(regexp_replace(normalized, '\s+', ' ', 'g')), '');
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+-- ===========================================
+-- CANONICAL PLACES
+-- ===========================================
+
+-- Geocoder results (street, municipality, postal code, ...; see
+-- shared::geo::NormalizedAddress) were never stored before, so
+-- address_normalized is only set on locations geocoded from now on.
+
+-- Identifies the place a location is at. Geocoders that identify places
+-- (HERE, Grab) supply their own ID; otherwise it's "addr:" and the
+-- normalized geocoded label, or the raw address when it wasn't geocoded.
+ALTER TABLE entity_locations ADD COLUMN IF NOT EXISTS place_id TEXT;
+
+-- Fills in place_id on every write, so each path that stores locations
+-- (API, agent tools, photo ingestion, merges) derives it the same way.
+-- An address change without a new place_id re-derives it.
+CREATE OR REPLACE FUNCTION set_entity_location_place_id()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.place_id IS NULL
+       OR (TG_OP = 'UPDATE'
+           AND NEW.place_id IS NOT DISTINCT FROM OLD.place_id
+           AND (NEW.address_raw IS DISTINCT FROM OLD.address_raw
+                OR NEW.address_normalized IS DISTINCT FROM OLD.address_normalized)) THEN
+        NEW.place_id := 'addr:' || normalize_address(
+            COALESCE(NEW.address_normalized->>'label', NEW.address_raw)
+        );
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_set_entity_location_place_id ON entity_locations;
+CREATE TRIGGER trg_set_entity_location_place_id
+BEFORE INSERT OR UPDATE ON entity_locations
+FOR EACH ROW
+EXECUTE FUNCTION set_entity_location_place_id();
+
+-- Derive place_id for existing locations (the trigger fills it in)
+UPDATE entity_locations SET place_id = NULL WHERE place_id IS NULL;
+
+-- ===========================================
+-- DEDUPLICATION
+-- ===========================================
+
+-- End all but the most recently updated current location of each entity at
+-- each place, as merging entities does for locations with the same label
+UPDATE entity_locations l
+SET valid_to = CURRENT_DATE, updated_at = NOW()
+WHERE l.valid_to IS NULL
+  AND l.place_id IS NOT NULL
+  AND EXISTS (
+      SELECT 1 FROM entity_locations k
+      WHERE k.entity_id = l.entity_id
+        AND k.place_id = l.place_id
+        AND k.valid_to IS NULL
+        AND (k.updated_at, k.id) > (l.updated_at, l.id)
+  );
+
+-- One current location per entity and place. Stores look for an existing
+-- one first (shared::geo::find_duplicate_location); this catches races.
+CREATE UNIQUE INDEX IF NOT EXISTS idx_entity_locations_current_place
+    ON entity_locations(entity_id, place_id)
+    WHERE valid_to IS NULL;
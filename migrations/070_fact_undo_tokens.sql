@@ -0,0 +1,33 @@
+-- Migration: 070_fact_undo_tokens
+-- Description: Short undo window for facts edited or forgotten from chat
+-- Date: 2026-10-16
+
+-- ===========================================
+-- UNDO TOKENS
+-- ===========================================
+
+-- Edits and deletions made through the internal facts Lambda (Discord
+-- /edit and /forget) apply at once and hand back a token that reverts them
+-- for a few minutes (see shared::fact_actions::undo). Deletions are undone
+-- by restoring the fact from the trash; edits by putting back the previous
+-- content, unless the fact has been changed again since. Rows past expiry
+-- are deleted as the user's new tokens are handed out.
+CREATE TABLE IF NOT EXISTS fact_undo_tokens (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    fact_id UUID NOT NULL REFERENCES facts(id) ON DELETE CASCADE,
+
+    action VARCHAR(10) NOT NULL CHECK (action IN ('edit', 'delete')),
+    -- Content before and after an edit
+    previous_content TEXT,
+    applied_content TEXT,
+
+    expires_at TIMESTAMPTZ NOT NULL,
+    undone_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CHECK (action = 'delete' OR (previous_content IS NOT NULL AND applied_content IS NOT NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_fact_undo_tokens_user_expires
+    ON fact_undo_tokens(user_id, expires_at);
@@ -0,0 +1,19 @@
+-- Migration: 071_fact_duplicates
+-- Description: Link ingested facts to the earlier facts they duplicate
+-- Date: 2026-10-16
+
+-- ===========================================
+-- DUPLICATES
+-- ===========================================
+
+-- Ingest compares each new fact with recent facts about the same entity
+-- (see shared::fact_duplicates). One that restates an earlier fact word for
+-- word is merged into it and moved to the trash; one that says nearly the
+-- same thing is kept and flagged. Either way duplicate_of points at the
+-- earlier fact.
+ALTER TABLE facts
+    ADD COLUMN IF NOT EXISTS duplicate_of UUID REFERENCES facts(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_facts_duplicate_of
+    ON facts(duplicate_of)
+    WHERE duplicate_of IS NOT NULL;
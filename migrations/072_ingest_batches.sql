@@ -0,0 +1,35 @@
+-- Migration: 072_ingest_batches
+-- Description: Background jobs for large batch ingests (POST /ingest/batch)
+-- Date: 2026-10-16
+
+-- ===========================================
+-- INGEST BATCHES
+-- ===========================================
+
+-- Batches too large to ingest while the caller waits (see shared::ingest)
+-- are recorded here and processed by the ingest_batch Lambda, which appends
+-- per-item results as it goes. The items themselves are cleared once the
+-- batch finishes.
+CREATE TABLE IF NOT EXISTS ingest_batches (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'running', 'completed', 'failed')),
+
+    -- Requested items, as for POST /ingest
+    items JSONB NOT NULL DEFAULT '[]',
+    total_items INT NOT NULL,
+    stored_items INT NOT NULL DEFAULT 0,
+    failed_items INT NOT NULL DEFAULT 0,
+    -- One result per processed item, in item order
+    results JSONB NOT NULL DEFAULT '[]',
+
+    error_message TEXT,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    started_at TIMESTAMPTZ,
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_ingest_batches_user ON ingest_batches(user_id, created_at DESC);
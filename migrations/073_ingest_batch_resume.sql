@@ -0,0 +1,23 @@
+-- Migration: 073_ingest_batch_resume
+-- Description: Let batch ingest jobs that stop partway resume where they left off
+-- Date: 2026-10-16
+
+-- ===========================================
+-- INGEST BATCHES
+-- ===========================================
+
+-- The ingest_batch Lambda records each item's result as it finishes and
+-- renews heartbeat_at; a running job that hasn't in a while has died (timed
+-- out, crashed) and is picked up by the scheduled sweep, which resumes it
+-- with the items that have no result yet. attempts counts those resumptions.
+ALTER TABLE ingest_batches
+    ADD COLUMN IF NOT EXISTS heartbeat_at TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS attempts INT NOT NULL DEFAULT 0;
+
+-- Results were appended in item order; they're now appended as items finish
+COMMENT ON COLUMN ingest_batches.results IS
+    'One result per processed item, in the order they finished (see index)';
+
+CREATE INDEX IF NOT EXISTS idx_ingest_batches_unfinished
+    ON ingest_batches(status, heartbeat_at)
+    WHERE status IN ('pending', 'running');
@@ -0,0 +1,22 @@
+-- Migration: 074_fact_embedding_failures
+-- Description: Record facts the embedding indexer failed on so its sweep stops retrying them
+-- Date: 2026-10-16
+
+-- ===========================================
+-- FACT EMBEDDING FAILURES
+-- ===========================================
+
+-- One row per fact the embedding_indexer Lambda couldn't embed, for the
+-- version of the fact it tried (fact_updated_at). permanent marks failures
+-- that retrying can't fix, like content the model rejects; other failures
+-- are retried by the scheduled sweep until attempts reaches its limit. Editing
+-- the fact gives it a new version, which is tried afresh; indexing it removes
+-- the row.
+CREATE TABLE IF NOT EXISTS fact_embedding_failures (
+    fact_id UUID PRIMARY KEY REFERENCES facts(id) ON DELETE CASCADE,
+    fact_updated_at TIMESTAMPTZ NOT NULL,
+    attempts INT NOT NULL DEFAULT 1,
+    permanent BOOLEAN NOT NULL DEFAULT FALSE,
+    last_error TEXT NOT NULL,
+    failed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
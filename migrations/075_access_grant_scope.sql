@@ -0,0 +1,18 @@
+-- Migration: 075_access_grant_scope
+-- Description: Let an access grant cover only the facts about one entity or
+--              with one tag instead of every row at its tier
+-- Date: 2026-10-16
+
+-- ===========================================
+-- ACCESS GRANT SCOPE
+-- ===========================================
+
+-- NULL means unscoped. With entity_id set, the grant covers that entity and
+-- the facts about or mentioning it (a babysitter sees the kids, not the
+-- parents' finances); with tag_id set, only facts carrying the tag. Both set
+-- means both must hold. Deleting the entity or tag ends the grant rather than
+-- widening it.
+ALTER TABLE access_grants
+    ADD COLUMN IF NOT EXISTS entity_id UUID REFERENCES entities(id) ON DELETE CASCADE;
+ALTER TABLE access_grants
+    ADD COLUMN IF NOT EXISTS tag_id UUID REFERENCES tags(id) ON DELETE CASCADE;
@@ -0,0 +1,103 @@
+-- Migration: 076_relationship_schema_fixes
+-- Description: Store the relationship types the API uses, allow one
+--              relationship per pair of users and fix the access cache refresh
+-- Date: 2026-10-16
+
+-- ===========================================
+-- ACCESS CACHE REFRESH
+-- ===========================================
+
+-- Postgres rejects the original's cycle check, which read the recursive CTE
+-- from a subquery, so every refresh (and, through the relationships trigger,
+-- every relationship write) failed. Each path now carries the users it has
+-- visited instead. Replaced first so the changes below can fire the trigger.
+CREATE OR REPLACE FUNCTION refresh_user_access_cache(p_user_id UUID)
+RETURNS VOID AS $$
+BEGIN
+    DELETE FROM user_access_cache WHERE viewer_user_id = p_user_id;
+
+    INSERT INTO user_access_cache (viewer_user_id, target_user_id, access_tier, relationship_path, hop_count)
+    WITH RECURSIVE accessible_users AS (
+        -- Base case: direct relationships
+        SELECT
+            r.source_user_id AS viewer_user_id,
+            r.target_user_id,
+            r.access_tier,
+            ARRAY[r.id] AS relationship_path,
+            ARRAY[r.source_user_id, r.target_user_id] AS visited,
+            1 AS hop_count
+        FROM relationships r
+        WHERE r.source_user_id = p_user_id
+          AND (r.valid_to IS NULL OR r.valid_to > CURRENT_DATE)
+          AND (r.valid_from IS NULL OR r.valid_from <= CURRENT_DATE)
+
+        UNION ALL
+
+        -- Recursive case: follow relationships (max 4 hops), never back to a
+        -- user already on the path
+        SELECT
+            au.viewer_user_id,
+            r.target_user_id,
+            GREATEST(au.access_tier, r.access_tier) AS access_tier,
+            au.relationship_path || r.id,
+            au.visited || r.target_user_id,
+            au.hop_count + 1
+        FROM accessible_users au
+        JOIN relationships r ON r.source_user_id = au.target_user_id
+        WHERE au.hop_count < 4
+          AND r.target_user_id != ALL(au.visited)
+          AND (r.valid_to IS NULL OR r.valid_to > CURRENT_DATE)
+          AND (r.valid_from IS NULL OR r.valid_from <= CURRENT_DATE)
+    )
+    SELECT DISTINCT ON (viewer_user_id, target_user_id)
+        viewer_user_id,
+        target_user_id,
+        access_tier,
+        relationship_path,
+        hop_count
+    FROM accessible_users
+    ORDER BY viewer_user_id, target_user_id, access_tier ASC, hop_count ASC;
+END;
+$$ LANGUAGE plpgsql;
+
+-- ===========================================
+-- RELATIONSHIP TYPES
+-- ===========================================
+
+-- The API (and relationship_requests) use plain types like 'parent' and
+-- 'friend'; the original enum ('parent_of', 'colleague', ...) rejected them.
+-- Types the API has no name for become 'other'.
+ALTER TABLE relationships DROP CONSTRAINT IF EXISTS relationships_custom_requires_name;
+
+ALTER TABLE relationships
+    ALTER COLUMN relationship_type TYPE VARCHAR(50)
+    USING CASE relationship_type::TEXT
+        WHEN 'parent_of' THEN 'parent'
+        WHEN 'child_of' THEN 'child'
+        WHEN 'grandparent_of' THEN 'grandparent'
+        WHEN 'grandchild_of' THEN 'grandchild'
+        WHEN 'spouse' THEN 'spouse'
+        WHEN 'sibling' THEN 'sibling'
+        WHEN 'friend' THEN 'friend'
+        ELSE 'other'
+    END;
+
+DROP TYPE IF EXISTS relationship_type;
+
+-- ===========================================
+-- ONE RELATIONSHIP PER PAIR
+-- ===========================================
+
+-- Accepting a request replaces the relationship between the same users, so
+-- keep only the one granting the most access (newest on a tie)
+DELETE FROM relationships r
+USING relationships keep
+WHERE keep.source_user_id = r.source_user_id
+  AND keep.target_user_id = r.target_user_id
+  AND keep.id != r.id
+  AND (keep.access_tier, r.created_at, r.id) < (r.access_tier, keep.created_at, keep.id);
+
+DROP INDEX IF EXISTS idx_relationships_unique_active;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_relationships_pair
+    ON relationships(source_user_id, target_user_id);